@@ -20,7 +20,7 @@ fn hnsw_insert_search_bench(c: &mut Criterion) {
 
     c.bench_function("hnsw_search_1k", |b| {
         b.iter(|| {
-            let _ = index.search(&query, 10, 200, &vectors);
+            let _ = index.search(&query, 10, 200, &vectors, None, &HashMap::new());
         })
     });
 }
@@ -36,6 +36,9 @@ enum Commands {
         /// Override data dir (sets DATA_DIR)
         #[arg(long)]
         data_dir: Option<PathBuf>,
+        /// Run as a routing proxy in front of these nodes (comma-separated URLs, sets CLUSTER_NODES)
+        #[arg(long)]
+        cluster_nodes: Option<String>,
         /// Skip the short animation
         #[arg(long)]
         no_anim: bool,
@@ -54,6 +57,105 @@ enum Commands {
         no_anim: bool,
     },
 
+    /// Check a collection's files for consistency; refused while a server has the collection open.
+    Fsck {
+        /// Collection name (its files are looked up in the data dir)
+        collection: String,
+        /// Optional config file to load (overrides CONFIG_FILE)
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// Override data dir (sets DATA_DIR)
+        #[arg(long)]
+        data_dir: Option<PathBuf>,
+        /// Drop unreadable pointers and orphan index nodes and index missing documents
+        #[arg(long)]
+        repair: bool,
+    },
+
+    /// Measure recall@k of the indexes on synthetic clustered data; exits 2 below the floor.
+    Recall {
+        /// Vectors per dataset
+        #[arg(long, default_value_t = 5000)]
+        count: usize,
+        /// Vector dimensions
+        #[arg(long, default_value_t = 64)]
+        dimensions: usize,
+        /// Gaussian clusters the vectors are drawn from
+        #[arg(long, default_value_t = 32)]
+        clusters: usize,
+        /// Queries per dataset
+        #[arg(long, default_value_t = 100)]
+        queries: usize,
+        #[arg(long, default_value_t = 10)]
+        k: usize,
+        /// Lowest acceptable recall of every grid point, on every seed
+        #[arg(long, default_value_t = 0.9)]
+        floor: f32,
+        /// Dataset seeds (comma-separated)
+        #[arg(long, value_delimiter = ',', default_value = "42")]
+        seeds: Vec<u64>,
+        /// Indexes to check (comma-separated; default all)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        index: Vec<IndexKind>,
+        /// HNSW ef values to sweep (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        ef: Vec<usize>,
+        /// IVF nprobe values to sweep (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        nprobe: Vec<usize>,
+        /// cosine, euclidean or dot_product
+        #[arg(long, default_value = "cosine", value_parser = parse_metric)]
+        metric: piramid::Metric,
+    },
+
+    /// Benchmark inserts and searches on synthetic data, embedded or against a running server (--url).
+    Bench {
+        /// Vectors to insert
+        #[arg(long, default_value_t = 10_000)]
+        count: usize,
+        /// Vector dimensions
+        #[arg(long, default_value_t = 64)]
+        dimensions: usize,
+        /// Gaussian clusters the vectors are drawn from
+        #[arg(long, default_value_t = 32)]
+        clusters: usize,
+        /// Searches to run
+        #[arg(long, default_value_t = 1000)]
+        queries: usize,
+        #[arg(long, default_value_t = 10)]
+        k: usize,
+        /// Vectors per insert
+        #[arg(long, default_value_t = 500)]
+        batch_size: usize,
+        /// Searches (and HTTP inserts) in flight at once
+        #[arg(long, default_value_t = 1)]
+        concurrency: usize,
+        /// Dataset seed
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+        /// Embedded index (default: auto); a server uses its own config
+        #[arg(long, value_enum)]
+        index: Option<IndexKind>,
+        /// cosine, euclidean or dot_product (embedded)
+        #[arg(long, default_value = "cosine", value_parser = parse_metric)]
+        metric: piramid::Metric,
+        /// Keep the embedded collection's files here (removed afterwards) instead of in memory
+        #[arg(long)]
+        data_dir: Option<String>,
+        /// Benchmark this server (e.g. http://localhost:6333) instead of the embedded library
+        #[arg(long)]
+        url: Option<String>,
+        /// Collection created on the server for the run; must not exist
+        #[arg(long, default_value = "bench")]
+        collection: String,
+        /// Sent as x-api-key
+        #[arg(long)]
+        api_key: Option<String>,
+        /// Leave the server's collection in place afterwards
+        #[arg(long)]
+        keep: bool,
+    },
+
     /// Show the resolved config (after env overrides).
     ShowConfig {
         /// Optional config file to load (overrides CONFIG_FILE)
@@ -68,6 +170,25 @@ enum OutputFormat {
     Json,
 }
 
+#[derive(Copy, Clone, ValueEnum)]
+enum IndexKind {
+    Hnsw,
+    Ivf,
+    Flat,
+}
+
+fn index_type(kind: IndexKind) -> piramid::IndexType {
+    match kind {
+        IndexKind::Hnsw => piramid::IndexType::Hnsw,
+        IndexKind::Ivf => piramid::IndexType::Ivf,
+        IndexKind::Flat => piramid::IndexType::Flat,
+    }
+}
+
+fn parse_metric(name: &str) -> Result<piramid::Metric, String> {
+    piramid::Metric::parse(name).ok_or_else(|| format!("unknown metric {name}"))
+}
+
 fn main() {
     let cli = Cli::parse();
     match cli.command {
@@ -78,6 +199,75 @@ fn main() {
             }
             println!("Wrote config to {}", path.display());
         }
+        Some(Commands::Fsck { collection, config, data_dir, repair }) => {
+            if let Some(path) = config {
+                std::env::set_var("CONFIG_FILE", path);
+            }
+            if let Some(dir) = data_dir {
+                std::env::set_var("DATA_DIR", dir);
+            }
+            match fsck(&collection, repair) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(2),
+                Err(e) => {
+                    eprintln!("fsck failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Recall { count, dimensions, clusters, queries, k, floor, seeds, index, ef, nprobe, metric }) => {
+            let dataset = piramid::testing::SyntheticDataset { count, dimensions, clusters, queries, ..Default::default() };
+            let mut grid = piramid::testing::RecallGrid::standard(count, metric);
+            if !index.is_empty() {
+                let types: Vec<piramid::IndexType> = index.iter().map(|kind| index_type(*kind)).collect();
+                grid = grid.only(&types);
+            }
+            if !ef.is_empty() {
+                grid.ef = ef;
+            }
+            if !nprobe.is_empty() {
+                grid.nprobe = nprobe;
+            }
+            let check = piramid::testing::RecallCheck { dataset, grid, seeds, k, floor, metric };
+            match piramid::testing::run_recall_check(&check) {
+                Ok(report) => {
+                    println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+                    if !report.passed {
+                        std::process::exit(2);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("recall check failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Bench { count, dimensions, clusters, queries, k, batch_size, concurrency, seed, index, metric, data_dir, url, collection, api_key, keep }) => {
+            let dataset = piramid::testing::SyntheticDataset { count, dimensions, clusters, queries, ..Default::default() };
+            let target = match url {
+                Some(url) => piramid::testing::BenchTarget::Http { url, collection, api_key, keep },
+                None => {
+                    // The recall check's configuration of the index, sized for `count`
+                    let index = match index {
+                        Some(kind) => piramid::testing::RecallGrid::standard(count, metric).only(&[index_type(kind)]).indexes.remove(0),
+                        None => piramid::index::IndexConfig::Auto {
+                            metric,
+                            mode: Default::default(),
+                            search: Default::default(),
+                        },
+                    };
+                    piramid::testing::BenchTarget::Embedded { index, data_dir }
+                }
+            };
+            let bench = piramid::testing::BenchConfig { dataset, seed, k, batch_size, concurrency, target };
+            match piramid::testing::run_bench(&bench) {
+                Ok(report) => println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default()),
+                Err(e) => {
+                    eprintln!("bench failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
         Some(Commands::ShowConfig { config }) => {
             if let Some(path) = config {
                 std::env::set_var("CONFIG_FILE", path);
@@ -90,6 +280,7 @@ fn main() {
             config,
             port,
             data_dir,
+            cluster_nodes,
             no_anim: _,
         }) => {
             if let Some(path) = config {
@@ -101,6 +292,9 @@ fn main() {
             if let Some(dir) = data_dir {
                 std::env::set_var("DATA_DIR", dir);
             }
+            if let Some(nodes) = cluster_nodes {
+                std::env::set_var("CLUSTER_NODES", nodes);
+            }
             if let Err(e) = start_server_inline() {
                 eprintln!("Failed to start piramid-server: {e}");
                 std::process::exit(1);
@@ -110,6 +304,21 @@ fn main() {
     }
 }
 
+// Print the report; returns whether the collection is consistent (or was repaired)
+fn fsck(collection: &str, repair: bool) -> piramid::Result<bool> {
+    piramid::validation::validate_collection_name(collection)?;
+    let runtime = piramid::config::loader::load_runtime_config();
+    let path = piramid::storage::collection::collection_path(&runtime.data_dir, collection);
+    if !Path::new(&path).exists() {
+        return Err(piramid::error::ServerError::NotFound(format!("No collection at {path}")).into());
+    }
+    let options = piramid::storage::collection::CollectionOpenOptions::from(runtime.app.collection_config(collection));
+    let mut storage = piramid::Collection::open_with_options(&path, options)?;
+    let report = if repair { storage.repair()? } else { storage.verify()? };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(report.is_consistent() || report.repaired)
+}
+
 fn write_config_file(path: &Path, fmt: OutputFormat) -> std::io::Result<()> {
     let cfg = AppConfig::default();
     let contents = match fmt {
@@ -125,7 +334,7 @@ fn write_config_file(path: &Path, fmt: OutputFormat) -> std::io::Result<()> {
 }
 
 fn start_server_inline() -> std::io::Result<()> {
-    let rt = Runtime::new().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    let rt = Runtime::new().map_err(std::io::Error::other)?;
     rt.block_on(async {
         let RuntimeConfig {
             app: app_config,
@@ -136,8 +345,43 @@ fn start_server_inline() -> std::io::Result<()> {
             disk_min_free_bytes,
             disk_readonly_on_low_space,
             cache_max_bytes,
+            cluster,
         } = piramid::config::loader::load_runtime_config();
 
+        // Router mode: no local collections, every request is proxied to the backend nodes
+        if let Some(cluster) = cluster {
+            let state = piramid::cluster::ClusterState::new(&cluster).map_err(std::io::Error::other)?;
+            let app = piramid::cluster::create_cluster_router(std::sync::Arc::new(state));
+            let addr = format!("0.0.0.0:{}", port);
+            let listener = tokio::net::TcpListener::bind(&addr).await.map_err(|e| {
+                std::io::Error::other(format!("bind failed: {e}"))
+            })?;
+            return axum::serve(listener, app)
+                .await
+                .map_err(std::io::Error::other);
+        }
+
+        // One server per data dir: refuse to start next to a live owner, or wait for it as a standby
+        let addr = format!("0.0.0.0:{}", port);
+        let lock_config = app_config.data_dir_lock;
+        let _data_dir_lock = if lock_config.enabled {
+            let lock = match piramid::server::data_dir_lock::DataDirLock::acquire(&data_dir, &lock_config) {
+                Err(piramid::PiramidError::Server(piramid::error::ServerError::DataDirLocked(_)))
+                    if lock_config.on_conflict == piramid::config::DataDirConflict::Standby =>
+                {
+                    piramid::server::data_dir_lock::run_standby(&addr, &data_dir, &lock_config).await
+                }
+                acquired => acquired,
+            }
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+            let lock = std::sync::Arc::new(lock);
+            lock.spawn_heartbeat(Duration::from_secs(lock_config.heartbeat_secs));
+            tracing::info!(data_dir=%data_dir, pid=lock.owner().pid, "data_dir_locked");
+            Some(lock)
+        } else {
+            None
+        };
+
         let state = match embedding_config.clone() {
             Some(config) => {
                 let timeout = std::env::var("EMBEDDING_TIMEOUT_SECS")
@@ -180,14 +424,53 @@ fn start_server_inline() -> std::io::Result<()> {
             )),
         };
 
+        piramid::metrics::jit::log_dispatch(app_config.execution);
+
+        // Register the collections already in data_dir and open the eager ones before serving
+        match state.discover_collections() {
+            Ok(count) => tracing::info!(collections=count, loaded=state.collections.len(), "collections_discovered"),
+            Err(e) => tracing::warn!(error=%e, "collection_discovery_failed"),
+        }
+
+        // Pick up the admin jobs the previous process queued or left running
+        let pending = piramid::jobs::spawn_jobs(state.clone());
+        if pending > 0 {
+            tracing::info!(jobs=pending, "jobs_resumed");
+        }
+
+        // Latency histograms are written out periodically so a restart keeps their distribution
+        if app_config.persist_latency_histograms {
+            let state = state.clone();
+            let every = std::time::Duration::from_secs(app_config.latency_persist_interval_secs.max(1));
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(every);
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = state.save_latency_histograms() {
+                        tracing::warn!(error=%e, "latency_histograms_save_failed");
+                    }
+                }
+            });
+        }
+
+        // Recall of approximate indexes, measured against sampled exact results
+        if app_config.recall_monitor.enabled {
+            piramid::server::recall_monitor::spawn_recall_monitor(state.clone());
+        }
+
+        // Consume the configured Kafka/NATS sources into their collections
+        if !app_config.ingest.is_empty() {
+            let sources = piramid::ingest::spawn_ingestion(state.clone());
+            tracing::info!(sources, "ingestion_started");
+        }
+
         let app = server::create_router(state);
-        let addr = format!("0.0.0.0:{}", port);
         let listener = tokio::net::TcpListener::bind(&addr).await.map_err(|e| {
-            std::io::Error::new(std::io::ErrorKind::Other, format!("bind failed: {e}"))
+            std::io::Error::other(format!("bind failed: {e}"))
         })?;
         axum::serve(listener, app)
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+            .map_err(std::io::Error::other)
     })
 }
 
@@ -0,0 +1,127 @@
+// Merging of per-shard JSON responses when a collection is sharded by document.
+// Every shard answers with the normal single-node response shape; these helpers fold them into
+// one response of the same shape so clients cannot tell they talked to a router.
+
+use serde_json::{json, Value};
+
+// Merge search responses: `{"results": [hit..]}` or, for batch search, `{"results": [[hit..]..]}`.
+// Hits are re-ranked by score across shards and cut to k. Latency is the slowest shard's.
+pub fn merge_search(bodies: &[Value], k: usize) -> Value {
+    let is_batch = bodies.iter().any(|b| {
+        b.get("results")
+            .and_then(|r| r.as_array())
+            .and_then(|r| r.first())
+            .is_some_and(|first| first.is_array())
+    });
+
+    let results = if is_batch {
+        let queries = bodies
+            .iter()
+            .filter_map(|b| b.get("results").and_then(|r| r.as_array()).map(|r| r.len()))
+            .max()
+            .unwrap_or(0);
+        let merged: Vec<Value> = (0..queries)
+            .map(|q| {
+                let hits = bodies.iter().filter_map(|b| {
+                    b.get("results")
+                        .and_then(|r| r.get(q))
+                        .and_then(|r| r.as_array())
+                        .cloned()
+                });
+                Value::Array(top_k(hits.flatten().collect(), k))
+            })
+            .collect();
+        Value::Array(merged)
+    } else {
+        let hits = bodies
+            .iter()
+            .filter_map(|b| b.get("results").and_then(|r| r.as_array()).cloned())
+            .flatten()
+            .collect();
+        Value::Array(top_k(hits, k))
+    };
+
+    let mut out = json!({ "results": results });
+    if let Some(latency) = max_latency(bodies) {
+        out["latency_ms"] = json!(latency);
+    }
+    out
+}
+
+// Merge range search responses: every shard already applied the threshold, so keep all hits
+pub fn merge_range(bodies: &[Value]) -> Value {
+    let hits: Vec<Value> = bodies
+        .iter()
+        .filter_map(|b| b.get("results").and_then(|r| r.as_array()).cloned())
+        .flatten()
+        .collect();
+    let len = hits.len();
+    let mut out = json!({ "results": top_k(hits, len) });
+    if let Some(latency) = max_latency(bodies) {
+        out["latency_ms"] = json!(latency);
+    }
+    out
+}
+
+// Sum a numeric field (count, deleted_count) and keep the rest of the first shard's body
+pub fn sum_field(bodies: &[Value], field: &str) -> Value {
+    let total: u64 = bodies.iter().filter_map(|b| b.get(field).and_then(|v| v.as_u64())).sum();
+    let mut out = bodies.first().cloned().unwrap_or_else(|| json!({}));
+    out[field] = json!(total);
+    out
+}
+
+// `{"deleted": bool}` is true if any shard deleted something
+pub fn any_deleted(bodies: &[Value]) -> Value {
+    let deleted = bodies.iter().any(|b| b.get("deleted").and_then(|v| v.as_bool()).unwrap_or(false));
+    json!({ "deleted": deleted })
+}
+
+// Concatenate array bodies (vector listings), cut to `limit`
+pub fn concat_arrays(bodies: &[Value], limit: usize) -> Value {
+    let items: Vec<Value> = bodies
+        .iter()
+        .filter_map(|b| b.as_array().cloned())
+        .flatten()
+        .take(limit)
+        .collect();
+    Value::Array(items)
+}
+
+// Merge `{"collections": [...]}` listings: one entry per name, counts summed across shards
+pub fn merge_collections(bodies: &[Value]) -> Value {
+    let mut merged: Vec<Value> = Vec::new();
+    for info in bodies
+        .iter()
+        .filter_map(|b| b.get("collections").and_then(|c| c.as_array()))
+        .flatten()
+    {
+        let name = info.get("name").cloned().unwrap_or(Value::Null);
+        match merged.iter_mut().find(|m| m.get("name") == Some(&name)) {
+            Some(existing) => {
+                let count = existing.get("count").and_then(|c| c.as_u64()).unwrap_or(0)
+                    + info.get("count").and_then(|c| c.as_u64()).unwrap_or(0);
+                existing["count"] = json!(count);
+            }
+            None => merged.push(info.clone()),
+        }
+    }
+    json!({ "collections": merged })
+}
+
+fn top_k(mut hits: Vec<Value>, k: usize) -> Vec<Value> {
+    hits.sort_by(|a, b| {
+        let sa = a.get("score").and_then(|s| s.as_f64()).unwrap_or(f64::MIN);
+        let sb = b.get("score").and_then(|s| s.as_f64()).unwrap_or(f64::MIN);
+        sb.partial_cmp(&sa).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    hits.truncate(k);
+    hits
+}
+
+fn max_latency(bodies: &[Value]) -> Option<f64> {
+    bodies
+        .iter()
+        .filter_map(|b| b.get("latency_ms").and_then(|l| l.as_f64()))
+        .reduce(f64::max)
+}
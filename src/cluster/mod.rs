@@ -0,0 +1,11 @@
+// Cluster module - router mode for running piramid across several nodes
+// - ring.rs: consistent hash ring used to place collections/documents on nodes
+// - merge.rs: folding per-shard JSON responses into one single-node shaped response
+// - router.rs: the proxying HTTP router served instead of the normal API
+
+pub mod ring;
+pub mod merge;
+pub mod router;
+
+pub use ring::HashRing;
+pub use router::{ClusterState, SharedClusterState, create_cluster_router};
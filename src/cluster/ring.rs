@@ -0,0 +1,62 @@
+// Consistent hash ring.
+// Each backend node is placed on a 64-bit ring at `virtual_nodes` points; a key belongs to the
+// first node point at or after the key's hash (wrapping around). Adding or removing a node only
+// moves the keys that fall between its points and their predecessors, instead of reshuffling
+// everything like `hash % nodes` would.
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+pub struct HashRing {
+    nodes: Vec<String>,
+    ring: BTreeMap<u64, usize>, // ring position -> index into `nodes`
+}
+
+impl HashRing {
+    pub fn new(nodes: Vec<String>, virtual_nodes: usize) -> Self {
+        let mut ring = BTreeMap::new();
+        for (idx, node) in nodes.iter().enumerate() {
+            for v in 0..virtual_nodes.max(1) {
+                ring.insert(hash_key(&format!("{node}#{v}")), idx);
+            }
+        }
+        Self { nodes, ring }
+    }
+
+    // Node that owns the given key, None only when the ring is empty
+    pub fn node_for(&self, key: &str) -> Option<&str> {
+        let h = hash_key(key);
+        self.ring
+            .range(h..)
+            .next()
+            .or_else(|| self.ring.iter().next())
+            .map(|(_, idx)| self.nodes[*idx].as_str())
+    }
+
+    pub fn nodes(&self) -> &[String] {
+        &self.nodes
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+// FNV-1a followed by a splitmix64 finalizer. Stable across builds and platforms (unlike
+// std's DefaultHasher), which matters because every router must agree on placement.
+pub fn hash_key(key: &str) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in key.as_bytes() {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h ^= h >> 30;
+    h = h.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h ^= h >> 27;
+    h = h.wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
@@ -0,0 +1,313 @@
+// HTTP router for cluster mode.
+// Exposes the same /api surface as a single node, but every request is proxied:
+// - shard_by=collection: the request goes to the node that owns the collection name.
+// - shard_by=document: writes go to one node (chosen by document id), reads by id and searches
+//   fan out to every node and the per-shard responses are merged (see merge.rs).
+// Endpoints that are not collection-scoped (metrics, readyz, config, ...) are broadcast and the
+// per-node answers are returned side by side.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Bytes,
+    extract::{OriginalUri, Path, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{any, get},
+    Router,
+};
+use serde_json::{json, Value};
+use tower_http::trace::TraceLayer;
+
+use crate::config::{ClusterConfig, ShardBy};
+use crate::error::{Result, ServerError};
+use crate::server::request_id::assign_request_id;
+use super::merge;
+use super::ring::HashRing;
+
+// Headers worth passing through to the backend nodes
+const FORWARDED_HEADERS: [&str; 3] = ["content-type", "authorization", "x-request-id"];
+
+pub struct ClusterState {
+    pub ring: HashRing,
+    pub shard_by: ShardBy,
+    client: reqwest::Client,
+}
+
+pub type SharedClusterState = Arc<ClusterState>;
+
+// One backend node's answer
+#[derive(Debug, Clone)]
+pub struct ShardResponse {
+    pub node: String,
+    pub status: StatusCode,
+    pub body: Bytes,
+}
+
+impl ShardResponse {
+    fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+
+    fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+
+    fn into_response(self) -> Response {
+        let mut response = (self.status, self.body).into_response();
+        response.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        response
+    }
+}
+
+impl ClusterState {
+    pub fn new(config: &ClusterConfig) -> Result<Self> {
+        config.validate().map_err(ServerError::InvalidRequest)?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout_secs))
+            .build()
+            .map_err(|e| ServerError::Internal(format!("Failed to build cluster client: {e}")))?;
+        Ok(Self {
+            ring: HashRing::new(config.nodes.clone(), config.virtual_nodes),
+            shard_by: config.shard_by,
+            client,
+        })
+    }
+
+    fn owner(&self, key: &str) -> Result<String> {
+        self.ring
+            .node_for(key)
+            .map(|n| n.to_string())
+            .ok_or_else(|| ServerError::ServiceUnavailable("No cluster nodes configured".into()).into())
+    }
+
+    async fn forward(&self, node: &str, method: &Method, uri: &str, headers: &HeaderMap, body: Bytes) -> Result<ShardResponse> {
+        send(self.client.clone(), node.to_string(), method.clone(), uri.to_string(), headers.clone(), body).await
+    }
+
+    // Send the same request to every node concurrently; fails if any node is unreachable
+    async fn broadcast(&self, method: &Method, uri: &str, headers: &HeaderMap, body: Bytes) -> Result<Vec<ShardResponse>> {
+        let tasks: Vec<_> = self
+            .ring
+            .nodes()
+            .iter()
+            .map(|node| {
+                tokio::spawn(send(
+                    self.client.clone(),
+                    node.clone(),
+                    method.clone(),
+                    uri.to_string(),
+                    headers.clone(),
+                    body.clone(),
+                ))
+            })
+            .collect();
+
+        let mut responses = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            let response = task
+                .await
+                .map_err(|e| ServerError::Internal(format!("Shard request task failed: {e}")))??;
+            responses.push(response);
+        }
+        Ok(responses)
+    }
+}
+
+async fn send(
+    client: reqwest::Client,
+    node: String,
+    method: Method,
+    uri: String,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<ShardResponse> {
+    let mut request = client.request(method, format!("{node}{uri}"));
+    for name in FORWARDED_HEADERS {
+        if let Some(value) = headers.get(name) {
+            request = request.header(name, value.clone());
+        }
+    }
+    if !body.is_empty() {
+        request = request.body(body);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| ServerError::ServiceUnavailable(format!("Shard {node} unreachable: {e}")))?;
+    let status = response.status();
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| ServerError::ServiceUnavailable(format!("Shard {node} read failed: {e}")))?;
+    Ok(ShardResponse { node, status, body })
+}
+
+fn json_response(value: Value) -> Response {
+    (StatusCode::OK, axum::Json(value)).into_response()
+}
+
+// First failed shard response, if any; merged responses are only built when every shard succeeded
+fn first_failure(responses: &[ShardResponse]) -> Option<ShardResponse> {
+    responses.iter().find(|r| !r.is_success()).cloned()
+}
+
+fn side_by_side(responses: Vec<ShardResponse>) -> Response {
+    let shards: Vec<Value> = responses
+        .iter()
+        .map(|r| json!({ "node": r.node, "status": r.status.as_u16(), "body": r.json() }))
+        .collect();
+    json_response(json!({ "shards": shards }))
+}
+
+fn query_param(uri: &axum::http::Uri, key: &str) -> Option<String> {
+    uri.query()?.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=')?;
+        (k == key).then(|| v.to_string())
+    })
+}
+
+// GET /api/collections - merged listing from every node
+async fn list_collections(
+    State(state): State<SharedClusterState>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let responses = state.broadcast(&Method::GET, &uri.to_string(), &headers, Bytes::new()).await?;
+    if let Some(failed) = first_failure(&responses) {
+        return Ok(failed.into_response());
+    }
+    let bodies: Vec<Value> = responses.iter().map(|r| r.json()).collect();
+    Ok(json_response(merge::merge_collections(&bodies)))
+}
+
+// POST /api/collections - create on the owner (or on every node when sharding documents)
+async fn create_collection(
+    State(state): State<SharedClusterState>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response> {
+    let uri = uri.to_string();
+    match state.shard_by {
+        ShardBy::Collection => {
+            let name = serde_json::from_slice::<Value>(&body)
+                .ok()
+                .and_then(|v| v.get("name").and_then(|n| n.as_str()).map(|n| n.to_string()))
+                .ok_or_else(|| ServerError::InvalidRequest("Collection name is required".into()))?;
+            let node = state.owner(&name)?;
+            Ok(state.forward(&node, &Method::POST, &uri, &headers, body).await?.into_response())
+        }
+        ShardBy::Document => {
+            let responses = state.broadcast(&Method::POST, &uri, &headers, body).await?;
+            if let Some(failed) = first_failure(&responses) {
+                return Ok(failed.into_response());
+            }
+            let bodies: Vec<Value> = responses.iter().map(|r| r.json()).collect();
+            Ok(json_response(merge::sum_field(&bodies, "count")))
+        }
+    }
+}
+
+// ANY /api/collections/{collection}[/...] - everything scoped to one collection
+async fn proxy_collection(
+    State(state): State<SharedClusterState>,
+    Path(params): Path<HashMap<String, String>>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response> {
+    let collection = params.get("collection").cloned().unwrap_or_default();
+    let rest = params.get("rest").cloned().unwrap_or_default();
+    let uri_str = uri.to_string();
+
+    if state.shard_by == ShardBy::Collection {
+        let node = state.owner(&collection)?;
+        return Ok(state.forward(&node, &method, &uri_str, &headers, body).await?.into_response());
+    }
+
+    let segments: Vec<&str> = rest.split('/').filter(|s| !s.is_empty()).collect();
+    let is_write = method == Method::POST
+        && matches!(segments.as_slice(), ["vectors"] | ["upsert"] | ["embed"]);
+
+    // Writes land on exactly one node. Upserts carry their id, so repeated upserts of the same
+    // document keep hitting the same shard; fresh inserts get a random placement.
+    if is_write {
+        let key = serde_json::from_slice::<Value>(&body)
+            .ok()
+            .and_then(|v| v.get("id").and_then(|id| id.as_str()).map(|id| id.to_string()))
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let node = state.owner(&format!("{collection}/{key}"))?;
+        return Ok(state.forward(&node, &method, &uri_str, &headers, body).await?.into_response());
+    }
+
+    let responses = state.broadcast(&method, &uri_str, &headers, body.clone()).await?;
+
+    // Lookups by id: the document lives on one shard, the others answer 404
+    if method == Method::GET && matches!(segments.as_slice(), ["vectors", _]) {
+        let found = responses.iter().find(|r| r.is_success()).cloned();
+        return Ok(found.unwrap_or_else(|| responses[0].clone()).into_response());
+    }
+
+    if let Some(failed) = first_failure(&responses) {
+        return Ok(failed.into_response());
+    }
+    let bodies: Vec<Value> = responses.iter().map(|r| r.json()).collect();
+
+    let merged = match (method.clone(), segments.as_slice()) {
+        (Method::POST, ["search"]) | (Method::POST, ["search", "text"]) => {
+            let k = serde_json::from_slice::<Value>(&body)
+                .ok()
+                .and_then(|v| v.get("k").and_then(|k| k.as_u64()))
+                .unwrap_or(10) as usize;
+            merge::merge_search(&bodies, k)
+        }
+        (Method::POST, ["search", "range"]) => merge::merge_range(&bodies),
+        (Method::GET, []) | (Method::GET, ["count"]) => merge::sum_field(&bodies, "count"),
+        (Method::GET, ["vectors"]) => {
+            let limit = query_param(&uri, "limit").and_then(|l| l.parse().ok()).unwrap_or(100);
+            merge::concat_arrays(&bodies, limit)
+        }
+        (Method::DELETE, ["vectors"]) => merge::sum_field(&bodies, "deleted_count"),
+        (Method::DELETE, []) | (Method::DELETE, ["vectors", _]) => merge::any_deleted(&bodies),
+        _ => return Ok(side_by_side(responses)),
+    };
+    Ok(json_response(merged))
+}
+
+// Everything else (metrics, readyz, config, version): ask every node, answer side by side
+async fn broadcast_fallback(
+    State(state): State<SharedClusterState>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response> {
+    let responses = state.broadcast(&method, &uri.to_string(), &headers, body).await?;
+    Ok(side_by_side(responses))
+}
+
+fn api_router(state: SharedClusterState) -> Router<SharedClusterState> {
+    Router::new()
+        .route("/health", get(crate::server::handlers::health))
+        .route("/collections", get(list_collections).post(create_collection))
+        .route("/collections/{collection}", any(proxy_collection))
+        .route("/collections/{collection}/{*rest}", any(proxy_collection))
+        .fallback(broadcast_fallback)
+        .with_state(state)
+}
+
+// Router served instead of the normal API when the process runs in cluster mode
+pub fn create_cluster_router(state: SharedClusterState) -> Router {
+    let api = api_router(state.clone());
+    Router::<SharedClusterState>::new()
+        .nest("/api", api.clone())
+        .nest("/api/v1", api)
+        .layer(axum::middleware::from_fn(assign_request_id))
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
@@ -1,9 +1,14 @@
 use serde::{Serialize, Deserialize};
 
 use super::{
-    CollectionConfig, SearchConfig, QuantizationConfig, MemoryConfig, WalConfig,
-        ParallelismConfig, ExecutionMode, LimitsConfig,
+    CollectionConfig, SearchConfig, QuantizationConfig, MemoryConfig, ChecksumVerification, WalConfig,
+        ParallelismConfig, ExecutionMode, LimitsConfig, TransformConfig, TwoStageConfig, LoadSheddingConfig,
+        VectorValidationConfig, NonFinitePolicy, PreloadPolicy, IngestSourceConfig, CompressionConfig,
+        QueryCacheConfig, WalCompression, WalKey, CoreRange, MetadataIndexConfig, UsageConfig, SlowQueryConfig,
+        FaultInjectionConfig, SchedulerConfig, OffPeakWindow, QueryLogConfig, RerankConfig,
+        RecallMonitorConfig, EnrichmentConfig, EnrichmentStageConfig, DataDirLockConfig, DataDirConflict,
 };
+use std::collections::HashMap;
 use crate::index::IndexConfig;
 
 // Main application configuration struct that encompasses all sub-configurations for the collection, index, search, quantization, memory management, WAL, parallelism, and execution mode. This struct can be easily serialized/deserialized from JSON or other formats for configuration files or environment variable overrides.
@@ -17,8 +22,63 @@ pub struct AppConfig {
     pub execution: ExecutionMode,
     pub search: SearchConfig,
     pub limits: LimitsConfig,
+    #[serde(default)]
+    pub transform: TransformConfig, // default for every collection
+    #[serde(default)]
+    pub collection_transforms: HashMap<String, TransformConfig>, // per-collection overrides by name
+    #[serde(default)]
+    pub deterministic_collections: Vec<String>, // collections whose index builds are reproducible (see CollectionConfig::deterministic)
+    #[serde(default)]
+    pub two_stage: TwoStageConfig,
+    #[serde(default)]
+    pub hot_collections: HashMap<String, usize>, // collection name -> in-memory read replicas to keep
+    #[serde(default)]
+    pub persist_latency_histograms: bool, // keep per-collection latency histograms across restarts
+    #[serde(default = "default_latency_persist_interval_secs")]
+    pub latency_persist_interval_secs: u64, // how often persisted histograms are written (read at startup)
+    #[serde(default)]
+    pub load_shedding: LoadSheddingConfig, // interactive vs batch concurrency limits (read at startup)
+    #[serde(default)]
+    pub validation: VectorValidationConfig, // NaN/Inf and zero-vector checks for every collection
+    #[serde(default)]
+    pub preload: PreloadPolicy, // when collections found in data_dir at startup are opened
+    #[serde(default)]
+    pub collection_preload: HashMap<String, PreloadPolicy>, // per-collection overrides by name
+    #[serde(default)]
+    pub ingest: Vec<IngestSourceConfig>, // Kafka/NATS sources consumed into collections (read at startup)
+    #[serde(default)]
+    pub compression: CompressionConfig, // gzip/zstd response compression
+    #[serde(default)]
+    pub query_cache: QueryCacheConfig, // cached results of repeated searches (read at startup)
+    #[serde(default)]
+    pub metadata_index: MetadataIndexConfig, // metadata fields indexed for filtering in every collection
+    #[serde(default)]
+    pub enrichment: EnrichmentConfig, // metadata fields computed at insert time in every collection
+    #[serde(default)]
+    pub collection_enrichment: HashMap<String, EnrichmentConfig>, // per-collection overrides by name
+    #[serde(default = "default_min_seq_wait_ms")]
+    pub min_seq_wait_ms: u64, // longest a read with min_seq waits for the collection to catch up
+    #[serde(default)]
+    pub usage: UsageConfig, // embedding usage retention and monthly budgets
+    #[serde(default)]
+    pub slow_queries: SlowQueryConfig, // ring buffer of recent slow searches (read at startup)
+    #[serde(default)]
+    pub query_log: QueryLogConfig, // searches and click feedback kept on disk for offline evaluation (read at startup)
+    #[serde(default)]
+    pub rerank: RerankConfig, // collections re-ranked with recorded relevance feedback
+    #[serde(default)]
+    pub recall_monitor: RecallMonitorConfig, // live recall of approximate indexes against sampled exact results (read at startup)
+    #[serde(default)]
+    pub fault_injection: FaultInjectionConfig, // simulated latency, lock contention and errors per route (debug builds)
+    #[serde(default)]
+    pub scheduler: SchedulerConfig, // concurrency, priorities and off-peak hours of background work
+    #[serde(default)]
+    pub data_dir_lock: DataDirLockConfig, // one server per data dir, and what a second one does (read at startup)
 }
 
+fn default_min_seq_wait_ms() -> u64 { 5_000 }
+fn default_latency_persist_interval_secs() -> u64 { 30 }
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
@@ -30,39 +90,132 @@ impl Default for AppConfig {
             execution: ExecutionMode::Auto,
             search: SearchConfig::default(),
             limits: LimitsConfig::default(),
+            transform: TransformConfig::default(),
+            collection_transforms: HashMap::new(),
+            deterministic_collections: Vec::new(),
+            two_stage: TwoStageConfig::default(),
+            hot_collections: HashMap::new(),
+            persist_latency_histograms: false,
+            latency_persist_interval_secs: default_latency_persist_interval_secs(),
+            load_shedding: LoadSheddingConfig::default(),
+            validation: VectorValidationConfig::default(),
+            preload: PreloadPolicy::default(),
+            collection_preload: HashMap::new(),
+            ingest: Vec::new(),
+            compression: CompressionConfig::default(),
+            query_cache: QueryCacheConfig::default(),
+            metadata_index: MetadataIndexConfig::default(),
+            enrichment: EnrichmentConfig::default(),
+            collection_enrichment: HashMap::new(),
+            min_seq_wait_ms: default_min_seq_wait_ms(),
+            usage: UsageConfig::default(),
+            slow_queries: SlowQueryConfig::default(),
+            query_log: QueryLogConfig::default(),
+            rerank: RerankConfig::default(),
+            recall_monitor: RecallMonitorConfig::default(),
+            fault_injection: FaultInjectionConfig::default(),
+            scheduler: SchedulerConfig::default(),
+            data_dir_lock: DataDirLockConfig::default(),
         }
     }
 }
 
 impl AppConfig {
     pub fn validate(&self) -> Result<(), String> {
-        if self.wal.enabled {
-            if self.wal.checkpoint_frequency == 0 {
-                return Err("WAL checkpoint_frequency must be > 0 when WAL is enabled".into());
-            }
+        if self.wal.enabled && self.wal.checkpoint_frequency == 0 {
+            return Err("WAL checkpoint_frequency must be > 0 when WAL is enabled".into());
         }
         if self.search.filter_overfetch == 0 {
             return Err("SEARCH filter_overfetch must be >= 1".into());
         }
+        if self.search.max_filter_overfetch < self.search.filter_overfetch {
+            return Err("SEARCH max_filter_overfetch must be >= filter_overfetch".into());
+        }
         if self.memory.use_mmap && self.memory.initial_mmap_size == 0 {
             return Err("MEMORY initial_mmap_size must be > 0 when mmap is enabled".into());
         }
+        if let IndexConfig::Hnsw { shards: 0, .. } = self.index {
+            return Err("INDEX hnsw shards must be >= 1".into());
+        }
+        if self.persist_latency_histograms && self.latency_persist_interval_secs == 0 {
+            return Err("latency_persist_interval_secs must be >= 1 when persist_latency_histograms is set".into());
+        }
+        self.wal.backpressure.validate()?;
+        self.transform.validate()?;
+        for (name, transform) in &self.collection_transforms {
+            transform.validate().map_err(|e| format!("{e} (collection '{name}')"))?;
+        }
+        self.two_stage.validate()?;
+        self.load_shedding.validate()?;
+        self.compression.validate()?;
+        self.query_cache.validate()?;
+        self.parallelism.validate()?;
+        self.scheduler.validate()?;
+        self.data_dir_lock.validate()?;
+        self.metadata_index.validate()?;
+        self.enrichment.validate()?;
+        for (name, enrichment) in &self.collection_enrichment {
+            enrichment.validate().map_err(|e| format!("{e} (collection '{name}')"))?;
+        }
+        self.usage.validate()?;
+        self.slow_queries.validate()?;
+        self.query_log.validate()?;
+        self.rerank.validate()?;
+        self.recall_monitor.validate()?;
+        self.fault_injection.validate()?;
+        self.limits.validate()?;
+        for (i, source) in self.ingest.iter().enumerate() {
+            source.validate()?;
+            if self.ingest[..i].iter().any(|s| s.name == source.name) {
+                return Err(format!("INGEST source name '{}' is used twice", source.name));
+            }
+        }
+        for (name, replicas) in &self.hot_collections {
+            if *replicas == 0 {
+                return Err(format!("HOT_COLLECTIONS replicas must be >= 1 (collection '{name}')"));
+            }
+        }
         Ok(())
     }
 
     pub fn to_collection_config(&self) -> CollectionConfig {
         CollectionConfig {
             index: self.index.clone(),
-            search: self.search.clone(),
-            quantization: self.quantization.clone(),
-            memory: self.memory.clone(),
-            wal: self.wal.clone(),
-            parallelism: self.parallelism.clone(),
+            search: self.search,
+            quantization: self.quantization,
+            memory: self.memory,
+            wal: self.wal,
+            parallelism: self.parallelism,
             execution: self.execution,
-            limits: self.limits.clone(),
+            limits: self.limits,
+            transform: self.transform,
+            two_stage: self.two_stage,
+            validation: self.validation,
+            metadata_index: self.metadata_index.clone(),
+            enrichment: self.enrichment.clone(),
+            ephemeral: false,
+            deterministic: false,
         }
     }
 
+    // Collection config with the per-collection overrides for `name` applied
+    pub fn collection_config(&self, name: &str) -> CollectionConfig {
+        let mut config = self.to_collection_config();
+        if let Some(transform) = self.collection_transforms.get(name) {
+            config.transform = *transform;
+        }
+        if let Some(enrichment) = self.collection_enrichment.get(name) {
+            config.enrichment = enrichment.clone();
+        }
+        config.deterministic = self.deterministic_collections.iter().any(|n| n == name);
+        config
+    }
+
+    // Preload policy for `name`, the per-collection override if there is one
+    pub fn preload_policy(&self, name: &str) -> PreloadPolicy {
+        self.collection_preload.get(name).copied().unwrap_or(self.preload)
+    }
+
     /// Apply environment variable overrides to an existing config.
     pub fn apply_env_overrides(&mut self) {
         // Check for environment variables that can override the default configuration values. This allows users to configure the application using environment variables without needing to modify configuration files. Each variable is checked and parsed, and if valid, it updates the corresponding configuration field.
@@ -71,7 +224,7 @@ impl AppConfig {
                 "flat" => IndexConfig::Flat {
                     metric: crate::metrics::Metric::Cosine,
                     mode: ExecutionMode::Auto,
-                    search: self.search.clone(),
+                    search: self.search,
                 },
                 "hnsw" => IndexConfig::Hnsw {
                     m: 16,
@@ -81,7 +234,8 @@ impl AppConfig {
                     ml: 1.0 / (16.0_f32).ln(),
                     metric: crate::metrics::Metric::Cosine,
                     mode: ExecutionMode::Auto,
-                    search: self.search.clone(),
+                    search: self.search,
+                    shards: 1,
                 },
                 "ivf" => IndexConfig::Ivf {
                     num_clusters: 256,
@@ -89,11 +243,16 @@ impl AppConfig {
                     max_iterations: 20,
                     metric: crate::metrics::Metric::Cosine,
                     mode: ExecutionMode::Auto,
-                    search: self.search.clone(),
+                    search: self.search,
                 },
                 _ => self.index.clone(),
             };
         }
+        if let Ok(val) = std::env::var("HNSW_SHARDS") {
+            if let (Ok(n), IndexConfig::Hnsw { shards, .. }) = (val.parse::<usize>(), &mut self.index) {
+                *shards = n.max(1);
+            }
+        }
 
         if let Ok(val) = std::env::var("WAL_ENABLED") {
             self.wal.enabled = val == "1" || val.eq_ignore_ascii_case("true");
@@ -108,10 +267,219 @@ impl AppConfig {
                 self.wal.checkpoint_interval_secs = Some(secs.max(1));
             }
         }
+        if let Ok(val) = std::env::var("WAL_HISTORY_RETENTION_SECS") {
+            if let Ok(secs) = val.parse::<u64>() {
+                self.wal.history_retention_secs = Some(secs);
+            }
+        }
+        if let Ok(val) = std::env::var("WAL_ARCHIVE_SEGMENTS") {
+            self.wal.archive_segments = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("WAL_MAX_LOG_SIZE_MB") {
+            if let Ok(mb) = val.parse::<usize>() {
+                self.wal.max_log_size = mb * 1024 * 1024;
+            }
+        }
+        if let Ok(val) = std::env::var("WAL_COMPRESSION") {
+            if let Some(compression) = WalCompression::parse(&val) {
+                self.wal.compression = compression;
+            }
+        }
+        if let Ok(val) = std::env::var("WAL_ENCRYPTION_KEY") {
+            if let Ok(key) = WalKey::from_hex(&val) {
+                self.wal.encryption_key = Some(key);
+            }
+        }
+        if let Ok(val) = std::env::var("WAL_THROTTLE_MB") {
+            if let Ok(mb) = val.parse::<u64>() {
+                self.wal.backpressure.throttle_bytes = Some(mb * 1024 * 1024);
+            }
+        }
+        if let Ok(val) = std::env::var("WAL_REJECT_MB") {
+            if let Ok(mb) = val.parse::<u64>() {
+                self.wal.backpressure.reject_bytes = Some(mb * 1024 * 1024);
+            }
+        }
+        if let Ok(val) = std::env::var("WAL_THROTTLE_OPS") {
+            if let Ok(ops) = val.parse::<u64>() {
+                self.wal.backpressure.throttle_ops = Some(ops);
+            }
+        }
+        if let Ok(val) = std::env::var("WAL_REJECT_OPS") {
+            if let Ok(ops) = val.parse::<u64>() {
+                self.wal.backpressure.reject_ops = Some(ops);
+            }
+        }
+        if let Ok(val) = std::env::var("WAL_WRITE_RETRIES") {
+            if let Ok(attempts) = val.parse::<u32>() {
+                self.wal.retry.attempts = attempts;
+            }
+        }
+
+        if let Ok(val) = std::env::var("LOAD_SHEDDING_ENABLED") {
+            self.load_shedding.enabled = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("LOAD_SHEDDING_INTERACTIVE_CONCURRENCY") {
+            if let Ok(n) = val.parse::<usize>() {
+                self.load_shedding.interactive.max_concurrent = n.max(1);
+            }
+        }
+        if let Ok(val) = std::env::var("LOAD_SHEDDING_BATCH_CONCURRENCY") {
+            if let Ok(n) = val.parse::<usize>() {
+                self.load_shedding.batch.max_concurrent = n.max(1);
+            }
+        }
+        if let Ok(val) = std::env::var("LOAD_SHEDDING_BATCH_API_KEYS") {
+            self.load_shedding.batch_api_keys = val
+                .split(',')
+                .map(|k| k.trim().to_string())
+                .filter(|k| !k.is_empty())
+                .collect();
+        }
+        if let Ok(val) = std::env::var("COMPRESSION_ENABLED") {
+            self.compression.enabled = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("COMPRESSION_MIN_SIZE_BYTES") {
+            if let Ok(n) = val.parse::<usize>() {
+                self.compression.min_size_bytes = n;
+            }
+        }
+        if let Ok(val) = std::env::var("COMPRESSION_LEVEL") {
+            if let Ok(n) = val.parse::<u32>() {
+                self.compression.level = n.clamp(1, 9);
+            }
+        }
+        if let Ok(val) = std::env::var("QUERY_CACHE_ENABLED") {
+            self.query_cache.enabled = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("QUERY_CACHE_MAX_ENTRIES") {
+            if let Ok(n) = val.parse::<usize>() {
+                self.query_cache.max_entries = n.max(1);
+            }
+        }
+        if let Ok(val) = std::env::var("QUERY_CACHE_TTL_SECS") {
+            if let Ok(secs) = val.parse::<u64>() {
+                self.query_cache.ttl_secs = secs;
+            }
+        }
+        if let Ok(val) = std::env::var("QUERY_CACHE_MAX_SEQ_LAG") {
+            if let Ok(lag) = val.parse::<u64>() {
+                self.query_cache.max_seq_lag = lag;
+            }
+        }
+        if let Ok(val) = std::env::var("SLOW_QUERIES_ENABLED") {
+            self.slow_queries.enabled = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("SLOW_QUERIES_CAPACITY") {
+            if let Ok(n) = val.parse::<usize>() {
+                self.slow_queries.capacity = n.max(1);
+            }
+        }
+        if let Ok(val) = std::env::var("QUERY_LOG_ENABLED") {
+            self.query_log.enabled = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("QUERY_LOG_STORE_QUERIES") {
+            self.query_log.store_queries = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("QUERY_LOG_MAX_FILE_MB") {
+            if let Ok(mb) = val.parse::<u64>() {
+                self.query_log.max_file_bytes = mb * 1024 * 1024;
+            }
+        }
+        if let Ok(val) = std::env::var("RERANK_COLLECTIONS") {
+            self.rerank.collections = val.split(',').map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect();
+        }
+        if let Ok(val) = std::env::var("RERANK_PRIOR_STRENGTH") {
+            if let Ok(n) = val.parse::<f32>() {
+                self.rerank.prior_strength = n;
+            }
+        }
+        if let Ok(val) = std::env::var("RERANK_LEARNING_RATE") {
+            if let Ok(n) = val.parse::<f32>() {
+                self.rerank.learning_rate = n;
+            }
+        }
+        if let Ok(val) = std::env::var("RECALL_MONITOR_ENABLED") {
+            self.recall_monitor.enabled = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("RECALL_MONITOR_QUERIES") {
+            if let Ok(n) = val.parse::<usize>() {
+                self.recall_monitor.queries = n.max(1);
+            }
+        }
+        if let Ok(val) = std::env::var("RECALL_MONITOR_K") {
+            if let Ok(n) = val.parse::<usize>() {
+                self.recall_monitor.k = n.max(1);
+            }
+        }
+        if let Ok(val) = std::env::var("RECALL_MONITOR_INTERVAL_SECS") {
+            if let Ok(secs) = val.parse::<u64>() {
+                self.recall_monitor.interval_secs = secs.max(1);
+            }
+        }
+        if let Ok(val) = std::env::var("RECALL_MONITOR_ALERT_BELOW") {
+            if let Ok(n) = val.parse::<f32>() {
+                self.recall_monitor.alert_below = n;
+            }
+        }
+        if let Ok(val) = std::env::var("FAULT_INJECTION_ENABLED") {
+            self.fault_injection.enabled = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("MIN_SEQ_WAIT_MS") {
+            if let Ok(ms) = val.parse::<u64>() {
+                self.min_seq_wait_ms = ms;
+            }
+        }
+        if let Ok(val) = std::env::var("PRELOAD_DEFAULT") {
+            if let Some(policy) = PreloadPolicy::parse(&val) {
+                self.preload = policy;
+            }
+        }
+        // name:policy pairs, e.g. PRELOAD_COLLECTIONS=docs:eager,archive:never
+        if let Ok(val) = std::env::var("PRELOAD_COLLECTIONS") {
+            for pair in val.split(',') {
+                if let Some((name, policy)) = pair.split_once(':') {
+                    if let Some(policy) = PreloadPolicy::parse(policy.trim()) {
+                        self.collection_preload.insert(name.trim().to_string(), policy);
+                    }
+                }
+            }
+        }
+        // Comma-separated collection names
+        if let Ok(val) = std::env::var("DETERMINISTIC_COLLECTIONS") {
+            self.deterministic_collections = val.split(',').map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect();
+        }
+        if let Ok(val) = std::env::var("LATENCY_PERSIST") {
+            self.persist_latency_histograms = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("LATENCY_PERSIST_INTERVAL_SECS") {
+            if let Ok(secs) = val.parse::<u64>() {
+                self.latency_persist_interval_secs = secs.max(1);
+            }
+        }
 
         if let Ok(val) = std::env::var("MEMORY_USE_MMAP") {
             self.memory.use_mmap = val == "1" || val.eq_ignore_ascii_case("true");
         }
+        if let Ok(val) = std::env::var("MEMORY_VECTOR_COLUMN") {
+            self.memory.vector_column = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("MEMORY_IO_URING") {
+            self.memory.io_uring = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("MEMORY_PREFETCH") {
+            self.memory.prefetch = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("MEMORY_CHECKSUMS") {
+            if let Some(mode) = ChecksumVerification::parse(&val) {
+                self.memory.checksums = mode;
+            }
+        }
+        if let Ok(val) = std::env::var("INDEX_MEMORY_BUDGET_MB") {
+            if let Ok(mb) = val.parse::<usize>() {
+                self.memory.index_memory_budget = Some(mb * 1024 * 1024);
+            }
+        }
         if let Ok(val) = std::env::var("MEMORY_INITIAL_MMAP_MB") {
             if let Ok(mb) = val.parse::<usize>() {
                 self.memory.initial_mmap_size = mb * 1024 * 1024;
@@ -126,6 +494,40 @@ impl AppConfig {
                 self.parallelism = self.parallelism.with_num_threads(n);
             }
         }
+        if let Ok(val) = std::env::var("QUERY_THREADS") {
+            if let Ok(n) = val.parse::<usize>() {
+                self.parallelism.query_threads = Some(n.max(1));
+            }
+        }
+        if let Ok(val) = std::env::var("MAINTENANCE_THREADS") {
+            if let Ok(n) = val.parse::<usize>() {
+                self.parallelism.maintenance_threads = Some(n.max(1));
+            }
+        }
+        if let Ok(val) = std::env::var("QUERY_CORES") {
+            if let Some(range) = CoreRange::parse(&val) {
+                self.parallelism.query_cores = Some(range);
+            }
+        }
+        if let Ok(val) = std::env::var("MAINTENANCE_CORES") {
+            if let Some(range) = CoreRange::parse(&val) {
+                self.parallelism.maintenance_cores = Some(range);
+            }
+        }
+
+        if let Ok(val) = std::env::var("SCHEDULER_MAX_CONCURRENT") {
+            if let Ok(n) = val.parse::<usize>() {
+                self.scheduler.max_concurrent = n.max(1);
+            }
+        }
+        if let Ok(val) = std::env::var("SCHEDULER_MAX_LOW_PRIORITY") {
+            if let Ok(n) = val.parse::<usize>() {
+                self.scheduler.max_low_priority = n.max(1);
+            }
+        }
+        if let Ok(val) = std::env::var("SCHEDULER_OFF_PEAK") {
+            self.scheduler.off_peak = OffPeakWindow::parse(&val);
+        }
 
         if let Ok(val) = std::env::var("EXECUTION_MODE") {
             self.execution = match val.to_lowercase().as_str() {
@@ -134,6 +536,7 @@ impl AppConfig {
                 "gpu" => ExecutionMode::Gpu,
                 "parallel" => ExecutionMode::Parallel,
                 "binary" => ExecutionMode::Binary,
+                "binary_rerank" => ExecutionMode::BinaryRerank,
                 "jit" => ExecutionMode::Jit,
                 _ => ExecutionMode::Auto,
             };
@@ -146,6 +549,76 @@ impl AppConfig {
                 self.search.filter_overfetch = factor.max(1);
             }
         }
+        if let Ok(val) = std::env::var("SEARCH_ADAPTIVE_OVERFETCH") {
+            self.search.adaptive_overfetch = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("SEARCH_MAX_FILTER_OVERFETCH") {
+            if let Ok(cap) = val.parse::<usize>() {
+                self.search.max_filter_overfetch = cap.max(1);
+            }
+        }
+
+        if let Ok(val) = std::env::var("TRANSFORM_TRUNCATE_DIMS") {
+            if let Ok(dims) = val.parse::<usize>() {
+                self.transform.truncate_dims = (dims > 0).then_some(dims);
+            }
+        }
+        if let Ok(val) = std::env::var("TRANSFORM_NORMALIZE") {
+            self.transform.normalize = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("TRANSFORM_RERANK") {
+            self.transform.rerank = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("TRANSFORM_RERANK_OVERFETCH") {
+            if let Ok(factor) = val.parse::<usize>() {
+                self.transform.rerank_overfetch = factor.max(1);
+            }
+        }
+
+        if let Ok(val) = std::env::var("TWO_STAGE_ENABLED") {
+            self.two_stage.enabled = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("TWO_STAGE_CANDIDATES") {
+            if let Ok(c) = val.parse::<usize>() {
+                self.two_stage.candidates = c.max(1);
+            }
+        }
+
+        if let Ok(val) = std::env::var("METADATA_INDEX_FIELDS") {
+            self.metadata_index.fields = val.split(',').map(str::trim).filter(|f| !f.is_empty()).map(String::from).collect();
+        }
+
+        if let Ok(val) = std::env::var("DATA_DIR_LOCK_ENABLED") {
+            self.data_dir_lock.enabled = val == "1" || val.eq_ignore_ascii_case("true");
+        }
+        if let Ok(val) = std::env::var("DATA_DIR_LOCK_ON_CONFLICT") {
+            if let Some(on_conflict) = DataDirConflict::parse(&val) {
+                self.data_dir_lock.on_conflict = on_conflict;
+            }
+        }
+        if let Ok(val) = std::env::var("DATA_DIR_LOCK_HEARTBEAT_SECS") {
+            if let Ok(secs) = val.parse::<u64>() {
+                self.data_dir_lock.heartbeat_secs = secs;
+            }
+        }
+        if let Ok(val) = std::env::var("DATA_DIR_LOCK_STALE_AFTER_SECS") {
+            if let Ok(secs) = val.parse::<u64>() {
+                self.data_dir_lock.stale_after_secs = secs;
+            }
+        }
+
+        if let Ok(val) = std::env::var("ENRICHMENT_STAGES") {
+            self.enrichment.stages = val.split(',').map(str::trim).filter_map(EnrichmentStageConfig::parse).collect();
+        }
+
+        if let Ok(val) = std::env::var("VECTOR_NON_FINITE") {
+            if let Some(policy) = NonFinitePolicy::parse(&val) {
+                self.validation.non_finite = policy;
+            }
+        }
+        if let Ok(val) = std::env::var("VECTOR_REJECT_ZERO_COSINE") {
+            self.validation.reject_zero_cosine = val == "1" || val.eq_ignore_ascii_case("true");
+        }
 
         if let Ok(val) = std::env::var("LIMIT_MAX_VECTORS") {
             if let Ok(v) = val.parse::<usize>() {
@@ -162,6 +635,21 @@ impl AppConfig {
                 self.limits.max_vector_bytes = Some(v);
             }
         }
+        if let Ok(val) = std::env::var("LIMIT_MAX_DOCUMENT_BYTES") {
+            if let Ok(v) = val.parse::<usize>() {
+                self.limits.max_document_bytes = v;
+            }
+        }
+        if let Ok(val) = std::env::var("LIMIT_MAX_BODY_BYTES") {
+            if let Ok(v) = val.parse::<usize>() {
+                self.limits.max_body_bytes = v;
+            }
+        }
+        if let Ok(val) = std::env::var("LIMIT_SPOOL_THRESHOLD_BYTES") {
+            if let Ok(v) = val.parse::<usize>() {
+                self.limits.spool_threshold_bytes = v;
+            }
+        }
     }
 
     pub fn from_env() -> Self {
@@ -0,0 +1,61 @@
+// Cluster (router mode) configuration
+// When backend nodes are configured, a piramid process runs as a stateless router: it owns no
+// collections itself and proxies every request to the backend node(s) chosen by consistent hashing.
+use serde::{Deserialize, Serialize};
+
+// What the router hashes to pick a backend node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShardBy {
+    // Every collection lives entirely on one node (simple, no result merging)
+    #[default]
+    Collection,
+    // Documents of a collection are spread across all nodes; searches fan out and merge
+    Document,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClusterConfig {
+    // Base URLs of the backend piramid nodes, e.g. "http://10.0.0.2:6333"
+    pub nodes: Vec<String>,
+
+    #[serde(default)]
+    pub shard_by: ShardBy,
+
+    // Points per node on the hash ring (more = smoother distribution)
+    #[serde(default = "default_virtual_nodes")]
+    pub virtual_nodes: usize,
+
+    // Per-request timeout when talking to a backend node
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+}
+
+impl ClusterConfig {
+    pub fn new(nodes: Vec<String>) -> Self {
+        Self {
+            nodes,
+            shard_by: ShardBy::default(),
+            virtual_nodes: default_virtual_nodes(),
+            request_timeout_secs: default_request_timeout_secs(),
+        }
+    }
+
+    pub fn with_shard_by(mut self, shard_by: ShardBy) -> Self {
+        self.shard_by = shard_by;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.nodes.is_empty() {
+            return Err("CLUSTER nodes must not be empty".into());
+        }
+        if self.virtual_nodes == 0 {
+            return Err("CLUSTER virtual_nodes must be >= 1".into());
+        }
+        Ok(())
+    }
+}
+
+fn default_virtual_nodes() -> usize { 64 }
+fn default_request_timeout_secs() -> u64 { 30 }
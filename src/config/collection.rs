@@ -37,6 +37,35 @@ pub struct CollectionConfig {
     // Limits configuration
     #[serde(default)]
     pub limits: LimitsConfig,
+
+    // Vector transformation (dimension truncation) applied before indexing and search
+    #[serde(default)]
+    pub transform: TransformConfig,
+
+    // Two-stage search: quantized scan + exact re-rank on full-precision vectors
+    #[serde(default)]
+    pub two_stage: TwoStageConfig,
+
+    // NaN/Inf and zero-vector checks on inserted vectors and search queries
+    #[serde(default)]
+    pub validation: VectorValidationConfig,
+
+    // Fields with per-value posting lists for filter evaluation
+    #[serde(default)]
+    pub metadata_index: MetadataIndexConfig,
+
+    // Metadata fields computed from each inserted document (text length, language, URL host)
+    #[serde(default)]
+    pub enrichment: EnrichmentConfig,
+
+    // Keep everything in RAM: no data file, WAL, checkpoints or sidecar files, nothing left behind on drop
+    #[serde(default)]
+    pub ephemeral: bool,
+
+    // Reproducible ANN results: HNSW layers and IVF's initial centroids come from the vector ids
+    // instead of a random draw or hash map order, so the same vectors give the same index every run
+    #[serde(default)]
+    pub deterministic: bool,
 }
 
 impl Default for CollectionConfig {
@@ -50,11 +79,25 @@ impl Default for CollectionConfig {
             parallelism: ParallelismConfig::default(),
             execution: ExecutionMode::Auto,
             limits: LimitsConfig::default(),
+            transform: TransformConfig::default(),
+            two_stage: TwoStageConfig::default(),
+            validation: VectorValidationConfig::default(),
+            metadata_index: MetadataIndexConfig::default(),
+            enrichment: EnrichmentConfig::default(),
+            ephemeral: false,
+            deterministic: false,
         }
     }
 }
 
 impl CollectionConfig {
+    // Empty vector index of the configured kind, set up for `num_vectors` vectors
+    pub fn create_index(&self, num_vectors: usize) -> Box<dyn crate::index::VectorIndex> {
+        let mut index = self.index.create_index(num_vectors);
+        index.set_deterministic(self.deterministic);
+        index
+    }
+
     // Create a new config with custom index
     pub fn with_index(index: crate::index::IndexConfig) -> Self {
         CollectionConfig {
@@ -98,4 +141,40 @@ impl CollectionConfig {
         self.limits = limits;
         self
     }
+
+    // Set vector transformation
+    pub fn with_transform(mut self, transform: TransformConfig) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    // Enable two-stage search
+    pub fn with_two_stage(mut self, two_stage: TwoStageConfig) -> Self {
+        self.two_stage = two_stage;
+        self
+    }
+
+    // Set vector validation
+    pub fn with_validation(mut self, validation: VectorValidationConfig) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    // Index the given metadata fields for filtering
+    pub fn with_metadata_index(mut self, metadata_index: MetadataIndexConfig) -> Self {
+        self.metadata_index = metadata_index;
+        self
+    }
+
+    // Compute metadata fields for each inserted document
+    pub fn with_enrichment(mut self, enrichment: EnrichmentConfig) -> Self {
+        self.enrichment = enrichment;
+        self
+    }
+
+    // Keep the collection in memory only (the path just names it)
+    pub fn ephemeral(mut self) -> Self {
+        self.ephemeral = true;
+        self
+    }
 }
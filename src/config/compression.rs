@@ -0,0 +1,60 @@
+// Response compression configuration
+// Responses are compressed with gzip or zstd when the client's Accept-Encoding allows one of the
+// enabled encodings and the body is at least `min_size_bytes`. Small bodies are sent as they are:
+// the headers and the CPU time cost more than the bytes saved. Read on every response, so a config
+// reload takes effect immediately.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    // Bodies smaller than this are never compressed
+    #[serde(default = "default_min_size_bytes")]
+    pub min_size_bytes: usize,
+
+    // Compression level, 1 (fastest) to 9 (smallest output)
+    #[serde(default = "default_level")]
+    pub level: u32,
+
+    #[serde(default = "default_true")]
+    pub gzip: bool,
+
+    #[serde(default = "default_true")]
+    pub zstd: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_min_size_bytes() -> usize {
+    1024
+}
+
+fn default_level() -> u32 {
+    6
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size_bytes: default_min_size_bytes(),
+            level: default_level(),
+            gzip: true,
+            zstd: true,
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=9).contains(&self.level) {
+            return Err("COMPRESSION level must be between 1 and 9".into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+// Ownership of the data dir by one server process
+// A server takes `piramid.lock` in its data dir at startup and keeps a heartbeat in it; a second
+// server pointed at the same dir finds a live owner and refuses to start, or waits as a standby
+// until the owner is gone. See server/data_dir_lock.rs.
+
+use serde::{Deserialize, Serialize};
+
+// What a server does when another live server owns its data dir
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataDirConflict {
+    // Exit with the owner named in the error
+    #[default]
+    Refuse,
+    // Serve /api/health and turn everything else away with DATA_DIR_LOCKED, then take over once the owner is gone
+    Standby,
+}
+
+impl DataDirConflict {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "refuse" => Some(Self::Refuse),
+            "standby" => Some(Self::Standby),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataDirLockConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub on_conflict: DataDirConflict,
+    // How often the owner rewrites its heartbeat; also how often a standby checks the lock
+    #[serde(default = "default_heartbeat_secs")]
+    pub heartbeat_secs: u64,
+    // An owner on another host (a shared filesystem that may not honour file locks) counts as gone
+    // once its heartbeat is this old
+    #[serde(default = "default_stale_after_secs")]
+    pub stale_after_secs: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_heartbeat_secs() -> u64 {
+    10
+}
+
+fn default_stale_after_secs() -> u64 {
+    60
+}
+
+impl Default for DataDirLockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            on_conflict: DataDirConflict::default(),
+            heartbeat_secs: default_heartbeat_secs(),
+            stale_after_secs: default_stale_after_secs(),
+        }
+    }
+}
+
+impl DataDirLockConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.heartbeat_secs == 0 {
+            return Err("DATA_DIR_LOCK heartbeat_secs must be >= 1".into());
+        }
+        if self.stale_after_secs <= self.heartbeat_secs {
+            return Err("DATA_DIR_LOCK stale_after_secs must be > heartbeat_secs".into());
+        }
+        Ok(())
+    }
+}
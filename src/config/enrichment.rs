@@ -0,0 +1,105 @@
+// Insert-time metadata enrichment
+// Stages run on every inserted or upserted document before it is logged, writing fields derived
+// from its text or metadata (length, language, URL host), so those can be filtered and indexed like
+// fields the client sent. What each stage computes lives in storage/enrichment.rs; library users can
+// add stages of their own to an open collection next to the configured ones.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EnrichmentConfig {
+    // Run in order; a later stage sees the fields written by earlier ones
+    #[serde(default)]
+    pub stages: Vec<EnrichmentStageConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EnrichmentStageConfig {
+    // Length of the document's text
+    TextLength {
+        #[serde(default = "default_length_field")]
+        field: String,
+        #[serde(default)]
+        unit: LengthUnit,
+    },
+    // ISO 639-1 code of the text's language; left unset when it cannot be told
+    Language {
+        #[serde(default = "default_language_field")]
+        field: String,
+    },
+    // Lowercased host of the URL in metadata field `source` (each host when it holds a list of URLs)
+    UrlHost {
+        #[serde(default = "default_url_field")]
+        source: String,
+        #[serde(default = "default_host_field")]
+        field: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthUnit {
+    #[default]
+    Chars,
+    Words,
+}
+
+fn default_length_field() -> String {
+    "text_length".to_string()
+}
+
+fn default_language_field() -> String {
+    "language".to_string()
+}
+
+fn default_url_field() -> String {
+    "url".to_string()
+}
+
+fn default_host_field() -> String {
+    "url_host".to_string()
+}
+
+impl EnrichmentStageConfig {
+    // The stage of this type with its default fields, e.g. from ENRICHMENT_STAGES
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "text_length" => Some(Self::TextLength { field: default_length_field(), unit: LengthUnit::Chars }),
+            "language" => Some(Self::Language { field: default_language_field() }),
+            "url_host" => Some(Self::UrlHost { source: default_url_field(), field: default_host_field() }),
+            _ => None,
+        }
+    }
+
+    // Metadata field the stage writes
+    pub fn field(&self) -> &str {
+        match self {
+            Self::TextLength { field, .. } | Self::Language { field } | Self::UrlHost { field, .. } => field,
+        }
+    }
+}
+
+impl EnrichmentConfig {
+    pub fn with_stages(stages: Vec<EnrichmentStageConfig>) -> Self {
+        Self { stages }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.stages.is_empty()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, stage) in self.stages.iter().enumerate() {
+            let field = stage.field();
+            // Fields starting with '_' (the client id among them) belong to the engine
+            if field.trim().is_empty() || field.starts_with(crate::storage::RESERVED_KEY_PREFIX) {
+                return Err(format!("ENRICHMENT cannot write field '{field}'"));
+            }
+            if self.stages[..i].iter().any(|s| s.field() == field) {
+                return Err(format!("ENRICHMENT field '{field}' is written by two stages"));
+            }
+        }
+        Ok(())
+    }
+}
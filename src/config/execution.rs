@@ -4,18 +4,23 @@
 use serde::{Deserialize, Serialize};
 
 // Execution mode for vector operations
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub enum ExecutionMode {
     #[default]
     Auto,
     Simd,
     Scalar,
-    // Offload to GPU via CUDA/OpenCL
+    // Batched flat search on the GPU (wgpu with the `gpu` feature, or a registered device); CPU
+    // fallback without one
     Gpu,
     // Multi-threaded CPU execution
     Parallel,
-    // Use bitwise operations on 1-bit quantized vectors
+    // Score from 1-bit (sign) codes of the vectors. Scores are approximations that only keep the
+    // ranking roughly, and are not comparable with scores of any other mode
     Binary,
+    // Binary prefilter + f32 re-rank: every document is scored from its sign bits, the best
+    // BINARY_RERANK_OVERFETCH * k are re-scored with the full vectors, and hits carry exact scores
+    BinaryRerank,
     // Use Just-In-Time compiled kernels for specific vector dimensions
     Jit,
 }
@@ -61,11 +66,12 @@ impl ExecutionMode {
             },
             ExecutionMode::Scalar => ExecutionMode::Scalar,
             ExecutionMode::Gpu => {
-                // GPU not implemented, fallback to best available
+                // Single distances run on the CPU; only flat batches use the device
                 ExecutionMode::Auto.resolve()
             },
             ExecutionMode::Parallel => ExecutionMode::Parallel,
             ExecutionMode::Binary => ExecutionMode::Binary,
+            ExecutionMode::BinaryRerank => ExecutionMode::BinaryRerank,
             ExecutionMode::Jit => ExecutionMode::Jit,
         }
     }
@@ -75,6 +81,19 @@ impl ExecutionMode {
         matches!(self.resolve(), ExecutionMode::Simd | ExecutionMode::Parallel)
     }
     
+    // Mode for scores that have to be exact: the binary modes fall back to the best exact kernels
+    pub fn exact(&self) -> ExecutionMode {
+        match self {
+            ExecutionMode::Binary | ExecutionMode::BinaryRerank => ExecutionMode::Auto.resolve(),
+            other => other.resolve(),
+        }
+    }
+
+    // Whether hits come back with exact scores. Only `Binary` returns sign-code approximations.
+    pub fn exact_scores(&self) -> bool {
+        !matches!(self, ExecutionMode::Binary)
+    }
+
     // Check if parallel execution should be used
     pub fn use_parallel(&self) -> bool {
         matches!(self.resolve(), ExecutionMode::Parallel)
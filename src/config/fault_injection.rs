@@ -0,0 +1,93 @@
+// Simulated failures, for testing clients' retry and fallback logic against a real server
+// Each rule names a route pattern and, independently drawn per request, the chances of added
+// latency, of the route's collection being write-locked for a while first (so the request and
+// everything else on that collection queue behind it), and of an error response in place of the
+// real one. Only served by debug builds or ones with the `fault-injection` feature; a config that
+// enables it is rejected anywhere else. Read per request, so a config reload applies it.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FaultInjectionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    // Every rule matching a request applies; their faults add up
+    #[serde(default)]
+    pub rules: Vec<FaultRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FaultRule {
+    // Path under /api (or /api/v1): `*` matches one segment, a trailing `**` the rest,
+    // e.g. `/api/collections/*/search` or `/api/collections/docs/**`
+    pub route: String,
+
+    // HTTP methods the rule applies to; any when empty
+    #[serde(default)]
+    pub methods: Vec<String>,
+
+    #[serde(default)]
+    pub latency_ms: u64,
+    #[serde(default)]
+    pub latency_probability: f64,
+
+    // How long the collection stays write-locked before the request goes on
+    #[serde(default)]
+    pub lock_ms: u64,
+    #[serde(default)]
+    pub lock_probability: f64,
+
+    #[serde(default = "default_error_status")]
+    pub error_status: u16,
+    #[serde(default)]
+    pub error_probability: f64,
+}
+
+fn default_error_status() -> u16 {
+    503
+}
+
+impl Default for FaultRule {
+    fn default() -> Self {
+        Self {
+            route: "/api/**".into(),
+            methods: Vec::new(),
+            latency_ms: 0,
+            latency_probability: 0.0,
+            lock_ms: 0,
+            lock_probability: 0.0,
+            error_status: default_error_status(),
+            error_probability: 0.0,
+        }
+    }
+}
+
+impl FaultInjectionConfig {
+    // Whether this build can inject faults at all
+    pub fn supported() -> bool {
+        cfg!(any(debug_assertions, feature = "fault-injection"))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if !Self::supported() {
+            return Err("FAULT_INJECTION needs a debug build or the fault-injection feature".into());
+        }
+        for rule in &self.rules {
+            if !rule.route.starts_with('/') {
+                return Err(format!("FAULT_INJECTION route '{}' must start with /", rule.route));
+            }
+            let probabilities = [rule.latency_probability, rule.lock_probability, rule.error_probability];
+            if probabilities.iter().any(|p| !(0.0..=1.0).contains(p)) {
+                return Err(format!("FAULT_INJECTION probabilities must be in [0, 1] (route '{}')", rule.route));
+            }
+            if !(400..=599).contains(&rule.error_status) {
+                return Err(format!("FAULT_INJECTION error_status must be 4xx or 5xx (route '{}')", rule.route));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+// Ingestion sources
+// Each source consumes `{id, text, vector?, metadata}` JSON messages from a Kafka topic partition or a
+// NATS JetStream consumer and upserts them into one collection in batches. Delivery is at-least-once:
+// a source position is only committed (Kafka: stored locally, NATS: acked) after the batch is in the
+// collection's WAL, so a crash re-delivers at most the batch in flight and upserts make the replay
+// idempotent for messages that carry an id.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestSourceConfig {
+    // Name of the source; also names its checkpoint file under data_dir/ingest/
+    pub name: String,
+    // Collection the messages are written to (created on first use)
+    pub collection: String,
+    #[serde(flatten)]
+    pub source: IngestSourceKind,
+    // Messages written per batch (one lock acquisition and one checkpoint per batch)
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    // How long a fetch waits for messages before writing a partial batch
+    #[serde(default = "default_batch_timeout_ms")]
+    pub batch_timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IngestSourceKind {
+    Kafka {
+        // Bootstrap brokers, host:port[,host:port...]; the partition leader is looked up from them
+        broker: String,
+        topic: String,
+        #[serde(default)]
+        partition: i32,
+        // Where to begin when there is no checkpoint yet
+        #[serde(default)]
+        start: IngestStart,
+    },
+    Nats {
+        // host:port of a NATS server with JetStream enabled
+        url: String,
+        // Stream and durable pull consumer; both must already exist
+        stream: String,
+        consumer: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestStart {
+    #[default]
+    Earliest,
+    Latest,
+}
+
+fn default_batch_size() -> usize {
+    256
+}
+
+fn default_batch_timeout_ms() -> u64 {
+    500
+}
+
+impl IngestSourceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            IngestSourceKind::Kafka { .. } => "kafka",
+            IngestSourceKind::Nats { .. } => "nats",
+        }
+    }
+
+    // Whether this build has the client (the `kafka` and `nats` features)
+    pub fn available(&self) -> bool {
+        match self {
+            IngestSourceKind::Kafka { .. } => cfg!(feature = "kafka"),
+            IngestSourceKind::Nats { .. } => cfg!(feature = "nats"),
+        }
+    }
+}
+
+impl IngestSourceConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() || !self.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("INGEST source name '{}' must be non-empty and use only [A-Za-z0-9_-]", self.name));
+        }
+        if self.batch_size == 0 {
+            return Err(format!("INGEST batch_size must be >= 1 (source '{}')", self.name));
+        }
+        if !self.source.available() {
+            let kind = self.source.name();
+            return Err(format!("INGEST source '{}' is {kind}, which needs a build with the `{kind}` feature", self.name));
+        }
+        Ok(())
+    }
+}
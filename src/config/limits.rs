@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LimitsConfig {
     /// Max number of vectors allowed in a collection (None = unlimited).
     pub max_vectors: Option<usize>,
@@ -8,4 +8,48 @@ pub struct LimitsConfig {
     pub max_bytes: Option<u64>,
     /// Optional per-vector serialized size cap (None = unlimited).
     pub max_vector_bytes: Option<usize>,
+    /// Max bytes of a single document's text in an insert or upsert; larger ones get a 413.
+    #[serde(default = "default_max_document_bytes")]
+    pub max_document_bytes: usize,
+    /// Max bytes of a request body; larger ones get a 413 before they are read in full.
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Insert and upsert bodies above this size are spooled to a temp file in data_dir while they
+    /// arrive, instead of being held in memory next to the documents decoded from them.
+    #[serde(default = "default_spool_threshold_bytes")]
+    pub spool_threshold_bytes: usize,
+}
+
+fn default_max_document_bytes() -> usize {
+    1_000_000
+}
+
+fn default_max_body_bytes() -> usize {
+    100 * 1024 * 1024
+}
+
+fn default_spool_threshold_bytes() -> usize {
+    8 * 1024 * 1024
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_vectors: None,
+            max_bytes: None,
+            max_vector_bytes: None,
+            max_document_bytes: default_max_document_bytes(),
+            max_body_bytes: default_max_body_bytes(),
+            spool_threshold_bytes: default_spool_threshold_bytes(),
+        }
+    }
+}
+
+impl LimitsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_document_bytes == 0 || self.max_body_bytes == 0 {
+            return Err("LIMITS max_document_bytes and max_body_bytes must be >= 1".into());
+        }
+        Ok(())
+    }
 }
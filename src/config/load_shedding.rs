@@ -0,0 +1,68 @@
+// Load shedding configuration
+// Requests are split into two priority classes. Interactive traffic (searches, single writes) and
+// batch traffic (bulk imports, compaction, rebuilds, anything tagged as batch) get their own
+// concurrency limit and wait queue, so a bulk job cannot take every slot. While the interactive
+// class is saturated, new batch requests are shed outright so the capacity goes to interactive work.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassLimits {
+    // Requests of this class executing at once
+    pub max_concurrent: usize,
+    // Requests allowed to wait for a slot; beyond this they are rejected with 503
+    pub max_queue: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadSheddingConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_interactive")]
+    pub interactive: ClassLimits,
+
+    #[serde(default = "default_batch")]
+    pub batch: ClassLimits,
+
+    // How long a queued request waits for a slot before it is shed
+    #[serde(default = "default_queue_timeout_ms")]
+    pub queue_timeout_ms: u64,
+
+    // API keys (x-api-key header) whose requests always run as batch
+    #[serde(default)]
+    pub batch_api_keys: Vec<String>,
+}
+
+fn default_interactive() -> ClassLimits {
+    ClassLimits { max_concurrent: 256, max_queue: 1024 }
+}
+
+fn default_batch() -> ClassLimits {
+    ClassLimits { max_concurrent: 4, max_queue: 16 }
+}
+
+fn default_queue_timeout_ms() -> u64 {
+    2000
+}
+
+impl Default for LoadSheddingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interactive: default_interactive(),
+            batch: default_batch(),
+            queue_timeout_ms: default_queue_timeout_ms(),
+            batch_api_keys: Vec::new(),
+        }
+    }
+}
+
+impl LoadSheddingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && (self.interactive.max_concurrent == 0 || self.batch.max_concurrent == 0) {
+            return Err("LOAD_SHEDDING max_concurrent must be >= 1 for both classes".into());
+        }
+        Ok(())
+    }
+}
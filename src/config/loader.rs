@@ -14,15 +14,12 @@ pub struct RuntimeConfig {
     pub disk_min_free_bytes: Option<u64>,
     pub disk_readonly_on_low_space: bool,
     pub cache_max_bytes: Option<u64>,
+    pub cluster: Option<crate::config::ClusterConfig>,
 }
 
 /// Load configuration from (optional) file, then apply environment overrides.
 pub fn load_app_config() -> AppConfig {
-    let mut cfg = if let Some(file_cfg) = load_from_file() {
-        file_cfg
-    } else {
-        AppConfig::default()
-    };
+    let mut cfg = load_from_file().unwrap_or_default();
 
     // Apply environment overrides
     cfg.apply_env_overrides();
@@ -58,6 +55,18 @@ pub fn load_runtime_config() -> RuntimeConfig {
     let embedding_timeout = env::var("EMBEDDING_TIMEOUT_SECS")
         .ok()
         .and_then(|v| v.parse::<u64>().ok());
+    let embedding_max_concurrency = env::var("EMBEDDING_MAX_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok());
+    let embedding_max_batch_size = env::var("EMBEDDING_MAX_BATCH_SIZE")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok());
+    let embedding_pool_max_idle = env::var("EMBEDDING_POOL_MAX_IDLE")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok());
+    let embedding_pool_idle_timeout = env::var("EMBEDDING_POOL_IDLE_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok());
 
     let disk_min_free_bytes = env::var("DISK_MIN_FREE_BYTES")
         .ok()
@@ -86,10 +95,16 @@ pub fn load_runtime_config() -> RuntimeConfig {
             api_key: embedding_api_key,
             base_url: embedding_base_url,
             options: serde_json::json!({}),
-             timeout: embedding_timeout,
+            timeout: embedding_timeout,
+            max_concurrency: embedding_max_concurrency,
+            max_batch_size: embedding_max_batch_size,
+            pool_max_idle_per_host: embedding_pool_max_idle,
+            pool_idle_timeout_secs: embedding_pool_idle_timeout,
         }
     });
 
+    let cluster = load_cluster_config();
+
     RuntimeConfig {
         app,
         port,
@@ -99,7 +114,36 @@ pub fn load_runtime_config() -> RuntimeConfig {
         disk_min_free_bytes,
         disk_readonly_on_low_space,
         cache_max_bytes,
+        cluster,
+    }
+}
+
+/// Router mode is enabled by listing backend nodes in CLUSTER_NODES (comma-separated base URLs).
+pub fn load_cluster_config() -> Option<crate::config::ClusterConfig> {
+    let nodes: Vec<String> = env::var("CLUSTER_NODES")
+        .ok()?
+        .split(',')
+        .map(|n| n.trim().trim_end_matches('/').to_string())
+        .filter(|n| !n.is_empty())
+        .collect();
+    if nodes.is_empty() {
+        return None;
+    }
+
+    let mut cluster = crate::config::ClusterConfig::new(nodes);
+    if let Ok(val) = env::var("CLUSTER_SHARD_BY") {
+        cluster.shard_by = match val.to_lowercase().as_str() {
+            "document" | "documents" | "id" => crate::config::ShardBy::Document,
+            _ => crate::config::ShardBy::Collection,
+        };
+    }
+    if let Some(n) = env::var("CLUSTER_VIRTUAL_NODES").ok().and_then(|v| v.parse::<usize>().ok()) {
+        cluster.virtual_nodes = n.max(1);
+    }
+    if let Some(secs) = env::var("CLUSTER_TIMEOUT_SECS").ok().and_then(|v| v.parse::<u64>().ok()) {
+        cluster.request_timeout_secs = secs;
     }
+    Some(cluster)
 }
 
 fn load_from_file() -> Option<AppConfig> {
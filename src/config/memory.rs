@@ -2,6 +2,32 @@
 
 use serde::{Deserialize, Serialize};
 
+// Reads checked in one of every CHECKSUM_SAMPLE_INTERVAL with ChecksumVerification::Sampled
+pub const CHECKSUM_SAMPLE_INTERVAL: u64 = 64;
+
+// Which document reads are checked against the CRC32 kept in the entry's pointer. A mismatch reads
+// as a missing document (and is logged and counted) instead of decoding damaged bytes. Verify and
+// repair always check.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumVerification {
+    #[default]
+    Always,
+    Sampled, // one read in CHECKSUM_SAMPLE_INTERVAL
+    Never,
+}
+
+impl ChecksumVerification {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "always" => Some(ChecksumVerification::Always),
+            "sampled" => Some(ChecksumVerification::Sampled),
+            "never" => Some(ChecksumVerification::Never),
+            _ => None,
+        }
+    }
+}
+
 // Memory limit configuration
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct MemoryConfig {
@@ -13,6 +39,36 @@ pub struct MemoryConfig {
     
     // Enable memory-mapped files
     pub use_mmap: bool,
+
+    // Keep a fixed-stride vector column file next to the document log, so flat/IVF scans and
+    // IVF training read contiguous vectors instead of the per-document cache entries
+    #[serde(default)]
+    pub vector_column: bool,
+
+    // Read documents back with explicit reads (io_uring with the `io-uring` build feature on Linux)
+    // instead of through the mmap, so a search over a collection larger than RAM does not block on
+    // page faults; the candidates of a search are read in one batch before they are scored
+    #[serde(default)]
+    pub io_uring: bool,
+
+    // Most bytes the vector index of each collection keeps in memory (None = unlimited). Past it,
+    // IVF inverted lists of the least probed clusters are moved to a file next to the collection
+    // and read from there when a search probes them
+    #[serde(default)]
+    pub index_memory_budget: Option<usize>,
+
+    // Which document reads verify the entry's checksum
+    #[serde(default)]
+    pub checksums: ChecksumVerification,
+
+    // Before reading a search's candidates through the mmap, hint their pages to the kernel in file
+    // order (madvise WILLNEED), so cold documents are read in together rather than faulted one by one
+    #[serde(default = "default_true")]
+    pub prefetch: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Default for MemoryConfig {
@@ -21,6 +77,11 @@ impl Default for MemoryConfig {
             max_memory_per_collection: None,  // Unlimited
             initial_mmap_size: 1024 * 1024,   // 1MB
             use_mmap: true,
+            vector_column: false,
+            io_uring: false,
+            index_memory_budget: None,
+            checksums: ChecksumVerification::Always,
+            prefetch: true,
         }
     }
 }
@@ -32,6 +93,11 @@ impl MemoryConfig {
             max_memory_per_collection: Some(limit_mb * 1024 * 1024),
             initial_mmap_size: 1024 * 1024,
             use_mmap: true,
+            vector_column: false,
+            io_uring: false,
+            index_memory_budget: None,
+            checksums: ChecksumVerification::Always,
+            prefetch: true,
         }
     }
     
@@ -41,6 +107,11 @@ impl MemoryConfig {
             max_memory_per_collection: None,
             initial_mmap_size: size_mb * 1024 * 1024,
             use_mmap: true,
+            vector_column: false,
+            io_uring: false,
+            index_memory_budget: None,
+            checksums: ChecksumVerification::Always,
+            prefetch: true,
         }
     }
     
@@ -50,6 +121,11 @@ impl MemoryConfig {
             max_memory_per_collection: None,
             initial_mmap_size: 0,
             use_mmap: false,
+            vector_column: false,
+            io_uring: false,
+            index_memory_budget: None,
+            checksums: ChecksumVerification::Always,
+            prefetch: true,
         }
     }
 }
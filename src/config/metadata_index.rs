@@ -0,0 +1,36 @@
+// Secondary metadata index configuration
+// Each listed field gets per-value posting lists (roaring bitmaps over dense document ids), so
+// filters on those fields are answered with set operations instead of a check per document.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetadataIndexConfig {
+    // Metadata fields to index; empty means no index
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+impl MetadataIndexConfig {
+    // Index the given metadata fields
+    pub fn with_fields<I, S>(fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            fields: fields.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.fields.is_empty()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.fields.iter().any(|f| f.trim().is_empty()) {
+            return Err("METADATA_INDEX_FIELDS must not contain empty field names".into());
+        }
+        Ok(())
+    }
+}
@@ -13,6 +13,25 @@ mod wal;
 mod collection;
 mod search_mode;
 mod app;
+mod cluster;
+mod transform;
+mod two_stage;
+mod load_shedding;
+mod vector_validation;
+mod preload;
+mod ingest;
+mod compression;
+mod query_cache;
+mod metadata_index;
+mod usage;
+mod slow_queries;
+mod fault_injection;
+mod scheduler;
+mod query_log;
+mod rerank;
+mod recall_monitor;
+mod enrichment;
+mod data_dir_lock;
 pub use crate::embeddings::EmbeddingConfig;
 pub mod loader;
 
@@ -20,11 +39,30 @@ pub use execution::ExecutionMode;
 pub use storage::StorageConfig;
 pub use search::SearchConfig;
 pub use quantization::{QuantizationConfig, QuantizationLevel};
-pub use parallelism::{ParallelismConfig, ParallelismMode};
-pub use memory::MemoryConfig;
+pub use parallelism::{ParallelismConfig, ParallelismMode, CoreRange};
+pub use memory::{ChecksumVerification, MemoryConfig, CHECKSUM_SAMPLE_INTERVAL};
 pub use cache::CacheConfig;
 pub use limits::LimitsConfig;
-pub use wal::WalConfig;
+pub use wal::{WalConfig, WalCompression, WalKey, WalBackpressure, WalRetry};
 pub use collection::CollectionConfig;
 pub use search_mode::{SearchMode, RangeSearchParams};
 pub use app::AppConfig;
+pub use cluster::{ClusterConfig, ShardBy};
+pub use transform::TransformConfig;
+pub use two_stage::TwoStageConfig;
+pub use load_shedding::{LoadSheddingConfig, ClassLimits};
+pub use vector_validation::{VectorValidationConfig, NonFinitePolicy};
+pub use preload::PreloadPolicy;
+pub use ingest::{IngestSourceConfig, IngestSourceKind, IngestStart};
+pub use compression::CompressionConfig;
+pub use query_cache::QueryCacheConfig;
+pub use metadata_index::MetadataIndexConfig;
+pub use usage::{UsageBudget, UsageConfig};
+pub use slow_queries::SlowQueryConfig;
+pub use fault_injection::{FaultInjectionConfig, FaultRule};
+pub use scheduler::{SchedulerConfig, OffPeakWindow};
+pub use query_log::QueryLogConfig;
+pub use rerank::RerankConfig;
+pub use recall_monitor::RecallMonitorConfig;
+pub use enrichment::{EnrichmentConfig, EnrichmentStageConfig, LengthUnit};
+pub use data_dir_lock::{DataDirConflict, DataDirLockConfig};
@@ -14,6 +14,36 @@ pub enum ParallelismMode {
     Fixed(usize),
 }
 
+// Inclusive range of CPU cores a thread pool is pinned to, written "2-5" (or "3" for one core)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CoreRange {
+    pub first: usize,
+    pub last: usize,
+}
+
+impl CoreRange {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (first, last) = value.split_once('-').unwrap_or((value, value));
+        let (first, last) = (first.trim().parse().ok()?, last.trim().parse().ok()?);
+        (first <= last).then_some(CoreRange { first, last })
+    }
+}
+
+impl TryFrom<String> for CoreRange {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        CoreRange::parse(&value).ok_or_else(|| format!("invalid core range '{}' (expected e.g. \"0-3\")", value))
+    }
+}
+
+impl From<CoreRange> for String {
+    fn from(range: CoreRange) -> Self {
+        format!("{}-{}", range.first, range.last)
+    }
+}
+
 // Parallelism configuration
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct ParallelismConfig {
@@ -22,6 +52,17 @@ pub struct ParallelismConfig {
     
     // Enable parallel search (when applicable)
     pub parallel_search: bool,
+
+    // Searches and background work (index builds, projection training, statistics) run on separate
+    // pools, built once per process; see `crate::parallel`
+    #[serde(default)]
+    pub query_threads: Option<usize>, // query pool size (default: num_threads)
+    #[serde(default)]
+    pub maintenance_threads: Option<usize>, // maintenance pool size (default: a quarter of num_threads, at least 1)
+    #[serde(default)]
+    pub query_cores: Option<CoreRange>, // pin query threads to these cores (Linux only)
+    #[serde(default)]
+    pub maintenance_cores: Option<CoreRange>, // pin maintenance threads to these cores (Linux only)
 }
 
 impl Default for ParallelismConfig {
@@ -29,6 +70,10 @@ impl Default for ParallelismConfig {
         ParallelismConfig {
             mode: ParallelismMode::Auto,
             parallel_search: true,
+            query_threads: None,
+            maintenance_threads: None,
+            query_cores: None,
+            maintenance_cores: None,
         }
     }
 }
@@ -39,6 +84,7 @@ impl ParallelismConfig {
         ParallelismConfig {
             mode: ParallelismMode::SingleThreaded,
             parallel_search: false,
+            ..Default::default()
         }
     }
     
@@ -47,6 +93,7 @@ impl ParallelismConfig {
         ParallelismConfig {
             mode: ParallelismMode::Fixed(num_threads),
             parallel_search: true,
+            ..Default::default()
         }
     }
     
@@ -59,6 +106,29 @@ impl ParallelismConfig {
         }
     }
 
+    pub fn query_threads(&self) -> usize {
+        self.query_threads.unwrap_or_else(|| self.num_threads()).max(1)
+    }
+
+    pub fn maintenance_threads(&self) -> usize {
+        self.maintenance_threads.unwrap_or_else(|| self.num_threads() / 4).max(1)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.query_threads == Some(0) || self.maintenance_threads == Some(0) {
+            return Err("PARALLELISM query_threads and maintenance_threads must be >= 1".into());
+        }
+        let cores = num_cpus::get();
+        for (name, range) in [("query_cores", self.query_cores), ("maintenance_cores", self.maintenance_cores)] {
+            if let Some(range) = range.filter(|r| r.last >= cores) {
+                return Err(format!(
+                    "PARALLELISM {} {}-{} is beyond the {} available cores", name, range.first, range.last, cores
+                ));
+            }
+        }
+        Ok(())
+    }
+
     pub fn with_num_threads(mut self, n: usize) -> Self {
         self.mode = ParallelismMode::Fixed(n);
         self.parallel_search = n > 1;
@@ -0,0 +1,38 @@
+// Collection preload policy
+// At startup the server lists data_dir and registers every collection it finds from its metadata
+// file alone, without opening the data file or loading the index. The policy then decides when the
+// collection itself is opened:
+// - eager: during startup, before the server accepts requests (predictable first-request latency)
+// - lazy: on the first request that touches it (startup stays fast)
+// - never: not opened by requests at all; it is listed but requests to it get 503 until the policy
+//   changes (e.g. archived collections kept on disk)
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreloadPolicy {
+    Eager,
+    #[default]
+    Lazy,
+    Never,
+}
+
+impl PreloadPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "eager" => Some(Self::Eager),
+            "lazy" => Some(Self::Lazy),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Eager => "eager",
+            Self::Lazy => "lazy",
+            Self::Never => "never",
+        }
+    }
+}
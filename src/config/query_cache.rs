@@ -0,0 +1,68 @@
+// Query-result cache configuration
+// Repeated searches (dashboards re-running the same queries) are answered from memory. Query
+// vectors are rounded to a grid of `quantization_step` before they are compared, so vectors that
+// differ only by float noise share an entry; 0 compares them exactly. Entries are dropped once the
+// collection's WAL sequence is more than `max_seq_lag` past the one they were computed at, after
+// `ttl_secs` (0 = only on writes), and least recently used first beyond `max_entries`. Read at startup.
+//
+// With `max_seq_lag` above 0 a read-heavy workload trades freshness for hits: an entry a few writes
+// behind is still served, flagged stale, and the first search to find it behind recomputes it in the
+// background (stale-while-revalidate).
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QueryCacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    // Cached result lists across all collections
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+
+    #[serde(default = "default_quantization_step")]
+    pub quantization_step: f32,
+
+    // WAL entries an entry may be behind the collection and still be served; 0 = only current ones
+    #[serde(default)]
+    pub max_seq_lag: u64,
+}
+
+fn default_max_entries() -> usize {
+    10_000
+}
+
+fn default_ttl_secs() -> u64 {
+    60
+}
+
+fn default_quantization_step() -> f32 {
+    1e-4
+}
+
+impl Default for QueryCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_entries: default_max_entries(),
+            ttl_secs: default_ttl_secs(),
+            quantization_step: default_quantization_step(),
+            max_seq_lag: 0,
+        }
+    }
+}
+
+impl QueryCacheConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.max_entries == 0 {
+            return Err("QUERY_CACHE max_entries must be >= 1 when the cache is enabled".into());
+        }
+        if !self.quantization_step.is_finite() || self.quantization_step < 0.0 {
+            return Err("QUERY_CACHE quantization_step must be a finite number >= 0".into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+// Query log configuration
+// With `enabled`, every search (vector, batch, range, text) is appended to
+// data_dir/query_log/<collection>.jsonl with what it asked for and which documents it returned, and
+// clients can send click feedback for it; see `crate::server::query_log`. `store_queries` keeps the
+// query vectors and texts; without it only their hash is kept. A collection's log is rotated once
+// it reaches `max_file_bytes`, keeping one previous file. Read at startup.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QueryLogConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_store_queries")]
+    pub store_queries: bool,
+
+    #[serde(default = "default_max_file_bytes")]
+    pub max_file_bytes: u64,
+}
+
+fn default_store_queries() -> bool {
+    true
+}
+
+fn default_max_file_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+impl Default for QueryLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            store_queries: default_store_queries(),
+            max_file_bytes: default_max_file_bytes(),
+        }
+    }
+}
+
+impl QueryLogConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.max_file_bytes < 4096 {
+            return Err("QUERY_LOG max_file_bytes must be >= 4096 when the query log is enabled".into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+// Live recall monitoring configuration
+// With `enabled`, every `interval_secs` each loaded collection with an approximate index (HNSW, IVF)
+// searches `queries` stored vectors it sampled once as queries and compares the top `k` with their
+// exact top `k`, recomputed in the background when the collection changed since. The recall is
+// reported in /api/metrics and Prometheus; one below `alert_below` is logged as a warning and flagged
+// as degraded. See `crate::server::recall_monitor`. Read at startup.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RecallMonitorConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_queries")]
+    pub queries: usize,
+
+    #[serde(default = "default_k")]
+    pub k: usize,
+
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+
+    #[serde(default = "default_alert_below")]
+    pub alert_below: f32,
+}
+
+fn default_queries() -> usize {
+    32
+}
+
+fn default_k() -> usize {
+    10
+}
+
+fn default_interval_secs() -> u64 {
+    300
+}
+
+fn default_alert_below() -> f32 {
+    0.9
+}
+
+impl Default for RecallMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            queries: default_queries(),
+            k: default_k(),
+            interval_secs: default_interval_secs(),
+            alert_below: default_alert_below(),
+        }
+    }
+}
+
+impl RecallMonitorConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.queries == 0 || self.k == 0 {
+            return Err("RECALL_MONITOR queries and k must be >= 1".into());
+        }
+        if self.interval_secs == 0 {
+            return Err("RECALL_MONITOR interval_secs must be >= 1".into());
+        }
+        if !(0.0..=1.0).contains(&self.alert_below) {
+            return Err("RECALL_MONITOR alert_below must be in [0, 1]".into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+// Feedback re-ranking configuration
+// Searches on the listed collections are re-ranked with the relevance feedback recorded for them
+// (POST /api/collections/{name}/feedback); see `crate::server::feedback`. `prior_strength` is how
+// many neutral signals a document's feedback prior starts from, so a handful of clicks moves it
+// little; `learning_rate` is the step the blend's weights take per signal sent with a score.
+// Applied on reload.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RerankConfig {
+    #[serde(default)]
+    pub collections: Vec<String>,
+
+    #[serde(default = "default_prior_strength")]
+    pub prior_strength: f32,
+
+    #[serde(default = "default_learning_rate")]
+    pub learning_rate: f32,
+}
+
+fn default_prior_strength() -> f32 {
+    4.0
+}
+
+fn default_learning_rate() -> f32 {
+    0.05
+}
+
+impl Default for RerankConfig {
+    fn default() -> Self {
+        Self {
+            collections: Vec::new(),
+            prior_strength: default_prior_strength(),
+            learning_rate: default_learning_rate(),
+        }
+    }
+}
+
+impl RerankConfig {
+    pub fn enabled_for(&self, collection: &str) -> bool {
+        self.collections.iter().any(|c| c == collection)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.prior_strength.is_finite() && self.prior_strength > 0.0) {
+            return Err("RERANK prior_strength must be a positive number".into());
+        }
+        if !(self.learning_rate.is_finite() && self.learning_rate > 0.0 && self.learning_rate <= 1.0) {
+            return Err("RERANK learning_rate must be in (0, 1]".into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+// Background scheduler configuration; see `crate::scheduler`.
+// `max_concurrent` caps the normal- and low-priority tasks running at once, and `max_low_priority`
+// the low-priority ones among them. With an `off_peak` window, low-priority work (queued index
+// rebuilds and compactions) only starts between its hours, UTC. Checkpoints and index recovery are
+// never held back. Applied again on a config reload.
+
+use serde::{Deserialize, Serialize};
+
+// Hours of the day, UTC, written "1-5": from the start of hour 1 to the start of hour 5. A window
+// whose start is after its end runs over midnight ("22-4").
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct OffPeakWindow {
+    pub start_hour: u8,
+    pub end_hour: u8,
+}
+
+impl OffPeakWindow {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.split_once('-')?;
+        let (start_hour, end_hour) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+        (start_hour < 24 && end_hour <= 24 && start_hour != end_hour).then_some(OffPeakWindow { start_hour, end_hour })
+    }
+
+    pub fn contains(&self, hour: u8) -> bool {
+        if self.start_hour < self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+impl TryFrom<String> for OffPeakWindow {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        OffPeakWindow::parse(&value).ok_or_else(|| format!("invalid off-peak window '{}' (expected e.g. \"1-5\")", value))
+    }
+}
+
+impl From<OffPeakWindow> for String {
+    fn from(window: OffPeakWindow) -> Self {
+        format!("{}-{}", window.start_hour, window.end_hour)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SchedulerConfig {
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+
+    #[serde(default = "default_max_low_priority")]
+    pub max_low_priority: usize,
+
+    #[serde(default)]
+    pub off_peak: Option<OffPeakWindow>, // low-priority work waits for these hours; none means any time
+}
+
+fn default_max_concurrent() -> usize {
+    2
+}
+
+fn default_max_low_priority() -> usize {
+    1
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: default_max_concurrent(),
+            max_low_priority: default_max_low_priority(),
+            off_peak: None,
+        }
+    }
+}
+
+impl SchedulerConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_concurrent == 0 || self.max_low_priority == 0 {
+            return Err("SCHEDULER max_concurrent and max_low_priority must be >= 1".into());
+        }
+        Ok(())
+    }
+}
@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 // - HNSW: uses ef (candidates explored during search)
 // - IVF: uses nprobe (number of clusters to search)
 // - Flat: always exhaustive (ignores these settings)
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SearchConfig {
     // HNSW: Number of candidates to explore (higher = better recall, slower)
     // Default: uses ef_search from config, or ef_construction if not set
@@ -21,6 +21,14 @@ pub struct SearchConfig {
     // How many extra candidates to pull when a filter is present (multiplier of k)
     #[serde(default = "default_filter_overfetch")]
     pub filter_overfetch: usize,
+
+    // Raise the overfetch per query from observed filter selectivity (filter_overfetch stays the floor)
+    #[serde(default = "default_adaptive_overfetch")]
+    pub adaptive_overfetch: bool,
+
+    // Upper bound for the auto-tuned overfetch multiplier
+    #[serde(default = "default_max_filter_overfetch")]
+    pub max_filter_overfetch: usize,
 }
 
 impl Default for SearchConfig {
@@ -29,6 +37,8 @@ impl Default for SearchConfig {
             ef: None,      // Use index config default
             nprobe: None,  // Use index config default
             filter_overfetch: default_filter_overfetch(),
+            adaptive_overfetch: default_adaptive_overfetch(),
+            max_filter_overfetch: default_max_filter_overfetch(),
         }
     }
 }
@@ -40,6 +50,8 @@ impl SearchConfig {
             ef: Some(400),
             nprobe: Some(20),
             filter_overfetch: default_filter_overfetch(),
+            adaptive_overfetch: default_adaptive_overfetch(),
+            max_filter_overfetch: default_max_filter_overfetch(),
         }
     }
     
@@ -54,8 +66,12 @@ impl SearchConfig {
             ef: Some(50),
             nprobe: Some(1),
             filter_overfetch: default_filter_overfetch(),
+            adaptive_overfetch: default_adaptive_overfetch(),
+            max_filter_overfetch: default_max_filter_overfetch(),
         }
     }
 }
 
 fn default_filter_overfetch() -> usize { 10 }
+fn default_adaptive_overfetch() -> bool { true }
+fn default_max_filter_overfetch() -> usize { 100 }
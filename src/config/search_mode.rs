@@ -3,20 +3,15 @@
 use serde::{Deserialize, Serialize};
 
 // Search mode
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum SearchMode {
     // K-nearest neighbors (return top k results)
+    #[default]
     KNN,
     // Range search (return all within distance threshold)
     Range,
 }
 
-impl Default for SearchMode {
-    fn default() -> Self {
-        SearchMode::KNN
-    }
-}
-
 // Range search parameters
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct RangeSearchParams {
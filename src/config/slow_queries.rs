@@ -0,0 +1,47 @@
+// Slow-query capture configuration
+// Searches slower than SLOW_QUERY_MS are logged with a warning either way; with `enabled` they are
+// also kept in a ring buffer of the last `capacity` ones, served by GET /api/slow_queries, so the
+// exact offending queries can be replayed and profiled. `store_queries` keeps the query vectors
+// (and the text of text searches) with each entry; without it only their hash is kept. Read at
+// startup.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SlowQueryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+
+    #[serde(default = "default_store_queries")]
+    pub store_queries: bool,
+}
+
+fn default_capacity() -> usize {
+    100
+}
+
+fn default_store_queries() -> bool {
+    true
+}
+
+impl Default for SlowQueryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capacity: default_capacity(),
+            store_queries: default_store_queries(),
+        }
+    }
+}
+
+impl SlowQueryConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.capacity == 0 {
+            return Err("SLOW_QUERIES capacity must be >= 1 when capture is enabled".into());
+        }
+        Ok(())
+    }
+}
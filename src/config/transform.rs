@@ -0,0 +1,97 @@
+// Vector transformation applied before vectors reach the index.
+// Matryoshka-style embedding models pack most of the signal into the leading dimensions, so the
+// index (and the in-memory vector cache it reads from) can work on a prefix of each vector, e.g.
+// 256 of 768 dims. The full vector stays on disk and is used to re-rank the candidates.
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TransformConfig {
+    // Keep only the first N dimensions for indexing and candidate search (None = use full vectors)
+    #[serde(default)]
+    pub truncate_dims: Option<usize>,
+    // Re-normalize truncated vectors to unit length (matryoshka prefixes are not unit length)
+    #[serde(default = "default_true")]
+    pub normalize: bool,
+    // Re-score candidates on the full stored vectors before returning them
+    #[serde(default = "default_true")]
+    pub rerank: bool,
+    // How many candidates per requested result to fetch from the index when re-ranking
+    #[serde(default = "default_rerank_overfetch")]
+    pub rerank_overfetch: usize,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_rerank_overfetch() -> usize {
+    4
+}
+
+impl Default for TransformConfig {
+    fn default() -> Self {
+        Self {
+            truncate_dims: None,
+            normalize: true,
+            rerank: true,
+            rerank_overfetch: default_rerank_overfetch(),
+        }
+    }
+}
+
+impl TransformConfig {
+    // Truncate to the first `dims` dimensions, re-normalize and re-rank on full vectors
+    pub fn truncate(dims: usize) -> Self {
+        Self {
+            truncate_dims: Some(dims),
+            ..Default::default()
+        }
+    }
+
+    pub fn without_rerank(mut self) -> Self {
+        self.rerank = false;
+        self
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.truncate_dims.is_some()
+    }
+
+    // Candidates to pull from the index for a top-k search
+    pub fn candidates(&self, k: usize) -> usize {
+        if self.is_active() && self.rerank {
+            k.saturating_mul(self.rerank_overfetch.max(1))
+        } else {
+            k
+        }
+    }
+
+    // Vector as seen by the index. Borrowed when no transformation applies.
+    pub fn apply<'a>(&self, vector: &'a [f32]) -> Cow<'a, [f32]> {
+        let dims = match self.truncate_dims {
+            Some(dims) if dims < vector.len() => dims,
+            _ => return Cow::Borrowed(vector),
+        };
+        let mut out = vector[..dims].to_vec();
+        if self.normalize {
+            let norm = out.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                out.iter_mut().for_each(|x| *x /= norm);
+            }
+        }
+        Cow::Owned(out)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.truncate_dims == Some(0) {
+            return Err("TRANSFORM truncate_dims must be > 0".into());
+        }
+        if self.rerank_overfetch == 0 {
+            return Err("TRANSFORM rerank_overfetch must be >= 1".into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+// Two-stage retrieval configuration
+// Stage 1 scans the compact quantized codes (int8 or PQ) held in memory and keeps the top
+// `candidates`; stage 2 re-scores those candidates with the full-precision vectors kept in a
+// memory-mapped sidecar file. High recall without holding float32 vectors for the whole scan.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TwoStageConfig {
+    // Keep full-precision vectors on disk and search with the two-stage pipeline
+    #[serde(default)]
+    pub enabled: bool,
+
+    // Candidates (C) kept by the quantized scan for exact re-ranking; never fewer than k
+    #[serde(default = "default_candidates")]
+    pub candidates: usize,
+}
+
+fn default_candidates() -> usize {
+    100
+}
+
+impl Default for TwoStageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            candidates: default_candidates(),
+        }
+    }
+}
+
+impl TwoStageConfig {
+    // Enable two-stage search keeping `candidates` for the exact re-rank
+    pub fn with_candidates(candidates: usize) -> Self {
+        Self {
+            enabled: true,
+            candidates,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.candidates == 0 {
+            return Err("TWO_STAGE candidates must be >= 1".into());
+        }
+        Ok(())
+    }
+}
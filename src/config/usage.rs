@@ -0,0 +1,64 @@
+// Embedding usage accounting configuration
+// Every call to an embedding provider is counted (requests, texts, tokens) per provider, model,
+// collection and API key. `budgets` cap what the calls matching a scope may use per calendar month
+// (UTC); once a budget is spent, further embed calls in its scope are refused until the month
+// ends. Hourly buckets older than `retention_days` are dropped. Re-read on every call, so a config
+// reload applies new budgets.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageBudget {
+    #[serde(default)]
+    pub name: Option<String>, // shown in the error and in /api/usage
+    // Scope: the calls matching every field that is set
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub collection: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>, // the x-api-key value itself; usage only keeps its fingerprint
+    // Monthly limits; at least one is required
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+    #[serde(default)]
+    pub max_requests: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageConfig {
+    #[serde(default)]
+    pub budgets: Vec<UsageBudget>,
+
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u64,
+}
+
+fn default_retention_days() -> u64 {
+    90
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            budgets: Vec::new(),
+            retention_days: default_retention_days(),
+        }
+    }
+}
+
+impl UsageConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.retention_days == 0 {
+            return Err("USAGE retention_days must be >= 1".into());
+        }
+        for (i, budget) in self.budgets.iter().enumerate() {
+            if budget.max_tokens.is_none() && budget.max_requests.is_none() {
+                return Err(format!("USAGE budget {i} needs max_tokens and/or max_requests"));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+// Vector validation configuration
+// The distance kernels assume finite inputs: a single NaN makes every score it touches NaN, and
+// NaN compares as neither greater nor smaller, so the result ordering turns into garbage. These
+// checks run at the collection boundary (insert, upsert, update and search), so they also cover
+// library users that never go through the HTTP validation.
+
+use serde::{Deserialize, Serialize};
+
+// What to do with NaN / +-Infinity components
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NonFinitePolicy {
+    // Fail the operation with StorageError::NonFiniteValue
+    #[default]
+    Reject,
+    // Replace every non-finite component with 0.0 and carry on
+    Sanitize,
+    // No check (behaviour before validation existed)
+    Allow,
+}
+
+impl NonFinitePolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "reject" => Some(Self::Reject),
+            "sanitize" => Some(Self::Sanitize),
+            "allow" => Some(Self::Allow),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct VectorValidationConfig {
+    #[serde(default)]
+    pub non_finite: NonFinitePolicy,
+
+    // Reject all-zero vectors when the collection's metric is cosine (their similarity is undefined)
+    #[serde(default)]
+    pub reject_zero_cosine: bool,
+}
+
+impl VectorValidationConfig {
+    // No checks at all
+    pub fn permissive() -> Self {
+        Self {
+            non_finite: NonFinitePolicy::Allow,
+            reject_zero_cosine: false,
+        }
+    }
+
+    // Reject non-finite components and zero vectors under cosine
+    pub fn strict() -> Self {
+        Self {
+            non_finite: NonFinitePolicy::Reject,
+            reject_zero_cosine: true,
+        }
+    }
+}
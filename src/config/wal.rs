@@ -1,6 +1,139 @@
 // Write-Ahead Log (WAL) configuration
 // This configuration struct defines the parameters for the write-ahead log, which is used to ensure durability and recoverability of the collection in case of crashes or unexpected shutdowns. The WAL allows us to log changes to the collection before they are applied, so that we can replay those changes during recovery to bring the collection back to a consistent state.
-use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+// Compression applied to each WAL entry before it is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WalCompression {
+    #[default]
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl WalCompression {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" | "" => Some(WalCompression::None),
+            "lz4" => Some(WalCompression::Lz4),
+            "zstd" => Some(WalCompression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+// 256-bit key for WAL encryption, written as 64 hex characters. It is never serialized back out
+// and its Debug output is redacted.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct WalKey(pub [u8; 32]);
+
+impl WalKey {
+    pub fn from_hex(hex: &str) -> Result<Self, String> {
+        let hex = hex.trim();
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err("WAL encryption key must be 64 hex characters".into());
+        }
+        let mut key = [0u8; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+                .map_err(|_| "WAL encryption key must be 64 hex characters".to_string())?;
+        }
+        Ok(WalKey(key))
+    }
+}
+
+impl fmt::Debug for WalKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WalKey(..)")
+    }
+}
+
+impl<'de> Deserialize<'de> for WalKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        WalKey::from_hex(&hex).map_err(serde::de::Error::custom)
+    }
+}
+
+// Write backpressure from the WAL backlog: the bytes and entries logged since the last checkpoint
+// that is on disk. Past a throttle threshold a checkpoint is started if none is running and each
+// write waits, longer the closer the backlog gets to the reject threshold. Past a reject threshold
+// writes fail with 429 and a Retry-After until a checkpoint catches up. Unset thresholds never apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalBackpressure {
+    #[serde(default)]
+    pub throttle_bytes: Option<u64>,
+    #[serde(default)]
+    pub throttle_ops: Option<u64>,
+    #[serde(default)]
+    pub reject_bytes: Option<u64>,
+    #[serde(default)]
+    pub reject_ops: Option<u64>,
+    // Longest wait of a throttled write, reached at the reject threshold
+    #[serde(default = "default_max_delay_ms")]
+    pub max_delay_ms: u64,
+}
+
+fn default_max_delay_ms() -> u64 {
+    1000
+}
+
+impl Default for WalBackpressure {
+    fn default() -> Self {
+        WalBackpressure {
+            throttle_bytes: None,
+            throttle_ops: None,
+            reject_bytes: None,
+            reject_ops: None,
+            max_delay_ms: default_max_delay_ms(),
+        }
+    }
+}
+
+impl WalBackpressure {
+    pub fn enabled(&self) -> bool {
+        self.throttle_bytes.or(self.throttle_ops).or(self.reject_bytes).or(self.reject_ops).is_some()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (throttle, reject, unit) in [(self.throttle_bytes, self.reject_bytes, "bytes"), (self.throttle_ops, self.reject_ops, "ops")] {
+            if let (Some(throttle), Some(reject)) = (throttle, reject) {
+                if throttle >= reject {
+                    return Err(format!("WAL backpressure throttle_{unit} must be below reject_{unit}"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Retries of a failed WAL append. The failed write is cut back out of the file before each retry,
+// `backoff_ms` longer every time. A full disk (ENOSPC, or a quota) is not retried: the write fails
+// with STORAGE_FULL at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalRetry {
+    #[serde(default = "default_retry_attempts")]
+    pub attempts: u32,
+    #[serde(default = "default_retry_backoff_ms")]
+    pub backoff_ms: u64,
+}
+
+fn default_retry_attempts() -> u32 {
+    2
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    10
+}
+
+impl Default for WalRetry {
+    fn default() -> Self {
+        WalRetry { attempts: default_retry_attempts(), backoff_ms: default_retry_backoff_ms() }
+    }
+}
 
 // WAL configuration
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -20,6 +153,30 @@ pub struct WalConfig {
     
     // Sync to disk after every write (slower but safer)
     pub sync_on_write: bool,
+
+    // Keep checkpointed WAL segments for this many seconds so the collection can be read "as of"
+    // an earlier sequence number or timestamp (None = truncate the WAL at every checkpoint)
+    #[serde(default)]
+    pub history_retention_secs: Option<u64>,
+
+    // Copy every WAL file a checkpoint releases to `{collection}.wal.archive/` before it is dropped,
+    // for replicas and point-in-time restores that need the whole log
+    #[serde(default)]
+    pub archive_segments: bool,
+
+    // Compress each entry (lz4 or zstd); entries that would not shrink are written as they are
+    #[serde(default)]
+    pub compression: WalCompression,
+
+    // Encrypt each entry with ChaCha20-Poly1305 under this key (None = plaintext)
+    #[serde(default, skip_serializing)]
+    pub encryption_key: Option<WalKey>,
+    // Delay or reject writes while checkpoints fall behind (see WalBackpressure)
+    #[serde(default)]
+    pub backpressure: WalBackpressure,
+    // Retries of failed appends (see WalRetry)
+    #[serde(default)]
+    pub retry: WalRetry,
 }
 
 impl Default for WalConfig {
@@ -30,6 +187,12 @@ impl Default for WalConfig {
             checkpoint_interval_secs: None,
             max_log_size: 100 * 1024 * 1024,  // 100MB
             sync_on_write: false,
+            history_retention_secs: None,
+            archive_segments: false,
+            compression: WalCompression::None,
+            encryption_key: None,
+            backpressure: WalBackpressure::default(),
+            retry: WalRetry::default(),
         }
     }
 }
@@ -43,6 +206,12 @@ impl WalConfig {
             max_log_size: 0,
             sync_on_write: false,
             checkpoint_interval_secs: None,
+            history_retention_secs: None,
+            archive_segments: false,
+            compression: WalCompression::None,
+            encryption_key: None,
+            backpressure: WalBackpressure::default(),
+            retry: WalRetry::default(),
         }
     }
     
@@ -54,6 +223,12 @@ impl WalConfig {
             max_log_size: 50 * 1024 * 1024,  // 50MB
             sync_on_write: true,
             checkpoint_interval_secs: Some(1),
+            history_retention_secs: None,
+            archive_segments: false,
+            compression: WalCompression::None,
+            encryption_key: None,
+            backpressure: WalBackpressure::default(),
+            retry: WalRetry::default(),
         }
     }
     
@@ -65,6 +240,12 @@ impl WalConfig {
             max_log_size: 500 * 1024 * 1024,  // 500MB
             sync_on_write: false,
             checkpoint_interval_secs: None,
+            history_retention_secs: None,
+            archive_segments: false,
+            compression: WalCompression::None,
+            encryption_key: None,
+            backpressure: WalBackpressure::default(),
+            retry: WalRetry::default(),
         }
     }
 }
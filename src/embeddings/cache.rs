@@ -8,7 +8,7 @@ use lru::LruCache;
 use std::num::NonZeroUsize;
 use std::sync::Mutex;
 
-use super::types::{Embedder, EmbeddingResponse, EmbeddingResult};
+use super::types::{Embedder, EmbeddingError, EmbeddingResponse, EmbeddingResult};
 
 pub struct CachedEmbedder<E: Embedder> {
     inner: E,
@@ -68,6 +68,47 @@ impl<E: Embedder> Embedder for CachedEmbedder<E> {
         Ok(response)
     }
 
+    async fn embed_batch(&self, texts: &[String]) -> EmbeddingResult<Vec<EmbeddingResponse>> {
+        // Serve hits from the cache and send only the misses to the provider, in one batch
+        let mut responses: Vec<Option<EmbeddingResponse>> = Vec::with_capacity(texts.len());
+        let mut misses = Vec::new();
+        {
+            let mut cache = self.cache.lock().unwrap();
+            for (idx, text) in texts.iter().enumerate() {
+                match cache.get(text) {
+                    Some(embedding) => responses.push(Some(EmbeddingResponse {
+                        embedding: embedding.clone(),
+                        tokens: None,
+                        model: self.inner.model_name().to_string(),
+                    })),
+                    None => {
+                        responses.push(None);
+                        misses.push(idx);
+                    }
+                }
+            }
+        }
+
+        if !misses.is_empty() {
+            let miss_texts: Vec<String> = misses.iter().map(|&idx| texts[idx].clone()).collect();
+            let fetched = self.inner.embed_batch(&miss_texts).await?;
+            let mut cache = self.cache.lock().unwrap();
+            for (idx, response) in misses.into_iter().zip(fetched) {
+                cache.put(texts[idx].clone(), response.embedding.clone());
+                responses[idx] = Some(response);
+            }
+        }
+
+        responses
+            .into_iter()
+            .map(|r| r.ok_or_else(|| EmbeddingError::InvalidResponse("Missing embedding in batch response".to_string())))
+            .collect()
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.inner.max_batch_size()
+    }
+
     fn provider_name(&self) -> &str {
         self.inner.provider_name()
     }
@@ -0,0 +1,148 @@
+// Concurrency limit and batch chunking for embedding providers
+// Every caller (insert handlers, the embed endpoint, text search) shares one embedder, so without
+// a limit a few large batch inserts can open hundreds of simultaneous provider requests and run
+// straight into rate limits. LimitedEmbedder puts a semaphore in front of the provider: each
+// provider request holds one permit for its whole duration. Batches are split into chunks of at
+// most the provider's batch size, and the chunks run concurrently up to the same limit.
+// `embed_batch` fails on the first chunk that fails; `embed_batch_partial` waits for every chunk
+// and fails only the texts of the chunks that did.
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use super::types::{Embedder, EmbeddingConfig, EmbeddingError, EmbeddingResponse, EmbeddingResult};
+
+// Provider requests in flight at once when the config does not say otherwise
+pub const DEFAULT_MAX_CONCURRENCY: usize = 8;
+
+pub struct LimitedEmbedder {
+    inner: Arc<dyn Embedder>,
+    permits: Arc<Semaphore>,
+    max_concurrency: usize,
+    chunk_size: usize,
+}
+
+impl LimitedEmbedder {
+    pub fn new(inner: Arc<dyn Embedder>, max_concurrency: usize, max_batch_size: Option<usize>) -> Self {
+        let max_concurrency = max_concurrency.max(1);
+        // A configured batch size can only shrink the provider's own maximum
+        let provider_max = inner.max_batch_size().max(1);
+        let chunk_size = max_batch_size.unwrap_or(provider_max).clamp(1, provider_max);
+        Self {
+            inner,
+            permits: Arc::new(Semaphore::new(max_concurrency)),
+            max_concurrency,
+            chunk_size,
+        }
+    }
+
+    pub fn from_config(inner: Arc<dyn Embedder>, config: &EmbeddingConfig) -> Self {
+        Self::new(
+            inner,
+            config.max_concurrency.unwrap_or(DEFAULT_MAX_CONCURRENCY),
+            config.max_batch_size,
+        )
+    }
+
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    // Provider requests currently holding a permit
+    pub fn in_flight(&self) -> usize {
+        self.max_concurrency - self.permits.available_permits()
+    }
+}
+
+#[async_trait]
+impl Embedder for LimitedEmbedder {
+    async fn embed(&self, text: &str) -> EmbeddingResult<EmbeddingResponse> {
+        let _permit = self.permits.acquire().await
+            .map_err(|e| EmbeddingError::RequestFailed(e.to_string()))?;
+        self.inner.embed(text).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> EmbeddingResult<Vec<EmbeddingResponse>> {
+        if texts.len() <= self.chunk_size {
+            let _permit = self.permits.acquire().await
+                .map_err(|e| EmbeddingError::RequestFailed(e.to_string()))?;
+            return self.inner.embed_batch(texts).await;
+        }
+
+        // Spawn one task per chunk; the semaphore (not the number of tasks) bounds how many reach
+        // the provider at once. Results are put back in chunk order.
+        let mut tasks = JoinSet::new();
+        for (chunk_idx, chunk) in texts.chunks(self.chunk_size).enumerate() {
+            let inner = Arc::clone(&self.inner);
+            let permits = Arc::clone(&self.permits);
+            let chunk = chunk.to_vec();
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await
+                    .map_err(|e| EmbeddingError::RequestFailed(e.to_string()))?;
+                inner.embed_batch(&chunk).await.map(|responses| (chunk_idx, responses))
+            });
+        }
+
+        let mut chunks: Vec<Option<Vec<EmbeddingResponse>>> = vec![None; texts.len().div_ceil(self.chunk_size)];
+        while let Some(joined) = tasks.join_next().await {
+            // Dropping the JoinSet on an early return aborts the chunks still waiting
+            let (chunk_idx, responses) = joined
+                .map_err(|e| EmbeddingError::RequestFailed(e.to_string()))??;
+            chunks[chunk_idx] = Some(responses);
+        }
+        Ok(chunks.into_iter().flatten().flatten().collect())
+    }
+
+    async fn embed_batch_partial(&self, texts: &[String]) -> Vec<EmbeddingResult<EmbeddingResponse>> {
+        let mut tasks = JoinSet::new();
+        for (chunk_idx, chunk) in texts.chunks(self.chunk_size).enumerate() {
+            let inner = Arc::clone(&self.inner);
+            let permits = Arc::clone(&self.permits);
+            let chunk = chunk.to_vec();
+            tasks.spawn(async move {
+                let outcomes = match permits.acquire_owned().await {
+                    Ok(_permit) => inner.embed_batch_partial(&chunk).await,
+                    Err(e) => chunk.iter().map(|_| Err(EmbeddingError::RequestFailed(e.to_string()))).collect(),
+                };
+                (chunk_idx, outcomes)
+            });
+        }
+
+        let mut chunks: Vec<Option<Vec<EmbeddingResult<EmbeddingResponse>>>> = vec![None; texts.len().div_ceil(self.chunk_size)];
+        while let Some(joined) = tasks.join_next().await {
+            // A chunk whose task panicked has no index to report; its texts are failed below
+            if let Ok((chunk_idx, outcomes)) = joined {
+                chunks[chunk_idx] = Some(outcomes);
+            }
+        }
+        chunks
+            .into_iter()
+            .zip(texts.chunks(self.chunk_size))
+            .flat_map(|(outcomes, chunk)| {
+                outcomes.unwrap_or_else(|| chunk.iter().map(|_| Err(EmbeddingError::RequestFailed("embedding task failed".into()))).collect())
+            })
+            .collect()
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        self.inner.dimensions()
+    }
+}
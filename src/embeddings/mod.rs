@@ -6,10 +6,15 @@ mod types;
 pub mod providers;
 pub mod cache;
 pub mod retry;
+pub mod limit;
 
 pub use types::{Embedder, EmbeddingConfig, EmbeddingResponse, EmbeddingResult};
 pub use providers::{EmbeddingProvider, create_embedder};
 pub use cache::{CachedEmbedder, CacheStats};
 pub use retry::RetryEmbedder;
+pub use limit::{LimitedEmbedder, DEFAULT_MAX_CONCURRENCY};
 pub use crate::error::embedding::EmbeddingError;
 
+// Metadata key recording which model produced a vector embedded by the server
+pub const EMBEDDING_MODEL_KEY: &str = "embedding_model";
+
@@ -6,6 +6,7 @@ use crate::embeddings::types::{Embedder, EmbeddingConfig, EmbeddingError, Embedd
 use super::openai::OpenAIEmbedder;
 use super::ollama::OllamaEmbedder;
 use super::local::LocalEmbedder;
+use crate::embeddings::limit::LimitedEmbedder;
 
 // Enum of supported embedding providers
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -17,6 +18,7 @@ pub enum EmbeddingProvider {
 
 impl EmbeddingProvider {
     // Parse provider from string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "openai" => Some(Self::OpenAI),
@@ -43,18 +45,12 @@ pub fn create_embedder(config: &EmbeddingConfig) -> EmbeddingResult<Arc<dyn Embe
         EmbeddingError::ConfigError(format!("Unknown provider: {}", config.provider))
     })?;
 
-    match provider {
-        EmbeddingProvider::OpenAI => {
-            let embedder = OpenAIEmbedder::new(config)?;
-            Ok(Arc::new(embedder))
-        }
-        EmbeddingProvider::Ollama => {
-            let embedder = OllamaEmbedder::new(config)?;
-            Ok(Arc::new(embedder))
-        }
-        EmbeddingProvider::Local => {
-            let embedder = LocalEmbedder::new(config)?;
-            Ok(Arc::new(embedder))
-        }
-    }
+    let embedder: Arc<dyn Embedder> = match provider {
+        EmbeddingProvider::OpenAI => Arc::new(OpenAIEmbedder::new(config)?),
+        EmbeddingProvider::Ollama => Arc::new(OllamaEmbedder::new(config)?),
+        EmbeddingProvider::Local => Arc::new(LocalEmbedder::new(config)?),
+    };
+
+    // All callers share this embedder, so the concurrency limit applies provider-wide
+    Ok(Arc::new(LimitedEmbedder::from_config(embedder, config)))
 }
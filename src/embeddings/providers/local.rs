@@ -4,7 +4,6 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
-use std::time::Duration;
 
 use crate::embeddings::types::{Embedder, EmbeddingConfig, EmbeddingError, EmbeddingResponse, EmbeddingResult};
 use crate::embeddings::cache::CachedEmbedder;
@@ -37,14 +36,7 @@ impl LocalEmbedderInner {
             .clone()
             .ok_or_else(|| EmbeddingError::ConfigError("LOCAL provider requires base_url".into()))?;
 
-        let client = if let Some(timeout_secs) = config.timeout {
-            Client::builder()
-                .timeout(Duration::from_secs(timeout_secs))
-                .build()
-                .map_err(|e| EmbeddingError::RequestFailed(e.to_string()))?
-        } else {
-            Client::new()
-        };
+        let client = super::build_client(config)?;
 
         Ok(Self {
             client,
@@ -153,6 +145,14 @@ impl Embedder for LocalEmbedder {
         self.cached.embed(text).await
     }
 
+    async fn embed_batch(&self, texts: &[String]) -> EmbeddingResult<Vec<EmbeddingResponse>> {
+        self.cached.embed_batch(texts).await
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.cached.max_batch_size()
+    }
+
     fn provider_name(&self) -> &str {
         self.cached.provider_name()
     }
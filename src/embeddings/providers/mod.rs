@@ -7,3 +7,26 @@ pub use factory::{EmbeddingProvider, create_embedder};
 pub use openai::OpenAIEmbedder;
 pub use ollama::OllamaEmbedder;
 pub use local::LocalEmbedder;
+
+use reqwest::Client;
+use std::time::Duration;
+
+use crate::embeddings::types::{EmbeddingConfig, EmbeddingError, EmbeddingResult};
+
+// HTTP client shared by every request of one provider. reqwest keeps a connection pool per client,
+// so building it once per embedder (not per request) is what lets batch embeds reuse connections.
+pub(crate) fn build_client(config: &EmbeddingConfig) -> EmbeddingResult<Client> {
+    let mut builder = Client::builder();
+    if let Some(timeout_secs) = config.timeout {
+        builder = builder.timeout(Duration::from_secs(timeout_secs));
+    }
+    if let Some(max_idle) = config.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(idle_secs) = config.pool_idle_timeout_secs {
+        builder = builder.pool_idle_timeout(Duration::from_secs(idle_secs));
+    }
+    builder
+        .build()
+        .map_err(|e| EmbeddingError::RequestFailed(e.to_string()))
+}
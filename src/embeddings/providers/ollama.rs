@@ -6,7 +6,6 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
-use std::time::Duration;
 
 use crate::embeddings::types::{Embedder, EmbeddingConfig, EmbeddingError, EmbeddingResponse, EmbeddingResult};
 use crate::embeddings::cache::CachedEmbedder;
@@ -50,14 +49,7 @@ impl OllamaEmbedderInner {
             .clone()
             .unwrap_or_else(|| DEFAULT_OLLAMA_URL.to_string());
 
-        let client = if let Some(timeout_secs) = config.timeout {
-            reqwest::Client::builder()
-                .timeout(Duration::from_secs(timeout_secs))
-                .build()
-                .map_err(|e| EmbeddingError::RequestFailed(e.to_string()))?
-        } else {
-            Client::new()
-        };
+        let client = super::build_client(config)?;
 
         Ok(Self {
             client,
@@ -143,6 +135,14 @@ impl Embedder for OllamaEmbedder {
         self.cached.embed(text).await
     }
 
+    async fn embed_batch(&self, texts: &[String]) -> EmbeddingResult<Vec<EmbeddingResponse>> {
+        self.cached.embed_batch(texts).await
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.cached.max_batch_size()
+    }
+
     fn provider_name(&self) -> &str {
         self.cached.provider_name()
     }
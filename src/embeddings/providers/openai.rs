@@ -6,13 +6,14 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
-use std::time::Duration;
 
 use crate::embeddings::types::{Embedder, EmbeddingConfig, EmbeddingError, EmbeddingResponse, EmbeddingResult};
 use crate::embeddings::cache::CachedEmbedder;
 
 const DEFAULT_OPENAI_API_URL: &str = "https://api.openai.com/v1/embeddings";
 const DEFAULT_CACHE_SIZE: usize = 10000;
+// The embeddings endpoint accepts at most 2048 inputs per request
+const OPENAI_MAX_BATCH_SIZE: usize = 2048;
 
 // OpenAI embedding provider (with built-in LRU cache)
 struct OpenAIEmbedderInner {
@@ -61,14 +62,7 @@ impl OpenAIEmbedderInner {
             .clone()
             .unwrap_or_else(|| DEFAULT_OPENAI_API_URL.to_string());
 
-        let client = if let Some(timeout_secs) = config.timeout {
-            reqwest::Client::builder()
-                .timeout(Duration::from_secs(timeout_secs))
-                .build()
-                .map_err(|e| EmbeddingError::RequestFailed(e.to_string()))?
-        } else {
-            Client::new()
-        };
+        let client = super::build_client(config)?;
 
         Ok(Self {
             client,
@@ -78,23 +72,10 @@ impl OpenAIEmbedderInner {
         })
     }
 
-    // Get dimensions for known OpenAI models
-    fn get_dimensions(&self) -> Option<usize> {
-        match self.model.as_str() {
-            "text-embedding-3-small" => Some(1536),
-            "text-embedding-3-large" => Some(3072),
-            "text-embedding-ada-002" => Some(1536),
-            _ => None,
-        }
-    }
-}
-
-#[async_trait]
-impl Embedder for OpenAIEmbedderInner {
-    async fn embed(&self, text: &str) -> EmbeddingResult<EmbeddingResponse> {
+    async fn request(&self, input: OpenAIInput) -> EmbeddingResult<OpenAIEmbeddingResponse> {
         let request = OpenAIEmbeddingRequest {
             model: self.model.clone(),
-            input: text.to_string(),
+            input,
             encoding_format: Some("float".to_string()),
         };
 
@@ -122,10 +103,27 @@ impl Embedder for OpenAIEmbedderInner {
             });
         }
 
-        let api_response: OpenAIEmbeddingResponse = response
+        response
             .json()
             .await
-            .map_err(|e| EmbeddingError::InvalidResponse(e.to_string()))?;
+            .map_err(|e| EmbeddingError::InvalidResponse(e.to_string()))
+    }
+
+    // Get dimensions for known OpenAI models
+    fn get_dimensions(&self) -> Option<usize> {
+        match self.model.as_str() {
+            "text-embedding-3-small" => Some(1536),
+            "text-embedding-3-large" => Some(3072),
+            "text-embedding-ada-002" => Some(1536),
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for OpenAIEmbedderInner {
+    async fn embed(&self, text: &str) -> EmbeddingResult<EmbeddingResponse> {
+        let api_response = self.request(OpenAIInput::Single(text.to_string())).await?;
 
         let first_embedding = api_response
             .data
@@ -139,6 +137,36 @@ impl Embedder for OpenAIEmbedderInner {
         })
     }
 
+    async fn embed_batch(&self, texts: &[String]) -> EmbeddingResult<Vec<EmbeddingResponse>> {
+        let mut api_response = self.request(OpenAIInput::Batch(texts.to_vec())).await?;
+        if api_response.data.len() != texts.len() {
+            return Err(EmbeddingError::InvalidResponse(format!(
+                "Expected {} embeddings, got {}",
+                texts.len(),
+                api_response.data.len()
+            )));
+        }
+
+        // The API reports usage for the whole request: it is attributed to the first response so
+        // that summing tokens over the batch still gives the right total
+        api_response.data.sort_by_key(|d| d.index);
+        let total_tokens = api_response.usage.total_tokens;
+        Ok(api_response
+            .data
+            .into_iter()
+            .enumerate()
+            .map(|(i, d)| EmbeddingResponse {
+                embedding: d.embedding,
+                tokens: Some(if i == 0 { total_tokens } else { 0 }),
+                model: api_response.model.clone(),
+            })
+            .collect())
+    }
+
+    fn max_batch_size(&self) -> usize {
+        OPENAI_MAX_BATCH_SIZE
+    }
+
     fn provider_name(&self) -> &str {
         "openai"
     }
@@ -159,6 +187,14 @@ impl Embedder for OpenAIEmbedder {
         self.cached.embed(text).await
     }
 
+    async fn embed_batch(&self, texts: &[String]) -> EmbeddingResult<Vec<EmbeddingResponse>> {
+        self.cached.embed_batch(texts).await
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.cached.max_batch_size()
+    }
+
     fn provider_name(&self) -> &str {
         self.cached.provider_name()
     }
@@ -177,11 +213,18 @@ impl Embedder for OpenAIEmbedder {
 #[derive(Debug, Serialize)]
 struct OpenAIEmbeddingRequest {
     model: String,
-    input: String,
+    input: OpenAIInput,
     #[serde(skip_serializing_if = "Option::is_none")]
     encoding_format: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum OpenAIInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
 #[derive(Debug, Deserialize)]
 struct OpenAIEmbeddingResponse {
     data: Vec<EmbeddingData>,
@@ -189,7 +232,6 @@ struct OpenAIEmbeddingResponse {
     usage: Usage,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct EmbeddingData {
     embedding: Vec<f32>,
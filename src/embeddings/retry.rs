@@ -47,19 +47,19 @@ impl RetryEmbedder {
             max_delay_ms: options.max_delay_ms,
         }
     }
-}
 
-// Implement the Embedder trait for RetryEmbedder. The embed method will attempt to call the inner embedder's embed method, and if it fails with a retryable error, it will wait for a certain amount of time (starting with initial_delay_ms and doubling each time) before retrying, up to max_retries times. If all attempts fail, it will return the last error.
-#[async_trait]
-impl Embedder for RetryEmbedder {
-    async fn embed(&self, text: &str) -> EmbeddingResult<EmbeddingResponse> {
+    async fn with_retries<T, F, Fut>(&self, mut attempt: F) -> EmbeddingResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = EmbeddingResult<T>>,
+    {
         // We will keep track of the number of attempts and the current delay. We will loop until we either get a successful response or exhaust our retries. On each failure, we check if the error is retryable. If it is not retryable, we return the error immediately. If it is retryable, we log the failure and wait for the specified delay before trying again. The delay increases exponentially with each attempt, up to a maximum limit.
         let mut attempts = 0;
         let mut delay_ms = self.initial_delay_ms;
         
         // Loop to attempt embedding with retries
         loop {
-            match self.inner.embed(text).await {
+            match attempt().await {
                 Ok(result) => return Ok(result),
                 Err(e) => {
                     attempts += 1;
@@ -82,6 +82,52 @@ impl Embedder for RetryEmbedder {
             }
         }
     }
+}
+
+// Implement the Embedder trait for RetryEmbedder. The embed method will attempt to call the inner embedder's embed method, and if it fails with a retryable error, it will wait for a certain amount of time (starting with initial_delay_ms and doubling each time) before retrying, up to max_retries times. If all attempts fail, it will return the last error.
+#[async_trait]
+impl Embedder for RetryEmbedder {
+    async fn embed(&self, text: &str) -> EmbeddingResult<EmbeddingResponse> {
+        self.with_retries(|| self.inner.embed(text)).await
+    }
+
+    // A failed batch is retried as a whole; the provider-side cache makes the texts that already
+    // succeeded in an earlier chunk free on the next attempt
+    async fn embed_batch(&self, texts: &[String]) -> EmbeddingResult<Vec<EmbeddingResponse>> {
+        self.with_retries(|| self.inner.embed_batch(texts)).await
+    }
+
+    // Only the texts that failed with a retryable error are sent again
+    async fn embed_batch_partial(&self, texts: &[String]) -> Vec<EmbeddingResult<EmbeddingResponse>> {
+        let mut outcomes = self.inner.embed_batch_partial(texts).await;
+        let mut delay_ms = self.initial_delay_ms;
+        for attempt in 1..=self.max_retries {
+            let failed: Vec<usize> = (0..outcomes.len())
+                .filter(|&i| outcomes[i].as_ref().is_err_and(is_retryable_error))
+                .collect();
+            if failed.is_empty() {
+                break;
+            }
+            tracing::warn!(
+                attempt,
+                max_retries = self.max_retries,
+                delay_ms,
+                failed = failed.len(),
+                "embedding_batch_retrying"
+            );
+            sleep(Duration::from_millis(delay_ms)).await;
+            delay_ms = (delay_ms * 2).min(self.max_delay_ms);
+            let retry: Vec<String> = failed.iter().map(|&i| texts[i].clone()).collect();
+            for (i, outcome) in failed.into_iter().zip(self.inner.embed_batch_partial(&retry).await) {
+                outcomes[i] = outcome;
+            }
+        }
+        outcomes
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.inner.max_batch_size()
+    }
 
     fn provider_name(&self) -> &str {
         self.inner.provider_name()
@@ -29,6 +29,22 @@ pub struct EmbeddingConfig {
     // timeout for embedding requests (in seconds)
     #[serde(default)]
     pub timeout: Option<u64>,
+
+    // Maximum number of provider requests in flight at once (shared by every caller of this
+    // embedder). None uses DEFAULT_MAX_CONCURRENCY.
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+
+    // Texts per provider request when embedding a batch. Clamped to what the provider accepts;
+    // None uses the provider maximum.
+    #[serde(default)]
+    pub max_batch_size: Option<usize>,
+
+    // HTTP connection pool: idle connections kept per host, and how long they may stay idle
+    #[serde(default)]
+    pub pool_max_idle_per_host: Option<usize>,
+    #[serde(default)]
+    pub pool_idle_timeout_secs: Option<u64>,
 }
 
 impl Default for EmbeddingConfig {
@@ -40,6 +56,10 @@ impl Default for EmbeddingConfig {
             base_url: None,
             options: serde_json::json!({}),
             timeout: None,
+            max_concurrency: None,
+            max_batch_size: None,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout_secs: None,
         }
     }
 }
@@ -63,7 +83,31 @@ pub trait Embedder: Send + Sync {
     // Generate an embedding for a single text
     async fn embed(&self, text: &str) -> EmbeddingResult<EmbeddingResponse>;
 
-    // Generate embeddings for multiple texts in a batch
+    // Generate embeddings for multiple texts, in input order. The default embeds them one by one;
+    // providers with a batch endpoint override it and raise max_batch_size.
+    async fn embed_batch(&self, texts: &[String]) -> EmbeddingResult<Vec<EmbeddingResponse>> {
+        let mut responses = Vec::with_capacity(texts.len());
+        for text in texts {
+            responses.push(self.embed(text).await?);
+        }
+        Ok(responses)
+    }
+
+    // Embed a batch with one outcome per text, in input order, so a failure is reported for the
+    // texts it hit instead of failing the batch. The default sends the batch as one request;
+    // LimitedEmbedder splits it into chunks, and a failed chunk then fails only its own texts.
+    async fn embed_batch_partial(&self, texts: &[String]) -> Vec<EmbeddingResult<EmbeddingResponse>> {
+        match self.embed_batch(texts).await {
+            Ok(responses) => responses.into_iter().map(Ok).collect(),
+            Err(e) => texts.iter().map(|_| Err(e.clone())).collect(),
+        }
+    }
+
+    // Largest number of texts the provider accepts in one embed_batch request
+    fn max_batch_size(&self) -> usize {
+        1
+    }
+
     // Get the provider name
     fn provider_name(&self) -> &str;
 
@@ -0,0 +1,116 @@
+use serde::Serialize;
+
+// Stable, machine-readable error codes sent as `error_code` next to the message, so clients can
+// branch on what went wrong without matching message text. Codes are only ever added, never renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    InvalidRequest,
+    ValidationFailed,
+    InvalidVector,
+    DimensionMismatch,
+    NotFound,
+    CollectionNotFound,
+    VectorNotFound,
+    AlreadyExists,
+    Conflict,
+    CollectionSealed,
+    CollectionLocked,
+    DataDirLocked,
+    AuthenticationFailed,
+    AuthorizationFailed,
+    RateLimited,
+    BudgetExceeded,
+    WriteThrottled,
+    WritesPaused,
+    PayloadTooLarge,
+    Timeout,
+    ServiceUnavailable,
+    StorageFull,
+    StorageIo,
+    LockFailed,
+    WalIo,
+    DataCorrupt,
+    IndexCorrupt,
+    IndexError,
+    EmbeddingRateLimited,
+    EmbeddingTimeout,
+    EmbeddingUnavailable,
+    EmbeddingFailed,
+    EmbeddingConfig,
+    IoError,
+    SerializationError,
+    Internal,
+}
+
+impl ErrorCode {
+    // Whether sending the same request again, after a backoff, can succeed. Everything else needs
+    // the request, the configuration or the server's disk fixed first.
+    pub fn retryable(self) -> bool {
+        matches!(
+            self,
+            Self::RateLimited
+                | Self::WriteThrottled
+                | Self::WritesPaused
+                | Self::Timeout
+                | Self::ServiceUnavailable
+                | Self::DataDirLocked
+                | Self::LockFailed
+                | Self::EmbeddingRateLimited
+                | Self::EmbeddingTimeout
+                | Self::EmbeddingUnavailable
+        )
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidRequest => "INVALID_REQUEST",
+            Self::ValidationFailed => "VALIDATION_FAILED",
+            Self::InvalidVector => "INVALID_VECTOR",
+            Self::DimensionMismatch => "DIMENSION_MISMATCH",
+            Self::NotFound => "NOT_FOUND",
+            Self::CollectionNotFound => "COLLECTION_NOT_FOUND",
+            Self::VectorNotFound => "VECTOR_NOT_FOUND",
+            Self::AlreadyExists => "ALREADY_EXISTS",
+            Self::Conflict => "CONFLICT",
+            Self::CollectionSealed => "COLLECTION_SEALED",
+            Self::CollectionLocked => "COLLECTION_LOCKED",
+            Self::DataDirLocked => "DATA_DIR_LOCKED",
+            Self::AuthenticationFailed => "AUTHENTICATION_FAILED",
+            Self::AuthorizationFailed => "AUTHORIZATION_FAILED",
+            Self::RateLimited => "RATE_LIMITED",
+            Self::BudgetExceeded => "BUDGET_EXCEEDED",
+            Self::WriteThrottled => "WRITE_THROTTLED",
+            Self::WritesPaused => "WRITES_PAUSED",
+            Self::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
+            Self::Timeout => "TIMEOUT",
+            Self::ServiceUnavailable => "SERVICE_UNAVAILABLE",
+            Self::StorageFull => "STORAGE_FULL",
+            Self::StorageIo => "STORAGE_IO",
+            Self::LockFailed => "LOCK_FAILED",
+            Self::WalIo => "WAL_IO",
+            Self::DataCorrupt => "DATA_CORRUPT",
+            Self::IndexCorrupt => "INDEX_CORRUPT",
+            Self::IndexError => "INDEX_ERROR",
+            Self::EmbeddingRateLimited => "EMBEDDING_RATE_LIMITED",
+            Self::EmbeddingTimeout => "EMBEDDING_TIMEOUT",
+            Self::EmbeddingUnavailable => "EMBEDDING_UNAVAILABLE",
+            Self::EmbeddingFailed => "EMBEDDING_FAILED",
+            Self::EmbeddingConfig => "EMBEDDING_CONFIG",
+            Self::IoError => "IO_ERROR",
+            Self::SerializationError => "SERIALIZATION_ERROR",
+            Self::Internal => "INTERNAL",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
@@ -1,7 +1,9 @@
 use thiserror::Error;
 
+use super::ErrorCode;
+
 // Define the error type for embedding operations. This enum represents various kinds of errors that can occur when working with embedding providers, such as HTTP request failures, API errors, invalid responses, configuration issues, rate limits, authentication failures, provider unavailability, timeouts, and invalid models. Each variant includes a message that provides more details about the error. The is_recoverable method allows us to determine if an error is something that we can retry or if it is a fatal error that should not be retried.
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum EmbeddingError {
     #[error("HTTP request failed: {0}")]
     RequestFailed(String),
@@ -45,4 +47,14 @@ impl EmbeddingError {
             Self::InvalidModel(_) => false,
         }
     }
+
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::RateLimitExceeded => ErrorCode::EmbeddingRateLimited,
+            Self::Timeout(_) => ErrorCode::EmbeddingTimeout,
+            Self::RequestFailed(_) | Self::ProviderUnavailable(_) => ErrorCode::EmbeddingUnavailable,
+            Self::ApiError(_) | Self::InvalidResponse(_) => ErrorCode::EmbeddingFailed,
+            Self::ConfigError(_) | Self::InvalidModel(_) | Self::AuthenticationFailed(_) => ErrorCode::EmbeddingConfig,
+        }
+    }
 }
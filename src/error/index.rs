@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use super::ErrorCode;
+
 #[derive(Error, Debug)]
 pub enum IndexError {
     #[error("Index not initialized")]
@@ -44,4 +46,11 @@ impl IndexError {
             Self::LoadFailed(_) => false,
         }
     }
+
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::Corrupted(_) => ErrorCode::IndexCorrupt,
+            _ => ErrorCode::IndexError,
+        }
+    }
 }
@@ -4,9 +4,11 @@ pub mod index;
 pub mod server;
 pub mod embedding;
 pub mod context;
+pub mod code;
 
 pub use types::{PiramidError, Result};
 pub use context::ErrorContext;
+pub use code::ErrorCode;
 pub use server::ServerError;
 pub use storage::StorageError;
 pub use index::IndexError;
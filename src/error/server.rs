@@ -4,6 +4,8 @@ use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde_json::json;
 
+use super::ErrorCode;
+
 #[derive(Error, Debug)]
 pub enum ServerError {
     #[error("Invalid request: {0}")]
@@ -12,12 +14,36 @@ pub enum ServerError {
     #[error("Validation failed: {0}")]
     ValidationFailed(String),
 
+    #[error("Invalid request: {0}")]
+    DimensionMismatch(String),
+
     #[error("Resource not found: {0}")]
     NotFound(String),
 
+    #[error("Resource not found: Collection not found")]
+    CollectionNotFound,
+
+    #[error("Resource not found: Vector not found")]
+    VectorNotFound,
+
     #[error("Resource already exists: {0}")]
     AlreadyExists(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    // A write to a collection sealed write-once (see storage/collection/worm.rs)
+    #[error("Collection sealed: {0}")]
+    CollectionSealed(String),
+
+    // A collection whose files another process has open (see storage/collection/lock.rs)
+    #[error("Collection locked: {0}")]
+    CollectionLocked(String),
+
+    // Another server owns the data dir (see server/data_dir_lock.rs)
+    #[error("Data dir locked: {0}")]
+    DataDirLocked(String),
+
     #[error("Authentication failed: {0}")]
     AuthenticationFailed(String),
 
@@ -27,6 +53,21 @@ pub enum ServerError {
     #[error("Rate limit exceeded")]
     RateLimitExceeded,
 
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(String),
+
+    // Sent with a Retry-After header of `retry_after_secs`
+    #[error("Writes throttled: {message}")]
+    WriteThrottled { message: String, retry_after_secs: u64 },
+
+    // A write to a collection whose writes an operator paused (see storage/collection/pause.rs); sent
+    // with a Retry-After header of the seconds left until the pause lapses
+    #[error("Writes paused: {message}")]
+    WritesPaused { message: String, retry_after_secs: u64 },
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
     #[error("Request timeout")]
     Timeout,
 
@@ -42,11 +83,22 @@ impl ServerError {
         match self {
             Self::InvalidRequest(_) => true,
             Self::ValidationFailed(_) => true,
+            Self::DimensionMismatch(_) => true,
             Self::NotFound(_) => true,
+            Self::CollectionNotFound => true,
+            Self::VectorNotFound => true,
             Self::AlreadyExists(_) => true,
+            Self::Conflict(_) => true,
+            Self::CollectionSealed(_) => true,
+            Self::CollectionLocked(_) => true,
+            Self::DataDirLocked(_) => true,
             Self::AuthenticationFailed(_) => true,
             Self::AuthorizationFailed(_) => true,
             Self::RateLimitExceeded => true,
+            Self::BudgetExceeded(_) => true,
+            Self::WriteThrottled { .. } => true,
+            Self::WritesPaused { .. } => true,
+            Self::PayloadTooLarge(_) => true,
             Self::Timeout => true,
             Self::Internal(_) => false,
             Self::ServiceUnavailable(_) => true,
@@ -57,25 +109,79 @@ impl ServerError {
         match self {
             Self::InvalidRequest(_) => StatusCode::BAD_REQUEST,
             Self::ValidationFailed(_) => StatusCode::BAD_REQUEST,
+            Self::DimensionMismatch(_) => StatusCode::BAD_REQUEST,
             Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::CollectionNotFound => StatusCode::NOT_FOUND,
+            Self::VectorNotFound => StatusCode::NOT_FOUND,
             Self::AlreadyExists(_) => StatusCode::CONFLICT,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::CollectionSealed(_) => StatusCode::CONFLICT,
+            Self::CollectionLocked(_) => StatusCode::CONFLICT,
+            Self::DataDirLocked(_) => StatusCode::SERVICE_UNAVAILABLE,
             Self::AuthenticationFailed(_) => StatusCode::UNAUTHORIZED,
             Self::AuthorizationFailed(_) => StatusCode::FORBIDDEN,
             Self::RateLimitExceeded => StatusCode::TOO_MANY_REQUESTS,
+            Self::BudgetExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::WriteThrottled { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::WritesPaused { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
             Self::Timeout => StatusCode::REQUEST_TIMEOUT,
             Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
         }
     }
+
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::InvalidRequest(_) => ErrorCode::InvalidRequest,
+            Self::ValidationFailed(_) => ErrorCode::ValidationFailed,
+            Self::DimensionMismatch(_) => ErrorCode::DimensionMismatch,
+            Self::NotFound(_) => ErrorCode::NotFound,
+            Self::CollectionNotFound => ErrorCode::CollectionNotFound,
+            Self::VectorNotFound => ErrorCode::VectorNotFound,
+            Self::AlreadyExists(_) => ErrorCode::AlreadyExists,
+            Self::Conflict(_) => ErrorCode::Conflict,
+            Self::CollectionSealed(_) => ErrorCode::CollectionSealed,
+            Self::CollectionLocked(_) => ErrorCode::CollectionLocked,
+            Self::DataDirLocked(_) => ErrorCode::DataDirLocked,
+            Self::AuthenticationFailed(_) => ErrorCode::AuthenticationFailed,
+            Self::AuthorizationFailed(_) => ErrorCode::AuthorizationFailed,
+            Self::RateLimitExceeded => ErrorCode::RateLimited,
+            Self::BudgetExceeded(_) => ErrorCode::BudgetExceeded,
+            Self::WriteThrottled { .. } => ErrorCode::WriteThrottled,
+            Self::WritesPaused { .. } => ErrorCode::WritesPaused,
+            Self::PayloadTooLarge(_) => ErrorCode::PayloadTooLarge,
+            Self::Timeout => ErrorCode::Timeout,
+            Self::Internal(_) => ErrorCode::Internal,
+            Self::ServiceUnavailable(_) => ErrorCode::ServiceUnavailable,
+        }
+    }
+}
+
+// Body of every error response: the message, the HTTP status as `code`, and a stable `error_code`
+// with a `retryable` hint for clients deciding whether to send the request again
+pub(crate) fn error_body(status: StatusCode, message: String, code: ErrorCode) -> Response {
+    let body = Json(json!({
+        "error": message,
+        "code": status.as_u16(),
+        "error_code": code,
+        "retryable": code.retryable(),
+    }));
+    let mut res = (status, body).into_response();
+    res.extensions_mut().insert(code);
+    res
 }
 
 impl IntoResponse for ServerError {
     fn into_response(self) -> Response {
-        let status = self.status_code();
-        let body = Json(json!({
-            "error": self.to_string(),
-            "code": status.as_u16(),
-        }));
-        (status, body).into_response()
+        let retry_after = match &self {
+            Self::WriteThrottled { retry_after_secs, .. } | Self::WritesPaused { retry_after_secs, .. } => Some(*retry_after_secs),
+            _ => None,
+        };
+        let mut res = error_body(self.status_code(), self.to_string(), self.error_code());
+        if let Some(secs) = retry_after {
+            res.headers_mut().insert("retry-after", axum::http::HeaderValue::from(secs));
+        }
+        res
     }
 }
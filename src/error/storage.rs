@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use super::ErrorCode;
+
 #[derive(Error, Debug)]
 pub enum StorageError {
     #[error("Vector not found: {0}")]
@@ -17,6 +19,12 @@ pub enum StorageError {
     #[error("Invalid vector data: {0}")]
     InvalidVectorData(String),
 
+    #[error("Vector contains a non-finite value ({value}) at index {index}")]
+    NonFiniteValue { index: usize, value: f32 },
+
+    #[error("Zero vector has no direction and cannot be used with the cosine metric")]
+    ZeroVector,
+
     #[error("Storage corruption detected: {0}")]
     CorruptedData(String),
 
@@ -37,9 +45,17 @@ pub enum StorageError {
 
     #[error("Read operation failed: {0}")]
     ReadFailed(String),
+
+    #[error("WAL write failed: {0}")]
+    WalFailed(String),
 }
 
 impl StorageError {
+    // Errors caused by the caller's input rather than by the storage itself
+    pub fn is_invalid_input(&self) -> bool {
+        matches!(self, Self::NonFiniteValue { .. } | Self::ZeroVector)
+    }
+
     pub fn is_recoverable(&self) -> bool {
         match self {
             Self::VectorNotFound(_) => true,
@@ -47,6 +63,8 @@ impl StorageError {
             Self::CollectionExists(_) => true,
             Self::InvalidDimension { .. } => true,
             Self::InvalidVectorData(_) => true,
+            Self::NonFiniteValue { .. } => true,
+            Self::ZeroVector => true,
             Self::StorageFull(_) => false,
             Self::CorruptedData(_) => false,
             Self::CorruptedIndex(_) => false,
@@ -54,6 +72,23 @@ impl StorageError {
             Self::LockFailed(_) => true,
             Self::WriteFailed(_) => false,
             Self::ReadFailed(_) => true,
+            Self::WalFailed(_) => false,
+        }
+    }
+
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::VectorNotFound(_) => ErrorCode::VectorNotFound,
+            Self::CollectionNotFound(_) => ErrorCode::CollectionNotFound,
+            Self::CollectionExists(_) => ErrorCode::AlreadyExists,
+            Self::InvalidDimension { .. } => ErrorCode::DimensionMismatch,
+            Self::InvalidVectorData(_) | Self::NonFiniteValue { .. } | Self::ZeroVector => ErrorCode::InvalidVector,
+            Self::CorruptedData(_) => ErrorCode::DataCorrupt,
+            Self::StorageFull(_) => ErrorCode::StorageFull,
+            Self::CorruptedIndex(_) => ErrorCode::IndexCorrupt,
+            Self::MemoryMapError(_) | Self::WriteFailed(_) | Self::ReadFailed(_) => ErrorCode::StorageIo,
+            Self::LockFailed(_) => ErrorCode::LockFailed,
+            Self::WalFailed(_) => ErrorCode::WalIo,
         }
     }
 }
@@ -3,6 +3,8 @@ use std::io;
 use axum::response::{IntoResponse, Response};
 use axum::http::StatusCode;
 
+use super::ErrorCode;
+
 pub type Result<T> = std::result::Result<T, PiramidError>;
 
 #[derive(Error, Debug)]
@@ -61,6 +63,9 @@ impl PiramidError {
     pub fn status_code(&self) -> StatusCode {
         match self {
             Self::Server(e) => e.status_code(),
+            Self::Storage(e) if e.is_invalid_input() => StatusCode::BAD_REQUEST,
+            Self::Storage(super::storage::StorageError::StorageFull(_)) => StatusCode::INSUFFICIENT_STORAGE,
+            Self::Io(e) if is_disk_full(e) => StatusCode::INSUFFICIENT_STORAGE,
             Self::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::Index(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::Embedding(_) => StatusCode::BAD_GATEWAY,
@@ -70,20 +75,31 @@ impl PiramidError {
             Self::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
+
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::Server(e) => e.error_code(),
+            Self::Storage(e) => e.error_code(),
+            Self::Index(e) => e.error_code(),
+            Self::Embedding(e) => e.error_code(),
+            Self::Io(e) if is_disk_full(e) => ErrorCode::StorageFull,
+            Self::Io(_) => ErrorCode::IoError,
+            Self::Serialization(_) | Self::Json(_) => ErrorCode::SerializationError,
+            Self::Other(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+// ENOSPC, or a disk quota used up
+fn is_disk_full(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded)
 }
 
 impl IntoResponse for PiramidError {
     fn into_response(self) -> Response {
         match self {
             Self::Server(e) => e.into_response(),
-            _ => {
-                let status = self.status_code();
-                let body = axum::Json(serde_json::json!({
-                    "error": self.to_string(),
-                    "code": status.as_u16(),
-                }));
-                (status, body).into_response()
-            }
+            _ => super::server::error_body(self.status_code(), self.to_string(), self.error_code()),
         }
     }
 }
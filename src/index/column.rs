@@ -0,0 +1,54 @@
+// Column view - contiguous, fixed-stride access to a collection's vectors
+// The vector cache is a HashMap of separately allocated Vecs, so a scan over it chases one pointer
+// per vector. The column file lays the same vectors out as rows of `dims` floats back to back;
+// indexes that scan (flat, IVF lists, k-means training) read them through this view instead.
+
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Clone, Copy)]
+pub struct ColumnView<'a> {
+    dims: usize,
+    data: &'a [f32],                  // slots.len() * dims floats
+    ids: &'a [Uuid],                  // slot -> id; Uuid::nil() marks a free slot
+    slots: &'a HashMap<Uuid, usize>,  // id -> slot
+}
+
+impl<'a> ColumnView<'a> {
+    pub fn new(dims: usize, data: &'a [f32], ids: &'a [Uuid], slots: &'a HashMap<Uuid, usize>) -> Self {
+        debug_assert_eq!(data.len(), ids.len() * dims);
+        Self { dims, data, ids, slots }
+    }
+
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    // Number of live vectors
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn contains(&self, id: &Uuid) -> bool {
+        self.slots.contains_key(id)
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<&'a [f32]> {
+        let slot = *self.slots.get(id)?;
+        self.data.get(slot * self.dims..(slot + 1) * self.dims)
+    }
+
+    // Live rows in slot order, i.e. in file order
+    pub fn rows(&self) -> impl Iterator<Item = (Uuid, &'a [f32])> + 'a {
+        let ids = self.ids;
+        self.data
+            .chunks_exact(self.dims.max(1))
+            .zip(ids.iter())
+            .filter(|(_, id)| !id.is_nil())
+            .map(|(row, id)| (*id, row))
+    }
+}
@@ -0,0 +1,248 @@
+// Batched brute force for ExecutionMode::Gpu
+// A large batch of queries against a flat index is scored as one matrix product instead of query by
+// query. The index keeps its vectors "uploaded": int8 rows (min/max per row, as in scalar
+// quantization) in one contiguous buffer, rebuilt on the first batch after the index changed. The
+// registered device gets that buffer and the batch and returns each query's candidates. With the
+// `gpu` feature the first Gpu batch probes for a wgpu adapter (gpu.rs) and registers it; other
+// backends can be installed with `register_device`. Without a device, or when the device call
+// fails, the same blocked computation runs on the CPU (rayon over query blocks, row blocks kept in
+// cache). The fallback is logged once as `gpu_unavailable_cpu_fallback`.
+//
+// Quantized scores only pick the candidates; the caller re-scores them on the full vectors, so the
+// results are those of the exact flat search as long as the true top k are among the candidates.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Once, OnceLock};
+
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use uuid::Uuid;
+
+use crate::metrics::Metric;
+use crate::quantization::ScalarQuantizedVector;
+
+// Smallest batch worth sending to the device; smaller ones go query by query
+pub const DEVICE_MIN_BATCH: usize = 16;
+// Candidates per result kept from the quantized scores for exact re-scoring
+pub const DEVICE_RERANK_FACTOR: usize = 4;
+
+// Rows and queries scored together, so a block of rows stays in cache while a block of queries reads it
+const ROW_BLOCK: usize = 256;
+const QUERY_BLOCK: usize = 8;
+
+// Vectors of a flat index in the layout a device takes them: row i is `ids[i]`, its `dims` codes at
+// `codes[i * dims..]`, decoded as `offset + scale * code`
+pub struct QuantizedMatrix {
+    pub ids: Vec<Uuid>,
+    pub dims: usize,
+    pub codes: Vec<i8>,
+    pub offsets: Vec<f32>,
+    pub scales: Vec<f32>,
+    pub norms: Vec<f32>, // of the decoded rows
+    pub generation: u64, // unique per build, for a device caching its upload
+}
+
+impl QuantizedMatrix {
+    pub fn build<'a>(rows: impl Iterator<Item = (Uuid, &'a [f32])>, dims: usize, generation: u64) -> Self {
+        let mut matrix = QuantizedMatrix {
+            ids: Vec::new(),
+            dims,
+            codes: Vec::new(),
+            offsets: Vec::new(),
+            scales: Vec::new(),
+            norms: Vec::new(),
+            generation,
+        };
+        for (id, vector) in rows.filter(|(_, v)| v.len() == dims) {
+            let quantized = ScalarQuantizedVector::from_f32(vector);
+            // Scalar codes map [-127, 127] onto [min, max]
+            let scale = (quantized.max - quantized.min) / 254.0;
+            let offset = (quantized.min + quantized.max) / 2.0;
+            let norm = quantized.values.iter().map(|&c| (offset + scale * c as f32).powi(2)).sum::<f32>().sqrt();
+            matrix.ids.push(id);
+            matrix.codes.extend_from_slice(&quantized.values);
+            matrix.offsets.push(offset);
+            matrix.scales.push(scale);
+            matrix.norms.push(norm);
+        }
+        matrix
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.codes.len() + self.ids.len() * (std::mem::size_of::<Uuid>() + 3 * std::mem::size_of::<f32>())
+    }
+
+    // Approximate score of `query` (with its sum and norm precomputed) against row `row`
+    fn score(&self, metric: Metric, query: &[f32], sum: f32, norm: f32, row: usize) -> f32 {
+        let codes = &self.codes[row * self.dims..(row + 1) * self.dims];
+        let coded: f32 = query.iter().zip(codes).map(|(q, &c)| q * c as f32).sum();
+        let dot = self.offsets[row] * sum + self.scales[row] * coded;
+        match metric {
+            Metric::DotProduct => dot,
+            Metric::Cosine => {
+                let denom = norm * self.norms[row];
+                if denom > 0.0 { dot / denom } else { 0.0 }
+            }
+            Metric::Euclidean => {
+                let squared = (norm * norm + self.norms[row] * self.norms[row] - 2.0 * dot).max(0.0);
+                1.0 / (1.0 + squared.sqrt())
+            }
+        }
+    }
+}
+
+// A device that scores a batch of queries against an uploaded matrix
+pub trait FlatDevice: Send + Sync {
+    fn name(&self) -> &str;
+
+    // Row indexes of each query's `candidates` best rows, best first. None when the device failed
+    // (out of memory, lost context); the batch is then scored on the CPU.
+    fn top_candidates(&self, matrix: &QuantizedMatrix, queries: &[Vec<f32>], metric: Metric, candidates: usize) -> Option<Vec<Vec<usize>>>;
+}
+
+static DEVICE: OnceLock<Box<dyn FlatDevice>> = OnceLock::new();
+static FALLBACK_LOGGED: Once = Once::new();
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+// Install the device backend for the process. Only the first registration counts; returns whether
+// this one did.
+pub fn register_device(device: Box<dyn FlatDevice>) -> bool {
+    let name = device.name().to_string();
+    let registered = DEVICE.set(device).is_ok();
+    if registered {
+        tracing::info!(device=%name, "gpu_device_registered");
+    }
+    registered
+}
+
+pub fn device() -> Option<&'static dyn FlatDevice> {
+    #[cfg(feature = "gpu")]
+    {
+        static PROBED: Once = Once::new();
+        PROBED.call_once(|| match super::gpu::WgpuDevice::new() {
+            Some(device) => {
+                register_device(Box::new(device));
+            }
+            None => tracing::info!("gpu_adapter_not_found"),
+        });
+    }
+    DEVICE.get().map(|device| device.as_ref())
+}
+
+// Score on the device when there is one, on the CPU otherwise
+pub fn top_candidates(matrix: &QuantizedMatrix, queries: &[Vec<f32>], metric: Metric, candidates: usize) -> Vec<Vec<usize>> {
+    if let Some(device) = device() {
+        if let Some(found) = device.top_candidates(matrix, queries, metric, candidates) {
+            return found;
+        }
+        tracing::warn!(device=%device.name(), queries=queries.len(), "gpu_batch_failed_cpu_fallback");
+    } else {
+        FALLBACK_LOGGED.call_once(|| tracing::info!("gpu_unavailable_cpu_fallback"));
+    }
+    cpu_top_candidates(matrix, queries, metric, candidates)
+}
+
+// Score with total order on f32, lowest first, so a max-heap of these keeps the worst on top
+#[derive(PartialEq)]
+struct Candidate(f32, usize);
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0).then_with(|| self.1.cmp(&other.1))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Row indexes of the `candidates` best of `scores` (one per row), best first
+pub fn best_rows(scores: &[f32], candidates: usize) -> Vec<usize> {
+    let candidates = candidates.max(1);
+    let mut heap = BinaryHeap::with_capacity(candidates + 1);
+    for (row, &score) in scores.iter().enumerate() {
+        heap.push(Candidate(score, row));
+        if heap.len() > candidates {
+            heap.pop();
+        }
+    }
+    heap.into_sorted_vec().into_iter().map(|c| c.1).collect()
+}
+
+// The blocked computation a device runs, on the CPU
+pub fn cpu_top_candidates(matrix: &QuantizedMatrix, queries: &[Vec<f32>], metric: Metric, candidates: usize) -> Vec<Vec<usize>> {
+    let candidates = candidates.max(1);
+    queries
+        .par_chunks(QUERY_BLOCK)
+        .flat_map_iter(|block| {
+            let sums: Vec<f32> = block.iter().map(|q| q.iter().sum()).collect();
+            let norms: Vec<f32> = block.iter().map(|q| q.iter().map(|x| x * x).sum::<f32>().sqrt()).collect();
+            let mut heaps: Vec<BinaryHeap<Candidate>> = block.iter().map(|_| BinaryHeap::with_capacity(candidates + 1)).collect();
+            for start in (0..matrix.len()).step_by(ROW_BLOCK) {
+                let rows = start..(start + ROW_BLOCK).min(matrix.len());
+                for (q, query) in block.iter().enumerate() {
+                    if query.len() != matrix.dims {
+                        continue;
+                    }
+                    let heap = &mut heaps[q];
+                    for row in rows.clone() {
+                        heap.push(Candidate(matrix.score(metric, query, sums[q], norms[q], row), row));
+                        if heap.len() > candidates {
+                            heap.pop();
+                        }
+                    }
+                }
+            }
+            heaps.into_iter().map(|heap| heap.into_sorted_vec().into_iter().map(|c| c.1).collect::<Vec<_>>())
+        })
+        .collect()
+}
+
+// The uploaded matrix of a flat index, dropped whenever the index changes. Not saved with the index
+// and not carried over by a clone.
+#[derive(Default)]
+pub struct DeviceCache {
+    matrix: Mutex<Option<Arc<QuantizedMatrix>>>,
+}
+
+impl DeviceCache {
+    pub fn invalidate(&self) {
+        self.matrix.lock().take();
+    }
+
+    pub fn get_or_build(&self, ids: &[Uuid], vectors: &HashMap<Uuid, Vec<f32>>, dims: usize) -> Arc<QuantizedMatrix> {
+        let mut matrix = self.matrix.lock();
+        if let Some(matrix) = matrix.as_ref().filter(|m| m.dims == dims) {
+            return matrix.clone();
+        }
+        let generation = NEXT_GENERATION.fetch_add(1, AtomicOrdering::Relaxed);
+        let rows = ids.iter().filter_map(|id| vectors.get(id).map(|v| (*id, v.as_slice())));
+        let built = Arc::new(QuantizedMatrix::build(rows, dims, generation));
+        *matrix = Some(built.clone());
+        built
+    }
+
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.matrix.lock().as_ref().map_or(0, |m| m.memory_usage_bytes())
+    }
+}
+
+impl Clone for DeviceCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,279 @@
+// wgpu device backend for flat batches (`gpu` feature)
+// The quantized matrix is uploaded once per generation: codes packed four to a u32 (rows padded to
+// a whole word) and (offset, scale, norm) per row. A batch is split into chunks of queries whose
+// score matrix fits one storage buffer; each chunk is one dispatch of `SHADER` (a thread per
+// query and row), read back and cut to the best candidates per query on the CPU. Validation and
+// out-of-memory errors are caught per batch and reported as a failed call, so the caller falls
+// back to the CPU.
+
+use std::borrow::Cow;
+use std::sync::mpsc;
+
+use parking_lot::Mutex;
+use wgpu::util::DeviceExt;
+
+use super::device::{best_rows, FlatDevice, QuantizedMatrix};
+use crate::metrics::Metric;
+
+const WORKGROUP: u32 = 64;
+// Upper bound on one chunk's score matrix, below the binding limit of most adapters
+const SCORE_BUFFER_BYTES: u64 = 64 << 20;
+
+const SHADER: &str = r#"
+struct Params {
+    rows: u32,
+    dims: u32,
+    stride: u32,
+    queries: u32,
+    metric: u32,
+    pad0: u32,
+    pad1: u32,
+    pad2: u32,
+}
+
+@group(0) @binding(0) var<storage, read> codes: array<u32>;
+@group(0) @binding(1) var<storage, read> row_info: array<vec4<f32>>;
+@group(0) @binding(2) var<storage, read> queries: array<f32>;
+@group(0) @binding(3) var<storage, read_write> scores: array<f32>;
+@group(0) @binding(4) var<uniform> params: Params;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let row = id.x;
+    let q = id.y;
+    if (row >= params.rows || q >= params.queries) {
+        return;
+    }
+    var coded = 0.0;
+    var sum = 0.0;
+    var norm = 0.0;
+    for (var d = 0u; d < params.dims; d++) {
+        let word = i32(codes[row * params.stride + d / 4u]);
+        let code = f32(extractBits(word, (d % 4u) * 8u, 8u));
+        let x = queries[q * params.dims + d];
+        coded += x * code;
+        sum += x;
+        norm += x * x;
+    }
+    norm = sqrt(norm);
+    let info = row_info[row];
+    let dot = info.x * sum + info.y * coded;
+    var score = dot;
+    if (params.metric == 0u) {
+        let denom = norm * info.z;
+        score = select(0.0, dot / denom, denom > 0.0);
+    } else if (params.metric == 1u) {
+        let squared = max(norm * norm + info.z * info.z - 2.0 * dot, 0.0);
+        score = 1.0 / (1.0 + sqrt(squared));
+    }
+    scores[q * params.rows + row] = score;
+}
+"#;
+
+// The matrix as uploaded, kept until a batch arrives for another generation
+struct Upload {
+    generation: u64,
+    codes: wgpu::Buffer,
+    row_info: wgpu::Buffer,
+}
+
+pub struct WgpuDevice {
+    name: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    limits: wgpu::Limits,
+    upload: Mutex<Option<Upload>>,
+}
+
+impl WgpuDevice {
+    // The first high-performance Vulkan, Metal or DX12 adapter, or None when there is none (no
+    // driver, headless machine). GL is left out: its compute support is too uneven.
+    pub fn new() -> Option<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor { backends: wgpu::Backends::PRIMARY, ..Default::default() });
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))?;
+        let limits = adapter.limits();
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("piramid-flat"),
+                required_features: wgpu::Features::empty(),
+                required_limits: limits.clone(),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        ))
+        .ok()?;
+        // Errors outside an error scope are logged instead of panicking the search thread
+        device.on_uncaptured_error(Box::new(|error| tracing::warn!(error=%error, "gpu_device_error")));
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("piramid-flat-scores"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("piramid-flat-scores"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let info = adapter.get_info();
+        Some(Self {
+            name: format!("{} ({:?})", info.name, info.backend),
+            device,
+            queue,
+            pipeline,
+            limits,
+            upload: Mutex::new(None),
+        })
+    }
+
+    fn storage(&self, label: &str, contents: &[u8]) -> wgpu::Buffer {
+        self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage: wgpu::BufferUsages::STORAGE,
+        })
+    }
+
+    fn upload(&self, matrix: &QuantizedMatrix) -> Upload {
+        let stride = matrix.dims.div_ceil(4);
+        let mut codes = vec![0u8; matrix.len() * stride * 4];
+        for row in 0..matrix.len() {
+            let source = &matrix.codes[row * matrix.dims..(row + 1) * matrix.dims];
+            for (byte, &code) in codes[row * stride * 4..].iter_mut().zip(source) {
+                *byte = code as u8;
+            }
+        }
+        let row_info: Vec<u8> = (0..matrix.len())
+            .flat_map(|row| [matrix.offsets[row], matrix.scales[row], matrix.norms[row], 0.0])
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        Upload { generation: matrix.generation, codes: self.storage("piramid-flat-codes", &codes), row_info: self.storage("piramid-flat-rows", &row_info) }
+    }
+
+    // Scores of `queries` (all of the matrix's dimension) against every row, query by query
+    fn scores(&self, upload: &Upload, matrix: &QuantizedMatrix, queries: &[&[f32]], metric: Metric) -> Option<Vec<f32>> {
+        let rows = matrix.len() as u32;
+        let metric = match metric {
+            Metric::Cosine => 0u32,
+            Metric::Euclidean => 1,
+            Metric::DotProduct => 2,
+        };
+        let params: Vec<u8> = [rows, matrix.dims as u32, matrix.dims.div_ceil(4) as u32, queries.len() as u32, metric, 0, 0, 0]
+            .into_iter()
+            .flat_map(u32::to_le_bytes)
+            .collect();
+        let query_data: Vec<u8> = queries.iter().flat_map(|q| q.iter()).flat_map(|x| x.to_le_bytes()).collect();
+        let size = queries.len() as u64 * rows as u64 * 4;
+
+        let params = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("piramid-flat-params"),
+            contents: &params,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let query_buffer = self.storage("piramid-flat-queries", &query_data);
+        let scores = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("piramid-flat-scores"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("piramid-flat-readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("piramid-flat-bindings"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: upload.codes.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: upload.row_info.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: query_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: scores.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: params.as_entire_binding() },
+            ],
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("piramid-flat-batch") });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("piramid-flat-batch"), timestamp_writes: None });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(rows.div_ceil(WORKGROUP), queries.len() as u32, 1);
+        }
+        encoder.copy_buffer_to_buffer(&scores, 0, &readback, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv().ok()?.ok()?;
+        let scores = slice.get_mapped_range().chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+        readback.unmap();
+        Some(scores)
+    }
+
+    fn batch(&self, matrix: &QuantizedMatrix, queries: &[Vec<f32>], metric: Metric, candidates: usize) -> Option<Vec<Vec<usize>>> {
+        let max_groups = self.limits.max_compute_workgroups_per_dimension as u64;
+        let max_binding = (self.limits.max_storage_buffer_binding_size as u64).min(self.limits.max_buffer_size);
+        let stride = matrix.dims.div_ceil(4) as u64;
+        let rows = matrix.len() as u64;
+        // Query chunk whose score matrix fits one buffer; matrices past the limits stay on the CPU
+        let chunk = (SCORE_BUFFER_BYTES.min(max_binding) / (rows * 4)).min(max_groups);
+        if chunk == 0 || rows.div_ceil(WORKGROUP as u64) > max_groups || rows * stride * 4 > max_binding {
+            return None;
+        }
+
+        let mut upload = self.upload.lock();
+        if upload.as_ref().is_none_or(|u| u.generation != matrix.generation) {
+            *upload = Some(self.upload(matrix));
+        }
+        let upload = upload.as_ref()?;
+
+        // Queries of another dimension get no candidates, as on the CPU
+        let valid: Vec<(usize, &[f32])> = queries.iter().enumerate().filter(|(_, q)| q.len() == matrix.dims).map(|(i, q)| (i, q.as_slice())).collect();
+        let mut found = vec![Vec::new(); queries.len()];
+        for block in valid.chunks(chunk as usize) {
+            let vectors: Vec<&[f32]> = block.iter().map(|(_, q)| *q).collect();
+            let scores = self.scores(upload, matrix, &vectors, metric)?;
+            for ((index, _), scores) in block.iter().zip(scores.chunks_exact(matrix.len())) {
+                found[*index] = best_rows(scores, candidates);
+            }
+        }
+        Some(found)
+    }
+}
+
+impl FlatDevice for WgpuDevice {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn top_candidates(&self, matrix: &QuantizedMatrix, queries: &[Vec<f32>], metric: Metric, candidates: usize) -> Option<Vec<Vec<usize>>> {
+        if matrix.is_empty() {
+            return Some(vec![Vec::new(); queries.len()]);
+        }
+        self.device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let found = self.batch(matrix, queries, metric, candidates);
+        let validation = pollster::block_on(self.device.pop_error_scope());
+        let out_of_memory = pollster::block_on(self.device.pop_error_scope());
+        if let Some(error) = validation.or(out_of_memory) {
+            tracing::warn!(device=%self.name, error=%error, "gpu_batch_error");
+            // A half-written upload is not reused
+            self.upload.lock().take();
+            return None;
+        }
+        found
+    }
+}
@@ -7,7 +7,10 @@ use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
 
 use super::config::FlatConfig;
+use super::device::{self, DeviceCache};
 use crate::index::traits::{VectorIndex, IndexStats, IndexDetails, IndexType};
+use crate::index::ColumnView;
+use crate::search::utils::best_first;
 
 // Flat index - simple brute force search
 // Stores nothing except config (vectors are in main storage)
@@ -15,6 +18,8 @@ use crate::index::traits::{VectorIndex, IndexStats, IndexDetails, IndexType};
 pub struct FlatIndex {
     config: FlatConfig, // Configuration for the flat index, including distance metric and execution mode
     vector_ids: Vec<Uuid>,  // Track which vectors we've seen
+    #[serde(skip)]
+    device: DeviceCache, // Quantized copy of the vectors for batched (GPU mode) search
 }
 
 // Implement methods for FlatIndex
@@ -23,6 +28,7 @@ impl FlatIndex {
         FlatIndex {
             config,
             vector_ids: Vec::new(),
+            device: DeviceCache::default(),
         }
     }
 }
@@ -30,6 +36,7 @@ impl FlatIndex {
 impl VectorIndex for FlatIndex {
     fn insert(&mut self, id: Uuid, _vector: &[f32], _vectors: &HashMap<Uuid, Vec<f32>>) {
         // Just track the ID - no indexing structure needed
+        self.device.invalidate();
         if !self.vector_ids.contains(&id) {
             self.vector_ids.push(id);
         }
@@ -57,10 +64,8 @@ impl VectorIndex for FlatIndex {
             })
             .collect();
         
-        // Sort by score (descending for similarity)
-        distances.sort_by(|a, b| {
-            b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal)
-        });
+        // Sort by score (descending for similarity, ties by id)
+        distances.sort_by(best_first);
         
         // Return top k IDs
         distances.iter()
@@ -70,14 +75,20 @@ impl VectorIndex for FlatIndex {
     }
     
     fn remove(&mut self, id: &Uuid) {
+        self.device.invalidate();
         self.vector_ids.retain(|vid| vid != id);
     }
+
+    fn ids(&self) -> Vec<Uuid> {
+        self.vector_ids.clone()
+    }
     
     fn stats(&self) -> IndexStats {
         IndexStats {
             index_type: IndexType::Flat,
+            metric: self.config.metric,
             total_vectors: self.vector_ids.len(),
-            memory_usage_bytes: self.vector_ids.len() * std::mem::size_of::<Uuid>(),
+            memory_usage_bytes: self.vector_ids.len() * std::mem::size_of::<Uuid>() + self.device.memory_usage_bytes(),
             details: IndexDetails::Flat,
         }
     }
@@ -85,4 +96,45 @@ impl VectorIndex for FlatIndex {
     fn index_type(&self) -> IndexType {
         IndexType::Flat
     }
+
+    fn metric(&self) -> crate::metrics::Metric {
+        self.config.metric
+    }
+
+    // Same brute force, but over contiguous rows in file order
+    fn search_column(
+        &self,
+        query: &[f32],
+        k: usize,
+        column: ColumnView<'_>,
+        _quality: crate::config::SearchConfig,
+        _filter: Option<&crate::search::query::Filter>,
+        _metadatas: &HashMap<Uuid, crate::metadata::Metadata>,
+    ) -> Option<Vec<Uuid>> {
+        let mut distances: Vec<(Uuid, f32)> = column
+            .rows()
+            .map(|(id, vec)| (id, self.config.metric.calculate(query, vec, self.config.mode)))
+            .collect();
+        distances.sort_by(best_first);
+        Some(distances.iter().take(k).map(|(id, _)| *id).collect())
+    }
+
+    // The batch scored as one matrix product, on the registered device or on the CPU
+    fn search_batch(
+        &self,
+        queries: &[Vec<f32>],
+        candidates: usize,
+        vectors: &HashMap<Uuid, Vec<f32>>,
+    ) -> Option<Vec<Vec<Uuid>>> {
+        let dims = queries.first()?.len();
+        let matrix = self.device.get_or_build(&self.vector_ids, vectors, dims);
+        let rows = device::top_candidates(&matrix, queries, self.config.metric, candidates);
+        Some(rows.into_iter().map(|rows| rows.into_iter().map(|row| matrix.ids[row]).collect()).collect())
+    }
+
+    fn build_from_column(&mut self, column: ColumnView<'_>) -> bool {
+        self.device.invalidate();
+        self.vector_ids = column.rows().map(|(id, _)| id).collect();
+        true
+    }
 }
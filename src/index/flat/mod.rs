@@ -1,6 +1,9 @@
 // Flat index module
 
 mod config;
+pub mod device;
+#[cfg(feature = "gpu")]
+pub mod gpu;
 mod index;
 
 pub use config::FlatConfig;
@@ -40,5 +40,15 @@ pub struct HnswStats {
     pub layer_sizes: Vec<usize>,
     pub tombstones: usize,
     pub avg_connections: f32,
+    pub avg_out_degree: Vec<f32>, // mean neighbours of the live nodes on each layer
+    pub entry_point_depth: isize, // top layer of the entry point (-1 when empty)
     pub memory_usage_bytes: usize,
 }
+
+// How well the graph holds together, following edges of every layer and through tombstoned
+// nodes, as searches do. One pass over all edges, so it is only computed when asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HnswConnectivity {
+    pub components: usize, // weakly connected pieces holding at least one live node
+    pub unreachable: usize, // live nodes no path from the entry point leads to
+}
@@ -1,18 +1,18 @@
 use uuid::Uuid;
 use std::collections::{HashMap, HashSet, BinaryHeap};
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 use crate::metrics::Metric;
 use serde::{Serialize, Deserialize};
 
-use super::config::{HnswConfig, HnswStats};
+use super::config::{HnswConfig, HnswStats, HnswConnectivity};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct HnswNode{
+pub(super) struct HnswNode{
     // connections[layer] = Vec of neighbor IDs at that layer
     // Layer 0 is at index 0
-    connections: Vec<Vec<Uuid>>,
+    pub(super) connections: Vec<Vec<Uuid>>,
     // Marked true when deleted; we keep edges so traversal stays connected.
-    tombstone: bool,
+    pub(super) tombstone: bool,
 }
 
 // Helper struct for priority queue during search
@@ -28,7 +28,7 @@ struct SearchCandidate {
 // how to compare them in the fn eq() method
 impl PartialEq for SearchCandidate {
     fn eq(&self, other: &Self) -> bool {
-        self.distance == other.distance // equality based on distance
+        self.cmp(other) == Ordering::Equal // equal when both distance and id are
     }
 }
 
@@ -51,21 +51,26 @@ impl PartialOrd for SearchCandidate {
 impl Ord for SearchCandidate {
     fn cmp(&self, other: &Self) -> Ordering {
         // For max heap, reverse ordering so closest (smallest distance) comes first
-        other.distance.partial_cmp(&self.distance).unwrap_or(Ordering::Equal) 
+        other.distance.partial_cmp(&self.distance).unwrap_or(Ordering::Equal)
+            .then_with(|| other.id.cmp(&self.id))
         // Ordering::Equal means the two distances are equal
         // by doing this, we order SearchCandidate in descending order of distance. how? by 
         // comparing other to self instead of self to other, so the one with smaller distance
-        // is considered "greater" in the context of a max-heap, thus it will be popped first
+        // is considered "greater" in the context of a max-heap, thus it will be popped first.
+        // Equal distances fall back to the id (smaller first), so the order neighbours are
+        // expanded in never depends on the order they were found in
     }
 }
 
 // Main HNSW index structure
 #[derive(Clone, Serialize, Deserialize)]
 pub struct HnswIndex{
-    config: HnswConfig, // configuration parameters, for example: m, ef_construction, ml, metric
-    nodes: HashMap<Uuid, HnswNode>,
-    max_level: isize,
-    start_node: Option<Uuid>,
+    pub(super) config: HnswConfig, // configuration parameters, for example: m, ef_construction, ml, metric
+    pub(super) nodes: HashMap<Uuid, HnswNode>,
+    pub(super) max_level: isize,
+    pub(super) start_node: Option<Uuid>,
+    #[serde(default)]
+    pub(super) deterministic: bool, // layers drawn from the node id instead of at random
 }
 
 impl HnswIndex{
@@ -75,6 +80,29 @@ impl HnswIndex{
             nodes: HashMap::new(),
             max_level: -1,
             start_node: None,
+            deterministic: false,
+        }
+    }
+
+    // Build an index from a graph produced elsewhere (e.g. an offline builder).
+    // graph[id][layer] = neighbors of id at that layer; the caller must have checked that every
+    // neighbor is itself a key of the graph and that the entry point sits on the top layer.
+    pub fn from_graph(config: HnswConfig, graph: HashMap<Uuid, Vec<Vec<Uuid>>>, entry_point: Option<Uuid>) -> Self {
+        let max_level = graph
+            .values()
+            .map(|connections| connections.len() as isize - 1)
+            .max()
+            .unwrap_or(-1);
+        let nodes = graph
+            .into_iter()
+            .map(|(id, connections)| (id, HnswNode { connections, tombstone: false }))
+            .collect();
+        HnswIndex {
+            config,
+            nodes,
+            max_level,
+            start_node: entry_point,
+            deterministic: false,
         }
     }
 
@@ -90,10 +118,12 @@ impl HnswIndex{
     // Generate a random layer for a new node based on exponential decay why? because 
     // in HNSW, higher layers have exponentially fewer nodes, so we want to assign layers
     // to new nodes in a way that reflects this distribution
-    fn random_layer(&self) -> usize{
+    // In deterministic mode the uniform draw is a hash of the id, so a node gets the same layer
+    // in every build, whatever order the nodes are inserted in
+    fn random_layer(&self, id: &Uuid) -> usize{
         // exponential decay probability 
         // floor(-ln(uniform_random) * ml)
-        let r: f32 = rand::random();
+        let r: f32 = if self.deterministic { uniform_from_id(id) } else { rand::random() };
         (-r.ln() * self.config.ml).floor() as usize // this basically gives us a layer based on
                                                     // exponential decay
     }
@@ -104,7 +134,7 @@ impl HnswIndex{
         // in hnsw, we add nodes one at a time, connecting them to existing nodes
         // first, we need to create the node and determine its level
         // determine the layer for the new node 
-        let layer = self.random_layer(); // this gives us a layer based on exponential decay
+        let layer = self.random_layer(&id); // this gives us a layer based on exponential decay
 
         // if first node, make it entry point and return 
         if self.start_node.is_none(){
@@ -247,6 +277,7 @@ impl HnswIndex{
     }
 
     // Search within a specific layer - returns nearest neighbor IDs sorted by distance
+    #[allow(clippy::too_many_arguments)]
     fn search_layer(
         &self,
         query: &[f32],
@@ -259,7 +290,8 @@ impl HnswIndex{
     ) -> Vec<Uuid> {
         let mut visited = HashSet::new();
         let mut candidates = BinaryHeap::new();
-        let mut nearest = BinaryHeap::new();
+        // SearchCandidate orders closest first; reversed, the top of `nearest` is its furthest member
+        let mut nearest: BinaryHeap<Reverse<SearchCandidate>> = BinaryHeap::new();
 
         // Initialize with entry points
         for &ep in entry_points {
@@ -274,7 +306,7 @@ impl HnswIndex{
                 let dist = self.distance(query, ep_vector);
                 candidates.push(SearchCandidate { id: ep, distance: dist });
                 if !self.is_tombstone(&ep) {
-                    nearest.push(SearchCandidate { id: ep, distance: dist });
+                    nearest.push(Reverse(SearchCandidate { id: ep, distance: dist }));
                 }
                 visited.insert(ep);
             }
@@ -282,7 +314,7 @@ impl HnswIndex{
 
         // we track furthest distance by looking at the top of the nearest heap (since it's a
         // max-heap)
-        let mut furthest_distance = nearest.peek().map(|c| c.distance).unwrap_or(f32::INFINITY);
+        let mut furthest_distance = nearest.peek().map(|c| c.0.distance).unwrap_or(f32::INFINITY);
 
         // Greedy search within the layer basically, greedy search means we always explore the
         // closest candidate first
@@ -313,14 +345,14 @@ impl HnswIndex{
                                 if dist < furthest_distance || nearest.len() < num_closest {
                                     candidates.push(SearchCandidate { id: neighbor_id, distance: dist });
                                     if !neighbor_dead {
-                                        nearest.push(SearchCandidate { id: neighbor_id, distance: dist });
+                                        nearest.push(Reverse(SearchCandidate { id: neighbor_id, distance: dist }));
                                         
                                         if nearest.len() > num_closest {
                                             nearest.pop(); // remove furthest
                                         }
                                         
                                         // Update furthest distance
-                                        furthest_distance = nearest.peek().map(|c| c.distance).unwrap_or(f32::INFINITY);
+                                        furthest_distance = nearest.peek().map(|c| c.0.distance).unwrap_or(f32::INFINITY);
                                     }
                                 }
                             }
@@ -331,15 +363,15 @@ impl HnswIndex{
         }
 
         // Convert heap to sorted vector (closest first)
-        let mut result: Vec<_> = nearest.into_iter().collect();
-        result.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(Ordering::Equal)); // sort
-                                                                                               // ascending
-                                                                                               // by
-                                                                                               // distance
+        let mut result: Vec<_> = nearest.into_iter().map(|Reverse(c)| c).collect();
+        result.sort_by(|a, b| b.cmp(a)); // sort ascending by distance, then id
         result.into_iter().map(|c| c.id).collect() // return only IDs
     }
 
-    // Select M best neighbors using simple heuristic
+    // Select M neighbors with the HNSW heuristic: closest first, skipping a candidate that is closer to
+    // an already selected neighbor than to the node itself, then topping up with the skipped ones.
+    // Picking only the M closest keeps every edge inside a dense cluster, and once pruning drops the
+    // few links between clusters, whole clusters become unreachable.
     fn select_neighbors(
         &self,
         candidates: &[Uuid],
@@ -351,23 +383,33 @@ impl HnswIndex{
             return candidates.to_vec();
         }
 
-        // Simple heuristic: select M closest by distance
         let mut distances: Vec<_> = candidates
             .iter()
             .filter_map(|&id| {
                 if self.is_tombstone(&id) {
                     return None;
                 }
-                vectors.get(&id).map(|vec| {
-                    let dist = self.distance(query, vec);
-                    (id, dist)
-                })
+                vectors.get(&id).map(|vec| (id, vec, self.distance(query, vec)))
             })
             .collect();
+        distances.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal).then_with(|| a.0.cmp(&b.0)));
 
-        distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
-        distances.truncate(m);
-        distances.into_iter().map(|(id, _)| id).collect()
+        let mut selected: Vec<(Uuid, &Vec<f32>)> = Vec::with_capacity(m);
+        let mut skipped = Vec::new();
+        for (id, vec, dist) in distances {
+            if selected.len() == m {
+                break;
+            }
+            if selected.iter().all(|(_, chosen)| self.distance(vec, chosen) > dist) {
+                selected.push((id, vec));
+            } else {
+                skipped.push(id);
+            }
+        }
+        let mut result: Vec<Uuid> = selected.into_iter().map(|(id, _)| id).collect();
+        let room = m - result.len();
+        result.extend(skipped.into_iter().take(room));
+        result
     }
 
     #[allow(dead_code)]
@@ -384,13 +426,9 @@ impl HnswIndex{
     // distance function that calculates using configured metric
     fn distance(&self, a: &[f32], b: &[f32]) -> f32 {
         // HNSW works with distances (lower = better)
-        // But our metrics return similarity scores (higher = better for Cosine/Dot)
-        // So we need to invert for those metrics
-        match self.config.metric {
-            Metric::Cosine => 1.0 - self.config.metric.calculate(a, b, self.config.mode),
-            Metric::DotProduct => 1.0 - self.config.metric.calculate(a, b, self.config.mode),
-            Metric::Euclidean => self.config.metric.calculate(a, b, self.config.mode),
-        }
+        // But our metrics return similarity scores (higher = better), Euclidean included: it
+        // comes back as 1 / (1 + distance). So we invert them all
+        1.0 - self.config.metric.calculate(a, b, self.config.mode)
     }
 
     // Remove a node from the index
@@ -400,11 +438,12 @@ impl HnswIndex{
         }
         self.mark_tombstone(id);
 
-        // Update entry point if needed
+        // Update entry point if needed: a live node on the highest layer, the smallest id among them
         if self.start_node == Some(*id) {
             self.start_node = self.nodes
                 .iter()
-                .find(|(_, n)| !n.tombstone)
+                .filter(|(_, n)| !n.tombstone)
+                .max_by_key(|(k, n)| (n.connections.len(), Reverse(**k)))
                 .map(|(k, _)| *k);
             self.max_level = self.nodes
                 .values()
@@ -415,6 +454,11 @@ impl HnswIndex{
         }
     }
 
+    // Ids of the nodes that are not tombstoned
+    pub fn live_ids(&self) -> Vec<Uuid> {
+        self.nodes.iter().filter(|(_, n)| !n.tombstone).map(|(id, _)| *id).collect()
+    }
+
     // Get statistics about the index
     // we do this by iterating through all nodes and collecting data such as
     // total number of nodes, max layer, size of each layer, average connections per node
@@ -422,10 +466,9 @@ impl HnswIndex{
         let mut total_nodes = 0;
         let mut tombstones = 0;
         let mut layer_sizes = vec![0; (self.max_level + 1) as usize];
+        let mut layer_edges = vec![0; layer_sizes.len()];
         let mut total_connections = 0;
 
-
-
         for node in self.nodes.values() {
             if node.tombstone {
                 tombstones += 1;
@@ -434,11 +477,20 @@ impl HnswIndex{
                 for (layer, connections) in node.connections.iter().enumerate() {
                     if layer < layer_sizes.len() {
                         layer_sizes[layer] += 1;
+                        layer_edges[layer] += connections.len();
                     }
                     total_connections += connections.len();
                 }
             }
         }
+        let avg_out_degree = layer_sizes
+            .iter()
+            .zip(&layer_edges)
+            .map(|(&size, &edges)| if size > 0 { edges as f32 / size as f32 } else { 0.0 })
+            .collect();
+        let entry_point_depth = self.start_node
+            .and_then(|id| self.nodes.get(&id))
+            .map_or(-1, |node| node.connections.len() as isize - 1);
 
         // calculate memory usage bytes 
         let memory_usage_bytes = self.nodes.len() * std::mem::size_of::<(Uuid, HnswNode)>() + 
@@ -449,6 +501,8 @@ impl HnswIndex{
             tombstones,
             max_layer: self.max_level,
             layer_sizes,
+            avg_out_degree,
+            entry_point_depth,
             memory_usage_bytes,
             avg_connections: if total_nodes > 0 {
                 total_connections as f32 / total_nodes as f32
@@ -458,9 +512,74 @@ impl HnswIndex{
         }
     }
     
+    // Components by union-find over every edge, reachability by a walk from the entry point
+    pub fn connectivity(&self) -> HnswConnectivity {
+        let ids: Vec<Uuid> = self.nodes.keys().copied().collect();
+        let index: HashMap<Uuid, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let mut parent: Vec<usize> = (0..ids.len()).collect();
+        fn root(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+        for (i, id) in ids.iter().enumerate() {
+            for &neighbor in self.nodes[id].connections.iter().flatten() {
+                if let Some(&j) = index.get(&neighbor) {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[a] = b;
+                }
+            }
+        }
+        let mut components = HashSet::new();
+        for (i, id) in ids.iter().enumerate() {
+            if !self.nodes[id].tombstone {
+                components.insert(root(&mut parent, i));
+            }
+        }
+
+        let mut reached = HashSet::new();
+        let mut stack: Vec<Uuid> = self.start_node.into_iter().collect();
+        while let Some(id) = stack.pop() {
+            if !reached.insert(id) {
+                continue;
+            }
+            if let Some(node) = self.nodes.get(&id) {
+                stack.extend(node.connections.iter().flatten().filter(|n| !reached.contains(*n)));
+            }
+        }
+        let unreachable = self.nodes
+            .iter()
+            .filter(|(id, node)| !node.tombstone && !reached.contains(*id))
+            .count();
+
+        HnswConnectivity { components: components.len(), unreachable }
+    }
+
+    // Draw layers from node ids from now on (see random_layer)
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
     // Get configured ef_search parameter
     pub fn get_ef_search(&self) -> usize {
         self.config.ef_search
     }
+
+    // Metric the graph's distances are computed with
+    pub fn metric(&self) -> Metric {
+        self.config.metric
+    }
 }
 
+// A value in (0, 1] from the id's bits (a splitmix64 finalizer over both halves), uniform enough for
+// drawing layers and the same on every run
+fn uniform_from_id(id: &Uuid) -> f32 {
+    let (high, low) = id.as_u64_pair();
+    let mut x = high ^ low.rotate_left(32);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^= x >> 31;
+    ((x >> 40) as f32 + 1.0) / (1u64 << 24) as f32
+}
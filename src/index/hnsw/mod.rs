@@ -1,14 +1,21 @@
 mod config;
 mod graph;
+mod packed;
+mod sharded;
 
-pub use config::{HnswConfig, HnswStats};
+pub use config::{HnswConfig, HnswStats, HnswConnectivity};
 pub use graph::HnswIndex;
+pub use packed::PackedHnsw;
+pub use sharded::{HnswShardStats, ShardedHnsw};
 
 // Implement VectorIndex trait for HnswIndex
 use uuid::Uuid;
 use std::collections::HashMap;
 use crate::index::traits::{VectorIndex, IndexStats, IndexDetails, IndexType};
 
+// Largest ef a latency-target search raises to; past this the candidate list covers most graphs
+const MAX_EF: usize = 1024;
+
 // Implement the VectorIndex trait for HnswIndex. This includes methods for inserting vectors, searching for nearest neighbors, removing vectors, and getting index statistics. The insert method adds a vector to the HNSW graph structure. The search method performs an approximate nearest neighbor search using the HNSW algorithm, which is more efficient than a brute force search while still providing good accuracy. The remove method removes a vector from the graph, and the stats method returns information about the index such as total nodes, max layer, layer sizes, average connections, and memory usage.
 impl VectorIndex for HnswIndex {
     fn insert(&mut self, id: Uuid, vector: &[f32], vectors: &HashMap<Uuid, Vec<f32>>) {
@@ -33,6 +40,14 @@ impl VectorIndex for HnswIndex {
     fn remove(&mut self, id: &Uuid) {
         self.remove(id);
     }
+
+    fn ids(&self) -> Vec<Uuid> {
+        self.live_ids()
+    }
+
+    fn set_deterministic(&mut self, deterministic: bool) {
+        self.set_deterministic(deterministic);
+    }
     
     // Get statistics about the HNSW index, including total nodes, max layer, layer sizes, average connections, and memory usage. This information can be useful for monitoring the health of the index and understanding its structure and performance characteristics.
     fn stats(&self) -> IndexStats {
@@ -40,12 +55,17 @@ impl VectorIndex for HnswIndex {
         
         IndexStats {
             index_type: IndexType::Hnsw,
+            metric: self.metric(),
             total_vectors: hnsw_stats.total_nodes,
             memory_usage_bytes: hnsw_stats.memory_usage_bytes,
             details: IndexDetails::Hnsw {
                 max_layer: hnsw_stats.max_layer,
                 layer_sizes: hnsw_stats.layer_sizes,
                 avg_connections: hnsw_stats.avg_connections,
+                avg_out_degree: hnsw_stats.avg_out_degree,
+                entry_point_depth: hnsw_stats.entry_point_depth,
+                diagnostics: None,
+                shards: Vec::new(),
             },
         }
     }
@@ -53,4 +73,17 @@ impl VectorIndex for HnswIndex {
     fn index_type(&self) -> IndexType {
         IndexType::Hnsw
     }
+
+    fn connectivity(&self) -> Option<HnswConnectivity> {
+        Some(self.connectivity())
+    }
+
+    fn search_effort(&self) -> Option<(usize, usize)> {
+        let ef = self.get_ef_search();
+        Some((ef, ef.max(MAX_EF)))
+    }
+
+    fn metric(&self) -> crate::metrics::Metric {
+        self.metric()
+    }
 }
@@ -0,0 +1,86 @@
+// Read-optimized file layout of an HNSW graph, written for sealed collections (see
+// storage/collection/worm.rs), whose graph never changes again. Nodes are numbered by their position
+// in `ids`; every neighbor list is a run of u32 positions in one flat `neighbors` array, found through
+// `offsets` (one entry per node and layer), in place of a map of per-node lists of 16-byte ids. The
+// file is a fraction of the size, and loading unpacks it into an HnswIndex with exactly sized lists.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::config::HnswConfig;
+use super::graph::{HnswIndex, HnswNode};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PackedHnsw {
+    config: HnswConfig,
+    ids: Vec<Uuid>,
+    layers: Vec<u8>, // layers each node is on
+    offsets: Vec<u32>, // start of each node's list per layer in `neighbors`, plus the end
+    neighbors: Vec<u32>,
+    tombstones: Vec<u32>,
+    max_level: isize,
+    start_node: Option<u32>,
+}
+
+impl PackedHnsw {
+    pub fn pack(index: &HnswIndex) -> Self {
+        let mut ids: Vec<Uuid> = index.nodes.keys().copied().collect();
+        ids.sort_unstable();
+        let position: HashMap<Uuid, u32> = ids.iter().enumerate().map(|(i, id)| (*id, i as u32)).collect();
+
+        let mut layers = Vec::with_capacity(ids.len());
+        let mut offsets = vec![0u32];
+        let mut neighbors = Vec::new();
+        let mut tombstones = Vec::new();
+        for (i, id) in ids.iter().enumerate() {
+            let node = &index.nodes[id];
+            layers.push(node.connections.len() as u8);
+            for layer in &node.connections {
+                neighbors.extend(layer.iter().filter_map(|neighbor| position.get(neighbor)));
+                offsets.push(neighbors.len() as u32);
+            }
+            if node.tombstone {
+                tombstones.push(i as u32);
+            }
+        }
+        Self {
+            config: index.config.clone(),
+            start_node: index.start_node.and_then(|id| position.get(&id).copied()),
+            max_level: index.max_level,
+            ids,
+            layers,
+            offsets,
+            neighbors,
+            tombstones,
+        }
+    }
+
+    pub fn unpack(self) -> HnswIndex {
+        let mut nodes = HashMap::with_capacity(self.ids.len());
+        let mut list = 0;
+        for (id, layers) in self.ids.iter().zip(&self.layers) {
+            let connections = (0..*layers)
+                .map(|_| {
+                    let (start, end) = (self.offsets[list] as usize, self.offsets[list + 1] as usize);
+                    list += 1;
+                    self.neighbors[start..end].iter().map(|n| self.ids[*n as usize]).collect()
+                })
+                .collect();
+            nodes.insert(*id, HnswNode { connections, tombstone: false });
+        }
+        for position in &self.tombstones {
+            if let Some(node) = nodes.get_mut(&self.ids[*position as usize]) {
+                node.tombstone = true;
+            }
+        }
+        HnswIndex {
+            config: self.config,
+            nodes,
+            max_level: self.max_level,
+            start_node: self.start_node.map(|n| self.ids[n as usize]),
+            deterministic: false,
+        }
+    }
+}
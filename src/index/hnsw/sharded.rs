@@ -0,0 +1,163 @@
+// HNSW split into independent graphs, searched in parallel
+// A document goes to the shard its id hashes to, so shards fill evenly and an id is always found in
+// the same one. A search runs on every shard at once (rayon, on the calling pool) with the full k and
+// ef, and the candidates are merged by score. Each graph is smaller, so a shard's search is cheaper
+// and a query scales with the cores it gets; the price is some recall, as a neighbour that a single
+// graph would have reached through another region is now only found if its own shard's walk does.
+// Inserts and removes touch one shard.
+
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::index::traits::{VectorIndex, IndexStats, IndexDetails, IndexType};
+use super::{HnswConfig, HnswConnectivity, HnswIndex};
+
+// Per-shard numbers reported in the index stats
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswShardStats {
+    pub vectors: usize,
+    pub tombstones: usize,
+    pub max_layer: isize,
+    pub avg_connections: f32,
+    pub memory_usage_bytes: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ShardedHnsw {
+    shards: Vec<HnswIndex>,
+}
+
+impl ShardedHnsw {
+    pub fn new(config: HnswConfig, shards: usize) -> Self {
+        Self { shards: (0..shards.max(1)).map(|_| HnswIndex::new(config.clone())).collect() }
+    }
+
+    fn shard_of(&self, id: &Uuid) -> usize {
+        (id.as_u128() % self.shards.len() as u128) as usize
+    }
+
+    pub fn shard_stats(&self) -> Vec<HnswShardStats> {
+        self.shards
+            .iter()
+            .map(|shard| {
+                let stats = shard.stats();
+                HnswShardStats {
+                    vectors: stats.total_nodes,
+                    tombstones: stats.tombstones,
+                    max_layer: stats.max_layer,
+                    avg_connections: stats.avg_connections,
+                    memory_usage_bytes: stats.memory_usage_bytes,
+                }
+            })
+            .collect()
+    }
+}
+
+impl VectorIndex for ShardedHnsw {
+    fn insert(&mut self, id: Uuid, vector: &[f32], vectors: &HashMap<Uuid, Vec<f32>>) {
+        let shard = self.shard_of(&id);
+        self.shards[shard].insert(id, vector, vectors);
+    }
+
+    fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        vectors: &HashMap<Uuid, Vec<f32>>,
+        quality: crate::config::SearchConfig,
+        filter: Option<&crate::search::query::Filter>,
+        metadatas: &HashMap<Uuid, crate::metadata::Metadata>,
+    ) -> Vec<Uuid> {
+        let config = &self.shards[0].config;
+        let ef = quality.ef.unwrap_or(config.ef_search).max(k);
+        let mut scored: Vec<(f32, Uuid)> = self.shards
+            .par_iter()
+            .flat_map_iter(|shard| shard.search(query, k, ef, vectors, filter, metadatas))
+            .filter_map(|id| vectors.get(&id).map(|v| (config.metric.calculate(query, v, config.mode), id)))
+            .collect();
+        // Highest score first; equal scores by id, so the merge does not depend on shard timing
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        scored.truncate(k);
+        scored.into_iter().map(|(_, id)| id).collect()
+    }
+
+    fn remove(&mut self, id: &Uuid) {
+        let shard = self.shard_of(id);
+        self.shards[shard].remove(id);
+    }
+
+    fn ids(&self) -> Vec<Uuid> {
+        self.shards.iter().flat_map(|shard| shard.live_ids()).collect()
+    }
+
+    fn set_deterministic(&mut self, deterministic: bool) {
+        for shard in &mut self.shards {
+            shard.set_deterministic(deterministic);
+        }
+    }
+
+    // The shards' numbers added up (layer sizes per layer, connections averaged over all nodes), with
+    // each shard's own in `shards`
+    fn stats(&self) -> IndexStats {
+        let stats: Vec<_> = self.shards.iter().map(|shard| shard.stats()).collect();
+        let total_nodes: usize = stats.iter().map(|s| s.total_nodes).sum();
+        let max_layer = stats.iter().map(|s| s.max_layer).max().unwrap_or(-1);
+        let mut layer_sizes = vec![0; (max_layer + 1) as usize];
+        let mut layer_edges = vec![0.0f32; layer_sizes.len()];
+        for s in &stats {
+            for (layer, (&size, &degree)) in s.layer_sizes.iter().zip(&s.avg_out_degree).enumerate() {
+                layer_sizes[layer] += size;
+                layer_edges[layer] += degree * size as f32;
+            }
+        }
+        let avg_out_degree = layer_sizes
+            .iter()
+            .zip(&layer_edges)
+            .map(|(&size, &edges)| if size > 0 { edges / size as f32 } else { 0.0 })
+            .collect();
+        let connections: f32 = stats.iter().map(|s| s.avg_connections * s.total_nodes as f32).sum();
+
+        IndexStats {
+            index_type: IndexType::Hnsw,
+            metric: self.metric(),
+            total_vectors: total_nodes,
+            memory_usage_bytes: stats.iter().map(|s| s.memory_usage_bytes).sum(),
+            details: IndexDetails::Hnsw {
+                max_layer,
+                layer_sizes,
+                avg_connections: if total_nodes > 0 { connections / total_nodes as f32 } else { 0.0 },
+                avg_out_degree,
+                entry_point_depth: stats.iter().map(|s| s.entry_point_depth).max().unwrap_or(-1),
+                diagnostics: None,
+                shards: self.shard_stats(),
+            },
+        }
+    }
+
+    fn index_type(&self) -> IndexType {
+        IndexType::Hnsw
+    }
+
+    fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    // Each shard is a graph of its own, so a healthy sharded index has one component per shard
+    fn connectivity(&self) -> Option<HnswConnectivity> {
+        Some(self.shards.iter().map(|shard| shard.connectivity()).fold(
+            HnswConnectivity { components: 0, unreachable: 0 },
+            |total, c| HnswConnectivity { components: total.components + c.components, unreachable: total.unreachable + c.unreachable },
+        ))
+    }
+
+    fn search_effort(&self) -> Option<(usize, usize)> {
+        self.shards[0].search_effort()
+    }
+
+    fn metric(&self) -> crate::metrics::Metric {
+        self.shards[0].metric()
+    }
+}
@@ -33,7 +33,7 @@ impl IvfConfig {
     // Auto-configure based on dataset size
     pub fn auto(num_vectors: usize) -> Self {
         let num_clusters = (num_vectors as f32).sqrt().max(10.0) as usize;
-        let num_probes = (num_clusters as f32 * 0.1).max(1.0).min(10.0) as usize;
+        let num_probes = (num_clusters as f32 * 0.1).clamp(1.0, 10.0) as usize;
         
         IvfConfig {
             num_clusters,
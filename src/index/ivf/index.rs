@@ -1,22 +1,80 @@
 // IVF (Inverted File Index) implementation
 // Clusters vectors using k-means, then searches only relevant clusters
 // O(√N) search complexity - much faster than brute force for large datasets
+//
+// A metadata filter is pushed down into cluster selection: each cluster's matching documents are
+// counted before it is scanned, clusters without any are skipped without using up a probe, and only
+// the matches are scored. Probing goes on past nprobe while fewer than k matches have been found, so
+// a selective filter no longer scans nprobe full lists only to have everything dropped afterwards.
 
 use uuid::Uuid;
+use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use serde::{Serialize, Deserialize};
 
 use super::config::IvfConfig;
+use super::spill::{spill_path, ListSpill};
 use crate::index::traits::{VectorIndex, IndexStats, IndexDetails, IndexType};
+use crate::index::ColumnView;
+use crate::metadata::Metadata;
+use crate::search::query::Filter;
+use crate::search::utils::best_first;
+
+// Index writes between two choices of which lists stay in memory
+const REBALANCE_WRITES: usize = 1024;
+
+// What filter push-down did, since the index was loaded
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IvfFilterStats {
+    pub searches: u64, // filtered searches
+    pub clusters_skipped: u64, // clusters passed over for having no matching documents
+    pub extra_probes: u64, // clusters probed past nprobe because fewer than k matches had been found
+}
+
+#[derive(Default)]
+struct FilterCounters {
+    searches: AtomicU64,
+    clusters_skipped: AtomicU64,
+    extra_probes: AtomicU64,
+}
 
 // IVF index structure
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct IvfIndex {
     config: IvfConfig,
     centroids: Vec<Vec<f32>>,                    // Cluster centroids
-    inverted_lists: Vec<Vec<Uuid>>,              // vectors[cluster_id] = [vector_ids]
+    inverted_lists: Vec<Vec<Uuid>>,              // vectors[cluster_id] = [vector_ids]; empty while spilled
     vector_to_cluster: HashMap<Uuid, usize>,     // Track which cluster each vector belongs to
     dimensions: usize,
+    #[serde(skip)]
+    spill: Option<ListSpill>,                    // Lists moved to disk under a memory budget
+    #[serde(default)]
+    deterministic: bool,                         // k-means starts from the vectors in id order
+    #[serde(skip)]
+    filter_counters: FilterCounters,
+}
+
+// A copy holds every list in memory (it is what gets saved) and does not spill
+impl Clone for IvfIndex {
+    fn clone(&self) -> Self {
+        let inverted_lists = self.inverted_lists
+            .iter()
+            .enumerate()
+            .map(|(cluster, ids)| self.spill.as_ref().and_then(|s| s.peek(cluster)).unwrap_or_else(|| ids.clone()))
+            .collect();
+        IvfIndex {
+            config: self.config.clone(),
+            centroids: self.centroids.clone(),
+            inverted_lists,
+            vector_to_cluster: self.vector_to_cluster.clone(),
+            dimensions: self.dimensions,
+            spill: None,
+            deterministic: self.deterministic,
+            filter_counters: FilterCounters::default(),
+        }
+    }
 }
 
 impl IvfIndex {
@@ -42,11 +100,48 @@ impl IvfIndex {
             inverted_lists: Vec::new(),
             vector_to_cluster: HashMap::new(),
             dimensions: 0,
+            spill: None,
+            deterministic: false,
+            filter_counters: FilterCounters::default(),
         }
     }
     
+    // Build an index from clusters computed elsewhere (e.g. an offline k-means job).
+    // lists[c] holds the ids assigned to centroids[c]; the caller must have checked that both
+    // have the same length and that every id appears in exactly one list.
+    pub fn from_clusters(mut config: IvfConfig, centroids: Vec<Vec<f32>>, lists: Vec<Vec<Uuid>>) -> Self {
+        let dimensions = centroids.first().map(|c| c.len()).unwrap_or(0);
+        let vector_to_cluster = lists
+            .iter()
+            .enumerate()
+            .flat_map(|(cluster, ids)| ids.iter().map(move |id| (*id, cluster)))
+            .collect();
+        config.num_clusters = centroids.len();
+        IvfIndex {
+            config,
+            centroids,
+            inverted_lists: lists,
+            vector_to_cluster,
+            dimensions,
+            spill: None,
+            deterministic: false,
+            filter_counters: FilterCounters::default(),
+        }
+    }
+
     // Build clusters using k-means
     pub fn build_clusters(&mut self, vectors: &HashMap<Uuid, Vec<f32>>) {
+        let rows: Vec<(Uuid, &[f32])> = vectors.iter().map(|(id, vec)| (*id, vec.as_slice())).collect();
+        self.build_clusters_from_rows(rows);
+    }
+
+    // Build clusters from rows read sequentially out of the vector column
+    pub fn build_clusters_from_column(&mut self, column: ColumnView<'_>) {
+        let rows: Vec<(Uuid, &[f32])> = column.rows().collect();
+        self.build_clusters_from_rows(rows);
+    }
+
+    fn build_clusters_from_rows(&mut self, mut vector_list: Vec<(Uuid, &[f32])>) {
         // building clusters is an offline process that can be done periodically as new vectors are
         // added
         // on high level, it works by:
@@ -54,35 +149,35 @@ impl IvfIndex {
         // 2. Assign each vector to nearest centroid (forming clusters)
         // 3. Update centroids by computing mean of assigned vectors
         // 4. Repeat until convergence or max iterations
-        if vectors.is_empty() {
+        if vector_list.is_empty() {
             return;
         }
         
-        // Get dimensions from first vector
-        if let Some(v) = vectors.values().next() {
-            self.dimensions = v.len();
+        // The initial centroids are the first rows, which come in hash map (or file) order; sorted
+        // by id they are the same on every run
+        if self.deterministic {
+            vector_list.sort_unstable_by_key(|(id, _)| *id);
         }
+
+        // Get dimensions from first vector
+        self.dimensions = vector_list[0].1.len();
         
-        let num_clusters = self.config.num_clusters.min(vectors.len());
+        let num_clusters = self.config.num_clusters.min(vector_list.len());
         
         // Initialize centroids randomly from existing vectors
-        let vector_list: Vec<(Uuid, Vec<f32>)> = vectors.iter()
-            .map(|(id, vec)| (*id, vec.clone()))
-            .collect();
-        
         self.centroids = vector_list.iter()
             .take(num_clusters)
-            .map(|(_, v)| v.clone())
+            .map(|(_, v)| v.to_vec())
             .collect();
         
         // K-means iterations
         for _ in 0..self.config.max_iterations {
             // Assign each vector to nearest centroid
-            let mut clusters: Vec<Vec<(Uuid, Vec<f32>)>> = vec![Vec::new(); num_clusters];
+            let mut clusters: Vec<Vec<&[f32]>> = vec![Vec::new(); num_clusters];
             
-            for (id, vec) in &vector_list {
+            for (_, vec) in &vector_list {
                 let cluster_id = self.find_nearest_centroid(vec);
-                clusters[cluster_id].push((*id, vec.clone()));
+                clusters[cluster_id].push(vec);
             }
             
             // Update centroids
@@ -117,8 +212,131 @@ impl IvfIndex {
             self.inverted_lists[cluster_id].push(*id);
             self.vector_to_cluster.insert(*id, cluster_id);
         }
+
+        // Every list is in memory again; spill afresh under the same budget
+        if let Some(spill) = self.spill.take() {
+            let (budget, path) = (spill.budget, spill.path.clone());
+            drop(spill); // removes the old file before the new one is created in its place
+            self.start_spill((budget, path));
+        }
+    }
+
+    // Ids of one cluster, read from the spill file when the list is on disk
+    fn list(&self, cluster: usize) -> Cow<'_, [Uuid]> {
+        if let Some(spill) = &self.spill {
+            if let Some(ids) = spill.read(cluster) {
+                spill.touch(cluster, false);
+                return Cow::Owned(ids);
+            }
+            spill.touch(cluster, true);
+        }
+        Cow::Borrowed(self.inverted_lists.get(cluster).map(Vec::as_slice).unwrap_or(&[]))
+    }
+
+    // Memory that stays resident whatever the budget: centroids and the id -> cluster map
+    fn fixed_bytes(&self) -> usize {
+        self.centroids.len() * self.dimensions * std::mem::size_of::<f32>() +
+            self.vector_to_cluster.len() * (std::mem::size_of::<Uuid>() + std::mem::size_of::<usize>())
+    }
+
+    fn start_spill(&mut self, (budget, path): (usize, std::path::PathBuf)) {
+        match ListSpill::create(path.clone(), budget, self.inverted_lists.len()) {
+            Ok(spill) => {
+                self.spill = Some(spill);
+                self.rebalance();
+            }
+            Err(e) => tracing::warn!(path=%path.display(), error=%e, "ivf_spill_unavailable"),
+        }
+    }
+
+    // Keep the most probed lists that fit in the budget in memory and the rest on disk
+    fn rebalance(&mut self) {
+        let fixed = self.fixed_bytes();
+        let Some(spill) = self.spill.as_mut() else { return };
+        spill.writes = 0;
+        let clusters = self.inverted_lists.len();
+        spill.resize(clusters);
+        let lists = &mut self.inverted_lists;
+        let len = |spill: &ListSpill, lists: &[Vec<Uuid>], c: usize| spill.spilled_len(c).unwrap_or(lists[c].len());
+
+        // Hottest first; among equally probed lists the smaller ones, so more of them fit
+        let mut order: Vec<usize> = (0..clusters).collect();
+        order.sort_by_key(|&c| (Reverse(spill.heat(c)), len(spill, lists, c)));
+        let mut room = spill.budget.saturating_sub(fixed + spill.delta_bytes());
+        let mut keep = vec![false; clusters];
+        for c in order {
+            let bytes = len(spill, lists, c) * std::mem::size_of::<Uuid>();
+            if bytes <= room {
+                room -= bytes;
+                keep[c] = true;
+            }
+        }
+
+        for (c, keep) in keep.into_iter().enumerate() {
+            if keep {
+                if let Some(ids) = spill.take(c) {
+                    lists[c] = ids;
+                }
+            } else if !spill.is_spilled(c) {
+                match spill.spill(c, &lists[c]) {
+                    Ok(()) => lists[c] = Vec::new(),
+                    Err(e) => tracing::warn!(cluster=c, error=%e, "ivf_spill_write_failed"),
+                }
+            }
+        }
+        spill.cool();
+        if let Err(e) = spill.compact_if_sparse() {
+            tracing::warn!(error=%e, "ivf_spill_compact_failed");
+        }
+    }
+
+    fn after_write(&mut self) {
+        if let Some(spill) = self.spill.as_mut() {
+            spill.writes += 1;
+            if spill.writes >= REBALANCE_WRITES {
+                self.rebalance();
+            }
+        }
     }
     
+    // Ids to score, cluster by cluster, nearest cluster first. Without a filter these are the nprobe
+    // nearest lists; with one, only the matching ids of the nearest clusters that have any.
+    fn probe(&self, query: &[f32], k: usize, nprobe: usize, filter: Option<&Filter>, metadatas: &HashMap<Uuid, Metadata>) -> Vec<Cow<'_, [Uuid]>> {
+        let mut centroid_distances: Vec<(usize, f32)> = self.centroids.iter()
+            .enumerate()
+            .map(|(i, centroid)| (i, self.config.metric.calculate(query, centroid, self.config.mode)))
+            .collect();
+        centroid_distances.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let Some(filter) = filter.filter(|f| !f.is_empty()) else {
+            return centroid_distances.iter().take(nprobe).map(|(cluster, _)| self.list(*cluster)).collect();
+        };
+        let counters = &self.filter_counters;
+        counters.searches.fetch_add(1, Ordering::Relaxed);
+        let mut probed = Vec::new();
+        let mut matched = 0;
+        for (cluster, _) in centroid_distances {
+            if probed.len() >= nprobe && matched >= k {
+                break;
+            }
+            let ids: Vec<Uuid> = self.list(cluster)
+                .iter()
+                .filter(|id| metadatas.get(id).is_some_and(|m| filter.matches(m)))
+                .copied()
+                .collect();
+            if ids.is_empty() {
+                counters.clusters_skipped.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            if probed.len() >= nprobe {
+                counters.extra_probes.fetch_add(1, Ordering::Relaxed);
+            }
+            matched += ids.len();
+            probed.push(Cow::Owned(ids));
+        }
+        probed
+    }
+
     fn find_nearest_centroid(&self, vector: &[f32]) -> usize {
         self.centroids.iter()
             .enumerate()
@@ -131,7 +349,7 @@ impl IvfIndex {
             .unwrap_or(0)
     }
     
-    fn compute_centroid(&self, cluster: &[(Uuid, Vec<f32>)]) -> Vec<f32> {
+    fn compute_centroid(&self, cluster: &[&[f32]]) -> Vec<f32> {
         // Compute mean vector for the cluster by summing all vectors and dividing by count 
         if cluster.is_empty() {
             return vec![0.0; self.dimensions];
@@ -139,7 +357,7 @@ impl IvfIndex {
         
         let mut centroid = vec![0.0; self.dimensions];
         
-        for (_, vec) in cluster {
+        for vec in cluster {
             for (i, &val) in vec.iter().enumerate() {
                 centroid[i] += val;
             }
@@ -167,12 +385,14 @@ impl VectorIndex for IvfIndex {
         
         let cluster_id = self.find_nearest_centroid(vector);
         
-        // Add to inverted list
-        if cluster_id < self.inverted_lists.len() {
-            if !self.inverted_lists[cluster_id].contains(&id) {
+        // Add to inverted list (or to the deltas of a spilled one)
+        if cluster_id < self.inverted_lists.len() && self.vector_to_cluster.get(&id) != Some(&cluster_id) {
+            let spilled = self.spill.as_mut().is_some_and(|s| s.add(cluster_id, id));
+            if !spilled && !self.inverted_lists[cluster_id].contains(&id) {
                 self.inverted_lists[cluster_id].push(id);
-                self.vector_to_cluster.insert(id, cluster_id);
             }
+            self.vector_to_cluster.insert(id, cluster_id);
+            self.after_write();
         }
     }
     
@@ -182,81 +402,86 @@ impl VectorIndex for IvfIndex {
         k: usize,
         vectors: &HashMap<Uuid, Vec<f32>>,
         quality: crate::config::SearchConfig,
-        _filter: Option<&crate::search::query::Filter>,
-        _metadatas: &HashMap<Uuid, crate::metadata::Metadata>,
+        filter: Option<&Filter>,
+        metadatas: &HashMap<Uuid, Metadata>,
     ) -> Vec<Uuid> {
+        let matches = |id: &Uuid| filter.is_none_or(|f| metadatas.get(id).is_some_and(|m| f.matches(m)));
         if self.centroids.is_empty() {
             // No clusters yet - fallback to brute force
             let mut distances: Vec<(Uuid, f32)> = vectors.iter()
+                .filter(|(id, _)| matches(id))
                 .map(|(id, vec)| {
                     let score = self.config.metric.calculate(query, vec, self.config.mode);
                     (*id, score)
                 })
                 .collect();
             
-            distances.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            distances.sort_by(best_first);
             return distances.iter().take(k).map(|(id, _)| *id).collect();
         }
         
-        // Find nearest centroids
-        let mut centroid_distances: Vec<(usize, f32)> = self.centroids.iter()
-            .enumerate()
-            .map(|(i, centroid)| {
-                let score = self.config.metric.calculate(query, centroid, self.config.mode);
-                (i, score)
-            })
-            .collect();
-        
-        centroid_distances.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        
         // Use quality.nprobe if provided, otherwise use configured num_probes
         let nprobe = quality.nprobe.unwrap_or(self.config.num_probes);
         
-        // Search top nprobe clusters
+        // Search the probed clusters
         let mut candidates: Vec<(Uuid, f32)> = Vec::new();
         
-        for (cluster_id, _) in centroid_distances.iter().take(nprobe) {
-            if let Some(vector_ids) = self.inverted_lists.get(*cluster_id) {
-                for id in vector_ids {
-                    if let Some(vec) = vectors.get(id) {
-                        let score = self.config.metric.calculate(query, vec, self.config.mode);
-                        candidates.push((*id, score));
-                    }
+        for ids in self.probe(query, k, nprobe, filter, metadatas) {
+            for id in ids.iter() {
+                if let Some(vec) = vectors.get(id) {
+                    let score = self.config.metric.calculate(query, vec, self.config.mode);
+                    candidates.push((*id, score));
                 }
             }
         }
         
         // Sort and return top k
-        candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        candidates.sort_by(best_first);
         candidates.iter().take(k).map(|(id, _)| *id).collect()
     }
     
     fn remove(&mut self, id: &Uuid) {
         if let Some(cluster_id) = self.vector_to_cluster.remove(id) {
-            if let Some(list) = self.inverted_lists.get_mut(cluster_id) {
+            if self.spill.as_mut().is_some_and(|s| s.remove(cluster_id, id)) {
+                self.after_write();
+            } else if let Some(list) = self.inverted_lists.get_mut(cluster_id) {
                 list.retain(|vid| vid != id);
+                self.after_write();
             }
         }
     }
+
+    // Empty until the clusters are built; searches scan every cached vector until then
+    fn ids(&self) -> Vec<Uuid> {
+        self.vector_to_cluster.keys().copied().collect()
+    }
     
     fn stats(&self) -> IndexStats {
         let vectors_per_cluster = self.inverted_lists.iter()
-            .map(|list| list.len())
+            .enumerate()
+            .map(|(cluster, list)| self.spill.as_ref().and_then(|s| s.spilled_len(cluster)).unwrap_or(list.len()))
             .collect();
         
-        let memory_usage = 
-            self.centroids.len() * self.dimensions * std::mem::size_of::<f32>() +
-            self.vector_to_cluster.len() * (std::mem::size_of::<Uuid>() + std::mem::size_of::<usize>()) +
-            self.inverted_lists.iter().map(|l| l.len() * std::mem::size_of::<Uuid>()).sum::<usize>();
+        // Spilled lists count only for their in-memory deltas
+        let memory_usage = self.fixed_bytes() +
+            self.inverted_lists.iter().map(|l| l.len() * std::mem::size_of::<Uuid>()).sum::<usize>() +
+            self.spill.as_ref().map_or(0, ListSpill::delta_bytes);
         
         IndexStats {
             index_type: IndexType::Ivf,
+            metric: self.config.metric,
             total_vectors: self.vector_to_cluster.len(),
             memory_usage_bytes: memory_usage,
             details: IndexDetails::Ivf {
                 num_clusters: self.centroids.len(),
                 vectors_per_cluster,
                 centroids_computed: !self.centroids.is_empty(),
+                spill: self.spill.as_ref().map(|s| s.stats(self.inverted_lists.len())),
+                filter: IvfFilterStats {
+                    searches: self.filter_counters.searches.load(Ordering::Relaxed),
+                    clusters_skipped: self.filter_counters.clusters_skipped.load(Ordering::Relaxed),
+                    extra_probes: self.filter_counters.extra_probes.load(Ordering::Relaxed),
+                },
             },
         }
     }
@@ -264,4 +489,80 @@ impl VectorIndex for IvfIndex {
     fn index_type(&self) -> IndexType {
         IndexType::Ivf
     }
+
+    fn search_effort(&self) -> Option<(usize, usize)> {
+        Some((self.config.num_probes, self.centroids.len().max(1)))
+    }
+
+    fn pushes_down_filters(&self) -> bool {
+        true
+    }
+
+    fn metric(&self) -> crate::metrics::Metric {
+        self.config.metric
+    }
+
+    fn search_column(
+        &self,
+        query: &[f32],
+        k: usize,
+        column: ColumnView<'_>,
+        quality: crate::config::SearchConfig,
+        filter: Option<&Filter>,
+        metadatas: &HashMap<Uuid, Metadata>,
+    ) -> Option<Vec<Uuid>> {
+        let score = |vec: &[f32]| self.config.metric.calculate(query, vec, self.config.mode);
+        let mut candidates: Vec<(Uuid, f32)> = if self.centroids.is_empty() {
+            // No clusters yet - brute force, in file order
+            column.rows()
+                .filter(|(id, _)| filter.is_none_or(|f| metadatas.get(id).is_some_and(|m| f.matches(m))))
+                .map(|(id, vec)| (id, score(vec)))
+                .collect()
+        } else {
+            let nprobe = quality.nprobe.unwrap_or(self.config.num_probes);
+            self.probe(query, k, nprobe, filter, metadatas)
+                .iter()
+                .flat_map(|ids| {
+                    ids.iter()
+                        .filter_map(|id| column.get(id).map(|vec| (*id, score(vec))))
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        };
+
+        candidates.sort_by(best_first);
+        Some(candidates.iter().take(k).map(|(id, _)| *id).collect())
+    }
+
+    fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    fn set_memory_budget(&mut self, budget: Option<usize>, collection_path: &str) {
+        match (budget, self.spill.as_mut()) {
+            (Some(budget), Some(spill)) => {
+                spill.budget = budget;
+                self.rebalance();
+            }
+            (Some(budget), None) => self.start_spill((budget, spill_path(collection_path))),
+            (None, Some(_)) => {
+                // Back to everything in memory; dropping the spill removes its file
+                let spill = self.spill.take().unwrap();
+                for (cluster, list) in self.inverted_lists.iter_mut().enumerate() {
+                    if let Some(ids) = spill.peek(cluster) {
+                        *list = ids;
+                    }
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn build_from_column(&mut self, column: ColumnView<'_>) -> bool {
+        // Online inserts only start clustering once num_clusters vectors exist; keep that rule
+        if column.len() >= self.config.num_clusters {
+            self.build_clusters_from_column(column);
+        }
+        true
+    }
 }
@@ -2,6 +2,8 @@
 
 mod config;
 mod index;
+mod spill;
 
 pub use config::IvfConfig;
-pub use index::IvfIndex;
+pub use index::{IvfFilterStats, IvfIndex};
+pub use spill::{spill_path, IvfSpillStats};
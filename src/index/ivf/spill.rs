@@ -0,0 +1,269 @@
+// Inverted lists kept on disk instead of in memory
+// With an index memory budget, the lists of the coldest clusters are written to a file next to the
+// collection and read back when a search probes them. Writes to a spilled list are kept as small
+// in-memory deltas on top of the copy on disk. The file is only a cache of the index: the saved
+// index holds every list, so it is recreated empty whenever the budget is applied.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const ID_BYTES: usize = 16;
+
+// Spill file of the collection at `collection_path`
+pub fn spill_path(collection_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.ivfspill", collection_path))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IvfSpillStats {
+    pub budget_bytes: usize,
+    pub resident_clusters: usize,
+    pub spilled_clusters: usize,
+    pub spilled_vectors: usize,
+    pub disk_bytes: u64, // spill file size, including space of lists rewritten since
+    pub hits: u64, // probes of a resident list
+    pub misses: u64, // probes that read a list from disk
+}
+
+struct SpilledList {
+    offset: u64,
+    len: usize, // ids on disk
+    added: Vec<Uuid>, // inserted since the list was written
+    removed: HashSet<Uuid>, // removed since the list was written
+}
+
+impl SpilledList {
+    fn live_len(&self) -> usize {
+        self.len - self.removed.len() + self.added.len()
+    }
+}
+
+pub struct ListSpill {
+    pub(super) path: PathBuf,
+    file: File,
+    pub(super) budget: usize,
+    lists: HashMap<usize, SpilledList>,
+    end: u64, // where the next list is written
+    live: u64, // bytes of the file still referenced
+    heat: Vec<AtomicU64>, // probes per cluster, halved at every rebalance
+    hits: AtomicU64,
+    misses: AtomicU64,
+    pub(super) writes: usize, // index writes since the last rebalance
+}
+
+impl ListSpill {
+    pub fn create(path: PathBuf, budget: usize, clusters: usize) -> std::io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path)?;
+        Ok(Self {
+            path,
+            file,
+            budget,
+            lists: HashMap::new(),
+            end: 0,
+            live: 0,
+            heat: (0..clusters).map(|_| AtomicU64::new(0)).collect(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            writes: 0,
+        })
+    }
+
+    pub fn is_spilled(&self, cluster: usize) -> bool {
+        self.lists.contains_key(&cluster)
+    }
+
+    pub fn spilled_len(&self, cluster: usize) -> Option<usize> {
+        self.lists.get(&cluster).map(SpilledList::live_len)
+    }
+
+    // A search probed `cluster`; resident lists count as hits
+    pub fn touch(&self, cluster: usize, resident: bool) {
+        if let Some(heat) = self.heat.get(cluster) {
+            heat.fetch_add(1, Ordering::Relaxed);
+        }
+        if resident {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn heat(&self, cluster: usize) -> u64 {
+        self.heat.get(cluster).map_or(0, |h| h.load(Ordering::Relaxed))
+    }
+
+    // Halve every probe count so the resident set follows recent searches
+    pub fn cool(&self) {
+        for heat in &self.heat {
+            heat.store(heat.load(Ordering::Relaxed) / 2, Ordering::Relaxed);
+        }
+    }
+
+    pub fn resize(&mut self, clusters: usize) {
+        self.heat.resize_with(clusters, || AtomicU64::new(0));
+    }
+
+    // The ids of a spilled list, read from disk for a search; None when the list is resident
+    pub fn read(&self, cluster: usize) -> Option<Vec<Uuid>> {
+        let ids = self.peek(cluster)?;
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        Some(ids)
+    }
+
+    // Same as `read`, without counting a miss
+    pub fn peek(&self, cluster: usize) -> Option<Vec<Uuid>> {
+        self.lists.get(&cluster).map(|list| self.load(cluster, list))
+    }
+
+    fn load(&self, cluster: usize, list: &SpilledList) -> Vec<Uuid> {
+        let mut buf = vec![0u8; list.len * ID_BYTES];
+        let mut ids: Vec<Uuid> = match read_at(&self.file, list.offset, &mut buf) {
+            Ok(()) => buf.chunks_exact(ID_BYTES).map(|b| Uuid::from_bytes(b.try_into().unwrap())).collect(),
+            Err(e) => {
+                tracing::warn!(path=%self.path.display(), cluster, error=%e, "ivf_spill_read_failed");
+                Vec::new()
+            }
+        };
+        if !list.removed.is_empty() {
+            ids.retain(|id| !list.removed.contains(id));
+        }
+        ids.extend_from_slice(&list.added);
+        ids
+    }
+
+    // Write a list out; the caller drops its resident copy
+    pub fn spill(&mut self, cluster: usize, ids: &[Uuid]) -> std::io::Result<()> {
+        let bytes = encode(ids);
+        write_at(&self.file, self.end, &bytes)?;
+        let list = SpilledList { offset: self.end, len: ids.len(), added: Vec::new(), removed: HashSet::new() };
+        self.end += bytes.len() as u64;
+        self.live += bytes.len() as u64;
+        self.lists.insert(cluster, list);
+        Ok(())
+    }
+
+    // Bring a list back into memory, leaving its space in the file unused
+    pub fn take(&mut self, cluster: usize) -> Option<Vec<Uuid>> {
+        let list = self.lists.remove(&cluster)?;
+        self.live -= (list.len * ID_BYTES) as u64;
+        Some(self.load(cluster, &list))
+    }
+
+    // Record an insert into a spilled list; false when the list is resident
+    pub fn add(&mut self, cluster: usize, id: Uuid) -> bool {
+        let Some(list) = self.lists.get_mut(&cluster) else { return false };
+        if !list.removed.remove(&id) {
+            list.added.push(id);
+        }
+        true
+    }
+
+    // Record a removal from a spilled list; false when the list is resident
+    pub fn remove(&mut self, cluster: usize, id: &Uuid) -> bool {
+        let Some(list) = self.lists.get_mut(&cluster) else { return false };
+        match list.added.iter().position(|a| a == id) {
+            Some(at) => {
+                list.added.swap_remove(at);
+            }
+            None => {
+                list.removed.insert(*id);
+            }
+        }
+        true
+    }
+
+    // Memory held by the deltas of spilled lists
+    pub fn delta_bytes(&self) -> usize {
+        self.lists.values().map(|l| (l.added.len() + l.removed.len()) * ID_BYTES).sum()
+    }
+
+    // Rewrite the file once more than half of it is unused, folding the deltas in. The new file is
+    // written next to the old one and renamed over it, so a failure leaves the old one in use.
+    pub fn compact_if_sparse(&mut self) -> std::io::Result<()> {
+        let unused = self.end - self.live;
+        if unused <= self.live && self.delta_bytes() as u64 <= self.live / 2 {
+            return Ok(());
+        }
+        let fresh_path = self.path.with_extension("ivfspill.tmp");
+        let written = (|| {
+            let fresh = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&fresh_path)?;
+            let mut lists = HashMap::with_capacity(self.lists.len());
+            let mut end = 0u64;
+            for (&cluster, list) in &self.lists {
+                let ids = self.load(cluster, list);
+                let bytes = encode(&ids);
+                write_at(&fresh, end, &bytes)?;
+                lists.insert(cluster, SpilledList { offset: end, len: ids.len(), added: Vec::new(), removed: HashSet::new() });
+                end += bytes.len() as u64;
+            }
+            std::fs::rename(&fresh_path, &self.path)?;
+            Ok((fresh, lists, end))
+        })();
+        match written {
+            Ok((file, lists, end)) => {
+                self.file = file;
+                self.lists = lists;
+                self.end = end;
+                self.live = end;
+                Ok(())
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&fresh_path);
+                Err(e)
+            }
+        }
+    }
+
+    pub fn stats(&self, clusters: usize) -> IvfSpillStats {
+        IvfSpillStats {
+            budget_bytes: self.budget,
+            resident_clusters: clusters.saturating_sub(self.lists.len()),
+            spilled_clusters: self.lists.len(),
+            spilled_vectors: self.lists.values().map(SpilledList::live_len).sum(),
+            disk_bytes: self.end,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for ListSpill {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn encode(ids: &[Uuid]) -> Vec<u8> {
+    ids.iter().flat_map(|id| *id.as_bytes()).collect()
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, offset: u64, buf: &mut [u8]) -> std::io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buf, offset)
+}
+
+#[cfg(not(unix))]
+fn read_at(file: &File, offset: u64, buf: &mut [u8]) -> std::io::Result<()> {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut file = file.try_clone()?;
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buf)
+}
+
+#[cfg(unix)]
+fn write_at(file: &File, offset: u64, buf: &[u8]) -> std::io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.write_all_at(buf, offset)
+}
+
+#[cfg(not(unix))]
+fn write_at(file: &File, offset: u64, buf: &[u8]) -> std::io::Result<()> {
+    use std::io::{Seek, SeekFrom, Write};
+    let mut file = file.try_clone()?;
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(buf)
+}
@@ -1,17 +1,19 @@
 // Index module - unified interface for multiple indexing strategies
-// Supports: HNSW, Flat, IVF
+// Supports: HNSW (single or sharded), Flat, IVF
 
 mod traits;
 mod selector;
+mod column;
 pub mod hnsw;
 pub mod flat;
 pub mod ivf;
 
 // Re-export trait and types
-pub use traits::{VectorIndex, IndexStats, IndexDetails, IndexType, SerializableIndex};
+pub use traits::{VectorIndex, IndexStats, IndexDetails, IndexType, SerializableIndex, HnswDiagnostics};
 pub use selector::IndexConfig;
+pub use column::ColumnView;
 
 // Re-export index implementations
-pub use hnsw::{HnswIndex, HnswConfig, HnswStats};
+pub use hnsw::{HnswIndex, HnswConfig, HnswStats, HnswConnectivity, HnswShardStats, PackedHnsw, ShardedHnsw};
 pub use flat::{FlatIndex, FlatConfig};
-pub use ivf::{IvfIndex, IvfConfig};
+pub use ivf::{IvfIndex, IvfConfig, IvfFilterStats, IvfSpillStats, spill_path};
@@ -7,10 +7,10 @@ use crate::config::ExecutionMode;
 use crate::config::SearchConfig;
 
 use super::traits::{VectorIndex, IndexType};
-use super::{FlatIndex, FlatConfig, HnswIndex, HnswConfig, IvfIndex, IvfConfig};
+use super::{FlatIndex, FlatConfig, HnswIndex, HnswConfig, IvfIndex, IvfConfig, ShardedHnsw};
 
 // Unified index configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum IndexConfig {
     // Auto-select based on size (default)
@@ -42,6 +42,8 @@ pub enum IndexConfig {
         mode: ExecutionMode,
         #[serde(default)]
         search: SearchConfig,
+        #[serde(default = "default_shards")]
+        shards: usize, // Graphs searched in parallel and merged by score; 1 keeps a single graph
     },
     // IVF index
     Ivf {
@@ -56,6 +58,10 @@ pub enum IndexConfig {
     },
 }
 
+fn default_shards() -> usize {
+    1
+}
+
 impl Default for IndexConfig {
     fn default() -> Self {
         IndexConfig::Auto { 
@@ -97,9 +103,9 @@ impl IndexConfig {
                 Box::new(FlatIndex::new(FlatConfig { metric, mode }))
             }
             IndexType::Hnsw => {
-                let config = match self {
-                    IndexConfig::Hnsw { m, m_max, ef_construction, ef_search, ml, metric, mode, .. } => {
-                        HnswConfig {
+                let (config, shards) = match self {
+                    IndexConfig::Hnsw { m, m_max, ef_construction, ef_search, ml, metric, mode, shards, .. } => {
+                        (HnswConfig {
                             m: *m,
                             m_max: *m_max,
                             ef_construction: *ef_construction,
@@ -107,12 +113,12 @@ impl IndexConfig {
                             ml: *ml,
                             metric: *metric,
                             mode: *mode,
-                        }
+                        }, *shards)
                     }
                     _ => {
                         // For auto-selection, we use default HNSW parameters but apply the metric and mode from the config. The ef_search parameter defaults to the same value as ef_construction if not explicitly set, allowing users to configure search quality separately if desired.
                         let (metric, mode) = self.get_metric_and_simd();
-                        HnswConfig {
+                        (HnswConfig {
                             m: 16,
                             m_max: 32,
                             ef_construction: 200,
@@ -120,10 +126,14 @@ impl IndexConfig {
                             ml: 1.0 / (16.0_f32).ln(),
                             metric,
                             mode,
-                        }
+                        }, 1)
                     }
                 };
-                Box::new(HnswIndex::new(config))
+                if shards > 1 {
+                    Box::new(ShardedHnsw::new(config, shards))
+                } else {
+                    Box::new(HnswIndex::new(config))
+                }
             }
             IndexType::Ivf => {
                 let config = match self {
@@ -169,12 +179,23 @@ impl IndexConfig {
         }
     }
 
+    // Exact index with this config's metric, e.g. a stand-in while the configured one is rebuilt
+    pub fn create_flat_index(&self) -> Box<dyn VectorIndex> {
+        let (metric, mode) = self.get_metric_and_simd();
+        Box::new(FlatIndex::new(FlatConfig { metric, mode }))
+    }
+
+    // Distance metric the index is built with
+    pub fn metric(&self) -> Metric {
+        self.get_metric_and_simd().0
+    }
+
     pub fn search_config(&self) -> SearchConfig {
         match self {
-            IndexConfig::Auto { search, .. } => search.clone(),
-            IndexConfig::Flat { search, .. } => search.clone(),
-            IndexConfig::Hnsw { search, .. } => search.clone(),
-            IndexConfig::Ivf { search, .. } => search.clone(),
+            IndexConfig::Auto { search, .. } => *search,
+            IndexConfig::Flat { search, .. } => *search,
+            IndexConfig::Hnsw { search, .. } => *search,
+            IndexConfig::Ivf { search, .. } => *search,
         }
     }
 }
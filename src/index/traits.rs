@@ -48,18 +48,88 @@ pub trait VectorIndex: Send + Sync {
     
     // Remove a vector from the index
     fn remove(&mut self, id: &Uuid);
+
+    // Ids of the vectors the index currently holds (removed HNSW nodes excluded)
+    fn ids(&self) -> Vec<Uuid>;
     
     // Get index statistics
     fn stats(&self) -> IndexStats;
     
     // Get the index type name
     fn index_type(&self) -> IndexType;
+
+    // Metric the index was built with; its candidate order is only meaningful for this metric
+    fn metric(&self) -> crate::metrics::Metric;
+
+    // Search reading vectors from the column file instead of the vector cache. Indexes that do not
+    // scan (HNSW walks its graph) return None and the caller falls back to `search`.
+    fn search_column(
+        &self,
+        _query: &[f32],
+        _k: usize,
+        _column: crate::index::ColumnView<'_>,
+        _quality: SearchConfig,
+        _filter: Option<&crate::search::query::Filter>,
+        _metadatas: &HashMap<Uuid, crate::metadata::Metadata>,
+    ) -> Option<Vec<Uuid>> {
+        None
+    }
+
+    // Whether a filtered search returns only matching documents, k of them when it can find them
+    // (IVF applies the filter while picking clusters). The caller then has no need to overfetch.
+    fn pushes_down_filters(&self) -> bool {
+        false
+    }
+
+    // Cap the memory the index keeps resident at `budget` bytes, moving what does not fit to a file
+    // next to the collection at `collection_path`; None keeps everything in memory. Only IVF spills
+    // (its inverted lists); the other indexes ignore it.
+    fn set_memory_budget(&mut self, _budget: Option<usize>, _collection_path: &str) {}
+
+    // Take the random choices of a build (HNSW layers, IVF's initial centroids) from the vector ids,
+    // so the same vectors give the same index on every run. Flat has none and ignores it.
+    fn set_deterministic(&mut self, _deterministic: bool) {}
+
+    // Build the index in one pass over the column (e.g. train IVF centroids) instead of one insert
+    // per vector. Returns false when the index has no bulk path; the caller then inserts one by one.
+    fn build_from_column(&mut self, _column: crate::index::ColumnView<'_>) -> bool {
+        false
+    }
+
+    // Graph connectivity, for indexes that are a graph (HNSW); None for the others
+    fn connectivity(&self) -> Option<crate::index::HnswConnectivity> {
+        None
+    }
+
+    // Candidates for a whole batch of queries scored together (a flat index under ExecutionMode::Gpu),
+    // `candidates` per query, best first. None from indexes that search query by query.
+    fn search_batch(
+        &self,
+        _queries: &[Vec<f32>],
+        _candidates: usize,
+        _vectors: &HashMap<Uuid, Vec<f32>>,
+    ) -> Option<Vec<Vec<Uuid>>> {
+        None
+    }
+
+    // Graphs the index is split into and searched in parallel (sharded HNSW); 1 for the others
+    fn shards(&self) -> usize {
+        1
+    }
+
+    // The search-time effort knob, ef (HNSW) or nprobe (IVF), as (configured default, largest useful
+    // value); None for exhaustive indexes
+    fn search_effort(&self) -> Option<(usize, usize)> {
+        None
+    }
 }
 
 // Statistics about an index
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexStats {
     pub index_type: IndexType, // Type of index (Flat, HNSW, IVF)
+    #[serde(default)]
+    pub metric: crate::metrics::Metric, // Metric the index was built with
     pub total_vectors: usize, // Total number of vectors indexed
     pub memory_usage_bytes: usize, // Approximate memory usage of the index in bytes
     pub details: IndexDetails, // Index-specific details (e.g. HNSW layer sizes, IVF cluster counts)
@@ -74,14 +144,36 @@ pub enum IndexDetails {
         max_layer: isize, // Maximum layer in the HNSW graph
         layer_sizes: Vec<usize>, // Number of nodes in each layer
         avg_connections: f32, // Average number of connections per node
+        #[serde(default)]
+        avg_out_degree: Vec<f32>, // Mean neighbours per node on each layer
+        #[serde(default)]
+        entry_point_depth: isize, // Top layer of the entry point; below max_layer after it was re-picked on a delete
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        diagnostics: Option<HnswDiagnostics>, // Connectivity and sampled recall, only when asked for
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        shards: Vec<crate::index::HnswShardStats>, // Each graph of a sharded index; empty for a single graph
     },
     Ivf {
         num_clusters: usize, // Number of clusters in the IVF index
         vectors_per_cluster: Vec<usize>, // Number of vectors assigned to each cluster
         centroids_computed: bool, // Whether centroids have been computed for the clusters
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        spill: Option<crate::index::IvfSpillStats>, // Lists on disk and probe hits/misses, with an index memory budget
+        #[serde(default)]
+        filter: crate::index::IvfFilterStats, // Clusters filtered searches skipped or probed past nprobe
     },
 }
 
+// Graph quality checks of an HNSW index, from `Collection::index_diagnostics`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswDiagnostics {
+    pub components: usize, // Weakly connected pieces of the graph holding live nodes (1 when healthy)
+    pub unreachable: usize, // Live nodes no path from the entry point leads to
+    pub recall_estimate: Option<f32>, // Recall@k of the search path against brute force; None when empty
+    pub recall_k: usize,
+    pub recall_queries: usize, // Stored vectors sampled as queries
+}
+
 // Supported index types
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IndexType {
@@ -110,6 +202,10 @@ pub enum SerializableIndex {
     Flat(crate::index::flat::FlatIndex),
     Hnsw(crate::index::hnsw::HnswIndex),
     Ivf(crate::index::ivf::IvfIndex),
+    // HNSW graph of a sealed collection, see hnsw/packed.rs
+    PackedHnsw(crate::index::hnsw::PackedHnsw),
+    // HNSW split into graphs searched in parallel, see hnsw/sharded.rs
+    ShardedHnsw(crate::index::hnsw::ShardedHnsw),
 }
 // Implement a method to convert the SerializableIndex back into a trait object for use in the system. This allows us to persist the index state and later restore it while still using the unified VectorIndex interface for operations.
 impl SerializableIndex {
@@ -118,6 +214,8 @@ impl SerializableIndex {
             SerializableIndex::Flat(idx) => Box::new(idx),
             SerializableIndex::Hnsw(idx) => Box::new(idx),
             SerializableIndex::Ivf(idx) => Box::new(idx),
+            SerializableIndex::PackedHnsw(packed) => Box::new(packed.unpack()),
+            SerializableIndex::ShardedHnsw(idx) => Box::new(idx),
         }
     }
 }
@@ -0,0 +1,88 @@
+// Source positions recorded against the collection's WAL sequence.
+// After every batch the worker appends (position, wal_seq): "messages up to `position` are in the
+// collection as of WAL seq `wal_seq`". On startup it resumes after the newest entry whose wal_seq the
+// collection has actually reached. If the collection went back in time (snapshot restore, deleted and
+// recreated), the source is rewound to the matching position and the lost messages are consumed again.
+//
+// Stored as JSON in data_dir/ingest/<source>.json, written to a temp file and renamed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+// Entries kept per source; older ones only matter for rewinds far into the past
+const MAX_ENTRIES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointEntry {
+    pub position: u64,
+    pub wal_seq: u64,
+    pub at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IngestCheckpoint {
+    pub source: String,
+    pub collection: String,
+    pub entries: Vec<CheckpointEntry>, // oldest first
+}
+
+impl IngestCheckpoint {
+    pub fn path(data_dir: &str, source: &str) -> PathBuf {
+        Path::new(data_dir).join("ingest").join(format!("{source}.json"))
+    }
+
+    // The stored checkpoint, or an empty one. A checkpoint left by the same source name for another
+    // collection does not apply.
+    pub fn load(path: &Path, source: &str, collection: &str) -> Result<Self> {
+        let empty = Self { source: source.to_string(), collection: collection.to_string(), entries: Vec::new() };
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(empty),
+            Err(e) => return Err(e.into()),
+        };
+        let checkpoint: Self = serde_json::from_slice(&data)?;
+        if checkpoint.collection != collection {
+            return Ok(empty);
+        }
+        Ok(checkpoint)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, position: u64, wal_seq: u64) {
+        let at = crate::testing::clock::now_secs();
+        // A rewind invalidates everything recorded after the point it went back to
+        self.entries.retain(|e| e.position < position && e.wal_seq <= wal_seq);
+        self.entries.push(CheckpointEntry { position, wal_seq, at });
+        if self.entries.len() > MAX_ENTRIES {
+            let excess = self.entries.len() - MAX_ENTRIES;
+            self.entries.drain(..excess);
+        }
+    }
+
+    // Newest entry the collection's current WAL head covers
+    pub fn resume_entry(&self, head_seq: u64) -> Option<&CheckpointEntry> {
+        self.entries.iter().rev().find(|e| e.wal_seq <= head_seq)
+    }
+
+    // Position to continue after, given the collection's current WAL head
+    pub fn resume_position(&self, head_seq: u64) -> Option<u64> {
+        self.resume_entry(head_seq).map(|e| e.position)
+    }
+
+    pub fn last(&self) -> Option<&CheckpointEntry> {
+        self.entries.last()
+    }
+}
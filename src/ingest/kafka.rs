@@ -0,0 +1,95 @@
+// Kafka consumer for one topic partition, on rdkafka (librdkafka).
+// The partition is assigned directly rather than through a consumer group, and nothing is committed
+// to the broker: the position lives in the ingest checkpoint, so `seek` re-assigns the partition at
+// the offset after it. librdkafka finds the partition leader and follows leader changes, decompresses
+// batches, checks their CRCs and, with `isolation.level=read_committed`, leaves out aborted
+// transactions and control records.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::FutureExt;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::{ClientConfig, Message, Offset, TopicPartitionList};
+use tokio::time::Instant;
+
+use crate::config::IngestStart;
+use crate::error::{PiramidError, Result};
+use super::{IngestSource, SourceMessage};
+
+pub struct KafkaSource {
+    consumer: StreamConsumer,
+    topic: String,
+    partition: i32,
+    start: IngestStart,
+}
+
+impl KafkaSource {
+    pub async fn connect(broker: &str, topic: &str, partition: i32, start: IngestStart) -> Result<Self> {
+        let consumer = ClientConfig::new()
+            .set("bootstrap.servers", broker)
+            .set("group.id", "piramid-ingest")
+            .set("enable.auto.commit", "false")
+            .set("enable.auto.offset.store", "false")
+            .set("isolation.level", "read_committed")
+            .set("check.crcs", "true")
+            // An offset that fell out of the retained range restarts from the configured start
+            .set("auto.offset.reset", match start {
+                IngestStart::Earliest => "earliest",
+                IngestStart::Latest => "latest",
+            })
+            .create()
+            .map_err(kafka)?;
+        let mut source = Self { consumer, topic: topic.to_string(), partition, start };
+        source.seek(None).await?;
+        Ok(source)
+    }
+}
+
+#[async_trait]
+impl IngestSource for KafkaSource {
+    // Waits for the first message, then takes only those already fetched
+    async fn fetch(&mut self, max: usize, wait: Duration) -> Result<Vec<SourceMessage>> {
+        let deadline = Instant::now() + wait;
+        let mut messages = Vec::new();
+        while messages.len() < max {
+            let received = if messages.is_empty() {
+                match tokio::time::timeout_at(deadline, self.consumer.recv()).await {
+                    Ok(received) => received,
+                    Err(_) => break,
+                }
+            } else {
+                match self.consumer.recv().now_or_never() {
+                    Some(received) => received,
+                    None => break,
+                }
+            };
+            let message = received.map_err(kafka)?;
+            messages.push(SourceMessage {
+                position: message.offset() as u64,
+                payload: message.payload().unwrap_or_default().to_vec(),
+            });
+        }
+        Ok(messages)
+    }
+
+    // The offset is recorded in the ingest checkpoint; nothing is stored on the broker
+    async fn commit(&mut self, _position: u64) -> Result<()> {
+        Ok(())
+    }
+
+    async fn seek(&mut self, position: Option<u64>) -> Result<()> {
+        let offset = match (position, self.start) {
+            (Some(position), _) => Offset::Offset(position as i64 + 1),
+            (None, IngestStart::Earliest) => Offset::Beginning,
+            (None, IngestStart::Latest) => Offset::End,
+        };
+        let mut assignment = TopicPartitionList::new();
+        assignment.add_partition_offset(&self.topic, self.partition, offset).map_err(kafka)?;
+        self.consumer.assign(&assignment).map_err(kafka)
+    }
+}
+
+fn kafka(e: rdkafka::error::KafkaError) -> PiramidError {
+    PiramidError::Other(format!("Kafka: {}", e))
+}
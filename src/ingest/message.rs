@@ -0,0 +1,52 @@
+// Message format consumed by the ingestion sources:
+//
+//   {"id": "doc-1", "text": "...", "vector": [0.1, ...], "metadata": {"lang": "en"}}
+//
+// `id` is optional and is either a UUID or a client id (stored as the document's external id), as in
+// the upsert endpoint. Messages without a vector are embedded from their text when an embedder is
+// configured.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::error::{Result, ServerError};
+use crate::server::helpers::client_metadata;
+use crate::storage::Document;
+use crate::validation;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IngestMessage {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub vector: Option<Vec<f32>>,
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl IngestMessage {
+    pub fn parse(payload: &[u8]) -> Result<Self> {
+        serde_json::from_slice(payload)
+            .map_err(|e| ServerError::InvalidRequest(format!("Malformed ingest message: {e}")).into())
+    }
+
+    // Document to upsert; the vector is the message's own or the one embedded from its text
+    pub fn into_document(self, vector: Vec<f32>) -> Result<Document> {
+        validation::validate_vector(&vector)?;
+        let mut entry = Document::with_metadata(vector, self.text, client_metadata(self.metadata)?);
+        if let Some(id) = self.id {
+            match Uuid::parse_str(&id) {
+                Ok(uuid) => entry.id = uuid,
+                Err(_) => {
+                    validation::validate_external_id(&id)?;
+                    entry = entry.with_external_id(id);
+                }
+            }
+        }
+        Ok(entry)
+    }
+}
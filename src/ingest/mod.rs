@@ -0,0 +1,72 @@
+// Ingestion module - consume documents from message brokers into collections
+// - message.rs: the `{id, text, vector?, metadata}` JSON message and its conversion to a Document
+// - checkpoint.rs: per-source positions recorded against the collection's WAL sequence
+// - kafka.rs: Kafka partition consumer on rdkafka (`kafka` feature)
+// - nats.rs: NATS JetStream pull consumer on async-nats (`nats` feature)
+// - worker.rs: the fetch -> upsert -> checkpoint -> commit loop and its status
+//
+// A source whose client is not built in is refused when the config is validated. Kafka
+// consumer-group offsets are not used: the position lives in the checkpoint file next to the
+// collection, so it can be tied to the WAL sequence the batch ended at.
+
+pub mod message;
+pub mod checkpoint;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "nats")]
+pub mod nats;
+pub mod worker;
+
+pub use message::IngestMessage;
+pub use checkpoint::{IngestCheckpoint, CheckpointEntry};
+#[cfg(feature = "kafka")]
+pub use kafka::KafkaSource;
+#[cfg(feature = "nats")]
+pub use nats::NatsSource;
+pub use worker::{IngestWorker, IngestStatus, spawn_ingestion};
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::config::IngestSourceConfig;
+#[cfg(any(feature = "kafka", feature = "nats"))]
+use crate::config::IngestSourceKind;
+use crate::error::{PiramidError, Result};
+
+// One message as delivered by a source
+#[derive(Debug, Clone)]
+pub struct SourceMessage {
+    // Monotonic position within the source (Kafka offset, JetStream stream sequence)
+    pub position: u64,
+    pub payload: Vec<u8>,
+}
+
+#[async_trait]
+pub trait IngestSource: Send {
+    // Up to `max` messages, waiting at most `wait` when none are available. An empty batch is normal.
+    async fn fetch(&mut self, max: usize, wait: Duration) -> Result<Vec<SourceMessage>>;
+
+    // Every message up to and including `position` is durable in the collection
+    async fn commit(&mut self, position: u64) -> Result<()>;
+
+    // Continue after `position`, or from the configured start when there is none. Called at startup
+    // and after a failed batch, so uncommitted messages are delivered again.
+    async fn seek(&mut self, position: Option<u64>) -> Result<()>;
+}
+
+// Connect the client for a configured source
+pub async fn connect_source(config: &IngestSourceConfig) -> Result<Box<dyn IngestSource>> {
+    match &config.source {
+        #[cfg(feature = "kafka")]
+        IngestSourceKind::Kafka { broker, topic, partition, start } => {
+            Ok(Box::new(KafkaSource::connect(broker, topic, *partition, *start).await?))
+        }
+        #[cfg(feature = "nats")]
+        IngestSourceKind::Nats { url, stream, consumer } => {
+            Ok(Box::new(NatsSource::connect(url, stream, consumer).await?))
+        }
+        #[allow(unreachable_patterns)]
+        kind => Err(PiramidError::Other(format!("{} ingestion needs a build with the `{}` feature", kind.name(), kind.name()))),
+    }
+}
@@ -0,0 +1,75 @@
+// NATS JetStream pull consumer, on async-nats. The durable consumer must already exist; each fetch
+// is one pull request for up to `max` messages that expires after `wait`. The position of a message
+// is its stream sequence.
+//
+// Committing acks every delivered message up to the position. Messages a failed batch left unacked
+// are nak'ed on seek so JetStream redelivers them right away; messages at or before the committed
+// position that come back anyway (an ack lost in a crash) are acked and dropped. JetStream cannot
+// re-deliver acked messages, so rewinding to an earlier checkpoint only moves the committed position.
+
+use std::time::Duration;
+
+use async_nats::jetstream::{self, consumer::PullConsumer, AckKind};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+
+use crate::error::{PiramidError, Result};
+use super::{IngestSource, SourceMessage};
+
+pub struct NatsSource {
+    client: async_nats::Client,
+    consumer: PullConsumer,
+    pending: Vec<(u64, jetstream::Message)>, // delivered, not yet acked
+    committed: Option<u64>,
+}
+
+impl NatsSource {
+    pub async fn connect(url: &str, stream: &str, consumer: &str) -> Result<Self> {
+        let client = async_nats::ConnectOptions::new().name("piramid-ingest").connect(url).await.map_err(nats)?;
+        let consumer = jetstream::new(client.clone()).get_consumer_from_stream(consumer, stream).await.map_err(nats)?;
+        Ok(Self { client, consumer, pending: Vec::new(), committed: None })
+    }
+}
+
+#[async_trait]
+impl IngestSource for NatsSource {
+    async fn fetch(&mut self, max: usize, wait: Duration) -> Result<Vec<SourceMessage>> {
+        let mut batch = self.consumer.batch().max_messages(max).expires(wait).messages().await.map_err(nats)?;
+        let mut messages = Vec::new();
+        while let Some(message) = batch.next().await {
+            let message = message.map_err(nats)?;
+            let seq = message.info().map_err(nats)?.stream_sequence;
+            if self.committed.is_some_and(|c| seq <= c) {
+                message.ack().await.map_err(nats)?;
+                continue;
+            }
+            messages.push(SourceMessage { position: seq, payload: message.payload.to_vec() });
+            self.pending.push((seq, message));
+        }
+        Ok(messages)
+    }
+
+    async fn commit(&mut self, position: u64) -> Result<()> {
+        let (acked, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending).into_iter().partition(|(seq, _)| *seq <= position);
+        self.pending = kept;
+        for (_, message) in acked {
+            message.ack().await.map_err(nats)?;
+        }
+        self.client.flush().await.map_err(nats)?;
+        self.committed = Some(position);
+        Ok(())
+    }
+
+    async fn seek(&mut self, position: Option<u64>) -> Result<()> {
+        for (_, message) in std::mem::take(&mut self.pending) {
+            message.ack_with(AckKind::Nak(None)).await.map_err(nats)?;
+        }
+        self.client.flush().await.map_err(nats)?;
+        self.committed = position;
+        Ok(())
+    }
+}
+
+fn nats(e: impl std::fmt::Display) -> PiramidError {
+    PiramidError::Other(format!("NATS: {}", e))
+}
@@ -0,0 +1,282 @@
+// Ingestion worker: one per configured source.
+// Each batch is fetched, parsed, embedded where a message has no vector, and upserted under one
+// collection write lock. Then the source position is recorded in the checkpoint against the WAL
+// sequence the collection reached, and only after that is the source committed (NATS ack). A failed
+// batch seeks the source back to the last committed position, so every message is written at least
+// once; messages with an id are upserts, so replays do not create duplicates.
+//
+// Malformed messages and messages the collection rejects (wrong dimensions, bad ids) are skipped
+// and counted, since retrying them can never succeed. Anything else fails the whole batch, which is
+// retried with backoff.
+
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::config::IngestSourceConfig;
+use crate::error::{Result, ServerError};
+use crate::server::metrics::record_lock_write;
+use crate::server::state::SharedState;
+use crate::server::usage::UsageScope;
+use crate::testing::clock::now_secs;
+use super::{connect_source, IngestCheckpoint, IngestMessage, IngestSource};
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+// Status of a source, exposed by GET /api/ingest
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestStatus {
+    pub source: String,
+    pub kind: &'static str,
+    pub collection: String,
+    pub running: bool,
+    pub position: Option<u64>, // Last committed source position
+    pub wal_seq: Option<u64>, // Collection WAL sequence that position was recorded at
+    pub ingested: u64, // Messages written since startup
+    pub skipped: u64, // Messages dropped as malformed or rejected
+    pub batches: u64,
+    pub last_error: Option<String>,
+    pub updated_at: u64,
+}
+
+pub struct IngestWorker {
+    state: SharedState,
+    config: IngestSourceConfig,
+    source: Box<dyn IngestSource>,
+    checkpoint: IngestCheckpoint,
+    checkpoint_path: PathBuf,
+    committed: Option<u64>,
+}
+
+impl IngestWorker {
+    // Load the checkpoint and move the source to where the collection's WAL says it got to
+    pub async fn start(state: SharedState, config: IngestSourceConfig, mut source: Box<dyn IngestSource>) -> Result<Self> {
+        state.get_or_create_collection(&config.collection)?;
+        let head_seq = {
+            let storage_ref = state.collections.get(&config.collection)
+                .ok_or(ServerError::CollectionNotFound)?;
+            let storage = storage_ref.read();
+            storage.head_seq()
+        };
+
+        let checkpoint_path = IngestCheckpoint::path(&state.data_dir, &config.name);
+        let checkpoint = IngestCheckpoint::load(&checkpoint_path, &config.name, &config.collection)?;
+        let resume_entry = checkpoint.resume_entry(head_seq).copied();
+        let resume = resume_entry.map(|e| e.position);
+        if let Some(last) = checkpoint.last() {
+            if last.wal_seq > head_seq {
+                tracing::warn!(
+                    source=%config.name,
+                    collection=%config.collection,
+                    checkpoint_wal_seq=last.wal_seq,
+                    head_seq,
+                    resume=?resume,
+                    "ingest_rewind_collection_behind_checkpoint"
+                );
+            }
+        }
+        source.seek(resume).await?;
+        tracing::info!(source=%config.name, kind=config.source.name(), collection=%config.collection, resume=?resume, "ingest_started");
+
+        state.ingest.insert(config.name.clone(), IngestStatus {
+            source: config.name.clone(),
+            kind: config.source.name(),
+            collection: config.collection.clone(),
+            running: true,
+            position: resume,
+            wal_seq: resume_entry.map(|e| e.wal_seq),
+            ingested: 0,
+            skipped: 0,
+            batches: 0,
+            last_error: None,
+            updated_at: now_secs(),
+        });
+
+        Ok(Self { state, config, source, checkpoint, checkpoint_path, committed: resume })
+    }
+
+    // Fetch and write one batch. Returns the number of messages written.
+    pub async fn poll(&mut self) -> Result<usize> {
+        let wait = Duration::from_millis(self.config.batch_timeout_ms);
+        let messages = self.source.fetch(self.config.batch_size, wait).await?;
+        let Some(last) = messages.last().map(|m| m.position) else {
+            return Ok(0);
+        };
+
+        let (written, skipped, wal_seq) = match self.write_batch(messages).await {
+            Ok(result) => result,
+            Err(e) => {
+                // Uncommitted messages are delivered again on the next fetch
+                if let Err(seek_err) = self.source.seek(self.committed).await {
+                    tracing::warn!(source=%self.config.name, error=%seek_err, "ingest_seek_failed");
+                }
+                return Err(e);
+            }
+        };
+
+        self.checkpoint.record(last, wal_seq);
+        self.checkpoint.save(&self.checkpoint_path)?;
+        self.committed = Some(last);
+        // The checkpoint already covers the batch; a lost ack only means a redelivery that is dropped
+        if let Err(e) = self.source.commit(last).await {
+            tracing::warn!(source=%self.config.name, position=last, error=%e, "ingest_commit_failed");
+        }
+
+        if let Some(mut status) = self.state.ingest.get_mut(&self.config.name) {
+            status.position = Some(last);
+            status.wal_seq = Some(wal_seq);
+            status.ingested += written as u64;
+            status.skipped += skipped as u64;
+            status.batches += 1;
+            status.last_error = None;
+            status.updated_at = now_secs();
+        }
+        Ok(written)
+    }
+
+    // Returns (written, skipped, WAL sequence after the batch)
+    async fn write_batch(&mut self, messages: Vec<super::SourceMessage>) -> Result<(usize, usize, u64)> {
+        let mut skipped = 0;
+        let embedder = self.state.embedder_for(&self.config.collection);
+        let mut parsed = Vec::with_capacity(messages.len());
+        for message in messages {
+            match IngestMessage::parse(&message.payload) {
+                Ok(m) if m.vector.is_none() && (m.text.is_empty() || embedder.is_none()) => {
+                    tracing::warn!(source=%self.config.name, position=message.position, "ingest_message_without_vector");
+                    skipped += 1;
+                }
+                Ok(m) => parsed.push((message.position, m)),
+                Err(e) => {
+                    tracing::warn!(source=%self.config.name, position=message.position, error=%e, "ingest_message_invalid");
+                    skipped += 1;
+                }
+            }
+        }
+
+        // Embed before taking the write lock: the embedder call can take a while
+        let texts: Vec<String> = parsed.iter().filter(|(_, m)| m.vector.is_none()).map(|(_, m)| m.text.clone()).collect();
+        let mut embedded = Vec::new();
+        let mut embedded_dims = None;
+        if let (false, Some(embedder)) = (texts.is_empty(), embedder.as_ref()) {
+            let usage = UsageScope::new(&**embedder, &self.config.collection, None);
+            self.state.check_usage(&usage)?;
+            let start = Instant::now();
+            let responses = embedder.embed_batch(&texts).await?;
+            embedded_dims = responses.first().map(|r| r.embedding.len());
+            self.state.record_embedding(&usage, responses.len() as u64, responses.iter().filter_map(|r| r.tokens).map(|t| t as u64).sum(), start.elapsed());
+            embedded = responses.into_iter().map(|r| r.embedding).collect();
+        }
+
+        self.state.ensure_write_allowed()?;
+        self.state.throttle_writes(&self.config.collection).await?;
+        self.state.get_or_create_collection(&self.config.collection)?;
+        let storage_ref = self.state.collections.get(&self.config.collection)
+            .ok_or(ServerError::CollectionNotFound)?;
+        let lock_start = Instant::now();
+        let mut storage = storage_ref.write();
+        record_lock_write(self.state.latency_tracker.get(&self.config.collection).as_deref(), lock_start);
+
+        if let (Some(dims), Some(embedder)) = (embedded_dims, embedder.as_ref()) {
+            storage.check_embedding_model(embedder.model_name(), Some(dims))?;
+        }
+
+        let mut written = 0;
+        let mut embedded = embedded.into_iter();
+        let documents: Vec<(u64, Result<crate::Document>)> = parsed.into_iter().map(|(position, mut message)| {
+            let vector = match message.vector.take() {
+                Some(vector) => vector,
+                None => embedded.next().unwrap_or_default(),
+            };
+            (position, message.into_document(vector))
+        }).collect();
+        // Documents the batch would create count against the project's quota; over it, the batch is retried later
+        let creating = documents.iter()
+            .filter_map(|(_, entry)| entry.as_ref().ok())
+            .filter(|entry| storage.get(&entry.id).is_none() && entry.external_id().is_none_or(|ext| storage.resolve_id(ext).is_none()))
+            .count();
+        self.state.ensure_project_quota(&self.config.collection, storage.count(), creating)?;
+        for (position, entry) in documents {
+            let result = entry.and_then(|entry| storage.upsert(entry));
+            match result {
+                Ok(_) => written += 1,
+                Err(e) if e.status_code().is_client_error() => {
+                    tracing::warn!(source=%self.config.name, position, error=%e, "ingest_message_rejected");
+                    skipped += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if let (Some(dims), Some(embedder)) = (embedded_dims, embedder.as_ref()) {
+            storage.record_embedding_model(embedder.model_name(), dims)?;
+        }
+        let wal_seq = storage.head_seq();
+        drop(storage);
+        self.state.enforce_cache_budget();
+        Ok((written, skipped, wal_seq))
+    }
+
+    // Poll until shutdown. Errors are retried with backoff on a fresh connection.
+    pub async fn run(mut self) {
+        let mut backoff = Duration::from_millis(500);
+        while !self.state.shutting_down.load(Ordering::Relaxed) {
+            match self.poll().await {
+                Ok(_) => backoff = Duration::from_millis(500),
+                Err(e) => {
+                    tracing::warn!(source=%self.config.name, error=%e, retry_ms=backoff.as_millis() as u64, "ingest_batch_failed");
+                    if let Some(mut status) = self.state.ingest.get_mut(&self.config.name) {
+                        status.last_error = Some(e.to_string());
+                        status.updated_at = now_secs();
+                    }
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    self.reconnect().await;
+                }
+            }
+        }
+        if let Some(mut status) = self.state.ingest.get_mut(&self.config.name) {
+            status.running = false;
+            status.updated_at = now_secs();
+        }
+        tracing::info!(source=%self.config.name, "ingest_stopped");
+    }
+
+    async fn reconnect(&mut self) {
+        match connect_source(&self.config).await {
+            Ok(mut source) => match source.seek(self.committed).await {
+                Ok(()) => self.source = source,
+                Err(e) => tracing::warn!(source=%self.config.name, error=%e, "ingest_reconnect_failed"),
+            },
+            Err(e) => tracing::warn!(source=%self.config.name, error=%e, "ingest_reconnect_failed"),
+        }
+    }
+}
+
+// Start a worker task for every source in the config. Sources that cannot connect yet are retried
+// in the background until the server shuts down.
+pub fn spawn_ingestion(state: SharedState) -> usize {
+    let sources = state.app_config.read().ingest.clone();
+    let count = sources.len();
+    for config in sources {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut backoff = Duration::from_millis(500);
+            while !state.shutting_down.load(Ordering::Relaxed) {
+                let started = match connect_source(&config).await {
+                    Ok(source) => IngestWorker::start(state.clone(), config.clone(), source).await,
+                    Err(e) => Err(e),
+                };
+                match started {
+                    Ok(worker) => return worker.run().await,
+                    Err(e) => {
+                        tracing::warn!(source=%config.name, error=%e, retry_ms=backoff.as_millis() as u64, "ingest_start_failed");
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                    }
+                }
+            }
+        });
+    }
+    count
+}
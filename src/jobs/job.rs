@@ -0,0 +1,166 @@
+// A job record: what to run, on which collection, and how far it got.
+// Serialized as it is to data_dir/jobs/<id>.json and returned by the jobs API.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::QuantizationLevel;
+use crate::index::IndexConfig;
+use crate::scheduler::Priority;
+use crate::storage::collection::{CompactStats, DocumentImportReport, FieldMapping, ImportReport, IndexMigrationReport, RequantizeReport, SourceFormat};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobKind {
+    RebuildIndex,
+    Compact,
+    Import { path: String }, // Server-side bundle directory, as for POST .../index/import
+    Reembed { batch_size: usize }, // Documents per embedding request and per collection write
+    ImportDocuments {
+        path: String, // Server-side source file, as for POST .../import
+        format: SourceFormat,
+        #[serde(default)]
+        mapping: FieldMapping,
+        batch_size: usize, // Rows per collection write
+    },
+    MigrateIndex { index: IndexConfig }, // The index the collection switches to, as for POST .../index/migrate
+    Requantize { level: QuantizationLevel, batch_size: usize }, // Documents re-encoded per collection write
+}
+
+impl JobKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobKind::RebuildIndex => "rebuild_index",
+            JobKind::Compact => "compact",
+            JobKind::Import { .. } => "import",
+            JobKind::Reembed { .. } => "reembed",
+            JobKind::ImportDocuments { .. } => "import_documents",
+            JobKind::MigrateIndex { .. } => "migrate_index",
+            JobKind::Requantize { .. } => "requantize",
+        }
+    }
+
+    // Whether a run a restart interrupted can be run again: a rebuild, compaction or requantize starts
+    // over, a re-embed or document import continues from its cursor, but an index import only loads
+    // into an empty collection
+    pub fn resumable(&self) -> bool {
+        !matches!(self, JobKind::Import { .. })
+    }
+
+    // Priority of a queued job: rebuilds, index migrations, requantizes and compactions are maintenance
+    // that can wait for the off-peak window, the others bring in data someone asked for. A job someone
+    // waits on runs as normal.
+    pub fn priority(&self) -> Priority {
+        match self {
+            JobKind::RebuildIndex | JobKind::MigrateIndex { .. } | JobKind::Requantize { .. } | JobKind::Compact => Priority::Low,
+            _ => Priority::Normal,
+        }
+    }
+
+    // Whether a running job stops on a cancel request; the others are one step under the write lock
+    pub fn interruptible(&self) -> bool {
+        matches!(self, JobKind::Reembed { .. } | JobKind::ImportDocuments { .. } | JobKind::Requantize { .. })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobState::Queued => "queued",
+            JobState::Running => "running",
+            JobState::Completed => "completed",
+            JobState::Failed => "failed",
+            JobState::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobState::Completed | JobState::Failed | JobState::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub done: u64, // Documents handled so far (batched jobs only)
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    pub seq: u64, // Submission order; the queue runs the lowest queued seq of the highest priority next
+    #[serde(default)]
+    pub priority: Priority, // Scheduler priority of its work; low-priority jobs wait for the off-peak window
+    pub collection: String,
+    #[serde(flatten)]
+    pub kind: JobKind,
+    pub state: JobState,
+    pub created_at: u64, // Timestamps in seconds since UNIX epoch
+    #[serde(default)]
+    pub started_at: Option<u64>,
+    #[serde(default)]
+    pub finished_at: Option<u64>,
+    #[serde(default)]
+    pub elapsed_ms: Option<u64>, // Duration of the last run
+    #[serde(default)]
+    pub attempts: u32, // Runs started, counting ones a restart interrupted
+    #[serde(default)]
+    pub progress: JobProgress,
+    #[serde(default)]
+    pub cursor: Option<String>, // Last document id a re-embed finished, or rows a document import wrote; it resumes after it
+    #[serde(default)]
+    pub cancel_requested: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub result: Option<serde_json::Value>, // Summary of what the job did, once completed (a document import's counts so far while it runs)
+}
+
+// What a completed job produced, handed to the request waiting on it
+#[derive(Debug)]
+pub enum JobOutput {
+    Rebuilt,
+    Compacted(CompactStats),
+    Imported(ImportReport),
+    Reembedded { documents: u64 },
+    ImportedDocuments(DocumentImportReport),
+    MigratedIndex(IndexMigrationReport),
+    Requantized(RequantizeReport),
+}
+
+impl JobOutput {
+    pub fn summary(&self) -> serde_json::Value {
+        match self {
+            JobOutput::Rebuilt => serde_json::json!({}),
+            JobOutput::Compacted(stats) => serde_json::json!({
+                "original_entries": stats.original_entries,
+                "compacted_entries": stats.compacted_entries,
+            }),
+            JobOutput::Imported(report) => serde_json::json!({
+                "index_type": report.index_type.to_string(),
+                "imported": report.imported,
+            }),
+            JobOutput::Reembedded { documents } => serde_json::json!({ "documents": documents }),
+            JobOutput::ImportedDocuments(report) => serde_json::json!(report),
+            JobOutput::MigratedIndex(report) => serde_json::json!(report),
+            JobOutput::Requantized(report) => serde_json::json!(report),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Finished {
+    pub output: JobOutput,
+    pub elapsed: Duration,
+}
@@ -0,0 +1,19 @@
+// Jobs module - persistent queue for long-running admin tasks
+// - job.rs: the job record (kind, state, progress, cursor) and what a finished job produced
+// - queue.rs: the queue itself; every change to a job is written to data_dir/jobs/<id>.json
+// - runner.rs: the worker that runs queued jobs one at a time, and the entry points handlers use
+//
+// Index rebuilds, compactions, prebuilt-index imports, re-embeds and document imports run here rather
+// than on the request that asked for them, so they survive a restart: on startup a job that was running
+// goes back to the queue when its kind can safely run again (a rebuild or compaction starts over, a
+// re-embed or document import continues after the last batch it finished) and is failed otherwise (an
+// index import into a collection it already half filled). Queued jobs can be cancelled; a running
+// re-embed or document import stops at its next batch.
+
+pub mod job;
+pub mod queue;
+pub mod runner;
+
+pub use job::{Job, JobKind, JobState, JobProgress, JobOutput, Finished};
+pub use queue::JobQueue;
+pub use runner::{spawn_jobs, submit, run_and_wait};
@@ -0,0 +1,282 @@
+// The job queue: every job in memory, each also written to data_dir/jobs/<id>.json whenever it changes
+// (temp file + rename, like the ingest checkpoints). Opening the queue reads them back and settles
+// the jobs a restart interrupted. Finished jobs are kept for inspection up to MAX_FINISHED, oldest
+// dropped first.
+//
+// The queue only holds state; runner.rs pulls work from it. A request that waits for its job (compact,
+// import) registers a one-shot waiter when it submits and gets the job's own result or error back.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use tokio::sync::{oneshot, Notify};
+use uuid::Uuid;
+
+use crate::error::{Result, ServerError};
+use crate::scheduler::Priority;
+use super::job::{Finished, Job, JobKind, JobProgress, JobState};
+
+const MAX_FINISHED: usize = 100;
+
+type Waiter = oneshot::Sender<Result<Finished>>;
+
+pub struct JobQueue {
+    dir: PathBuf,
+    jobs: Mutex<HashMap<String, Job>>,
+    waiters: Mutex<HashMap<String, Waiter>>,
+    notify: Notify,
+    runner_started: AtomicBool,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+impl JobQueue {
+    // Load the jobs stored under data_dir/jobs. A job left running by the previous process is queued
+    // again when its kind is resumable and failed otherwise. Unreadable files are skipped.
+    pub fn open(data_dir: &str) -> Self {
+        let dir = Path::new(data_dir).join("jobs");
+        let mut jobs = HashMap::new();
+        if let Ok(entries) = fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                    continue;
+                }
+                match fs::read(&path).map_err(|e| e.to_string()).and_then(|d| serde_json::from_slice::<Job>(&d).map_err(|e| e.to_string())) {
+                    Ok(job) => {
+                        jobs.insert(job.id.clone(), job);
+                    }
+                    Err(e) => tracing::warn!(path=%path.display(), error=%e, "job_file_unreadable"),
+                }
+            }
+        }
+        for job in jobs.values_mut().filter(|j| j.state == JobState::Running) {
+            if job.kind.resumable() {
+                job.state = JobState::Queued;
+            } else {
+                job.state = JobState::Failed;
+                job.finished_at = Some(now());
+                job.error = Some("Interrupted by a server restart; not safe to run again".into());
+            }
+            if let Err(e) = save(&dir, job) {
+                tracing::warn!(job=%job.id, error=%e, "job_save_failed");
+            }
+        }
+        Self {
+            dir,
+            jobs: Mutex::new(jobs),
+            waiters: Mutex::new(HashMap::new()),
+            notify: Notify::new(),
+            runner_started: AtomicBool::new(false),
+        }
+    }
+
+    // Add a job; with `waiter` its outcome is also sent there when it finishes
+    pub(super) fn push(&self, collection: &str, kind: JobKind, waiter: Option<Waiter>) -> Result<Job> {
+        let mut jobs = self.jobs.lock();
+        let job = Job {
+            id: Uuid::new_v4().to_string(),
+            seq: jobs.values().map(|j| j.seq).max().map_or(1, |s| s + 1),
+            collection: collection.to_string(),
+            priority: if waiter.is_some() { Priority::Normal } else { kind.priority() },
+            kind,
+            state: JobState::Queued,
+            created_at: now(),
+            started_at: None,
+            finished_at: None,
+            elapsed_ms: None,
+            attempts: 0,
+            progress: JobProgress::default(),
+            cursor: None,
+            cancel_requested: false,
+            error: None,
+            result: None,
+        };
+        save(&self.dir, &job)?;
+        // The waiter goes in first, so even a job that fails at once finds it
+        if let Some(waiter) = waiter {
+            self.waiters.lock().insert(job.id.clone(), waiter);
+        }
+        jobs.insert(job.id.clone(), job.clone());
+        drop(jobs);
+        self.notify.notify_one();
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.lock().get(id).cloned()
+    }
+
+    // Jobs newest first, optionally only those of one collection
+    pub fn list(&self, collection: Option<&str>) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self
+            .jobs
+            .lock()
+            .values()
+            .filter(|j| collection.is_none_or(|c| j.collection == c))
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|j| std::cmp::Reverse(j.seq));
+        jobs
+    }
+
+    // Newest job of a kind for a collection
+    pub fn latest(&self, collection: &str, kind: &str) -> Option<Job> {
+        self.jobs
+            .lock()
+            .values()
+            .filter(|j| j.collection == collection && j.kind.name() == kind)
+            .max_by_key(|j| j.seq)
+            .cloned()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.jobs.lock().values().filter(|j| j.state == JobState::Queued).count()
+    }
+
+    // A queued job is cancelled at once; a running one only if it stops between batches, in which case
+    // it is flagged and the runner cancels it at its next batch
+    pub fn cancel(&self, id: &str) -> Result<Job> {
+        let mut jobs = self.jobs.lock();
+        let job = jobs.get_mut(id).ok_or_else(|| ServerError::NotFound(format!("Job '{}' not found", id)))?;
+        match job.state {
+            JobState::Queued => {
+                job.state = JobState::Cancelled;
+                job.finished_at = Some(now());
+            }
+            JobState::Running if job.kind.interruptible() => job.cancel_requested = true,
+            JobState::Running => {
+                return Err(ServerError::InvalidRequest(format!(
+                    "Job '{}' ({}) is running and cannot be interrupted",
+                    id,
+                    job.kind.name()
+                )).into());
+            }
+            state => {
+                return Err(ServerError::InvalidRequest(format!("Job '{}' is already {}", id, state.name())).into());
+            }
+        }
+        save(&self.dir, job)?;
+        let job = job.clone();
+        drop(jobs);
+        if job.state == JobState::Cancelled {
+            self.notify_waiter(&job.id, Err(ServerError::ServiceUnavailable(format!("Job '{}' was cancelled", id)).into()));
+        }
+        Ok(job)
+    }
+
+    // Take the oldest queued job of the highest priority and mark it running; low-priority jobs only
+    // when `off_peak`
+    pub(super) fn next(&self, off_peak: bool) -> Option<Job> {
+        let mut jobs = self.jobs.lock();
+        let job = jobs
+            .values_mut()
+            .filter(|j| j.state == JobState::Queued && (off_peak || j.priority != Priority::Low))
+            .min_by_key(|j| (j.priority, j.seq))?;
+        job.state = JobState::Running;
+        job.started_at = Some(now());
+        job.attempts += 1;
+        job.error = None;
+        self.persist(job);
+        Some(job.clone())
+    }
+
+    pub(super) fn update(&self, id: &str, f: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.jobs.lock().get_mut(id) {
+            f(job);
+            self.persist(job);
+        }
+    }
+
+    pub(super) fn cancel_requested(&self, id: &str) -> bool {
+        self.jobs.lock().get(id).is_some_and(|j| j.cancel_requested)
+    }
+
+    pub(super) fn finish(&self, id: &str, outcome: Result<Finished>) {
+        self.update(id, |job| {
+            job.finished_at = Some(now());
+            match &outcome {
+                Ok(finished) => {
+                    job.state = JobState::Completed;
+                    job.elapsed_ms = Some(finished.elapsed.as_millis() as u64);
+                    job.result = Some(finished.output.summary());
+                }
+                Err(e) => {
+                    job.state = JobState::Failed;
+                    job.error = Some(e.to_string());
+                }
+            }
+        });
+        self.notify_waiter(id, outcome);
+        self.prune();
+    }
+
+    pub(super) fn mark_cancelled(&self, id: &str) {
+        self.update(id, |job| {
+            job.state = JobState::Cancelled;
+            job.finished_at = Some(now());
+        });
+        self.notify_waiter(id, Err(ServerError::ServiceUnavailable(format!("Job '{}' was cancelled", id)).into()));
+        self.prune();
+    }
+
+    // Wait until a job may have been queued
+    pub(super) async fn queued(&self) {
+        self.notify.notified().await
+    }
+
+    // Have the runner look at the queue again, e.g. after a config change moved the off-peak window
+    pub fn wake(&self) {
+        self.notify.notify_one();
+    }
+
+    // True for the one caller that gets to start the runner
+    pub(super) fn claim_runner(&self) -> bool {
+        !self.runner_started.swap(true, Ordering::AcqRel)
+    }
+
+    fn notify_waiter(&self, id: &str, outcome: Result<Finished>) {
+        if let Some(waiter) = self.waiters.lock().remove(id) {
+            let _ = waiter.send(outcome);
+        }
+    }
+
+    fn persist(&self, job: &Job) {
+        if let Err(e) = save(&self.dir, job) {
+            tracing::warn!(job=%job.id, error=%e, "job_save_failed");
+        }
+    }
+
+    fn prune(&self) {
+        let mut jobs = self.jobs.lock();
+        let mut finished: Vec<(u64, String)> =
+            jobs.values().filter(|j| j.state.is_finished()).map(|j| (j.seq, j.id.clone())).collect();
+        if finished.len() <= MAX_FINISHED {
+            return;
+        }
+        finished.sort_unstable();
+        for (_, id) in &finished[..finished.len() - MAX_FINISHED] {
+            jobs.remove(id);
+            let _ = fs::remove_file(job_path(&self.dir, id));
+        }
+    }
+}
+
+fn job_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.json", id))
+}
+
+fn save(dir: &Path, job: &Job) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = job_path(dir, &job.id);
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(job)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
@@ -0,0 +1,363 @@
+// The job runner: one task that takes the next queued job, runs it, records the outcome, and waits
+// for more. It starts with the first submitted job, or at startup when jobs were left queued.
+// Jobs run one at a time; each holds its collection's write lock for as long as it works on it, and
+// running two admin tasks at once would only make them contend for disk.
+//
+// Normal-priority jobs go before low-priority ones, which are only taken inside the scheduler's
+// off-peak window; while any wait for it the runner looks again every OFF_PEAK_RECHECK. A job's
+// blocking work runs as a background task of its priority (see `crate::scheduler`).
+//
+// An index migration is the exception: it builds under the read lock and takes the write lock only to
+// swap the new index in (see `crate::storage::collection::migrate_index`).
+//
+// A re-embed walks the collection in document id order. Each batch is embedded, written with one
+// update_vectors call, and then recorded as the job's cursor, so a restart or a cancel loses at most the
+// batch in flight. When it completes, the collection's recorded embedding model becomes the current one.
+//
+// A requantize switches the collection's quantization level, then re-encodes its documents in id order,
+// taking the write lock once per batch. It keeps no cursor: a rerun looks at every document again and
+// skips the ones already re-encoded.
+//
+// A document import reads its source in one background task and takes the write lock once per batch;
+// its cursor is the number of rows written, and a resumed run skips that many. Rows of the batch in
+// flight are written again, which is an upsert for rows with ids.
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::RwLock;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::error::{Result, ServerError};
+use crate::scheduler;
+use crate::server::helpers::EMBEDDING_NOT_CONFIGURED;
+use crate::server::state::SharedState;
+use crate::server::usage::UsageScope;
+use crate::config::QuantizationLevel;
+use crate::index::IndexConfig;
+use crate::storage::collection::{compact, import_prebuilt, migrate_index, write_documents, DocumentSource, FieldMapping, RequantizeReport, SourceFormat};
+use crate::Collection;
+use super::job::{Finished, Job, JobKind, JobOutput, JobProgress};
+
+const OFF_PEAK_RECHECK: Duration = Duration::from_secs(60);
+
+// How a run ended short of producing output
+enum Step {
+    Done(JobOutput),
+    Cancelled,
+    Interrupted, // the server is shutting down; the job stays running and is resumed on the next start
+}
+
+// Start the runner if jobs were left queued by the previous process. Returns how many are waiting.
+pub fn spawn_jobs(state: SharedState) -> usize {
+    let pending = state.jobs.pending();
+    if pending > 0 {
+        ensure_runner(&state);
+    }
+    pending
+}
+
+// Queue a job and return at once
+pub fn submit(state: &SharedState, collection: &str, kind: JobKind) -> Result<Job> {
+    let job = state.jobs.push(collection, kind, None)?;
+    ensure_runner(state);
+    tracing::info!(job=%job.id, kind=job.kind.name(), collection=%collection, "job_queued");
+    Ok(job)
+}
+
+// Queue a job and wait for its outcome. The job keeps running if the caller goes away.
+pub async fn run_and_wait(state: &SharedState, collection: &str, kind: JobKind) -> Result<Finished> {
+    let (tx, rx) = oneshot::channel();
+    let job = state.jobs.push(collection, kind, Some(tx))?;
+    ensure_runner(state);
+    tracing::info!(job=%job.id, kind=job.kind.name(), collection=%collection, "job_queued");
+    rx.await.map_err(|_| ServerError::Internal(format!("Job '{}' ended without an outcome", job.id)))?
+}
+
+fn ensure_runner(state: &SharedState) {
+    if state.jobs.claim_runner() {
+        let state = state.clone();
+        tokio::spawn(async move { run(state).await });
+    }
+}
+
+async fn run(state: SharedState) {
+    loop {
+        if state.shutting_down.load(Ordering::Relaxed) {
+            return;
+        }
+        match state.jobs.next(scheduler::off_peak()) {
+            Some(job) => execute(&state, job).await,
+            // Jobs still queued are waiting for the off-peak window
+            None if state.jobs.pending() > 0 => {
+                let _ = tokio::time::timeout(OFF_PEAK_RECHECK, state.jobs.queued()).await;
+            }
+            None => state.jobs.queued().await,
+        }
+    }
+}
+
+async fn execute(state: &SharedState, job: Job) {
+    tracing::info!(job=%job.id, kind=job.kind.name(), collection=%job.collection, attempt=job.attempts, "job_started");
+    let start = Instant::now();
+    let step = match job.kind.clone() {
+        JobKind::RebuildIndex => {
+            with_collection(state, &job, |storage| storage.rebuild_index().map(|_| JobOutput::Rebuilt)).await
+        }
+        JobKind::Compact => with_collection(state, &job, |storage| compact(storage).map(JobOutput::Compacted)).await,
+        JobKind::Import { path } => import(state, &job, path).await,
+        JobKind::Reembed { batch_size } => reembed(state, &job, batch_size).await,
+        JobKind::ImportDocuments { path, format, mapping, batch_size } => {
+            import_documents(state, &job, path, format, mapping, batch_size).await
+        }
+        JobKind::MigrateIndex { index } => migrate(state, &job, index).await,
+        JobKind::Requantize { level, batch_size } => requantize(state, &job, level, batch_size).await,
+    };
+    let elapsed = start.elapsed();
+    match step {
+        Ok(Step::Done(output)) => {
+            tracing::info!(job=%job.id, kind=job.kind.name(), collection=%job.collection, elapsed_ms=elapsed.as_millis() as u64, "job_completed");
+            state.jobs.finish(&job.id, Ok(Finished { output, elapsed }));
+        }
+        Ok(Step::Cancelled) => {
+            tracing::info!(job=%job.id, kind=job.kind.name(), collection=%job.collection, "job_cancelled");
+            state.jobs.mark_cancelled(&job.id);
+        }
+        Ok(Step::Interrupted) => {
+            tracing::info!(job=%job.id, kind=job.kind.name(), collection=%job.collection, "job_interrupted");
+        }
+        Err(e) => {
+            tracing::error!(job=%job.id, kind=job.kind.name(), collection=%job.collection, error=%e, "job_failed");
+            state.jobs.finish(&job.id, Err(e));
+        }
+    }
+}
+
+// The collection's handle, opening it if it was only discovered. A job whose collection was deleted
+// after it was queued fails rather than recreating it.
+fn collection_handle(state: &SharedState, name: &str) -> Result<Arc<RwLock<Collection>>> {
+    if !state.collections.contains_key(name) && !state.discovered.contains_key(name) {
+        return Err(ServerError::CollectionNotFound.into());
+    }
+    state.get_or_create_collection(name)?;
+    state
+        .collections
+        .get(name)
+        .map(|c| c.clone())
+        .ok_or_else(|| ServerError::CollectionNotFound.into())
+}
+
+// Run `f` as a background task of the job's priority and wait for it
+async fn in_background<R: Send + 'static>(job: &Job, f: impl FnOnce() -> R + Send + 'static) -> Result<R> {
+    scheduler::spawn(job.priority, job.kind.name(), f)
+        .wait()
+        .await
+        .map_err(|_| ServerError::Internal(format!("Job '{}' panicked", job.id)).into())
+}
+
+// Run `f` in the background under the collection's write lock
+async fn with_collection<F>(state: &SharedState, job: &Job, f: F) -> Result<Step>
+where
+    F: FnOnce(&mut Collection) -> Result<JobOutput> + Send + 'static,
+{
+    let handle = collection_handle(state, &job.collection)?;
+    let output = in_background(job, move || f(&mut handle.write())).await??;
+    Ok(Step::Done(output))
+}
+
+async fn import(state: &SharedState, job: &Job, path: String) -> Result<Step> {
+    let shared = state.clone();
+    let collection = job.collection.clone();
+    let step = with_collection(state, job, move |storage| {
+        let report = import_prebuilt(storage, &path)?;
+        // The import replaced the data wholesale without going through the WAL, so replicas are re-taken
+        if let Some(previous) = shared.replicas_for(&collection) {
+            shared.replicas.insert(collection, storage.create_replicas(previous.len())?);
+        }
+        Ok(JobOutput::Imported(report))
+    })
+    .await?;
+    state.enforce_cache_budget();
+    Ok(step)
+}
+
+// Only the swap takes the write lock; the build and most of the catch-up run under the read lock
+async fn migrate(state: &SharedState, job: &Job, index: IndexConfig) -> Result<Step> {
+    let handle = collection_handle(state, &job.collection)?;
+    let shared = state.clone();
+    let collection = job.collection.clone();
+    let report = in_background(job, move || -> Result<_> {
+        let report = migrate_index(&handle, index)?;
+        // Replicas answer from a copy of the index, so they are re-taken to serve from the new one
+        if let Some(previous) = shared.replicas_for(&collection) {
+            shared.replicas.insert(collection, handle.write().create_replicas(previous.len())?);
+        }
+        Ok(report)
+    })
+    .await??;
+    Ok(Step::Done(JobOutput::MigratedIndex(report)))
+}
+
+async fn reembed(state: &SharedState, job: &Job, batch_size: usize) -> Result<Step> {
+    let embedder = state
+        .embedder_for(&job.collection)
+        .ok_or_else(|| ServerError::ServiceUnavailable(EMBEDDING_NOT_CONFIGURED.to_string()))?;
+    let handle = collection_handle(state, &job.collection)?;
+    let (ids, dimensions) = {
+        let storage = handle.read();
+        (storage.ids(), storage.metadata().dimensions)
+    };
+    // Documents added after the job started are embedded by their own insert (or kept as given)
+    let after: Option<Uuid> = job.cursor.as_deref().and_then(|c| c.parse().ok());
+    let start = after.map_or(0, |cursor| ids.partition_point(|id| *id <= cursor));
+    let mut done = start as u64;
+    let total = ids.len() as u64;
+    state.jobs.update(&job.id, |j| j.progress = JobProgress { done, total: Some(total) });
+
+    let mut embedded_dims = None;
+    for chunk in ids[start..].chunks(batch_size.max(1)) {
+        if state.jobs.cancel_requested(&job.id) {
+            return Ok(Step::Cancelled);
+        }
+        if state.shutting_down.load(Ordering::Relaxed) {
+            return Ok(Step::Interrupted);
+        }
+        let docs: Vec<(Uuid, String)> = {
+            let storage = handle.read();
+            chunk
+                .iter()
+                .filter_map(|id| storage.get(id))
+                .filter(|doc| !doc.text.is_empty())
+                .map(|doc| (doc.id, doc.text))
+                .collect()
+        };
+        if !docs.is_empty() {
+            let texts: Vec<String> = docs.iter().map(|(_, text)| text.clone()).collect();
+            let usage = UsageScope::new(&*embedder, &job.collection, None);
+            state.check_usage(&usage)?;
+            let start = Instant::now();
+            let responses = embedder.embed_batch(&texts).await?;
+            state.record_embedding(&usage, texts.len() as u64, responses.iter().filter_map(|r| r.tokens).map(u64::from).sum(), start.elapsed());
+            if responses.len() != docs.len() {
+                return Err(ServerError::Internal(format!(
+                    "Embedder returned {} embeddings for {} texts", responses.len(), docs.len()
+                )).into());
+            }
+            let updates: Vec<(Uuid, Vec<f32>)> = docs.iter().map(|(id, _)| *id).zip(responses.into_iter().map(|r| r.embedding)).collect();
+            // Vectors of another size would not fit the index; fail before anything is written
+            if let Some((_, wrong)) = updates.iter().find(|(_, v)| dimensions.is_some_and(|d| d != v.len())) {
+                return Err(ServerError::InvalidRequest(format!(
+                    "Model '{}' produced {} dimensions, the collection holds {}",
+                    embedder.model_name(), wrong.len(), dimensions.unwrap_or(0)
+                )).into());
+            }
+            embedded_dims = updates.first().map(|(_, v)| v.len());
+            let writer = handle.clone();
+            in_background(job, move || writer.write().update_vectors(updates)).await??;
+        }
+        done += chunk.len() as u64;
+        let cursor = chunk.last().map(|id| id.to_string());
+        state.jobs.update(&job.id, |j| {
+            j.progress.done = done;
+            j.cursor = cursor;
+        });
+    }
+
+    if let Some(dims) = embedded_dims.or(dimensions) {
+        handle.write().replace_embedding_model(embedder.model_name(), dims)?;
+    }
+    Ok(Step::Done(JobOutput::Reembedded { documents: total }))
+}
+
+// The level switches first, so writes made while the job runs are already in it. A cancelled job
+// leaves the rest of the documents in their old encoding; running it again finishes them.
+async fn requantize(state: &SharedState, job: &Job, level: QuantizationLevel, batch_size: usize) -> Result<Step> {
+    let handle = collection_handle(state, &job.collection)?;
+    let ids = {
+        let mut storage = handle.write();
+        storage.set_quantization(level)?;
+        storage.ids()
+    };
+    let total = ids.len() as u64;
+    state.jobs.update(&job.id, |j| j.progress = JobProgress { done: 0, total: Some(total) });
+
+    let mut done = 0;
+    let mut reencoded = 0;
+    for chunk in ids.chunks(batch_size.max(1)) {
+        if state.jobs.cancel_requested(&job.id) {
+            return Ok(Step::Cancelled);
+        }
+        if state.shutting_down.load(Ordering::Relaxed) {
+            return Ok(Step::Interrupted);
+        }
+        done += chunk.len();
+        let writer = handle.clone();
+        let chunk = chunk.to_vec();
+        reencoded += in_background(job, move || writer.write().requantize(&chunk)).await??;
+        state.jobs.update(&job.id, |j| j.progress.done = done as u64);
+    }
+
+    // The rewrites are not in the WAL; until this checkpoint a crash puts documents back in their old encoding
+    let reader = handle.clone();
+    in_background(job, move || reader.read().checkpoint()).await??;
+    tracing::info!(collection=%job.collection, level=?level, reencoded, "collection_requantized");
+    Ok(Step::Done(JobOutput::Requantized(RequantizeReport { level, documents: ids.len(), reencoded })))
+}
+
+async fn import_documents(
+    state: &SharedState,
+    job: &Job,
+    path: String,
+    format: SourceFormat,
+    mapping: FieldMapping,
+    batch_size: usize,
+) -> Result<Step> {
+    let handle = collection_handle(state, &job.collection)?;
+    let shared = state.clone();
+    let running = job.clone();
+    let step = in_background(job, move || -> Result<Step> {
+        let state = shared;
+        let job = running;
+        let mut source = DocumentSource::open(&path, format, &mapping)?;
+        let mut report = match job.result.clone().map(serde_json::from_value) {
+            Some(Ok(report)) => report,
+            _ => crate::storage::collection::DocumentImportReport::default(),
+        };
+        report.skipped_columns = source.skipped_columns().to_vec();
+        source.skip(job.cursor.as_deref().and_then(|c| c.parse().ok()).unwrap_or(0))?;
+        let total = source.total();
+        state.jobs.update(&job.id, |j| j.progress = JobProgress { done: source.position(), total });
+
+        loop {
+            if state.jobs.cancel_requested(&job.id) {
+                return Ok(Step::Cancelled);
+            }
+            if state.shutting_down.load(Ordering::Relaxed) {
+                return Ok(Step::Interrupted);
+            }
+            let Some(docs) = source.next_batch(batch_size)? else { break };
+            let mut collection = handle.write();
+            let creating = docs.iter()
+                .filter(|doc| collection.get(&doc.id).is_none() && doc.external_id().is_none_or(|ext| collection.resolve_id(ext).is_none()))
+                .count();
+            state.ensure_project_quota(&job.collection, collection.count(), creating)?;
+            let (inserted, updated) = write_documents(&mut collection, docs)?;
+            drop(collection);
+            report.inserted += inserted;
+            report.updated += updated;
+            report.rows = source.position();
+            let summary = serde_json::to_value(&report).ok();
+            state.jobs.update(&job.id, |j| {
+                j.progress.done = report.rows;
+                j.cursor = Some(report.rows.to_string());
+                j.result = summary;
+            });
+        }
+        Ok(Step::Done(JobOutput::ImportedDocuments(report)))
+    })
+    .await??;
+    state.enforce_cache_budget();
+    Ok(step)
+}
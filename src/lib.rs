@@ -2,9 +2,12 @@
 // - Core: storage, metrics, metadata, query, search
 // - Server: HTTP API (axum-based, modular)
 // - Error handling: thiserror-based Result types
+// - Testing: fixtures, a frozen clock, seeded ids and WAL fault injection
 
 pub mod config;
 pub mod metrics;
+pub mod parallel;
+pub mod scheduler;
 pub mod error;
 pub mod validation;
 pub mod metadata;
@@ -15,6 +18,10 @@ pub mod embeddings;
 pub mod index;
 pub mod quantization;
 pub mod cli;
+pub mod cluster;
+pub mod ingest;
+pub mod jobs;
+pub mod testing;
 
 pub use config::*;
 pub use metrics::Metric;
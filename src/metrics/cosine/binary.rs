@@ -1,5 +1,5 @@
 // Binary quantization for cosine similarity
-// Uses 1-bit quantized vectors for ultra-fast computation
+// The cosine of the sign vectors (+1 for x >= 0, -1 otherwise): 1 - 2 * hamming / d, in [-1, 1]
 // Trade-off: Lower precision but 32x memory reduction and much faster
 
 pub fn cosine_similarity_binary(a: &[f32], b: &[f32]) -> f32 {
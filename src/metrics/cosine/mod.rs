@@ -27,6 +27,8 @@ pub fn cosine_similarity(a: &[f32], b: &[f32], mode: ExecutionMode) -> f32 {
         ExecutionMode::Parallel => cosine_similarity_parallel(a, b),
         ExecutionMode::Binary => cosine_similarity_binary(a, b),
         ExecutionMode::Jit => cosine_similarity_jit(a, b),
+        // Re-rank stage of a binary prefilter: exact
+        ExecutionMode::BinaryRerank => cosine_similarity(a, b, mode.exact()),
         _ => cosine_similarity_scalar(a, b),
     }
 }
@@ -1,19 +1,20 @@
 // Binary quantization for dot product
+// The dot product of the sign vectors (+1 for x >= 0, -1 otherwise): dimensions whose signs agree
+// minus dimensions whose signs differ, in [-d, d]. Equals d * the binary cosine similarity.
 
 pub fn dot_product_binary(a: &[f32], b: &[f32]) -> f32 {
     assert_eq!(a.len(), b.len(), "Vectors must have same length");
     
-    let mut count_both_positive = 0u32;
+    let mut hamming_distance = 0u32;
     
     for i in 0..a.len() {
         let bit_a = if a[i] >= 0.0 { 1u8 } else { 0u8 };
         let bit_b = if b[i] >= 0.0 { 1u8 } else { 0u8 };
         
-        if bit_a == 1 && bit_b == 1 {
-            count_both_positive += 1;
+        if bit_a != bit_b {
+            hamming_distance += 1;
         }
     }
     
-    // Approximate dot product from bit agreement
-    count_both_positive as f32
+    a.len() as f32 - 2.0 * hamming_distance as f32
 }
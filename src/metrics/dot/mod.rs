@@ -22,6 +22,8 @@ pub fn dot_product(a: &[f32], b: &[f32], mode: ExecutionMode) -> f32 {
         ExecutionMode::Parallel => dot_product_parallel(a, b),
         ExecutionMode::Binary => dot_product_binary(a, b),
         ExecutionMode::Jit => dot_product_jit(a, b),
+        // Re-rank stage of a binary prefilter: exact
+        ExecutionMode::BinaryRerank => dot_product(a, b, mode.exact()),
         _ => dot_product_scalar(a, b),
     }
 }
@@ -1,21 +1,23 @@
 // Parallel implementation of dot product
-// Uses rayon for multi-threaded computation
+// Uses rayon for multi-threaded computation, on the pool of the caller (the query pool by default)
 
 use rayon::prelude::*;
 
 pub fn dot_product_parallel(a: &[f32], b: &[f32]) -> f32 {
     assert_eq!(a.len(), b.len(), "Vectors must have same length");
-    
-    let chunk_size = (a.len() / num_cpus::get()).max(1024);
-    
-    a.par_chunks(chunk_size)
-        .zip(b.par_chunks(chunk_size))
-        .map(|(chunk_a, chunk_b)| {
-            let mut sum = 0.0;
-            for i in 0..chunk_a.len() {
-                sum += chunk_a[i] * chunk_b[i];
-            }
-            sum
-        })
-        .sum()
+
+    crate::parallel::current(|| {
+        let chunk_size = (a.len() / rayon::current_num_threads()).max(1024);
+
+        a.par_chunks(chunk_size)
+            .zip(b.par_chunks(chunk_size))
+            .map(|(chunk_a, chunk_b)| {
+                let mut sum = 0.0;
+                for i in 0..chunk_a.len() {
+                    sum += chunk_a[i] * chunk_b[i];
+                }
+                sum
+            })
+            .sum()
+    })
 }
@@ -1,5 +1,6 @@
 // Binary quantization for Euclidean distance
-// Uses 1-bit quantized vectors
+// The square root of the Hamming distance between the sign codes (the number of dimensions whose
+// signs differ); the squared variant is the Hamming distance itself
 
 pub fn euclidean_distance_binary(a: &[f32], b: &[f32]) -> f32 {
     assert_eq!(a.len(), b.len(), "Vectors must have same length");
@@ -22,6 +22,8 @@ pub fn euclidean_distance(a: &[f32], b: &[f32], mode: ExecutionMode) -> f32 {
         ExecutionMode::Parallel => euclidean_distance_parallel(a, b),
         ExecutionMode::Binary => euclidean_distance_binary(a, b),
         ExecutionMode::Jit => euclidean_distance_jit(a, b),
+        // Re-rank stage of a binary prefilter: exact
+        ExecutionMode::BinaryRerank => euclidean_distance(a, b, mode.exact()),
         _ => euclidean_distance_scalar(a, b),
     }
 }
@@ -34,6 +36,8 @@ pub fn euclidean_distance_squared(a: &[f32], b: &[f32], mode: ExecutionMode) ->
         ExecutionMode::Parallel => euclidean_distance_squared_parallel(a, b),
         ExecutionMode::Binary => euclidean_distance_squared_binary(a, b),
         ExecutionMode::Jit => euclidean_distance_squared_jit(a, b),
+        // Re-rank stage of a binary prefilter: exact
+        ExecutionMode::BinaryRerank => euclidean_distance_squared(a, b, mode.exact()),
         _ => euclidean_distance_squared_scalar(a, b),
     }
 }
@@ -0,0 +1,157 @@
+// Lock-free latency histogram with HDR-style log-linear buckets.
+// Values (microseconds) below 64 get one bucket each; above that every power of two is split into
+// 32 sub-buckets, so any recorded value is reported within ~3% while the whole range up to ~12 days
+// fits in a fixed array of counters. Recording is a couple of relaxed atomic adds, cheap enough for
+// every request, and quantiles are read by walking the counters.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+const SUB_BUCKET_BITS: u32 = 5;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS; // 32 per power of two
+const LINEAR_LIMIT: u64 = SUB_BUCKETS * 2; // values below this get their own bucket
+const MAX_SHIFT: u32 = 35; // largest tracked value is just under 64 << 35 us
+const BUCKETS: usize = (LINEAR_LIMIT + MAX_SHIFT as u64 * SUB_BUCKETS) as usize;
+
+fn bucket_index(value: u64) -> usize {
+    if value < LINEAR_LIMIT {
+        return value as usize;
+    }
+    let exponent = 63 - value.leading_zeros();
+    let shift = (exponent - SUB_BUCKET_BITS).min(MAX_SHIFT);
+    let mantissa = (value >> shift).min(LINEAR_LIMIT - 1) - SUB_BUCKETS;
+    (LINEAR_LIMIT + (shift as u64 - 1) * SUB_BUCKETS + mantissa) as usize
+}
+
+// Largest value that lands in the bucket (what HDR histograms report for a percentile)
+fn bucket_upper(index: usize) -> u64 {
+    let index = index as u64;
+    if index < LINEAR_LIMIT {
+        return index;
+    }
+    let shift = (index - LINEAR_LIMIT) / SUB_BUCKETS + 1;
+    let mantissa = (index - LINEAR_LIMIT) % SUB_BUCKETS + SUB_BUCKETS;
+    ((mantissa + 1) << shift) - 1
+}
+
+pub struct LatencyHistogram {
+    counts: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum_us: AtomicU64,
+    max_us: AtomicU64,
+}
+
+// On-disk form: only the non-empty buckets
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistogramState {
+    pub buckets: Vec<(u32, u64)>,
+    pub sum_us: u64,
+    pub max_us: u64,
+}
+
+impl std::fmt::Debug for LatencyHistogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LatencyHistogram")
+            .field("count", &self.count())
+            .field("max_us", &self.max_us.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self {
+            counts: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_us: AtomicU64::new(0),
+            max_us: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record_us(&self, value: u64) {
+        self.counts[bucket_index(value)].fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(value, Ordering::Relaxed);
+        self.max_us.fetch_max(value, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Forget every recorded value
+    pub fn reset(&self) {
+        for count in self.counts.iter() {
+            count.store(0, Ordering::Relaxed);
+        }
+        self.count.store(0, Ordering::Relaxed);
+        self.sum_us.store(0, Ordering::Relaxed);
+        self.max_us.store(0, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    pub fn sum_us(&self) -> u64 {
+        self.sum_us.load(Ordering::Relaxed)
+    }
+
+    pub fn max_us(&self) -> u64 {
+        self.max_us.load(Ordering::Relaxed)
+    }
+
+    pub fn mean_us(&self) -> Option<f64> {
+        let count = self.count();
+        (count > 0).then(|| self.sum_us() as f64 / count as f64)
+    }
+
+    // Value at quantile `q` (0.0..=1.0), never above the largest value actually recorded
+    pub fn quantile_us(&self, q: f64) -> Option<u64> {
+        let total: u64 = self.counts.iter().map(|c| c.load(Ordering::Relaxed)).sum();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, counter) in self.counts.iter().enumerate() {
+            seen += counter.load(Ordering::Relaxed);
+            if seen >= rank {
+                return Some(bucket_upper(index).min(self.max_us()));
+            }
+        }
+        Some(self.max_us())
+    }
+
+    pub fn state(&self) -> HistogramState {
+        HistogramState {
+            buckets: self
+                .counts
+                .iter()
+                .enumerate()
+                .filter_map(|(i, c)| {
+                    let n = c.load(Ordering::Relaxed);
+                    (n > 0).then_some((i as u32, n))
+                })
+                .collect(),
+            sum_us: self.sum_us(),
+            max_us: self.max_us(),
+        }
+    }
+
+    pub fn from_state(state: &HistogramState) -> Self {
+        let histogram = Self::new();
+        for &(index, n) in &state.buckets {
+            if let Some(counter) = histogram.counts.get(index as usize) {
+                counter.store(n, Ordering::Relaxed);
+                histogram.count.fetch_add(n, Ordering::Relaxed);
+            }
+        }
+        histogram.sum_us.store(state.sum_us, Ordering::Relaxed);
+        histogram.max_us.store(state.max_us, Ordering::Relaxed);
+        histogram
+    }
+}
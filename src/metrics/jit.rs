@@ -0,0 +1,281 @@
+// Kernel cache behind ExecutionMode::Jit
+// The CPU is probed once at runtime (AVX-512F, AVX2+FMA, NEON). Each (op, dim, path) gets its own
+// kernel: the common embedding sizes are monomorphized with the dimension as a constant, so loop
+// bounds are known and the accumulators stay in registers, and the x86 paths are compiled with their
+// target features enabled. A kernel is selected the first time a key is seen and kept in a
+// process-wide cache (plus a one-entry per-thread cache in front of it), so repeated searches of a
+// collection resolve their kernel without matching again. Anything the CPU lacks falls back to
+// the next path down, ending at the portable kernel.
+
+use std::cell::Cell;
+use std::sync::OnceLock;
+
+use dashmap::DashMap;
+use serde::Serialize;
+
+use crate::config::ExecutionMode;
+
+pub type Kernel = fn(&[f32], &[f32]) -> f32;
+
+// Dimensions that get a kernel of their own; others use the length-generic one
+pub const SPECIALIZED_DIMS: [usize; 8] = [128, 256, 384, 512, 768, 1024, 1536, 3072];
+
+// Lanes of independent accumulators, enough for one AVX-512 register
+const LANES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KernelOp {
+    Cosine,
+    Dot,
+    EuclideanSquared,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CpuPath {
+    Portable, // plain Rust, vectorized for the baseline target only
+    Neon, // aarch64: NEON is part of the baseline, so this is the portable code
+    Avx2, // x86_64 with AVX2 and FMA
+    Avx512, // x86_64 with AVX-512F
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CpuFeatures {
+    pub avx2: bool,
+    pub fma: bool,
+    pub avx512f: bool,
+    pub neon: bool,
+}
+
+impl CpuFeatures {
+    fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            Self {
+                avx2: is_x86_feature_detected!("avx2"),
+                fma: is_x86_feature_detected!("fma"),
+                avx512f: is_x86_feature_detected!("avx512f"),
+                neon: false,
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            Self { neon: std::arch::is_aarch64_feature_detected!("neon"), ..Self::default() }
+        }
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+        {
+            Self::default()
+        }
+    }
+
+    // Whether kernels for `path` can run on this CPU
+    pub fn supports(&self, path: CpuPath) -> bool {
+        match path {
+            CpuPath::Portable => true,
+            CpuPath::Neon => self.neon,
+            CpuPath::Avx2 => self.avx2 && self.fma,
+            CpuPath::Avx512 => self.avx512f,
+        }
+    }
+
+    // Best path this CPU runs
+    pub fn best_path(&self) -> CpuPath {
+        [CpuPath::Avx512, CpuPath::Avx2, CpuPath::Neon]
+            .into_iter()
+            .find(|&path| self.supports(path))
+            .unwrap_or(CpuPath::Portable)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct KernelKey {
+    pub op: KernelOp,
+    pub dim: usize,
+    pub path: CpuPath,
+}
+
+static FEATURES: OnceLock<CpuFeatures> = OnceLock::new();
+static CACHE: OnceLock<DashMap<KernelKey, Kernel>> = OnceLock::new();
+
+thread_local! {
+    static LAST: Cell<Option<(KernelKey, Kernel)>> = const { Cell::new(None) };
+}
+
+pub fn cpu_features() -> CpuFeatures {
+    *FEATURES.get_or_init(CpuFeatures::detect)
+}
+
+fn cache() -> &'static DashMap<KernelKey, Kernel> {
+    CACHE.get_or_init(DashMap::new)
+}
+
+// Kernel for `op` over `dim`-dimensional vectors on the best path of this CPU
+#[inline]
+pub fn kernel(op: KernelOp, dim: usize) -> Kernel {
+    let key = KernelKey { op, dim, path: cpu_features().best_path() };
+    if let Some((last, kernel)) = LAST.get() {
+        if last == key {
+            return kernel;
+        }
+    }
+    let kernel = *cache().entry(key).or_insert_with(|| {
+        tracing::debug!(op=?op, dim, path=?key.path, specialized=SPECIALIZED_DIMS.contains(&dim), "jit_kernel_selected");
+        select(op, dim, key.path)
+    });
+    LAST.set(Some((key, kernel)));
+    kernel
+}
+
+// `$kernel::<D>` for the specialized dimensions, `$kernel::<0>` (length taken from the slices) otherwise
+macro_rules! by_dim {
+    ($kernel:ident, $dim:expr) => {
+        match $dim {
+            128 => $kernel::<128> as Kernel,
+            256 => $kernel::<256>,
+            384 => $kernel::<384>,
+            512 => $kernel::<512>,
+            768 => $kernel::<768>,
+            1024 => $kernel::<1024>,
+            1536 => $kernel::<1536>,
+            3072 => $kernel::<3072>,
+            _ => $kernel::<0>,
+        }
+    };
+}
+
+// Kernel for `op` over `dim` dimensions on `path`, stepping down to a path the CPU supports
+pub fn select(op: KernelOp, dim: usize, path: CpuPath) -> Kernel {
+    let features = cpu_features();
+    let path = [CpuPath::Avx512, CpuPath::Avx2, CpuPath::Neon, CpuPath::Portable]
+        .into_iter()
+        .find(|&p| p <= path && features.supports(p))
+        .unwrap_or(CpuPath::Portable);
+    match (op, path) {
+        (KernelOp::Cosine, CpuPath::Avx512) => by_dim!(cosine_avx512, dim),
+        (KernelOp::Cosine, CpuPath::Avx2) => by_dim!(cosine_avx2, dim),
+        (KernelOp::Cosine, _) => by_dim!(cosine_body, dim),
+        (KernelOp::Dot, CpuPath::Avx512) => by_dim!(dot_avx512, dim),
+        (KernelOp::Dot, CpuPath::Avx2) => by_dim!(dot_avx2, dim),
+        (KernelOp::Dot, _) => by_dim!(dot_body, dim),
+        (KernelOp::EuclideanSquared, CpuPath::Avx512) => by_dim!(euclidean_squared_avx512, dim),
+        (KernelOp::EuclideanSquared, CpuPath::Avx2) => by_dim!(euclidean_squared_avx2, dim),
+        (KernelOp::EuclideanSquared, _) => by_dim!(euclidean_squared_body, dim),
+    }
+}
+
+// Kernels selected so far
+pub fn cached_kernels() -> Vec<KernelKey> {
+    cache().iter().map(|entry| *entry.key()).collect()
+}
+
+// Startup log of how distances will be computed
+pub fn log_dispatch(mode: ExecutionMode) {
+    let features = cpu_features();
+    tracing::info!(
+        execution=?mode,
+        resolved=?mode.resolve(),
+        jit_path=?features.best_path(),
+        avx2=features.avx2,
+        fma=features.fma,
+        avx512f=features.avx512f,
+        neon=features.neon,
+        specialized_dims=?SPECIALIZED_DIMS,
+        "distance_kernels"
+    );
+}
+
+// The same body compiled with x86 target features enabled. Only handed out by `select` once the
+// CPU has reported them; elsewhere it is the portable body.
+macro_rules! x86_kernel {
+    ($name:ident, $body:ident, $features:literal) => {
+        #[cfg(target_arch = "x86_64")]
+        fn $name<const D: usize>(a: &[f32], b: &[f32]) -> f32 {
+            #[target_feature(enable = $features)]
+            unsafe fn inner<const D: usize>(a: &[f32], b: &[f32]) -> f32 {
+                $body::<D>(a, b)
+            }
+            // Safe: select() only returns this kernel when the features were detected
+            unsafe { inner::<D>(a, b) }
+        }
+
+        #[cfg(not(target_arch = "x86_64"))]
+        fn $name<const D: usize>(a: &[f32], b: &[f32]) -> f32 {
+            $body::<D>(a, b)
+        }
+    };
+}
+
+x86_kernel!(cosine_avx2, cosine_body, "avx2,fma");
+x86_kernel!(cosine_avx512, cosine_body, "avx512f");
+x86_kernel!(dot_avx2, dot_body, "avx2,fma");
+x86_kernel!(dot_avx512, dot_body, "avx512f");
+x86_kernel!(euclidean_squared_avx2, euclidean_squared_body, "avx2,fma");
+x86_kernel!(euclidean_squared_avx512, euclidean_squared_body, "avx512f");
+
+#[inline(always)]
+fn len<const D: usize>(a: &[f32], b: &[f32]) -> usize {
+    assert_eq!(a.len(), b.len(), "Vectors must have same length");
+    if D == 0 {
+        a.len()
+    } else {
+        assert_eq!(a.len(), D, "Kernel dimension mismatch");
+        D
+    }
+}
+
+#[inline(always)]
+fn dot_body<const D: usize>(a: &[f32], b: &[f32]) -> f32 {
+    let n = len::<D>(a, b);
+    let (a, b) = (&a[..n], &b[..n]);
+    let mut acc = [0.0f32; LANES];
+    for (ca, cb) in a.chunks_exact(LANES).zip(b.chunks_exact(LANES)) {
+        for l in 0..LANES {
+            acc[l] += ca[l] * cb[l];
+        }
+    }
+    let tail = n - n % LANES;
+    acc.iter().sum::<f32>() + a[tail..].iter().zip(&b[tail..]).map(|(x, y)| x * y).sum::<f32>()
+}
+
+#[inline(always)]
+fn euclidean_squared_body<const D: usize>(a: &[f32], b: &[f32]) -> f32 {
+    let n = len::<D>(a, b);
+    let (a, b) = (&a[..n], &b[..n]);
+    let mut acc = [0.0f32; LANES];
+    for (ca, cb) in a.chunks_exact(LANES).zip(b.chunks_exact(LANES)) {
+        for l in 0..LANES {
+            let d = ca[l] - cb[l];
+            acc[l] += d * d;
+        }
+    }
+    let tail = n - n % LANES;
+    acc.iter().sum::<f32>() + a[tail..].iter().zip(&b[tail..]).map(|(x, y)| (x - y) * (x - y)).sum::<f32>()
+}
+
+#[inline(always)]
+fn cosine_body<const D: usize>(a: &[f32], b: &[f32]) -> f32 {
+    let n = len::<D>(a, b);
+    let (a, b) = (&a[..n], &b[..n]);
+    let (mut dot, mut norm_a, mut norm_b) = ([0.0f32; LANES], [0.0f32; LANES], [0.0f32; LANES]);
+    for (ca, cb) in a.chunks_exact(LANES).zip(b.chunks_exact(LANES)) {
+        for l in 0..LANES {
+            dot[l] += ca[l] * cb[l];
+            norm_a[l] += ca[l] * ca[l];
+            norm_b[l] += cb[l] * cb[l];
+        }
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (dot.iter().sum::<f32>(), norm_a.iter().sum::<f32>(), norm_b.iter().sum::<f32>());
+    for i in n - n % LANES..n {
+        dot += a[i] * b[i];
+        norm_a += a[i] * a[i];
+        norm_b += b[i] * b[i];
+    }
+
+    let denominator = norm_a.sqrt() * norm_b.sqrt();
+    if denominator == 0.0 {
+        0.0
+    } else {
+        dot / denominator
+    }
+}
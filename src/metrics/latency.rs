@@ -1,26 +1,71 @@
 // Operation latency tracking for metrics
+// Every operation keeps a full latency histogram (see histogram.rs) rather than a moving average,
+// so tail latencies (p99, p99.9) are visible and not smoothed away.
 
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use serde::{Deserialize, Serialize};
+
+use super::histogram::{HistogramState, LatencyHistogram};
+use crate::error::Result;
+
+// Operations with their own latency histogram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyOp {
+    Insert,
+    Search,
+    Delete,
+    Update,
+    LockRead,
+    LockWrite,
+}
+
+impl LatencyOp {
+    pub const ALL: [LatencyOp; 6] = [
+        LatencyOp::Insert,
+        LatencyOp::Search,
+        LatencyOp::Delete,
+        LatencyOp::Update,
+        LatencyOp::LockRead,
+        LatencyOp::LockWrite,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LatencyOp::Insert => "insert",
+            LatencyOp::Search => "search",
+            LatencyOp::Delete => "delete",
+            LatencyOp::Update => "update",
+            LatencyOp::LockRead => "lock_read",
+            LatencyOp::LockWrite => "lock_write",
+        }
+    }
+}
+
+// Quantiles of one operation, in milliseconds
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub mean_ms: f32,
+    pub p50_ms: f32,
+    pub p90_ms: f32,
+    pub p99_ms: f32,
+    pub p999_ms: f32,
+    pub max_ms: f32,
+}
+
+// Per-operation histograms. Clones share the same counters.
 #[derive(Debug, Clone)]
 pub struct LatencyTracker {
-    // Moving average of operation latencies (in microseconds)
-    insert_latency_us: Arc<AtomicU64>,
-    search_latency_us: Arc<AtomicU64>,
-    delete_latency_us: Arc<AtomicU64>,
-    update_latency_us: Arc<AtomicU64>,
-    lock_read_latency_us: Arc<AtomicU64>,
-    lock_write_latency_us: Arc<AtomicU64>,
-    
-    // Operation counts
-    insert_count: Arc<AtomicU64>,
-    search_count: Arc<AtomicU64>,
-    delete_count: Arc<AtomicU64>,
-    update_count: Arc<AtomicU64>,
-    lock_read_count: Arc<AtomicU64>,
-    lock_write_count: Arc<AtomicU64>,
+    histograms: Arc<[LatencyHistogram; 6]>,
+}
+
+// On-disk form used to carry histograms across restarts
+#[derive(Serialize, Deserialize, Default)]
+struct TrackerState {
+    operations: Vec<(String, HistogramState)>,
 }
 
 impl Default for LatencyTracker {
@@ -29,136 +74,139 @@ impl Default for LatencyTracker {
     }
 }
 
+fn us_to_ms(us: f64) -> f32 {
+    (us / 1000.0) as f32
+}
+
 impl LatencyTracker {
     pub fn new() -> Self {
         Self {
-            insert_latency_us: Arc::new(AtomicU64::new(0)),
-            search_latency_us: Arc::new(AtomicU64::new(0)),
-            delete_latency_us: Arc::new(AtomicU64::new(0)),
-            update_latency_us: Arc::new(AtomicU64::new(0)),
-            lock_read_latency_us: Arc::new(AtomicU64::new(0)),
-            lock_write_latency_us: Arc::new(AtomicU64::new(0)),
-            insert_count: Arc::new(AtomicU64::new(0)),
-            search_count: Arc::new(AtomicU64::new(0)),
-            delete_count: Arc::new(AtomicU64::new(0)),
-            update_count: Arc::new(AtomicU64::new(0)),
-            lock_read_count: Arc::new(AtomicU64::new(0)),
-            lock_write_count: Arc::new(AtomicU64::new(0)),
+            histograms: Arc::new(std::array::from_fn(|_| LatencyHistogram::new())),
         }
     }
-    
+
+    pub fn histogram(&self, op: LatencyOp) -> &LatencyHistogram {
+        &self.histograms[op as usize]
+    }
+
+    pub fn record(&self, op: LatencyOp, duration: Duration) {
+        self.histogram(op).record_us(duration.as_micros() as u64);
+    }
+
+    // Empty every histogram; clones see the reset too
+    pub fn reset(&self) {
+        for histogram in self.histograms.iter() {
+            histogram.reset();
+        }
+    }
+
     // Record insert operation latency
     pub fn record_insert(&self, duration: Duration) {
-        self.insert_count.fetch_add(1, Ordering::Relaxed);
-        let us = duration.as_micros() as u64;
-        self.update_moving_average(&self.insert_latency_us, us, &self.insert_count);
+        self.record(LatencyOp::Insert, duration);
     }
     
     // Record search operation latency
     pub fn record_search(&self, duration: Duration) {
-        self.search_count.fetch_add(1, Ordering::Relaxed);
-        let us = duration.as_micros() as u64;
-        self.update_moving_average(&self.search_latency_us, us, &self.search_count);
+        self.record(LatencyOp::Search, duration);
     }
     
     // Record delete operation latency
     pub fn record_delete(&self, duration: Duration) {
-        self.delete_count.fetch_add(1, Ordering::Relaxed);
-        let us = duration.as_micros() as u64;
-        self.update_moving_average(&self.delete_latency_us, us, &self.delete_count);
+        self.record(LatencyOp::Delete, duration);
     }
     
     // Record update operation latency
     pub fn record_update(&self, duration: Duration) {
-        self.update_count.fetch_add(1, Ordering::Relaxed);
-        let us = duration.as_micros() as u64;
-        self.update_moving_average(&self.update_latency_us, us, &self.update_count);
+        self.record(LatencyOp::Update, duration);
     }
 
     pub fn record_lock_read(&self, duration: Duration) {
-        self.lock_read_count.fetch_add(1, Ordering::Relaxed);
-        let us = duration.as_micros() as u64;
-        self.update_moving_average(&self.lock_read_latency_us, us, &self.lock_read_count);
+        self.record(LatencyOp::LockRead, duration);
     }
 
     pub fn record_lock_write(&self, duration: Duration) {
-        self.lock_write_count.fetch_add(1, Ordering::Relaxed);
-        let us = duration.as_micros() as u64;
-        self.update_moving_average(&self.lock_write_latency_us, us, &self.lock_write_count);
+        self.record(LatencyOp::LockWrite, duration);
+    }
+
+    // Mean latency in milliseconds, None until the operation has been recorded
+    pub fn avg_latency_ms(&self, op: LatencyOp) -> Option<f32> {
+        self.histogram(op).mean_us().map(us_to_ms)
+    }
+
+    // Latency at quantile `q` (e.g. 0.99) in milliseconds
+    pub fn quantile_ms(&self, op: LatencyOp, q: f64) -> Option<f32> {
+        self.histogram(op).quantile_us(q).map(|us| us_to_ms(us as f64))
+    }
+
+    pub fn summary(&self, op: LatencyOp) -> Option<LatencySummary> {
+        let histogram = self.histogram(op);
+        let quantile = |q| self.quantile_ms(op, q).unwrap_or(0.0);
+        Some(LatencySummary {
+            count: histogram.count(),
+            mean_ms: us_to_ms(histogram.mean_us()?),
+            p50_ms: quantile(0.5),
+            p90_ms: quantile(0.9),
+            p99_ms: quantile(0.99),
+            p999_ms: quantile(0.999),
+            max_ms: us_to_ms(histogram.max_us() as f64),
+        })
     }
     
-    // Get average insert latency in milliseconds
     pub fn avg_insert_latency_ms(&self) -> Option<f32> {
-        let us = self.insert_latency_us.load(Ordering::Relaxed);
-        if us > 0 {
-            Some(us as f32 / 1000.0)
-        } else {
-            None
-        }
+        self.avg_latency_ms(LatencyOp::Insert)
     }
     
-    // Get average search latency in milliseconds
     pub fn avg_search_latency_ms(&self) -> Option<f32> {
-        let us = self.search_latency_us.load(Ordering::Relaxed);
-        if us > 0 {
-            Some(us as f32 / 1000.0)
-        } else {
-            None
-        }
+        self.avg_latency_ms(LatencyOp::Search)
     }
     
-    // Get average delete latency in milliseconds
     pub fn avg_delete_latency_ms(&self) -> Option<f32> {
-        let us = self.delete_latency_us.load(Ordering::Relaxed);
-        if us > 0 {
-            Some(us as f32 / 1000.0)
-        } else {
-            None
-        }
+        self.avg_latency_ms(LatencyOp::Delete)
     }
     
-    // Get average update latency in milliseconds
     pub fn avg_update_latency_ms(&self) -> Option<f32> {
-        let us = self.update_latency_us.load(Ordering::Relaxed);
-        if us > 0 {
-            Some(us as f32 / 1000.0)
-        } else {
-            None
-        }
+        self.avg_latency_ms(LatencyOp::Update)
     }
 
     pub fn avg_lock_read_latency_ms(&self) -> Option<f32> {
-        let us = self.lock_read_latency_us.load(Ordering::Relaxed);
-        if us > 0 {
-            Some(us as f32 / 1000.0)
-        } else {
-            None
-        }
+        self.avg_latency_ms(LatencyOp::LockRead)
     }
 
     pub fn avg_lock_write_latency_ms(&self) -> Option<f32> {
-        let us = self.lock_write_latency_us.load(Ordering::Relaxed);
-        if us > 0 {
-            Some(us as f32 / 1000.0)
-        } else {
-            None
-        }
+        self.avg_latency_ms(LatencyOp::LockWrite)
     }
-    
-    // Update moving average (exponential moving average with alpha=0.2)
-    fn update_moving_average(&self, avg: &AtomicU64, new_value: u64, count: &AtomicU64) {
-        let current = avg.load(Ordering::Relaxed);
-        let cnt = count.load(Ordering::Relaxed);
-        
-        // For first few samples, use simple average
-        if cnt <= 5 {
-            let new_avg = ((current * (cnt - 1)) + new_value) / cnt;
-            avg.store(new_avg, Ordering::Relaxed);
-        } else {
-            // Exponential moving average: new_avg = 0.8 * old_avg + 0.2 * new_value
-            let new_avg = ((current * 4) + new_value) / 5;
-            avg.store(new_avg, Ordering::Relaxed);
-        }
+
+    // Write every histogram to `path` (JSON, written to a temp file and renamed)
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let state = TrackerState {
+            operations: LatencyOp::ALL
+                .iter()
+                .map(|op| (op.name().to_string(), self.histogram(*op).state()))
+                .collect(),
+        };
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&state)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    // Restore histograms saved by `save`; a missing file gives an empty tracker
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(_) => return Ok(Self::new()),
+        };
+        let state: TrackerState = serde_json::from_slice(&data)?;
+        let histograms = std::array::from_fn(|i| {
+            let name = LatencyOp::ALL[i].name();
+            state
+                .operations
+                .iter()
+                .find(|(op, _)| op == name)
+                .map(|(_, h)| LatencyHistogram::from_state(h))
+                .unwrap_or_default()
+        });
+        Ok(Self { histograms: Arc::new(histograms) })
     }
 }
 
@@ -8,12 +8,15 @@ pub mod cosine;
 pub mod euclidean;
 pub mod dot;
 pub mod latency;
+pub mod histogram;
 pub mod embed;
+pub mod jit;
 
 pub use cosine::cosine_similarity;
 pub use euclidean::{euclidean_distance, euclidean_distance_squared};
 pub use dot::dot_product;
-pub use latency::{LatencyTracker, time_operation, time_operation_sync};
+pub use latency::{LatencyTracker, LatencyOp, LatencySummary, time_operation, time_operation_sync};
+pub use histogram::{LatencyHistogram, HistogramState};
 pub use embed::{EmbedMetrics, EmbedMetricsSnapshot};
 
 use crate::config::ExecutionMode;
@@ -23,7 +26,7 @@ use crate::config::ExecutionMode;
 // Different metrics have different semantics:
 // - Similarity metrics (Cosine, DotProduct): higher = more similar
 // - Distance metrics (Euclidean): lower = more similar
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize)]
 pub enum Metric {
     #[default]
     Cosine,
@@ -42,4 +45,22 @@ impl Metric {
             Metric::DotProduct => dot_product(a, b, mode),
         }
     }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Cosine => "cosine",
+            Metric::Euclidean => "euclidean",
+            Metric::DotProduct => "dot_product",
+        }
+    }
+
+    // Parse a metric name as accepted by the HTTP API
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "cosine" => Some(Metric::Cosine),
+            "euclidean" => Some(Metric::Euclidean),
+            "dot" | "dot_product" => Some(Metric::DotProduct),
+            _ => None,
+        }
+    }
 }
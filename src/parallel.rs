@@ -0,0 +1,98 @@
+// Rayon thread pools.
+// Searches run on the query pool, and background work (index builds, projection training, vector
+// statistics) on the maintenance pool, so a rebuild cannot take the threads a search is waiting for.
+// Both are built once per process from the first parallelism config they are asked for (the first
+// collection opened, or the server's), and can be pinned to a range of cores.
+//
+// Work already running on a pool thread stays on that pool: the parallel metric kernels and nested
+// parallel iterators use whichever pool called them, and fall back to the query pool otherwise.
+
+use std::sync::OnceLock;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use crate::config::{CoreRange, ParallelismConfig};
+
+struct Pools {
+    query: ThreadPool,
+    maintenance: ThreadPool,
+}
+
+static POOLS: OnceLock<Pools> = OnceLock::new();
+
+// Build the pools; later calls, with any config, keep the pools already built
+pub fn init(config: &ParallelismConfig) {
+    POOLS.get_or_init(|| build(config));
+}
+
+fn pools() -> &'static Pools {
+    POOLS.get_or_init(|| build(&ParallelismConfig::default()))
+}
+
+fn build(config: &ParallelismConfig) -> Pools {
+    Pools {
+        query: build_pool("piramid-query", config.query_threads(), config.query_cores),
+        maintenance: build_pool("piramid-maintenance", config.maintenance_threads(), config.maintenance_cores),
+    }
+}
+
+fn build_pool(name: &'static str, threads: usize, cores: Option<CoreRange>) -> ThreadPool {
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(move |i| format!("{}-{}", name, i))
+        .start_handler(move |_| {
+            if let Some(cores) = cores {
+                pin(cores);
+            }
+        })
+        .build()
+        .unwrap_or_else(|e| panic!("failed to start the {} thread pool: {}", name, e))
+}
+
+#[cfg(target_os = "linux")]
+fn pin(cores: CoreRange) {
+    // SAFETY: cpu_set_t is plain data, and CPU_SET is only given cores inside the set
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for core in cores.first..=cores.last.min(libc::CPU_SETSIZE as usize - 1) {
+            libc::CPU_SET(core, &mut set);
+        }
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            tracing::warn!(
+                first = cores.first,
+                last = cores.last,
+                error = %std::io::Error::last_os_error(),
+                "thread_pinning_failed"
+            );
+        }
+    }
+}
+
+// Affinity is only applied on Linux
+#[cfg(not(target_os = "linux"))]
+fn pin(_cores: CoreRange) {}
+
+// Run `op` on the query pool
+pub fn query<R: Send>(op: impl FnOnce() -> R + Send) -> R {
+    pools().query.install(op)
+}
+
+// Run `op` on the maintenance pool
+pub fn maintenance<R: Send>(op: impl FnOnce() -> R + Send) -> R {
+    pools().maintenance.install(op)
+}
+
+// Run `op` on the pool the caller is on, or the query pool when it is on none
+pub fn current<R: Send>(op: impl FnOnce() -> R + Send) -> R {
+    if rayon::current_thread_index().is_some() {
+        op()
+    } else {
+        query(op)
+    }
+}
+
+pub fn query_threads() -> usize {
+    pools().query.current_num_threads()
+}
+
+pub fn maintenance_threads() -> usize {
+    pools().maintenance.current_num_threads()
+}
@@ -5,6 +5,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::config::QuantizationConfig;
+use crate::metrics::Metric;
 
 // Tracks which encoding is used; defaults to Scalar so old checkpoints still load.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -110,7 +111,7 @@ impl ProductQuantizedVector {
 
         let dim = vector.len();
         let subquantizers = subquantizers.max(1).min(dim);
-        let block_len = (dim + subquantizers - 1) / subquantizers;
+        let block_len = dim.div_ceil(subquantizers);
 
         let mut codes = Vec::with_capacity(dim);
         let mut block_mins = Vec::with_capacity(subquantizers);
@@ -153,7 +154,7 @@ impl ProductQuantizedVector {
         }
 
         let mut values = Vec::with_capacity(self.dim);
-        let block_len = (self.dim + self.subquantizers - 1) / self.subquantizers;
+        let block_len = self.dim.div_ceil(self.subquantizers);
         let mut idx = 0;
 
         for block_idx in 0..self.subquantizers {
@@ -208,6 +209,24 @@ impl QuantizedVector {
         }
     }
 
+    // Name of the stored encoding: "int8", or "pq" with its subquantizers (e.g. "pq8")
+    pub fn encoding(&self) -> String {
+        match (self.kind, self.pq.as_ref()) {
+            (QuantizationKind::Pq, Some(pq)) => format!("pq{}", pq.subquantizers),
+            _ => "int8".to_string(),
+        }
+    }
+
+    // Name of the encoding `cfg` gives a vector of `dim` values; every level but PQ stores int8 codes
+    pub fn encoding_for(cfg: &QuantizationConfig, dim: usize) -> String {
+        match cfg.level {
+            crate::config::QuantizationLevel::Pq { subquantizers } if dim > 0 => {
+                format!("pq{}", subquantizers.max(1).min(dim))
+            }
+            _ => "int8".to_string(),
+        }
+    }
+
     fn from_scalar(vector: &[f32]) -> Self {
         let scalar = ScalarQuantizedVector::from_f32(vector);
         QuantizedVector {
@@ -263,4 +282,63 @@ impl QuantizedVector {
                 .unwrap_or(self.values.len()),
         }
     }
+
+    // Score a float32 query against the codes without dequantizing them (asymmetric distance).
+    // Every code decodes as `code * scale + offset` within its block (the whole vector for scalar
+    // codes), so dot(q, x) and |x|^2 fall out of per-block sums over the raw codes.
+    // Same score conventions as Metric::calculate.
+    pub fn approx_score(&self, query: &[f32], metric: Metric) -> f32 {
+        let mut dot = 0.0f32;
+        let mut norm_sq = 0.0f32;
+        let mut accumulate = |q: &[f32], codes: &mut dyn Iterator<Item = f32>, scale: f32, offset: f32| {
+            let (mut qc, mut qs, mut cc, mut cs, mut n) = (0.0f32, 0.0f32, 0.0f32, 0.0f32, 0.0f32);
+            for (qi, ci) in q.iter().zip(codes) {
+                qc += qi * ci;
+                qs += qi;
+                cc += ci * ci;
+                cs += ci;
+                n += 1.0;
+            }
+            dot += scale * qc + offset * qs;
+            norm_sq += scale * scale * cc + 2.0 * scale * offset * cs + n * offset * offset;
+        };
+
+        match (self.kind, self.pq.as_ref()) {
+            (QuantizationKind::Pq, Some(pq)) if pq.subquantizers > 0 => {
+                let block_len = pq.dim.div_ceil(pq.subquantizers);
+                for (block_idx, (lo, hi)) in pq.block_mins.iter().zip(&pq.block_maxs).enumerate() {
+                    let start = block_idx * block_len;
+                    let end = (start + block_len).min(pq.dim).min(query.len());
+                    if start >= end {
+                        break;
+                    }
+                    let scale = (hi - lo).max(f32::EPSILON) / 255.0;
+                    let mut codes = pq.codes[start..end].iter().map(|&c| c as f32);
+                    accumulate(&query[start..end], &mut codes, scale, *lo);
+                }
+            }
+            _ => {
+                // Flat vectors decode to `min` everywhere, which scale = 0 expresses directly
+                let range = self.max - self.min;
+                let scale = if range.abs() < f32::EPSILON { 0.0 } else { range / 254.0 };
+                let offset = self.min + 127.0 * scale;
+                let mut codes = self.values.iter().map(|&c| c as f32);
+                accumulate(query, &mut codes, scale, offset);
+            }
+        }
+
+        match metric {
+            Metric::DotProduct => dot,
+            Metric::Cosine => {
+                let query_norm = query.iter().map(|x| x * x).sum::<f32>().sqrt();
+                let denom = query_norm * norm_sq.max(0.0).sqrt();
+                if denom == 0.0 { 0.0 } else { dot / denom }
+            }
+            Metric::Euclidean => {
+                let query_sq = query.iter().map(|x| x * x).sum::<f32>();
+                let dist = (query_sq - 2.0 * dot + norm_sq).max(0.0).sqrt();
+                1.0 / (1.0 + dist)
+            }
+        }
+    }
 }
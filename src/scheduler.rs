@@ -0,0 +1,270 @@
+// Background task scheduler.
+// Checkpoint writes, index recovery, cache warming and the blocking steps of jobs (rebuilds,
+// compactions, imports, re-embeds) are all submitted here instead of each caller spawning its own
+// thread, so how much of the machine they take at once is set in one place. Each task runs on a
+// thread of its own once it is admitted; the rest wait in order of priority, then submission.
+//
+// - High: checkpoints and index recovery. Admitted at once, whatever else is running: holding a
+//   checkpoint back only lets the WAL grow, and a recovering collection is not searchable.
+// - Normal: cache warming and jobs someone is waiting on. At most `max_concurrent` normal- and
+//   low-priority tasks run at once.
+// - Low: queued index rebuilds and compactions. At most `max_low_priority` of them run at once, and
+//   with an off-peak window they only start inside it (checked again every TICK while any wait).
+//
+// A task may wait on a high-priority one (a compaction waits for the checkpoint being written),
+// but never on one that could be queued behind it.
+//
+// Work done for a request that is waiting on it (sealing, exports) stays on tokio's blocking pool.
+// The parallel parts of a task still run on the rayon pools of `crate::parallel`.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use crate::config::SchedulerConfig;
+
+const TICK: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Priority::High => "high",
+            Priority::Normal => "normal",
+            Priority::Low => "low",
+        }
+    }
+
+    fn slot(self) -> usize {
+        self as usize
+    }
+}
+
+struct Queued {
+    priority: Priority,
+    seq: u64,
+    name: &'static str,
+    run: Box<dyn FnOnce() + Send>,
+}
+
+// Max-heap order: the highest priority first, then the oldest
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.cmp(&self.priority).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+struct Scheduler {
+    config: SchedulerConfig,
+    queue: BinaryHeap<Queued>,
+    running: [usize; 3], // by Priority::slot
+    next_seq: u64,
+    ticking: bool, // a thread is re-checking the off-peak window for waiting low-priority tasks
+}
+
+static SCHEDULER: OnceLock<Mutex<Scheduler>> = OnceLock::new();
+
+fn scheduler() -> &'static Mutex<Scheduler> {
+    SCHEDULER.get_or_init(|| {
+        Mutex::new(Scheduler {
+            config: SchedulerConfig::default(),
+            queue: BinaryHeap::new(),
+            running: [0; 3],
+            next_seq: 0,
+            ticking: false,
+        })
+    })
+}
+
+// Use `config` from now on; waiting tasks it admits start at once
+pub fn configure(config: &SchedulerConfig) {
+    let mut scheduler = scheduler().lock();
+    scheduler.config = *config;
+    scheduler.dispatch();
+}
+
+// Run `op` on a background thread once its priority admits it
+pub fn spawn<R, F>(priority: Priority, name: &'static str, op: F) -> Task<R>
+where
+    R: Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+{
+    let slot = Arc::new(Slot { value: Mutex::new(None), done: Condvar::new(), notify: Notify::new() });
+    let filled = slot.clone();
+    let run = Box::new(move || {
+        let value = catch_unwind(AssertUnwindSafe(op));
+        *filled.value.lock() = Some(value);
+        filled.done.notify_all();
+        filled.notify.notify_one();
+    });
+    let mut scheduler = scheduler().lock();
+    let seq = scheduler.next_seq;
+    scheduler.next_seq += 1;
+    scheduler.queue.push(Queued { priority, seq, name, run });
+    scheduler.dispatch();
+    Task { slot }
+}
+
+// Whether low-priority work may start now: always without an off-peak window, else inside it
+pub fn off_peak() -> bool {
+    scheduler().lock().off_peak()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PriorityCounts {
+    pub high: usize,
+    pub normal: usize,
+    pub low: usize,
+}
+
+impl PriorityCounts {
+    fn from_slots(slots: [usize; 3]) -> Self {
+        PriorityCounts { high: slots[0], normal: slots[1], low: slots[2] }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SchedulerStats {
+    pub running: PriorityCounts,
+    pub queued: PriorityCounts,
+    pub off_peak: bool,
+}
+
+pub fn stats() -> SchedulerStats {
+    let scheduler = scheduler().lock();
+    let mut queued = [0; 3];
+    for task in &scheduler.queue {
+        queued[task.priority.slot()] += 1;
+    }
+    SchedulerStats {
+        running: PriorityCounts::from_slots(scheduler.running),
+        queued: PriorityCounts::from_slots(queued),
+        off_peak: scheduler.off_peak(),
+    }
+}
+
+impl Scheduler {
+    fn off_peak(&self) -> bool {
+        let hour = (crate::testing::clock::now_secs() / 3600 % 24) as u8;
+        self.config.off_peak.is_none_or(|window| window.contains(hour))
+    }
+
+    fn admits(&self, priority: Priority) -> bool {
+        let [_, normal, low] = self.running;
+        match priority {
+            Priority::High => true,
+            Priority::Normal => normal + low < self.config.max_concurrent,
+            Priority::Low => normal + low < self.config.max_concurrent && low < self.config.max_low_priority && self.off_peak(),
+        }
+    }
+
+    // Start waiting tasks, best first, until the next one is not admitted
+    fn dispatch(&mut self) {
+        while self.queue.peek().is_some_and(|task| self.admits(task.priority)) {
+            let Some(task) = self.queue.pop() else { break };
+            self.running[task.priority.slot()] += 1;
+            start(task);
+        }
+        let low_waiting = self.queue.iter().any(|task| task.priority == Priority::Low);
+        if low_waiting && self.config.off_peak.is_some() && !self.ticking {
+            self.ticking = true;
+            std::thread::Builder::new()
+                .name("piramid-scheduler".into())
+                .spawn(tick)
+                .unwrap_or_else(|e| panic!("failed to start the scheduler thread: {}", e));
+        }
+    }
+}
+
+fn start(task: Queued) {
+    let Queued { priority, name, run, .. } = task;
+    std::thread::Builder::new()
+        .name(format!("piramid-{}", name))
+        .spawn(move || {
+            run();
+            let mut scheduler = scheduler().lock();
+            scheduler.running[priority.slot()] -= 1;
+            scheduler.dispatch();
+        })
+        .unwrap_or_else(|e| panic!("failed to start background task {}: {}", name, e));
+}
+
+// Re-check the off-peak window while low-priority tasks wait for it
+fn tick() {
+    loop {
+        std::thread::sleep(TICK);
+        let mut scheduler = scheduler().lock();
+        scheduler.dispatch();
+        if !scheduler.queue.iter().any(|task| task.priority == Priority::Low) {
+            scheduler.ticking = false;
+            return;
+        }
+    }
+}
+
+struct Slot<R> {
+    value: Mutex<Option<std::thread::Result<R>>>,
+    done: Condvar,
+    notify: Notify,
+}
+
+// A submitted task. Dropping it leaves the task to run on its own.
+pub struct Task<R> {
+    slot: Arc<Slot<R>>,
+}
+
+impl<R> Task<R> {
+    // Block until the task has run; Err holds its panic
+    pub fn join(self) -> std::thread::Result<R> {
+        let mut value = self.slot.value.lock();
+        loop {
+            if let Some(value) = value.take() {
+                return value;
+            }
+            self.slot.done.wait(&mut value);
+        }
+    }
+
+    // Wait for the task without blocking the async runtime
+    pub async fn wait(self) -> std::thread::Result<R> {
+        loop {
+            if let Some(value) = self.slot.value.lock().take() {
+                return value;
+            }
+            // A notification sent before this wait starts is kept, so none is missed
+            self.slot.notify.notified().await;
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.slot.value.lock().is_some()
+    }
+}
@@ -0,0 +1,93 @@
+// Score bands: search hits bucketed into named relevance tiers (e.g. high/medium/low), so clients can
+// render confidence tiers without each one keeping its own thresholds.
+//
+// Bands are listed best first, each with the lowest score it takes; a hit goes to the first band
+// whose `min_score` it reaches. The last band may leave `min_score` out to take every other hit;
+// otherwise hits below every band are left out. Hits are ordered by band, keeping their order
+// within it, so a primary `order_by` sorts each band by its field ("high relevance, newest first").
+// `only` keeps the hits of the named bands; the per-band counts are taken before it, so a client can
+// still say how many lower-relevance hits it is not showing.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, ServerError};
+
+const MAX_BANDS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBand {
+    pub name: String,
+    #[serde(default)]
+    pub min_score: Option<f32>, // lowest score in the band; only the last band may leave it out
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBands {
+    pub bands: Vec<ScoreBand>, // best first, with decreasing min_score
+    #[serde(default)]
+    pub only: Vec<String>, // keep only hits in these bands (all when empty)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BandCount {
+    pub name: String,
+    pub count: usize,
+}
+
+impl ScoreBands {
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: String| Err(ServerError::InvalidRequest(format!("score_bands: {}", msg)).into());
+        if self.bands.is_empty() || self.bands.len() > MAX_BANDS {
+            return invalid(format!("give between 1 and {} bands", MAX_BANDS));
+        }
+        let mut previous: Option<f32> = None;
+        for (i, band) in self.bands.iter().enumerate() {
+            if band.name.is_empty() {
+                return invalid("band names must not be empty".into());
+            }
+            if self.bands[..i].iter().any(|b| b.name == band.name) {
+                return invalid(format!("band '{}' is listed twice", band.name));
+            }
+            match band.min_score {
+                Some(min) if !min.is_finite() => return invalid(format!("min_score of '{}' must be a finite number", band.name)),
+                Some(min) if previous.is_some_and(|p| min >= p) => {
+                    return invalid(format!("min_score of '{}' must be below the band before it", band.name));
+                }
+                Some(min) => previous = Some(min),
+                None if i + 1 < self.bands.len() => {
+                    return invalid(format!("only the last band may leave out min_score ('{}' does)", band.name));
+                }
+                None => {}
+            }
+        }
+        if let Some(unknown) = self.only.iter().find(|name| !self.bands.iter().any(|b| &b.name == *name)) {
+            return invalid(format!("'only' names '{}', which is not a band", unknown));
+        }
+        Ok(())
+    }
+
+    // Index of the band `score` falls in
+    pub fn band_of(&self, score: f32) -> Option<usize> {
+        self.bands.iter().position(|band| band.min_score.is_none_or(|min| score >= min))
+    }
+
+    // Bucket `hits` (in result order): the kept hits with their band index, ordered by band, and
+    // every band's count before `only` was applied
+    pub fn apply<T>(&self, hits: Vec<T>, score: impl Fn(&T) -> f32) -> (Vec<(T, usize)>, Vec<BandCount>) {
+        let mut counts: Vec<BandCount> = self.bands.iter().map(|b| BandCount { name: b.name.clone(), count: 0 }).collect();
+        let mut banded: Vec<(T, usize)> = hits
+            .into_iter()
+            .filter_map(|hit| {
+                let band = self.band_of(score(&hit))?;
+                counts[band].count += 1;
+                Some((hit, band))
+            })
+            .collect();
+        if !self.only.is_empty() {
+            banded.retain(|(_, band)| self.only.contains(&self.bands[*band].name));
+        }
+        // Stable, so each band keeps the order the hits came in
+        banded.sort_by_key(|(_, band)| *band);
+        (banded, counts)
+    }
+}
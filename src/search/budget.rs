@@ -0,0 +1,79 @@
+// Latency-target search: ef (HNSW) or nprobe (IVF) picked per query so that a search fits a time
+// budget. Recall grows with that effort, so the best value under a budget is the largest one whose
+// predicted latency fits. The prediction is a moving average of the time a search took per unit of
+// effort, kept per collection and updated after every budgeted search. Fixed costs (reading the hits
+// back) are folded into the average, which makes it settle on the effort whose latency is the target.
+//
+// The first budgeted search of an index uses the configured value. After that each search moves the
+// effort by at most MAX_STEP, so one slow outlier (a page fault, a busy pool) does not collapse it.
+
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::index::IndexType;
+
+// Weight of the newest observation in the moving average
+const SMOOTHING: f64 = 0.3;
+
+// Largest factor the effort may grow or shrink by from one search to the next
+const MAX_STEP: f64 = 2.0;
+
+// Search parameters a latency-target search ran with, returned with its results
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EffectiveSearch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ef: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nprobe: Option<usize>,
+    pub target_ms: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicted_ms: Option<f32>, // None until the collection has timed a budgeted search on this index
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Observed {
+    index_type: IndexType,
+    ms_per_unit: f64,
+    effort: usize, // effort of the last budgeted search
+}
+
+// Per-collection latency model. Interior mutability so searches (which only hold a read lock on the
+// collection) can record.
+#[derive(Debug, Default)]
+pub struct LatencyBudget {
+    observed: Mutex<Option<Observed>>,
+}
+
+impl LatencyBudget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Effort for a search with `target_ms`, within min..=max, and the latency predicted for it
+    pub fn choose(&self, index_type: IndexType, target_ms: f32, configured: usize, min: usize, max: usize) -> (usize, Option<f32>) {
+        let max = max.max(min);
+        let Some(observed) = (*self.observed.lock()).filter(|o| o.index_type == index_type) else {
+            return (configured.clamp(min, max), None);
+        };
+        let last = observed.effort as f64;
+        let fits = target_ms.max(0.0) as f64 / observed.ms_per_unit.max(f64::MIN_POSITIVE);
+        let effort = (fits.clamp(last / MAX_STEP, last * MAX_STEP) as usize).clamp(min, max);
+        (effort, Some((effort as f64 * observed.ms_per_unit) as f32))
+    }
+
+    // Fold in the latency of a search that ran with `effort`
+    pub fn record(&self, index_type: IndexType, effort: usize, elapsed: Duration) {
+        let ms_per_unit = elapsed.as_secs_f64() * 1000.0 / effort.max(1) as f64;
+        let mut observed = self.observed.lock();
+        *observed = Some(match *observed {
+            Some(o) if o.index_type == index_type => Observed {
+                index_type,
+                ms_per_unit: SMOOTHING * ms_per_unit + (1.0 - SMOOTHING) * o.ms_per_unit,
+                effort,
+            },
+            _ => Observed { index_type, ms_per_unit, effort },
+        });
+    }
+}
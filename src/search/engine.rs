@@ -3,10 +3,17 @@
 
 use crate::config::ExecutionMode;
 use crate::metrics::Metric;
-use crate::search::{Hit, query::Filter, utils::sort_and_truncate};
+use crate::search::{Hit, OrderBy, ScoreExpr, query::Filter, selectivity::tuned_overfetch, utils::{best_first, dedup_by_metadata, sort_and_truncate}};
 use crate::storage::Collection;
+use crate::storage::collection::{index_space, Projection, TwoStageState};
+use crate::config::CollectionConfig;
+use crate::index::{ColumnView, IndexType, VectorIndex};
+use crate::metadata::Metadata;
+use crate::search::{EffectiveSearch, LatencyBudget, SelectivityTracker};
+use crate::search::query::FilterMatches;
+use parking_lot::MappedRwLockReadGuard;
 use uuid::Uuid;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 // Parameters for a search request.
 #[derive(Debug, Clone, Copy)]
@@ -15,6 +22,14 @@ pub struct SearchParams<'a> {
     pub filter: Option<&'a Filter>,
     pub filter_overfetch_override: Option<usize>,
     pub search_config_override: Option<crate::config::SearchConfig>,
+    // Collapse hits sharing the same value for this metadata key, keeping the best-scoring one
+    pub dedup_by: Option<&'a str>,
+    // Re-score the candidates with this expression (similarity and metadata) and rank by its value
+    pub score_expr: Option<&'a ScoreExpr>,
+    // Sort the final hits by a metadata field, or break score ties with it
+    pub order_by: Option<&'a OrderBy>,
+    // Leave these documents out (already-seen results); k others are still returned when there are
+    pub exclude_ids: Option<&'a HashSet<Uuid>>,
 }
 
 impl Default for SearchParams<'_> {
@@ -24,74 +39,552 @@ impl Default for SearchParams<'_> {
             filter: None,
             filter_overfetch_override: None,
             search_config_override: None,
+            dedup_by: None,
+            score_expr: None,
+            order_by: None,
+            exclude_ids: None,
         }
     }
 }
 
-fn search_collection_with_maps(
-    storage: &Collection,
+// Extra candidates pulled from an index built for a different metric than the query's, so re-ranking has something to reorder
+const METRIC_MISMATCH_OVERFETCH: usize = 4;
+
+// How many candidates per requested hit a deduplicated search starts with; doubled while duplicates leave it short of k
+const DEDUP_OVERFETCH: usize = 4;
+
+// How many candidates per requested hit a scoring expression gets to reorder
+const SCORE_EXPR_OVERFETCH: usize = 4;
+
+// How many candidates per requested hit a tie-breaking order looks at, so ties straddling the k-th hit are settled by the field rather than by index order
+const ORDER_TIE_OVERFETCH: usize = 2;
+
+// Candidates per requested hit that the sign-bit prefilter of ExecutionMode::BinaryRerank passes on to the exact re-rank
+pub const BINARY_RERANK_OVERFETCH: usize = 8;
+
+// Per-document metadata as a search target hands it out. A collection's lives behind its data latch,
+// which stays held (shared) for as long as the map is in use; a replica owns its copy outright.
+pub enum MetadataMap<'a> {
+    Borrowed(&'a HashMap<Uuid, Metadata>),
+    Latched(MappedRwLockReadGuard<'a, HashMap<Uuid, Metadata>>),
+}
+
+impl std::ops::Deref for MetadataMap<'_> {
+    type Target = HashMap<Uuid, Metadata>;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            MetadataMap::Borrowed(map) => map,
+            MetadataMap::Latched(guard) => guard,
+        }
+    }
+}
+
+// Everything the engine reads while searching. Implemented by the collection itself and by its in-memory read replicas, so both go through the same search path.
+pub trait SearchTarget: Sync {
+    fn config(&self) -> &CollectionConfig;
+    fn vector_index(&self) -> &dyn VectorIndex;
+    fn selectivity(&self) -> &SelectivityTracker;
+    fn latency_budget(&self) -> &LatencyBudget;
+    // Length of the stored vectors, None while the target is empty
+    fn dimensions(&self) -> Option<usize>;
+    // Vectors as seen by the index (after the collection's transform)
+    fn vectors(&self) -> &HashMap<Uuid, Vec<f32>>;
+    fn metadatas(&self) -> MetadataMap<'_>;
+    // Text, stored vector and metadata of a document, used to build a Hit
+    fn document(&self, id: &Uuid) -> Option<(String, Vec<f32>, Metadata)>;
+    // `document` for each of `ids`, for targets that can read a batch at once
+    fn documents(&self, ids: &[Uuid]) -> Vec<Option<(String, Vec<f32>, Metadata)>> {
+        ids.iter().map(|id| self.document(id)).collect()
+    }
+    fn two_stage(&self) -> Option<&TwoStageState> {
+        None
+    }
+    // Contiguous copy of `vectors`, when the collection keeps one
+    fn vector_column(&self) -> Option<ColumnView<'_>> {
+        None
+    }
+    // Trained projection between the transform and the index, when the collection has one
+    fn projection(&self) -> Option<&Projection> {
+        None
+    }
+    // Documents matching `filter` from the metadata index, when one covers every field it reads
+    fn filter_matches(&self, _filter: &Filter, _max_ids: usize) -> Option<FilterMatches> {
+        None
+    }
+}
+
+impl SearchTarget for Collection {
+    fn config(&self) -> &CollectionConfig {
+        &self.config
+    }
+
+    fn vector_index(&self) -> &dyn VectorIndex {
+        Collection::vector_index(self)
+    }
+
+    fn selectivity(&self) -> &SelectivityTracker {
+        Collection::selectivity(self)
+    }
+
+    fn latency_budget(&self) -> &LatencyBudget {
+        Collection::latency_budget(self)
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        self.metadata().dimensions
+    }
+
+    fn vectors(&self) -> &HashMap<Uuid, Vec<f32>> {
+        self.get_vectors()
+    }
+
+    fn metadatas(&self) -> MetadataMap<'_> {
+        MetadataMap::Latched(self.metadata_view())
+    }
+
+    fn document(&self, id: &Uuid) -> Option<(String, Vec<f32>, Metadata)> {
+        self.get(id).map(|entry| {
+            let vector = entry.get_vector();
+            (entry.text, vector, entry.metadata)
+        })
+    }
+
+    fn documents(&self, ids: &[Uuid]) -> Vec<Option<(String, Vec<f32>, Metadata)>> {
+        self.get_many(ids).into_iter().map(|entry| entry.map(|entry| {
+            let vector = entry.get_vector();
+            (entry.text, vector, entry.metadata)
+        })).collect()
+    }
+
+    fn two_stage(&self) -> Option<&TwoStageState> {
+        Collection::two_stage(self)
+    }
+
+    fn vector_column(&self) -> Option<ColumnView<'_>> {
+        Collection::vector_column(self).map(|column| column.view())
+    }
+
+    fn projection(&self) -> Option<&Projection> {
+        Collection::projection(self)
+    }
+
+    fn filter_matches(&self, filter: &Filter, max_ids: usize) -> Option<FilterMatches> {
+        Collection::filter_matches(self, filter, max_ids)
+    }
+}
+
+// The index holds reduced vectors when the collection truncates or projects them; reduced candidates are re-ranked on the full stored vectors
+fn reduced<T: SearchTarget + ?Sized>(storage: &T) -> bool {
+    storage.config().transform.is_active() || storage.projection().is_some()
+}
+
+// Candidates to pull from the index for a top-k search
+fn rerank_candidates<T: SearchTarget + ?Sized>(storage: &T, k: usize) -> usize {
+    let transform = storage.config().transform;
+    if reduced(storage) && transform.rerank {
+        k.saturating_mul(transform.rerank_overfetch.max(1))
+    } else {
+        k
+    }
+}
+
+fn search_target_with_maps<T: SearchTarget + ?Sized>(
+    storage: &T,
     query: &[f32],
     k: usize,
     metric: Metric,
     params: SearchParams<'_>,
     vectors: &HashMap<Uuid, Vec<f32>>,
-    metadatas: &HashMap<Uuid, crate::metadata::Metadata>,
+    metadatas: &HashMap<Uuid, Metadata>,
 ) -> Vec<Hit> {
+    if let Some(order) = params.order_by {
+        return ordered_search(storage, query, k, metric, params, order, vectors, metadatas);
+    }
+    if let Some(key) = params.dedup_by {
+        return deduplicated_search(storage, query, k, metric, params, key, vectors, metadatas);
+    }
+    if let Some(expr) = params.score_expr {
+        return expression_search(storage, query, k, metric, params, expr, vectors, metadatas);
+    }
+    if let Some(ids) = params.exclude_ids.filter(|ids| !ids.is_empty()) {
+        return excluding_search(storage, query, k, metric, params, ids, vectors, metadatas);
+    }
+
+    // A binary prefilter skips the index walk too: a scan over sign bits picks the candidates and the stored vectors rank them
+    if params.mode == ExecutionMode::BinaryRerank {
+        return binary_rerank_search(storage, query, k, metric, params, vectors, metadatas);
+    }
+
+    // Two-stage collections skip the index walk: a scan over quantized codes picks the candidates and full-precision vectors rank them
+    if let Some(two_stage) = storage.two_stage() {
+        return two_stage_search(storage, two_stage, query, k, metric, params, metadatas);
+    }
+
     // 1. Determine effective search config and overfetch factor
-    let effective_search = params.search_config_override.unwrap_or(storage.config.search);
+    let effective_search = params.search_config_override.unwrap_or(storage.config().search);
+
+    // The index and the vector cache hold transformed (e.g. truncated or projected) vectors, so the query has to go through the same transform before it meets them. The untouched query is kept for re-ranking on the full stored vectors.
+    let transform = storage.config().transform;
+    let index_query = index_space(&transform, storage.projection(), query);
+    let reduced = reduced(storage);
 
     // 2. Calculate overfetch factor based on filter presence and configuration. If a filter is applied, we need to overfetch more results from the vector index to ensure that after filtering we still have enough results to return. The overfetch factor is determined by the search configuration's filter_overfetch parameter, which specifies how many times more results to fetch compared to k when a filter is applied. If no filter is present, we can just fetch k results directly.
     let base_overfetch = effective_search.filter_overfetch.max(1);
     
     // If the caller provided an override for filter overfetch, use that instead of the configured value. This allows for dynamic adjustment of the overfetch factor on a per-search basis, which can be useful for certain queries or workloads where the default overfetch might not be sufficient or might be too aggressive.
-    let expansion = params
+    let mut expansion = params
         .filter_overfetch_override
         .unwrap_or(base_overfetch)
         .max(1);
 
+    // A metadata index covering the filter gives the exact set of matches before the index is walked. When there are no more of them than the index would have to return to surface k (k times the largest overfetch), score them exactly; otherwise their share of the collection sizes the overfetch.
+    let prefiltered = params.filter.and_then(|filter| {
+        storage.filter_matches(filter, k.saturating_mul(effective_search.max_filter_overfetch.max(1)))
+    });
+    if let Some(matches) = prefiltered.as_ref() {
+        if let Some(ids) = matches.ids.as_deref() {
+            return exact_scan(storage, query, &index_query, k, metric, params.mode, ids.iter(), vectors);
+        }
+        let selectivity = matches.count as f32 / vectors.len().max(1) as f32;
+        expansion = tuned_overfetch(selectivity, expansion, effective_search.max_filter_overfetch);
+    }
+
+    // If adaptive overfetch is on and we have seen this filter shape before, size the overfetch from the observed selectivity instead of trusting the static factor. When the estimate says fewer than k documents match at all, the index cannot find them by overfetching, so scan the matching documents exactly instead.
+    let shape = params
+        .filter
+        .filter(|_| effective_search.adaptive_overfetch && prefiltered.is_none())
+        .map(|f| f.shape());
+    if let (Some(filter), Some(shape)) = (params.filter, shape.as_deref()) {
+        if let Some(selectivity) = storage.selectivity().estimate(shape) {
+            let estimated_matches = selectivity * vectors.len() as f32;
+            if estimated_matches < k as f32 {
+                return exact_filtered_scan(storage, query, &index_query, k, metric, params.mode, filter, shape, vectors, metadatas);
+            }
+            expansion = tuned_overfetch(selectivity, expansion, effective_search.max_filter_overfetch);
+        }
+    }
+
     // 3. Perform search on the vector index with the calculated overfetch factor. If a filter is present, we multiply k by the expansion factor to fetch more results from the vector index, which increases the likelihood that after filtering we will have at least k results to return. If no filter is present, we just fetch k results directly from the vector index.
-    let search_k = if params.filter.is_some() { k.saturating_mul(expansion) } else { k };
+    // An index that applies the filter itself (IVF) already returns k matches when there are any
+    let pushed_down = params.filter.is_some() && storage.vector_index().pushes_down_filters();
+    let search_k = if params.filter.is_some() && !pushed_down { k.saturating_mul(expansion) } else { k };
+    // When re-ranking truncated candidates, pull extra ones so the full-dimension scores can reorder them
+    let search_k = rerank_candidates(storage, search_k);
+    // The index orders candidates by the metric it was built with. For any other metric its order is only a rough pre-selection: pull more and re-rank them by the query's metric.
+    let metric_mismatch = metric != storage.vector_index().metric();
+    let search_k = if metric_mismatch { search_k.saturating_mul(METRIC_MISMATCH_OVERFETCH) } else { search_k };
     
     // 4. Search the vector index for nearest neighbors to the query vector. This will return a list of candidate IDs based on vector similarity. The search method of the vector index will use the effective search configuration, which may include parameters like ef for HNSW or num_probes for IVF, to control the tradeoff between search speed and accuracy. The filter and metadata parameters are passed to the search method, although they may not be used by all index types.
     let mode = params.mode;
 
 
-    let neighbor_ids = storage.vector_index().search(
-        query,
-        search_k,
-        vectors,
-        effective_search,
-        params.filter,
-        metadatas,
-    );
+    // Scanning indexes read the column when there is one: same candidates, contiguous memory
+    let neighbor_ids = storage
+        .vector_column()
+        .and_then(|column| storage.vector_index().search_column(&index_query, search_k, column, effective_search, params.filter, metadatas))
+        .unwrap_or_else(|| storage.vector_index().search(
+            &index_query,
+            search_k,
+            vectors,
+            effective_search,
+            params.filter,
+            metadatas,
+        ));
 
     let mut results = Vec::new();
 
     // 5. For each candidate ID returned by the vector index search, retrieve the corresponding vector and metadata from storage, calculate the similarity score using the specified metric, and construct a Hit object that includes the ID, score, text, vector, and metadata. This step involves looking up each candidate ID in the storage to get the full information needed to return to the caller. The similarity score is calculated using the configured metric (e.g., cosine similarity), which takes into account the query vector and the candidate vector.
-    for id in neighbor_ids {
-        if let Some(entry) = storage.get(&id) {
-            let vec = entry.get_vector();
-            let score = metric.calculate(query, &vec, mode);
+    // With a transform but no re-rank, the truncated score from the cache is the final score.
+    let score_truncated = reduced && !transform.rerank;
+    // All candidates are fetched before scoring, so a collection reading its data file explicitly has their reads in flight together
+    let documents = storage.documents(&neighbor_ids);
+    for (id, document) in neighbor_ids.into_iter().zip(documents) {
+        if let Some((text, vec, metadata)) = document {
+            // A stored vector of another size (written before the collection's dimensions were enforced) cannot be scored
+            if vec.len() != query.len() {
+                continue;
+            }
+            let score = match vectors.get(&id) {
+                Some(cached) if score_truncated => metric.calculate(&index_query, cached, mode),
+                _ => metric.calculate(query, &vec, mode),
+            };
             results.push(Hit {
                 id,
                 score,
-                text: entry.text,
+                text,
                 vector: vec,
-                metadata: entry.metadata.clone(),
+                metadata,
             });
         }
     }
     // 6. If a filter is provided, apply the filter to the results to retain only those hits that match the filter criteria. After filtering, sort the results by score and truncate to the top k results to return to the caller. If no filter is provided, we can skip this step and just return the results as they are already sorted by the vector index search.
     if let Some(filter) = params.filter {
         let mut filtered = results;
+        let candidates = filtered.len();
         filtered.retain(|hit| filter.matches(&hit.metadata));
+        // Pushed-down candidates all match, which says nothing about the filter's selectivity
+        if let Some(shape) = shape.as_deref().filter(|_| !pushed_down) {
+            storage.selectivity().record(shape, candidates, filtered.len());
+        }
         sort_and_truncate(&mut filtered, k);
         filtered
+    } else if reduced || metric_mismatch || !mode.exact_scores() {
+        sort_and_truncate(&mut results, k);
+        results
     } else {
         results
     }
 }
 
+// Deduplication has to happen before the cut to k, otherwise a page split into many chunks fills every slot. Search for more candidates than k, collapse them, and widen the search until k distinct hits are found or the collection has nothing more to give.
+#[allow(clippy::too_many_arguments)]
+fn deduplicated_search<T: SearchTarget + ?Sized>(
+    storage: &T,
+    query: &[f32],
+    k: usize,
+    metric: Metric,
+    params: SearchParams<'_>,
+    key: &str,
+    vectors: &HashMap<Uuid, Vec<f32>>,
+    metadatas: &HashMap<Uuid, Metadata>,
+) -> Vec<Hit> {
+    let inner = SearchParams { dedup_by: None, ..params };
+    let total = vectors.len().max(metadatas.len());
+    let mut fetch = k.saturating_mul(DEDUP_OVERFETCH);
+    loop {
+        let mut hits = search_target_with_maps(storage, query, fetch, metric, inner, vectors, metadatas);
+        let exhausted = hits.len() < fetch || fetch >= total;
+        dedup_by_metadata(&mut hits, key);
+        if hits.len() >= k || exhausted {
+            hits.truncate(k);
+            return hits;
+        }
+        fetch = fetch.saturating_mul(2);
+    }
+}
+
+// Excluded documents are dropped before the cut to k, below deduplication and scoring so they cannot
+// stand in for a group or take a slot. No more than all of them are among the nearest k + n, so that
+// many candidates leave k others whenever the collection has them.
+#[allow(clippy::too_many_arguments)]
+fn excluding_search<T: SearchTarget + ?Sized>(
+    storage: &T,
+    query: &[f32],
+    k: usize,
+    metric: Metric,
+    params: SearchParams<'_>,
+    ids: &HashSet<Uuid>,
+    vectors: &HashMap<Uuid, Vec<f32>>,
+    metadatas: &HashMap<Uuid, Metadata>,
+) -> Vec<Hit> {
+    let inner = SearchParams { exclude_ids: None, ..params };
+    let mut hits = search_target_with_maps(storage, query, k.saturating_add(ids.len()), metric, inner, vectors, metadatas);
+    hits.retain(|hit| !ids.contains(&hit.id));
+    hits.truncate(k);
+    hits
+}
+
+// A scoring expression can lift a candidate the similarity order left below k, so it gets a wider pool
+// to reorder. Candidates without a finite value are dropped.
+#[allow(clippy::too_many_arguments)]
+fn expression_search<T: SearchTarget + ?Sized>(
+    storage: &T,
+    query: &[f32],
+    k: usize,
+    metric: Metric,
+    params: SearchParams<'_>,
+    expr: &ScoreExpr,
+    vectors: &HashMap<Uuid, Vec<f32>>,
+    metadatas: &HashMap<Uuid, Metadata>,
+) -> Vec<Hit> {
+    let inner = SearchParams { score_expr: None, ..params };
+    let fetch = k.saturating_mul(SCORE_EXPR_OVERFETCH);
+    let mut hits = search_target_with_maps(storage, query, fetch, metric, inner, vectors, metadatas);
+    hits.retain_mut(|hit| match expr.eval(hit.score, &hit.metadata) {
+        Some(value) => {
+            hit.score = value as f32;
+            true
+        }
+        None => false,
+    });
+    sort_and_truncate(&mut hits, k);
+    hits
+}
+
+// Ordering applies to the final hits, after deduplication and scoring expressions. A tie-breaking
+// order looks a little past k so that equal scores at the cut are decided by the field too.
+#[allow(clippy::too_many_arguments)]
+fn ordered_search<T: SearchTarget + ?Sized>(
+    storage: &T,
+    query: &[f32],
+    k: usize,
+    metric: Metric,
+    params: SearchParams<'_>,
+    order: &OrderBy,
+    vectors: &HashMap<Uuid, Vec<f32>>,
+    metadatas: &HashMap<Uuid, Metadata>,
+) -> Vec<Hit> {
+    let inner = SearchParams { order_by: None, ..params };
+    let fetch = if order.tie_break { k.saturating_mul(ORDER_TIE_OVERFETCH) } else { k };
+    let mut hits = search_target_with_maps(storage, query, fetch, metric, inner, vectors, metadatas);
+    order.apply(&mut hits);
+    hits.truncate(k);
+    hits
+}
+
+// Exact search over the documents matching the filter. Used when the filter is so selective that the index (which ranks by similarity only) would need to return most of the collection to surface k matches.
+#[allow(clippy::too_many_arguments)]
+fn exact_filtered_scan<T: SearchTarget + ?Sized>(
+    storage: &T,
+    query: &[f32],
+    index_query: &[f32],
+    k: usize,
+    metric: Metric,
+    mode: ExecutionMode,
+    filter: &Filter,
+    shape: &str,
+    vectors: &HashMap<Uuid, Vec<f32>>,
+    metadatas: &HashMap<Uuid, Metadata>,
+) -> Vec<Hit> {
+    let matching = metadatas.iter().filter(|(_, metadata)| filter.matches(metadata)).map(|(id, _)| id);
+    let mut matched = 0;
+    let hits = exact_scan(storage, query, index_query, k, metric, mode, matching.inspect(|_| matched += 1), vectors);
+
+    // The scan sees every document, so this observation is the true selectivity of the filter
+    storage.selectivity().record(shape, vectors.len(), matched);
+    hits
+}
+
+// Exact top-k over the given documents
+#[allow(clippy::too_many_arguments)]
+fn exact_scan<'a, T: SearchTarget + ?Sized>(
+    storage: &T,
+    query: &[f32],
+    index_query: &[f32],
+    k: usize,
+    metric: Metric,
+    mode: ExecutionMode,
+    ids: impl Iterator<Item = &'a Uuid>,
+    vectors: &HashMap<Uuid, Vec<f32>>,
+) -> Vec<Hit> {
+    let mut scored: Vec<(Uuid, f32)> = ids
+        .filter_map(|id| vectors.get(id).filter(|vec| vec.len() == index_query.len()).map(|vec| (*id, metric.calculate(index_query, vec, mode))))
+        .collect();
+
+    scored.sort_by(best_first);
+    scored.truncate(rerank_candidates(storage, k));
+    let rerank = reduced(storage) && storage.config().transform.rerank;
+
+    let ids: Vec<Uuid> = scored.iter().map(|(id, _)| *id).collect();
+    let mut hits: Vec<Hit> = scored
+        .into_iter()
+        .zip(storage.documents(&ids))
+        .filter_map(|((id, score), document)| {
+            document.map(|(text, vec, metadata)| {
+                let score = if rerank { metric.calculate(query, &vec, mode) } else { score };
+                Hit {
+                    id,
+                    score,
+                    text,
+                    vector: vec,
+                    metadata,
+                }
+            })
+        })
+        .collect();
+    sort_and_truncate(&mut hits, k);
+    hits
+}
+
+// Stage 1 scores every (filter-matching) document from its int8/PQ codes without dequantizing and keeps the top C; stage 2 re-scores those C with the exact vectors from the full-precision sidecar and returns the top k.
+fn two_stage_search<T: SearchTarget + ?Sized>(
+    storage: &T,
+    two_stage: &TwoStageState,
+    query: &[f32],
+    k: usize,
+    metric: Metric,
+    params: SearchParams<'_>,
+    metadatas: &HashMap<Uuid, Metadata>,
+) -> Vec<Hit> {
+    let candidates = storage.config().two_stage.candidates.max(k);
+
+    let mut coarse: Vec<(Uuid, f32)> = two_stage
+        .codes()
+        .iter()
+        .filter(|(id, _)| params.filter.is_none_or(|f| metadatas.get(id).is_some_and(|m| f.matches(m))))
+        .map(|(id, code)| (*id, code.approx_score(query, metric)))
+        .collect();
+    if coarse.len() > candidates {
+        coarse.select_nth_unstable_by(candidates - 1, best_first);
+        coarse.truncate(candidates);
+    }
+
+    let ids: Vec<Uuid> = coarse.iter().map(|(id, _)| *id).collect();
+    let mut hits: Vec<Hit> = ids
+        .iter()
+        .zip(storage.documents(&ids))
+        .filter_map(|(&id, document)| {
+            let (text, stored, metadata) = document?;
+            // Documents written before two-stage was enabled have no exact copy; their codes are the best we have
+            let exact = two_stage.full_precision(&id).unwrap_or(stored);
+            let score = metric.calculate(query, &exact, params.mode);
+            Some(Hit {
+                id,
+                score,
+                text,
+                vector: exact,
+                metadata,
+            })
+        })
+        .collect();
+    sort_and_truncate(&mut hits, k);
+    hits
+}
+
+// Stage 1 scores every (filter-matching) document from the signs of its vector, 1 bit per dimension, and keeps the best BINARY_RERANK_OVERFETCH * k; stage 2 re-scores those with the stored f32 vectors and returns the top k with exact scores. Recall is that of the sign codes at that depth: a true neighbour is only lost when more than BINARY_RERANK_OVERFETCH * k documents look closer by their signs.
+fn binary_rerank_search<T: SearchTarget + ?Sized>(
+    storage: &T,
+    query: &[f32],
+    k: usize,
+    metric: Metric,
+    params: SearchParams<'_>,
+    vectors: &HashMap<Uuid, Vec<f32>>,
+    metadatas: &HashMap<Uuid, Metadata>,
+) -> Vec<Hit> {
+    let index_query = index_space(&storage.config().transform, storage.projection(), query);
+    let candidates = k.saturating_mul(BINARY_RERANK_OVERFETCH).max(1);
+
+    let mut coarse: Vec<(Uuid, f32)> = vectors
+        .iter()
+        .filter(|(_, vec)| vec.len() == index_query.len())
+        .filter(|(id, _)| params.filter.is_none_or(|f| metadatas.get(id).is_some_and(|m| f.matches(m))))
+        .map(|(id, vec)| (*id, metric.calculate(&index_query, vec, ExecutionMode::Binary)))
+        .collect();
+    if coarse.len() > candidates {
+        coarse.select_nth_unstable_by(candidates - 1, best_first);
+        coarse.truncate(candidates);
+    }
+
+    let ids: Vec<Uuid> = coarse.iter().map(|(id, _)| *id).collect();
+    let mut hits: Vec<Hit> = ids
+        .iter()
+        .zip(storage.documents(&ids))
+        .filter_map(|(&id, document)| {
+            let (text, vec, metadata) = document?;
+            (vec.len() == query.len()).then(|| Hit {
+                id,
+                score: metric.calculate(query, &vec, params.mode.exact()),
+                text,
+                vector: vec,
+                metadata,
+            })
+        })
+        .collect();
+    sort_and_truncate(&mut hits, k);
+    hits
+}
+
 pub fn search_collection(
     storage: &Collection,
     query: &[f32],
@@ -99,10 +592,7 @@ pub fn search_collection(
     metric: Metric,
     params: SearchParams<'_>,
 ) -> Vec<Hit> {
-    // Get vectors and metadatas from storage to pass to the search function. This allows us to perform the search using the vector index while also having access to the metadata for filtering and constructing the Hit objects. The search_collection_with_maps function is then called with these maps to perform the actual search and return the results.
-    let vectors = storage.get_vectors();
-    let metadatas = storage.metadata_view();
-    search_collection_with_maps(storage, query, k, metric, params, vectors, metadatas)
+    search_target(storage, query, k, metric, params)
 }
 
 pub fn search_batch_collection(
@@ -112,19 +602,201 @@ pub fn search_batch_collection(
     metric: Metric,
     params: SearchParams<'_>,
 ) -> Vec<Vec<Hit>> {
-    let vectors = storage.get_vectors();
-    let metadatas = storage.metadata_view();
+    search_batch_target(storage, queries, k, metric, params)
+}
+
+pub fn search_target<T: SearchTarget + ?Sized>(
+    storage: &T,
+    query: &[f32],
+    k: usize,
+    metric: Metric,
+    params: SearchParams<'_>,
+) -> Vec<Hit> {
+    // Get vectors and metadatas from storage to pass to the search function. This allows us to perform the search using the vector index while also having access to the metadata for filtering and constructing the Hit objects. The search_target_with_maps function is then called with these maps to perform the actual search and return the results.
+    let vectors = storage.vectors();
+    let metadatas = storage.metadatas();
+    validated_search(storage, query, k, metric, params, vectors, &metadatas)
+}
+
+// Search within a latency target: the index's effort parameter (ef or nprobe) comes from the target's
+// recent latencies, starting from the configured or requested value, and this search's latency is
+// recorded for the next one. Exhaustive indexes and two-stage collections search as usual.
+pub fn search_target_within<T: SearchTarget + ?Sized>(
+    storage: &T,
+    query: &[f32],
+    k: usize,
+    metric: Metric,
+    mut params: SearchParams<'_>,
+    target_ms: f32,
+) -> (Vec<Hit>, EffectiveSearch) {
+    let mut search = params.search_config_override.unwrap_or(storage.config().search);
+    let index = storage.vector_index();
+    let mut effective = EffectiveSearch { ef: None, nprobe: None, target_ms, predicted_ms: None };
+    let effort = index.search_effort().filter(|_| storage.two_stage().is_none());
+    let Some((default, max)) = effort else {
+        return (search_target(storage, query, k, metric, params), effective);
+    };
+
+    let index_type = index.index_type();
+    let ivf = index_type == IndexType::Ivf;
+    let (configured, min) = if ivf { (search.nprobe, 1) } else { (search.ef, k.max(1)) };
+    let (effort, predicted) = storage
+        .latency_budget()
+        .choose(index_type, target_ms, configured.unwrap_or(default), min, max);
+    if ivf {
+        search.nprobe = Some(effort);
+        effective.nprobe = Some(effort);
+    } else {
+        search.ef = Some(effort);
+        effective.ef = Some(effort);
+    }
+    effective.predicted_ms = predicted;
+    params.search_config_override = Some(search);
+
+    let start = std::time::Instant::now();
+    let hits = search_target(storage, query, k, metric, params);
+    storage.latency_budget().record(index_type, effort, start.elapsed());
+    (hits, effective)
+}
+
+// Effort of a deadline search's quick round, as a fraction of the configured one
+const QUICK_ROUND_DIVISOR: usize = 4;
+
+// Search in rounds for searches with a deadline: a quick one at a quarter of the index's effort (ef or
+// nprobe), whose hits stand in when the deadline passes before the configured search is done, then
+// that search. `round` gets each round's hits and whether they are the final ones. Exhaustive indexes
+// and two-stage collections search once.
+pub fn search_target_in_rounds<T: SearchTarget + ?Sized>(
+    storage: &T,
+    query: &[f32],
+    k: usize,
+    metric: Metric,
+    params: SearchParams<'_>,
+    mut round: impl FnMut(Vec<Hit>, bool),
+) {
+    let index = storage.vector_index();
+    if let Some((default, _)) = index.search_effort().filter(|_| storage.two_stage().is_none()) {
+        let mut search = params.search_config_override.unwrap_or(storage.config().search);
+        let ivf = index.index_type() == IndexType::Ivf;
+        let (configured, min) = if ivf { (search.nprobe, 1) } else { (search.ef, k.max(1)) };
+        let configured = configured.unwrap_or(default);
+        let quick = (configured / QUICK_ROUND_DIVISOR).max(min);
+        if quick < configured {
+            if ivf {
+                search.nprobe = Some(quick);
+            } else {
+                search.ef = Some(quick);
+            }
+            let quick_params = SearchParams { search_config_override: Some(search), ..params };
+            round(search_target(storage, query, k, metric, quick_params), false);
+        }
+    }
+    round(search_target(storage, query, k, metric, params), true);
+}
+
+// Queries that fail the collection's vector validation match nothing: a NaN query would score every candidate as NaN and scramble the ordering, and one of another size than the stored vectors would fail the distance kernels' length check. Collection::try_search reports the reason instead.
+fn validated_search<T: SearchTarget + ?Sized>(
+    storage: &T,
+    query: &[f32],
+    k: usize,
+    metric: Metric,
+    params: SearchParams<'_>,
+    vectors: &HashMap<Uuid, Vec<f32>>,
+    metadatas: &HashMap<Uuid, Metadata>,
+) -> Vec<Hit> {
+    let checked = crate::validation::check_query_dimensions(query, storage.dimensions())
+        .and_then(|_| crate::validation::check_vector(query, &storage.config().validation, metric));
+    match checked {
+        Ok(query) => search_target_with_maps(storage, &query, k, metric, params, vectors, metadatas),
+        Err(e) => {
+            tracing::debug!(error = %e, "search_query_rejected");
+            Vec::new()
+        }
+    }
+}
+
+// ExecutionMode::Gpu: a large batch of plain searches (no filter, ordering, dedup, expression or
+// exclusions) against an index that scores batches together (flat) is scored as one matrix product,
+// and each query's candidates are re-scored exactly. None when the batch does not qualify.
+fn device_batch_search<T: SearchTarget + ?Sized>(
+    storage: &T,
+    queries: &[Vec<f32>],
+    k: usize,
+    metric: Metric,
+    params: SearchParams<'_>,
+    vectors: &HashMap<Uuid, Vec<f32>>,
+) -> Option<Vec<Vec<Hit>>> {
+    use crate::index::flat::device::{DEVICE_MIN_BATCH, DEVICE_RERANK_FACTOR};
+    let plain = params.filter.is_none()
+        && params.order_by.is_none()
+        && params.dedup_by.is_none()
+        && params.score_expr.is_none()
+        && params.exclude_ids.is_none_or(|ids| ids.is_empty());
+    if params.mode != ExecutionMode::Gpu
+        || queries.len() < DEVICE_MIN_BATCH
+        || !plain
+        || storage.two_stage().is_some()
+        || reduced(storage)
+        || metric != storage.vector_index().metric()
+    {
+        return None;
+    }
+    let checked: Vec<Option<Vec<f32>>> = queries
+        .iter()
+        .map(|query| {
+            crate::validation::check_query_dimensions(query, storage.dimensions())
+                .and_then(|_| crate::validation::check_vector(query, &storage.config().validation, metric))
+                .map(|query| query.into_owned())
+                .map_err(|e| tracing::debug!(error = %e, "search_query_rejected"))
+                .ok()
+        })
+        .collect();
+    let valid: Vec<Vec<f32>> = checked.iter().flatten().cloned().collect();
+    let mut candidates = storage
+        .vector_index()
+        .search_batch(&valid, k.saturating_mul(DEVICE_RERANK_FACTOR), vectors)?
+        .into_iter();
+    Some(
+        checked
+            .iter()
+            .map(|query| match query {
+                Some(query) => {
+                    let ids = candidates.next().unwrap_or_default();
+                    exact_scan(storage, query, query, k, metric, params.mode, ids.iter(), vectors)
+                }
+                None => Vec::new(),
+            })
+            .collect(),
+    )
+}
+
+pub fn search_batch_target<T: SearchTarget + ?Sized>(
+    storage: &T,
+    queries: &[Vec<f32>],
+    k: usize,
+    metric: Metric,
+    params: SearchParams<'_>,
+) -> Vec<Vec<Hit>> {
+    let vectors = storage.vectors();
+    let metadatas = storage.metadatas();
+    let metadatas = &*metadatas;
+
+    if let Some(hits) = device_batch_search(storage, queries, k, metric, params, vectors) {
+        return hits;
+    }
     
     if storage.config().parallelism.parallel_search {
         use rayon::prelude::*; // If parallel search is enabled in the configuration, we use Rayon to perform the searches for each query in parallel. This can significantly speed up batch searches when there are multiple queries and the underlying hardware supports parallel execution. Each query is processed independently, and the results are collected into a vector of vectors of hits, where each inner vector corresponds to the results for a single query.
-        queries
-            .par_iter()
-            .map(|query| search_collection_with_maps(storage, query, k, metric, params, vectors, metadatas))
-            .collect() 
+        crate::parallel::query(|| {
+            queries
+                .par_iter()
+                .map(|query| validated_search(storage, query, k, metric, params, vectors, metadatas))
+                .collect()
+        })
     } else {
         queries
             .iter()
-            .map(|query| search_collection_with_maps(storage, query, k, metric, params, vectors, metadatas))
+            .map(|query| validated_search(storage, query, k, metric, params, vectors, metadatas))
             .collect() 
     }
 }
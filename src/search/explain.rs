@@ -0,0 +1,46 @@
+// How a search is carried out, returned with its results when a request sets `explain`.
+// Hits always come back in score order; `exact_scores` says whether those scores are the metric
+// computed on the stored vectors, or (ExecutionMode::Binary) approximations from their sign bits.
+
+use serde::Serialize;
+
+use crate::config::ExecutionMode;
+use crate::index::IndexType;
+use super::engine::{SearchTarget, BINARY_RERANK_OVERFETCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchStrategy {
+    Index, // walk (or scan) the vector index
+    BinaryRerank, // sign-bit scan of every document, exact re-rank of the best
+    TwoStage, // quantized-code scan of every document, exact re-rank of the best
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SearchExplain {
+    pub execution: ExecutionMode, // the collection's configured mode
+    pub kernel: ExecutionMode, // distance kernels the returned scores came from
+    pub strategy: SearchStrategy,
+    pub exact_scores: bool,
+    pub index_type: IndexType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub candidates: Option<usize>, // prefiltered documents re-scored exactly
+}
+
+pub fn explain<T: SearchTarget + ?Sized>(storage: &T, k: usize, mode: ExecutionMode) -> SearchExplain {
+    let (strategy, candidates) = if mode == ExecutionMode::BinaryRerank {
+        (SearchStrategy::BinaryRerank, Some(k.saturating_mul(BINARY_RERANK_OVERFETCH).max(1)))
+    } else if storage.two_stage().is_some() {
+        (SearchStrategy::TwoStage, Some(storage.config().two_stage.candidates.max(k)))
+    } else {
+        (SearchStrategy::Index, None)
+    };
+    SearchExplain {
+        execution: mode,
+        kernel: if mode.exact_scores() { mode.exact() } else { ExecutionMode::Binary },
+        strategy,
+        exact_scores: mode.exact_scores(),
+        index_type: storage.vector_index().index_type(),
+        candidates,
+    }
+}
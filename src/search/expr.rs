@@ -0,0 +1,327 @@
+// Scoring expressions: a small arithmetic language that re-scores search candidates from their
+// similarity and numeric metadata, e.g. `0.8 * similarity + 0.2 * log(1 + metadata.popularity)`.
+//
+// - numbers, `similarity` (also `score`: the metric's score, higher is closer), `metadata.<key>`
+// - + - * / ^ (right-associative power), unary minus, parentheses
+// - functions: log (natural), log10, exp, sqrt, abs, min, max, pow, clamp(x, lo, hi),
+//   coalesce(a, b, ...) (the first argument with a finite value)
+//
+// Integer, float and boolean (1/0) metadata are numbers; a missing key or any other value is
+// missing, and so is everything computed from it except through coalesce. A candidate whose
+// expression has no finite value (a missing field, log of 0) is dropped from the results.
+//
+// Expressions are parsed once per request into a tree and evaluated per candidate. There are
+// no loops, variables or calls out, and length and nesting are capped, so evaluation is bounded.
+
+use crate::error::{Result, ServerError};
+use crate::metadata::{Metadata, MetadataValue};
+
+const MAX_LENGTH: usize = 1024;
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Func {
+    Log,
+    Log10,
+    Exp,
+    Sqrt,
+    Abs,
+    Min,
+    Max,
+    Pow,
+    Clamp,
+    Coalesce,
+}
+
+impl Func {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "log" | "ln" => Func::Log,
+            "log10" => Func::Log10,
+            "exp" => Func::Exp,
+            "sqrt" => Func::Sqrt,
+            "abs" => Func::Abs,
+            "min" => Func::Min,
+            "max" => Func::Max,
+            "pow" => Func::Pow,
+            "clamp" => Func::Clamp,
+            "coalesce" => Func::Coalesce,
+            _ => return None,
+        })
+    }
+
+    // Accepted argument counts (min, max)
+    fn arity(self) -> (usize, usize) {
+        match self {
+            Func::Log | Func::Log10 | Func::Exp | Func::Sqrt | Func::Abs => (1, 1),
+            Func::Pow => (2, 2),
+            Func::Clamp => (3, 3),
+            Func::Min | Func::Max | Func::Coalesce => (1, usize::MAX),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Similarity,
+    Field(String),
+    Neg(Box<Node>),
+    Binary(Op, Box<Node>, Box<Node>),
+    Call(Func, Vec<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreExpr {
+    source: String,
+    root: Node,
+}
+
+impl ScoreExpr {
+    pub fn parse(source: &str) -> Result<Self> {
+        if source.len() > MAX_LENGTH {
+            return Err(ServerError::InvalidRequest(format!("Score expression longer than {} bytes", MAX_LENGTH)).into());
+        }
+        let mut parser = Parser { src: source.as_bytes(), pos: 0, depth: 0 };
+        let root = parser.sum()?;
+        parser.skip_space();
+        if parser.pos < source.len() {
+            return Err(parser.error("unexpected input"));
+        }
+        Ok(Self { source: source.to_string(), root })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // The expression's value for one candidate; None when it has no finite value
+    pub fn eval(&self, similarity: f32, metadata: &Metadata) -> Option<f64> {
+        eval(&self.root, similarity as f64, metadata).filter(|v| v.is_finite())
+    }
+}
+
+fn number(value: &MetadataValue) -> Option<f64> {
+    match value {
+        MetadataValue::Integer(i) => Some(*i as f64),
+        MetadataValue::Float(f) => Some(*f),
+        MetadataValue::Boolean(b) => Some(if *b { 1.0 } else { 0.0 }),
+        _ => None,
+    }
+}
+
+fn eval(node: &Node, similarity: f64, metadata: &Metadata) -> Option<f64> {
+    let arg = |n: &Node| eval(n, similarity, metadata);
+    Some(match node {
+        Node::Number(v) => *v,
+        Node::Similarity => similarity,
+        Node::Field(key) => number(metadata.get(key)?)?,
+        Node::Neg(inner) => -arg(inner)?,
+        Node::Binary(op, a, b) => {
+            let (a, b) = (arg(a)?, arg(b)?);
+            match op {
+                Op::Add => a + b,
+                Op::Sub => a - b,
+                Op::Mul => a * b,
+                Op::Div => a / b,
+                Op::Pow => a.powf(b),
+            }
+        }
+        Node::Call(Func::Coalesce, args) => return args.iter().filter_map(arg).find(|v| v.is_finite()),
+        Node::Call(func, args) => {
+            let mut values = args.iter().map(arg);
+            let mut next = || values.next().flatten();
+            match func {
+                Func::Log => next()?.ln(),
+                Func::Log10 => next()?.log10(),
+                Func::Exp => next()?.exp(),
+                Func::Sqrt => next()?.sqrt(),
+                Func::Abs => next()?.abs(),
+                Func::Pow => next()?.powf(next()?),
+                Func::Clamp => {
+                    let (x, lo, hi) = (next()?, next()?, next()?);
+                    x.max(lo).min(hi)
+                }
+                Func::Min | Func::Max => {
+                    let values = args.iter().map(arg).collect::<Option<Vec<f64>>>()?;
+                    let fold = if *func == Func::Min { f64::min } else { f64::max };
+                    values.into_iter().reduce(fold)?
+                }
+                Func::Coalesce => unreachable!("handled above"),
+            }
+        }
+    })
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> crate::error::PiramidError {
+        ServerError::InvalidRequest(format!("Invalid score expression at position {}: {}", self.pos, msg)).into()
+    }
+
+    fn skip_space(&mut self) {
+        while self.src.get(self.pos).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_space();
+        if self.src.get(self.pos) == Some(&c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.depth += 1;
+        let out = f(self);
+        self.depth -= 1;
+        out
+    }
+
+    fn sum(&mut self) -> Result<Node> {
+        let mut node = self.product()?;
+        loop {
+            let op = if self.eat(b'+') { Op::Add } else if self.eat(b'-') { Op::Sub } else { return Ok(node) };
+            node = Node::Binary(op, Box::new(node), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Node> {
+        let mut node = self.unary()?;
+        loop {
+            let op = if self.eat(b'*') { Op::Mul } else if self.eat(b'/') { Op::Div } else { return Ok(node) };
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node> {
+        if self.eat(b'-') {
+            return self.nested(|p| Ok(Node::Neg(Box::new(p.unary()?))));
+        }
+        let base = self.atom()?;
+        if self.eat(b'^') {
+            return self.nested(|p| Ok(Node::Binary(Op::Pow, Box::new(base), Box::new(p.unary()?))));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Node> {
+        self.skip_space();
+        let start = self.pos;
+        match self.src.get(self.pos) {
+            Some(b'(') => {
+                self.pos += 1;
+                let node = self.nested(|p| p.sum())?;
+                if !self.eat(b')') {
+                    return Err(self.error("expected ')'"));
+                }
+                Ok(node)
+            }
+            Some(c) if c.is_ascii_digit() || *c == b'.' => {
+                while self.src.get(self.pos).is_some_and(|c| c.is_ascii_digit() || *c == b'.') {
+                    self.pos += 1;
+                }
+                // An exponent: 1e-3, 2.5E+4
+                if self.src.get(self.pos).is_some_and(|c| *c == b'e' || *c == b'E') {
+                    let mark = self.pos;
+                    self.pos += 1;
+                    if self.src.get(self.pos).is_some_and(|c| *c == b'+' || *c == b'-') {
+                        self.pos += 1;
+                    }
+                    if self.src.get(self.pos).is_some_and(u8::is_ascii_digit) {
+                        while self.src.get(self.pos).is_some_and(u8::is_ascii_digit) {
+                            self.pos += 1;
+                        }
+                    } else {
+                        self.pos = mark;
+                    }
+                }
+                let text = std::str::from_utf8(&self.src[start..self.pos]).unwrap_or_default();
+                text.parse().map(Node::Number).map_err(|_| {
+                    self.pos = start;
+                    self.error("invalid number")
+                })
+            }
+            Some(c) if c.is_ascii_alphabetic() || *c == b'_' => {
+                let name = self.word();
+                match name.as_str() {
+                    "similarity" | "score" => Ok(Node::Similarity),
+                    "metadata" => {
+                        if self.src.get(self.pos) != Some(&b'.') {
+                            return Err(self.error("expected '.' and a metadata key after 'metadata'"));
+                        }
+                        self.pos += 1;
+                        let key = self.word();
+                        if key.is_empty() {
+                            return Err(self.error("expected a metadata key"));
+                        }
+                        Ok(Node::Field(key))
+                    }
+                    _ => {
+                        let Some(func) = Func::parse(&name) else {
+                            self.pos = start;
+                            return Err(self.error(&format!("unknown name '{}'", name)));
+                        };
+                        if !self.eat(b'(') {
+                            return Err(self.error(&format!("expected '(' after '{}'", name)));
+                        }
+                        let args = self.nested(|p| p.arguments())?;
+                        let (min, max) = func.arity();
+                        if args.len() < min || args.len() > max {
+                            self.pos = start;
+                            return Err(self.error(&format!("wrong number of arguments to '{}'", name)));
+                        }
+                        Ok(Node::Call(func, args))
+                    }
+                }
+            }
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of expression")),
+        }
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Node>> {
+        let mut args = Vec::new();
+        if self.eat(b')') {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.sum()?);
+            if self.eat(b')') {
+                return Ok(args);
+            }
+            if !self.eat(b',') {
+                return Err(self.error("expected ',' or ')'"));
+            }
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.pos;
+        while self.src.get(self.pos).is_some_and(|c| c.is_ascii_alphanumeric() || *c == b'_') {
+            self.pos += 1;
+        }
+        String::from_utf8_lossy(&self.src[start..self.pos]).into_owned()
+    }
+}
@@ -10,8 +10,20 @@ mod types;
 pub mod utils;
 pub mod query;
 pub mod engine;
+pub mod selectivity;
+pub mod budget;
+pub mod expr;
+pub mod order;
+pub mod bands;
+pub mod explain;
 
 pub use types::Hit;
 pub use query::{Filter, FilterCondition};
-pub use engine::{SearchParams, search_collection, search_batch_collection};
+pub use engine::{BINARY_RERANK_OVERFETCH, MetadataMap, SearchParams, SearchTarget, search_collection, search_batch_collection, search_target, search_target_within, search_target_in_rounds, search_batch_target};
+pub use selectivity::{SelectivityTracker, SelectivityEstimate};
+pub use budget::{LatencyBudget, EffectiveSearch};
+pub use expr::ScoreExpr;
+pub use order::OrderBy;
+pub use bands::{BandCount, ScoreBand, ScoreBands};
+pub use explain::{explain, SearchExplain, SearchStrategy};
 pub use crate::metrics::Metric;
@@ -0,0 +1,107 @@
+// Ordering search hits by a metadata field, for browsing semantically matched content in e.g.
+// chronological order.
+//
+// - primary (default): the k best hits by similarity, optionally cut at `min_score`, are sorted by
+//   the field instead of by score
+// - tie_break: hits stay in score order and the field only orders hits with equal scores, so ties
+//   come back in the same order on every query
+//
+// Integer and float values compare as numbers, strings compare lexicographically (ISO 8601
+// datetimes therefore sort chronologically), and numbers sort before strings. Hits without a
+// usable value (missing, boolean, null, array) sort last in either direction. Remaining ties fall
+// back to score and then id.
+
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, ServerError};
+use crate::metadata::MetadataValue;
+use crate::search::Hit;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderBy {
+    pub field: String, // metadata key, e.g. "published_at" or "_created_at"
+    #[serde(default)]
+    pub descending: bool,
+    #[serde(default)]
+    pub tie_break: bool, // only order hits with equal scores
+    #[serde(default)]
+    pub min_score: Option<f32>, // primary ordering: drop hits below this score first
+}
+
+impl Hash for OrderBy {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.field.hash(state);
+        self.descending.hash(state);
+        self.tie_break.hash(state);
+        self.min_score.map(f32::to_bits).hash(state);
+    }
+}
+
+// Sort key of a hit: a number, a string, or nothing
+#[derive(Debug, PartialEq)]
+enum Key<'a> {
+    Number(f64),
+    String(&'a str),
+}
+
+impl Key<'_> {
+    fn of(value: Option<&MetadataValue>) -> Option<Key<'_>> {
+        match value? {
+            MetadataValue::Integer(i) => Some(Key::Number(*i as f64)),
+            MetadataValue::Float(f) if !f.is_nan() => Some(Key::Number(*f)),
+            MetadataValue::String(s) => Some(Key::String(s)),
+            _ => None,
+        }
+    }
+
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Key::Number(a), Key::Number(b)) => a.total_cmp(b),
+            (Key::String(a), Key::String(b)) => a.cmp(b),
+            (Key::Number(_), Key::String(_)) => Ordering::Less,
+            (Key::String(_), Key::Number(_)) => Ordering::Greater,
+        }
+    }
+}
+
+impl OrderBy {
+    pub fn validate(&self) -> Result<()> {
+        if self.field.is_empty() {
+            return Err(ServerError::InvalidRequest("order_by.field must not be empty".into()).into());
+        }
+        if self.min_score.is_some_and(|s| !s.is_finite()) {
+            return Err(ServerError::InvalidRequest("order_by.min_score must be a finite number".into()).into());
+        }
+        if self.tie_break && self.min_score.is_some() {
+            return Err(ServerError::InvalidRequest("order_by.min_score applies to primary ordering, not tie_break".into()).into());
+        }
+        Ok(())
+    }
+
+    // Field order of two hits: missing values last, `descending` flips the rest
+    fn cmp_field(&self, a: &Hit, b: &Hit) -> Ordering {
+        match (Key::of(a.metadata.get(&self.field)), Key::of(b.metadata.get(&self.field))) {
+            (Some(a), Some(b)) if self.descending => b.cmp(&a),
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    // Reorder `hits` (in score order, best first)
+    pub fn apply(&self, hits: &mut Vec<Hit>) {
+        let by_score = |a: &Hit, b: &Hit| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal);
+        if self.tie_break {
+            hits.sort_by(|a, b| by_score(a, b).then_with(|| self.cmp_field(a, b)).then_with(|| a.id.cmp(&b.id)));
+            return;
+        }
+        if let Some(min_score) = self.min_score {
+            hits.retain(|hit| hit.score >= min_score);
+        }
+        hits.sort_by(|a, b| self.cmp_field(a, b).then_with(|| by_score(a, b)).then_with(|| a.id.cmp(&b.id)));
+    }
+}
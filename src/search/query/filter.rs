@@ -2,7 +2,8 @@
 
 use crate::metadata::{Metadata, MetadataValue};
 
-// Chainable filter builder. All conditions must match (AND logic).
+// Chainable filter builder. All conditions must match (AND logic); `or` and `not` nest other
+// filters, so any AND/OR/NOT tree can be built.
 #[derive(Debug, Clone)]
 pub struct Filter {
     conditions: Vec<FilterCondition>,
@@ -56,6 +57,34 @@ impl Filter {
         self
     }
 
+    pub fn any_in(mut self, field: &str, values: Vec<MetadataValue>) -> Self {
+        // - `any_in`: the field (an array, or a single value) holds at least one of the values
+        self.conditions.push(FilterCondition::AnyIn(field.to_string(), values));
+        self
+    }
+
+    pub fn all_in(mut self, field: &str, values: Vec<MetadataValue>) -> Self {
+        // - `all_in`: the field (an array, or a single value) holds every one of the values
+        self.conditions.push(FilterCondition::AllIn(field.to_string(), values));
+        self
+    }
+
+    pub fn or(mut self, alternatives: Vec<Filter>) -> Self {
+        // - `or`: at least one of the alternatives matches
+        self.conditions.push(FilterCondition::Or(alternatives));
+        self
+    }
+
+    pub fn not(mut self, filter: Filter) -> Self {
+        // - `not`: the nested filter does not match
+        self.conditions.push(FilterCondition::Not(Box::new(filter)));
+        self
+    }
+
+    pub fn conditions(&self) -> &[FilterCondition] {
+        &self.conditions
+    }
+
     pub fn matches(&self, metadata: &Metadata) -> bool {
         self.conditions.iter().all(|cond| cond.matches(metadata))
     }
@@ -63,6 +92,15 @@ impl Filter {
     pub fn is_empty(&self) -> bool {
         self.conditions.is_empty()
     }
+
+    // Shape of the filter: the fields and operators with the values dropped, in a stable order.
+    // `category = "a"` and `category = "b"` share a shape, so selectivity observed for one
+    // is a usable estimate for the other.
+    pub fn shape(&self) -> String {
+        let mut parts: Vec<String> = self.conditions.iter().map(|c| c.shape()).collect();
+        parts.sort();
+        parts.join("&")
+    }
 }
 
 impl Default for Filter {
@@ -81,16 +119,43 @@ pub enum FilterCondition {
     Lt(String, MetadataValue),
     Lte(String, MetadataValue),
     In(String, Vec<MetadataValue>),
+    // Element tests on array fields, e.g. tags; a scalar field counts as an array of one. An empty
+    // list matches nothing with AnyIn and everything with AllIn.
+    AnyIn(String, Vec<MetadataValue>),
+    AllIn(String, Vec<MetadataValue>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
 }
 
 impl FilterCondition {
+    fn shape(&self) -> String {
+        let (field, op) = match self {
+            FilterCondition::Or(alternatives) => {
+                let mut parts: Vec<String> = alternatives.iter().map(|f| f.shape()).collect();
+                parts.sort();
+                return format!("or({})", parts.join("|"));
+            }
+            FilterCondition::Not(filter) => return format!("not({})", filter.shape()),
+            FilterCondition::Eq(field, _) => (field, "eq"),
+            FilterCondition::Ne(field, _) => (field, "ne"),
+            FilterCondition::Gt(field, _) => (field, "gt"),
+            FilterCondition::Gte(field, _) => (field, "gte"),
+            FilterCondition::Lt(field, _) => (field, "lt"),
+            FilterCondition::Lte(field, _) => (field, "lte"),
+            FilterCondition::In(field, _) => (field, "in"),
+            FilterCondition::AnyIn(field, _) => (field, "any"),
+            FilterCondition::AllIn(field, _) => (field, "all"),
+        };
+        format!("{field}:{op}")
+    }
+
     pub fn matches(&self, metadata: &Metadata) -> bool {
         match self {
             FilterCondition::Eq(field, expected) => {
-                metadata.get(field).map_or(false, |v| v == expected)
+                metadata.get(field).is_some_and(|v| v == expected)
             }
             FilterCondition::Ne(field, expected) => {
-                metadata.get(field).map_or(true, |v| v != expected)
+                metadata.get(field).is_none_or(|v| v != expected)
             }
             FilterCondition::Gt(field, expected) => {
                 compare_values(metadata.get(field), expected, |a, b| a > b)
@@ -105,12 +170,28 @@ impl FilterCondition {
                 compare_values(metadata.get(field), expected, |a, b| a <= b)
             }
             FilterCondition::In(field, values) => {
-                metadata.get(field).map_or(false, |v| values.contains(v))
+                metadata.get(field).is_some_and(|v| values.contains(v))
+            }
+            FilterCondition::AnyIn(field, values) => {
+                metadata.get(field).is_some_and(|v| values.iter().any(|value| holds(v, value)))
+            }
+            FilterCondition::AllIn(field, values) => {
+                values.iter().all(|value| metadata.get(field).is_some_and(|v| holds(v, value)))
             }
+            FilterCondition::Or(alternatives) => alternatives.iter().any(|f| f.matches(metadata)),
+            FilterCondition::Not(filter) => !filter.matches(metadata),
         }
     }
 }
 
+// Whether a field's value holds `value`: as an element when it is an array, by equality otherwise
+fn holds(field: &MetadataValue, value: &MetadataValue) -> bool {
+    match field {
+        MetadataValue::Array(elements) => elements.contains(value),
+        field => field == value,
+    }
+}
+
 // Helper to compare numeric metadata values
 fn compare_values<F>(actual: Option<&MetadataValue>, expected: &MetadataValue, cmp: F) -> bool
 where
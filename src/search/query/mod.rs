@@ -3,5 +3,8 @@
 // Allows filtering vectors by metadata during similarity search.
 
 mod filter;
+mod postings;
 
 pub use filter::{Filter, FilterCondition};
+pub use roaring::RoaringBitmap;
+pub use postings::{MetadataPostings, FilterMatches};
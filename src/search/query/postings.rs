@@ -0,0 +1,316 @@
+// Secondary metadata index: per-value posting lists for the configured fields, as roaring bitmaps
+// over dense internal ids. The elements of array values get posting lists of their own, for the
+// any/all element filters; equality still only matches whole values. A filter whose every condition reads an indexed field is evaluated as a
+// tree of bitmap operations (AND = intersection, OR = union, NOT = difference from the live set),
+// giving the exact set of matching documents before the vector index is touched.
+//
+// Dense ids are handed out on first sight of a document id and kept when it is removed, so a
+// re-inserted document gets its old slot back. Slots of removed documents are only reclaimed when
+// the index is rebuilt (cache rebuild, reopen).
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Bound;
+
+use uuid::Uuid;
+
+use roaring::RoaringBitmap;
+
+use super::filter::{Filter, FilterCondition};
+use crate::metadata::{Metadata, MetadataValue};
+
+// Equality key of a scalar metadata value. Arrays have none: they only equal other arrays, which
+// the index does not answer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ValueKey {
+    String(String),
+    Integer(i64),
+    Float(u64), // bits, with -0.0 folded into 0.0
+    Boolean(bool),
+    Null,
+}
+
+impl ValueKey {
+    fn of(value: &MetadataValue) -> Option<ValueKey> {
+        Some(match value {
+            MetadataValue::String(s) => ValueKey::String(s.clone()),
+            MetadataValue::Integer(i) => ValueKey::Integer(*i),
+            MetadataValue::Float(f) if f.is_nan() => return None, // NaN equals nothing
+            MetadataValue::Float(f) => ValueKey::Float(if *f == 0.0 { 0f64.to_bits() } else { f.to_bits() }),
+            MetadataValue::Boolean(b) => ValueKey::Boolean(*b),
+            MetadataValue::Null => ValueKey::Null,
+            MetadataValue::Array(_) => return None,
+        })
+    }
+}
+
+// Numeric value for range conditions, ordered; integers and floats compare as f64 like the filter does
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Number(f64);
+
+impl Number {
+    fn of(value: &MetadataValue) -> Option<Number> {
+        let n = match value {
+            MetadataValue::Integer(i) => *i as f64,
+            MetadataValue::Float(f) => *f,
+            _ => return None,
+        };
+        // NaN fails every comparison; -0.0 and 0.0 compare equal
+        (!n.is_nan()).then_some(Number(if n == 0.0 { 0.0 } else { n }))
+    }
+}
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[derive(Debug, Default)]
+struct FieldPostings {
+    values: HashMap<ValueKey, RoaringBitmap>,
+    numbers: BTreeMap<Number, RoaringBitmap>,
+    elements: HashMap<ValueKey, RoaringBitmap>, // scalar elements of array values
+}
+
+impl FieldPostings {
+    fn eq(&self, value: &MetadataValue) -> Option<RoaringBitmap> {
+        match value {
+            MetadataValue::Array(_) => None,
+            value => Some(ValueKey::of(value).and_then(|key| self.values.get(&key)).cloned().unwrap_or_default()),
+        }
+    }
+
+    fn range(&self, value: &MetadataValue, lower: bool, inclusive: bool) -> RoaringBitmap {
+        let Some(n) = Number::of(value) else {
+            return RoaringBitmap::new();
+        };
+        let bound = if inclusive { Bound::Included(n) } else { Bound::Excluded(n) };
+        let range = if lower { (bound, Bound::Unbounded) } else { (Bound::Unbounded, bound) };
+        self.numbers.range(range).fold(RoaringBitmap::new(), |acc, (_, ids)| acc | ids)
+    }
+
+    // Documents whose value is `value` or an array holding it; None for an array, which only the
+    // filter itself compares
+    fn holds(&self, value: &MetadataValue) -> Option<RoaringBitmap> {
+        let whole = self.eq(value)?;
+        Some(match ValueKey::of(value).and_then(|key| self.elements.get(&key)) {
+            Some(elements) => whole | elements,
+            None => whole,
+        })
+    }
+}
+
+// Keys of the scalar elements of an array value
+fn element_keys(value: &MetadataValue) -> impl Iterator<Item = ValueKey> + '_ {
+    let elements = match value {
+        MetadataValue::Array(elements) => elements.as_slice(),
+        _ => &[],
+    };
+    elements.iter().filter_map(ValueKey::of)
+}
+
+// Ids matching a filter, as evaluated on the postings
+#[derive(Debug, Clone)]
+pub struct FilterMatches {
+    pub count: usize,
+    pub ids: Option<Vec<Uuid>>, // the matching ids, when there were no more than asked for
+}
+
+#[derive(Debug, Default)]
+pub struct MetadataPostings {
+    fields: HashSet<String>,
+    dense: HashMap<Uuid, u32>,
+    ids: Vec<Uuid>, // dense id -> document id
+    live: RoaringBitmap,
+    postings: HashMap<String, FieldPostings>,
+}
+
+impl MetadataPostings {
+    pub fn new(fields: &[String]) -> Self {
+        Self {
+            fields: fields.iter().cloned().collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.live.len() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        let fields = std::mem::take(&mut self.fields);
+        *self = Self { fields, ..Default::default() };
+    }
+
+    // Index `metadata` for `id`, replacing what was indexed for `previous` (its prior metadata)
+    pub fn insert(&mut self, id: Uuid, previous: Option<&Metadata>, metadata: &Metadata) {
+        if let Some(previous) = previous {
+            self.remove(&id, previous);
+        }
+        let dense = match self.dense.get(&id) {
+            Some(&dense) => dense,
+            None => {
+                let dense = self.ids.len() as u32;
+                self.ids.push(id);
+                self.dense.insert(id, dense);
+                dense
+            }
+        };
+        self.live.insert(dense);
+        for (field, value) in metadata.iter().filter(|(field, _)| self.fields.contains(*field)) {
+            let postings = self.postings.entry(field.clone()).or_default();
+            if let Some(key) = ValueKey::of(value) {
+                postings.values.entry(key).or_default().insert(dense);
+            }
+            if let Some(n) = Number::of(value) {
+                postings.numbers.entry(n).or_default().insert(dense);
+            }
+            for key in element_keys(value) {
+                postings.elements.entry(key).or_default().insert(dense);
+            }
+        }
+    }
+
+    // Drop `id`, whose indexed metadata is `metadata`
+    pub fn remove(&mut self, id: &Uuid, metadata: &Metadata) {
+        let Some(&dense) = self.dense.get(id) else {
+            return;
+        };
+        self.live.remove(dense);
+        for (field, value) in metadata.iter() {
+            let Some(postings) = self.postings.get_mut(field) else {
+                continue;
+            };
+            if let Some(key) = ValueKey::of(value) {
+                if let Some(ids) = postings.values.get_mut(&key) {
+                    ids.remove(dense);
+                    if ids.is_empty() {
+                        postings.values.remove(&key);
+                    }
+                }
+            }
+            if let Some(n) = Number::of(value) {
+                if let Some(ids) = postings.numbers.get_mut(&n) {
+                    ids.remove(dense);
+                    if ids.is_empty() {
+                        postings.numbers.remove(&n);
+                    }
+                }
+            }
+            for key in element_keys(value) {
+                if let Some(ids) = postings.elements.get_mut(&key) {
+                    ids.remove(dense);
+                    if ids.is_empty() {
+                        postings.elements.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+
+    // Whether every condition of the filter reads an indexed field
+    pub fn covers(&self, filter: &Filter) -> bool {
+        filter.conditions().iter().all(|condition| match condition {
+            FilterCondition::Or(alternatives) => alternatives.iter().all(|f| self.covers(f)),
+            FilterCondition::Not(filter) => self.covers(filter),
+            FilterCondition::Eq(field, _)
+            | FilterCondition::Ne(field, _)
+            | FilterCondition::Gt(field, _)
+            | FilterCondition::Gte(field, _)
+            | FilterCondition::Lt(field, _)
+            | FilterCondition::Lte(field, _)
+            | FilterCondition::In(field, _)
+            | FilterCondition::AnyIn(field, _)
+            | FilterCondition::AllIn(field, _) => self.fields.contains(field),
+        })
+    }
+
+    // Dense ids of the live documents matching the filter; None when the filter reads a field that
+    // is not indexed or compares against a value the postings cannot answer (an array)
+    pub fn evaluate(&self, filter: &Filter) -> Option<RoaringBitmap> {
+        if !self.covers(filter) {
+            return None;
+        }
+        self.evaluate_and(filter)
+    }
+
+    fn evaluate_and(&self, filter: &Filter) -> Option<RoaringBitmap> {
+        let mut result: Option<RoaringBitmap> = None;
+        for condition in filter.conditions() {
+            let ids = self.evaluate_condition(condition)?;
+            result = Some(match result {
+                Some(acc) => acc & ids,
+                None => ids,
+            });
+        }
+        // No conditions match everything
+        Some(result.unwrap_or_else(|| self.live.clone()))
+    }
+
+    fn evaluate_condition(&self, condition: &FilterCondition) -> Option<RoaringBitmap> {
+        let empty = FieldPostings::default();
+        let field = |name: &String| self.postings.get(name).unwrap_or(&empty);
+        Some(match condition {
+            FilterCondition::Eq(name, value) => field(name).eq(value)?,
+            // A document without the field is not equal to anything
+            FilterCondition::Ne(name, value) => &self.live - field(name).eq(value)?,
+            FilterCondition::Gt(name, value) => field(name).range(value, true, false),
+            FilterCondition::Gte(name, value) => field(name).range(value, true, true),
+            FilterCondition::Lt(name, value) => field(name).range(value, false, false),
+            FilterCondition::Lte(name, value) => field(name).range(value, false, true),
+            FilterCondition::In(name, values) => {
+                let postings = field(name);
+                let mut ids = RoaringBitmap::new();
+                for value in values {
+                    ids |= postings.eq(value)?;
+                }
+                ids
+            }
+            FilterCondition::AnyIn(name, values) => {
+                let postings = field(name);
+                let mut ids = RoaringBitmap::new();
+                for value in values {
+                    ids |= postings.holds(value)?;
+                }
+                ids
+            }
+            // Smallest lists first, so the intersection shrinks as early as it can
+            FilterCondition::AllIn(name, values) => {
+                let postings = field(name);
+                let mut lists = values.iter().map(|value| postings.holds(value)).collect::<Option<Vec<_>>>()?;
+                lists.sort_by_key(RoaringBitmap::len);
+                let mut lists = lists.into_iter();
+                let first = lists.next().unwrap_or_else(|| self.live.clone());
+                lists.fold(first, |acc, ids| acc & ids)
+            }
+            FilterCondition::Or(alternatives) => {
+                let mut ids = RoaringBitmap::new();
+                for alternative in alternatives {
+                    ids |= self.evaluate_and(alternative)?;
+                }
+                ids
+            }
+            FilterCondition::Not(filter) => &self.live - self.evaluate_and(filter)?,
+        })
+    }
+
+    // Evaluate the filter; the matching document ids are returned when there are at most `max_ids`
+    pub fn matches(&self, filter: &Filter, max_ids: usize) -> Option<FilterMatches> {
+        let bitmap = self.evaluate(filter)?;
+        let count = bitmap.len() as usize;
+        let ids = (count <= max_ids).then(|| bitmap.iter().map(|dense| self.ids[dense as usize]).collect());
+        Some(FilterMatches { count, ids })
+    }
+}
@@ -0,0 +1,70 @@
+// Filter selectivity tracking for payload-aware overfetch.
+// A static filter_overfetch works for loose filters but starves highly selective ones: if only 1%
+// of the collection matches, fetching 10*k candidates from the index leaves ~0.1*k hits after the
+// post-filter. We record, per filter shape, what fraction of index candidates survived the filter
+// and keep a moving average of it. The search engine uses that estimate to size the overfetch of
+// the next query with the same shape, or to skip the index and scan exactly when the estimated
+// number of matches is below k.
+
+use dashmap::DashMap;
+
+// Weight of the newest observation in the moving average
+const SMOOTHING: f32 = 0.3;
+
+// Floor for the estimate so a shape that matched nothing does not divide by zero
+const MIN_SELECTIVITY: f32 = 1e-6;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SelectivityEstimate {
+    pub selectivity: f32, // Fraction of candidates that matched the filter (0.0 - 1.0)
+    pub samples: u64,     // Number of observations folded into the estimate
+}
+
+// Per-collection selectivity stats, keyed by Filter::shape().
+// Interior mutability so searches (which only hold a read lock on the collection) can record.
+#[derive(Debug, Default)]
+pub struct SelectivityTracker {
+    shapes: DashMap<String, SelectivityEstimate>,
+}
+
+impl SelectivityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Record one observation: `matched` of `candidates` passed the filter
+    pub fn record(&self, shape: &str, candidates: usize, matched: usize) {
+        if candidates == 0 {
+            return;
+        }
+        let observed = (matched.min(candidates) as f32 / candidates as f32).max(MIN_SELECTIVITY);
+        self.shapes
+            .entry(shape.to_string())
+            .and_modify(|est| {
+                est.selectivity = SMOOTHING * observed + (1.0 - SMOOTHING) * est.selectivity;
+                est.samples += 1;
+            })
+            .or_insert(SelectivityEstimate { selectivity: observed, samples: 1 });
+    }
+
+    pub fn estimate(&self, shape: &str) -> Option<f32> {
+        self.shapes.get(shape).map(|est| est.selectivity)
+    }
+
+    pub fn snapshot(&self) -> Vec<(String, SelectivityEstimate)> {
+        self.shapes.iter().map(|e| (e.key().clone(), *e.value())).collect()
+    }
+
+    pub fn clear(&self) {
+        self.shapes.clear();
+    }
+}
+
+// Overfetch multiplier that should leave about k hits after filtering, given the selectivity.
+// Never goes below `floor` (the configured/requested overfetch) and never above `cap`, unless the
+// floor itself is already higher than the cap.
+pub fn tuned_overfetch(selectivity: f32, floor: usize, cap: usize) -> usize {
+    let needed = (1.0 / selectivity.max(MIN_SELECTIVITY)).ceil();
+    let needed = if needed >= usize::MAX as f32 { usize::MAX } else { needed as usize };
+    needed.min(cap).max(floor).max(1)
+}
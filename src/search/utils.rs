@@ -1,13 +1,33 @@
 // Helper utilities for search operations
 
+use std::cmp::Ordering;
+
+use uuid::Uuid;
+
 use crate::search::Hit;
 
-// Sort search results by score (descending) and truncate to k
+// Order of (id, score) candidates: higher score first, equal scores by id. Without the id, ties would
+// come out in whatever order the candidates were gathered in (often hash map order), which can change
+// from one run to the next.
+pub(crate) fn best_first(a: &(Uuid, f32), b: &(Uuid, f32)) -> Ordering {
+    b.1.partial_cmp(&a.1)
+        .unwrap_or(Ordering::Equal) // Handle NaN cases by treating them as equal
+        .then_with(|| a.0.cmp(&b.0))
+}
+
+// Sort search results by score (descending, ties by id) and truncate to k
 pub(crate) fn sort_and_truncate(results: &mut Vec<Hit>, k: usize) {
-    results.sort_by(|a, b| {
-        b.score
-            .partial_cmp(&a.score) // Sort by score (descending)
-            .unwrap_or(std::cmp::Ordering::Equal) // Handle NaN cases by treating them as equal
-    }); // Sort by score (descending)
+    results.sort_by(|a, b| best_first(&(a.id, a.score), &(b.id, b.score)));
     results.truncate(k);
 }
+
+// Keep only the best-scoring hit for each value of a metadata key. Hits without the key are all kept.
+pub(crate) fn dedup_by_metadata(results: &mut Vec<Hit>, key: &str) {
+    results.sort_by(|a, b| best_first(&(a.id, a.score), &(b.id, b.score)));
+    let mut seen = std::collections::HashSet::new();
+    results.retain(|hit| match hit.metadata.get(key) {
+        // MetadataValue holds floats, so compare on the serialized form
+        Some(value) => seen.insert(serde_json::to_string(value).unwrap_or_default()),
+        None => true,
+    });
+}
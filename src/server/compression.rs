@@ -0,0 +1,66 @@
+// HTTP compression, on tower-http's gzip and zstd codecs.
+// Responses are compressed when the client's Accept-Encoding allows one of the enabled encodings
+// (tower-http negotiates q-values), the content type is textual and the body reaches
+// `compression.min_size_bytes`. NDJSON search streams are left alone so they keep streaming.
+// The layer is built from the config on every response, so a config reload applies at once.
+// Request bodies sent with `Content-Encoding: gzip` or `zstd` are decompressed before the extractors
+// (and the body limit) see them; other encodings are refused with 415 (see routes.rs).
+
+use axum::{
+    body::{Body, HttpBody},
+    extract::State,
+    http::{header, Method, Request, Response as HttpResponse, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use tower::{Layer, ServiceExt};
+use tower_http::compression::{predicate::Predicate, CompressionLayer, CompressionLevel};
+
+use super::state::SharedState;
+
+fn compressible_type(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    essence.starts_with("text/")
+        || essence == "application/json"
+        || essence == "application/javascript"
+        || essence.ends_with("+json")
+}
+
+// Which responses may be compressed, whatever the client accepts
+#[derive(Clone, Copy)]
+struct Compressible {
+    min_size: u64,
+}
+
+impl Predicate for Compressible {
+    fn should_compress<B: HttpBody>(&self, res: &HttpResponse<B>) -> bool {
+        let headers = res.headers();
+        let size = res.body().size_hint().exact().or_else(|| {
+            headers.get(header::CONTENT_LENGTH).and_then(|v| v.to_str().ok()).and_then(|v| v.parse().ok())
+        });
+        res.status() != StatusCode::NO_CONTENT
+            && res.status() != StatusCode::NOT_MODIFIED
+            && size.is_none_or(|size| size >= self.min_size)
+            && headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(compressible_type)
+    }
+}
+
+/// Middleware that compresses responses for clients that accept gzip or zstd.
+pub async fn compress_response(State(state): State<SharedState>, req: Request<Body>, next: Next) -> Response {
+    let config = state.app_config.read().compression.clone();
+    if !config.enabled || req.method() == Method::HEAD {
+        return next.run(req).await;
+    }
+    let layer = CompressionLayer::new()
+        .gzip(config.gzip)
+        .zstd(config.zstd)
+        .quality(CompressionLevel::Precise(config.level as i32))
+        .compress_when(Compressible { min_size: config.min_size_bytes as u64 });
+    match layer.layer(next).oneshot(req).await {
+        Ok(res) => res.map(Body::new),
+        Err(never) => match never {},
+    }
+}
@@ -0,0 +1,209 @@
+// One server per data dir.
+// Collection locks (storage/collection/lock.rs) keep two processes off a collection's files, but the
+// rest of the data dir (the job queue, projects, usage, feedback, the query log, the body spool) is
+// read at startup and rewritten as the server runs, so a second server on the same dir would undo
+// the first one's work. At startup the server takes `piramid.lock` in the data dir: an exclusive
+// advisory lock, plus the owner's pid, host and a heartbeat rewritten every `heartbeat_secs`.
+//
+// Another owner counts as live when it holds the file lock, or, when the lock is free but the file
+// names a process on another host with a heartbeat under `stale_after_secs` old: shared filesystems
+// do not always carry locks between hosts. On the owner's own host a free lock means it is gone.
+// A server finding a live owner refuses to start, or with `on_conflict: standby` serves
+// `/api/health` and answers everything else with DATA_DIR_LOCKED until it can take the lock. It
+// opens nothing under the data dir before then: reading collections would mean replaying a WAL the
+// owner is still appending to, over files it rewrites at every checkpoint.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use axum::{routing::get, Json, Router};
+use fs2::FileExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config::DataDirLockConfig;
+use crate::error::{PiramidError, Result, ServerError};
+use crate::testing::clock::now_secs;
+
+pub const DATA_DIR_LOCK_FILE: &str = "piramid.lock";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataDirOwner {
+    pub pid: u32,
+    pub host: String,
+    pub started_at: u64,
+    pub heartbeat_at: u64,
+}
+
+impl DataDirOwner {
+    fn describe(&self) -> String {
+        let now = now_secs();
+        format!(
+            "pid {} on {} (started {}s ago, last heartbeat {}s ago)",
+            self.pid,
+            self.host,
+            now.saturating_sub(self.started_at),
+            now.saturating_sub(self.heartbeat_at),
+        )
+    }
+}
+
+pub fn host_name() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn lock_path(data_dir: &str) -> PathBuf {
+    Path::new(data_dir).join(DATA_DIR_LOCK_FILE)
+}
+
+fn read_owner(file: &mut File) -> Option<DataDirOwner> {
+    let mut contents = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut contents).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+// The owner the lock file names, live or not
+pub fn data_dir_owner(data_dir: &str) -> Option<DataDirOwner> {
+    read_owner(&mut File::open(lock_path(data_dir)).ok()?)
+}
+
+fn locked(data_dir: &str, owner: Option<&DataDirOwner>) -> PiramidError {
+    ServerError::DataDirLocked(format!(
+        "data dir {data_dir} is in use by {}; stop it or point this server at another data dir",
+        owner.map_or_else(|| "another process".to_string(), DataDirOwner::describe),
+    )).into()
+}
+
+// Held by the server that owns the data dir; dropping it empties the file and releases the lock
+pub struct DataDirLock {
+    file: Mutex<File>,
+    owner: Mutex<DataDirOwner>,
+}
+
+impl DataDirLock {
+    pub fn acquire(data_dir: &str, config: &DataDirLockConfig) -> Result<Self> {
+        std::fs::create_dir_all(data_dir)?;
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(lock_path(data_dir))?;
+        if let Err(e) = file.try_lock_exclusive() {
+            if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                return Err(locked(data_dir, read_owner(&mut file).as_ref()));
+            }
+            return Err(e.into());
+        }
+        let host = host_name();
+        if let Some(previous) = read_owner(&mut file) {
+            let fresh = now_secs().saturating_sub(previous.heartbeat_at) < config.stale_after_secs;
+            if previous.host != host && fresh {
+                return Err(locked(data_dir, Some(&previous)));
+            }
+            tracing::warn!(owner=%previous.describe(), "data_dir_lock_taken_over");
+        }
+        let now = now_secs();
+        let lock = DataDirLock {
+            file: Mutex::new(file),
+            owner: Mutex::new(DataDirOwner { pid: std::process::id(), host, started_at: now, heartbeat_at: now }),
+        };
+        lock.heartbeat()?;
+        Ok(lock)
+    }
+
+    // Take the lock once the live owner is gone, checking every `heartbeat_secs`
+    pub async fn wait_for(data_dir: &str, config: &DataDirLockConfig) -> Result<Self> {
+        loop {
+            match Self::acquire(data_dir, config) {
+                Err(PiramidError::Server(ServerError::DataDirLocked(_))) => {
+                    tokio::time::sleep(Duration::from_secs(config.heartbeat_secs)).await;
+                }
+                acquired => return acquired,
+            }
+        }
+    }
+
+    pub fn owner(&self) -> DataDirOwner {
+        self.owner.lock().clone()
+    }
+
+    // Rewrite the lock file with the current time as the heartbeat
+    pub fn heartbeat(&self) -> Result<()> {
+        let mut owner = self.owner.lock();
+        owner.heartbeat_at = now_secs();
+        let mut file = self.file.lock();
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&serde_json::to_vec(&*owner)?)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    // Keep the heartbeat going until the lock is dropped
+    pub fn spawn_heartbeat(self: &Arc<Self>, every: Duration) {
+        let lock: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(lock) = lock.upgrade() else { break };
+                if let Err(e) = lock.heartbeat() {
+                    tracing::warn!(error=%e, "data_dir_heartbeat_failed");
+                }
+            }
+        });
+    }
+}
+
+impl Drop for DataDirLock {
+    fn drop(&mut self) {
+        let _ = self.file.get_mut().set_len(0);
+    }
+}
+
+// What a standby answers: its health, and DATA_DIR_LOCKED naming the owner for everything else
+pub fn standby_router(data_dir: String) -> Router {
+    let health = || async { Json(json!({"status": "standby"})) };
+    let owned = {
+        let data_dir = data_dir.clone();
+        move || {
+            let data_dir = data_dir.clone();
+            async move { locked(&data_dir, data_dir_owner(&data_dir).as_ref()) }
+        }
+    };
+    Router::new()
+        .route("/api/health", get(health))
+        .route("/api/v1/health", get(health))
+        .fallback(owned)
+}
+
+// Serve as a standby on `addr` until the data dir's owner is gone, then hand back its lock
+pub async fn run_standby(addr: &str, data_dir: &str, config: &DataDirLockConfig) -> Result<DataDirLock> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let owner = data_dir_owner(data_dir);
+    tracing::warn!(data_dir, owner=?owner.as_ref().map(DataDirOwner::describe), "data_dir_standby");
+    let (acquired, taken) = tokio::sync::oneshot::channel();
+    let (data_dir, config) = (data_dir.to_string(), *config);
+    let waiter = data_dir.clone();
+    tokio::spawn(async move {
+        let _ = acquired.send(DataDirLock::wait_for(&waiter, &config).await);
+    });
+    let lock = Arc::new(Mutex::new(None));
+    let result = lock.clone();
+    axum::serve(listener, standby_router(data_dir))
+        .with_graceful_shutdown(async move {
+            *result.lock() = taken.await.ok();
+        })
+        .await?;
+    let taken = lock.lock().take();
+    taken.unwrap_or_else(|| Err(ServerError::Internal("standby stopped before taking the data dir".to_string()).into()))
+}
@@ -0,0 +1,140 @@
+// Simulated failures per route (see config/fault_injection.rs), for exercising clients' retry and
+// fallback logic. Only compiled into debug builds and builds with the `fault-injection` feature.
+// Runs inside load shedding, so a slowed request holds its slot like a really slow one would.
+// Injected errors carry the status, error_code and Retry-After a real one would; the
+// `x-piramid-fault` header names what was injected (latency, lock, error) so tests can tell.
+
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::config::FaultRule;
+use crate::error::server::error_body;
+use crate::error::ErrorCode;
+use super::state::SharedState;
+
+pub const FAULT_HEADER: &str = "x-piramid-fault";
+
+pub async fn inject_faults(State(state): State<SharedState>, req: Request<Body>, next: Next) -> Response {
+    let rules = {
+        let config = state.app_config.read();
+        config.fault_injection.enabled.then(|| config.fault_injection.rules.clone())
+    };
+    let Some(rules) = rules else {
+        return next.run(req).await;
+    };
+    let Some(path) = api_path(req.uri().path()).map(str::to_owned) else {
+        return next.run(req).await;
+    };
+
+    let (mut latency_ms, mut lock_ms, mut error) = (0u64, 0u64, None);
+    for rule in rules.iter().filter(|rule| applies(rule, req.method(), &path)) {
+        if roll(rule.latency_probability) {
+            latency_ms += rule.latency_ms;
+        }
+        if roll(rule.lock_probability) {
+            lock_ms = lock_ms.max(rule.lock_ms);
+        }
+        if error.is_none() && roll(rule.error_probability) {
+            error = Some(rule.error_status);
+        }
+    }
+
+    let mut injected = Vec::new();
+    if lock_ms > 0 && hold_collection_lock(&state, &path, Duration::from_millis(lock_ms)).await {
+        injected.push("lock");
+    }
+    if latency_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(latency_ms)).await;
+        injected.push("latency");
+    }
+    let mut res = match error {
+        Some(status) => {
+            injected.push("error");
+            error_response(status)
+        }
+        None => next.run(req).await,
+    };
+    if !injected.is_empty() {
+        tracing::debug!(path=%path, faults=%injected.join(","), "fault_injected");
+        if let Ok(value) = HeaderValue::from_str(&injected.join(",")) {
+            res.headers_mut().insert(FAULT_HEADER, value);
+        }
+    }
+    res
+}
+
+// The path with /api/v1 folded into /api; None outside the API
+fn api_path(path: &str) -> Option<&str> {
+    let rest = path.strip_prefix("/api")?;
+    let rest = rest.strip_prefix("/v1").filter(|r| r.is_empty() || r.starts_with('/')).unwrap_or(rest);
+    (rest.is_empty() || rest.starts_with('/')).then_some(rest)
+}
+
+fn applies(rule: &FaultRule, method: &Method, path: &str) -> bool {
+    let method_ok = rule.methods.is_empty() || rule.methods.iter().any(|m| m.eq_ignore_ascii_case(method.as_str()));
+    method_ok && route_matches(api_path(&rule.route).unwrap_or(&rule.route), path)
+}
+
+// `*` matches one segment, a trailing `**` any rest (none included)
+pub fn route_matches(pattern: &str, path: &str) -> bool {
+    let mut pattern = pattern.trim_end_matches('/').split('/');
+    let mut path = path.trim_end_matches('/').split('/');
+    loop {
+        match (pattern.next(), path.next()) {
+            (Some("**"), _) => return pattern.next().is_none(),
+            (Some(want), Some(got)) if want == "*" || want == got => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+fn roll(probability: f64) -> bool {
+    probability > 0.0 && rand::random::<f64>() < probability
+}
+
+// Write-lock the collection the path names for `hold`, returning once the lock is taken, so the
+// request and anything else on the collection wait behind it. False when no such collection is open.
+async fn hold_collection_lock(state: &SharedState, path: &str, hold: Duration) -> bool {
+    let mut segments = path.trim_start_matches('/').split('/');
+    let (Some("collections"), Some(name)) = (segments.next(), segments.next()) else {
+        return false;
+    };
+    let Some(collection) = state.collections.get(name).map(|entry| entry.value().clone()) else {
+        return false;
+    };
+    let (held_tx, held_rx) = tokio::sync::oneshot::channel();
+    tokio::task::spawn_blocking(move || {
+        let _guard = collection.write();
+        let _ = held_tx.send(());
+        std::thread::sleep(hold);
+    });
+    held_rx.await.is_ok()
+}
+
+// What a real error with this status looks like
+fn error_response(status: u16) -> Response {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+    let code = match status.as_u16() {
+        400 => ErrorCode::InvalidRequest,
+        404 => ErrorCode::NotFound,
+        408 | 504 => ErrorCode::Timeout,
+        409 => ErrorCode::Conflict,
+        413 => ErrorCode::PayloadTooLarge,
+        429 => ErrorCode::RateLimited,
+        503 => ErrorCode::ServiceUnavailable,
+        _ => ErrorCode::Internal,
+    };
+    let mut res = error_body(status, format!("Injected fault: {}", status.canonical_reason().unwrap_or("error")), code);
+    if matches!(status.as_u16(), 429 | 503) {
+        res.headers_mut().insert("retry-after", HeaderValue::from(1));
+    }
+    res
+}
@@ -0,0 +1,237 @@
+// Relevance feedback and the re-ranker learned from it.
+// POST /api/collections/{name}/feedback records one signal for one document: `click` or
+// `positive` when it was relevant, `skip` (shown and passed over) or `negative` when it was not.
+//
+// - Every signal moves the document's feedback prior, the smoothed log-odds of its positive
+//   against its negative signals: ln((positive + s/2) / (negative + s/2)) with
+//   s = rerank.prior_strength, so it is 0 until the document has feedback.
+// - A signal sent with the score the document was shown with is also one step of online logistic
+//   regression of relevance on (similarity, prior). The weights start out ranking by similarity
+//   with a light nudge from the prior, and learn from there how much the prior is worth.
+// - Searches on the collections in `rerank.collections` reorder their hits by the blend's
+//   probability (`rerank_score`); `score` stays the similarity. Only the hits a search returns are
+//   reordered, so feedback moves a document up the page, not onto it. A primary `order_by` wins.
+//
+// Stored in data_dir/feedback/<collection>.json (temp file + rename), written at most every
+// SAVE_INTERVAL_SECS while signals come in and on checkpoint.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::RerankConfig;
+use crate::error::Result;
+use crate::server::types::HitResponse;
+
+// Longest a collection's feedback goes unsaved while signals are being recorded
+const SAVE_INTERVAL_SECS: u64 = 5;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Signal {
+    Click,
+    Positive,
+    Skip,
+    Negative,
+}
+
+impl Signal {
+    pub fn is_positive(self) -> bool {
+        matches!(self, Signal::Click | Signal::Positive)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignalCounts {
+    pub positive: u64,
+    pub negative: u64,
+}
+
+impl SignalCounts {
+    pub fn prior(&self, strength: f32) -> f32 {
+        let half = strength / 2.0;
+        ((self.positive as f32 + half) / (self.negative as f32 + half)).ln()
+    }
+}
+
+// The blend P(relevant) = sigmoid(bias + similarity * score + prior * feedback prior)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RerankWeights {
+    pub bias: f32,
+    pub similarity: f32,
+    pub prior: f32,
+    pub trained_on: u64, // signals with a score the weights have learned from
+}
+
+impl Default for RerankWeights {
+    fn default() -> Self {
+        Self { bias: 0.0, similarity: 10.0, prior: 1.0, trained_on: 0 }
+    }
+}
+
+impl RerankWeights {
+    pub fn probability(&self, score: f32, prior: f32) -> f32 {
+        1.0 / (1.0 + (-(self.bias + self.similarity * score + self.prior * prior)).exp())
+    }
+
+    // One gradient step of the log loss on a labelled example
+    fn learn(&mut self, score: f32, prior: f32, relevant: bool, rate: f32) {
+        let error = self.probability(score, prior) - if relevant { 1.0 } else { 0.0 };
+        self.bias -= rate * error;
+        self.similarity -= rate * error * score;
+        self.prior -= rate * error * prior;
+        self.trained_on += 1;
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CollectionFeedback {
+    #[serde(default)]
+    weights: RerankWeights,
+    #[serde(default)]
+    documents: HashMap<String, SignalCounts>, // by document UUID
+    #[serde(skip)]
+    dirty: bool,
+}
+
+// What POST .../feedback did to the document and the blend
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedbackOutcome {
+    pub document_id: String,
+    pub counts: SignalCounts,
+    pub prior: f32,
+    pub trained: bool, // the signal had a score, so the weights learned from it
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedbackStats {
+    pub rerank_enabled: bool,
+    pub documents: usize, // with at least one signal
+    pub positive: u64,
+    pub negative: u64,
+    pub weights: RerankWeights,
+}
+
+pub struct FeedbackStore {
+    dir: PathBuf,
+    collections: Mutex<HashMap<String, CollectionFeedback>>,
+    last_save: AtomicU64,
+}
+
+impl FeedbackStore {
+    pub fn open(data_dir: &str) -> Self {
+        Self { dir: Path::new(data_dir).join("feedback"), collections: Mutex::new(HashMap::new()), last_save: AtomicU64::new(now()) }
+    }
+
+    fn path(&self, collection: &str) -> PathBuf {
+        self.dir.join(format!("{collection}.json"))
+    }
+
+    // A collection's feedback, loaded on first use; an unreadable file starts empty
+    fn with<R>(&self, collection: &str, f: impl FnOnce(&mut CollectionFeedback) -> R) -> R {
+        let mut collections = self.collections.lock();
+        let feedback = collections.entry(collection.to_string()).or_insert_with(|| {
+            let path = self.path(collection);
+            match fs::read(&path) {
+                Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                    tracing::warn!(path=%path.display(), error=%e, "feedback_unreadable");
+                    CollectionFeedback::default()
+                }),
+                Err(_) => CollectionFeedback::default(),
+            }
+        });
+        f(feedback)
+    }
+
+    // Record a signal for `document` (a UUID), with the score it was shown with if known
+    pub fn record(&self, collection: &str, document: &str, signal: Signal, score: Option<f32>, config: &RerankConfig) -> FeedbackOutcome {
+        let outcome = self.with(collection, |feedback| {
+            let counts = feedback.documents.entry(document.to_string()).or_default();
+            // The weights learn from the prior the document had when it was shown
+            let prior = counts.prior(config.prior_strength);
+            if signal.is_positive() {
+                counts.positive += 1;
+            } else {
+                counts.negative += 1;
+            }
+            let counts = *counts;
+            if let Some(score) = score {
+                feedback.weights.learn(score, prior, signal.is_positive(), config.learning_rate);
+            }
+            feedback.dirty = true;
+            FeedbackOutcome { document_id: document.to_string(), counts, prior: counts.prior(config.prior_strength), trained: score.is_some() }
+        });
+        let now = now();
+        let last = self.last_save.load(Ordering::Relaxed);
+        if now.saturating_sub(last) >= SAVE_INTERVAL_SECS
+            && self.last_save.compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed).is_ok()
+        {
+            if let Err(e) = self.save() {
+                tracing::warn!(error=%e, "feedback_save_failed");
+            }
+        }
+        outcome
+    }
+
+    // Reorder hits by the blend's probability, best first; ties keep their order
+    pub fn rerank(&self, collection: &str, hits: Vec<HitResponse>, config: &RerankConfig) -> Vec<HitResponse> {
+        let mut hits = self.with(collection, |feedback| {
+            hits.into_iter()
+                .map(|mut hit| {
+                    let prior = feedback.documents.get(&hit.id).map_or(0.0, |c| c.prior(config.prior_strength));
+                    hit.rerank_score = Some(feedback.weights.probability(hit.score, prior));
+                    hit
+                })
+                .collect::<Vec<_>>()
+        });
+        hits.sort_by(|a, b| b.rerank_score.partial_cmp(&a.rerank_score).unwrap_or(std::cmp::Ordering::Equal));
+        hits
+    }
+
+    pub fn stats(&self, collection: &str, config: &RerankConfig) -> FeedbackStats {
+        self.with(collection, |feedback| FeedbackStats {
+            rerank_enabled: config.enabled_for(collection),
+            documents: feedback.documents.len(),
+            positive: feedback.documents.values().map(|c| c.positive).sum(),
+            negative: feedback.documents.values().map(|c| c.negative).sum(),
+            weights: feedback.weights,
+        })
+    }
+
+    // Forget a collection's feedback and learned weights; returns whether it had any
+    pub fn clear(&self, collection: &str) -> bool {
+        let held = self.collections.lock().remove(collection).is_some_and(|f| !f.documents.is_empty());
+        fs::remove_file(self.path(collection)).is_ok() || held
+    }
+
+    pub fn rename(&self, from: &str, to: &str) {
+        let mut collections = self.collections.lock();
+        if let Some(feedback) = collections.remove(from) {
+            collections.insert(to.to_string(), feedback);
+        }
+        fs::rename(self.path(from), self.path(to)).ok();
+    }
+
+    // Write every collection whose feedback changed since it was last saved
+    pub fn save(&self) -> Result<()> {
+        let mut collections = self.collections.lock();
+        for (name, feedback) in collections.iter_mut().filter(|(_, f)| f.dirty) {
+            fs::create_dir_all(&self.dir)?;
+            let path = self.path(name);
+            let tmp = path.with_extension("json.tmp");
+            fs::write(&tmp, serde_json::to_vec(&*feedback)?)?;
+            fs::rename(&tmp, &path)?;
+            feedback.dirty = false;
+        }
+        Ok(())
+    }
+}
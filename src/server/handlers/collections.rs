@@ -1,16 +1,19 @@
-use axum::{extract::{Path, State}, response::Json};
+use axum::{extract::{Path, Query, State}, http::HeaderMap, response::Json};
 use std::sync::atomic::Ordering;
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::time::Instant;
 use crate::error::{Result, ServerError};
 use crate::validation;
-use crate::server::metrics::record_lock_read;
+use crate::jobs::{JobKind, JobOutput};
+use crate::server::metrics::{record_lock_read, record_lock_write};
 use crate::metrics::Metric;
+use crate::storage::collection::ChangeKind;
+use crate::server::helpers::metadata_to_json;
 use super::super::{
-    state::{SharedState, RebuildState, RebuildJobStatus},
+    state::SharedState,
     types::*,
 };
 
-// GET /api/collections - list all loaded collections
+// GET /api/collections - list loaded collections, plus the ones discovered on disk that are not open yet
 pub async fn list_collections(State(state): State<SharedState>) -> Result<Json<CollectionsResponse>> {
     if state.shutting_down.load(Ordering::Relaxed) {
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
@@ -28,8 +31,30 @@ pub async fn list_collections(State(state): State<SharedState>) -> Result<Json<C
             created_at: Some(meta.created_at),
             updated_at: Some(meta.updated_at),
             dimensions: meta.dimensions,
+            loaded: true,
+            warning: storage.index_recovery().map(|r| r.warning()),
+            sealed_at: meta.sealed_at,
+            counters: None,
         });
     }
+    for entry in state.discovered.iter() {
+        if state.collections.contains_key(entry.key()) {
+            continue;
+        }
+        let meta = entry.value();
+        infos.push(CollectionInfo {
+            name: entry.key().clone(),
+            count: meta.vector_count,
+            created_at: Some(meta.created_at),
+            updated_at: Some(meta.updated_at),
+            dimensions: meta.dimensions,
+            loaded: false,
+            warning: None,
+            sealed_at: meta.sealed_at,
+            counters: None,
+        });
+    }
+    infos.sort_by(|a, b| a.name.cmp(&b.name));
     
     Ok(Json(CollectionsResponse { collections: infos }))
 }
@@ -37,6 +62,7 @@ pub async fn list_collections(State(state): State<SharedState>) -> Result<Json<C
 // POST /api/collections - create a new collection
 pub async fn create_collection(
     State(state): State<SharedState>,
+    headers: HeaderMap,
     Json(req): Json<CreateCollectionRequest>,
 ) -> Result<Json<CollectionInfo>> {
     if state.shutting_down.load(Ordering::Relaxed) {
@@ -46,7 +72,19 @@ pub async fn create_collection(
     // Validate collection name
     validation::validate_collection_name(&req.name)?;
 
+    // Joining a project takes one of its keys and room under its max_collections
+    if let Some(name) = &req.project {
+        let project = state.projects.get(name)
+            .ok_or_else(|| ServerError::NotFound(format!("Project '{name}' not found")))?;
+        project.authorize(headers.get("x-api-key").and_then(|v| v.to_str().ok()))?;
+        state.projects.add_collection(name, &req.name)?;
+    }
+
     state.get_or_create_collection(&req.name)?;
+    if req.project.is_some() {
+        // An existing collection that just joined takes the project's search defaults
+        state.apply_project_settings(std::slice::from_ref(&req.name));
+    }
     
     let storage_ref = state.collections.get(&req.name)
         .ok_or_else(|| ServerError::Internal("Collection not found after creation".into()))?;
@@ -61,6 +99,10 @@ pub async fn create_collection(
         created_at: Some(meta.created_at),
         updated_at: Some(meta.updated_at),
         dimensions: meta.dimensions,
+        loaded: true,
+        warning: storage.index_recovery().map(|r| r.warning()),
+        sealed_at: meta.sealed_at,
+        counters: None,
     }))
 }
 
@@ -76,7 +118,7 @@ pub async fn get_collection(
     state.get_or_create_collection(&collection)?;
     
     let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound("Collection not found".into()))?;
+        .ok_or(ServerError::CollectionNotFound)?;
     let lock_start = std::time::Instant::now();
     let storage = storage_ref.read();
     record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
@@ -88,6 +130,10 @@ pub async fn get_collection(
         created_at: Some(meta.created_at),
         updated_at: Some(meta.updated_at),
         dimensions: meta.dimensions,
+        loaded: true,
+        warning: storage.index_recovery().map(|r| r.warning()),
+        sealed_at: meta.sealed_at,
+        counters: Some(meta.counters.clone()),
     }))
 }
 
@@ -99,20 +145,70 @@ pub async fn delete_collection(
     if state.shutting_down.load(Ordering::Relaxed) {
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
     }
+    state.ensure_not_paused(&collection)?;
 
-    let existed = state.collections.remove(&collection).is_some();
+    let loaded = state.collections.remove(&collection).is_some();
+    let existed = state.discovered.remove(&collection).is_some() || loaded;
+    state.replicas.remove(&collection);
+    state.latency_tracker.remove(&collection);
+    state.query_cache.invalidate(&collection);
+    state.projects.forget_collection(&collection)?;
     
     if existed {
-        let path = format!("{}/{}.db", state.data_dir, collection);
+        let path = crate::storage::collection::collection_path(&state.data_dir, &collection);
         std::fs::remove_file(&path).ok();
+        // Without its metadata file the collection is not rediscovered on the next start
+        std::fs::remove_file(format!("{path}.metadata.db")).ok();
+        std::fs::remove_file(state.latency_path(&collection)).ok();
+        state.query_log.clear(&collection);
+        state.feedback.clear(&collection);
+        state.recall_monitor.forget(&collection);
+        // A collection created later under the same name starts from the configured search settings
+        std::fs::remove_file(crate::storage::collection::get_tuning_path(&path)).ok();
+        // ... and from the configured index type
+        std::fs::remove_file(crate::storage::collection::get_index_config_path(&path)).ok();
+        std::fs::remove_file(crate::storage::collection::get_quantization_config_path(&path)).ok();
+        std::fs::remove_file(crate::storage::collection::get_lock_path(&path)).ok();
     }
     
     Ok(Json(DeleteResponse { 
         deleted: existed,
+        seq: None,
         latency_ms: None,  // Collection deletion is a filesystem operation
     }))
 }
 
+// POST /api/collections/:name/rename - move a collection and its files to a new name
+pub async fn rename_collection(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<RenameCollectionRequest>,
+) -> Result<Json<CollectionInfo>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    state.ensure_not_paused(&collection)?;
+
+    state.rename_collection(&collection, &req.name)?;
+    tracing::info!(from=%collection, to=%req.name, "collection_renamed");
+
+    let storage_ref = state.collections.get(&req.name)
+        .ok_or_else(|| ServerError::Internal("Collection not found after rename".into()))?;
+    let storage = storage_ref.read();
+    let meta = storage.metadata();
+    Ok(Json(CollectionInfo {
+        name: req.name,
+        count: storage.count(),
+        created_at: Some(meta.created_at),
+        updated_at: Some(meta.updated_at),
+        dimensions: meta.dimensions,
+        loaded: true,
+        warning: storage.index_recovery().map(|r| r.warning()),
+        sealed_at: meta.sealed_at,
+        counters: None,
+    }))
+}
+
 // GET /api/collections/:name/count - just the count
 pub async fn collection_count(
     State(state): State<SharedState>,
@@ -125,7 +221,7 @@ pub async fn collection_count(
     state.get_or_create_collection(&collection)?;
     
     let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound("Collection not found".into()))?;
+        .ok_or(ServerError::CollectionNotFound)?;
     let lock_start = std::time::Instant::now();
     let storage = storage_ref.read();
     record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
@@ -134,10 +230,11 @@ pub async fn collection_count(
     Ok(Json(CountResponse { count }))
 }
 
-// GET /api/collections/:name/index/stats - get index statistics
+// GET /api/collections/:name/index/stats?diagnostics=true&sample_size=100&k=10 - get index statistics
 pub async fn index_stats(
     State(state): State<SharedState>,
     Path(collection): Path<String>,
+    Query(params): Query<IndexStatsQuery>,
 ) -> Result<Json<IndexStatsResponse>> {
     if state.shutting_down.load(Ordering::Relaxed) {
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
@@ -146,15 +243,21 @@ pub async fn index_stats(
     state.get_or_create_collection(&collection)?;
     
     let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound("Collection not found".into()))?;
+        .ok_or(ServerError::CollectionNotFound)?;
     let lock_start = std::time::Instant::now();
     let storage = storage_ref.read();
     record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
     
-    let stats = storage.vector_index().stats();
+    let stats = if params.diagnostics {
+        let sample_size = params.sample_size.unwrap_or(crate::storage::collection::DEFAULT_TUNING_SAMPLE);
+        storage.index_diagnostics(sample_size, params.k)?
+    } else {
+        storage.vector_index().stats()
+    };
     
     Ok(Json(IndexStatsResponse {
         index_type: stats.index_type.to_string(),
+        metric: stats.metric.name(),
         total_vectors: stats.total_vectors,
         memory_usage_bytes: stats.memory_usage_bytes,
         details: serde_json::to_value(&stats.details).unwrap_or(serde_json::json!({})),
@@ -173,56 +276,80 @@ pub async fn rebuild_index(
 
     state.get_or_create_collection(&collection)?;
     
-    let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound("Collection not found".into()))?;
-
-    let started_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-    state.rebuild_jobs.insert(collection.clone(), RebuildJobStatus {
-        status: RebuildState::Running,
-        started_at,
-        finished_at: None,
-        error: None,
-        elapsed_ms: None,
-    });
-
-    // Run rebuild in background to avoid blocking the request.
-    let collection_name = collection.clone();
-    let storage_ref_clone = storage_ref.clone();
-    let jobs = state.rebuild_jobs.clone();
-
-    tokio::task::spawn_blocking(move || {
-        let mut storage = storage_ref_clone.write();
-        let start = Instant::now();
-        if let Err(e) = storage.rebuild_index() {
-            tracing::error!(collection=%collection_name, error=%e, "index_rebuild_failed");
-            let finished = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-            jobs.insert(collection_name.clone(), RebuildJobStatus {
-                status: RebuildState::Failed,
-                started_at,
-                finished_at: Some(finished),
-                error: Some(e.to_string()),
-                elapsed_ms: Some(start.elapsed().as_millis()),
-            });
-        } else {
-            tracing::info!(
-                collection=%collection_name,
-                elapsed_ms = start.elapsed().as_millis(),
-                "index_rebuild_complete"
-            );
-            let finished = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-            jobs.insert(collection_name.clone(), RebuildJobStatus {
-                status: RebuildState::Completed,
-                started_at,
-                finished_at: Some(finished),
-                error: None,
-                elapsed_ms: Some(start.elapsed().as_millis()),
-            });
-        }
-    });
+    // Queued rather than run here, so the rebuild survives a restart; poll .../index/rebuild/status
+    let job = crate::jobs::submit(&state, &collection, JobKind::RebuildIndex)?;
 
     Ok(Json(RebuildIndexResponse { 
         success: true,
         latency_ms: None,
+        job_id: Some(job.id),
+    }))
+}
+
+// POST /api/collections/:name/index/migrate - switch the collection to another index type online
+pub async fn migrate_index(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<MigrateIndexRequest>,
+) -> Result<Json<RebuildIndexResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+
+    state.get_or_create_collection(&collection)?;
+
+    // Searches and writes go on against the old index while the job builds the new one; poll GET /api/jobs/{id}
+    let job = crate::jobs::submit(&state, &collection, JobKind::MigrateIndex { index: req.index })?;
+
+    Ok(Json(RebuildIndexResponse {
+        success: true,
+        latency_ms: None,
+        job_id: Some(job.id),
+    }))
+}
+
+// GET /api/collections/:name/quantization - stored encodings, and how many documents a requantize still has to rewrite
+pub async fn quantization_composition(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<crate::storage::collection::QuantizationComposition>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+
+    state.get_or_create_collection(&collection)?;
+
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let lock_start = std::time::Instant::now();
+    let storage = storage_ref.read();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+
+    Ok(Json(storage.quantization_composition()))
+}
+
+// POST /api/collections/:name/quantization - switch the quantization level and re-encode the stored vectors
+pub async fn requantize_collection(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<RequantizeRequest>,
+) -> Result<Json<RebuildIndexResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    if req.batch_size == 0 {
+        return Err(ServerError::InvalidRequest("batch_size must be >= 1".into()).into());
+    }
+
+    state.get_or_create_collection(&collection)?;
+
+    // Searches go on against the unchanged index while the job rewrites the documents; poll GET /api/jobs/{id}
+    let job = crate::jobs::submit(&state, &collection, JobKind::Requantize { level: req.level, batch_size: req.batch_size })?;
+
+    Ok(Json(RebuildIndexResponse {
+        success: true,
+        latency_ms: None,
+        job_id: Some(job.id),
     }))
 }
 
@@ -239,7 +366,7 @@ pub async fn find_duplicates(
     state.get_or_create_collection(&collection)?;
 
     let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound("Collection not found".into()))?;
+        .ok_or(ServerError::CollectionNotFound)?;
     let lock_start = std::time::Instant::now();
     let storage = storage_ref.read();
     record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
@@ -279,27 +406,208 @@ pub async fn compact_collection(
 
     state.get_or_create_collection(&collection)?;
     
-    let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound("Collection not found".into()))?;
-    
-    let mut storage = storage_ref.write();
-    let start = Instant::now();
-    let stats = crate::storage::collection::compact(&mut storage)?;
-    let duration = start.elapsed();
+    let finished = crate::jobs::run_and_wait(&state, &collection, JobKind::Compact).await?;
+    let stats = match finished.output {
+        JobOutput::Compacted(stats) => stats,
+        other => return Err(ServerError::Internal(format!("Unexpected compact output: {:?}", other)).into()),
+    };
     tracing::info!(
         collection=%collection,
         original=stats.original_entries,
         compacted=stats.compacted_entries,
-        elapsed_ms=duration.as_millis(),
+        elapsed_ms=finished.elapsed.as_millis(),
         "collection_compacted"
     );
 
     Ok(Json(RebuildIndexResponse {
         success: true,
-        latency_ms: Some(duration.as_millis() as f32),
+        latency_ms: Some(finished.elapsed.as_millis() as f32),
+        job_id: None,
     }))
 }
 
+// POST /api/collections/:collection/seal - make the collection write-once: compacted, without a WAL,
+// and rejecting every write from then on. Idempotent; sealing again only reports.
+pub async fn seal_collection(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    body: Option<Json<SealRequest>>,
+) -> Result<Json<SealResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    state.ensure_write_allowed()?;
+    validation::validate_collection_name(&collection)?;
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+
+    state.get_or_create_collection(&collection)?;
+    let handle = state.collections.get(&collection)
+        .map(|c| c.value().clone())
+        .ok_or(ServerError::CollectionNotFound)?;
+    let start = Instant::now();
+    let shared = state.clone();
+    let name = collection.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        let lock_start = Instant::now();
+        let mut storage = handle.write();
+        record_lock_write(shared.latency_tracker.get(&name).as_deref(), lock_start);
+        storage.seal(req.index)
+    })
+    .await
+    .map_err(|e| ServerError::Internal(e.to_string()))??;
+    Ok(Json(SealResponse { report, latency_ms: start.elapsed().as_millis() as f32 }))
+}
+
+// POST /api/collections/:collection/index/import - import vectors plus a pre-built index
+pub async fn import_index(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<ImportIndexRequest>,
+) -> Result<Json<ImportIndexResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    state.ensure_write_allowed()?;
+    validation::validate_collection_name(&collection)?;
+
+    state.get_or_create_collection(&collection)?;
+
+    // Run through the job queue, which also re-takes replicas and enforces the cache budget afterwards
+    let finished = crate::jobs::run_and_wait(&state, &collection, JobKind::Import { path: req.path }).await?;
+    let report = match finished.output {
+        JobOutput::Imported(report) => report,
+        other => return Err(ServerError::Internal(format!("Unexpected import output: {:?}", other)).into()),
+    };
+
+    Ok(Json(ImportIndexResponse {
+        index_type: report.index_type.to_string(),
+        imported: report.imported,
+        id_map: report.id_map.into_iter().map(|(ext, id)| (ext, id.to_string())).collect(),
+        latency_ms: Some(finished.elapsed.as_millis() as f32),
+    }))
+}
+
+// POST /api/collections/:collection/replicas - set the number of in-memory read replicas
+pub async fn set_replicas(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<SetReplicasRequest>,
+) -> Result<Json<ReplicasResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+
+    state.get_or_create_collection(&collection)?;
+    let set = state.set_replicas(&collection, req.replicas)?;
+    tracing::info!(collection=%collection, replicas=req.replicas, "replicas_updated");
+
+    Ok(Json(replicas_response(set.as_deref())))
+}
+
+// GET /api/collections/:collection/replicas - replica count and replication lag
+pub async fn replicas_status(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<ReplicasResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+
+    let set = state.replicas_for(&collection);
+    Ok(Json(replicas_response(set.as_deref())))
+}
+
+fn replicas_response(set: Option<&crate::storage::collection::ReplicaSet>) -> ReplicasResponse {
+    match set {
+        Some(set) => ReplicasResponse {
+            replicas: set.len(),
+            pending_writes: set.pending(),
+            applied_seq: set.applied_seqs(),
+        },
+        None => ReplicasResponse {
+            replicas: 0,
+            pending_writes: 0,
+            applied_seq: Vec::new(),
+        },
+    }
+}
+
+// GET /api/collections/:collection/history - how far back point-in-time reads can go
+pub async fn history_status(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<HistoryResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+
+    state.get_or_create_collection(&collection)?;
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let lock_start = Instant::now();
+    let storage = storage_ref.read();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+
+    let summary = storage.history_summary();
+    Ok(Json(HistoryResponse {
+        enabled: summary.is_some(),
+        head_seq: storage.head_seq(),
+        oldest_seq: summary.as_ref().map(|s| s.base_seq),
+        oldest_timestamp: summary.as_ref().map(|s| s.base_timestamp),
+        segments: summary.as_ref().map(|s| s.segments).unwrap_or(0),
+        retention_secs: summary.map(|s| s.retention_secs),
+    }))
+}
+
+// GET /api/collections/:collection/changes?since_seq=N - committed writes after a WAL sequence number, in order
+pub async fn list_changes(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Query(params): Query<ChangesQuery>,
+) -> Result<Json<ChangesResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+
+    state.get_or_create_collection(&collection)?;
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let lock_start = Instant::now();
+    let storage = storage_ref.read();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+    let batch = storage.changes(params.since_seq, params.limit)?;
+    drop(storage);
+
+    let changes = batch.changes.into_iter().map(|change| {
+        let op = match change.kind {
+            ChangeKind::Insert => ChangeOp::Insert,
+            ChangeKind::Update => ChangeOp::Update,
+            ChangeKind::Delete => ChangeOp::Delete,
+        };
+        let mut record = ChangeRecord {
+            seq: change.seq,
+            op,
+            id: change.id.to_string(),
+            external_id: None,
+            text: None,
+            vector: None,
+            metadata: None,
+        };
+        if let Some((text, vector, metadata)) = change.document {
+            record.external_id = crate::storage::external_id_of(&metadata).map(str::to_string);
+            record.text = Some(text);
+            record.vector = params.include_vectors.then_some(vector);
+            record.metadata = Some(metadata_to_json(&metadata));
+        }
+        record
+    }).collect();
+    Ok(Json(ChangesResponse { changes, next_seq: batch.next_seq, oldest_seq: batch.oldest_seq, head_seq: batch.head_seq }))
+}
+
 // GET /api/collections/:name/index/rebuild/status - check rebuild status
 pub async fn rebuild_index_status(
     State(state): State<SharedState>,
@@ -309,18 +617,14 @@ pub async fn rebuild_index_status(
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
     }
 
-    if let Some(job) = state.rebuild_jobs.get(&collection) {
-        let status_str = match job.status {
-            RebuildState::Running => "running",
-            RebuildState::Completed => "completed",
-            RebuildState::Failed => "failed",
-        };
+    if let Some(job) = state.jobs.latest(&collection, JobKind::RebuildIndex.name()) {
         Ok(Json(RebuildIndexStatusResponse {
-            status: status_str.to_string(),
-            started_at: Some(job.started_at),
+            status: job.state.name().to_string(),
+            started_at: job.started_at,
             finished_at: job.finished_at,
             elapsed_ms: job.elapsed_ms.map(|ms| ms as f32),
-            error: job.error.clone(),
+            error: job.error,
+            job_id: Some(job.id),
         }))
     } else {
         Err(ServerError::NotFound("No rebuild job found for this collection".into()).into())
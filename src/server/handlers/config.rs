@@ -20,16 +20,17 @@ pub async fn config_status(State(state): State<SharedState>) -> Result<Json<Conf
     }))
 }
 
-// POST /api/config/reload - hot reload limited subset (currently full AppConfig)
+// POST /api/config/reload - hot reload the AppConfig, applying collection settings where safe
 pub async fn reload_config(State(state): State<SharedState>) -> Result<Json<ConfigReloadResponse>> {
     if state.shutting_down.load(Ordering::Relaxed) {
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
     }
-    let cfg = state.reload_config()?;
+    let (cfg, collections) = state.reload_config()?;
     let ts = state.config_last_reload.load(Ordering::Relaxed);
     Ok(Json(ConfigReloadResponse {
         success: true,
         reloaded_at: Some(ts),
         app_config: cfg,
+        collections,
     }))
 }
@@ -1,10 +1,14 @@
-use axum::{extract::{Path, State, Extension}, Json};
+use axum::{extract::{Path, State, Extension}, http::HeaderMap, Json};
 use std::collections::HashMap;
 use std::sync::atomic::Ordering;
 use std::time::Instant;
-use crate::{Metric, Document};
+use crate::Document;
 use crate::error::{Result, ServerError};
 use crate::server::metrics::{record_lock_read, record_lock_write};
+use crate::server::slow_queries::{SlowQuery, SlowQueryKind, SlowQueryLatency};
+use crate::server::query_log::{LoggedHit, LoggedQuery, QueryKind, QueryOptions};
+use crate::server::usage::UsageScope;
+use crate::storage::collection::SearchGuard;
 use super::super::{
     state::SharedState,
     types::*,
@@ -14,40 +18,53 @@ use tracing::info;
 
 // Embedding endpoints: embed text then reuse storage/search flows
 
-// Parse similarity metric from string
-fn parse_metric(s: Option<String>) -> Metric {
-    match s.as_deref() {
-        Some("euclidean") => Metric::Euclidean,
-        Some("dot") | Some("dot_product") => Metric::DotProduct,
-        _ => Metric::Cosine,
+// Reject an embedding model (or output size) that differs from the one the collection was first embedded with
+pub(crate) fn ensure_embedding_model(state: &SharedState, collection: &str, model: &str, dimensions: Option<usize>) -> Result<()> {
+    if let Some(storage) = state.collections.get(collection) {
+        storage.read().check_embedding_model(model, dimensions)?;
     }
+    Ok(())
 }
 
 // POST /api/collections/:collection/embed - embed text and store
 pub async fn embed_text(
     State(state): State<SharedState>,
     Path(collection): Path<String>,
+    headers: HeaderMap,
     Json(req): Json<EmbedRequest>,
 ) -> Result<Json<EmbedResultsResponse>> {
     if state.shutting_down.load(Ordering::Relaxed) {
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
     }
     state.ensure_write_allowed()?;
+    state.throttle_writes(&collection).await?;
+    let max_document_bytes = state.app_config.read().limits.max_document_bytes;
+    for text in req.text.iter().chain(req.texts.iter().flatten()) {
+        crate::validation::check_document_size(text, max_document_bytes)?;
+    }
+    // Before anything is embedded
+    crate::validation::validate_metadata_keys(req.metadata.keys().chain(req.metadata_list.iter().flat_map(HashMap::keys)))?;
 
     state.get_or_create_collection(&collection)?;
 
-    let embedder = state.embedder.as_ref()
+    let embedder = state.embedder_for(&collection)
         .ok_or(ServerError::ServiceUnavailable(super::super::helpers::EMBEDDING_NOT_CONFIGURED.to_string()))?;
+    let check_model = !req.allow_model_mismatch;
+    if check_model {
+        ensure_embedding_model(&state, &collection, embedder.model_name(), None)?;
+    }
+    let usage = UsageScope::new(&*embedder, &collection, headers.get("x-api-key").and_then(|v| v.to_str().ok()));
+    state.check_usage(&usage)?;
 
     let response = match (req.text.clone(), req.texts.clone()) {
         (Some(text), None) => {
             info!(collection=%collection, "embed_single_request");
             let start = Instant::now();
             let response = embedder.embed(&text).await?;
-            let embed_duration = start.elapsed();
+            state.record_embedding(&usage, 1, response.tokens.unwrap_or(0) as u64, start.elapsed());
 
             let storage_ref = state.collections.get(&collection)
-                .ok_or_else(|| ServerError::NotFound(super::super::helpers::COLLECTION_NOT_FOUND.to_string()))?;
+                .ok_or(ServerError::CollectionNotFound)?;
             let lock_start = Instant::now();
             let mut storage = storage_ref.write();
             record_lock_write(state.latency_tracker.get(&collection).as_deref(), lock_start);
@@ -59,14 +76,18 @@ pub async fn embed_text(
                 metadata,
             );
 
+            if check_model {
+                storage.check_embedding_model(embedder.model_name(), Some(response.embedding.len()))?;
+            }
             let id = storage.insert(entry)?;
+            storage.record_embedding_model(embedder.model_name(), response.embedding.len())?;
             state.enforce_cache_budget();
-            state.embed_metrics.record(1, 1, response.tokens.unwrap_or(0) as u64, embed_duration);
 
             EmbedResultsResponse::Single(EmbedResponse {
                 id: id.to_string(),
                 embedding: response.embedding,
                 tokens: response.tokens,
+                seq: storage.head_seq(),
             })
         }
         (None, Some(texts)) => {
@@ -80,34 +101,37 @@ pub async fn embed_text(
             let mut total_tokens: u32 = 0;
             let mut entries = Vec::with_capacity(texts.len());
             let start = Instant::now();
-            for (idx, t) in texts.iter().enumerate() {
-                let resp = embedder.embed(t).await?;
+            let responses = embedder.embed_batch(&texts).await?;
+            let tokens = responses.iter().filter_map(|r| r.tokens).map(u64::from).sum();
+            state.record_embedding(&usage, texts.len() as u64, tokens, start.elapsed());
+            for (idx, (t, resp)) in texts.iter().zip(responses).enumerate() {
                 embeddings.push(resp.embedding.clone());
                 if let Some(tokens) = resp.tokens {
                     total_tokens = total_tokens.saturating_add(tokens);
                 }
-                let md = if idx < req.metadata_list.len() {
-                    json_to_metadata(req.metadata_list[idx].clone())
-                } else {
-                    json_to_metadata(HashMap::new())
-                };
+                let md = json_to_metadata(req.metadata_list.get(idx).cloned().unwrap_or_default());
                 entries.push(Document::with_metadata(resp.embedding, t.clone(), md));
             }
 
             let storage_ref = state.collections.get(&collection)
-                .ok_or_else(|| ServerError::NotFound(super::super::helpers::COLLECTION_NOT_FOUND.to_string()))?;
+                .ok_or(ServerError::CollectionNotFound)?;
             let lock_start = Instant::now();
             let mut storage = storage_ref.write();
             record_lock_write(state.latency_tracker.get(&collection).as_deref(), lock_start);
 
+            let dimensions = embeddings[0].len();
+            if check_model {
+                storage.check_embedding_model(embedder.model_name(), Some(dimensions))?;
+            }
             let insert_ids = storage.insert_batch(entries)?;
+            storage.record_embedding_model(embedder.model_name(), dimensions)?;
             ids.extend(insert_ids.into_iter().map(|id| id.to_string()));
             state.enforce_cache_budget();
-            state.embed_metrics.record(1, ids.len() as u64, total_tokens as u64, start.elapsed());
 
             EmbedResultsResponse::Multi(MultiEmbedResponse {
                 ids,
                 embeddings,
+                seq: storage.head_seq(),
                 total_tokens: if total_tokens > 0 { Some(total_tokens) } else { None },
             })
         }
@@ -118,67 +142,169 @@ pub async fn embed_text(
     Ok(Json(response))
 }
 
-// POST /api/collections/:collection/search/text - search by text query
+// POST /api/collections/:collection/search/text - search by text query, or by a batch of them
 pub async fn search_by_text(
     State(state): State<SharedState>,
     Path(collection): Path<String>,
     Extension(request_id): Extension<crate::server::request_id::RequestId>,
-    Json(req): Json<TextSearchRequest>,
-) -> Result<Json<SearchResponse>> {
+    headers: HeaderMap,
+    Json(mut req): Json<TextSearchRequest>,
+) -> Result<Json<SearchResultsResponse>> {
     if state.shutting_down.load(Ordering::Relaxed) {
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
     }
+    let query = match (req.query.take(), req.queries.take()) {
+        (Some(query), None) => query,
+        (None, Some(queries)) => {
+            return search_by_texts(state, collection, request_id, headers, req, queries).await
+                .map(|response| Json(SearchResultsResponse::Multi(response)));
+        }
+        (Some(_), Some(_)) => return Err(ServerError::InvalidRequest("Provide either query or queries, not both".to_string()).into()),
+        (None, None) => return Err(ServerError::InvalidRequest("No query provided".to_string()).into()),
+    };
 
     state.get_or_create_collection(&collection)?;
+    // Parsed before the query is embedded, so a bad expression costs no embedding call
+    let score_expr = req.score_expr.as_deref().map(crate::search::ScoreExpr::parse).transpose()?;
+    if let Some(order) = &req.order_by {
+        order.validate()?;
+    }
+    if let Some(bands) = &req.score_bands {
+        bands.validate()?;
+    }
 
-    let embedder = state.embedder.as_ref()
+    let embedder = state.embedder_for(&collection)
         .ok_or(ServerError::ServiceUnavailable(super::super::helpers::EMBEDDING_NOT_CONFIGURED.to_string()))?;
 
     info!(collection=%collection, "search_by_text_request");
+    let usage = UsageScope::new(&*embedder, &collection, headers.get("x-api-key").and_then(|v| v.to_str().ok()));
+    state.check_usage(&usage)?;
     let start = Instant::now();
-    let response = embedder.embed(&req.query).await?;
+    let response = embedder.embed(&query).await?;
     let embed_duration = start.elapsed();
-    state.embed_metrics.record(1, 1, response.tokens.unwrap_or(0) as u64, embed_duration);
+    state.record_embedding(&usage, 1, response.tokens.unwrap_or(0) as u64, embed_duration);
+    if !req.allow_model_mismatch {
+        ensure_embedding_model(&state, &collection, embedder.model_name(), Some(response.embedding.len()))?;
+    }
 
-    let metric = parse_metric(req.metric);
+    state.wait_for_seq(&collection, req.min_seq).await?;
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    // Taken before the exclusions consume the request's filter
+    let logged = state.query_log.enabled().then(|| QueryOptions::of_text_search(&req));
+    let (excluded, exclude_filter) = crate::server::handlers::vectors::resolve_exclusions(&storage_ref.read(), &req.exclude_ids, req.exclude_filter.take())?;
+    let replicas = state.replicas_for(&collection);
+    let lock_start = Instant::now();
+    // Hot collections are searched on one of their read replicas instead of under the collection lock
+    let storage = SearchGuard::acquire(&storage_ref, replicas.as_deref());
+    let lock_wait = lock_start.elapsed();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+    // Also covers allow_model_mismatch with a model of another size
+    crate::validation::check_query_dimensions(&response.embedding, storage.dimensions())?;
+    let metric = crate::server::handlers::vectors::resolve_metric(req.metric, storage.vector_index().metric(), req.allow_metric_mismatch)?;
     let effective_search = crate::server::handlers::vectors::apply_search_overrides(
-        state.collections.get(&collection)
-            .map(|c| c.read().config().search)
-            .unwrap_or_default(),
+        storage.config().search,
         req.ef,
         req.nprobe,
         req.overfetch,
         req.preset.clone(),
     );
 
-    let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound(super::super::helpers::COLLECTION_NOT_FOUND.to_string()))?;
-    let lock_start = Instant::now();
-    let storage = storage_ref.read();
-    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
-
     let start = Instant::now();
-    let results: Vec<HitResponse> = storage
-        .search(
-            &response.embedding,
-            req.k,
-            metric,
-            crate::SearchParams {
+    let cache_key = (state.query_cache.enabled() && exclude_filter.is_none()).then(|| {
+        let mut excluded: Vec<&uuid::Uuid> = excluded.iter().collect();
+        excluded.sort_unstable();
+        let options = (metric, effective_search, storage.config().execution, req.dedup_by.as_deref(), score_expr.as_ref().map(|e| e.source()), req.order_by.as_ref(), excluded);
+        (state.query_cache.key(&collection, &response.embedding, req.k, options), storage.seq())
+    });
+    if let Some((key, cached)) = cache_key.as_ref().and_then(|(key, seq)| Some((key, state.query_cache.lookup(key, *seq)?))) {
+        // Results a few writes behind are served stale while one search recomputes them
+        if cached.revalidate {
+            crate::server::search_deadline::DeadlineSearch {
+                collection: storage_ref.value().clone(),
+                replicas: replicas.clone(),
+                query: response.embedding.clone(),
+                k: req.k,
+                metric,
                 mode: storage.config().execution,
+                search: effective_search,
                 filter: None,
-                filter_overfetch_override: req.overfetch,
-                search_config_override: Some(effective_search),
-            },
-        )
-        .into_iter()
-        .map(|r| HitResponse {
-            id: r.id.to_string(),
-            score: r.score,
-            text: r.text,
-            metadata: metadata_to_json(&r.metadata),
-        })
-        .collect();
+                overfetch: req.overfetch,
+                dedup_by: req.dedup_by.clone(),
+                score_expr: score_expr.clone(),
+                order_by: req.order_by.clone(),
+                exclude_ids: excluded.clone(),
+            }
+            .revalidate(&state, key.clone());
+        }
+        let results = crate::server::handlers::vectors::rerank_hits(&state, &collection, req.order_by.as_ref(), cached.results);
+        let (results, bands) = crate::server::handlers::vectors::band_hits(req.score_bands.as_ref(), results);
+        let query_id = logged.and_then(|options| state.query_log.record(&collection, LoggedQuery {
+            kind: QueryKind::TextSearch,
+            model: Some(embedder.model_name().to_string()),
+            vector: Some(response.embedding.clone()),
+            text: Some(query.clone()),
+            k: req.k,
+            metric,
+            options,
+            results: LoggedHit::of(&results),
+            ..Default::default()
+        }));
+        return Ok(Json(SearchResultsResponse::Single(SearchResponse {
+            results,
+            latency_ms: Some(start.elapsed().as_millis() as f32),
+            effective: None,
+            explain: None,
+            bands,
+            query_id,
+            partial: false,
+            stale: cached.lag > 0,
+        })));
+    }
+    let results: Vec<HitResponse> = crate::search::search_target(
+        &*storage,
+        &response.embedding,
+        req.k,
+        metric,
+        crate::SearchParams {
+            mode: storage.config().execution,
+            filter: exclude_filter.as_ref(),
+            filter_overfetch_override: req.overfetch,
+            search_config_override: Some(effective_search),
+            dedup_by: req.dedup_by.as_deref(),
+            score_expr: score_expr.as_ref(),
+            order_by: req.order_by.as_ref(),
+            exclude_ids: Some(&excluded),
+        },
+    )
+    .into_iter()
+    .map(|r| HitResponse {
+        id: r.id.to_string(),
+        external_id: crate::storage::external_id_of(&r.metadata).map(str::to_string),
+        score: r.score,
+        text: r.text,
+        metadata: metadata_to_json(&r.metadata),
+        band: None,
+        rerank_score: None,
+    })
+    .collect();
+    if let Some((key, seq)) = cache_key {
+        state.query_cache.insert(key, seq, &results);
+    }
+    let results = crate::server::handlers::vectors::rerank_hits(&state, &collection, req.order_by.as_ref(), results);
+    let (results, bands) = crate::server::handlers::vectors::band_hits(req.score_bands.as_ref(), results);
     let duration = start.elapsed();
+    let query_id = logged.and_then(|options| state.query_log.record(&collection, LoggedQuery {
+        kind: QueryKind::TextSearch,
+        model: Some(embedder.model_name().to_string()),
+        vector: Some(response.embedding.clone()),
+        text: Some(query.clone()),
+        k: req.k,
+        metric,
+        options,
+        results: LoggedHit::of(&results),
+        ..Default::default()
+    }));
     if duration.as_millis() > state.slow_query_ms {
         tracing::warn!(
             collection=%collection,
@@ -186,6 +312,21 @@ pub async fn search_by_text(
             elapsed_ms = duration.as_millis(),
             "slow_text_search"
         );
+        state.slow_queries.record(SlowQuery {
+            collection: collection.clone(),
+            request_id: request_id.0.clone(),
+            kind: SlowQueryKind::TextSearch,
+            vectors: vec![response.embedding.clone()],
+            text: Some(query.clone()),
+            k: req.k,
+            metric,
+            search: effective_search,
+            dedup_by: req.dedup_by.clone(),
+            score_expr: score_expr.as_ref().map(|e| e.source().to_string()),
+            order_by: req.order_by.clone(),
+            latency: SlowQueryLatency::new(Some(embed_duration), lock_wait, duration),
+            ..Default::default()
+        });
     }
     
     // Record latency
@@ -193,8 +334,159 @@ pub async fn search_by_text(
         tracker.record_search(duration);
     }
 
-    Ok(Json(SearchResponse { 
+    Ok(Json(SearchResultsResponse::Single(SearchResponse {
+        results,
+        latency_ms: Some(duration.as_millis() as f32),
+        effective: None,
+        explain: None,
+        bands,
+        query_id,
+        partial: false,
+        stale: false,
+    })))
+}
+
+// Batch text search: every query is embedded in one call (split into provider-sized chunks by the
+// embedder), then searched together like a batch vector search, in parallel with
+// `parallelism.parallel_search`. Results, bands and query ids line up with `queries`. The query cache
+// is not used, as for batch vector searches.
+async fn search_by_texts(
+    state: SharedState,
+    collection: String,
+    request_id: crate::server::request_id::RequestId,
+    headers: HeaderMap,
+    mut req: TextSearchRequest,
+    queries: Vec<String>,
+) -> Result<MultiSearchResponse> {
+    crate::validation::validate_batch_size(queries.len(), crate::server::handlers::vectors::MAX_BATCH_SIZE, "Text search")?;
+    state.get_or_create_collection(&collection)?;
+    let score_expr = req.score_expr.as_deref().map(crate::search::ScoreExpr::parse).transpose()?;
+    if let Some(order) = &req.order_by {
+        order.validate()?;
+    }
+    if let Some(bands) = &req.score_bands {
+        bands.validate()?;
+    }
+
+    let embedder = state.embedder_for(&collection)
+        .ok_or(ServerError::ServiceUnavailable(super::super::helpers::EMBEDDING_NOT_CONFIGURED.to_string()))?;
+
+    info!(collection=%collection, batch=queries.len(), "search_by_text_batch_request");
+    let usage = UsageScope::new(&*embedder, &collection, headers.get("x-api-key").and_then(|v| v.to_str().ok()));
+    state.check_usage(&usage)?;
+    let start = Instant::now();
+    let responses = embedder.embed_batch(&queries).await?;
+    let embed_duration = start.elapsed();
+    let tokens = responses.iter().filter_map(|r| r.tokens).map(u64::from).sum();
+    state.record_embedding(&usage, queries.len() as u64, tokens, embed_duration);
+    let embeddings: Vec<Vec<f32>> = responses.into_iter().map(|r| r.embedding).collect();
+    if embeddings.len() != queries.len() {
+        return Err(ServerError::Internal(format!("{} embeddings returned for {} queries", embeddings.len(), queries.len())).into());
+    }
+    if !req.allow_model_mismatch {
+        ensure_embedding_model(&state, &collection, embedder.model_name(), Some(embeddings[0].len()))?;
+    }
+
+    state.wait_for_seq(&collection, req.min_seq).await?;
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let logged = state.query_log.enabled().then(|| QueryOptions::of_text_search(&req));
+    let (excluded, exclude_filter) = crate::server::handlers::vectors::resolve_exclusions(&storage_ref.read(), &req.exclude_ids, req.exclude_filter.take())?;
+    let replicas = state.replicas_for(&collection);
+    let lock_start = Instant::now();
+    let storage = SearchGuard::acquire(&storage_ref, replicas.as_deref());
+    let lock_wait = lock_start.elapsed();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+    for embedding in &embeddings {
+        crate::validation::check_query_dimensions(embedding, storage.dimensions())?;
+    }
+    let metric = crate::server::handlers::vectors::resolve_metric(req.metric, storage.vector_index().metric(), req.allow_metric_mismatch)?;
+    let effective_search = crate::server::handlers::vectors::apply_search_overrides(
+        storage.config().search,
+        req.ef,
+        req.nprobe,
+        req.overfetch,
+        req.preset.clone(),
+    );
+
+    let start = Instant::now();
+    let batch_results = crate::search::search_batch_target(
+        &*storage,
+        &embeddings,
+        req.k,
+        metric,
+        crate::SearchParams {
+            mode: storage.config().execution,
+            filter: exclude_filter.as_ref(),
+            filter_overfetch_override: req.overfetch,
+            search_config_override: Some(effective_search),
+            dedup_by: req.dedup_by.as_deref(),
+            score_expr: score_expr.as_ref(),
+            order_by: req.order_by.as_ref(),
+            exclude_ids: Some(&excluded),
+        },
+    );
+    let duration = start.elapsed();
+    drop(storage);
+    if duration.as_millis() > state.slow_query_ms {
+        tracing::warn!(
+            collection=%collection,
+            request_id = request_id.0.as_str(),
+            elapsed_ms = duration.as_millis(),
+            "slow_text_batch_search"
+        );
+        state.slow_queries.record(SlowQuery {
+            collection: collection.clone(),
+            request_id: request_id.0.clone(),
+            kind: SlowQueryKind::BatchTextSearch,
+            vectors: embeddings.clone(),
+            k: req.k,
+            metric,
+            search: effective_search,
+            dedup_by: req.dedup_by.clone(),
+            score_expr: score_expr.as_ref().map(|e| e.source().to_string()),
+            order_by: req.order_by.clone(),
+            latency: SlowQueryLatency::new(Some(embed_duration), lock_wait, duration),
+            ..Default::default()
+        });
+    }
+    if let Some(tracker) = state.latency_tracker.get(&collection) {
+        tracker.record_search(duration);
+    }
+
+    let (results, bands): (Vec<_>, Vec<_>) = batch_results
+        .into_iter()
+        .map(|hits| {
+            let hits = hits.into_iter().map(|r| HitResponse {
+                id: r.id.to_string(),
+                external_id: crate::storage::external_id_of(&r.metadata).map(str::to_string),
+                score: r.score,
+                text: r.text,
+                metadata: metadata_to_json(&r.metadata),
+                band: None,
+                rerank_score: None,
+            }).collect();
+            let hits = crate::server::handlers::vectors::rerank_hits(&state, &collection, req.order_by.as_ref(), hits);
+            crate::server::handlers::vectors::band_hits(req.score_bands.as_ref(), hits)
+        })
+        .unzip();
+    let query_ids = logged.and_then(|options| {
+        queries.iter().zip(&embeddings).zip(&results).map(|((query, embedding), hits)| state.query_log.record(&collection, LoggedQuery {
+            kind: QueryKind::BatchTextSearch,
+            model: Some(embedder.model_name().to_string()),
+            vector: Some(embedding.clone()),
+            text: Some(query.clone()),
+            k: req.k,
+            metric,
+            options: options.clone(),
+            results: LoggedHit::of(hits),
+            ..Default::default()
+        })).collect()
+    });
+    Ok(MultiSearchResponse {
         results,
         latency_ms: Some(duration.as_millis() as f32),
-    }))
+        bands: bands.into_iter().collect(),
+        query_ids,
+    })
 }
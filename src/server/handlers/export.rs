@@ -0,0 +1,57 @@
+use axum::{extract::{Path, State}, http::header, response::{IntoResponse, Json, Response}};
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use crate::error::{Result, ServerError};
+use crate::server::helpers::json_to_filter;
+use crate::server::metrics::record_lock_read;
+use crate::validation;
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+// POST /api/collections/:collection/export - the documents as a Parquet or Arrow IPC file
+pub async fn export_collection(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    body: Option<Json<ExportRequest>>,
+) -> Result<Response> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+
+    // Equality on every listed field, or any/all of an array field's elements
+    let filter = req.filter.map(json_to_filter).transpose()?;
+
+    state.get_or_create_collection(&collection)?;
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let start = Instant::now();
+    let lock_start = Instant::now();
+    let storage = storage_ref.read();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+
+    let mut bytes = Vec::new();
+    let report = storage.export(&mut bytes, req.format, filter.as_ref())?;
+    drop(storage);
+    tracing::info!(
+        collection=%collection,
+        format=req.format.extension(),
+        rows=report.rows,
+        bytes=bytes.len(),
+        elapsed_ms=start.elapsed().as_millis(),
+        "collection_exported"
+    );
+
+    let disposition = format!("attachment; filename=\"{}.{}\"", collection, req.format.extension());
+    Ok((
+        [
+            (header::CONTENT_TYPE, req.format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::HeaderName::from_static("x-export-rows"), report.rows.to_string()),
+        ],
+        bytes,
+    ).into_response())
+}
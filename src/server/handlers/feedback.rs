@@ -0,0 +1,57 @@
+use axum::{extract::{Path, State}, response::Json};
+use crate::error::{Result, ServerError};
+use crate::server::feedback::{FeedbackOutcome, FeedbackStats};
+use crate::validation;
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+// POST /api/collections/:collection/feedback - record a relevance signal for a document
+pub async fn record_feedback(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<FeedbackRequest>,
+) -> Result<Json<FeedbackOutcome>> {
+    validation::validate_collection_name(&collection)?;
+    if req.score.is_some_and(|score| !score.is_finite()) {
+        return Err(ServerError::InvalidRequest("score must be a finite number".into()).into());
+    }
+    state.get_or_create_collection(&collection)?;
+    let document = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?
+        .read()
+        .resolve_id(&req.document_id)
+        .ok_or(ServerError::VectorNotFound)?;
+
+    // A click on a logged search is also recorded in the query log, for offline evaluation
+    if let Some(query_id) = &req.query_id {
+        if req.signal.is_positive() && state.query_log.enabled() {
+            state.query_log.feedback(&collection, query_id, vec![req.document_id.clone()])?;
+        }
+    }
+    let config = state.app_config.read().rerank.clone();
+    let outcome = state.feedback.record(&collection, &document.to_string(), req.signal, req.score, &config);
+    Ok(Json(outcome))
+}
+
+// GET /api/collections/:collection/feedback - signals recorded and the re-ranker's weights
+pub async fn feedback_stats(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<FeedbackStats>> {
+    validation::validate_collection_name(&collection)?;
+    let config = state.app_config.read().rerank.clone();
+    Ok(Json(state.feedback.stats(&collection, &config)))
+}
+
+// DELETE /api/collections/:collection/feedback - forget the signals and learned weights
+pub async fn clear_feedback(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<ClearFeedbackResponse>> {
+    validation::validate_collection_name(&collection)?;
+    let cleared = state.feedback.clear(&collection);
+    tracing::info!(collection = %collection, cleared, "feedback_cleared");
+    Ok(Json(ClearFeedbackResponse { cleared }))
+}
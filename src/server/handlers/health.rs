@@ -1,9 +1,10 @@
-use axum::{http::StatusCode, response::Json};
-use super::super::{state::SharedState, types::{HealthResponse, MetricsResponse, CollectionMetrics, EmbeddingMetricsResponse}};
-use axum::extract::State;
+use axum::{http::{header, StatusCode}, response::{IntoResponse, Json}};
+use super::super::{state::SharedState, types::{HealthResponse, MetricsResponse, CollectionMetrics, EmbeddingMetricsResponse, LatencyResetQuery, LatencyResetResponse}};
+use axum::extract::{Query, State};
 use crate::error::Result;
 use crate::server::types::WalStats;
 use crate::server::metrics::record_lock_read;
+use crate::metrics::LatencyOp;
 
 // GET /api/health - simple liveness check
 pub async fn health() -> Json<HealthResponse> {
@@ -39,18 +40,24 @@ pub async fn metrics(State(state): State<SharedState>) -> Result<Json<MetricsRes
         let count = storage.count();
         let index_type = storage.vector_index().index_type().to_string();
         let memory_usage_bytes = storage.memory_usage_bytes();
+        let index_spill = index_spill(storage.vector_index());
         
         // Get latency stats for this collection
-        let (insert_latency_ms, search_latency_ms, lock_read_ms, lock_write_ms) =
+        let (insert_latency_ms, search_latency_ms, lock_read_ms, lock_write_ms, latency) =
             if let Some(tracker) = state.latency_tracker.get(&collection_name) {
+                let latency = LatencyOp::ALL
+                    .iter()
+                    .filter_map(|op| tracker.summary(*op).map(|s| (op.name(), s)))
+                    .collect();
                 (
                     tracker.avg_insert_latency_ms(),
                     tracker.avg_search_latency_ms(),
                     tracker.avg_lock_read_latency_ms(),
                     tracker.avg_lock_write_latency_ms(),
+                    latency,
                 )
             } else {
-                (None, None, None, None)
+                (None, None, None, None, Default::default())
             };
 
         total_vectors += count;
@@ -61,6 +68,7 @@ pub async fn metrics(State(state): State<SharedState>) -> Result<Json<MetricsRes
             crate::index::IndexConfig::Ivf { num_probes, search, .. } => (Some(search.filter_overfetch), None, Some(*num_probes)),
         };
 
+        let recall = state.recall_monitor.status(&collection_name);
         collection_metrics.push(CollectionMetrics {
             name: collection_name,
             vector_count: count,
@@ -73,12 +81,15 @@ pub async fn metrics(State(state): State<SharedState>) -> Result<Json<MetricsRes
             search_overfetch,
             hnsw_ef_search,
             ivf_nprobe,
+            latency,
+            index_spill,
+            recall,
         });
 
-        let wal_size = std::fs::metadata(&format!("{}.wal.db", storage.path))
+        let wal_size = std::fs::metadata(format!("{}.wal.db", storage.path))
             .map(|m| m.len())
             .ok();
-        let checkpoint_age_secs = storage.persistence.last_checkpoint().and_then(|ts| {
+        let checkpoint_age_secs = storage.last_checkpoint().and_then(|ts| {
             let now = std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .ok()?
@@ -87,9 +98,10 @@ pub async fn metrics(State(state): State<SharedState>) -> Result<Json<MetricsRes
         });
         wal_stats.push(WalStats {
             collection: storage.path.clone(),
-            last_checkpoint: storage.persistence.last_checkpoint(),
+            last_checkpoint: storage.last_checkpoint(),
             checkpoint_age_secs,
             wal_size_bytes: wal_size,
+            backlog: storage.wal_backlog(),
         });
     } 
 
@@ -108,5 +120,158 @@ pub async fn metrics(State(state): State<SharedState>) -> Result<Json<MetricsRes
         app_config: state.current_config(),
         wal_stats,
         embedding: embed_metrics_response,
+        load_shedding: state.load_shedder.stats(),
+        query_cache: state.query_cache.stats(),
     }))
 }
+
+// GET /api/metrics/prometheus - the same numbers in the Prometheus text exposition format
+// Latencies are exported as summaries (quantile labels plus _sum/_count) in seconds.
+pub async fn metrics_prometheus(State(state): State<SharedState>) -> impl IntoResponse {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    let _ = writeln!(out, "# HELP piramid_collections Loaded collections.");
+    let _ = writeln!(out, "# TYPE piramid_collections gauge");
+    let _ = writeln!(out, "piramid_collections {}", state.collections.len());
+
+    let _ = writeln!(out, "# HELP piramid_collection_vectors Vectors stored per collection.");
+    let _ = writeln!(out, "# TYPE piramid_collection_vectors gauge");
+    let mut spills = Vec::new();
+    let mut backlogs = Vec::new();
+    let mut checksum_failures = Vec::new();
+    let mut prefetches = Vec::new();
+    for item in state.collections.iter() {
+        let lock_start = std::time::Instant::now();
+        let storage = item.value().read();
+        record_lock_read(state.latency_tracker.get(item.key()).as_deref(), lock_start);
+        let _ = writeln!(out, "piramid_collection_vectors{{collection=\"{}\"}} {}", item.key(), storage.count());
+        if let Some(spill) = index_spill(storage.vector_index()) {
+            spills.push((item.key().clone(), spill));
+        }
+        backlogs.push((item.key().clone(), storage.wal_backlog()));
+        checksum_failures.push((item.key().clone(), storage.checksum_failures()));
+        prefetches.push((item.key().clone(), storage.prefetched_ranges()));
+    }
+    if !spills.is_empty() {
+        let _ = writeln!(out, "# HELP piramid_ivf_list_probes_total IVF list probes under an index memory budget, served from memory (hit) or disk (miss).");
+        let _ = writeln!(out, "# TYPE piramid_ivf_list_probes_total counter");
+        for (collection, spill) in &spills {
+            let _ = writeln!(out, "piramid_ivf_list_probes_total{{collection=\"{collection}\",result=\"hit\"}} {}", spill.hits);
+            let _ = writeln!(out, "piramid_ivf_list_probes_total{{collection=\"{collection}\",result=\"miss\"}} {}", spill.misses);
+        }
+        let _ = writeln!(out, "# HELP piramid_ivf_spilled_clusters IVF clusters whose inverted list is on disk.");
+        let _ = writeln!(out, "# TYPE piramid_ivf_spilled_clusters gauge");
+        for (collection, spill) in &spills {
+            let _ = writeln!(out, "piramid_ivf_spilled_clusters{{collection=\"{collection}\"}} {}", spill.spilled_clusters);
+        }
+    }
+
+    let _ = writeln!(out, "# HELP piramid_wal_backlog_bytes WAL bytes not yet covered by a checkpoint on disk.");
+    let _ = writeln!(out, "# TYPE piramid_wal_backlog_bytes gauge");
+    for (collection, backlog) in &backlogs {
+        let _ = writeln!(out, "piramid_wal_backlog_bytes{{collection=\"{collection}\"}} {}", backlog.bytes);
+    }
+    let _ = writeln!(out, "# HELP piramid_wal_backlog_ops WAL entries logged since the last checkpoint on disk.");
+    let _ = writeln!(out, "# TYPE piramid_wal_backlog_ops gauge");
+    for (collection, backlog) in &backlogs {
+        let _ = writeln!(out, "piramid_wal_backlog_ops{{collection=\"{collection}\"}} {}", backlog.ops);
+    }
+
+    let _ = writeln!(out, "# HELP piramid_checksum_failures_total Document reads that found an entry not matching its checksum.");
+    let _ = writeln!(out, "# TYPE piramid_checksum_failures_total counter");
+    for (collection, failures) in &checksum_failures {
+        let _ = writeln!(out, "piramid_checksum_failures_total{{collection=\"{collection}\"}} {failures}");
+    }
+    let _ = writeln!(out, "# HELP piramid_prefetched_ranges_total Byte ranges of search candidates hinted to the kernel before being read.");
+    let _ = writeln!(out, "# TYPE piramid_prefetched_ranges_total counter");
+    for (collection, ranges) in &prefetches {
+        let _ = writeln!(out, "piramid_prefetched_ranges_total{{collection=\"{collection}\"}} {ranges}");
+    }
+
+    let recalls: Vec<_> = state.collections.iter()
+        .filter_map(|item| state.recall_monitor.status(item.key()).map(|s| (item.key().clone(), s)))
+        .collect();
+    if !recalls.is_empty() {
+        let _ = writeln!(out, "# HELP piramid_index_recall Recall@k of the index against sampled exact results.");
+        let _ = writeln!(out, "# TYPE piramid_index_recall gauge");
+        for (collection, status) in &recalls {
+            let _ = writeln!(out, "piramid_index_recall{{collection=\"{collection}\",k=\"{}\"}} {}", status.k, status.recall);
+        }
+        let _ = writeln!(out, "# HELP piramid_index_recall_degraded 1 when the index recall is below recall_monitor.alert_below.");
+        let _ = writeln!(out, "# TYPE piramid_index_recall_degraded gauge");
+        for (collection, status) in &recalls {
+            let _ = writeln!(out, "piramid_index_recall_degraded{{collection=\"{collection}\"}} {}", status.degraded as u8);
+        }
+    }
+
+    let _ = writeln!(out, "# HELP piramid_operation_latency_seconds Operation latency per collection.");
+    let _ = writeln!(out, "# TYPE piramid_operation_latency_seconds summary");
+    for tracker in state.latency_tracker.iter() {
+        for op in LatencyOp::ALL {
+            let histogram = tracker.histogram(op);
+            if histogram.count() == 0 {
+                continue;
+            }
+            let labels = format!("collection=\"{}\",op=\"{}\"", tracker.key(), op.name());
+            for q in [0.5, 0.9, 0.99, 0.999] {
+                let seconds = histogram.quantile_us(q).unwrap_or(0) as f64 / 1e6;
+                let _ = writeln!(out, "piramid_operation_latency_seconds{{{labels},quantile=\"{q}\"}} {seconds}");
+            }
+            let _ = writeln!(out, "piramid_operation_latency_seconds_sum{{{labels}}} {}", histogram.sum_us() as f64 / 1e6);
+            let _ = writeln!(out, "piramid_operation_latency_seconds_count{{{labels}}} {}", histogram.count());
+        }
+    }
+
+    let embed = state.embed_metrics.snapshot();
+    let _ = writeln!(out, "# HELP piramid_embedding_requests_total Embedding provider requests.");
+    let _ = writeln!(out, "# TYPE piramid_embedding_requests_total counter");
+    let _ = writeln!(out, "piramid_embedding_requests_total {}", embed.requests);
+
+    let shedding = state.load_shedder.stats();
+    let classes = [("interactive", &shedding.interactive), ("batch", &shedding.batch)];
+    let _ = writeln!(out, "# HELP piramid_requests_shed_total Requests rejected by load shedding, per priority class.");
+    let _ = writeln!(out, "# TYPE piramid_requests_shed_total counter");
+    for (class, stats) in classes {
+        let _ = writeln!(out, "piramid_requests_shed_total{{class=\"{class}\"}} {}", stats.shed_total);
+    }
+    let _ = writeln!(out, "# HELP piramid_requests_in_flight Requests holding a slot, per priority class.");
+    let _ = writeln!(out, "# TYPE piramid_requests_in_flight gauge");
+    for (class, stats) in classes {
+        let _ = writeln!(out, "piramid_requests_in_flight{{class=\"{class}\"}} {}", stats.in_flight);
+    }
+
+    let cache = state.query_cache.stats();
+    let _ = writeln!(out, "# HELP piramid_query_cache_hits_total Searches answered from the query cache.");
+    let _ = writeln!(out, "# TYPE piramid_query_cache_hits_total counter");
+    let _ = writeln!(out, "piramid_query_cache_hits_total {}", cache.hits);
+    let _ = writeln!(out, "# HELP piramid_query_cache_misses_total Searches the query cache could not answer.");
+    let _ = writeln!(out, "# TYPE piramid_query_cache_misses_total counter");
+    let _ = writeln!(out, "piramid_query_cache_misses_total {}", cache.misses);
+    let _ = writeln!(out, "# HELP piramid_query_cache_stale_hits_total Searches answered with cached results behind recent writes.");
+    let _ = writeln!(out, "# TYPE piramid_query_cache_stale_hits_total counter");
+    let _ = writeln!(out, "piramid_query_cache_stale_hits_total {}", cache.stale_hits);
+    let _ = writeln!(out, "# HELP piramid_query_cache_entries Result lists held by the query cache.");
+    let _ = writeln!(out, "# TYPE piramid_query_cache_entries gauge");
+    let _ = writeln!(out, "piramid_query_cache_entries {}", cache.entries);
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], out)
+}
+
+// DELETE /api/metrics/latency - empty latency histograms (one collection with ?collection=), on disk too
+pub async fn reset_latency(
+    State(state): State<SharedState>,
+    Query(query): Query<LatencyResetQuery>,
+) -> Result<Json<LatencyResetResponse>> {
+    let reset = state.reset_latency_histograms(query.collection.as_deref())?;
+    tracing::info!(collections=reset.len(), "latency_histograms_reset");
+    Ok(Json(LatencyResetResponse { reset }))
+}
+
+// Spill figures of an IVF index running under memory.index_memory_budget
+fn index_spill(index: &dyn crate::index::VectorIndex) -> Option<crate::index::IvfSpillStats> {
+    match index.stats().details {
+        crate::index::IndexDetails::Ivf { spill, .. } => spill,
+        _ => None,
+    }
+}
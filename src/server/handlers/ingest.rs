@@ -0,0 +1,15 @@
+use axum::{extract::State, response::Json};
+use crate::error::Result;
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+// GET /api/ingest - positions and counters of the configured ingestion sources
+pub async fn ingest_status(
+    State(state): State<SharedState>,
+) -> Result<Json<IngestStatusResponse>> {
+    let mut sources: Vec<_> = state.ingest.iter().map(|s| s.value().clone()).collect();
+    sources.sort_by(|a, b| a.source.cmp(&b.source));
+    Ok(Json(IngestStatusResponse { sources }))
+}
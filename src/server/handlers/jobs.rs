@@ -0,0 +1,91 @@
+use axum::{extract::{Path, Query, State}, response::Json};
+use std::sync::atomic::Ordering;
+use crate::error::{Result, ServerError};
+use crate::jobs::{Job, JobKind};
+use crate::storage::collection::SourceFormat;
+use crate::validation;
+use super::super::{
+    helpers::EMBEDDING_NOT_CONFIGURED,
+    state::SharedState,
+    types::*,
+};
+
+// GET /api/jobs?collection=... - queued, running and recently finished jobs, newest first
+pub async fn list_jobs(
+    State(state): State<SharedState>,
+    Query(query): Query<ListJobsQuery>,
+) -> Result<Json<JobsResponse>> {
+    Ok(Json(JobsResponse { jobs: state.jobs.list(query.collection.as_deref()) }))
+}
+
+// GET /api/jobs/:id - one job's state and progress
+pub async fn get_job(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>> {
+    state.jobs.get(&id)
+        .map(Json)
+        .ok_or_else(|| ServerError::NotFound(format!("Job '{}' not found", id)).into())
+}
+
+// POST /api/jobs/:id/cancel - cancel a queued job, or stop a running re-embed at its next batch
+pub async fn cancel_job(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>> {
+    let job = state.jobs.cancel(&id)?;
+    tracing::info!(job=%id, state=job.state.name(), "job_cancel_requested");
+    Ok(Json(job))
+}
+
+// POST /api/collections/:collection/reembed - queue re-embedding every document's text with the configured model
+pub async fn reembed_collection(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<ReembedRequest>,
+) -> Result<Json<Job>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    state.ensure_write_allowed()?;
+    validation::validate_collection_name(&collection)?;
+    if state.embedder_for(&collection).is_none() {
+        return Err(ServerError::ServiceUnavailable(EMBEDDING_NOT_CONFIGURED.to_string()).into());
+    }
+    if req.batch_size == 0 {
+        return Err(ServerError::InvalidRequest("batch_size must be >= 1".into()).into());
+    }
+    if !state.collections.contains_key(&collection) && !state.discovered.contains_key(&collection) {
+        return Err(ServerError::CollectionNotFound.into());
+    }
+
+    let job = crate::jobs::submit(&state, &collection, JobKind::Reembed { batch_size: req.batch_size })?;
+    Ok(Json(job))
+}
+
+// POST /api/collections/:collection/import - queue importing a Parquet/Arrow file or a Qdrant/Chroma dump
+pub async fn import_documents(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<ImportDocumentsRequest>,
+) -> Result<Json<Job>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    state.ensure_write_allowed()?;
+    validation::validate_collection_name(&collection)?;
+    if req.batch_size == 0 {
+        return Err(ServerError::InvalidRequest("batch_size must be >= 1".into()).into());
+    }
+    if !std::path::Path::new(&req.path).is_file() {
+        return Err(ServerError::InvalidRequest(format!("No file at '{}'", req.path)).into());
+    }
+    let format = req.format.or_else(|| SourceFormat::from_path(&req.path)).ok_or_else(|| {
+        ServerError::InvalidRequest("format is required (parquet, arrow, qdrant or chroma)".into())
+    })?;
+
+    state.get_or_create_collection(&collection)?;
+    let kind = JobKind::ImportDocuments { path: req.path, format, mapping: req.mapping, batch_size: req.batch_size };
+    let job = crate::jobs::submit(&state, &collection, kind)?;
+    Ok(Json(job))
+}
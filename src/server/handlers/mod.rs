@@ -6,6 +6,21 @@ pub mod embeddings;
 pub mod config;
 pub mod ready;
 pub mod version;
+pub mod snapshots;
+pub mod projection;
+pub mod tuning;
+pub mod verify;
+pub mod export;
+pub mod transfer;
+pub mod stats;
+pub mod ingest;
+pub mod jobs;
+pub mod projects;
+pub mod usage;
+pub mod slow_queries;
+pub mod query_log;
+pub mod feedback;
+pub mod writes;
 
 // Re-export all handlers
 pub use health::*;
@@ -15,3 +30,18 @@ pub use embeddings::*;
 pub use config::*;
 pub use ready::*;
 pub use version::*;
+pub use snapshots::*;
+pub use projection::*;
+pub use tuning::*;
+pub use verify::*;
+pub use export::*;
+pub use transfer::*;
+pub use stats::*;
+pub use ingest::*;
+pub use jobs::*;
+pub use projects::*;
+pub use usage::*;
+pub use slow_queries::*;
+pub use query_log::*;
+pub use feedback::*;
+pub use writes::*;
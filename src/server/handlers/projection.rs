@@ -0,0 +1,89 @@
+use axum::{extract::{Path, State}, response::Json};
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use crate::error::{Result, ServerError};
+use crate::validation;
+use crate::server::metrics::record_lock_read;
+use crate::storage::collection::DEFAULT_PROJECTION_SAMPLE;
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+// POST /api/collections/:collection/projection/train - learn a PCA/OPQ projection and re-index through it
+pub async fn train_projection(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<TrainProjectionRequest>,
+) -> Result<Json<ProjectionResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    state.ensure_write_allowed()?;
+    validation::validate_collection_name(&collection)?;
+    let sample_size = req.sample_size.unwrap_or(DEFAULT_PROJECTION_SAMPLE);
+    if sample_size == 0 {
+        return Err(ServerError::InvalidRequest("sample_size must be > 0".to_string()).into());
+    }
+
+    let start = Instant::now();
+    let projection = state.train_projection(&collection, req.kind, req.dims, sample_size)?;
+    tracing::info!(
+        collection=%collection,
+        kind=?projection.kind,
+        input_dims=projection.input_dims,
+        output_dims=projection.output_dims,
+        sample_size=projection.sample_size,
+        explained_variance=projection.explained_variance,
+        elapsed_ms=start.elapsed().as_millis(),
+        "projection_trained"
+    );
+
+    Ok(Json(ProjectionResponse {
+        projection: Some(ProjectionInfo::from(&projection)),
+        latency_ms: Some(start.elapsed().as_millis() as f32),
+    }))
+}
+
+// GET /api/collections/:collection/projection - the trained projection, if any
+pub async fn get_projection(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<ProjectionResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+
+    state.get_or_create_collection(&collection)?;
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let lock_start = Instant::now();
+    let storage = storage_ref.read();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+
+    Ok(Json(ProjectionResponse {
+        projection: storage.projection().map(ProjectionInfo::from),
+        latency_ms: None,
+    }))
+}
+
+// DELETE /api/collections/:collection/projection - drop the projection and re-index the full vectors
+pub async fn delete_projection(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<DeleteResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    state.ensure_write_allowed()?;
+    validation::validate_collection_name(&collection)?;
+
+    let start = Instant::now();
+    let deleted = state.clear_projection(&collection)?;
+    Ok(Json(DeleteResponse {
+        deleted,
+        seq: None,
+        latency_ms: Some(start.elapsed().as_millis() as f32),
+    }))
+}
@@ -0,0 +1,99 @@
+use axum::{extract::{Path, State}, response::Json};
+use std::sync::atomic::Ordering;
+use crate::error::{Result, ServerError};
+use crate::server::projects::{Project, ProjectConfig};
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+fn project_info(state: &SharedState, project: &Project) -> ProjectInfo {
+    let config = &project.config;
+    ProjectInfo {
+        name: config.name.clone(),
+        collections: config.collections.clone(),
+        embedding: config.embedding.as_ref().map(|e| ProjectEmbeddingInfo { provider: e.provider.clone(), model: e.model.clone() }),
+        api_keys: config.api_keys.len(),
+        search: config.search,
+        quotas: config.quotas,
+        vectors: config.collections.iter().map(|c| state.stored_count(c)).sum(),
+    }
+}
+
+fn ensure_running(state: &SharedState) -> Result<()> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    Ok(())
+}
+
+// Collections of either version of a project, whose search defaults follow it
+fn affected(project: Option<&Project>, previous: Option<&Project>) -> Vec<String> {
+    let mut names: Vec<String> = project.into_iter().chain(previous)
+        .flat_map(|p| p.config.collections.iter().cloned())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+// GET /api/projects - list projects
+pub async fn list_projects(State(state): State<SharedState>) -> Result<Json<ProjectsResponse>> {
+    ensure_running(&state)?;
+    let projects = state.projects.list().iter().map(|p| project_info(&state, p)).collect();
+    Ok(Json(ProjectsResponse { projects }))
+}
+
+// POST /api/projects - create a project
+pub async fn create_project(
+    State(state): State<SharedState>,
+    Json(config): Json<ProjectConfig>,
+) -> Result<Json<ProjectInfo>> {
+    ensure_running(&state)?;
+    if state.projects.get(&config.name).is_some() {
+        return Err(ServerError::AlreadyExists(format!("Project '{}' already exists", config.name)).into());
+    }
+    let (project, _) = state.projects.put(config)?;
+    state.apply_project_settings(&affected(Some(&project), None));
+    Ok(Json(project_info(&state, &project)))
+}
+
+// GET /api/projects/:name - one project
+pub async fn get_project(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<ProjectInfo>> {
+    ensure_running(&state)?;
+    let project = state.projects.get(&name)
+        .ok_or_else(|| ServerError::NotFound(format!("Project '{name}' not found")))?;
+    Ok(Json(project_info(&state, &project)))
+}
+
+// PUT /api/projects/:name - replace a project's settings (creates it when missing)
+pub async fn update_project(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(mut config): Json<ProjectConfig>,
+) -> Result<Json<ProjectInfo>> {
+    ensure_running(&state)?;
+    if !config.name.is_empty() && config.name != name {
+        return Err(ServerError::InvalidRequest(format!("Project name '{}' does not match the path", config.name)).into());
+    }
+    config.name = name;
+    let (project, previous) = state.projects.put(config)?;
+    state.apply_project_settings(&affected(Some(&project), previous.as_deref()));
+    Ok(Json(project_info(&state, &project)))
+}
+
+// DELETE /api/projects/:name - remove a project; its collections are kept and leave it
+pub async fn delete_project(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<DeleteResponse>> {
+    ensure_running(&state)?;
+    let removed = state.projects.remove(&name)?;
+    if let Some(removed) = &removed {
+        state.apply_project_settings(&affected(None, Some(removed)));
+    }
+    Ok(Json(DeleteResponse { deleted: removed.is_some(), seq: None, latency_ms: None }))
+}
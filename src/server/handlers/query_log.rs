@@ -0,0 +1,51 @@
+use axum::{extract::{Path, Query, State}, http::header, response::{IntoResponse, Json, Response}};
+use crate::error::{Result, ServerError};
+use crate::validation;
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+// POST /api/collections/:collection/queries/:query_id/feedback - documents used from a logged search
+pub async fn query_feedback(
+    State(state): State<SharedState>,
+    Path((collection, query_id)): Path<(String, String)>,
+    Json(req): Json<QueryFeedbackRequest>,
+) -> Result<Json<QueryFeedbackResponse>> {
+    validation::validate_collection_name(&collection)?;
+    let clicked = req.clicked.len();
+    state.query_log.feedback(&collection, &query_id, req.clicked)?;
+    Ok(Json(QueryFeedbackResponse { query_id, clicked }))
+}
+
+// GET /api/collections/:collection/queries/export - the logged searches with their feedback, one NDJSON line each
+pub async fn export_query_log(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Query(query): Query<QueryLogExportQuery>,
+) -> Result<Response> {
+    validation::validate_collection_name(&collection)?;
+    let log = state.query_log.clone();
+    let body = tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        for entry in log.export(&collection, query.since.unwrap_or(0))? {
+            serde_json::to_writer(&mut out, &entry).map_err(|e| ServerError::Internal(e.to_string()))?;
+            out.push(b'\n');
+        }
+        Ok(out)
+    })
+    .await
+    .map_err(|e| ServerError::Internal(format!("query log export task failed: {}", e)))??;
+    Ok(([(header::CONTENT_TYPE, crate::server::types::ndjson::CONTENT_TYPE)], body).into_response())
+}
+
+// DELETE /api/collections/:collection/queries - drop the collection's query log
+pub async fn clear_query_log(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<ClearQueryLogResponse>> {
+    validation::validate_collection_name(&collection)?;
+    let cleared = state.query_log.clear(&collection);
+    tracing::info!(collection = %collection, cleared, "query_log_cleared");
+    Ok(Json(ClearQueryLogResponse { cleared }))
+}
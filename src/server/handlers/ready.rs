@@ -36,6 +36,8 @@ fn disk_stats(path: &str) -> (Option<u64>, Option<u64>) {
 // - If the server is in the process of shutting down (returns 503 if so)
 // - For each collection: if it's loaded, vector count, index type, last checkpoint time, checkpoint age, WAL size, schema version, and integrity status
 // - Disk usage stats for the data directory
+// - Whether writes are accepted, and why not when the server is read-only (not counted against `ok`,
+//   reads are still served)
 pub async fn readyz(State(state): State<SharedState>) -> Result<Json<ReadyzResponse>> {
     // 1. Check if server is shutting down - if so, return 503 to indicate we're not ready to serve traffic
     if state.shutting_down.load(Ordering::Relaxed) {
@@ -56,12 +58,12 @@ pub async fn readyz(State(state): State<SharedState>) -> Result<Json<ReadyzRespo
         total_vectors += count;
         let index_type = storage.vector_index().index_type().to_string();
         let schema_version = Some(storage.metadata.schema_version);
-        let last_checkpoint = storage.persistence.last_checkpoint();
+        let last_checkpoint = storage.last_checkpoint();
         let checkpoint_age_secs = last_checkpoint.and_then(|ts| {
             let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
             now.checked_sub(ts)
         });
-        let wal_size_bytes = std::fs::metadata(&format!("{}.wal.db", storage.path))
+        let wal_size_bytes = std::fs::metadata(format!("{}.wal.db", storage.path))
             .map(|m| m.len())
             .ok(); // If the WAL file doesn't exist or we can't read it, we just return None for its size
 
@@ -77,6 +79,7 @@ pub async fn readyz(State(state): State<SharedState>) -> Result<Json<ReadyzRespo
             schema_version,
             integrity_ok: true,
             error: None,
+            warning: storage.index_recovery().map(|r| r.warning()),
         });
     }
 
@@ -100,6 +103,7 @@ pub async fn readyz(State(state): State<SharedState>) -> Result<Json<ReadyzRespo
                         schema_version: None,
                         integrity_ok: false,
                         error: Some("not loaded".to_string()),
+                        warning: None,
                     });
                 }
             }
@@ -121,6 +125,8 @@ pub async fn readyz(State(state): State<SharedState>) -> Result<Json<ReadyzRespo
         total_vectors,
         disk_total_bytes,
         disk_available_bytes,
+        writable: !state.read_only.load(Ordering::Relaxed),
+        read_only: state.read_only_status(),
         collections: collections_health,
     }))
 }
@@ -0,0 +1,27 @@
+use axum::{extract::{Query, State}, response::Json};
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+// GET /api/slow_queries - captured slow searches, newest first, optionally for one collection
+pub async fn list_slow_queries(
+    State(state): State<SharedState>,
+    Query(query): Query<SlowQueriesQuery>,
+) -> Json<SlowQueriesResponse> {
+    let log = &state.slow_queries;
+    Json(SlowQueriesResponse {
+        enabled: log.enabled(),
+        threshold_ms: state.slow_query_ms,
+        capacity: log.capacity(),
+        held: log.len(),
+        queries: log.list(query.collection.as_deref(), query.limit.unwrap_or(usize::MAX)),
+    })
+}
+
+// DELETE /api/slow_queries - drop every captured query
+pub async fn clear_slow_queries(State(state): State<SharedState>) -> Json<ClearSlowQueriesResponse> {
+    let cleared = state.slow_queries.clear();
+    tracing::info!(cleared, "slow_queries_cleared");
+    Json(ClearSlowQueriesResponse { cleared })
+}
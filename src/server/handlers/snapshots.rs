@@ -0,0 +1,115 @@
+use axum::{extract::{Path, State}, response::Json};
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use crate::error::{Result, ServerError};
+use crate::validation;
+use crate::server::metrics::record_lock_read;
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+// POST /api/collections/:collection/snapshots - take a named snapshot of the collection's files
+pub async fn create_snapshot(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<CreateSnapshotRequest>,
+) -> Result<Json<SnapshotInfo>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    state.ensure_write_allowed()?;
+    validation::validate_collection_name(&collection)?;
+    // Snapshot names become directory names, so they follow the collection name rules
+    validation::validate_collection_name(&req.name)?;
+
+    let start = Instant::now();
+    let manifest = state.create_snapshot(&collection, &req.name)?;
+    tracing::info!(
+        collection=%collection,
+        snapshot=%req.name,
+        bytes=manifest.size_bytes(),
+        elapsed_ms=start.elapsed().as_millis(),
+        "snapshot_created"
+    );
+
+    Ok(Json(manifest.into()))
+}
+
+// GET /api/collections/:collection/snapshots - list the collection's snapshots, oldest first
+pub async fn list_snapshots(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<SnapshotsResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+
+    let snapshots = crate::storage::collection::list_snapshots(&state.snapshot_root(&collection))?;
+    Ok(Json(SnapshotsResponse {
+        snapshots: snapshots.into_iter().map(SnapshotInfo::from).collect(),
+    }))
+}
+
+// POST /api/collections/:collection/snapshots/:snapshot/restore - restore in place or into a new collection
+pub async fn restore_snapshot(
+    State(state): State<SharedState>,
+    Path((collection, snapshot)): Path<(String, String)>,
+    body: Option<Json<RestoreSnapshotRequest>>,
+) -> Result<Json<RestoreSnapshotResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    state.ensure_write_allowed()?;
+    validation::validate_collection_name(&collection)?;
+    validation::validate_collection_name(&snapshot)?;
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    if let Some(target) = req.target.as_deref() {
+        validation::validate_collection_name(target)?;
+    }
+    state.ensure_not_paused(req.target.as_deref().unwrap_or(&collection))?;
+
+    let start = Instant::now();
+    let (target, replaced) = state.restore_snapshot(&collection, &snapshot, req.target.as_deref())?;
+    let storage_ref = state.collections.get(&target)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let lock_start = Instant::now();
+    let count = storage_ref.read().count();
+    record_lock_read(state.latency_tracker.get(&target).as_deref(), lock_start);
+    state.enforce_cache_budget();
+    tracing::info!(
+        collection=%collection,
+        snapshot=%snapshot,
+        target=%target,
+        replaced,
+        elapsed_ms=start.elapsed().as_millis(),
+        "snapshot_restored"
+    );
+
+    Ok(Json(RestoreSnapshotResponse {
+        collection: target,
+        replaced,
+        count,
+        latency_ms: Some(start.elapsed().as_millis() as f32),
+    }))
+}
+
+// DELETE /api/collections/:collection/snapshots/:snapshot - remove a snapshot
+pub async fn delete_snapshot(
+    State(state): State<SharedState>,
+    Path((collection, snapshot)): Path<(String, String)>,
+) -> Result<Json<DeleteResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+    validation::validate_collection_name(&snapshot)?;
+
+    let deleted = crate::storage::collection::delete_snapshot(&state.snapshot_root(&collection).join(&snapshot))?;
+    Ok(Json(DeleteResponse {
+        deleted,
+        seq: None,
+        latency_ms: None, // Snapshot deletion is a filesystem operation
+    }))
+}
@@ -0,0 +1,166 @@
+use axum::{extract::{Path, State}, response::Json};
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use crate::error::{Result, ServerError};
+use crate::server::helpers::json_to_filter;
+use crate::server::metrics::record_lock_read;
+use crate::storage::collection::{
+    OutlierOptions, OutlierQuery, StatsOptions, DEFAULT_HISTOGRAM_BINS, DEFAULT_INTRINSIC_SAMPLE, DEFAULT_OUTLIER_K,
+    DEFAULT_OUTLIER_SAMPLE,
+};
+use crate::validation;
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+const MAX_HISTOGRAM_BINS: usize = 1000;
+const MAX_INTRINSIC_SAMPLE: usize = 10_000; // the estimate is quadratic in the sample
+const MAX_OUTLIER_QUERIES: usize = 1000;
+const MAX_OUTLIER_SAMPLE: usize = 10_000;
+
+// POST /api/collections/:collection/statistics - centroid, variance, norm histogram and intrinsic dimensionality
+pub async fn vector_statistics(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    body: Option<Json<VectorStatsRequest>>,
+) -> Result<Json<VectorStatsResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    let histogram_bins = req.histogram_bins.unwrap_or(DEFAULT_HISTOGRAM_BINS);
+    if histogram_bins == 0 || histogram_bins > MAX_HISTOGRAM_BINS {
+        return Err(ServerError::InvalidRequest(format!("histogram_bins must be between 1 and {}", MAX_HISTOGRAM_BINS)).into());
+    }
+    let intrinsic_sample = req.intrinsic_sample.unwrap_or(DEFAULT_INTRINSIC_SAMPLE);
+    if intrinsic_sample > MAX_INTRINSIC_SAMPLE {
+        return Err(ServerError::InvalidRequest(format!("intrinsic_sample must be <= {}", MAX_INTRINSIC_SAMPLE)).into());
+    }
+
+    // Equality on every listed field, or any/all of an array field's elements
+    let filter = req.filter.map(json_to_filter).transpose()?;
+
+    state.get_or_create_collection(&collection)?;
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let start = Instant::now();
+    let lock_start = Instant::now();
+    let storage = storage_ref.read();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+
+    let stats = storage.vector_stats(&StatsOptions { filter, histogram_bins, intrinsic_sample })?;
+    drop(storage);
+    tracing::info!(
+        collection=%collection,
+        count=stats.count,
+        centroid_norm=stats.centroid_norm,
+        intrinsic_dimension=stats.intrinsic_dimension.as_ref().map(|d| d.estimate),
+        elapsed_ms=start.elapsed().as_millis(),
+        "vector_statistics_computed"
+    );
+
+    Ok(Json(VectorStatsResponse {
+        stats,
+        latency_ms: Some(start.elapsed().as_millis() as f32),
+    }))
+}
+
+// GET /api/collections/:collection/statistics - norms and metadata cardinality from the reservoir sample, without a scan
+pub async fn sampled_statistics(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<SampledStatsResponse>> {
+    validation::validate_collection_name(&collection)?;
+    state.get_or_create_collection(&collection)?;
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let start = Instant::now();
+    let lock_start = Instant::now();
+    let storage = storage_ref.read();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+    let stats = storage.sampled_stats();
+    drop(storage);
+
+    Ok(Json(SampledStatsResponse {
+        stats,
+        latency_ms: Some(start.elapsed().as_millis() as f32),
+    }))
+}
+
+// GET /api/collections/:collection/dimensions - documents per vector length, to find vectors left over from another model
+pub async fn dimension_distribution(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<DimensionsResponse>> {
+    validation::validate_collection_name(&collection)?;
+    state.get_or_create_collection(&collection)?;
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let lock_start = Instant::now();
+    let storage = storage_ref.read();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+    let report = storage.dimension_report();
+    let embedding_model = storage.metadata().embedding_model.as_ref().map(|m| m.model.clone());
+    drop(storage);
+
+    let hint = (report.mismatched > 0).then(|| format!(
+        "{} documents do not have the collection's {} dimensions and cannot be compared with its queries. Re-insert them \
+         with vectors from the collection's model, or move everything to a new collection embedded with one \
+         model and swap it in with POST /api/collections/{collection}/rename",
+        report.mismatched, report.expected.unwrap_or(0)
+    ));
+    Ok(Json(DimensionsResponse { report, embedding_model, hint }))
+}
+
+// POST /api/collections/:collection/outliers - how isolated vectors or stored documents are, as percentiles of the collection
+pub async fn score_outliers(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<OutlierRequest>,
+) -> Result<Json<OutlierResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+    validation::validate_batch_size(req.vectors.len() + req.ids.len(), MAX_OUTLIER_QUERIES, "Outlier")?;
+    for vector in &req.vectors {
+        validation::validate_vector(vector)?;
+    }
+    let sample_size = req.sample_size.unwrap_or(DEFAULT_OUTLIER_SAMPLE);
+    if sample_size == 0 || sample_size > MAX_OUTLIER_SAMPLE {
+        return Err(ServerError::InvalidRequest(format!("sample_size must be between 1 and {}", MAX_OUTLIER_SAMPLE)).into());
+    }
+
+    state.get_or_create_collection(&collection)?;
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let start = Instant::now();
+    let lock_start = Instant::now();
+    let storage = storage_ref.read();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+
+    let mut queries: Vec<OutlierQuery> = req.vectors.into_iter().map(OutlierQuery::Vector).collect();
+    for id in &req.ids {
+        let resolved = storage.resolve_id(id)
+            .ok_or_else(|| ServerError::NotFound(format!("Document '{}' not found", id)))?;
+        queries.push(OutlierQuery::Document(resolved));
+    }
+    let opts = OutlierOptions { method: req.method, k: req.k.unwrap_or(DEFAULT_OUTLIER_K), sample_size };
+    let report = storage.score_outliers(&queries, &opts)?;
+    drop(storage);
+    tracing::info!(
+        collection=%collection,
+        method=?report.method,
+        scored=report.scores.len(),
+        max_percentile=report.scores.iter().map(|s| s.percentile).fold(0.0f32, f32::max),
+        elapsed_ms=start.elapsed().as_millis(),
+        "outliers_scored"
+    );
+
+    Ok(Json(OutlierResponse {
+        report,
+        latency_ms: Some(start.elapsed().as_millis() as f32),
+    }))
+}
@@ -0,0 +1,196 @@
+// Streaming bulk transfer as an Arrow IPC stream (application/vnd.apache.arrow.stream), in both
+// directions, for moving whole collections at the speed of the link rather than of JSON encoding.
+// Record batches carry the export columns (id, vector, text, metadata as JSON; see storage/columnar).
+// - GET streams the collection out batch by batch, taking the read lock per batch only, so writes
+//   interleave with a long export (which then sees each document as it is when its batch is read)
+// - POST reads a stream in as it arrives and writes every `batch_size` rows under the write lock,
+//   upserting ids already present (the same mapping and semantics as document import jobs); record
+//   batches may be LZ4 or ZSTD compressed, as `pyarrow.ipc.IpcWriteOptions(compression=...)` writes
+// Neither side holds more than a few batches in memory, and the request body is not subject to
+// `limits.max_body_bytes`.
+
+use std::io::Read;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Json, Response},
+};
+use futures_util::StreamExt;
+use tokio::sync::mpsc;
+
+use crate::error::{Result, ServerError};
+use crate::server::metrics::{record_lock_read, record_lock_write};
+use crate::storage::collection::{export_rows, write_documents, DocumentImportReport, DocumentSource, FieldMapping};
+use crate::storage::columnar::ArrowStreamWriter;
+use crate::validation;
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+pub const ARROW_STREAM_CONTENT_TYPE: &str = "application/vnd.apache.arrow.stream";
+
+// Encoded batches (or request body chunks) in flight between the async and the blocking side
+const CHANNEL_BATCHES: usize = 4;
+
+// GET /api/collections/:collection/arrow?batch_rows=N - the documents as an Arrow IPC stream
+pub async fn export_arrow_stream(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Query(params): Query<ArrowExportQuery>,
+) -> Result<Response> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+    if params.batch_rows == 0 {
+        return Err(ServerError::InvalidRequest("batch_rows must be >= 1".into()).into());
+    }
+
+    state.get_or_create_collection(&collection)?;
+    let handle = state.collections.get(&collection)
+        .map(|c| c.value().clone())
+        .ok_or(ServerError::CollectionNotFound)?;
+    let (ids, dimensions) = {
+        let storage = handle.read();
+        (storage.ids(), storage.metadata.dimensions.unwrap_or(0))
+    };
+    let total = ids.len();
+
+    let (tx, mut rx) = mpsc::channel::<std::io::Result<Bytes>>(CHANNEL_BATCHES);
+    let shared = state.clone();
+    let name = collection.clone();
+    tokio::task::spawn_blocking(move || {
+        let state = shared;
+        let start = Instant::now();
+        let send = |bytes: Vec<u8>| tx.blocking_send(Ok(Bytes::from(bytes))).is_ok();
+        let result = (|| -> Result<Option<usize>> {
+            let mut writer = ArrowStreamWriter::new(Vec::new(), dimensions)?;
+            let mut rows = 0;
+            for chunk in ids.chunks(params.batch_rows) {
+                let lock_start = Instant::now();
+                let storage = handle.read();
+                record_lock_read(state.latency_tracker.get(&name).as_deref(), lock_start);
+                let batch = export_rows(&storage, chunk, dimensions, None)?;
+                drop(storage);
+                if batch.is_empty() {
+                    continue;
+                }
+                rows += batch.len();
+                writer.write_batch(&batch)?;
+                if !send(std::mem::take(writer.get_mut())) {
+                    return Ok(None); // the client went away
+                }
+            }
+            Ok(send(writer.finish()?).then_some(rows))
+        })();
+        match result {
+            Ok(Some(rows)) => tracing::info!(
+                collection=%name, rows, elapsed_ms=start.elapsed().as_millis(), "arrow_stream_exported"
+            ),
+            Ok(None) => tracing::info!(collection=%name, "arrow_stream_export_abandoned"),
+            Err(e) => {
+                tracing::warn!(collection=%name, error=%e, "arrow_stream_export_failed");
+                let _ = tx.blocking_send(Err(std::io::Error::other(e.to_string())));
+            }
+        }
+    });
+
+    let stream = futures_util::stream::poll_fn(move |cx| rx.poll_recv(cx));
+    Ok((
+        [
+            (header::CONTENT_TYPE, ARROW_STREAM_CONTENT_TYPE.to_string()),
+            (header::HeaderName::from_static("x-export-documents"), total.to_string()),
+        ],
+        Body::from_stream(stream),
+    ).into_response())
+}
+
+// Request body chunks handed to the blocking reader
+struct ChannelReader {
+    rx: mpsc::Receiver<Bytes>,
+    chunk: Bytes,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.chunk.is_empty() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => self.chunk = chunk,
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len());
+        buf[..n].copy_from_slice(&self.chunk.split_to(n));
+        Ok(n)
+    }
+}
+
+// POST /api/collections/:collection/arrow?batch_size=N - write the documents of an Arrow IPC stream
+pub async fn import_arrow_stream(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Query(params): Query<ArrowImportQuery>,
+    body: Body,
+) -> Result<Json<ArrowImportResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    state.ensure_write_allowed()?;
+    state.throttle_writes(&collection).await?;
+    validation::validate_collection_name(&collection)?;
+    if params.batch_size == 0 {
+        return Err(ServerError::InvalidRequest("batch_size must be >= 1".into()).into());
+    }
+
+    state.get_or_create_collection(&collection)?;
+    let handle = state.collections.get(&collection)
+        .map(|c| c.value().clone())
+        .ok_or(ServerError::CollectionNotFound)?;
+    let start = Instant::now();
+    let (tx, rx) = mpsc::channel::<Bytes>(CHANNEL_BATCHES);
+    let shared = state.clone();
+    let name = collection.clone();
+    let import = tokio::task::spawn_blocking(move || -> Result<(DocumentImportReport, u64)> {
+        let state = shared;
+        let reader = ChannelReader { rx, chunk: Bytes::new() };
+        let mut source = DocumentSource::arrow_stream(reader, &FieldMapping::default())?;
+        let mut report = DocumentImportReport { skipped_columns: source.skipped_columns().to_vec(), ..Default::default() };
+        while let Some(docs) = source.next_batch(params.batch_size)? {
+            let lock_start = Instant::now();
+            let mut storage = handle.write();
+            record_lock_write(state.latency_tracker.get(&name).as_deref(), lock_start);
+            let creating = docs.iter()
+                .filter(|doc| storage.get(&doc.id).is_none() && doc.external_id().is_none_or(|ext| storage.resolve_id(ext).is_none()))
+                .count();
+            state.ensure_project_quota(&name, storage.count(), creating)?;
+            let (inserted, updated) = write_documents(&mut storage, docs)?;
+            report.inserted += inserted;
+            report.updated += updated;
+            report.rows = source.position();
+        }
+        let seq = handle.read().head_seq();
+        Ok((report, seq))
+    });
+
+    // Feed the body to the reader; a reader that stopped early (a bad stream) drops its end
+    let mut data = body.into_data_stream();
+    while let Some(chunk) = data.next().await {
+        let chunk = chunk.map_err(|e| ServerError::InvalidRequest(format!("Failed to read the request body: {e}")))?;
+        if tx.send(chunk).await.is_err() {
+            break;
+        }
+    }
+    drop(tx);
+    let (report, seq) = import.await.map_err(|e| ServerError::Internal(e.to_string()))??;
+    state.enforce_cache_budget();
+    tracing::info!(
+        collection=%collection, rows=report.rows, inserted=report.inserted, updated=report.updated,
+        elapsed_ms=start.elapsed().as_millis(), "arrow_stream_imported"
+    );
+    Ok(Json(ArrowImportResponse { report, seq, latency_ms: start.elapsed().as_millis() as f32 }))
+}
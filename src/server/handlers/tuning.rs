@@ -0,0 +1,133 @@
+use axum::{extract::{Path, State}, response::Json};
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use uuid::Uuid;
+use crate::error::{Result, ServerError};
+use crate::validation;
+use crate::server::metrics::record_lock_read;
+use crate::storage::collection::{TuneOptions, DEFAULT_TARGET_RECALL, DEFAULT_TUNING_SAMPLE};
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+// POST /api/collections/:collection/tuning/sweep - sweep ef/nprobe over sample queries and recommend a value
+pub async fn tune_collection(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<TuneRequest>,
+) -> Result<Json<TuneResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    if req.persist {
+        state.ensure_write_allowed()?;
+    }
+    validation::validate_collection_name(&collection)?;
+    let sample_size = req.sample_size.unwrap_or(DEFAULT_TUNING_SAMPLE);
+    if sample_size == 0 {
+        return Err(ServerError::InvalidRequest("sample_size must be > 0".to_string()).into());
+    }
+    let queries = req.queries.unwrap_or_default();
+    for query in &queries {
+        validation::validate_vector(query)?;
+    }
+    if req.ground_truth.is_some() && queries.is_empty() {
+        return Err(ServerError::InvalidRequest("ground_truth needs the queries it belongs to".to_string()).into());
+    }
+
+    // Expected ids may be document ids or client-provided ids
+    state.get_or_create_collection(&collection)?;
+    let ground_truth = match req.ground_truth {
+        Some(truth) => {
+            let storage_ref = state.collections.get(&collection)
+                .ok_or(ServerError::CollectionNotFound)?;
+            let lock_start = Instant::now();
+            let storage = storage_ref.read();
+            record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+            let resolved = truth
+                .iter()
+                .map(|ids| {
+                    ids.iter()
+                        .map(|id| storage.resolve_id(id).ok_or_else(|| {
+                            ServerError::InvalidRequest(format!("ground_truth id '{}' is not in the collection", id)).into()
+                        }))
+                        .collect::<Result<Vec<Uuid>>>()
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Some(resolved)
+        }
+        None => None,
+    };
+
+    let opts = TuneOptions {
+        queries,
+        ground_truth,
+        sample_size,
+        k: req.k,
+        target_recall: req.target_recall.unwrap_or(DEFAULT_TARGET_RECALL),
+        values: req.values,
+        metric: None,
+    };
+    let start = Instant::now();
+    let report = state.tune_collection(&collection, opts, req.persist)?;
+    tracing::info!(
+        collection=%collection,
+        param=?report.param,
+        value=report.recommended.value,
+        recall=report.recommended.recall,
+        met_target=report.met_target,
+        persisted=req.persist,
+        elapsed_ms=start.elapsed().as_millis(),
+        "search_tuned"
+    );
+
+    Ok(Json(TuneResponse {
+        report,
+        persisted: req.persist,
+        latency_ms: Some(start.elapsed().as_millis() as f32),
+    }))
+}
+
+// GET /api/collections/:collection/tuning - the kept recommendation and the search defaults it produces
+pub async fn get_tuning(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<TuningResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+
+    state.get_or_create_collection(&collection)?;
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let lock_start = Instant::now();
+    let storage = storage_ref.read();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+
+    Ok(Json(TuningResponse {
+        tuning: storage.tuning().cloned(),
+        search: storage.config().search,
+    }))
+}
+
+// DELETE /api/collections/:collection/tuning - drop the recommendation and go back to the configured search settings
+pub async fn delete_tuning(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<DeleteResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    state.ensure_write_allowed()?;
+    validation::validate_collection_name(&collection)?;
+
+    let start = Instant::now();
+    let deleted = state.clear_tuning(&collection)?;
+    Ok(Json(DeleteResponse {
+        deleted,
+        seq: None,
+        latency_ms: Some(start.elapsed().as_millis() as f32),
+    }))
+}
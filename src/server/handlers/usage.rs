@@ -0,0 +1,36 @@
+use axum::{extract::{Query, State}, response::Json};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::error::{Result, ServerError};
+use crate::server::usage::{month_start, UsageCounts, UsageFilter};
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+// GET /api/usage - embedding provider usage by time bucket, with the monthly budgets
+pub async fn embedding_usage(
+    State(state): State<SharedState>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageResponse>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let from = query.from.unwrap_or_else(|| month_start(now));
+    // The current hour is still filling up, so the default end takes it in
+    let to = query.to.unwrap_or(now + 1);
+    if from >= to {
+        return Err(ServerError::InvalidRequest("from must be before to".into()).into());
+    }
+    let filter = UsageFilter {
+        provider: query.provider,
+        model: query.model,
+        collection: query.collection,
+        api_key: query.api_key,
+    };
+
+    let buckets = state.usage.report(query.granularity, from, to, &filter);
+    let mut totals = UsageCounts::default();
+    for bucket in &buckets {
+        totals.add(&bucket.counts);
+    }
+    let budgets = state.usage.budgets(&state.app_config.read().usage.budgets);
+    Ok(Json(UsageResponse { granularity: query.granularity, from, to, buckets, totals, budgets }))
+}
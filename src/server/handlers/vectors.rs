@@ -1,21 +1,36 @@
-use axum::{extract::{Path, Query, State, Extension}, Json};
+use axum::{extract::{Path, Query, State, Extension}, http::HeaderMap, response::{IntoResponse, Response}, Json};
 use uuid::Uuid;
 use std::sync::atomic::Ordering;
-use std::time::Instant;
-use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use std::collections::{HashMap, HashSet};
 use crate::{Metric, Document};
+use crate::search::{BandCount, Filter, OrderBy, ScoreBands};
 use crate::error::{Result, ServerError};
 use crate::validation;
 use crate::server::metrics::{record_lock_read, record_lock_write};
+use crate::server::slow_queries::{SlowQuery, SlowQueryKind, SlowQueryLatency};
+use crate::server::query_log::{recorded_model, LoggedHit, LoggedQuery, QueryKind, QueryOptions};
+use crate::server::usage::UsageScope;
+use crate::server::search_deadline::{DeadlineSearch, Deadlined};
+use crate::embeddings::EmbeddingError;
+use crate::storage::collection::{AsOf, SearchGuard};
 use crate::server::types::range::RangeSearchRequest;
+use crate::server::types::body::{Format, Payload, Reply};
+use crate::server::types::ndjson::{self, Streamed};
 use tracing::info;
 use super::super::{
     state::SharedState,
     types::*,
-    helpers::{json_to_metadata, metadata_to_json},
+    helpers::{client_metadata, json_to_filter, metadata_to_json},
 };
 
-const MAX_BATCH_SIZE: usize = 10_000;
+pub(crate) const MAX_BATCH_SIZE: usize = 10_000;
+
+// What a single-vector search came back with
+enum Searched {
+    Hits { results: Vec<crate::search::Hit>, effective: Option<crate::search::EffectiveSearch>, partial: bool },
+    Cached { results: Vec<HitResponse>, stale: bool }, // from the query cache; stale past timeout_ms
+}
 
 fn build_single_entry(mut req: InsertRequest) -> Result<Document> {
     let text = req.text.clone().ok_or_else(|| ServerError::InvalidRequest("text is required for single insert".to_string()))?;
@@ -26,8 +41,15 @@ fn build_single_entry(mut req: InsertRequest) -> Result<Document> {
     if req.normalize {
         vec_to_store = validation::normalize_vector(&vec_to_store);
     }
-    let metadata = json_to_metadata(req.metadata);
-    Ok(Document::with_metadata(vec_to_store, text, metadata))
+    let metadata = client_metadata(req.metadata)?;
+    let entry = Document::with_metadata(vec_to_store, text, metadata);
+    match req.external_id {
+        Some(external_id) => {
+            validation::validate_external_id(&external_id)?;
+            Ok(entry.with_external_id(external_id))
+        }
+        None => Ok(entry),
+    }
 }
 
 fn build_batch_entries(mut req: InsertRequest) -> Result<Vec<Document>> {
@@ -41,6 +63,12 @@ fn build_batch_entries(mut req: InsertRequest) -> Result<Vec<Document>> {
     for t in &texts {
         validation::validate_text(t)?;
     }
+    if !req.external_ids.is_empty() && req.external_ids.len() != vectors.len() {
+        return Err(ServerError::InvalidRequest("vectors and external_ids length mismatch".to_string()).into());
+    }
+    for external_id in &req.external_ids {
+        validation::validate_external_id(external_id)?;
+    }
     let vectors = if req.normalize {
         vectors.iter().map(|v| validation::normalize_vector(v)).collect()
     } else {
@@ -49,28 +77,171 @@ fn build_batch_entries(mut req: InsertRequest) -> Result<Vec<Document>> {
 
     let mut entries = Vec::with_capacity(vectors.len());
     for (idx, vector) in vectors.into_iter().enumerate() {
-        let md = if idx < req.metadata_list.len() {
-            json_to_metadata(req.metadata_list[idx].clone())
-        } else {
-            json_to_metadata(HashMap::new())
-        };
-        let entry = Document::with_metadata(
+        let md = client_metadata(req.metadata_list.get(idx).cloned().unwrap_or_default())?;
+        let mut entry = Document::with_metadata(
             vector,
             texts[idx].clone(),
             md,
         );
+        if let Some(external_id) = req.external_ids.get(idx) {
+            entry = entry.with_external_id(external_id.clone());
+        }
         entries.push(entry);
     }
     Ok(entries)
 }
 
-// Parse similarity metric from string
-fn parse_metric(s: Option<String>) -> Metric {
-    match s.as_deref() {
-        Some("euclidean") => Metric::Euclidean,
-        Some("dot") | Some("dot_product") => Metric::DotProduct,
-        _ => Metric::Cosine,
+// With allow_partial each item is checked on its own: a bad item becomes its error instead of failing the request
+fn build_partial_entries(mut req: InsertRequest, mut embed_failures: HashMap<usize, EmbeddingError>) -> Result<Vec<Result<Document>>> {
+    let vectors = req.vectors.take().ok_or_else(|| ServerError::InvalidRequest("vectors are required for batch insert".to_string()))?;
+    let texts = req.texts.take().ok_or_else(|| ServerError::InvalidRequest("texts are required for batch insert".to_string()))?;
+    validation::validate_batch_size(vectors.len(), MAX_BATCH_SIZE, "Insert")?;
+    if vectors.len() != texts.len() {
+        return Err(ServerError::InvalidRequest("vectors and texts length mismatch".to_string()).into());
+    }
+    if !req.external_ids.is_empty() && req.external_ids.len() != vectors.len() {
+        return Err(ServerError::InvalidRequest("vectors and external_ids length mismatch".to_string()).into());
+    }
+
+    let mut build = |idx: usize, vector: Vec<f32>, text: String| -> Result<Document> {
+        if let Some(e) = embed_failures.remove(&idx) {
+            return Err(e.into());
+        }
+        validation::validate_vector(&vector)?;
+        validation::validate_text(&text)?;
+        let vector = if req.normalize { validation::normalize_vector(&vector) } else { vector };
+        let md = client_metadata(req.metadata_list.get(idx).cloned().unwrap_or_default())?;
+        let entry = Document::with_metadata(vector, text, md);
+        match req.external_ids.get(idx) {
+            Some(external_id) => {
+                validation::validate_external_id(external_id)?;
+                Ok(entry.with_external_id(external_id.clone()))
+            }
+            None => Ok(entry),
+        }
+    };
+    Ok(vectors.into_iter().zip(texts).enumerate().map(|(idx, (vector, text))| build(idx, vector, text)).collect())
+}
+
+// What embed_missing_vectors did
+#[derive(Default)]
+struct Embedded {
+    dimensions: Option<usize>, // of the embeddings, when it embedded anything
+    failed: HashMap<usize, EmbeddingError>, // allow_partial batches: the texts that could not be embedded, by index
+}
+
+// Inserts that carry text but no vectors are embedded here when an embedder is configured, saving the client a round trip to /embed. The model is recorded in each document's metadata. With allow_partial, a batch keeps the texts that were embedded and reports the others as failed items.
+async fn embed_missing_vectors(state: &SharedState, collection: &str, api_key: Option<&str>, req: &mut InsertRequest) -> Result<Embedded> {
+    if req.vector.is_some() || req.vectors.is_some() {
+        return Ok(Embedded::default());
+    }
+    let Some(embedder) = state.embedder_for(collection) else {
+        return Ok(Embedded::default());
+    };
+    if (req.text.is_some() || req.texts.is_some()) && !req.allow_model_mismatch {
+        super::embeddings::ensure_embedding_model(state, collection, embedder.model_name(), None)?;
+    }
+    let usage = UsageScope::new(&*embedder, collection, api_key);
+    let model = |response: &crate::embeddings::EmbeddingResponse| {
+        let name = if response.model.is_empty() { embedder.model_name() } else { response.model.as_str() };
+        serde_json::Value::String(name.to_string())
+    };
+
+    let start = Instant::now();
+    match (req.text.as_deref(), req.texts.as_ref()) {
+        (Some(text), None) => {
+            validation::validate_text(text)?;
+            state.check_usage(&usage)?;
+            let response = embedder.embed(text).await?;
+            state.record_embedding(&usage, 1, response.tokens.unwrap_or(0) as u64, start.elapsed());
+            req.metadata
+                .entry(crate::embeddings::EMBEDDING_MODEL_KEY.to_string())
+                .or_insert_with(|| model(&response));
+            let dimensions = response.embedding.len();
+            req.vector = Some(response.embedding);
+            return Ok(Embedded { dimensions: Some(dimensions), ..Default::default() });
+        }
+        (None, Some(texts)) => {
+            validation::validate_batch_size(texts.len(), MAX_BATCH_SIZE, "Insert")?;
+            if req.metadata_list.len() < texts.len() {
+                req.metadata_list.resize_with(texts.len(), HashMap::new);
+            }
+            for text in texts {
+                validation::validate_text(text)?;
+            }
+            state.check_usage(&usage)?;
+            let outcomes = if req.allow_partial {
+                embedder.embed_batch_partial(texts).await
+            } else {
+                embedder.embed_batch(texts).await?.into_iter().map(Ok).collect()
+            };
+            let mut embedded = Embedded::default();
+            let mut vectors = Vec::with_capacity(texts.len());
+            let mut total_tokens: u64 = 0;
+            for (idx, outcome) in outcomes.into_iter().enumerate() {
+                let response = match outcome {
+                    Ok(response) => response,
+                    Err(e) => {
+                        // Reported as the item's error; the placeholder is never written
+                        embedded.failed.insert(idx, e);
+                        vectors.push(Vec::new());
+                        continue;
+                    }
+                };
+                total_tokens = total_tokens.saturating_add(response.tokens.unwrap_or(0) as u64);
+                req.metadata_list[idx]
+                    .entry(crate::embeddings::EMBEDDING_MODEL_KEY.to_string())
+                    .or_insert_with(|| model(&response));
+                embedded.dimensions.get_or_insert(response.embedding.len());
+                vectors.push(response.embedding);
+            }
+            let embedded_texts = (texts.len() - embedded.failed.len()) as u64;
+            state.record_embedding(&usage, embedded_texts, total_tokens, start.elapsed());
+            req.vectors = Some(vectors);
+            return Ok(embedded);
+        }
+        _ => {}
+    }
+    Ok(Embedded::default())
+}
+
+// Metric for a search against an index built with `index_metric`. No metric means the index's own; a different one is rejected unless the request allows the mismatch, since the index can only pre-select candidates for it.
+pub(crate) fn resolve_metric(requested: Option<String>, index_metric: Metric, allow_mismatch: bool) -> Result<Metric> {
+    let Some(name) = requested else {
+        return Ok(index_metric);
+    };
+    let metric = Metric::parse(&name)
+        .ok_or_else(|| ServerError::InvalidRequest(format!("Unknown metric '{}'", name)))?;
+    if metric != index_metric && !allow_mismatch {
+        return Err(ServerError::InvalidRequest(format!(
+            "Collection index is built for the {} metric but the request asked for {}; set allow_metric_mismatch to re-rank with {}",
+            index_metric.name(), metric.name(), metric.name()
+        )).into());
+    }
+    Ok(metric)
+}
+
+// Most documents one search may leave out
+pub(crate) const MAX_EXCLUDE_IDS: usize = 10_000;
+
+// What a search leaves out: the documents of `ids` (UUIDs or client ids; ones the collection does not
+// hold are skipped, they may have been deleted since the client saw them), and with `filter` the
+// documents matching it (see `json_to_filter`), as a filter matching every other document
+pub(crate) fn resolve_exclusions(
+    storage: &crate::Collection,
+    ids: &[String],
+    filter: Option<HashMap<String, serde_json::Value>>,
+) -> Result<(HashSet<Uuid>, Option<Filter>)> {
+    if ids.len() > MAX_EXCLUDE_IDS {
+        return Err(ServerError::InvalidRequest(format!("exclude_ids holds at most {} ids", MAX_EXCLUDE_IDS)).into());
     }
+    let excluded = ids.iter().filter_map(|id| storage.resolve_id(id)).collect();
+    let filter = filter
+        .map(json_to_filter)
+        .transpose()?
+        .filter(|filter| !filter.is_empty())
+        .map(|filter| Filter::new().not(filter));
+    Ok((excluded, filter))
 }
 
 pub(crate) fn apply_search_overrides(base: crate::config::SearchConfig, req_ef: Option<usize>, req_nprobe: Option<usize>, req_overfetch: Option<usize>, preset: Option<String>) -> crate::config::SearchConfig {
@@ -105,17 +276,27 @@ pub(crate) fn apply_search_overrides(base: crate::config::SearchConfig, req_ef:
 pub async fn insert_vector(
     State(state): State<SharedState>,
     Path(collection): Path<String>,
-    Json(mut req): Json<InsertRequest>,
-) -> Result<Json<InsertResultsResponse>> {
+    headers: HeaderMap,
+    format: Format,
+    Payload(mut req): Payload<InsertRequest>,
+) -> Result<Reply<InsertResultsResponse>> {
     if state.shutting_down.load(Ordering::Relaxed) {
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
     }
     state.ensure_write_allowed()?;
+    state.throttle_writes(&collection).await?;
 
     // Validate inputs
     validation::validate_collection_name(&collection)?;
+    let max_document_bytes = state.app_config.read().limits.max_document_bytes;
+    for text in req.text.iter().chain(req.texts.iter().flatten()) {
+        validation::check_document_size(text, max_document_bytes)?;
+    }
 
     state.get_or_create_collection(&collection)?;
+    // Embed before taking the write lock: the embedder call can take a while
+    let api_key = headers.get("x-api-key").and_then(|v| v.to_str().ok());
+    let Embedded { dimensions: embedded_dims, failed: embed_failures } = embed_missing_vectors(&state, &collection, api_key, &mut req).await?;
     info!(
         collection=%collection,
         single=req.vector.is_some(),
@@ -124,10 +305,25 @@ pub async fn insert_vector(
     );
     
     let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound(super::super::helpers::COLLECTION_NOT_FOUND.to_string()))?;
+        .ok_or(ServerError::CollectionNotFound)?;
     let lock_start = Instant::now();
     let mut storage = storage_ref.write();
     record_lock_write(state.latency_tracker.get(&collection).as_deref(), lock_start);
+
+    // Text embedded above is checked against, or becomes, the collection's recorded embedding model
+    let embedder = state.embedder_for(&collection);
+    let embedded_model = match (embedded_dims, embedder.as_ref()) {
+        (Some(dims), Some(embedder)) => {
+            if !req.allow_model_mismatch {
+                storage.check_embedding_model(embedder.model_name(), Some(dims))?;
+            }
+            Some((embedder.model_name(), dims))
+        }
+        _ => None,
+    };
+    // Every inserted vector counts against the project's quota
+    let adding = req.vectors.as_ref().map_or(usize::from(req.vector.is_some()), Vec::len);
+    state.ensure_project_quota(&collection, storage.count(), adding)?;
     
     let response = match (req.vector.take(), req.vectors.take()) {
         (Some(vector), None) => {
@@ -145,9 +341,25 @@ pub async fn insert_vector(
             
             InsertResultsResponse::Single(InsertResponse { 
                 id: id.to_string(),
+                seq: storage.head_seq(),
                 latency_ms: Some(duration.as_millis() as f32),
             })
         }
+        (None, Some(vectors)) if req.allow_partial => {
+            req.vectors = Some(vectors);
+            let built = build_partial_entries(req, embed_failures)?;
+
+            let start = Instant::now();
+            let outcomes = apply_partial(built, |entries| storage.insert_batch_partial(entries))?;
+            let duration = start.elapsed();
+
+            if let Some(tracker) = state.latency_tracker.get(&collection) {
+                tracker.record_insert(duration);
+            }
+            state.enforce_cache_budget();
+
+            InsertResultsResponse::Partial(partial_response(outcomes, storage.head_seq(), duration))
+        }
         (None, Some(vectors)) => {
             req.vectors = Some(vectors);
             let texts_len = req.texts.as_ref().map(|t| t.len()).unwrap_or(0);
@@ -165,101 +377,142 @@ pub async fn insert_vector(
             InsertResultsResponse::Multi(MultiInsertResponse { 
                 ids: ids.into_iter().map(|id| id.to_string()).collect(),
                 count: texts_len,
+                seq: storage.head_seq(),
                 latency_ms: Some(duration.as_millis() as f32),
             })
         }
         (Some(_), Some(_)) => return Err(ServerError::InvalidRequest("Provide either vector or vectors, not both".to_string()).into()),
+        (None, None) if embedder.is_none() && (req.text.is_some() || req.texts.is_some()) => {
+            return Err(ServerError::ServiceUnavailable(super::super::helpers::EMBEDDING_NOT_CONFIGURED.to_string()).into())
+        }
         (None, None) => return Err(ServerError::InvalidRequest("No vectors provided".to_string()).into()),
     };
+    if let Some((model, dims)) = embedded_model {
+        storage.record_embedding_model(model, dims)?;
+    }
     
-    Ok(Json(response))
+    Ok(format.reply(response))
 }
 
-// GET /api/collections/:collection/vectors/:id - get one vector
+// GET /api/collections/:collection/vectors/:id?min_seq=N - get one vector
 pub async fn get_vector(
     State(state): State<SharedState>,
     Path((collection, id)): Path<(String, String)>,
-) -> Result<Json<VectorResponse>> {
+    Query(params): Query<ReadQuery>,
+    format: Format,
+) -> Result<Reply<VectorResponse>> {
     if state.shutting_down.load(Ordering::Relaxed) {
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
     }
 
     state.get_or_create_collection(&collection)?;
     
-    let uuid = Uuid::parse_str(&id)
-        .map_err(|_| ServerError::InvalidRequest("Invalid UUID".to_string()))?;
-    
+    state.wait_for_seq(&collection, params.min_seq).await?;
     let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound(super::super::helpers::COLLECTION_NOT_FOUND.to_string()))?;
+        .ok_or(ServerError::CollectionNotFound)?;
     let lock_start = Instant::now();
     let storage = storage_ref.read();
     record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
     
-    let entry = storage.get(&uuid)
-        .ok_or(ServerError::NotFound(super::super::helpers::VECTOR_NOT_FOUND.to_string()))?;
+    // The path id is either the document UUID or its client-provided id
+    let entry = storage.resolve_id(&id)
+        .and_then(|uuid| storage.get(&uuid))
+        .ok_or(ServerError::VectorNotFound)?;
     
-    Ok(Json(VectorResponse {
+    Ok(format.reply(VectorResponse {
         id: entry.id.to_string(),
+        external_id: entry.external_id().map(str::to_string),
         vector: entry.get_vector(),
+        version: entry.version(),
+        created_at: entry.created_at(),
+        updated_at: entry.updated_at(),
         text: entry.text,
         metadata: metadata_to_json(&entry.metadata),
     }))
 }
 
-// GET /api/collections/:collection/vectors?limit=100&offset=0 - list vectors
+// GET /api/collections/:collection/vectors?limit=100&offset=0&min_seq=N - list vectors
 pub async fn list_vectors(
     State(state): State<SharedState>,
     Path(collection): Path<String>,
     Query(params): Query<ListVectorsQuery>,
-) -> Result<Json<Vec<VectorResponse>>> {
+    format: Format,
+) -> Result<Reply<Vec<VectorResponse>>> {
     if state.shutting_down.load(Ordering::Relaxed) {
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
     }
 
     state.get_or_create_collection(&collection)?;
     
+    state.wait_for_seq(&collection, params.min_seq).await?;
     let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound(super::super::helpers::COLLECTION_NOT_FOUND.to_string()))?;
+        .ok_or(ServerError::CollectionNotFound)?;
     let lock_start = Instant::now();
     let storage = storage_ref.read();
     record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
     
-    let vectors: Vec<VectorResponse> = storage.get_all()
+    let mut documents = storage.get_all();
+    if let Some(sort) = params.sort {
+        // Ties (same second) are broken by id so pages do not overlap
+        let key = |doc: &Document| match sort {
+            DocumentSort::CreatedAt | DocumentSort::CreatedAtDesc => doc.created_at(),
+            DocumentSort::UpdatedAt | DocumentSort::UpdatedAtDesc => doc.updated_at(),
+        };
+        documents.sort_by(|a, b| key(a).cmp(&key(b)).then(a.id.cmp(&b.id)));
+        if matches!(sort, DocumentSort::CreatedAtDesc | DocumentSort::UpdatedAtDesc) {
+            documents.reverse();
+        }
+    }
+    let vectors: Vec<VectorResponse> = documents
         .into_iter()
         .skip(params.offset)
         .take(params.limit)
         .map(|e| VectorResponse {
             id: e.id.to_string(),
+            external_id: e.external_id().map(str::to_string),
             vector: e.get_vector(),
             text: e.text.clone(),
             metadata: metadata_to_json(&e.metadata),
+            version: e.version(),
+            created_at: e.created_at(),
+            updated_at: e.updated_at(),
         })
         .collect();
     
-    Ok(Json(vectors))
+    Ok(format.reply(vectors))
 }
 
 // DELETE /api/collections/:collection/vectors/:id - delete a vector
 pub async fn delete_vector(
     State(state): State<SharedState>,
     Path((collection, id)): Path<(String, String)>,
+    Query(params): Query<DeleteVectorQuery>,
 ) -> Result<Json<DeleteResultsResponse>> {
     if state.shutting_down.load(Ordering::Relaxed) {
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
     }
     state.ensure_write_allowed()?;
+    state.throttle_writes(&collection).await?;
 
     state.get_or_create_collection(&collection)?;
     
-    let uuid = Uuid::parse_str(&id)
-        .map_err(|_| ServerError::InvalidRequest("Invalid UUID".to_string()))?;
-    
     let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound(super::super::helpers::COLLECTION_NOT_FOUND.to_string()))?;
+        .ok_or(ServerError::CollectionNotFound)?;
     let mut storage = storage_ref.write();
     
     let start = Instant::now();
-    let deleted = storage.delete(&uuid)?;
+    let uuid = storage.resolve_id(&id);
+    if let Some(expected) = params.if_version {
+        match uuid {
+            Some(uuid) => storage.ensure_version(&uuid, Some(expected))?,
+            None if expected != 0 => return Err(ServerError::Conflict(format!("document {id} is at version 0, not {expected}")).into()),
+            None => {}
+        }
+    }
+    let deleted = match uuid {
+        Some(uuid) => storage.delete(&uuid)?,
+        None => false,
+    };
     let duration = start.elapsed();
     
     // Record latency
@@ -269,6 +522,7 @@ pub async fn delete_vector(
     
     Ok(Json(DeleteResultsResponse::Single(DeleteResponse { 
         deleted,
+        seq: Some(storage.head_seq()),
         latency_ms: Some(duration.as_millis() as f32),
     })))
 }
@@ -283,6 +537,7 @@ pub async fn delete_vectors(
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
     }
     state.ensure_write_allowed()?;
+    state.throttle_writes(&collection).await?;
 
     validation::validate_collection_name(&collection)?;
     validation::validate_batch_size(req.ids.len(), MAX_BATCH_SIZE, "Delete")?;
@@ -290,18 +545,36 @@ pub async fn delete_vectors(
     state.get_or_create_collection(&collection)?;
 
     let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound(super::super::helpers::COLLECTION_NOT_FOUND.to_string()))?;
+        .ok_or(ServerError::CollectionNotFound)?;
     let lock_start = Instant::now();
     let mut storage = storage_ref.write();
     record_lock_write(state.latency_tracker.get(&collection).as_deref(), lock_start);
 
-    let mut uuids = Vec::with_capacity(req.ids.len());
-    for id_str in &req.ids {
-        let uuid = Uuid::parse_str(id_str)
-            .map_err(|_| ServerError::InvalidRequest(format!("Invalid UUID: {}", id_str)))?;
-        uuids.push(uuid);
+    if req.allow_partial {
+        // Each id is reported: unknown ones, and repeats of an id already in the batch, are errors
+        let mut seen = std::collections::HashSet::new();
+        let outcomes: Vec<Result<Uuid>> = req.ids.iter().map(|id| {
+            let uuid = storage.resolve_id(id)
+                .ok_or_else(|| ServerError::NotFound(format!("Document '{}' not found", id)))?;
+            if !seen.insert(uuid) {
+                return Err(ServerError::InvalidRequest(format!("Duplicate id '{}' in batch", id)).into());
+            }
+            Ok(uuid)
+        }).collect();
+        let uuids: Vec<Uuid> = outcomes.iter().filter_map(|outcome| outcome.as_ref().ok().copied()).collect();
+
+        let start = Instant::now();
+        storage.delete_batch(&uuids)?;
+        let duration = start.elapsed();
+        if let Some(tracker) = state.latency_tracker.get(&collection) {
+            tracker.record_delete(duration);
+        }
+        return Ok(Json(DeleteResultsResponse::Partial(partial_response(outcomes, storage.head_seq(), duration))));
     }
 
+    // Ids may be UUIDs or client-provided ids; unknown ones are simply not deleted
+    let uuids: Vec<Uuid> = req.ids.iter().filter_map(|id| storage.resolve_id(id)).collect();
+
     let start = Instant::now();
     let deleted_count = storage.delete_batch(&uuids)?;
     let duration = start.elapsed();
@@ -312,6 +585,7 @@ pub async fn delete_vectors(
 
     Ok(Json(DeleteResultsResponse::Multi(MultiDeleteResponse { 
         deleted_count,
+        seq: storage.head_seq(),
         latency_ms: Some(duration.as_millis() as f32),
     })))
 }
@@ -321,8 +595,10 @@ pub async fn search_vectors(
     State(state): State<SharedState>,
     Path(collection): Path<String>,
     Extension(request_id): Extension<crate::server::request_id::RequestId>,
-    Json(req): Json<SearchRequest>,
-) -> Result<Json<SearchResultsResponse>> {
+    format: Format,
+    Streamed(streamed): Streamed,
+    Payload(mut req): Payload<SearchRequest>,
+) -> Result<Response> {
 
     // 1. Check if server is shutting down and reject new search requests if so, to allow for graceful shutdown without accepting new work.
     if state.shutting_down.load(Ordering::Relaxed) {
@@ -333,16 +609,64 @@ pub async fn search_vectors(
     validation::validate_collection_name(&collection)?;
 
     state.get_or_create_collection(&collection)?;
+    // Read-your-writes: hold the search until the collection has the client's last write
+    state.wait_for_seq(&collection, req.min_seq).await?;
     
     // 3. Acquire a read lock on the collection's storage to ensure thread-safe access while performing the search operation, and record the time taken to acquire the lock for latency tracking purposes.
     let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound(super::super::helpers::COLLECTION_NOT_FOUND.to_string()))?;
+        .ok_or(ServerError::CollectionNotFound)?;
+    // Query by id: client ids live on the collection (not its replicas), so resolve before taking the search lock
+    let query_id = match req.id.as_deref() {
+        Some(id) => Some(storage_ref.read().resolve_id(id)
+            .ok_or(ServerError::VectorNotFound)?),
+        None => None,
+    };
+    // Taken before the exclusions consume the request's filter
+    let logged = state.query_log.enabled().then(|| QueryOptions::of_search(&req));
+    let model = logged.as_ref().and_then(|_| recorded_model(&storage_ref.read()));
+    let (mut excluded, exclude_filter) = resolve_exclusions(&storage_ref.read(), &req.exclude_ids, req.exclude_filter.take())?;
+    let replicas = state.replicas_for(&collection);
     let lock_start = Instant::now();
-    let storage = storage_ref.read();
+    // Hot collections are searched on one of their read replicas instead of under the collection lock
+    let storage = SearchGuard::acquire(&storage_ref, replicas.as_deref());
+    let lock_wait = lock_start.elapsed();
     record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
     // 4. Parse the similarity metric and apply any search configuration overrides based on the request parameters, such as ef, nprobe, overfetch, or preset, to determine the effective search configuration that will be used for the search operation.
-    let SearchRequest { vector, vectors, k, metric, ef, nprobe, overfetch, preset } = req;
-    let metric = parse_metric(metric);
+    let SearchRequest { vector, vectors, k, metric, ef, nprobe, overfetch, preset, dedup_by, score_expr, order_by, score_bands, allow_metric_mismatch, target_ms, explain, timeout_ms, on_timeout, .. } = req;
+    if let Some(timeout_ms) = timeout_ms {
+        if timeout_ms == 0 {
+            return Err(ServerError::InvalidRequest("timeout_ms must be positive".to_string()).into());
+        }
+        if vectors.is_some() || target_ms.is_some() {
+            return Err(ServerError::InvalidRequest("timeout_ms applies to single-vector searches without target_ms".to_string()).into());
+        }
+    }
+    if let Some(target_ms) = target_ms {
+        if !(target_ms.is_finite() && target_ms > 0.0) {
+            return Err(ServerError::InvalidRequest("target_ms must be a positive number".to_string()).into());
+        }
+        if vectors.is_some() {
+            return Err(ServerError::InvalidRequest("target_ms applies to single-vector searches".to_string()).into());
+        }
+    }
+    // The stored document's vector stands in for the query, and the document is left out of the hits
+    let vector = match query_id {
+        Some(_) if vector.is_some() || vectors.is_some() => {
+            return Err(ServerError::InvalidRequest("Provide one of vector, vectors or id".to_string()).into());
+        }
+        Some(uuid) => Some(storage.document(&uuid)
+            .ok_or(ServerError::VectorNotFound)?.1),
+        None => vector,
+    };
+    excluded.extend(query_id);
+    let score_expr = score_expr.as_deref().map(crate::search::ScoreExpr::parse).transpose()?;
+    if let Some(order) = &order_by {
+        order.validate()?;
+    }
+    if let Some(bands) = &score_bands {
+        bands.validate()?;
+    }
+    let metric = resolve_metric(metric, storage.vector_index().metric(), allow_metric_mismatch)?;
     let effective_search = apply_search_overrides(
         storage.config().search,
         ef,
@@ -355,19 +679,98 @@ pub async fn search_vectors(
         (Some(vec), None) => {
             // 1. Validate the search vector to ensure it meets the required format and constraints before performing the search operation.
             validation::validate_vector(&vec)?;
+            validation::check_query_dimensions(&vec, storage.dimensions())?;
+            validation::check_vector(&vec, &storage.config().validation, metric)?;
             let start = Instant::now();
-            // 2. Start storage search with the provided search vector, k, metric, and effective search configuration. The search method will return a list of results that are similar to the search vector based on the specified metric and search configuration.
-            let results = storage.search(
-                &vec,
+            // Repeated queries are answered from the query cache while the collection is unchanged. Latency-target searches
+            // bypass it: their parameters change from one query to the next.
+            // Searches leaving out a filter's matches are not cached either: the key would need the filter
+            let cache_key = (state.query_cache.enabled() && target_ms.is_none() && exclude_filter.is_none()).then(|| {
+                let mut excluded: Vec<&Uuid> = excluded.iter().collect();
+                excluded.sort_unstable();
+                let options = (metric, effective_search, storage.config().execution, dedup_by.as_deref(), score_expr.as_ref().map(|e| e.source()), order_by.as_ref(), excluded);
+                (state.query_cache.key(&collection, &vec, k, options), storage.seq())
+            });
+            let owned_search = || DeadlineSearch {
+                collection: storage_ref.value().clone(),
+                replicas: replicas.clone(),
+                query: vec.clone(),
                 k,
                 metric,
-                crate::SearchParams {
-                    mode: storage.config().execution,
-                    filter: None,
-                    filter_overfetch_override: overfetch,
-                    search_config_override: Some(effective_search),
-                },
-            );
+                mode: storage.config().execution,
+                search: effective_search,
+                filter: exclude_filter.clone(),
+                overfetch,
+                dedup_by: dedup_by.clone(),
+                score_expr: score_expr.clone(),
+                order_by: order_by.clone(),
+                exclude_ids: excluded.clone(),
+            };
+            let searched = match cache_key.as_ref().and_then(|(key, seq)| Some((key, state.query_cache.lookup(key, *seq)?))) {
+                // Results a few writes behind are served stale while one search recomputes them
+                Some((key, cached)) => {
+                    if cached.revalidate {
+                        owned_search().revalidate(&state, key.clone());
+                    }
+                    Searched::Cached { results: cached.results, stale: cached.lag > 0 }
+                }
+                None => {
+                    // 2. Start storage search with the provided search vector, k, metric, and effective search configuration. The search method will return a list of results that are similar to the search vector based on the specified metric and search configuration.
+                    let params = crate::SearchParams {
+                        mode: storage.config().execution,
+                        filter: exclude_filter.as_ref(),
+                        filter_overfetch_override: overfetch,
+                        search_config_override: Some(effective_search),
+                        dedup_by: dedup_by.as_deref(),
+                        score_expr: score_expr.as_ref(),
+                        order_by: order_by.as_ref(),
+                        exclude_ids: Some(&excluded),
+                    };
+                    // With a latency target the engine picks ef/nprobe from the collection's recent latencies
+                    match (target_ms, timeout_ms) {
+                        (Some(target_ms), _) => {
+                            let (results, effective) = crate::search::search_target_within(&*storage, &vec, k, metric, params, target_ms);
+                            Searched::Hits { results, effective: Some(effective), partial: false }
+                        }
+                        // Past a timeout the quick round's hits or the cache's last results may stand in
+                        (None, Some(timeout_ms)) => {
+                            match owned_search().run(&state, cache_key.clone(), Duration::from_millis(timeout_ms), on_timeout)? {
+                                Deadlined::Done(results) => Searched::Hits { results, effective: None, partial: false },
+                                Deadlined::Partial(results) => Searched::Hits { results, effective: None, partial: true },
+                                Deadlined::Cached { results, stale } => Searched::Cached { results, stale },
+                            }
+                        }
+                        (None, None) => Searched::Hits { results: crate::search::search_target(&*storage, &vec, k, metric, params), effective: None, partial: false },
+                    }
+                }
+            };
+            let (results, effective, partial) = match searched {
+                Searched::Hits { results, effective, partial } => (results, effective, partial),
+                Searched::Cached { results, stale } => {
+                    let results = rerank_hits(&state, &collection, order_by.as_ref(), results);
+                    let (results, bands) = band_hits(score_bands.as_ref(), results);
+                    let query_id = logged.and_then(|options| state.query_log.record(&collection, LoggedQuery {
+                        kind: QueryKind::Search,
+                        model,
+                        vector: Some(vec.clone()),
+                        k,
+                        metric,
+                        options,
+                        results: LoggedHit::of(&results),
+                        ..Default::default()
+                    }));
+                    return Ok(search_reply(format, streamed, SearchResultsResponse::Single(SearchResponse {
+                        results,
+                        latency_ms: Some(start.elapsed().as_millis() as f32),
+                        effective: None,
+                        explain: explain.then(|| crate::search::explain(&*storage, k, storage.config().execution)),
+                        bands,
+                        query_id,
+                        partial: false,
+                        stale,
+                    })));
+                }
+            };
             // 3. If the search is a range search (indicated by the presence of min_score), filter the search results to include only those that meet the minimum score threshold, ensuring that the final results returned to the client are relevant based on the specified criteria.
             let duration = start.elapsed();
             if duration.as_millis() > state.slow_query_ms {
@@ -377,6 +780,21 @@ pub async fn search_vectors(
                     elapsed_ms = duration.as_millis(),
                     "slow_search"
                 );
+                state.slow_queries.record(SlowQuery {
+                    collection: collection.clone(),
+                    request_id: request_id.0.clone(),
+                    kind: SlowQueryKind::Search,
+                    vectors: vec![vec.clone()],
+                    k,
+                    metric,
+                    search: effective_search,
+                    target_ms,
+                    dedup_by: dedup_by.clone(),
+                    score_expr: score_expr.as_ref().map(|e| e.source().to_string()),
+                    order_by: order_by.clone(),
+                    latency: SlowQueryLatency::new(None, lock_wait, duration),
+                    ..Default::default()
+                });
             }
             
             // 4. Record the latency of the search operation using the latency tracker associated with the collection, if available, to monitor and analyze search performance over time.
@@ -391,34 +809,69 @@ pub async fn search_vectors(
                 .into_iter()
                 .map(|r| HitResponse {
                     id: r.id.to_string(),
+                    external_id: crate::storage::external_id_of(&r.metadata).map(str::to_string),
                     score: r.score,
                     text: r.text,
                     metadata: metadata_to_json(&r.metadata),
+                    band: None,
+                    rerank_score: None,
                 })
                 .collect();
+            // The quick round's hits are not the query's results
+            if let Some((key, seq)) = cache_key.filter(|_| !partial) {
+                state.query_cache.insert(key, seq, &search_results);
+            }
+            // Re-ranked and banded after caching: feedback moves on without the collection changing, and the
+            // cached hits serve requests with other bands too
+            let search_results = rerank_hits(&state, &collection, order_by.as_ref(), search_results);
+            let (search_results, bands) = band_hits(score_bands.as_ref(), search_results);
+            let query_id = logged.and_then(|options| state.query_log.record(&collection, LoggedQuery {
+                kind: QueryKind::Search,
+                model,
+                vector: Some(vec.clone()),
+                k,
+                metric,
+                options,
+                results: LoggedHit::of(&search_results),
+                ..Default::default()
+            }));
             
             // 6. Return the search results in a structured response format, including the list of hits and the latency of the search operation, to provide the client with the relevant information about the search results and the performance of the search.
             SearchResultsResponse::Single(SearchResponse { 
                 results: search_results,
                 latency_ms: Some(duration.as_millis() as f32),
+                effective,
+                explain: explain.then(|| crate::search::explain(&*storage, k, storage.config().execution)),
+                bands,
+                query_id,
+                partial,
+                stale: false,
             })
         }
         (None, Some(queries)) => {
             // 1. Validate the batch of search vectors to ensure they meet the required format and constraints before performing the batch search operation.
             validation::validate_batch_size(queries.len(), MAX_BATCH_SIZE, "Search")?;
             validation::validate_vectors(&queries)?;
+            for query in &queries {
+                validation::check_query_dimensions(query, storage.dimensions())?;
+                validation::check_vector(query, &storage.config().validation, metric)?;
+            }
 
             // 2. Start batch search with the provided search vectors, k, metric, and effective search configuration. The batch search method will return a list of results for each search vector, where each result is a list of hits that are similar to the corresponding search vector based on the specified metric and search configuration.
 
             let start = Instant::now();
             let params = crate::SearchParams {
                 mode: storage.config().execution,
-                filter: None,
+                filter: exclude_filter.as_ref(),
                 filter_overfetch_override: overfetch,
                 search_config_override: Some(effective_search),
+                dedup_by: dedup_by.as_deref(),
+                score_expr: score_expr.as_ref(),
+                order_by: order_by.as_ref(),
+                exclude_ids: Some(&excluded),
             };
-            let batch_results = crate::search::search_batch_collection(
-                &storage,
+            let batch_results = crate::search::search_batch_target(
+                &*storage,
                 &queries,
                 k,
                 metric,
@@ -433,6 +886,20 @@ pub async fn search_vectors(
                     elapsed_ms = duration.as_millis(),
                     "slow_batch_search"
                 );
+                state.slow_queries.record(SlowQuery {
+                    collection: collection.clone(),
+                    request_id: request_id.0.clone(),
+                    kind: SlowQueryKind::BatchSearch,
+                    vectors: queries.clone(),
+                    k,
+                    metric,
+                    search: effective_search,
+                    dedup_by: dedup_by.clone(),
+                    score_expr: score_expr.as_ref().map(|e| e.source().to_string()),
+                    order_by: order_by.clone(),
+                    latency: SlowQueryLatency::new(None, lock_wait, duration),
+                    ..Default::default()
+                });
             }
 
             if let Some(tracker) = state.latency_tracker.get(&collection) {
@@ -448,9 +915,12 @@ pub async fn search_vectors(
                         .into_iter()
                         .map(|r| HitResponse {
                             id: r.id.to_string(),
+                            external_id: crate::storage::external_id_of(&r.metadata).map(str::to_string),
                             score: r.score,
                             text: r.text,
                             metadata: metadata_to_json(&r.metadata),
+                            band: None,
+                            rerank_score: None,
                         })
                         .collect()
                 })
@@ -458,69 +928,161 @@ pub async fn search_vectors(
 
             // 5. Return the batch search results in a structured response format, where each entry corresponds to the results for a specific search vector, along with the latency of the batch search operation, to provide the client with comprehensive information about the batch search results and the performance of the batch search.
 
+            let (response_results, bands): (Vec<_>, Vec<_>) =
+                response_results.into_iter().map(|hits| band_hits(score_bands.as_ref(), rerank_hits(&state, &collection, order_by.as_ref(), hits))).unzip();
+            // One log entry per query, each with its own feedback id
+            let query_ids = logged.and_then(|options| {
+                queries.iter().zip(&response_results).map(|(query, hits)| state.query_log.record(&collection, LoggedQuery {
+                    kind: QueryKind::BatchSearch,
+                    model: model.clone(),
+                    vector: Some(query.clone()),
+                    k,
+                    metric,
+                    options: options.clone(),
+                    results: LoggedHit::of(hits),
+                    ..Default::default()
+                })).collect()
+            });
             SearchResultsResponse::Multi(MultiSearchResponse { 
                 results: response_results,
                 latency_ms: Some(duration.as_millis() as f32),
+                bands: bands.into_iter().collect(),
+                query_ids,
             })
         }
         (Some(_), Some(_)) => {
             return Err(ServerError::InvalidRequest("Provide either vector or vectors, not both".to_string()).into());
         }
         (None, None) => {
-            return Err(ServerError::InvalidRequest("No search vector(s) provided".to_string()).into());
+            return Err(ServerError::InvalidRequest("No search vector(s) or id provided".to_string()).into());
         }
     };
     
-    Ok(Json(response))
+    Ok(search_reply(format, streamed, response))
 }
 
-// POST /api/collections/:collection/upsert - insert or update a vector
+// Reorder hits with the collection's relevance feedback when it is re-ranked (and no field order
+// sorts them instead); see crate::server::feedback
+pub(crate) fn rerank_hits(state: &SharedState, collection: &str, order_by: Option<&OrderBy>, hits: Vec<HitResponse>) -> Vec<HitResponse> {
+    let config = state.app_config.read();
+    if !config.rerank.enabled_for(collection) || order_by.is_some_and(|order| !order.tie_break) {
+        return hits;
+    }
+    state.feedback.rerank(collection, hits, &config.rerank)
+}
+
+// Bucket hits into the request's score bands; see crate::search::ScoreBands
+pub(crate) fn band_hits(bands: Option<&ScoreBands>, hits: Vec<HitResponse>) -> (Vec<HitResponse>, Option<Vec<BandCount>>) {
+    let Some(bands) = bands else { return (hits, None) };
+    let (banded, counts) = bands.apply(hits, |hit| hit.score);
+    let hits = banded
+        .into_iter()
+        .map(|(mut hit, band)| {
+            hit.band = Some(bands.bands[band].name.clone());
+            hit
+        })
+        .collect();
+    (hits, Some(counts))
+}
+
+// NDJSON lines when the client asked for a stream, otherwise one document in the negotiated format
+fn search_reply(format: Format, streamed: bool, response: SearchResultsResponse) -> Response {
+    if streamed {
+        ndjson::search_stream(response)
+    } else {
+        format.reply(response).into_response()
+    }
+}
+
+// Resolve an upsert item to the document it writes, and whether that document exists already
+fn build_upsert_entry(storage: &crate::Collection, item: UpsertItem, normalize: bool) -> Result<(Document, bool)> {
+    validation::validate_text(&item.text)?;
+    validation::validate_vector(&item.vector)?;
+    let vector = if normalize { validation::normalize_vector(&item.vector) } else { item.vector };
+
+    // `id` is either a UUID or a client-provided id; a client id may also come in `external_id`
+    let mut external_id = item.external_id;
+    let mut uuid = None;
+    if let Some(id_str) = item.id {
+        match Uuid::parse_str(&id_str) {
+            Ok(parsed) => uuid = Some(parsed),
+            Err(_) => {
+                if external_id.as_ref().is_some_and(|ext| *ext != id_str) {
+                    return Err(ServerError::InvalidRequest("id and external_id refer to different documents".to_string()).into());
+                }
+                external_id = Some(id_str);
+            }
+        }
+    }
+    if let Some(ext) = &external_id {
+        validation::validate_external_id(ext)?;
+    }
+
+    // Check if entry exists
+    let resolved = external_id.as_deref().and_then(|ext| storage.resolve_id(ext));
+    let id = uuid.or(resolved).unwrap_or_else(Uuid::new_v4);
+    let exists = storage.get(&id).is_some() || resolved.is_some();
+
+    let mut entry = Document::with_metadata(vector, item.text, client_metadata(item.metadata)?);
+    entry.id = id;
+    if let Some(ext) = external_id {
+        entry = entry.with_external_id(ext);
+    }
+    Ok((entry, exists))
+}
+
+// POST /api/collections/:collection/upsert - insert or update a vector, or a batch of them as `items`
 pub async fn upsert_vector(
     State(state): State<SharedState>,
     Path(collection): Path<String>,
-    Json(mut req): Json<UpsertRequest>,
-) -> Result<Json<UpsertResponse>> {
+    format: Format,
+    Payload(req): Payload<UpsertRequest>,
+) -> Result<Reply<UpsertResultsResponse>> {
     if state.shutting_down.load(Ordering::Relaxed) {
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
     }
     state.ensure_write_allowed()?;
-    state.ensure_write_allowed()?;
+    state.throttle_writes(&collection).await?;
 
     // Validate inputs
     validation::validate_collection_name(&collection)?;
-    validation::validate_text(&req.text)?;
-    validation::validate_vector(&req.vector)?;
-    
-    // Normalize if requested
-    if req.normalize {
-        req.vector = validation::normalize_vector(&req.vector);
+    if req.if_version.is_some() && req.items.is_some() {
+        return Err(ServerError::InvalidRequest("if_version applies to a single upsert".to_string()).into());
+    }
+    let max_document_bytes = state.app_config.read().limits.max_document_bytes;
+    for text in req.text.iter().chain(req.items.iter().flatten().map(|item| &item.text)) {
+        validation::check_document_size(text, max_document_bytes)?;
     }
+    let single = match (req.vector, req.text, req.items) {
+        (Some(vector), Some(text), None) => Some(UpsertItem { id: req.id, external_id: req.external_id, vector, text, metadata: req.metadata }),
+        (None, None, Some(items)) => {
+            validation::validate_batch_size(items.len(), MAX_BATCH_SIZE, "Upsert")?;
+            return upsert_batch(&state, &collection, items, req.normalize, req.allow_partial, req.skip_unchanged).map(|r| format.reply(r));
+        }
+        (_, _, Some(_)) => return Err(ServerError::InvalidRequest("Provide either vector and text, or items, not both".to_string()).into()),
+        _ => None,
+    };
+    let item = single.ok_or_else(|| ServerError::InvalidRequest("vector and text are required for single upsert".to_string()))?;
 
     state.get_or_create_collection(&collection)?;
     
     let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound(super::super::helpers::COLLECTION_NOT_FOUND.to_string()))?;
+        .ok_or(ServerError::CollectionNotFound)?;
     let lock_start = Instant::now();
     let mut storage = storage_ref.write();
     record_lock_write(state.latency_tracker.get(&collection).as_deref(), lock_start);
     
-    // Check if entry exists
-    let id = if let Some(id_str) = req.id {
-        Uuid::parse_str(&id_str)
-            .map_err(|_| ServerError::InvalidRequest("Invalid UUID".to_string()))?
-    } else {
-        Uuid::new_v4()
-    };
-    
-    let exists = storage.get(&id).is_some();
-    
-    let metadata = json_to_metadata(req.metadata);
-    let mut entry = Document::with_metadata(req.vector, req.text, metadata);
-    entry.id = id;
+    let (entry, exists) = build_upsert_entry(&storage, item, req.normalize)?;
+    // Checked under the write lock, so no other write can land between the check and this one
+    storage.ensure_version(&entry.id, req.if_version)?;
+    if !exists {
+        state.ensure_project_quota(&collection, storage.count(), 1)?;
+    }
     
     let start = Instant::now();
-    storage.upsert(entry)?;
+    let (id, changed) = if req.skip_unchanged { storage.upsert_if_changed(entry)? } else { (storage.upsert(entry)?, true) };
     let duration = start.elapsed();
+    let version = storage.get(&id).map_or(1, |doc| doc.version());
     
     // Record latency (treat as insert or update)
     if let Some(tracker) = state.latency_tracker.get(&collection) {
@@ -535,12 +1097,205 @@ pub async fn upsert_vector(
         collection=%collection,
         id=%id,
         created=!exists,
+        changed,
         "upsert_request"
     );
     
-    Ok(Json(UpsertResponse { 
+    Ok(format.reply(UpsertResultsResponse::Single(UpsertResponse { 
         id: id.to_string(),
         created: !exists,
+        changed,
+        version,
+        seq: storage.head_seq(),
+        latency_ms: Some(duration.as_millis() as f32),
+    })))
+}
+
+// Items are applied in order under one write lock. Without allow_partial the first failing item
+// fails the request, and the items before it stay written.
+fn upsert_batch(state: &SharedState, collection: &str, items: Vec<UpsertItem>, normalize: bool, allow_partial: bool, skip_unchanged: bool) -> Result<UpsertResultsResponse> {
+    state.get_or_create_collection(collection)?;
+    let storage_ref = state.collections.get(collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let lock_start = Instant::now();
+    let mut storage = storage_ref.write();
+    record_lock_write(state.latency_tracker.get(collection).as_deref(), lock_start);
+
+    let start = Instant::now();
+    let count = items.len();
+    // Entries are built up front so the documents the batch creates can be checked against the project's quota at once
+    let built: Vec<Result<(Document, bool)>> = items.into_iter().map(|item| build_upsert_entry(&storage, item, normalize)).collect();
+    let creating = built.iter().filter(|item| matches!(item, Ok((_, false)))).count();
+    state.ensure_project_quota(collection, storage.count(), creating)?;
+    let response = if allow_partial {
+        let built: Vec<Result<Document>> = built.into_iter().map(|item| item.map(|(entry, _)| entry)).collect();
+        // Outcomes come back for the built items in order; their changed flags are put next to them below
+        let applied: Vec<usize> = built.iter().enumerate().filter(|(_, item)| item.is_ok()).map(|(index, _)| index).collect();
+        let mut changed = Vec::new();
+        let outcomes = apply_partial(built, |entries| {
+            let outcomes = storage.upsert_batch_partial_changed(entries, skip_unchanged)?;
+            changed = outcomes.iter().map(|outcome| outcome.as_ref().ok().map(|(_, changed)| *changed)).collect();
+            Ok(outcomes.into_iter().map(|outcome| outcome.map(|(id, _)| id)).collect())
+        })?;
+        let mut response = partial_response(outcomes, storage.head_seq(), start.elapsed());
+        if skip_unchanged {
+            for (index, changed) in applied.into_iter().zip(changed) {
+                response.results[index].changed = changed;
+            }
+        }
+        UpsertResultsResponse::Partial(response)
+    } else {
+        let mut ids = Vec::with_capacity(count);
+        let (mut created, mut unchanged) = (0, 0);
+        for (index, item) in built.into_iter().enumerate() {
+            let (entry, exists) = item.map_err(|e| item_error(index, e))?;
+            let (id, changed) = if skip_unchanged {
+                storage.upsert_if_changed(entry).map_err(|e| item_error(index, e))?
+            } else {
+                (storage.upsert(entry).map_err(|e| item_error(index, e))?, true)
+            };
+            ids.push(id.to_string());
+            created += usize::from(!exists);
+            unchanged += usize::from(!changed);
+        }
+        UpsertResultsResponse::Multi(MultiUpsertResponse { ids, created, unchanged, seq: storage.head_seq(), latency_ms: Some(start.elapsed().as_millis() as f32) })
+    };
+    let duration = start.elapsed();
+    if let Some(tracker) = state.latency_tracker.get(collection) {
+        tracker.record_update(duration);
+    }
+    state.enforce_cache_budget();
+    info!(collection=%collection, items=count, partial=allow_partial, skip_unchanged, "upsert_batch_request");
+    Ok(response)
+}
+
+// A request error of one batch item, with its position added to the message
+fn item_error(index: usize, e: crate::error::PiramidError) -> crate::error::PiramidError {
+    match e {
+        crate::error::PiramidError::Server(ServerError::InvalidRequest(msg)) => ServerError::InvalidRequest(format!("item {}: {}", index, msg)).into(),
+        crate::error::PiramidError::Server(ServerError::ValidationFailed(msg)) => ServerError::ValidationFailed(format!("item {}: {}", index, msg)).into(),
+        crate::error::PiramidError::Server(ServerError::DimensionMismatch(msg)) => ServerError::DimensionMismatch(format!("item {}: {}", index, msg)).into(),
+        crate::error::PiramidError::Server(ServerError::AlreadyExists(msg)) => ServerError::AlreadyExists(format!("item {}: {}", index, msg)).into(),
+        other => other,
+    }
+}
+
+// Run `apply` on the items that were built and put its outcomes back at their positions; items
+// that could not be built keep their own error
+fn apply_partial(
+    built: Vec<Result<Document>>,
+    apply: impl FnOnce(Vec<Document>) -> Result<Vec<Result<Uuid>>>,
+) -> Result<Vec<Result<Uuid>>> {
+    let mut outcomes = Vec::with_capacity(built.len());
+    let mut positions = Vec::new();
+    let mut entries = Vec::new();
+    for (index, item) in built.into_iter().enumerate() {
+        match item {
+            Ok(entry) => {
+                positions.push(index);
+                entries.push(entry);
+                outcomes.push(Ok(Uuid::nil()));
+            }
+            Err(e) => outcomes.push(Err(e)),
+        }
+    }
+    if !entries.is_empty() {
+        for (index, outcome) in positions.into_iter().zip(apply(entries)?) {
+            outcomes[index] = outcome;
+        }
+    }
+    Ok(outcomes)
+}
+
+// Per-item results of a partial batch, in request order
+fn partial_response(outcomes: Vec<Result<Uuid>>, seq: u64, duration: std::time::Duration) -> PartialBatchResponse {
+    let results: Vec<BatchItemResult> = outcomes
+        .into_iter()
+        .enumerate()
+        .map(|(index, outcome)| match outcome {
+            Ok(id) => BatchItemResult {
+                index, status: ItemStatus::Ok, id: Some(id.to_string()), error: None, code: None, error_code: None, retryable: None, changed: None,
+            },
+            Err(e) => {
+                let code = e.status_code().as_u16();
+                let error_code = e.error_code();
+                // Server errors read as they would on their own response
+                let error = match e {
+                    crate::error::PiramidError::Server(e) => e.to_string(),
+                    other => other.to_string(),
+                };
+                BatchItemResult {
+                    index,
+                    status: ItemStatus::Error,
+                    id: None,
+                    error: Some(error),
+                    code: Some(code),
+                    error_code: Some(error_code),
+                    retryable: Some(error_code.retryable()),
+                    changed: None,
+                }
+            }
+        })
+        .collect();
+    let failed = results.iter().filter(|r| r.status == ItemStatus::Error).count();
+    PartialBatchResponse {
+        succeeded: results.len() - failed,
+        failed,
+        results,
+        seq,
+        latency_ms: Some(duration.as_millis() as f32),
+    }
+}
+
+// PATCH /api/collections/:collection/vectors/:id/metadata - replace a vector's metadata
+pub async fn update_vector_metadata(
+    State(state): State<SharedState>,
+    Path((collection, id)): Path<(String, String)>,
+    Json(req): Json<UpdateMetadataRequest>,
+) -> Result<Json<UpdateMetadataResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    state.ensure_write_allowed()?;
+    state.throttle_writes(&collection).await?;
+
+    state.get_or_create_collection(&collection)?;
+
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    // Metadata-only updates leave the vector index alone, so a read lock is enough and searches keep running
+    let lock_start = Instant::now();
+    let storage = storage_ref.read();
+    record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+
+    let start = Instant::now();
+    let version = match storage.resolve_id(&id) {
+        Some(uuid) => {
+            let mut metadata = client_metadata(req.metadata)?;
+            if let Some(external_id) = req.external_id {
+                validation::validate_external_id(&external_id)?;
+                metadata.insert(crate::storage::EXTERNAL_ID_KEY.to_string(), crate::MetadataValue::String(external_id));
+            }
+            storage.update_metadata_if(&uuid, metadata, req.if_version)?
+        }
+        None => match req.if_version {
+            Some(expected) if expected != 0 => {
+                return Err(ServerError::Conflict(format!("document {id} is at version 0, not {expected}")).into());
+            }
+            _ => None,
+        },
+    };
+    let updated = version.is_some();
+    let duration = start.elapsed();
+
+    if let Some(tracker) = state.latency_tracker.get(&collection) {
+        tracker.record_update(duration);
+    }
+
+    Ok(Json(UpdateMetadataResponse {
+        updated,
+        version,
+        seq: storage.head_seq(),
         latency_ms: Some(duration.as_millis() as f32),
     }))
 }
@@ -550,24 +1305,39 @@ pub async fn range_search_vectors(
     State(state): State<SharedState>,
     Path(collection): Path<String>,
     Extension(request_id): Extension<crate::server::request_id::RequestId>,
-    Json(req): Json<RangeSearchRequest>,
-) -> Result<Json<SearchResponse>> {
+    format: Format,
+    Payload(req): Payload<RangeSearchRequest>,
+) -> Result<Reply<SearchResponse>> {
     if state.shutting_down.load(Ordering::Relaxed) {
         return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
     }
 
     validation::validate_collection_name(&collection)?;
     validation::validate_vector(&req.vector)?;
+    if let Some(order) = &req.order_by {
+        order.validate()?;
+    }
+    if let Some(bands) = &req.score_bands {
+        bands.validate()?;
+    }
 
     state.get_or_create_collection(&collection)?;
 
+    state.wait_for_seq(&collection, req.min_seq).await?;
     let storage_ref = state.collections.get(&collection)
-        .ok_or_else(|| ServerError::NotFound(super::super::helpers::COLLECTION_NOT_FOUND.to_string()))?;
+        .ok_or(ServerError::CollectionNotFound)?;
+    let logged = state.query_log.enabled().then(|| QueryOptions::of_range_search(&req));
+    let model = logged.as_ref().and_then(|_| recorded_model(&storage_ref.read()));
+    let replicas = state.replicas_for(&collection);
     let lock_start = Instant::now();
-    let storage = storage_ref.read();
+    // Hot collections are searched on one of their read replicas instead of under the collection lock
+    let storage = SearchGuard::acquire(&storage_ref, replicas.as_deref());
+    let lock_wait = lock_start.elapsed();
     record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
 
-    let metric = parse_metric(req.metric);
+    let metric = resolve_metric(req.metric, storage.vector_index().metric(), req.allow_metric_mismatch)?;
+    validation::check_query_dimensions(&req.vector, storage.dimensions())?;
+    validation::check_vector(&req.vector, &storage.config().validation, metric)?;
     let effective_search = apply_search_overrides(
         storage.config().search,
         req.ef,
@@ -577,7 +1347,8 @@ pub async fn range_search_vectors(
     );
 
     let start = Instant::now();
-    let mut results = storage.search(
+    let mut results = crate::search::search_target(
+        &*storage,
         &req.vector,
         req.k,
         metric,
@@ -586,9 +1357,13 @@ pub async fn range_search_vectors(
             filter: None,
             filter_overfetch_override: req.overfetch,
             search_config_override: Some(effective_search),
+            dedup_by: req.dedup_by.as_deref(),
+            score_expr: None,
+            order_by: req.order_by.as_ref(),
+            exclude_ids: None,
         },
     );
-    // Filter by min_score
+    // Filter by min_score (a primary order_by keeps its order through this)
     results.retain(|r| r.score >= req.min_score);
     let duration = start.elapsed();
     if duration.as_millis() > state.slow_query_ms {
@@ -598,20 +1373,109 @@ pub async fn range_search_vectors(
             elapsed_ms = duration.as_millis(),
             "slow_range_search"
         );
+        state.slow_queries.record(SlowQuery {
+            collection: collection.clone(),
+            request_id: request_id.0.clone(),
+            kind: SlowQueryKind::RangeSearch,
+            vectors: vec![req.vector.clone()],
+            k: req.k,
+            metric,
+            search: effective_search,
+            min_score: Some(req.min_score),
+            dedup_by: req.dedup_by.clone(),
+            order_by: req.order_by.clone(),
+            latency: SlowQueryLatency::new(None, lock_wait, duration),
+            ..Default::default()
+        });
     }
 
     let search_results: Vec<HitResponse> = results
         .into_iter()
         .map(|r| HitResponse {
             id: r.id.to_string(),
+            external_id: crate::storage::external_id_of(&r.metadata).map(str::to_string),
             score: r.score,
             text: r.text,
             metadata: metadata_to_json(&r.metadata),
+            band: None,
+            rerank_score: None,
         })
         .collect();
+    let search_results = rerank_hits(&state, &collection, req.order_by.as_ref(), search_results);
+    let (search_results, bands) = band_hits(req.score_bands.as_ref(), search_results);
+    let query_id = logged.and_then(|options| state.query_log.record(&collection, LoggedQuery {
+        kind: QueryKind::RangeSearch,
+        model,
+        vector: Some(req.vector.clone()),
+        k: req.k,
+        metric,
+        options,
+        results: LoggedHit::of(&search_results),
+        ..Default::default()
+    }));
 
-    Ok(Json(SearchResponse {
+    Ok(format.reply(SearchResponse {
         results: search_results,
         latency_ms: Some(duration.as_millis() as f32),
+        effective: None,
+        explain: None,
+        bands,
+        query_id,
+        partial: false,
+        stale: false,
+    }))
+}
+
+// POST /api/collections/:collection/history/search - search the collection as of an earlier WAL seq or time
+pub async fn search_history(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    Json(req): Json<HistorySearchRequest>,
+) -> Result<Json<HistorySearchResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+    validation::validate_vector(&req.vector)?;
+    let as_of = match (req.as_of_seq, req.as_of_timestamp) {
+        (Some(seq), None) => AsOf::Seq(seq),
+        (None, Some(ts)) => AsOf::Timestamp(ts),
+        _ => return Err(ServerError::InvalidRequest("Provide exactly one of as_of_seq or as_of_timestamp".to_string()).into()),
+    };
+
+    state.get_or_create_collection(&collection)?;
+    let storage_ref = state.collections.get(&collection)
+        .ok_or(ServerError::CollectionNotFound)?;
+    let start = Instant::now();
+    // The view is rebuilt under the read lock and searched after it is released
+    let view = {
+        let lock_start = Instant::now();
+        let storage = storage_ref.read();
+        record_lock_read(state.latency_tracker.get(&collection).as_deref(), lock_start);
+        storage.view_as_of(as_of)?
+    };
+    drop(storage_ref);
+
+    let metric = resolve_metric(req.metric, view.vector_index().metric(), req.allow_metric_mismatch)?;
+    let results = view.search(&req.vector, req.k, metric, crate::SearchParams::default());
+    let duration = start.elapsed();
+    info!(collection=%collection, as_of_seq=view.as_of_seq, elapsed_ms=duration.as_millis(), "history_search");
+
+    Ok(Json(HistorySearchResponse {
+        as_of_seq: view.as_of_seq,
+        count: view.count(),
+        results: results
+            .into_iter()
+            .map(|r| HitResponse {
+                id: r.id.to_string(),
+                external_id: crate::storage::external_id_of(&r.metadata).map(str::to_string),
+                score: r.score,
+                text: r.text,
+                metadata: metadata_to_json(&r.metadata),
+                band: None,
+                rerank_score: None,
+            })
+            .collect(),
+        latency_ms: Some(duration.as_millis() as f32),
     }))
 }
@@ -0,0 +1,52 @@
+use axum::{extract::{Path, State}, response::Json};
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use crate::error::{Result, ServerError};
+use crate::validation;
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+// POST /api/collections/:collection/verify - cross-check pointers, data file and vector index, optionally repairing
+pub async fn verify_collection(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    body: Option<Json<VerifyRequest>>,
+) -> Result<Json<VerifyResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    if req.repair {
+        state.ensure_write_allowed()?;
+    }
+    validation::validate_collection_name(&collection)?;
+
+    let start = Instant::now();
+    let report = state.verify_collection(&collection, req.repair)?;
+    let consistent = report.is_consistent();
+    if consistent {
+        tracing::info!(collection=%collection, documents=report.documents, elapsed_ms=start.elapsed().as_millis(), "collection_verified");
+    } else {
+        tracing::warn!(
+            collection=%collection,
+            documents=report.documents,
+            out_of_bounds=report.out_of_bounds.count,
+            unreadable=report.unreadable.count,
+            orphan_index_nodes=report.orphan_index_nodes.count,
+            missing_from_index=report.missing_from_index.count,
+            missing_metadata=report.missing_metadata.count,
+            recorded_count=report.recorded_count,
+            repaired=report.repaired,
+            elapsed_ms=start.elapsed().as_millis(),
+            "collection_inconsistent"
+        );
+    }
+
+    Ok(Json(VerifyResponse {
+        report,
+        consistent,
+        latency_ms: Some(start.elapsed().as_millis() as f32),
+    }))
+}
@@ -0,0 +1,86 @@
+use axum::{extract::{Path, State}, response::Json};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::{Result, ServerError};
+use crate::server::metrics::record_lock_write;
+use crate::validation;
+use super::super::{
+    state::SharedState,
+    types::*,
+};
+
+// GET /api/writes - whether writes are accepted, and why not in read-only mode
+pub async fn writes_status(State(state): State<SharedState>) -> Json<WritesStatusResponse> {
+    Json(WritesStatusResponse {
+        writable: !state.read_only.load(Ordering::Relaxed),
+        read_only: state.read_only_status(),
+        disk_available_bytes: state.disk_free_bytes(),
+        disk_min_free_bytes: state.disk_min_free_bytes,
+    })
+}
+
+// POST /api/writes/resume - leave read-only mode once disk space has been freed
+pub async fn resume_writes(State(state): State<SharedState>) -> Result<Json<ResumeWritesResponse>> {
+    let was_read_only = state.read_only.load(Ordering::Relaxed);
+    let previous = state.resume_writes()?;
+    Ok(Json(ResumeWritesResponse { resumed: was_read_only, read_only: previous }))
+}
+
+// GET /api/collections/:collection/writes - whether an operator holds the collection's writes
+pub async fn collection_writes_status(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<CollectionWritesResponse>> {
+    state.get_or_create_collection(&collection)?;
+    let storage_ref = state.collections.get(&collection).ok_or(ServerError::CollectionNotFound)?;
+    let pause = storage_ref.read().write_pause().cloned();
+    Ok(Json(CollectionWritesResponse { collection, paused: pause.is_some(), pause }))
+}
+
+// POST /api/collections/:collection/writes/pause - finish the writes in flight, checkpoint, and reject
+// writes until resumed or `timeout_secs` passed, e.g. around a filesystem snapshot
+pub async fn pause_collection_writes(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+    body: Option<Json<PauseWritesRequest>>,
+) -> Result<Json<CollectionWritesResponse>> {
+    if state.shutting_down.load(Ordering::Relaxed) {
+        return Err(ServerError::ServiceUnavailable("Server is shutting down".to_string()).into());
+    }
+    validation::validate_collection_name(&collection)?;
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+
+    state.get_or_create_collection(&collection)?;
+    let handle = state.collections.get(&collection).map(|c| c.clone()).ok_or(ServerError::CollectionNotFound)?;
+    let lock_start = Instant::now();
+    let mut storage = handle.write();
+    record_lock_write(state.latency_tracker.get(&collection).as_deref(), lock_start);
+    let pause = storage.pause_writes(req.timeout_secs, req.reason)?;
+    drop(storage);
+
+    // The pause lapses by itself at its deadline; this only clears it and logs that nobody resumed it
+    let deadline = UNIX_EPOCH + Duration::from_secs(pause.resume_at);
+    let handle = std::sync::Arc::downgrade(&handle);
+    tokio::spawn(async move {
+        tokio::time::sleep(deadline.duration_since(SystemTime::now()).unwrap_or_default()).await;
+        if let Some(handle) = handle.upgrade() {
+            handle.write().expire_write_pause();
+        }
+    });
+    Ok(Json(CollectionWritesResponse { collection, paused: true, pause: Some(pause) }))
+}
+
+// POST /api/collections/:collection/writes/resume - accept writes again
+pub async fn resume_collection_writes(
+    State(state): State<SharedState>,
+    Path(collection): Path<String>,
+) -> Result<Json<CollectionWritesResponse>> {
+    state.get_or_create_collection(&collection)?;
+    let storage_ref = state.collections.get(&collection).ok_or(ServerError::CollectionNotFound)?;
+    let lock_start = Instant::now();
+    let mut storage = storage_ref.write();
+    record_lock_write(state.latency_tracker.get(&collection).as_deref(), lock_start);
+    let lifted = storage.resume_writes();
+    Ok(Json(CollectionWritesResponse { collection, paused: false, pause: lifted }))
+}
@@ -1,39 +1,81 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use crate::error::{Result, ServerError};
+use crate::search::Filter;
 use crate::{Metadata, MetadataValue};
 
 // Common error messages
-pub const COLLECTION_NOT_FOUND: &str = "Collection not found";
-pub const VECTOR_NOT_FOUND: &str = "Vector not found";
 pub const EMBEDDING_NOT_CONFIGURED: &str = "Embedding service not configured";
 
-// Convert JSON values to internal Metadata type
+// Convert JSON values to internal Metadata type. Arrays of scalars (e.g. tags) are kept; objects,
+// and arrays holding anything else, are dropped.
 pub fn json_to_metadata(json: HashMap<String, serde_json::Value>) -> Metadata {
     let mut metadata = Metadata::new();
     
     for (k, v) in json {
-        let value = match v {
-            serde_json::Value::String(s) => MetadataValue::String(s),
-            serde_json::Value::Number(n) => {
-                if let Some(i) = n.as_i64() {
-                    MetadataValue::Integer(i)
-                } else if let Some(f) = n.as_f64() {
-                    MetadataValue::Float(f)
-                } else {
-                    continue;
-                }
-            }
-            serde_json::Value::Bool(b) => MetadataValue::Boolean(b),
-            serde_json::Value::Null => MetadataValue::Null,
-            _ => continue,
-        };
-        metadata.insert(k, value);
+        if let Some(value) = json_to_value(v) {
+            metadata.insert(k, value);
+        }
     }
     
     metadata
 }
 
-// Convert internal Metadata to JSON for responses
-pub fn metadata_to_json(metadata: &Metadata) -> HashMap<String, serde_json::Value> {
+// Metadata sent by a client for a write, whose keys may not be reserved ones
+pub fn client_metadata(json: HashMap<String, serde_json::Value>) -> Result<Metadata> {
+    crate::validation::validate_metadata_keys(json.keys())?;
+    Ok(json_to_metadata(json))
+}
+
+fn json_to_value(value: serde_json::Value) -> Option<MetadataValue> {
+    match value {
+        serde_json::Value::Array(items) => items.into_iter().map(json_to_scalar).collect::<Option<_>>().map(MetadataValue::Array),
+        value => json_to_scalar(value),
+    }
+}
+
+fn json_to_scalar(value: serde_json::Value) -> Option<MetadataValue> {
+    Some(match value {
+        serde_json::Value::String(s) => MetadataValue::String(s),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => MetadataValue::Integer(i),
+            None => MetadataValue::Float(n.as_f64()?),
+        },
+        serde_json::Value::Bool(b) => MetadataValue::Boolean(b),
+        serde_json::Value::Null => MetadataValue::Null,
+        _ => return None,
+    })
+}
+
+// Filter of a request's `{"field": value, ...}` map: equality on every field, except that
+// `{"any": [...]}` and `{"all": [...]}` test the elements of an array field (see FilterCondition::AnyIn)
+pub fn json_to_filter(json: HashMap<String, serde_json::Value>) -> Result<Filter> {
+    let mut filter = Filter::new();
+    for (field, value) in json {
+        let serde_json::Value::Object(test) = value else {
+            if let Some(value) = json_to_value(value) {
+                filter = filter.eq(&field, value);
+            }
+            continue;
+        };
+        let invalid = || ServerError::InvalidRequest(format!(
+            "filter on '{field}' takes a value, {{\"any\": [...]}} or {{\"all\": [...]}}"
+        ));
+        let mut test = test.into_iter();
+        let (Some((op, serde_json::Value::Array(values))), None) = (test.next(), test.next()) else {
+            return Err(invalid().into());
+        };
+        let values = values.into_iter().map(json_to_scalar).collect::<Option<Vec<_>>>().ok_or_else(invalid)?;
+        filter = match op.as_str() {
+            "any" => filter.any_in(&field, values),
+            "all" => filter.all_in(&field, values),
+            _ => return Err(invalid().into()),
+        };
+    }
+    Ok(filter)
+}
+
+// Convert internal Metadata to JSON for responses, ordered by key
+pub fn metadata_to_json(metadata: &Metadata) -> BTreeMap<String, serde_json::Value> {
     metadata
         .iter()
         .map(|(k, v)| {
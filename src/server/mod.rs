@@ -5,6 +5,20 @@
 // - `handlers.rs` - the actual endpoint logic
 // - `routes.rs` - wires handlers to URL paths
 // - `helpers.rs` - utility functions and macros
+// - `shedding.rs` - interactive/batch priority classes and load shedding
+// - `query_cache.rs` - cached results of repeated searches
+// - `search_deadline.rs` - searches with a timeout, answered with partial or cached results past it
+// - `projects.rs` - collection groups sharing embedding, API keys, search defaults and quotas
+// - `usage.rs` - embedding provider usage accounting and monthly budgets
+// - `slow_queries.rs` - capture of slow searches for replay and profiling
+// - `query_log.rs` - searches and click feedback on disk, exported as an evaluation dataset
+// - `feedback.rs` - relevance feedback signals and the re-ranker learned from them
+// - `recall_monitor.rs` - live recall of approximate indexes against sampled exact results
+// - `compression.rs` - gzip/zstd response compression (tower-http)
+// - `msgpack.rs` - MessagePack bodies for the vector endpoints (rmp-serde)
+// - `read_only.rs` - read-only mode on a full disk, and resuming writes
+// - `data_dir_lock.rs` - one server per data dir, and standbys waiting for the owner to go away
+// - `faults.rs` - simulated latency, lock contention and errors per route (debug builds)
 
 pub mod state;
 pub mod types;
@@ -13,7 +27,22 @@ pub mod routes;
 pub mod helpers;
 pub mod metrics;
 pub mod request_id;
+pub mod shedding;
+pub mod query_cache;
+pub mod search_deadline;
+pub mod projects;
+pub mod usage;
+pub mod slow_queries;
+pub mod query_log;
+pub mod feedback;
+pub mod recall_monitor;
+pub mod compression;
+pub mod msgpack;
+pub mod read_only;
+pub mod data_dir_lock;
+#[cfg(any(debug_assertions, feature = "fault-injection"))]
+pub mod faults;
 
 pub use state::{AppState, SharedState};
 pub use routes::create_router;
-pub use helpers::{json_to_metadata, metadata_to_json};
+pub use helpers::{client_metadata, json_to_filter, json_to_metadata, metadata_to_json};
@@ -0,0 +1,40 @@
+// MessagePack (https://github.com/msgpack/msgpack/blob/master/spec.md) for request and response bodies,
+// through rmp-serde.
+//
+// The point is vectors: an f32 goes out as a float 32 (5 bytes) instead of up to a dozen bytes of
+// decimal text, and neither side formats or parses floats. Structs are maps keyed by field name and
+// enums follow serde_json's external tagging, so every API type keeps the shape its JSON form has.
+// Extension types are not used.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+pub const CONTENT_TYPE: &str = "application/msgpack";
+
+// Deepest nesting of arrays and maps a request body may have
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, thiserror::Error)]
+#[error("msgpack: {0}")]
+pub struct Error(String);
+
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    rmp_serde::to_vec_named(value).map_err(|e| Error(e.to_string()))
+}
+
+// The whole of `data` has to be one value; trailing bytes are an error
+pub fn from_slice<T: DeserializeOwned>(data: &[u8]) -> Result<T, Error> {
+    let mut rest = data;
+    let mut decoder = rmp_serde::Deserializer::new(&mut rest);
+    decoder.set_max_depth(MAX_DEPTH);
+    let value = T::deserialize(&mut decoder).map_err(|e| Error(e.to_string()))?;
+    match rest.len() {
+        0 => Ok(value),
+        trailing => Err(Error(format!("{trailing} trailing bytes"))),
+    }
+}
+
+// Content types clients use for MessagePack
+pub fn is_msgpack(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    matches!(essence.as_str(), "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack")
+}
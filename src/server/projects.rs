@@ -0,0 +1,271 @@
+// Projects: named groups of collections that share an embedding provider, API keys, default search
+// settings and quotas, so a server hosting several teams keeps that configuration in one place
+// instead of repeating it per collection. Managed through /api/projects and stored in
+// data_dir/projects.json (temp file + rename, like the job files).
+//
+// - embedding: collections of the project embed text with the project's provider instead of the
+//   server's
+// - api_keys: requests to the project and its collections need one of them in `x-api-key`
+// - search: default search settings of its collections (a kept tuning recommendation still applies
+//   on top)
+// - quotas: how many collections the project may hold and how many vectors across all of them
+//
+// A collection belongs to at most one project; collections outside any project behave as before.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use axum::{body::Body, extract::State, http::Request, middleware::Next, response::{IntoResponse, Response}};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::config::SearchConfig;
+use crate::embeddings::{self, Embedder, EmbeddingConfig};
+use crate::error::{Result, ServerError};
+use super::state::SharedState;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectQuotas {
+    // Collections the project may hold
+    #[serde(default)]
+    pub max_collections: Option<usize>,
+    // Vectors across all of the project's collections
+    #[serde(default)]
+    pub max_vectors: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectConfig {
+    #[serde(default)]
+    pub name: String, // taken from the path on PUT
+    #[serde(default)]
+    pub collections: Vec<String>,
+    #[serde(default)]
+    pub embedding: Option<EmbeddingConfig>,
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    #[serde(default)]
+    pub search: Option<SearchConfig>,
+    #[serde(default)]
+    pub quotas: ProjectQuotas,
+}
+
+pub struct Project {
+    pub config: ProjectConfig,
+    pub embedder: Option<Arc<dyn Embedder>>, // built from `config.embedding`
+}
+
+impl Project {
+    // `previous` is the version being replaced; its embedder (and connection pool) is kept when the
+    // embedding settings did not change
+    fn new(config: ProjectConfig, previous: Option<&Project>) -> Result<Self> {
+        let same_embedding = |p: &&Project| serde_json::to_value(&p.config.embedding).ok() == serde_json::to_value(&config.embedding).ok();
+        if let Some(previous) = previous.filter(same_embedding) {
+            return Ok(Self { embedder: previous.embedder.clone(), config });
+        }
+        let embedder = match &config.embedding {
+            Some(embedding) => {
+                let embedder = embeddings::create_embedder(embedding).map_err(|e| {
+                    ServerError::ValidationFailed(format!("project '{}' embedding: {e}", config.name))
+                })?;
+                Some(Arc::new(embeddings::RetryEmbedder::new(embedder)) as Arc<dyn Embedder>)
+            }
+            None => None,
+        };
+        Ok(Self { config, embedder })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    pub fn contains(&self, collection: &str) -> bool {
+        self.config.collections.iter().any(|c| c == collection)
+    }
+
+    // Whether a request carrying `key` may use the project
+    pub fn accepts(&self, key: Option<&str>) -> bool {
+        self.config.api_keys.is_empty() || key.is_some_and(|key| self.config.api_keys.iter().any(|k| k == key))
+    }
+
+    // 401 without a key, 403 with one the project does not list
+    pub fn authorize(&self, key: Option<&str>) -> Result<()> {
+        if self.accepts(key) {
+            return Ok(());
+        }
+        Err(match key {
+            None => ServerError::AuthenticationFailed(format!("project '{}' requires an x-api-key", self.name())),
+            Some(_) => ServerError::AuthorizationFailed(format!("x-api-key is not valid for project '{}'", self.name())),
+        }.into())
+    }
+}
+
+pub struct ProjectRegistry {
+    path: PathBuf,
+    projects: RwLock<BTreeMap<String, Arc<Project>>>,
+}
+
+impl ProjectRegistry {
+    // Load data_dir/projects.json. A project whose embedder cannot be built is kept without one (text
+    // requests to its collections fail until it is fixed); an unreadable file starts empty.
+    pub fn open(data_dir: &str) -> Self {
+        let path = Path::new(data_dir).join("projects.json");
+        let configs: Vec<ProjectConfig> = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                tracing::warn!(path=%path.display(), error=%e, "projects_unreadable");
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        let projects = configs
+            .into_iter()
+            .map(|config| {
+                let project = Project::new(config.clone(), None).unwrap_or_else(|e| {
+                    tracing::warn!(project=%config.name, error=%e, "project_embedder_unavailable");
+                    Project { config, embedder: None }
+                });
+                (project.config.name.clone(), Arc::new(project))
+            })
+            .collect();
+        Self { path, projects: RwLock::new(projects) }
+    }
+
+    pub fn list(&self) -> Vec<Arc<Project>> {
+        self.projects.read().values().cloned().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Project>> {
+        self.projects.read().get(name).cloned()
+    }
+
+    // Project the collection belongs to
+    pub fn of_collection(&self, collection: &str) -> Option<Arc<Project>> {
+        self.projects.read().values().find(|p| p.contains(collection)).cloned()
+    }
+
+    // Create or replace a project. Returns the project and the one it replaced.
+    pub fn put(&self, mut config: ProjectConfig) -> Result<(Arc<Project>, Option<Arc<Project>>)> {
+        crate::validation::validate_collection_name(&config.name)?;
+        config.collections.sort();
+        config.collections.dedup();
+        for collection in &config.collections {
+            crate::validation::validate_collection_name(collection)?;
+        }
+        if config.api_keys.iter().any(|k| k.is_empty()) {
+            return Err(ServerError::ValidationFailed("project api_keys must not be empty strings".into()).into());
+        }
+        if let Some(max) = config.quotas.max_collections {
+            if config.collections.len() > max {
+                return Err(ServerError::ValidationFailed(format!(
+                    "project '{}' lists {} collections, more than its max_collections ({max})", config.name, config.collections.len()
+                )).into());
+            }
+        }
+        let previous = self.get(&config.name);
+        let project = Arc::new(Project::new(config, previous.as_deref())?);
+
+        let mut projects = self.projects.write();
+        if let Some((other, collection)) = projects.values()
+            .filter(|p| p.name() != project.name())
+            .find_map(|p| project.config.collections.iter().find(|c| p.contains(c)).map(|c| (p.name(), c)))
+        {
+            return Err(ServerError::AlreadyExists(format!("collection '{collection}' already belongs to project '{other}'")).into());
+        }
+        let previous = projects.insert(project.name().to_string(), project.clone());
+        if let Err(e) = self.save(&projects) {
+            match previous.clone() {
+                Some(previous) => projects.insert(previous.name().to_string(), previous),
+                None => projects.remove(project.name()),
+            };
+            return Err(e);
+        }
+        Ok((project, previous))
+    }
+
+    // Add a collection to a project, within its max_collections
+    pub fn add_collection(&self, name: &str, collection: &str) -> Result<Arc<Project>> {
+        let project = self.get(name).ok_or_else(|| ServerError::NotFound(format!("Project '{name}' not found")))?;
+        if project.contains(collection) {
+            return Ok(project);
+        }
+        if let Some(max) = project.config.quotas.max_collections {
+            if project.config.collections.len() >= max {
+                return Err(ServerError::InvalidRequest(format!("Project '{name}' max collections reached")).into());
+            }
+        }
+        let mut config = project.config.clone();
+        config.collections.push(collection.to_string());
+        Ok(self.put(config)?.0)
+    }
+
+    // Drop a deleted collection from whichever project held it
+    pub fn forget_collection(&self, collection: &str) -> Result<()> {
+        let Some(project) = self.of_collection(collection) else {
+            return Ok(());
+        };
+        let mut config = project.config.clone();
+        config.collections.retain(|c| c != collection);
+        self.put(config).map(|_| ())
+    }
+
+    // Follow a collection to its new name
+    pub fn rename_collection(&self, from: &str, to: &str) -> Result<()> {
+        let Some(project) = self.of_collection(from) else {
+            return Ok(());
+        };
+        let mut config = project.config.clone();
+        config.collections.retain(|c| c != from);
+        config.collections.push(to.to_string());
+        self.put(config).map(|_| ())
+    }
+
+    pub fn remove(&self, name: &str) -> Result<Option<Arc<Project>>> {
+        let mut projects = self.projects.write();
+        let Some(removed) = projects.remove(name) else {
+            return Ok(None);
+        };
+        if let Err(e) = self.save(&projects) {
+            projects.insert(name.to_string(), removed);
+            return Err(e);
+        }
+        Ok(Some(removed))
+    }
+
+    fn save(&self, projects: &BTreeMap<String, Arc<Project>>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let configs: Vec<&ProjectConfig> = projects.values().map(|p| &p.config).collect();
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&configs)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+// Project named by a request path: /api[/v1]/projects/{project}/.. or the project of
+// /api[/v1]/collections/{collection}/..
+fn project_of_path(state: &SharedState, path: &str) -> Option<Arc<Project>> {
+    let rest = path.strip_prefix("/api/")?;
+    let rest = rest.strip_prefix("v1/").unwrap_or(rest);
+    let mut segments = rest.split('/');
+    match (segments.next()?, segments.next().filter(|s| !s.is_empty())?) {
+        ("projects", name) => state.projects.get(name),
+        ("collections", name) => state.projects.of_collection(name),
+        _ => None,
+    }
+}
+
+/// Middleware that turns away requests to a project's collections (or the project itself) without
+/// one of its API keys.
+pub async fn require_project_key(State(state): State<SharedState>, req: Request<Body>, next: Next) -> Response {
+    if let Some(project) = project_of_path(&state, req.uri().path()) {
+        let key = req.headers().get("x-api-key").and_then(|v| v.to_str().ok());
+        if let Err(e) = project.authorize(key) {
+            return e.into_response();
+        }
+    }
+    next.run(req).await
+}
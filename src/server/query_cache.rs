@@ -0,0 +1,249 @@
+// Query-result cache for single-vector searches.
+// Results are keyed by collection, the query vector rounded to the configured grid, k and a hash of
+// everything else that shapes them (metric, effective search parameters, execution mode, dedup key
+// and score expression). Each entry remembers the collection's WAL sequence number when it was
+// computed: every write moves the sequence on, so an entry from before it is stale, and dropped on
+// its next lookup once it is more than `max_seq_lag` entries behind. Up to that bound `lookup` still
+// answers with it, and hands the first search that finds it behind the job of recomputing it (a
+// `Revalidation`, which gives the job back if the search fails); later ones keep getting the old
+// results until the new ones are inserted. Operations that change results without a write
+// (restores, projections, tuning, deleting the collection) clear the collection's entries through
+// `invalidate`.
+//
+// A dropped entry's results are kept aside, out of the stats and lookups, for searches that run out of
+// time: `fallback` answers them with the last results computed for the query, however stale.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use lru::LruCache;
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::config::QueryCacheConfig;
+use super::types::HitResponse;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryKey {
+    collection: String,
+    vector: Vec<i64>,
+    k: usize,
+    options: u64,
+}
+
+struct CachedResult {
+    seq: u64, // collection WAL sequence the results were computed at
+    inserted: Instant,
+    results: Vec<HitResponse>,
+    revalidating: bool, // a search is recomputing the results at a later sequence
+}
+
+// Results answered from the cache
+pub struct CachedHits {
+    pub results: Vec<HitResponse>,
+    pub lag: u64, // WAL entries written since they were computed; above 0 they are stale
+    pub revalidate: bool, // the caller is the one to recompute them and `insert` the new results
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryCacheStats {
+    pub enabled: bool,
+    pub entries: usize,
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub stale_hits: u64, // hits behind the collection's sequence, within max_seq_lag
+    pub invalidated: u64, // stale or expired entries dropped on lookup, counted as misses too
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hit_rate: Option<f64>,
+}
+
+pub struct QueryCache {
+    config: QueryCacheConfig,
+    entries: Mutex<LruCache<QueryKey, CachedResult>>,
+    stale: Mutex<LruCache<QueryKey, Vec<HitResponse>>>, // results of dropped entries, for `fallback`
+    hits: AtomicU64,
+    misses: AtomicU64,
+    stale_hits: AtomicU64,
+    invalidated: AtomicU64,
+}
+
+impl QueryCache {
+    pub fn new(config: &QueryCacheConfig) -> Self {
+        let capacity = NonZeroUsize::new(config.max_entries).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            config: *config,
+            entries: Mutex::new(LruCache::new(capacity)),
+            stale: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            stale_hits: AtomicU64::new(0),
+            invalidated: AtomicU64::new(0),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn key(&self, collection: &str, vector: &[f32], k: usize, options: impl Hash) -> QueryKey {
+        let step = self.config.quantization_step;
+        let vector = vector
+            .iter()
+            .map(|&x| if step > 0.0 { (x / step).round() as i64 } else { x.to_bits() as i64 })
+            .collect();
+        let mut hasher = DefaultHasher::new();
+        options.hash(&mut hasher);
+        QueryKey { collection: collection.to_string(), vector, k, options: hasher.finish() }
+    }
+
+    // Cached results for `key` if they were computed at most `max_seq_lag` entries before `seq` and
+    // have not expired
+    pub fn get(&self, key: &QueryKey, seq: u64) -> Option<Vec<HitResponse>> {
+        self.find(key, seq, false).map(|hits| hits.results)
+    }
+
+    // Like `get`, and the first caller finding the results behind `seq` is asked to revalidate them
+    pub fn lookup(&self, key: &QueryKey, seq: u64) -> Option<CachedHits> {
+        self.find(key, seq, true)
+    }
+
+    fn find(&self, key: &QueryKey, seq: u64, claim: bool) -> Option<CachedHits> {
+        if !self.config.enabled {
+            return None;
+        }
+        let ttl = (self.config.ttl_secs > 0).then(|| Duration::from_secs(self.config.ttl_secs));
+        let mut entries = self.entries.lock();
+        let lag = entries.get(key).map(|entry| {
+            let lag = seq.checked_sub(entry.seq).filter(|&lag| lag <= self.config.max_seq_lag);
+            lag.filter(|_| ttl.is_none_or(|ttl| entry.inserted.elapsed() < ttl))
+        });
+        match lag {
+            Some(Some(lag)) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                let entry = entries.get_mut(key)?;
+                let revalidate = claim && lag > 0 && !entry.revalidating;
+                if lag > 0 {
+                    self.stale_hits.fetch_add(1, Ordering::Relaxed);
+                    entry.revalidating |= revalidate;
+                }
+                Some(CachedHits { results: entry.results.clone(), lag, revalidate })
+            }
+            Some(None) => {
+                if let Some(entry) = entries.pop(key) {
+                    self.stale.lock().put(key.clone(), entry.results);
+                }
+                self.invalidated.fetch_add(1, Ordering::Relaxed);
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub fn insert(&self, key: QueryKey, seq: u64, results: &[HitResponse]) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        // A revalidation finishing after a search at a later sequence does not replace its results
+        if entries.peek(&key).is_some_and(|entry| entry.seq > seq) {
+            return;
+        }
+        let entry = CachedResult { seq, inserted: Instant::now(), results: results.to_vec(), revalidating: false };
+        self.stale.lock().pop(&key);
+        entries.put(key, entry);
+    }
+
+    // Let the next lookup behind `key` revalidate it again
+    fn release(&self, key: &QueryKey) {
+        if let Some(entry) = self.entries.lock().peek_mut(key) {
+            entry.revalidating = false;
+        }
+    }
+
+    // The last results computed for `key`, and whether they are stale at `seq` (or expired); for
+    // searches past their deadline
+    pub fn fallback(&self, key: &QueryKey, seq: u64) -> Option<(Vec<HitResponse>, bool)> {
+        if !self.config.enabled {
+            return None;
+        }
+        let ttl = (self.config.ttl_secs > 0).then(|| Duration::from_secs(self.config.ttl_secs));
+        if let Some(entry) = self.entries.lock().peek(key) {
+            let stale = entry.seq != seq || ttl.is_some_and(|ttl| entry.inserted.elapsed() >= ttl);
+            return Some((entry.results.clone(), stale));
+        }
+        self.stale.lock().peek(key).map(|results| (results.clone(), true))
+    }
+
+    // Drop every entry of `collection`
+    pub fn invalidate(&self, collection: &str) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        let keys: Vec<QueryKey> = entries
+            .iter()
+            .filter(|(key, _)| key.collection == collection)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            entries.pop(&key);
+        }
+        let mut stale = self.stale.lock();
+        let keys: Vec<QueryKey> = stale.iter().filter(|(key, _)| key.collection == collection).map(|(key, _)| key.clone()).collect();
+        for key in keys {
+            stale.pop(&key);
+        }
+    }
+
+    pub fn stats(&self) -> QueryCacheStats {
+        let entries = self.entries.lock();
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        QueryCacheStats {
+            enabled: self.config.enabled,
+            entries: entries.len(),
+            capacity: entries.cap().get(),
+            hits,
+            misses,
+            stale_hits: self.stale_hits.load(Ordering::Relaxed),
+            invalidated: self.invalidated.load(Ordering::Relaxed),
+            hit_rate: (hits + misses > 0).then(|| hits as f64 / (hits + misses) as f64),
+        }
+    }
+}
+
+// The claim of a search recomputing a stale entry. Dropped without `finish` (the search failed or
+// panicked), it releases the entry, so a later lookup is asked to revalidate it instead of every
+// one getting the old results until the entry falls past `max_seq_lag`.
+pub struct Revalidation {
+    cache: Arc<QueryCache>,
+    key: Option<QueryKey>,
+}
+
+impl Revalidation {
+    pub fn new(cache: Arc<QueryCache>, key: QueryKey) -> Self {
+        Self { cache, key: Some(key) }
+    }
+
+    pub fn finish(mut self, seq: u64, results: &[HitResponse]) {
+        if let Some(key) = self.key.take() {
+            self.cache.insert(key, seq, results);
+        }
+    }
+}
+
+impl Drop for Revalidation {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.cache.release(&key);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+// Query log: searches kept on disk, with click feedback, for offline evaluation.
+// With `query_log.enabled`, every search (vector, batch, range, text) appends a "query" line to
+// data_dir/query_log/<collection>.jsonl: what was asked (the query vector or text, unless
+// `store_queries` is off, k, metric, the filters and ranking options), which model embedded the
+// collection, and the hits returned. Its `query_id` comes back in the search response; clients
+// report what users went on to use with POST .../queries/{query_id}/feedback, which appends a
+// "feedback" line. Feedback is not checked against the log, so an id that was never logged (or
+// has been rotated out) is simply left out of exports.
+//
+// GET .../queries/export joins the two into one NDJSON dataset line per query, e.g. to replay real
+// traffic against a collection re-embedded with another model and compare the hits with what was
+// clicked. A log is rotated to <collection>.1.jsonl once it reaches `max_file_bytes`; exports read
+// both files.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::QueryLogConfig;
+use crate::error::{Result, ServerError};
+use crate::search::{OrderBy, ScoreBands};
+use crate::server::types::range::RangeSearchRequest;
+use crate::server::types::{HitResponse, SearchRequest, TextSearchRequest};
+use crate::Metric;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryKind {
+    #[default]
+    Search,
+    BatchSearch,
+    RangeSearch,
+    TextSearch,
+    BatchTextSearch,
+}
+
+// The filters and ranking options a search was sent with
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueryOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>, // query by this stored document
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclude_filter: Option<HashMap<String, serde_json::Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_score: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedup_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_expr: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_by: Option<OrderBy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_bands: Option<ScoreBands>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+}
+
+impl QueryOptions {
+    pub fn of_search(req: &SearchRequest) -> Self {
+        Self {
+            id: req.id.clone(),
+            exclude_ids: req.exclude_ids.clone(),
+            exclude_filter: req.exclude_filter.clone(),
+            min_score: None,
+            dedup_by: req.dedup_by.clone(),
+            score_expr: req.score_expr.clone(),
+            order_by: req.order_by.clone(),
+            score_bands: req.score_bands.clone(),
+            preset: req.preset.clone(),
+        }
+    }
+
+    pub fn of_text_search(req: &TextSearchRequest) -> Self {
+        Self {
+            id: None,
+            exclude_ids: req.exclude_ids.clone(),
+            exclude_filter: req.exclude_filter.clone(),
+            min_score: None,
+            dedup_by: req.dedup_by.clone(),
+            score_expr: req.score_expr.clone(),
+            order_by: req.order_by.clone(),
+            score_bands: req.score_bands.clone(),
+            preset: req.preset.clone(),
+        }
+    }
+
+    pub fn of_range_search(req: &RangeSearchRequest) -> Self {
+        Self {
+            min_score: Some(req.min_score),
+            dedup_by: req.dedup_by.clone(),
+            order_by: req.order_by.clone(),
+            score_bands: req.score_bands.clone(),
+            preset: req.preset.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedHit {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    pub score: f32,
+}
+
+impl LoggedHit {
+    pub fn of(hits: &[HitResponse]) -> Vec<LoggedHit> {
+        hits.iter().map(|h| LoggedHit { id: h.id.clone(), external_id: h.external_id.clone(), score: h.score }).collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoggedQuery {
+    #[serde(default)]
+    pub query_id: String,
+    #[serde(default)]
+    pub at: u64, // unix ms
+    pub kind: QueryKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>, // embedding model recorded for the collection (or that embedded a text query)
+    #[serde(default)]
+    pub query_hash: String, // hash of the query vector, equal for repeats of the same query
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector: Option<Vec<f32>>, // unless store_queries is off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>, // text searches, unless store_queries is off
+    pub k: usize,
+    pub metric: Metric,
+    #[serde(default)]
+    pub options: QueryOptions,
+    #[serde(default)]
+    pub results: Vec<LoggedHit>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Line {
+    Query(Box<LoggedQuery>),
+    Feedback { query_id: String, at: u64, clicked: Vec<String> },
+}
+
+// One dataset line: a logged query with every document reported as clicked for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedQuery {
+    #[serde(flatten)]
+    pub query: LoggedQuery,
+    pub clicked: Vec<String>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+pub struct QueryLog {
+    config: QueryLogConfig,
+    dir: PathBuf,
+    // Held while appending or rotating, so lines from concurrent searches never interleave
+    write: Mutex<()>,
+}
+
+impl QueryLog {
+    pub fn open(data_dir: &str, config: &QueryLogConfig) -> Self {
+        Self { config: *config, dir: Path::new(data_dir).join("query_log"), write: Mutex::new(()) }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    fn path(&self, collection: &str) -> PathBuf {
+        self.dir.join(format!("{collection}.jsonl"))
+    }
+
+    fn rotated_path(&self, collection: &str) -> PathBuf {
+        self.dir.join(format!("{collection}.1.jsonl"))
+    }
+
+    fn append(&self, collection: &str, line: &Line) -> std::io::Result<()> {
+        let mut encoded = serde_json::to_vec(line)?;
+        encoded.push(b'\n');
+        let _write = self.write.lock();
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(collection);
+        if fs::metadata(&path).is_ok_and(|m| m.len() + encoded.len() as u64 > self.config.max_file_bytes) {
+            fs::rename(&path, self.rotated_path(collection))?;
+        }
+        OpenOptions::new().create(true).append(true).open(&path)?.write_all(&encoded)
+    }
+
+    // Log a search; returns the id to send its feedback for, or None when the log is off.
+    // A failed write is only warned about: the search has been answered either way.
+    pub fn record(&self, collection: &str, mut query: LoggedQuery) -> Option<String> {
+        if !self.config.enabled {
+            return None;
+        }
+        query.query_id = uuid::Uuid::new_v4().to_string();
+        query.at = now_ms();
+        query.query_hash = crate::server::slow_queries::query_hash(query.vector.as_slice());
+        if !self.config.store_queries {
+            query.vector = None;
+            query.text = None;
+        }
+        let query_id = query.query_id.clone();
+        if let Err(e) = self.append(collection, &Line::Query(Box::new(query))) {
+            tracing::warn!(collection = %collection, error = %e, "query_log_write_failed");
+            return None;
+        }
+        Some(query_id)
+    }
+
+    // Record that the documents in `clicked` (ids or external ids) were used from a logged search
+    pub fn feedback(&self, collection: &str, query_id: &str, clicked: Vec<String>) -> Result<()> {
+        if !self.config.enabled {
+            return Err(ServerError::InvalidRequest("the query log is not enabled (set query_log.enabled)".into()).into());
+        }
+        if uuid::Uuid::parse_str(query_id).is_err() {
+            return Err(ServerError::InvalidRequest(format!("'{}' is not a query id", query_id)).into());
+        }
+        if clicked.is_empty() {
+            return Err(ServerError::InvalidRequest("clicked must name at least one document".into()).into());
+        }
+        self.append(collection, &Line::Feedback { query_id: query_id.to_string(), at: now_ms(), clicked })?;
+        Ok(())
+    }
+
+    // Logged queries from `since` (unix ms) on, oldest first, with their feedback
+    pub fn export(&self, collection: &str, since: u64) -> Result<Vec<ExportedQuery>> {
+        let mut queries: Vec<ExportedQuery> = Vec::new();
+        let mut position: HashMap<String, usize> = HashMap::new();
+        let mut feedback: Vec<(String, Vec<String>)> = Vec::new();
+        for path in [self.rotated_path(collection), self.path(collection)] {
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            for line in BufReader::new(file).lines() {
+                // A line cut short by a crash is skipped rather than failing the export
+                match serde_json::from_str::<Line>(&line?) {
+                    Ok(Line::Query(query)) if query.at >= since => {
+                        position.insert(query.query_id.clone(), queries.len());
+                        queries.push(ExportedQuery { query: *query, clicked: Vec::new() });
+                    }
+                    Ok(Line::Feedback { query_id, clicked, .. }) => feedback.push((query_id, clicked)),
+                    _ => {}
+                }
+            }
+        }
+        for (query_id, clicked) in feedback {
+            if let Some(&i) = position.get(&query_id) {
+                let exported = &mut queries[i].clicked;
+                for id in clicked {
+                    if !exported.contains(&id) {
+                        exported.push(id);
+                    }
+                }
+            }
+        }
+        Ok(queries)
+    }
+
+    // Drop a collection's log; returns whether there was one
+    pub fn clear(&self, collection: &str) -> bool {
+        let _write = self.write.lock();
+        let current = fs::remove_file(self.path(collection)).is_ok();
+        fs::remove_file(self.rotated_path(collection)).is_ok() || current
+    }
+
+    pub fn rename(&self, from: &str, to: &str) {
+        let _write = self.write.lock();
+        fs::rename(self.path(from), self.path(to)).ok();
+        fs::rename(self.rotated_path(from), self.rotated_path(to)).ok();
+    }
+}
+
+// Name of the model recorded for a collection's embeddings, if it was embedded server-side
+pub fn recorded_model(storage: &crate::Collection) -> Option<String> {
+    storage.metadata().embedding_model.as_ref().map(|m| m.model.clone())
+}
@@ -0,0 +1,37 @@
+// Read-only mode: writes are turned away with 503 while the disk is too full to take them; reads go
+// on. It is entered when free space drops under `disk_min_free_bytes` (with
+// `disk_readonly_on_low_space`), and when a write fails with STORAGE_FULL, e.g. ENOSPC on a WAL
+// append. The WAL cuts a failed append back out of its file (see storage/wal/log.rs), so the log
+// still matches what the collection holds in memory. `/readyz` and `GET /api/writes` say why and
+// since when; `POST /api/writes/resume` lets writes through again once space has been freed.
+
+use axum::{body::Body, extract::State, http::Request, middleware::Next, response::Response};
+use serde::Serialize;
+
+use crate::error::ErrorCode;
+use super::state::SharedState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadOnlyCause {
+    DiskFull,
+    LowDiskSpace,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadOnlyStatus {
+    pub cause: ReadOnlyCause,
+    pub message: String,
+    pub since: u64,
+}
+
+// Switch to read-only mode when a request failed with STORAGE_FULL (error responses carry their
+// ErrorCode as an extension)
+pub async fn watch_disk_full(State(state): State<SharedState>, req: Request<Body>, next: Next) -> Response {
+    let route = format!("{} {}", req.method(), req.uri().path());
+    let res = next.run(req).await;
+    if res.extensions().get::<ErrorCode>() == Some(&ErrorCode::StorageFull) {
+        state.enter_read_only(ReadOnlyCause::DiskFull, format!("{route} ran out of disk space"));
+    }
+    res
+}
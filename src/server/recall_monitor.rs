@@ -0,0 +1,147 @@
+// Live recall of approximate indexes against a small ground-truth set.
+// With `recall_monitor.enabled`, each loaded HNSW or IVF collection samples `queries` of its stored
+// vectors once and keeps them as queries with their exact top `k`. Every `interval_secs` the queries
+// are searched with the collection's current search settings and the share of the exact results
+// found is its recall. Writes move the exact results, so they are recomputed first whenever the
+// collection changed since (head_seq moved); that is a scan of every stored vector, run as a
+// low-priority background task under the collection's read lock.
+//
+// Deletes and updates leave HNSW tombstones and stale IVF centroids behind, which is what the recall
+// shows degrading over time. A recall below `alert_below` is logged once as `index_recall_degraded`
+// (and `index_recall_recovered` when it is back), and reported as `degraded` in /api/metrics and as
+// piramid_index_recall_degraded in Prometheus, for an alert rule to pick up.
+//
+// Kept in memory only: after a restart the queries are sampled again.
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::config::RecallMonitorConfig;
+use crate::index::IndexType;
+use crate::scheduler::{self, Priority};
+use crate::server::state::SharedState;
+use crate::Collection;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecallStatus {
+    pub recall: f32, // recall@k of the last measurement
+    pub k: usize,
+    pub queries: usize,
+    pub alert_below: f32,
+    pub degraded: bool, // recall < alert_below
+    pub measured_at: u64, // unix secs
+    pub truth_seq: u64, // sequence number the exact results were computed at
+    pub truth_computed_at: u64, // unix secs
+}
+
+struct GroundTruth {
+    queries: Vec<Vec<f32>>,
+    truth: Vec<Vec<Uuid>>,
+    seq: u64,
+    computed_at: u64,
+}
+
+pub struct RecallMonitor {
+    config: RecallMonitorConfig,
+    truths: Mutex<HashMap<String, GroundTruth>>,
+    statuses: Mutex<HashMap<String, RecallStatus>>,
+}
+
+impl RecallMonitor {
+    pub fn new(config: &RecallMonitorConfig) -> Self {
+        Self { config: *config, truths: Mutex::new(HashMap::new()), statuses: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn status(&self, collection: &str) -> Option<RecallStatus> {
+        self.statuses.lock().get(collection).cloned()
+    }
+
+    // Measure one collection now, refreshing its exact results first if it changed since. Returns None
+    // for exact (flat) indexes and collections with nothing stored.
+    pub fn measure(&self, name: &str, storage: &Collection) -> Option<RecallStatus> {
+        if storage.vector_index().index_type() == IndexType::Flat || storage.count() == 0 {
+            self.forget(name);
+            return None;
+        }
+        let k = self.config.k;
+        let seq = storage.head_seq();
+        let mut truth = self.truths.lock().remove(name).filter(|t| !t.queries.is_empty()).unwrap_or_else(|| GroundTruth {
+            queries: storage.sample_vectors(self.config.queries),
+            truth: Vec::new(),
+            seq: 0,
+            computed_at: 0,
+        });
+        if truth.truth.is_empty() || truth.seq != seq {
+            truth.truth = storage.exact_top_k(&truth.queries, k);
+            truth.seq = seq;
+            truth.computed_at = now();
+        }
+        let recall = storage.recall(&truth.queries, &truth.truth, k);
+        let status = recall.map(|recall| RecallStatus {
+            recall,
+            k,
+            queries: truth.queries.len(),
+            alert_below: self.config.alert_below,
+            degraded: recall < self.config.alert_below,
+            measured_at: now(),
+            truth_seq: truth.seq,
+            truth_computed_at: truth.computed_at,
+        });
+        self.truths.lock().insert(name.to_string(), truth);
+
+        let status = status?;
+        let was_degraded = self.statuses.lock().insert(name.to_string(), status.clone()).is_some_and(|s| s.degraded);
+        match (was_degraded, status.degraded) {
+            (false, true) => tracing::warn!(
+                collection=%name, recall=status.recall, k, alert_below=status.alert_below, "index_recall_degraded"
+            ),
+            (true, false) => tracing::info!(collection=%name, recall=status.recall, k, "index_recall_recovered"),
+            _ => {}
+        }
+        Some(status)
+    }
+
+    // Drop a collection's queries and last measurement
+    pub fn forget(&self, collection: &str) {
+        self.truths.lock().remove(collection);
+        self.statuses.lock().remove(collection);
+    }
+}
+
+// Measure every loaded collection, one at a time
+pub fn check_all(state: &SharedState) {
+    let handles: Vec<_> = state.collections.iter().map(|c| (c.key().clone(), c.value().clone())).collect();
+    for (name, handle) in handles {
+        if state.shutting_down.load(std::sync::atomic::Ordering::Relaxed) {
+            return;
+        }
+        let storage = handle.read();
+        state.recall_monitor.measure(&name, &storage);
+    }
+}
+
+// Measure every `interval_secs`, as a low-priority background task
+pub fn spawn_recall_monitor(state: SharedState) {
+    let every = Duration::from_secs(state.recall_monitor.config.interval_secs.max(1));
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        interval.tick().await; // the first tick is immediate; give collections time to open
+        loop {
+            interval.tick().await;
+            let shared = state.clone();
+            let _ = scheduler::spawn(Priority::Low, "recall_monitor", move || check_all(&shared)).wait().await;
+        }
+    });
+}
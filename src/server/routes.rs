@@ -2,17 +2,18 @@
 // - GET    = read (list, get one)
 // - POST   = create or action (store, search)
 // - DELETE = remove
-// - PUT    = replace (not used yet)
+// - PUT    = replace
 // - PATCH  = partial update (not used yet)
 
 use axum::{
     extract::DefaultBodyLimit,
-    routing::{delete, get, post},
+    routing::{delete, get, patch, post, put},
     Router,
     middleware,
 };
 use axum::http::HeaderValue;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::decompression::RequestDecompressionLayer;
 use tower_http::services::{ServeDir, ServeFile};
 use tower_http::set_header::SetResponseHeaderLayer;
 use tower_http::trace::{TraceLayer, DefaultMakeSpan, DefaultOnResponse};
@@ -21,6 +22,10 @@ use tracing::Level;
 use super::handlers;
 use super::state::SharedState;
 use super::request_id::assign_request_id;
+use super::shedding::shed_load;
+use super::compression::compress_response;
+use super::projects::require_project_key;
+use super::read_only::watch_disk_full;
 
 fn api_router(state: SharedState) -> Router<SharedState> {
     Router::new()
@@ -29,6 +34,8 @@ fn api_router(state: SharedState) -> Router<SharedState> {
         .route("/health/embeddings", get(handlers::health_embeddings))
         .route("/readyz", get(handlers::readyz))
         .route("/metrics", get(handlers::metrics))
+        .route("/metrics/prometheus", get(handlers::metrics_prometheus))
+        .route("/metrics/latency", delete(handlers::reset_latency))
         .route("/version", get(handlers::version))
         
         // Collections CRUD
@@ -37,15 +44,85 @@ fn api_router(state: SharedState) -> Router<SharedState> {
         .route("/collections/{collection}", get(handlers::get_collection))
         .route("/collections/{collection}", delete(handlers::delete_collection))
         .route("/collections/{collection}/count", get(handlers::collection_count))
+        .route("/collections/{collection}/rename", post(handlers::rename_collection))
         .route("/collections/{collection}/index/stats", get(handlers::index_stats))
         .route("/collections/{collection}/index/rebuild", post(handlers::rebuild_index))
         .route("/collections/{collection}/index/rebuild/status", get(handlers::rebuild_index_status))
+        .route("/collections/{collection}/index/migrate", post(handlers::migrate_index))
+        .route("/collections/{collection}/index/import", post(handlers::import_index))
+        .route("/collections/{collection}/quantization", get(handlers::quantization_composition))
+        .route("/collections/{collection}/quantization", post(handlers::requantize_collection))
         .route("/collections/{collection}/compact", post(handlers::compact_collection))
+        .route("/collections/{collection}/seal", post(handlers::seal_collection))
+        .route("/collections/{collection}/writes", get(handlers::collection_writes_status))
+        .route("/collections/{collection}/writes/pause", post(handlers::pause_collection_writes))
+        .route("/collections/{collection}/writes/resume", post(handlers::resume_collection_writes))
         .route("/collections/{collection}/duplicates", post(handlers::find_duplicates))
+        .route("/collections/{collection}/replicas", get(handlers::replicas_status))
+        .route("/collections/{collection}/replicas", post(handlers::set_replicas))
+        .route("/collections/{collection}/history", get(handlers::history_status))
+        .route("/collections/{collection}/history/search", post(handlers::search_history))
+        .route("/collections/{collection}/changes", get(handlers::list_changes))
+        .route("/collections/{collection}/snapshots", get(handlers::list_snapshots))
+        .route("/collections/{collection}/snapshots", post(handlers::create_snapshot))
+        .route("/collections/{collection}/snapshots/{snapshot}", delete(handlers::delete_snapshot))
+        .route("/collections/{collection}/snapshots/{snapshot}/restore", post(handlers::restore_snapshot))
+        .route("/collections/{collection}/projection", get(handlers::get_projection))
+        .route("/collections/{collection}/projection", delete(handlers::delete_projection))
+        .route("/collections/{collection}/projection/train", post(handlers::train_projection))
+        .route("/collections/{collection}/tuning", get(handlers::get_tuning))
+        .route("/collections/{collection}/tuning", delete(handlers::delete_tuning))
+        .route("/collections/{collection}/tuning/sweep", post(handlers::tune_collection))
+        .route("/collections/{collection}/verify", post(handlers::verify_collection))
+        .route("/collections/{collection}/export", post(handlers::export_collection))
+        .route("/collections/{collection}/arrow", get(handlers::export_arrow_stream))
+        .route("/collections/{collection}/arrow", post(handlers::import_arrow_stream))
+        .route("/collections/{collection}/statistics", post(handlers::vector_statistics))
+        .route("/collections/{collection}/statistics", get(handlers::sampled_statistics))
+        .route("/collections/{collection}/outliers", post(handlers::score_outliers))
+        .route("/collections/{collection}/dimensions", get(handlers::dimension_distribution))
         
+        // Projects (groups of collections sharing embedding, keys, search defaults and quotas)
+        .route("/projects", get(handlers::list_projects))
+        .route("/projects", post(handlers::create_project))
+        .route("/projects/{project}", get(handlers::get_project))
+        .route("/projects/{project}", put(handlers::update_project))
+        .route("/projects/{project}", delete(handlers::delete_project))
+
+        // Embedding provider usage and budgets
+        .route("/usage", get(handlers::embedding_usage))
+
+        // Captured slow searches
+        .route("/slow_queries", get(handlers::list_slow_queries))
+        .route("/slow_queries", delete(handlers::clear_slow_queries))
+
+        // Query log for offline evaluation: click feedback and dataset export
+        .route("/collections/{collection}/queries", delete(handlers::clear_query_log))
+        .route("/collections/{collection}/queries/export", get(handlers::export_query_log))
+        .route("/collections/{collection}/queries/{query_id}/feedback", post(handlers::query_feedback))
+
+        // Relevance feedback and the re-ranker learned from it
+        .route("/collections/{collection}/feedback", post(handlers::record_feedback))
+        .route("/collections/{collection}/feedback", get(handlers::feedback_stats))
+        .route("/collections/{collection}/feedback", delete(handlers::clear_feedback))
+
+        // Read-only mode status, and resuming writes after it
+        .route("/writes", get(handlers::writes_status))
+        .route("/writes/resume", post(handlers::resume_writes))
+
         // Config hot reload/status
         .route("/config", get(handlers::config_status))
         .route("/config/reload", post(handlers::reload_config))
+
+        // Ingestion sources
+        .route("/ingest", get(handlers::ingest_status))
+
+        // Admin jobs
+        .route("/jobs", get(handlers::list_jobs))
+        .route("/jobs/{id}", get(handlers::get_job))
+        .route("/jobs/{id}/cancel", post(handlers::cancel_job))
+        .route("/collections/{collection}/reembed", post(handlers::reembed_collection))
+        .route("/collections/{collection}/import", post(handlers::import_documents))
         
         // Vectors CRUD
         .route("/collections/{collection}/vectors", get(handlers::list_vectors))
@@ -53,6 +130,7 @@ fn api_router(state: SharedState) -> Router<SharedState> {
         .route("/collections/{collection}/vectors", delete(handlers::delete_vectors))
         .route("/collections/{collection}/vectors/{id}", get(handlers::get_vector))
         .route("/collections/{collection}/vectors/{id}", delete(handlers::delete_vector))
+        .route("/collections/{collection}/vectors/{id}/metadata", patch(handlers::update_vector_metadata))
         
         // Upsert
         .route("/collections/{collection}/upsert", post(handlers::upsert_vector))
@@ -78,12 +156,28 @@ pub fn create_router(state: SharedState) -> Router {
         .allow_headers(Any);  // any headers
 
     let api = api_router(state.clone());
+    let max_body_bytes = state.app_config.read().limits.max_body_bytes;
     
-    Router::<SharedState>::new()
+    let router = Router::<SharedState>::new()
         .nest("/api", api.clone())
         .nest("/api/v1", api)
         // Middleware layers
-        .layer(DefaultBodyLimit::max(100 * 1024 * 1024))  // 100MB for batch operations
+        .layer(DefaultBodyLimit::max(max_body_bytes))  // limits.max_body_bytes, read at startup
+        // A write that ran out of disk space switches the server to read-only mode
+        .layer(middleware::from_fn_with_state(state.clone(), watch_disk_full))
+        // API keys of the project a collection belongs to
+        .layer(middleware::from_fn_with_state(state.clone(), require_project_key))
+        // gzip/zstd request bodies are decompressed, and the body limit counts the decompressed bytes
+        .layer(RequestDecompressionLayer::new())
+        // gzip/zstd for clients that accept it (large search, list and export responses)
+        .layer(middleware::from_fn_with_state(state.clone(), compress_response));
+    // Simulated failures (fault_injection config), inside load shedding like real slow requests
+    #[cfg(any(debug_assertions, feature = "fault-injection"))]
+    let router = router.layer(middleware::from_fn_with_state(state.clone(), super::faults::inject_faults));
+
+    router
+        // Priority classes: shed batch work first when the server saturates
+        .layer(middleware::from_fn_with_state(state.clone(), shed_load))
         .layer(cors)
         // Assign request IDs to all requests
         .layer(middleware::from_fn(assign_request_id))
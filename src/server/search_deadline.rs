@@ -0,0 +1,159 @@
+// Single-vector searches with a deadline (timeout_ms).
+// The search runs on a blocking thread in rounds (see `search_target_in_rounds`): a quick one at a
+// fraction of the index's effort, then the configured one. The request waits up to the deadline for
+// the final round; past it, `on_timeout` picks what stands in for it: the quick round's hits (partial),
+// the last results the query cache holds for the query (stale), or a Timeout error. A search still
+// running then is left to finish, and its hits fill the query cache for the next request.
+//
+// The wait blocks the request's thread like the search itself would: the collection guard the request
+// holds cannot be held across an await. The thread takes its own guard with `acquire_shared`, so a
+// writer queued behind the request's guard does not hold it up.
+//
+// The same owned search recomputes query cache entries served behind the collection's sequence
+// (`revalidate`), on a blocking thread the request does not wait for.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use parking_lot::RwLock;
+use uuid::Uuid;
+
+use crate::config::{ExecutionMode, SearchConfig};
+use crate::error::{Result, ServerError};
+use crate::search::{Filter, Hit, OrderBy, ScoreExpr};
+use crate::storage::collection::{ReplicaSet, SearchGuard};
+use crate::{Collection, Metric};
+use super::helpers::metadata_to_json;
+use super::query_cache::{QueryKey, Revalidation};
+use super::state::SharedState;
+use super::types::{HitResponse, SearchTimeoutFallback};
+
+// What a deadline search came back with
+pub enum Deadlined {
+    Done(Vec<Hit>),
+    Partial(Vec<Hit>), // the quick round's hits
+    Cached { results: Vec<HitResponse>, stale: bool }, // the query cache's last results for the query
+}
+
+// A search's parameters, owned so they can move to the search thread
+pub struct DeadlineSearch {
+    pub collection: Arc<RwLock<Collection>>,
+    pub replicas: Option<Arc<ReplicaSet>>,
+    pub query: Vec<f32>,
+    pub k: usize,
+    pub metric: Metric,
+    pub mode: ExecutionMode,
+    pub search: SearchConfig,
+    pub filter: Option<Filter>,
+    pub overfetch: Option<usize>,
+    pub dedup_by: Option<String>,
+    pub score_expr: Option<ScoreExpr>,
+    pub order_by: Option<OrderBy>,
+    pub exclude_ids: HashSet<Uuid>,
+}
+
+impl DeadlineSearch {
+    // Search again in the background and cache the results at the sequence they were computed at
+    pub fn revalidate(self, state: &SharedState, key: QueryKey) {
+        let revalidation = Revalidation::new(state.query_cache.clone(), key);
+        tokio::task::spawn_blocking(move || {
+            let storage = SearchGuard::acquire_shared(&self.collection, self.replicas.as_deref());
+            let params = crate::SearchParams {
+                mode: self.mode,
+                filter: self.filter.as_ref(),
+                filter_overfetch_override: self.overfetch,
+                search_config_override: Some(self.search),
+                dedup_by: self.dedup_by.as_deref(),
+                score_expr: self.score_expr.as_ref(),
+                order_by: self.order_by.as_ref(),
+                exclude_ids: Some(&self.exclude_ids),
+            };
+            let hits = crate::search::search_target(&*storage, &self.query, self.k, self.metric, params);
+            revalidation.finish(storage.seq(), &hit_responses(hits));
+        });
+    }
+
+    pub fn run(
+        self,
+        state: &SharedState,
+        cache_key: Option<(QueryKey, u64)>,
+        timeout: Duration,
+        on_timeout: SearchTimeoutFallback,
+    ) -> Result<Deadlined> {
+        let deadline = Instant::now() + timeout;
+        let fallback_key = cache_key.clone();
+        let (tx, rx) = mpsc::channel();
+        let late = state.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut cache_key = cache_key;
+            let storage = SearchGuard::acquire_shared(&self.collection, self.replicas.as_deref());
+            let params = crate::SearchParams {
+                mode: self.mode,
+                filter: self.filter.as_ref(),
+                filter_overfetch_override: self.overfetch,
+                search_config_override: Some(self.search),
+                dedup_by: self.dedup_by.as_deref(),
+                score_expr: self.score_expr.as_ref(),
+                order_by: self.order_by.as_ref(),
+                exclude_ids: Some(&self.exclude_ids),
+            };
+            crate::search::search_target_in_rounds(&*storage, &self.query, self.k, self.metric, params, |hits, last| {
+                // The request gave up waiting: keep the final hits for its next try
+                if let Err(mpsc::SendError((hits, true))) = tx.send((hits, last)) {
+                    if let Some((key, seq)) = cache_key.take() {
+                        late.query_cache.insert(key, seq, &hit_responses(hits));
+                    }
+                }
+            });
+        });
+
+        let mut quick = None;
+        loop {
+            match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok((hits, true)) => return Ok(Deadlined::Done(hits)),
+                Ok((hits, false)) => quick = Some(hits),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(ServerError::Internal("search thread stopped before its final round".to_string()).into());
+                }
+            }
+        }
+        // Results the search thread cached since the request looked come back as current
+        let cached = || {
+            let (key, seq) = fallback_key.as_ref()?;
+            let (results, stale) = state.query_cache.fallback(key, *seq)?;
+            Some(Deadlined::Cached { results, stale })
+        };
+        let fallback = match on_timeout {
+            SearchTimeoutFallback::Error => None,
+            SearchTimeoutFallback::Partial => quick.map(Deadlined::Partial).or_else(cached),
+            SearchTimeoutFallback::Cached => cached().or_else(|| quick.map(Deadlined::Partial)),
+        };
+        tracing::warn!(
+            timeout_ms = timeout.as_millis() as u64,
+            fallback = match &fallback {
+                Some(Deadlined::Partial(_)) => "partial",
+                Some(Deadlined::Cached { .. }) => "cached",
+                _ => "none",
+            },
+            "search_deadline_passed"
+        );
+        fallback.ok_or_else(|| ServerError::Timeout.into())
+    }
+}
+
+fn hit_responses(hits: Vec<Hit>) -> Vec<HitResponse> {
+    hits.into_iter()
+        .map(|r| HitResponse {
+            id: r.id.to_string(),
+            external_id: crate::storage::external_id_of(&r.metadata).map(str::to_string),
+            score: r.score,
+            text: r.text,
+            metadata: metadata_to_json(&r.metadata),
+            band: None,
+            rerank_score: None,
+        })
+        .collect()
+}
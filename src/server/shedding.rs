@@ -0,0 +1,194 @@
+// Priority classes and load shedding for API requests.
+// Every /api request is classified as interactive or batch and must take a slot from its class
+// before it runs. A class that is out of slots queues up to `max_queue` requests for at most
+// `queue_timeout_ms`; anything beyond that is rejected with 503 + Retry-After instead of piling up
+// on the collection locks. Batch requests are shed as soon as the interactive class is saturated,
+// so under overload bulk work gives way first.
+//
+// Classification, first match wins:
+// 1. an x-api-key listed in `load_shedding.batch_api_keys` -> batch
+// 2. an `x-priority: batch|interactive` header
+// 3. the route: index and document imports, index rebuilds, compaction, sealing, verification, exports, Arrow streams, vector statistics, outlier scoring and duplicate scans are batch, the rest interactive
+// Health, readiness, metrics and version endpoints are never shed.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{body::Body, extract::State, http::{HeaderValue, Request}, middleware::Next, response::{IntoResponse, Response}};
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::{ClassLimits, LoadSheddingConfig};
+use crate::error::ServerError;
+use super::state::SharedState;
+
+const EXEMPT_SUFFIXES: [&str; 5] = ["/health", "/health/embeddings", "/readyz", "/version", "/metrics"];
+const BATCH_SUFFIXES: [&str; 13] = ["/import", "/arrow", "/index/rebuild", "/projection/train", "/tuning/sweep", "/verify", "/export", "/statistics", "/outliers", "/compact", "/seal", "/duplicates", "/reembed"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Interactive,
+    Batch,
+}
+
+impl Priority {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Priority::Interactive => "interactive",
+            Priority::Batch => "batch",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassStats {
+    pub max_concurrent: usize,
+    pub in_flight: usize,
+    pub queued: usize,
+    pub shed_total: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadSheddingStats {
+    pub enabled: bool,
+    pub interactive: ClassStats,
+    pub batch: ClassStats,
+}
+
+struct ClassGate {
+    slots: Arc<Semaphore>,
+    limits: ClassLimits,
+    queued: AtomicUsize,
+    shed: AtomicU64,
+}
+
+impl ClassGate {
+    fn new(limits: ClassLimits) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(limits.max_concurrent.max(1))),
+            limits,
+            queued: AtomicUsize::new(0),
+            shed: AtomicU64::new(0),
+        }
+    }
+
+    fn saturated(&self) -> bool {
+        self.slots.available_permits() == 0
+    }
+
+    fn stats(&self) -> ClassStats {
+        let max_concurrent = self.limits.max_concurrent.max(1);
+        ClassStats {
+            max_concurrent,
+            in_flight: max_concurrent - self.slots.available_permits(),
+            queued: self.queued.load(Ordering::Relaxed),
+            shed_total: self.shed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+pub struct LoadShedder {
+    config: LoadSheddingConfig,
+    interactive: ClassGate,
+    batch: ClassGate,
+}
+
+impl LoadShedder {
+    pub fn new(config: &LoadSheddingConfig) -> Self {
+        Self {
+            interactive: ClassGate::new(config.interactive),
+            batch: ClassGate::new(config.batch),
+            config: config.clone(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    fn gate(&self, priority: Priority) -> &ClassGate {
+        match priority {
+            Priority::Interactive => &self.interactive,
+            Priority::Batch => &self.batch,
+        }
+    }
+
+    // Priority class of a request, or None for endpoints that are never shed
+    pub fn classify<B>(&self, req: &Request<B>) -> Option<Priority> {
+        let path = req.uri().path();
+        if !path.starts_with("/api/") || EXEMPT_SUFFIXES.iter().any(|s| path.ends_with(s)) {
+            return None;
+        }
+        let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+        if header("x-api-key").is_some_and(|key| self.config.batch_api_keys.iter().any(|k| k == key)) {
+            return Some(Priority::Batch);
+        }
+        match header("x-priority").map(|p| p.to_ascii_lowercase()).as_deref() {
+            Some("batch") => return Some(Priority::Batch),
+            Some("interactive") => return Some(Priority::Interactive),
+            _ => {}
+        }
+        if BATCH_SUFFIXES.iter().any(|s| path.ends_with(s)) {
+            Some(Priority::Batch)
+        } else {
+            Some(Priority::Interactive)
+        }
+    }
+
+    // Take a slot for `priority`, waiting in the class queue if needed. The slot is released when
+    // the permit is dropped.
+    pub async fn admit(&self, priority: Priority) -> Result<OwnedSemaphorePermit, ServerError> {
+        let gate = self.gate(priority);
+        let shed = || {
+            gate.shed.fetch_add(1, Ordering::Relaxed);
+            ServerError::ServiceUnavailable(format!("Server overloaded; {} request shed", priority.name()))
+        };
+
+        if priority == Priority::Batch && self.interactive.saturated() {
+            return Err(shed());
+        }
+        if let Ok(permit) = gate.slots.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+        if gate.queued.fetch_add(1, Ordering::Relaxed) >= gate.limits.max_queue {
+            gate.queued.fetch_sub(1, Ordering::Relaxed);
+            return Err(shed());
+        }
+        let waited = tokio::time::timeout(
+            Duration::from_millis(self.config.queue_timeout_ms),
+            gate.slots.clone().acquire_owned(),
+        )
+        .await;
+        gate.queued.fetch_sub(1, Ordering::Relaxed);
+        match waited {
+            Ok(Ok(permit)) => Ok(permit),
+            _ => Err(shed()),
+        }
+    }
+
+    pub fn stats(&self) -> LoadSheddingStats {
+        LoadSheddingStats {
+            enabled: self.config.enabled,
+            interactive: self.interactive.stats(),
+            batch: self.batch.stats(),
+        }
+    }
+}
+
+/// Middleware that holds a class slot for the duration of the request, or sheds it.
+pub async fn shed_load(State(state): State<SharedState>, req: Request<Body>, next: Next) -> Response {
+    let shedder = &state.load_shedder;
+    let Some(priority) = shedder.enabled().then(|| shedder.classify(&req)).flatten() else {
+        return next.run(req).await;
+    };
+    match shedder.admit(priority).await {
+        Ok(_permit) => next.run(req).await,
+        Err(e) => {
+            tracing::warn!(class = priority.name(), path = %req.uri().path(), "request_shed");
+            let mut res = e.into_response();
+            res.headers_mut().insert("retry-after", HeaderValue::from_static("1"));
+            res
+        }
+    }
+}
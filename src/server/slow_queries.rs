@@ -0,0 +1,151 @@
+// Capture of slow searches for replay and profiling.
+// A search over the SLOW_QUERY_MS threshold is logged with a warning; with capture enabled it is also
+// pushed into a ring buffer holding the last `capacity` of them. An entry has what it takes to send
+// the query again: the query vectors (hashed, and kept unless `store_queries` is off), k, metric,
+// the effective ef/nprobe/overfetch and the ranking options, along with where the time went.
+
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::config::{SearchConfig, SlowQueryConfig};
+use crate::search::OrderBy;
+use crate::Metric;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlowQueryKind {
+    #[default]
+    Search,
+    BatchSearch,
+    RangeSearch,
+    TextSearch,
+    BatchTextSearch,
+}
+
+// Where a slow query's time went, in ms
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct SlowQueryLatency {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embed_ms: Option<f32>, // text searches: embedding the query
+    pub lock_wait_ms: f32, // waiting for the collection (or replica) read lock
+    pub search_ms: f32, // the search itself; compared against the threshold
+}
+
+impl SlowQueryLatency {
+    pub fn new(embed: Option<Duration>, lock_wait: Duration, search: Duration) -> Self {
+        let ms = |d: Duration| d.as_secs_f32() * 1000.0;
+        Self { embed_ms: embed.map(ms), lock_wait_ms: ms(lock_wait), search_ms: ms(search) }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SlowQuery {
+    pub id: u64, // capture number, increasing
+    pub captured_at: u64, // unix ms
+    pub collection: String,
+    pub request_id: String,
+    pub kind: SlowQueryKind,
+    pub query_hash: String, // hash of the query vectors, equal for repeats of the same query
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub vectors: Vec<Vec<f32>>, // one per query; empty unless store_queries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>, // text searches, unless store_queries is off
+    pub k: usize,
+    pub metric: Metric,
+    pub search: SearchConfig, // effective ef/nprobe/overfetch the search ran with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_ms: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedup_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_expr: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_by: Option<OrderBy>,
+    pub latency: SlowQueryLatency,
+}
+
+pub struct SlowQueryLog {
+    config: SlowQueryConfig,
+    entries: Mutex<VecDeque<SlowQuery>>,
+    next_id: AtomicU64,
+}
+
+impl SlowQueryLog {
+    pub fn new(config: &SlowQueryConfig) -> Self {
+        Self { config: *config, entries: Mutex::new(VecDeque::new()), next_id: AtomicU64::new(1) }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.config.capacity
+    }
+
+    // Add a slow query, dropping the oldest one when the buffer is full
+    pub fn record(&self, mut query: SlowQuery) {
+        if !self.config.enabled {
+            return;
+        }
+        query.id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        query.captured_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+        query.query_hash = query_hash(&query.vectors);
+        if !self.config.store_queries {
+            query.vectors.clear();
+            query.text = None;
+        }
+        let mut entries = self.entries.lock();
+        while entries.len() >= self.config.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(query);
+    }
+
+    // Captured queries, newest first
+    pub fn list(&self, collection: Option<&str>, limit: usize) -> Vec<SlowQuery> {
+        self.entries
+            .lock()
+            .iter()
+            .rev()
+            .filter(|q| collection.is_none_or(|c| q.collection == c))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+
+    // Drop every captured query; returns how many there were
+    pub fn clear(&self) -> usize {
+        let mut entries = self.entries.lock();
+        let cleared = entries.len();
+        entries.clear();
+        cleared
+    }
+}
+
+// Hex hash of the exact query vectors
+pub fn query_hash(vectors: &[Vec<f32>]) -> String {
+    let mut hasher = DefaultHasher::new();
+    for vector in vectors {
+        vector.len().hash(&mut hasher);
+        for x in vector {
+            x.to_bits().hash(&mut hasher);
+        }
+    }
+    format!("{:016x}", hasher.finish())
+}
@@ -1,32 +1,23 @@
 use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
 use parking_lot::RwLock;
 use dashmap::DashMap;
-use tokio::runtime::Handle;
+use crate::scheduler::{self, Priority};
 
 use crate::Collection;
-use crate::storage::collection::CollectionOpenOptions;
+use crate::storage::collection::{
+    CollectionOpenOptions, ConfigChanges, WritePressure, Projection, ProjectionKind, ReplicaSet, TuneOptions, TuningReport, VerifyReport, SnapshotManifest, create_snapshot, stage_restore, commit_restore, discard_restore, rename_files,
+    collection_path, get_lock_path,
+};
 use crate::embeddings::Embedder;
+use super::usage::{UsageCounts, UsageScope};
+use super::read_only::{ReadOnlyCause, ReadOnlyStatus};
 use crate::metrics::{LatencyTracker, EmbedMetrics};
 use crate::error::{Result, ServerError};
-use crate::config::AppConfig;
+use crate::config::{AppConfig, PreloadPolicy};
+use crate::storage::CollectionMetadata;
 use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 use std::time::{SystemTime, UNIX_EPOCH};
-
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum RebuildState {
-    Running,
-    Completed,
-    Failed,
-}
-
-#[derive(Clone)]
-pub struct RebuildJobStatus {
-    pub status: RebuildState, // Current status of the rebuild job (Running, Completed, Failed)
-    pub started_at: u64, // Timestamp when the rebuild job started (in seconds since UNIX epoch)
-    pub finished_at: Option<u64>, // Optional timestamp when the rebuild job finished (in seconds since UNIX epoch)
-    pub error: Option<String>, // Optional error message if the rebuild job failed
-    pub elapsed_ms: Option<u128>, // Optional elapsed time for the rebuild job in milliseconds
-}
+use std::collections::BTreeMap;
 
 // Shared application state
 // Each collection is an independent Collection with its own file.
@@ -34,19 +25,31 @@ pub struct RebuildJobStatus {
 // Holds config + optional embedder so handlers can access without reloading.
 pub struct AppState {
     pub collections: DashMap<String, Arc<RwLock<Collection>>>, // Map of collection name to its storage handle. Wrapped in Arc<RwLock> for shared mutable access across threads.
+    pub discovered: DashMap<String, CollectionMetadata>, // Collections found in data_dir at startup, registered from their metadata file whether or not they are loaded
+    pub replicas: DashMap<String, Arc<ReplicaSet>>, // In-memory read replicas of hot collections; searches use these instead of the collection lock
     pub data_dir: String, // Base directory for collection files, e.g. "./data"
     pub embedder: Option<Arc<dyn Embedder>>, // Optional embedder, if configured. Wrapped in Arc for shared ownership.
     pub shutting_down: Arc<AtomicBool>, // Flag to indicate server is shutting down, used to reject new requests gracefully
     pub read_only: Arc<AtomicBool>, // Flag for disk-pressure read-only mode
+    pub read_only_status: Arc<RwLock<Option<ReadOnlyStatus>>>, // Why and since when the server is read-only (see read_only.rs)
     pub latency_tracker: Arc<DashMap<String, LatencyTracker>>,  // Per-collection latency tracking
     pub embed_metrics: Arc<EmbedMetrics>,
     pub app_config: Arc<RwLock<AppConfig>>, // Global config accessible to handlers, protected by RwLock for dynamic updates
     pub slow_query_ms: u128, // Threshold for logging slow queries in ms
-    pub rebuild_jobs: Arc<DashMap<String, RebuildJobStatus>>, // Track index rebuild jobs by collection name
+    pub jobs: Arc<crate::jobs::JobQueue>, // Persistent queue of admin jobs (rebuilds, compactions, imports, re-embeds), stored under data_dir/jobs
     pub config_last_reload: Arc<AtomicU64>, // Timestamp of last config reload for cache invalidation
     pub disk_min_free_bytes: Option<u64>,
     pub disk_readonly_on_low_space: bool,
     pub cache_max_bytes: Option<u64>,
+    pub load_shedder: Arc<super::shedding::LoadShedder>, // Interactive/batch concurrency limits, sized from the startup config
+    pub query_cache: Arc<super::query_cache::QueryCache>, // Results of repeated searches, sized from the startup config
+    pub ingest: Arc<DashMap<String, crate::ingest::IngestStatus>>, // Status of the ingestion sources by source name
+    pub projects: Arc<super::projects::ProjectRegistry>, // Collection groups sharing embedding, API keys, search defaults and quotas, stored under data_dir
+    pub usage: Arc<super::usage::UsageLedger>, // Embedding provider usage per provider/model/collection/API key, stored under data_dir
+    pub slow_queries: Arc<super::slow_queries::SlowQueryLog>, // Recent searches over slow_query_ms, sized from the startup config
+    pub query_log: Arc<super::query_log::QueryLog>, // Searches and click feedback under data_dir/query_log, from the startup config
+    pub feedback: Arc<super::feedback::FeedbackStore>, // Relevance signals and learned re-ranking weights per collection, under data_dir/feedback
+    pub recall_monitor: Arc<super::recall_monitor::RecallMonitor>, // Sampled queries, their exact results and the last recall per collection
 }
 
 impl AppState {
@@ -59,18 +62,34 @@ impl AppState {
         cache_max_bytes: Option<u64>,
     ) -> Self {
         std::fs::create_dir_all(data_dir).ok();
+        super::types::body::clear_spool(data_dir);
+        // The server's parallelism config sizes the thread pools, before any collection is opened
+        crate::parallel::init(&app_config.parallelism);
+        scheduler::configure(&app_config.scheduler);
         
         Self {
             collections: DashMap::new(),
+            discovered: DashMap::new(),
+            replicas: DashMap::new(),
             data_dir: data_dir.to_string(),
             embedder: None,
             shutting_down: Arc::new(AtomicBool::new(false)),
             read_only: Arc::new(AtomicBool::new(false)),
+            read_only_status: Arc::new(RwLock::new(None)),
             latency_tracker: Arc::new(DashMap::new()),
             embed_metrics: Arc::new(EmbedMetrics::default()),
+            load_shedder: Arc::new(super::shedding::LoadShedder::new(&app_config.load_shedding)),
+            query_cache: Arc::new(super::query_cache::QueryCache::new(&app_config.query_cache)),
+            slow_queries: Arc::new(super::slow_queries::SlowQueryLog::new(&app_config.slow_queries)),
+            query_log: Arc::new(super::query_log::QueryLog::open(data_dir, &app_config.query_log)),
+            feedback: Arc::new(super::feedback::FeedbackStore::open(data_dir)),
+            recall_monitor: Arc::new(super::recall_monitor::RecallMonitor::new(&app_config.recall_monitor)),
             app_config: Arc::new(RwLock::new(app_config)),
             slow_query_ms,
-            rebuild_jobs: Arc::new(DashMap::new()),
+            jobs: Arc::new(crate::jobs::JobQueue::open(data_dir)),
+            ingest: Arc::new(DashMap::new()),
+            projects: Arc::new(super::projects::ProjectRegistry::open(data_dir)),
+            usage: Arc::new(super::usage::UsageLedger::open(data_dir)),
             // Initialize to current time; updated on each config reload
             config_last_reload: Arc::new(AtomicU64::new(
                 SystemTime::now()
@@ -94,18 +113,34 @@ impl AppState {
         cache_max_bytes: Option<u64>,
     ) -> Self {
         std::fs::create_dir_all(data_dir).ok();
+        super::types::body::clear_spool(data_dir);
+        // The server's parallelism config sizes the thread pools, before any collection is opened
+        crate::parallel::init(&app_config.parallelism);
+        scheduler::configure(&app_config.scheduler);
         
         Self {
             collections: DashMap::new(),
+            discovered: DashMap::new(),
+            replicas: DashMap::new(),
             data_dir: data_dir.to_string(),
             embedder: Some(embedder),
             shutting_down: Arc::new(AtomicBool::new(false)),
             read_only: Arc::new(AtomicBool::new(false)),
+            read_only_status: Arc::new(RwLock::new(None)),
             latency_tracker: Arc::new(DashMap::new()),
             embed_metrics: Arc::new(EmbedMetrics::default()),
+            load_shedder: Arc::new(super::shedding::LoadShedder::new(&app_config.load_shedding)),
+            query_cache: Arc::new(super::query_cache::QueryCache::new(&app_config.query_cache)),
+            slow_queries: Arc::new(super::slow_queries::SlowQueryLog::new(&app_config.slow_queries)),
+            query_log: Arc::new(super::query_log::QueryLog::open(data_dir, &app_config.query_log)),
+            feedback: Arc::new(super::feedback::FeedbackStore::open(data_dir)),
+            recall_monitor: Arc::new(super::recall_monitor::RecallMonitor::new(&app_config.recall_monitor)),
             app_config: Arc::new(RwLock::new(app_config)),
             slow_query_ms,
-            rebuild_jobs: Arc::new(DashMap::new()),
+            jobs: Arc::new(crate::jobs::JobQueue::open(data_dir)),
+            ingest: Arc::new(DashMap::new()),
+            projects: Arc::new(super::projects::ProjectRegistry::open(data_dir)),
+            usage: Arc::new(super::usage::UsageLedger::open(data_dir)),
             config_last_reload: Arc::new(AtomicU64::new(
                 SystemTime::now()
                     .duration_since(UNIX_EPOCH)
@@ -126,53 +161,519 @@ impl AppState {
         }
 
         if !self.collections.contains_key(name) {
-            let path = format!("{}/{}.db", self.data_dir, name);
+            let path = collection_path(&self.data_dir, name);
             let cfg = { self.app_config.read().clone() };
-            let storage = Collection::open_with_options(
+            if self.discovered.contains_key(name) && cfg.preload_policy(name) == PreloadPolicy::Never {
+                return Err(ServerError::ServiceUnavailable(format!(
+                    "Collection '{}' is not loaded (preload policy: never)", name
+                )).into());
+            }
+            let mut storage = Collection::open_with_options(
                 &path,
-                CollectionOpenOptions::from(cfg.to_collection_config()),
+                CollectionOpenOptions::from(self.collection_config(&cfg, name)),
             )?;
+            if let Some(&count) = cfg.hot_collections.get(name) {
+                self.replicas.insert(name.to_string(), storage.create_replicas(count)?);
+            }
+            let recovering = storage.index_recovery().is_some();
             let handle = Arc::new(RwLock::new(storage));
             self.collections.insert(name.to_string(), handle.clone());
+
+            // Its index file was corrupt and moved aside at open: rebuild it off the request path
+            if recovering {
+                let recover_handle = handle.clone();
+                let collection = name.to_string();
+                scheduler::spawn(Priority::High, "index_recovery", move || {
+                    if let Err(e) = crate::storage::collection::recover_index(&recover_handle) {
+                        tracing::warn!(collection=%collection, error=%e, "vector_index_recovery_failed");
+                    }
+                });
+            }
             
-            // Create latency tracker for this collection
-            self.latency_tracker.insert(name.to_string(), LatencyTracker::new());
+            // Create latency tracker for this collection, picking up saved histograms if configured
+            let tracker = if cfg.persist_latency_histograms {
+                LatencyTracker::load(&self.latency_path(name)).unwrap_or_else(|e| {
+                    tracing::warn!(collection=%name, error=%e, "latency_histograms_unreadable");
+                    LatencyTracker::new()
+                })
+            } else {
+                LatencyTracker::new()
+            };
+            self.latency_tracker.insert(name.to_string(), tracker);
 
             // Warm caches in the background to avoid first-request latency.
             let warm_handle = handle.clone();
-            if let Ok(rt) = Handle::try_current() {
-                rt.spawn_blocking(move || {
-                    let guard = warm_handle.read();
-                    guard.warm_page_cache();
-                });
-            }
+            scheduler::spawn(Priority::Normal, "warm_cache", move || {
+                let guard = warm_handle.read();
+                guard.warm_page_cache();
+            });
         }
         
         Ok(())
     }
 
+    // Config a collection opens with: the server's, the per-collection overrides, then its project's search defaults
+    fn collection_config(&self, cfg: &AppConfig, name: &str) -> crate::config::CollectionConfig {
+        let mut config = cfg.collection_config(name);
+        if let Some(search) = self.projects.of_collection(name).and_then(|p| p.config.search) {
+            config.search = search;
+        }
+        config
+    }
+
+    // Embedder for text sent to `collection`: its project's when the project has one, else the server's
+    pub fn embedder_for(&self, collection: &str) -> Option<Arc<dyn Embedder>> {
+        match self.projects.of_collection(collection) {
+            Some(project) if project.config.embedding.is_some() => project.embedder.clone(),
+            _ => self.embedder.clone(),
+        }
+    }
+
+    // Refuse an embed call once a monthly budget covering it is spent
+    pub fn check_usage(&self, scope: &UsageScope) -> Result<()> {
+        self.usage.check(scope, &self.app_config.read().usage.budgets)
+    }
+
+    // Count one embed call of `texts` texts in the embedding metrics and the usage ledger
+    pub fn record_embedding(&self, scope: &UsageScope, texts: u64, tokens: u64, latency: std::time::Duration) {
+        self.embed_metrics.record(1, texts, tokens, latency);
+        let retention_days = self.app_config.read().usage.retention_days;
+        self.usage.record(scope, UsageCounts { requests: 1, texts, tokens }, retention_days);
+    }
+
+    // Re-apply search defaults to open collections after their project changed
+    pub fn apply_project_settings(&self, collections: &[String]) {
+        let cfg = { self.app_config.read().clone() };
+        for name in collections {
+            if let Some(handle) = self.collections.get(name).map(|c| c.value().clone()) {
+                handle.write().set_search_defaults(self.collection_config(&cfg, name).search);
+                self.query_cache.invalidate(name);
+            }
+        }
+    }
+
+    // Vectors in a collection, from its metadata when it is not open; 0 when it does not exist
+    pub fn stored_count(&self, collection: &str) -> usize {
+        match self.collections.get(collection).map(|c| c.value().clone()) {
+            Some(handle) => handle.read().count(),
+            None => self.discovered.get(collection).map_or(0, |meta| meta.vector_count),
+        }
+    }
+
+    // Refuse `adding` more vectors to `collection`, which holds `count` now, when its project would go
+    // over max_vectors. The caller may hold the collection's lock, so its count is passed in.
+    pub fn ensure_project_quota(&self, collection: &str, count: usize, adding: usize) -> Result<()> {
+        let Some(project) = self.projects.of_collection(collection) else {
+            return Ok(());
+        };
+        let Some(max) = project.config.quotas.max_vectors else {
+            return Ok(());
+        };
+        let others: usize = project.config.collections.iter()
+            .filter(|name| *name != collection)
+            .map(|name| self.stored_count(name))
+            .sum();
+        if others.saturating_add(count).saturating_add(adding) > max {
+            return Err(ServerError::InvalidRequest(format!("Project '{}' max vectors reached", project.name())).into());
+        }
+        Ok(())
+    }
+
+    // List data_dir and register every collection found there from its metadata file, without
+    // opening data files or loading indexes. Collections whose preload policy is eager are then
+    // opened. Returns the number of collections registered.
+    pub fn discover_collections(&self) -> Result<usize> {
+        let cfg = { self.app_config.read().clone() };
+        let mut names = Vec::new();
+        for dir_entry in std::fs::read_dir(&self.data_dir)? {
+            let file_name = dir_entry?.file_name();
+            let Some(name) = file_name.to_str().and_then(|f| f.strip_suffix(".db.metadata.db")) else {
+                continue;
+            };
+            if crate::validation::validate_collection_name(name).is_err() {
+                continue;
+            }
+            let path = collection_path(&self.data_dir, name);
+            match crate::storage::load_metadata(&path) {
+                Ok(Some(metadata)) => {
+                    self.discovered.insert(name.to_string(), metadata);
+                    names.push(name.to_string());
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!(collection=%name, error=%e, "collection_metadata_unreadable"),
+            }
+        }
+        names.sort();
+
+        for name in &names {
+            if cfg.preload_policy(name) != PreloadPolicy::Eager {
+                continue;
+            }
+            let start = std::time::Instant::now();
+            match self.get_or_create_collection(name) {
+                Ok(()) => tracing::info!(collection=%name, elapsed_ms=start.elapsed().as_millis(), "collection_preloaded"),
+                Err(e) => tracing::warn!(collection=%name, error=%e, "collection_preload_failed"),
+            }
+        }
+        Ok(names.len())
+    }
+
+    // Replace the read replicas of a loaded collection; a count of 0 removes them
+    pub fn set_replicas(&self, name: &str, count: usize) -> Result<Option<Arc<ReplicaSet>>> {
+        let handle = self.collections.get(name)
+            .map(|c| c.value().clone())
+            .ok_or(ServerError::CollectionNotFound)?;
+        let mut storage = handle.write();
+        if count == 0 {
+            storage.drop_replicas();
+            self.replicas.remove(name);
+            return Ok(None);
+        }
+        let set = storage.create_replicas(count)?;
+        self.replicas.insert(name.to_string(), set.clone());
+        Ok(Some(set))
+    }
+
+    // Snapshots of a collection live under data_dir/snapshots/<collection>/<snapshot>
+    pub fn snapshot_root(&self, collection: &str) -> std::path::PathBuf {
+        std::path::Path::new(&self.data_dir).join("snapshots").join(collection)
+    }
+
+    pub fn create_snapshot(&self, collection: &str, name: &str) -> Result<SnapshotManifest> {
+        self.get_or_create_collection(collection)?;
+        let handle = self.collections.get(collection)
+            .map(|c| c.value().clone())
+            .ok_or(ServerError::CollectionNotFound)?;
+        let root = self.snapshot_root(collection);
+        std::fs::create_dir_all(&root)?;
+        let mut storage = handle.write();
+        create_snapshot(&mut storage, &root.join(name), name)
+    }
+
+    // Restore `collection`'s snapshot into `target` (default: the collection itself). Replacing a
+    // collection swaps the open Collection under its write lock, so requests see either the old or
+    // the restored data; a new target must not exist yet. Returns (target, replaced).
+    pub fn restore_snapshot(&self, collection: &str, name: &str, target: Option<&str>) -> Result<(String, bool)> {
+        let dir = self.snapshot_root(collection).join(name);
+        let target = target.unwrap_or(collection).to_string();
+        let path = collection_path(&self.data_dir, &target);
+        let exists = self.collections.contains_key(&target)
+            || self.discovered.contains_key(&target)
+            || std::path::Path::new(&path).exists();
+        if exists && target != collection {
+            return Err(ServerError::AlreadyExists(format!(
+                "Collection '{}' already exists; restore into a new name or into '{}' itself", target, collection
+            )).into());
+        }
+
+        // Copy first: the slow part runs without holding any lock
+        let manifest = stage_restore(&dir, &path)?;
+        if !exists {
+            let committed = commit_restore(&manifest, &path);
+            if committed.is_err() {
+                discard_restore(&manifest, &path);
+            }
+            committed?;
+            self.get_or_create_collection(&target)?;
+            return Ok((target, false));
+        }
+
+        if let Err(e) = self.get_or_create_collection(&target) {
+            discard_restore(&manifest, &path);
+            return Err(e);
+        }
+        let handle = self.collections.get(&target)
+            .map(|c| c.value().clone())
+            .ok_or(ServerError::CollectionNotFound)?;
+        let mut storage = handle.write();
+        // A sealed collection keeps its data; restore into a new name instead
+        if storage.is_sealed() {
+            discard_restore(&manifest, &path);
+            return Err(ServerError::CollectionSealed(format!(
+                "'{}' cannot be restored over; restore into a new name", target
+            )).into());
+        }
+        storage.wait_for_checkpoint();
+        if let Err(e) = commit_restore(&manifest, &path) {
+            discard_restore(&manifest, &path);
+            return Err(e);
+        }
+        let cfg = { self.app_config.read().clone() };
+        *storage = Collection::open_with_options(&path, CollectionOpenOptions::from(cfg.collection_config(&target)))?;
+        // The old replicas follow the replaced collection's WAL, so they are re-taken
+        if let Some(previous) = self.replicas_for(&target) {
+            self.replicas.insert(target.clone(), storage.create_replicas(previous.len())?);
+        }
+        self.discovered.remove(&target);
+        self.query_cache.invalidate(&target);
+        Ok((target, true))
+    }
+
+    // Rename a collection: its files move under the write lock, the collection is reopened from the
+    // new path and takes the new name in the registry before the lock is released, so requests see it
+    // under exactly one of the two names. Replicas, latency histograms, snapshots and project
+    // membership follow it. Refused while jobs of the collection are queued or running.
+    pub fn rename_collection(&self, from: &str, to: &str) -> Result<()> {
+        crate::validation::validate_collection_name(from)?;
+        crate::validation::validate_collection_name(to)?;
+        if from == to {
+            return Err(ServerError::InvalidRequest(format!("Collection is already named '{}'", to)).into());
+        }
+        let from_path = collection_path(&self.data_dir, from);
+        let to_path = collection_path(&self.data_dir, to);
+        let exists = |name: &str, path: &str| {
+            self.collections.contains_key(name) || self.discovered.contains_key(name) || std::path::Path::new(path).exists()
+        };
+        if !exists(from, &from_path) {
+            return Err(ServerError::CollectionNotFound.into());
+        }
+        if exists(to, &to_path) {
+            return Err(ServerError::AlreadyExists(format!("Collection '{}' already exists", to)).into());
+        }
+        if self.jobs.list(Some(from)).iter().any(|j| matches!(j.state, crate::jobs::JobState::Queued | crate::jobs::JobState::Running)) {
+            return Err(ServerError::Conflict(format!("Collection '{}' has unfinished jobs", from)).into());
+        }
+
+        self.get_or_create_collection(from)?;
+        let handle = self.collections.get(from)
+            .map(|c| c.value().clone())
+            .ok_or(ServerError::CollectionNotFound)?;
+        let mut storage = handle.write();
+        if storage.config().ephemeral {
+            return Err(ServerError::InvalidRequest("Ephemeral collections have no files to rename".into()).into());
+        }
+        storage.wait_for_checkpoint();
+        storage.checkpoint()?;
+        storage.flush()?;
+        rename_files(&from_path, &to_path)?;
+        // The project's search defaults apply to the reopened collection under its new name
+        if let Err(e) = self.projects.rename_collection(from, to) {
+            let _ = rename_files(&to_path, &from_path);
+            return Err(e);
+        }
+        let cfg = { self.app_config.read().clone() };
+        match Collection::open_with_options(&to_path, CollectionOpenOptions::from(self.collection_config(&cfg, to))) {
+            Ok(reopened) => {
+                *storage = reopened;
+                // Windows keeps the old lock file while it is open; it is released with the old collection
+                std::fs::remove_file(get_lock_path(&from_path)).ok();
+            }
+            Err(e) => {
+                let _ = self.projects.rename_collection(to, from);
+                let _ = rename_files(&to_path, &from_path);
+                return Err(e);
+            }
+        }
+
+        self.collections.insert(to.to_string(), handle.clone());
+        self.collections.remove(from);
+        self.discovered.remove(from);
+        self.query_cache.invalidate(from);
+        if let Some((_, tracker)) = self.latency_tracker.remove(from) {
+            self.latency_tracker.insert(to.to_string(), tracker);
+        }
+        // The old replicas follow the old collection's WAL, so they are re-taken
+        if let Some((_, previous)) = self.replicas.remove(from) {
+            self.replicas.insert(to.to_string(), storage.create_replicas(previous.len())?);
+        }
+        drop(storage);
+
+        std::fs::rename(self.latency_path(from), self.latency_path(to)).ok();
+        self.query_log.rename(from, to);
+        self.feedback.rename(from, to);
+        self.recall_monitor.forget(from);
+        let snapshots = self.snapshot_root(from);
+        if snapshots.exists() {
+            std::fs::rename(&snapshots, self.snapshot_root(to))?;
+        }
+        Ok(())
+    }
+
+    // Train (or retrain) the collection's projection. Replicas hold index-space vectors, so they are
+    // re-taken in the new space.
+    pub fn train_projection(&self, collection: &str, kind: ProjectionKind, dims: usize, sample_size: usize) -> Result<Projection> {
+        self.get_or_create_collection(collection)?;
+        let handle = self.collections.get(collection)
+            .map(|c| c.value().clone())
+            .ok_or(ServerError::CollectionNotFound)?;
+        let mut storage = handle.write();
+        let projection = storage.train_projection(kind, dims, sample_size)?.clone();
+        self.query_cache.invalidate(collection);
+        if let Some(previous) = self.replicas_for(collection) {
+            self.replicas.insert(collection.to_string(), storage.create_replicas(previous.len())?);
+        }
+        Ok(projection)
+    }
+
+    pub fn clear_projection(&self, collection: &str) -> Result<bool> {
+        self.get_or_create_collection(collection)?;
+        let handle = self.collections.get(collection)
+            .map(|c| c.value().clone())
+            .ok_or(ServerError::CollectionNotFound)?;
+        let mut storage = handle.write();
+        let cleared = storage.clear_projection()?;
+        if cleared {
+            self.query_cache.invalidate(collection);
+        }
+        if let (true, Some(previous)) = (cleared, self.replicas_for(collection)) {
+            self.replicas.insert(collection.to_string(), storage.create_replicas(previous.len())?);
+        }
+        Ok(cleared)
+    }
+
+    // Sweep search parameters under a shared lock; a kept recommendation changes the search defaults,
+    // which replicas copy, so they are re-taken
+    pub fn tune_collection(&self, collection: &str, opts: TuneOptions, persist: bool) -> Result<TuningReport> {
+        self.get_or_create_collection(collection)?;
+        let handle = self.collections.get(collection)
+            .map(|c| c.value().clone())
+            .ok_or(ServerError::CollectionNotFound)?;
+        let report = handle.read().tune(opts)?;
+        if persist {
+            let mut storage = handle.write();
+            storage.set_tuning(Some(report.recommended.clone()))?;
+            if let Some(previous) = self.replicas_for(collection) {
+                self.replicas.insert(collection.to_string(), storage.create_replicas(previous.len())?);
+            }
+        }
+        Ok(report)
+    }
+
+    pub fn clear_tuning(&self, collection: &str) -> Result<bool> {
+        self.get_or_create_collection(collection)?;
+        let handle = self.collections.get(collection)
+            .map(|c| c.value().clone())
+            .ok_or(ServerError::CollectionNotFound)?;
+        let mut storage = handle.write();
+        let cleared = storage.set_tuning(None)?;
+        if let (true, Some(previous)) = (cleared, self.replicas_for(collection)) {
+            self.replicas.insert(collection.to_string(), storage.create_replicas(previous.len())?);
+        }
+        Ok(cleared)
+    }
+
+    // A plain check runs under a shared lock; a repair rewrites the pointers and the index under the
+    // write lock and, when it changed anything, re-takes the replicas
+    pub fn verify_collection(&self, collection: &str, repair: bool) -> Result<VerifyReport> {
+        self.get_or_create_collection(collection)?;
+        let handle = self.collections.get(collection)
+            .map(|c| c.value().clone())
+            .ok_or(ServerError::CollectionNotFound)?;
+        if !repair {
+            return handle.read().verify();
+        }
+        let mut storage = handle.write();
+        let report = storage.repair()?;
+        if report.repaired {
+            self.query_cache.invalidate(collection);
+        }
+        if let (true, Some(previous)) = (report.repaired, self.replicas_for(collection)) {
+            self.replicas.insert(collection.to_string(), storage.create_replicas(previous.len())?);
+        }
+        Ok(report)
+    }
+
+    pub fn replicas_for(&self, name: &str) -> Option<Arc<ReplicaSet>> {
+        self.replicas.get(name).map(|r| r.value().clone())
+    }
+
     pub fn checkpoint_all(&self) -> Result<()> {
-        for mut entry in self.collections.iter_mut() {
-            let storage = entry.value_mut();
-            let mut storage_guard = storage.write();
-            storage_guard.checkpoint()?;
-            storage_guard.flush()?;
+        // A shared lock is enough: checkpoints serialize on the collection's own latches, so searches keep
+        // running. It is only held while the checkpoint is captured; writes resume while the files are written.
+        for entry in self.collections.iter() {
+            let pending = {
+                let storage_guard = entry.value().read();
+                storage_guard.flush()?;
+                storage_guard.begin_checkpoint()?
+            };
+            if let Some(pending) = pending {
+                pending.write()?;
+            }
+        }
+        self.usage.save()?;
+        self.feedback.save()?;
+        self.save_latency_histograms()
+    }
+
+    pub fn latency_path(&self, name: &str) -> std::path::PathBuf {
+        std::path::Path::new(&self.data_dir).join(format!("{name}.latency.json"))
+    }
+
+    // Write every collection's latency histograms to disk (no-op unless persist_latency_histograms is set)
+    pub fn save_latency_histograms(&self) -> Result<()> {
+        if !self.app_config.read().persist_latency_histograms {
+            return Ok(());
+        }
+        for entry in self.latency_tracker.iter() {
+            entry.value().save(&self.latency_path(entry.key()))?;
         }
         Ok(())
     }
 
-    pub fn reload_config(&self) -> Result<AppConfig> {
+    // Empty the latency histograms of one collection (or all of them) and drop their saved copies,
+    // so the next start does not bring the old distribution back. Returns the collections reset.
+    pub fn reset_latency_histograms(&self, collection: Option<&str>) -> Result<Vec<String>> {
+        let names: Vec<String> = match collection {
+            Some(name) => {
+                if !self.collections.contains_key(name) && !self.discovered.contains_key(name) {
+                    return Err(ServerError::CollectionNotFound.into());
+                }
+                vec![name.to_string()]
+            }
+            None => {
+                let mut names: Vec<String> = self.latency_tracker.iter().map(|e| e.key().clone()).collect();
+                names.extend(self.discovered.iter().map(|e| e.key().clone()));
+                names.sort();
+                names.dedup();
+                names
+            }
+        };
+        for name in &names {
+            if let Some(tracker) = self.latency_tracker.get(name) {
+                tracker.reset();
+            }
+            match std::fs::remove_file(self.latency_path(name)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(names)
+    }
+
+    // Swap in the config from its sources; see `apply_config`
+    pub fn reload_config(&self) -> Result<(AppConfig, BTreeMap<String, ConfigChanges>)> {
         let new_cfg = crate::config::loader::load_app_config();
-        {
-            let mut guard = self.app_config.write();
-            *guard = new_cfg.clone();
+        let changes = self.apply_config(new_cfg.clone());
+        Ok((new_cfg, changes))
+    }
+
+    // Swap in `new_cfg` and apply what changed to the open collections where that is safe while they
+    // stay open. Returns, per collection with changes, what was applied and what waits for a reopen.
+    pub fn apply_config(&self, new_cfg: AppConfig) -> BTreeMap<String, ConfigChanges> {
+        let old_cfg = std::mem::replace(&mut *self.app_config.write(), new_cfg.clone());
+        scheduler::configure(&new_cfg.scheduler);
+        self.jobs.wake();
+        let mut changes = BTreeMap::new();
+        for entry in self.collections.iter() {
+            let name = entry.key();
+            let old = self.collection_config(&old_cfg, name);
+            let new = self.collection_config(&new_cfg, name);
+            let report = entry.value().write().reconfigure(&old, &new);
+            if report.is_empty() {
+                continue;
+            }
+            if !report.applied.is_empty() {
+                self.query_cache.invalidate(name);
+            }
+            tracing::info!(collection=%name, applied=?report.applied, needs_reopen=?report.needs_reopen, "collection_reconfigured");
+            changes.insert(name.clone(), report);
         }
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
         self.config_last_reload.store(now, AtomicOrdering::Relaxed);
-        Ok(new_cfg)
+        changes
     }
 
     pub fn current_config(&self) -> AppConfig {
@@ -183,7 +684,7 @@ impl AppState {
         self.shutting_down.store(true, Ordering::Relaxed);
     }
 
-    fn disk_free_bytes(&self) -> Option<u64> {
+    pub fn disk_free_bytes(&self) -> Option<u64> {
         #[cfg(target_family = "unix")]
         {
             use std::ffi::CString;
@@ -198,18 +699,30 @@ impl AppState {
         None
     }
 
+    // Err while an operator holds the writes of `collection`, for deleting, renaming or restoring over it
+    pub fn ensure_not_paused(&self, collection: &str) -> Result<()> {
+        match self.collections.get(collection) {
+            Some(storage) => storage.read().ensure_not_paused(),
+            None => Ok(()),
+        }
+    }
+
     pub fn ensure_write_allowed(&self) -> Result<()> {
         if self.shutting_down.load(Ordering::Relaxed) {
             return Err(ServerError::ServiceUnavailable("Server is shutting down".into()).into());
         }
         if self.read_only.load(Ordering::Relaxed) {
-            return Err(ServerError::ServiceUnavailable("Server is in read-only mode due to low disk space".into()).into());
+            let reason = self.read_only_status.read().as_ref().map(|s| s.message.clone());
+            return Err(ServerError::ServiceUnavailable(format!(
+                "Server is in read-only mode ({}); free disk space, then POST /api/writes/resume",
+                reason.as_deref().unwrap_or("low disk space")
+            )).into());
         }
         if let Some(min_free) = self.disk_min_free_bytes {
             if let Some(free) = self.disk_free_bytes() {
                 if free < min_free {
                     if self.disk_readonly_on_low_space {
-                        self.read_only.store(true, Ordering::Relaxed);
+                        self.enter_read_only(ReadOnlyCause::LowDiskSpace, format!("{free} bytes free, under disk_min_free_bytes ({min_free})"));
                         return Err(ServerError::ServiceUnavailable("Low disk space; write operations disabled".into()).into());
                     } else {
                         tracing::warn!(free_bytes=free, min_free=min_free, "disk_space_low");
@@ -220,6 +733,90 @@ impl AppState {
         Ok(())
     }
 
+    // Turn writes away until `resume_writes`; the status keeps the first cause
+    pub fn enter_read_only(&self, cause: ReadOnlyCause, message: String) {
+        let mut status = self.read_only_status.write();
+        if status.is_none() {
+            tracing::error!(?cause, %message, "read_only_entered");
+            *status = Some(ReadOnlyStatus { cause, message, since: crate::testing::clock::now_secs() });
+        }
+        self.read_only.store(true, Ordering::Relaxed);
+    }
+
+    pub fn read_only_status(&self) -> Option<ReadOnlyStatus> {
+        if !self.read_only.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.read_only_status.read().clone()
+    }
+
+    // Let writes through again, returning why they were stopped. Refused while free space is still
+    // under `disk_min_free_bytes`.
+    pub fn resume_writes(&self) -> Result<Option<ReadOnlyStatus>> {
+        if let (Some(min_free), Some(free)) = (self.disk_min_free_bytes, self.disk_free_bytes()) {
+            if free < min_free {
+                return Err(ServerError::Conflict(format!(
+                    "Only {free} bytes free, under disk_min_free_bytes ({min_free}); free more space first"
+                )).into());
+            }
+        }
+        let previous = self.read_only_status.write().take();
+        if self.read_only.swap(false, Ordering::Relaxed) {
+            tracing::info!(cause=?previous.as_ref().map(|s| s.cause), "writes_resumed");
+        }
+        Ok(previous)
+    }
+
+    // WAL backpressure for a write to `collection`: waits while its backlog is over a throttle
+    // threshold and fails with 429 past a reject threshold. A collection not open yet has no backlog.
+    pub async fn throttle_writes(&self, collection: &str) -> Result<()> {
+        let pressure = match self.collections.get(collection) {
+            Some(storage) => storage.read().write_pressure()?,
+            None => return Ok(()),
+        };
+        match pressure {
+            WritePressure::None => Ok(()),
+            WritePressure::Throttle(delay) => {
+                tokio::time::sleep(delay).await;
+                Ok(())
+            }
+            WritePressure::Reject { retry_after } => {
+                let retry_after_secs = retry_after.as_secs_f64().ceil() as u64;
+                tracing::warn!(collection, retry_after_secs, "wal_backlog_write_rejected");
+                Err(ServerError::WriteThrottled {
+                    message: format!("WAL backlog of collection '{}' is over its limit until a checkpoint catches up", collection),
+                    retry_after_secs,
+                }.into())
+            }
+        }
+    }
+
+    // Read-your-writes: wait until `collection` has applied write sequence `min_seq`, up to
+    // `min_seq_wait_ms`. Writes publish to the read replicas under the collection's write lock, so
+    // once the collection is there a replica catches up to it when it is next read.
+    pub async fn wait_for_seq(&self, collection: &str, min_seq: Option<u64>) -> Result<()> {
+        let Some(min_seq) = min_seq else { return Ok(()) };
+        let deadline = std::time::Instant::now()
+            + std::time::Duration::from_millis(self.app_config.read().min_seq_wait_ms);
+        let mut backoff = std::time::Duration::from_millis(1);
+        loop {
+            let head = self.collections.get(collection)
+                .map(|storage| storage.read().head_seq())
+                .ok_or(ServerError::CollectionNotFound)?;
+            if head >= min_seq {
+                return Ok(());
+            }
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                return Err(ServerError::ServiceUnavailable(format!(
+                    "Collection '{}' is at sequence {}, not yet {}", collection, head, min_seq
+                )).into());
+            }
+            tokio::time::sleep(backoff.min(deadline - now)).await;
+            backoff = (backoff * 2).min(std::time::Duration::from_millis(50));
+        }
+    }
+
     pub fn enforce_cache_budget(&self) {
         let max_bytes = match self.cache_max_bytes {
             Some(v) => v,
@@ -2,7 +2,7 @@
 // Serde does the heavy lifting: Serialize = Rust → JSON, Deserialize = JSON → Rust.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 // =============================================================================
 // HEALTH
@@ -25,6 +25,13 @@ pub struct CollectionInfo {
     pub created_at: Option<u64>, // Timestamp when the collection was created (in seconds since UNIX epoch)
     pub updated_at: Option<u64>, // Timestamp when the collection was last updated (in seconds since UNIX epoch)
     pub dimensions: Option<usize>, // Number of dimensions for vectors in this collection, if known
+    pub loaded: bool, // Whether the collection is open; discovered collections are listed from their metadata until first use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>, // e.g. a corrupt index file being rebuilt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sealed_at: Option<u64>, // Set once the collection is sealed write-once
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counters: Option<crate::storage::CollectionCounters>, // Cumulative inserts/updates/deletes, maintenance times and dead bytes (GET /api/collections/:name)
 }
 
 #[derive(Serialize)]
@@ -35,6 +42,13 @@ pub struct CollectionsResponse {
 #[derive(Deserialize)]
 pub struct CreateCollectionRequest {
     pub name: String, // Name of the collection to create
+    #[serde(default)]
+    pub project: Option<String>, // Project to create it in (needs one of the project's API keys)
+}
+
+#[derive(Deserialize)]
+pub struct RenameCollectionRequest {
+    pub name: String, // New name; must not be taken
 }
 
 // =============================================================================
@@ -56,14 +70,23 @@ pub struct InsertRequest {
     pub metadata: HashMap<String, serde_json::Value>,
     #[serde(default)]  // per-item metadata for batch
     pub metadata_list: Vec<HashMap<String, serde_json::Value>>,
+    #[serde(default)]
+    pub external_id: Option<String>, // Optional client-provided id (unique per collection) for single insert
+    #[serde(default)]
+    pub external_ids: Vec<String>, // Optional client-provided ids for batch insert; must match vectors length when given
     #[serde(default)]  // if missing, defaults to false
     pub normalize: bool,  // Whether to normalize the vector(s) to unit length
+    #[serde(default)]
+    pub allow_model_mismatch: bool, // Embed text even if the configured model differs from the collection's recorded one
+    #[serde(default)]
+    pub allow_partial: bool, // Batch only: store the valid items and report each item's outcome instead of failing the request
 }
 
 // What we return after storing (single)
 #[derive(Serialize)]
 pub struct InsertResponse {
     pub id: String, // ID of the inserted vector (UUID string)
+    pub seq: u64, // Collection WAL sequence number after this write; pass it as min_seq to read the write back
     #[serde(skip_serializing_if = "Option::is_none")]
     pub latency_ms: Option<f32>, // Optional latency for the insert operation in milliseconds
 }
@@ -72,6 +95,7 @@ pub struct InsertResponse {
 pub struct MultiInsertResponse {
     pub ids: Vec<String>, // List of IDs for the inserted vectors (UUID strings)
     pub count: usize, // Number of vectors inserted
+    pub seq: u64, // Collection WAL sequence number after this write; pass it as min_seq to read the write back
     #[serde(skip_serializing_if = "Option::is_none")]
     pub latency_ms: Option<f32>, // Optional latency for the batch insert operation in milliseconds
 }
@@ -81,15 +105,59 @@ pub struct MultiInsertResponse {
 pub enum InsertResultsResponse {
     Single(InsertResponse), // Response for single vector insert
     Multi(MultiInsertResponse), // Response for batch vector insert
+    Partial(PartialBatchResponse), // Response for batch vector insert with allow_partial
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemStatus {
+    Ok,
+    Error,
+}
+
+// Outcome of one item of a partial batch
+#[derive(Serialize)]
+pub struct BatchItemResult {
+    pub index: usize, // Position of the item in the request
+    pub status: ItemStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>, // Document the item wrote or deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>, // Why the item failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<u16>, // HTTP status the item alone would have failed with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<crate::error::ErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retryable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed: Option<bool>, // Upserts with skip_unchanged: false when the stored document already matched
+}
+
+#[derive(Serialize)]
+pub struct PartialBatchResponse {
+    pub results: Vec<BatchItemResult>, // One per item, in request order
+    pub succeeded: usize,
+    pub failed: usize,
+    pub seq: u64, // Collection WAL sequence number after this write; pass it as min_seq to read the write back
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>,
 }
 
 // Full vector data returned to client
 #[derive(Serialize)]
 pub struct VectorResponse {
     pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>, // Client-provided id, if the vector was stored with one
     pub vector: Vec<f32>,
     pub text: String,
-    pub metadata: HashMap<String, serde_json::Value>,
+    pub metadata: BTreeMap<String, serde_json::Value>, // Ordered by key, so the same document always reads back the same
+    pub version: u64, // 1 when inserted, bumped by every rewrite; pass it as if_version to make a write conditional
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>, // When the document was first inserted (unix seconds); None for documents written before it was kept
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<u64>, // When it was last written
 }
 
 // Query params for listing vectors: ?limit=100&offset=0
@@ -99,6 +167,30 @@ pub struct ListVectorsQuery {
     pub limit: usize, // How many vectors to return (default 100)
     #[serde(default)]
     pub offset: usize, // How many vectors to skip for pagination (default 0)
+    #[serde(default)]
+    pub sort: Option<DocumentSort>, // created_at / updated_at, or -created_at / -updated_at for newest first
+    #[serde(default)]
+    pub min_seq: Option<u64>, // Wait (bounded) until the collection has applied this write sequence before reading
+}
+
+// Order of a vector listing by the engine-maintained timestamps; documents without one sort as oldest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum DocumentSort {
+    #[serde(rename = "created_at")]
+    CreatedAt,
+    #[serde(rename = "-created_at")]
+    CreatedAtDesc,
+    #[serde(rename = "updated_at")]
+    UpdatedAt,
+    #[serde(rename = "-updated_at")]
+    UpdatedAtDesc,
+}
+
+// Query parameters of single-document reads
+#[derive(Deserialize)]
+pub struct ReadQuery {
+    #[serde(default)]
+    pub min_seq: Option<u64>, // Wait (bounded) until the collection has applied this write sequence before reading
 }
 
 fn default_limit() -> usize { 100 }
@@ -113,6 +205,8 @@ pub struct SearchRequest {
     pub vector: Option<Vec<f32>>,
     #[serde(default)]
     pub vectors: Option<Vec<Vec<f32>>>,
+    #[serde(default)]
+    pub id: Option<String>, // Search with this stored document's vector (UUID or client id); the document itself is left out
     #[serde(default = "default_k")]
     pub k: usize,  // how many results to return
     #[serde(default)]
@@ -125,16 +219,56 @@ pub struct SearchRequest {
     pub overfetch: Option<usize>,
     #[serde(default)]
     pub preset: Option<String>, // "fast", "balanced", "high"
+    #[serde(default)]
+    pub dedup_by: Option<String>, // Metadata key; hits sharing its value collapse to the best-scoring one
+    #[serde(default)]
+    pub score_expr: Option<String>, // Ranks candidates by e.g. "0.8 * similarity + 0.2 * log(1 + metadata.popularity)"
+    #[serde(default)]
+    pub order_by: Option<crate::search::OrderBy>, // Sort hits by a metadata field, or break score ties with it
+    #[serde(default)]
+    pub score_bands: Option<crate::search::ScoreBands>, // Bucket hits into named relevance tiers, with per-tier counts
+    #[serde(default)]
+    pub allow_metric_mismatch: bool, // Search with a metric other than the index's; candidates are re-ranked by it
+    #[serde(default)]
+    pub min_seq: Option<u64>, // Wait (bounded) until the collection has applied this write sequence before reading
+    #[serde(default)]
+    pub target_ms: Option<f32>, // Latency budget: ef/nprobe are picked per query to fit it (ef/nprobe/preset give the starting value)
+    #[serde(default)]
+    pub explain: bool, // Return how the search was carried out (execution mode, strategy, whether scores are exact)
+    #[serde(default)]
+    pub exclude_ids: Vec<String>, // Leave these documents out (UUIDs or client ids), e.g. results already shown
+    #[serde(default)]
+    pub exclude_filter: Option<HashMap<String, serde_json::Value>>, // Leave out documents whose metadata has all these values
+    #[serde(default)]
+    pub timeout_ms: Option<u64>, // Deadline for a single-vector search; on_timeout says what is returned past it
+    #[serde(default)]
+    pub on_timeout: SearchTimeoutFallback,
 }
 
 fn default_k() -> usize { 10 }
 
-#[derive(Serialize)]
+// What a search past its timeout_ms returns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchTimeoutFallback {
+    #[default]
+    Error, // a 408 Timeout error
+    Partial, // the hits of the search's quick first round (partial), else the query cache's last results
+    Cached, // the query cache's last results for the query (stale), else the quick round's hits
+}
+
+#[derive(Clone, Serialize)]
 pub struct HitResponse {
     pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>, // Client-provided id, if the vector was stored with one
     pub score: f32, // Similarity score (higher is more similar)
     pub text: String,
-    pub metadata: HashMap<String, serde_json::Value>, // Metadata associated with the vector
+    pub metadata: BTreeMap<String, serde_json::Value>, // Metadata associated with the vector
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub band: Option<String>, // Score band the hit falls in, when score_bands were requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerank_score: Option<f32>, // Relevance the feedback re-ranker puts on the hit, on re-ranked collections
 }
 
 #[derive(Serialize)]
@@ -142,6 +276,18 @@ pub struct SearchResponse {
     pub results: Vec<HitResponse>, 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub latency_ms: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective: Option<crate::search::EffectiveSearch>, // Parameters a search with target_ms ran with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explain: Option<crate::search::SearchExplain>, // Requested with explain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bands: Option<Vec<crate::search::BandCount>>, // Hits per score band, counted before score_bands.only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_id: Option<String>, // Id in the query log, to send click feedback for; with query_log enabled
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool, // Past timeout_ms: the best hits found by then, not the full search's
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool, // The last results computed for the query, before recent writes: past timeout_ms, or cached within query_cache.max_seq_lag
 }
 
 #[derive(Serialize)]
@@ -149,6 +295,10 @@ pub struct MultiSearchResponse {
     pub results: Vec<Vec<HitResponse>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub latency_ms: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bands: Option<Vec<Vec<crate::search::BandCount>>>, // Hits per score band, per query
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_ids: Option<Vec<String>>, // Query log id per query; with query_log enabled
 }
 
 #[derive(Serialize)]
@@ -166,17 +316,29 @@ pub enum SearchResultsResponse {
 pub struct DeleteResponse {
     pub deleted: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>, // Collection WAL sequence number after a document delete; pass it as min_seq to read the write back
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub latency_ms: Option<f32>,
 }
 
+// Query params for deleting one vector: ?if_version=N deletes it only at that version, else 409
+#[derive(Deserialize)]
+pub struct DeleteVectorQuery {
+    #[serde(default)]
+    pub if_version: Option<u64>,
+}
+
 #[derive(Deserialize)]
 pub struct DeleteVectorsRequest {
     pub ids: Vec<String>,
+    #[serde(default)]
+    pub allow_partial: bool, // Report each id's outcome; unknown ids become errors instead of being skipped
 }
 
 #[derive(Serialize)]
 pub struct MultiDeleteResponse {
     pub deleted_count: usize,
+    pub seq: u64, // Collection WAL sequence number after this write; pass it as min_seq to read the write back
     #[serde(skip_serializing_if = "Option::is_none")]
     pub latency_ms: Option<f32>,
 }
@@ -186,6 +348,7 @@ pub struct MultiDeleteResponse {
 pub enum DeleteResultsResponse {
     Single(DeleteResponse),
     Multi(MultiDeleteResponse),
+    Partial(PartialBatchResponse),
 }
 
 #[derive(Serialize)]
@@ -208,6 +371,8 @@ pub struct EmbedRequest {
     pub metadata: HashMap<String, serde_json::Value>, // Metadata to associate with the vector(s)
     #[serde(default)]
     pub metadata_list: Vec<HashMap<String, serde_json::Value>>, // For batch embedding, a list of metadata maps corresponding to each text
+    #[serde(default)]
+    pub allow_model_mismatch: bool, // Embed even if the configured model differs from the collection's recorded one
 }
 
 // Response from embedding and storing
@@ -216,12 +381,14 @@ pub struct EmbedResponse {
     pub id: String,
     pub embedding: Vec<f32>,
     pub tokens: Option<u32>,
+    pub seq: u64, // Collection WAL sequence number after this write; pass it as min_seq to read the write back
 }
 
 #[derive(Serialize)]
 pub struct MultiEmbedResponse {
     pub ids: Vec<String>,
     pub embeddings: Vec<Vec<f32>>,
+    pub seq: u64, // Collection WAL sequence number after this write; pass it as min_seq to read the write back
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_tokens: Option<u32>,
 }
@@ -237,7 +404,10 @@ pub enum EmbedResultsResponse {
 // Request to search by text query (auto-embeds)
 #[derive(Deserialize)]
 pub struct TextSearchRequest {
-    pub query: String,
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub queries: Option<Vec<String>>, // Batch: embedded in one call and searched in parallel; results come back in this order
     #[serde(default = "default_k")]
     pub k: usize,
     #[serde(default)]
@@ -250,27 +420,111 @@ pub struct TextSearchRequest {
     pub overfetch: Option<usize>,
     #[serde(default)]
     pub preset: Option<String>,
+    #[serde(default)]
+    pub dedup_by: Option<String>, // Metadata key; hits sharing its value collapse to the best-scoring one
+    #[serde(default)]
+    pub score_expr: Option<String>, // Ranks candidates by e.g. "0.8 * similarity + 0.2 * log(1 + metadata.popularity)"
+    #[serde(default)]
+    pub order_by: Option<crate::search::OrderBy>, // Sort hits by a metadata field, or break score ties with it
+    #[serde(default)]
+    pub score_bands: Option<crate::search::ScoreBands>, // Bucket hits into named relevance tiers, with per-tier counts
+    #[serde(default)]
+    pub allow_model_mismatch: bool, // Search even if the configured model differs from the collection's recorded one
+    #[serde(default)]
+    pub allow_metric_mismatch: bool, // Search with a metric other than the index's; candidates are re-ranked by it
+    #[serde(default)]
+    pub min_seq: Option<u64>, // Wait (bounded) until the collection has applied this write sequence before reading
+    #[serde(default)]
+    pub exclude_ids: Vec<String>, // Leave these documents out (UUIDs or client ids), e.g. results already shown
+    #[serde(default)]
+    pub exclude_filter: Option<HashMap<String, serde_json::Value>>, // Leave out documents whose metadata has all these values
 }
 
 // =============================================================================
 // UPSERT
 // =============================================================================
 
+// A single upsert sets vector and text; a batch sends them as items instead
 #[derive(Deserialize)]
 pub struct UpsertRequest {
-    pub id: Option<String>,  // If provided, use this ID; otherwise generate new
+    #[serde(default)]
+    pub id: Option<String>,  // If provided, use this ID (UUID or client-provided id); otherwise generate new
+    #[serde(default)]
+    pub external_id: Option<String>, // Client-provided id to store with the vector; upserts the vector that already has it
+    #[serde(default)]
+    pub vector: Option<Vec<f32>>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub normalize: bool,  // Whether to normalize the vector(s)
+    #[serde(default)]
+    pub items: Option<Vec<UpsertItem>>, // Batch upsert, applied in order
+    #[serde(default)]
+    pub allow_partial: bool, // Batch only: apply the valid items and report each item's outcome instead of failing the request
+    #[serde(default)]
+    pub if_version: Option<u64>, // Single only: write only if the document is at this version (0: only if it does not exist), else 409
+    #[serde(default)]
+    pub skip_unchanged: bool, // Leave documents whose stored vector, text and metadata already match alone: no WAL write, no index update
+}
+
+#[derive(Deserialize)]
+pub struct UpsertItem {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub external_id: Option<String>,
     pub vector: Vec<f32>,
     pub text: String,
     #[serde(default)]
     pub metadata: HashMap<String, serde_json::Value>,
-    #[serde(default)]
-    pub normalize: bool,  // Whether to normalize the vector
 }
 
 #[derive(Serialize)]
 pub struct UpsertResponse {
     pub id: String,
     pub created: bool,  // true if inserted, false if updated
+    pub changed: bool, // false when skip_unchanged found the stored document identical and nothing was written
+    pub version: u64, // Version the document was written as
+    pub seq: u64, // Collection WAL sequence number after this write; pass it as min_seq to read the write back
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>,
+}
+
+#[derive(Serialize)]
+pub struct MultiUpsertResponse {
+    pub ids: Vec<String>,
+    pub created: usize, // How many of the items were inserted rather than updated
+    pub unchanged: usize, // How many of the items skip_unchanged left alone
+    pub seq: u64, // Collection WAL sequence number after this write; pass it as min_seq to read the write back
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum UpsertResultsResponse {
+    Single(UpsertResponse),
+    Multi(MultiUpsertResponse),
+    Partial(PartialBatchResponse),
+}
+
+#[derive(Deserialize)]
+pub struct UpdateMetadataRequest {
+    pub metadata: HashMap<String, serde_json::Value>, // Replaces the document's metadata
+    #[serde(default)]
+    pub external_id: Option<String>, // New client id for the document; the current one is kept when left out
+    #[serde(default)]
+    pub if_version: Option<u64>, // Update only if the document is at this version, else 409
+}
+
+#[derive(Serialize)]
+pub struct UpdateMetadataResponse {
+    pub updated: bool, // false when the document does not exist
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>, // Version the document was written as
+    pub seq: u64, // Collection WAL sequence number after this write; pass it as min_seq to read the write back
     #[serde(skip_serializing_if = "Option::is_none")]
     pub latency_ms: Option<f32>,
 }
@@ -279,6 +533,9 @@ pub struct UpsertResponse {
 // METRICS
 // =============================================================================
 pub mod range;
+pub mod body;
+pub mod schema;
+pub mod ndjson;
 
 #[derive(Serialize)]
 pub struct MetricsResponse {
@@ -288,6 +545,8 @@ pub struct MetricsResponse {
     pub app_config: crate::config::AppConfig,
     pub wal_stats: Vec<WalStats>,
     pub embedding: EmbeddingMetricsResponse,
+    pub load_shedding: crate::server::shedding::LoadSheddingStats,
+    pub query_cache: crate::server::query_cache::QueryCacheStats,
 }
 
 #[derive(Serialize)]
@@ -303,6 +562,11 @@ pub struct CollectionMetrics {
     pub search_overfetch: Option<usize>, // Average overfetch factor used in search operations for this collection
     pub hnsw_ef_search: Option<usize>, // Average ef_search parameter used in HNSW search operations for this collection
     pub ivf_nprobe: Option<usize>, // Average nprobe parameter used in IVF search operations for this collection
+    pub latency: std::collections::BTreeMap<&'static str, crate::metrics::LatencySummary>, // Quantiles per operation (insert, search, lock_read, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_spill: Option<crate::index::IvfSpillStats>, // IVF lists on disk and probe hits/misses, under memory.index_memory_budget
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recall: Option<crate::server::recall_monitor::RecallStatus>, // Last live recall measurement, with recall_monitor.enabled
 }
 
 #[derive(Serialize)]
@@ -311,6 +575,7 @@ pub struct WalStats {
     pub last_checkpoint: Option<u64>,
     pub checkpoint_age_secs: Option<u64>, // Age of the last checkpoint in seconds
     pub wal_size_bytes: Option<u64>, // Total size of the WAL file for this collection in bytes
+    pub backlog: crate::storage::collection::WalBacklog, // What checkpoints have not caught up with, checked by wal.backpressure
 }
 
 #[derive(Serialize)]
@@ -356,23 +621,532 @@ pub struct DuplicateResponse {
     pub pairs: Vec<DuplicatePair>,
 }
 
+// =============================================================================
+// READ REPLICAS
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct SetReplicasRequest {
+    pub replicas: usize, // In-memory read replicas to keep; 0 turns them off
+}
+
+#[derive(Serialize)]
+pub struct ReplicasResponse {
+    pub replicas: usize,
+    pub pending_writes: usize, // Writes not yet applied by every replica
+    pub applied_seq: Vec<u64>, // Last WAL sequence applied, per replica
+}
+
+// =============================================================================
+// POINT-IN-TIME READS
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct HistorySearchRequest {
+    #[serde(default)]
+    pub as_of_seq: Option<u64>, // WAL sequence number to read at
+    #[serde(default)]
+    pub as_of_timestamp: Option<u64>, // Unix seconds; resolved to the newest checkpoint at or before it
+    pub vector: Vec<f32>,
+    #[serde(default = "default_k")]
+    pub k: usize,
+    #[serde(default)]
+    pub metric: Option<String>,
+    #[serde(default)]
+    pub allow_metric_mismatch: bool, // Search with a metric other than the index's; candidates are re-ranked by it
+}
+
+#[derive(Serialize)]
+pub struct HistorySearchResponse {
+    pub as_of_seq: u64,
+    pub count: usize, // Vectors in the collection at that point
+    pub results: Vec<HitResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>,
+}
+
+// GET /changes?since_seq=N&limit=M&include_vectors=false
+#[derive(Deserialize)]
+pub struct ChangesQuery {
+    #[serde(default)]
+    pub since_seq: u64, // Changes with a higher sequence number; 0 for everything still retained
+    #[serde(default = "default_changes_limit")]
+    pub limit: usize,
+    #[serde(default = "default_include_vectors")]
+    pub include_vectors: bool,
+}
+
+fn default_changes_limit() -> usize { 1000 }
+
+fn default_include_vectors() -> bool { true }
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOp {
+    Insert,
+    Update,
+    Delete,
+}
+
+#[derive(Serialize)]
+pub struct ChangeRecord {
+    pub seq: u64,
+    pub op: ChangeOp,
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>, // Client id of the written document (not known for deletes)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<BTreeMap<String, serde_json::Value>>,
+}
+
+#[derive(Serialize)]
+pub struct ChangesResponse {
+    pub changes: Vec<ChangeRecord>,
+    pub next_seq: u64, // Pass as since_seq to read on from here
+    pub oldest_seq: u64, // Changes after this sequence number are still retained
+    pub head_seq: u64, // Latest sequence number written
+}
+
+#[derive(Serialize)]
+pub struct HistoryResponse {
+    pub enabled: bool,
+    pub head_seq: u64, // Latest sequence number written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest_seq: Option<u64>, // Oldest sequence number a view can be opened at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest_timestamp: Option<u64>,
+    pub segments: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention_secs: Option<u64>,
+}
+
+// =============================================================================
+// SNAPSHOTS
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct CreateSnapshotRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize, Default)]
+pub struct RestoreSnapshotRequest {
+    // Collection to restore into; defaults to the snapshot's own collection, which is then replaced.
+    // Any other name must not exist yet and is created from the snapshot.
+    #[serde(default)]
+    pub target: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SnapshotInfo {
+    pub name: String,
+    pub collection: String, // Collection the snapshot was taken from
+    pub created_at: u64,
+    pub vector_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<usize>,
+    pub head_seq: u64, // WAL sequence the snapshot corresponds to
+    pub size_bytes: u64,
+}
+
+impl From<crate::storage::collection::SnapshotManifest> for SnapshotInfo {
+    fn from(manifest: crate::storage::collection::SnapshotManifest) -> Self {
+        Self {
+            size_bytes: manifest.size_bytes(),
+            name: manifest.name,
+            collection: manifest.collection,
+            created_at: manifest.created_at,
+            vector_count: manifest.vector_count,
+            dimensions: manifest.dimensions,
+            head_seq: manifest.head_seq,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SnapshotsResponse {
+    pub snapshots: Vec<SnapshotInfo>,
+}
+
+#[derive(Serialize)]
+pub struct RestoreSnapshotResponse {
+    pub collection: String, // Collection that now holds the snapshot's data
+    pub replaced: bool, // true when an existing collection was replaced
+    pub count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>,
+}
+
+// =============================================================================
+// PROJECTION
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct TrainProjectionRequest {
+    #[serde(default = "default_projection_kind")]
+    pub kind: crate::storage::collection::ProjectionKind, // "pca" or "opq"
+    pub dims: usize, // Output dimensions the index works on
+    #[serde(default)]
+    pub sample_size: Option<usize>, // Vectors to train on (default 10000)
+}
+
+fn default_projection_kind() -> crate::storage::collection::ProjectionKind {
+    crate::storage::collection::ProjectionKind::Pca
+}
+
+#[derive(Serialize)]
+pub struct ProjectionInfo {
+    pub kind: crate::storage::collection::ProjectionKind,
+    pub input_dims: usize,
+    pub output_dims: usize,
+    pub sample_size: usize,
+    pub explained_variance: f32, // Share of the sample's variance the output dimensions keep
+    pub trained_at: u64,
+}
+
+impl From<&crate::storage::collection::Projection> for ProjectionInfo {
+    fn from(projection: &crate::storage::collection::Projection) -> Self {
+        Self {
+            kind: projection.kind,
+            input_dims: projection.input_dims,
+            output_dims: projection.output_dims,
+            sample_size: projection.sample_size,
+            explained_variance: projection.explained_variance,
+            trained_at: projection.trained_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProjectionResponse {
+    pub projection: Option<ProjectionInfo>, // None while the index works on the full (transformed) vectors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub struct TuneRequest {
+    #[serde(default)]
+    pub queries: Option<Vec<Vec<f32>>>, // Sample queries (default: an evenly spread sample of stored vectors)
+    #[serde(default)]
+    pub ground_truth: Option<Vec<Vec<String>>>, // Expected ids per query, best first (default: computed by exact search)
+    #[serde(default)]
+    pub sample_size: Option<usize>, // Stored vectors to sample when no queries are given (default 100)
+    #[serde(default = "default_k")]
+    pub k: usize,
+    #[serde(default)]
+    pub target_recall: Option<f32>, // Recall the recommendation has to reach (default 0.95)
+    #[serde(default)]
+    pub values: Option<Vec<usize>>, // ef or nprobe values to try (default: doubling sweep)
+    #[serde(default = "default_persist_tuning")]
+    pub persist: bool, // Keep the recommendation as the collection's search default
+}
+
+fn default_persist_tuning() -> bool {
+    true
+}
+
+#[derive(Serialize)]
+pub struct TuneResponse {
+    #[serde(flatten)]
+    pub report: crate::storage::collection::TuningReport,
+    pub persisted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>,
+}
+
+#[derive(Serialize)]
+pub struct TuningResponse {
+    pub tuning: Option<crate::storage::collection::TuningPreset>, // None while the configured search settings apply unchanged
+    pub search: crate::config::SearchConfig, // Effective search defaults
+}
+
+#[derive(Deserialize, Default)]
+pub struct VerifyRequest {
+    #[serde(default)]
+    pub repair: bool, // Fix what the check finds (default: only report)
+}
+
+#[derive(Serialize)]
+pub struct VerifyResponse {
+    #[serde(flatten)]
+    pub report: crate::storage::collection::VerifyReport,
+    pub consistent: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ExportRequest {
+    #[serde(default)]
+    pub format: crate::storage::collection::ExportFormat, // parquet (default) or arrow
+    #[serde(default)]
+    pub filter: Option<HashMap<String, serde_json::Value>>, // only documents whose metadata has these values
+}
+
+#[derive(Deserialize)]
+pub struct ArrowExportQuery {
+    #[serde(default = "default_arrow_batch_rows")]
+    pub batch_rows: usize, // Documents per record batch (default 1024)
+}
+
+fn default_arrow_batch_rows() -> usize {
+    crate::storage::collection::EXPORT_BATCH_ROWS
+}
+
+#[derive(Deserialize)]
+pub struct ArrowImportQuery {
+    #[serde(default = "default_import_batch_size")]
+    pub batch_size: usize, // Rows written per write lock (default 1000)
+}
+
+#[derive(Deserialize, Default)]
+pub struct SealRequest {
+    #[serde(default)]
+    pub index: Option<crate::index::IndexConfig>, // rebuild the index as this before sealing; the configured one when unset
+}
+
+#[derive(Serialize)]
+pub struct SealResponse {
+    #[serde(flatten)]
+    pub report: crate::storage::collection::SealReport,
+    pub latency_ms: f32,
+}
+
+#[derive(Serialize)]
+pub struct ArrowImportResponse {
+    #[serde(flatten)]
+    pub report: crate::storage::collection::DocumentImportReport, // rows, inserted, updated, skipped_columns
+    pub seq: u64,
+    pub latency_ms: f32,
+}
+
+#[derive(Deserialize, Default)]
+pub struct VectorStatsRequest {
+    #[serde(default)]
+    pub filter: Option<HashMap<String, serde_json::Value>>, // only documents whose metadata has these values
+    #[serde(default)]
+    pub histogram_bins: Option<usize>, // Norm histogram bins (default 20)
+    #[serde(default)]
+    pub intrinsic_sample: Option<usize>, // Points for the intrinsic dimensionality estimate (default 1000, 0 skips it)
+}
+
+#[derive(Serialize)]
+pub struct VectorStatsResponse {
+    #[serde(flatten)]
+    pub stats: crate::storage::collection::VectorStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>,
+}
+
+#[derive(Serialize)]
+pub struct SampledStatsResponse {
+    #[serde(flatten)]
+    pub stats: crate::storage::collection::SampledStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>,
+}
+
+// GET /api/usage?granularity=day&from=..&to=..&provider=..&model=..&collection=..&api_key=..
+#[derive(Deserialize)]
+pub struct UsageQuery {
+    #[serde(default)]
+    pub granularity: crate::server::usage::Granularity, // hour, day (default) or month, in UTC
+    #[serde(default)]
+    pub from: Option<u64>, // unix seconds; default the start of the current month
+    #[serde(default)]
+    pub to: Option<u64>, // unix seconds, exclusive; default now
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub collection: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>, // the key itself or its fingerprint
+}
+
+#[derive(Serialize)]
+pub struct UsageResponse {
+    pub granularity: crate::server::usage::Granularity,
+    pub from: u64,
+    pub to: u64,
+    pub buckets: Vec<crate::server::usage::UsageBucket>, // per bucket and provider/model/collection/API key, oldest first
+    pub totals: crate::server::usage::UsageCounts,
+    pub budgets: Vec<crate::server::usage::BudgetStatus>, // every configured budget with this month's usage
+}
+
+// GET /api/slow_queries?collection=..&limit=..
+#[derive(Deserialize)]
+pub struct SlowQueriesQuery {
+    #[serde(default)]
+    pub collection: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>, // newest first; default all that are held
+}
+
+#[derive(Serialize)]
+pub struct SlowQueriesResponse {
+    pub enabled: bool,
+    pub threshold_ms: u128, // SLOW_QUERY_MS
+    pub capacity: usize,
+    pub held: usize, // captured queries in the buffer, across collections
+    pub queries: Vec<crate::server::slow_queries::SlowQuery>, // newest first
+}
+
+#[derive(Serialize)]
+pub struct ClearSlowQueriesResponse {
+    pub cleared: usize,
+}
+
+// POST /api/collections/:collection/queries/:query_id/feedback
+#[derive(Deserialize)]
+pub struct QueryFeedbackRequest {
+    pub clicked: Vec<String>, // ids or external ids of the documents used from the hits
+}
+
+#[derive(Serialize)]
+pub struct QueryFeedbackResponse {
+    pub query_id: String,
+    pub clicked: usize,
+}
+
+// GET /api/collections/:collection/queries/export?since=..
+#[derive(Deserialize)]
+pub struct QueryLogExportQuery {
+    #[serde(default)]
+    pub since: Option<u64>, // unix ms; the whole log when absent
+}
+
+#[derive(Serialize)]
+pub struct ClearQueryLogResponse {
+    pub cleared: bool,
+}
+
+// POST /api/collections/:collection/feedback
+#[derive(Deserialize)]
+pub struct FeedbackRequest {
+    pub document_id: String, // UUID or client id
+    pub signal: crate::server::feedback::Signal, // "click", "positive", "skip", "negative"
+    #[serde(default)]
+    pub score: Option<f32>, // score the document was shown with; the re-ranker learns from signals that have one
+    #[serde(default)]
+    pub query_id: Option<String>, // search the document was shown for; clicks are added to the query log
+}
+
+#[derive(Serialize)]
+pub struct ClearFeedbackResponse {
+    pub cleared: bool,
+}
+
+// DELETE /api/metrics/latency?collection=..
+#[derive(Deserialize)]
+pub struct LatencyResetQuery {
+    #[serde(default)]
+    pub collection: Option<String>, // all collections when absent
+}
+
+#[derive(Serialize)]
+pub struct LatencyResetResponse {
+    pub reset: Vec<String>, // collections whose histograms were emptied
+}
+
+#[derive(Serialize)]
+pub struct DimensionsResponse {
+    #[serde(flatten)]
+    pub report: crate::storage::collection::DimensionReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>, // model recorded for server-side embeddings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>, // how to get rid of mismatched documents, when there are any
+}
+
+#[derive(Deserialize, Default)]
+pub struct OutlierRequest {
+    #[serde(default)]
+    pub vectors: Vec<Vec<f32>>, // Vectors to score
+    #[serde(default)]
+    pub ids: Vec<String>, // Stored documents to score (document or client-provided ids)
+    #[serde(default)]
+    pub method: crate::storage::collection::OutlierMethod, // knn (default) or centroid
+    #[serde(default)]
+    pub k: Option<usize>, // Neighbour whose similarity is the knn score (default 10)
+    #[serde(default)]
+    pub sample_size: Option<usize>, // Stored documents the percentiles are ranked against (default 200)
+}
+
+#[derive(Serialize)]
+pub struct OutlierResponse {
+    #[serde(flatten)]
+    pub report: crate::storage::collection::OutlierReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>,
+}
+
 // =============================================================================
 // INDEX STATISTICS
 // =============================================================================
 
+#[derive(Deserialize)]
+pub struct ImportIndexRequest {
+    pub path: String, // Server-side directory holding the bundle (manifest.json, vectors.jsonl, graph.json/clusters.json)
+}
+
+#[derive(Serialize)]
+pub struct ImportIndexResponse {
+    pub index_type: String,
+    pub imported: usize,
+    pub id_map: HashMap<String, String>, // external id -> stored id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub struct IndexStatsQuery {
+    #[serde(default)]
+    pub diagnostics: bool, // HNSW connectivity and a sampled recall estimate (scans every stored vector)
+    #[serde(default)]
+    pub sample_size: Option<usize>, // Stored vectors sampled as recall queries (default 100)
+    #[serde(default = "default_k")]
+    pub k: usize,
+}
+
 #[derive(Serialize)]
 pub struct IndexStatsResponse {
     pub index_type: String,
+    pub metric: &'static str, // Metric the index was built with
     pub total_vectors: usize, // Total number of vectors indexed
     pub memory_usage_bytes: usize, // Approximate memory usage of the index in bytes
     pub details: serde_json::Value, // Index-specific details as a JSON value (e.g., HNSW layer sizes, IVF cluster counts)
 }
 
+#[derive(Deserialize)]
+pub struct MigrateIndexRequest {
+    pub index: crate::index::IndexConfig, // e.g. {"type": "Ivf", "num_clusters": 256, ...}, as in the index config
+}
+
+#[derive(Deserialize)]
+pub struct RequantizeRequest {
+    pub level: crate::config::QuantizationLevel, // e.g. "Int8" or {"Pq": {"subquantizers": 8}}, as in the quantization config
+    #[serde(default = "default_requantize_batch_size")]
+    pub batch_size: usize, // Documents re-encoded per collection write
+}
+
+fn default_requantize_batch_size() -> usize {
+    1024
+}
+
 #[derive(Serialize)]
 pub struct RebuildIndexResponse {
     pub success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub latency_ms: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>, // Set when the work was queued as a job rather than done in the request
 }
 
 #[derive(Serialize)]
@@ -386,6 +1160,8 @@ pub struct RebuildIndexStatusResponse {
     pub elapsed_ms: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
 }
 
 // =============================================================================
@@ -405,6 +1181,8 @@ pub struct ConfigReloadResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reloaded_at: Option<u64>, // Timestamp of when the config was reloaded (in seconds since UNIX epoch)
     pub app_config: crate::config::AppConfig,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub collections: BTreeMap<String, crate::storage::collection::ConfigChanges>, // per open collection whose settings changed
 }
 
 // =============================================================================
@@ -430,6 +1208,8 @@ pub struct CollectionHealth {
     pub integrity_ok: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -444,5 +1224,131 @@ pub struct ReadyzResponse {
     pub disk_total_bytes: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub disk_available_bytes: Option<u64>,
+    pub writable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<super::read_only::ReadOnlyStatus>,
     pub collections: Vec<CollectionHealth>,
 }
+
+// =============================================================================
+// WRITES (read-only mode)
+// =============================================================================
+
+#[derive(Serialize)]
+pub struct WritesStatusResponse {
+    pub writable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<super::read_only::ReadOnlyStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_available_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_min_free_bytes: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct ResumeWritesResponse {
+    pub resumed: bool, // false when writes were not stopped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<super::read_only::ReadOnlyStatus>, // what had stopped them
+}
+
+#[derive(Deserialize)]
+pub struct PauseWritesRequest {
+    #[serde(default = "default_write_pause_secs")]
+    pub timeout_secs: u64, // Writes are accepted again after this long even without a resume
+    #[serde(default)]
+    pub reason: Option<String>, // Shown to rejected writers, e.g. "lvm snapshot"
+}
+
+impl Default for PauseWritesRequest {
+    fn default() -> Self {
+        Self { timeout_secs: default_write_pause_secs(), reason: None }
+    }
+}
+
+fn default_write_pause_secs() -> u64 {
+    crate::storage::collection::DEFAULT_WRITE_PAUSE_SECS
+}
+
+#[derive(Serialize)]
+pub struct CollectionWritesResponse {
+    pub collection: String,
+    pub paused: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause: Option<crate::storage::collection::WritePause>, // the pause in force, or the one a resume lifted
+}
+
+// =============================================================================
+// INGEST
+// =============================================================================
+
+#[derive(Serialize)]
+pub struct IngestStatusResponse {
+    pub sources: Vec<crate::ingest::IngestStatus>,
+}
+
+// =============================================================================
+// JOBS
+// =============================================================================
+
+#[derive(Deserialize)]
+pub struct ListJobsQuery {
+    pub collection: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct JobsResponse {
+    pub jobs: Vec<crate::jobs::Job>,
+}
+
+#[derive(Deserialize)]
+pub struct ReembedRequest {
+    #[serde(default = "default_reembed_batch_size")]
+    pub batch_size: usize,
+}
+
+fn default_reembed_batch_size() -> usize {
+    64
+}
+
+#[derive(Deserialize)]
+pub struct ImportDocumentsRequest {
+    pub path: String, // Server-side file
+    #[serde(default)]
+    pub format: Option<crate::storage::collection::SourceFormat>, // From the extension when left out (.parquet, .arrow, .feather)
+    #[serde(default)]
+    pub mapping: crate::storage::collection::FieldMapping,
+    #[serde(default = "default_import_batch_size")]
+    pub batch_size: usize,
+}
+
+fn default_import_batch_size() -> usize {
+    crate::storage::collection::DEFAULT_IMPORT_BATCH_SIZE
+}
+
+// =============================================================================
+// PROJECTS
+// =============================================================================
+
+#[derive(Serialize)]
+pub struct ProjectEmbeddingInfo {
+    pub provider: String,
+    pub model: String,
+}
+
+// A project as returned by the API; its API keys and the provider key are never echoed back
+#[derive(Serialize)]
+pub struct ProjectInfo {
+    pub name: String,
+    pub collections: Vec<String>,
+    pub embedding: Option<ProjectEmbeddingInfo>,
+    pub api_keys: usize, // Number of keys configured
+    pub search: Option<crate::config::SearchConfig>,
+    pub quotas: crate::server::projects::ProjectQuotas,
+    pub vectors: usize, // Vectors across the project's collections
+}
+
+#[derive(Serialize)]
+pub struct ProjectsResponse {
+    pub projects: Vec<ProjectInfo>,
+}
@@ -0,0 +1,235 @@
+//! Request and response bodies in JSON or MessagePack.
+//! `Payload<T>` decodes the body by its Content-Type (JSON when it is not a MessagePack type, so
+//! existing clients see no change); `Format` is taken from the Accept header and `Format::reply`
+//! encodes the handler's response in it. Error bodies stay JSON whatever the client asked for.
+//! `Payload` reads the body chunk by chunk up to `limits.max_body_bytes` (413 beyond it); a body
+//! past `limits.spool_threshold_bytes` goes to a temp file under data_dir/.spool and is decoded
+//! from a map of that file, so a multi-MB document is not held in memory twice.
+//! A JSON body that parses but does not fit `T` is answered with a 422 listing each bad field by
+//! JSON pointer (see schema.rs) instead of serde's first error alone.
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::{rejection::JsonRejection, FromRef, FromRequest, FromRequestParts, Request},
+    http::{header, request::Parts, HeaderValue},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::LimitsConfig;
+use crate::error::ServerError;
+use crate::server::msgpack;
+use crate::server::state::SharedState;
+use super::schema;
+
+const SPOOL_DIR: &str = ".spool";
+
+pub struct Payload<T>(pub T);
+
+impl<T, S> FromRequest<S> for Payload<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+    SharedState: FromRef<S>,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Response> {
+        let is_msgpack = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(msgpack::is_msgpack);
+        let app = SharedState::from_ref(state);
+        let limits = app.app_config.read().limits;
+        let (parts, body) = req.into_parts();
+        let decode_error = |e: String| ServerError::InvalidRequest(e).into_response();
+        match read_body(&parts, body, &limits, &app.data_dir).await.map_err(IntoResponse::into_response)? {
+            Buffered::Memory(bytes) if !is_msgpack => {
+                let bytes = Bytes::from(bytes);
+                let req = Request::from_parts(parts, Body::from(bytes.clone()));
+                let Json(value) = Json::<T>::from_request(req, state).await.map_err(|e| json_rejection::<T>(e, &bytes))?;
+                Ok(Payload(value))
+            }
+            Buffered::Memory(bytes) => msgpack::from_slice(&bytes).map(Payload).map_err(|e| decode_error(e.to_string())),
+            Buffered::Spooled(spool) => {
+                let map = spool.map().map_err(IntoResponse::into_response)?;
+                if is_msgpack {
+                    msgpack::from_slice(&map).map(Payload).map_err(|e| decode_error(e.to_string()))
+                } else {
+                    Json::<T>::from_bytes(&map).map(|Json(value)| Payload(value)).map_err(|e| json_rejection::<T>(e, &map))
+                }
+            }
+        }
+    }
+}
+
+// A body that is JSON but not a `T` gets every mismatched field back (see schema.rs); other
+// rejections (content type, syntax) are axum's own
+fn json_rejection<T: DeserializeOwned>(rejection: JsonRejection, body: &[u8]) -> Response {
+    match rejection {
+        JsonRejection::JsonDataError(_) => {
+            let errors = schema::field_errors::<T>(body);
+            if errors.is_empty() { rejection.into_response() } else { schema::rejection(errors) }
+        }
+        _ => rejection.into_response(),
+    }
+}
+
+enum Buffered {
+    Memory(Vec<u8>),
+    Spooled(Spool),
+}
+
+// A request body written to data_dir/.spool; the file is removed when this is dropped
+struct Spool {
+    path: PathBuf,
+}
+
+impl Spool {
+    fn map(&self) -> Result<memmap2::Mmap, ServerError> {
+        let file = std::fs::File::open(&self.path).map_err(|e| ServerError::Internal(format!("spool: {e}")))?;
+        // The file is private to this request and no longer written
+        unsafe { memmap2::Mmap::map(&file) }.map_err(|e| ServerError::Internal(format!("spool: {e}")))
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn too_large(max_bytes: usize) -> ServerError {
+    ServerError::PayloadTooLarge(format!("Request body is over {max_bytes} bytes (max set by limits.max_body_bytes)"))
+}
+
+// Read the body up to limits.max_body_bytes, moving it to a spool file once it passes the threshold
+async fn read_body(parts: &Parts, mut body: Body, limits: &LimitsConfig, data_dir: &str) -> Result<Buffered, ServerError> {
+    let declared = parts
+        .headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared.is_some_and(|len| len > limits.max_body_bytes as u64) {
+        return Err(too_large(limits.max_body_bytes));
+    }
+
+    let spool_error = |e: std::io::Error| ServerError::Internal(format!("spool: {e}"));
+    let mut buf = Vec::new();
+    let mut spool: Option<(Spool, tokio::fs::File)> = None;
+    let mut total = 0usize;
+    while let Some(frame) = std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await {
+        let frame = frame.map_err(|e| ServerError::InvalidRequest(format!("Failed to read request body: {e}")))?;
+        let Ok(chunk) = frame.into_data() else { continue };
+        total += chunk.len();
+        if total > limits.max_body_bytes {
+            return Err(too_large(limits.max_body_bytes));
+        }
+        if spool.is_none() && total > limits.spool_threshold_bytes {
+            let dir = Path::new(data_dir).join(SPOOL_DIR);
+            tokio::fs::create_dir_all(&dir).await.map_err(spool_error)?;
+            let path = dir.join(format!("{}.body", uuid::Uuid::new_v4()));
+            let file = tokio::fs::File::create(&path).await.map_err(spool_error)?;
+            spool = Some((Spool { path }, file));
+        }
+        match spool.as_mut() {
+            Some((_, file)) => {
+                if !buf.is_empty() {
+                    file.write_all(&buf).await.map_err(spool_error)?;
+                    buf = Vec::new();
+                }
+                file.write_all(&chunk).await.map_err(spool_error)?;
+            }
+            None => buf.extend_from_slice(&chunk),
+        }
+    }
+    match spool {
+        Some((spool, mut file)) => {
+            file.flush().await.map_err(spool_error)?;
+            Ok(Buffered::Spooled(spool))
+        }
+        None => Ok(Buffered::Memory(buf)),
+    }
+}
+
+// Remove spool files left behind by a crash; called at startup
+pub fn clear_spool(data_dir: &str) {
+    let _ = std::fs::remove_dir_all(Path::new(data_dir).join(SPOOL_DIR));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    MsgPack,
+}
+
+impl Format {
+    // MessagePack only when the client names it and does not prefer JSON; `*/*` alone means JSON
+    pub fn from_accept(accept: &str) -> Self {
+        let (mut msgpack_q, mut json_q, mut any_q) = (0.0f32, None, None);
+        for item in accept.split(',') {
+            let mut parts = item.split(';');
+            let media = parts.next().unwrap_or("").trim();
+            let q = parts
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if msgpack::is_msgpack(media) {
+                msgpack_q = msgpack_q.max(q);
+            } else if media.eq_ignore_ascii_case("application/json") {
+                json_q = Some(q);
+            } else if media == "*/*" || media.eq_ignore_ascii_case("application/*") {
+                any_q = Some(any_q.unwrap_or(0.0f32).max(q));
+            }
+        }
+        let json_q = json_q.or(any_q).unwrap_or(0.0);
+        if msgpack_q > 0.0 && msgpack_q >= json_q { Format::MsgPack } else { Format::Json }
+    }
+
+    pub fn reply<T: Serialize>(self, value: T) -> Reply<T> {
+        Reply { format: self, value }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Format {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Infallible> {
+        Ok(parts
+            .headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .map(Format::from_accept)
+            .unwrap_or(Format::Json))
+    }
+}
+
+pub struct Reply<T> {
+    format: Format,
+    value: T,
+}
+
+impl<T> Reply<T> {
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Serialize> IntoResponse for Reply<T> {
+    fn into_response(self) -> Response {
+        let mut res = match self.format {
+            Format::Json => Json(self.value).into_response(),
+            Format::MsgPack => match msgpack::to_vec(&self.value) {
+                Ok(bytes) => ([(header::CONTENT_TYPE, HeaderValue::from_static(msgpack::CONTENT_TYPE))], bytes).into_response(),
+                Err(e) => return ServerError::Internal(e.to_string()).into_response(),
+            },
+        };
+        res.headers_mut().append(header::VARY, HeaderValue::from_static("accept"));
+        res
+    }
+}
@@ -0,0 +1,116 @@
+//! Search hits as newline-delimited JSON, for candidate generation with k in the thousands.
+//! A client that sends `Accept: application/x-ndjson` gets one line per hit instead of one JSON
+//! document. Lines are serialized while the body is being sent, a chunk at a time, so neither the
+//! whole encoded response is held in memory nor does the first byte wait for the last hit. Hits of a
+//! batch search carry the index of their query as `query`. The last line sums the response up:
+//! `{"done": true, "hits": n, "latency_ms": ...}` plus `effective` and `explain` when they apply;
+//! a stream that ends without it was cut short.
+use axum::{
+    body::{Body, Bytes},
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::convert::Infallible;
+
+use super::{HitResponse, SearchResultsResponse};
+
+pub const CONTENT_TYPE: &str = "application/x-ndjson";
+
+// Hits encoded per body chunk
+const HITS_PER_CHUNK: usize = 256;
+
+// Whether the client asked for NDJSON and does not prefer plain JSON
+pub fn accepts(accept: &str) -> bool {
+    let (mut ndjson_q, mut json_q) = (0.0f32, 0.0f32);
+    for item in accept.split(',') {
+        let mut parts = item.split(';');
+        let media = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let q = parts
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match media.as_str() {
+            "application/x-ndjson" | "application/ndjson" | "application/jsonl" => ndjson_q = ndjson_q.max(q),
+            "application/json" => json_q = json_q.max(q),
+            _ => {}
+        }
+    }
+    ndjson_q > 0.0 && ndjson_q >= json_q
+}
+
+// Extractor: true when the response should be streamed as NDJSON
+pub struct Streamed(pub bool);
+
+impl<S: Send + Sync> FromRequestParts<S> for Streamed {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Infallible> {
+        Ok(Streamed(parts.headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()).is_some_and(accepts)))
+    }
+}
+
+#[derive(Serialize)]
+struct HitLine<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<usize>,
+    #[serde(flatten)]
+    hit: &'a HitResponse,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    done: bool,
+    hits: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    effective: Option<crate::search::EffectiveSearch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explain: Option<crate::search::SearchExplain>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bands: Option<serde_json::Value>, // a single search's band counts, or one list per query
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query_ids: Option<Vec<String>>,
+}
+
+fn line<T: Serialize>(out: &mut Vec<u8>, value: &T) -> Result<(), serde_json::Error> {
+    serde_json::to_writer(&mut *out, value)?;
+    out.push(b'\n');
+    Ok(())
+}
+
+// Stream a search response, hits first and the summary last
+pub fn search_stream(response: SearchResultsResponse) -> Response {
+    let (hits, mut summary): (Vec<(Option<usize>, HitResponse)>, Summary) = match response {
+        SearchResultsResponse::Single(single) => (
+            single.results.into_iter().map(|hit| (None, hit)).collect(),
+            Summary { done: true, hits: 0, latency_ms: single.latency_ms, effective: single.effective, explain: single.explain, bands: single.bands.map(|b| serde_json::json!(b)), query_id: single.query_id, query_ids: None },
+        ),
+        SearchResultsResponse::Multi(multi) => (
+            multi.results.into_iter().enumerate().flat_map(|(query, hits)| hits.into_iter().map(move |hit| (Some(query), hit))).collect(),
+            Summary { done: true, hits: 0, latency_ms: multi.latency_ms, effective: None, explain: None, bands: multi.bands.map(|b| serde_json::json!(b)), query_id: None, query_ids: multi.query_ids },
+        ),
+    };
+    summary.hits = hits.len();
+
+    let mut hits = hits.into_iter();
+    let mut summary = Some(summary);
+    let chunks = std::iter::from_fn(move || {
+        let mut out = Vec::new();
+        let encoded = if hits.len() > 0 {
+            hits.by_ref().take(HITS_PER_CHUNK).try_for_each(|(query, hit)| line(&mut out, &HitLine { query, hit: &hit }))
+        } else {
+            line(&mut out, &summary.take()?)
+        };
+        Some(encoded.map(|_| Bytes::from(out)))
+    });
+
+    let mut res = Body::from_stream(futures_util::stream::iter(chunks)).into_response();
+    res.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE));
+    res.headers_mut().append(header::VARY, HeaderValue::from_static("accept"));
+    res
+}
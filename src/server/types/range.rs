@@ -20,4 +20,14 @@ pub struct RangeSearchRequest {
     pub overfetch: Option<usize>,
     #[serde(default)]
     pub preset: Option<String>,
+    #[serde(default)]
+    pub dedup_by: Option<String>,
+    #[serde(default)]
+    pub order_by: Option<crate::search::OrderBy>, // Sort the hits above min_score by a metadata field, or break score ties with it
+    #[serde(default)]
+    pub score_bands: Option<crate::search::ScoreBands>, // Bucket the hits above min_score into named relevance tiers
+    #[serde(default)]
+    pub allow_metric_mismatch: bool, // Search with a metric other than the index's; candidates are re-ranked by it
+    #[serde(default)]
+    pub min_seq: Option<u64>, // Wait (bounded) until the collection has applied this write sequence before searching
 }
@@ -0,0 +1,149 @@
+//! Field-level errors for JSON bodies that do not match their request type.
+//! serde stops at the first mismatch and axum reports it as an opaque 422. When that happens the
+//! body is decoded again as a `serde_json::Value` and checked against the type once per error:
+//! each mismatch is reported with the JSON pointer of the offending value and the type serde
+//! expected there. A mismatch inside an array (one bad vector component, one bad batch item) is
+//! patched over with another item of the array, and a mistyped object field is taken out, before
+//! checking again, so a batch reports every bad item rather than only the first. The check ends at an error
+//! neither of those gets past (a required field, a bad item with no good sibling).
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use serde_path_to_error::Segment;
+
+use crate::error::ErrorCode;
+
+// Mismatches reported for one body
+pub const MAX_FIELD_ERRORS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub pointer: String, // RFC 6901 pointer into the request body; "" is the body itself
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>, // what serde wanted at `pointer`, e.g. "f32" or "a sequence"
+}
+
+// Every mismatch between `body` and `T`, in the order serde meets them (object keys sorted);
+// empty when `body` is not valid JSON or decodes fine
+pub fn field_errors<T: DeserializeOwned>(body: &[u8]) -> Vec<FieldError> {
+    let Ok(mut value) = serde_json::from_slice::<Value>(body) else { return Vec::new() };
+    let mut errors: Vec<FieldError> = Vec::new();
+    let mut first_items: HashMap<String, usize> = HashMap::new(); // array pointer -> item copied over its first
+    let mut tried: HashMap<String, HashSet<usize>> = HashMap::new(); // array pointer -> items tried there
+    let mut removed: HashSet<(String, String)> = HashSet::new(); // (object pointer, key) taken out
+
+    for _ in 0..MAX_FIELD_ERRORS * 2 {
+        let err = match serde_path_to_error::deserialize::<_, T>(&value) {
+            Ok(_) => break,
+            Err(err) => err,
+        };
+        let segments: Vec<&Segment> = err.path().iter().collect();
+        let mut at = pointer(&segments);
+        let message = err.inner().to_string();
+        let item = innermost_item(&segments);
+
+        // An error in an item copied over the first one belongs to the item it was copied from
+        if let Some((array, 0)) = &item {
+            if let Some(source) = first_items.get(array) {
+                at = format!("{array}/{source}{}", &at[array.len() + 2..]);
+            }
+        }
+        // A field taken out that turns out to be required was reported already
+        let required = removed.iter().any(|(object, key)| *object == at && message == format!("missing field `{key}`"));
+        if !required && !errors.iter().any(|e| e.pointer == at) {
+            errors.push(FieldError { pointer: at, expected: expected(&message), message });
+            if errors.len() >= MAX_FIELD_ERRORS {
+                break;
+            }
+        }
+
+        // A mistyped field is taken out, which is enough when it is optional
+        if let (Some(Segment::Map { key }), false) = (segments.last(), required) {
+            let object = pointer(&segments[..segments.len() - 1]);
+            if let Some(fields) = value.pointer_mut(&object).and_then(Value::as_object_mut) {
+                if fields.remove(key.as_str()).is_some() {
+                    removed.insert((object, key.clone()));
+                    continue;
+                }
+            }
+        }
+
+        // Otherwise the array item holding the error is replaced: items before it decoded fine, so
+        // the first one will do; the first item itself is replaced by each later one until one fits
+        let Some((array, index)) = item else { break };
+        let Some(items) = value.pointer_mut(&array).and_then(Value::as_array_mut) else { break };
+        let source = if index > 0 {
+            Some(0)
+        } else {
+            let tried = tried.entry(array.clone()).or_default();
+            (1..items.len()).find(|i| tried.insert(*i))
+        };
+        let Some(source) = source else { break };
+        items[index] = items[source].clone();
+        if index == 0 {
+            first_items.insert(array, source);
+        }
+    }
+    errors
+}
+
+// The array and index of the last sequence element on the path
+fn innermost_item(segments: &[&Segment]) -> Option<(String, usize)> {
+    let at = segments.iter().rposition(|s| matches!(s, Segment::Seq { .. }))?;
+    let Segment::Seq { index } = segments[at] else { return None };
+    if segments[..at].iter().any(|s| matches!(s, Segment::Unknown)) {
+        return None;
+    }
+    Some((pointer(&segments[..at]), *index))
+}
+
+fn pointer(segments: &[&Segment]) -> String {
+    let mut out = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Seq { index } => out.push_str(&format!("/{index}")),
+            Segment::Map { key } => out.push_str(&format!("/{}", key.replace('~', "~0").replace('/', "~1"))),
+            Segment::Enum { variant } => out.push_str(&format!("/{}", variant.replace('~', "~0").replace('/', "~1"))),
+            Segment::Unknown => break,
+        }
+    }
+    out
+}
+
+// serde messages name what they wanted after "expected"
+fn expected(message: &str) -> Option<String> {
+    let (_, wanted) = message.split_once("expected ")?;
+    Some(wanted.trim().to_string())
+}
+
+// 422 with the usual error body plus every field error
+pub fn rejection(errors: Vec<FieldError>) -> Response {
+    let status = StatusCode::UNPROCESSABLE_ENTITY;
+    let code = ErrorCode::ValidationFailed;
+    let message = match errors.first() {
+        Some(first) if errors.len() > 1 => {
+            format!("Validation failed: {}: {} (and {} more)", display(&first.pointer), first.message, errors.len() - 1)
+        }
+        Some(first) => format!("Validation failed: {}: {}", display(&first.pointer), first.message),
+        None => "Validation failed: request body does not match the expected type".to_string(),
+    };
+    let body = Json(json!({
+        "error": message,
+        "code": status.as_u16(),
+        "error_code": code,
+        "retryable": code.retryable(),
+        "errors": errors,
+    }));
+    (status, body).into_response()
+}
+
+fn display(pointer: &str) -> &str {
+    if pointer.is_empty() { "/" } else { pointer }
+}
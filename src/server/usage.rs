@@ -0,0 +1,325 @@
+// Embedding usage accounting: every call to an embedding provider (text inserts, /embed,
+// text search, ingestion, re-embed jobs) is counted in hourly buckets per provider, model,
+// collection and API key, so the cost of a shared provider can be split between the teams using
+// it. Stored in data_dir/usage.json (temp file + rename), written at most every
+// SAVE_INTERVAL_SECS while calls come in and on checkpoint.
+//
+// - GET /api/usage sums the buckets by hour, day or calendar month (UTC)
+// - `usage.budgets` in the config cap tokens and/or requests per calendar month for the calls in a
+//   scope; once a budget is spent, further calls in its scope fail with 429 until the month ends.
+//   The call that crosses the limit still completes, so a budget can be overshot by one call.
+// - API keys are kept as fingerprints (FNV-1a), never in the clear
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::UsageBudget;
+use crate::embeddings::Embedder;
+use crate::error::{Result, ServerError};
+
+const HOUR: u64 = 3600;
+const DAY: u64 = 86_400;
+
+// Longest the ledger goes unsaved while calls are being recorded
+const SAVE_INTERVAL_SECS: u64 = 5;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// Stable fingerprint of an API key, so usage can be told apart per key without storing it
+pub fn key_fingerprint(key: &str) -> String {
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+    format!("key-{hash:016x}")
+}
+
+// Days since 1970-01-01 to (year, month) and back (Howard Hinnant's civil calendar algorithms)
+fn civil_from_days(days: i64) -> (i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month)
+}
+
+fn days_from_civil(year: i64, month: i64) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5;
+    era * 146_097 + yoe * 365 + yoe / 4 - yoe / 100 + doy - 719_468
+}
+
+// First second of the UTC calendar month holding `ts`
+pub fn month_start(ts: u64) -> u64 {
+    let (year, month) = civil_from_days((ts / DAY) as i64);
+    days_from_civil(year, month) as u64 * DAY
+}
+
+// First second of the month after the one holding `ts`
+fn next_month_start(ts: u64) -> u64 {
+    month_start(month_start(ts) + 32 * DAY)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+    Hour,
+    #[default]
+    Day,
+    Month,
+}
+
+impl Granularity {
+    fn bucket(self, ts: u64) -> u64 {
+        match self {
+            Granularity::Hour => ts - ts % HOUR,
+            Granularity::Day => ts - ts % DAY,
+            Granularity::Month => month_start(ts),
+        }
+    }
+}
+
+// Who a call is accounted to
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct UsageScope {
+    pub provider: String,
+    pub model: String,
+    pub collection: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>, // fingerprint of the request's x-api-key
+}
+
+impl UsageScope {
+    pub fn new(embedder: &dyn Embedder, collection: &str, api_key: Option<&str>) -> Self {
+        Self {
+            provider: embedder.provider_name().to_string(),
+            model: embedder.model_name().to_string(),
+            collection: collection.to_string(),
+            api_key: api_key.map(key_fingerprint),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageCounts {
+    pub requests: u64, // embed calls
+    pub texts: u64,
+    pub tokens: u64, // as reported by the provider
+}
+
+impl UsageCounts {
+    pub fn add(&mut self, other: &UsageCounts) {
+        self.requests = self.requests.saturating_add(other.requests);
+        self.texts = self.texts.saturating_add(other.texts);
+        self.tokens = self.tokens.saturating_add(other.tokens);
+    }
+}
+
+// One bucket as stored and as reported: `start` is the first second of its hour, day or month
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageBucket {
+    pub start: u64,
+    #[serde(flatten)]
+    pub scope: UsageScope,
+    #[serde(flatten)]
+    pub counts: UsageCounts,
+}
+
+// Narrows a report to the calls matching every field that is set
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UsageFilter {
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub collection: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>, // the key itself or its fingerprint
+}
+
+impl UsageFilter {
+    fn matches(&self, scope: &UsageScope) -> bool {
+        let field = |want: &Option<String>, have: &str| want.as_deref().is_none_or(|w| w == have);
+        field(&self.provider, &scope.provider)
+            && field(&self.model, &scope.model)
+            && field(&self.collection, &scope.collection)
+            && self.api_key.as_deref().is_none_or(|key| {
+                scope.api_key.as_deref().is_some_and(|fp| fp == key || *fp == key_fingerprint(key))
+            })
+    }
+}
+
+impl From<&UsageBudget> for UsageFilter {
+    fn from(budget: &UsageBudget) -> Self {
+        Self {
+            provider: budget.provider.clone(),
+            model: budget.model.clone(),
+            collection: budget.collection.clone(),
+            api_key: budget.api_key.clone(),
+        }
+    }
+}
+
+// A budget with what its scope used this month
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetStatus {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_requests: Option<u64>,
+    pub used: UsageCounts,
+    pub exceeded: bool,
+    pub resets_at: u64, // start of next month (UTC)
+}
+
+// Readable scope of a budget, without its API key
+fn describe(budget: &UsageBudget) -> String {
+    let mut parts = Vec::new();
+    for (label, value) in [("provider", &budget.provider), ("model", &budget.model), ("collection", &budget.collection)] {
+        if let Some(value) = value {
+            parts.push(format!("{label} '{value}'"));
+        }
+    }
+    if let Some(key) = &budget.api_key {
+        parts.push(format!("api key {}", key_fingerprint(key)));
+    }
+    if parts.is_empty() { "all embedding calls".to_string() } else { parts.join(", ") }
+}
+
+pub struct UsageLedger {
+    path: PathBuf,
+    buckets: Mutex<BTreeMap<(u64, UsageScope), UsageCounts>>, // by hour
+    last_save: AtomicU64,
+}
+
+impl UsageLedger {
+    // Load data_dir/usage.json; an unreadable file starts empty
+    pub fn open(data_dir: &str) -> Self {
+        let path = Path::new(data_dir).join("usage.json");
+        let stored: Vec<UsageBucket> = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                tracing::warn!(path=%path.display(), error=%e, "usage_unreadable");
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        let buckets = stored.into_iter().map(|b| ((b.start, b.scope), b.counts)).collect();
+        Self { path, buckets: Mutex::new(buckets), last_save: AtomicU64::new(now()) }
+    }
+
+    pub fn record(&self, scope: &UsageScope, counts: UsageCounts, retention_days: u64) {
+        let now = now();
+        self.buckets.lock().entry((Granularity::Hour.bucket(now), scope.clone())).or_default().add(&counts);
+        let last = self.last_save.load(Ordering::Relaxed);
+        if now.saturating_sub(last) >= SAVE_INTERVAL_SECS
+            && self.last_save.compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed).is_ok()
+        {
+            self.prune(now, retention_days);
+            if let Err(e) = self.save() {
+                tracing::warn!(error=%e, "usage_save_failed");
+            }
+        }
+    }
+
+    fn prune(&self, now: u64, retention_days: u64) {
+        let cutoff = now.saturating_sub(retention_days.saturating_mul(DAY));
+        self.buckets.lock().retain(|(hour, _), _| *hour >= cutoff);
+    }
+
+    // Usage of the calls matching `filter` since `from`
+    fn total(&self, filter: &UsageFilter, from: u64) -> UsageCounts {
+        let buckets = self.buckets.lock();
+        let mut total = UsageCounts::default();
+        for (_, counts) in buckets.range((from, UsageScope::default())..).filter(|((_, scope), _)| filter.matches(scope)) {
+            total.add(counts);
+        }
+        total
+    }
+
+    // Every budget with this month's usage of its scope
+    pub fn budgets(&self, budgets: &[UsageBudget]) -> Vec<BudgetStatus> {
+        let now = now();
+        budgets
+            .iter()
+            .map(|budget| {
+                let used = self.total(&UsageFilter::from(budget), month_start(now));
+                let exceeded = budget.max_tokens.is_some_and(|max| used.tokens >= max)
+                    || budget.max_requests.is_some_and(|max| used.requests >= max);
+                BudgetStatus {
+                    name: budget.name.clone(),
+                    scope: describe(budget),
+                    max_tokens: budget.max_tokens,
+                    max_requests: budget.max_requests,
+                    used,
+                    exceeded,
+                    resets_at: next_month_start(now),
+                }
+            })
+            .collect()
+    }
+
+    // Refuse a call in `scope` once a budget covering it is spent
+    pub fn check(&self, scope: &UsageScope, budgets: &[UsageBudget]) -> Result<()> {
+        let covering: Vec<UsageBudget> = budgets.iter().filter(|b| UsageFilter::from(*b).matches(scope)).cloned().collect();
+        let Some(spent) = self.budgets(&covering).into_iter().find(|b| b.exceeded) else {
+            return Ok(());
+        };
+        let limit = match (spent.max_tokens, spent.max_requests) {
+            (Some(max), _) if spent.used.tokens >= max => format!("{}/{max} tokens", spent.used.tokens),
+            (_, Some(max)) => format!("{}/{max} requests", spent.used.requests),
+            _ => String::new(),
+        };
+        let name = spent.name.map(|n| format!("'{n}' ")).unwrap_or_default();
+        Err(ServerError::BudgetExceeded(format!(
+            "monthly embedding budget {name}({}) is spent: {limit} used; it resets at {} (unix time)",
+            spent.scope, spent.resets_at
+        )).into())
+    }
+
+    // Usage of the calls matching `filter` in the hours from the one holding `from` up to `to`, summed by `granularity`
+    pub fn report(&self, granularity: Granularity, from: u64, to: u64, filter: &UsageFilter) -> Vec<UsageBucket> {
+        let mut summed: BTreeMap<(u64, UsageScope), UsageCounts> = BTreeMap::new();
+        {
+            let buckets = self.buckets.lock();
+            for ((hour, scope), counts) in buckets.range((Granularity::Hour.bucket(from), UsageScope::default())..) {
+                if *hour >= to {
+                    break;
+                }
+                if filter.matches(scope) {
+                    summed.entry((granularity.bucket(*hour), scope.clone())).or_default().add(counts);
+                }
+            }
+        }
+        summed.into_iter().map(|((start, scope), counts)| UsageBucket { start, scope, counts }).collect()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let stored: Vec<UsageBucket> = self
+            .buckets
+            .lock()
+            .iter()
+            .map(|((start, scope), counts)| UsageBucket { start: *start, scope: scope.clone(), counts: *counts })
+            .collect();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(&stored)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
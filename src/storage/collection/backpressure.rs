@@ -0,0 +1,97 @@
+// Write backpressure from the WAL backlog (see WalBackpressure)
+// The backlog is what a crash would have to replay: the live WAL file, a sealed one whose checkpoint
+// is still being written, and the entries logged since the last checkpoint that reached the disk.
+// A throttled write waits in proportion to how far the backlog is between the throttle and reject
+// thresholds; a rejected one is told to come back once the running checkpoint should be done, going
+// by how long the last one took.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::config::WalBackpressure;
+use crate::error::Result;
+use crate::storage::persistence::get_wal_path;
+use crate::storage::wal::get_sealed_path;
+use super::storage::Collection;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WalBacklog {
+    pub bytes: u64,
+    pub ops: u64,
+    pub segments: usize, // closed WAL segments waiting for a checkpoint
+    pub checkpoint_running_ms: Option<u64>, // age of the checkpoint being written out
+    pub last_checkpoint_ms: Option<u64>, // capture to files written, for the last one
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritePressure {
+    None,
+    Throttle(Duration), // wait this long, then write
+    Reject { retry_after: Duration },
+}
+
+pub(super) fn backlog(collection: &Collection) -> WalBacklog {
+    if !collection.config.wal.enabled || collection.config.ephemeral {
+        return WalBacklog::default();
+    }
+    let wal_path = PathBuf::from(get_wal_path(&collection.path));
+    let size = |path: &Path| std::fs::metadata(path).map_or(0, |m| m.len());
+    let (next_seq, segments) = {
+        let persistence = collection.persistence.lock();
+        (persistence.wal.next_seq, persistence.wal.segments().clone())
+    };
+    let segments = segments.list().unwrap_or_default();
+    let progress = &collection.checkpoint_progress;
+    WalBacklog {
+        bytes: size(&wal_path) + size(&get_sealed_path(&wal_path)) + segments.iter().map(|s| s.bytes).sum::<u64>(),
+        segments: segments.len(),
+        ops: next_seq.saturating_sub(1).saturating_sub(progress.written_seq()),
+        checkpoint_running_ms: progress.running().map(|d| d.as_millis() as u64),
+        last_checkpoint_ms: progress.last_duration().map(|d| d.as_millis() as u64),
+    }
+}
+
+// How far past its throttle threshold the backlog is, as a share of the way to the reject
+// threshold (1.0 without one); None while under every throttle threshold
+fn excess(backlog: &WalBacklog, limits: &WalBackpressure) -> Option<f64> {
+    [(backlog.bytes, limits.throttle_bytes, limits.reject_bytes), (backlog.ops, limits.throttle_ops, limits.reject_ops)]
+        .into_iter()
+        .filter_map(|(value, throttle, reject)| {
+            let throttle = throttle?;
+            (value >= throttle).then(|| match reject {
+                Some(reject) => (value - throttle) as f64 / (reject - throttle).max(1) as f64,
+                None => 1.0,
+            })
+        })
+        .reduce(f64::max)
+}
+
+pub(super) fn pressure(collection: &Collection) -> Result<WritePressure> {
+    let limits = collection.config.wal.backpressure;
+    if !limits.enabled() {
+        return Ok(WritePressure::None);
+    }
+    let backlog = backlog(collection);
+    let over = |value: u64, limit: Option<u64>| limit.is_some_and(|limit| value >= limit);
+    let rejected = over(backlog.bytes, limits.reject_bytes) || over(backlog.ops, limits.reject_ops);
+    let excess = excess(&backlog, &limits);
+    if !rejected && excess.is_none() {
+        return Ok(WritePressure::None);
+    }
+
+    // Only a checkpoint brings the backlog down, and with writes held back none would be triggered
+    let running = collection.checkpoint_progress.running();
+    if running.is_none() {
+        tracing::info!(collection=%collection.metadata.name, wal_bytes=backlog.bytes, wal_ops=backlog.ops, "wal_backlog_checkpoint");
+        collection.checkpoint_in_background()?;
+    }
+    if rejected {
+        let expected = collection.checkpoint_progress.last_duration().unwrap_or(Duration::from_secs(1));
+        let remaining = expected.saturating_sub(running.unwrap_or_default());
+        return Ok(WritePressure::Reject { retry_after: remaining.max(Duration::from_secs(1)) });
+    }
+    let share = excess.unwrap_or(0.0).clamp(0.0, 1.0);
+    Ok(WritePressure::Throttle(Duration::from_millis((limits.max_delay_ms as f64 * share) as u64)))
+}
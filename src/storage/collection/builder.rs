@@ -1,10 +1,11 @@
 // Collection builder and initialization
 use std::collections::HashMap;
 use std::fs::OpenOptions;
+use parking_lot::{Mutex, RwLock};
 use uuid::Uuid;
 
-use crate::error::Result;
-use crate::storage::wal::{Wal, WalEntry};
+use crate::error::{PiramidError, Result, ServerError, StorageError};
+use crate::storage::wal::{get_archive_dir, Wal, WalCodec, WalEntry, WalHistory};
 use crate::storage::persistence::{
     get_wal_path, ensure_file_size, create_mmap, load_index,
     load_metadata, load_vector_index
@@ -12,18 +13,18 @@ use crate::storage::persistence::{
 use crate::storage::document::Document;
 use crate::storage::metadata::CollectionMetadata;
 use crate::quantization::QuantizedVector;
-use super::{CollectionOpenOptions, storage::Collection};
-use super::persistence::{load_wal_meta, PersistenceService};
+use super::{CollectionOpenOptions, data::DataStore, storage::Collection};
+use super::persistence::{load_wal_meta, CheckpointProgress, PersistenceService};
 
 pub struct CollectionBuilder;
 
 impl CollectionBuilder {
     pub fn open(path: &str, options: CollectionOpenOptions) -> Result<Collection> {
         
-        let config = options.config;
+        let mut config = options.config;
         
-        // Initialize Rayon thread pool based on config
-        Collection::init_rayon_pool(&config.parallelism);
+        // Build the query and maintenance thread pools (once per process)
+        crate::parallel::init(&config.parallelism);
         
         // Derive collection name from file path
         let collection_name = std::path::Path::new(path)
@@ -31,13 +32,37 @@ impl CollectionBuilder {
             .and_then(|s| s.to_str())
             .unwrap_or("unknown")
             .to_string();
+
+        if config.ephemeral {
+            return Self::open_ephemeral(path, collection_name, config);
+        }
+
+        // Fail before replaying or checkpointing anything if another process has the collection open
+        let file_lock = super::lock::acquire(path)?;
         
         let file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
+            .truncate(false)
             .open(path)?;
 
+        // A sealed collection (see worm.rs) runs without a WAL, and its data file keeps no room to grow
+        let stored_metadata = load_metadata(path)?;
+        let sealed = stored_metadata.as_ref().is_some_and(|meta| meta.sealed_at.is_some());
+        if sealed {
+            config.wal = crate::config::WalConfig::disabled();
+        }
+
+        // An index type switched to online replaces the configured one (see index_migration.rs)
+        if let Some(index) = super::index_migration::load_index_override(path)? {
+            config.index = index;
+        }
+        // ... and so does a quantization level switched to (see requantize.rs)
+        if let Some(quantization) = super::requantize::load_quantization_override(path)? {
+            config.quantization = quantization;
+        }
+
         // Ensure the file is at least the initial size to avoid mmap issues
         let initial_size = if config.memory.use_mmap {
             config.memory.initial_mmap_size as u64
@@ -45,7 +70,9 @@ impl CollectionBuilder {
             1024 * 1024
         };
         
-        ensure_file_size(&file, initial_size)?;
+        if !sealed {
+            ensure_file_size(&file, initial_size)?;
+        }
 
         // Create memory map if enabled
         let mmap = if config.memory.use_mmap {
@@ -58,7 +85,7 @@ impl CollectionBuilder {
         let index = load_index(path)?;
 
         // If metadata exists, update vector count based on loaded index
-        let metadata = match load_metadata(path)? {
+        let metadata = match stored_metadata {
             Some(meta) => {
                 let mut meta = meta;
                 meta.update_vector_count(index.len());
@@ -67,14 +94,22 @@ impl CollectionBuilder {
             None => CollectionMetadata::new(collection_name),
         };
 
-        // Load or create vector index
-        let mut vector_index = match load_vector_index(path)? {
-            Some(loaded_index) => loaded_index,
-            None => config.index.create_index(index.len())
+        // Load or create vector index. One that cannot be read is moved aside; searches then run on an
+        // exact index over the stored vectors until the configured one has been rebuilt.
+        let mut index_recovery = None;
+        let mut vector_index = match load_vector_index(path) {
+            Ok(Some(loaded_index)) => loaded_index,
+            Ok(None) => config.create_index(index.len()),
+            Err(PiramidError::Storage(StorageError::CorruptedIndex(error))) => {
+                index_recovery = Some(super::recovery::quarantine(path, error)?);
+                config.index.create_flat_index()
+            }
+            Err(e) => return Err(e),
         };
         
-        // If WAL is enabled, determine the minimum sequence number to replay from
-        let min_seq = if config.wal.enabled {
+        // If WAL is enabled, determine the minimum sequence number to replay from. A sealed collection
+        // has nothing to replay, but carries on numbering from where its WAL stopped.
+        let min_seq = if config.wal.enabled || sealed {
             load_wal_meta(path)?
         } else {
             0
@@ -84,15 +119,40 @@ impl CollectionBuilder {
         
         let wal_path = get_wal_path(path);
 
+        // A trained projection decides the space of everything the index holds, WAL replay included
+        let projection = super::projection::Projection::load(path)?.map(std::sync::Arc::new);
+
+        // A kept tuning recommendation is the default on top of the configured search settings
+        let tuning = super::tuning::TuningPreset::load(path)?;
+        let base_search = config.search;
+        if let Some(preset) = &tuning {
+            preset.apply(&mut config.search);
+        }
+
         // Initialize WAL and persistence service
-        let wal = if config.wal.enabled {
-            Wal::new(wal_path.into(), next_seq)?
+        let mut wal = if config.wal.enabled {
+            Wal::new(wal_path.into(), next_seq, WalCodec::new(&config.wal))?
+                .with_retry(config.wal.retry)
+                .with_segments(config.wal.max_log_size as u64)
         } else {
             Wal::disabled(wal_path.into(), next_seq)?
         };
 
+        if config.wal.enabled && config.wal.archive_segments {
+            wal = wal.with_archive(get_archive_dir(path));
+        }
+
+        // Retained history needs a base snapshot; a missing one, or one that stops short of the last
+        // checkpoint (history was switched off for a while), is rewritten from the loaded state below
+        let mut needs_history_base = false;
+        if let (true, Some(retention)) = (config.wal.enabled, config.wal.history_retention_secs) {
+            let history = WalHistory::open(path, retention, WalCodec::new(&config.wal))?;
+            needs_history_base = !history.has_base() || history.last_seq()? < min_seq;
+            wal = wal.with_history(history);
+        }
+
         // Create persistence service which will handle WAL replay and checkpointing
-        let persistence = PersistenceService::new(wal);
+        let mut persistence = PersistenceService::new(wal);
         
 
         // If WAL is enabled, replay entries from the WAL starting from the minimum sequence number
@@ -101,22 +161,41 @@ impl CollectionBuilder {
         } else {
             Vec::new()
         };
+        // Numbering carries on after the replayed entries, so the checkpoint that follows covers them all
+        if let Some(last) = wal_entries.last() {
+            persistence.wal.next_seq = persistence.wal.next_seq.max(last.seq() + 1);
+        }
 
         // If there are WAL entries to replay, we need to apply them to a temporary collection before checkpointing
         // why? Because we need to ensure that the collection state is consistent with the WAL entries before we can checkpoint and clear the WAL. By applying the WAL entries to a temporary collection, we can bring it up to date with all the changes recorded in the WAL, and then checkpoint that state to persist it. This way, we ensure that no changes are lost and that the collection is in sync with the WAL before we clear it.
         
         if !wal_entries.is_empty() {
             let mut temp_storage = Collection {
-                data_file: file,
-                mmap,
-                index,
+                data: RwLock::new(DataStore::new(file, mmap, index, config.memory.io_uring).with_metadata_index(&config.metadata_index).with_checksums(config.memory.checksums).with_prefetch(config.memory.prefetch)),
                 vector_index,
                 vector_cache: HashMap::new(),
-                metadata_cache: HashMap::new(),
                 config: config.clone(),
                 metadata,
                 path: path.to_string(),
-                persistence,
+                persistence: Mutex::new(persistence),
+                shared_writes: Mutex::new(()),
+                selectivity: crate::search::SelectivityTracker::new(),
+                latency_budget: crate::search::LatencyBudget::new(),
+                two_stage: Self::open_two_stage(path, &config)?,
+                column: Self::open_column(path, &config)?,
+                projection: projection.clone(),
+                tuning,
+                base_search,
+                replication: Mutex::new(None),
+                checkpoint_lock: Default::default(),
+                saved: Default::default(),
+                background_checkpoint: Mutex::new(None),
+                checkpoint_progress: std::sync::Arc::new(CheckpointProgress::new(min_seq)),
+                index_recovery: index_recovery.clone(),
+                sampler: Default::default(),
+                write_pause: None,
+                enrichment_stages: Vec::new(),
+                _file_lock: Some(file_lock.clone()),
             };
             
 
@@ -125,10 +204,18 @@ impl CollectionBuilder {
 
             // After replaying, rebuild the vector cache to ensure it's in sync with the index
             temp_storage.rebuild_vector_cache();
+            if temp_storage.index_recovery.is_some() {
+                super::recovery::index_vector_cache(&mut temp_storage);
+            }
+            super::sampler::reseed(&temp_storage);
+            temp_storage.apply_index_settings();
             
 
             // Checkpoint the collection to persist the changes from the WAL replay, which will also clear the WAL
-            super::persistence::checkpoint(&mut temp_storage)?;
+            super::persistence::checkpoint(&temp_storage)?;
+            if needs_history_base {
+                super::history::rebase(&temp_storage)?;
+            }
             
 
             // After checkpointing, we can use the updated collection as our main collection instance
@@ -137,60 +224,163 @@ impl CollectionBuilder {
         
 
         // If the index is not empty but the vector index is missing, we need to rebuild the vector index from the existing data
-        if !index.is_empty() && load_vector_index(path)?.is_none() {
+        if index_recovery.is_none() && !index.is_empty() && load_vector_index(path)?.is_none() {
             if let Some(ref mmap_ref) = mmap {
-                Self::rebuild_vector_index(&mut vector_index, &index, mmap_ref);
+                crate::parallel::maintenance(|| {
+                    Self::rebuild_vector_index(&mut vector_index, &index, mmap_ref, &config.transform, projection.as_deref())
+                });
             }
         }
 
         // Finally, create the collection instance with the loaded index, metadata, and vector index
+        let two_stage = Self::open_two_stage(path, &config)?;
+        let column = Self::open_column(path, &config)?;
         let mut collection = Collection {
-            data_file: file,
-            mmap,
-            index,
+            data: RwLock::new(DataStore::new(file, mmap, index, config.memory.io_uring).with_metadata_index(&config.metadata_index).with_checksums(config.memory.checksums).with_prefetch(config.memory.prefetch)),
             vector_index,
             vector_cache: HashMap::new(),
-            metadata_cache: HashMap::new(),
             config,
             metadata,
             path: path.to_string(),
-            persistence,
+            persistence: Mutex::new(persistence),
+            shared_writes: Mutex::new(()),
+            selectivity: crate::search::SelectivityTracker::new(),
+            latency_budget: crate::search::LatencyBudget::new(),
+            two_stage,
+            column,
+            projection,
+            tuning,
+            base_search,
+            replication: Mutex::new(None),
+            checkpoint_lock: Default::default(),
+            saved: Default::default(),
+            background_checkpoint: Mutex::new(None),
+            checkpoint_progress: std::sync::Arc::new(CheckpointProgress::new(min_seq)),
+            index_recovery,
+            sampler: Default::default(),
+            write_pause: None,
+            enrichment_stages: Vec::new(),
+            _file_lock: Some(file_lock),
         };
 
         
         
         collection.rebuild_vector_cache();
+        if collection.index_recovery.is_some() {
+            super::recovery::index_vector_cache(&mut collection);
+        }
+        super::sampler::reseed(&collection);
+        collection.apply_index_settings();
+        if needs_history_base {
+            super::history::rebase(&collection)?;
+        }
         Ok(collection)
     }
 
+    // An ephemeral collection starts empty and never touches `path`, which only names it. Its documents
+    // go to anonymous memory, there is no WAL, and the file-backed vector column is left off; two-stage
+    // search keeps its full-precision vectors in a sidecar file, so it is refused rather than dropped.
+    fn open_ephemeral(path: &str, collection_name: String, mut config: crate::config::CollectionConfig) -> Result<Collection> {
+        if config.two_stage.enabled {
+            return Err(ServerError::InvalidRequest(
+                "Two-stage search is not available for ephemeral collections".into(),
+            ).into());
+        }
+        config.wal = crate::config::WalConfig::disabled();
+        config.memory.vector_column = false;
+        let initial_size = if config.memory.use_mmap {
+            config.memory.initial_mmap_size as u64
+        } else {
+            1024 * 1024
+        };
+
+        Ok(Collection {
+            data: RwLock::new(DataStore::in_memory(initial_size.max(1))?.with_metadata_index(&config.metadata_index).with_checksums(config.memory.checksums).with_prefetch(config.memory.prefetch)),
+            vector_index: config.create_index(0),
+            vector_cache: HashMap::new(),
+            metadata: CollectionMetadata::new(collection_name),
+            path: path.to_string(),
+            persistence: Mutex::new(PersistenceService::new(Wal::disabled(get_wal_path(path).into(), 1)?)),
+            shared_writes: Mutex::new(()),
+            selectivity: crate::search::SelectivityTracker::new(),
+            latency_budget: crate::search::LatencyBudget::new(),
+            two_stage: None,
+            column: None,
+            projection: None,
+            tuning: None,
+            base_search: config.search,
+            replication: Mutex::new(None),
+            checkpoint_lock: Default::default(),
+            saved: Default::default(),
+            background_checkpoint: Mutex::new(None),
+            checkpoint_progress: std::sync::Arc::new(CheckpointProgress::new(0)),
+            index_recovery: None,
+            sampler: Default::default(),
+            write_pause: None,
+            enrichment_stages: Vec::new(),
+            _file_lock: None,
+            config,
+        })
+    }
+
+    fn open_two_stage(path: &str, config: &crate::config::CollectionConfig) -> Result<Option<super::two_stage::TwoStageState>> {
+        if config.two_stage.enabled {
+            Ok(Some(super::two_stage::TwoStageState::open(path)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn open_column(path: &str, config: &crate::config::CollectionConfig) -> Result<Option<super::column::VectorColumn>> {
+        if config.memory.vector_column {
+            Ok(Some(super::column::VectorColumn::open(path)?))
+        } else {
+            Ok(None)
+        }
+    }
+
     fn replay_wal(storage: &mut Collection, entries: Vec<WalEntry>) -> Result<()> {
+        // Metadata saved outside a checkpoint (rebuilds, compaction, ...) already counted the entries
+        // logged before it, up to `counters_seq`
+        let counted_through = storage.metadata.counters_seq;
 
         // Apply each WAL entry to the collection. Inserts and updates will add or modify entries, while deletes will remove them.
         for entry in entries {
             match entry {
                 // For inserts and updates, we create a Document from the WAL entry and insert it into the collection. Updates are treated as a delete followed by an insert to ensure the index is updated correctly.
-                WalEntry::Insert { id, vector, text, metadata, .. } => {
+                WalEntry::Insert { id, vector, text, metadata, seq } => {
                     let vec_entry = Document {
                         id,
                         vector: QuantizedVector::from_f32_with_config(&vector, &storage.config.quantization),
                         text,
                         metadata,
+                        full_precision: Some(vector),
                     };
                     let _ = super::operations::insert_internal(storage, vec_entry);
+                    if seq > counted_through {
+                        storage.metadata.counters.record_inserts(1);
+                    }
                 }
 
-                WalEntry::Update { id, vector, text, metadata, .. } => {
+                WalEntry::Update { id, vector, text, metadata, seq } => {
                     super::operations::delete_internal(storage, &id);
                     let vec_entry = Document {
                         id,
                         vector: QuantizedVector::from_f32_with_config(&vector, &storage.config.quantization),
                         text,
                         metadata,
+                        full_precision: Some(vector),
                     };
                     let _ = super::operations::insert_internal(storage, vec_entry);
+                    if seq > counted_through {
+                        storage.metadata.counters.record_updates(1);
+                    }
                 }
-                WalEntry::Delete { id, .. } => {
+                WalEntry::Delete { id, seq } => {
                     super::operations::delete_internal(storage, &id);
+                    if seq > counted_through {
+                        storage.metadata.counters.record_deletes(1);
+                    }
                 }
                 WalEntry::Checkpoint { .. } => {}
             }
@@ -201,7 +391,9 @@ impl CollectionBuilder {
     fn rebuild_vector_index(
         vector_index: &mut Box<dyn crate::index::VectorIndex>,
         index: &HashMap<Uuid, crate::storage::persistence::EntryPointer>,
-        mmap_ref: &memmap2::MmapMut
+        mmap_ref: &memmap2::MmapMut,
+        transform: &crate::config::TransformConfig,
+        projection: Option<&super::projection::Projection>,
     ) {
         // If the vector index is missing but we have an existing index, we need to rebuild the vector index from the existing data. We read each entry from the memory-mapped file based on the offsets and lengths in the index, deserialize it into a Document, and then insert it into the vector index.
         let mut vectors: HashMap<Uuid, Vec<f32>> = HashMap::new();
@@ -211,14 +403,14 @@ impl CollectionBuilder {
             if offset + length <= mmap_ref.len() {
                 let bytes = &mmap_ref[offset..offset + length];
                 if let Ok(entry) = bincode::deserialize::<Document>(bytes) {
-                    vectors.insert(*id, entry.get_vector());
+                    vectors.insert(*id, super::projection::index_space(transform, projection, &entry.get_vector()).into_owned());
                 }
             }
         }
 
         // Once we have all the vectors loaded from the existing data, we can insert them into the vector index. This will rebuild the vector index so that it is in sync with the existing data in the collection.
         
-        for (id, vector) in &vectors {
+        for (id, vector) in super::storage::in_id_order(&vectors) {
             vector_index.insert(*id, vector, &vectors);
         }
     }
@@ -0,0 +1,96 @@
+// Change records for external change-data-capture. Every committed write already carries a WAL
+// sequence number, so the changes after a sequence number are read back from the WAL itself: the
+// archived history segments (when wal.history_retention_secs is set) and the live and sealed WAL
+// files. A consumer keeps the `next_seq` of each page and asks for the changes after it.
+//
+// Without history a checkpoint truncates the WAL, so only changes since the last checkpoint can be
+// read (after a restart: since the oldest entry left in the log files); with history, everything
+// after the base. Asking for older changes is a conflict: the
+// consumer has fallen behind and has to resync (e.g. from an export) before following again.
+// Writes that bypass the WAL (pre-built index and document imports) produce no change records.
+
+use uuid::Uuid;
+
+use crate::error::{Result, ServerError};
+use crate::metadata::Metadata;
+use crate::storage::wal::WalEntry;
+use super::storage::Collection;
+
+pub const MAX_CHANGES_PER_PAGE: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub seq: u64,
+    pub kind: ChangeKind,
+    pub id: Uuid,
+    pub document: Option<(String, Vec<f32>, Metadata)>, // text, vector and metadata written; None for deletes
+}
+
+#[derive(Debug, Clone)]
+pub struct ChangeBatch {
+    pub changes: Vec<Change>,
+    pub next_seq: u64, // pass as since_seq for the next page
+    pub oldest_seq: u64, // changes after this sequence number can still be read
+    pub head_seq: u64,
+}
+
+impl Collection {
+    // Up to `limit` changes with a sequence number above `since_seq`, in order
+    pub fn changes(&self, since_seq: u64, limit: usize) -> Result<ChangeBatch> {
+        if !self.config.wal.enabled || self.config.ephemeral {
+            return Err(ServerError::InvalidRequest("Changes are read from the WAL, which this collection does not keep".into()).into());
+        }
+        let limit = limit.clamp(1, MAX_CHANGES_PER_PAGE);
+        let persistence = self.persistence.lock();
+        let head_seq = persistence.wal.next_seq.saturating_sub(1);
+        let live = persistence.wal.replay(0)?;
+        let (mut entries, oldest_seq) = match persistence.wal.history() {
+            Some(history) => (history.entries_after(since_seq)?, history.base_seq()?),
+            // Without history, what the log files still hold; a checkpoint's own entry is no change, so
+            // a consumer that read up to the change before it is not behind
+            None => {
+                let in_files = live.first().map(|e| e.seq().saturating_sub(1)).unwrap_or(head_seq);
+                (Vec::new(), persistence.wal.dropped_through().unwrap_or(in_files))
+            }
+        };
+        drop(persistence);
+        if since_seq < oldest_seq {
+            return Err(ServerError::Conflict(format!(
+                "changes after seq {since_seq} are no longer retained (oldest readable is after {oldest_seq}); resync and continue from a newer seq"
+            )).into());
+        }
+
+        entries.extend(live.into_iter().filter(|e| e.seq() > since_seq));
+        entries.sort_by_key(|e| e.seq());
+        entries.dedup_by_key(|e| e.seq());
+        let mut changes = Vec::new();
+        for entry in entries {
+            if changes.len() == limit {
+                break;
+            }
+            changes.push(match entry {
+                WalEntry::Insert { id, vector, text, metadata, seq } => {
+                    Change { seq, kind: ChangeKind::Insert, id, document: Some((text, vector, metadata)) }
+                }
+                WalEntry::Update { id, vector, text, metadata, seq } => {
+                    Change { seq, kind: ChangeKind::Update, id, document: Some((text, vector, metadata)) }
+                }
+                WalEntry::Delete { id, seq } => Change { seq, kind: ChangeKind::Delete, id, document: None },
+                WalEntry::Checkpoint { .. } => continue,
+            });
+        }
+        // A full page continues after its last change; otherwise everything up to the head was read
+        let next_seq = match changes.last() {
+            Some(last) if changes.len() == limit => last.seq,
+            _ => head_seq.max(since_seq),
+        };
+        Ok(ChangeBatch { changes, next_seq, oldest_seq, head_seq })
+    }
+}
@@ -0,0 +1,243 @@
+// Vector column: the collection's vectors in fixed-stride slots, kept next to the document log.
+//
+// Documents in the data file interleave the vector with text and metadata, so scanning vectors out
+// of it reads far more bytes than the scan needs. The column holds exactly what the index sees
+// (after the collection's transform) in two files:
+//
+// - `.vcol.db`  a u32 dimension header followed by one row of `dims` native-endian f32 per slot
+// - `.vcol.ids` one 16-byte uuid per slot; the nil uuid marks a free slot
+//
+// Slots are fixed size, so an update overwrites its row in place and a delete frees the slot for the
+// next insert; the file never needs compaction. Both files are memory-mapped and grow by doubling.
+// There is no separate index: opening the column rebuilds id -> slot from the ids file, and the
+// collection re-syncs the whole column from its vector cache whenever the two disagree (first
+// enable, crash between the document write and the column write, transform change).
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+
+use memmap2::MmapMut;
+use uuid::Uuid;
+
+use crate::error::{Result, ServerError};
+use crate::index::ColumnView;
+use crate::storage::persistence::create_mmap;
+
+const HEADER_LEN: usize = 4;
+const ID_LEN: usize = 16;
+const MIN_SLOTS: usize = 1024;
+
+pub fn get_column_path(collection_path: &str) -> String {
+    format!("{}.vcol.db", collection_path)
+}
+
+pub fn get_column_ids_path(collection_path: &str) -> String {
+    format!("{}.vcol.ids", collection_path)
+}
+
+pub struct VectorColumn {
+    data_file: File,
+    ids_file: File,
+    data: Option<MmapMut>,
+    id_map: Option<MmapMut>,
+    dims: usize,
+    capacity: usize,             // slots both files are sized for
+    ids: Vec<Uuid>,              // slot -> id up to the highest used slot; nil = free
+    slots: HashMap<Uuid, usize>, // id -> slot
+    free: Vec<usize>,
+}
+
+impl VectorColumn {
+    pub fn open(collection_path: &str) -> Result<Self> {
+        let open = |path: String| {
+            OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)
+        };
+        let mut column = Self {
+            data_file: open(get_column_path(collection_path))?,
+            ids_file: open(get_column_ids_path(collection_path))?,
+            data: None,
+            id_map: None,
+            dims: 0,
+            capacity: 0,
+            ids: Vec::new(),
+            slots: HashMap::new(),
+            free: Vec::new(),
+        };
+        if column.load().is_err() {
+            // Unreadable or mismatched files are rewritten by the next sync
+            column.reset()?;
+        }
+        Ok(column)
+    }
+
+    fn load(&mut self) -> Result<()> {
+        let data_len = self.data_file.metadata()?.len() as usize;
+        let ids_len = self.ids_file.metadata()?.len() as usize;
+        if data_len < HEADER_LEN || ids_len == 0 {
+            return Err(ServerError::Internal("empty vector column".into()).into());
+        }
+        let data = create_mmap(&self.data_file)?;
+        let id_map = create_mmap(&self.ids_file)?;
+        let dims = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let capacity = ids_len / ID_LEN;
+        if dims == 0 || data_len != HEADER_LEN + capacity * dims * 4 {
+            return Err(ServerError::Internal("vector column size mismatch".into()).into());
+        }
+
+        let mut ids: Vec<Uuid> = id_map
+            .chunks_exact(ID_LEN)
+            .map(|b| Uuid::from_slice(b).unwrap_or_else(|_| Uuid::nil()))
+            .collect();
+        while ids.last().is_some_and(|id| id.is_nil()) {
+            ids.pop();
+        }
+        for (slot, id) in ids.iter().enumerate() {
+            if id.is_nil() {
+                self.free.push(slot);
+            } else {
+                self.slots.insert(*id, slot);
+            }
+        }
+        self.dims = dims;
+        self.capacity = capacity;
+        self.ids = ids;
+        self.data = Some(data);
+        self.id_map = Some(id_map);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    pub fn view(&self) -> ColumnView<'_> {
+        ColumnView::new(self.dims, self.floats(), &self.ids, &self.slots)
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<&[f32]> {
+        let slot = *self.slots.get(id)?;
+        self.floats().get(slot * self.dims..(slot + 1) * self.dims)
+    }
+
+    // Rows up to the highest used slot, as f32s
+    fn floats(&self) -> &[f32] {
+        let Some(data) = self.data.as_ref() else { return &[] };
+        let bytes = &data[HEADER_LEN..HEADER_LEN + self.ids.len() * self.dims * 4];
+        // The mmap is page aligned and the header is 4 bytes, so rows are f32 aligned
+        let (prefix, floats, _) = unsafe { bytes.align_to::<f32>() };
+        debug_assert!(prefix.is_empty());
+        floats
+    }
+
+    // Write (or overwrite) the row of `id`
+    pub fn put(&mut self, id: Uuid, vector: &[f32]) -> Result<()> {
+        if self.dims == 0 {
+            self.dims = vector.len();
+        }
+        if vector.len() != self.dims {
+            return Err(ServerError::InvalidRequest(format!(
+                "Vector column expects {} dimensions, got {}",
+                self.dims,
+                vector.len()
+            )).into());
+        }
+        let slot = match self.slots.get(&id) {
+            Some(&slot) => slot,
+            None => {
+                let slot = self.free.pop().unwrap_or(self.ids.len());
+                if slot >= self.capacity {
+                    self.grow(slot + 1)?;
+                }
+                if slot == self.ids.len() {
+                    self.ids.push(id);
+                } else {
+                    self.ids[slot] = id;
+                }
+                self.slots.insert(id, slot);
+                self.write_id(slot, &id);
+                slot
+            }
+        };
+
+        let dims = self.dims;
+        if let Some(data) = self.data.as_mut() {
+            let start = HEADER_LEN + slot * dims * 4;
+            let (_, row, _) = unsafe { data[start..start + dims * 4].align_to_mut::<f32>() };
+            row.copy_from_slice(vector);
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        if let Some(slot) = self.slots.remove(id) {
+            self.ids[slot] = Uuid::nil();
+            self.write_id(slot, &Uuid::nil());
+            self.free.push(slot);
+        }
+    }
+
+    // Make the column hold exactly `vectors`, rewriting it when the id sets or dimensions differ
+    pub fn sync(&mut self, vectors: &HashMap<Uuid, Vec<f32>>) -> Result<bool> {
+        let dims_match = vectors.values().next().is_none_or(|v| v.len() == self.dims);
+        if dims_match && self.slots.len() == vectors.len() && vectors.keys().all(|id| self.slots.contains_key(id)) {
+            return Ok(false);
+        }
+        self.reset()?;
+        for (id, vector) in vectors {
+            self.put(*id, vector)?;
+        }
+        Ok(true)
+    }
+
+    // Drop every slot; used by compaction before the live documents are written back
+    pub fn reset(&mut self) -> Result<()> {
+        self.data = None;
+        self.id_map = None;
+        self.data_file.set_len(0)?;
+        self.ids_file.set_len(0)?;
+        self.dims = 0;
+        self.capacity = 0;
+        self.ids.clear();
+        self.slots.clear();
+        self.free.clear();
+        Ok(())
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        if let Some(data) = self.data.as_ref() {
+            data.flush()?;
+        }
+        if let Some(id_map) = self.id_map.as_ref() {
+            id_map.flush()?;
+        }
+        Ok(())
+    }
+
+    fn write_id(&mut self, slot: usize, id: &Uuid) {
+        if let Some(id_map) = self.id_map.as_mut() {
+            id_map[slot * ID_LEN..(slot + 1) * ID_LEN].copy_from_slice(id.as_bytes());
+        }
+    }
+
+    fn grow(&mut self, min_slots: usize) -> Result<()> {
+        let capacity = min_slots.max(self.capacity * 2).max(MIN_SLOTS);
+        self.data = None;
+        self.id_map = None;
+        self.data_file.set_len((HEADER_LEN + capacity * self.dims * 4) as u64)?;
+        self.ids_file.set_len((capacity * ID_LEN) as u64)?;
+        let mut data = create_mmap(&self.data_file)?;
+        data[..HEADER_LEN].copy_from_slice(&(self.dims as u32).to_ne_bytes());
+        self.data = Some(data);
+        self.id_map = Some(create_mmap(&self.ids_file)?);
+        self.capacity = capacity;
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+// Document store: the data file and its mmap, the id -> entry pointer index, and the per-document
+// metadata and client ids read from it.
+//
+// A collection keeps the store behind its own RwLock (the data latch), separate from the lock a
+// server holds around the whole collection. That outer lock is in effect the index lock: writes that
+// change vectors (and so the vector index and caches) take it exclusively and reach the store through
+// `get_mut`, which needs no locking. Metadata-only writes and checkpoints only take it shared, so
+// searches keep running; they take the data latch for the moment they append a new entry version and
+// swap its pointer, or, for a checkpoint, shared while the index is written out. Searches hold the
+// latch shared while they filter on metadata and read documents back.
+//
+// With `metadata_index` fields configured, metadata writes go through `set_metadata`/`remove_metadata`
+// so the posting lists of those fields stay in step with the metadata cache.
+//
+// With `memory.io_uring` documents are read back from the data file with explicit reads, batched per
+// search, and the mmap is only written through.
+//
+// An ephemeral collection has no data file: its entries live in an anonymous map that is copied into a
+// larger one when it fills up.
+//
+// Through the mmap, the candidates of a search have their pages hinted to the kernel (madvise
+// WILLNEED) in file order before the first is read, with `memory.prefetch`.
+//
+// Every pointer carries the CRC32 of its entry. Reads check it as `memory.checksums` says (always, one
+// in CHECKSUM_SAMPLE_INTERVAL, or never); an entry that fails reads as a missing document rather than
+// one decoded from damaged bytes, and is logged and counted.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use memmap2::MmapMut;
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::config::{ChecksumVerification, MetadataIndexConfig, CHECKSUM_SAMPLE_INTERVAL};
+use crate::metadata::Metadata;
+use crate::search::query::{FilterMatches, MetadataPostings};
+use crate::search::Filter;
+use crate::storage::document::Document;
+use crate::storage::persistence::{
+    EntryPointer, create_anon_mmap, create_mmap, ensure_file_size, grow_anon_mmap_if_needed, grow_mmap_if_needed,
+    prefetch_mmap, prefetch_ranges, read_entries, PREFETCH_GAP,
+};
+
+pub struct DataStore {
+    pub(super) data_file: Option<File>, // None for ephemeral collections
+    pub(super) mmap: Option<MmapMut>,
+    pub(super) index: HashMap<Uuid, EntryPointer>,
+    pub(super) metadata_cache: HashMap<Uuid, Metadata>,
+    pub(super) external_ids: HashMap<String, Uuid>, // client-provided id -> document id
+    postings: Option<MetadataPostings>, // per-value posting lists of the indexed metadata fields
+    explicit_reads: bool, // read documents from the file (io_uring or pread) rather than the mmap
+    checksums: ChecksumVerification,
+    prefetch: bool,
+    prefetched_ranges: AtomicU64, // byte ranges hinted ahead of batched reads through the mmap
+    reads: AtomicU64, // picks the sampled reads
+    checksum_failures: AtomicU64, // reads that found an entry not matching its checksum
+}
+
+impl DataStore {
+    pub(super) fn new(data_file: File, mmap: Option<MmapMut>, index: HashMap<Uuid, EntryPointer>, explicit_reads: bool) -> Self {
+        Self {
+            data_file: Some(data_file),
+            mmap,
+            index,
+            metadata_cache: HashMap::new(),
+            external_ids: HashMap::new(),
+            postings: None,
+            explicit_reads,
+            checksums: ChecksumVerification::default(),
+            prefetch: false,
+            prefetched_ranges: AtomicU64::new(0),
+            reads: AtomicU64::new(0),
+            checksum_failures: AtomicU64::new(0),
+        }
+    }
+
+    // Empty store in anonymous memory, for ephemeral collections
+    pub(super) fn in_memory(initial_size: u64) -> Result<Self> {
+        Ok(Self {
+            data_file: None,
+            mmap: Some(create_anon_mmap(initial_size)?),
+            index: HashMap::new(),
+            metadata_cache: HashMap::new(),
+            external_ids: HashMap::new(),
+            postings: None,
+            explicit_reads: false,
+            checksums: ChecksumVerification::default(),
+            prefetch: false,
+            prefetched_ranges: AtomicU64::new(0),
+            reads: AtomicU64::new(0),
+            checksum_failures: AtomicU64::new(0),
+        })
+    }
+
+    // Keep posting lists for the configured metadata fields
+    pub(super) fn with_metadata_index(mut self, config: &MetadataIndexConfig) -> Self {
+        self.postings = config.is_enabled().then(|| MetadataPostings::new(&config.fields));
+        self
+    }
+
+    pub(super) fn with_checksums(mut self, checksums: ChecksumVerification) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    // Hint the pages of batched reads through the mmap; an anonymous map has nothing to read in
+    pub(super) fn with_prefetch(mut self, prefetch: bool) -> Self {
+        self.prefetch = prefetch && self.data_file.is_some();
+        self
+    }
+
+    pub fn checksum_failures(&self) -> u64 {
+        self.checksum_failures.load(Ordering::Relaxed)
+    }
+
+    pub fn prefetched_ranges(&self) -> u64 {
+        self.prefetched_ranges.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn set_metadata(&mut self, id: Uuid, metadata: Metadata) {
+        if let Some(postings) = self.postings.as_mut() {
+            postings.insert(id, self.metadata_cache.get(&id), &metadata);
+        }
+        self.metadata_cache.insert(id, metadata);
+    }
+
+    pub(super) fn remove_metadata(&mut self, id: &Uuid) {
+        self.unindex_metadata(id);
+        self.metadata_cache.remove(id);
+    }
+
+    // Take a deleted document out of the posting lists while its metadata stays cached (HNSW keeps
+    // deleted nodes, and their vectors and metadata, until the next rebuild)
+    pub(super) fn unindex_metadata(&mut self, id: &Uuid) {
+        if let (Some(postings), Some(metadata)) = (self.postings.as_mut(), self.metadata_cache.get(id)) {
+            postings.remove(id, metadata);
+        }
+    }
+
+    pub(super) fn clear_metadata(&mut self) {
+        self.metadata_cache.clear();
+        if let Some(postings) = self.postings.as_mut() {
+            postings.clear();
+        }
+    }
+
+    // Documents matching `filter` according to the posting lists; None without an index covering
+    // every field the filter reads
+    pub fn filter_matches(&self, filter: &Filter, max_ids: usize) -> Option<FilterMatches> {
+        self.postings.as_ref()?.matches(filter, max_ids)
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<Document> {
+        let pointer = self.index.get(id)?;
+        self.decode(id, pointer, &self.entry_bytes(pointer)?, self.sample_checksum())
+    }
+
+    // Whether the entry of `id` matches its checksum, whatever `memory.checksums` says; None when it
+    // cannot be read at all
+    pub(super) fn checksum_matches(&self, id: &Uuid) -> Option<bool> {
+        let pointer = self.index.get(id)?;
+        Some(pointer.matches(&self.entry_bytes(pointer)?))
+    }
+
+    fn entry_bytes(&self, pointer: &EntryPointer) -> Option<Cow<'_, [u8]>> {
+        let offset = pointer.offset as usize;
+        let length = pointer.length as usize;
+        match self.mmap.as_ref() {
+            // A pointer past the end of the map (a damaged pointer file) reads as a missing document
+            Some(mmap) if !self.explicit_reads => mmap.get(offset..offset.checked_add(length)?).map(Cow::Borrowed),
+            _ => {
+                let file = self.data_file.as_ref()?;
+                read_entries(file, &[(pointer.offset, length)]).pop()?.map(Cow::Owned)
+            }
+        }
+    }
+
+    fn sample_checksum(&self) -> bool {
+        match self.checksums {
+            ChecksumVerification::Always => true,
+            ChecksumVerification::Sampled => self.reads.fetch_add(1, Ordering::Relaxed) % CHECKSUM_SAMPLE_INTERVAL == 0,
+            ChecksumVerification::Never => false,
+        }
+    }
+
+    fn decode(&self, id: &Uuid, pointer: &EntryPointer, bytes: &[u8], check: bool) -> Option<Document> {
+        if check && !pointer.matches(bytes) {
+            self.checksum_failures.fetch_add(1, Ordering::Relaxed);
+            tracing::error!(id=%id, offset=pointer.offset, length=pointer.length, "entry_checksum_mismatch");
+            return None;
+        }
+        bincode::deserialize(bytes).ok()
+    }
+
+    // Documents of `ids`, in order. With explicit reads every entry is read in one batch, so the
+    // reads of a search's candidates are in flight together instead of faulting in one at a time.
+    pub fn get_many(&self, ids: &[Uuid]) -> Vec<Option<Document>> {
+        let file = match self.data_file.as_ref() {
+            Some(file) if self.explicit_reads => file,
+            _ => {
+                self.prefetch(ids);
+                return ids.iter().map(|id| self.get(id)).collect();
+            }
+        };
+        let pointers: Vec<Option<&EntryPointer>> = ids.iter().map(|id| self.index.get(id)).collect();
+        let entries: Vec<(u64, usize)> = pointers.iter().flatten().map(|p| (p.offset, p.length as usize)).collect();
+        let mut read = read_entries(file, &entries).into_iter();
+        ids.iter().zip(&pointers).map(|(id, pointer)| {
+            let pointer = (*pointer)?;
+            self.decode(id, pointer, &read.next()??, self.sample_checksum())
+        }).collect()
+    }
+
+    fn prefetch(&self, ids: &[Uuid]) {
+        let Some(mmap) = self.mmap.as_ref().filter(|_| self.prefetch && ids.len() > 1) else { return };
+        let entries: Vec<(u64, usize)> = ids.iter()
+            .filter_map(|id| self.index.get(id))
+            .map(|p| (p.offset, p.length as usize))
+            .collect();
+        let hinted = prefetch_mmap(mmap, &prefetch_ranges(&entries, PREFETCH_GAP));
+        self.prefetched_ranges.fetch_add(hinted as u64, Ordering::Relaxed);
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    // Offset just past the last live entry; new entries and new versions are appended there
+    pub(super) fn end_offset(&self) -> u64 {
+        self.index.values()
+            .map(|idx| idx.offset + idx.length as u64)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub(super) fn file_len(&self) -> Result<u64> {
+        match &self.data_file {
+            Some(file) => Ok(file.metadata()?.len()),
+            None => Ok(self.mmap.as_ref().map_or(0, |m| m.len() as u64)),
+        }
+    }
+
+    // Grow the data file and its mmap to hold at least `size` bytes
+    pub(super) fn reserve(&mut self, size: u64) -> Result<()> {
+        match &self.data_file {
+            Some(file) => grow_mmap_if_needed(&mut self.mmap, file, size),
+            None => grow_anon_mmap_if_needed(&mut self.mmap, size),
+        }
+    }
+
+    // Empty the store for a rewrite (compaction): truncate the data file back to `initial_size` and
+    // map it again, or start over with a fresh anonymous map
+    pub(super) fn reset(&mut self, initial_size: u64, use_mmap: bool) -> Result<()> {
+        drop(self.mmap.take());
+        match &self.data_file {
+            Some(file) => {
+                file.set_len(0)?;
+                ensure_file_size(file, initial_size)?;
+                self.mmap = if use_mmap { Some(create_mmap(file)?) } else { None };
+            }
+            None => self.mmap = Some(create_anon_mmap(initial_size)?),
+        }
+        self.index.clear();
+        self.clear_metadata();
+        self.external_ids.clear();
+        Ok(())
+    }
+
+    // Cut the data file back to the end of the last entry (at least one byte, which an mmap needs),
+    // dropping the room kept for growth, and map it again. For stores that will not be written again.
+    pub(super) fn trim(&mut self) -> Result<()> {
+        let Some(file) = &self.data_file else { return Ok(()) };
+        let remap = match self.mmap.take() {
+            Some(mmap) => {
+                mmap.flush()?;
+                true
+            }
+            None => false,
+        };
+        file.set_len(self.end_offset().max(1))?;
+        file.sync_all()?;
+        if remap {
+            self.mmap = Some(create_mmap(file)?);
+        }
+        Ok(())
+    }
+
+    // Copy `bytes` into the data file at `offset`, growing the file and its mmap first when needed
+    pub(super) fn write_at(&mut self, offset: u64, bytes: &[u8]) -> Result<()> {
+        self.reserve(offset + bytes.len() as u64)?;
+        let mmap = self.mmap.as_mut().unwrap();
+        mmap[offset as usize..(offset as usize + bytes.len())]
+            .copy_from_slice(bytes);
+        Ok(())
+    }
+}
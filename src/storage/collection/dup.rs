@@ -56,7 +56,7 @@ pub fn find_duplicates(
             vectors,
             search_cfg,
             None,
-            metadatas,
+            &metadatas,
         );
         for neighbor_id in neighbors {
             if neighbor_id == *id {
@@ -0,0 +1,122 @@
+// Bulk export of a collection's documents as Parquet or an Arrow IPC file, for offline analysis
+// (DuckDB, Polars, pandas) against the stored vectors. One row per document in id order, with the
+// columns described in storage/columnar: id, vector, text and metadata (JSON).
+//
+// The vector is the exact one when two-stage search keeps it and the stored (dequantized) one
+// otherwise; a trained projection only changes what the index holds, so it does not show here.
+// Documents are read and written in batches of EXPORT_BATCH_ROWS, each a row group or record batch.
+
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{Result, StorageError};
+use crate::search::query::Filter;
+use crate::storage::columnar::{ArrowFileWriter, ParquetWriter, RowBatch};
+use super::storage::Collection;
+
+pub const EXPORT_BATCH_ROWS: usize = 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Parquet,
+    Arrow,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+            ExportFormat::Arrow => "application/vnd.apache.arrow.file",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Arrow => "arrow",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportReport {
+    pub rows: usize,
+    pub dimensions: usize,
+}
+
+// Both writers take the same batches
+trait BatchSink {
+    fn write_batch(&mut self, batch: &RowBatch) -> Result<()>;
+}
+
+impl<W: Write + Send> BatchSink for ParquetWriter<W> {
+    fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
+        ParquetWriter::write_batch(self, batch)
+    }
+}
+
+impl<W: Write> BatchSink for ArrowFileWriter<W> {
+    fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
+        ArrowFileWriter::write_batch(self, batch)
+    }
+}
+
+/// Write every document matching `filter` (all of them without one) to `writer`.
+pub fn export<W: Write + Send>(collection: &Collection, writer: W, format: ExportFormat, filter: Option<&Filter>) -> Result<ExportReport> {
+    let dimensions = collection.metadata.dimensions.unwrap_or(0);
+    match format {
+        ExportFormat::Parquet => {
+            let mut sink = ParquetWriter::new(writer, dimensions)?;
+            let rows = write_rows(collection, &mut sink, dimensions, filter)?;
+            sink.finish()?;
+            Ok(ExportReport { rows, dimensions })
+        }
+        ExportFormat::Arrow => {
+            let mut sink = ArrowFileWriter::new(writer, dimensions)?;
+            let rows = write_rows(collection, &mut sink, dimensions, filter)?;
+            sink.finish()?;
+            Ok(ExportReport { rows, dimensions })
+        }
+    }
+}
+
+/// The rows of the documents `ids` names that match `filter`; ids no longer in the collection are
+/// left out. Streaming exports call this a batch at a time, between which the collection may change.
+pub fn export_rows(collection: &Collection, ids: &[Uuid], dimensions: usize, filter: Option<&Filter>) -> Result<RowBatch> {
+    let mut batch = RowBatch::default();
+    let data = collection.data.read_recursive();
+    for id in ids {
+        let Some(doc) = data.get(id) else { continue };
+        if filter.is_some_and(|f| !f.matches(&doc.metadata)) {
+            continue;
+        }
+        let vector = collection.two_stage.as_ref()
+            .and_then(|t| t.full_precision(id))
+            .unwrap_or_else(|| doc.get_vector());
+        if vector.len() != dimensions {
+            return Err(StorageError::InvalidDimension { expected: dimensions, actual: vector.len() }.into());
+        }
+        let metadata = crate::server::metadata_to_json(&doc.metadata);
+        batch.ids.push(id.to_string());
+        batch.vectors.extend(vector);
+        batch.texts.push(doc.text);
+        batch.metadata.push(serde_json::to_string(&metadata)?);
+    }
+    Ok(batch)
+}
+
+fn write_rows(collection: &Collection, sink: &mut impl BatchSink, dimensions: usize, filter: Option<&Filter>) -> Result<usize> {
+    let mut rows = 0;
+    for ids in collection.ids().chunks(EXPORT_BATCH_ROWS) {
+        let batch = export_rows(collection, ids, dimensions, filter)?;
+        if !batch.is_empty() {
+            rows += batch.len();
+            sink.write_batch(&batch)?;
+        }
+    }
+    Ok(rows)
+}
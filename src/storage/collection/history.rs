@@ -0,0 +1,143 @@
+// Point-in-time views over a collection's retained WAL history.
+// A view is a throwaway ephemeral collection, rebuilt from the history base plus every archived and
+// live WAL entry up to the requested sequence number. It lives only in memory, so it never touches
+// the live collection's files and leaves nothing behind when dropped.
+
+use std::ops::Deref;
+
+use crate::error::{Result, ServerError};
+use crate::quantization::QuantizedVector;
+use crate::storage::document::Document;
+use crate::storage::wal::{fold_entries, HistorySummary, Wal, WalEntry, WalHistory};
+use crate::testing::clock::now_secs;
+use super::operations;
+use super::storage::Collection;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsOf {
+    Seq(u64),
+    Timestamp(u64), // unix seconds; resolved to the newest checkpoint at or before it
+}
+
+pub struct CollectionView {
+    collection: Collection,
+    pub as_of_seq: u64,
+}
+
+impl Deref for CollectionView {
+    type Target = Collection;
+
+    fn deref(&self) -> &Collection {
+        &self.collection
+    }
+}
+
+// Write the current documents as the history base at the latest sequence number. Used when
+// history is first enabled, after a gap (history was off for a while) and after imports that
+// bypass the WAL.
+pub(super) fn rebase(collection: &Collection) -> Result<()> {
+    if collection.persistence.lock().wal.history().is_none() {
+        return Ok(());
+    }
+    // Read the documents before taking the WAL latch: the data latch comes first in lock order
+    let documents = collection
+        .get_all()
+        .into_iter()
+        .map(|doc| {
+            let vector = collection
+                .two_stage
+                .as_ref()
+                .and_then(|ts| ts.full_precision(&doc.id))
+                .unwrap_or_else(|| doc.get_vector());
+            (doc.id, vector, doc.text, doc.metadata)
+        })
+        .collect::<Vec<_>>();
+    let persistence = collection.persistence.lock();
+    let Some(history) = persistence.wal.history() else { return Ok(()) };
+    let base_seq = persistence.wal.next_seq.saturating_sub(1);
+    let documents = documents
+        .into_iter()
+        .map(|(id, vector, text, metadata)| WalEntry::Insert { id, vector, text, metadata, seq: base_seq })
+        .collect();
+    history.write_base(base_seq, now_secs(), documents)
+}
+
+fn history_of(wal: &Wal) -> Result<&WalHistory> {
+    wal.history().ok_or_else(|| {
+        ServerError::InvalidRequest("WAL history is not enabled for this collection (set wal.history_retention_secs)".into()).into()
+    })
+}
+
+impl Collection {
+    pub fn history_summary(&self) -> Option<HistorySummary> {
+        self.persistence.lock().wal.history().and_then(|h| h.summary().ok())
+    }
+
+    // Latest sequence number written to the collection
+    pub fn head_seq(&self) -> u64 {
+        self.persistence.lock().wal.next_seq.saturating_sub(1)
+    }
+
+    pub fn resolve_as_of(&self, as_of: AsOf) -> Result<u64> {
+        let head = self.head_seq();
+        let persistence = self.persistence.lock();
+        let history = history_of(&persistence.wal)?;
+        let base_seq = history.base_seq()?;
+        match as_of {
+            AsOf::Seq(seq) if seq < base_seq => Err(ServerError::InvalidRequest(format!(
+                "seq {seq} is older than the retained history (oldest is {base_seq})"
+            )).into()),
+            AsOf::Seq(seq) if seq > head => Err(ServerError::InvalidRequest(format!(
+                "seq {seq} has not been written yet (latest is {head})"
+            )).into()),
+            AsOf::Seq(seq) => Ok(seq),
+            AsOf::Timestamp(ts) => {
+                let mut anchors = history.time_anchors()?;
+                for entry in persistence.wal.replay(0)? {
+                    if let WalEntry::Checkpoint { timestamp, seq } = entry {
+                        anchors.push((timestamp, seq));
+                    }
+                }
+                anchors.push((now_secs(), head));
+                anchors
+                    .into_iter()
+                    .filter(|(timestamp, _)| *timestamp <= ts)
+                    .map(|(_, seq)| seq)
+                    .max()
+                    .ok_or_else(|| ServerError::InvalidRequest(format!(
+                        "timestamp {ts} is older than the retained history"
+                    )).into())
+            }
+        }
+    }
+
+    // Rebuild the collection as it was at `as_of` into a temporary, read-only collection
+    pub fn view_as_of(&self, as_of: AsOf) -> Result<CollectionView> {
+        let target = self.resolve_as_of(as_of)?;
+        let persistence = self.persistence.lock();
+        let history = history_of(&persistence.wal)?;
+
+        let mut entries = history.entries_until(target)?;
+        entries.extend(persistence.wal.replay(history.last_seq()?)?);
+        drop(persistence);
+        let documents = fold_entries(entries, target);
+
+        let mut config = self.config.clone();
+        config.two_stage = Default::default();
+        let mut collection = Collection::open_with_options(&self.path, config.ephemeral().into())?;
+        for entry in documents {
+            if let WalEntry::Insert { id, vector, text, metadata, .. } = entry {
+                let doc = Document {
+                    id,
+                    vector: QuantizedVector::from_f32_with_config(&vector, &collection.config.quantization),
+                    text,
+                    metadata,
+                    full_precision: Some(vector),
+                };
+                operations::insert_internal(&mut collection, doc)?;
+            }
+        }
+        collection.rebuild_vector_cache();
+        Ok(CollectionView { collection, as_of_seq: target })
+    }
+}
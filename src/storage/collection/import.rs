@@ -0,0 +1,309 @@
+// Write-once import of vectors together with an index that was built offline.
+// Building HNSW/IVF on the serving node is the slow part of bulk ingestion; an offline pipeline
+// (Spark, Ray, ...) can produce the graph/clusters in parallel and ship them as a bundle that we
+// only validate and map onto our storage. The bundle is a directory with:
+//
+// - manifest.json   {"format_version": 1, "index_type": "flat"|"hnsw"|"ivf", "dimensions": D,
+//                    "count": N, "metric": "cosine"|"euclidean"|"dot",
+//                    "hnsw": {"m", "m_max", "ef_construction", "ef_search"},   (optional)
+//                    "ivf": {"num_probes"}}                                    (optional)
+// - vectors.jsonl   one {"id": "<external id>", "vector": [..], "text": "..", "metadata": {..}} per line
+// - graph.json      (hnsw) {"entry_point": "<external id>", "nodes": {"<external id>": [[layer 0 neighbors], [layer 1], ..]}}
+// - clusters.json   (ivf)  {"centroids": [[..], ..], "lists": [["<external id>", ..], ..]}
+//
+// External ids are mapped to fresh Uuids (ids that already are Uuids are kept) and stored in the
+// document metadata under `external_id`. The import only runs against an empty collection.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::error::{Result, ServerError};
+use crate::index::{FlatConfig, FlatIndex, HnswConfig, HnswIndex, IndexConfig, IndexType, IvfConfig, IvfIndex, VectorIndex};
+use crate::metadata::MetadataValue;
+use crate::metrics::Metric;
+use crate::storage::document::{Document, EXTERNAL_ID_KEY};
+use super::operations;
+use super::storage::Collection;
+
+pub const IMPORT_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportManifest {
+    pub format_version: u32,
+    pub index_type: String,
+    pub dimensions: usize,
+    pub count: usize,
+    #[serde(default)]
+    pub metric: Option<String>,
+    #[serde(default)]
+    pub hnsw: Option<ImportHnswParams>,
+    #[serde(default)]
+    pub ivf: Option<ImportIvfParams>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportHnswParams {
+    pub m: usize,
+    pub m_max: usize,
+    pub ef_construction: usize,
+    #[serde(default)]
+    pub ef_search: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportIvfParams {
+    pub num_probes: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportRecord {
+    id: String,
+    vector: Vec<f32>,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    metadata: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportGraph {
+    entry_point: String,
+    nodes: HashMap<String, Vec<Vec<String>>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportClusters {
+    centroids: Vec<Vec<f32>>,
+    lists: Vec<Vec<String>>,
+}
+
+#[derive(Debug)]
+pub struct ImportReport {
+    pub index_type: IndexType,
+    pub imported: usize,
+    pub id_map: HashMap<String, Uuid>, // external id -> stored Uuid
+}
+
+fn invalid(msg: impl Into<String>) -> crate::error::PiramidError {
+    ServerError::ValidationFailed(format!("Invalid import bundle: {}", msg.into())).into()
+}
+
+fn parse_metric(name: Option<&str>) -> Result<Metric> {
+    match name.map(|n| n.to_lowercase()).as_deref() {
+        None | Some("cosine") => Ok(Metric::Cosine),
+        Some("euclidean") => Ok(Metric::Euclidean),
+        Some("dot") | Some("dot_product") => Ok(Metric::DotProduct),
+        Some(other) => Err(invalid(format!("unknown metric '{other}'"))),
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let data = fs::read(path).map_err(|e| invalid(format!("cannot read {}: {e}", path.display())))?;
+    serde_json::from_slice(&data).map_err(|e| invalid(format!("cannot parse {}: {e}", path.display())))
+}
+
+/// Import a pre-built bundle from `dir` into an empty collection.
+pub fn import_prebuilt(collection: &mut Collection, dir: &str) -> Result<ImportReport> {
+    super::pause::ensure_writable(collection)?;
+    let dir = Path::new(dir);
+
+    // 1. Manifest: format version, index type and the shape everything else is checked against
+    let manifest: ImportManifest = read_json(&dir.join("manifest.json"))?;
+    if manifest.format_version != IMPORT_FORMAT_VERSION {
+        return Err(invalid(format!(
+            "unsupported format_version {} (expected {IMPORT_FORMAT_VERSION})",
+            manifest.format_version
+        )));
+    }
+    let index_type = match manifest.index_type.to_lowercase().as_str() {
+        "flat" => IndexType::Flat,
+        "hnsw" => IndexType::Hnsw,
+        "ivf" => IndexType::Ivf,
+        other => return Err(invalid(format!("unknown index_type '{other}'"))),
+    };
+    let metric = parse_metric(manifest.metric.as_deref())?;
+    if manifest.dimensions == 0 {
+        return Err(invalid("dimensions must be > 0"));
+    }
+
+    if collection.count() > 0 {
+        return Err(ServerError::AlreadyExists(
+            "Pre-built index import requires an empty collection".into(),
+        ).into());
+    }
+    if let Some(dim) = collection.metadata.dimensions {
+        if dim != manifest.dimensions {
+            return Err(invalid(format!("collection has {dim} dimensions, bundle has {}", manifest.dimensions)));
+        }
+    }
+
+    // 2. Vectors: every record must be well-formed before anything is written
+    let file = fs::File::open(dir.join("vectors.jsonl"))
+        .map_err(|e| invalid(format!("cannot read vectors.jsonl: {e}")))?;
+    let mut id_map: HashMap<String, Uuid> = HashMap::with_capacity(manifest.count);
+    let mut docs = Vec::with_capacity(manifest.count);
+    for (line_no, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: ImportRecord = serde_json::from_str(&line)
+            .map_err(|e| invalid(format!("vectors.jsonl line {}: {e}", line_no + 1)))?;
+        if record.vector.len() != manifest.dimensions {
+            return Err(invalid(format!(
+                "vector '{}' has {} dimensions, expected {}",
+                record.id, record.vector.len(), manifest.dimensions
+            )));
+        }
+        if record.vector.iter().any(|v| !v.is_finite()) {
+            return Err(invalid(format!("vector '{}' contains NaN or infinite values", record.id)));
+        }
+        let uuid = Uuid::parse_str(&record.id).unwrap_or_else(|_| crate::testing::ids::new_id());
+        if id_map.insert(record.id.clone(), uuid).is_some() {
+            return Err(invalid(format!("duplicate id '{}'", record.id)));
+        }
+
+        let mut metadata = crate::server::json_to_metadata(record.metadata);
+        metadata.insert(EXTERNAL_ID_KEY.to_string(), MetadataValue::String(record.id));
+        let mut doc = Document::with_metadata(record.vector, record.text, metadata);
+        doc.id = uuid;
+        docs.push(doc);
+    }
+    if docs.len() != manifest.count {
+        return Err(invalid(format!("manifest count is {}, vectors.jsonl has {}", manifest.count, docs.len())));
+    }
+
+    let resolve = |ext: &str| -> Result<Uuid> {
+        id_map.get(ext).copied().ok_or_else(|| invalid(format!("index references unknown id '{ext}'")))
+    };
+
+    // 3. Index structure: map external ids and check it covers exactly the imported vectors
+    let mode = collection.config.execution;
+    let search = collection.config.search;
+    let (vector_index, index_config): (Box<dyn VectorIndex>, IndexConfig) = match index_type {
+        IndexType::Flat => {
+            let mut flat = FlatIndex::new(FlatConfig { metric, mode });
+            let empty = HashMap::new();
+            for doc in &docs {
+                flat.insert(doc.id, &[], &empty);
+            }
+            (Box::new(flat), IndexConfig::Flat { metric, mode, search })
+        }
+        IndexType::Hnsw => {
+            let params = manifest.hnsw.clone().unwrap_or(ImportHnswParams {
+                m: 16,
+                m_max: 32,
+                ef_construction: 200,
+                ef_search: None,
+            });
+            let config = HnswConfig {
+                m: params.m,
+                m_max: params.m_max,
+                ef_construction: params.ef_construction,
+                ef_search: params.ef_search.unwrap_or(params.ef_construction),
+                ml: 1.0 / (params.m.max(2) as f32).ln(),
+                metric,
+                mode,
+            };
+            let raw: ImportGraph = read_json(&dir.join("graph.json"))?;
+            if raw.nodes.len() != docs.len() {
+                return Err(invalid(format!("graph has {} nodes, expected {}", raw.nodes.len(), docs.len())));
+            }
+            let mut graph = HashMap::with_capacity(raw.nodes.len());
+            for (ext, layers) in &raw.nodes {
+                if layers.is_empty() {
+                    return Err(invalid(format!("node '{ext}' has no layers")));
+                }
+                let layers = layers
+                    .iter()
+                    .map(|neighbors| neighbors.iter().map(|n| resolve(n)).collect::<Result<Vec<_>>>())
+                    .collect::<Result<Vec<_>>>()?;
+                graph.insert(resolve(ext)?, layers);
+            }
+            let entry = resolve(&raw.entry_point)?;
+            let top = graph.values().map(|l| l.len()).max().unwrap_or(0);
+            if graph.get(&entry).map(|l| l.len()) != Some(top) {
+                return Err(invalid("entry_point is not on the top layer"));
+            }
+            let index_config = IndexConfig::Hnsw {
+                m: config.m,
+                m_max: config.m_max,
+                ef_construction: config.ef_construction,
+                ef_search: config.ef_search,
+                ml: config.ml,
+                metric,
+                mode,
+                search,
+                shards: 1,
+            };
+            (Box::new(HnswIndex::from_graph(config, graph, Some(entry))), index_config)
+        }
+        IndexType::Ivf => {
+            let raw: ImportClusters = read_json(&dir.join("clusters.json"))?;
+            if raw.centroids.is_empty() || raw.centroids.len() != raw.lists.len() {
+                return Err(invalid("centroids and lists must be non-empty and the same length"));
+            }
+            if raw.centroids.iter().any(|c| c.len() != manifest.dimensions) {
+                return Err(invalid("centroid dimensions do not match the manifest"));
+            }
+            let mut seen = HashSet::with_capacity(docs.len());
+            let mut lists = Vec::with_capacity(raw.lists.len());
+            for list in &raw.lists {
+                let mut ids = Vec::with_capacity(list.len());
+                for ext in list {
+                    let id = resolve(ext)?;
+                    if !seen.insert(id) {
+                        return Err(invalid(format!("id '{ext}' appears in more than one cluster")));
+                    }
+                    ids.push(id);
+                }
+                lists.push(ids);
+            }
+            if seen.len() != docs.len() {
+                return Err(invalid(format!("clusters cover {} of {} vectors", seen.len(), docs.len())));
+            }
+            let num_probes = manifest.ivf.as_ref().map(|p| p.num_probes).unwrap_or(8).max(1);
+            let config = IvfConfig {
+                num_clusters: raw.centroids.len(),
+                num_probes,
+                max_iterations: 20,
+                metric,
+                mode,
+            };
+            let index_config = IndexConfig::Ivf {
+                num_clusters: config.num_clusters,
+                num_probes,
+                max_iterations: config.max_iterations,
+                metric,
+                mode,
+                search,
+            };
+            // Centroids live in the same space as the indexed vectors, so they get the collection's transform (and projection) too
+            let centroids = raw.centroids.iter().map(|c| collection.index_vector(c).into_owned()).collect();
+            (Box::new(IvfIndex::from_clusters(config, centroids, lists)), index_config)
+        }
+    };
+
+    // 4. Everything checked out: write the documents, swap in the imported index and persist.
+    // The WAL is bypassed; the checkpoint below makes the import durable in one step.
+    let imported = docs.len();
+    for doc in docs {
+        operations::append_document(collection, doc)?;
+    }
+    collection.metadata.counters.record_inserts(imported);
+    collection.vector_index = vector_index;
+    collection.config.index = index_config;
+    collection.apply_index_settings();
+    super::persistence::checkpoint(collection)?;
+    // The imported documents never went through the WAL, so history restarts from here
+    super::history::rebase(collection)?;
+
+    tracing::info!(collection=%collection.path, index_type=%index_type, imported, "prebuilt_index_imported");
+
+    Ok(ImportReport { index_type, imported, id_map })
+}
@@ -0,0 +1,197 @@
+// Online switch of a collection's index type (e.g. HNSW to IVF) while it keeps serving.
+// The new index is built from the stored vectors under a shared lock, so searches and writes carry on
+// against the old one. Writes that landed during the build are then read back from the WAL (see
+// changes.rs) and applied to the new index, first under the shared lock and, for the last few, under
+// the exclusive one, where the indexes are swapped. The old index stays in place until the new one
+// has been saved: if the save fails the old index is put back and the collection carries on as before.
+//
+// Without the WAL changes (WAL off, or a checkpoint truncated them mid-build) the swap falls back to
+// the recovery rule: the build is kept only if nothing was written since, otherwise the new index is
+// rebuilt under the exclusive lock.
+//
+// The chosen index config is stored in `.indexcfg.json` and replaces the configured one when the
+// collection opens, so restarts and later rebuilds keep it. It is part of snapshots.
+
+use std::collections::HashSet;
+use std::fs;
+use std::time::Instant;
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{Result, ServerError};
+use crate::index::{IndexConfig, IndexType, VectorIndex};
+use super::changes::MAX_CHANGES_PER_PAGE;
+use super::storage::Collection;
+
+// Catch-up passes under the shared lock before the rest is applied under the exclusive one
+const CATCH_UP_ROUNDS: usize = 4;
+
+pub fn get_index_config_path(collection_path: &str) -> String {
+    format!("{}.indexcfg.json", collection_path)
+}
+
+// The index config a previous migration chose, if any
+pub fn load_index_override(collection_path: &str) -> Result<Option<IndexConfig>> {
+    match fs::read(get_index_config_path(collection_path)) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save_index_override(collection_path: &str, index: &IndexConfig) -> Result<()> {
+    let path = get_index_config_path(collection_path);
+    let tmp = format!("{path}.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(index)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexMigrationReport {
+    pub from: IndexType,
+    pub to: IndexType,
+    pub vectors: usize,
+    pub caught_up: usize, // writes made during the build and applied to the new index afterwards
+    pub rebuilt: bool, // the writes could not be read back, so the index was rebuilt under the exclusive lock
+    pub build_ms: u64,
+    pub swap_ms: u64, // time the collection was held exclusively
+}
+
+// Apply the changes after `seq` to `index`, from the vectors the collection holds now. Returns the
+// sequence number reached and how many documents were touched, or None when the changes cannot be read.
+fn catch_up(collection: &Collection, index: &mut Box<dyn VectorIndex>, mut seq: u64) -> Option<(u64, usize)> {
+    let mut touched: HashSet<Uuid> = HashSet::new();
+    loop {
+        let batch = collection.changes(seq, MAX_CHANGES_PER_PAGE).ok()?;
+        touched.extend(batch.changes.iter().map(|c| c.id));
+        if batch.changes.is_empty() || batch.next_seq <= seq {
+            break;
+        }
+        seq = batch.next_seq;
+    }
+    // Whatever happened to a document in between, the index ends up holding its current vector. The
+    // vector cache keeps deleted vectors for an HNSW graph's tombstones, so liveness comes from the pointers.
+    let data = collection.data.read_recursive();
+    for id in &touched {
+        index.remove(id);
+        if let Some(vector) = collection.vector_cache.get(id).filter(|_| data.index.contains_key(id)) {
+            index.insert(*id, vector, &collection.vector_cache);
+        }
+    }
+    drop(data);
+    Some((seq.max(collection.head_seq()), touched.len()))
+}
+
+// Build an index of `target`, catch it up and swap it in
+pub fn migrate_index(handle: &RwLock<Collection>, target: IndexConfig) -> Result<IndexMigrationReport> {
+    let start = Instant::now();
+    let (mut built, mut seq) = {
+        let collection = handle.read();
+        if collection.two_stage.is_some() {
+            return Err(ServerError::InvalidRequest(
+                "Two-stage collections scan quantized codes; they have no index to migrate".into(),
+            ).into());
+        }
+        if collection.metadata.sealed_at.is_some() {
+            return Err(ServerError::InvalidRequest("Sealed collections keep the index they were sealed with".into()).into());
+        }
+        super::pause::ensure_writable(&collection)?;
+        (collection.build_index_of(&target)?, collection.head_seq())
+    };
+
+    // Writes made during the build, while searches and writes still go to the old index
+    let mut caught_up = 0;
+    let mut readable = true;
+    for _ in 0..CATCH_UP_ROUNDS {
+        let collection = handle.read();
+        if collection.head_seq() == seq {
+            break;
+        }
+        match catch_up(&collection, &mut built, seq) {
+            Some((reached, touched)) => {
+                seq = reached;
+                caught_up += touched;
+            }
+            None => {
+                readable = false;
+                break;
+            }
+        }
+    }
+    let build_ms = start.elapsed().as_millis() as u64;
+
+    let swap_start = Instant::now();
+    let mut collection = handle.write();
+    // Paused while the new index was built: the files stay as they are and the build is dropped
+    super::pause::ensure_writable(&collection)?;
+    let from = collection.vector_index.index_type();
+    let mut rebuilt = false;
+    if collection.head_seq() != seq {
+        let caught = if readable { catch_up(&collection, &mut built, seq) } else { None };
+        match caught {
+            Some((_, touched)) => caught_up += touched,
+            None => {
+                built = collection.build_index_of(&target)?;
+                rebuilt = true;
+            }
+        }
+    }
+
+    // The old index and config stay at hand until the new ones are on disk
+    let previous_config = std::mem::replace(&mut collection.config.index, target.clone());
+    let previous = std::mem::replace(&mut collection.vector_index, built);
+    let previous_recovery = collection.index_recovery.take();
+    collection.apply_index_settings();
+    let saved = super::persistence::save_vector_index(&collection).and_then(|_| {
+        if collection.config.ephemeral {
+            Ok(())
+        } else {
+            save_index_override(&collection.path, &target)
+        }
+    });
+    if let Err(e) = saved {
+        collection.vector_index = previous;
+        collection.config.index = previous_config;
+        collection.index_recovery = previous_recovery;
+        collection.apply_index_settings();
+        if let Err(restore) = super::persistence::save_vector_index(&collection) {
+            tracing::warn!(collection=%collection.path, error=%restore, "vector_index_restore_failed");
+        }
+        tracing::error!(collection=%collection.path, error=%e, "index_migration_reverted");
+        return Err(e);
+    }
+
+    collection.metadata.counters.record_rebuild();
+    if let Err(e) = super::persistence::save_metadata(&collection) {
+        tracing::warn!(collection=%collection.path, error=%e, "metadata_save_failed");
+    }
+
+    // What the old graph kept for its tombstones has no use for the new index
+    if from == IndexType::Hnsw && collection.vector_index.index_type() != IndexType::Hnsw {
+        let collection = &mut *collection;
+        let data = collection.data.get_mut();
+        let dead: Vec<Uuid> = collection.vector_cache.keys().filter(|id| !data.index.contains_key(id)).copied().collect();
+        for id in &dead {
+            collection.vector_cache.remove(id);
+            data.remove_metadata(id);
+        }
+    }
+
+    let report = IndexMigrationReport {
+        from,
+        to: collection.vector_index.index_type(),
+        vectors: collection.vector_index.stats().total_vectors,
+        caught_up,
+        rebuilt,
+        build_ms,
+        swap_ms: swap_start.elapsed().as_millis() as u64,
+    };
+    tracing::info!(
+        collection=%collection.path, from=%report.from, to=%report.to, caught_up=report.caught_up,
+        rebuilt=report.rebuilt, swap_ms=report.swap_ms, "index_migrated"
+    );
+    Ok(report)
+}
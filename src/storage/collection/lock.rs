@@ -0,0 +1,70 @@
+// Keeping a second process off a collection's files, and where those files live.
+// Opening a collection takes an exclusive lock on `<path>.lock` and holds it while the collection is
+// open; a process finding it taken fails at once with `CollectionLocked` instead of replaying and
+// checkpointing the same WAL as the owner. The lock is advisory (flock on Unix, LockFileEx on
+// Windows) and goes with the process, so a crashed owner leaves nothing to clean up. It sits on a
+// file of its own because Windows locks are mandatory: one on the data file would also stop the
+// owner's own reads and maps of it. The lock file itself stays behind, holding the owner's pid.
+//
+// Collections of one process opened on the same path (a restore reopening the collection it
+// replaces, tests reopening one) share the lock instead of contending for it.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, Weak};
+
+use fs2::FileExt;
+use parking_lot::Mutex;
+
+use crate::error::{Result, ServerError};
+
+// The file of collection `name` in `data_dir`, which its other files are named after
+pub fn collection_path(data_dir: impl AsRef<Path>, name: &str) -> String {
+    data_dir.as_ref().join(format!("{name}.db")).to_string_lossy().into_owned()
+}
+
+pub fn get_lock_path(collection_path: &str) -> String {
+    format!("{}.lock", collection_path)
+}
+
+// Held while a collection is open; released when the last collection sharing it is dropped
+pub struct CollectionLock {
+    _file: File,
+}
+
+// Locks this process holds, by canonical lock file path
+fn held() -> &'static Mutex<HashMap<PathBuf, Weak<CollectionLock>>> {
+    static HELD: OnceLock<Mutex<HashMap<PathBuf, Weak<CollectionLock>>>> = OnceLock::new();
+    HELD.get_or_init(Default::default)
+}
+
+pub(super) fn acquire(collection_path: &str) -> Result<Arc<CollectionLock>> {
+    let path = get_lock_path(collection_path);
+    let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+    let key = std::fs::canonicalize(&path)?;
+    let mut held = held().lock();
+    if let Some(lock) = held.get(&key).and_then(Weak::upgrade) {
+        return Ok(lock);
+    }
+    if let Err(e) = file.try_lock_exclusive() {
+        if e.raw_os_error() != fs2::lock_contended_error().raw_os_error() {
+            return Err(e.into());
+        }
+        let mut owner = String::new();
+        let _ = file.read_to_string(&mut owner);
+        let owner = owner.trim();
+        return Err(ServerError::CollectionLocked(format!(
+            "{collection_path} is open in another process{}; stop it or use another data dir",
+            if owner.is_empty() { String::new() } else { format!(" (pid {owner})") },
+        )).into());
+    }
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    write!(file, "{}", std::process::id())?;
+    held.retain(|_, lock| lock.strong_count() > 0);
+    let lock = Arc::new(CollectionLock { _file: file });
+    held.insert(key, Arc::downgrade(&lock));
+    Ok(lock)
+}
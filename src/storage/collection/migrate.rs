@@ -0,0 +1,490 @@
+// Import of documents that other tools wrote: Parquet and Arrow IPC files (our exports included),
+// Qdrant point dumps and Chroma `get()` dumps. Each source is read as rows of named fields, and a
+// small mapping says which field is the id, the vector, the text and a JSON metadata object; the
+// other fields (all of them, or the listed ones) become metadata keys.
+//
+// - parquet, arrow: one field per column
+// - qdrant: points as JSON lines, a JSON array, or a scroll response ({"result": {"points": [..]}});
+//   a point's `id` and `vector` plus its payload keys. Named vectors are `vector.<name>`.
+// - chroma: the object `collection.get(include=[...])` returns, or one such page per line;
+//   `id`, `embedding`, `document` and `uri` plus the metadata keys
+//
+// Ids that are Uuids are kept; any other id is stored as the document's external id, so rows can be
+// imported again (or a job resumed) without duplicating documents that already arrived: rows whose
+// id is already in the collection are upserted, the rest inserted batch by batch.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines, Read};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::error::{PiramidError, Result, ServerError};
+use crate::storage::columnar::{ArrowReader, ArrowStreamReader, BatchReader, Column, ColumnBatch, ParquetReader, Selection};
+use crate::storage::document::Document;
+use super::storage::Collection;
+
+pub const DEFAULT_IMPORT_BATCH_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceFormat {
+    Parquet,
+    Arrow,
+    Qdrant,
+    Chroma,
+}
+
+impl SourceFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SourceFormat::Parquet => "parquet",
+            SourceFormat::Arrow => "arrow",
+            SourceFormat::Qdrant => "qdrant",
+            SourceFormat::Chroma => "chroma",
+        }
+    }
+
+    // Columnar files are recognised by extension; dumps are JSON either way and must be named
+    pub fn from_path(path: &str) -> Option<Self> {
+        match Path::new(path).extension()?.to_str()?.to_lowercase().as_str() {
+            "parquet" | "pq" => Some(SourceFormat::Parquet),
+            "arrow" | "arrows" | "feather" | "ipc" => Some(SourceFormat::Arrow),
+            _ => None,
+        }
+    }
+
+    // Field names a mapping falls back to: (id, vector, text, metadata)
+    fn defaults(&self) -> (&'static str, &'static str, &'static str, Option<&'static str>) {
+        match self {
+            SourceFormat::Parquet | SourceFormat::Arrow => ("id", "vector", "text", Some("metadata")),
+            SourceFormat::Qdrant => ("id", "vector", "text", None),
+            SourceFormat::Chroma => ("id", "embedding", "document", None),
+        }
+    }
+}
+
+// Which source field is which; unset entries take the format's defaults. A default field the source
+// does not have is left out, a named one must exist.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FieldMapping {
+    pub id: Option<String>,
+    pub vector: Option<String>,
+    pub text: Option<String>,
+    pub metadata: Option<String>,    // a field holding a JSON object (or its text) merged into the metadata
+    pub fields: Option<Vec<String>>, // copied into the metadata under their own names; None copies every other field
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocumentImportReport {
+    pub rows: u64,
+    pub inserted: u64,
+    pub updated: u64,
+    pub skipped_columns: Vec<String>, // columns of a type that cannot be imported
+}
+
+// A field's value: JSON, or the floats of a list column read without going through JSON
+#[derive(Debug, Clone)]
+enum Cell {
+    Json(Value),
+    Floats(Vec<f32>),
+}
+
+type Row = Vec<(String, Cell)>;
+
+fn invalid(msg: impl std::fmt::Display) -> PiramidError {
+    ServerError::ValidationFailed(format!("Invalid import: {msg}")).into()
+}
+
+fn float_json(v: f64) -> Value {
+    serde_json::Number::from_f64(v).map_or(Value::Null, Value::Number)
+}
+
+fn column_cells(column: Column) -> Vec<Cell> {
+    match column {
+        Column::Bool(v) => v.into_iter().map(|x| Cell::Json(x.map_or(Value::Null, Value::Bool))).collect(),
+        Column::Int(v) => v.into_iter().map(|x| Cell::Json(x.map_or(Value::Null, Value::from))).collect(),
+        Column::Float(v) => v.into_iter().map(|x| Cell::Json(x.map_or(Value::Null, float_json))).collect(),
+        Column::Utf8(v) => v.into_iter().map(|x| Cell::Json(x.map_or(Value::Null, Value::String))).collect(),
+        Column::FloatList(v) => v.into_iter().map(|x| x.map_or(Cell::Json(Value::Null), Cell::Floats)).collect(),
+    }
+}
+
+fn batch_rows(batch: ColumnBatch) -> Vec<Row> {
+    let mut columns: Vec<(String, std::vec::IntoIter<Cell>)> =
+        batch.columns.into_iter().map(|(name, column)| (name, column_cells(column).into_iter())).collect();
+    (0..batch.rows)
+        .map(|_| {
+            columns
+                .iter_mut()
+                .map(|(name, cells)| (name.clone(), cells.next().unwrap_or(Cell::Json(Value::Null))))
+                .collect()
+        })
+        .collect()
+}
+
+fn json_fields(object: serde_json::Map<String, Value>) -> impl Iterator<Item = (String, Cell)> {
+    object.into_iter().map(|(k, v)| (k, Cell::Json(v)))
+}
+
+// A dump's unit: a point or a page of points (Qdrant), or a page of columns (Chroma)
+fn dump_rows(format: SourceFormat, value: Value, out: &mut VecDeque<Row>) -> Result<()> {
+    match (format, value) {
+        (_, Value::Array(items)) => {
+            for item in items {
+                dump_rows(format, item, out)?;
+            }
+        }
+        (SourceFormat::Qdrant, Value::Object(mut object)) => {
+            if let Some(result) = object.remove("result") {
+                return dump_rows(format, result, out);
+            }
+            if let Some(points) = object.remove("points") {
+                return dump_rows(format, points, out);
+            }
+            let mut row: Row = match object.remove("payload") {
+                Some(Value::Object(payload)) => json_fields(payload).collect(),
+                _ => Vec::new(),
+            };
+            match object.remove("vector") {
+                Some(Value::Object(named)) => row.extend(named.into_iter().map(|(name, v)| (format!("vector.{name}"), Cell::Json(v)))),
+                Some(vector) => row.push(("vector".into(), Cell::Json(vector))),
+                None => {}
+            }
+            row.push(("id".into(), Cell::Json(object.remove("id").unwrap_or(Value::Null))));
+            out.push_back(row);
+        }
+        (SourceFormat::Chroma, Value::Object(mut object)) => {
+            let mut take = |key: &str| match object.remove(key) {
+                Some(Value::Array(items)) => items,
+                _ => Vec::new(),
+            };
+            let ids = take("ids");
+            let mut embeddings = take("embeddings").into_iter();
+            let mut documents = take("documents").into_iter();
+            let mut uris = take("uris").into_iter();
+            let mut metadatas = take("metadatas").into_iter();
+            for id in ids {
+                let mut row: Row = match metadatas.next() {
+                    Some(Value::Object(metadata)) => json_fields(metadata).collect(),
+                    _ => Vec::new(),
+                };
+                row.push(("id".into(), Cell::Json(id)));
+                row.push(("embedding".into(), Cell::Json(embeddings.next().unwrap_or(Value::Null))));
+                row.push(("document".into(), Cell::Json(documents.next().unwrap_or(Value::Null))));
+                if let Some(uri) = uris.next().filter(|u| !u.is_null()) {
+                    row.push(("uri".into(), Cell::Json(uri)));
+                }
+                out.push_back(row);
+            }
+        }
+        (format, _) => return Err(invalid(format!("not a {} dump", format.name()))),
+    }
+    Ok(())
+}
+
+enum Rows {
+    Columnar(Box<dyn BatchReader + Send>),
+    JsonLines(Lines<BufReader<File>>, usize), // and the line number
+    Loaded,                                   // the whole dump was one JSON document, already in the buffer
+}
+
+// The mapping with defaults applied, against the fields the source has where that is known
+#[derive(Debug, Clone)]
+struct Resolved {
+    id: Option<String>,
+    vector: String,
+    text: Option<String>,
+    metadata: Option<String>,
+    fields: Option<Vec<String>>,
+}
+
+impl Resolved {
+    fn new(format: SourceFormat, mapping: &FieldMapping, known: Option<&[String]>) -> Result<Self> {
+        let (id, vector, text, metadata) = format.defaults();
+        let has = |name: &str| known.is_none_or(|fields| fields.iter().any(|f| f == name));
+        let named = mapping.id.iter().chain(&mapping.text).chain(&mapping.metadata).chain(mapping.fields.iter().flatten());
+        if let Some(missing) = named.into_iter().find(|name| !has(name)) {
+            return Err(invalid(format!("no field '{missing}' (fields: {})", known.unwrap_or_default().join(", "))));
+        }
+        let vector = mapping.vector.clone().unwrap_or_else(|| vector.to_string());
+        if !has(&vector) {
+            return Err(invalid(format!(
+                "no vector field '{vector}' (fields: {}); set mapping.vector",
+                known.unwrap_or_default().join(", ")
+            )));
+        }
+        Ok(Self {
+            id: mapping.id.clone().or_else(|| has(id).then(|| id.to_string())),
+            vector,
+            text: mapping.text.clone().or_else(|| has(text).then(|| text.to_string())),
+            metadata: mapping.metadata.clone().or_else(|| metadata.filter(|m| has(m)).map(str::to_string)),
+            fields: mapping.fields.clone(),
+        })
+    }
+
+    fn mapped(&self) -> impl Iterator<Item = &String> {
+        self.id.iter().chain([&self.vector]).chain(self.text.iter()).chain(self.metadata.iter())
+    }
+
+    fn selection(&self) -> Selection {
+        match &self.fields {
+            None => Selection::All,
+            Some(fields) => Selection::Only(self.mapped().chain(fields).cloned().collect()),
+        }
+    }
+
+    fn document(&self, row: Row, number: u64) -> Result<Document> {
+        let at = |what: &str| invalid(format!("row {number}: {what}"));
+        let mut vector = None;
+        let mut id = None;
+        let mut text = String::new();
+        let mut metadata: HashMap<String, Value> = HashMap::new();
+        let mut extra = Vec::new();
+        for (name, cell) in row {
+            if name == self.vector {
+                vector = Some(match cell {
+                    Cell::Floats(v) => v,
+                    Cell::Json(Value::Array(items)) => items
+                        .iter()
+                        .map(|v| v.as_f64().map(|f| f as f32))
+                        .collect::<Option<Vec<f32>>>()
+                        .ok_or_else(|| at(&format!("'{name}' is not a list of numbers")))?,
+                    Cell::Json(Value::Null) => return Err(at(&format!("no vector in '{name}'"))),
+                    Cell::Json(_) => return Err(at(&format!("'{name}' is not a list of numbers"))),
+                });
+            } else if self.id.as_ref() == Some(&name) {
+                id = match cell {
+                    Cell::Json(Value::String(s)) => Some(s),
+                    Cell::Json(Value::Number(n)) => Some(n.to_string()),
+                    Cell::Json(Value::Null) => None,
+                    _ => return Err(at(&format!("'{name}' is not a string or number"))),
+                };
+            } else if self.text.as_ref() == Some(&name) {
+                text = match cell {
+                    Cell::Json(Value::String(s)) => s,
+                    Cell::Json(Value::Null) => String::new(),
+                    Cell::Json(other) => other.to_string(),
+                    Cell::Floats(_) => return Err(at(&format!("'{name}' is a list, not text"))),
+                };
+            } else if self.metadata.as_ref() == Some(&name) {
+                let object = match cell {
+                    Cell::Json(Value::String(s)) => serde_json::from_str(&s).map_err(|e| at(&format!("'{name}' is not JSON: {e}")))?,
+                    Cell::Json(other) => other,
+                    Cell::Floats(_) => Value::Null,
+                };
+                match object {
+                    Value::Object(object) => metadata.extend(object),
+                    Value::Null => {}
+                    _ => return Err(at(&format!("'{name}' is not a JSON object"))),
+                }
+            } else if self.fields.as_ref().is_none_or(|fields| fields.contains(&name)) {
+                extra.push((name, cell));
+            }
+        }
+        // Named fields win over keys of the metadata object
+        for (name, cell) in extra {
+            let value = match cell {
+                Cell::Json(Value::Null) => continue,
+                Cell::Json(value) => value,
+                Cell::Floats(v) => Value::Array(v.into_iter().map(|f| float_json(f as f64)).collect()),
+            };
+            metadata.insert(name, value);
+        }
+
+        let vector = vector.ok_or_else(|| at(&format!("no vector in '{}'", self.vector)))?;
+        let mut doc = Document::with_metadata(vector, text, crate::server::json_to_metadata(metadata));
+        match id.as_deref().map(|id| (id, Uuid::parse_str(id))) {
+            Some((_, Ok(uuid))) => doc.id = uuid,
+            Some((external, Err(_))) => doc = doc.with_external_id(external),
+            None => {}
+        }
+        Ok(doc)
+    }
+}
+
+// Rows of a source file turned into documents, a batch at a time
+pub struct DocumentSource {
+    rows: Rows,
+    buffer: VecDeque<Row>,
+    format: SourceFormat,
+    mapping: Resolved,
+    total: Option<u64>,
+    skipped_columns: Vec<String>,
+    read: u64,
+}
+
+impl DocumentSource {
+    pub fn open(path: &str, format: SourceFormat, mapping: &FieldMapping) -> Result<Self> {
+        let file = File::open(path).map_err(|e| invalid(format!("cannot read {path}: {e}")))?;
+        let mut buffer = VecDeque::new();
+        let (rows, resolved, total, skipped_columns) = match format {
+            SourceFormat::Parquet | SourceFormat::Arrow => {
+                let mut reader: Box<dyn BatchReader + Send> = match format {
+                    SourceFormat::Parquet => Box::new(ParquetReader::new(file)?),
+                    _ => Box::new(ArrowReader::new(BufReader::new(file))?),
+                };
+                let resolved = Resolved::new(format, mapping, Some(&reader.columns()))?;
+                reader.select(&resolved.selection())?;
+                let (total, skipped) = (reader.total_rows(), reader.skipped_columns().to_vec());
+                (Rows::Columnar(reader), resolved, total, skipped)
+            }
+            SourceFormat::Qdrant | SourceFormat::Chroma => {
+                let resolved = Resolved::new(format, mapping, None)?;
+                let mut lines = BufReader::new(file).lines();
+                let mut number = 0;
+                // A first line that is a whole JSON value means JSON lines; otherwise one document
+                let first = loop {
+                    match lines.next().transpose()? {
+                        Some(line) if line.trim().is_empty() => number += 1,
+                        other => break other,
+                    }
+                };
+                let rows = match first.as_deref().map(serde_json::from_str::<Value>) {
+                    Some(Ok(value)) => {
+                        dump_rows(format, value, &mut buffer)?;
+                        Rows::JsonLines(lines, number + 1)
+                    }
+                    Some(Err(_)) => {
+                        drop(lines);
+                        let file = File::open(path)?;
+                        let value: Value = serde_json::from_reader(BufReader::new(file))
+                            .map_err(|e| invalid(format!("{path} is neither JSON lines nor a JSON document: {e}")))?;
+                        dump_rows(format, value, &mut buffer)?;
+                        Rows::Loaded
+                    }
+                    None => Rows::Loaded,
+                };
+                let total = matches!(rows, Rows::Loaded).then_some(buffer.len() as u64);
+                (rows, resolved, total, Vec::new())
+            }
+        };
+        Ok(Self { rows, buffer, format, mapping: resolved, total, skipped_columns, read: 0 })
+    }
+
+    // Rows of an Arrow IPC stream read as it arrives (a request body), with the Arrow defaults
+    pub fn arrow_stream(reader: impl Read + Send + 'static, mapping: &FieldMapping) -> Result<Self> {
+        let mut reader = ArrowStreamReader::new(reader)?;
+        let mapping = Resolved::new(SourceFormat::Arrow, mapping, Some(&reader.columns()))?;
+        reader.select(&mapping.selection())?;
+        let skipped_columns = reader.skipped_columns().to_vec();
+        Ok(Self {
+            rows: Rows::Columnar(Box::new(reader)),
+            buffer: VecDeque::new(),
+            format: SourceFormat::Arrow,
+            mapping,
+            total: None,
+            skipped_columns,
+            read: 0,
+        })
+    }
+
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+
+    pub fn skipped_columns(&self) -> &[String] {
+        &self.skipped_columns
+    }
+
+    // Rows handed out (or skipped) so far
+    pub fn position(&self) -> u64 {
+        self.read
+    }
+
+    // Refill the buffer from the source; false at its end
+    fn fill(&mut self) -> Result<bool> {
+        while self.buffer.is_empty() {
+            match &mut self.rows {
+                Rows::Columnar(reader) => match reader.next_batch()? {
+                    Some(batch) => self.buffer.extend(batch_rows(batch)),
+                    None => return Ok(false),
+                },
+                Rows::JsonLines(lines, number) => match lines.next().transpose()? {
+                    Some(line) => {
+                        *number += 1;
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        let value = serde_json::from_str(&line).map_err(|e| invalid(format!("line {number}: {e}")))?;
+                        dump_rows(self.format, value, &mut self.buffer)?;
+                    }
+                    None => return Ok(false),
+                },
+                Rows::Loaded => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
+    // Pass over the first `rows` rows, which a resumed import already wrote
+    pub fn skip(&mut self, rows: u64) -> Result<()> {
+        while self.read < rows && self.fill()? {
+            let take = (rows - self.read).min(self.buffer.len() as u64) as usize;
+            self.buffer.drain(..take);
+            self.read += take as u64;
+        }
+        Ok(())
+    }
+
+    // The next `size` rows as documents, None once the source is exhausted
+    pub fn next_batch(&mut self, size: usize) -> Result<Option<Vec<Document>>> {
+        let mut docs = Vec::with_capacity(size.min(DEFAULT_IMPORT_BATCH_SIZE));
+        while docs.len() < size.max(1) && self.fill()? {
+            let row = self.buffer.pop_front().unwrap_or_default();
+            self.read += 1;
+            docs.push(self.mapping.document(row, self.read)?);
+        }
+        Ok((!docs.is_empty()).then_some(docs))
+    }
+}
+
+// Write one batch: documents whose id (or external id) is already in the collection are upserted,
+// the rest inserted together. Returns (inserted, updated).
+pub fn write_documents(collection: &mut Collection, docs: Vec<Document>) -> Result<(u64, u64)> {
+    let (existing, new): (Vec<Document>, Vec<Document>) = {
+        let data = collection.data.read_recursive();
+        docs.into_iter().partition(|doc| {
+            data.index.contains_key(&doc.id) || doc.external_id().is_some_and(|ext| data.external_ids.contains_key(ext))
+        })
+    };
+    let updated = existing.len() as u64;
+    for doc in existing {
+        collection.upsert(doc)?;
+    }
+    let inserted = new.len() as u64;
+    if !new.is_empty() {
+        collection.insert_batch(new)?;
+    }
+    Ok((inserted, updated))
+}
+
+/// Import every row of `path` in batches of `batch_size`, calling `progress` with the rows written
+/// so far and the total when the source knows it.
+pub fn import_documents(
+    collection: &mut Collection,
+    path: &str,
+    format: SourceFormat,
+    mapping: &FieldMapping,
+    batch_size: usize,
+    mut progress: impl FnMut(u64, Option<u64>),
+) -> Result<DocumentImportReport> {
+    let mut source = DocumentSource::open(path, format, mapping)?;
+    let mut report = DocumentImportReport { skipped_columns: source.skipped_columns().to_vec(), ..Default::default() };
+    while let Some(docs) = source.next_batch(batch_size)? {
+        let (inserted, updated) = write_documents(collection, docs)?;
+        report.inserted += inserted;
+        report.updated += updated;
+        report.rows = source.position();
+        progress(report.rows, source.total());
+    }
+    tracing::info!(
+        collection=%collection.path, format=format.name(), rows=report.rows, inserted=report.inserted,
+        updated=report.updated, "documents_imported"
+    );
+    Ok(report)
+}
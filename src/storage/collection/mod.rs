@@ -2,12 +2,36 @@
 //
 // This module now uses a modular structure:
 // - storage.rs: Core data structure and basic accessors
+// - data.rs: Document store (data file, pointers, metadata) behind the collection's data latch
 // - builder.rs: Initialization and recovery logic
 // - operations.rs: CRUD operations (insert, delete, update)
 // - search.rs: Search helpers (single/batch)
+// - import.rs: Write-once import of vectors with a pre-built index
+// - two_stage.rs: Quantized codes + full-precision sidecar for two-stage search
+// - column.rs: Fixed-stride vector column file for sequential scans
+// - projection.rs: Trained PCA/OPQ projection applied before indexing
+// - tuning.rs: ef/nprobe sweeps against exact results and the kept recommendation
+// - verify.rs: Consistency check of pointers, data file and vector index, and repair
+// - export.rs: Bulk export of the documents as Parquet or an Arrow IPC file
+// - stats.rs: Aggregate vector statistics (centroid, variance, norms, intrinsic dimensionality)
+// - sampler.rs: Reservoir sample kept up to date by writes, for statistics without a full scan
+// - outlier.rs: Outlier scores (k-th neighbour or centroid similarity) ranked against stored documents
+// - migrate.rs: Batched import of Parquet/Arrow files and Qdrant/Chroma dumps through a field mapping
+// - replica.rs: In-memory read replicas that follow the collection's WAL sequence
+// - history.rs: Point-in-time views rebuilt from retained WAL history
+// - changes.rs: Ordered change records read back from the WAL for change-data-capture
+// - snapshot.rs: Named on-disk snapshots and restore
+// - rename.rs: Moving a collection's files to a new name
+// - lock.rs: Lock keeping other processes off an open collection's files, and the collection path in a data dir
+// - index_migration.rs: Online switch to another index type, built in the background and swapped in
+// - requantize.rs: Runtime switch of the quantization level and re-encoding of the stored vectors
+// - backpressure.rs: Write throttling from the WAL backlog checkpoints have not caught up with
+// - pause.rs: Operator pauses of a collection's writes, with consistent files on disk and a deadline
+// - worm.rs: Sealing a collection write-once: compacted, without a WAL, rejecting every write
 // - persistence.rs: Disk operations and checkpointing
 
 mod storage;
+mod data;
 mod operations;
 mod builder;
 mod cache;
@@ -15,24 +39,80 @@ mod persistence;
 mod search;
 mod dup;
 mod compact;
+mod import;
+mod two_stage;
+mod column;
+mod projection;
+mod tuning;
+mod verify;
+mod export;
+mod stats;
+mod outlier;
+mod migrate;
+mod replica;
+mod history;
+mod changes;
+mod snapshot;
+mod rename;
+mod recovery;
+mod index_migration;
+mod requantize;
+mod reconfigure;
+mod sampler;
+mod backpressure;
+mod worm;
+mod pause;
+mod lock;
 
 pub use storage::Collection;
+pub use data::DataStore;
 pub use builder::CollectionBuilder;
 pub use compact::{compact, CompactStats};
 pub use dup::{find_duplicates, DuplicateHit};
+pub use import::{import_prebuilt, ImportReport, ImportManifest, IMPORT_FORMAT_VERSION};
+pub use two_stage::{TwoStageState, FullPrecisionStore, get_full_precision_path};
+pub use column::{VectorColumn, get_column_path, get_column_ids_path};
+pub use projection::{Projection, ProjectionKind, index_space, get_projection_path, DEFAULT_PROJECTION_SAMPLE};
+pub use tuning::{
+    TuneOptions, TunedParam, TuningPoint, TuningPreset, TuningReport, get_tuning_path, DEFAULT_TUNING_SAMPLE,
+    DEFAULT_TARGET_RECALL,
+};
+pub use verify::{VerifyReport, IssueList, MAX_REPORTED_IDS};
+pub use persistence::PendingCheckpoint;
+pub use backpressure::{WalBacklog, WritePressure};
+pub use worm::SealReport;
+pub use pause::{WritePause, DEFAULT_WRITE_PAUSE_SECS, MAX_WRITE_PAUSE_SECS};
+pub use export::{export_rows, ExportFormat, ExportReport, EXPORT_BATCH_ROWS};
+pub use stats::{
+    DimensionCount, DimensionReport, HistogramBin, IntrinsicDimension, NormStats, StatsOptions, VectorStats,
+    DEFAULT_HISTOGRAM_BINS, DEFAULT_INTRINSIC_SAMPLE,
+};
+pub use sampler::{FieldStats, SampledNorms, SampledStats, StatsSampler, ValueShare, DEFAULT_STATS_SAMPLE, MAX_TOP_VALUES};
+pub use outlier::{
+    OutlierMethod, OutlierOptions, OutlierQuery, OutlierReport, OutlierScore, DEFAULT_OUTLIER_K, DEFAULT_OUTLIER_SAMPLE,
+};
+pub use migrate::{
+    import_documents, write_documents, DocumentImportReport, DocumentSource, FieldMapping, SourceFormat,
+    DEFAULT_IMPORT_BATCH_SIZE,
+};
+pub use replica::{ReplicaSet, ReadReplica, ReplicationFeed, SearchGuard};
+pub use history::{AsOf, CollectionView};
+pub use changes::{Change, ChangeKind, ChangeBatch, MAX_CHANGES_PER_PAGE};
+pub use rename::rename_files;
+pub use lock::{collection_path, get_lock_path, CollectionLock};
+pub use recovery::{IndexRecovery, recover_index};
+pub use index_migration::{migrate_index, get_index_config_path, IndexMigrationReport};
+pub use requantize::{get_quantization_config_path, QuantizationComposition, RequantizeReport};
+pub use reconfigure::ConfigChanges;
+pub use snapshot::{
+    create_snapshot, list_snapshots, read_manifest, delete_snapshot, stage_restore, commit_restore,
+    discard_restore, SnapshotManifest, SnapshotFile, SNAPSHOT_FORMAT_VERSION,
+};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct CollectionOpenOptions {
     pub config: crate::config::CollectionConfig,
 }
-
-impl Default for CollectionOpenOptions {
-    fn default() -> Self {
-        Self {
-            config: crate::config::CollectionConfig::default(),
-        }
-    }
-}
 // Implement conversion from CollectionConfig to CollectionOpenOptions for easier API usage. This allows users to directly pass a CollectionConfig when opening a collection, and it will be automatically converted into the appropriate open options. This simplifies the API and makes it more convenient for users who want to customize their collection configuration without needing to manually construct the open options.
 impl From<crate::config::CollectionConfig> for CollectionOpenOptions {
     fn from(config: crate::config::CollectionConfig) -> Self {
@@ -62,39 +142,114 @@ impl Collection {
         operations::get(self, id)
     }
 
+    // Documents of `ids` in order, read in one batch
+    pub fn get_many(&self, ids: &[Uuid]) -> Vec<Option<Document>> {
+        operations::get_many(self, ids)
+    }
+
     pub fn insert(&mut self, entry: Document) -> Result<Uuid> {
-        operations::insert(self, entry)
+        let result = operations::insert(self, entry);
+        self.finish_replication(result.is_ok());
+        result
     }
 
     pub fn insert_batch(&mut self, entries: Vec<Document>) -> Result<Vec<Uuid>> {
-        operations::insert_batch(self, entries)
+        let result = operations::insert_batch(self, entries);
+        self.finish_replication(result.is_ok());
+        result
     }
     
     pub fn upsert(&mut self, entry: Document) -> Result<Uuid> {
-        operations::upsert(self, entry)
+        let result = operations::upsert(self, entry);
+        self.finish_replication(result.is_ok());
+        result
+    }
+
+    // Upsert that skips documents whose stored vector, text and metadata already match; returns the id and whether it was written
+    pub fn upsert_if_changed(&mut self, entry: Document) -> Result<(Uuid, bool)> {
+        let result = operations::upsert_changed(self, entry, true);
+        self.finish_replication(result.is_ok());
+        result
+    }
+
+    // Partial batches: every entry's outcome in request order, see operations.rs
+    pub fn insert_batch_partial(&mut self, entries: Vec<Document>) -> Result<Vec<Result<Uuid>>> {
+        let result = operations::insert_batch_partial(self, entries);
+        self.finish_replication(result.is_ok());
+        result
+    }
+
+    pub fn upsert_batch_partial(&mut self, entries: Vec<Document>) -> Result<Vec<Result<Uuid>>> {
+        self.upsert_batch_partial_changed(entries, false)
+            .map(|outcomes| outcomes.into_iter().map(|outcome| outcome.map(|(id, _)| id)).collect())
+    }
+
+    // With `skip_unchanged`, entries matching the stored document are left alone; each outcome says whether it was written
+    pub fn upsert_batch_partial_changed(&mut self, entries: Vec<Document>, skip_unchanged: bool) -> Result<Vec<Result<(Uuid, bool)>>> {
+        let result = operations::upsert_batch_partial(self, entries, skip_unchanged);
+        self.finish_replication(result.is_ok());
+        result
     }
 
     pub fn delete(&mut self, id: &Uuid) -> Result<bool> {
-        operations::delete(self, id)
+        let result = operations::delete(self, id);
+        self.finish_replication(result.is_ok());
+        result
     }
 
     pub fn delete_batch(&mut self, ids: &[Uuid]) -> Result<usize> {
-        operations::delete_batch(self, ids)
+        let result = operations::delete_batch(self, ids);
+        self.finish_replication(result.is_ok());
+        result
     }
 
     
-    pub fn update_metadata(&mut self, id: &Uuid, metadata: Metadata) -> Result<bool> {
-        operations::update_metadata(self, id, metadata)
+    // Takes &self: metadata-only updates run under a shared collection lock (see data.rs)
+    pub fn update_metadata(&self, id: &Uuid, metadata: Metadata) -> Result<bool> {
+        operations::update_metadata(self, id, metadata, None).map(|version| version.is_some())
+    }
+
+    // update_metadata that fails with a conflict unless the document is at version `if_version`.
+    // Returns the version written, None when the document does not exist.
+    pub fn update_metadata_if(&self, id: &Uuid, metadata: Metadata, if_version: Option<u64>) -> Result<Option<u64>> {
+        operations::update_metadata(self, id, metadata, if_version)
+    }
+
+    // Optimistic-concurrency precondition: a conflict unless the document is at version `expected`
+    // (0 for a missing one). Writers check it under the write lock before writing.
+    pub fn ensure_version(&self, id: &Uuid, expected: Option<u64>) -> Result<()> {
+        operations::ensure_version(self, id, expected)
     }
     
     pub fn update_vector(&mut self, id: &Uuid, vector: Vec<f32>) -> Result<bool> {
-        operations::update_vector(self, id, vector)
+        let result = operations::update_vector(self, id, vector);
+        self.finish_replication(result.is_ok());
+        result
+    }
+
+    pub fn update_vectors(&mut self, updates: Vec<(Uuid, Vec<f32>)>) -> Result<usize> {
+        let result = operations::update_vectors(self, updates);
+        self.finish_replication(result.is_ok());
+        result
     }
 
     pub fn search(&self, query: &[f32], k: usize, metric: Metric, params: crate::search::SearchParams) -> Vec<Hit> {
         search::search(self, query, k, metric, params)
     }
 
+    // Like search, but a query rejected by the collection's vector validation is an error instead of an empty result
+    pub fn try_search(&self, query: &[f32], k: usize, metric: Metric, params: crate::search::SearchParams) -> Result<Vec<Hit>> {
+        crate::validation::check_query_dimensions(query, self.metadata().dimensions)?;
+        crate::validation::check_vector(query, &self.config().validation, metric)?;
+        Ok(search::search(self, query, k, metric, params))
+    }
+
+    // Search with ef/nprobe picked to fit `target_ms`, from the latencies of earlier such searches;
+    // also returns the parameters it ran with
+    pub fn search_within(&self, query: &[f32], k: usize, metric: Metric, params: crate::search::SearchParams, target_ms: f32) -> (Vec<Hit>, crate::search::EffectiveSearch) {
+        search::search_within(self, query, k, metric, params, target_ms)
+    }
+
     pub fn search_batch(&self, queries: &[Vec<f32>], k: usize, metric: Metric) -> Vec<Vec<Hit>> {
         search::search_batch(self, queries, k, metric)
     }
@@ -103,11 +258,206 @@ impl Collection {
         self.vectors_view()
     }
 
-    pub fn checkpoint(&mut self) -> Result<()> {
+    // Train a PCA/OPQ projection to `dims` dimensions on up to `sample_size` stored vectors and re-index through it
+    pub fn train_projection(&mut self, kind: ProjectionKind, dims: usize, sample_size: usize) -> Result<&Projection> {
+        pause::ensure_writable(self)?;
+        projection::train(self, kind, dims, sample_size)?;
+        Ok(self.projection().expect("just trained"))
+    }
+
+    // Remove the trained projection and re-index the full (transformed) vectors
+    pub fn clear_projection(&mut self) -> Result<bool> {
+        pause::ensure_writable(self)?;
+        projection::clear(self)
+    }
+
+    // Sweep ef/nprobe over sample queries and recommend the cheapest value reaching the target recall
+    pub fn tune(&self, opts: TuneOptions) -> Result<TuningReport> {
+        tuning::tune(self, opts)
+    }
+
+    // Index stats with, for HNSW, graph connectivity and recall@k of the search path on sampled stored vectors
+    pub fn index_diagnostics(&self, sample_size: usize, k: usize) -> Result<crate::index::IndexStats> {
+        tuning::diagnose(self, sample_size, k)
+    }
+
+    // Evenly spread stored vectors, to use as sample queries
+    pub fn sample_vectors(&self, count: usize) -> Vec<Vec<f32>> {
+        tuning::sample_queries(self, count)
+    }
+
+    // Each query's exact top k, scoring every stored document's full vector with the index's metric
+    pub fn exact_top_k(&self, queries: &[Vec<f32>], k: usize) -> Vec<Vec<Uuid>> {
+        tuning::exact_top_k(self, queries, k, self.vector_index().metric())
+    }
+
+    // Recall@k of searches with the current search settings against exact results from exact_top_k
+    pub fn recall(&self, queries: &[Vec<f32>], truth: &[Vec<Uuid>], k: usize) -> Option<f32> {
+        tuning::recall(self, queries, truth, k, self.vector_index().metric())
+    }
+
+    // Keep a tuning recommendation as the search default, or drop it with None. Returns whether one was set before.
+    pub fn set_tuning(&mut self, preset: Option<TuningPreset>) -> Result<bool> {
+        pause::ensure_not_paused(self)?;
+        tuning::set(self, preset)
+    }
+
+    // Replace the configured search settings (a kept tuning recommendation still applies on top)
+    pub fn set_search_defaults(&mut self, search: crate::config::SearchConfig) {
+        tuning::set_base(self, search)
+    }
+
+    // Run `stage` on every document inserted or upserted from now on, after the configured stages
+    pub fn add_enrichment_stage(&mut self, stage: std::sync::Arc<dyn crate::storage::EnrichmentStage>) {
+        self.enrichment_stages.push(stage);
+    }
+
+    // Cross-check pointers against the data file and the vector index; changes nothing
+    pub fn verify(&self) -> Result<VerifyReport> {
+        verify::verify(self)
+    }
+
+    // Apply what changed between the settings the collection opened with (`old`) and `new` where it
+    // is safe on a live collection; the report lists what waits for a reopen
+    pub fn reconfigure(&mut self, old: &crate::config::CollectionConfig, new: &crate::config::CollectionConfig) -> ConfigChanges {
+        reconfigure::reconfigure(self, old, new)
+    }
+
+    // Set when the vector index file could not be read at open, until the index has been rebuilt
+    pub fn index_recovery(&self) -> Option<&IndexRecovery> {
+        self.index_recovery.as_ref()
+    }
+
+    // Verify, then drop unreadable pointers and orphan index nodes and index what is missing
+    pub fn repair(&mut self) -> Result<VerifyReport> {
+        pause::ensure_writable(self)?;
+        verify::repair(self)
+    }
+
+    // Make the collection read-only for good: compact it, drop its WAL and write its index for reading
+    // only, optionally rebuilt as `index` first. Sealing a sealed collection just reports on it.
+    pub fn seal(&mut self, index: Option<crate::index::IndexConfig>) -> Result<SealReport> {
+        worm::seal(self, index)
+    }
+
+    // Encode new writes with `level` from now on, kept across restarts; returns the level it replaces.
+    // Stored documents keep their encoding until `requantize` rewrites them.
+    pub fn set_quantization(&mut self, level: crate::config::QuantizationLevel) -> Result<crate::config::QuantizationLevel> {
+        requantize::set_quantization(self, level)
+    }
+
+    // Re-encode the documents of `ids` stored in another encoding than the collection's; returns how many
+    pub fn requantize(&mut self, ids: &[Uuid]) -> Result<usize> {
+        requantize::requantize(self, ids)
+    }
+
+    // Documents per stored encoding, and how many are not in the collection's own yet
+    pub fn quantization_composition(&self) -> QuantizationComposition {
+        requantize::composition(self)
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        self.metadata.sealed_at.is_some()
+    }
+
+    // Finish the writes in flight, checkpoint, and reject writes for `timeout_secs` or until resumed
+    pub fn pause_writes(&mut self, timeout_secs: u64, reason: Option<String>) -> Result<WritePause> {
+        pause::pause(self, timeout_secs, reason)
+    }
+
+    // Accept writes again; returns the pause that was in force
+    pub fn resume_writes(&mut self) -> Option<WritePause> {
+        pause::resume(self)
+    }
+
+    // Drop a pause past its deadline (writes were already accepted again); returns it when there was one
+    pub fn expire_write_pause(&mut self) -> Option<WritePause> {
+        pause::expire(self)
+    }
+
+    // The pause in force, if any
+    pub fn write_pause(&self) -> Option<&WritePause> {
+        pause::active(self)
+    }
+
+    // Err while writes are paused, for what replaces or removes the collection's files from outside
+    pub fn ensure_not_paused(&self) -> Result<()> {
+        pause::ensure_not_paused(self)
+    }
+
+    // Write the documents matching `filter` as Parquet or an Arrow IPC file
+    pub fn export<W: std::io::Write + Send>(&self, writer: W, format: ExportFormat, filter: Option<&crate::search::query::Filter>) -> Result<ExportReport> {
+        export::export(self, writer, format, filter)
+    }
+
+    // Centroid, per-dimension mean/variance, norm histogram and intrinsic dimensionality of the stored vectors
+    pub fn vector_stats(&self, opts: &StatsOptions) -> Result<VectorStats> {
+        stats::vector_stats(self, opts)
+    }
+
+    // Norms and metadata cardinality from the reservoir sample, without reading the documents
+    pub fn sampled_stats(&self) -> SampledStats {
+        let count = self.count();
+        self.sampler.lock().stats(count, self.metadata.dimensions)
+    }
+
+    // Documents per stored vector length, against the collection's recorded dimensions
+    pub fn dimension_report(&self) -> DimensionReport {
+        stats::dimension_report(self)
+    }
+
+    // How isolated each vector is (k-th neighbour or centroid similarity), as a percentile of the stored documents
+    pub fn score_outliers(&self, queries: &[OutlierQuery], opts: &OutlierOptions) -> Result<OutlierReport> {
+        outlier::score_outliers(self, queries, opts)
+    }
+
+    // Both take &self so a server can run them under a shared collection lock while searches continue
+    pub fn checkpoint(&self) -> Result<()> {
+        let _writer = self.shared_writes.lock();
         persistence::checkpoint(self)
     }
 
-    pub fn flush(&mut self) -> Result<()> {
+    // The locked half of a checkpoint; the caller releases the collection, then writes it out.
+    // None for an ephemeral collection.
+    pub fn begin_checkpoint(&self) -> Result<Option<PendingCheckpoint>> {
+        let _writer = self.shared_writes.lock();
+        PendingCheckpoint::capture(self)
+    }
+
+    // Capture a checkpoint and write it from a background thread
+    pub fn checkpoint_in_background(&self) -> Result<()> {
+        let _writer = self.shared_writes.lock();
+        persistence::checkpoint_in_background(self)?;
+        self.persistence.lock().reset_counter();
+        Ok(())
+    }
+
+    // Document reads that found an entry not matching its checksum since the collection was opened
+    pub fn checksum_failures(&self) -> u64 {
+        self.data.read_recursive().checksum_failures()
+    }
+
+    // Byte ranges of search candidates hinted to the kernel before they were read (see `memory.prefetch`)
+    pub fn prefetched_ranges(&self) -> u64 {
+        self.data.read_recursive().prefetched_ranges()
+    }
+
+    // WAL bytes and entries a checkpoint has not caught up with yet
+    pub fn wal_backlog(&self) -> WalBacklog {
+        backpressure::backlog(self)
+    }
+
+    // Whether the WAL backlog holds the next write back; starts a checkpoint when it does and none is running
+    pub fn write_pressure(&self) -> Result<WritePressure> {
+        backpressure::pressure(self)
+    }
+
+    // Block until a checkpoint being written out in the background is on disk
+    pub fn wait_for_checkpoint(&self) {
+        persistence::wait_for_checkpoint(self)
+    }
+
+    pub fn flush(&self) -> Result<()> {
         persistence::flush(self)
     }
 }
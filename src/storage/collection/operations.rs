@@ -5,24 +5,29 @@ use uuid::Uuid;
 use crate::error::{Result, ServerError};
 use crate::storage::document::Document;
 use crate::storage::wal::WalEntry;
-use crate::storage::persistence::{EntryPointer, grow_mmap_if_needed};
+use crate::storage::persistence::EntryPointer;
 use crate::quantization::QuantizedVector;
 use crate::metadata::Metadata;
+use super::data::DataStore;
 use super::storage::Collection;
 use tracing::debug;
 
 // Enforce collection limits for a single entry. This function checks the size of the entry being inserted against the configured limits for the collection, such as maximum number of vectors, maximum total bytes, and maximum bytes per vector. If any of the limits are exceeded, it returns an error to prevent inserting data that would violate the collection's constraints. This is important for maintaining the integrity of the collection and ensuring that it operates within defined resource limits, especially when inserting large entries that could potentially consume excessive resources.
 fn enforce_limits_single(storage: &Collection, entry_bytes: usize) -> Result<()> {
-    let limits = storage.config.limits;
-
-    if let Some(max_vecs) = limits.max_vectors {
+    if let Some(max_vecs) = storage.config.limits.max_vectors {
         if storage.count() >= max_vecs {
             return Err(ServerError::InvalidRequest("Collection max vectors reached".into()).into());
         }
     }
+    enforce_size_limits(storage, entry_bytes)
+}
+
+// The byte limits alone, for rewrites of an existing document that do not change the vector count
+fn enforce_size_limits(storage: &Collection, entry_bytes: usize) -> Result<()> {
+    let limits = storage.config.limits;
 
     if let Some(max_bytes) = limits.max_bytes {
-        let current_size = storage.data_file.metadata()?.len();
+        let current_size = storage.data.read_recursive().file_len()?;
         let required = current_size.saturating_add(entry_bytes as u64);
         if required > max_bytes {
             return Err(ServerError::InvalidRequest("Collection max size reached".into()).into());
@@ -50,7 +55,7 @@ fn enforce_limits_batch(storage: &Collection, total_entries: usize, total_bytes:
     }
 
     if let Some(max_bytes) = limits.max_bytes {
-        let current_size = storage.data_file.metadata()?.len();
+        let current_size = storage.data.read_recursive().file_len()?;
         let required = current_size.saturating_add(total_bytes);
         if required > max_bytes {
             return Err(ServerError::InvalidRequest("Collection max size reached".into()).into());
@@ -70,24 +75,33 @@ fn enforce_limits_batch(storage: &Collection, total_entries: usize, total_bytes:
     Ok(())
 }
 
-pub fn get(storage: &Collection, id: &Uuid) -> Option<Document> {
-    let index_entry = storage.index.get(id)?;
-    let offset = index_entry.offset as usize;
-    let length = index_entry.length as usize;
-    if let Some(mmap) = storage.mmap.as_ref() {
-        let bytes = &mmap[offset..offset + length];
-        bincode::deserialize(bytes).ok()
-    } else {
-        use std::io::{Read, Seek, SeekFrom};
-        let mut file = storage.data_file.try_clone().ok()?;
-        let mut buf = vec![0u8; length];
-        file.seek(SeekFrom::Start(index_entry.offset)).ok()?;
-        file.read_exact(&mut buf).ok()?;
-        bincode::deserialize(&buf).ok()
+// Client-provided ids are unique per collection. An id is available when nobody has it yet or when it already belongs to `owner` (re-writing the same document).
+fn ensure_external_id_available(storage: &Collection, external_id: &str, owner: &Uuid) -> Result<()> {
+    match storage.data.read_recursive().external_ids.get(external_id) {
+        Some(existing) if existing != owner => Err(ServerError::AlreadyExists(format!(
+            "A document with id '{}' already exists",
+            external_id
+        )).into()),
+        _ => Ok(()),
     }
 }
 
-pub fn insert_internal(storage: &mut Collection, mut entry: Document) -> Result<Uuid> {
+pub fn get(storage: &Collection, id: &Uuid) -> Option<Document> {
+    storage.data.read_recursive().get(id)
+}
+
+pub fn get_many(storage: &Collection, ids: &[Uuid]) -> Vec<Option<Document>> {
+    storage.data.read_recursive().get_many(ids)
+}
+
+pub fn insert_internal(storage: &mut Collection, entry: Document) -> Result<Uuid> {
+    let (id, index_vec) = append_document(storage, entry)?;
+    storage.vector_index.insert(id, &index_vec, &storage.vector_cache);
+    Ok(id)
+}
+
+// Write a document to the data file and the id/vector/metadata caches without touching the vector index. Used by insert_internal and by imports that bring their own pre-built index. Returns the vector as the index sees it (after the collection's transform, e.g. truncation, and its trained projection).
+pub(super) fn append_document(storage: &mut Collection, mut entry: Document) -> Result<(Uuid, Vec<f32>)> {
     // 1. Serialize the document entry into bytes using bincode. This will allow us to write the document data to the memory-mapped file in a compact binary format. The serialized bytes will include all the necessary information about the document, such as its ID, vector, text, and metadata.
     let id = entry.id;
     let raw_vec = entry.get_vector();
@@ -98,24 +112,16 @@ pub fn insert_internal(storage: &mut Collection, mut entry: Document) -> Result<
     enforce_limits_single(storage, bytes.len())?;
 
     // 2. Calculate the offset for where to write the new document in the memory-mapped file. We find the maximum offset of existing entries in the index and add the length of those entries to determine where the new entry should be written. This ensures that we append new entries to the end of the file without overwriting existing data.
-    let offset = storage.index.values()
-        .map(|idx| idx.offset + idx.length as u64)
-        .max()
-        .unwrap_or(0);
+    let data = storage.data.get_mut();
+    let offset = data.end_offset();
 
     // 3. Check if we need to grow the memory-mapped file to accommodate the new entry. If the required size (offset + length of new entry) exceeds the current size of the memory-mapped file, we need to grow it. This involves unmapping the current memory map, resizing the underlying file, and creating a new memory map with the updated size. By growing the memory-mapped file as needed, we can ensure that we have enough space to write new entries without running into out-of-bounds errors.
-    let required_size = offset + bytes.len() as u64;
-    grow_mmap_if_needed(&mut storage.mmap, &storage.data_file, required_size)?;
-    
-
     // 4. Write the serialized bytes of the document to the memory-mapped file at the calculated offset. We use the memory map to directly write the bytes to the file, which allows for efficient I/O operations. After writing the bytes, we create an index entry that records the offset and length of the new document in the file, and we insert this entry into the main index of the collection. This will allow us to quickly locate and retrieve the document in future get operations.
-    let mmap = storage.mmap.as_mut().unwrap();
-    mmap[offset as usize..(offset as usize + bytes.len())]
-        .copy_from_slice(&bytes);
+    data.write_at(offset, &bytes)?;
     
     // 5. Update the vector index and cache with the new document's vector. We extract the vector from the document, update the metadata with the dimensions of the vector, and then insert the vector into the in-memory cache and the vector index. This ensures that the new document is included in future search operations and that its vector is readily available for similarity calculations.
-    let index_entry = EntryPointer::new(offset, bytes.len() as u32);
-    storage.index.insert(id, index_entry.clone());
+    let index_entry = EntryPointer::new(offset, &bytes);
+    data.index.insert(id, index_entry.clone());
     
     // Update the collection metadata with the dimensions of the new vector. This is important for ensuring that all vectors in the collection have consistent dimensions, which is a requirement for similarity search. If the collection already has a defined dimension, we validate that the new vector matches that dimension. If the collection does not have a defined dimension yet, we set it based on the first inserted vector.
     storage.metadata.set_dimensions(raw_vec.len());
@@ -126,27 +132,195 @@ pub fn insert_internal(storage: &mut Collection, mut entry: Document) -> Result<
     }
     
     // Insert the new vector into the in-memory cache and the vector index. This allows for fast access to the vector during search operations without needing to read from the memory-mapped file. By keeping the vector cache and index updated with new entries, we can ensure that search operations remain efficient and that the collection is ready to handle queries immediately after insertion.
-    storage.vector_cache.insert(id, raw_vec.clone());
-    storage.vector_index.insert(id, &raw_vec, &storage.vector_cache);
+    let index_vec = storage.index_vector(&raw_vec).into_owned();
+    if let Some(column) = storage.column.as_mut() {
+        column.put(id, &index_vec)?;
+    }
+    storage.vector_cache.insert(id, index_vec.clone());
+    let data = storage.data.get_mut();
+    data.set_metadata(id, entry.metadata.clone());
+    if let Some(external_id) = entry.external_id() {
+        data.external_ids.insert(external_id.to_string(), id);
+    }
+    let count = data.len();
+    // Documents re-written without their exact vector (metadata updates, compaction) keep the record already in the store
+    if let Some(two_stage) = storage.two_stage.as_mut() {
+        two_stage.codes.insert(id, entry.vector.clone());
+        if let Some(exact) = entry.full_precision.as_deref() {
+            two_stage.full.append(id, exact)?;
+        }
+    }
     
-    storage.metadata.update_vector_count(storage.index.len());
+    storage.sampler.get_mut().insert(id, &raw_vec, &entry.metadata, count);
+    storage.metadata.update_vector_count(count);
     debug!(collection=%storage.path, id=%id, offset=index_entry.offset, len=bytes.len(), "inserted_document");
     
-    Ok(id)
+    Ok((id, index_vec))
+}
+
+// Rewrite an existing document without a delete + reinsert: the new version is appended to the data file and the id's pointer swapped to it (the old bytes are reclaimed by compaction). The vector index and vector caches are only touched when `vector_changed`, so metadata and text edits leave the HNSW graph alone.
+pub(super) fn update_internal(storage: &mut Collection, mut entry: Document, vector_changed: bool) -> Result<()> {
+    let id = entry.id;
+    let previous_external_id = get(storage, &id).and_then(|doc| doc.external_id().map(str::to_string));
+    let raw_vec = entry.get_vector();
+    if vector_changed {
+        if let Some(expected_dim) = storage.metadata.dimensions {
+            crate::validation::validate_dimensions(&raw_vec, expected_dim)?;
+        }
+    }
+    entry.vector = QuantizedVector::from_f32_with_config(&raw_vec, &storage.config.quantization);
+    let bytes = bincode::serialize(&entry)?;
+    enforce_size_limits(storage, bytes.len())?;
+
+    storage.sampler.get_mut().refresh(id, &raw_vec, &entry.metadata);
+    let data = storage.data.get_mut();
+    let (offset, superseded) = write_version(data, id, &bytes, previous_external_id, entry.external_id().map(str::to_string), entry.metadata)?;
+    let count = data.len();
+    storage.metadata.counters.record_dead_bytes(superseded);
+
+    if vector_changed {
+        let index_vec = storage.index_vector(&raw_vec).into_owned();
+        if let Some(column) = storage.column.as_mut() {
+            column.put(id, &index_vec)?;
+        }
+        storage.vector_cache.insert(id, index_vec.clone());
+        storage.vector_index.remove(&id);
+        storage.vector_index.insert(id, &index_vec, &storage.vector_cache);
+        if let Some(two_stage) = storage.two_stage.as_mut() {
+            two_stage.codes.insert(id, entry.vector);
+            if let Some(exact) = entry.full_precision.as_deref() {
+                two_stage.full.append(id, exact)?;
+            }
+        }
+    }
+
+    storage.metadata.update_vector_count(count);
+    debug!(collection=%storage.path, id=%id, offset, len=bytes.len(), vector_changed, "updated_document");
+    Ok(())
+}
+
+// Rewrite a document's stored vector in the collection's current quantization, encoded from
+// `source`, leaving its version, the vector index and the vector caches alone (see requantize.rs)
+pub(super) fn reencode_internal(storage: &mut Collection, mut entry: Document, source: &[f32]) -> Result<()> {
+    let id = entry.id;
+    let external_id = entry.external_id().map(str::to_string);
+    entry.vector = QuantizedVector::from_f32_with_config(source, &storage.config.quantization);
+    let bytes = bincode::serialize(&entry)?;
+    enforce_size_limits(storage, bytes.len())?;
+
+    let data = storage.data.get_mut();
+    let (offset, superseded) = write_version(data, id, &bytes, external_id.clone(), external_id, entry.metadata)?;
+    storage.metadata.counters.record_dead_bytes(superseded);
+    if let Some(two_stage) = storage.two_stage.as_mut() {
+        two_stage.codes.insert(id, entry.vector);
+    }
+    debug!(collection=%storage.path, id=%id, offset, len=bytes.len(), "reencoded_document");
+    Ok(())
+}
+
+// Append a new version of a document and point its id at it. The client id moves with the metadata. Returns the offset written at and the length of the version it replaced.
+fn write_version(
+    data: &mut DataStore,
+    id: Uuid,
+    bytes: &[u8],
+    previous_external_id: Option<String>,
+    external_id: Option<String>,
+    metadata: Metadata,
+) -> Result<(u64, u64)> {
+    let offset = data.end_offset();
+    data.write_at(offset, bytes)?;
+    let superseded = data.index.insert(id, EntryPointer::new(offset, bytes)).map_or(0, |p| p.length as u64);
+
+    if let Some(previous) = previous_external_id.filter(|p| external_id.as_ref() != Some(p)) {
+        if data.external_ids.get(&previous) == Some(&id) {
+            data.external_ids.remove(&previous);
+        }
+    }
+    if let Some(external_id) = external_id {
+        data.external_ids.insert(external_id, id);
+    }
+    data.set_metadata(id, metadata);
+    Ok((offset, superseded))
 }
 
 pub fn delete_internal(storage: &mut Collection, id: &Uuid) {
-    storage.index.remove(id);
+    // Drop the client id mapping while the document is still readable. The check on the target keeps a mapping that was already re-pointed to another document.
+    let data = storage.data.get_mut();
+    if let Some(doc) = data.get(id) {
+        if let Some(external_id) = doc.external_id() {
+            if data.external_ids.get(external_id) == Some(id) {
+                data.external_ids.remove(external_id);
+            }
+        }
+    }
+    if let Some(pointer) = data.index.remove(id) {
+        storage.metadata.counters.record_dead_bytes(pointer.length as u64);
+    }
     storage.vector_index.remove(id);
+    if let Some(two_stage) = storage.two_stage.as_mut() {
+        two_stage.codes.remove(id);
+    }
+    if let Some(column) = storage.column.as_mut() {
+        column.remove(id);
+    }
+    let data = storage.data.get_mut();
     if storage.vector_index.index_type() != crate::index::IndexType::Hnsw {
         storage.vector_cache.remove(id);
-        storage.metadata_cache.remove(id);
+        data.remove_metadata(id);
+    } else {
+        data.unindex_metadata(id);
+    }
+    let count = data.len();
+    storage.metadata.update_vector_count(count);
+    storage.sampler.get_mut().remove(id);
+    if storage.sampler.get_mut().depleted(count) {
+        super::sampler::reseed(storage);
+    }
+}
+
+// Log to the WAL and stage the entry for the collection's read replicas, if it has any
+fn log_wal(storage: &mut Collection, entry: &mut WalEntry) -> Result<()> {
+    storage.persistence.get_mut().wal.log(entry)?;
+    if let Some(replication) = storage.replication.get_mut().as_mut() {
+        replication.stage(entry);
+    }
+    Ok(())
+}
+
+// Log a batch as one append, so a failed write leaves none of it in the WAL
+fn log_wal_batch(storage: &mut Collection, entries: &mut [WalEntry]) -> Result<()> {
+    storage.persistence.get_mut().wal.log_batch(entries)?;
+    if let Some(replication) = storage.replication.get_mut().as_mut() {
+        for entry in entries.iter() {
+            replication.stage(entry);
+        }
+    }
+    Ok(())
+}
+
+// Run the collection's vector validation and enrichment stages on a document before anything about it is logged. A sanitized vector replaces the document's own, so the WAL, the data file and the index all see the same values.
+fn check_document(storage: &Collection, entry: &mut Document) -> Result<()> {
+    let vector = entry.exact_vector();
+    // A vector of the wrong size is turned away here too, before it reaches the WAL or the data file
+    if let Some(expected) = storage.metadata.dimensions {
+        crate::validation::validate_dimensions(&vector, expected)?;
+    }
+    let checked = crate::validation::check_vector(&vector, &storage.config.validation, storage.config.index.metric())?;
+    if let std::borrow::Cow::Owned(sanitized) = checked {
+        entry.vector = QuantizedVector::from_f32(&sanitized);
+        entry.full_precision = Some(sanitized);
     }
-    storage.metadata.update_vector_count(storage.index.len());
+    crate::storage::enrichment::enrich(&storage.config.enrichment.stages, &storage.enrichment_stages, entry)
 }
 
-pub fn insert(storage: &mut Collection, entry: Document) -> Result<Uuid> {
-    let vector = entry.get_vector();
+pub fn insert(storage: &mut Collection, mut entry: Document) -> Result<Uuid> {
+    super::pause::ensure_writable(storage)?;
+    entry.stamp(None, now_secs());
+    check_document(storage, &mut entry)?;
+    if let Some(external_id) = entry.external_id() {
+        ensure_external_id_available(storage, external_id, &entry.id)?;
+    }
+    let vector = entry.exact_vector();
     let mut wal_entry = WalEntry::Insert { 
         id: entry.id, 
         vector,
@@ -154,45 +328,64 @@ pub fn insert(storage: &mut Collection, entry: Document) -> Result<Uuid> {
         metadata: entry.metadata.clone(),
         seq: 0,
     };
-    storage.persistence.wal.log(&mut wal_entry)?;
+    log_wal(storage, &mut wal_entry)?;
     
     super::persistence::save_index(storage)?;
-    storage.track_operation()?;
 
-    insert_internal(storage, entry)
+    // A checkpoint captures the index as it is, so it is only taken once the document is in it
+    let id = insert_internal(storage, entry)?;
+    storage.metadata.counters.record_inserts(1);
+    storage.track_operation()?;
+    Ok(id)
 }
 
 pub fn insert_batch(storage: &mut Collection, mut entries: Vec<Document>) -> Result<Vec<Uuid>> {
+    super::pause::ensure_writable(storage)?;
     // Log all the entries to the WAL before inserting them into the collection. This ensures that we have a record of all the operations in the WAL for durability and recovery purposes. By logging the entries first, we can guarantee that even if there is a failure during the insertion process, we can recover the intended state of the collection by replaying the WAL entries.
     let mut ids = Vec::with_capacity(entries.len());
-    
-    //  Iterate through each entry and log it to the WAL. For each entry, we create a corresponding WAL entry with the necessary information (ID, vector, text, metadata) and log it using the WAL instance. This allows us to maintain a complete history of all insert operations, which is crucial for ensuring durability and enabling recovery in case of crashes or unexpected shutdowns.
+
+    // One bad vector fails the whole batch before any of it is logged
+    let now = now_secs();
+    for entry in &mut entries {
+        entry.stamp(None, now);
+        check_document(storage, entry)?;
+    }
+
+    // Reject client id collisions, against the collection and inside the batch, before anything is logged
+    let mut batch_external_ids = std::collections::HashSet::new();
     for entry in &entries {
-        let vector = entry.get_vector();
-        let mut wal_entry = WalEntry::Insert {
-            id: entry.id,
-            vector,
-            text: entry.text.clone(),
-            metadata: entry.metadata.clone(),
-            seq: 0,
-        };
-        storage.persistence.wal.log(&mut wal_entry)?;
+        if let Some(external_id) = entry.external_id() {
+            ensure_external_id_available(storage, external_id, &entry.id)?;
+            if !batch_external_ids.insert(external_id) {
+                return Err(ServerError::InvalidRequest(format!(
+                    "Duplicate id '{}' in batch",
+                    external_id
+                )).into());
+            }
+        }
     }
+    
+    //  Iterate through each entry and log it to the WAL. For each entry, we create a corresponding WAL entry with the necessary information (ID, vector, text, metadata) and log it using the WAL instance. This allows us to maintain a complete history of all insert operations, which is crucial for ensuring durability and enabling recovery in case of crashes or unexpected shutdowns.
+    let mut wal_entries: Vec<WalEntry> = entries.iter().map(|entry| WalEntry::Insert {
+        id: entry.id,
+        vector: entry.exact_vector(),
+        text: entry.text.clone(),
+        metadata: entry.metadata.clone(),
+        seq: 0,
+    }).collect();
+    log_wal_batch(storage, &mut wal_entries)?;
     // After logging all entries to the WAL, we proceed to insert them into the collection. This involves serializing each entry, writing it to the memory-mapped file, updating the index and vector index, and updating the in-memory caches. By separating the logging and insertion steps, we can ensure that we have a clear record of all operations in the WAL while also maintaining the integrity and consistency of the collection's data structures.
     let mut serialized: Vec<(Uuid, Vec<u8>)> = Vec::with_capacity(entries.len());
-    let mut raw_vectors: Vec<(Uuid, Vec<f32>)> = Vec::with_capacity(entries.len());
+    let mut raw_vectors: Vec<(Uuid, Vec<f32>, Metadata)> = Vec::with_capacity(entries.len());
     for entry in &mut entries {
         let raw_vec = entry.get_vector();
         entry.vector = QuantizedVector::from_f32_with_config(&raw_vec, &storage.config.quantization);
         let bytes = bincode::serialize(entry)?;
         serialized.push((entry.id, bytes));
-        raw_vectors.push((entry.id, raw_vec));
+        raw_vectors.push((entry.id, raw_vec, entry.metadata.clone()));
     }
     // Calculate the total size required to write all new entries and grow the memory-mapped file if necessary. We sum the lengths of all serialized entries and add that to the current offset to determine the required size of the memory-mapped file. If the required size exceeds the current size of the memory-mapped file, we call the grow_mmap_if_needed function to resize the underlying file and create a new memory map with the updated size. This ensures that we have enough space to write all new entries without running into out-of-bounds errors.
-    let current_offset = storage.index.values()
-        .map(|idx| idx.offset + idx.length as u64)
-        .max()
-        .unwrap_or(0);
+    let current_offset = storage.data.get_mut().end_offset();
     
     //  Calculate the total size required to write all new entries and grow the memory-mapped file if necessary. We sum the lengths of all serialized entries and add that to the current offset to determine the required size of the memory-mapped file. If the required size exceeds the current size of the memory-mapped file, we call the grow_mmap_if_needed function to resize the underlying file and create a new memory map with the updated size. This ensures that we have enough space to write all new entries without running into out-of-bounds errors.
     let total_bytes: u64 = serialized.iter().map(|(_, b)| b.len() as u64).sum();
@@ -201,54 +394,180 @@ pub fn insert_batch(storage: &mut Collection, mut entries: Vec<Document>) -> Res
     let required_size = current_offset + total_bytes;
     
     // Grow the memory-mapped file if needed to accommodate all new entries. This involves unmapping the current memory map, resizing the underlying file, and creating a new memory map with the updated size. By ensuring that the memory-mapped file is large enough to hold all new entries, we can safely write the serialized data without risking out-of-bounds errors or data corruption.
-    grow_mmap_if_needed(&mut storage.mmap, &storage.data_file, required_size)?;
+    let data = storage.data.get_mut();
+    data.reserve(required_size)?;
     
     let mut offset = current_offset;
-    let mmap = storage.mmap.as_mut().unwrap();
 
     // Write each serialized entry to the memory-mapped file at the calculated offset. For each entry, we copy the bytes to the appropriate location in the memory map, create an index entry that records the offset and length of the entry, and insert this entry into the main index of the collection. We also keep track of the IDs of the inserted entries in a vector, which will be returned at the end of the function.
     for (id, bytes) in &serialized {
-        mmap[offset as usize..(offset as usize + bytes.len())]
-            .copy_from_slice(bytes);
+        data.write_at(offset, bytes)?;
         
-        let index_entry = EntryPointer {
-            offset,
-            length: bytes.len() as u32,
-        };
-        storage.index.insert(*id, index_entry);
+        let index_entry = EntryPointer::new(offset, bytes);
+        data.index.insert(*id, index_entry);
         ids.push(*id);
         
         offset += bytes.len() as u64;
     }
     // After writing all entries to the memory-mapped file and updating the index, we need to update the vector index and cache with the new entries. We iterate through each entry, extract the vector, and insert it into the in-memory cache and the vector index. This ensures that all new entries are included in future search operations and that their vectors are readily available for similarity calculations.
     super::persistence::save_index(storage)?;
-    storage.track_operation()?;
     // Update the collection metadata with the new vector count. After inserting the new entries, we need to update the metadata to reflect the new total number of vectors in the collection. This is important for maintaining accurate metadata information, which can be used for various purposes such as validating operations, providing insights about the collection, and ensuring that the collection's state is consistent with its contents.
-    for (id, vec_f32) in raw_vectors {
+    let mut live = storage.data.get_mut().len() - raw_vectors.len();
+    for (id, vec_f32, metadata) in raw_vectors {
         storage.metadata.set_dimensions(vec_f32.len());
         if let Some(expected_dim) = storage.metadata.dimensions {
             crate::validation::validate_dimensions(&vec_f32, expected_dim)?;
         }
-        storage.vector_cache.insert(id, vec_f32.clone());
-        storage.vector_index.insert(id, &vec_f32, &storage.vector_cache);
+        live += 1;
+        storage.sampler.get_mut().insert(id, &vec_f32, &metadata, live);
+        let data = storage.data.get_mut();
+        if let Some(external_id) = crate::storage::document::external_id_of(&metadata) {
+            data.external_ids.insert(external_id.to_string(), id);
+        }
+        data.set_metadata(id, metadata);
+        let index_vec = storage.index_vector(&vec_f32).into_owned();
+        if let Some(column) = storage.column.as_mut() {
+            column.put(id, &index_vec)?;
+        }
+        storage.vector_cache.insert(id, index_vec.clone());
+        storage.vector_index.insert(id, &index_vec, &storage.vector_cache);
+    }
+    if let Some(two_stage) = storage.two_stage.as_mut() {
+        two_stage.full.append_many(
+            entries.iter().filter_map(|e| e.full_precision.as_deref().map(|v| (e.id, v))),
+        )?;
+        for entry in &entries {
+            two_stage.codes.insert(entry.id, entry.vector.clone());
+        }
     }
-    storage.metadata.update_vector_count(storage.index.len());
+    storage.metadata.update_vector_count(storage.data.get_mut().len());
+    storage.metadata.counters.record_inserts(ids.len());
+    storage.track_operation()?;
     
     Ok(ids)
 }
 
-pub fn upsert(storage: &mut Collection, mut entry: Document) -> Result<Uuid> {
+// Checks that fail one entry of a partial batch on its own, made before anything is logged: the
+// collection's vector validation, and the dimensions the batch has fixed while the collection has none
+fn check_batch_entry(storage: &Collection, entry: &mut Document, dimensions: Option<usize>) -> Result<()> {
+    check_document(storage, entry)?;
+    if let Some(expected) = dimensions {
+        crate::validation::validate_dimensions(&entry.exact_vector(), expected)?;
+    }
+    Ok(())
+}
+
+// Insert the entries that pass validation and report each entry's outcome in order. A bad vector,
+// a dimension mismatch or a client id collision only fails its own entry; the rest go in as one batch.
+pub fn insert_batch_partial(storage: &mut Collection, entries: Vec<Document>) -> Result<Vec<Result<Uuid>>> {
+    super::pause::ensure_writable(storage)?;
+    let mut dimensions = storage.metadata.dimensions;
+    let mut batch_external_ids = std::collections::HashSet::new();
+    let mut outcomes = Vec::with_capacity(entries.len());
+    let mut accepted = Vec::with_capacity(entries.len());
+    for mut entry in entries {
+        let checked = check_batch_entry(storage, &mut entry, dimensions).and_then(|()| match entry.external_id() {
+            Some(external_id) => {
+                ensure_external_id_available(storage, external_id, &entry.id)?;
+                if batch_external_ids.contains(external_id) {
+                    return Err(ServerError::InvalidRequest(format!("Duplicate id '{}' in batch", external_id)).into());
+                }
+                batch_external_ids.insert(external_id.to_string());
+                Ok(())
+            }
+            None => Ok(()),
+        });
+        match checked {
+            Ok(()) => {
+                dimensions.get_or_insert(entry.exact_vector().len());
+                outcomes.push(Ok(entry.id));
+                accepted.push(entry);
+            }
+            Err(e) => outcomes.push(Err(e)),
+        }
+    }
+    if !accepted.is_empty() {
+        insert_batch(storage, accepted)?;
+    }
+    Ok(outcomes)
+}
+
+// Upsert each entry and report its outcome in order. Request errors (validation, dimensions, a
+// client id owned by another document) fail their own entry before it is logged; anything else,
+// such as an I/O error, stops the batch.
+pub fn upsert_batch_partial(storage: &mut Collection, entries: Vec<Document>, skip_unchanged: bool) -> Result<Vec<Result<(Uuid, bool)>>> {
+    super::pause::ensure_writable(storage)?;
+    let mut dimensions = storage.metadata.dimensions;
+    let mut outcomes = Vec::with_capacity(entries.len());
+    for mut entry in entries {
+        if let Err(e) = check_batch_entry(storage, &mut entry, dimensions) {
+            outcomes.push(Err(e));
+            continue;
+        }
+        let length = entry.exact_vector().len();
+        match upsert_changed(storage, entry, skip_unchanged) {
+            Ok(outcome) => {
+                dimensions.get_or_insert(length);
+                outcomes.push(Ok(outcome));
+            }
+            Err(e @ crate::error::PiramidError::Server(_)) => outcomes.push(Err(e)),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(outcomes)
+}
+
+pub fn upsert(storage: &mut Collection, entry: Document) -> Result<Uuid> {
+    upsert_changed(storage, entry, false).map(|(id, _)| id)
+}
+
+// Upsert that returns whether anything was written. With `skip_unchanged`, a document whose stored
+// vector, text and metadata (apart from its version and timestamps) already match is left alone:
+// nothing is logged to the WAL and neither the data file nor the index is touched.
+pub fn upsert_changed(storage: &mut Collection, mut entry: Document, skip_unchanged: bool) -> Result<(Uuid, bool)> {
+    super::pause::ensure_writable(storage)?;
     // For an upsert operation, if the document already exists, we treat it as an update. This involves deleting the existing entry and then inserting the new entry with the updated information. By doing this, we ensure that the index and vector index are properly updated to reflect the changes in the document, and that the WAL accurately captures the update operation for durability and recovery purposes.
 
+    // A client id that already belongs to another document makes this an upsert of that document, unless the caller also named a different existing document.
+    let owner = entry.external_id().and_then(|ext| storage.data.get_mut().external_ids.get(ext).copied());
+    if let Some(owner) = owner {
+        if owner != entry.id {
+            if storage.data.get_mut().index.contains_key(&entry.id) {
+                return Err(ServerError::AlreadyExists(format!(
+                    "id '{}' already belongs to document {}",
+                    entry.external_id().unwrap_or_default(),
+                    owner
+                )).into());
+            }
+            entry.id = owner;
+        }
+    } else if entry.external_id().is_none() {
+        // Replacing a document by Uuid keeps its client id
+        if let Some(previous) = get(storage, &entry.id) {
+            if let Some(external_id) = previous.external_id() {
+                entry = entry.with_external_id(external_id);
+            }
+        }
+    }
+
+    check_document(storage, &mut entry)?;
     let id = entry.id;
+    let existing = get(storage, &id);
+    entry.stamp(existing.as_ref().map(|previous| &previous.metadata), now_secs());
     let raw_vec = entry.get_vector();
     entry.vector = QuantizedVector::from_f32_with_config(&raw_vec, &storage.config.quantization);
+
+    if let Some(previous) = existing.as_ref().filter(|_| skip_unchanged) {
+        if !vector_changed(storage, previous, &entry) && previous.text == entry.text && same_metadata(&previous.metadata, &entry.metadata) {
+            debug!(collection=%storage.path, id=%id, "upsert_unchanged");
+            return Ok((id, false));
+        }
+    }
     let bytes = bincode::serialize(&entry)?;
 
-    let existing = storage.index.contains_key(&id);
-    if existing {
-        enforce_limits_single(storage, bytes.len())?;
-        let vector = entry.get_vector();
+    if let Some(previous) = existing {
+        enforce_size_limits(storage, bytes.len())?;
+        let vector = entry.exact_vector();
         let mut wal_entry = WalEntry::Update {
             id,
             vector,
@@ -256,31 +575,50 @@ pub fn upsert(storage: &mut Collection, mut entry: Document) -> Result<Uuid> {
             metadata: entry.metadata.clone(),
             seq: 0,
         }; // this means we need to log an update to the WAL instead of an insert
-        storage.persistence.wal.log(&mut wal_entry)?;
-        
+        log_wal(storage, &mut wal_entry)?;
 
-        delete_internal(storage, &id);
-        // reuse serialized bytes by deserializing again for insert? easiest: use entry clone? entry moved
-        let doc = bincode::deserialize(&bytes)?;
-        insert_internal(storage, doc)?;
+        // Same vector (e.g. a metadata-only upsert): only the stored entry is rewritten
+        let vector_changed = vector_changed(storage, &previous, &entry);
+        update_internal(storage, entry, vector_changed)?;
+        storage.metadata.counters.record_updates(1);
         super::persistence::save_index(storage)?;
-        super::persistence::save_vector_index(storage)?;
+        if vector_changed {
+            super::persistence::save_vector_index(storage)?;
+        }
         storage.track_operation()?;
-        Ok(id)
+        Ok((id, true))
     } else {
         enforce_limits_single(storage, bytes.len())?;
         // reconstruct doc from bytes to avoid double serialize? we already have entry; serialize used just for size check
-        insert(storage, entry)
+        insert(storage, entry).map(|id| (id, true))
+    }
+}
+
+// Whether `entry` (already quantized) stores a different vector than `previous`: the exact vectors when a two-stage store keeps them, the stored codes otherwise
+fn vector_changed(storage: &Collection, previous: &Document, entry: &Document) -> bool {
+    match (storage.two_stage.as_ref().and_then(|t| t.full_precision(&entry.id)), entry.full_precision.as_ref()) {
+        (Some(stored), Some(new)) => stored != *new,
+        _ => previous.vector.to_f32() != entry.vector.to_f32(),
     }
 }
 
+// Metadata equality, leaving out the version and timestamps the write itself stamps
+fn same_metadata(stored: &Metadata, new: &Metadata) -> bool {
+    use crate::storage::document::{CREATED_AT_KEY, UPDATED_AT_KEY, VERSION_KEY};
+    let stamped = |key: &String| [VERSION_KEY, CREATED_AT_KEY, UPDATED_AT_KEY].contains(&key.as_str());
+    let fields = |m: &Metadata| m.keys().filter(|k| !stamped(k)).count();
+    fields(stored) == fields(new) && new.iter().filter(|(k, _)| !stamped(k)).all(|(k, v)| stored.get(k) == Some(v))
+}
+
 pub fn delete(storage: &mut Collection, id: &Uuid) -> Result<bool> {
+    super::pause::ensure_writable(storage)?;
     // For a delete operation, we first check if the document exists in the collection. If it does, we log a delete entry to the WAL to ensure that the deletion is recorded for durability and recovery purposes. After logging the delete operation, we proceed to remove the entry from the index, vector index, and in-memory caches. Finally, we save the updated index and vector index to disk and track the operation for checkpointing purposes. If the document does not exist, we simply return false to indicate that no deletion occurred.
-    if storage.index.contains_key(id) {
+    if storage.data.get_mut().index.contains_key(id) {
         let mut wal_entry = WalEntry::Delete { id: *id, seq: 0 };
-        storage.persistence.wal.log(&mut wal_entry)?;
+        log_wal(storage, &mut wal_entry)?;
         
         delete_internal(storage, id);
+        storage.metadata.counters.record_deletes(1);
         super::persistence::save_index(storage)?;
         super::persistence::save_vector_index(storage)?;
         storage.track_operation()?;
@@ -291,24 +629,26 @@ pub fn delete(storage: &mut Collection, id: &Uuid) -> Result<bool> {
 }
 
 pub fn delete_batch(storage: &mut Collection, ids: &[Uuid]) -> Result<usize> {
+    super::pause::ensure_writable(storage)?;
     // For a batch delete operation, we first iterate through the list of IDs and log a delete entry to the WAL for each ID that exists in the collection. This ensures that all delete operations are recorded in the WAL for durability and recovery purposes. After logging the delete operations, we proceed to remove each existing entry from the index, vector index, and in-memory caches. We keep track of the number of successfully deleted entries, and if any entries were deleted, we save the updated index and vector index to disk and track the operation for checkpointing purposes. Finally, we return the count of deleted entries.
     let mut deleted_count = 0;
     
-    for id in ids {
-        if storage.index.contains_key(id) {
-            let mut wal_entry = WalEntry::Delete { id: *id, seq: 0 };
-            storage.persistence.wal.log(&mut wal_entry)?;
-        }
-    }
+    let data = storage.data.get_mut();
+    let mut wal_entries: Vec<WalEntry> = ids.iter()
+        .filter(|id| data.index.contains_key(id))
+        .map(|id| WalEntry::Delete { id: *id, seq: 0 })
+        .collect();
+    log_wal_batch(storage, &mut wal_entries)?;
     
     for id in ids {
-        if storage.index.contains_key(id) {
+        if storage.data.get_mut().index.contains_key(id) {
             delete_internal(storage, id);
             deleted_count += 1;
         }
     }
     
     if deleted_count > 0 {
+        storage.metadata.counters.record_deletes(deleted_count);
         super::persistence::save_index(storage)?;
         super::persistence::save_vector_index(storage)?;
         storage.track_operation()?;
@@ -317,49 +657,145 @@ pub fn delete_batch(storage: &mut Collection, ids: &[Uuid]) -> Result<usize> {
     Ok(deleted_count)
 }
 
-pub fn update_metadata(storage: &mut Collection, id: &Uuid, metadata: Metadata) -> Result<bool> {
-    // For an update metadata operation, we first check if the document exists in the collection. If it does, we log an update entry to the WAL with the new metadata to ensure that the change is recorded for durability and recovery purposes. After logging the update operation, we retrieve the existing document, update its metadata, and then perform a delete followed by an insert to ensure that the index and vector index are properly updated to reflect the changes in the document. Finally, we save the updated index and vector index to disk and track the operation for checkpointing purposes. If the document does not exist, we simply return false to indicate that no update occurred.
-    if let Some(entry) = get(storage, id) {
-        let vector = entry.get_vector();
-        
+fn now_secs() -> u64 {
+    crate::testing::clock::now_secs()
+}
+
+// Fail with a conflict unless the document is at version `expected`; a missing document is at version 0
+pub fn ensure_version(storage: &Collection, id: &Uuid, expected: Option<u64>) -> Result<()> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    let current = get(storage, id).map_or(0, |doc| doc.version());
+    if current != expected {
+        return Err(ServerError::Conflict(format!("document {id} is at version {current}, not {expected}")).into());
+    }
+    Ok(())
+}
+
+// Returns the version written, None when the document does not exist
+pub fn update_metadata(storage: &Collection, id: &Uuid, metadata: Metadata, if_version: Option<u64>) -> Result<Option<u64>> {
+    super::pause::ensure_writable(storage)?;
+    // A metadata-only update leaves the vector index and the vector caches alone, so it runs under a shared collection lock: searches keep going while it logs to the WAL, and only wait for the moment the new version's pointer is swapped in under the data latch. The shared-writes lock keeps concurrent metadata updates (and checkpoints) in WAL order.
+    let _writer = storage.shared_writes.lock();
+    // Checked under the shared-writes lock, so two conditional updates cannot both pass
+    ensure_version(storage, id, if_version)?;
+    let Some(mut entry) = get(storage, id) else {
+        return Ok(None);
+    };
+    // Log the exact vector when a two-stage store has it, so replaying the update does not degrade it to the quantized one
+    let vector = storage.two_stage.as_ref()
+        .and_then(|two_stage| two_stage.full_precision(id))
+        .unwrap_or_else(|| entry.get_vector());
+
+    // The client id is part of the document's identity: keep it when the new metadata leaves it out
+    let mut metadata = metadata;
+    match crate::storage::document::external_id_of(&metadata) {
+        Some(external_id) => ensure_external_id_available(storage, external_id, id)?,
+        None => {
+            if let Some(external_id) = entry.metadata.get(crate::storage::document::EXTERNAL_ID_KEY) {
+                metadata.insert(crate::storage::document::EXTERNAL_ID_KEY.to_string(), external_id.clone());
+            }
+        }
+    }
+
+    // The stored (quantized) vector is kept as it is; only the metadata of the new version differs
+    let previous_external_id = entry.external_id().map(str::to_string);
+    let previous = std::mem::replace(&mut entry.metadata, metadata);
+    entry.stamp(Some(&previous), now_secs());
+    let version = entry.version();
+    let bytes = bincode::serialize(&entry)?;
+    enforce_size_limits(storage, bytes.len())?;
+
+    let mut wal_entry = WalEntry::Update {
+        id: *id,
+        vector,
+        text: entry.text.clone(),
+        metadata: entry.metadata.clone(),
+        seq: 0,
+    };
+    storage.persistence.lock().wal.log(&mut wal_entry)?;
+
+    let external_id = entry.external_id().map(str::to_string);
+    storage.sampler.lock().refresh_metadata(id, &entry.metadata);
+    let (offset, superseded) = write_version(&mut storage.data.write(), *id, &bytes, previous_external_id, external_id, entry.metadata)?;
+    storage.metadata.counters.record_updates(1);
+    storage.metadata.counters.record_dead_bytes(superseded);
+    if let Some(replication) = storage.replication.lock().as_mut() {
+        replication.stage(&wal_entry);
+        replication.finish(true);
+    }
+    debug!(collection=%storage.path, id=%id, offset, len=bytes.len(), version, "updated_metadata");
+
+    super::persistence::save_index(storage)?;
+    storage.track_operation()?;
+    Ok(Some(version))
+}
+
+pub fn update_vector(storage: &mut Collection, id: &Uuid, vector: Vec<f32>) -> Result<bool> {
+    super::pause::ensure_writable(storage)?;
+    let vector = crate::validation::check_vector(&vector, &storage.config.validation, storage.config.index.metric())?.into_owned();
+    // For an update vector operation, we first check if the document exists in the collection. If it does, we log an update entry to the WAL with the new vector to ensure that the change is recorded for durability and recovery purposes. After logging the update operation, we retrieve the existing document, update its vector, and then perform a delete followed by an insert to ensure that the index and vector index are properly updated to reflect the changes in the document. Finally, we save the updated index and vector index to disk and track the operation for checkpointing purposes. If the document does not exist, we simply return false to indicate that no update occurred.
+    if let Some(mut entry) = get(storage, id) {
+        let previous = entry.metadata.clone();
+        entry.stamp(Some(&previous), now_secs());
         let mut wal_entry = WalEntry::Update {
             id: *id,
-            vector,
+            vector: vector.clone(),
             text: entry.text.clone(),
-            metadata: metadata.clone(),
+            metadata: entry.metadata.clone(),
             seq: 0,
         };
-        storage.persistence.wal.log(&mut wal_entry)?;
+        log_wal(storage, &mut wal_entry)?;
         
-        let mut entry = entry;
-        entry.metadata = metadata;
-        delete(storage, id)?;
-        insert(storage, entry)?;
+        entry.vector = QuantizedVector::from_f32_with_config(&vector, &storage.config.quantization);
+        entry.full_precision = Some(vector);
+        update_internal(storage, entry, true)?;
+        storage.metadata.counters.record_updates(1);
+        super::persistence::save_index(storage)?;
+        super::persistence::save_vector_index(storage)?;
+        storage.track_operation()?;
         Ok(true)
     } else {
         Ok(false)
     }
 }
 
-pub fn update_vector(storage: &mut Collection, id: &Uuid, vector: Vec<f32>) -> Result<bool> {
-    // For an update vector operation, we first check if the document exists in the collection. If it does, we log an update entry to the WAL with the new vector to ensure that the change is recorded for durability and recovery purposes. After logging the update operation, we retrieve the existing document, update its vector, and then perform a delete followed by an insert to ensure that the index and vector index are properly updated to reflect the changes in the document. Finally, we save the updated index and vector index to disk and track the operation for checkpointing purposes. If the document does not exist, we simply return false to indicate that no update occurred.
-    if let Some(entry) = get(storage, id) {
+// Replace the vectors of many documents, saving the index and vector index once at the end (re-embedding
+// a collection one update_vector call at a time would write the whole vector index per document).
+// Every vector is validated before anything is logged; ids that no longer exist are skipped.
+pub fn update_vectors(storage: &mut Collection, updates: Vec<(Uuid, Vec<f32>)>) -> Result<usize> {
+    super::pause::ensure_writable(storage)?;
+    let mut checked = Vec::with_capacity(updates.len());
+    for (id, vector) in updates {
+        let vector = crate::validation::check_vector(&vector, &storage.config.validation, storage.config.index.metric())?.into_owned();
+        checked.push((id, vector));
+    }
+    let mut updated = 0;
+    for (id, vector) in checked {
+        let Some(mut entry) = get(storage, &id) else {
+            continue;
+        };
+        let previous = entry.metadata.clone();
+        entry.stamp(Some(&previous), now_secs());
         let mut wal_entry = WalEntry::Update {
-            id: *id,
+            id,
             vector: vector.clone(),
             text: entry.text.clone(),
             metadata: entry.metadata.clone(),
             seq: 0,
         };
-        storage.persistence.wal.log(&mut wal_entry)?;
-        
-        let mut entry = entry;
+        log_wal(storage, &mut wal_entry)?;
         entry.vector = QuantizedVector::from_f32_with_config(&vector, &storage.config.quantization);
-        delete(storage, id)?;
-        
-        insert(storage, entry)?;
-        Ok(true)
-    } else {
-        Ok(false)
+        entry.full_precision = Some(vector);
+        update_internal(storage, entry, true)?;
+        updated += 1;
+    }
+    if updated > 0 {
+        storage.metadata.counters.record_updates(updated);
+        super::persistence::save_index(storage)?;
+        super::persistence::save_vector_index(storage)?;
+        storage.track_operation()?;
     }
+    Ok(updated)
 }
@@ -0,0 +1,147 @@
+// Outlier scoring of vectors against a collection, for monitoring the quality of incoming embeddings.
+//
+// - knn: similarity to the k-th nearest stored neighbour, found through the vector index like any
+//   search (a stored document does not count as its own neighbour)
+// - centroid: similarity to the mean of the stored vectors
+//
+// Scores use the collection's metric, where higher is closer. A raw score means little across
+// models and metrics, so each one is ranked against the same score for an evenly spread sample of
+// the stored documents: `percentile` is the share of that sample lying closer to its neighbours (or
+// the centroid) than the scored vector does. 99 means more isolated than 99% of the collection.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{Result, ServerError};
+use crate::metrics::Metric;
+use crate::search::SearchParams;
+use super::stats::{vector_stats, StatsOptions};
+use super::storage::Collection;
+
+pub const DEFAULT_OUTLIER_K: usize = 10;
+pub const DEFAULT_OUTLIER_SAMPLE: usize = 200;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutlierMethod {
+    #[default]
+    Knn,
+    Centroid,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutlierOptions {
+    pub method: OutlierMethod,
+    pub k: usize, // knn only
+    pub sample_size: usize, // stored documents the percentiles are ranked against
+}
+
+impl Default for OutlierOptions {
+    fn default() -> Self {
+        Self { method: OutlierMethod::Knn, k: DEFAULT_OUTLIER_K, sample_size: DEFAULT_OUTLIER_SAMPLE }
+    }
+}
+
+// What to score: a vector from outside, or a document already stored
+#[derive(Debug, Clone)]
+pub enum OutlierQuery {
+    Vector(Vec<f32>),
+    Document(Uuid),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlierScore {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>, // set when a stored document was scored
+    pub score: f32,
+    pub percentile: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlierReport {
+    pub method: OutlierMethod,
+    pub metric: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k: Option<usize>,
+    pub reference_size: usize, // sampled documents the percentiles come from
+    pub scores: Vec<OutlierScore>,
+}
+
+/// Score each query and rank it against a sample of the stored documents.
+pub fn score_outliers(collection: &Collection, queries: &[OutlierQuery], opts: &OutlierOptions) -> Result<OutlierReport> {
+    let count = collection.count();
+    let metric = collection.config.index.metric();
+    if opts.method == OutlierMethod::Knn && opts.k == 0 {
+        return Err(ServerError::InvalidRequest("k must be >= 1".into()).into());
+    }
+    if opts.method == OutlierMethod::Knn && count <= opts.k {
+        return Err(ServerError::InvalidRequest(format!(
+            "k must be below the number of documents ({count})"
+        )).into());
+    }
+    if count == 0 {
+        return Err(ServerError::InvalidRequest("The collection has no documents".into()).into());
+    }
+
+    let centroid = match opts.method {
+        OutlierMethod::Centroid => vector_stats(collection, &StatsOptions { intrinsic_sample: 0, ..Default::default() })?.mean,
+        OutlierMethod::Knn => Vec::new(),
+    };
+    let score = |vector: &[f32], own: Option<Uuid>| match opts.method {
+        OutlierMethod::Knn => kth_neighbour(collection, vector, own, opts.k, metric),
+        OutlierMethod::Centroid => metric.calculate(vector, &centroid, collection.config.execution),
+    };
+
+    // The reference distribution, ascending
+    let ids = collection.ids();
+    let step = (ids.len() / opts.sample_size.max(1)).max(1);
+    let sampled: HashSet<Uuid> = ids.iter().step_by(step).take(opts.sample_size).copied().collect();
+    let mut reference: Vec<f32> = sampled
+        .iter()
+        .filter_map(|id| collection.get(id).map(|doc| score(&doc.get_vector(), Some(*id))))
+        .collect();
+    reference.sort_by(|a, b| a.total_cmp(b));
+
+    let mut scores = Vec::with_capacity(queries.len());
+    for query in queries {
+        let (id, vector) = match query {
+            OutlierQuery::Vector(vector) => (None, vector.clone()),
+            OutlierQuery::Document(id) => {
+                let doc = collection.get(id)
+                    .ok_or_else(|| ServerError::NotFound(format!("Document '{}' not found", id)))?;
+                (Some(*id), doc.get_vector())
+            }
+        };
+        if let Some(expected) = collection.metadata.dimensions {
+            crate::validation::validate_dimensions(&vector, expected)?;
+        }
+        let value = score(&vector, id);
+        let closer = reference.len() - reference.partition_point(|&s| s <= value);
+        // A sampled document is not ranked against its own entry
+        let others = reference.len() - id.is_some_and(|id| sampled.contains(&id)) as usize;
+        let percentile = if others == 0 { 0.0 } else { 100.0 * closer as f32 / others as f32 };
+        scores.push(OutlierScore { id, score: value, percentile });
+    }
+
+    Ok(OutlierReport {
+        method: opts.method,
+        metric: metric.name().to_string(),
+        k: (opts.method == OutlierMethod::Knn).then_some(opts.k),
+        reference_size: reference.len(),
+        scores,
+    })
+}
+
+// Similarity to the k-th nearest document other than `own`
+fn kth_neighbour(collection: &Collection, vector: &[f32], own: Option<Uuid>, k: usize, metric: Metric) -> f32 {
+    let params = SearchParams { mode: collection.config.execution, ..Default::default() };
+    let hits = collection.search(vector, k + 1, metric, params);
+    hits.iter()
+        .filter(|hit| Some(hit.id) != own)
+        .nth(k - 1)
+        .or(hits.last())
+        .map(|hit| hit.score)
+        .unwrap_or(f32::NEG_INFINITY)
+}
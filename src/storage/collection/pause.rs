@@ -0,0 +1,97 @@
+// Pausing a collection's writes, so an operator can take a filesystem-level snapshot or run external
+// tooling against files that hold still.
+// A pause is taken under the write lock, so the writes in flight have finished by then. It waits for
+// a checkpoint being written out, then checkpoints and flushes, so the data, index and WAL files on
+// disk are complete and agree with each other. Until the pause is lifted every write is rejected with
+// `WritesPaused` (the sealed check's place, see `ensure_writable`) and checkpoints do nothing, so the
+// files do not change; searches and reads carry on.
+//
+// Every pause has a deadline: past it the collection accepts writes again even if nobody resumed it,
+// so a snapshot script that died cannot leave the collection stuck. Pauses are not kept across restarts.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, ServerError};
+use crate::testing::clock::now_secs;
+use super::storage::Collection;
+
+// How long a pause lasts when the request does not say
+pub const DEFAULT_WRITE_PAUSE_SECS: u64 = 300;
+// Longest pause that can be asked for
+pub const MAX_WRITE_PAUSE_SECS: u64 = 24 * 3600;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WritePause {
+    pub paused_at: u64, // unix secs
+    pub resume_at: u64, // writes are accepted again from here on, resumed or not
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+// The pause in force, if any; one past its deadline no longer counts
+pub(super) fn active(collection: &Collection) -> Option<&WritePause> {
+    collection.write_pause.as_ref().filter(|pause| now_secs() < pause.resume_at)
+}
+
+// Every write checks this first: sealed collections and paused ones reject it
+pub(super) fn ensure_writable(collection: &Collection) -> Result<()> {
+    super::worm::ensure_unsealed(collection)?;
+    ensure_not_paused(collection)
+}
+
+// For what rewrites files of a sealed collection too (index rebuilds, tuning)
+pub(super) fn ensure_not_paused(collection: &Collection) -> Result<()> {
+    match active(collection) {
+        Some(pause) => Err(ServerError::WritesPaused {
+            message: format!(
+                "writes to '{}' are paused until {}{}; POST .../writes/resume lifts the pause",
+                collection.metadata.name,
+                pause.resume_at,
+                pause.reason.as_deref().map(|r| format!(" ({r})")).unwrap_or_default(),
+            ),
+            retry_after_secs: pause.resume_at.saturating_sub(now_secs()).max(1),
+        }.into()),
+        None => Ok(()),
+    }
+}
+
+// Drain, make the files on disk consistent, and hold writes for `timeout_secs`. Pausing a paused
+// collection moves its deadline and keeps its start.
+pub(super) fn pause(collection: &mut Collection, timeout_secs: u64, reason: Option<String>) -> Result<WritePause> {
+    if timeout_secs == 0 || timeout_secs > MAX_WRITE_PAUSE_SECS {
+        return Err(ServerError::InvalidRequest(format!("timeout_secs must be between 1 and {MAX_WRITE_PAUSE_SECS}")).into());
+    }
+    let now = now_secs();
+    let paused_at = match active(collection) {
+        Some(pause) => pause.paused_at,
+        None => {
+            super::persistence::wait_for_checkpoint(collection);
+            super::persistence::checkpoint(collection)?;
+            super::persistence::flush(collection)?;
+            now
+        }
+    };
+    let pause = WritePause { paused_at, resume_at: now + timeout_secs, reason };
+    collection.write_pause = Some(pause.clone());
+    tracing::info!(collection=%collection.path, resume_at=pause.resume_at, reason=?pause.reason, "collection_writes_paused");
+    Ok(pause)
+}
+
+// Drop a pause whose deadline has passed; returns it when there was one
+pub(super) fn expire(collection: &mut Collection) -> Option<WritePause> {
+    if collection.write_pause.as_ref().is_some_and(|pause| now_secs() >= pause.resume_at) {
+        let lapsed = collection.write_pause.take();
+        tracing::warn!(collection=%collection.path, "collection_writes_auto_resumed");
+        return lapsed;
+    }
+    None
+}
+
+// Lift the pause; returns it when one was in force
+pub(super) fn resume(collection: &mut Collection) -> Option<WritePause> {
+    let lifted = collection.write_pause.take().filter(|pause| now_secs() < pause.resume_at);
+    if let Some(pause) = &lifted {
+        tracing::info!(collection=%collection.path, paused_secs=now_secs().saturating_sub(pause.paused_at), "collection_writes_resumed");
+    }
+    lifted
+}
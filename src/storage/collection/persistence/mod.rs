@@ -1,11 +1,23 @@
 // This module defines the persistence service for the collection, which is responsible for managing the write-ahead log (WAL) and performing checkpoints to save the state of the collection to disk. It provides functions to save the index, vector index, and metadata of the collection, as well as to load and save WAL metadata. The checkpoint function saves the current state of the collection and rotates the WAL if necessary, while the flush function ensures that all pending WAL entries are flushed to disk. The persistence service also includes logic to determine when a checkpoint should be performed based on the configured checkpoint frequency and to record the timestamp of the last checkpoint for recovery purposes.
 
 use crate::error::Result;
-use crate::storage::persistence::{save_index as save_idx, save_vector_index as save_vec_idx, save_metadata as save_meta};
-use crate::storage::wal::Wal;
+use crate::index::VectorIndex;
+use crate::scheduler::{self, Priority};
+use crate::storage::metadata::CollectionMetadata;
+use crate::storage::persistence::{
+    save_index as save_idx, save_vector_index as save_vec_idx, save_packed_vector_index, save_metadata as save_meta, clone_vector_index,
+    get_wal_path, write_atomic, EntryPointer,
+};
+use crate::storage::wal::{Wal, WalHistory, WalSegments, release_sealed};
 use super::storage::Collection;
+use parking_lot::{ArcMutexGuard, Mutex, RawMutex};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::{fs, path::PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
 
 pub struct PersistenceService {
     pub wal: Wal, // The write-ahead log instance for managing durability and recovery
@@ -44,16 +56,60 @@ impl PersistenceService {
     }
 }
 
+// Counts the direct saves of each file. A save holds its file's slot while writing, and a checkpoint
+// being written out skips a file that was saved after its capture, so an older copy never lands on top.
+#[derive(Default)]
+pub struct SavedFiles {
+    index: Mutex<u64>,
+    vector_index: Mutex<u64>,
+    metadata: Mutex<u64>,
+}
+
+fn save_counted(slot: &Mutex<u64>, save: impl FnOnce() -> Result<()>) -> Result<()> {
+    let mut saves = slot.lock();
+    save()?;
+    *saves += 1;
+    Ok(())
+}
+
+fn save_unless_newer(slot: &Mutex<u64>, captured: u64, save: impl FnOnce() -> Result<()>) -> Result<()> {
+    let saves = slot.lock();
+    if *saves == captured {
+        save()?;
+    }
+    Ok(())
+}
+
+// An ephemeral collection has nowhere to save to; its saves, checkpoints and flushes do nothing
 pub fn save_index(storage: &Collection) -> Result<()> {
-    save_idx(&storage.path, &storage.index)
+    if storage.config.ephemeral {
+        return Ok(());
+    }
+    save_counted(&storage.saved.index, || save_idx(&storage.path, &storage.data.read_recursive().index))
 }
 
+// The flat stand-in used while a corrupt index is rebuilt is not saved either. A sealed collection's
+// index is rewritten only by a rebuild, in the packed layout.
 pub fn save_vector_index(storage: &Collection) -> Result<()> {
-    save_vec_idx(&storage.path, storage.vector_index.as_ref()) // We pass a reference to the vector index to the save function, which will handle serializing and writing it to disk. The vector index is a critical component of the collection that allows for efficient similarity search, so it's important to ensure that it is saved correctly during checkpoints. By saving the vector index along with the main index and metadata, we can ensure that we have a consistent state of the collection that can be recovered in case of a crash or unexpected shutdown.
+    if storage.config.ephemeral || storage.index_recovery.is_some() {
+        return Ok(());
+    }
+    if storage.metadata.sealed_at.is_some() {
+        return save_counted(&storage.saved.vector_index, || save_packed_vector_index(&storage.path, storage.vector_index.as_ref()));
+    }
+    save_counted(&storage.saved.vector_index, || save_vec_idx(&storage.path, storage.vector_index.as_ref())) // We pass a reference to the vector index to the save function, which will handle serializing and writing it to disk. The vector index is a critical component of the collection that allows for efficient similarity search, so it's important to ensure that it is saved correctly during checkpoints. By saving the vector index along with the main index and metadata, we can ensure that we have a consistent state of the collection that can be recovered in case of a crash or unexpected shutdown.
 }
 
 pub fn save_metadata(storage: &Collection) -> Result<()> {
-    save_meta(&storage.path, &storage.metadata) // Similar to saving the index and vector index, we also need to save the metadata of the collection during checkpoints. The metadata contains important information about the documents in the collection, such as their IDs and any associated metadata fields. By saving the metadata along with the index and vector index, we can ensure that we have a complete snapshot of the collection's state that can be used for recovery if needed.
+    if storage.config.ephemeral {
+        return Ok(());
+    }
+    // The counters include every WAL entry logged so far, which replay must not count again
+    let mut metadata = storage.metadata.clone();
+    if storage.config.wal.enabled {
+        metadata.counters_seq = storage.persistence.lock().wal.next_seq.saturating_sub(1);
+    }
+    save_counted(&storage.saved.metadata, || save_meta(&storage.path, &metadata)) // Similar to saving the index and vector index, we also need to save the metadata of the collection during checkpoints. The metadata contains important information about the documents in the collection, such as their IDs and any associated metadata fields. By saving the metadata along with the index and vector index, we can ensure that we have a complete snapshot of the collection's state that can be used for recovery if needed.
 }
 
 fn wal_meta_path(path: &str) -> PathBuf {
@@ -76,44 +132,199 @@ pub fn load_wal_meta(path: &str) -> Result<u64> {
 }
 
 fn save_wal_meta(path: &str, last_checkpoint_seq: u64) -> Result<()> {
-    let meta_path = wal_meta_path(path);
-    let tmp_path = meta_path.with_extension("tmp");
     let meta = WalMeta { last_checkpoint_seq };
-    fs::write(&tmp_path, serde_json::to_vec(&meta)?)?;
-    fs::rename(&tmp_path, &meta_path)?;
-    if let Ok(file) = fs::File::open(&meta_path) {
-        let _ = file.sync_all();
+    write_atomic(&wal_meta_path(path).to_string_lossy(), &serde_json::to_vec(&meta)?)
+}
+
+
+// How far checkpoints have got, for write backpressure: the last WAL sequence number covered by a
+// checkpoint on disk, when the checkpoint being written out was captured, and how long the last one
+// took from capture to its files being written
+pub struct CheckpointProgress {
+    written_seq: AtomicU64,
+    writing_since: Mutex<Option<Instant>>,
+    last_duration: Mutex<Option<Duration>>,
+}
+
+impl CheckpointProgress {
+    pub fn new(written_seq: u64) -> Self {
+        Self { written_seq: AtomicU64::new(written_seq), writing_since: Mutex::new(None), last_duration: Mutex::new(None) }
     }
-    Ok(())
+
+    pub fn written_seq(&self) -> u64 {
+        self.written_seq.load(Ordering::Acquire)
+    }
+
+    // How long the checkpoint being written out has been running; None when there is none
+    pub fn running(&self) -> Option<Duration> {
+        self.writing_since.lock().map(|since| since.elapsed())
+    }
+
+    pub fn last_duration(&self) -> Option<Duration> {
+        *self.last_duration.lock()
+    }
+
+    fn start(&self) {
+        *self.writing_since.lock() = Some(Instant::now());
+    }
+
+    fn finish(&self) {
+        if let Some(since) = self.writing_since.lock().take() {
+            *self.last_duration.lock() = Some(since.elapsed());
+        }
+    }
+}
+
+// A checkpoint in two steps. `capture` runs under the collection lock: it logs the checkpoint entry,
+// seals the WAL and clones the pointer index, vector index and metadata, which is quick next to
+// writing them. `write` then runs without the lock: each file goes to a temporary file that is
+// fsynced and renamed over the old one, then the WAL meta moves to the checkpoint's sequence number
+// and the sealed WAL is dropped. Writes logged meanwhile go to the fresh WAL file and are replayed
+// on top of the checkpoint after a crash; until `write` finishes, replay still covers the sealed file.
+//
+// The collection's checkpoint lock is held from capture to the end of the write, so checkpoints land
+// in order. Direct saves (every insert's pointer index, compaction, rebuilds) do not wait: a file saved
+// after the capture is newer than the checkpoint's copy, which is then left out.
+pub struct PendingCheckpoint {
+    path: String,
+    wal_path: PathBuf,
+    timestamp: u64,
+    seq: Option<u64>, // last WAL seq the captured state includes; None when the WAL is disabled
+    history: Option<WalHistory>,
+    segments: WalSegments,
+    index: HashMap<Uuid, EntryPointer>,
+    vector_index: Option<Box<dyn VectorIndex>>, // None while a corrupt index is rebuilt
+    metadata: CollectionMetadata,
+    saved: Arc<SavedFiles>,
+    saves_at_capture: [u64; 3], // index, vector index, metadata
+    progress: Arc<CheckpointProgress>,
+    _lock: ArcMutexGuard<RawMutex, ()>,
 }
 
+impl PendingCheckpoint {
+    // The caller holds the collection (shared or exclusive) and the shared-writes lock, and neither the
+    // data latch nor the WAL latch, so no write lands between the clone and the seal
+    pub fn capture(storage: &Collection) -> Result<Option<Self>> {
+        // A sealed collection's files were written for the last time when it was sealed, and a paused
+        // one's stay as they were when it was paused
+        if storage.config.ephemeral || storage.metadata.sealed_at.is_some() || storage.write_pause().is_some() {
+            return Ok(None);
+        }
+        let lock = storage.checkpoint_lock.lock_arc();
+        let timestamp = crate::testing::clock::now_secs();
 
-pub fn checkpoint(storage: &mut Collection) -> Result<()> {
-    // 1. Get the current timestamp to record when the checkpoint is being performed. This timestamp can be used for recovery purposes to determine the point in time at which the checkpoint was taken, which can help in replaying the WAL entries correctly during recovery.
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
+        let saves_at_capture = [
+            *storage.saved.index.lock(),
+            *storage.saved.vector_index.lock(),
+            *storage.saved.metadata.lock(),
+        ];
+        let index = storage.data.read_recursive().index.clone();
+        let vector_index = storage.index_recovery.is_none().then(|| clone_vector_index(storage.vector_index.as_ref()));
+        let mut metadata = storage.metadata.clone();
 
-    // 2. Save the current state of the index, vector index, and metadata to disk. This involves serializing the in-memory data structures and writing them to their respective files on disk. By saving these components, we ensure that we have a consistent snapshot of the collection's state that can be used for recovery if needed.
-    save_index(storage)?;
-    save_vector_index(storage)?;
-    save_metadata(storage)?;
+        let mut persistence = storage.persistence.lock();
+        let seq = if storage.config.wal.enabled {
+            persistence.wal.checkpoint(timestamp)?;
+            persistence.wal.seal()?;
+            persistence.record_checkpoint(timestamp);
+            Some(persistence.wal.next_seq.saturating_sub(1))
+        } else {
+            None
+        };
+        if let Some(seq) = seq {
+            metadata.counters_seq = seq;
+        }
 
-    // 3. If WAL is enabled in the configuration, we need to checkpoint the WAL to ensure that all pending entries are flushed to disk and that the WAL is rotated if necessary. This involves calling the checkpoint method on the WAL instance, which will handle flushing any buffered entries and rotating the log file if it exceeds the configured size or if a checkpoint is triggered based on the operation count.
-    if storage.config.wal.enabled {
-        storage.persistence.wal.checkpoint(timestamp)?;
-        storage.persistence.record_checkpoint(timestamp);
-        let last_seq = storage.persistence.wal.next_seq.saturating_sub(1);
-        save_wal_meta(&storage.path, last_seq)?;
-        storage.persistence.wal.rotate()?;
+        storage.checkpoint_progress.start();
+        Ok(Some(Self {
+            path: storage.path.clone(),
+            wal_path: get_wal_path(&storage.path).into(),
+            timestamp,
+            seq,
+            history: persistence.wal.history().cloned(),
+            segments: persistence.wal.segments().clone(),
+            index,
+            vector_index,
+            metadata,
+            saved: storage.saved.clone(),
+            saves_at_capture,
+            progress: storage.checkpoint_progress.clone(),
+            _lock: lock,
+        }))
     }
 
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    pub fn write(self) -> Result<()> {
+        let [index, vector_index, metadata] = self.saves_at_capture;
+        save_unless_newer(&self.saved.index, index, || save_idx(&self.path, &self.index))?;
+        if let Some(index) = self.vector_index.as_deref() {
+            save_unless_newer(&self.saved.vector_index, vector_index, || save_vec_idx(&self.path, index))?;
+        }
+        save_unless_newer(&self.saved.metadata, metadata, || save_meta(&self.path, &self.metadata))?;
+        if let Some(seq) = self.seq {
+            save_wal_meta(&self.path, seq)?;
+            self.progress.written_seq.store(seq, Ordering::Release);
+            release_sealed(&self.wal_path, self.history.as_ref(), &self.segments, seq)?;
+        }
+        Ok(())
+    }
+}
+
+// Written out or given up on, the checkpoint is no longer running
+impl Drop for PendingCheckpoint {
+    fn drop(&mut self) {
+        self.progress.finish();
+    }
+}
+
+// Capture and write in one go, for callers that have to see the files on disk when it returns
+pub fn checkpoint(storage: &Collection) -> Result<()> {
+    match PendingCheckpoint::capture(storage)? {
+        Some(pending) => pending.write(),
+        None => Ok(()),
+    }
+}
+
+// Capture under the caller's lock and write as a high-priority background task, so an operation that
+// hits the checkpoint frequency does not hold the collection while the files are written. A failed
+// write is logged and its sealed WAL stays; the next checkpoint folds it in and writes again.
+pub fn checkpoint_in_background(storage: &Collection) -> Result<()> {
+    let Some(pending) = PendingCheckpoint::capture(storage)? else { return Ok(()) };
+    let path = storage.path.clone();
+    let task = scheduler::spawn(Priority::High, "checkpoint", move || {
+        if let Err(e) = pending.write() {
+            tracing::warn!(collection=%path, error=%e, "background_checkpoint_failed");
+        }
+    });
+    // The previous write finished before this capture could take the checkpoint lock
+    if let Some(previous) = storage.background_checkpoint.lock().replace(task) {
+        let _ = previous.join();
+    }
     Ok(())
 }
 
-pub fn flush(storage: &mut Collection) -> Result<()> {
+// Wait for a checkpoint that is being written out, e.g. before replacing the collection's files
+pub fn wait_for_checkpoint(storage: &Collection) {
+    if let Some(task) = storage.background_checkpoint.lock().take() {
+        let _ = task.join();
+    }
+    drop(storage.checkpoint_lock.lock());
+}
+
+pub fn flush(storage: &Collection) -> Result<()> {
+    if storage.config.ephemeral {
+        return Ok(());
+    }
     // If WAL is enabled, we need to flush any pending entries to disk to ensure durability. This involves calling the flush method on the WAL instance, which will write any buffered entries to the log file and ensure that they are persisted on disk. Flushing is important to guarantee that all operations are safely stored in the WAL before we perform a checkpoint or before shutting down the collection, as it allows us to recover from any crashes or unexpected shutdowns without losing data.
-    storage.persistence.wal.flush()?;
+    storage.persistence.lock().wal.flush()?;
+    if let Some(two_stage) = storage.two_stage.as_ref() {
+        two_stage.full.flush()?;
+    }
+    if let Some(column) = storage.column.as_ref() {
+        column.flush()?;
+    }
     Ok(())
 }
@@ -0,0 +1,328 @@
+// Trained dimension-reducing projection (PCA or OPQ rotation) applied before indexing.
+// Where the transform truncates a fixed prefix, a projection is learned from the collection's own
+// vectors: PCA keeps the directions that carry most of the variance, so 1536-dim embeddings can be
+// indexed as e.g. 256 dims with little recall loss. The index, the vector cache and the vector
+// column all hold projected vectors; documents keep their full vectors and candidates are re-ranked
+// on them (the transform's `rerank` settings apply to both).
+//
+// OPQ here is the non-parametric variant: the PCA basis followed by a random rotation that spreads
+// the variance evenly over the output dimensions. The collection's quantizer is per-vector scalar
+// (one min/max per vector), so balanced dimensions lose less precision; there are no PQ
+// sub-codebooks to optimize jointly.
+//
+// The projection is stored in `.proj.db` (bincode) and is part of snapshots.
+
+use std::borrow::Cow;
+use std::fs;
+use std::sync::Arc;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::TransformConfig;
+use crate::error::{Result, ServerError};
+use super::operations;
+use super::storage::Collection;
+
+pub const DEFAULT_PROJECTION_SAMPLE: usize = 10_000;
+
+// Subspace iterations; each one multiplies the basis by the sample covariance
+const POWER_ITERATIONS: usize = 8;
+const JACOBI_SWEEPS: usize = 30;
+const SEED: u64 = 0x5eed_f00d;
+
+pub fn get_projection_path(collection_path: &str) -> String {
+    format!("{}.proj.db", collection_path)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectionKind {
+    Pca,
+    Opq,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Projection {
+    pub kind: ProjectionKind,
+    pub input_dims: usize,
+    pub output_dims: usize,
+    pub sample_size: usize,
+    pub explained_variance: f32, // share of the sample's variance kept by the output dimensions
+    pub trained_at: u64,
+    mean: Vec<f32>,
+    matrix: Vec<f32>, // output_dims rows of input_dims
+}
+
+impl Projection {
+    #[allow(clippy::needless_range_loop)]
+    pub fn train(kind: ProjectionKind, samples: &[Vec<f32>], dims: usize) -> Result<Self> {
+        let input_dims = samples.first().map(Vec::len).unwrap_or(0);
+        if dims == 0 || dims >= input_dims {
+            return Err(ServerError::InvalidRequest(format!(
+                "Projection dims must be between 1 and {} (the indexed dimensions)",
+                input_dims.saturating_sub(1)
+            )).into());
+        }
+        if samples.len() <= dims {
+            return Err(ServerError::InvalidRequest(format!(
+                "Training a {}-dim projection needs more than {} vectors, got {}",
+                dims, dims, samples.len()
+            )).into());
+        }
+        if samples.iter().any(|s| s.len() != input_dims) {
+            return Err(ServerError::InvalidRequest("Training vectors have mixed dimensions".into()).into());
+        }
+
+        let n = samples.len() as f64;
+        let mut mean = vec![0.0f64; input_dims];
+        for sample in samples {
+            mean.iter_mut().zip(sample).for_each(|(m, x)| *m += *x as f64);
+        }
+        mean.iter_mut().for_each(|m| *m /= n);
+        let centered: Vec<Vec<f64>> = samples
+            .par_iter()
+            .map(|s| s.iter().zip(&mean).map(|(x, m)| *x as f64 - m).collect())
+            .collect();
+        let total_variance: f64 = centered.par_iter().map(|x| dot(x, x)).sum::<f64>() / n;
+
+        // Randomized subspace iteration: start from a random basis and repeatedly apply X^T X
+        let mut rng = StdRng::seed_from_u64(SEED);
+        let mut basis: Vec<Vec<f64>> = (0..dims)
+            .map(|_| (0..input_dims).map(|_| rng.gen::<f64>() - 0.5).collect())
+            .collect();
+        orthonormalize(&mut basis);
+        for _ in 0..POWER_ITERATIONS {
+            let scores = project_rows(&centered, &basis);
+            basis = (0..dims)
+                .into_par_iter()
+                .map(|j| {
+                    let mut column = vec![0.0f64; input_dims];
+                    for (x, s) in centered.iter().zip(&scores) {
+                        column.iter_mut().zip(x).for_each(|(c, v)| *c += s[j] * v);
+                    }
+                    column
+                })
+                .collect();
+            orthonormalize(&mut basis);
+        }
+
+        // Rayleigh-Ritz: diagonalize the covariance restricted to the subspace to get ordered components
+        let scores = project_rows(&centered, &basis);
+        let mut restricted = vec![vec![0.0f64; dims]; dims];
+        for s in &scores {
+            for a in 0..dims {
+                for b in a..dims {
+                    restricted[a][b] += s[a] * s[b];
+                }
+            }
+        }
+        for a in 0..dims {
+            for b in a..dims {
+                restricted[a][b] /= n;
+                restricted[b][a] = restricted[a][b];
+            }
+        }
+        let (eigenvalues, eigenvectors) = jacobi_eigen(restricted);
+        let mut order: Vec<usize> = (0..dims).collect();
+        order.sort_by(|a, b| eigenvalues[*b].partial_cmp(&eigenvalues[*a]).unwrap_or(std::cmp::Ordering::Equal));
+        let mut components: Vec<Vec<f64>> = order
+            .iter()
+            .map(|&m| {
+                let mut component = vec![0.0f64; input_dims];
+                for (j, q) in basis.iter().enumerate() {
+                    let weight = eigenvectors[j][m];
+                    component.iter_mut().zip(q).for_each(|(c, v)| *c += weight * v);
+                }
+                component
+            })
+            .collect();
+        let kept: f64 = eigenvalues.iter().map(|v| v.max(0.0)).sum();
+
+        if kind == ProjectionKind::Opq {
+            let mut rotation: Vec<Vec<f64>> = (0..dims)
+                .map(|_| (0..dims).map(|_| gaussian(&mut rng)).collect())
+                .collect();
+            orthonormalize(&mut rotation);
+            components = rotation
+                .iter()
+                .map(|row| {
+                    let mut rotated = vec![0.0f64; input_dims];
+                    for (weight, component) in row.iter().zip(&components) {
+                        rotated.iter_mut().zip(component).for_each(|(r, c)| *r += weight * c);
+                    }
+                    rotated
+                })
+                .collect();
+        }
+
+        Ok(Self {
+            kind,
+            input_dims,
+            output_dims: dims,
+            sample_size: samples.len(),
+            explained_variance: if total_variance > 0.0 { (kept / total_variance).min(1.0) as f32 } else { 1.0 },
+            trained_at: crate::testing::clock::now_secs(),
+            mean: mean.into_iter().map(|m| m as f32).collect(),
+            matrix: components.into_iter().flatten().map(|c| c as f32).collect(),
+        })
+    }
+
+    pub fn apply(&self, vector: &[f32]) -> Vec<f32> {
+        self.matrix
+            .chunks_exact(self.input_dims)
+            .map(|row| {
+                row.iter()
+                    .zip(vector.iter().zip(&self.mean))
+                    .map(|(w, (x, m))| w * (x - m))
+                    .sum()
+            })
+            .collect()
+    }
+
+    pub fn load(collection_path: &str) -> Result<Option<Self>> {
+        match fs::read(get_projection_path(collection_path)) {
+            Ok(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, collection_path: &str) -> Result<()> {
+        let path = get_projection_path(collection_path);
+        let tmp = format!("{path}.tmp");
+        fs::write(&tmp, bincode::serialize(self)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+// Vector as the index sees it: the collection's transform, then its trained projection
+pub fn index_space<'a>(transform: &TransformConfig, projection: Option<&Projection>, vector: &'a [f32]) -> Cow<'a, [f32]> {
+    let transformed = transform.apply(vector);
+    match projection {
+        Some(projection) if projection.input_dims == transformed.len() => Cow::Owned(projection.apply(&transformed)),
+        _ => transformed,
+    }
+}
+
+// Train on an evenly spread sample of the stored vectors, then rebuild the caches and the index in
+// the projected space
+pub(super) fn train(collection: &mut Collection, kind: ProjectionKind, dims: usize, sample_size: usize) -> Result<()> {
+    if collection.two_stage.is_some() {
+        return Err(ServerError::InvalidRequest(
+            "Projections are not available for two-stage collections".into(),
+        ).into());
+    }
+    let ids: Vec<_> = collection.data().index.keys().copied().collect();
+    let step = (ids.len() / sample_size.max(1)).max(1);
+    let transform = collection.config.transform;
+    let samples: Vec<Vec<f32>> = ids
+        .iter()
+        .step_by(step)
+        .take(sample_size)
+        .filter_map(|id| operations::get(collection, id))
+        .map(|doc| transform.apply(&doc.get_vector()).into_owned())
+        .collect();
+
+    let projection = crate::parallel::maintenance(|| Projection::train(kind, &samples, dims))?;
+    if !collection.config.ephemeral {
+        projection.save(&collection.path)?;
+    }
+    collection.projection = Some(Arc::new(projection));
+    collection.rebuild_vector_cache();
+    collection.rebuild_index()
+}
+
+// Drop the projection and go back to indexing the transformed vectors
+pub(super) fn clear(collection: &mut Collection) -> Result<bool> {
+    if collection.projection.take().is_none() {
+        return Ok(false);
+    }
+    if !collection.config.ephemeral {
+        match fs::remove_file(get_projection_path(&collection.path)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    collection.rebuild_vector_cache();
+    collection.rebuild_index()?;
+    Ok(true)
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+// Scores of every row on every basis vector (n x dims)
+fn project_rows(rows: &[Vec<f64>], basis: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    rows.par_iter()
+        .map(|x| basis.iter().map(|q| dot(x, q)).collect())
+        .collect()
+}
+
+// Modified Gram-Schmidt over the rows of `vectors`
+fn orthonormalize(vectors: &mut [Vec<f64>]) {
+    for i in 0..vectors.len() {
+        let (done, rest) = vectors.split_at_mut(i);
+        let v = &mut rest[0];
+        for u in done.iter() {
+            let proj = dot(v, u);
+            v.iter_mut().zip(u).for_each(|(a, b)| *a -= proj * b);
+        }
+        let norm = dot(v, v).sqrt();
+        if norm > 1e-12 {
+            v.iter_mut().for_each(|a| *a /= norm);
+        }
+    }
+}
+
+// Cyclic Jacobi eigendecomposition of a symmetric matrix; eigenvectors are the columns
+#[allow(clippy::needless_range_loop)]
+fn jacobi_eigen(mut a: Vec<Vec<f64>>) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = a.len();
+    let mut v: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+    for _ in 0..JACOBI_SWEEPS {
+        let off: f64 = (0..n).flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j))).map(|(i, j)| a[i][j] * a[i][j]).sum();
+        let scale: f64 = (0..n).map(|i| a[i][i] * a[i][i]).sum();
+        if off <= 1e-22 * scale.max(1e-300) {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for k in 0..n {
+                    let (akp, akq) = (a[k][p], a[k][q]);
+                    a[k][p] = c * akp - s * akq;
+                    a[k][q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[p][k], a[q][k]);
+                    a[p][k] = c * apk - s * aqk;
+                    a[q][k] = s * apk + c * aqk;
+                }
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    ((0..n).map(|i| a[i][i]).collect(), v)
+}
+
+fn gaussian(rng: &mut StdRng) -> f64 {
+    // Box-Muller
+    let u1 = rng.gen::<f64>().max(f64::MIN_POSITIVE);
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
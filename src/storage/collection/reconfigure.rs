@@ -0,0 +1,101 @@
+// Runtime changes to an open collection's settings.
+// A config reload compares the settings a collection would open with before and after, setting by
+// setting. The ones read on every operation (search defaults, limits, validation, enrichment, execution
+// mode and the checkpoint policy) are applied to the live collection. The rest decide how its files, WAL,
+// index or caches were set up at open and take effect when it is next opened; an index or transform
+// change also needs an index rebuild then, since a saved index is loaded as it is.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::CollectionConfig;
+use super::storage::Collection;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConfigChanges {
+    pub applied: Vec<String>, // settings now in effect, e.g. "search.filter_overfetch"
+    pub needs_reopen: Vec<String>, // settings that take effect when the collection is next opened
+}
+
+impl ConfigChanges {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.needs_reopen.is_empty()
+    }
+}
+
+// Settings (or whole sections) that can change under a live collection
+const LIVE: &[&str] = &[
+    "search",
+    "limits",
+    "validation",
+    "enrichment",
+    "execution",
+    "wal.checkpoint_frequency",
+    "wal.checkpoint_interval_secs",
+    "wal.max_log_size",
+    "wal.sync_on_write",
+    "wal.backpressure",
+    "memory.max_memory_per_collection",
+    "memory.index_memory_budget",
+];
+
+fn under(path: &str, setting: &str) -> bool {
+    path == setting || path.strip_prefix(setting).is_some_and(|rest| rest.starts_with('.'))
+}
+
+// Dotted paths of the values that differ, down to the leaves both sides have as objects
+fn changed_paths(old: &Value, new: &Value, prefix: &str, out: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys().filter(|k| !a.contains_key(*k))).collect();
+            keys.sort();
+            for key in keys {
+                let path = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+                changed_paths(a.get(key).unwrap_or(&Value::Null), b.get(key).unwrap_or(&Value::Null), &path, out);
+            }
+        }
+        _ if old != new => out.push(prefix.to_string()),
+        _ => {}
+    }
+}
+
+pub(super) fn reconfigure(collection: &mut Collection, old: &CollectionConfig, new: &CollectionConfig) -> ConfigChanges {
+    let mut paths = Vec::new();
+    let (Ok(old_value), Ok(new_value)) = (serde_json::to_value(old), serde_json::to_value(new)) else {
+        return ConfigChanges::default();
+    };
+    changed_paths(&old_value, &new_value, "", &mut paths);
+
+    let mut changes = ConfigChanges::default();
+    for path in paths {
+        match LIVE.iter().find(|setting| under(&path, setting)) {
+            Some(setting) => {
+                apply(collection, setting, new);
+                changes.applied.push(path);
+            }
+            None => changes.needs_reopen.push(path),
+        }
+    }
+    changes
+}
+
+fn apply(collection: &mut Collection, setting: &str, new: &CollectionConfig) {
+    match setting {
+        "search" => super::tuning::set_base(collection, new.search),
+        "limits" => collection.config.limits = new.limits,
+        "validation" => collection.config.validation = new.validation,
+        "enrichment" => collection.config.enrichment = new.enrichment.clone(),
+        "execution" => collection.config.execution = new.execution,
+        "wal.checkpoint_frequency" => collection.config.wal.checkpoint_frequency = new.wal.checkpoint_frequency,
+        "wal.checkpoint_interval_secs" => collection.config.wal.checkpoint_interval_secs = new.wal.checkpoint_interval_secs,
+        "wal.max_log_size" => collection.config.wal.max_log_size = new.wal.max_log_size,
+        "wal.sync_on_write" => collection.config.wal.sync_on_write = new.wal.sync_on_write,
+        "wal.backpressure" => collection.config.wal.backpressure = new.wal.backpressure,
+        "memory.max_memory_per_collection" => collection.config.memory.max_memory_per_collection = new.memory.max_memory_per_collection,
+        "memory.index_memory_budget" => {
+            collection.config.memory.index_memory_budget = new.memory.index_memory_budget;
+            collection.apply_index_settings();
+        }
+        _ => {}
+    }
+}
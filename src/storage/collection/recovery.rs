@@ -0,0 +1,77 @@
+// Recovery from a vector index file that cannot be read.
+// At open the file is renamed to `<index file>.corrupt-<unix secs>` and the collection comes up on a
+// flat (exact) index over the vector cache, so searches stay correct while slower. The configured
+// index is then rebuilt from the data file under a shared lock, and swapped in under an exclusive one.
+// Until then the stand-in is never saved: a restart finds no index file and rebuilds as usual.
+
+use parking_lot::RwLock;
+use serde::Serialize;
+
+use crate::error::Result;
+use super::storage::Collection;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexRecovery {
+    pub error: String, // why the index file could not be read
+    pub quarantined_to: String, // where the unreadable file was moved
+    pub detected_at: u64, // unix secs
+}
+
+impl IndexRecovery {
+    // Shown in collection info and /api/readyz until the rebuild is done
+    pub fn warning(&self) -> String {
+        format!(
+            "Vector index file was corrupt ({}) and was moved to {}; searches use an exact scan until the index is rebuilt",
+            self.error, self.quarantined_to
+        )
+    }
+}
+
+pub(super) fn quarantine(path: &str, error: String) -> Result<IndexRecovery> {
+    let quarantined_to = crate::storage::persistence::quarantine_vector_index(path)?;
+    tracing::warn!(collection=%path, error=%error, quarantined_to=%quarantined_to, "vector_index_corrupt");
+    Ok(IndexRecovery {
+        error,
+        quarantined_to,
+        detected_at: crate::testing::clock::now_secs(),
+    })
+}
+
+// Point the stand-in flat index at everything in the vector cache
+pub(super) fn index_vector_cache(collection: &mut Collection) {
+    let mut index = collection.config.index.create_flat_index();
+    for (id, vector) in &collection.vector_cache {
+        index.insert(*id, vector, &collection.vector_cache);
+    }
+    collection.vector_index = index;
+}
+
+// Build the configured index while searches continue on the stand-in, then swap it in. Writes that
+// landed during the build are not in it, so then the index is rebuilt again under the exclusive lock.
+// Returns false when there was nothing to recover.
+pub fn recover_index(handle: &RwLock<Collection>) -> Result<bool> {
+    let (built, seq) = {
+        let collection = handle.read();
+        if collection.index_recovery.is_none() {
+            return Ok(false);
+        }
+        (collection.build_vector_index()?, collection.head_seq())
+    };
+
+    let mut collection = handle.write();
+    if collection.index_recovery.is_none() {
+        return Ok(false);
+    }
+    if collection.head_seq() == seq {
+        collection.vector_index = built;
+        collection.apply_index_settings();
+        collection.index_recovery = None;
+        collection.metadata.counters.record_rebuild();
+        super::persistence::save_vector_index(&collection)?;
+        super::persistence::save_metadata(&collection)?;
+    } else {
+        collection.rebuild_index()?;
+    }
+    tracing::info!(collection=%collection.path, index_type=%collection.vector_index.index_type(), "vector_index_recovered");
+    Ok(true)
+}
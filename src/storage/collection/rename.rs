@@ -0,0 +1,55 @@
+// Renaming a collection on disk: every file of the collection (the snapshot set plus retained WAL
+// history and a sealed WAL) moves from one base path to another, and the recorded name in the
+// metadata file follows. The caller holds the collection's write lock with the collection
+// checkpointed, and reopens it from the new path afterwards.
+//
+// Renames within a directory are atomic one by one, not as a set: when one fails, the files moved
+// so far are moved back, so the collection is left whole under one of its two names.
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::{Result, ServerError};
+use crate::storage::persistence::{load_metadata, save_metadata};
+use super::snapshot::{DISCARDED_ON_RESTORE, SNAPSHOT_FILES};
+
+pub fn rename_files(from_path: &str, to_path: &str) -> Result<()> {
+    let suffixes = SNAPSHOT_FILES.iter().chain(DISCARDED_ON_RESTORE);
+    if let Some(taken) = suffixes.clone().map(|s| format!("{to_path}{s}")).find(|p| Path::new(p).exists()) {
+        return Err(ServerError::AlreadyExists(format!("{taken} already exists")).into());
+    }
+
+    let mut moved = Vec::new();
+    let move_back = |moved: &[&&str]| {
+        for suffix in moved.iter().rev() {
+            let _ = fs::rename(format!("{to_path}{suffix}"), format!("{from_path}{suffix}"));
+        }
+    };
+    for suffix in suffixes {
+        let source = format!("{from_path}{suffix}");
+        if !Path::new(&source).exists() {
+            continue;
+        }
+        if let Err(e) = fs::rename(&source, format!("{to_path}{suffix}")) {
+            move_back(&moved);
+            return Err(e.into());
+        }
+        moved.push(suffix);
+    }
+
+    let name = Path::new(to_path).file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let renamed = load_metadata(to_path).and_then(|metadata| match metadata {
+        Some(mut metadata) => {
+            metadata.name = name.to_string();
+            save_metadata(to_path, &metadata)
+        }
+        None => Ok(()),
+    });
+    if renamed.is_err() {
+        move_back(&moved);
+        return renamed;
+    }
+    // The lock is not moved: the collection reopened from the new path takes its own
+    let _ = fs::remove_file(super::lock::get_lock_path(from_path));
+    Ok(())
+}
@@ -0,0 +1,410 @@
+// In-process read replicas for hot collections.
+// A replica is a read-only, in-memory copy of a collection's vector index plus the caches a search
+// reads (index-space vectors, metadata, document text). Searches are spread round-robin over the
+// replicas, each behind its own lock, so concurrent readers stop piling onto the collection's single
+// RwLock on many-core machines.
+//
+// Replicas follow the primary through its WAL sequence: every write the collection commits is
+// published to a shared feed as the WAL entry it logged (with its seq). A replica applies the entries
+// newer than its own applied seq the next time it is picked for a search, so a client that writes and
+// then searches sees its write. The feed is trimmed once every replica has applied an entry.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use parking_lot::{Mutex, RwLock, RwLockReadGuard};
+use uuid::Uuid;
+
+use crate::config::CollectionConfig;
+use crate::error::{Result, ServerError};
+use crate::index::VectorIndex;
+use crate::metadata::Metadata;
+use crate::metrics::Metric;
+use crate::quantization::QuantizedVector;
+use crate::search::{Hit, LatencyBudget, MetadataMap, SearchParams, SearchTarget, SelectivityTracker};
+use crate::storage::persistence::clone_vector_index;
+use crate::storage::wal::WalEntry;
+use super::projection::{index_space, Projection};
+use super::storage::Collection;
+
+// Committed WAL entries not yet applied by every replica
+#[derive(Default)]
+pub struct ReplicationFeed {
+    entries: Mutex<VecDeque<WalEntry>>,
+    last_seq: AtomicU64,
+}
+
+impl ReplicationFeed {
+    fn publish(&self, staged: Vec<WalEntry>) {
+        let Some(last) = staged.last().map(WalEntry::seq) else { return };
+        self.entries.lock().extend(staged);
+        self.last_seq.store(last, Ordering::Release);
+    }
+
+    fn since(&self, seq: u64) -> Vec<WalEntry> {
+        self.entries
+            .lock()
+            .iter()
+            .filter(|e| e.seq() > seq)
+            .cloned()
+            .collect()
+    }
+
+    fn trim(&self, applied: u64) {
+        let mut entries = self.entries.lock();
+        while entries.front().is_some_and(|e| e.seq() <= applied) {
+            entries.pop_front();
+        }
+    }
+
+    pub fn pending(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq.load(Ordering::Acquire)
+    }
+}
+
+// Primary side of replication: entries logged by the operation in flight are staged and only
+// published once it succeeds, so replicas never see a write the collection rejected.
+pub struct ReplicationSource {
+    feed: Arc<ReplicationFeed>,
+    staged: Vec<WalEntry>,
+}
+
+impl ReplicationSource {
+    pub(super) fn stage(&mut self, entry: &WalEntry) {
+        self.staged.push(entry.clone());
+    }
+
+    pub(super) fn finish(&mut self, committed: bool) {
+        let staged = std::mem::take(&mut self.staged);
+        if committed {
+            self.feed.publish(staged);
+        }
+    }
+}
+
+struct ReplicaDocument {
+    text: String,
+    // Stored vector, kept only when the index works on transformed or projected vectors; otherwise the
+    // index-space vector is the stored one
+    vector: Option<Vec<f32>>,
+}
+
+pub struct ReadReplica {
+    config: CollectionConfig,
+    projection: Option<Arc<Projection>>,
+    vector_index: Box<dyn VectorIndex>,
+    vectors: HashMap<Uuid, Vec<f32>>,
+    metadatas: HashMap<Uuid, Metadata>,
+    documents: HashMap<Uuid, ReplicaDocument>,
+    selectivity: SelectivityTracker,
+    latency_budget: LatencyBudget,
+    dimensions: Option<usize>,
+    applied_seq: u64,
+}
+
+impl ReadReplica {
+    // Snapshot of the collection as of its last logged WAL entry
+    fn snapshot(collection: &Collection) -> Self {
+        let transform = collection.config.transform;
+        let projection = collection.projection.clone();
+        let reduced = transform.is_active() || projection.is_some();
+        // HNSW keeps deleted ids in its graph, so their vectors stay with it, as in the collection
+        let mut vectors = collection.vector_cache.clone();
+        let data = collection.data();
+        let mut metadatas = data.metadata_cache.clone();
+        let mut documents = HashMap::with_capacity(data.len());
+        for id in data.index.keys() {
+            let Some(entry) = data.get(id) else { continue };
+            let stored = entry.get_vector();
+            vectors
+                .entry(*id)
+                .or_insert_with(|| index_space(&transform, projection.as_deref(), &stored).into_owned());
+            documents.insert(*id, ReplicaDocument {
+                text: entry.text,
+                vector: reduced.then_some(stored),
+            });
+            metadatas.insert(*id, entry.metadata);
+        }
+        drop(data);
+
+        Self {
+            config: collection.config.clone(),
+            projection,
+            vector_index: clone_vector_index(collection.vector_index()),
+            vectors,
+            metadatas,
+            documents,
+            selectivity: SelectivityTracker::new(),
+            latency_budget: LatencyBudget::new(),
+            dimensions: collection.metadata.dimensions,
+            applied_seq: collection.head_seq(),
+        }
+    }
+
+    fn duplicate(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            projection: self.projection.clone(),
+            vector_index: clone_vector_index(self.vector_index.as_ref()),
+            vectors: self.vectors.clone(),
+            metadatas: self.metadatas.clone(),
+            documents: self
+                .documents
+                .iter()
+                .map(|(id, doc)| (*id, ReplicaDocument { text: doc.text.clone(), vector: doc.vector.clone() }))
+                .collect(),
+            selectivity: SelectivityTracker::new(),
+            latency_budget: LatencyBudget::new(),
+            dimensions: self.dimensions,
+            applied_seq: self.applied_seq,
+        }
+    }
+
+    // Apply one WAL entry the same way WAL replay applies it to the collection
+    fn apply(&mut self, entry: WalEntry) {
+        let seq = entry.seq();
+        match entry {
+            WalEntry::Insert { id, vector, text, metadata, .. } => self.insert(id, vector, text, metadata),
+            WalEntry::Update { id, vector, text, metadata, .. } => {
+                self.remove(&id);
+                self.insert(id, vector, text, metadata);
+            }
+            WalEntry::Delete { id, .. } => self.remove(&id),
+            WalEntry::Checkpoint { .. } => {}
+        }
+        self.applied_seq = self.applied_seq.max(seq);
+    }
+
+    fn insert(&mut self, id: Uuid, vector: Vec<f32>, text: String, metadata: Metadata) {
+        if self.dimensions.is_some_and(|dims| dims != vector.len()) {
+            return;
+        }
+        self.dimensions = Some(vector.len());
+        // Search the vector the collection stores, not the exact one from the WAL
+        let stored = QuantizedVector::from_f32_with_config(&vector, &self.config.quantization).to_f32();
+        let index_vec = index_space(&self.config.transform, self.projection.as_deref(), &stored).into_owned();
+        self.vectors.insert(id, index_vec.clone());
+        self.metadatas.insert(id, metadata);
+        self.documents.insert(id, ReplicaDocument {
+            text,
+            vector: (self.config.transform.is_active() || self.projection.is_some()).then_some(stored),
+        });
+        self.vector_index.insert(id, &index_vec, &self.vectors);
+    }
+
+    fn remove(&mut self, id: &Uuid) {
+        self.documents.remove(id);
+        self.vector_index.remove(id);
+        if self.vector_index.index_type() != crate::index::IndexType::Hnsw {
+            self.vectors.remove(id);
+            self.metadatas.remove(id);
+        }
+    }
+
+    pub fn applied_seq(&self) -> u64 {
+        self.applied_seq
+    }
+
+    pub fn count(&self) -> usize {
+        self.documents.len()
+    }
+}
+
+impl SearchTarget for ReadReplica {
+    fn config(&self) -> &CollectionConfig {
+        &self.config
+    }
+
+    fn vector_index(&self) -> &dyn VectorIndex {
+        self.vector_index.as_ref()
+    }
+
+    fn selectivity(&self) -> &SelectivityTracker {
+        &self.selectivity
+    }
+
+    fn latency_budget(&self) -> &LatencyBudget {
+        &self.latency_budget
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        self.dimensions
+    }
+
+    fn vectors(&self) -> &HashMap<Uuid, Vec<f32>> {
+        &self.vectors
+    }
+
+    fn metadatas(&self) -> MetadataMap<'_> {
+        MetadataMap::Borrowed(&self.metadatas)
+    }
+
+    fn projection(&self) -> Option<&Projection> {
+        self.projection.as_deref()
+    }
+
+    fn document(&self, id: &Uuid) -> Option<(String, Vec<f32>, Metadata)> {
+        let doc = self.documents.get(id)?;
+        let vector = doc.vector.clone().or_else(|| self.vectors.get(id).cloned())?;
+        Some((doc.text.clone(), vector, self.metadatas.get(id).cloned().unwrap_or_default()))
+    }
+}
+
+struct ReplicaSlot {
+    replica: RwLock<ReadReplica>,
+    applied_seq: AtomicU64, // mirrors replica.applied_seq so lag checks do not take the lock
+}
+
+pub struct ReplicaSet {
+    slots: Vec<ReplicaSlot>,
+    next: AtomicUsize,
+    feed: Arc<ReplicationFeed>,
+}
+
+impl ReplicaSet {
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    // Writes published by the collection that at least one replica has not applied yet
+    pub fn pending(&self) -> usize {
+        self.feed.pending()
+    }
+
+    pub fn applied_seqs(&self) -> Vec<u64> {
+        self.slots.iter().map(|s| s.applied_seq.load(Ordering::Acquire)).collect()
+    }
+
+    // Next replica in round-robin order, brought up to date with the collection first
+    pub fn read(&self) -> RwLockReadGuard<'_, ReadReplica> {
+        let slot = &self.slots[self.next.fetch_add(1, Ordering::Relaxed) % self.slots.len()];
+        if self.feed.last_seq() > slot.applied_seq.load(Ordering::Acquire) {
+            self.catch_up(slot);
+        }
+        slot.replica.read()
+    }
+
+    fn catch_up(&self, slot: &ReplicaSlot) {
+        {
+            let mut replica = slot.replica.write();
+            for entry in self.feed.since(replica.applied_seq) {
+                replica.apply(entry);
+            }
+            slot.applied_seq.store(replica.applied_seq, Ordering::Release);
+        }
+        let applied = self.applied_seqs().into_iter().min().unwrap_or(0);
+        self.feed.trim(applied);
+    }
+
+    pub fn search(&self, query: &[f32], k: usize, metric: Metric, params: SearchParams) -> Vec<Hit> {
+        super::search::search_target(&*self.read(), query, k, metric, params)
+    }
+
+    pub fn search_batch(&self, queries: &[Vec<f32>], k: usize, metric: Metric) -> Vec<Vec<Hit>> {
+        super::search::search_batch_target(&*self.read(), queries, k, metric)
+    }
+}
+
+impl Collection {
+    // Start serving searches from `count` in-memory copies of this collection. Replaces any previous
+    // set; the old one stops receiving writes.
+    pub fn create_replicas(&mut self, count: usize) -> Result<Arc<ReplicaSet>> {
+        if count == 0 {
+            return Err(ServerError::InvalidRequest("Replica count must be >= 1".into()).into());
+        }
+        if self.two_stage.is_some() {
+            return Err(ServerError::InvalidRequest(
+                "Read replicas are not supported for two-stage collections".into(),
+            ).into());
+        }
+
+        let first = ReadReplica::snapshot(self);
+        let mut replicas: Vec<ReadReplica> = (1..count).map(|_| first.duplicate()).collect();
+        replicas.insert(0, first);
+
+        let feed = Arc::new(ReplicationFeed::default());
+        *self.replication.get_mut() = Some(ReplicationSource {
+            feed: feed.clone(),
+            staged: Vec::new(),
+        });
+
+        Ok(Arc::new(ReplicaSet {
+            slots: replicas
+                .into_iter()
+                .map(|replica| ReplicaSlot {
+                    applied_seq: AtomicU64::new(replica.applied_seq),
+                    replica: RwLock::new(replica),
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+            feed,
+        }))
+    }
+
+    // Stop publishing writes to the current replica set
+    pub fn drop_replicas(&mut self) {
+        *self.replication.get_mut() = None;
+    }
+
+    pub fn has_replicas(&self) -> bool {
+        self.replication.lock().is_some()
+    }
+
+    // Publish (or discard) the WAL entries staged by the operation that just finished
+    pub(super) fn finish_replication(&mut self, committed: bool) {
+        if let Some(source) = self.replication.get_mut().as_mut() {
+            source.finish(committed);
+        }
+    }
+}
+
+// Read access for a search: one of the collection's replicas when it has them, else the collection
+pub enum SearchGuard<'a> {
+    Primary(RwLockReadGuard<'a, Collection>),
+    Replica(RwLockReadGuard<'a, ReadReplica>),
+}
+
+impl<'a> SearchGuard<'a> {
+    pub fn acquire(collection: &'a RwLock<Collection>, replicas: Option<&'a ReplicaSet>) -> Self {
+        match replicas {
+            Some(set) if !set.is_empty() => SearchGuard::Replica(set.read()),
+            _ => SearchGuard::Primary(collection.read()),
+        }
+    }
+
+    // For a second search of a request that already holds a guard: a waiting writer does not hold it up
+    pub fn acquire_shared(collection: &'a RwLock<Collection>, replicas: Option<&'a ReplicaSet>) -> Self {
+        match replicas {
+            Some(set) if !set.is_empty() => SearchGuard::Replica(set.read()),
+            _ => SearchGuard::Primary(collection.read_recursive()),
+        }
+    }
+
+    // WAL sequence number of the last write the searched data includes
+    pub fn seq(&self) -> u64 {
+        match self {
+            SearchGuard::Primary(guard) => guard.head_seq(),
+            SearchGuard::Replica(guard) => guard.applied_seq(),
+        }
+    }
+}
+
+impl std::ops::Deref for SearchGuard<'_> {
+    type Target = dyn SearchTarget;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            SearchGuard::Primary(guard) => &**guard,
+            SearchGuard::Replica(guard) => &**guard,
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// Switching a collection's quantization level at runtime.
+// Documents keep the encoding they were written with, so a new `quantization.level` alone only reaches
+// new writes and leaves the collection mixed. `set_quantization` makes the level the collection's own
+// (stored in `.quantcfg.json`, which replaces the configured level when the collection opens, like
+// `.indexcfg.json` for the index type), and the requantize job then rewrites the older documents in
+// batches. A rewrite appends the document again with its id, version and metadata unchanged and only
+// its codes re-encoded; the vector index and vector caches are left alone, so searches carry on as
+// before while it runs.
+//
+// Vectors are re-encoded from the full-precision sidecar of a two-stage collection and from their
+// stored codes otherwise, so every switch costs the precision of the coarser of the two encodings.
+//
+// Rewrites are not logged to the WAL; they reach disk with the checkpoint the job ends with. Until
+// then the old versions stay in the data file, so a crash only puts documents back in their old
+// encoding, and a rerun skips the ones already in the target encoding.
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::{QuantizationConfig, QuantizationLevel};
+use crate::error::Result;
+use crate::quantization::QuantizedVector;
+use super::storage::Collection;
+
+// Documents read per batch while counting encodings
+const COMPOSITION_BATCH: usize = 1024;
+
+pub fn get_quantization_config_path(collection_path: &str) -> String {
+    format!("{}.quantcfg.json", collection_path)
+}
+
+// The quantization a previous switch chose, if any
+pub fn load_quantization_override(collection_path: &str) -> Result<Option<QuantizationConfig>> {
+    match fs::read(get_quantization_config_path(collection_path)) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save_quantization_override(collection_path: &str, quantization: &QuantizationConfig) -> Result<()> {
+    let path = get_quantization_config_path(collection_path);
+    let tmp = format!("{path}.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(quantization)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuantizationComposition {
+    pub level: QuantizationLevel, // what new writes are encoded with
+    pub encodings: BTreeMap<String, usize>, // documents per stored encoding, e.g. {"int8": 10, "pq8": 90}
+    pub pending: usize, // documents not in the level's encoding yet
+    pub mixed: bool, // more than one encoding is stored
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequantizeReport {
+    pub level: QuantizationLevel,
+    pub documents: usize, // documents looked at
+    pub reencoded: usize,
+}
+
+// Make `level` the collection's quantization for new writes and requantize batches. Returns the
+// level it replaces.
+pub(super) fn set_quantization(collection: &mut Collection, level: QuantizationLevel) -> Result<QuantizationLevel> {
+    super::pause::ensure_writable(collection)?;
+    let previous = collection.config.quantization;
+    let target = QuantizationConfig { level, ..previous };
+    if !collection.config.ephemeral {
+        save_quantization_override(&collection.path, &target)?;
+    }
+    collection.config.quantization = target;
+    if previous.level != level {
+        tracing::info!(collection=%collection.path, from=?previous.level, to=?level, "quantization_switched");
+    }
+    Ok(previous.level)
+}
+
+// Re-encode the documents of `ids` not in the collection's encoding yet; ids no longer stored are
+// skipped. Returns how many were rewritten.
+pub(super) fn requantize(collection: &mut Collection, ids: &[Uuid]) -> Result<usize> {
+    super::pause::ensure_writable(collection)?;
+    let mut reencoded = 0;
+    for doc in collection.get_many(ids).into_iter().flatten() {
+        if doc.vector.encoding() == QuantizedVector::encoding_for(&collection.config.quantization, doc.vector.dim()) {
+            continue;
+        }
+        let source = collection
+            .two_stage
+            .as_ref()
+            .and_then(|two_stage| two_stage.full.get(&doc.id))
+            .unwrap_or_else(|| doc.get_vector());
+        super::operations::reencode_internal(collection, doc, &source)?;
+        reencoded += 1;
+    }
+    Ok(reencoded)
+}
+
+pub(super) fn composition(collection: &Collection) -> QuantizationComposition {
+    let quantization = collection.config.quantization;
+    let mut encodings: BTreeMap<String, usize> = BTreeMap::new();
+    let mut pending = 0;
+    for chunk in collection.ids().chunks(COMPOSITION_BATCH) {
+        for doc in collection.get_many(chunk).into_iter().flatten() {
+            let encoding = doc.vector.encoding();
+            if encoding != QuantizedVector::encoding_for(&quantization, doc.vector.dim()) {
+                pending += 1;
+            }
+            *encodings.entry(encoding).or_default() += 1;
+        }
+    }
+    QuantizationComposition { level: quantization.level, mixed: encodings.len() > 1, encodings, pending }
+}
@@ -0,0 +1,220 @@
+// Reservoir sample of a collection's documents, kept up to date by its writes, so summary
+// statistics (norms, metadata cardinality) come back without reading every document.
+//
+// Inserts go through algorithm R against the live document count: while the reservoir has room a
+// document is always taken, afterwards it replaces a random entry with probability capacity / count.
+// Updates refresh a sampled document in place and deletes drop it. Deletes shrink the reservoir;
+// once it holds less than half of what it could, it is drawn again from the live documents. It is
+// also drawn when the collection opens, reading only the sampled documents.
+//
+// Metadata cardinality is the GEE estimate (Charikar et al., 2000) from the sample's value counts:
+// sqrt(count / sample) * (values seen once) + (values seen more than once). It is exact when the
+// sample holds every document.
+
+use std::collections::HashMap;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::metadata::{Metadata, MetadataValue};
+use super::storage::Collection;
+
+pub const DEFAULT_STATS_SAMPLE: usize = 1024;
+pub const MAX_TOP_VALUES: usize = 10;
+
+#[derive(Debug, Clone)]
+struct Sampled {
+    id: Uuid,
+    norm: f32,
+    metadata: Metadata,
+}
+
+#[derive(Debug)]
+pub struct StatsSampler {
+    capacity: usize,
+    entries: Vec<Sampled>,
+    slots: HashMap<Uuid, usize>, // id -> position in entries
+}
+
+impl Default for StatsSampler {
+    fn default() -> Self {
+        Self::new(DEFAULT_STATS_SAMPLE)
+    }
+}
+
+impl StatsSampler {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), entries: Vec::new(), slots: HashMap::new() }
+    }
+
+    // A document was added; `count` is the number of live documents including it
+    pub(super) fn insert(&mut self, id: Uuid, vector: &[f32], metadata: &Metadata, count: usize) {
+        if self.refresh(id, vector, metadata) {
+            return;
+        }
+        let entry = Sampled { id, norm: norm(vector), metadata: metadata.clone() };
+        if self.entries.len() < self.capacity {
+            self.slots.insert(id, self.entries.len());
+            self.entries.push(entry);
+            return;
+        }
+        let slot = rand::thread_rng().gen_range(0..count.max(1));
+        if slot < self.capacity {
+            self.slots.remove(&self.entries[slot].id);
+            self.slots.insert(id, slot);
+            self.entries[slot] = entry;
+        }
+    }
+
+    // A document changed; true when it is in the sample
+    pub(super) fn refresh(&mut self, id: Uuid, vector: &[f32], metadata: &Metadata) -> bool {
+        let Some(&slot) = self.slots.get(&id) else { return false };
+        self.entries[slot].norm = norm(vector);
+        self.entries[slot].metadata = metadata.clone();
+        true
+    }
+
+    pub(super) fn refresh_metadata(&mut self, id: &Uuid, metadata: &Metadata) {
+        if let Some(&slot) = self.slots.get(id) {
+            self.entries[slot].metadata = metadata.clone();
+        }
+    }
+
+    pub(super) fn remove(&mut self, id: &Uuid) {
+        let Some(slot) = self.slots.remove(id) else { return };
+        self.entries.swap_remove(slot);
+        if let Some(moved) = self.entries.get(slot) {
+            self.slots.insert(moved.id, slot);
+        }
+    }
+
+    // Deletes left the reservoir under half of what `count` documents could fill
+    pub(super) fn depleted(&self, count: usize) -> bool {
+        self.entries.len() * 2 < self.capacity.min(count)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Statistics of the sample, for a collection of `count` documents
+    pub fn stats(&self, count: usize, dimensions: Option<usize>) -> SampledStats {
+        let sample = self.entries.len();
+        let norms = if sample == 0 {
+            SampledNorms::default()
+        } else {
+            let n = sample as f64;
+            let mean = self.entries.iter().map(|e| e.norm as f64).sum::<f64>() / n;
+            let variance = self.entries.iter().map(|e| (e.norm as f64 - mean).powi(2)).sum::<f64>() / n;
+            SampledNorms {
+                min: self.entries.iter().map(|e| e.norm).fold(f32::INFINITY, f32::min),
+                max: self.entries.iter().map(|e| e.norm).fold(f32::NEG_INFINITY, f32::max),
+                mean: mean as f32,
+                std_dev: variance.sqrt() as f32,
+            }
+        };
+
+        // Per field: how often each value appears in the sample
+        let mut values: HashMap<&str, HashMap<String, (&MetadataValue, usize)>> = HashMap::new();
+        for entry in &self.entries {
+            for (field, value) in &entry.metadata {
+                let key = serde_json::to_string(value).unwrap_or_default();
+                values.entry(field).or_default().entry(key).or_insert((value, 0)).1 += 1;
+            }
+        }
+        let exact = sample >= count;
+        let scale = if sample > 0 { (count as f64 / sample as f64).sqrt() } else { 0.0 };
+        let mut fields: Vec<FieldStats> = values
+            .into_iter()
+            .map(|(field, counts)| {
+                let present: usize = counts.values().map(|(_, c)| c).sum();
+                let singletons = counts.values().filter(|(_, c)| *c == 1).count();
+                let estimated_distinct = if exact {
+                    counts.len()
+                } else {
+                    (scale * singletons as f64 + (counts.len() - singletons) as f64).round() as usize
+                };
+                let mut top: Vec<ValueShare> = counts
+                    .values()
+                    .map(|(value, c)| ValueShare { value: (*value).clone(), share: *c as f32 / sample as f32 })
+                    .collect();
+                top.sort_by(|a, b| b.share.total_cmp(&a.share));
+                top.truncate(MAX_TOP_VALUES);
+                FieldStats {
+                    field: field.to_string(),
+                    coverage: present as f32 / sample as f32,
+                    distinct_in_sample: counts.len(),
+                    estimated_distinct: estimated_distinct.min(count),
+                    top_values: top,
+                }
+            })
+            .collect();
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+
+        SampledStats { count, dimensions, sample, exact, norms, fields }
+    }
+}
+
+fn norm(vector: &[f32]) -> f32 {
+    vector.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SampledNorms {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub std_dev: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValueShare {
+    pub value: MetadataValue,
+    pub share: f32, // of the sampled documents
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldStats {
+    pub field: String,
+    pub coverage: f32, // share of the sampled documents that have the field
+    pub distinct_in_sample: usize,
+    pub estimated_distinct: usize,
+    pub top_values: Vec<ValueShare>, // most common first
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampledStats {
+    pub count: usize, // live documents, exact
+    pub dimensions: Option<usize>,
+    pub sample: usize, // documents the figures below come from
+    pub exact: bool, // the sample holds every document
+    pub norms: SampledNorms,
+    pub fields: Vec<FieldStats>, // by field name
+}
+
+/// Draw the reservoir again from the live documents, reading only the ones picked.
+pub(super) fn reseed(collection: &Collection) {
+    let ids = collection.ids();
+    let capacity = collection.sampler.lock().capacity;
+    let picked = rand::seq::index::sample(&mut rand::thread_rng(), ids.len(), capacity.min(ids.len()));
+    let mut sampler = StatsSampler::new(capacity);
+    {
+        let data = collection.data.read_recursive();
+        for i in picked {
+            let id = ids[i];
+            let Some(doc) = data.get(&id) else { continue };
+            let vector = collection.two_stage.as_ref().and_then(|t| t.full_precision(&id)).unwrap_or_else(|| doc.get_vector());
+            sampler.insert(id, &vector, &doc.metadata, ids.len());
+        }
+    }
+    *collection.sampler.lock() = sampler;
+}
@@ -1,5 +1,5 @@
 use crate::metrics::Metric;
-use crate::search::Hit;
+use crate::search::{EffectiveSearch, Hit, SearchTarget};
 use crate::storage::Collection;
 
 pub fn search(
@@ -7,30 +7,69 @@ pub fn search(
     query: &[f32],
     k: usize,
     metric: Metric,
-    mut params: crate::search::SearchParams,
+    params: crate::search::SearchParams,
 ) -> Vec<Hit> {
+    search_target(collection, query, k, metric, params)
+}
+
+pub fn search_batch(
+    collection: &Collection,
+    queries: &[Vec<f32>],
+    k: usize,
+    metric: Metric,
+) -> Vec<Vec<Hit>> {
+    search_batch_target(collection, queries, k, metric)
+}
+
+// Shared by the collection and its read replicas
+pub(super) fn search_target<T: SearchTarget + ?Sized>(
+    target: &T,
+    query: &[f32],
+    k: usize,
+    metric: Metric,
+    params: crate::search::SearchParams,
+) -> Vec<Hit> {
+    crate::search::search_target(target, query, k, metric, with_defaults(target, params))
+}
+
+pub fn search_within(
+    collection: &Collection,
+    query: &[f32],
+    k: usize,
+    metric: Metric,
+    params: crate::search::SearchParams,
+    target_ms: f32,
+) -> (Vec<Hit>, EffectiveSearch) {
+    crate::search::search_target_within(collection, query, k, metric, with_defaults(collection, params), target_ms)
+}
+
+fn with_defaults<'a, T: SearchTarget + ?Sized>(target: &T, mut params: crate::search::SearchParams<'a>) -> crate::search::SearchParams<'a> {
     // If the execution mode in the search parameters is set to Auto, we override it with the collection's configured execution mode. 
     if matches!(params.mode, crate::config::ExecutionMode::Auto) {
-        params.mode = collection.config().execution;
+        params.mode = target.config().execution;
     }
     // If the filter overfetch override is not set in the search parameters, we set it to the collection's configured filter overfetch value.
     if params.filter_overfetch_override.is_none() {
-        params.filter_overfetch_override = Some(collection.config.search.filter_overfetch);
+        params.filter_overfetch_override = Some(target.config().search.filter_overfetch);
     }
-    crate::search::search_collection(collection, query, k, metric, params)
+    params
 }
 
-pub fn search_batch(
-    collection: &Collection,
+pub(super) fn search_batch_target<T: SearchTarget + ?Sized>(
+    target: &T,
     queries: &[Vec<f32>],
     k: usize,
     metric: Metric,
 ) -> Vec<Vec<Hit>> {
     let params = crate::search::SearchParams {
-        mode: collection.config().execution,
+        mode: target.config().execution,
         filter: None,
         filter_overfetch_override: None,
         search_config_override: None,
+        dedup_by: None,
+        score_expr: None,
+        order_by: None,
+        exclude_ids: None,
     };
-    crate::search::search_batch_collection(collection, queries, k, metric, params)
+    crate::search::search_batch_target(target, queries, k, metric, params)
 }
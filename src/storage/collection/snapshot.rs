@@ -0,0 +1,211 @@
+// Named snapshots of a collection's on-disk state.
+// A snapshot is a directory holding a copy of every file of the collection (taken right after a
+// checkpoint, so the index files match the data file) and a manifest.json describing it:
+//
+// - manifest.json        {"format_version": 1, "name", "collection", "created_at", "vector_count",
+//                         "dimensions", "head_seq", "files": [{"suffix": ".index.db", "bytes": N}, ..]}
+// - collection.db<suffix> one per file, e.g. collection.db, collection.db.index.db, collection.db.wal.db
+//
+// Snapshots are written to a temporary directory and renamed into place, so a crash mid-copy never
+// leaves a half-written snapshot under its final name. Restoring is split in two steps: stage_restore
+// copies the files next to the target under a `.restore` suffix (slow, no lock needed), then
+// commit_restore renames them over the target files (fast, done while the caller holds the
+// collection's write lock and swaps in a freshly opened Collection).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, ServerError};
+use crate::storage::persistence::{load_metadata, save_metadata};
+use super::storage::Collection;
+
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+// Files that make up a collection, as suffixes of its base path. Retained WAL history (.wal.hist) is
+// not part of a snapshot: it describes versions the restored collection never had.
+pub(super) const SNAPSHOT_FILES: &[&str] = &["", ".index.db", ".vecindex.db", ".metadata.db", ".f32.db", ".vcol.db", ".vcol.ids", ".proj.db", ".tune.json", ".indexcfg.json", ".quantcfg.json", ".wal.db", ".wal.meta"];
+pub(super) const DISCARDED_ON_RESTORE: &[&str] = &[".wal.hist", ".wal.sealed", ".wal.segments", ".wal.archive"];
+
+const MANIFEST_FILE: &str = "manifest.json";
+const SNAPSHOT_BASE: &str = "collection.db";
+const STAGED_SUFFIX: &str = ".restore";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub format_version: u32,
+    pub name: String,
+    pub collection: String,
+    pub created_at: u64,
+    pub vector_count: usize,
+    pub dimensions: Option<usize>,
+    pub head_seq: u64,
+    pub files: Vec<SnapshotFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    pub suffix: String,
+    pub bytes: u64,
+}
+
+impl SnapshotManifest {
+    pub fn size_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.bytes).sum()
+    }
+}
+
+// Checkpoint the collection and copy its files into `dir` (which must not exist yet)
+pub fn create_snapshot(collection: &mut Collection, dir: &Path, name: &str) -> Result<SnapshotManifest> {
+    if collection.config.ephemeral {
+        return Err(ServerError::InvalidRequest("Snapshots are not available for ephemeral collections".into()).into());
+    }
+    if dir.exists() {
+        return Err(ServerError::AlreadyExists(format!("Snapshot '{}' already exists", name)).into());
+    }
+    collection.checkpoint()?;
+    collection.flush()?;
+
+    let staging = staging_dir(dir);
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir_all(&staging)?;
+
+    let copied = (|| -> Result<SnapshotManifest> {
+        let mut files = Vec::new();
+        for suffix in SNAPSHOT_FILES {
+            let source = format!("{}{}", collection.path, suffix);
+            if !Path::new(&source).exists() {
+                continue;
+            }
+            let bytes = fs::copy(&source, staging.join(format!("{SNAPSHOT_BASE}{suffix}")))?;
+            files.push(SnapshotFile { suffix: suffix.to_string(), bytes });
+        }
+
+        let metadata = collection.metadata();
+        let manifest = SnapshotManifest {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            name: name.to_string(),
+            collection: metadata.name.clone(),
+            created_at: crate::testing::clock::now_secs(),
+            vector_count: collection.count(),
+            dimensions: metadata.dimensions,
+            head_seq: collection.head_seq(),
+            files,
+        };
+        fs::write(staging.join(MANIFEST_FILE), serde_json::to_vec_pretty(&manifest)?)?;
+        Ok(manifest)
+    })();
+
+    match copied {
+        Ok(manifest) => {
+            fs::rename(&staging, dir)?;
+            Ok(manifest)
+        }
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
+            Err(e)
+        }
+    }
+}
+
+pub fn read_manifest(dir: &Path) -> Result<SnapshotManifest> {
+    let data = fs::read(dir.join(MANIFEST_FILE))
+        .map_err(|_| ServerError::NotFound(format!("Snapshot not found: {}", dir.display())))?;
+    let manifest: SnapshotManifest = serde_json::from_slice(&data)?;
+    if manifest.format_version != SNAPSHOT_FORMAT_VERSION {
+        return Err(ServerError::InvalidRequest(format!(
+            "Unsupported snapshot format version {} (expected {})",
+            manifest.format_version, SNAPSHOT_FORMAT_VERSION
+        )).into());
+    }
+    Ok(manifest)
+}
+
+// Snapshots found under `root`, oldest first. Unreadable entries (and in-progress copies) are skipped.
+pub fn list_snapshots(root: &Path) -> Result<Vec<SnapshotManifest>> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut manifests = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            if let Ok(manifest) = read_manifest(&path) {
+                manifests.push(manifest);
+            }
+        }
+    }
+    manifests.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.name.cmp(&b.name)));
+    Ok(manifests)
+}
+
+pub fn delete_snapshot(dir: &Path) -> Result<bool> {
+    if !dir.join(MANIFEST_FILE).exists() {
+        return Ok(false);
+    }
+    fs::remove_dir_all(dir)?;
+    Ok(true)
+}
+
+// Copy the snapshot files next to `target_path` under a staging suffix, checking their sizes
+// against the manifest. Nothing the target collection reads is touched yet.
+pub fn stage_restore(dir: &Path, target_path: &str) -> Result<SnapshotManifest> {
+    let manifest = read_manifest(dir)?;
+    for file in &manifest.files {
+        let source = dir.join(format!("{SNAPSHOT_BASE}{}", file.suffix));
+        let staged = format!("{}{}{}", target_path, file.suffix, STAGED_SUFFIX);
+        let bytes = fs::copy(&source, &staged)?;
+        if bytes != file.bytes {
+            discard_restore(&manifest, target_path);
+            return Err(ServerError::Internal(format!(
+                "Snapshot file {} is {} bytes, manifest says {}",
+                source.display(), bytes, file.bytes
+            )).into());
+        }
+    }
+    Ok(manifest)
+}
+
+// Move staged files over the target's files and remove target files the snapshot does not have.
+// Renames are cheap, so this is the only part that needs the collection to be locked.
+pub fn commit_restore(manifest: &SnapshotManifest, target_path: &str) -> Result<()> {
+    for file in &manifest.files {
+        let target = format!("{}{}", target_path, file.suffix);
+        fs::rename(format!("{target}{STAGED_SUFFIX}"), &target)?;
+    }
+    for suffix in SNAPSHOT_FILES.iter().chain(DISCARDED_ON_RESTORE) {
+        if !manifest.files.iter().any(|f| f.suffix == *suffix) {
+            let target = PathBuf::from(format!("{}{}", target_path, suffix));
+            if target.is_dir() {
+                fs::remove_dir_all(&target)?;
+            } else if target.exists() {
+                fs::remove_file(&target)?;
+            }
+        }
+    }
+
+    // A snapshot restored under another name still carries the source collection's name
+    let name = Path::new(target_path).file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    if let Some(mut metadata) = load_metadata(target_path)? {
+        if metadata.name != name {
+            metadata.name = name.to_string();
+            save_metadata(target_path, &metadata)?;
+        }
+    }
+    Ok(())
+}
+
+// Remove staged files after a failed restore
+pub fn discard_restore(manifest: &SnapshotManifest, target_path: &str) {
+    for file in &manifest.files {
+        let _ = fs::remove_file(format!("{}{}{}", target_path, file.suffix, STAGED_SUFFIX));
+    }
+}
+
+fn staging_dir(dir: &Path) -> PathBuf {
+    let name = dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    dir.with_file_name(format!(".{name}.tmp"))
+}
@@ -0,0 +1,253 @@
+// Aggregate statistics of the stored vectors, for spotting embedding drift: compare a collection
+// before and after a model change, or two document sets through a metadata filter.
+//
+// One pass over the documents (the exact vector when two-stage search keeps one, the stored one
+// otherwise) accumulates per-dimension mean and variance (Welford), the centroid of the
+// unit-length vectors and every vector's L2 norm, from which the norm histogram is built. The
+// centroid is a direction: its length (`centroid_norm`) is near 1 when the vectors point the same
+// way and falls towards 0 as they spread out.
+//
+// The intrinsic dimensionality is the TwoNN estimate (Facco et al., 2017) on an evenly spread
+// sample: for each point, the ratio of the Euclidean distances to its second and first nearest
+// neighbours within the sample; the estimate is n / sum(ln ratio). Points with a duplicate at
+// distance 0 are left out. Neighbours are found by brute force, so the cost is quadratic in the
+// sample size.
+
+use std::collections::BTreeMap;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{Result, StorageError};
+use crate::search::query::Filter;
+use super::storage::Collection;
+
+pub const DEFAULT_HISTOGRAM_BINS: usize = 20;
+pub const DEFAULT_INTRINSIC_SAMPLE: usize = 1000;
+
+const READ_BATCH: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct StatsOptions {
+    pub filter: Option<Filter>, // only documents whose metadata matches
+    pub histogram_bins: usize,
+    pub intrinsic_sample: usize, // 0 skips the intrinsic dimensionality estimate
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        Self { filter: None, histogram_bins: DEFAULT_HISTOGRAM_BINS, intrinsic_sample: DEFAULT_INTRINSIC_SAMPLE }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistogramBin {
+    pub lower: f32,
+    pub upper: f32,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NormStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub std_dev: f32,
+    pub histogram: Vec<HistogramBin>, // equal-width bins from min to max; the last one includes max
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntrinsicDimension {
+    pub estimate: f32,
+    pub method: String,
+    pub sample: usize, // points the estimate used
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorStats {
+    pub count: usize,
+    pub dimensions: usize,
+    pub centroid: Vec<f32>, // mean of the unit-length vectors
+    pub centroid_norm: f32,
+    pub mean: Vec<f32>,
+    pub variance: Vec<f32>, // population variance per dimension
+    pub total_variance: f32, // sum of the per-dimension variances
+    pub norms: NormStats,
+    pub intrinsic_dimension: Option<IntrinsicDimension>, // None when skipped or the sample has too few distinct points
+}
+
+// Running sums for the single pass
+struct Moments {
+    count: usize,
+    mean: Vec<f64>,
+    m2: Vec<f64>,
+    unit_sum: Vec<f64>,
+    norms: Vec<f32>,
+}
+
+impl Moments {
+    fn new(dimensions: usize) -> Self {
+        Self { count: 0, mean: vec![0.0; dimensions], m2: vec![0.0; dimensions], unit_sum: vec![0.0; dimensions], norms: Vec::new() }
+    }
+
+    fn add(&mut self, vector: &[f32]) {
+        self.count += 1;
+        let n = self.count as f64;
+        let mut norm_sq = 0.0f64;
+        for (d, &x) in vector.iter().enumerate() {
+            let x = x as f64;
+            norm_sq += x * x;
+            let delta = x - self.mean[d];
+            self.mean[d] += delta / n;
+            self.m2[d] += delta * (x - self.mean[d]);
+        }
+        let norm = norm_sq.sqrt();
+        if norm > 0.0 {
+            for (sum, &x) in self.unit_sum.iter_mut().zip(vector) {
+                *sum += x as f64 / norm;
+            }
+        }
+        self.norms.push(norm as f32);
+    }
+}
+
+/// Statistics of the vectors of every document matching `opts.filter`.
+pub fn vector_stats(collection: &Collection, opts: &StatsOptions) -> Result<VectorStats> {
+    let dimensions = collection.metadata.dimensions.unwrap_or(0);
+    let mut ids = collection.ids();
+    if let Some(filter) = &opts.filter {
+        let metadata = collection.metadata_view();
+        ids.retain(|id| metadata.get(id).is_some_and(|m| filter.matches(m)));
+    }
+    let step = (ids.len() / opts.intrinsic_sample.max(1)).max(1);
+
+    let mut moments = Moments::new(dimensions);
+    let mut sample = Vec::with_capacity(opts.intrinsic_sample.min(ids.len()));
+    for (batch, chunk) in ids.chunks(READ_BATCH).enumerate() {
+        let data = collection.data.read_recursive();
+        for (i, id) in chunk.iter().enumerate() {
+            let Some(vector) = stored_vector(collection, &data, id) else { continue };
+            if vector.len() != dimensions {
+                return Err(StorageError::InvalidDimension { expected: dimensions, actual: vector.len() }.into());
+            }
+            moments.add(&vector);
+            if (batch * READ_BATCH + i) % step == 0 && sample.len() < opts.intrinsic_sample {
+                sample.push(vector);
+            }
+        }
+    }
+
+    let count = moments.count;
+    let variance: Vec<f32> = moments.m2.iter().map(|m2| if count > 0 { (m2 / count as f64) as f32 } else { 0.0 }).collect();
+    let centroid: Vec<f32> = moments.unit_sum.iter().map(|s| if count > 0 { (s / count as f64) as f32 } else { 0.0 }).collect();
+    Ok(VectorStats {
+        count,
+        dimensions,
+        centroid_norm: centroid.iter().map(|x| x * x).sum::<f32>().sqrt(),
+        centroid,
+        mean: moments.mean.iter().map(|&m| m as f32).collect(),
+        total_variance: variance.iter().sum(),
+        variance,
+        norms: norm_stats(&moments.norms, opts.histogram_bins),
+        intrinsic_dimension: crate::parallel::maintenance(|| two_nn(&sample)),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DimensionCount {
+    pub dimensions: usize,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DimensionReport {
+    pub expected: Option<usize>, // dimensions the collection records; None while it is empty
+    pub distribution: Vec<DimensionCount>, // documents per vector length, most common first
+    pub mismatched: usize, // documents whose vector is not `expected` long
+}
+
+/// How many documents hold vectors of each length. Writes are checked against the recorded
+/// dimensions, so anything but a single entry points at data written before that check (or by hand).
+pub fn dimension_report(collection: &Collection) -> DimensionReport {
+    let expected = collection.metadata.dimensions;
+    let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+    for chunk in collection.ids().chunks(READ_BATCH) {
+        let data = collection.data.read_recursive();
+        for id in chunk {
+            if let Some(vector) = stored_vector(collection, &data, id) {
+                *counts.entry(vector.len()).or_default() += 1;
+            }
+        }
+    }
+    let mismatched = counts.iter().filter(|(d, _)| Some(**d) != expected).map(|(_, c)| c).sum();
+    let mut distribution: Vec<DimensionCount> = counts.into_iter().map(|(dimensions, count)| DimensionCount { dimensions, count }).collect();
+    distribution.sort_by(|a, b| b.count.cmp(&a.count).then(a.dimensions.cmp(&b.dimensions)));
+    DimensionReport { expected, distribution, mismatched }
+}
+
+fn stored_vector(collection: &Collection, data: &super::data::DataStore, id: &Uuid) -> Option<Vec<f32>> {
+    let exact = collection.two_stage.as_ref().and_then(|t| t.full_precision(id));
+    exact.or_else(|| data.get(id).map(|doc| doc.get_vector()))
+}
+
+fn norm_stats(norms: &[f32], bins: usize) -> NormStats {
+    if norms.is_empty() {
+        return NormStats::default();
+    }
+    let min = norms.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = norms.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let n = norms.len() as f64;
+    let mean = norms.iter().map(|&x| x as f64).sum::<f64>() / n;
+    let variance = norms.iter().map(|&x| (x as f64 - mean).powi(2)).sum::<f64>() / n;
+
+    // All norms equal: one bin holding everything
+    let bins = if max > min { bins.max(1) } else { 1 };
+    let width = (max - min) / bins as f32;
+    let mut counts = vec![0usize; bins];
+    for &x in norms {
+        let bin = if width > 0.0 { ((x - min) / width) as usize } else { 0 };
+        counts[bin.min(bins - 1)] += 1;
+    }
+    let histogram = counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| HistogramBin {
+            lower: min + width * i as f32,
+            upper: if i + 1 == bins { max } else { min + width * (i + 1) as f32 },
+            count,
+        })
+        .collect();
+    NormStats { min, max, mean: mean as f32, std_dev: variance.sqrt() as f32, histogram }
+}
+
+fn two_nn(sample: &[Vec<f32>]) -> Option<IntrinsicDimension> {
+    if sample.len() < 3 {
+        return None;
+    }
+    let log_ratios: Vec<f64> = (0..sample.len())
+        .into_par_iter()
+        .filter_map(|i| {
+            let (mut first, mut second) = (f32::INFINITY, f32::INFINITY);
+            for (j, other) in sample.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                let d = sample[i].iter().zip(other).map(|(a, b)| (a - b) * (a - b)).sum::<f32>();
+                if d < first {
+                    second = first;
+                    first = d;
+                } else if d < second {
+                    second = d;
+                }
+            }
+            // Squared distances, hence the half
+            (first > 0.0 && second.is_finite()).then(|| 0.5 * (second as f64 / first as f64).ln())
+        })
+        .collect();
+    let sum: f64 = log_ratios.iter().sum();
+    if log_ratios.len() < 3 || sum <= 0.0 {
+        return None;
+    }
+    Some(IntrinsicDimension { estimate: (log_ratios.len() as f64 / sum) as f32, method: "two_nn".to_string(), sample: log_ratios.len() })
+}
@@ -1,50 +1,64 @@
 // Core Collection storage structure
 // Manages the memory-mapped file, in-memory index, vector index, and caches for vectors and metadata.
-use memmap2::MmapMut;
+// The document store (data file, pointers, metadata) sits behind its own latch and the WAL behind a
+// mutex, so metadata-only writes and checkpoints can run while the collection is only read-locked.
+use parking_lot::{MappedRwLockReadGuard, Mutex, RwLock, RwLockReadGuard};
 use std::collections::HashMap;
-use std::fs::File;
 use uuid::Uuid;
 
 use crate::error::Result;
 use crate::index::VectorIndex;
-use crate::storage::persistence::{EntryPointer, warm_mmap, warm_file, get_wal_path, save_vector_index};
+use crate::storage::persistence::{EntryPointer, warm_mmap, warm_file, get_wal_path};
 use crate::storage::metadata::CollectionMetadata;
 use super::persistence::PersistenceService;
 use super::cache;
 
 pub struct Collection {
-    pub(super) data_file: File,
-    pub(super) mmap: Option<MmapMut>,
-    pub(super) index: HashMap<Uuid, EntryPointer>,
+    pub(super) data: RwLock<super::data::DataStore>, // data file, id -> pointer index, metadata and client ids (the data latch)
     pub(super) vector_index: Box<dyn VectorIndex>,
     pub(super) vector_cache: HashMap<Uuid, Vec<f32>>,
-    pub(super) metadata_cache: HashMap<Uuid, crate::metadata::Metadata>,
     pub config: crate::config::CollectionConfig,
     pub metadata: CollectionMetadata,
     pub path: String,
-    pub persistence: PersistenceService,
+    pub persistence: Mutex<PersistenceService>, // WAL latch: logged to by metadata-only writes under a shared collection lock
+    pub(super) shared_writes: Mutex<()>, // orders the writes that run under a shared collection lock (metadata updates, checkpoints)
+    pub(super) selectivity: crate::search::SelectivityTracker,
+    pub(super) latency_budget: crate::search::LatencyBudget, // latency per unit of ef/nprobe, for latency-target searches
+    pub(super) two_stage: Option<super::two_stage::TwoStageState>, // present when two-stage search is enabled
+    pub(super) column: Option<super::column::VectorColumn>, // present when the vector column layout is enabled
+    pub(super) projection: Option<std::sync::Arc<super::projection::Projection>>, // present once a projection has been trained
+    pub(super) tuning: Option<super::tuning::TuningPreset>, // present once a tuning recommendation has been kept
+    pub(super) base_search: crate::config::SearchConfig, // the configured search settings, before the tuning recommendation
+    pub(super) replication: Mutex<Option<super::replica::ReplicationSource>>, // present while read replicas follow this collection
+    pub(super) checkpoint_lock: std::sync::Arc<Mutex<()>>, // held from capturing a checkpoint until its files are written
+    pub(super) saved: std::sync::Arc<super::persistence::SavedFiles>, // direct saves per file, checked by a checkpoint being written out
+    pub(super) background_checkpoint: Mutex<Option<crate::scheduler::Task<()>>>, // the checkpoint being written out after a tracked operation
+    pub(super) checkpoint_progress: std::sync::Arc<super::persistence::CheckpointProgress>, // WAL position and timing of checkpoints, for write backpressure
+    pub(super) index_recovery: Option<super::recovery::IndexRecovery>, // set while a corrupt index file is rebuilt
+    pub(super) sampler: Mutex<super::sampler::StatsSampler>, // reservoir sample kept by writes, for statistics without a scan
+    pub(super) write_pause: Option<super::pause::WritePause>, // set while an operator holds writes (see pause.rs)
+    pub(super) enrichment_stages: Vec<std::sync::Arc<dyn crate::storage::EnrichmentStage>>, // library stages run after the configured ones
+    pub(super) _file_lock: Option<std::sync::Arc<super::lock::CollectionLock>>, // held while open, released last (none when ephemeral)
 }
 
-impl Collection {
-    pub(super) fn init_rayon_pool(config: &crate::config::ParallelismConfig) {
-        let num_threads = config.num_threads();
-        if num_threads > 0 {
-            rayon::ThreadPoolBuilder::new()
-                .num_threads(num_threads)
-                .build_global()
-                .ok();
+// A checkpoint still being written out finishes before the collection goes away, so reopening it
+// sees complete files
+impl Drop for Collection {
+    fn drop(&mut self) {
+        if let Some(task) = self.background_checkpoint.get_mut().take() {
+            let _ = task.join();
         }
     }
+}
 
-    // Track operations to trigger checkpoints based on WAL config
-    pub(super) fn track_operation(&mut self) -> Result<()> {
-        let interval_due = if let Some(last) = self.persistence.last_checkpoint() {
+impl Collection {
+    // Track operations to trigger checkpoints based on WAL config. The caller holds the collection
+    // exclusively or the shared-writes lock, and neither the data latch nor the WAL latch.
+    pub(super) fn track_operation(&self) -> Result<()> {
+        let mut persistence = self.persistence.lock();
+        let interval_due = if let Some(last) = persistence.last_checkpoint() {
             if let Some(interval) = self.config.wal.checkpoint_interval_secs {
-                let now = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                now.saturating_sub(last) >= interval
+                crate::testing::clock::now_secs().saturating_sub(last) >= interval
             } else {
                 false
             }
@@ -52,9 +66,10 @@ impl Collection {
             false
         };
 
-        if self.persistence.should_checkpoint(&self.config.wal) || interval_due {
-            super::persistence::checkpoint(self)?;
-            self.persistence.reset_counter();
+        if persistence.should_checkpoint(&self.config.wal) || interval_due {
+            drop(persistence);
+            super::persistence::checkpoint_in_background(self)?;
+            self.persistence.lock().reset_counter();
         }
         Ok(())
     }
@@ -64,18 +79,28 @@ impl Collection {
     }
 
     pub fn count(&self) -> usize {
-        self.index.len()
+        self.data.read_recursive().len()
+    }
+
+    // The document store, shared with searches and metadata-only writes
+    pub fn data(&self) -> RwLockReadGuard<'_, super::data::DataStore> {
+        self.data.read_recursive()
+    }
+
+    pub fn last_checkpoint(&self) -> Option<u64> {
+        self.persistence.lock().last_checkpoint()
     }
     
     pub fn memory_usage_bytes(&self) -> usize {
         // Calculate memory usage by summing the sizes of the memory-mapped file, index, vector cache, metadata cache, and vector index.
-        let mmap_size = self.mmap.as_ref().map(|m| m.len()).unwrap_or(0); // Size of the memory-mapped file
-        let index_size = self.index.capacity() * std::mem::size_of::<(Uuid, EntryPointer)>(); // Approximate size of the index based on its capacity
+        let data = self.data.read_recursive();
+        let mmap_size = data.mmap.as_ref().map(|m| m.len()).unwrap_or(0); // Size of the memory-mapped file
+        let index_size = data.index.capacity() * std::mem::size_of::<(Uuid, EntryPointer)>(); // Approximate size of the index based on its capacity
 
-        let vector_cache_size = self.vector_cache.iter()
-            .map(|(_, vec)| std::mem::size_of::<Uuid>() + vec.len() * std::mem::size_of::<f32>())
+        let vector_cache_size = self.vector_cache.values()
+            .map(|vec| std::mem::size_of::<Uuid>() + vec.len() * std::mem::size_of::<f32>())
             .sum::<usize>(); // Size of the vector cache based on the number of entries and their lengths
-        let metadata_cache_size = self.metadata_cache.len() * std::mem::size_of::<(Uuid, crate::metadata::Metadata)>(); // Approximate size of the metadata cache based on its capacity
+        let metadata_cache_size = data.metadata_cache.len() * std::mem::size_of::<(Uuid, crate::metadata::Metadata)>(); // Approximate size of the metadata cache based on its capacity
         
         
         mmap_size + index_size + vector_cache_size + metadata_cache_size + self.vector_index.stats().memory_usage_bytes
@@ -86,23 +111,26 @@ impl Collection {
     }
 
     pub fn cache_usage_bytes(&self) -> usize {
-        let vector_cache_size = self.vector_cache.iter()
-            .map(|(_, vec)| std::mem::size_of::<Uuid>() + vec.len() * std::mem::size_of::<f32>())
+        let vector_cache_size = self.vector_cache.values()
+            .map(|vec| std::mem::size_of::<Uuid>() + vec.len() * std::mem::size_of::<f32>())
             .sum::<usize>();
-        let metadata_cache_size = self.metadata_cache.len() * std::mem::size_of::<(Uuid, crate::metadata::Metadata)>();
+        let metadata_cache_size = self.data.read_recursive().metadata_cache.len() * std::mem::size_of::<(Uuid, crate::metadata::Metadata)>();
         vector_cache_size + metadata_cache_size
     }
 
     pub fn clear_caches(&mut self) {
         self.vector_cache.clear();
-        self.metadata_cache.clear();
+        self.data.get_mut().clear_metadata();
     }
 
     /// Fault frequently used files into the page cache to reduce cold-start latency.
     pub fn warm_page_cache(&self) {
-        if let Some(mmap) = self.mmap.as_ref() {
+        if let Some(mmap) = self.data.read_recursive().mmap.as_ref() {
             warm_mmap(mmap);
         }
+        if self.config.ephemeral {
+            return;
+        }
         let base = self.path.clone();
         let _ = warm_file(&format!("{}.vecindex.db", base));
         let _ = warm_file(&format!("{}.index.db", base));
@@ -113,22 +141,98 @@ impl Collection {
         &self.vector_cache
     }
 
-    pub fn metadata_view(&self) -> &HashMap<Uuid, crate::metadata::Metadata> {
-        &self.metadata_cache
+    pub fn metadata_view(&self) -> MappedRwLockReadGuard<'_, HashMap<Uuid, crate::metadata::Metadata>> {
+        RwLockReadGuard::map(self.data.read_recursive(), |data| &data.metadata_cache)
+    }
+
+    // Documents matching `filter` from the metadata index; None when no index covers it
+    pub fn filter_matches(&self, filter: &crate::search::Filter, max_ids: usize) -> Option<crate::search::query::FilterMatches> {
+        self.data.read_recursive().filter_matches(filter, max_ids)
+    }
+
+    // Observed filter selectivity, used to auto-tune filter overfetch at search time
+    pub fn selectivity(&self) -> &crate::search::SelectivityTracker {
+        &self.selectivity
+    }
+
+    // Observed search latency per unit of effort, used to pick ef/nprobe for latency-target searches
+    pub fn latency_budget(&self) -> &crate::search::LatencyBudget {
+        &self.latency_budget
+    }
+
+    // Resolve an id given by a client: a document Uuid, or otherwise a client-provided id
+    pub fn resolve_id(&self, id: &str) -> Option<Uuid> {
+        let data = self.data.read_recursive();
+        if let Ok(uuid) = Uuid::parse_str(id) {
+            if data.index.contains_key(&uuid) {
+                return Some(uuid);
+            }
+        }
+        data.external_ids.get(id).copied()
+    }
+
+    pub fn two_stage(&self) -> Option<&super::two_stage::TwoStageState> {
+        self.two_stage.as_ref()
+    }
+
+    // The column is only handed out while it holds every live document; otherwise scans use the cache
+    pub fn vector_column(&self) -> Option<&super::column::VectorColumn> {
+        self.column.as_ref().filter(|column| column.len() == self.count())
+    }
+
+    pub fn projection(&self) -> Option<&super::projection::Projection> {
+        self.projection.as_deref()
+    }
+
+    pub fn tuning(&self) -> Option<&super::tuning::TuningPreset> {
+        self.tuning.as_ref()
+    }
+
+    // Vector as the index sees it (transform, then projection)
+    pub fn index_vector<'a>(&self, vector: &'a [f32]) -> std::borrow::Cow<'a, [f32]> {
+        super::projection::index_space(&self.config.transform, self.projection(), vector)
+    }
+
+    pub fn external_ids_view(&self) -> MappedRwLockReadGuard<'_, HashMap<String, Uuid>> {
+        RwLockReadGuard::map(self.data.read_recursive(), |data| &data.external_ids)
+    }
+
+    // Check an embedding model (and the dimensions it produced, once known) against the one recorded for this collection
+    pub fn check_embedding_model(&self, model: &str, dimensions: Option<usize>) -> Result<()> {
+        self.metadata
+            .check_embedding_model(model, dimensions)
+            .map_err(|e| crate::error::ServerError::InvalidRequest(e).into())
+    }
+
+    // Remember the model behind the first server-side embedding; later ones are checked against it
+    pub fn record_embedding_model(&mut self, model: &str, dimensions: usize) -> Result<()> {
+        if self.metadata.set_embedding_model(model, dimensions) {
+            super::persistence::save_metadata(self)?;
+        }
+        Ok(())
+    }
+
+    // Record a new model once every document has been re-embedded with it
+    pub fn replace_embedding_model(&mut self, model: &str, dimensions: usize) -> Result<()> {
+        super::pause::ensure_writable(self)?;
+        self.metadata.embedding_model = None;
+        self.record_embedding_model(model, dimensions)
     }
 
     pub fn config(&self) -> &crate::config::CollectionConfig {
         &self.config
     }
 
+    // Every document id, sorted; a stable order to walk the collection in batches
+    pub fn ids(&self) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = self.data.read_recursive().index.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
     pub fn get_all(&self) -> Vec<crate::storage::document::Document> {
-        let mut all_entries = Vec::new();
-        for (id, _) in &self.index {
-            if let Some(entry) = super::operations::get(self, id) {
-                all_entries.push(entry);
-            }
-        }
-        all_entries
+        let data = self.data.read_recursive();
+        data.index.keys().filter_map(|id| data.get(id)).collect()
     }
 
     pub(super) fn rebuild_vector_cache(&mut self) {
@@ -140,46 +244,101 @@ impl Collection {
         cache::ensure_consistent(self);
     }
 
+    // Hand memory.index_memory_budget to the vector index, which spills what does not fit next to
+    // the collection files (an ephemeral collection has nowhere to spill to), and the deterministic
+    // setting, which a loaded index was saved with as it was then
+    pub(super) fn apply_index_settings(&mut self) {
+        self.vector_index.set_deterministic(self.config.deterministic);
+        if !self.config.ephemeral {
+            self.vector_index.set_memory_budget(self.config.memory.index_memory_budget, &self.path);
+        }
+    }
+
     /// Rebuild the vector index from on-disk data and persist it.
     pub fn rebuild_index(&mut self) -> Result<()> {
-        // Collect all vectors from storage
+        super::pause::ensure_not_paused(self)?;
+        let new_index = self.build_vector_index()?;
+
+        // Swap and persist
+        self.vector_index = new_index;
+        self.apply_index_settings();
+        self.rebuild_vector_cache();
+        self.index_recovery = None;
+        self.metadata.counters.record_rebuild();
+        super::persistence::save_vector_index(self)?;
+        super::persistence::save_metadata(self)?;
+        Ok(())
+    }
+
+    // A new vector index of the configured kind over the stored vectors. Reads only, so it can be
+    // built under a shared lock while searches continue.
+    pub(super) fn build_vector_index(&self) -> Result<Box<dyn VectorIndex>> {
+        self.build_index_of(&self.config.index)
+    }
+
+    // The same, of the given kind (an index migration builds the one it switches to)
+    pub(super) fn build_index_of(&self, index: &crate::index::IndexConfig) -> Result<Box<dyn VectorIndex>> {
+        let mut new_index = index.create_index(self.data.read_recursive().len());
+        new_index.set_deterministic(self.config.deterministic);
+
+        // Built on the maintenance pool, away from the threads searches run on
+        crate::parallel::maintenance(|| -> Result<()> {
+            // With the column present, flat/IVF build in one pass over it and nothing else is read
+            if let Some(column) = self.vector_column() {
+                if !new_index.build_from_column(column.view()) {
+                    let vectors: HashMap<Uuid, Vec<f32>> = column.view().rows().map(|(id, v)| (id, v.to_vec())).collect();
+                    for (id, vec) in in_id_order(&vectors) {
+                        new_index.insert(*id, vec, &vectors);
+                    }
+                }
+            } else {
+                let vectors = self.vectors_from_documents()?;
+                for (id, vec) in in_id_order(&vectors) {
+                    new_index.insert(*id, vec, &vectors);
+                }
+            }
+            Ok(())
+        })?;
+        Ok(new_index)
+    }
+
+    // Every live vector, decoded from the documents in the data file (after the transform)
+    fn vectors_from_documents(&self) -> Result<HashMap<Uuid, Vec<f32>>> {
         let mut vectors: HashMap<Uuid, Vec<f32>> = HashMap::new();
 
-        if let Some(mmap) = self.mmap.as_ref() {
-            for (id, pointer) in &self.index {
+        let data = self.data.read_recursive();
+        if let Some(mmap) = data.mmap.as_ref() {
+            for (id, pointer) in &data.index {
                 let offset = pointer.offset as usize;
                 let length = pointer.length as usize;
                 if offset + length <= mmap.len() {
                     let bytes = &mmap[offset..offset + length];
                     if let Ok(entry) = bincode::deserialize::<crate::storage::document::Document>(bytes) {
-                        vectors.insert(*id, entry.get_vector());
+                        vectors.insert(*id, self.index_vector(&entry.get_vector()).into_owned());
                     }
                 }
             }
-        } else {
+        } else if let Some(file) = data.data_file.as_ref() {
             // Fallback: read directly from file if mmap disabled.
             use std::io::{Read, Seek, SeekFrom};
-            let mut file = self.data_file.try_clone()?;
-            for (id, pointer) in &self.index {
+            let mut file = file.try_clone()?;
+            for (id, pointer) in &data.index {
                 let mut buf = vec![0u8; pointer.length as usize];
                 file.seek(SeekFrom::Start(pointer.offset))?;
                 file.read_exact(&mut buf)?;
                 if let Ok(entry) = bincode::deserialize::<crate::storage::document::Document>(&buf) {
-                    vectors.insert(*id, entry.get_vector());
+                    vectors.insert(*id, self.index_vector(&entry.get_vector()).into_owned());
                 }
             }
         }
-
-        // Build fresh index
-        let mut new_index = self.config.index.create_index(self.index.len());
-        for (id, vec) in &vectors {
-            new_index.insert(*id, vec, &vectors);
-        }
-
-        // Swap and persist
-        self.vector_index = new_index;
-        self.rebuild_vector_cache();
-        save_vector_index(self.path.as_str(), self.vector_index())?;
-        Ok(())
+        Ok(vectors)
     }
 }
+
+// Entries sorted by id. Indexes are rebuilt in this order rather than the map's, so a deterministic
+// collection gets the same graph or clusters back on every rebuild.
+pub(super) fn in_id_order<V>(map: &HashMap<Uuid, V>) -> Vec<(&Uuid, &V)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_unstable_by_key(|(id, _)| **id);
+    entries
+}
@@ -0,0 +1,357 @@
+// Search parameter tuning from sample queries.
+// A sweep runs the sample queries through the index at increasing ef (HNSW) or nprobe (IVF) and
+// compares each result list with the exact top-k, giving a recall/latency curve. The recommendation
+// is the cheapest value that reaches the target recall; when none does, the value with the best
+// recall is recommended and the report says the target was missed.
+//
+// Ground truth is either given with the queries or computed here by scoring every stored document on
+// its full vector, the same scores the search path re-ranks with. Without queries, an evenly spread
+// sample of the stored vectors is used.
+//
+// A recommendation that is kept becomes the collection's default for that parameter: it is layered on
+// the configured search settings when the collection opens, and request parameters still override it.
+// It is stored in `.tune.json` and is part of snapshots.
+//
+// Index diagnostics reuse the sampling and ground truth to estimate the recall of the collection's
+// current search settings, next to the HNSW graph's connectivity.
+
+use std::collections::HashSet;
+use std::fs;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::SearchConfig;
+use crate::error::{Result, ServerError};
+use crate::index::{HnswDiagnostics, IndexDetails, IndexStats, IndexType};
+use crate::metrics::Metric;
+use crate::search::SearchParams;
+use super::storage::Collection;
+
+pub const DEFAULT_TUNING_SAMPLE: usize = 100;
+pub const DEFAULT_TARGET_RECALL: f32 = 0.95;
+
+const MAX_EF: usize = 1024;
+const MAX_NPROBE: usize = 256;
+
+pub fn get_tuning_path(collection_path: &str) -> String {
+    format!("{}.tune.json", collection_path)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TunedParam {
+    Ef,
+    Nprobe,
+}
+
+impl TunedParam {
+    fn set(self, search: &mut SearchConfig, value: usize) {
+        match self {
+            TunedParam::Ef => search.ef = Some(value),
+            TunedParam::Nprobe => search.nprobe = Some(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TuneOptions {
+    pub queries: Vec<Vec<f32>>, // empty: sample stored vectors
+    pub ground_truth: Option<Vec<Vec<Uuid>>>, // one id list per query, best first; computed when absent
+    pub sample_size: usize,
+    pub k: usize,
+    pub target_recall: f32,
+    pub values: Option<Vec<usize>>, // the ef/nprobe values to try; a doubling sweep when absent
+    pub metric: Option<Metric>, // defaults to the index's metric
+}
+
+impl Default for TuneOptions {
+    fn default() -> Self {
+        Self {
+            queries: Vec::new(),
+            ground_truth: None,
+            sample_size: DEFAULT_TUNING_SAMPLE,
+            k: 10,
+            target_recall: DEFAULT_TARGET_RECALL,
+            values: None,
+            metric: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TuningPoint {
+    pub value: usize,
+    pub recall: f32,
+    pub mean_latency_us: u64,
+    pub p95_latency_us: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TuningPreset {
+    pub param: TunedParam,
+    pub value: usize,
+    pub recall: f32,
+    pub target_recall: f32,
+    pub k: usize,
+    pub queries: usize,
+    pub tuned_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TuningReport {
+    pub param: TunedParam,
+    pub curve: Vec<TuningPoint>,
+    pub recommended: TuningPreset,
+    pub met_target: bool,
+}
+
+impl TuningPreset {
+    pub fn apply(&self, search: &mut SearchConfig) {
+        self.param.set(search, self.value);
+    }
+
+    pub fn load(collection_path: &str) -> Result<Option<Self>> {
+        match fs::read(get_tuning_path(collection_path)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, collection_path: &str) -> Result<()> {
+        let path = get_tuning_path(collection_path);
+        let tmp = format!("{path}.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+// Run the sweep. Only reads the collection, so a server can tune under a shared lock.
+pub(super) fn tune(collection: &Collection, opts: TuneOptions) -> Result<TuningReport> {
+    if collection.two_stage.is_some() {
+        return Err(ServerError::InvalidRequest(
+            "Two-stage collections scan quantized codes; there is no index parameter to tune".into(),
+        ).into());
+    }
+    let stats = collection.vector_index().stats();
+    let (param, limit) = match (stats.index_type, &stats.details) {
+        (IndexType::Hnsw, _) => (TunedParam::Ef, MAX_EF),
+        (IndexType::Ivf, IndexDetails::Ivf { num_clusters, .. }) if *num_clusters > 0 => {
+            (TunedParam::Nprobe, (*num_clusters).min(MAX_NPROBE))
+        }
+        (IndexType::Ivf, _) => {
+            return Err(ServerError::InvalidRequest("The IVF index has no clusters yet; insert vectors before tuning".into()).into());
+        }
+        (IndexType::Flat, _) => {
+            return Err(ServerError::InvalidRequest("Flat indexes are exhaustive; there is nothing to tune".into()).into());
+        }
+    };
+    if opts.k == 0 {
+        return Err(ServerError::InvalidRequest("k must be > 0".into()).into());
+    }
+    if !(opts.target_recall > 0.0 && opts.target_recall <= 1.0) {
+        return Err(ServerError::InvalidRequest("target_recall must be in (0, 1]".into()).into());
+    }
+
+    let metric = opts.metric.unwrap_or_else(|| collection.vector_index().metric());
+    let queries = if opts.queries.is_empty() {
+        sample_queries(collection, opts.sample_size)
+    } else {
+        opts.queries
+    };
+    if queries.is_empty() {
+        return Err(ServerError::InvalidRequest("The collection is empty; there is nothing to tune against".into()).into());
+    }
+    let truth = match opts.ground_truth {
+        Some(truth) if truth.len() != queries.len() => {
+            return Err(ServerError::InvalidRequest(format!(
+                "ground_truth has {} entries for {} queries", truth.len(), queries.len()
+            )).into());
+        }
+        Some(truth) => truth,
+        None => exact_top_k(collection, &queries, opts.k, metric),
+    };
+
+    let mut values = match opts.values {
+        Some(values) => values.into_iter().filter(|v| *v > 0).collect(),
+        None => default_values(param, opts.k, limit),
+    };
+    values.sort_unstable();
+    values.dedup();
+    if values.is_empty() {
+        return Err(ServerError::InvalidRequest("values must contain at least one value > 0".into()).into());
+    }
+
+    let curve: Vec<TuningPoint> = values
+        .into_iter()
+        .map(|value| measure(collection, &queries, &truth, opts.k, metric, param, value))
+        .collect();
+    let reached = curve.iter().find(|point| point.recall >= opts.target_recall);
+    let best = reached.unwrap_or_else(|| {
+        curve
+            .iter()
+            .max_by(|a, b| a.recall.partial_cmp(&b.recall).unwrap_or(std::cmp::Ordering::Equal).then(b.value.cmp(&a.value)))
+            .expect("curve is not empty")
+    });
+
+    Ok(TuningReport {
+        param,
+        met_target: reached.is_some(),
+        recommended: TuningPreset {
+            param,
+            value: best.value,
+            recall: best.recall,
+            target_recall: opts.target_recall,
+            k: opts.k,
+            queries: queries.len(),
+            tuned_at: crate::testing::clock::now_secs(),
+        },
+        curve,
+    })
+}
+
+// Index stats, with HNSW diagnostics filled in. Other index types come back as they are.
+pub(super) fn diagnose(collection: &Collection, sample_size: usize, k: usize) -> Result<IndexStats> {
+    if sample_size == 0 || k == 0 {
+        return Err(ServerError::InvalidRequest("sample_size and k must be > 0".into()).into());
+    }
+    let index = collection.vector_index();
+    let mut stats = index.stats();
+    let (IndexDetails::Hnsw { diagnostics, .. }, Some(connectivity)) = (&mut stats.details, index.connectivity()) else {
+        return Ok(stats);
+    };
+
+    let metric = index.metric();
+    let queries = sample_queries(collection, sample_size);
+    let truth = exact_top_k(collection, &queries, k, metric);
+    *diagnostics = Some(HnswDiagnostics {
+        components: connectivity.components,
+        unreachable: connectivity.unreachable,
+        recall_estimate: recall(collection, &queries, &truth, k, metric),
+        recall_k: k,
+        recall_queries: queries.len(),
+    });
+    Ok(stats)
+}
+
+// Replace the configured search settings a kept recommendation applies on top of
+pub(super) fn set_base(collection: &mut Collection, search: SearchConfig) {
+    collection.base_search = search;
+    collection.config.search = search;
+    if let Some(preset) = &collection.tuning {
+        preset.apply(&mut collection.config.search);
+    }
+}
+
+// Keep (or drop) a recommendation: it becomes the default on top of the configured search settings
+pub(super) fn set(collection: &mut Collection, preset: Option<TuningPreset>) -> Result<bool> {
+    let had = collection.tuning.is_some();
+    if !collection.config.ephemeral {
+        match &preset {
+            Some(preset) => preset.save(&collection.path)?,
+            None => match fs::remove_file(get_tuning_path(&collection.path)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            },
+        }
+    }
+    collection.config.search = collection.base_search;
+    if let Some(preset) = &preset {
+        preset.apply(&mut collection.config.search);
+    }
+    collection.tuning = preset;
+    Ok(had)
+}
+
+fn default_values(param: TunedParam, k: usize, limit: usize) -> Vec<usize> {
+    let start = match param {
+        TunedParam::Ef => k.max(10),
+        TunedParam::Nprobe => 1,
+    };
+    let mut values = Vec::new();
+    let mut value = start;
+    while value < limit {
+        values.push(value);
+        value *= 2;
+    }
+    values.push(limit.max(start));
+    values
+}
+
+// Recall@k of searches with the current search settings against `truth`; None when nothing is expected
+pub(super) fn recall(collection: &Collection, queries: &[Vec<f32>], truth: &[Vec<Uuid>], k: usize, metric: Metric) -> Option<f32> {
+    let (mut found, mut expected) = (0usize, 0usize);
+    for (query, truth) in queries.iter().zip(truth) {
+        let hits = collection.search(query, k, metric, SearchParams::default());
+        let wanted: HashSet<&Uuid> = truth.iter().take(k).collect();
+        expected += wanted.len();
+        found += hits.iter().filter(|hit| wanted.contains(&hit.id)).count();
+    }
+    (expected > 0).then(|| found as f32 / expected as f32)
+}
+
+pub(super) fn sample_queries(collection: &Collection, sample_size: usize) -> Vec<Vec<f32>> {
+    let ids = collection.ids();
+    let step = (ids.len() / sample_size.max(1)).max(1);
+    ids.iter()
+        .step_by(step)
+        .take(sample_size)
+        .filter_map(|id| collection.get(id))
+        .map(|doc| doc.get_vector())
+        .collect()
+}
+
+pub(super) fn exact_top_k(collection: &Collection, queries: &[Vec<f32>], k: usize, metric: Metric) -> Vec<Vec<Uuid>> {
+    let mode = collection.config.execution;
+    let documents: Vec<(Uuid, Vec<f32>)> = collection.get_all().into_iter().map(|doc| (doc.id, doc.get_vector())).collect();
+    queries
+        .iter()
+        .map(|query| {
+            let mut scored: Vec<(f32, Uuid)> = documents
+                .iter()
+                .filter(|(_, vector)| vector.len() == query.len())
+                .map(|(id, vector)| (metric.calculate(query, vector, mode), *id))
+                .collect();
+            scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+            scored.into_iter().take(k).map(|(_, id)| id).collect()
+        })
+        .collect()
+}
+
+fn measure(
+    collection: &Collection,
+    queries: &[Vec<f32>],
+    truth: &[Vec<Uuid>],
+    k: usize,
+    metric: Metric,
+    param: TunedParam,
+    value: usize,
+) -> TuningPoint {
+    let mut search = collection.base_search;
+    param.set(&mut search, value);
+    let params = SearchParams { search_config_override: Some(search), ..Default::default() };
+
+    let mut latencies = Vec::with_capacity(queries.len());
+    let mut found = 0usize;
+    let mut expected = 0usize;
+    for (query, truth) in queries.iter().zip(truth) {
+        let start = Instant::now();
+        let hits = collection.search(query, k, metric, params);
+        latencies.push(start.elapsed().as_micros() as u64);
+        let wanted: HashSet<&Uuid> = truth.iter().take(k).collect();
+        expected += wanted.len();
+        found += hits.iter().filter(|hit| wanted.contains(&hit.id)).count();
+    }
+    latencies.sort_unstable();
+    let p95 = latencies[((latencies.len() * 95).div_ceil(100)).saturating_sub(1)];
+    TuningPoint {
+        value,
+        recall: if expected == 0 { 1.0 } else { found as f32 / expected as f32 },
+        mean_latency_us: latencies.iter().sum::<u64>() / latencies.len() as u64,
+        p95_latency_us: p95,
+    }
+}
@@ -0,0 +1,171 @@
+// State for two-stage search: the quantized codes scanned in stage 1 and the full-precision
+// vectors used to re-rank in stage 2.
+//
+// The data file only holds quantized vectors, so full-precision copies go to a `.f32.db` sidecar:
+// a u32 dimension header followed by fixed-size records of [16-byte uuid][dims x f32 LE].
+// Records are append-only and the last record for an id wins; stale records of deleted documents
+// are dropped when the collection is compacted. Opening the file rebuilds the id -> offset map
+// with one sequential scan, so there is no separate index file to keep in sync.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+
+use memmap2::{Mmap, MmapOptions};
+use uuid::Uuid;
+
+use crate::error::{Result, ServerError};
+use crate::quantization::QuantizedVector;
+
+const HEADER_LEN: u64 = 4;
+
+pub fn get_full_precision_path(collection_path: &str) -> String {
+    format!("{}.f32.db", collection_path)
+}
+
+pub struct FullPrecisionStore {
+    file: File,
+    mmap: Option<Mmap>,
+    dims: usize,
+    offsets: HashMap<Uuid, u64>,
+}
+
+impl FullPrecisionStore {
+    pub fn open(collection_path: &str) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(get_full_precision_path(collection_path))?;
+        let mut store = Self {
+            file,
+            mmap: None,
+            dims: 0,
+            offsets: HashMap::new(),
+        };
+        store.remap()?;
+        store.scan();
+        Ok(store)
+    }
+
+    fn record_len(&self) -> u64 {
+        16 + 4 * self.dims as u64
+    }
+
+    fn remap(&mut self) -> Result<()> {
+        self.mmap = None;
+        if self.file.metadata()?.len() > 0 {
+            self.mmap = Some(unsafe { MmapOptions::new().map(&self.file)? });
+        }
+        Ok(())
+    }
+
+    fn scan(&mut self) {
+        let Some(mmap) = self.mmap.as_ref() else { return };
+        if mmap.len() < HEADER_LEN as usize {
+            return;
+        }
+        self.dims = u32::from_le_bytes([mmap[0], mmap[1], mmap[2], mmap[3]]) as usize;
+        let record_len = self.record_len();
+        let mut offset = HEADER_LEN;
+        // A torn trailing record (crash mid-append) is ignored; the WAL replay rewrites it
+        while offset + record_len <= mmap.len() as u64 {
+            let start = offset as usize;
+            if let Ok(id) = Uuid::from_slice(&mmap[start..start + 16]) {
+                self.offsets.insert(id, offset);
+            }
+            offset += record_len;
+        }
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<Vec<f32>> {
+        let offset = *self.offsets.get(id)? as usize + 16;
+        let mmap = self.mmap.as_ref()?;
+        let bytes = mmap.get(offset..offset + 4 * self.dims)?;
+        Some(
+            bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+        )
+    }
+
+    pub fn contains(&self, id: &Uuid) -> bool {
+        self.offsets.contains_key(id)
+    }
+
+    pub fn append(&mut self, id: Uuid, vector: &[f32]) -> Result<()> {
+        self.append_many(std::iter::once((id, vector)))
+    }
+
+    // Append several records and remap once
+    pub fn append_many<'a>(&mut self, records: impl IntoIterator<Item = (Uuid, &'a [f32])>) -> Result<()> {
+        let mut pos = self.file.seek(SeekFrom::End(0))?;
+        let mut buf = Vec::new();
+        let mut written = Vec::new();
+        for (id, vector) in records {
+            // The first record of an empty file fixes the dimensions and writes the header
+            if pos == 0 {
+                self.dims = vector.len();
+                buf.extend_from_slice(&(self.dims as u32).to_le_bytes());
+                pos = HEADER_LEN;
+            }
+            if vector.len() != self.dims {
+                return Err(ServerError::InvalidRequest(format!(
+                    "Full-precision store expects {} dimensions, got {}",
+                    self.dims,
+                    vector.len()
+                )).into());
+            }
+            buf.extend_from_slice(id.as_bytes());
+            for v in vector {
+                buf.extend_from_slice(&v.to_le_bytes());
+            }
+            written.push((id, pos));
+            pos += self.record_len();
+        }
+        if buf.is_empty() {
+            return Ok(());
+        }
+        self.file.write_all(&buf)?;
+        self.offsets.extend(written);
+        self.remap()
+    }
+
+    // Drop every record; used by compaction before the live documents are written back
+    pub fn reset(&mut self) -> Result<()> {
+        self.mmap = None;
+        self.file.set_len(0)?;
+        self.dims = 0;
+        self.offsets.clear();
+        Ok(())
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        self.file.sync_data()?;
+        Ok(())
+    }
+}
+
+pub struct TwoStageState {
+    pub(super) codes: HashMap<Uuid, QuantizedVector>, // stage 1: compact codes scanned per query
+    pub(super) full: FullPrecisionStore,               // stage 2: exact re-rank
+}
+
+impl TwoStageState {
+    pub fn open(collection_path: &str) -> Result<Self> {
+        Ok(Self {
+            codes: HashMap::new(),
+            full: FullPrecisionStore::open(collection_path)?,
+        })
+    }
+
+    pub fn codes(&self) -> &HashMap<Uuid, QuantizedVector> {
+        &self.codes
+    }
+
+    pub fn full_precision(&self, id: &Uuid) -> Option<Vec<f32>> {
+        self.full.get(id)
+    }
+}
@@ -0,0 +1,191 @@
+// Consistency check and repair (fsck) for a collection.
+// Every entry pointer is checked against the data file: it has to lie within it, match its checksum
+// (whatever `memory.checksums` says for ordinary reads) and decode to the document it is keyed by. The vector index is then compared with the pointers (nodes for documents
+// that no longer exist, documents the index does not hold), and the recorded vector count and the
+// metadata cache with the pointer count.
+//
+// Repair drops the pointers that cannot be read or fail their checksum, removes orphan index nodes, indexes the missing
+// documents, rebuilds the caches from the pointers and rewrites the pointer, vector index and
+// metadata files. A document whose pointer cannot be read is lost from the data file; the WAL is left
+// alone, so one it still holds an entry for comes back when the collection is next opened.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::index::{IndexDetails, IndexType};
+use super::storage::Collection;
+
+// Ids listed per kind of problem; the counts are always complete
+pub const MAX_REPORTED_IDS: usize = 100;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IssueList {
+    pub count: usize,
+    pub ids: Vec<Uuid>, // the first MAX_REPORTED_IDS, sorted
+}
+
+impl IssueList {
+    fn from_ids(mut ids: Vec<Uuid>) -> Self {
+        ids.sort_unstable();
+        let count = ids.len();
+        ids.truncate(MAX_REPORTED_IDS);
+        Self { count, ids }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub documents: usize, // entry pointers checked
+    pub out_of_bounds: IssueList, // pointers reaching past the end of the data file
+    #[serde(default)]
+    pub checksum_mismatches: IssueList, // entries whose bytes no longer match the checksum in their pointer
+    pub unreadable: IssueList, // pointers whose bytes do not decode to the document they are keyed by
+    pub orphan_index_nodes: IssueList, // index nodes without a document
+    pub missing_from_index: IssueList, // documents the vector index does not hold
+    pub missing_metadata: IssueList, // readable documents without a metadata cache entry
+    pub recorded_count: usize, // vector count in the collection metadata
+    pub repaired: bool,
+}
+
+impl VerifyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.out_of_bounds.is_empty()
+            && self.checksum_mismatches.is_empty()
+            && self.unreadable.is_empty()
+            && self.orphan_index_nodes.is_empty()
+            && self.missing_from_index.is_empty()
+            && self.missing_metadata.is_empty()
+            && self.recorded_count == self.documents
+    }
+}
+
+// The check alone. Only reads the collection, so a server can run it under a shared lock.
+pub(super) fn verify(collection: &Collection) -> Result<VerifyReport> {
+    let data = collection.data.read_recursive();
+    let file_len = match data.mmap.as_ref() {
+        Some(mmap) => mmap.len() as u64,
+        None => data.file_len()?,
+    };
+
+    let mut out_of_bounds = Vec::new();
+    let mut checksum_mismatches = Vec::new();
+    let mut unreadable = Vec::new();
+    let mut missing_metadata = Vec::new();
+    for (id, pointer) in &data.index {
+        if pointer.offset.saturating_add(pointer.length as u64) > file_len {
+            out_of_bounds.push(*id);
+            continue;
+        }
+        if data.checksum_matches(id) == Some(false) {
+            checksum_mismatches.push(*id);
+            continue;
+        }
+        match data.get(id) {
+            Some(doc) if doc.id == *id => {
+                if !data.metadata_cache.contains_key(id) {
+                    missing_metadata.push(*id);
+                }
+            }
+            _ => unreadable.push(*id),
+        }
+    }
+
+    let indexed: HashSet<Uuid> = collection.vector_index.ids().into_iter().collect();
+    let orphan_index_nodes = indexed.iter().filter(|id| !data.index.contains_key(id)).copied().collect();
+    let missing_from_index = if indexes_every_document(collection) {
+        data.index.keys().filter(|id| !indexed.contains(id)).copied().collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(VerifyReport {
+        documents: data.len(),
+        out_of_bounds: IssueList::from_ids(out_of_bounds),
+        checksum_mismatches: IssueList::from_ids(checksum_mismatches),
+        unreadable: IssueList::from_ids(unreadable),
+        orphan_index_nodes: IssueList::from_ids(orphan_index_nodes),
+        missing_from_index: IssueList::from_ids(missing_from_index),
+        missing_metadata: IssueList::from_ids(missing_metadata),
+        recorded_count: collection.metadata.vector_count,
+        repaired: false,
+    })
+}
+
+// Check, then fix what was found. The report describes the collection as it was before the repair.
+pub(super) fn repair(collection: &mut Collection) -> Result<VerifyReport> {
+    let mut report = verify(collection)?;
+    if report.is_consistent() {
+        return Ok(report);
+    }
+
+    // The full lists, not the truncated ones in the report
+    let data = collection.data.get_mut();
+    let file_len = match data.mmap.as_ref() {
+        Some(mmap) => mmap.len() as u64,
+        None => data.file_len()?,
+    };
+    let broken: Vec<Uuid> = data.index.iter()
+        .filter(|(id, pointer)| {
+            pointer.offset.saturating_add(pointer.length as u64) > file_len
+                || data.checksum_matches(id) == Some(false)
+                || data.get(id).is_none_or(|doc| doc.id != **id)
+        })
+        .map(|(id, _)| *id)
+        .collect();
+    for id in &broken {
+        data.index.remove(id);
+    }
+
+    // Caches, client ids, two-stage codes, the vector column and the statistics sample follow the remaining pointers
+    collection.rebuild_vector_cache();
+    super::sampler::reseed(collection);
+
+    let live: HashSet<Uuid> = collection.data.get_mut().index.keys().copied().collect();
+    for id in collection.vector_index.ids() {
+        if !live.contains(&id) {
+            collection.vector_index.remove(&id);
+        }
+    }
+    if indexes_every_document(collection) {
+        let indexed: HashSet<Uuid> = collection.vector_index.ids().into_iter().collect();
+        let mut missing: Vec<&Uuid> = live.iter().filter(|id| !indexed.contains(id)).collect();
+        missing.sort_unstable();
+        for id in missing {
+            if let Some(vector) = collection.vector_cache.get(id) {
+                collection.vector_index.insert(*id, vector, &collection.vector_cache);
+            }
+        }
+    }
+    collection.metadata.update_vector_count(live.len());
+
+    super::persistence::save_index(collection)?;
+    super::persistence::save_vector_index(collection)?;
+    super::persistence::save_metadata(collection)?;
+    tracing::warn!(
+        collection=%collection.path,
+        dropped_pointers=broken.len(),
+        orphan_index_nodes=report.orphan_index_nodes.count,
+        missing_from_index=report.missing_from_index.count,
+        "collection_repaired"
+    );
+
+    report.repaired = true;
+    Ok(report)
+}
+
+// An IVF index only assigns documents once its clusters are built; until then searches scan every
+// cached vector, so no document is missing from it
+fn indexes_every_document(collection: &Collection) -> bool {
+    let stats = collection.vector_index.stats();
+    !matches!(
+        (stats.index_type, stats.details),
+        (IndexType::Ivf, IndexDetails::Ivf { centroids_computed: false, .. })
+    )
+}
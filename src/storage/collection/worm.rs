@@ -0,0 +1,113 @@
+// Write-once (WORM) collections: sealing makes a collection read-only for good, for datasets that
+// must not change after they are published. Sealing compacts the data file and cuts it back to the
+// last document, records `sealed_at` in the metadata, and drops the WAL along with its retained
+// history; the last sequence number stays in the WAL meta, so head_seq holds across reopens. After
+// that every insert, update, delete, compaction, projection and import fails with COLLECTION_SEALED.
+// An HNSW graph is written in its packed layout (see index/hnsw/packed.rs); an index config given
+// to `seal` replaces the configured one first, e.g. to rebuild a growing collection's HNSW as IVF.
+// A rebuild of the index (after corruption, or on request) is still allowed; it changes no documents.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::config::WalConfig;
+use crate::error::{Result, ServerError};
+use crate::index::IndexConfig;
+use crate::storage::persistence::get_wal_path;
+use crate::storage::wal::{get_history_dir, get_sealed_path, get_segments_dir, Wal};
+use super::storage::Collection;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SealReport {
+    pub sealed_at: u64,
+    pub documents: usize,
+    pub index_type: String,
+    pub data_bytes_before: u64,
+    pub data_bytes: u64,
+    pub wal_bytes_dropped: u64,
+    pub index_bytes: u64,
+    pub already_sealed: bool,
+}
+
+// Seal the collection; sealing one that already is only reports on it
+pub fn seal(collection: &mut Collection, index: Option<IndexConfig>) -> Result<SealReport> {
+    if let Some(sealed_at) = collection.metadata.sealed_at {
+        let data_bytes = collection.data.get_mut().file_len()?;
+        return Ok(report(collection, sealed_at, data_bytes, 0, true));
+    }
+    if collection.config.ephemeral {
+        return Err(ServerError::InvalidRequest("Ephemeral collections cannot be sealed".into()).into());
+    }
+    if collection.index_recovery.is_some() {
+        return Err(ServerError::Conflict("The vector index is being rebuilt; seal once it has recovered".into()).into());
+    }
+
+    let data_bytes_before = collection.data.get_mut().file_len()?;
+    if let Some(index) = index {
+        collection.config.index = index;
+    }
+    super::compact(collection)?;
+    collection.data.get_mut().trim()?;
+    super::persistence::checkpoint(collection)?;
+
+    // From here on the collection is read-only; the packed index is written with the metadata
+    collection.metadata.sealed_at = Some(crate::testing::clock::now_secs());
+    super::persistence::save_metadata(collection)?;
+    super::persistence::save_vector_index(collection)?;
+
+    let wal_path = get_wal_path(&collection.path);
+    let next_seq = collection.persistence.get_mut().wal.next_seq;
+    collection.persistence.get_mut().wal = Wal::disabled(wal_path.clone().into(), next_seq)?;
+    collection.config.wal = WalConfig::disabled();
+    let mut wal_bytes_dropped = 0;
+    for file in [Path::new(&wal_path).to_path_buf(), get_sealed_path(Path::new(&wal_path))] {
+        if let Ok(meta) = fs::metadata(&file) {
+            wal_bytes_dropped += meta.len();
+            fs::remove_file(&file)?;
+        }
+    }
+    let segments = get_segments_dir(Path::new(&wal_path));
+    if segments.exists() {
+        wal_bytes_dropped += collection.persistence.get_mut().wal.segments().size_bytes();
+        fs::remove_dir_all(&segments)?;
+    }
+    let history = get_history_dir(&collection.path);
+    if history.exists() {
+        fs::remove_dir_all(&history)?;
+    }
+
+    let sealed_at = collection.metadata.sealed_at.unwrap_or_default();
+    let data_bytes = collection.data.get_mut().file_len()?;
+    let mut report = report(collection, sealed_at, data_bytes, wal_bytes_dropped, false);
+    report.data_bytes_before = data_bytes_before;
+    tracing::info!(
+        collection=%collection.path, documents=report.documents, data_bytes=report.data_bytes,
+        wal_bytes_dropped, index_type=%report.index_type, "collection_sealed"
+    );
+    Ok(report)
+}
+
+fn report(collection: &Collection, sealed_at: u64, data_bytes: u64, wal_bytes_dropped: u64, already_sealed: bool) -> SealReport {
+    SealReport {
+        sealed_at,
+        documents: collection.count(),
+        index_type: collection.vector_index.index_type().to_string(),
+        data_bytes_before: data_bytes,
+        data_bytes,
+        wal_bytes_dropped,
+        index_bytes: fs::metadata(format!("{}.vecindex.db", collection.path)).map_or(0, |m| m.len()),
+        already_sealed,
+    }
+}
+
+// Fails with COLLECTION_SEALED on a sealed collection; every write path checks it first
+pub(super) fn ensure_unsealed(collection: &Collection) -> Result<()> {
+    match collection.metadata.sealed_at {
+        Some(sealed_at) => Err(ServerError::CollectionSealed(format!(
+            "'{}' was sealed at {} and accepts no writes", collection.metadata.name, sealed_at
+        )).into()),
+        None => Ok(()),
+    }
+}
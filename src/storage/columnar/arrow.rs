@@ -0,0 +1,61 @@
+// Arrow IPC writers over arrow-ipc: the file format (`ARROW1`, the schema, one record batch message
+// per batch, the footer) for exports, and the stream format (the same messages without the magic
+// and footer) for the streaming transfer endpoints, which send each batch on as it is written.
+// Nothing is compressed, so any Arrow reader takes the output.
+
+use std::io::Write;
+
+use arrow_ipc::writer::{FileWriter, StreamWriter};
+use arrow_schema::SchemaRef;
+
+use crate::error::Result;
+use super::{export_schema, record_batch, write_failed, RowBatch};
+
+pub struct ArrowFileWriter<W: Write> {
+    inner: FileWriter<W>,
+    schema: SchemaRef,
+}
+
+impl<W: Write> ArrowFileWriter<W> {
+    pub fn new(writer: W, dimensions: usize) -> Result<Self> {
+        let schema = export_schema(dimensions)?;
+        let inner = FileWriter::try_new(writer, &schema).map_err(write_failed)?;
+        Ok(Self { inner, schema })
+    }
+
+    pub fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
+        let batch = record_batch(&self.schema, batch)?;
+        self.inner.write(&batch).map_err(write_failed)
+    }
+
+    pub fn finish(self) -> Result<W> {
+        self.inner.into_inner().map_err(write_failed)
+    }
+}
+
+pub struct ArrowStreamWriter<W: Write> {
+    inner: StreamWriter<W>,
+    schema: SchemaRef,
+}
+
+impl<W: Write> ArrowStreamWriter<W> {
+    pub fn new(writer: W, dimensions: usize) -> Result<Self> {
+        let schema = export_schema(dimensions)?;
+        let inner = StreamWriter::try_new(writer, &schema).map_err(write_failed)?;
+        Ok(Self { inner, schema })
+    }
+
+    pub fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
+        let batch = record_batch(&self.schema, batch)?;
+        self.inner.write(&batch).map_err(write_failed)
+    }
+
+    // The writer, for callers that send each batch on as it is written
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.get_mut()
+    }
+
+    pub fn finish(self) -> Result<W> {
+        self.inner.into_inner().map_err(write_failed)
+    }
+}
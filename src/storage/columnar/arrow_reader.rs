@@ -0,0 +1,152 @@
+// Arrow IPC readers over arrow-ipc, for both the file format (`ARROW1` ... footer) and the stream
+// format (messages back to back, as `pyarrow.ipc.new_stream` writes). ArrowReader tells the two
+// apart by the magic; a file's row count comes from the record batch headers its footer points to.
+// ArrowStreamReader reads only the stream format, from anything readable (a request body), since it
+// never seeks. Record batches may be LZ4 or ZSTD compressed. Every column is decoded and the
+// selected ones are kept.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use arrow_array::RecordBatch;
+use arrow_ipc::reader::{read_footer_length, FileReader, StreamReader};
+use arrow_schema::{ArrowError, SchemaRef};
+
+use crate::error::Result;
+use super::{arrow_failed, decode, invalid, select_fields, BatchReader, ColumnBatch, Selection};
+
+const MAGIC: &[u8; 6] = b"ARROW1";
+const CONTINUATION: [u8; 4] = [0xFF; 4];
+const FORMAT: &str = "Arrow";
+
+enum Batches<R: Read + Seek> {
+    File(FileReader<R>),
+    Stream(StreamReader<R>),
+}
+
+pub struct ArrowReader<R: Read + Seek> {
+    batches: Batches<R>,
+    schema: SchemaRef,
+    wanted: Vec<usize>,
+    skipped: Vec<String>,
+    total_rows: Option<u64>,
+}
+
+impl<R: Read + Seek> ArrowReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 6];
+        let is_file = reader.read_exact(&mut magic).is_ok() && &magic == MAGIC;
+        let (batches, total_rows) = if is_file {
+            let total = file_rows(&mut reader)?;
+            (Batches::File(FileReader::try_new(reader, None).map_err(|e| arrow_failed(FORMAT, e))?), Some(total))
+        } else {
+            reader.seek(SeekFrom::Start(0))?;
+            (Batches::Stream(StreamReader::try_new(reader, None).map_err(|e| arrow_failed(FORMAT, e))?), None)
+        };
+        let schema = match &batches {
+            Batches::File(file) => file.schema(),
+            Batches::Stream(stream) => stream.schema(),
+        };
+        let mut reader = Self { batches, schema, wanted: Vec::new(), skipped: Vec::new(), total_rows };
+        reader.select(&Selection::All)?;
+        Ok(reader)
+    }
+}
+
+// Rows in a file: the length in the header of every record batch the footer lists
+fn file_rows(reader: &mut (impl Read + Seek)) -> Result<u64> {
+    let end = reader.seek(SeekFrom::End(-10))?;
+    let mut tail = [0u8; 10];
+    reader.read_exact(&mut tail)?;
+    let footer_len = read_footer_length(tail).map_err(|e| arrow_failed(FORMAT, e))?;
+    let start = end.checked_sub(footer_len as u64).ok_or_else(|| invalid(FORMAT, "bad footer length"))?;
+    reader.seek(SeekFrom::Start(start))?;
+    let mut footer = vec![0u8; footer_len];
+    reader.read_exact(&mut footer)?;
+    let footer = arrow_ipc::root_as_footer(&footer).map_err(|e| invalid(FORMAT, format!("unreadable footer: {e}")))?;
+
+    let mut total = 0;
+    for block in footer.recordBatches().into_iter().flatten() {
+        reader.seek(SeekFrom::Start(u64::try_from(block.offset()).map_err(|_| invalid(FORMAT, "bad block offset"))?))?;
+        let mut metadata = vec![0u8; usize::try_from(block.metaDataLength()).map_err(|_| invalid(FORMAT, "bad block length"))?];
+        reader.read_exact(&mut metadata)?;
+        // Messages written before the continuation marker was introduced start with the length directly
+        let skip = if metadata.starts_with(&CONTINUATION) { 8 } else { 4 };
+        let message = arrow_ipc::root_as_message(metadata.get(skip..).unwrap_or_default())
+            .map_err(|e| invalid(FORMAT, format!("unreadable record batch header: {e}")))?;
+        total += message.header_as_record_batch().map_or(0, |batch| batch.length().max(0) as u64);
+    }
+    Ok(total)
+}
+
+fn next(format: &str, batch: Option<std::result::Result<RecordBatch, ArrowError>>, wanted: &[usize]) -> Result<Option<ColumnBatch>> {
+    let Some(batch) = batch.transpose().map_err(|e| arrow_failed(format, e))? else { return Ok(None) };
+    let batch = batch.project(wanted).map_err(|e| arrow_failed(format, e))?;
+    decode(format, &batch).map(Some)
+}
+
+impl<R: Read + Seek> BatchReader for ArrowReader<R> {
+    fn columns(&self) -> Vec<String> {
+        self.schema.fields().iter().map(|f| f.name().clone()).collect()
+    }
+
+    fn select(&mut self, selection: &Selection) -> Result<()> {
+        (self.wanted, self.skipped) = select_fields(FORMAT, &self.schema, selection)?;
+        Ok(())
+    }
+
+    fn total_rows(&self) -> Option<u64> {
+        self.total_rows
+    }
+
+    fn skipped_columns(&self) -> &[String] {
+        &self.skipped
+    }
+
+    fn next_batch(&mut self) -> Result<Option<ColumnBatch>> {
+        let batch = match &mut self.batches {
+            Batches::File(file) => file.next(),
+            Batches::Stream(stream) => stream.next(),
+        };
+        next(FORMAT, batch, &self.wanted)
+    }
+}
+
+pub struct ArrowStreamReader<R: Read> {
+    batches: StreamReader<R>,
+    schema: SchemaRef,
+    wanted: Vec<usize>,
+    skipped: Vec<String>,
+}
+
+impl<R: Read> ArrowStreamReader<R> {
+    // Reads the schema message, so a stream that is not Arrow fails here
+    pub fn new(reader: R) -> Result<Self> {
+        let batches = StreamReader::try_new(reader, None).map_err(|e| arrow_failed(FORMAT, e))?;
+        let schema = batches.schema();
+        let (wanted, skipped) = select_fields(FORMAT, &schema, &Selection::All)?;
+        Ok(Self { batches, schema, wanted, skipped })
+    }
+}
+
+impl<R: Read> BatchReader for ArrowStreamReader<R> {
+    fn columns(&self) -> Vec<String> {
+        self.schema.fields().iter().map(|f| f.name().clone()).collect()
+    }
+
+    fn select(&mut self, selection: &Selection) -> Result<()> {
+        (self.wanted, self.skipped) = select_fields(FORMAT, &self.schema, selection)?;
+        Ok(())
+    }
+
+    fn total_rows(&self) -> Option<u64> {
+        None
+    }
+
+    fn skipped_columns(&self) -> &[String] {
+        &self.skipped
+    }
+
+    fn next_batch(&mut self) -> Result<Option<ColumnBatch>> {
+        next(FORMAT, self.batches.next(), &self.wanted)
+    }
+}
@@ -0,0 +1,278 @@
+// Columnar file formats for bulk export and import (see collection/export.rs and migrate.rs), on
+// the arrow and parquet crates.
+// - arrow.rs: Arrow IPC file and stream writers
+// - arrow_reader.rs: Arrow IPC file and stream readers
+// - parquet.rs: Parquet writer
+// - parquet_reader.rs: Parquet reader
+//
+// Both writers take the same RowBatch, turned into one Arrow record batch with the same four
+// columns, none nullable:
+// - id        utf8, the document's Uuid
+// - vector    fixed-size list of float32 (Parquet: a LIST of required floats)
+// - text      utf8
+// - metadata  utf8, the metadata as a JSON object
+//
+// The readers take what other tools write: flat columns of booleans, integers, floats, strings (or
+// binaries, read as UTF-8), dates and timestamps (as their integer values) and dictionaries of
+// those, plus lists of floats for vectors, with nulls. Columns of any other type are skipped unless
+// asked for by name.
+
+mod arrow;
+mod parquet;
+mod arrow_reader;
+mod parquet_reader;
+
+pub use arrow::{ArrowFileWriter, ArrowStreamWriter};
+pub use arrow_reader::{ArrowReader, ArrowStreamReader};
+pub use parquet::ParquetWriter;
+pub use parquet_reader::ParquetReader;
+
+use std::sync::Arc;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float32Type, Float64Type, Int64Type, UInt64Type};
+use arrow_array::{Array, ArrayRef, FixedSizeListArray, Float32Array, RecordBatch, StringArray};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+
+use crate::error::{PiramidError, Result, ServerError, StorageError};
+
+pub const COLUMNS: [&str; 4] = ["id", "vector", "text", "metadata"];
+
+// One batch of rows, column by column; `vectors` holds `dimensions` floats per row, back to back
+#[derive(Debug, Clone, Default)]
+pub struct RowBatch {
+    pub ids: Vec<String>,
+    pub vectors: Vec<f32>,
+    pub texts: Vec<String>,
+    pub metadata: Vec<String>,
+}
+
+impl RowBatch {
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+// A column read back from a file, one value per row; None is a null (or an empty list)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Bool(Vec<Option<bool>>),
+    Int(Vec<Option<i64>>),
+    Float(Vec<Option<f64>>),
+    Utf8(Vec<Option<String>>),
+    FloatList(Vec<Option<Vec<f32>>>),
+}
+
+impl Column {
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Bool(v) => v.len(),
+            Column::Int(v) => v.len(),
+            Column::Float(v) => v.len(),
+            Column::Utf8(v) => v.len(),
+            Column::FloatList(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ColumnBatch {
+    pub rows: usize,
+    pub columns: Vec<(String, Column)>,
+}
+
+// Which columns a reader decodes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    All,               // every column of a supported type; the rest are reported as skipped
+    Only(Vec<String>), // these, which must exist and be of a supported type
+}
+
+impl Selection {
+    fn wants(&self, name: &str) -> bool {
+        match self {
+            Selection::All => true,
+            Selection::Only(names) => names.iter().any(|n| n == name),
+        }
+    }
+}
+
+// Both readers hand out one row group or record batch at a time, of every supported column until
+// `select` narrows that down
+pub trait BatchReader {
+    fn columns(&self) -> Vec<String>;
+    fn select(&mut self, selection: &Selection) -> Result<()>;
+    fn total_rows(&self) -> Option<u64>;
+    fn skipped_columns(&self) -> &[String];
+    fn next_batch(&mut self) -> Result<Option<ColumnBatch>>;
+}
+
+fn invalid(format: &str, msg: impl std::fmt::Display) -> PiramidError {
+    ServerError::ValidationFailed(format!("Invalid {format} file: {msg}")).into()
+}
+
+// Check a selection against the file's columns, each flagged with whether its type can be read.
+// Returns which columns to decode and which selected ones are skipped for their type.
+fn resolve_selection(format: &str, selection: &Selection, columns: &[(String, bool)]) -> Result<(Vec<bool>, Vec<String>)> {
+    if let Selection::Only(names) = selection {
+        for name in names {
+            match columns.iter().find(|(column, _)| column == name) {
+                Some((_, true)) => {}
+                Some((_, false)) => return Err(invalid(format, format!("column '{name}' has a type that cannot be imported"))),
+                None => {
+                    let known: Vec<&str> = columns.iter().map(|(c, _)| c.as_str()).collect();
+                    return Err(invalid(format, format!("no column '{name}' (columns: {})", known.join(", "))));
+                }
+            }
+        }
+    }
+    let wanted = columns.iter().map(|(name, supported)| *supported && selection.wants(name)).collect();
+    let skipped = columns
+        .iter()
+        .filter(|(name, supported)| !supported && selection.wants(name))
+        .map(|(name, _)| name.clone())
+        .collect();
+    Ok((wanted, skipped))
+}
+
+// A file that arrow-ipc or parquet could not read: cut short, malformed or of a layout it does not
+// take. Failures of the underlying reader itself stay I/O errors.
+fn arrow_failed(format: &str, e: ArrowError) -> PiramidError {
+    match e {
+        ArrowError::IoError(_, e) if e.kind() == std::io::ErrorKind::UnexpectedEof => invalid(format, "truncated message"),
+        ArrowError::IoError(_, e) => e.into(),
+        e => invalid(format, e),
+    }
+}
+
+// Types read as they are; dictionaries of them are read as their values
+fn scalar(data_type: &DataType) -> bool {
+    use DataType::*;
+    matches!(data_type, Null | Boolean | Int8 | Int16 | Int32 | Int64 | UInt8 | UInt16 | UInt32 | UInt64 | Float32 | Float64)
+        || matches!(data_type, Utf8 | LargeUtf8 | Utf8View | Binary | LargeBinary | BinaryView)
+        || data_type.is_temporal()
+}
+
+fn readable(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::List(item) | DataType::LargeList(item) | DataType::FixedSizeList(item, _) => {
+            matches!(item.data_type(), DataType::Float32 | DataType::Float64)
+        }
+        DataType::Dictionary(_, values) => scalar(values),
+        other => scalar(other),
+    }
+}
+
+// Which of a file's columns to decode, by index, and which selected ones are skipped for their type
+fn select_fields(format: &str, schema: &Schema, selection: &Selection) -> Result<(Vec<usize>, Vec<String>)> {
+    let columns: Vec<(String, bool)> = schema.fields().iter().map(|f| (f.name().clone(), readable(f.data_type()))).collect();
+    let (wanted, skipped) = resolve_selection(format, selection, &columns)?;
+    let wanted = wanted.iter().enumerate().filter(|(_, &w)| w).map(|(i, _)| i).collect();
+    Ok((wanted, skipped))
+}
+
+// Every column of a batch already narrowed down to the selected ones
+fn decode(format: &str, batch: &RecordBatch) -> Result<ColumnBatch> {
+    let columns = batch
+        .schema()
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, array)| Ok((field.name().clone(), to_column(format, field.name(), array)?)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ColumnBatch { rows: batch.num_rows(), columns })
+}
+
+fn to_column(format: &str, name: &str, array: &ArrayRef) -> Result<Column> {
+    let cast = |array: &ArrayRef, to: &DataType| arrow_cast::cast(array, to).map_err(|e| arrow_failed(format, e));
+    let array = match array.data_type() {
+        DataType::Dictionary(_, values) => cast(array, values)?,
+        _ => array.clone(),
+    };
+    let array = if array.data_type().is_temporal() { cast(&array, &DataType::Int64)? } else { array };
+    Ok(match array.data_type() {
+        DataType::Null => Column::Int(vec![None; array.len()]),
+        DataType::Boolean => Column::Bool(array.as_boolean().iter().collect()),
+        DataType::UInt64 => Column::Int(
+            array
+                .as_primitive::<UInt64Type>()
+                .iter()
+                .map(|v| v.map(i64::try_from).transpose())
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|_| invalid(format, format!("column '{name}' holds an unsigned value too large to import")))?,
+        ),
+        data_type if data_type.is_integer() => Column::Int(cast(&array, &DataType::Int64)?.as_primitive::<Int64Type>().iter().collect()),
+        DataType::Float32 | DataType::Float64 => {
+            Column::Float(cast(&array, &DataType::Float64)?.as_primitive::<Float64Type>().iter().collect())
+        }
+        DataType::Utf8 => Column::Utf8(array.as_string::<i32>().iter().map(|v| v.map(str::to_string)).collect()),
+        DataType::LargeUtf8 => Column::Utf8(array.as_string::<i64>().iter().map(|v| v.map(str::to_string)).collect()),
+        DataType::Utf8View => Column::Utf8(array.as_string_view().iter().map(|v| v.map(str::to_string)).collect()),
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView => {
+            let array = cast(&array, &DataType::LargeBinary)?;
+            Column::Utf8(array.as_binary::<i64>().iter().map(|v| v.map(|b| String::from_utf8_lossy(b).into_owned())).collect())
+        }
+        DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(..) => {
+            let mut rows = Vec::with_capacity(array.len());
+            for i in 0..array.len() {
+                let items = match array.data_type() {
+                    _ if array.is_null(i) => {
+                        rows.push(None);
+                        continue;
+                    }
+                    DataType::List(_) => array.as_list::<i32>().value(i),
+                    DataType::LargeList(_) => array.as_list::<i64>().value(i),
+                    _ => array.as_fixed_size_list().value(i),
+                };
+                // An empty list is no vector
+                if items.is_empty() {
+                    rows.push(None);
+                    continue;
+                }
+                if items.null_count() > 0 {
+                    return Err(invalid(format, format!("column '{name}' has a null inside a list")));
+                }
+                rows.push(Some(cast(&items, &DataType::Float32)?.as_primitive::<Float32Type>().values().to_vec()));
+            }
+            Column::FloatList(rows)
+        }
+        _ => unreachable!("columns of other types are never selected"),
+    })
+}
+
+fn write_failed(e: impl std::fmt::Display) -> PiramidError {
+    StorageError::WriteFailed(e.to_string()).into()
+}
+
+fn export_schema(dimensions: usize) -> Result<SchemaRef> {
+    let size = i32::try_from(dimensions).map_err(|_| write_failed(format!("{dimensions} dimensions is too many for a fixed-size list")))?;
+    let item = Arc::new(Field::new("item", DataType::Float32, false));
+    let fields = COLUMNS.map(|name| match name {
+        "vector" => Field::new(name, DataType::FixedSizeList(item.clone(), size), false),
+        _ => Field::new(name, DataType::Utf8, false),
+    });
+    Ok(Arc::new(Schema::new(fields.to_vec())))
+}
+
+fn record_batch(schema: &SchemaRef, batch: &RowBatch) -> Result<RecordBatch> {
+    let DataType::FixedSizeList(item, size) = schema.field(1).data_type() else {
+        unreachable!("export_schema makes the vector column a fixed-size list")
+    };
+    let values = Arc::new(Float32Array::from(batch.vectors.clone()));
+    let vectors = FixedSizeListArray::try_new(item.clone(), *size, values, None).map_err(write_failed)?;
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(&batch.ids)),
+        Arc::new(vectors),
+        Arc::new(StringArray::from_iter_values(&batch.texts)),
+        Arc::new(StringArray::from_iter_values(&batch.metadata)),
+    ];
+    RecordBatch::try_new(schema.clone(), columns).map_err(write_failed)
+}
@@ -0,0 +1,38 @@
+// Parquet writer over the parquet crate's ArrowWriter, one row group per batch. The vector column
+// becomes a standard three-level LIST of required floats, and the Arrow schema is embedded under
+// `ARROW:schema`, which lets Arrow-based readers restore the fixed-size list type. Pages are PLAIN
+// and uncompressed: the columns are mostly unique strings and float noise, which neither
+// dictionaries nor general-purpose codecs shrink by much.
+
+use std::io::Write;
+
+use arrow_schema::SchemaRef;
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+
+use crate::error::Result;
+use super::{export_schema, record_batch, write_failed, RowBatch};
+
+pub struct ParquetWriter<W: Write + Send> {
+    inner: ArrowWriter<W>,
+    schema: SchemaRef,
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    pub fn new(writer: W, dimensions: usize) -> Result<Self> {
+        let properties = WriterProperties::builder().set_dictionary_enabled(false).build();
+        let schema = export_schema(dimensions)?;
+        let inner = ArrowWriter::try_new(writer, schema.clone(), Some(properties)).map_err(write_failed)?;
+        Ok(Self { inner, schema })
+    }
+
+    pub fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
+        let batch = record_batch(&self.schema, batch)?;
+        self.inner.write(&batch).map_err(write_failed)?;
+        self.inner.flush().map_err(write_failed) // close the row group
+    }
+
+    pub fn finish(self) -> Result<W> {
+        self.inner.into_inner().map_err(write_failed)
+    }
+}
@@ -0,0 +1,71 @@
+// Parquet reader over the parquet crate's Arrow reader. The footer is read up front for the column
+// list and row count; the batches are read once the selection is known, and only the chunks of the
+// selected columns are fetched. Any codec writers use by default (Snappy, ZSTD, GZIP, LZ4) and any
+// page encoding is read. The Arrow schema a writer embeds is honoured, so our own exports come back
+// with the vector column as a fixed-size list.
+
+use parquet::arrow::arrow_reader::{ArrowReaderMetadata, ArrowReaderOptions, ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+use parquet::arrow::ProjectionMask;
+use parquet::errors::ParquetError;
+use parquet::file::reader::ChunkReader;
+
+use crate::error::{PiramidError, Result};
+use super::{arrow_failed, decode, invalid, select_fields, BatchReader, ColumnBatch, Selection};
+
+const FORMAT: &str = "Parquet";
+
+fn parquet_failed(e: ParquetError) -> PiramidError {
+    invalid(FORMAT, e)
+}
+
+pub struct ParquetReader<R: ChunkReader + 'static> {
+    source: Option<R>, // until the first batch
+    metadata: ArrowReaderMetadata,
+    batches: Option<ParquetRecordBatchReader>,
+    wanted: Vec<usize>,
+    skipped: Vec<String>,
+}
+
+impl<R: ChunkReader + 'static> ParquetReader<R> {
+    pub fn new(reader: R) -> Result<Self> {
+        let metadata = ArrowReaderMetadata::load(&reader, ArrowReaderOptions::new()).map_err(parquet_failed)?;
+        let mut reader = Self { source: Some(reader), metadata, batches: None, wanted: Vec::new(), skipped: Vec::new() };
+        reader.select(&Selection::All)?;
+        Ok(reader)
+    }
+}
+
+impl<R: ChunkReader + 'static> BatchReader for ParquetReader<R> {
+    fn columns(&self) -> Vec<String> {
+        self.metadata.schema().fields().iter().map(|f| f.name().clone()).collect()
+    }
+
+    fn select(&mut self, selection: &Selection) -> Result<()> {
+        (self.wanted, self.skipped) = select_fields(FORMAT, self.metadata.schema(), selection)?;
+        Ok(())
+    }
+
+    fn total_rows(&self) -> Option<u64> {
+        Some(self.metadata.metadata().file_metadata().num_rows().max(0) as u64)
+    }
+
+    fn skipped_columns(&self) -> &[String] {
+        &self.skipped
+    }
+
+    fn next_batch(&mut self) -> Result<Option<ColumnBatch>> {
+        if let Some(source) = self.source.take() {
+            let projection = ProjectionMask::roots(self.metadata.parquet_schema(), self.wanted.iter().copied());
+            let batches = ParquetRecordBatchReaderBuilder::new_with_metadata(source, self.metadata.clone())
+                .with_projection(projection)
+                .build()
+                .map_err(parquet_failed)?;
+            self.batches = Some(batches);
+        }
+        let Some(batches) = self.batches.as_mut() else { return Ok(None) };
+        match batches.next().transpose().map_err(|e| arrow_failed(FORMAT, e))? {
+            Some(batch) => decode(FORMAT, &batch).map(Some),
+            None => Ok(None),
+        }
+    }
+}
@@ -3,9 +3,26 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::metadata::Metadata;
+use crate::metadata::{Metadata, MetadataValue};
 use crate::quantization::QuantizedVector;
 
+// Metadata keys starting with this prefix are reserved for the fields below, which the engine
+// maintains; clients cannot send them (see validation::validate_metadata_keys).
+pub const RESERVED_KEY_PREFIX: char = '_';
+
+// Reserved metadata key holding the client-provided id of a document.
+// Kept in metadata (instead of a new Document field) so the on-disk format stays unchanged.
+pub const EXTERNAL_ID_KEY: &str = "_external_id";
+
+// Reserved metadata key holding a document's version, for the same reason. A document is at version
+// 1 when inserted (the key is left out) and each rewrite stores the next one; clients cannot set it.
+pub const VERSION_KEY: &str = "_version";
+
+// Reserved metadata keys holding when a document was first inserted and last written (unix seconds),
+// set by the engine on every write. Being metadata, they can be filtered on like any other field.
+pub const CREATED_AT_KEY: &str = "_created_at";
+pub const UPDATED_AT_KEY: &str = "_updated_at";
+
 // A single vector entry stored in the database
 // 
 // Vectors are stored as quantized int8 for 4x memory efficiency.
@@ -17,31 +34,103 @@ pub struct Document {
     pub text: String,
     #[serde(default)]
     pub metadata: Metadata,
+    // The float32 vector the document was built from, before quantization. Never serialized:
+    // it only travels with freshly built documents so two-stage search can keep an exact copy.
+    #[serde(skip)]
+    pub full_precision: Option<Vec<f32>>,
 }
 
 impl Document {
     // Create new entry from f32 vector (will be quantized)
     pub fn new(vector: Vec<f32>, text: String) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: crate::testing::ids::new_id(),
             vector: QuantizedVector::from_f32(&vector),
             text,
             metadata: Metadata::new(),
+            full_precision: Some(vector),
         }
     }
 
     // Create new entry with metadata (will be quantized)
     pub fn with_metadata(vector: Vec<f32>, text: String, metadata: Metadata) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: crate::testing::ids::new_id(),
             vector: QuantizedVector::from_f32(&vector),
             text,
             metadata,
+            full_precision: Some(vector),
         }
     }
 
+    // Exact float32 vector when known, otherwise the dequantized one
+    pub fn exact_vector(&self) -> Vec<f32> {
+        self.full_precision.clone().unwrap_or_else(|| self.get_vector())
+    }
+
     // Get the vector as f32 (dequantizes on demand)
     pub fn get_vector(&self) -> Vec<f32> {
         self.vector.to_f32()
     }
+
+    // Attach a client-provided id (stored under EXTERNAL_ID_KEY)
+    pub fn with_external_id(mut self, external_id: impl Into<String>) -> Self {
+        self.metadata.insert(EXTERNAL_ID_KEY.to_string(), MetadataValue::String(external_id.into()));
+        self
+    }
+
+    // Client-provided id, if the document has one
+    pub fn external_id(&self) -> Option<&str> {
+        external_id_of(&self.metadata)
+    }
+
+    pub fn version(&self) -> u64 {
+        version_of(&self.metadata)
+    }
+
+    pub fn created_at(&self) -> Option<u64> {
+        timestamp_of(&self.metadata, CREATED_AT_KEY)
+    }
+
+    pub fn updated_at(&self) -> Option<u64> {
+        timestamp_of(&self.metadata, UPDATED_AT_KEY)
+    }
+
+    // Set the engine-maintained fields for writing this document over `previous` (the metadata of the
+    // version it replaces, None for an insert): the next version, the original creation time and now
+    // as the update time. Whatever the client put under those keys is overwritten.
+    pub fn stamp(&mut self, previous: Option<&Metadata>, now: u64) {
+        let version = previous.map_or(1, |p| version_of(p) + 1);
+        match version {
+            1 => self.metadata.remove(VERSION_KEY),
+            v => self.metadata.insert(VERSION_KEY.to_string(), MetadataValue::Integer(v as i64)),
+        };
+        // Documents written before timestamps were kept have no creation time to carry over
+        match previous.map_or(Some(now), |p| timestamp_of(p, CREATED_AT_KEY)) {
+            Some(created) => self.metadata.insert(CREATED_AT_KEY.to_string(), MetadataValue::Integer(created as i64)),
+            None => self.metadata.remove(CREATED_AT_KEY),
+        };
+        self.metadata.insert(UPDATED_AT_KEY.to_string(), MetadataValue::Integer(now as i64));
+    }
+}
+
+pub fn version_of(metadata: &Metadata) -> u64 {
+    match metadata.get(VERSION_KEY) {
+        Some(MetadataValue::Integer(v)) if *v > 1 => *v as u64,
+        _ => 1,
+    }
+}
+
+fn timestamp_of(metadata: &Metadata, key: &str) -> Option<u64> {
+    match metadata.get(key) {
+        Some(MetadataValue::Integer(t)) if *t >= 0 => Some(*t as u64),
+        _ => None,
+    }
+}
+
+pub fn external_id_of(metadata: &Metadata) -> Option<&str> {
+    match metadata.get(EXTERNAL_ID_KEY) {
+        Some(MetadataValue::String(s)) => Some(s.as_str()),
+        _ => None,
+    }
 }
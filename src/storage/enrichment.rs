@@ -0,0 +1,182 @@
+// Enrichment stages: metadata fields computed from a document when it is written.
+// A collection runs the stages of its config (see config/enrichment.rs) and then the ones added to
+// it with `Collection::add_enrichment_stage`, on every insert and upsert, before anything is logged:
+// the WAL, the data file and the metadata index all see the enriched document, and a WAL replay does
+// not run the stages again. Metadata updates are not enriched.
+//
+// A document can pass through the stages twice in one write (an upsert that turns into an insert, a
+// partial batch checked entry by entry), so a stage must give the same fields when run again.
+
+use std::sync::Arc;
+
+use crate::config::{EnrichmentStageConfig, LengthUnit};
+use crate::error::Result;
+use crate::metadata::{Metadata, MetadataValue};
+use super::document::Document;
+
+pub trait EnrichmentStage: Send + Sync {
+    fn name(&self) -> &str;
+
+    // Add fields derived from the text and the other fields; an error rejects the document
+    fn enrich(&self, text: &str, metadata: &mut Metadata) -> Result<()>;
+}
+
+impl EnrichmentStage for EnrichmentStageConfig {
+    fn name(&self) -> &str {
+        match self {
+            Self::TextLength { .. } => "text_length",
+            Self::Language { .. } => "language",
+            Self::UrlHost { .. } => "url_host",
+        }
+    }
+
+    fn enrich(&self, text: &str, metadata: &mut Metadata) -> Result<()> {
+        match self {
+            Self::TextLength { field, unit } => {
+                let length = match unit {
+                    LengthUnit::Chars => text.chars().count(),
+                    LengthUnit::Words => text.split_whitespace().count(),
+                };
+                metadata.insert(field.clone(), MetadataValue::Integer(length as i64));
+            }
+            Self::Language { field } => {
+                if let Some(language) = detect_language(text) {
+                    metadata.insert(field.clone(), MetadataValue::String(language.to_string()));
+                }
+            }
+            Self::UrlHost { source, field } => {
+                let host = match metadata.get(source) {
+                    Some(MetadataValue::String(url)) => url_host(url).map(MetadataValue::String),
+                    Some(MetadataValue::Array(urls)) => {
+                        let mut hosts: Vec<String> = Vec::new();
+                        for host in urls.iter().filter_map(|u| u.as_string().and_then(url_host)) {
+                            if !hosts.contains(&host) {
+                                hosts.push(host);
+                            }
+                        }
+                        (!hosts.is_empty()).then(|| MetadataValue::Array(hosts.into_iter().map(MetadataValue::String).collect()))
+                    }
+                    _ => None,
+                };
+                if let Some(host) = host {
+                    metadata.insert(field.clone(), host);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Run the configured stages, then the added ones, on a document about to be written
+pub(crate) fn enrich(configured: &[EnrichmentStageConfig], added: &[Arc<dyn EnrichmentStage>], entry: &mut Document) -> Result<()> {
+    let stages = configured.iter().map(|s| s as &dyn EnrichmentStage).chain(added.iter().map(|s| &**s));
+    for stage in stages {
+        stage.enrich(&entry.text, &mut entry.metadata)?;
+    }
+    Ok(())
+}
+
+// Host of an absolute URL, lowercased, without user info, port or trailing dot
+pub fn url_host(url: &str) -> Option<String> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    if scheme.is_empty() || !scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = match host_port.strip_prefix('[') {
+        Some(ipv6) => ipv6.split_once(']')?.0,
+        None => host_port.split(':').next()?,
+    };
+    let host = host.trim_end_matches('.').to_lowercase();
+    (!host.is_empty()).then_some(host)
+}
+
+// Common words of the languages told apart within the Latin script
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "of", "to", "is", "in", "that", "it", "was", "for", "with", "this", "are", "be", "on", "not", "you", "have"]),
+    ("de", &["der", "die", "und", "das", "ist", "nicht", "ein", "eine", "zu", "den", "mit", "sich", "des", "auf", "ich", "auch", "dem", "wir"]),
+    ("fr", &["le", "la", "les", "et", "est", "des", "une", "un", "du", "que", "pas", "pour", "dans", "qui", "ce", "sur", "avec", "nous"]),
+    ("es", &["el", "la", "los", "las", "y", "es", "que", "de", "en", "un", "una", "por", "con", "para", "no", "del", "se", "su"]),
+    ("it", &["il", "la", "di", "che", "e", "è", "un", "una", "per", "non", "sono", "del", "della", "con", "gli", "lo", "nel", "anche"]),
+    ("pt", &["o", "a", "os", "as", "e", "é", "que", "de", "não", "um", "uma", "do", "da", "em", "para", "com", "por", "se"]),
+    ("nl", &["de", "het", "een", "en", "van", "is", "niet", "dat", "op", "te", "zijn", "met", "voor", "ook", "ik", "aan", "er", "maar"]),
+];
+
+// ISO 639-1 code of the text's language, from its dominant script and, for Latin text, from common
+// words. None when the text is too short or too mixed to tell.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let mut scripts = [0usize; 11];
+    const LATIN: usize = 0;
+    const CYRILLIC: usize = 1;
+    const GREEK: usize = 2;
+    const ARABIC: usize = 3;
+    const HEBREW: usize = 4;
+    const DEVANAGARI: usize = 5;
+    const THAI: usize = 6;
+    const HANGUL: usize = 7;
+    const KANA: usize = 8;
+    const HAN: usize = 9;
+    const UKRAINIAN: usize = 10; // Cyrillic letters Russian does not use
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        let script = match c as u32 {
+            0x0041..=0x024F => LATIN,
+            0x0370..=0x03FF => GREEK,
+            0x0400..=0x04FF => {
+                if matches!(c, 'і' | 'ї' | 'є' | 'ґ' | 'І' | 'Ї' | 'Є' | 'Ґ') {
+                    scripts[UKRAINIAN] += 1;
+                }
+                CYRILLIC
+            }
+            0x0590..=0x05FF => HEBREW,
+            0x0600..=0x06FF => ARABIC,
+            0x0900..=0x097F => DEVANAGARI,
+            0x0E00..=0x0E7F => THAI,
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => HANGUL,
+            0x3040..=0x30FF => KANA,
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF => HAN,
+            _ => continue,
+        };
+        scripts[script] += 1;
+    }
+    // Japanese mixes kana into Han text
+    let (script, count) = scripts[..UKRAINIAN]
+        .iter()
+        .enumerate()
+        .map(|(script, &count)| match script {
+            HAN if scripts[KANA] > 0 => (KANA, 0),
+            KANA => (KANA, count + scripts[HAN]),
+            _ => (script, count),
+        })
+        .max_by_key(|&(_, count)| count)?;
+    if count == 0 {
+        return None;
+    }
+    match script {
+        LATIN => latin_language(text),
+        CYRILLIC if scripts[UKRAINIAN] > 0 => Some("uk"),
+        CYRILLIC => Some("ru"),
+        GREEK => Some("el"),
+        ARABIC => Some("ar"),
+        HEBREW => Some("he"),
+        DEVANAGARI => Some("hi"),
+        THAI => Some("th"),
+        HANGUL => Some("ko"),
+        KANA => Some("ja"),
+        _ => Some("zh"),
+    }
+}
+
+// The language whose common words the text uses most, with at least two of them and no tie
+fn latin_language(text: &str) -> Option<&'static str> {
+    let words: Vec<String> = text.split(|c: char| !c.is_alphabetic()).filter(|w| !w.is_empty()).map(str::to_lowercase).collect();
+    let mut scores: Vec<(&'static str, usize)> = STOPWORDS
+        .iter()
+        .map(|(language, common)| (*language, words.iter().filter(|w| common.contains(&w.as_str())).count()))
+        .collect();
+    scores.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+    match scores.as_slice() {
+        [(language, best), (_, next), ..] if *best >= 2 && best > next => Some(language),
+        _ => None,
+    }
+}
@@ -1,7 +1,8 @@
-// Collection metadata tracking (created_at, updated_at, dimensions)
+// Collection metadata tracking (created_at, updated_at, dimensions, cumulative counters)
+
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use serde::{Serialize, Deserialize};
-use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CollectionMetadata {
@@ -12,9 +13,83 @@ pub struct CollectionMetadata {
     pub updated_at: u64,      // Unix timestamp (seconds)
     pub dimensions: Option<usize>,  // Expected vector dimensions (None = auto-detect)
     pub vector_count: usize,
+    #[serde(default)]
+    pub embedding_model: Option<EmbeddingModelInfo>, // Model behind the first server-side embedding
+    #[serde(default)]
+    pub sealed_at: Option<u64>, // Set once the collection is sealed write-once (see collection/worm.rs)
+    #[serde(default)]
+    pub counters: CollectionCounters,
+    #[serde(default)]
+    pub counters_seq: u64, // Last WAL seq the saved counters include; replay counts only the entries after it
+}
+
+// Embedding model (and its output dimensions) a collection was first embedded with.
+// Vectors from different models live in different spaces, so mixing them silently ruins search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingModelInfo {
+    pub model: String,
+    pub dimensions: usize,
+}
+
+// Cumulative counts since the collection was created, for capacity planning. Documents are counted
+// one by one (a batch of 10 is 10 inserts); an upsert of an existing document is an update. Atomic
+// because metadata updates run under a shared collection lock. Saved with the metadata (at checkpoints
+// and on direct saves) along with the WAL seq they include, and WAL replay counts only what came after,
+// so the counts survive restarts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CollectionCounters {
+    pub total_inserts: AtomicU64,
+    pub total_updates: AtomicU64,
+    pub total_deletes: AtomicU64,
+    pub last_compaction_at: Option<u64>, // Unix timestamp (seconds)
+    pub last_rebuild_at: Option<u64>, // Unix timestamp (seconds) of the last vector index rebuild or migration
+    pub dead_bytes: AtomicU64, // Estimate of data file bytes held by deleted and superseded entries, reclaimed by compaction
+}
+
+impl Clone for CollectionCounters {
+    fn clone(&self) -> Self {
+        let copy = |counter: &AtomicU64| AtomicU64::new(counter.load(Ordering::Relaxed));
+        Self {
+            total_inserts: copy(&self.total_inserts),
+            total_updates: copy(&self.total_updates),
+            total_deletes: copy(&self.total_deletes),
+            last_compaction_at: self.last_compaction_at,
+            last_rebuild_at: self.last_rebuild_at,
+            dead_bytes: copy(&self.dead_bytes),
+        }
+    }
+}
+
+impl CollectionCounters {
+    pub fn record_inserts(&self, count: usize) {
+        self.total_inserts.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_updates(&self, count: usize) {
+        self.total_updates.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_deletes(&self, count: usize) {
+        self.total_deletes.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    // An entry in the data file no longer pointed to
+    pub fn record_dead_bytes(&self, bytes: u64) {
+        self.dead_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn record_compaction(&mut self) {
+        self.last_compaction_at = Some(crate::testing::clock::now_secs());
+        *self.dead_bytes.get_mut() = 0;
+    }
+
+    pub fn record_rebuild(&mut self) {
+        self.last_rebuild_at = Some(crate::testing::clock::now_secs());
+    }
 }
 
-pub const SCHEMA_VERSION: u32 = 1;
+// v2 added embedding_model, v3 sealed_at, v4 counters, v5 counters_seq; older files are upgraded on load
+pub const SCHEMA_VERSION: u32 = 5;
 
 fn default_schema_version() -> u32 {
     SCHEMA_VERSION
@@ -22,10 +97,7 @@ fn default_schema_version() -> u32 {
 
 impl CollectionMetadata {
     pub fn new(name: String) -> Self {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let now = crate::testing::clock::now_secs();
         
         Self {
             schema_version: SCHEMA_VERSION,
@@ -34,6 +106,10 @@ impl CollectionMetadata {
             updated_at: now,
             dimensions: None,
             vector_count: 0,
+            embedding_model: None,
+            sealed_at: None,
+            counters: CollectionCounters::default(),
+            counters_seq: 0,
         }
     }
     
@@ -44,10 +120,7 @@ impl CollectionMetadata {
     }
     
     pub fn touch(&mut self) {
-        self.updated_at = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        self.updated_at = crate::testing::clock::now_secs();
     }
     
     pub fn set_dimensions(&mut self, dimensions: usize) {
@@ -56,6 +129,38 @@ impl CollectionMetadata {
         }
     }
     
+    // Record the embedding model if none is recorded yet. Returns true when it was recorded.
+    pub fn set_embedding_model(&mut self, model: &str, dimensions: usize) -> bool {
+        if self.embedding_model.is_some() {
+            return false;
+        }
+        self.embedding_model = Some(EmbeddingModelInfo {
+            model: model.to_string(),
+            dimensions,
+        });
+        true
+    }
+
+    // Check a model (and, once known, the dimensions it produced) against the recorded one
+    pub fn check_embedding_model(&self, model: &str, dimensions: Option<usize>) -> Result<(), String> {
+        let Some(recorded) = &self.embedding_model else {
+            return Ok(());
+        };
+        if recorded.model != model {
+            return Err(format!(
+                "Collection '{}' was embedded with model '{}', but the configured model is '{}'",
+                self.name, recorded.model, model
+            ));
+        }
+        if let Some(dims) = dimensions.filter(|d| *d != recorded.dimensions) {
+            return Err(format!(
+                "Collection '{}' holds {}-dimension embeddings from '{}', got {} dimensions",
+                self.name, recorded.dimensions, recorded.model, dims
+            ));
+        }
+        Ok(())
+    }
+    
     pub fn update_vector_count(&mut self, count: usize) {
         self.vector_count = count;
         self.touch();
@@ -4,7 +4,11 @@ mod document;
 pub mod collection;
 mod metadata;
 mod persistence;
+mod enrichment;
 pub mod wal;
-pub use document::Document;
+pub mod columnar;
+pub use document::{Document, CREATED_AT_KEY, EXTERNAL_ID_KEY, RESERVED_KEY_PREFIX, UPDATED_AT_KEY, VERSION_KEY, external_id_of, version_of};
 pub use collection::Collection;
-pub use metadata::CollectionMetadata;
+pub use metadata::{CollectionCounters, CollectionMetadata, EmbeddingModelInfo};
+pub use enrichment::{EnrichmentStage, detect_language, url_host};
+pub use persistence::{get_wal_path, load_metadata, io_uring_active, prefetch_ranges, PREFETCH_GAP};
@@ -0,0 +1,30 @@
+// Crash-safe file replacement for the files a checkpoint writes.
+// The new contents go to a temporary file that is fsynced and then renamed over the target, so a
+// reader (or a restart after a crash) sees either the old file or the new one, never a partial write.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::Result;
+
+pub fn write_atomic(path: &str, bytes: &[u8]) -> Result<()> {
+    let tmp = format!("{path}.tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    sync_parent_dir(path);
+    Ok(())
+}
+
+// Make the rename itself durable. Directories cannot be opened for syncing everywhere, so this is best effort.
+fn sync_parent_dir(path: &str) {
+    let parent = match Path::new(path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+}
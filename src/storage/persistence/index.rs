@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::error::Result;
+use crc32fast::hash as crc32;
 
 // Entry pointer: maps UUID to location in mmap file
 // This is NOT the VectorIndex trait (which is for search algorithms)
@@ -13,19 +14,32 @@ use crate::error::Result;
 pub struct EntryPointer {
     pub offset: u64,      // byte offset in file
     pub length: u32,      // size of serialized entry
+    pub checksum: Option<u32>, // CRC32 of the entry; None for entries written before checksums were kept
 }
 
 impl EntryPointer {
-    pub fn new(offset: u64, length: u32) -> Self {
-        Self { offset, length }
+    // Pointer to `bytes` written at `offset`
+    pub fn new(offset: u64, bytes: &[u8]) -> Self {
+        Self { offset, length: bytes.len() as u32, checksum: Some(crc32(bytes)) }
     }
+
+    // Whether `bytes` are the entry as it was written; entries without a checksum always pass
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        self.checksum.is_none_or(|checksum| crc32(bytes) == checksum)
+    }
+}
+
+// Pointer layout before checksums were kept
+#[derive(Deserialize)]
+struct LegacyEntryPointer {
+    offset: u64,
+    length: u32,
 }
 
 pub fn save_index(path: &str, index: &HashMap<Uuid, EntryPointer>) -> Result<()> {
     let index_path = format!("{}.index.db", path);
     let index_data = bincode::serialize(index)?;
-    std::fs::write(index_path, index_data)?;
-    Ok(())
+    super::write_atomic(&index_path, &index_data)
 }
 
 pub fn load_index(path: &str) -> Result<HashMap<Uuid, EntryPointer>> {
@@ -35,7 +49,13 @@ pub fn load_index(path: &str) -> Result<HashMap<Uuid, EntryPointer>> {
         use std::io::Read;
         let mut index_data = Vec::new();
         if index_file.read_to_end(&mut index_data).is_ok() {
-            Ok(bincode::deserialize(&index_data).unwrap_or_else(|_| HashMap::new()))
+            // A file from before checksums fails to decode as the current layout and is read as the old one
+            let index = bincode::deserialize(&index_data).unwrap_or_else(|_| {
+                bincode::deserialize::<HashMap<Uuid, LegacyEntryPointer>>(&index_data)
+                    .map(|legacy| legacy.into_iter().map(|(id, p)| (id, EntryPointer { offset: p.offset, length: p.length, checksum: None })).collect())
+                    .unwrap_or_default()
+            });
+            Ok(index)
         } else {
             Ok(HashMap::new())
         }
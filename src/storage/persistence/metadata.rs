@@ -16,8 +16,7 @@ pub fn get_metadata_path(collection_path: &str) -> String {
 pub fn save_metadata(collection_path: &str, metadata: &CollectionMetadata) -> Result<()> {
     let bytes = bincode::serialize(metadata)?;
     let metadata_path = get_metadata_path(collection_path);
-    fs::write(metadata_path, bytes)?;
-    Ok(())
+    super::write_atomic(&metadata_path, &bytes)
 }
 
 // Load collection metadata from disk
@@ -29,18 +28,153 @@ pub fn load_metadata(collection_path: &str) -> Result<Option<CollectionMetadata>
     }
     
     let bytes = fs::read(metadata_path)?;
-    let metadata: CollectionMetadata = bincode::deserialize(&bytes).map_err(|e| {
-        PiramidError::Storage(crate::error::storage::StorageError::CorruptedData(format!(
-            "Failed to read metadata: {e}"
-        )))
-    })?;
+    let metadata = match bincode::deserialize::<CollectionMetadata>(&bytes) {
+        Ok(metadata) => metadata,
+        // bincode has no field defaults, so an older file (without the trailing fields added since)
+        // fails to decode as the current layout and is read as the layout of its version
+        Err(e) => match (
+            bincode::deserialize::<CollectionMetadataV4>(&bytes),
+            bincode::deserialize::<CollectionMetadataV3>(&bytes),
+            bincode::deserialize::<CollectionMetadataV2>(&bytes),
+            bincode::deserialize::<CollectionMetadataV1>(&bytes),
+        ) {
+            (Ok(legacy), _, _, _) if legacy.schema_version == 4 => legacy.upgrade(),
+            (_, Ok(legacy), _, _) if legacy.schema_version == 3 => legacy.upgrade(),
+            (_, _, Ok(legacy), _) if legacy.schema_version == 2 => legacy.upgrade(),
+            (_, _, _, Ok(legacy)) if legacy.schema_version == 1 => legacy.upgrade(),
+            _ => {
+                return Err(PiramidError::Storage(crate::error::storage::StorageError::CorruptedData(format!(
+                    "Failed to read metadata: {e}"
+                ))));
+            }
+        },
+    };
     if metadata.schema_version != SCHEMA_VERSION {
         return Err(PiramidError::Storage(
             crate::error::storage::StorageError::CorruptedData(format!(
                 "Schema version mismatch: expected {}, found {}",
                 SCHEMA_VERSION, metadata.schema_version
             ))
-        ).into());
+        ));
     }
     Ok(Some(metadata))
 }
+
+// Layout of schema version 1, before embedding_model was added
+#[derive(serde::Deserialize)]
+struct CollectionMetadataV1 {
+    schema_version: u32,
+    name: String,
+    created_at: u64,
+    updated_at: u64,
+    dimensions: Option<usize>,
+    vector_count: usize,
+}
+
+impl CollectionMetadataV1 {
+    fn upgrade(self) -> CollectionMetadata {
+        CollectionMetadata {
+            schema_version: SCHEMA_VERSION,
+            name: self.name,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            dimensions: self.dimensions,
+            vector_count: self.vector_count,
+            embedding_model: None,
+            sealed_at: None,
+            counters: Default::default(),
+            counters_seq: 0,
+        }
+    }
+}
+
+// Layout of schema version 2, before sealed_at was added
+#[derive(serde::Deserialize)]
+struct CollectionMetadataV2 {
+    schema_version: u32,
+    name: String,
+    created_at: u64,
+    updated_at: u64,
+    dimensions: Option<usize>,
+    vector_count: usize,
+    embedding_model: Option<crate::storage::metadata::EmbeddingModelInfo>,
+}
+
+impl CollectionMetadataV2 {
+    fn upgrade(self) -> CollectionMetadata {
+        CollectionMetadata {
+            schema_version: SCHEMA_VERSION,
+            name: self.name,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            dimensions: self.dimensions,
+            vector_count: self.vector_count,
+            embedding_model: self.embedding_model,
+            sealed_at: None,
+            counters: Default::default(),
+            counters_seq: 0,
+        }
+    }
+}
+
+// Layout of schema version 3, before counters were added
+#[derive(serde::Deserialize)]
+struct CollectionMetadataV3 {
+    schema_version: u32,
+    name: String,
+    created_at: u64,
+    updated_at: u64,
+    dimensions: Option<usize>,
+    vector_count: usize,
+    embedding_model: Option<crate::storage::metadata::EmbeddingModelInfo>,
+    sealed_at: Option<u64>,
+}
+
+impl CollectionMetadataV3 {
+    fn upgrade(self) -> CollectionMetadata {
+        CollectionMetadata {
+            schema_version: SCHEMA_VERSION,
+            name: self.name,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            dimensions: self.dimensions,
+            vector_count: self.vector_count,
+            embedding_model: self.embedding_model,
+            sealed_at: self.sealed_at,
+            counters: Default::default(),
+            counters_seq: 0,
+        }
+    }
+}
+
+// Layout of schema version 4, before counters_seq was added. Its counters may already include some of
+// the WAL entries after the last checkpoint, which replay then counts once more.
+#[derive(serde::Deserialize)]
+struct CollectionMetadataV4 {
+    schema_version: u32,
+    name: String,
+    created_at: u64,
+    updated_at: u64,
+    dimensions: Option<usize>,
+    vector_count: usize,
+    embedding_model: Option<crate::storage::metadata::EmbeddingModelInfo>,
+    sealed_at: Option<u64>,
+    counters: crate::storage::metadata::CollectionCounters,
+}
+
+impl CollectionMetadataV4 {
+    fn upgrade(self) -> CollectionMetadata {
+        CollectionMetadata {
+            schema_version: SCHEMA_VERSION,
+            name: self.name,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            dimensions: self.dimensions,
+            vector_count: self.vector_count,
+            embedding_model: self.embedding_model,
+            sealed_at: self.sealed_at,
+            counters: self.counters,
+            counters_seq: 0,
+        }
+    }
+}
@@ -18,6 +18,11 @@ pub fn create_mmap(file: &File) -> Result<MmapMut> {
     unsafe { Ok(MmapOptions::new().map_mut(file)?) }
 }
 
+// Anonymous memory map of `len` bytes, backed by no file; used by ephemeral collections in place of the data file
+pub fn create_anon_mmap(len: u64) -> Result<MmapMut> {
+    Ok(MmapOptions::new().len(len as usize).map_anon()?)
+}
+
 /// Touch each page of the mmap to fault it into memory.
 pub fn warm_mmap(mmap: &MmapMut) {
     let len = mmap.len();
@@ -38,6 +43,42 @@ pub fn warm_mmap(mmap: &MmapMut) {
     std::hint::black_box(last);
 }
 
+// Entries closer than this are hinted as one range: the kernel reads whole pages anyway
+pub const PREFETCH_GAP: usize = 4096;
+
+// Byte ranges covering the (offset, length) entries, in file order, with entries less than `gap`
+// apart merged into one range
+pub fn prefetch_ranges(entries: &[(u64, usize)], gap: usize) -> Vec<(usize, usize)> {
+    let mut sorted: Vec<(usize, usize)> = entries.iter().map(|&(offset, len)| (offset as usize, len)).collect();
+    sorted.sort_unstable();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (offset, len) in sorted {
+        match ranges.last_mut() {
+            Some((start, range_len)) if offset <= (*start + *range_len).saturating_add(gap) => {
+                *range_len = (*range_len).max(offset + len - *start);
+            }
+            _ => ranges.push((offset, len)),
+        }
+    }
+    ranges
+}
+
+// Ask the kernel to start reading the ranges' pages in (MADV_WILLNEED), so the reads that follow find
+// them in memory instead of faulting on each one in turn. Only a hint: ranges past the end of the map
+// are skipped and failures ignored, and it does nothing off Unix. Returns the ranges hinted.
+pub fn prefetch_mmap(mmap: &MmapMut, ranges: &[(usize, usize)]) -> usize {
+    let in_map = ranges.iter().filter(|&&(offset, len)| offset.checked_add(len).is_some_and(|end| end <= mmap.len()));
+    #[cfg(unix)]
+    {
+        in_map.filter(|&&(offset, len)| mmap.advise_range(memmap2::Advice::WillNeed, offset, len).is_ok()).count()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = in_map;
+        0
+    }
+}
+
 pub fn grow_mmap_if_needed(
     mmap: &mut Option<MmapMut>,
     file: &File,
@@ -52,3 +93,15 @@ pub fn grow_mmap_if_needed(
     } // If the required size is within the current size, we can simply continue using the existing memory map without any changes.
     Ok(())
 }
+
+// Anonymous counterpart of grow_mmap_if_needed: there is no file to extend, so the contents move to a
+// new map twice the required size
+pub fn grow_anon_mmap_if_needed(mmap: &mut Option<MmapMut>, required_size: u64) -> Result<()> {
+    let current = mmap.as_ref().unwrap();
+    if required_size > current.len() as u64 {
+        let mut grown = create_anon_mmap(required_size * 2)?;
+        grown[..current.len()].copy_from_slice(current);
+        *mmap = Some(grown);
+    }
+    Ok(())
+}
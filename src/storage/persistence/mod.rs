@@ -2,9 +2,15 @@ mod index;
 mod mmap;
 mod vector_index;
 mod metadata;
+mod file;
+mod reader;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
 
 pub use index::{EntryPointer, save_index, load_index, get_wal_path};
-pub use mmap::{ensure_file_size, create_mmap, grow_mmap_if_needed, warm_mmap};
-pub use vector_index::{save_vector_index, load_vector_index, warm_file};
+pub use mmap::{ensure_file_size, create_mmap, create_anon_mmap, grow_mmap_if_needed, grow_anon_mmap_if_needed, warm_mmap, prefetch_ranges, prefetch_mmap, PREFETCH_GAP};
+pub use vector_index::{save_vector_index, save_packed_vector_index, load_vector_index, quarantine_vector_index, clone_vector_index, warm_file};
 pub use metadata::{save_metadata, load_metadata};
+pub use file::write_atomic;
+pub use reader::{read_entries, io_uring_active};
 
@@ -0,0 +1,83 @@
+// Explicit reads of data file entries, the alternative to faulting them in through the mmap.
+// With the `io-uring` feature on Linux a batch goes to this thread's io_uring ring with every read in
+// flight at once; without it, or when the kernel refuses to set up a ring, each entry is a plain
+// positioned read.
+
+use std::fs::File;
+
+// Reads submitted to a ring at once; larger batches go in several rounds
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+const RING_ENTRIES: u32 = 64;
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+thread_local! {
+    static RING: std::cell::RefCell<Option<super::uring::Ring>> = std::cell::RefCell::new(new_ring());
+}
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+fn new_ring() -> Option<super::uring::Ring> {
+    match super::uring::Ring::new(RING_ENTRIES) {
+        Ok(ring) => Some(ring),
+        Err(e) => {
+            static WARNED: std::sync::Once = std::sync::Once::new();
+            WARNED.call_once(|| tracing::warn!(error = %e, "io_uring_unavailable_using_pread"));
+            None
+        }
+    }
+}
+
+// Whether reads on this thread go through io_uring
+pub fn io_uring_active() -> bool {
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    {
+        RING.with(|ring| ring.borrow().is_some())
+    }
+    #[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+    {
+        false
+    }
+}
+
+// Bytes of each (offset, length) entry, None for an entry that could not be read in full
+pub fn read_entries(file: &File, entries: &[(u64, usize)]) -> Vec<Option<Vec<u8>>> {
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    if entries.len() > 1 {
+        use std::os::fd::AsRawFd;
+        let batched = RING.with(|ring| {
+            let mut ring = ring.borrow_mut();
+            let ring = ring.as_mut()?;
+            let reads: Vec<(u64, Vec<u8>)> = entries.iter().map(|&(offset, len)| (offset, vec![0u8; len])).collect();
+            let results = ring.read_batch(file.as_raw_fd(), reads);
+            Some(entries.iter().zip(results).map(|(&(offset, len), result)| {
+                // A read the ring failed is done again with pread
+                let (mut buf, done) = result.unwrap_or_else(|_| (vec![0u8; len], 0));
+                // A short read (entry straddling a page the kernel had not read ahead) finishes with pread
+                if done < buf.len() {
+                    read_at(file, offset + done as u64, &mut buf[done..])?;
+                }
+                Some(buf)
+            }).collect())
+        });
+        if let Some(batched) = batched {
+            return batched;
+        }
+    }
+    entries.iter().map(|&(offset, len)| {
+        let mut buf = vec![0u8; len];
+        read_at(file, offset, &mut buf).map(|_| buf)
+    }).collect()
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, offset: u64, buf: &mut [u8]) -> Option<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buf, offset).ok()
+}
+
+#[cfg(not(unix))]
+fn read_at(file: &File, offset: u64, buf: &mut [u8]) -> Option<()> {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut file = file.try_clone().ok()?;
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(buf).ok()
+}
@@ -0,0 +1,119 @@
+// io_uring ring for batches of positioned reads (Linux, `io-uring` feature), on the `io-uring` crate.
+// Only what the document read path needs: one ring per thread, read submissions and a blocking wait
+// for their completions. Submitting a whole batch before waiting keeps every read of the batch in
+// flight at once, where touching the mmap would fault the pages in one after another.
+//
+// The kernel writes into a read's buffer until its completion is reaped, so a batch whose wait fails
+// does not give its buffers back: the ring keeps them until every read it queued has completed (the
+// next batch waits for that first), and leaks them if it is dropped before then. Completions carry
+// their batch and index; ones of an earlier batch, or out of range, are dropped.
+
+use std::io;
+use std::os::fd::RawFd;
+
+use io_uring::{opcode, types, IoUring};
+
+pub struct Ring {
+    ring: IoUring,
+    batch: u32, // tags the user_data of the current batch's reads
+    outstanding: usize, // reads queued and not completed yet
+    orphans: Vec<Vec<u8>>, // buffers of failed batches the kernel may still write into
+}
+
+impl Ring {
+    pub fn new(entries: u32) -> io::Result<Self> {
+        Ok(Ring { ring: IoUring::new(entries)?, batch: 0, outstanding: 0, orphans: Vec::new() })
+    }
+
+    // Read `buf.len()` bytes at each offset of `fd`; gives back each buffer with its byte count, or the
+    // error. Reads may come back short, as with pread.
+    pub fn read_batch(&mut self, fd: RawFd, reads: Vec<(u64, Vec<u8>)>) -> Vec<io::Result<(Vec<u8>, usize)>> {
+        let capacity = self.ring.params().sq_entries() as usize;
+        let mut results = Vec::with_capacity(reads.len());
+        let mut reads = reads.into_iter().peekable();
+        while reads.peek().is_some() {
+            let mut chunk: Vec<(u64, Vec<u8>)> = reads.by_ref().take(capacity).collect();
+            match self.read_chunk(fd, &mut chunk) {
+                Ok(done) => results.extend(chunk.into_iter().zip(done).map(|((_, buf), done)| done.map(|n| (buf, n)))),
+                Err(err) => {
+                    results.extend(chunk.iter().map(|_| Err(io::Error::new(err.kind(), err.to_string()))));
+                    self.orphans.extend(chunk.into_iter().map(|(_, buf)| buf));
+                }
+            }
+        }
+        results
+    }
+
+    fn read_chunk(&mut self, fd: RawFd, chunk: &mut [(u64, Vec<u8>)]) -> io::Result<Vec<io::Result<usize>>> {
+        self.settle()?;
+        self.batch = self.batch.wrapping_add(1);
+        for (i, (offset, buf)) in chunk.iter_mut().enumerate() {
+            let read = opcode::Read::new(types::Fd(fd), buf.as_mut_ptr(), buf.len() as u32)
+                .offset(*offset)
+                .build()
+                .user_data((self.batch as u64) << 32 | i as u64);
+            // SAFETY: the buffer stays allocated until the read completes: it goes back to the caller
+            // after its completion is reaped, or into `orphans` when the wait fails first
+            unsafe { self.ring.submission().push(&read) }
+                .map_err(|_| io::Error::other("io_uring submission queue full"))?;
+            self.outstanding += 1;
+        }
+        let mut results: Vec<Option<io::Result<usize>>> = chunk.iter().map(|_| None).collect();
+        let mut pending = chunk.len();
+        while pending > 0 {
+            self.enter(1)?;
+            pending = pending.saturating_sub(self.reap(&mut results));
+        }
+        Ok(results.into_iter().map(|result| result.unwrap_or_else(|| Err(io::Error::other("read did not complete")))).collect())
+    }
+
+    // Wait for the reads of failed batches, then free their buffers
+    fn settle(&mut self) -> io::Result<()> {
+        while self.outstanding > 0 {
+            self.enter(self.outstanding)?;
+            self.reap(&mut []);
+        }
+        Ok(())
+    }
+
+    // Submit what is queued and wait for `want` completions
+    fn enter(&mut self, want: usize) -> io::Result<()> {
+        loop {
+            match self.ring.submit_and_wait(want) {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                result => return result.map(|_| ()),
+            }
+        }
+    }
+
+    // Move the current batch's finished reads into `results`; returns how many there were
+    fn reap(&mut self, results: &mut [Option<io::Result<usize>>]) -> usize {
+        let mut reaped = 0;
+        for cqe in self.ring.completion() {
+            self.outstanding = self.outstanding.saturating_sub(1);
+            let (batch, index) = ((cqe.user_data() >> 32) as u32, cqe.user_data() as u32 as usize);
+            if batch != self.batch {
+                continue;
+            }
+            let Some(result) = results.get_mut(index).filter(|result| result.is_none()) else { continue };
+            *result = Some(if cqe.result() < 0 {
+                Err(io::Error::from_raw_os_error(-cqe.result()))
+            } else {
+                Ok(cqe.result() as usize)
+            });
+            reaped += 1;
+        }
+        if self.outstanding == 0 {
+            self.orphans.clear();
+        }
+        reaped
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        if self.settle().is_err() {
+            std::mem::forget(std::mem::take(&mut self.orphans));
+        }
+    }
+}
@@ -4,18 +4,23 @@
 use std::fs;
 use std::path::Path;
 use std::io::{Read, BufReader};
-use crate::error::Result;
-use crate::index::{SerializableIndex, VectorIndex, HnswIndex, IvfIndex, FlatIndex};
+use crate::error::{Result, StorageError};
+use crate::index::{SerializableIndex, VectorIndex, HnswIndex, IvfIndex, FlatIndex, PackedHnsw, ShardedHnsw};
 
 // Get the index file path for a collection
 pub fn get_index_file_path(collection_path: &str) -> String {
     format!("{}.vecindex.db", collection_path)
 }
 
-// Save any index to disk
-pub fn save_vector_index(collection_path: &str, index: &dyn VectorIndex) -> Result<()> {
-    // Create the appropriate SerializableIndex variant
-    let serializable = match index.index_type() {
+// Snapshot any index into its serializable (concrete) form
+fn to_serializable(index: &dyn VectorIndex) -> SerializableIndex {
+    match index.index_type() {
+        // A sharded graph reports itself as HNSW too; it is told apart by its shard count
+        crate::index::IndexType::Hnsw if index.shards() > 1 => {
+            let sharded_ptr = index as *const dyn VectorIndex as *const ShardedHnsw;
+            let sharded_ref = unsafe { &*sharded_ptr };
+            SerializableIndex::ShardedHnsw(sharded_ref.clone())
+        }
         crate::index::IndexType::Hnsw => {
             // Downcast to concrete type
             let hnsw_ptr = index as *const dyn VectorIndex as *const HnswIndex;
@@ -37,12 +42,32 @@ pub fn save_vector_index(collection_path: &str, index: &dyn VectorIndex) -> Resu
             let flat_ref = unsafe { &*flat_ptr };
             SerializableIndex::Flat(flat_ref.clone())
         }
-    };
+    }
+}
+
+// Deep copy of an index (used for in-memory read replicas)
+pub fn clone_vector_index(index: &dyn VectorIndex) -> Box<dyn VectorIndex> {
+    to_serializable(index).to_trait_object()
+}
+
+// Save any index to disk
+pub fn save_vector_index(collection_path: &str, index: &dyn VectorIndex) -> Result<()> {
+    let serializable = to_serializable(index);
     
     let bytes = bincode::serialize(&serializable)?;
     let index_path = get_index_file_path(collection_path);
-    fs::write(index_path, bytes)?;
-    Ok(())
+    super::write_atomic(&index_path, &bytes)
+}
+
+// For a sealed collection: an HNSW graph goes to disk in its packed, read-only layout; other
+// index types are written as usual. Loading reads either.
+pub fn save_packed_vector_index(collection_path: &str, index: &dyn VectorIndex) -> Result<()> {
+    let serializable = match to_serializable(index) {
+        SerializableIndex::Hnsw(hnsw) => SerializableIndex::PackedHnsw(PackedHnsw::pack(&hnsw)),
+        other => other,
+    };
+    let bytes = bincode::serialize(&serializable)?;
+    super::write_atomic(&get_index_file_path(collection_path), &bytes)
 }
 
 
@@ -72,7 +97,18 @@ pub fn load_vector_index(collection_path: &str) -> Result<Option<Box<dyn VectorI
         return Ok(None);
     }
     
-    let bytes = fs::read(index_path)?;
-    let serializable: SerializableIndex = bincode::deserialize(&bytes)?;
+    let bytes = fs::read(&index_path)?;
+    let serializable: SerializableIndex = bincode::deserialize(&bytes)
+        .map_err(|e| StorageError::CorruptedIndex(format!("{}: {}", index_path, e)))?;
     Ok(Some(serializable.to_trait_object()))
 }
+
+// Move an index file that cannot be read out of the way, to `<index file>.corrupt-<unix secs>`, so the
+// collection opens without it and the file is kept for inspection. Returns where it went.
+pub fn quarantine_vector_index(collection_path: &str) -> Result<String> {
+    let index_path = get_index_file_path(collection_path);
+    let ts = crate::testing::clock::now_secs();
+    let quarantined = format!("{}.corrupt-{}", index_path, ts);
+    fs::rename(&index_path, &quarantined)?;
+    Ok(quarantined)
+}
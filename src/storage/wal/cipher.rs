@@ -0,0 +1,31 @@
+// ChaCha20-Poly1305 (RFC 8439) for encrypted WAL entries, from the `chacha20poly1305` crate.
+// `seal` returns nonce || ciphertext || tag with a random 96-bit nonce; `open` checks the tag
+// before decrypting anything. Associated data is authenticated but not encrypted.
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+pub(super) fn seal(key: &[u8; 32], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let sealed = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+        .expect("ChaCha20-Poly1305 encrypts any WAL entry");
+    let mut out = Vec::with_capacity(NONCE_LEN + sealed.len());
+    out.extend_from_slice(&nonce);
+    out.extend(sealed);
+    out
+}
+
+// None when the data is too short or fails authentication (wrong key, or altered)
+pub(super) fn open(key: &[u8; 32], aad: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+    if sealed.len() < NONCE_LEN + TAG_LEN {
+        return None;
+    }
+    let (nonce, rest) = sealed.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: rest, aad })
+        .ok()
+}
@@ -0,0 +1,114 @@
+// Per-entry compression and encryption of WAL lines, as set by `wal.compression` and
+// `wal.encryption_key`.
+// A plain entry is its JSON on one line, which is all a WAL held before entries could be encoded.
+// An encoded entry is `~` followed by the base64 of a frame:
+//
+// - one flags byte: bits 0-1 the compression (0 none, 1 lz4, 2 zstd), bit 2 encrypted
+// - compressed: the JSON's length (u32 LE) and the compressed bytes; otherwise the JSON itself
+// - encrypted: that body sealed with ChaCha20-Poly1305 (nonce || ciphertext || tag), the flags
+//   byte as associated data
+//
+// Every line says how it was written, so replay reads plain logs from before encoding was
+// switched on, and files that mix both after the settings change. Entries that compression would
+// not shrink are stored uncompressed.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+use crate::config::{WalCompression, WalConfig, WalKey};
+use crate::error::{PiramidError, Result};
+use super::cipher;
+use super::entry::WalEntry;
+
+const ENCODED_PREFIX: char = '~';
+const ENCRYPTED: u8 = 0b100;
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WalCodec {
+    compression: WalCompression,
+    key: Option<WalKey>,
+}
+
+impl WalCodec {
+    pub fn new(config: &WalConfig) -> Self {
+        Self { compression: config.compression, key: config.encryption_key }
+    }
+
+    pub fn compression(&self) -> WalCompression {
+        self.compression
+    }
+
+    pub fn encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    pub(super) fn encode(&self, entry: &WalEntry) -> Result<String> {
+        let json = serde_json::to_string(entry)?;
+        if self.compression == WalCompression::None && self.key.is_none() {
+            return Ok(json);
+        }
+
+        let compressed = match self.compression {
+            WalCompression::None => None,
+            WalCompression::Lz4 => Some((1, lz4_flex::block::compress(json.as_bytes()))),
+            WalCompression::Zstd => Some((2, zstd::bulk::compress(json.as_bytes(), ZSTD_LEVEL)?)),
+        };
+        let (mut flags, mut body) = match compressed {
+            Some((codec, bytes)) if bytes.len() + 4 < json.len() => {
+                let mut body = (json.len() as u32).to_le_bytes().to_vec();
+                body.extend(bytes);
+                (codec, body)
+            }
+            _ if self.key.is_none() => return Ok(json),
+            _ => (0, json.into_bytes()),
+        };
+        if let Some(key) = &self.key {
+            flags |= ENCRYPTED;
+            body = cipher::seal(&key.0, &[flags], &body);
+        }
+        let mut frame = vec![flags];
+        frame.extend(body);
+        let mut line = String::with_capacity(frame.len() * 4 / 3 + 5);
+        line.push(ENCODED_PREFIX);
+        BASE64.encode_string(&frame, &mut line);
+        Ok(line)
+    }
+
+    pub(super) fn decode(&self, line: &str) -> Result<WalEntry> {
+        let Some(encoded) = line.strip_prefix(ENCODED_PREFIX) else {
+            return Ok(serde_json::from_str(line)?);
+        };
+        let frame = BASE64.decode(encoded).map_err(|_| PiramidError::other("Corrupt WAL entry: invalid base64"))?;
+        let (&flags, body) = frame.split_first().ok_or_else(|| PiramidError::other("Corrupt WAL entry: empty frame"))?;
+        let body = if flags & ENCRYPTED != 0 {
+            let key = self.key.as_ref().ok_or_else(|| {
+                PiramidError::other("WAL entry is encrypted but no WAL encryption key is configured")
+            })?;
+            cipher::open(&key.0, &[flags], body).ok_or_else(|| {
+                PiramidError::other("WAL entry failed authentication: wrong encryption key or corrupt entry")
+            })?
+        } else {
+            body.to_vec()
+        };
+
+        let json = match flags & 0b11 {
+            0 => body,
+            codec => {
+                let (len, compressed) = body
+                    .split_first_chunk::<4>()
+                    .ok_or_else(|| PiramidError::other("Corrupt WAL entry: missing length"))?;
+                let len = u32::from_le_bytes(*len) as usize;
+                let json = match codec {
+                    1 => lz4_flex::block::decompress(compressed, len).map_err(|e| e.to_string()),
+                    2 => zstd::bulk::decompress(compressed, len).map_err(|e| e.to_string()).and_then(|json| {
+                        if json.len() == len { Ok(json) } else { Err("length mismatch".into()) }
+                    }),
+                    _ => Err(format!("unknown compression {}", codec)),
+                };
+                json.map_err(|e| PiramidError::other(format!("Corrupt WAL entry: {}", e)))?
+            }
+        };
+        Ok(serde_json::from_slice(&json)?)
+    }
+}
@@ -11,3 +11,14 @@ pub enum WalEntry{
     Delete { id: Uuid, seq : u64 },
     Checkpoint { timestamp: u64,   seq : u64 },
 }
+
+impl WalEntry {
+    pub fn seq(&self) -> u64 {
+        match self {
+            WalEntry::Insert { seq, .. }
+            | WalEntry::Update { seq, .. }
+            | WalEntry::Delete { seq, .. }
+            | WalEntry::Checkpoint { seq, .. } => *seq,
+        }
+    }
+}
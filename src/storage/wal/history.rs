@@ -0,0 +1,248 @@
+// Retained WAL history for point-in-time ("as of") reads.
+// Without history a checkpoint truncates the WAL. With a retention configured the closed file is
+// moved into `{collection}.wal.hist/` as a segment instead, so the collection can be rebuilt as it
+// was at any sequence number that is still covered. The directory holds:
+//
+// - base.wal                                  full state at `base_seq`: a Checkpoint{timestamp, base_seq}
+//                                             followed by one Insert per live document
+// - seg-{last_seq:020}-{closed_at}.wal        a closed WAL file covering (previous last_seq, last_seq]
+//
+// Both use the regular WAL format (header line + JSON entries, encoded as the WAL is). Segments closed more than
+// `retention_secs` ago are folded into the base, which moves the oldest readable point forward.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::error::Result;
+use super::codec::WalCodec;
+use super::entry::WalEntry;
+use super::log::{read_entries, write_entries};
+
+const BASE_FILE: &str = "base.wal";
+
+pub fn get_history_dir(collection_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.wal.hist", collection_path))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistorySummary {
+    pub retention_secs: u64,
+    pub base_seq: u64,
+    pub base_timestamp: u64,
+    pub segments: usize,
+    pub last_archived_seq: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    last_seq: u64,
+    closed_at: u64,
+    path: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalHistory {
+    dir: PathBuf,
+    retention_secs: u64,
+    codec: WalCodec,
+}
+
+impl WalHistory {
+    pub fn open(collection_path: &str, retention_secs: u64, codec: WalCodec) -> Result<Self> {
+        let dir = get_history_dir(collection_path);
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, retention_secs, codec })
+    }
+
+    pub fn retention_secs(&self) -> u64 {
+        self.retention_secs
+    }
+
+    fn base_path(&self) -> PathBuf {
+        self.dir.join(BASE_FILE)
+    }
+
+    pub fn has_base(&self) -> bool {
+        self.base_path().exists()
+    }
+
+    // The base starts with its Checkpoint, so only the first entry has to be read
+    fn base_checkpoint(&self) -> Result<Option<(u64, u64)>> {
+        let file = match fs::File::open(self.base_path()) {
+            Ok(file) => file,
+            Err(_) => return Ok(None),
+        };
+        for line in BufReader::new(file).lines().skip(1) {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            if let WalEntry::Checkpoint { timestamp, seq } = self.codec.decode(&line)? {
+                return Ok(Some((seq, timestamp)));
+            }
+            break;
+        }
+        Ok(None)
+    }
+
+    pub fn base_seq(&self) -> Result<u64> {
+        Ok(self.base_checkpoint()?.map(|(seq, _)| seq).unwrap_or(0))
+    }
+
+    fn segments(&self) -> Result<Vec<Segment>> {
+        let mut segments = Vec::new();
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+            let Some(stem) = name.strip_prefix("seg-").and_then(|n| n.strip_suffix(".wal")) else { continue };
+            let Some((last_seq, closed_at)) = stem.split_once('-') else { continue };
+            if let (Ok(last_seq), Ok(closed_at)) = (last_seq.parse(), closed_at.parse()) {
+                segments.push(Segment { last_seq, closed_at, path });
+            }
+        }
+        segments.sort_by_key(|s| s.last_seq);
+        Ok(segments)
+    }
+
+    // Highest sequence number covered by the base and the archived segments
+    pub fn last_seq(&self) -> Result<u64> {
+        let base = self.base_seq()?;
+        Ok(self.segments()?.last().map(|s| s.last_seq.max(base)).unwrap_or(base))
+    }
+
+    // Replace the base with the given documents (Insert entries) as the state at `base_seq`.
+    // Segments it already covers are removed.
+    pub fn write_base(&self, base_seq: u64, timestamp: u64, documents: Vec<WalEntry>) -> Result<()> {
+        let mut entries = Vec::with_capacity(documents.len() + 1);
+        entries.push(WalEntry::Checkpoint { timestamp, seq: base_seq });
+        entries.extend(documents);
+
+        let tmp = self.dir.join(format!("{BASE_FILE}.tmp"));
+        write_entries(&tmp, &entries, &self.codec)?;
+        fs::rename(&tmp, self.base_path())?;
+
+        for segment in self.segments()? {
+            if segment.last_seq <= base_seq {
+                let _ = fs::remove_file(&segment.path);
+            }
+        }
+        Ok(())
+    }
+
+    // Move a closed WAL file into the history; the caller re-creates the live file afterwards
+    pub fn archive(&self, wal_path: &Path, last_seq: u64, closed_at: u64) -> Result<()> {
+        if !wal_path.exists() {
+            return Ok(());
+        }
+        let target = self.dir.join(format!("seg-{last_seq:020}-{closed_at}.wal"));
+        fs::rename(wal_path, target)?;
+        Ok(())
+    }
+
+    // Fold segments that have outlived the retention into the base
+    pub fn prune(&self, now: u64) -> Result<()> {
+        let expired: Vec<Segment> = self
+            .segments()?
+            .into_iter()
+            .take_while(|s| s.closed_at.saturating_add(self.retention_secs) <= now)
+            .collect();
+        let Some(newest) = expired.last() else { return Ok(()) };
+
+        let mut entries = if self.has_base() { read_entries(&self.base_path(), &self.codec)? } else { Vec::new() };
+        for segment in &expired {
+            entries.extend(read_entries(&segment.path, &self.codec)?);
+        }
+        let documents = fold_entries(entries, newest.last_seq);
+        self.write_base(newest.last_seq, newest.closed_at, documents)
+    }
+
+    // Base + archived entries up to `target`, in sequence order. Entries still in the live WAL
+    // are not included.
+    pub fn entries_until(&self, target: u64) -> Result<Vec<WalEntry>> {
+        let mut entries = if self.has_base() { read_entries(&self.base_path(), &self.codec)? } else { Vec::new() };
+        let mut previous = self.base_seq()?;
+        for segment in self.segments()? {
+            if segment.last_seq <= previous {
+                continue;
+            }
+            entries.extend(read_entries(&segment.path, &self.codec)?);
+            if segment.last_seq >= target {
+                break;
+            }
+            previous = segment.last_seq;
+        }
+        entries.retain(|e| e.seq() <= target);
+        Ok(entries)
+    }
+
+    // Archived entries after `after`, in sequence order; the base (folded state, not changes) is left out
+    pub fn entries_after(&self, after: u64) -> Result<Vec<WalEntry>> {
+        let mut entries = Vec::new();
+        for segment in self.segments()? {
+            if segment.last_seq > after {
+                entries.extend(read_entries(&segment.path, &self.codec)?);
+            }
+        }
+        entries.retain(|e| e.seq() > after);
+        Ok(entries)
+    }
+
+    // Points in time that can be resolved to a sequence number: the base, every checkpoint and the
+    // close time of every segment, as (timestamp, seq) pairs
+    pub fn time_anchors(&self) -> Result<Vec<(u64, u64)>> {
+        let mut anchors = Vec::new();
+        if let Some((seq, timestamp)) = self.base_checkpoint()? {
+            anchors.push((timestamp, seq));
+        }
+        for segment in self.segments()? {
+            for entry in read_entries(&segment.path, &self.codec)? {
+                if let WalEntry::Checkpoint { timestamp, seq } = entry {
+                    anchors.push((timestamp, seq));
+                }
+            }
+            anchors.push((segment.closed_at, segment.last_seq));
+        }
+        Ok(anchors)
+    }
+
+    pub fn summary(&self) -> Result<HistorySummary> {
+        let (base_seq, base_timestamp) = self.base_checkpoint()?.unwrap_or((0, 0));
+        let segments = self.segments()?;
+        Ok(HistorySummary {
+            retention_secs: self.retention_secs,
+            base_seq,
+            base_timestamp,
+            segments: segments.len(),
+            last_archived_seq: segments.last().map(|s| s.last_seq).unwrap_or(base_seq).max(base_seq),
+        })
+    }
+}
+
+// Collapse a run of WAL entries into the documents that are live at `target`, one Insert per id.
+// Updates replace the document, deletes drop it, checkpoints carry no data.
+pub fn fold_entries(entries: impl IntoIterator<Item = WalEntry>, target: u64) -> Vec<WalEntry> {
+    let mut live: HashMap<Uuid, WalEntry> = HashMap::new();
+    for entry in entries {
+        if entry.seq() > target {
+            continue;
+        }
+        match entry {
+            WalEntry::Insert { id, vector, text, metadata, seq }
+            | WalEntry::Update { id, vector, text, metadata, seq } => {
+                live.insert(id, WalEntry::Insert { id, vector, text, metadata, seq });
+            }
+            WalEntry::Delete { id, .. } => {
+                live.remove(&id);
+            }
+            WalEntry::Checkpoint { .. } => {}
+        }
+    }
+    let mut documents: Vec<WalEntry> = live.into_values().collect();
+    documents.sort_by_key(|e| e.seq());
+    documents
+}
@@ -1,36 +1,81 @@
 //  This module provides a simple JSON-based WAL that supports appending entries, replaying entries from a certain sequence number, and checkpointing. The WAL is designed to be durable and efficient, with support for rotation to prevent unbounded growth.
 
 use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, BufWriter, Write};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 
-use crate::error::Result;
+use crate::config::{WalCompression, WalRetry};
+use crate::error::{Result, StorageError};
+use crate::testing::fault::Cut;
+use super::codec::WalCodec;
 use super::entry::WalEntry;
-// The WAL file starts with a header line containing the version number, followed by one JSON-serialized entry per line. Each entry includes a sequence number (seq) that is assigned when the entry is logged. The replay method reads the WAL file and returns all entries with a sequence number greater than a specified minimum sequence number (min_seq). The log method appends a new entry to the WAL file, automatically assigning it the next sequence number. The checkpoint method logs a special checkpoint entry that can be used to indicate a consistent state of the collection, allowing older entries to be safely discarded after checkpointing. The rotate method allows for rotating the WAL file by closing the current one and starting a new, empty file, which is typically done after checkpointing to prevent the WAL from growing indefinitely.
+use super::history::WalHistory;
+use super::segments::WalSegments;
+// The WAL file starts with a header line containing the version number, followed by one JSON-serialized entry per line (compressed and/or encrypted when configured, see codec.rs). Each entry includes a sequence number (seq) that is assigned when the entry is logged. The replay method reads the WAL file and returns all entries with a sequence number greater than a specified minimum sequence number (min_seq). The log method appends a new entry to the WAL file, automatically assigning it the next sequence number. The checkpoint method logs a special checkpoint entry that can be used to indicate a consistent state of the collection, allowing older entries to be safely discarded after checkpointing. The rotate method allows for rotating the WAL file by closing the current one and starting a new, empty file, which is typically done after checkpointing to prevent the WAL from growing indefinitely.
+// Version 2 files may hold encoded entries (see codec.rs); the header records how the file was
+// started, while each line says how it was written itself.
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 struct WalHeader {
     version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    compression: Option<WalCompression>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    encrypted: bool,
 }
 
-const WAL_VERSION: u32 = 1;
+impl WalHeader {
+    // Plain logs keep the version 1 header so older builds can still read them
+    fn for_codec(codec: &WalCodec) -> Self {
+        if codec.compression() == WalCompression::None && !codec.encrypted() {
+            return WalHeader { version: 1, compression: None, encrypted: false };
+        }
+        WalHeader { version: WAL_VERSION, compression: Some(codec.compression()), encrypted: codec.encrypted() }
+    }
+}
+
+const WAL_VERSION: u32 = 2;
 
 pub struct Wal {
     file: Option<BufWriter<File>>,
     path: PathBuf,
     pub next_seq: u64,
+    history: Option<WalHistory>,
+    codec: WalCodec,
+    // Change tracking for readers of the log (see collection/changes.rs): the last insert, update or
+    // delete logged, the last one in the sealed file, and the last one in files already dropped.
+    // Unknown until this process seals or rotates the log.
+    last_data_seq: u64,
+    sealed_data_seq: Option<u64>,
+    dropped_data_seq: Option<u64>,
+    retry: WalRetry,
+    // Where a failed append began, while it has not been cut back out of the file yet
+    torn_at: Option<u64>,
+    // Closed segments (see segments.rs) and the live file size that closes one; 0 never does
+    segments: WalSegments,
+    max_segment_bytes: u64,
 }
 
 impl Wal {
     /// Create a WAL writer starting at the provided sequence.
-    pub fn new(path: PathBuf, next_seq: u64) -> Result<Self> {
+    pub fn new(path: PathBuf, next_seq: u64, codec: WalCodec) -> Result<Self> {
+        truncate_torn_tail(&path)?;
         let file = OpenOptions::new()
             .create(true)
             .append(true)
             .open(&path)?;
         let mut wal = Wal {
             file: Some(BufWriter::new(file)),
+            segments: WalSegments::new(&path, codec),
+            max_segment_bytes: 0,
             path,
             next_seq,
+            history: None,
+            codec,
+            last_data_seq: next_seq.saturating_sub(1),
+            sealed_data_seq: None,
+            dropped_data_seq: None,
+            retry: WalRetry::default(),
+            torn_at: None,
         };
         wal.ensure_header()?;
         Ok(wal)
@@ -40,8 +85,17 @@ impl Wal {
     pub fn disabled(path: PathBuf, next_seq: u64) -> Result<Self> {
         Ok(Wal {
             file: None,
+            segments: WalSegments::new(&path, WalCodec::default()),
+            max_segment_bytes: 0,
             path,
             next_seq,
+            history: None,
+            codec: WalCodec::default(),
+            last_data_seq: next_seq.saturating_sub(1),
+            sealed_data_seq: None,
+            dropped_data_seq: None,
+            retry: WalRetry::default(),
+            torn_at: None,
         })
     }  
 
@@ -51,57 +105,145 @@ impl Wal {
             return Ok(Vec::new());
         }
         
-        let file = File::open(&self.path)?;
-        let reader = BufReader::new(file);
-        let mut entries = Vec::new();
-        
-        for line in reader.lines() {
-            let line = line?;
-            if line.is_empty() {
-                continue;
-            }
-            // Skip header if present (and validate version)
-            if let Ok(header) = serde_json::from_str::<WalHeader>(&line) {
-                if header.version != WAL_VERSION {
-                    return Err(crate::error::PiramidError::other(format!(
-                        "Unsupported WAL version {}, expected {}",
-                        header.version, WAL_VERSION
-                    )));
-                }
-                continue;
-            }
-            let entry: WalEntry = serde_json::from_str(&line)?;
-            let entry_seq = match &entry {
+        // Closed segments and the entries of a checkpoint still being written out come first. The
+        // files are decoded in parallel. A crash while sealing can leave an entry in two files, so
+        // duplicates are dropped by sequence number.
+        use rayon::prelude::*;
+        let sealed = self.sealed_path();
+        let mut files = self.segments.paths()?;
+        files.extend(sealed.exists().then_some(sealed));
+        files.push(self.path.clone());
+        let decoded: Vec<Vec<WalEntry>> = crate::parallel::maintenance(|| {
+            files.par_iter().map(|file| read_entries(file, &self.codec)).collect::<Result<_>>()
+        })?;
+        let mut entries: Vec<WalEntry> = decoded.into_iter().flatten().collect();
+        entries.sort_by_key(|entry| entry.seq());
+        entries.dedup_by_key(|entry| entry.seq());
+        entries.retain(|entry| entry.seq() > min_seq);
+        Ok(entries)
+    }
+
+    // Log a new WAL entry. This method assigns the next sequence number to the entry, serializes it to JSON (encoded as configured), and appends it to the WAL file. If the WAL is disabled (file is None), it simply increments the sequence number without writing anything.
+    pub fn log(&mut self, entry: &mut WalEntry) -> Result<()> {
+        self.log_batch(std::slice::from_mut(entry))
+    }
+
+    // Log entries in one append, with consecutive sequence numbers: all of them reach the file or,
+    // when the write fails, none of them do
+    pub fn log_batch(&mut self, entries: &mut [WalEntry]) -> Result<()> {
+        for (next, entry) in (self.next_seq..).zip(entries.iter_mut()) {
+            match entry {
                 WalEntry::Insert { seq, .. }
                 | WalEntry::Update { seq, .. }
                 | WalEntry::Delete { seq, .. }
-                | WalEntry::Checkpoint { seq, .. } => *seq,
-            };
-            if entry_seq <= min_seq {
-                continue;
+                | WalEntry::Checkpoint { seq, .. } => {
+                    *seq = next;
+                }
             }
-            entries.push(entry);
         }
-        
-        Ok(entries)
+        if self.file.is_some() && !entries.is_empty() {
+            let mut lines = Vec::new();
+            for entry in entries.iter() {
+                lines.extend_from_slice(self.codec.encode(entry)?.as_bytes());
+                lines.push(b'\n');
+            }
+            self.append(&lines)?;
+        }
+        for entry in entries.iter() {
+            if !matches!(entry, WalEntry::Checkpoint { .. }) {
+                self.last_data_seq = entry.seq();
+            }
+        }
+        self.next_seq += entries.len() as u64;
+        if self.max_segment_bytes > 0 && !entries.is_empty() {
+            self.close_segment_if_full();
+        }
+        Ok(())
     }
 
-    // Log a new WAL entry. This method assigns the next sequence number to the entry, serializes it to JSON, and appends it to the WAL file. If the WAL is disabled (file is None), it simply increments the sequence number without writing anything.
-    pub fn log(&mut self, entry: &mut WalEntry) -> Result<()> {
-        match entry {
-            WalEntry::Insert { seq, .. }
-            | WalEntry::Update { seq, .. }
-            | WalEntry::Delete { seq, .. }
-            | WalEntry::Checkpoint { seq, .. } => {
-                *seq = self.next_seq;
-            }
+    // Move the live file to the closed segments once it reaches `max_segment_bytes`. The entries are
+    // on disk either way, so a failure is logged and the live file keeps growing until the next try.
+    fn close_segment_if_full(&mut self) {
+        let full = self
+            .file
+            .as_ref()
+            .and_then(|file| file.get_ref().metadata().ok())
+            .is_some_and(|meta| meta.len() >= self.max_segment_bytes);
+        if !full || self.torn_at.is_some() {
+            return;
         }
-        if let Some(file) = &mut self.file {
-            let json = serde_json::to_string(entry)?;
-            writeln!(file, "{}", json)?;
+        if let Err(e) = self.close_segment() {
+            tracing::warn!(wal=%self.path.display(), error=%e, "wal_segment_rotation_failed");
+        }
+    }
+
+    fn close_segment(&mut self) -> Result<()> {
+        if let Some(mut file) = self.file.take() {
             file.flush()?;
         }
-        self.next_seq += 1;
+        let now = crate::testing::clock::now_secs();
+        let closed = self.segments.close(&self.path, self.next_seq.saturating_sub(1), now);
+        // The live file is opened again whether or not it moved
+        let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.file = Some(BufWriter::new(file));
+        self.ensure_header()?;
+        if let Some(segment) = closed? {
+            tracing::info!(wal=%self.path.display(), first_seq=segment.first_seq, last_seq=segment.last_seq, bytes=segment.bytes, "wal_segment_closed");
+        }
+        Ok(())
+    }
+
+    // Write `lines` at the end of the file. A failed write is cut back out (see repair_torn_tail) and
+    // tried again, up to `retry.attempts` times; on a full disk it fails with StorageFull at once.
+    fn append(&mut self, lines: &[u8]) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            self.repair_torn_tail()?;
+            let Some(file) = self.file.as_mut() else { return Ok(()) };
+            let start = file.get_ref().metadata()?.len();
+            let error = match crate::testing::fault::admit(&self.path, lines.len()) {
+                None => match file.write_all(lines).and_then(|_| file.flush()) {
+                    Ok(()) => return Ok(()),
+                    Err(e) => e,
+                },
+                // An injected crash: the part of the line that fit stays, and nothing is retried
+                Some(Cut::Crash(cut)) => {
+                    let e = file.write_all(&lines[..cut]).and_then(|_| file.flush()).err();
+                    let e = e.unwrap_or_else(|| std::io::Error::other("injected WAL fault"));
+                    return Err(StorageError::WalFailed(format!("{}: {}", self.path.display(), e)).into());
+                }
+                Some(Cut::DiskFull(cut)) => {
+                    let e = file.write_all(&lines[..cut]).and_then(|_| file.flush()).err();
+                    e.unwrap_or_else(|| ErrorKind::StorageFull.into())
+                }
+            };
+            // Cut back now; when that fails too it is tried again before the next append
+            self.torn_at = Some(start);
+            let _ = self.repair_torn_tail();
+            let message = format!("{}: {}", self.path.display(), error);
+            if matches!(error.kind(), ErrorKind::StorageFull | ErrorKind::QuotaExceeded) {
+                tracing::error!(wal=%self.path.display(), error=%error, "wal_disk_full");
+                return Err(StorageError::StorageFull(message).into());
+            }
+            if attempt >= self.retry.attempts {
+                return Err(StorageError::WalFailed(message).into());
+            }
+            attempt += 1;
+            tracing::warn!(wal=%self.path.display(), attempt, error=%error, "wal_write_retry");
+            std::thread::sleep(std::time::Duration::from_millis(self.retry.backoff_ms.saturating_mul(attempt as u64)));
+        }
+    }
+
+    // Truncate the file back to where a failed append began, dropping what is still buffered of it,
+    // so no partial line is left for the next append to land behind
+    fn repair_torn_tail(&mut self) -> Result<()> {
+        let Some(len) = self.torn_at else { return Ok(()) };
+        let Some(writer) = self.file.take() else { return Ok(()) };
+        let (file, _unwritten) = writer.into_parts();
+        let cut = file.set_len(len);
+        self.file = Some(BufWriter::new(file));
+        cut.map_err(|e| StorageError::WalFailed(format!("{}: {}", self.path.display(), e)))?;
+        self.torn_at = None;
         Ok(())
     }
 
@@ -112,12 +254,35 @@ impl Wal {
     }
     
     // Rotate the WAL file by closing the current one and starting a new, empty file. This is typically done after a checkpoint to prevent the WAL from growing indefinitely and to allow old entries to be safely discarded.
+    // With history enabled the closed file is archived as a history segment instead of being truncated.
+    // A sealed file left by a checkpoint is closed along with it.
     pub fn rotate(&mut self) -> Result<()> {
         if self.file.is_none() {
             return Ok(());
         }
+        self.repair_torn_tail()?;
         // Drop current writer to release handle
-        drop(self.file.take());
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+        let closed = if self.sealed_path().exists() {
+            self.move_to_sealed()?;
+            self.sealed_path()
+        } else {
+            self.path.clone()
+        };
+        let now = crate::testing::clock::now_secs();
+        let last_seq = self.next_seq.saturating_sub(1);
+        self.segments.release(last_seq, self.history.as_ref(), now)?;
+        self.segments.archive_file(&closed, last_seq, now)?;
+        if let Some(history) = &self.history {
+            history.archive(&closed, last_seq, now)?;
+            history.prune(now)?;
+        } else if closed != self.path {
+            std::fs::remove_file(&closed)?;
+        }
+        self.sealed_data_seq = Some(self.last_data_seq);
+        self.dropped_data_seq = Some(self.last_data_seq);
         // Open a fresh, truncated WAL file
         let file = OpenOptions::new()
             .write(true)
@@ -129,10 +294,92 @@ impl Wal {
         self.ensure_header()?;
         Ok(())
     }
+
+    // Close the live file for a checkpoint that is written out after the collection lock is released.
+    // Its entries move to the sealed file and logging continues in a fresh one; replay reads the sealed
+    // file first until `release_sealed` drops it once the checkpoint's files are on disk.
+    pub fn seal(&mut self) -> Result<()> {
+        self.repair_torn_tail()?;
+        let Some(mut file) = self.file.take() else { return Ok(()) };
+        file.flush()?;
+        drop(file);
+        self.move_to_sealed()?;
+        // The previous sealed file was released by the checkpoint that sealed it
+        self.dropped_data_seq = self.sealed_data_seq;
+        self.sealed_data_seq = Some(self.last_data_seq);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.file = Some(BufWriter::new(file));
+        self.ensure_header()?;
+        Ok(())
+    }
+
+    pub fn sealed_path(&self) -> PathBuf {
+        get_sealed_path(&self.path)
+    }
+
+    // Highest sequence number of an insert, update or delete that is no longer in the log files, when
+    // known: every change after it can still be replayed
+    pub fn dropped_through(&self) -> Option<u64> {
+        if self.sealed_path().exists() { self.dropped_data_seq } else { self.sealed_data_seq }
+    }
+
+    // Move the live file's entries to the sealed file, appending them when an earlier checkpoint that
+    // failed left one behind. The live file is gone afterwards.
+    fn move_to_sealed(&self) -> Result<()> {
+        let sealed = self.sealed_path();
+        if !sealed.exists() {
+            std::fs::rename(&self.path, &sealed)?;
+            return Ok(());
+        }
+        let entries = read_entries(&self.path, &self.codec)?;
+        let mut writer = BufWriter::new(OpenOptions::new().append(true).open(&sealed)?);
+        for entry in &entries {
+            writeln!(writer, "{}", self.codec.encode(entry)?)?;
+        }
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        std::fs::remove_file(&self.path)?;
+        Ok(())
+    }
+
+    // Retry failed appends as configured
+    pub fn with_retry(mut self, retry: WalRetry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    // Close the live file into a segment once it reaches `max_bytes` (0 = never)
+    pub fn with_segments(mut self, max_bytes: u64) -> Self {
+        self.max_segment_bytes = max_bytes;
+        self
+    }
+
+    // Copy released WAL files to `dir` (see segments.rs)
+    pub fn with_archive(mut self, dir: PathBuf) -> Self {
+        self.segments = self.segments.with_archive(dir);
+        self
+    }
+
+    pub fn segments(&self) -> &WalSegments {
+        &self.segments
+    }
+
+    // Keep closed WAL files as history (see history.rs)
+    pub fn with_history(mut self, history: WalHistory) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    pub fn history(&self) -> Option<&WalHistory> {
+        self.history.as_ref()
+    }
     
     pub fn flush(&mut self) -> Result<()> {
         if let Some(file) = &mut self.file {
-            file.flush()?;
+            file.flush().map_err(|e| StorageError::WalFailed(format!("{}: {}", self.path.display(), e)))?;
         }
         Ok(())
     }
@@ -145,7 +392,7 @@ impl Wal {
         let metadata = std::fs::metadata(&self.path)?;
         if metadata.len() == 0 {
             if let Some(writer) = &mut self.file {
-                let header = WalHeader { version: WAL_VERSION };
+                let header = WalHeader::for_codec(&self.codec);
                 let json = serde_json::to_string(&header)?;
                 writeln!(writer, "{}", json)?;
                 writer.flush()?;
@@ -154,3 +401,91 @@ impl Wal {
         Ok(())
     }
 }
+
+// The sealed file next to a WAL file: `{collection}.wal.sealed` for `{collection}.wal.db`
+pub fn get_sealed_path(wal_path: &Path) -> PathBuf {
+    wal_path.with_extension("sealed")
+}
+
+// Drop the sealed file and the closed segments once the checkpoint covering them (through
+// `last_seq`) is on disk. With history enabled they become history segments instead, and with an
+// archive they are copied there first. Runs without the WAL latch: the checkpoint that sealed the
+// file still holds the collection's checkpoint lock, so nothing appends to it meanwhile; segments
+// closed since hold later entries only and are kept.
+pub fn release_sealed(wal_path: &Path, history: Option<&WalHistory>, segments: &WalSegments, last_seq: u64) -> Result<()> {
+    let now = crate::testing::clock::now_secs();
+    segments.release(last_seq, history, now)?;
+    let sealed = get_sealed_path(wal_path);
+    if !sealed.exists() {
+        return Ok(());
+    }
+    segments.archive_file(&sealed, last_seq, now)?;
+    match history {
+        Some(history) => {
+            history.archive(&sealed, last_seq, now)?;
+            history.prune(now)?;
+        }
+        None => std::fs::remove_file(&sealed)?,
+    }
+    Ok(())
+}
+
+// A crash in the middle of an append leaves a last line without its newline. That entry was never
+// acknowledged, so it is cut off before the log is replayed or appended to again.
+fn truncate_torn_tail(path: &Path) -> Result<()> {
+    let Ok(mut file) = OpenOptions::new().read(true).write(true).open(path) else { return Ok(()) };
+    let len = file.metadata()?.len();
+    if len == 0 {
+        return Ok(());
+    }
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::Start(len - 1))?;
+    file.read_exact(&mut last)?;
+    if last[0] == b'\n' {
+        return Ok(());
+    }
+    let contents = std::fs::read(path)?;
+    let keep = contents.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1) as u64;
+    tracing::warn!(wal=%path.display(), dropped_bytes=len - keep, "wal_torn_tail_truncated");
+    file.set_len(keep)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+// Read every entry of a WAL-format file: a header line, then one entry per line
+pub(super) fn read_entries(path: &Path, codec: &WalCodec) -> Result<Vec<WalEntry>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut entries = Vec::new();
+    
+    for line in reader.lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        // Skip header if present (and validate version)
+        if let Ok(header) = serde_json::from_str::<WalHeader>(&line) {
+            if header.version == 0 || header.version > WAL_VERSION {
+                return Err(crate::error::PiramidError::other(format!(
+                    "Unsupported WAL version {}, expected at most {}",
+                    header.version, WAL_VERSION
+                )));
+            }
+            continue;
+        }
+        entries.push(codec.decode(&line)?);
+    }
+    Ok(entries)
+}
+
+// Write a complete WAL-format file (used for the history base snapshot)
+pub(super) fn write_entries(path: &Path, entries: &[WalEntry], codec: &WalCodec) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{}", serde_json::to_string(&WalHeader::for_codec(codec))?)?;
+    for entry in entries {
+        writeln!(writer, "{}", codec.encode(entry)?)?;
+    }
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok(())
+}
@@ -1,5 +1,12 @@
 mod entry;
 mod log;
+mod history;
+mod cipher;
+mod codec;
+mod segments;
 
 pub use entry::WalEntry;
-pub use log::Wal;
+pub use codec::WalCodec;
+pub use log::{Wal, get_sealed_path, release_sealed};
+pub use segments::{WalSegments, SegmentInfo, SegmentManifest, get_segments_dir, get_archive_dir};
+pub use history::{WalHistory, HistorySummary, get_history_dir, fold_entries};
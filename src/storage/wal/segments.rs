@@ -0,0 +1,198 @@
+// Closed WAL segments.
+// When the live file `{collection}.wal.db` grows past `wal.max_log_size` it is closed and moved to
+// `{collection}.wal.segments/` as `{first_seq:020}-{last_seq:020}.wal`, and logging continues in a
+// fresh live file. `manifest.json` in the same directory lists each segment's sequence range, size
+// and close time; a segment file the manifest does not know (a crash between the rename and the
+// manifest write) is picked up from its name. Segments stay until a checkpoint covers them: the
+// checkpoint that releases the sealed file releases every closed segment up to its sequence number
+// too, into the history when one is kept and deleted otherwise.
+//
+// With `wal.archive_segments` set, every released file (closed segments and the sealed file) is
+// first copied to `{collection}.wal.archive/` under the same naming, with a manifest of its own, so
+// a replica or a point-in-time restore can read the complete log from there. Nothing is ever
+// removed from the archive; shipping and pruning it is left to the operator.
+//
+// Segments are independent files in the WAL format, so replay decodes them in parallel.
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::storage::persistence::write_atomic;
+use super::codec::WalCodec;
+use super::entry::WalEntry;
+use super::history::WalHistory;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+// The segment directory next to a WAL file: `{collection}.wal.segments` for `{collection}.wal.db`
+pub fn get_segments_dir(wal_path: &Path) -> PathBuf {
+    wal_path.with_extension("segments")
+}
+
+pub fn get_archive_dir(collection_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.wal.archive", collection_path))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentInfo {
+    pub first_seq: u64,
+    pub last_seq: u64,
+    pub bytes: u64,
+    pub closed_at: u64, // unix secs; 0 when recovered from the file name alone
+}
+
+impl SegmentInfo {
+    fn file_name(&self) -> String {
+        format!("{:020}-{:020}.wal", self.first_seq, self.last_seq)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SegmentManifest {
+    pub segments: Vec<SegmentInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalSegments {
+    dir: PathBuf,
+    codec: WalCodec,
+    archive: Option<PathBuf>,
+}
+
+impl WalSegments {
+    pub fn new(wal_path: &Path, codec: WalCodec) -> Self {
+        Self { dir: get_segments_dir(wal_path), codec, archive: None }
+    }
+
+    // Copy released files to `dir`
+    pub fn with_archive(mut self, dir: PathBuf) -> Self {
+        self.archive = Some(dir);
+        self
+    }
+
+    pub fn archive_dir(&self) -> Option<&Path> {
+        self.archive.as_deref()
+    }
+
+    // Closed segments in sequence order, from the manifest and the files present
+    pub fn list(&self) -> Result<Vec<SegmentInfo>> {
+        Ok(reconcile(&self.dir)?.segments)
+    }
+
+    pub fn path_of(&self, segment: &SegmentInfo) -> PathBuf {
+        self.dir.join(segment.file_name())
+    }
+
+    pub fn size_bytes(&self) -> u64 {
+        self.list().map(|segments| segments.iter().map(|s| s.bytes).sum()).unwrap_or(0)
+    }
+
+    // Move a full live file into the segment directory, covering `last_seq`. Returns None when the
+    // file holds no entry yet.
+    pub fn close(&self, live: &Path, last_seq: u64, closed_at: u64) -> Result<Option<SegmentInfo>> {
+        let Some(first_seq) = first_seq(live, &self.codec)? else { return Ok(None) };
+        fs::create_dir_all(&self.dir)?;
+        let segment = SegmentInfo { first_seq, last_seq, bytes: fs::metadata(live)?.len(), closed_at };
+        fs::rename(live, self.path_of(&segment))?;
+        let mut manifest = reconcile(&self.dir)?;
+        manifest.segments.retain(|s| s.file_name() != segment.file_name());
+        manifest.segments.push(segment.clone());
+        manifest.segments.sort_by_key(|s| s.first_seq);
+        save_manifest(&self.dir, &manifest)?;
+        Ok(Some(segment))
+    }
+
+    // Every file of segments in sequence order, for replay
+    pub fn paths(&self) -> Result<Vec<PathBuf>> {
+        Ok(self.list()?.iter().map(|s| self.path_of(s)).collect())
+    }
+
+    // Hand the segments a checkpoint through `through` covers to the archive and the history (or
+    // delete them)
+    pub fn release(&self, through: u64, history: Option<&WalHistory>, now: u64) -> Result<()> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+        let mut manifest = reconcile(&self.dir)?;
+        let (released, kept): (Vec<_>, Vec<_>) = manifest.segments.into_iter().partition(|s| s.last_seq <= through);
+        for segment in &released {
+            let path = self.path_of(segment);
+            self.archive(&path, segment)?;
+            match history {
+                Some(history) => history.archive(&path, segment.last_seq, now)?,
+                None => fs::remove_file(&path)?,
+            }
+        }
+        manifest.segments = kept;
+        save_manifest(&self.dir, &manifest)
+    }
+
+    // Copy a closed WAL-format file (a segment or the sealed file) into the archive, when there is one
+    pub fn archive_file(&self, path: &Path, last_seq: u64, closed_at: u64) -> Result<()> {
+        if self.archive.is_none() || !path.exists() {
+            return Ok(());
+        }
+        let Some(first_seq) = first_seq(path, &self.codec)? else { return Ok(()) };
+        let segment = SegmentInfo { first_seq, last_seq, bytes: fs::metadata(path)?.len(), closed_at };
+        self.archive(path, &segment)
+    }
+
+    fn archive(&self, path: &Path, segment: &SegmentInfo) -> Result<()> {
+        let Some(dir) = self.archive.as_deref() else { return Ok(()) };
+        fs::create_dir_all(dir)?;
+        fs::copy(path, dir.join(segment.file_name()))?;
+        let mut manifest = reconcile(dir)?;
+        manifest.segments.retain(|s| s.file_name() != segment.file_name());
+        manifest.segments.push(segment.clone());
+        manifest.segments.sort_by_key(|s| s.first_seq);
+        save_manifest(dir, &manifest)
+    }
+}
+
+// The manifest of `dir` brought in line with the segment files present
+fn reconcile(dir: &Path) -> Result<SegmentManifest> {
+    let Ok(listing) = fs::read_dir(dir) else { return Ok(SegmentManifest::default()) };
+    let mut manifest: SegmentManifest = match fs::read(dir.join(MANIFEST_FILE)) {
+        Ok(data) => serde_json::from_slice(&data)?,
+        Err(_) => SegmentManifest::default(),
+    };
+    let mut present = Vec::new();
+    for entry in listing {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        let Some((first, last)) = name.strip_suffix(".wal").and_then(|n| n.split_once('-')) else { continue };
+        if let (Ok(first_seq), Ok(last_seq)) = (first.parse(), last.parse()) {
+            present.push((first_seq, last_seq, fs::metadata(&path)?.len()));
+        }
+    }
+    manifest.segments.retain(|s| present.iter().any(|&(first, last, _)| (first, last) == (s.first_seq, s.last_seq)));
+    for (first_seq, last_seq, bytes) in present {
+        if !manifest.segments.iter().any(|s| (s.first_seq, s.last_seq) == (first_seq, last_seq)) {
+            manifest.segments.push(SegmentInfo { first_seq, last_seq, bytes, closed_at: 0 });
+        }
+    }
+    manifest.segments.sort_by_key(|s| s.first_seq);
+    Ok(manifest)
+}
+
+fn save_manifest(dir: &Path, manifest: &SegmentManifest) -> Result<()> {
+    write_atomic(&dir.join(MANIFEST_FILE).to_string_lossy(), &serde_json::to_vec_pretty(manifest)?)
+}
+
+// Sequence number of the first entry of a WAL-format file; only the lines up to it are read
+fn first_seq(path: &Path, codec: &WalCodec) -> Result<Option<u64>> {
+    let file = fs::File::open(path)?;
+    for line in BufReader::new(file).lines().skip(1) {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let entry: WalEntry = codec.decode(&line)?;
+        return Ok(Some(entry.seq()));
+    }
+    Ok(None)
+}
@@ -0,0 +1,325 @@
+// Benchmarks: a synthetic dataset (see recall.rs) inserted in batches and then searched, against the
+// embedded library or a running server, reporting throughput and latency percentiles per phase. The
+// dataset follows from the seed, so two machines benchmarked with the same settings did the same work.
+//
+// Embedded runs build the collection in this process: in memory, or in `data_dir` to include the WAL
+// and checkpoints. Their inserts go one batch at a time (writes take the collection exclusively) and
+// their searches run on `concurrency` threads. HTTP runs create a collection of that name on the server,
+// which must not exist yet, send `concurrency` requests at a time for both phases and delete the
+// collection afterwards unless asked to keep it; latencies are measured at the client, network included.
+// The server's configuration decides the index there.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::CollectionConfig;
+use crate::error::{Result, ServerError};
+use crate::index::IndexConfig;
+use crate::search::SearchParams;
+use crate::storage::{Collection, Document};
+use super::recall::SyntheticDataset;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BenchTarget {
+    Embedded {
+        index: IndexConfig,
+        #[serde(default)]
+        data_dir: Option<String>, // the collection's files go here (removed afterwards); in memory when unset
+    },
+    Http {
+        url: String, // e.g. http://localhost:6333
+        collection: String,
+        #[serde(default)]
+        api_key: Option<String>, // sent as x-api-key
+        #[serde(default)]
+        keep: bool, // leave the collection on the server
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchConfig {
+    pub dataset: SyntheticDataset,
+    pub seed: u64,
+    pub k: usize,
+    pub batch_size: usize, // vectors per insert
+    pub concurrency: usize, // searches (and HTTP inserts) in flight at once
+    pub target: BenchTarget,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            dataset: SyntheticDataset { count: 10_000, queries: 1000, ..Default::default() },
+            seed: 42,
+            k: 10,
+            batch_size: 500,
+            concurrency: 1,
+            target: BenchTarget::Embedded { index: IndexConfig::default(), data_dir: None },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub mean_us: u64,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+impl LatencyStats {
+    fn of(mut latencies: Vec<u64>) -> Self {
+        if latencies.is_empty() {
+            return Self::default();
+        }
+        latencies.sort_unstable();
+        let at = |p: usize| latencies[(latencies.len() * p).div_ceil(100).saturating_sub(1)];
+        LatencyStats {
+            mean_us: latencies.iter().sum::<u64>() / latencies.len() as u64,
+            p50_us: at(50),
+            p95_us: at(95),
+            p99_us: at(99),
+            max_us: latencies[latencies.len() - 1],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseReport {
+    pub operations: usize, // vectors inserted, or queries searched
+    pub requests: usize, // insert batches, or searches
+    pub elapsed_ms: u64,
+    pub ops_per_sec: f64,
+    pub latency: LatencyStats, // per request
+}
+
+impl PhaseReport {
+    fn new(operations: usize, elapsed: Duration, latencies: Vec<u64>) -> Self {
+        PhaseReport {
+            operations,
+            requests: latencies.len(),
+            elapsed_ms: elapsed.as_millis() as u64,
+            ops_per_sec: operations as f64 / elapsed.as_secs_f64().max(f64::MIN_POSITIVE),
+            latency: LatencyStats::of(latencies),
+        }
+    }
+}
+
+// What the numbers were measured on, to tell reports from different hardware apart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchHost {
+    pub os: String,
+    pub arch: String,
+    pub cpus: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub target: String, // "embedded <index type>" or the server's URL
+    pub host: BenchHost, // of the process that ran the benchmark
+    pub vectors: usize,
+    pub dimensions: usize,
+    pub queries: usize,
+    pub k: usize,
+    pub batch_size: usize,
+    pub concurrency: usize,
+    pub insert: PhaseReport,
+    pub search: PhaseReport,
+}
+
+fn validate(config: &BenchConfig) -> Result<()> {
+    let dataset = &config.dataset;
+    let invalid = |message: &str| Err(ServerError::InvalidRequest(message.to_string()).into());
+    if dataset.count == 0 || dataset.dimensions == 0 || dataset.clusters == 0 || dataset.queries == 0 {
+        return invalid("count, dimensions, clusters and queries must be > 0");
+    }
+    if config.k == 0 || config.batch_size == 0 || config.concurrency == 0 {
+        return invalid("k, batch_size and concurrency must be > 0");
+    }
+    Ok(())
+}
+
+// Run the benchmark. HTTP targets are driven from a runtime of their own; async callers use
+// `run_http_bench` instead.
+pub fn run_bench(config: &BenchConfig) -> Result<BenchReport> {
+    validate(config)?;
+    match &config.target {
+        BenchTarget::Embedded { index, data_dir } => run_embedded(config, index, data_dir.as_deref()),
+        BenchTarget::Http { .. } => tokio::runtime::Runtime::new()?.block_on(run_http_bench(config)),
+    }
+}
+
+fn run_embedded(config: &BenchConfig, index: &IndexConfig, data_dir: Option<&str>) -> Result<BenchReport> {
+    let (vectors, queries) = config.dataset.generate(config.seed);
+    let collection_config = CollectionConfig::with_index(index.clone());
+    let path = data_dir.map(|dir| {
+        std::fs::create_dir_all(dir)?;
+        Ok::<_, std::io::Error>(crate::storage::collection::collection_path(dir, "bench"))
+    }).transpose()?;
+    let mut collection = match &path {
+        Some(path) => {
+            remove_collection_files(path);
+            Collection::open_with_options(path, collection_config.into())?
+        }
+        None => super::ephemeral_collection("bench", collection_config)?,
+    };
+
+    let mut latencies = Vec::new();
+    let start = Instant::now();
+    for (batch, chunk) in vectors.chunks(config.batch_size).enumerate() {
+        let documents = chunk
+            .iter()
+            .enumerate()
+            .map(|(i, vector)| Document::new(vector.clone(), format!("v{}", batch * config.batch_size + i)))
+            .collect();
+        let sent = Instant::now();
+        collection.insert_batch(documents)?;
+        latencies.push(sent.elapsed().as_micros() as u64);
+    }
+    let insert = PhaseReport::new(vectors.len(), start.elapsed(), latencies);
+
+    let metric = index.metric();
+    let next = AtomicUsize::new(0);
+    let start = Instant::now();
+    let latencies: Vec<u64> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..config.concurrency)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut latencies = Vec::new();
+                    while let Some(query) = queries.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let sent = Instant::now();
+                        collection.search(query, config.k, metric, SearchParams::default());
+                        latencies.push(sent.elapsed().as_micros() as u64);
+                    }
+                    latencies
+                })
+            })
+            .collect();
+        workers.into_iter().flat_map(|worker| worker.join().unwrap_or_default()).collect()
+    });
+    let search = PhaseReport::new(queries.len(), start.elapsed(), latencies);
+    let target = format!("embedded {:?}", collection.vector_index().index_type()).to_lowercase();
+    drop(collection);
+    if let Some(path) = &path {
+        remove_collection_files(path);
+    }
+    Ok(report(config, target, insert, search))
+}
+
+fn remove_collection_files(path: &str) {
+    let path = std::path::Path::new(path);
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else { return };
+    let name = name.to_string_lossy();
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with(&*name) {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+// The benchmark against a running server; `config.target` must be `Http`
+pub async fn run_http_bench(config: &BenchConfig) -> Result<BenchReport> {
+    validate(config)?;
+    let BenchTarget::Http { url, collection, api_key, keep } = &config.target else {
+        return Err(ServerError::InvalidRequest("run_http_bench needs an http target".to_string()).into());
+    };
+    crate::validation::validate_collection_name(collection)?;
+    let api = format!("{}/api", url.trim_end_matches('/'));
+    let client = reqwest::Client::new();
+    let send = |request: reqwest::RequestBuilder| {
+        let request = match api_key {
+            Some(key) => request.header("x-api-key", key),
+            None => request,
+        };
+        async move {
+            let response = request
+                .send()
+                .await
+                .map_err(|e| ServerError::ServiceUnavailable(format!("{url} unreachable: {e}")))?;
+            let status = response.status();
+            let body: Value = response
+                .json()
+                .await
+                .map_err(|e| ServerError::ServiceUnavailable(format!("{url} read failed: {e}")))?;
+            if !status.is_success() {
+                return Err(ServerError::Internal(format!("{url} answered {status}: {body}")).into());
+            }
+            Ok::<_, crate::error::PiramidError>(body)
+        }
+    };
+
+    let existing = send(client.get(format!("{api}/collections"))).await?;
+    let taken = existing["collections"].as_array().into_iter().flatten().any(|c| c["name"] == collection.as_str());
+    if taken {
+        return Err(ServerError::AlreadyExists(format!("Collection '{collection}' exists on {url}; benchmark into a new one")).into());
+    }
+    send(client.post(format!("{api}/collections")).json(&json!({"name": collection}))).await?;
+    // Files left behind under the name would be reopened with the new collection
+    let count = send(client.get(format!("{api}/collections/{collection}/count"))).await?;
+    if count["count"].as_u64().unwrap_or_default() > 0 {
+        return Err(ServerError::Conflict(format!("Collection '{collection}' on {url} is not empty; benchmark into another name")).into());
+    }
+
+    let (vectors, queries) = config.dataset.generate(config.seed);
+    let vectors_url = format!("{api}/collections/{collection}/vectors");
+    let timed = |request: reqwest::RequestBuilder| {
+        let sent = send(request);
+        async move {
+            let start = Instant::now();
+            sent.await.map(|_| start.elapsed().as_micros() as u64)
+        }
+    };
+    let start = Instant::now();
+    let inserted: Vec<Result<u64>> = stream::iter(vectors.chunks(config.batch_size).enumerate())
+        .map(|(batch, chunk)| {
+            let texts: Vec<String> = (0..chunk.len()).map(|i| format!("v{}", batch * config.batch_size + i)).collect();
+            timed(client.post(&vectors_url).json(&json!({"vectors": chunk, "texts": texts})))
+        })
+        .buffer_unordered(config.concurrency)
+        .collect()
+        .await;
+    let insert_elapsed = start.elapsed();
+
+    let search_url = format!("{api}/collections/{collection}/search");
+    let start = Instant::now();
+    let searched: Vec<Result<u64>> = stream::iter(&queries)
+        .map(|query| timed(client.post(&search_url).json(&json!({"vector": query, "k": config.k}))))
+        .buffer_unordered(config.concurrency)
+        .collect()
+        .await;
+    let search_elapsed = start.elapsed();
+
+    if !keep {
+        send(client.delete(format!("{api}/collections/{collection}"))).await?;
+    }
+    let insert = PhaseReport::new(vectors.len(), insert_elapsed, inserted.into_iter().collect::<Result<_>>()?);
+    let search = PhaseReport::new(queries.len(), search_elapsed, searched.into_iter().collect::<Result<_>>()?);
+    Ok(report(config, url.clone(), insert, search))
+}
+
+fn report(config: &BenchConfig, target: String, insert: PhaseReport, search: PhaseReport) -> BenchReport {
+    BenchReport {
+        target,
+        host: BenchHost {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpus: num_cpus::get(),
+        },
+        vectors: config.dataset.count,
+        dimensions: config.dataset.dimensions,
+        queries: config.dataset.queries,
+        k: config.k,
+        batch_size: config.batch_size,
+        concurrency: config.concurrency,
+        insert,
+        search,
+    }
+}
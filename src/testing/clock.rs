@@ -0,0 +1,44 @@
+// Wall clock of the storage layer, frozen per thread by tests
+use std::cell::Cell;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+thread_local! {
+    static FROZEN: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+// Seconds since the Unix epoch, or the frozen time when this thread has one
+pub fn now_secs() -> u64 {
+    FROZEN.with(|frozen| frozen.get()).unwrap_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_secs()
+    })
+}
+
+// Freeze this thread's clock at `secs` until the guard is dropped
+pub fn freeze_clock(secs: u64) -> ClockGuard {
+    let previous = FROZEN.with(|frozen| frozen.replace(Some(secs)));
+    ClockGuard { previous }
+}
+
+pub struct ClockGuard {
+    previous: Option<u64>, // restored on drop, so guards nest
+}
+
+impl ClockGuard {
+    pub fn now(&self) -> u64 {
+        now_secs()
+    }
+
+    pub fn set(&self, secs: u64) {
+        FROZEN.with(|frozen| frozen.set(Some(secs)));
+    }
+
+    pub fn advance(&self, secs: u64) {
+        FROZEN.with(|frozen| frozen.set(Some(now_secs() + secs)));
+    }
+}
+
+impl Drop for ClockGuard {
+    fn drop(&mut self) {
+        FROZEN.with(|frozen| frozen.set(self.previous));
+    }
+}
@@ -0,0 +1,79 @@
+// Injected WAL write failures. A WAL with a fault hook accepts appends until its byte budget is
+// spent; the append that crosses it writes only the bytes that still fit and fails, as do all after
+// it. Dropping the collection then reopening it replays what a crash at that byte would have left.
+// A disk-full hook fails the same appends with ENOSPC instead, the way a full disk does; the WAL
+// takes those back out of the file, and writes go through again once the hook is dropped.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::storage::get_wal_path;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FaultKind {
+    Crash,
+    DiskFull,
+}
+
+static FAULTS: OnceLock<Mutex<HashMap<PathBuf, (FaultKind, u64)>>> = OnceLock::new();
+
+fn faults() -> std::sync::MutexGuard<'static, HashMap<PathBuf, (FaultKind, u64)>> {
+    FAULTS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Fail appends to the WAL of the collection at `collection_path` once `bytes` more have been written
+pub fn fail_wal_after(collection_path: &str, bytes: u64) -> WalFault {
+    install(collection_path, FaultKind::Crash, bytes)
+}
+
+// Fail appends to the WAL of the collection at `collection_path` with ENOSPC once `bytes` more have
+// been written, until the returned guard is dropped (space is freed)
+pub fn fill_disk_after(collection_path: &str, bytes: u64) -> WalFault {
+    install(collection_path, FaultKind::DiskFull, bytes)
+}
+
+fn install(collection_path: &str, kind: FaultKind, bytes: u64) -> WalFault {
+    let wal_path = PathBuf::from(get_wal_path(collection_path));
+    faults().insert(wal_path.clone(), (kind, bytes));
+    WalFault { wal_path }
+}
+
+pub struct WalFault {
+    wal_path: PathBuf,
+}
+
+impl WalFault {
+    // Bytes the WAL may still take before appends fail
+    pub fn remaining(&self) -> u64 {
+        faults().get(&self.wal_path).map_or(0, |(_, remaining)| *remaining)
+    }
+}
+
+impl Drop for WalFault {
+    fn drop(&mut self) {
+        faults().remove(&self.wal_path);
+    }
+}
+
+// How an append the fault hook stops fails, after the first `n` bytes of it were written
+pub(crate) enum Cut {
+    Crash(usize),
+    DiskFull(usize),
+}
+
+// Called by the WAL before an append of `len` bytes: None lets it through
+pub(crate) fn admit(wal_path: &Path, len: usize) -> Option<Cut> {
+    let faults = FAULTS.get()?;
+    let mut faults = faults.lock().unwrap_or_else(|e| e.into_inner());
+    let (kind, remaining) = faults.get_mut(wal_path)?;
+    if (len as u64) <= *remaining {
+        *remaining -= len as u64;
+        return None;
+    }
+    let cut = *remaining as usize;
+    *remaining = 0;
+    Some(match kind {
+        FaultKind::Crash => Cut::Crash(cut),
+        FaultKind::DiskFull => Cut::DiskFull(cut),
+    })
+}
@@ -0,0 +1,50 @@
+// Test directories and collections opened for tests
+use std::path::{Path, PathBuf};
+
+use crate::config::CollectionConfig;
+use crate::error::Result;
+use crate::storage::Collection;
+
+// `.piramid/tests/<name>`, emptied when created and removed when dropped, also after a failed assert
+pub struct TestDir {
+    root: PathBuf,
+}
+
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        let root = Path::new(".piramid/tests").join(name);
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).expect("create test directory");
+        TestDir { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // The directory as the `data_dir` a server state is created with
+    pub fn data_dir(&self) -> &str {
+        self.root.to_str().expect("test directory names are UTF-8")
+    }
+
+    // Path of a file in the directory, as the str collections are opened with
+    pub fn path(&self, file: &str) -> String {
+        self.root.join(file).to_string_lossy().into_owned()
+    }
+
+    // Open (or reopen) the collection `<name>.db` in the directory
+    pub fn open(&self, name: &str, config: CollectionConfig) -> Result<Collection> {
+        Collection::open_with_options(&self.path(&format!("{name}.db")), config.into())
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+// A collection held in memory only; nothing is written to disk
+pub fn ephemeral_collection(name: &str, config: CollectionConfig) -> Result<Collection> {
+    Collection::open_with_options(&format!("{name}.db"), config.ephemeral().into())
+}
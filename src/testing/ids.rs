@@ -0,0 +1,47 @@
+// Document ids, drawn from a seeded generator per thread by tests
+use std::cell::Cell;
+
+use uuid::Uuid;
+
+thread_local! {
+    static SEEDED: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+// splitmix64: every seed gives a distinct, well spread sequence
+pub(super) fn splitmix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// A random v4 id, or the next one of this thread's seeded sequence
+pub fn new_id() -> Uuid {
+    SEEDED.with(|seeded| match seeded.get() {
+        Some(mut state) => {
+            let mut bytes = [0u8; 16];
+            bytes[..8].copy_from_slice(&splitmix(&mut state).to_le_bytes());
+            bytes[8..].copy_from_slice(&splitmix(&mut state).to_le_bytes());
+            seeded.set(Some(state));
+            uuid::Builder::from_random_bytes(bytes).into_uuid()
+        }
+        None => Uuid::new_v4(),
+    })
+}
+
+// Draw this thread's ids from `seed` until the guard is dropped
+pub fn seed_ids(seed: u64) -> IdGuard {
+    let previous = SEEDED.with(|seeded| seeded.replace(Some(seed)));
+    IdGuard { previous }
+}
+
+pub struct IdGuard {
+    previous: Option<u64>, // restored on drop, so guards nest
+}
+
+impl Drop for IdGuard {
+    fn drop(&mut self) {
+        SEEDED.with(|seeded| seeded.set(self.previous));
+    }
+}
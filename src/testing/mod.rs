@@ -0,0 +1,25 @@
+// Support for reproducible tests, ours and those of crates embedding piramid.
+// - clock: a frozen wall clock for the storage layer (document timestamps, checkpoint times,
+//   collection metadata), so time-dependent behavior can be stepped through
+// - ids: seeded document ids, so two runs build identical collections
+// - fault: WAL writes that fail after a number of bytes, leaving a torn entry behind the way a crash
+//   mid-write does, or failing with ENOSPC the way a full disk does
+// - fixture: a test directory removed when dropped, and collections opened in it or in memory
+// - recall: recall@k of index configurations on synthetic clustered data, checked against a floor
+// - bench: insert and search throughput and latency on synthetic data, embedded or over HTTP
+// Clock and id overrides apply to the thread that set them; fault hooks are keyed by WAL file, so
+// tests running in parallel on other collections are unaffected.
+
+pub mod clock;
+pub mod fault;
+pub mod fixture;
+pub mod ids;
+pub mod recall;
+pub mod bench;
+
+pub use clock::{freeze_clock, ClockGuard};
+pub use fault::{fail_wal_after, fill_disk_after, WalFault};
+pub use fixture::{ephemeral_collection, TestDir};
+pub use ids::{seed_ids, IdGuard};
+pub use recall::{run_recall_check, RecallCheck, RecallGrid, RecallPoint, RecallReport, SyntheticDataset};
+pub use bench::{run_bench, run_http_bench, BenchConfig, BenchHost, BenchReport, BenchTarget, LatencyStats, PhaseReport};
@@ -0,0 +1,313 @@
+// Recall checks for index changes: synthetic clustered datasets, searched through ANN indexes and
+// compared with the exact top-k, over a grid of index configurations and search parameters.
+//
+// A dataset is `clusters` Gaussian blobs around centers drawn uniformly from [-1, 1]^dimensions,
+// with queries drawn from the same blobs (not copies of stored vectors). Everything follows from the
+// seed, so a failing seed can be rerun. A check runs every seed it is given and holds a grid point to
+// its worst one: recall is a property of the index, not of one lucky dataset.
+//
+// Each index is built in an ephemeral collection and searched through `Collection::search`, the path
+// requests take. Ground truth scores every vector exactly as the collection stores it (quantized), like
+// tuning does, so the recall measured is the index's alone.
+
+use std::collections::HashSet;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::{CollectionConfig, ExecutionMode, SearchConfig};
+use crate::error::{Result, ServerError};
+use crate::index::{HnswConfig, IndexConfig, IndexType, IvfConfig};
+use crate::metrics::Metric;
+use crate::quantization::QuantizedVector;
+use crate::search::SearchParams;
+use crate::storage::{Collection, Document};
+use super::ids::splitmix;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyntheticDataset {
+    pub count: usize,
+    pub dimensions: usize,
+    pub clusters: usize,
+    pub spread: f32, // standard deviation of a blob on each axis
+    pub queries: usize,
+}
+
+impl Default for SyntheticDataset {
+    fn default() -> Self {
+        Self { count: 5000, dimensions: 64, clusters: 32, spread: 0.15, queries: 100 }
+    }
+}
+
+impl SyntheticDataset {
+    // The stored vectors and the queries for one seed
+    pub fn generate(&self, seed: u64) -> (Vec<Vec<f32>>, Vec<Vec<f32>>) {
+        let mut rng = Rng(seed);
+        let centers: Vec<Vec<f32>> = (0..self.clusters.max(1))
+            .map(|_| (0..self.dimensions).map(|_| rng.uniform() * 2.0 - 1.0).collect())
+            .collect();
+        let point = |rng: &mut Rng| {
+            let center = &centers[rng.below(centers.len())];
+            center.iter().map(|c| c + rng.gaussian() * self.spread).collect::<Vec<f32>>()
+        };
+        let vectors = (0..self.count).map(|_| point(&mut rng)).collect();
+        let queries = (0..self.queries).map(|_| point(&mut rng)).collect();
+        (vectors, queries)
+    }
+}
+
+// Index configurations to build, and the search parameters each is swept over: every `ef` for HNSW
+// indexes, every `nprobe` for IVF ones (their configured value when the list is empty)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecallGrid {
+    pub indexes: Vec<IndexConfig>,
+    #[serde(default)]
+    pub ef: Vec<usize>,
+    #[serde(default)]
+    pub nprobe: Vec<usize>,
+}
+
+impl RecallGrid {
+    // Default HNSW and IVF (sized for `count` vectors) with a doubling sweep, and a flat baseline
+    pub fn standard(count: usize, metric: Metric) -> Self {
+        let hnsw = HnswConfig { metric, ..Default::default() };
+        let ivf = IvfConfig { metric, ..IvfConfig::auto(count) };
+        RecallGrid {
+            indexes: vec![
+                IndexConfig::Hnsw {
+                    m: hnsw.m,
+                    m_max: hnsw.m_max,
+                    ef_construction: hnsw.ef_construction,
+                    ef_search: hnsw.ef_search,
+                    ml: hnsw.ml,
+                    metric,
+                    mode: ExecutionMode::default(),
+                    search: SearchConfig::default(),
+                    shards: 1,
+                },
+                IndexConfig::Ivf {
+                    num_clusters: ivf.num_clusters,
+                    num_probes: ivf.num_probes,
+                    max_iterations: ivf.max_iterations,
+                    metric,
+                    mode: ExecutionMode::default(),
+                    search: SearchConfig::default(),
+                },
+                IndexConfig::Flat { metric, mode: ExecutionMode::default(), search: SearchConfig::default() },
+            ],
+            ef: vec![16, 32, 64, 128],
+            nprobe: vec![1, 2, 4, 8, 16],
+        }
+    }
+
+    // Keep only the indexes of these types
+    pub fn only(mut self, types: &[IndexType]) -> Self {
+        self.indexes.retain(|index| types.contains(&index.select_type(0)));
+        self
+    }
+
+    fn cases(&self) -> Vec<(usize, String, Option<SearchConfig>)> {
+        let mut cases = Vec::new();
+        for (position, index) in self.indexes.iter().enumerate() {
+            let base = index.search_config();
+            match index {
+                IndexConfig::Hnsw { m, ef_construction, .. } if !self.ef.is_empty() => {
+                    for &ef in &self.ef {
+                        let label = format!("hnsw m={m} ef_construction={ef_construction} ef={ef}");
+                        cases.push((position, label, Some(SearchConfig { ef: Some(ef), ..base })));
+                    }
+                }
+                IndexConfig::Ivf { num_clusters, .. } if !self.nprobe.is_empty() => {
+                    for &nprobe in self.nprobe.iter().filter(|n| **n <= *num_clusters) {
+                        let label = format!("ivf clusters={num_clusters} nprobe={nprobe}");
+                        cases.push((position, label, Some(SearchConfig { nprobe: Some(nprobe), ..base })));
+                    }
+                }
+                other => cases.push((position, label(other), None)),
+            }
+        }
+        cases
+    }
+}
+
+fn label(index: &IndexConfig) -> String {
+    match index {
+        IndexConfig::Auto { .. } => "auto".into(),
+        IndexConfig::Flat { .. } => "flat".into(),
+        IndexConfig::Hnsw { m, ef_construction, ef_search, .. } => format!("hnsw m={m} ef_construction={ef_construction} ef={ef_search}"),
+        IndexConfig::Ivf { num_clusters, num_probes, .. } => format!("ivf clusters={num_clusters} nprobe={num_probes}"),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecallCheck {
+    pub dataset: SyntheticDataset,
+    pub grid: RecallGrid,
+    pub seeds: Vec<u64>,
+    pub k: usize,
+    pub floor: f32, // lowest acceptable recall@k of every grid point, on every seed
+    pub metric: Metric,
+}
+
+impl Default for RecallCheck {
+    fn default() -> Self {
+        let dataset = SyntheticDataset::default();
+        let grid = RecallGrid::standard(dataset.count, Metric::Cosine);
+        Self { dataset, grid, seeds: vec![42], k: 10, floor: 0.9, metric: Metric::Cosine }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecallPoint {
+    pub case: String,
+    pub index_type: IndexType,
+    pub recall: f32, // worst seed
+    pub mean_recall: f32,
+    pub mean_latency_us: u64,
+    pub p95_latency_us: u64,
+    pub passed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecallReport {
+    pub k: usize,
+    pub floor: f32,
+    pub vectors: usize,
+    pub queries: usize,
+    pub seeds: Vec<u64>,
+    pub points: Vec<RecallPoint>,
+    pub passed: bool,
+}
+
+impl RecallReport {
+    pub fn failures(&self) -> impl Iterator<Item = &RecallPoint> {
+        self.points.iter().filter(|point| !point.passed)
+    }
+
+    // Panic with the points under the floor, for use as a test assertion
+    pub fn assert_passed(&self) {
+        let failed: Vec<String> = self.failures().map(|p| format!("{}: recall {:.3}", p.case, p.recall)).collect();
+        assert!(failed.is_empty(), "recall@{} under the floor {}: {}", self.k, self.floor, failed.join(", "));
+    }
+}
+
+pub fn run_recall_check(check: &RecallCheck) -> Result<RecallReport> {
+    let dataset = &check.dataset;
+    let invalid = |message: &str| Err(ServerError::InvalidRequest(message.to_string()).into());
+    if check.k == 0 || dataset.count < check.k {
+        return invalid("k must be > 0 and at most the dataset's count");
+    }
+    if dataset.dimensions == 0 || dataset.clusters == 0 || dataset.queries == 0 {
+        return invalid("dimensions, clusters and queries must be > 0");
+    }
+    if !(0.0..=1.0).contains(&check.floor) {
+        return invalid("floor must be in [0, 1]");
+    }
+    if check.seeds.is_empty() {
+        return invalid("seeds must not be empty");
+    }
+    let cases = check.grid.cases();
+    if cases.is_empty() {
+        return invalid("the grid has no index to check");
+    }
+
+    // Per case: recall of each seed, and every query's latency
+    let mut recalls = vec![Vec::new(); cases.len()];
+    let mut latencies = vec![Vec::new(); cases.len()];
+    let mut index_types = vec![IndexType::Flat; cases.len()];
+    for &seed in &check.seeds {
+        let (vectors, queries) = dataset.generate(seed);
+        let stored: Vec<Vec<f32>> = vectors.iter().map(|vector| QuantizedVector::from_f32(vector).to_f32()).collect();
+        let truth = exact_top_k(&stored, &queries, check.k, check.metric);
+        for (position, index) in check.grid.indexes.iter().enumerate() {
+            let (collection, ids) = build(index, &vectors)?;
+            let truth: Vec<HashSet<Uuid>> = truth.iter().map(|rows| rows.iter().map(|row| ids[*row]).collect()).collect();
+            for (case, (_, _, search)) in cases.iter().enumerate().filter(|(_, c)| c.0 == position) {
+                index_types[case] = collection.vector_index().index_type();
+                let params = SearchParams { search_config_override: *search, ..Default::default() };
+                let (mut found, mut expected) = (0usize, 0usize);
+                for (query, wanted) in queries.iter().zip(&truth) {
+                    let start = Instant::now();
+                    let hits = collection.search(query, check.k, check.metric, params);
+                    latencies[case].push(start.elapsed().as_micros() as u64);
+                    expected += wanted.len();
+                    found += hits.iter().filter(|hit| wanted.contains(&hit.id)).count();
+                }
+                recalls[case].push(if expected == 0 { 1.0 } else { found as f32 / expected as f32 });
+            }
+        }
+    }
+
+    let points: Vec<RecallPoint> = cases
+        .into_iter()
+        .enumerate()
+        .map(|(case, (_, label, _))| {
+            let recall = recalls[case].iter().copied().fold(1.0f32, f32::min);
+            let latencies = &mut latencies[case];
+            latencies.sort_unstable();
+            RecallPoint {
+                case: label,
+                index_type: index_types[case],
+                recall,
+                mean_recall: recalls[case].iter().sum::<f32>() / recalls[case].len() as f32,
+                mean_latency_us: latencies.iter().sum::<u64>() / latencies.len() as u64,
+                p95_latency_us: latencies[((latencies.len() * 95).div_ceil(100)).saturating_sub(1)],
+                passed: recall >= check.floor,
+            }
+        })
+        .collect();
+    Ok(RecallReport {
+        k: check.k,
+        floor: check.floor,
+        vectors: dataset.count,
+        queries: dataset.queries,
+        seeds: check.seeds.clone(),
+        passed: points.iter().all(|point| point.passed),
+        points,
+    })
+}
+
+// An ephemeral collection holding the vectors under `index`; ids in the vectors' order
+fn build(index: &IndexConfig, vectors: &[Vec<f32>]) -> Result<(Collection, Vec<Uuid>)> {
+    let mut collection = super::ephemeral_collection("recall", CollectionConfig::with_index(index.clone()))?;
+    let documents = vectors.iter().enumerate().map(|(i, vector)| Document::new(vector.clone(), format!("v{i}"))).collect();
+    let ids = collection.insert_batch(documents)?;
+    // IVF clusters are trained on what is there when it is built
+    if index.select_type(vectors.len()) == IndexType::Ivf {
+        collection.rebuild_index()?;
+    }
+    Ok((collection, ids))
+}
+
+// Rows of the exact top-k of each query
+fn exact_top_k(vectors: &[Vec<f32>], queries: &[Vec<f32>], k: usize, metric: Metric) -> Vec<Vec<usize>> {
+    let mode = ExecutionMode::default();
+    queries
+        .iter()
+        .map(|query| {
+            let mut scored: Vec<(f32, usize)> = vectors.iter().enumerate().map(|(row, v)| (metric.calculate(query, v, mode), row)).collect();
+            scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+            scored.into_iter().take(k).map(|(_, row)| row).collect()
+        })
+        .collect()
+}
+
+struct Rng(u64);
+
+impl Rng {
+    fn uniform(&mut self) -> f32 {
+        (splitmix(&mut self.0) >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (splitmix(&mut self.0) % n as u64) as usize
+    }
+
+    // Box-Muller
+    fn gaussian(&mut self) -> f32 {
+        let u = self.uniform().max(f32::MIN_POSITIVE);
+        let v = self.uniform();
+        (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+    }
+}
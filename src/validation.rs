@@ -1,6 +1,10 @@
 // Input validation and sanitization for vectors and requests
 
-use crate::error::{Result, ServerError};
+use std::borrow::Cow;
+
+use crate::config::{NonFinitePolicy, VectorValidationConfig};
+use crate::error::{Result, ServerError, StorageError};
+use crate::metrics::Metric;
 
 // Validate vector format (check for NaN, Infinity)
 pub fn validate_vector(vector: &[f32]) -> Result<()> {
@@ -24,6 +28,29 @@ pub fn validate_vector(vector: &[f32]) -> Result<()> {
     Ok(())
 }
 
+// Apply a collection's vector validation to a vector about to be stored or searched with `metric`.
+// Borrows the input unless sanitizing had to change it.
+pub fn check_vector<'a>(vector: &'a [f32], config: &VectorValidationConfig, metric: Metric) -> Result<Cow<'a, [f32]>> {
+    let mut vector = Cow::Borrowed(vector);
+    if let Some(index) = vector.iter().position(|v| !v.is_finite()) {
+        match config.non_finite {
+            NonFinitePolicy::Reject => {
+                return Err(StorageError::NonFiniteValue { index, value: vector[index] }.into());
+            }
+            NonFinitePolicy::Sanitize => {
+                for value in vector.to_mut().iter_mut().filter(|v| !v.is_finite()) {
+                    *value = 0.0;
+                }
+            }
+            NonFinitePolicy::Allow => {}
+        }
+    }
+    if config.reject_zero_cosine && metric == Metric::Cosine && vector.iter().all(|&v| v == 0.0) {
+        return Err(StorageError::ZeroVector.into());
+    }
+    Ok(vector)
+}
+
 // Validate multiple vectors
 pub fn validate_vectors(vectors: &[Vec<f32>]) -> Result<()> {
     for (i, vector) in vectors.iter().enumerate() {
@@ -60,28 +87,73 @@ pub fn normalize_vector(vector: &[f32]) -> Vec<f32> {
 // Check if vector dimensions match expected dimensions
 pub fn validate_dimensions(vector: &[f32], expected_dim: usize) -> Result<()> {
     if vector.len() != expected_dim {
-        return Err(ServerError::InvalidRequest(
+        return Err(ServerError::DimensionMismatch(
             format!("Vector dimension mismatch: expected {}, got {}", expected_dim, vector.len())
         ).into());
     }
     Ok(())
 }
 
+// Check a query against the dimensions of the vectors a collection holds (None while it is empty).
+// A collection holds one size: a query of another size comes from a different model, and switching
+// models means re-embedding into a new collection.
+pub fn check_query_dimensions(query: &[f32], expected: Option<usize>) -> Result<()> {
+    match expected {
+        Some(expected) if query.len() != expected => Err(ServerError::DimensionMismatch(format!(
+            "Query has {} dimensions, the collection holds {}-dimension vectors. Embed the query with the \
+             collection's model, or migrate to the new model by writing its embeddings to a new collection \
+             and moving it into place with POST /api/collections/{{name}}/rename",
+            query.len(), expected
+        )).into()),
+        _ => Ok(()),
+    }
+}
+
 // Validate text input (basic sanitization)
 pub fn validate_text(text: &str) -> Result<()> {
     if text.is_empty() {
         return Err(ServerError::InvalidRequest("Text cannot be empty".to_string()).into());
     }
     
-    if text.len() > 1_000_000 {  // 1MB text limit
+    Ok(())
+}
+
+// Check the size of a document's text against limits.max_document_bytes
+pub fn check_document_size(text: &str, max_bytes: usize) -> Result<()> {
+    if text.len() > max_bytes {
+        return Err(ServerError::PayloadTooLarge(
+            format!("Document text is {} bytes (max {}, set by limits.max_document_bytes)", text.len(), max_bytes)
+        ).into());
+    }
+    Ok(())
+}
+
+// Validate a client-provided document id
+pub fn validate_external_id(id: &str) -> Result<()> {
+    if id.is_empty() {
+        return Err(ServerError::InvalidRequest("id cannot be empty".to_string()).into());
+    }
+
+    if id.len() > 256 {
         return Err(ServerError::InvalidRequest(
-            format!("Text too large: {} bytes (max 1MB)", text.len())
+            format!("id too long: {} bytes (max 256)", id.len())
         ).into());
     }
-    
+
     Ok(())
 }
 
+// Reject metadata keys in the reserved namespace (client id, version, timestamps), which only the
+// engine writes; a client sets its id through the request's `external_id` instead
+pub fn validate_metadata_keys<'a>(keys: impl IntoIterator<Item = &'a String>) -> Result<()> {
+    match keys.into_iter().find(|key| key.starts_with(crate::storage::RESERVED_KEY_PREFIX)) {
+        Some(key) => Err(ServerError::InvalidRequest(format!(
+            "metadata key '{key}' is reserved: keys starting with '_' are set by the engine"
+        )).into()),
+        None => Ok(()),
+    }
+}
+
 // Validate collection name
 pub fn validate_collection_name(name: &str) -> Result<()> {
     if name.is_empty() {
@@ -0,0 +1,150 @@
+mod common;
+
+use arrow_array::builder::{Float32Builder, ListBuilder};
+use arrow_array::{RecordBatch, StringArray};
+use arrow_ipc::writer::{IpcWriteOptions, StreamWriter};
+use arrow_ipc::CompressionType;
+use arrow_schema::{DataType, Field, Schema};
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::storage::columnar::{ArrowStreamReader, ArrowStreamWriter, BatchReader, Column, RowBatch};
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::io::Read;
+use std::sync::Arc;
+
+const STREAM: &str = "application/vnd.apache.arrow.stream";
+
+async fn serve(data_dir: &str) -> String {
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    format!("{}/collections", common::serve(state).await)
+}
+
+// Hands out a few bytes per read, the way a request body arrives
+struct Trickle(std::io::Cursor<Vec<u8>>);
+
+impl Read for Trickle {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(7);
+        self.0.read(&mut buf[..n])
+    }
+}
+
+#[tokio::test]
+async fn collections_stream_out_and_back_in_as_arrow() {
+    let dir = TestDir::new("arrow_stream");
+    let data_dir = dir.data_dir();
+    let base = serve(data_dir).await;
+    let client = reqwest::Client::new();
+    let vectors: Vec<Vec<f32>> = (0..10).map(|i| vec![i as f32, 1.0, -0.5 * i as f32]).collect();
+    let texts: Vec<String> = (0..10).map(|i| format!("doc {i}")).collect();
+    let metadata: Vec<Value> = (0..10).map(|i| json!({"n": i})).collect();
+    let res = client.post(format!("{base}/source/vectors"))
+        .json(&json!({"vectors": vectors, "texts": texts, "metadata_list": metadata}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    let res = client.get(format!("{base}/source/arrow?batch_rows=4")).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!((res.headers()["content-type"].to_str().unwrap(), res.headers()["x-export-documents"].to_str().unwrap()), (STREAM, "10"));
+    let stream = res.bytes().await.unwrap().to_vec();
+    assert_eq!(&stream[stream.len() - 8..], &[0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]);
+
+    let mut reader = ArrowStreamReader::new(Trickle(std::io::Cursor::new(stream.clone()))).unwrap();
+    assert_eq!(reader.columns(), ["id", "vector", "text", "metadata"]);
+    let mut sizes = Vec::new();
+    let mut exported_texts = Vec::new();
+    while let Some(batch) = reader.next_batch().unwrap() {
+        sizes.push(batch.rows);
+        if let Column::Utf8(values) = &batch.columns[2].1 {
+            exported_texts.extend(values.iter().flatten().cloned());
+        }
+    }
+    assert_eq!(sizes, [4, 4, 2]);
+    exported_texts.sort();
+    let mut expected = texts.clone();
+    expected.sort();
+    assert_eq!(exported_texts, expected);
+
+    // Into another collection, then again: ids already there are updated
+    let res = client.post(format!("{base}/copy/arrow?batch_size=3")).header("content-type", STREAM).body(stream.clone()).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let report: Value = res.json().await.unwrap();
+    assert_eq!((report["rows"].as_u64(), report["inserted"].as_u64(), report["updated"].as_u64()), (Some(10), Some(10), Some(0)), "{report}");
+    assert!(report["seq"].as_u64().unwrap() > 0);
+    let report: Value = client.post(format!("{base}/copy/arrow")).body(stream).send().await.unwrap().json().await.unwrap();
+    assert_eq!((report["inserted"].as_u64(), report["updated"].as_u64()), (Some(0), Some(10)), "{report}");
+
+    let listed: Value = client.get(format!("{base}/copy/vectors?limit=100")).send().await.unwrap().json().await.unwrap();
+    let docs = listed.as_array().or_else(|| listed["vectors"].as_array()).unwrap();
+    assert_eq!(docs.len(), 10, "{listed}");
+    let doc = docs.iter().find(|d| d["text"] == "doc 7").unwrap();
+    assert_eq!(doc["metadata"]["n"], 7, "{doc}");
+}
+
+#[tokio::test]
+async fn streams_from_other_writers_and_bad_streams() {
+    let dir = TestDir::new("arrow_stream_input");
+    let data_dir = dir.data_dir();
+    let base = serve(data_dir).await;
+    let client = reqwest::Client::new();
+
+    // A stream written batch by batch, ids not Uuids: they become external ids
+    let mut writer = ArrowStreamWriter::new(Vec::new(), 2).unwrap();
+    for start in [0, 3] {
+        let batch = RowBatch {
+            ids: (start..start + 3).map(|i| format!("row-{i}")).collect(),
+            vectors: (start..start + 3).flat_map(|i| [i as f32, 1.0]).collect(),
+            texts: (start..start + 3).map(|i| format!("text {i}")).collect(),
+            metadata: (start..start + 3).map(|i| json!({"i": i}).to_string()).collect(),
+        };
+        writer.write_batch(&batch).unwrap();
+    }
+    let stream = writer.finish().unwrap();
+    let report: Value = client.post(format!("{base}/docs/arrow")).body(stream.clone()).send().await.unwrap().json().await.unwrap();
+    assert_eq!((report["rows"].as_u64(), report["inserted"].as_u64()), (Some(6), Some(6)), "{report}");
+    let report: Value = client.post(format!("{base}/docs/arrow")).body(stream.clone()).send().await.unwrap().json().await.unwrap();
+    assert_eq!(report["updated"].as_u64(), Some(6), "{report}");
+
+    // Compressed record batches, as pyarrow writes with IpcWriteOptions(compression=...)
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Utf8, true),
+        Field::new("vector", DataType::List(Arc::new(Field::new("item", DataType::Float32, true))), true),
+        Field::new("text", DataType::Utf8, true),
+    ]));
+    let mut vectors = ListBuilder::new(Float32Builder::new());
+    for i in 0..4 {
+        vectors.values().append_slice(&[i as f32, 2.0]);
+        vectors.append(true);
+    }
+    let batch = RecordBatch::try_new(schema.clone(), vec![
+        Arc::new(StringArray::from_iter_values((0..4).map(|i| format!("packed-{i}")))),
+        Arc::new(vectors.finish()),
+        Arc::new(StringArray::from_iter_values((0..4).map(|i| format!("packed text {i}")))),
+    ])
+    .unwrap();
+    for compression in [CompressionType::ZSTD, CompressionType::LZ4_FRAME] {
+        let options = IpcWriteOptions::default().try_with_compression(Some(compression)).unwrap();
+        let mut writer = StreamWriter::try_new_with_options(Vec::new(), &schema, options).unwrap();
+        writer.write(&batch).unwrap();
+        let body = writer.into_inner().unwrap();
+        let report: Value = client.post(format!("{base}/packed/arrow")).body(body).send().await.unwrap().json().await.unwrap();
+        assert_eq!(report["rows"].as_u64(), Some(4), "{compression:?}: {report}");
+    }
+    let found: Value = client.get(format!("{base}/packed/vectors?limit=100")).send().await.unwrap().json().await.unwrap();
+    let docs = found.as_array().or_else(|| found["vectors"].as_array()).unwrap();
+    assert_eq!(docs.len(), 4, "{found}");
+    assert!(docs.iter().any(|d| d["text"] == "packed text 3"), "{found}");
+
+    // Not Arrow, or cut short (the batches before the cut are written)
+    let res = client.post(format!("{base}/docs/arrow")).body("{\"vectors\": []}").send().await.unwrap();
+    assert_eq!(res.status(), 400);
+    let res = client.post(format!("{base}/docs/arrow")).body(stream[..stream.len() - 40].to_vec()).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+    assert!(res.text().await.unwrap().contains("truncated"));
+    let res = client.post(format!("{base}/docs/arrow?batch_size=0")).body(stream).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+
+    let res = client.get(format!("{base}/docs/arrow?batch_rows=0")).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+}
@@ -0,0 +1,70 @@
+use piramid::config::{AppConfig, ExecutionMode, SearchConfig};
+use piramid::index::IndexConfig;
+use piramid::server::routes::create_router;
+use piramid::server::state::AppState;
+use piramid::testing::{BenchConfig, BenchTarget, SyntheticDataset, TestDir, run_bench, run_http_bench};
+use piramid::Metric;
+use serde_json::Value;
+use std::fs;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+fn config(target: BenchTarget) -> BenchConfig {
+    BenchConfig {
+        dataset: SyntheticDataset { count: 300, dimensions: 16, clusters: 4, queries: 40, ..Default::default() },
+        batch_size: 128,
+        concurrency: 3,
+        target,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn embedded_bench_reports_both_phases_and_cleans_up() {
+    let flat = IndexConfig::Flat { metric: Metric::Cosine, mode: ExecutionMode::default(), search: SearchConfig::default() };
+    let report = run_bench(&config(BenchTarget::Embedded { index: flat.clone(), data_dir: None })).unwrap();
+    assert_eq!(report.target, "embedded flat");
+    assert_eq!((report.insert.operations, report.insert.requests), (300, 3));
+    assert_eq!((report.search.operations, report.search.requests), (40, 40));
+    let latency = &report.search.latency;
+    assert!(latency.p50_us <= latency.p95_us && latency.p95_us <= latency.p99_us && latency.p99_us <= latency.max_us);
+    assert!(report.search.ops_per_sec > 0.0 && report.host.cpus > 0);
+
+    // On disk, the collection's files go once the run is over
+    let dir = TestDir::new("bench_embedded");
+    let data_dir = Some(dir.path("data"));
+    let report = run_bench(&config(BenchTarget::Embedded { index: flat.clone(), data_dir })).unwrap();
+    assert_eq!(report.insert.operations, 300);
+    assert_eq!(fs::read_dir(dir.path("data")).unwrap().count(), 0);
+
+    let idle = BenchConfig { concurrency: 0, ..config(BenchTarget::Embedded { index: flat, data_dir: None }) };
+    assert!(run_bench(&idle).is_err());
+}
+
+#[tokio::test]
+async fn http_bench_runs_against_a_fresh_collection() {
+    let dir = TestDir::new("bench_http");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, create_router(state)).await.unwrap() });
+    let collections = || async {
+        let list: Value = reqwest::get(format!("{url}/api/collections")).await.unwrap().json().await.unwrap();
+        list["collections"].as_array().unwrap().iter().map(|c| c["name"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+    };
+    let http = |collection: &str, keep| BenchTarget::Http { url: url.clone(), collection: collection.into(), api_key: None, keep };
+
+    let report = run_http_bench(&config(http("bench", false))).await.unwrap();
+    assert_eq!(report.target, url);
+    assert_eq!((report.insert.operations, report.insert.requests, report.search.requests), (300, 3, 40));
+    assert!(report.search.latency.mean_us > 0);
+    assert!(collections().await.is_empty());
+
+    // Kept, the collection holds the dataset, and the next run refuses to touch it
+    run_http_bench(&config(http("kept", true))).await.unwrap();
+    assert_eq!(collections().await, vec!["kept".to_string()]);
+    let count: Value = reqwest::get(format!("{url}/api/collections/kept/count")).await.unwrap().json().await.unwrap();
+    assert_eq!(count["count"], 300);
+    assert!(run_http_bench(&config(http("kept", false))).await.is_err());
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{Collection, CollectionConfig, Document, ExecutionMode, Metric, SearchParams};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// Deterministic vectors spread around a few centres, the way embeddings cluster
+fn vector(i: usize, dims: usize) -> Vec<f32> {
+    let centre = i % 8;
+    (0..dims)
+        .map(|d| ((centre * dims + d) as f32 * 1.37).sin() + 0.6 * ((i * dims + d) as f32 * 0.61).sin())
+        .collect()
+}
+
+#[test]
+fn binary_kernels_and_rerank_agree_with_scalar() {
+    // Binary scores are those of the sign vectors: cosine in [-1, 1], dot = d * cosine, euclidean^2 = Hamming distance
+    for i in 0..50 {
+        let (a, b) = (vector(i, 48), vector(i * 7 + 3, 48));
+        let cosine = Metric::Cosine.calculate(&a, &b, ExecutionMode::Binary);
+        assert!((-1.0..=1.0).contains(&cosine));
+        assert_eq!(cosine, Metric::Cosine.calculate(&b, &a, ExecutionMode::Binary));
+        assert!((piramid::metrics::dot_product(&a, &b, ExecutionMode::Binary) - 48.0 * cosine).abs() < 1e-4);
+        let hamming = piramid::metrics::euclidean_distance_squared(&a, &b, ExecutionMode::Binary);
+        assert!((cosine - (1.0 - 2.0 * hamming / 48.0)).abs() < 1e-6);
+        let negated: Vec<f32> = a.iter().map(|&x| if x >= 0.0 { -1.0 } else { 1.0 }).collect();
+        assert_eq!(Metric::Cosine.calculate(&a, &a, ExecutionMode::Binary), 1.0);
+        assert_eq!(Metric::Cosine.calculate(&a, &negated, ExecutionMode::Binary), -1.0);
+    }
+
+    let dir = TestDir::new("binary_execution");
+
+    let path = dir.path("docs.db");
+    let dims = 64;
+    let config = CollectionConfig::default().with_execution_mode(ExecutionMode::BinaryRerank);
+    let mut storage = Collection::open_with_options(&path, config.into()).unwrap();
+    let docs: Vec<Document> = (0..2000).map(|i| Document::new(vector(i, dims), format!("doc {i}"))).collect();
+    storage.insert_batch(docs).unwrap();
+
+    for metric in [Metric::Cosine, Metric::DotProduct, Metric::Euclidean] {
+        let (mut found, mut wanted) = (0, 0);
+        for q in 0..20 {
+            let query = vector(q * 97 + 5000, dims);
+            // Scalar reference: exact scores of every document
+            let mut exact: Vec<_> = storage
+                .get_all()
+                .into_iter()
+                .map(|doc| (doc.id, metric.calculate(&query, &doc.get_vector(), ExecutionMode::Scalar)))
+                .collect();
+            exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let truth: HashSet<_> = exact[..10].iter().map(|(id, _)| *id).collect();
+
+            let params = SearchParams { mode: ExecutionMode::BinaryRerank, ..Default::default() };
+            let hits = storage.search(&query, 10, metric, params);
+            assert_eq!(hits.len(), 10);
+            assert!(hits.windows(2).all(|w| w[0].score >= w[1].score));
+            for hit in &hits {
+                // Re-ranked hits carry the exact score, not the sign-code one
+                let score = exact.iter().find(|(id, _)| *id == hit.id).unwrap().1;
+                assert!((hit.score - score).abs() <= 1e-4 * score.abs().max(1.0), "{metric:?}: {} vs {score}", hit.score);
+            }
+            found += hits.iter().filter(|h| truth.contains(&h.id)).count();
+            wanted += truth.len();
+        }
+        let recall = found as f32 / wanted as f32;
+        assert!(recall >= 0.9, "{metric:?} recall@10 {recall}");
+    }
+    drop(storage);
+}
+
+#[tokio::test]
+async fn explain_reports_the_execution_mode() {
+    let dir = TestDir::new("binary_execution");
+    let data_dir = dir.data_dir();
+    let config = AppConfig { execution: ExecutionMode::BinaryRerank, ..Default::default() };
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    let base = format!("{}/collections", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let vectors: Vec<Vec<f32>> = (0..100).map(|i| vector(i, 16)).collect();
+    let texts: Vec<String> = (0..100).map(|i| format!("doc {i}")).collect();
+    let res = client.post(format!("{base}/docs/vectors")).json(&json!({"vectors": vectors, "texts": texts})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    let search = json!({"vector": vector(3, 16), "k": 5, "explain": true});
+    let body: Value = client.post(format!("{base}/docs/search")).json(&search).send().await.unwrap().json().await.unwrap();
+    let explain = &body["explain"];
+    assert_eq!((explain["execution"].as_str(), explain["strategy"].as_str()), (Some("BinaryRerank"), Some("binary_rerank")), "{body}");
+    assert_eq!((explain["exact_scores"].as_bool(), explain["candidates"].as_u64()), (Some(true), Some(40)));
+    assert_ne!(explain["kernel"], "Binary");
+    assert!((body["results"][0]["score"].as_f64().unwrap() - 1.0).abs() < 1e-5);
+
+    // Only when asked for
+    let body: Value = client.post(format!("{base}/docs/search")).json(&json!({"vector": vector(3, 16), "k": 5})).send().await.unwrap().json().await.unwrap();
+    assert!(body.get("explain").is_none());
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::storage::collection::ChangeKind;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, CollectionConfig, Document};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+#[test]
+fn changes_are_read_back_from_the_wal() {
+    let dir = TestDir::new("changes");
+    let path = dir.path("docs.db");
+    let mut storage = Collection::open(&path).unwrap();
+    let a = storage.insert(Document::new(vec![1.0, 0.0], "a".into())).unwrap();
+    let b = storage.insert(Document::new(vec![0.0, 1.0], "b".into())).unwrap();
+    storage.update_metadata(&b, metadata([("tag", "x".into())])).unwrap();
+    storage.delete(&a).unwrap();
+
+    let batch = storage.changes(0, 100).unwrap();
+    let kinds: Vec<_> = batch.changes.iter().map(|c| (c.kind, c.id)).collect();
+    assert_eq!(kinds, [(ChangeKind::Insert, a), (ChangeKind::Insert, b), (ChangeKind::Update, b), (ChangeKind::Delete, a)]);
+    assert!(batch.changes.windows(2).all(|w| w[0].seq < w[1].seq));
+    assert_eq!(batch.next_seq, storage.head_seq());
+    let (_, _, meta) = batch.changes[2].document.as_ref().unwrap();
+    assert_eq!(meta.get("tag").and_then(|v| v.as_string()), Some("x"));
+
+    // Paging: each page continues after the last change it returned
+    let first = storage.changes(0, 3).unwrap();
+    assert_eq!(first.changes.len(), 3);
+    let rest = storage.changes(first.next_seq, 3).unwrap();
+    assert_eq!(rest.changes.iter().map(|c| c.kind).collect::<Vec<_>>(), [ChangeKind::Delete]);
+    assert!(storage.changes(rest.next_seq, 3).unwrap().changes.is_empty());
+
+    // A checkpoint truncates the WAL: older changes are gone, newer ones keep coming
+    let head = storage.head_seq();
+    storage.checkpoint().unwrap();
+    assert_eq!(storage.changes(0, 100).unwrap_err().status_code(), 409);
+    let c = storage.insert(Document::new(vec![1.0, 1.0], "c".into())).unwrap();
+    let batch = storage.changes(head, 100).unwrap();
+    assert_eq!(batch.changes.iter().map(|c| c.id).collect::<Vec<_>>(), [c]);
+    drop(storage);
+
+    // With retained history the changes survive checkpoints
+    let mut config = CollectionConfig::default();
+    config.wal.history_retention_secs = Some(3600);
+    let mut storage = Collection::open_with_options(&dir.path("retained.db"), config.into()).unwrap();
+    storage.insert(Document::new(vec![1.0, 0.0], "a".into())).unwrap();
+    storage.checkpoint().unwrap();
+    storage.insert(Document::new(vec![0.0, 1.0], "b".into())).unwrap();
+    let oldest = storage.changes(0, 100).unwrap().oldest_seq;
+    let texts: Vec<_> = storage.changes(oldest, 100).unwrap().changes.into_iter().map(|c| c.document.unwrap().0).collect();
+    assert_eq!(texts, ["a", "b"]);
+    drop(storage);
+}
+
+#[tokio::test]
+async fn changes_endpoint_follows_writes() {
+    let dir = TestDir::new("changes_http");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let write: Value = client.post(format!("{base}/upsert")).json(&json!({"id": "doc-1", "vector": [1.0, 0.0], "text": "a"}))
+        .send().await.unwrap().json().await.unwrap();
+    let res: Value = client.get(format!("{base}/changes?since_seq=0")).send().await.unwrap().json().await.unwrap();
+    let change = &res["changes"][0];
+    assert_eq!((change["op"].as_str(), change["external_id"].as_str()), (Some("insert"), Some("doc-1")));
+    assert_eq!(change["seq"], write["seq"]);
+    assert_eq!(change["vector"], json!([1.0, 0.0]));
+    assert_eq!(change["metadata"]["_version"], Value::Null);
+
+    // A consumer resumes from next_seq and sees only what came after
+    let next = res["next_seq"].as_u64().unwrap();
+    client.delete(format!("{base}/vectors/doc-1")).send().await.unwrap();
+    let res: Value = client.get(format!("{base}/changes?since_seq={next}&include_vectors=false")).send().await.unwrap().json().await.unwrap();
+    let changes = res["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!((changes[0]["op"].as_str(), changes[0]["id"].clone()), (Some("delete"), change["id"].clone()));
+    assert!(changes[0].get("vector").is_none());
+    assert_eq!(res["next_seq"], res["head_seq"]);
+}
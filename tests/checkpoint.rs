@@ -0,0 +1,75 @@
+use parking_lot::RwLock;
+use piramid::config::{CollectionConfig, WalConfig};
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+use std::path::Path;
+use std::sync::Arc;
+
+fn doc(i: usize) -> Document {
+    let angle = i as f32 * 0.2;
+    Document::new(vec![angle.cos(), angle.sin(), 0.5], format!("doc {i}"))
+}
+
+#[test]
+fn writes_continue_while_a_checkpoint_is_written_out() {
+    let dir = TestDir::new("checkpoint_shadow");
+    let path = dir.path("docs.db");
+    let sealed = format!("{path}.wal.sealed");
+
+    let mut storage = Collection::open(&path).unwrap();
+    storage.insert_batch((0..10).map(doc).collect()).unwrap();
+    let collection = Arc::new(RwLock::new(storage));
+
+    // Only the capture holds the collection; a write lands before the files are written
+    let pending = collection.read().begin_checkpoint().unwrap().unwrap();
+    assert!(Path::new(&sealed).exists());
+    let late = collection.write().insert(doc(10)).unwrap();
+    pending.write().unwrap();
+    assert!(!Path::new(&sealed).exists());
+    assert!(collection.read().last_checkpoint().is_some());
+    drop(collection);
+
+    // The checkpoint holds the first ten documents and the WAL the one written meanwhile
+    let storage = Collection::open(&path).unwrap();
+    assert_eq!(storage.count(), 11);
+    assert!(storage.get(&late).is_some());
+    drop(storage);
+
+    // Checkpoints triggered by the write count are written out in the background
+    let config = CollectionConfig { wal: WalConfig { checkpoint_frequency: 4, ..Default::default() }, ..Default::default() };
+    let mut storage = Collection::open_with_options(&path, config.clone().into()).unwrap();
+    for i in 11..30 {
+        storage.insert(doc(i)).unwrap();
+    }
+    storage.wait_for_checkpoint();
+    drop(storage);
+    let storage = Collection::open_with_options(&path, config.into()).unwrap();
+    assert_eq!(storage.count(), 30);
+    let report = storage.verify().unwrap();
+    assert!(report.is_consistent(), "{report:?}");
+}
+
+#[test]
+fn sealed_wal_is_replayed_when_the_checkpoint_never_finished() {
+    let dir = TestDir::new("checkpoint_crash");
+    let path = dir.path("docs.db");
+    let sealed = format!("{path}.wal.sealed");
+
+    let mut storage = Collection::open(&path).unwrap();
+    let ids = storage.insert_batch((0..8).map(doc).collect()).unwrap();
+    storage.checkpoint().unwrap();
+
+    // Captured but never written, as if the process died before the files reached the disk
+    let more = storage.insert_batch((8..12).map(doc).collect()).unwrap();
+    drop(storage.begin_checkpoint().unwrap());
+    let last = storage.insert(doc(12)).unwrap();
+    drop(storage);
+    assert!(Path::new(&sealed).exists());
+
+    let storage = Collection::open(&path).unwrap();
+    assert_eq!(storage.count(), 13);
+    for id in ids.iter().chain(&more).chain([&last]) {
+        assert!(storage.get(id).is_some());
+    }
+    assert!(!Path::new(&sealed).exists());
+}
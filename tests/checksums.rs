@@ -0,0 +1,83 @@
+mod common;
+
+use piramid::config::{ChecksumVerification, CollectionConfig};
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+use std::collections::HashMap;
+use std::fs;
+use uuid::Uuid;
+
+fn vector(i: usize) -> Vec<f32> {
+    common::wave(i, 8, 0.41)
+}
+
+fn seed(path: &str) -> Vec<Uuid> {
+    let mut storage = Collection::open_with_options(path, CollectionConfig::default().into()).unwrap();
+    let docs = (0..10).map(|i| Document::new(vector(i), format!("original text {i}"))).collect();
+    let ids = storage.insert_batch(docs).unwrap();
+    storage.checkpoint().unwrap();
+    ids
+}
+
+fn with_checksums(checksums: ChecksumVerification) -> CollectionConfig {
+    let mut config = CollectionConfig::default();
+    config.memory.checksums = checksums;
+    config
+}
+
+#[test]
+fn damaged_entries_are_caught_on_read_and_by_verify() {
+    let dir = TestDir::new("checksums_damage");
+    let path = dir.path("docs.db");
+    let ids = seed(&path);
+
+    // Flip one letter of a stored text: the entry still decodes, just not to what was written
+    let mut data = fs::read(&path).unwrap();
+    let at = data.windows(15).position(|w| w == b"original text 3").unwrap();
+    data[at] = b'O';
+    fs::write(&path, data).unwrap();
+
+    let storage = Collection::open_with_options(&path, with_checksums(ChecksumVerification::Never).into()).unwrap();
+    assert_eq!(storage.get(&ids[3]).unwrap().text, "Original text 3");
+    assert_eq!(storage.checksum_failures(), 0);
+    drop(storage);
+
+    let mut storage = Collection::open_with_options(&path, with_checksums(ChecksumVerification::Always).into()).unwrap();
+    assert!(storage.get(&ids[3]).is_none());
+    assert_eq!(storage.get(&ids[4]).unwrap().text, "original text 4");
+    assert!(storage.checksum_failures() > 0);
+    let report = storage.verify().unwrap();
+    assert_eq!(report.checksum_mismatches.ids, vec![ids[3]]);
+    assert!(report.unreadable.is_empty() && !report.is_consistent());
+
+    storage.repair().unwrap();
+    assert_eq!(storage.count(), 9);
+    assert!(storage.verify().unwrap().is_consistent());
+    drop(storage);
+}
+
+#[test]
+fn pointer_files_from_before_checksums_still_load() {
+    let dir = TestDir::new("checksums_legacy");
+    let path = dir.path("docs.db");
+    let ids = seed(&path);
+
+    // Rewrite the pointers in the layout without a checksum
+    let pointers_path = format!("{path}.index.db");
+    let pointers: HashMap<Uuid, (u64, u32, Option<u32>)> = bincode::deserialize(&fs::read(&pointers_path).unwrap()).unwrap();
+    assert!(pointers.values().all(|p| p.2.is_some()));
+    let legacy: HashMap<Uuid, (u64, u32)> = pointers.into_iter().map(|(id, p)| (id, (p.0, p.1))).collect();
+    fs::write(&pointers_path, bincode::serialize(&legacy).unwrap()).unwrap();
+
+    let mut storage = Collection::open_with_options(&path, CollectionConfig::default().into()).unwrap();
+    assert_eq!(storage.count(), 10);
+    assert_eq!(storage.get(&ids[7]).unwrap().text, "original text 7");
+    assert!(storage.verify().unwrap().is_consistent());
+
+    // New entries get a checksum; the old ones go without until they are written again
+    let added = storage.insert(Document::new(vector(10), "added".into())).unwrap();
+    storage.checkpoint().unwrap();
+    drop(storage);
+    let pointers: HashMap<Uuid, (u64, u32, Option<u32>)> = bincode::deserialize(&fs::read(&pointers_path).unwrap()).unwrap();
+    assert!(pointers[&added].2.is_some() && pointers[&ids[0]].2.is_none());
+}
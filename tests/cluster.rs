@@ -0,0 +1,59 @@
+use piramid::cluster::{merge, HashRing};
+use serde_json::json;
+
+fn nodes(n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("http://node-{i}:6333")).collect()
+}
+
+#[test]
+fn ring_places_keys_deterministically_and_evenly() {
+    let ring = HashRing::new(nodes(3), 64);
+    let again = HashRing::new(nodes(3), 64);
+
+    let mut per_node = std::collections::HashMap::new();
+    for i in 0..3000 {
+        let key = format!("collection-{i}");
+        let node = ring.node_for(&key).unwrap();
+        assert_eq!(node, again.node_for(&key).unwrap());
+        *per_node.entry(node.to_string()).or_insert(0usize) += 1;
+    }
+
+    assert_eq!(per_node.len(), 3);
+    assert!(per_node.values().all(|&c| c > 600), "skewed placement: {per_node:?}");
+}
+
+#[test]
+fn adding_a_node_moves_only_a_fraction_of_keys() {
+    let before = HashRing::new(nodes(4), 64);
+    let after = HashRing::new(nodes(5), 64);
+
+    let moved = (0..5000)
+        .map(|i| format!("doc-{i}"))
+        .filter(|key| before.node_for(key) != after.node_for(key))
+        .count();
+
+    // Ideal is 1/5 of the keys; modulo hashing would move ~4/5
+    assert!(moved < 5000 * 2 / 5, "moved {moved} of 5000 keys");
+}
+
+#[test]
+fn merge_search_reranks_across_shards() {
+    let a = json!({"results": [{"id": "a1", "score": 0.9}, {"id": "a2", "score": 0.5}], "latency_ms": 3.0});
+    let b = json!({"results": [{"id": "b1", "score": 0.95}, {"id": "b2", "score": 0.1}], "latency_ms": 7.0});
+
+    let merged = merge::merge_search(&[a, b], 3);
+    let ids: Vec<&str> = merged["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|h| h["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["b1", "a1", "a2"]);
+    assert_eq!(merged["latency_ms"], json!(7.0));
+
+    let batch_a = json!({"results": [[{"id": "a1", "score": 0.2}], [{"id": "a2", "score": 0.8}]]});
+    let batch_b = json!({"results": [[{"id": "b1", "score": 0.4}], [{"id": "b2", "score": 0.3}]]});
+    let merged = merge::merge_search(&[batch_a, batch_b], 1);
+    assert_eq!(merged["results"][0][0]["id"], "b1");
+    assert_eq!(merged["results"][1][0]["id"], "a2");
+}
@@ -1,4 +1,5 @@
 use piramid::{Collection, Document, Metric, search::SearchParams};
+use piramid::testing::TestDir;
 use std::fs;
 
 fn ensure_test_dir() {
@@ -82,7 +83,7 @@ fn search_returns_results() {
     cleanup_test_files(&files);
 
     let mut storage = Collection::open(test_path).unwrap();
-    let vectors = vec![
+    let vectors = [
         vec![1.0, 0.0, 0.0],
         vec![0.0, 1.0, 0.0],
         vec![0.0, 0.0, 1.0],
@@ -129,3 +130,39 @@ fn batch_search_multi_queries() {
     drop(storage);
     cleanup_test_files(&files);
 }
+
+#[test]
+fn updates_rewrite_in_place() {
+    let dir = TestDir::new("update_in_place");
+    let test_path = dir.path("docs.db");
+
+    let (a, b);
+    {
+        let mut storage = Collection::open(&test_path).unwrap();
+        a = storage.insert(Document::new(vec![1.0, 0.0], "a".to_string()).with_external_id("doc-a")).unwrap();
+        b = storage.insert(Document::new(vec![0.0, 1.0], "b".to_string())).unwrap();
+
+        // A metadata update is a single WAL entry, not update + delete + insert
+        let seq = storage.head_seq();
+        let metadata = piramid::metadata([("tag", "x".into()), (piramid::storage::EXTERNAL_ID_KEY, "doc-a2".into())]);
+        assert!(storage.update_metadata(&a, metadata).unwrap());
+        assert_eq!(storage.head_seq(), seq + 1);
+        assert_eq!(storage.count(), 2);
+        assert_eq!(storage.resolve_id("doc-a2"), Some(a));
+        assert_eq!(storage.resolve_id("doc-a"), None);
+        assert_eq!(storage.get(&a).unwrap().get_vector(), vec![1.0, 0.0]);
+
+        // A vector update moves the document in the index
+        assert!(storage.update_vector(&b, vec![1.0, 0.1]).unwrap());
+        let hits = storage.search(&[1.0, 0.1], 1, Metric::Cosine, SearchParams::default());
+        assert_eq!(hits[0].id, b);
+        assert_eq!(storage.head_seq(), seq + 2);
+    }
+
+    let storage = Collection::open(&test_path).unwrap();
+    assert_eq!(storage.count(), 2);
+    assert_eq!(storage.get(&a).unwrap().metadata.get("tag").and_then(|v| v.as_string()), Some("x"));
+    assert_eq!(storage.resolve_id("doc-a2"), Some(a));
+    let hits = storage.search(&[1.0, 0.1], 1, Metric::Cosine, SearchParams::default());
+    assert_eq!(hits[0].id, b);
+}
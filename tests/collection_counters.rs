@@ -0,0 +1,105 @@
+mod common;
+
+use piramid::config::{AppConfig, CollectionConfig};
+use piramid::server::state::AppState;
+use piramid::storage::collection::compact;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document};
+use serde_json::{json, Value};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use common::vector;
+
+fn counts(storage: &Collection) -> (u64, u64, u64) {
+    let counters = &storage.metadata().counters;
+    (
+        counters.total_inserts.load(Ordering::Relaxed),
+        counters.total_updates.load(Ordering::Relaxed),
+        counters.total_deletes.load(Ordering::Relaxed),
+    )
+}
+
+#[test]
+fn counters_accumulate_and_survive_restarts_and_compaction() {
+    let dir = TestDir::new("collection_counters");
+    let path = dir.path("docs.db");
+
+    let mut storage = Collection::open(&path).unwrap();
+    let ids = storage.insert_batch((0..10).map(|i| Document::new(vector(i), format!("doc {i}"))).collect()).unwrap();
+    storage.insert(Document::new(vector(10), "one more".into())).unwrap();
+    storage.update_vector(&ids[0], vector(20)).unwrap();
+    storage.update_metadata(&ids[1], metadata([("kind", "note".into())])).unwrap();
+    storage.delete_batch(&ids[5..8]).unwrap();
+    assert_eq!(counts(&storage), (11, 2, 3));
+    assert!(storage.metadata().counters.dead_bytes.load(Ordering::Relaxed) > 0);
+    storage.checkpoint().unwrap();
+
+    // Written after the checkpoint, so counted again when the WAL is replayed
+    storage.delete(&ids[9]).unwrap();
+    drop(storage);
+    let mut storage = Collection::open(&path).unwrap();
+    assert_eq!(counts(&storage), (11, 2, 4));
+
+    // Compaction reclaims the dead bytes without counting its rewrites as inserts
+    assert!(storage.metadata().counters.last_compaction_at.is_none());
+    compact(&mut storage).unwrap();
+    let counters = &storage.metadata().counters;
+    assert_eq!(counters.dead_bytes.load(Ordering::Relaxed), 0);
+    assert!(counters.last_compaction_at.is_some());
+    assert_eq!(counts(&storage), (11, 2, 4));
+
+    storage.rebuild_index().unwrap();
+    drop(storage);
+    let storage = Collection::open(&path).unwrap();
+    let counters = &storage.metadata().counters;
+    assert!(counters.last_rebuild_at.is_some() && counters.last_compaction_at.is_some());
+    assert_eq!(counts(&storage), (11, 2, 4));
+    drop(storage);
+}
+
+#[test]
+fn metadata_saved_between_checkpoints_is_not_counted_again_on_replay() {
+    let dir = TestDir::new("collection_counters_replay");
+    let mut storage = dir.open("docs", CollectionConfig::default()).unwrap();
+    let ids: Vec<_> = (0..3).map(|i| storage.insert(Document::new(vector(i), format!("doc {i}"))).unwrap()).collect();
+    // Saves the metadata, counters included, while the inserts are still only in the WAL
+    storage.rebuild_index().unwrap();
+    storage.delete(&ids[0]).unwrap();
+    drop(storage);
+
+    let storage = dir.open("docs", CollectionConfig::default()).unwrap();
+    assert_eq!(storage.count(), 2);
+    assert_eq!(counts(&storage), (3, 0, 1));
+    drop(storage);
+    assert_eq!(counts(&dir.open("docs", CollectionConfig::default()).unwrap()), (3, 0, 1));
+}
+
+#[tokio::test]
+async fn collection_info_reports_the_counters() {
+    let dir = TestDir::new("collection_counters_http");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = common::serve(state).await;
+    let client = reqwest::Client::new();
+
+    client.post(format!("{base}/collections")).json(&json!({"name": "docs"})).send().await.unwrap();
+    let mut ids = Vec::new();
+    for i in 0..3 {
+        let res: Value = client.post(format!("{base}/collections/docs/vectors"))
+            .json(&json!({"vector": vector(i), "text": format!("doc {i}")}))
+            .send().await.unwrap().json().await.unwrap();
+        ids.push(res["id"].as_str().unwrap().to_string());
+    }
+    let res = client.delete(format!("{base}/collections/docs/vectors/{}", ids[0])).send().await.unwrap();
+    assert!(res.status().is_success());
+
+    let info: Value = client.get(format!("{base}/collections/docs")).send().await.unwrap().json().await.unwrap();
+    let counters = &info["counters"];
+    assert_eq!((counters["total_inserts"].as_u64(), counters["total_updates"].as_u64(), counters["total_deletes"].as_u64()), (Some(3), Some(0), Some(1)), "{info}");
+    assert!(counters["dead_bytes"].as_u64().unwrap() > 0);
+    assert!(counters["last_compaction_at"].is_null() && counters["last_rebuild_at"].is_null());
+
+    // Listings stay lean
+    let list: Value = client.get(format!("{base}/collections")).send().await.unwrap().json().await.unwrap();
+    assert!(list["collections"][0].get("counters").is_none());
+}
@@ -0,0 +1,48 @@
+use piramid::error::{ErrorCode, PiramidError, ServerError};
+use piramid::storage::collection::{collection_path, get_lock_path};
+use piramid::testing::TestDir;
+use piramid::{CollectionConfig, Document};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn collections_of_one_process_share_the_lock_and_record_its_pid() {
+    let dir = TestDir::new("collection_lock_shared");
+    let path = collection_path(dir.root(), "docs");
+    assert_eq!(Path::new(&path), dir.root().join("docs.db"));
+
+    let mut first = dir.open("docs", CollectionConfig::default()).unwrap();
+    first.insert(Document::new(vec![1.0, 0.0], "one".into())).unwrap();
+    let second = dir.open("docs", CollectionConfig::default()).unwrap();
+    assert_eq!(second.count(), 1);
+    assert_eq!(fs::read_to_string(get_lock_path(&path)).unwrap(), std::process::id().to_string());
+    drop((first, second));
+
+    // Released with the last collection, the lock is taken again on the next open
+    let reopened = dir.open("docs", CollectionConfig::default()).unwrap();
+    assert_eq!(reopened.count(), 1);
+}
+
+#[test]
+fn another_process_is_refused_while_the_collection_is_open() {
+    let dir = TestDir::new("collection_lock_process");
+    let data_dir = dir.root().canonicalize().unwrap();
+    let fsck = || Command::new(env!("CARGO_BIN_EXE_piramid")).arg("fsck").arg("docs").arg("--data-dir").arg(&data_dir).output().unwrap();
+
+    let mut storage = dir.open("docs", CollectionConfig::default()).unwrap();
+    storage.insert(Document::new(vec![1.0, 0.0], "one".into())).unwrap();
+    storage.checkpoint().unwrap();
+    let refused = fsck();
+    assert_eq!(refused.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&refused.stderr);
+    assert!(stderr.contains(&format!("open in another process (pid {})", std::process::id())), "{stderr}");
+
+    // Closed here, the other process gets it
+    drop(storage);
+    let checked = fsck();
+    assert!(checked.status.success(), "{}", String::from_utf8_lossy(&checked.stderr));
+
+    let locked: PiramidError = ServerError::CollectionLocked("docs".into()).into();
+    assert_eq!(locked.error_code(), ErrorCode::CollectionLocked);
+}
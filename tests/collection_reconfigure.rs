@@ -0,0 +1,70 @@
+use piramid::config::{AppConfig, CollectionConfig};
+use piramid::server::routes::create_router;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+#[test]
+fn live_settings_apply_and_the_rest_wait_for_a_reopen() {
+    let dir = TestDir::new("reconfigure");
+    let old = CollectionConfig::default();
+    let mut storage = Collection::open_with_options(&dir.path("docs.db"), old.clone().into()).unwrap();
+    assert!(storage.reconfigure(&old, &old).is_empty());
+
+    let mut new = old.clone();
+    new.limits.max_vectors = Some(2);
+    new.search.filter_overfetch = 7;
+    new.wal.checkpoint_frequency = 3;
+    new.memory.use_mmap = !old.memory.use_mmap;
+    new.wal.enabled = !old.wal.enabled;
+    let changes = storage.reconfigure(&old, &new);
+    assert_eq!(changes.applied, ["limits.max_vectors", "search.filter_overfetch", "wal.checkpoint_frequency"]);
+    assert_eq!(changes.needs_reopen, ["memory.use_mmap", "wal.enabled"]);
+
+    // Applied: in effect right away. Not applied: left as the collection opened with it
+    assert_eq!(storage.config.search.filter_overfetch, 7);
+    assert_eq!((storage.config.memory.use_mmap, storage.config.wal.enabled), (old.memory.use_mmap, old.wal.enabled));
+    for i in 0..2 {
+        storage.insert(Document::new(vec![1.0, i as f32], format!("doc {i}"))).unwrap();
+    }
+    assert!(storage.insert(Document::new(vec![0.0, 1.0], "one too many".into())).is_err());
+    drop(storage);
+}
+
+#[tokio::test]
+async fn config_reload_reconfigures_open_collections() {
+    let dir = TestDir::new("reconfigure_http");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}/api/collections", listener.local_addr().unwrap());
+    tokio::spawn({
+        let state = state.clone();
+        async move { axum::serve(listener, create_router(state)).await.unwrap() }
+    });
+    let client = reqwest::Client::new();
+    for name in ["a", "b"] {
+        let res = client.post(format!("{base}/{name}/vectors")).json(&json!({"vector": [0.0, 0.0], "text": "zero"})).send().await.unwrap();
+        assert_eq!(res.status(), 200);
+    }
+
+    let mut cfg = AppConfig::default();
+    cfg.validation.reject_zero_cosine = true;
+    cfg.two_stage.enabled = true;
+    cfg.collection_transforms.insert("b".into(), Default::default());
+    let changes = state.apply_config(cfg);
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes["a"].applied, ["validation.reject_zero_cosine"]);
+    assert_eq!(changes["a"].needs_reopen, ["two_stage.enabled"]);
+    assert_eq!(changes["a"], changes["b"]);
+
+    // The open collections refuse zero vectors now, without a reopen
+    for name in ["a", "b"] {
+        let res = client.post(format!("{base}/{name}/vectors")).json(&json!({"vector": [0.0, 0.0], "text": "zero"})).send().await.unwrap();
+        assert_eq!(res.status(), 400, "{name}");
+    }
+    assert!(state.apply_config(state.current_config()).is_empty());
+}
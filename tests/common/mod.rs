@@ -0,0 +1,44 @@
+// Helpers shared by the integration tests; each test file that needs them declares `mod common;`
+// - vectors: deterministic test vectors (smooth waves, uniform pseudo-random, counting)
+// - serve: the HTTP API for a server state on a free local port
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use piramid::server::routes::create_router;
+use piramid::server::state::AppState;
+use tokio::net::TcpListener;
+
+// Dimension d of vector i is sin((i * dims + d) * step): smooth, and different for every i
+pub fn wave(i: usize, dims: usize, step: f32) -> Vec<f32> {
+    (0..dims).map(|d| ((i * dims + d) as f32 * step).sin()).collect()
+}
+
+// The 8-dimensional wave most tests store
+pub fn vector(i: usize) -> Vec<f32> {
+    wave(i, 8, 0.37)
+}
+
+// Uniform in [-0.5, 0.5) per dimension, the same for the same i, so no two vectors point the same way
+pub fn random_vector(i: usize, dims: usize) -> Vec<f32> {
+    let mut state = (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) + 1;
+    (0..dims)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+        })
+        .collect()
+}
+
+// i * dims, i * dims + 1, ... each plus 0.25, so values survive a round trip recognizably
+pub fn counting_vector(i: usize, dims: usize) -> Vec<f32> {
+    (0..dims).map(|d| (i * dims + d) as f32 + 0.25).collect()
+}
+
+// Serve the API of `state` on 127.0.0.1 at a free port; returns `http://127.0.0.1:<port>/api`
+pub async fn serve(state: Arc<AppState>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let api = format!("http://{}/api", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, create_router(state)).await.unwrap() });
+    api
+}
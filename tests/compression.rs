@@ -0,0 +1,105 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::Document;
+use piramid::testing::TestDir;
+use std::sync::Arc;
+
+// Serve the full router on an ephemeral port with `count` 64-dimensional vectors in "docs"
+async fn serve(dir: &TestDir, count: usize) -> String {
+    let state = Arc::new(AppState::new(dir.data_dir(), AppConfig::default(), 500, None, false, None));
+    state.get_or_create_collection("docs").unwrap();
+    {
+        let collection = state.collections.get("docs").unwrap();
+        let mut storage = collection.write();
+        for i in 0..count {
+            storage.insert(Document::new(common::wave(i, 64, 0.37), format!("document number {i}"))).unwrap();
+        }
+    }
+    common::serve(state).await
+}
+
+async fn fetch(client: &reqwest::Client, url: &str, accept: &str) -> (Option<String>, Vec<u8>) {
+    let res = client.get(url).header("accept-encoding", accept).send().await.unwrap();
+    assert!(res.status().is_success());
+    let encoding = res.headers().get("content-encoding").map(|v| v.to_str().unwrap().to_string());
+    (encoding, res.bytes().await.unwrap().to_vec())
+}
+
+#[tokio::test]
+async fn large_vector_lists_shrink_on_the_wire() {
+    let dir = TestDir::new("compression_list");
+    let base = serve(&dir, 300).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base}/collections/docs/vectors?limit=1000");
+
+    let (encoding, identity) = fetch(&client, &url, "identity").await;
+    assert_eq!(encoding, None);
+    assert!(identity.len() > 100_000);
+
+    let (encoding, gzipped) = fetch(&client, &url, "gzip").await;
+    assert_eq!(encoding.as_deref(), Some("gzip"));
+    assert!(gzipped.len() * 10 < identity.len() * 6, "gzip {} vs {}", gzipped.len(), identity.len());
+    // The gzip trailer carries the CRC-32 and length of the uncompressed body
+    let trailer = &gzipped[gzipped.len() - 8..];
+    assert_eq!(u32::from_le_bytes(trailer[..4].try_into().unwrap()), crc32fast::hash(&identity));
+    assert_eq!(u32::from_le_bytes(trailer[4..].try_into().unwrap()) as usize, identity.len());
+
+    let (encoding, zstd) = fetch(&client, &url, "gzip;q=0.5, zstd").await;
+    assert_eq!(encoding.as_deref(), Some("zstd"));
+    assert_eq!(&zstd[..4], &[0x28, 0xb5, 0x2f, 0xfd]);
+    assert_eq!(zstd::decode_all(&zstd[..]).unwrap(), identity);
+    assert!(zstd.len() * 10 < identity.len() * 6, "zstd {} vs {}", zstd.len(), identity.len());
+
+    // Refused encodings and bodies under the threshold go out as they are
+    let (encoding, _) = fetch(&client, &url, "gzip;q=0, zstd;q=0").await;
+    assert_eq!(encoding, None);
+    let small = format!("{base}/collections/docs/vectors?limit=1");
+    let (encoding, _) = fetch(&client, &small, "gzip, zstd").await;
+    assert_eq!(encoding, None);
+}
+
+#[tokio::test]
+async fn http2_prior_knowledge_and_compressed_requests() {
+    let dir = TestDir::new("compression_h2");
+    let base = serve(&dir, 50).await;
+    let client = reqwest::Client::builder().http2_prior_knowledge().build().unwrap();
+    let res = client
+        .get(format!("{base}/collections/docs/vectors?limit=100"))
+        .header("accept-encoding", "gzip")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.version(), reqwest::Version::HTTP_2);
+    assert_eq!(res.headers().get("content-encoding").unwrap(), "gzip");
+
+    let url = format!("{base}/collections/docs/vectors?limit=100");
+    assert_eq!(fetch(&client, &url, "zstd;q=0.2, gzip;q=0.8").await.0.as_deref(), Some("gzip"));
+    assert_eq!(fetch(&client, &url, "br, identity").await.0, None);
+
+    // A zstd request body, as the reference encoder writes it, is decompressed before the handler
+    let vector: Vec<f32> = (0..64).map(|d| d as f32 / 64.0).collect();
+    let body = serde_json::to_vec(&serde_json::json!({"vector": vector, "text": "sent compressed"})).unwrap();
+    let res = client
+        .post(format!("{base}/collections/docs/vectors"))
+        .header("content-type", "application/json")
+        .header("content-encoding", "zstd")
+        .body(zstd::encode_all(&body[..], 3).unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success(), "{}", res.text().await.unwrap());
+    let count: serde_json::Value = client.get(format!("{base}/collections/docs/count")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(count["count"], 51);
+
+    let res = client
+        .post(format!("{base}/collections/docs/vectors"))
+        .header("content-type", "application/json")
+        .header("content-encoding", "br")
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
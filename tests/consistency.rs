@@ -0,0 +1,94 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+async fn serve(data_dir: &str, config: AppConfig) -> String {
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    format!("{}/collections/docs", common::serve(state).await)
+}
+
+#[tokio::test]
+async fn write_responses_carry_a_seq_that_reads_can_wait_for() {
+    let dir = TestDir::new("consistency_tokens");
+    let data_dir = dir.data_dir();
+    let base = serve(data_dir, AppConfig::default()).await;
+    let client = reqwest::Client::new();
+
+    let first: Value = client.post(format!("{base}/vectors"))
+        .json(&json!({"vector": [1.0, 0.0, 0.0], "text": "first", "external_id": "a"}))
+        .send().await.unwrap().json().await.unwrap();
+    let batch: Value = client.post(format!("{base}/vectors"))
+        .json(&json!({"vectors": [[0.0, 1.0, 0.0], [0.0, 0.0, 1.0]], "texts": ["second", "third"]}))
+        .send().await.unwrap().json().await.unwrap();
+    let upsert: Value = client.post(format!("{base}/upsert"))
+        .json(&json!({"external_id": "a", "vector": [1.0, 0.1, 0.0], "text": "first again"}))
+        .send().await.unwrap().json().await.unwrap();
+    let seqs: Vec<u64> = [&first, &batch, &upsert].iter().map(|res| res["seq"].as_u64().unwrap()).collect();
+    assert!(seqs.windows(2).all(|w| w[0] < w[1]), "{seqs:?}");
+
+    let min_seq = seqs[2];
+    let doc: Value = client.get(format!("{base}/vectors/a?min_seq={min_seq}"))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(doc["text"], "first again");
+    let res: Value = client.post(format!("{base}/search"))
+        .json(&json!({"vector": [1.0, 0.1, 0.0], "k": 1, "min_seq": min_seq}))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["results"][0]["text"], "first again");
+    let res: Value = client.post(format!("{base}/search/range"))
+        .json(&json!({"vector": [0.0, 1.0, 0.0], "min_score": 0.9, "min_seq": min_seq}))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["results"][0]["text"], "second");
+
+    let deleted: Value = client.delete(format!("{base}/vectors/a")).send().await.unwrap().json().await.unwrap();
+    assert!(deleted["seq"].as_u64().unwrap() > min_seq);
+    let list: Value = client.get(format!("{base}/vectors?min_seq={}", deleted["seq"]))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(list.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn reads_wait_a_bounded_time_for_the_sequence() {
+    let dir = TestDir::new("consistency_wait");
+    let data_dir = dir.data_dir();
+    let config = AppConfig {
+        min_seq_wait_ms: 200,
+        hot_collections: [("docs".to_string(), 2)].into(),
+        ..Default::default()
+    };
+    let base = serve(data_dir, config).await;
+    let client = reqwest::Client::new();
+    let res: Value = client.post(format!("{base}/vectors"))
+        .json(&json!({"vector": [1.0, 0.0], "text": "first"}))
+        .send().await.unwrap().json().await.unwrap();
+    let seq = res["seq"].as_u64().unwrap();
+
+    // A sequence nobody writes times out with 503 once the wait is over
+    let start = Instant::now();
+    let res = client.post(format!("{base}/search"))
+        .json(&json!({"vector": [1.0, 0.0], "k": 5, "min_seq": seq + 100}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 503);
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert!(res.text().await.unwrap().contains(&format!("not yet {}", seq + 100)));
+
+    // A search for the next write waits for it, and sees it on the collection's replicas
+    let search = tokio::spawn({
+        let (client, base) = (client.clone(), base.clone());
+        async move {
+            client.post(format!("{base}/search"))
+                .json(&json!({"vector": [0.0, 1.0], "k": 5, "min_seq": seq + 1}))
+                .send().await.unwrap().json::<Value>().await.unwrap()
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    client.post(format!("{base}/vectors"))
+        .json(&json!({"vector": [0.0, 1.0], "text": "second"}))
+        .send().await.unwrap();
+    let res = search.await.unwrap();
+    assert_eq!(res["results"][0]["text"], "second", "{res}");
+}
@@ -0,0 +1,114 @@
+use piramid::config::DataDirLockConfig;
+use piramid::error::{ErrorCode, PiramidError};
+use piramid::server::data_dir_lock::{data_dir_owner, host_name, DataDirLock, DataDirOwner, DATA_DIR_LOCK_FILE};
+use piramid::testing::TestDir;
+use serde_json::Value;
+use std::fs;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+fn write_owner(dir: &TestDir, owner: &DataDirOwner) {
+    fs::write(dir.path(DATA_DIR_LOCK_FILE), serde_json::to_vec(owner).unwrap()).unwrap();
+}
+
+#[test]
+fn data_dir_lock_refuses_live_owners_and_takes_over_dead_ones() {
+    let dir = TestDir::new("data_dir_lock");
+    let data_dir = dir.path("");
+    let config = DataDirLockConfig::default();
+
+    let lock = DataDirLock::acquire(&data_dir, &config).unwrap();
+    let owner = data_dir_owner(&data_dir).unwrap();
+    assert_eq!((owner.pid, owner.host.as_str()), (std::process::id(), host_name().as_str()));
+    assert_eq!(lock.owner(), owner);
+    let Err(refused) = DataDirLock::acquire(&data_dir, &config) else { panic!("second owner let in") };
+    assert_eq!(refused.error_code(), ErrorCode::DataDirLocked);
+    assert!(refused.to_string().contains(&format!("pid {} on {}", owner.pid, owner.host)), "{refused}");
+
+    // Released on drop, with nothing left naming the old owner
+    drop(lock);
+    assert!(data_dir_owner(&data_dir).is_none());
+
+    // A free lock naming this host means the owner died; one naming another host counts while its heartbeat is fresh
+    let now = piramid::testing::clock::now_secs();
+    let elsewhere = |heartbeat_at| DataDirOwner { pid: 1, host: "elsewhere".into(), started_at: now - 600, heartbeat_at };
+    write_owner(&dir, &DataDirOwner { host: host_name(), ..elsewhere(now) });
+    drop(DataDirLock::acquire(&data_dir, &config).unwrap());
+    write_owner(&dir, &elsewhere(now - 5));
+    let refused = DataDirLock::acquire(&data_dir, &config).err().unwrap();
+    assert!(matches!(&refused, PiramidError::Server(_)) && refused.to_string().contains("pid 1 on elsewhere"), "{refused}");
+    write_owner(&dir, &elsewhere(now - config.stale_after_secs));
+    assert!(DataDirLock::acquire(&data_dir, &config).is_ok());
+
+    assert!(DataDirLockConfig { stale_after_secs: 10, ..config }.validate().is_err());
+}
+
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+fn serve(data_dir: &str, port: u16, on_conflict: &str) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_piramid"));
+    command
+        .args(["serve", "--port", &port.to_string(), "--data-dir", data_dir])
+        .env_remove("CONFIG_FILE")
+        .env("DATA_DIR_LOCK_ON_CONFLICT", on_conflict)
+        .env("DATA_DIR_LOCK_HEARTBEAT_SECS", "1")
+        .env("DATA_DIR_LOCK_STALE_AFTER_SECS", "3")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    command
+}
+
+// GET `path` until `done` accepts the answer
+async fn poll(port: u16, path: &str, done: impl Fn(u16, &Value) -> bool) -> (u16, Value) {
+    let start = Instant::now();
+    loop {
+        if let Ok(res) = reqwest::get(format!("http://127.0.0.1:{port}{path}")).await {
+            let status = res.status().as_u16();
+            let body: Value = res.json().await.unwrap_or(Value::Null);
+            if done(status, &body) {
+                return (status, body);
+            }
+        }
+        assert!(start.elapsed() < Duration::from_secs(30), "{path} on {port} never got there");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[tokio::test]
+async fn second_server_refuses_or_stands_by_until_the_owner_exits() {
+    let dir = TestDir::new("data_dir_lock_servers");
+    let data_dir = dir.root().canonicalize().unwrap().to_string_lossy().into_owned();
+    let (owner_port, standby_port) = (free_port(), free_port());
+    let owner = Server(serve(&data_dir, owner_port, "refuse").spawn().unwrap());
+    poll(owner_port, "/api/collections", |status, _| status == 200).await;
+
+    let refused = serve(&data_dir, free_port(), "refuse").output().unwrap();
+    assert_eq!(refused.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&refused.stderr);
+    assert!(stderr.contains(&format!("in use by pid {}", owner.0.id())), "{stderr}");
+
+    let _standby = Server(serve(&data_dir, standby_port, "standby").spawn().unwrap());
+    let (_, health) = poll(standby_port, "/api/health", |status, _| status == 200).await;
+    assert_eq!(health["status"], "standby");
+    let (status, body) = poll(standby_port, "/api/collections", |_, _| true).await;
+    assert_eq!(status, 503);
+    assert_eq!(body["error_code"], "DATA_DIR_LOCKED");
+    assert_eq!(body["retryable"], true);
+
+    // Once the owner is gone the standby takes the data dir and serves it
+    drop(owner);
+    poll(standby_port, "/api/collections", |status, _| status == 200).await;
+    let (_, health) = poll(standby_port, "/api/health", |status, _| status == 200).await;
+    assert_ne!(health["status"], "standby");
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use piramid::config::{AppConfig, CollectionConfig, ExecutionMode, SearchConfig};
+use piramid::index::{IndexConfig, IndexDetails};
+use piramid::testing::{seed_ids, TestDir};
+use piramid::{Collection, Document, Metric, SearchParams};
+use uuid::Uuid;
+
+fn vector(i: usize) -> Vec<f32> {
+    common::wave(i, 8, 0.61)
+}
+
+fn build(dir: &TestDir, name: &str, index: IndexConfig, deterministic: bool) -> Collection {
+    let _ids = seed_ids(7);
+    let config = CollectionConfig { index, deterministic, ..Default::default() };
+    let mut storage = dir.open(name, config).unwrap();
+    storage.insert_batch((0..300).map(|i| Document::new(vector(i), format!("doc {i}"))).collect()).unwrap();
+    storage.checkpoint().unwrap();
+    storage
+}
+
+fn results(storage: &Collection) -> Vec<Vec<Uuid>> {
+    (1000..1020)
+        .map(|q| storage.search(&vector(q), 10, Metric::Cosine, SearchParams::default()).into_iter().map(|h| h.id).collect())
+        .collect()
+}
+
+fn layout(storage: &Collection) -> String {
+    match storage.vector_index().stats().details {
+        IndexDetails::Hnsw { layer_sizes, avg_out_degree, .. } => format!("{layer_sizes:?} {avg_out_degree:?}"),
+        IndexDetails::Ivf { vectors_per_cluster, .. } => format!("{vectors_per_cluster:?}"),
+        other => format!("{other:?}"),
+    }
+}
+
+#[test]
+fn deterministic_builds_give_the_same_index_and_results() {
+    let dir = TestDir::new("deterministic_index");
+    let hnsw = IndexConfig::Hnsw {
+        m: 4,
+        m_max: 8,
+        ef_construction: 16,
+        ef_search: 10,
+        ml: 1.0 / 4f32.ln(),
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+        shards: 1,
+    };
+    let ivf = IndexConfig::Ivf {
+        num_clusters: 12,
+        num_probes: 1,
+        max_iterations: 5,
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    };
+
+    for (kind, index) in [("hnsw", hnsw), ("ivf", ivf)] {
+        let mut a = build(&dir, &format!("{kind}_a"), index.clone(), true);
+        let mut b = build(&dir, &format!("{kind}_b"), index.clone(), true);
+        assert_eq!(layout(&a), layout(&b), "{kind}");
+        assert_eq!(results(&a), results(&b), "{kind}");
+
+        // Rebuilt, and rebuilt again after a reopen, the index comes back the same
+        a.rebuild_index().unwrap();
+        b.rebuild_index().unwrap();
+        let rebuilt = results(&a);
+        assert_eq!(rebuilt, results(&b), "{kind}");
+        drop(a);
+        let config = CollectionConfig { index, deterministic: true, ..Default::default() };
+        let mut a = dir.open(&format!("{kind}_a"), config).unwrap();
+        assert_eq!(results(&a), rebuilt, "{kind}");
+        a.rebuild_index().unwrap();
+        assert_eq!((layout(&a), results(&a)), (layout(&b), rebuilt), "{kind}");
+    }
+}
+
+#[test]
+fn equal_scores_are_ordered_by_id() {
+    let dir = TestDir::new("deterministic_ties");
+    for (name, index) in [("flat", IndexConfig::Flat { metric: Metric::Cosine, mode: ExecutionMode::default(), search: SearchConfig::default() }), ("auto", IndexConfig::default())] {
+        let mut storage = dir.open(name, CollectionConfig::with_index(index)).unwrap();
+        let mut ids: Vec<Uuid> = (0..6).map(|i| storage.insert(Document::new(vec![1.0, 0.0, 0.0], format!("copy {i}"))).unwrap()).collect();
+        storage.insert(Document::new(vec![0.0, 1.0, 0.0], "other".into())).unwrap();
+        ids.sort();
+        let hits: Vec<Uuid> = storage.search(&[1.0, 0.0, 0.0], 4, Metric::Cosine, SearchParams::default()).into_iter().map(|h| h.id).collect();
+        assert_eq!(hits, ids[..4], "{name}");
+    }
+
+    // Named collections are deterministic, the rest are not
+    let config = AppConfig { deterministic_collections: vec!["audit".into()], ..Default::default() };
+    assert!(config.collection_config("audit").deterministic);
+    assert!(!config.collection_config("docs").deterministic);
+}
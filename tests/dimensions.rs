@@ -0,0 +1,70 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document, Metric, SearchParams};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+#[test]
+fn queries_of_another_size_are_rejected() {
+    let dir = TestDir::new("dimensions");
+    let path = dir.path("docs.db");
+    let mut storage = Collection::open(&path).unwrap();
+    assert_eq!(storage.dimension_report().expected, None);
+    // An empty collection takes any query
+    assert!(storage.try_search(&[1.0, 0.0], 5, Metric::Cosine, SearchParams::default()).unwrap().is_empty());
+
+    for i in 0..5 {
+        storage.insert(Document::new(vec![1.0, i as f32, 0.5], format!("doc {i}"))).unwrap();
+    }
+    let err = storage.insert(Document::new(vec![1.0, 0.0], "short".into())).unwrap_err();
+    assert_eq!(err.status_code(), 400);
+
+    let err = storage.try_search(&[1.0, 0.0], 5, Metric::Cosine, SearchParams::default()).unwrap_err();
+    assert_eq!(err.status_code(), 400);
+    assert!(err.to_string().contains("rename"), "{err}");
+    assert!(storage.search(&[1.0, 0.0, 0.0, 0.0], 5, Metric::Cosine, SearchParams::default()).is_empty());
+    assert_eq!(storage.search(&[1.0, 0.0, 0.0], 5, Metric::Cosine, SearchParams::default()).len(), 5);
+
+    let report = storage.dimension_report();
+    assert_eq!(report.expected, Some(3));
+    assert_eq!(report.mismatched, 0);
+    assert_eq!((report.distribution.len(), report.distribution[0].dimensions, report.distribution[0].count), (1, 3, 5));
+    drop(storage);
+}
+
+#[tokio::test]
+async fn dimension_endpoint_and_search_rejection() {
+    let dir = TestDir::new("dimensions_http");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let res = client.post(format!("{base}/vectors"))
+        .json(&json!({"vectors": [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0]], "texts": ["a", "b"]}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    // Single, batch and range searches with a query of another size all get a 400 with a hint
+    for (path, body) in [
+        ("search", json!({"vector": [1.0, 0.0], "k": 2})),
+        ("search", json!({"vectors": [[1.0, 0.0, 0.0, 0.0], [1.0, 0.0]], "k": 2})),
+        ("search/range", json!({"vector": [1.0, 0.0, 0.0], "min_score": 0.0})),
+    ] {
+        let res = client.post(format!("{base}/{path}")).json(&body).send().await.unwrap();
+        assert_eq!(res.status(), 400, "{path}");
+        let text = res.text().await.unwrap();
+        assert!(text.contains("holds 4-dimension vectors"), "{text}");
+    }
+    let res = client.post(format!("{base}/search")).json(&json!({"vector": [1.0, 0.0, 0.0, 0.0], "k": 2})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    let report: Value = client.get(format!("{base}/dimensions")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(report["expected"], 4);
+    assert_eq!(report["distribution"], json!([{"dimensions": 4, "count": 2}]));
+    assert_eq!(report["mismatched"], 0);
+    assert!(report.get("hint").is_none());
+}
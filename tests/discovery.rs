@@ -0,0 +1,58 @@
+use piramid::config::{AppConfig, PreloadPolicy};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+
+fn seed(data_dir: &str, names: &[&str]) {
+    for name in names {
+        let mut storage = Collection::open(&format!("{data_dir}/{name}.db")).unwrap();
+        storage.insert(Document::new(vec![1.0, 0.0, 0.0], "a".into())).unwrap();
+        storage.insert(Document::new(vec![0.0, 1.0, 0.0], "b".into())).unwrap();
+        storage.checkpoint().unwrap();
+    }
+}
+
+#[test]
+fn discovery_registers_without_loading() {
+    let dir = TestDir::new("discovery_lazy");
+    let data_dir = dir.data_dir();
+    seed(data_dir, &["docs", "images"]);
+
+    let state = AppState::new(data_dir, AppConfig::default(), 500, None, false, None);
+    assert_eq!(state.discover_collections().unwrap(), 2);
+    assert!(state.collections.is_empty());
+    let docs = state.discovered.get("docs").unwrap();
+    assert_eq!(docs.vector_count, 2);
+    assert_eq!(docs.dimensions, Some(3));
+    drop(docs);
+
+    // Lazy collections open on first touch
+    state.get_or_create_collection("docs").unwrap();
+    assert_eq!(state.collections.get("docs").unwrap().read().count(), 2);
+
+    drop(state);
+}
+
+#[test]
+fn preload_policy_decides_what_opens() {
+    let dir = TestDir::new("discovery_policy");
+    let data_dir = dir.data_dir();
+    seed(data_dir, &["hot", "cold", "archive"]);
+
+    let mut config = AppConfig::default();
+    config.collection_preload.insert("hot".into(), PreloadPolicy::Eager);
+    config.collection_preload.insert("archive".into(), PreloadPolicy::Never);
+    assert_eq!(config.preload_policy("cold"), PreloadPolicy::Lazy);
+
+    let state = AppState::new(data_dir, config, 500, None, false, None);
+    assert_eq!(state.discover_collections().unwrap(), 3);
+    assert!(state.collections.contains_key("hot"));
+    assert!(!state.collections.contains_key("cold"));
+
+    // Never-loaded collections stay listed but refuse to open
+    assert!(state.get_or_create_collection("archive").is_err());
+    assert!(!state.collections.contains_key("archive"));
+    assert!(state.discovered.contains_key("archive"));
+
+    drop(state);
+}
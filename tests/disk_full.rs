@@ -0,0 +1,93 @@
+mod common;
+
+use piramid::config::{AppConfig, CollectionConfig};
+use piramid::error::{PiramidError, StorageError};
+use piramid::server::state::AppState;
+use piramid::testing::{TestDir, fill_disk_after};
+use piramid::Document;
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::Arc;
+
+fn doc(i: usize) -> Document {
+    let angle = i as f32 * 0.3;
+    Document::new(vec![angle.cos(), angle.sin(), 1.0], format!("doc {i}"))
+}
+
+#[test]
+fn disk_full_appends_leave_the_wal_as_it_was() {
+    let dir = TestDir::new("disk_full_wal");
+    let mut storage = dir.open("docs", CollectionConfig::default()).unwrap();
+    storage.insert_batch((0..3).map(doc).collect()).unwrap();
+    let wal_path = dir.path("docs.db.wal.db");
+    let before = fs::read(&wal_path).unwrap();
+    let head = storage.head_seq();
+
+    // A batch that runs out of room part way through is taken back out whole
+    let fault = fill_disk_after(&dir.path("docs.db"), 100);
+    let err = storage.insert_batch((3..8).map(doc).collect()).unwrap_err();
+    assert!(matches!(err, PiramidError::Storage(StorageError::StorageFull(_))), "{err}");
+    assert_eq!(err.error_code().as_str(), "STORAGE_FULL");
+    assert_eq!(err.status_code().as_u16(), 507);
+    assert!(storage.insert(doc(8)).is_err());
+    assert_eq!(fs::read(&wal_path).unwrap(), before);
+    assert_eq!((storage.count(), storage.head_seq()), (3, head));
+
+    // Space freed: the next writes go on from the same sequence number
+    drop(fault);
+    storage.insert_batch((3..8).map(doc).collect()).unwrap();
+    assert_eq!((storage.count(), storage.head_seq()), (8, head + 5));
+    drop(storage);
+    let storage = dir.open("docs", CollectionConfig::default()).unwrap();
+    assert_eq!(storage.count(), 8);
+}
+
+async fn serve(data_dir: &str, disk_min_free_bytes: Option<u64>) -> String {
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, disk_min_free_bytes, true, None));
+    common::serve(state).await
+}
+
+#[tokio::test]
+async fn storage_full_switches_to_read_only_until_resumed() {
+    let dir = TestDir::new("disk_full_api");
+    let data_dir = dir.data_dir();
+    let base = serve(data_dir, None).await;
+    let client = reqwest::Client::new();
+    let insert = |i: usize| client.post(format!("{base}/collections/docs/vectors")).json(&json!({"vector": doc(i).get_vector(), "text": format!("doc {i}")})).send();
+    assert_eq!(insert(0).await.unwrap().status(), 200);
+
+    let fault = fill_disk_after(&format!("{data_dir}/docs.db"), 0);
+    let res = insert(1).await.unwrap();
+    assert_eq!(res.status(), 507);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!((body["error_code"].as_str(), body["retryable"].as_bool()), (Some("STORAGE_FULL"), Some(false)), "{body}");
+
+    // Writes are turned away, also to other collections, while reads go on
+    drop(fault);
+    let res = client.post(format!("{base}/collections/other/vectors")).json(&json!({"vector": [1.0, 0.0, 0.0]})).send().await.unwrap();
+    assert_eq!(res.status(), 503);
+    assert!(res.text().await.unwrap().contains("read-only"));
+    let res = client.post(format!("{base}/collections/docs/search")).json(&json!({"vector": doc(0).get_vector(), "k": 1})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let ready: Value = client.get(format!("{base}/readyz")).send().await.unwrap().json().await.unwrap();
+    assert_eq!((ready["writable"].as_bool(), ready["read_only"]["cause"].as_str()), (Some(false), Some("disk_full")), "{ready}");
+    assert!(ready["read_only"]["message"].as_str().unwrap().contains("/collections/docs/vectors"), "{ready}");
+
+    let resumed: Value = client.post(format!("{base}/writes/resume")).send().await.unwrap().json().await.unwrap();
+    assert_eq!((resumed["resumed"].as_bool(), resumed["read_only"]["cause"].as_str()), (Some(true), Some("disk_full")), "{resumed}");
+    let status: Value = client.get(format!("{base}/writes")).send().await.unwrap().json().await.unwrap();
+    assert_eq!((status["writable"].as_bool(), status.get("read_only")), (Some(true), None), "{status}");
+    assert_eq!(insert(1).await.unwrap().status(), 200);
+    let count: Value = client.get(format!("{base}/collections/docs/count")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(count["count"].as_u64(), Some(2), "{count}");
+
+    // Under disk_min_free_bytes the server goes read-only, and stays so while space is short
+    let low_dir = TestDir::new("disk_full_low_space");
+    let base = serve(low_dir.data_dir(), Some(u64::MAX)).await;
+    let res = client.post(format!("{base}/collections/docs/vectors")).json(&json!({"vector": [1.0, 0.0, 0.0]})).send().await.unwrap();
+    assert_eq!(res.status(), 503);
+    let status: Value = client.get(format!("{base}/writes")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(status["read_only"]["cause"].as_str(), Some("low_disk_space"), "{status}");
+    let res = client.post(format!("{base}/writes/resume")).send().await.unwrap();
+    assert_eq!(res.status(), 409);
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+async fn serve(data_dir: &str, config: AppConfig) -> String {
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    format!("{}/collections/docs", common::serve(state).await)
+}
+
+// POST `text` as a chunked insert body, so the server cannot size it up front; returns the raw response
+async fn send_chunked(base: &str, text: &str) -> String {
+    let (host, path) = base.trim_start_matches("http://").split_once('/').unwrap();
+    let mut stream = TcpStream::connect(host).await.unwrap();
+    let head = format!("POST /{path}/vectors HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n");
+    stream.write_all(head.as_bytes()).await.unwrap();
+    let body = format!("{{\"vector\": [1.0, 0.0], \"text\": \"{text}{text}\"}}");
+    for chunk in body.as_bytes().chunks(64 * 1024) {
+        // The server may answer and close before the whole body is sent
+        if stream.write_all(format!("{:x}\r\n", chunk.len()).as_bytes()).await.is_err()
+            || stream.write_all(chunk).await.is_err()
+            || stream.write_all(b"\r\n").await.is_err()
+        {
+            break;
+        }
+    }
+    let _ = stream.write_all(b"0\r\n\r\n").await;
+    let mut response = Vec::new();
+    let _ = stream.read_to_end(&mut response).await;
+    String::from_utf8_lossy(&response).into_owned()
+}
+
+#[tokio::test]
+async fn oversized_documents_get_413() {
+    let dir = TestDir::new("document_limits");
+    let data_dir = dir.data_dir();
+    let mut config = AppConfig::default();
+    config.limits.max_document_bytes = 1000;
+    let base = serve(data_dir, config).await;
+    let client = reqwest::Client::new();
+
+    let fits = "a".repeat(1000);
+    let over = "a".repeat(1001);
+    let res = client.post(format!("{base}/vectors")).json(&json!({"vector": [1.0, 0.0], "text": fits})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    for (path, body) in [
+        ("vectors", json!({"vector": [1.0, 0.0], "text": over})),
+        ("vectors", json!({"vectors": [[1.0, 0.0], [0.0, 1.0]], "texts": ["ok", over]})),
+        ("upsert", json!({"vector": [1.0, 0.0], "text": over})),
+        ("upsert", json!({"items": [{"vector": [1.0, 0.0], "text": "ok"}, {"vector": [0.0, 1.0], "text": over}]})),
+    ] {
+        let res = client.post(format!("{base}/{path}")).json(&body).send().await.unwrap();
+        assert_eq!(res.status(), 413, "{path}");
+        let error: Value = res.json().await.unwrap();
+        assert!(error["error"].as_str().unwrap().contains("1001 bytes (max 1000"), "{error}");
+    }
+    let count: Value = reqwest::get(format!("{base}/count")).await.unwrap().json().await.unwrap();
+    assert_eq!(count["count"], 1);
+}
+
+#[tokio::test]
+async fn large_bodies_are_spooled_and_capped() {
+    let dir = TestDir::new("document_spool");
+    let data_dir = dir.data_dir();
+    let mut config = AppConfig::default();
+    config.limits.spool_threshold_bytes = 4096;
+    config.limits.max_body_bytes = 1024 * 1024;
+    let base = serve(data_dir, config).await;
+    let client = reqwest::Client::new();
+
+    // Past the spool threshold: decoded from the spool file, which is gone once the request ends
+    let text = "piramid ".repeat(50_000);
+    let res = client.post(format!("{base}/vectors")).json(&json!({"vector": [1.0, 0.0], "text": text})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let id = res.json::<Value>().await.unwrap()["id"].as_str().unwrap().to_string();
+    let doc: Value = reqwest::get(format!("{base}/vectors/{id}")).await.unwrap().json().await.unwrap();
+    assert_eq!(doc["text"].as_str().unwrap().len(), text.len());
+    let spooled = fs::read_dir(format!("{data_dir}/.spool")).unwrap().count();
+    assert_eq!(spooled, 0);
+
+    // Malformed JSON read from a spool file is still a client error
+    let res = client.post(format!("{base}/vectors")).header("content-type", "application/json")
+        .body(format!("{{\"text\": \"{text}\"")).send().await.unwrap();
+    assert!(res.status().is_client_error(), "{}", res.status());
+
+    // Over max_body_bytes the request is refused, with or without a Content-Length
+    let big = "a".repeat(1024 * 1024);
+    let res = client.post(format!("{base}/vectors")).json(&json!({"vector": [1.0, 0.0], "text": big})).send().await.unwrap();
+    assert_eq!(res.status(), 413);
+    let response = send_chunked(&base, &big).await;
+    assert!(response.starts_with("HTTP/1.1 413"), "{response}");
+    assert!(response.contains("limits.max_body_bytes"), "{response}");
+}
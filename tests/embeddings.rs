@@ -1,5 +1,6 @@
 use piramid::embeddings::{EmbeddingError, EmbeddingResponse, EmbeddingResult, EmbeddingProvider, RetryEmbedder, Embedder};
 use piramid::embeddings::retry::RetryConfig;
+use piramid::testing::TestDir;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, Ordering};
 
@@ -85,3 +86,50 @@ fn provider_from_str_roundtrip() {
     assert_eq!(EmbeddingProvider::from_str("local"), Some(EmbeddingProvider::Local));
     assert_eq!(EmbeddingProvider::from_str("unknown"), None);
 }
+
+#[tokio::test]
+async fn insert_without_vectors_embeds_text() {
+    use axum::{extract::{Path, State}, http::HeaderMap};
+    use piramid::server::types::body::{Format, Payload};
+    use piramid::server::{handlers::insert_vector, state::AppState, types::{InsertRequest, InsertResultsResponse}};
+
+    let dir = TestDir::new("embed_on_insert");
+    let data_dir = dir.data_dir();
+    let embedder: Arc<dyn Embedder> = Arc::new(MockEmbedder::new(0));
+    let state = Arc::new(AppState::with_embedder(
+        data_dir,
+        piramid::config::AppConfig::default(),
+        1_000,
+        embedder,
+        None,
+        false,
+        None,
+    ));
+
+    let single: InsertRequest = serde_json::from_value(serde_json::json!({ "text": "hello" })).unwrap();
+    let response = insert_vector(State(state.clone()), Path("docs".into()), HeaderMap::new(), Format::Json, Payload(single)).await.unwrap().into_inner();
+    assert!(matches!(response, InsertResultsResponse::Single(_)));
+    let batch: InsertRequest = serde_json::from_value(serde_json::json!({
+        "texts": ["a", "b"],
+        "metadata_list": [{ "embedding_model": "pinned" }],
+    }))
+    .unwrap();
+    let response = insert_vector(State(state.clone()), Path("docs".into()), HeaderMap::new(), Format::Json, Payload(batch)).await.unwrap().into_inner();
+    assert!(matches!(response, InsertResultsResponse::Multi(ref m) if m.ids.len() == 2));
+
+    let storage = state.collections.get("docs").unwrap();
+    let storage = storage.read();
+    let docs = storage.get_all();
+    assert_eq!(docs.len(), 3);
+    let mut models: Vec<_> = docs
+        .iter()
+        .map(|d| d.metadata.get(piramid::embeddings::EMBEDDING_MODEL_KEY).cloned().unwrap())
+        .collect();
+    models.sort_by_key(|m| format!("{m:?}"));
+    assert_eq!(models, vec!["pinned".into(), "test".into(), "test".into()]);
+    assert!(docs.iter().all(|d| d.get_vector().len() == 3));
+    let recorded = storage.metadata().embedding_model.clone().unwrap();
+    assert_eq!((recorded.model.as_str(), recorded.dimensions), ("mock-model", 3));
+    assert!(storage.check_embedding_model("other-model", None).is_err());
+
+}
@@ -0,0 +1,129 @@
+use piramid::embeddings::retry::RetryConfig;
+use piramid::embeddings::{Embedder, EmbeddingError, EmbeddingResponse, EmbeddingResult, LimitedEmbedder, RetryEmbedder};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Batch-capable provider that records chunk sizes and the peak number of concurrent requests.
+// A chunk holding "bad" fails for good; one holding a `flaky` text times out once.
+#[derive(Default)]
+struct BatchMock {
+    active: AtomicUsize,
+    peak: AtomicUsize,
+    chunks: Mutex<Vec<usize>>,
+    flaky: Mutex<HashSet<String>>,
+}
+
+#[async_trait::async_trait]
+impl Embedder for BatchMock {
+    async fn embed(&self, text: &str) -> EmbeddingResult<EmbeddingResponse> {
+        Ok(self.embed_batch(&[text.to_string()]).await?.remove(0))
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> EmbeddingResult<Vec<EmbeddingResponse>> {
+        let now = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(now, Ordering::SeqCst);
+        self.chunks.lock().unwrap().push(texts.len());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        self.active.fetch_sub(1, Ordering::SeqCst);
+        if texts.iter().any(|t| t == "bad") {
+            return Err(EmbeddingError::AuthenticationFailed("bad key".into()));
+        }
+        let mut flaky = self.flaky.lock().unwrap();
+        if texts.iter().any(|t| flaky.contains(t)) {
+            flaky.retain(|t| !texts.contains(t));
+            return Err(EmbeddingError::Timeout("slow".into()));
+        }
+        Ok(texts
+            .iter()
+            .map(|t| EmbeddingResponse {
+                embedding: vec![t.parse::<f32>().unwrap()],
+                tokens: Some(1),
+                model: "mock-model".into(),
+            })
+            .collect())
+    }
+
+    fn max_batch_size(&self) -> usize {
+        10
+    }
+
+    fn provider_name(&self) -> &str {
+        "mock"
+    }
+
+    fn model_name(&self) -> &str {
+        "mock-model"
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+fn texts(n: usize) -> Vec<String> {
+    (0..n).map(|i| i.to_string()).collect()
+}
+
+#[tokio::test]
+async fn batches_are_chunked_and_kept_in_order() {
+    let mock = Arc::new(BatchMock::default());
+    // The configured size is clamped to what the provider accepts
+    assert_eq!(LimitedEmbedder::new(mock.clone(), 4, Some(500)).chunk_size(), 10);
+
+    let limited = LimitedEmbedder::new(mock.clone(), 4, Some(4));
+    let responses = limited.embed_batch(&texts(10)).await.unwrap();
+    let values: Vec<f32> = responses.iter().map(|r| r.embedding[0]).collect();
+    assert_eq!(values, (0..10).map(|i| i as f32).collect::<Vec<_>>());
+
+    let mut chunks = mock.chunks.lock().unwrap().clone();
+    chunks.sort_unstable();
+    assert_eq!(chunks, vec![2, 4, 4]);
+}
+
+#[tokio::test]
+async fn concurrency_is_bounded_across_callers() {
+    let mock = Arc::new(BatchMock::default());
+    let limited = Arc::new(LimitedEmbedder::new(mock.clone(), 2, Some(1)));
+
+    let mut handles = Vec::new();
+    for _ in 0..3 {
+        let limited = Arc::clone(&limited);
+        handles.push(tokio::spawn(async move { limited.embed_batch(&texts(4)).await }));
+    }
+    for handle in handles {
+        assert_eq!(handle.await.unwrap().unwrap().len(), 4);
+    }
+
+    assert_eq!(mock.chunks.lock().unwrap().len(), 12);
+    assert_eq!(mock.peak.load(Ordering::SeqCst), 2);
+    assert_eq!(limited.in_flight(), 0);
+}
+
+#[tokio::test]
+async fn partial_batches_fail_only_the_failed_chunks() {
+    let mock = Arc::new(BatchMock::default());
+    mock.flaky.lock().unwrap().insert("5".into());
+    let limited: Arc<dyn Embedder> = Arc::new(LimitedEmbedder::new(mock.clone(), 4, Some(2)));
+    let mut batch = texts(8);
+    batch[2] = "bad".into();
+
+    // Without retries: chunk [bad, 3] fails for good and [4, 5] timed out; the rest keep their place
+    let outcomes = limited.embed_batch_partial(&batch).await;
+    let failed: Vec<usize> = (0..8).filter(|&i| outcomes[i].is_err()).collect();
+    assert_eq!(failed, [2, 3, 4, 5]);
+    assert_eq!(outcomes[7].as_ref().unwrap().embedding, [7.0]);
+    assert!(limited.embed_batch(&batch).await.is_err());
+
+    // With retries only the timed-out texts are sent again
+    mock.flaky.lock().unwrap().insert("5".into());
+    mock.chunks.lock().unwrap().clear();
+    let options = RetryConfig { max_retries: 2, initial_delay_ms: 1, max_delay_ms: 5 };
+    let retrying = RetryEmbedder::with_options(limited, options);
+    let outcomes = retrying.embed_batch_partial(&batch).await;
+    let failed: Vec<usize> = (0..8).filter(|&i| outcomes[i].is_err()).collect();
+    assert_eq!(failed, [2, 3]);
+    assert_eq!(outcomes[5].as_ref().unwrap().embedding, [5.0]);
+    assert_eq!(mock.chunks.lock().unwrap().len(), 5);
+}
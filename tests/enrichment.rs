@@ -0,0 +1,119 @@
+mod common;
+
+use piramid::config::{AppConfig, EnrichmentConfig, EnrichmentStageConfig, LengthUnit, MetadataIndexConfig};
+use piramid::error::ServerError;
+use piramid::metadata::{Metadata, MetadataValue};
+use piramid::server::state::AppState;
+use piramid::storage::{detect_language, url_host, EnrichmentStage};
+use piramid::testing::TestDir;
+use piramid::{metadata, CollectionConfig, Document, Filter};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Library stage: tags documents mentioning "rust", rejects empty ones
+struct Topic;
+
+impl EnrichmentStage for Topic {
+    fn name(&self) -> &str {
+        "topic"
+    }
+
+    fn enrich(&self, text: &str, metadata: &mut Metadata) -> piramid::Result<()> {
+        if text.is_empty() {
+            return Err(ServerError::InvalidRequest("empty document".into()).into());
+        }
+        // Sees what the configured stages wrote
+        let long = matches!(metadata.get("words"), Some(MetadataValue::Integer(n)) if *n > 3);
+        metadata.insert("rust".into(), (text.contains("rust") && long).into());
+        Ok(())
+    }
+}
+
+fn stages() -> EnrichmentConfig {
+    EnrichmentConfig::with_stages(vec![
+        EnrichmentStageConfig::TextLength { field: "words".into(), unit: LengthUnit::Words },
+        EnrichmentStageConfig::parse("language").unwrap(),
+        EnrichmentStageConfig::parse("url_host").unwrap(),
+    ])
+}
+
+#[test]
+fn inserted_documents_get_enriched_fields_that_survive_a_reopen() {
+    let dir = TestDir::new("enrichment_library");
+    let config = CollectionConfig::default()
+        .with_enrichment(stages())
+        .with_metadata_index(MetadataIndexConfig::with_fields(["language", "url_host"]));
+    let (english, german) = {
+        let mut storage = dir.open("docs", config.clone()).unwrap();
+        storage.add_enrichment_stage(Arc::new(Topic));
+        let english = storage.insert(Document::with_metadata(
+            vec![1.0, 0.0],
+            "the rust book is the guide to the language".into(),
+            metadata([("url", "HTTPS://user@Doc.Rust-Lang.org:443/book/?x#y".into())]),
+        )).unwrap();
+        let german = storage.upsert(Document::with_metadata(
+            vec![0.0, 1.0],
+            "Das ist nicht die Dokumentation und auch kein Buch".into(),
+            metadata([("url", MetadataValue::Array(vec!["http://a.de/x".into(), "http://A.de/y".into(), "mailto:x".into()]))]),
+        )).unwrap();
+        assert!(storage.insert(Document::new(vec![1.0, 1.0], String::new())).is_err());
+
+        let doc = storage.get(&english).unwrap();
+        assert_eq!(doc.metadata.get("words"), Some(&MetadataValue::Integer(9)));
+        assert_eq!(doc.metadata.get("language"), Some(&"en".into()));
+        assert_eq!(doc.metadata.get("url_host"), Some(&"doc.rust-lang.org".into()));
+        assert_eq!(doc.metadata.get("rust"), Some(&true.into()));
+        let doc = storage.get(&german).unwrap();
+        assert_eq!(doc.metadata.get("url_host"), Some(&MetadataValue::Array(vec!["a.de".into()])));
+        assert_eq!(storage.count(), 2);
+        (english, german)
+    };
+
+    // Replayed from the WAL without the library stage, the fields are still there and filterable
+    let storage = dir.open("docs", config).unwrap();
+    let ids = |filter: Filter| storage.filter_matches(&filter, 10).unwrap().ids.unwrap();
+    assert_eq!(ids(Filter::new().eq("language", "de")), vec![german]);
+    assert_eq!(ids(Filter::new().any_in("url_host", vec!["doc.rust-lang.org".into()])), vec![english]);
+    assert_eq!(storage.get(&german).unwrap().metadata.get("rust"), Some(&false.into()));
+
+    assert_eq!(url_host("https://[::1]:8080/a"), Some("::1".to_string()));
+    assert_eq!(url_host("example.com/path"), None);
+    assert_eq!(detect_language("Le chat est sur la table avec nous"), Some("fr"));
+    assert_eq!(detect_language("Привет, як справи? Це їжа"), Some("uk"));
+    assert_eq!(detect_language("東京は日本の首都です"), Some("ja"));
+    assert_eq!(detect_language("xyz 42"), None);
+    assert!(EnrichmentConfig::with_stages(vec![EnrichmentStageConfig::Language { field: "_version".into() }]).validate().is_err());
+}
+
+#[tokio::test]
+async fn server_enriches_documents_of_configured_collections() {
+    let dir = TestDir::new("enrichment_http");
+    let data_dir = dir.data_dir();
+    let config = AppConfig { collection_enrichment: HashMap::from([("docs".to_string(), stages())]), ..Default::default() };
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    let api = common::serve(state).await;
+    let client = reqwest::Client::new();
+
+    for collection in ["docs", "plain"] {
+        let body = json!({
+            "vectors": [[1.0, 0.0], [0.0, 1.0]],
+            "texts": ["the cat and the dog", "el gato y el perro de la casa"],
+            "metadata_list": [{"url": "https://www.example.com/a"}, {"url": "https://example.org"}],
+        });
+        let res = client.post(format!("{api}/collections/{collection}/vectors")).json(&body).send().await.unwrap();
+        assert_eq!(res.status(), 200);
+    }
+    let search = |collection: &str, exclude: Value| {
+        client.post(format!("{api}/collections/{collection}/search")).json(&json!({"vector": [1.0, 1.0], "k": 5, "exclude_filter": exclude})).send()
+    };
+    let hits: Value = search("docs", json!({"language": "en"})).await.unwrap().json().await.unwrap();
+    let hits = hits["results"].as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["metadata"]["url_host"], "example.org");
+    assert_eq!(hits[0]["metadata"]["words"], 8);
+
+    // Collections without stages store what was sent
+    let hits: Value = search("plain", json!({})).await.unwrap().json().await.unwrap();
+    assert!(hits["results"].as_array().unwrap().iter().all(|h| h["metadata"].get("language").is_none()));
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use piramid::config::{CollectionConfig, TwoStageConfig};
+use piramid::storage::collection::{compact, create_snapshot};
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document, Metric, search::SearchParams};
+use std::path::Path;
+
+fn vector(i: usize) -> Vec<f32> {
+    common::wave(i, 128, 0.37)
+}
+
+#[test]
+fn ephemeral_collection_keeps_the_api_and_writes_nothing() {
+    // The collection lives in a subdirectory that must never be created
+    let dir = TestDir::new("ephemeral");
+    let missing = dir.root().join("never_created");
+    let path = dir.path("never_created/docs.db");
+    let mut storage = Collection::open_with_options(&path, CollectionConfig::default().ephemeral().into()).unwrap();
+    assert_eq!(storage.metadata().name, "docs");
+    assert!(!storage.config().wal.enabled);
+
+    // Well past the initial 1MB map, so the anonymous memory has to grow
+    let docs: Vec<Document> = (0..3000).map(|i| Document::new(vector(i), format!("doc {i} {}", "x".repeat(200)))).collect();
+    let ids = storage.insert_batch(docs).unwrap();
+    let extra = storage.insert(Document::new(vector(3000), "extra".into()).with_external_id("ext")).unwrap();
+    assert_eq!(storage.count(), 3001);
+    assert_eq!(storage.get(&ids[2999]).unwrap().text, format!("doc 2999 {}", "x".repeat(200)));
+
+    let hits = storage.search(&vector(42), 3, Metric::Cosine, SearchParams::default());
+    assert_eq!(hits[0].id, ids[42]);
+    assert!(storage.update_metadata(&ids[1001], metadata([("tag", "a".into())])).unwrap());
+    assert!(storage.update_vector(&ids[1002], vector(7)).unwrap());
+    storage.upsert(Document::new(vector(5), "replaced".into()).with_external_id("ext")).unwrap();
+    assert_eq!(storage.get(&extra).unwrap().text, "replaced");
+    assert_eq!(storage.delete_batch(&ids[..1000]).unwrap(), 1000);
+
+    storage.checkpoint().unwrap();
+    storage.flush().unwrap();
+    let stats = compact(&mut storage).unwrap();
+    assert_eq!((stats.original_entries, stats.compacted_entries), (2001, 2001));
+    storage.rebuild_index().unwrap();
+    let hits = storage.search(&vector(1500), 1, Metric::Cosine, SearchParams::default());
+    assert_eq!(hits[0].id, ids[1500]);
+    assert!(storage.get(&ids[0]).is_none());
+    assert_eq!(storage.get(&ids[1001]).unwrap().metadata.get("tag").and_then(|v| v.as_string()), Some("a"));
+    let hits = storage.search(&vector(7), 2, Metric::Cosine, SearchParams::default());
+    assert!(hits.iter().any(|h| h.id == ids[1002]));
+
+    drop(storage);
+    assert!(!missing.exists());
+
+    // Nothing survives: the same name opens empty
+    let storage = Collection::open_with_options(&path, CollectionConfig::default().ephemeral().into()).unwrap();
+    assert_eq!(storage.count(), 0);
+    assert!(!missing.exists());
+}
+
+#[test]
+fn file_backed_features_are_refused() {
+    let two_stage = CollectionConfig::default()
+        .with_two_stage(TwoStageConfig { enabled: true, ..Default::default() })
+        .ephemeral();
+    assert!(Collection::open_with_options("two_stage.db", two_stage.into()).is_err());
+
+    let mut storage = Collection::open_with_options("snap.db", CollectionConfig::default().ephemeral().into()).unwrap();
+    storage.insert(Document::new(vec![1.0, 0.0], "a".into())).unwrap();
+    let dir = TestDir::new("ephemeral_snapshot");
+    let target = dir.root().join("snapshots");
+    assert!(create_snapshot(&mut storage, &target, "s1").is_err());
+    assert!(!target.exists());
+    assert!(!Path::new("snap.db").exists());
+
+    // Configs written before the field existed load as file-backed
+    let mut value = serde_json::to_value(CollectionConfig::default()).unwrap();
+    value.as_object_mut().unwrap().remove("ephemeral");
+    let config: CollectionConfig = serde_json::from_value(value).unwrap();
+    assert!(!config.ephemeral);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::error::{EmbeddingError, ErrorCode, PiramidError, ServerError, StorageError};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+#[test]
+fn every_error_maps_to_a_code_and_a_retry_hint() {
+    let cases: Vec<(PiramidError, &str, bool)> = vec![
+        (ServerError::CollectionNotFound.into(), "COLLECTION_NOT_FOUND", false),
+        (ServerError::DimensionMismatch("expected 3, got 2".into()).into(), "DIMENSION_MISMATCH", false),
+        (ServerError::ServiceUnavailable("queue full".into()).into(), "SERVICE_UNAVAILABLE", true),
+        (ServerError::BudgetExceeded("monthly tokens".into()).into(), "BUDGET_EXCEEDED", false),
+        (ServerError::WriteThrottled { message: "WAL backlog".into(), retry_after_secs: 2 }.into(), "WRITE_THROTTLED", true),
+        (StorageError::InvalidDimension { expected: 3, actual: 2 }.into(), "DIMENSION_MISMATCH", false),
+        (StorageError::WalFailed("disk full".into()).into(), "WAL_IO", false),
+        (StorageError::LockFailed("busy".into()).into(), "LOCK_FAILED", true),
+        (EmbeddingError::RateLimitExceeded.into(), "EMBEDDING_RATE_LIMITED", true),
+        (EmbeddingError::InvalidModel("nope".into()).into(), "EMBEDDING_CONFIG", false),
+        (std::io::Error::other("boom").into(), "IO_ERROR", false),
+        (PiramidError::other("boom"), "INTERNAL", false),
+    ];
+    for (error, code, retryable) in cases {
+        assert_eq!((error.error_code().as_str(), error.error_code().retryable()), (code, retryable), "{error}");
+    }
+    assert_eq!(serde_json::to_value(ErrorCode::WalIo).unwrap(), json!("WAL_IO"));
+    // Message text is unchanged by the more specific variants
+    assert_eq!(ServerError::CollectionNotFound.to_string(), "Resource not found: Collection not found");
+}
+
+#[tokio::test]
+async fn error_responses_carry_code_and_retryable() {
+    let dir = TestDir::new("error_codes");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let res = client.post(format!("{base}/missing/rename")).json(&json!({"name": "other"})).send().await.unwrap();
+    assert_eq!(res.status(), 404);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!((body["code"].as_u64(), body["error_code"].as_str(), body["retryable"].as_bool()), (Some(404), Some("COLLECTION_NOT_FOUND"), Some(false)));
+
+    let res = client.post(format!("{base}/docs/vectors")).json(&json!({"vector": [1.0, 0.0, 0.0], "text": "a"})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res = client.post(format!("{base}/docs/search")).json(&json!({"vector": [1.0, 0.0], "k": 1})).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["error_code"], "DIMENSION_MISMATCH", "{body}");
+
+    let res = client.get(format!("{base}/docs/vectors/{}", uuid::Uuid::new_v4())).send().await.unwrap();
+    assert_eq!(res.json::<Value>().await.unwrap()["error_code"], "VECTOR_NOT_FOUND");
+
+    // Items of a partial batch fail with the code their own request would have
+    let batch = json!({"vectors": [[0.0, 1.0, 0.0], [1.0, 2.0]], "texts": ["b", "c"], "allow_partial": true});
+    let res: Value = client.post(format!("{base}/docs/vectors")).json(&batch).send().await.unwrap().json().await.unwrap();
+    assert!(res["results"][0].get("error_code").is_none(), "{res}");
+    assert_eq!((res["results"][1]["error_code"].as_str(), res["results"][1]["retryable"].as_bool()), (Some("DIMENSION_MISMATCH"), Some(false)));
+}
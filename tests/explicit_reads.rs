@@ -0,0 +1,85 @@
+mod common;
+
+use piramid::testing::{freeze_clock, TestDir};
+use piramid::{metadata, MetadataValue};
+use piramid::{Collection, CollectionConfig, Document, Filter, Metric, SearchParams};
+use uuid::Uuid;
+
+fn config(io_uring: bool) -> CollectionConfig {
+    let mut config = CollectionConfig::default();
+    config.memory.io_uring = io_uring;
+    config
+}
+
+fn open(dir: &str, io_uring: bool) -> Collection {
+    Collection::open_with_options(&format!("{dir}/docs.db"), config(io_uring).into()).unwrap()
+}
+
+fn docs() -> Vec<Document> {
+    (0..200)
+        .map(|i| {
+            let vector = common::wave(i, 16, 0.61);
+            Document::with_metadata(vector, format!("doc {i} ").repeat(i % 7 + 1), metadata([("group", MetadataValue::Integer((i % 4) as i64))]))
+        })
+        .collect()
+}
+
+#[test]
+fn searches_read_the_same_documents_as_the_mmap() {
+    // Hits are compared with their metadata, `_created_at` and `_updated_at` included
+    let _clock = freeze_clock(1_700_000_000);
+    let (mapped_dir, explicit_dir) = (TestDir::new("explicit_reads_mmap"), TestDir::new("explicit_reads_uring"));
+    let mut mapped = open(mapped_dir.data_dir(), false);
+    let mut explicit = open(explicit_dir.data_dir(), true);
+    mapped.insert_batch(docs()).unwrap();
+    explicit.insert_batch(docs()).unwrap();
+    assert!(!piramid::storage::io_uring_active() || cfg!(all(feature = "io-uring", target_os = "linux")));
+
+    let texts = |hits: Vec<piramid::Hit>| hits.into_iter().map(|hit| (hit.text, hit.metadata)).collect::<Vec<_>>();
+    let filter = Filter::new().eq("group", 2i64);
+    for query in docs().iter().step_by(25).map(|doc| doc.get_vector()) {
+        let params = SearchParams::default();
+        assert_eq!(texts(explicit.search(&query, 10, Metric::Cosine, params)), texts(mapped.search(&query, 10, Metric::Cosine, params)));
+        let params = SearchParams { filter: Some(&filter), ..Default::default() };
+        let hits = explicit.search(&query, 5, Metric::Cosine, params);
+        assert_eq!(hits.len(), 5);
+        assert_eq!(texts(hits), texts(mapped.search(&query, 5, Metric::Cosine, params)));
+    }
+
+    // New versions and reopening read back from the file as well
+    let id = explicit.get_all()[0].id;
+    explicit.update_vector(&id, vec![1.0; 16]).unwrap();
+    explicit.checkpoint().unwrap();
+    drop(explicit);
+    let explicit = open(explicit_dir.data_dir(), true);
+    let hits = explicit.search(&[1.0; 16], 1, Metric::Cosine, SearchParams::default());
+    assert_eq!((hits[0].id, hits[0].vector.clone()), (id, vec![1.0; 16]));
+    assert_eq!(explicit.count(), 200);
+}
+
+#[test]
+fn batches_keep_order_across_missing_ids_and_rounds() {
+    let dir = TestDir::new("explicit_reads_batch");
+    let mut storage = open(dir.data_dir(), true);
+    let ids = storage.insert_batch(docs()).unwrap();
+
+    // More ids than one ring round, with unknown ids and repeats mixed in
+    let mut wanted: Vec<Uuid> = ids.iter().rev().copied().collect();
+    wanted.insert(3, Uuid::new_v4());
+    wanted.push(ids[0]);
+    wanted.push(Uuid::new_v4());
+    let read = storage.get_many(&wanted);
+    assert_eq!(read.len(), wanted.len());
+    for (id, doc) in wanted.iter().zip(&read) {
+        match ids.contains(id) {
+            true => assert_eq!(doc.as_ref().map(|doc| (doc.id, doc.text.clone())), storage.get(id).map(|doc| (*id, doc.text))),
+            false => assert!(doc.is_none()),
+        }
+    }
+    assert!(read[3].is_none());
+    assert!(storage.get_many(&[]).is_empty());
+
+    storage.delete(&ids[5]).unwrap();
+    assert!(storage.get_many(&ids[4..7])[1].is_none());
+    assert_eq!(storage.get_many(&ids[6..7])[0].as_ref().unwrap().text, docs()[6].text);
+}
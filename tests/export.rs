@@ -0,0 +1,111 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::storage::collection::{ExportFormat, EXPORT_BATCH_ROWS};
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document, Filter};
+use serde_json::json;
+use std::sync::Arc;
+
+fn vector(i: usize) -> Vec<f32> {
+    common::counting_vector(i, 4)
+}
+
+fn seed(path: &str, n: usize) -> Vec<uuid::Uuid> {
+    let mut storage = Collection::open(path).unwrap();
+    let docs = (0..n)
+        .map(|i| {
+            let tier = if i % 3 == 0 { "gold" } else { "silver" };
+            Document::with_metadata(vector(i), format!("doc {i}"), metadata([("tier", tier.into()), ("n", (i as i64).into())]))
+        })
+        .collect();
+    let ids = storage.insert_batch(docs).unwrap();
+    storage.checkpoint().unwrap();
+    ids
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+fn floats(v: &[f32]) -> Vec<u8> {
+    v.iter().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+#[test]
+fn export_writes_every_document_in_both_formats() {
+    let dir = TestDir::new("export_files");
+    let path = dir.path("docs.db");
+    let n = EXPORT_BATCH_ROWS * 2 + 10;
+    let ids = seed(&path, n);
+    let storage = Collection::open(&path).unwrap();
+
+    let mut parquet = Vec::new();
+    let report = storage.export(&mut parquet, ExportFormat::Parquet, None).unwrap();
+    assert_eq!((report.rows, report.dimensions), (n, 4));
+    assert_eq!((&parquet[..4], &parquet[parquet.len() - 4..]), (&b"PAR1"[..], &b"PAR1"[..]));
+    assert!(contains(&parquet, b"ARROW:schema"));
+
+    let mut arrow = Vec::new();
+    let report = storage.export(&mut arrow, ExportFormat::Arrow, None).unwrap();
+    assert_eq!(report.rows, n);
+    assert_eq!((&arrow[..8], &arrow[arrow.len() - 6..]), (&b"ARROW1\0\0"[..], &b"ARROW1"[..]));
+
+    // Nothing is compressed: ids, stored vectors and metadata JSON are there as written
+    for i in [0, EXPORT_BATCH_ROWS + 7, n - 1] {
+        let doc = storage.get(&ids[i]).unwrap();
+        let stored = doc.get_vector();
+        // Keys in order, the engine's timestamps first
+        let metadata = json!({
+            "_created_at": doc.created_at(),
+            "_updated_at": doc.updated_at(),
+            "n": i,
+            "tier": if i % 3 == 0 { "gold" } else { "silver" },
+        });
+        for file in [&parquet, &arrow] {
+            assert!(contains(file, ids[i].to_string().as_bytes()));
+            assert!(contains(file, &floats(&stored)));
+            assert!(contains(file, metadata.to_string().as_bytes()));
+        }
+    }
+
+    let gold = Filter::new().eq("tier", "gold");
+    let mut filtered = Vec::new();
+    let report = storage.export(&mut filtered, ExportFormat::Arrow, Some(&gold)).unwrap();
+    assert_eq!(report.rows, n.div_ceil(3));
+    assert!(contains(&filtered, ids[3].to_string().as_bytes()));
+    assert!(!contains(&filtered, ids[4].to_string().as_bytes()));
+}
+
+#[tokio::test]
+async fn export_endpoint_returns_the_file() {
+    let dir = TestDir::new("export_http");
+    let data_dir = dir.data_dir();
+    let ids = seed(&format!("{data_dir}/docs.db"), 30);
+
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    state.discover_collections().unwrap();
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let res = client.post(format!("{base}/export")).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/vnd.apache.parquet");
+    assert_eq!(res.headers()["content-disposition"], "attachment; filename=\"docs.parquet\"");
+    assert_eq!(res.headers()["x-export-rows"], "30");
+    assert!(res.bytes().await.unwrap().starts_with(b"PAR1"));
+
+    let res = client.post(format!("{base}/export"))
+        .json(&json!({"format": "arrow", "filter": {"tier": "gold"}}))
+        .send().await.unwrap();
+    assert_eq!(res.headers()["content-type"], "application/vnd.apache.arrow.file");
+    assert_eq!(res.headers()["x-export-rows"], "10");
+    let body = res.bytes().await.unwrap();
+    assert!(body.starts_with(b"ARROW1"));
+    assert!(contains(&body, ids[0].to_string().as_bytes()));
+    assert!(!contains(&body, ids[1].to_string().as_bytes()));
+
+    let res = client.post(format!("{base}/export")).json(&json!({"format": "csv"})).send().await.unwrap();
+    assert_eq!(res.status(), 422);
+}
@@ -0,0 +1,108 @@
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+
+#[test]
+fn client_ids_resolve_and_survive_reopen() {
+    let dir = TestDir::new("external_ids");
+    let path = dir.path("docs.db");
+
+    let id;
+    {
+        let mut storage = Collection::open(&path).unwrap();
+        id = storage
+            .insert(Document::new(vec![1.0, 0.0], "first".into()).with_external_id("doc-1"))
+            .unwrap();
+        let batch = storage
+            .insert_batch(vec![
+                Document::new(vec![0.0, 1.0], "second".into()).with_external_id("doc-2"),
+                Document::new(vec![1.0, 1.0], "third".into()),
+            ])
+            .unwrap();
+
+        assert_eq!(storage.resolve_id("doc-1"), Some(id));
+        assert_eq!(storage.resolve_id("doc-2"), Some(batch[0]));
+        assert_eq!(storage.resolve_id(&batch[1].to_string()), Some(batch[1]));
+        assert_eq!(storage.resolve_id("missing"), None);
+        assert_eq!(storage.get(&id).unwrap().external_id(), Some("doc-1"));
+    }
+
+    let storage = Collection::open(&path).unwrap();
+    assert_eq!(storage.resolve_id("doc-1"), Some(id));
+    assert_eq!(storage.external_ids_view().len(), 2);
+
+    drop(storage);
+}
+
+#[test]
+fn duplicate_client_ids_are_rejected() {
+    let dir = TestDir::new("external_ids_dup");
+    let path = dir.path("docs.db");
+
+    let mut storage = Collection::open(&path).unwrap();
+    storage
+        .insert(Document::new(vec![1.0, 0.0], "first".into()).with_external_id("doc-1"))
+        .unwrap();
+
+    assert!(storage
+        .insert(Document::new(vec![0.0, 1.0], "again".into()).with_external_id("doc-1"))
+        .is_err());
+    assert!(storage
+        .insert_batch(vec![
+            Document::new(vec![0.0, 1.0], "a".into()).with_external_id("doc-2"),
+            Document::new(vec![1.0, 1.0], "b".into()).with_external_id("doc-2"),
+        ])
+        .is_err());
+    assert_eq!(storage.count(), 1);
+
+    drop(storage);
+}
+
+#[test]
+fn upsert_and_delete_by_client_id() {
+    let dir = TestDir::new("external_ids_upsert");
+    let path = dir.path("docs.db");
+
+    let mut storage = Collection::open(&path).unwrap();
+    let id = storage
+        .upsert(Document::new(vec![1.0, 0.0], "v1".into()).with_external_id("doc-1"))
+        .unwrap();
+    // A fresh Uuid with a known client id updates the existing document
+    let again = storage
+        .upsert(Document::new(vec![0.0, 1.0], "v2".into()).with_external_id("doc-1"))
+        .unwrap();
+    assert_eq!(again, id);
+    assert_eq!(storage.count(), 1);
+    assert_eq!(storage.get(&id).unwrap().text, "v2");
+
+    let resolved = storage.resolve_id("doc-1").unwrap();
+    assert!(storage.delete(&resolved).unwrap());
+    assert_eq!(storage.resolve_id("doc-1"), None);
+
+    // The id is free again after the delete
+    storage
+        .insert(Document::new(vec![1.0, 1.0], "v3".into()).with_external_id("doc-1"))
+        .unwrap();
+
+    drop(storage);
+}
+
+#[test]
+fn plain_external_id_metadata_is_not_a_client_id() {
+    let dir = piramid::testing::TestDir::new("external_ids_plain_key");
+    let mut storage = dir.open("docs", Default::default()).unwrap();
+    // Two documents carrying the same value under a key of the user's own
+    let meta = || piramid::metadata([("external_id", "crm-7".into())]);
+    storage.insert(Document::with_metadata(vec![1.0, 0.0], "a".into(), meta())).unwrap();
+    storage.insert(Document::with_metadata(vec![0.0, 1.0], "b".into(), meta())).unwrap();
+    assert_eq!(storage.count(), 2);
+    assert_eq!(storage.resolve_id("crm-7"), None);
+    assert!(storage.external_ids_view().is_empty());
+
+    // Reserved keys cannot come from clients
+    let sent = |key: &str| std::collections::HashMap::from([(key.to_string(), serde_json::json!("x"))]);
+    assert!(piramid::server::client_metadata(sent("external_id")).is_ok());
+    for key in [piramid::storage::EXTERNAL_ID_KEY, "_version", "_created_at", "_anything"] {
+        let err = piramid::server::client_metadata(sent(key)).unwrap_err();
+        assert!(err.to_string().contains("reserved"), "{key}: {err}");
+    }
+}
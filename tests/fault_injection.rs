@@ -0,0 +1,88 @@
+#![cfg(any(debug_assertions, feature = "fault-injection"))]
+
+mod common;
+
+use piramid::config::{AppConfig, FaultInjectionConfig, FaultRule};
+use piramid::server::faults::{route_matches, FAULT_HEADER};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+async fn serve(dir: &TestDir, rules: Vec<FaultRule>) -> String {
+    let config = AppConfig { fault_injection: FaultInjectionConfig { enabled: true, rules }, ..Default::default() };
+    config.validate().unwrap();
+    let state = Arc::new(AppState::new(dir.data_dir(), config, 500, None, false, None));
+    format!("{}/collections", common::serve(state).await)
+}
+
+#[tokio::test]
+async fn matching_routes_get_the_configured_faults() {
+    let rules = vec![
+        FaultRule { route: "/api/collections/*/search".into(), methods: vec!["post".into()], error_status: 429, error_probability: 1.0, ..Default::default() },
+        FaultRule { route: "/api/collections/docs/**".into(), latency_ms: 150, latency_probability: 1.0, ..Default::default() },
+    ];
+    let dir = TestDir::new("fault_injection_routes");
+    let base = serve(&dir, rules).await;
+    let client = reqwest::Client::new();
+
+    let started = Instant::now();
+    let res = client.post(format!("{base}/docs/vectors")).json(&json!({"vector": [1.0, 0.0], "text": "a"})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()[FAULT_HEADER], "latency");
+    assert!(started.elapsed() >= Duration::from_millis(150));
+
+    // The /api/v1 spelling matches too, and the error looks like a real rate limit
+    let res = client.post(format!("{}/other/search", base.replace("/api", "/api/v1"))).json(&json!({"vector": [1.0, 0.0], "k": 1})).send().await.unwrap();
+    assert_eq!(res.status(), 429);
+    assert_eq!((res.headers()[FAULT_HEADER].to_str().unwrap(), res.headers()["retry-after"].to_str().unwrap()), ("error", "1"));
+    let body: Value = res.json().await.unwrap();
+    assert_eq!((body["error_code"].as_str(), body["retryable"].as_bool()), (Some("RATE_LIMITED"), Some(true)), "{body}");
+
+    let res = client.post(format!("{base}/docs/search")).json(&json!({"vector": [1.0, 0.0], "k": 1})).send().await.unwrap();
+    assert_eq!((res.status().as_u16(), res.headers()[FAULT_HEADER].to_str().unwrap()), (429, "latency,error"));
+
+    // Other routes and methods are left alone
+    let res = client.get(format!("{base}/other")).send().await.unwrap();
+    assert!(res.headers().get(FAULT_HEADER).is_none());
+    let res = client.get(format!("{base}/docs/search")).send().await.unwrap();
+    assert_ne!(res.status(), 429);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn lock_rules_hold_the_collection_and_bad_rules_are_rejected() {
+    let rules = vec![FaultRule { route: "/api/collections/*/vectors".into(), methods: vec!["GET".into()], lock_ms: 300, lock_probability: 1.0, ..Default::default() }];
+    let dir = TestDir::new("fault_injection_lock");
+    let base = serve(&dir, rules).await;
+    let client = reqwest::Client::new();
+    let res = client.post(format!("{base}/docs/vectors")).json(&json!({"vector": [1.0, 0.0], "text": "a"})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    // A write arriving while the injected lock is held waits for it
+    let locked = tokio::spawn({
+        let (client, url) = (client.clone(), format!("{base}/docs/vectors"));
+        async move { client.get(url).send().await.unwrap() }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let started = Instant::now();
+    let res = client.post(format!("{base}/docs/vectors")).json(&json!({"vector": [0.0, 1.0], "text": "b"})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert!(started.elapsed() >= Duration::from_millis(150), "{:?}", started.elapsed());
+    let res = locked.await.unwrap();
+    assert_eq!((res.status().as_u16(), res.headers()[FAULT_HEADER].to_str().unwrap()), (200, "lock"));
+
+    // No collection, nothing to lock
+    let res = client.get(format!("{base}/missing/vectors")).send().await.unwrap();
+    assert!(res.headers().get(FAULT_HEADER).is_none());
+
+    assert!(route_matches("/collections/*/search", "/collections/docs/search"));
+    assert!(route_matches("/collections/**", "/collections"));
+    assert!(!route_matches("/collections/*", "/collections/docs/search"));
+    let mut config = FaultInjectionConfig { enabled: true, rules: vec![FaultRule { error_probability: 1.5, ..Default::default() }] };
+    assert!(config.validate().is_err());
+    config.rules[0] = FaultRule { error_probability: 1.0, error_status: 200, ..Default::default() };
+    assert!(config.validate().is_err());
+    config.rules[0] = FaultRule { route: "collections".into(), ..Default::default() };
+    assert!(config.validate().is_err());
+}
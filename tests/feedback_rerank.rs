@@ -0,0 +1,107 @@
+mod common;
+
+use piramid::config::{AppConfig, RerankConfig};
+use piramid::server::feedback::{FeedbackStore, Signal};
+use piramid::server::state::AppState;
+use piramid::server::types::HitResponse;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+fn hit(id: &str, score: f32) -> HitResponse {
+    HitResponse { id: id.into(), external_id: None, score, text: id.into(), metadata: BTreeMap::new(), band: None, rerank_score: None }
+}
+
+fn ids(hits: &[HitResponse]) -> Vec<&str> {
+    hits.iter().map(|h| h.id.as_str()).collect()
+}
+
+#[test]
+fn feedback_priors_and_learned_weights_reorder_hits() {
+    let dir = TestDir::new("feedback_store");
+    let data_dir = dir.data_dir();
+    let config = RerankConfig { collections: vec!["docs".into()], ..Default::default() };
+    let store = FeedbackStore::open(data_dir);
+    let hits = || vec![hit("a", 0.82), hit("b", 0.80), hit("c", 0.5)];
+
+    // Without feedback the blend keeps the similarity order
+    let ranked = store.rerank("docs", hits(), &config);
+    assert_eq!(ids(&ranked), ["a", "b", "c"]);
+    assert!(ranked[0].rerank_score.unwrap() > ranked[1].rerank_score.unwrap());
+
+    // "b" is clicked and "a" passed over, as shown: the priors and the weights both move
+    for _ in 0..20 {
+        store.record("docs", "b", Signal::Click, Some(0.80), &config);
+        store.record("docs", "a", Signal::Skip, Some(0.82), &config);
+    }
+    let outcome = store.record("docs", "b", Signal::Positive, None, &config);
+    assert_eq!((outcome.counts.positive, outcome.counts.negative, outcome.trained), (21, 0, false));
+    assert!(outcome.prior > 0.0);
+    // Passed over that often, "a" may even fall below the unrelated "c"
+    let ranked = store.rerank("docs", hits(), &config);
+    assert_eq!(ids(&ranked)[0], "b");
+    assert!(ranked.iter().all(|h| h.id == "b" || h.rerank_score < ranked[0].rerank_score));
+    let stats = store.stats("docs", &config);
+    assert_eq!((stats.documents, stats.positive, stats.negative, stats.weights.trained_on), (2, 21, 20, 40));
+    assert!(stats.rerank_enabled);
+
+    // Saved and loaded again, under a new name after a rename
+    store.save().unwrap();
+    store.rename("docs", "articles");
+    let reopened = FeedbackStore::open(data_dir);
+    assert_eq!(ids(&reopened.rerank("articles", hits(), &config)), ids(&ranked));
+    assert!(reopened.clear("articles"));
+    assert_eq!(ids(&reopened.rerank("articles", hits(), &config)), ["a", "b", "c"]);
+}
+
+#[tokio::test]
+async fn feedback_reranks_searches_on_listed_collections() {
+    let dir = TestDir::new("feedback_api");
+    let data_dir = dir.data_dir();
+    for name in ["docs", "plain"] {
+        let mut storage = Collection::open(&format!("{data_dir}/{name}.db")).unwrap();
+        storage.insert(Document::new(vec![1.0, 0.0, 0.0], "top".into())).unwrap();
+        storage.insert(Document::new(vec![0.95, 0.2, 0.0], "liked".into())).unwrap();
+        storage.insert(Document::new(vec![0.0, 1.0, 0.0], "far".into())).unwrap();
+        storage.checkpoint().unwrap();
+    }
+    let mut config = AppConfig::default();
+    config.rerank.collections = vec!["docs".into()];
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    let base = format!("{}/collections", common::serve(state).await);
+    let client = reqwest::Client::new();
+    let search = |collection: &str, body: Value| {
+        let request = client.post(format!("{base}/{collection}/search")).json(&body);
+        async move { request.send().await.unwrap().json::<Value>().await.unwrap() }
+    };
+    let texts = |res: &Value| res["results"].as_array().unwrap().iter().map(|h| h["text"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+    let query = json!({"vector": [1.0, 0.0, 0.0], "k": 2});
+
+    let res = search("docs", query.clone()).await;
+    assert_eq!(texts(&res), ["top", "liked"]);
+    let liked = res["results"][1].clone();
+    for _ in 0..10 {
+        let body = json!({"document_id": liked["id"], "signal": "click", "score": liked["score"]});
+        let res = client.post(format!("{base}/docs/feedback")).json(&body).send().await.unwrap();
+        assert_eq!(res.status(), 200);
+        client.post(format!("{base}/plain/feedback")).json(&body).send().await.unwrap();
+    }
+
+    let res = search("docs", query.clone()).await;
+    assert_eq!(texts(&res), ["liked", "top"], "{res}");
+    assert!(res["results"][0]["rerank_score"].as_f64().unwrap() > res["results"][1]["rerank_score"].as_f64().unwrap());
+    // A primary field order is left alone, and collections not listed are not re-ranked
+    let ordered = search("docs", json!({"vector": [1.0, 0.0, 0.0], "k": 2, "order_by": {"field": "missing"}})).await;
+    assert!(ordered["results"][0]["rerank_score"].is_null());
+    let plain = search("plain", query).await;
+    assert_eq!(texts(&plain), ["top", "liked"]);
+
+    let stats: Value = client.get(format!("{base}/docs/feedback")).send().await.unwrap().json().await.unwrap();
+    assert_eq!((stats["positive"].as_u64(), stats["weights"]["trained_on"].as_u64()), (Some(10), Some(10)), "{stats}");
+    let unknown = client.post(format!("{base}/docs/feedback")).json(&json!({"document_id": "nope", "signal": "click"})).send().await.unwrap();
+    assert_eq!(unknown.status(), 404);
+    let cleared: Value = client.delete(format!("{base}/docs/feedback")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(cleared["cleared"], true);
+}
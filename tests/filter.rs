@@ -18,3 +18,41 @@ fn filter_numeric_comparisons_work() {
     assert!(Filter::new().lte("score", 75i64).matches(&meta));
     assert!(!Filter::new().gt("score", 80i64).matches(&meta));
 }
+
+#[test]
+fn filter_or_and_not_nest() {
+    let meta = metadata([("category", "tech".into()), ("score", 75i64.into())]);
+    let either = Filter::new().or(vec![Filter::new().eq("category", "sports"), Filter::new().gt("score", 50i64)]);
+    assert!(either.matches(&meta));
+    assert!(!either.clone().not(Filter::new().eq("category", "tech")).matches(&meta));
+    assert!(!Filter::new().or(vec![]).matches(&meta));
+    assert_eq!(either.shape(), Filter::new().or(vec![Filter::new().gt("score", 1i64), Filter::new().eq("category", "x")]).shape());
+}
+
+#[test]
+fn any_and_all_test_the_elements_of_array_fields() {
+    let tags = |values: &[&str]| MetadataValue::Array(values.iter().map(|&v| v.into()).collect());
+    let meta = metadata([("tags", tags(&["rust", "db"])), ("lang", "en".into())]);
+
+    assert!(Filter::new().any_in("tags", vec!["go".into(), "db".into()]).matches(&meta));
+    assert!(!Filter::new().any_in("tags", vec!["go".into()]).matches(&meta));
+    assert!(Filter::new().all_in("tags", vec!["db".into(), "rust".into()]).matches(&meta));
+    assert!(!Filter::new().all_in("tags", vec!["db".into(), "go".into()]).matches(&meta));
+    // A scalar is an array of one; equality still compares the whole value
+    assert!(Filter::new().any_in("lang", vec!["en".into(), "de".into()]).matches(&meta));
+    assert!(!Filter::new().eq("tags", "rust").matches(&meta));
+    assert!(!Filter::new().any_in("missing", vec!["x".into()]).matches(&meta));
+    assert!(!Filter::new().any_in("tags", vec![]).matches(&meta) && Filter::new().all_in("tags", vec![]).matches(&meta));
+    assert_ne!(Filter::new().any_in("tags", vec![]).shape(), Filter::new().all_in("tags", vec![]).shape());
+
+    // The JSON form of request filter maps
+    let json = |value: serde_json::Value| serde_json::from_value(value).unwrap();
+    let filter = piramid::server::json_to_filter(json(serde_json::json!({"tags": {"all": ["rust", "db"]}, "lang": "en"}))).unwrap();
+    assert!(filter.matches(&meta));
+    let filter = piramid::server::json_to_filter(json(serde_json::json!({"tags": {"any": ["go"]}}))).unwrap();
+    assert!(!filter.matches(&meta));
+    assert!(piramid::server::json_to_filter(json(serde_json::json!({"tags": ["db", "rust"]}))).unwrap().matches(&metadata([("tags", tags(&["db", "rust"]))])));
+    for bad in [serde_json::json!({"tags": {"some": ["a"]}}), serde_json::json!({"tags": {"any": "a"}}), serde_json::json!({"tags": {"any": [["a"]]}})] {
+        assert!(piramid::server::json_to_filter(json(bad)).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use piramid::config::{CollectionConfig, ExecutionMode, SearchConfig};
+use piramid::index::flat::device::{cpu_top_candidates, QuantizedMatrix};
+#[cfg(not(feature = "gpu"))]
+use piramid::index::flat::device::{register_device, FlatDevice};
+use piramid::index::IndexConfig;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document, Metric, SearchParams};
+#[cfg(not(feature = "gpu"))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Pseudo-random, so no two documents point the same way
+fn vector(i: usize) -> Vec<f32> {
+    common::random_vector(i, 24)
+}
+
+fn gpu_flat(dir: &TestDir, name: &str, metric: Metric) -> Collection {
+    let config = CollectionConfig {
+        execution: ExecutionMode::Gpu,
+        ..CollectionConfig::with_index(IndexConfig::Flat { metric, mode: ExecutionMode::default(), search: SearchConfig::default() })
+    };
+    let mut storage = dir.open(name, config).unwrap();
+    storage.insert_batch((0..800).map(|i| Document::new(vector(i), format!("doc {i}"))).collect()).unwrap();
+    storage
+}
+
+fn ids(hits: &[piramid::Hit]) -> Vec<uuid::Uuid> {
+    hits.iter().map(|hit| hit.id).collect()
+}
+
+#[test]
+fn gpu_batches_match_the_exact_flat_search() {
+    let dir = TestDir::new("gpu_batch_search");
+    let queries: Vec<Vec<f32>> = (1000..1040).map(vector).collect();
+    for metric in [Metric::Cosine, Metric::Euclidean, Metric::DotProduct] {
+        let mut storage = gpu_flat(&dir, metric.name(), metric);
+        let batch = storage.search_batch(&queries, 10, metric);
+        assert_eq!(batch.len(), queries.len());
+        for (query, hits) in queries.iter().zip(&batch) {
+            let single = storage.search(query, 10, metric, SearchParams { mode: ExecutionMode::Scalar, ..SearchParams::default() });
+            assert_eq!(ids(hits), ids(&single), "{metric:?}");
+        }
+
+        // The uploaded copy follows writes
+        let added = storage.insert(Document::new(queries[3].clone(), "exact".into())).unwrap();
+        let batch = storage.search_batch(&queries, 1, metric);
+        assert_eq!(batch[3][0].id, added, "{metric:?}");
+    }
+
+    // A wrong-sized query gets no hits without failing the rest of the batch
+    let storage = gpu_flat(&dir, "mixed", Metric::Cosine);
+    let mut mixed = queries.clone();
+    mixed[5] = vec![1.0; 3];
+    let batch = storage.search_batch(&mixed, 5, Metric::Cosine);
+    assert!(batch[5].is_empty());
+    assert_eq!(batch.iter().filter(|hits| hits.len() == 5).count(), queries.len() - 1);
+}
+
+// Scores on the CPU like the fallback, counting the batches of 20 queries it was given (the other
+// test, which may run at the same time, sends 40). Not with the `gpu` feature, whose adapter probe
+// may register first.
+#[cfg(not(feature = "gpu"))]
+static BATCHES: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(not(feature = "gpu"))]
+struct CountingDevice;
+
+#[cfg(not(feature = "gpu"))]
+impl FlatDevice for CountingDevice {
+    fn name(&self) -> &str {
+        "counting"
+    }
+
+    fn top_candidates(&self, matrix: &QuantizedMatrix, queries: &[Vec<f32>], metric: Metric, candidates: usize) -> Option<Vec<Vec<usize>>> {
+        if queries.len() == 20 {
+            BATCHES.fetch_add(1, Ordering::SeqCst);
+        }
+        Some(cpu_top_candidates(matrix, queries, metric, candidates))
+    }
+}
+
+#[cfg(not(feature = "gpu"))]
+#[test]
+fn large_batches_go_to_the_registered_device() {
+    assert!(register_device(Box::new(CountingDevice)));
+    assert!(!register_device(Box::new(CountingDevice)));
+
+    let dir = TestDir::new("gpu_batch_device");
+    let storage = gpu_flat(&dir, "docs", Metric::Cosine);
+    let queries: Vec<Vec<f32>> = (1000..1020).map(vector).collect();
+    let batch = storage.search_batch(&queries, 5, Metric::Cosine);
+    assert_eq!(BATCHES.load(Ordering::SeqCst), 1);
+    assert_eq!(batch[0].len(), 5);
+
+    // Small batches and queries of another metric than the index's search query by query
+    storage.search_batch(&queries[..4], 5, Metric::Cosine);
+    storage.search_batch(&queries, 5, Metric::Euclidean);
+    assert_eq!(BATCHES.load(Ordering::SeqCst), 1);
+
+    // Other execution modes never reach the device
+    let auto = dir.open("auto", CollectionConfig::with_index(IndexConfig::Flat {
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    })).unwrap();
+    auto.search_batch(&queries, 5, Metric::Cosine);
+    assert_eq!(BATCHES.load(Ordering::SeqCst), 1);
+}
+
+// The wgpu backend picks the same candidates as the CPU computation, up to ties within float
+// rounding. Passes without checking anything on machines without an adapter.
+#[cfg(feature = "gpu")]
+#[test]
+fn wgpu_candidates_match_the_cpu() {
+    use piramid::index::flat::device::FlatDevice;
+    use piramid::index::flat::gpu::WgpuDevice;
+
+    let Some(device) = WgpuDevice::new() else { return };
+    let rows: Vec<(uuid::Uuid, Vec<f32>)> = (0..3000).map(|i| (uuid::Uuid::new_v4(), vector(i))).collect();
+    let matrix = QuantizedMatrix::build(rows.iter().map(|(id, v)| (*id, v.as_slice())), 24, 1);
+    let mut queries: Vec<Vec<f32>> = (5000..5050).map(vector).collect();
+    queries[7] = vec![1.0; 3];
+    for metric in [Metric::Cosine, Metric::Euclidean, Metric::DotProduct] {
+        let found = device.top_candidates(&matrix, &queries, metric, 20).unwrap();
+        let expected = cpu_top_candidates(&matrix, &queries, metric, 20);
+        assert!(found[7].is_empty());
+        for (found, expected) in found.iter().zip(&expected) {
+            let shared = found.iter().filter(|row| expected.contains(row)).count();
+            assert!(shared + 1 >= expected.len(), "{metric:?}: {shared} of {}", expected.len());
+        }
+    }
+}
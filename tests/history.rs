@@ -0,0 +1,92 @@
+use piramid::storage::collection::AsOf;
+use piramid::testing::{freeze_clock, TestDir};
+use piramid::{metadata, Collection, CollectionConfig, Document, Metric, SearchParams};
+
+fn history_config(retention_secs: u64) -> CollectionConfig {
+    let mut config = CollectionConfig::default();
+    config.wal.history_retention_secs = Some(retention_secs);
+    config
+}
+
+#[test]
+fn view_shows_the_collection_as_it_was() {
+    let dir = TestDir::new("history");
+    let path = dir.path("docs.db");
+
+    let (a, b, before);
+    {
+        let mut storage = Collection::open_with_options(&path, history_config(3600).into()).unwrap();
+        a = storage.insert(Document::new(vec![1.0, 0.0, 0.0], "a".into())).unwrap();
+        b = storage.insert(Document::new(vec![0.0, 1.0, 0.0], "b".into())).unwrap();
+        before = storage.head_seq();
+
+        storage.delete(&a).unwrap();
+        storage.update_metadata(&b, metadata([("tag", "new".into())])).unwrap();
+        storage.insert(Document::new(vec![0.0, 0.0, 1.0], "c".into())).unwrap();
+        // Archive the live WAL into a history segment
+        storage.checkpoint().unwrap();
+        storage.insert(Document::new(vec![1.0, 1.0, 0.0], "d".into())).unwrap();
+
+        let view = storage.view_as_of(AsOf::Seq(before)).unwrap();
+        assert_eq!(view.as_of_seq, before);
+        assert_eq!(view.count(), 2);
+        assert!(!view.get(&b).unwrap().metadata.contains_key("tag"));
+        let hits = view.search(&[1.0, 0.0, 0.0], 1, Metric::Cosine, SearchParams::default());
+        assert_eq!(hits[0].id, a);
+
+        // The live collection is untouched
+        assert_eq!(storage.count(), 3);
+        assert!(storage.get(&a).is_none());
+        assert!(storage.view_as_of(AsOf::Seq(storage.head_seq() + 1)).is_err());
+    }
+
+    // Segments and the live WAL survive a reopen
+    let storage = Collection::open_with_options(&path, history_config(3600).into()).unwrap();
+    let view = storage.view_as_of(AsOf::Seq(before)).unwrap();
+    assert_eq!(view.count(), 2);
+    assert!(view.get(&a).is_some());
+    assert_eq!(storage.view_as_of(AsOf::Seq(storage.head_seq())).unwrap().count(), 3);
+
+    drop(storage);
+}
+
+#[test]
+fn expired_segments_fold_into_the_base() {
+    let dir = TestDir::new("history_retention");
+    let path = dir.path("docs.db");
+    let clock = freeze_clock(1_000);
+
+    let mut storage = Collection::open_with_options(&path, history_config(0).into()).unwrap();
+    let first = storage.insert(Document::new(vec![1.0, 0.0], "first".into())).unwrap();
+    let early = storage.head_seq();
+    storage.delete(&first).unwrap();
+    storage.insert(Document::new(vec![0.0, 1.0], "second".into())).unwrap();
+    storage.checkpoint().unwrap();
+
+    // Zero retention: the segment is folded immediately, so only the base and later are readable
+    let summary = storage.history_summary().unwrap();
+    assert_eq!(summary.segments, 0);
+    assert!(summary.base_seq > early);
+    assert!(storage.view_as_of(AsOf::Seq(early)).is_err());
+    let view = storage.view_as_of(AsOf::Seq(summary.base_seq)).unwrap();
+    assert_eq!(view.count(), 1);
+    assert!(view.get(&first).is_none());
+
+    // Timestamps resolve to the newest point at or before them
+    assert_eq!(summary.base_timestamp, 1_000);
+    assert!(storage.view_as_of(AsOf::Timestamp(999)).is_err());
+    storage.insert(Document::new(vec![1.0, 1.0], "third".into())).unwrap();
+    assert_eq!(storage.resolve_as_of(AsOf::Timestamp(1_000)).unwrap(), storage.head_seq());
+    clock.advance(60);
+    assert_eq!(storage.resolve_as_of(AsOf::Timestamp(1_059)).unwrap(), summary.base_seq);
+
+    // Without a retention there is no history to read from
+    let other_dir = TestDir::new("history_disabled");
+    let other = other_dir.path("docs.db");
+    let plain = Collection::open(&other).unwrap();
+    assert!(plain.history_summary().is_none());
+    assert!(plain.view_as_of(AsOf::Seq(0)).is_err());
+
+    drop(storage);
+    drop(plain);
+}
@@ -0,0 +1,93 @@
+use piramid::storage::collection::import_prebuilt;
+use piramid::testing::TestDir;
+use piramid::{Collection, IndexType, Metric, SearchParams};
+use std::fs;
+
+fn write_bundle(dir: &str, manifest: &str, index_file: (&str, &str)) {
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir).unwrap();
+    fs::write(format!("{dir}/manifest.json"), manifest).unwrap();
+    fs::write(
+        format!("{dir}/vectors.jsonl"),
+        concat!(
+            "{\"id\": \"a\", \"vector\": [1.0, 0.0, 0.0], \"text\": \"alpha\"}\n",
+            "{\"id\": \"b\", \"vector\": [0.0, 1.0, 0.0], \"text\": \"beta\", \"metadata\": {\"lang\": \"rust\"}}\n",
+            "{\"id\": \"c\", \"vector\": [0.0, 0.0, 1.0], \"text\": \"gamma\"}\n",
+        ),
+    )
+    .unwrap();
+    fs::write(format!("{dir}/{}", index_file.0), index_file.1).unwrap();
+}
+
+#[test]
+fn import_hnsw_bundle_and_reopen() {
+    let dir = TestDir::new("import_hnsw");
+    let test_db = dir.path("docs.db");
+    let bundle = dir.path("bundle");
+    write_bundle(
+        &bundle,
+        r#"{"format_version": 1, "index_type": "hnsw", "dimensions": 3, "count": 3, "metric": "cosine",
+            "hnsw": {"m": 4, "m_max": 8, "ef_construction": 16}}"#,
+        ("graph.json", r#"{"entry_point": "a", "nodes": {"a": [["b", "c"], ["b"]], "b": [["a", "c"], ["a"]], "c": [["a", "b"]]}}"#),
+    );
+
+    {
+        let mut storage = Collection::open(&test_db).unwrap();
+        let report = import_prebuilt(&mut storage, &bundle).unwrap();
+        assert_eq!(report.index_type, IndexType::Hnsw);
+        assert_eq!(report.imported, 3);
+        assert_eq!(storage.vector_index().index_type(), IndexType::Hnsw);
+
+        let hits = storage.search(&[0.0, 1.0, 0.0], 1, Metric::Cosine, SearchParams::default());
+        assert_eq!(hits[0].text, "beta");
+        assert_eq!(piramid::storage::external_id_of(&hits[0].metadata), Some("b"));
+
+        // Write-once: a second import into the now non-empty collection is refused
+        assert!(import_prebuilt(&mut storage, &bundle).is_err());
+    }
+
+    {
+        let storage = Collection::open(&test_db).unwrap();
+        assert_eq!(storage.count(), 3);
+        assert_eq!(storage.vector_index().index_type(), IndexType::Hnsw);
+        let hits = storage.search(&[0.0, 0.0, 1.0], 1, Metric::Cosine, SearchParams::default());
+        assert_eq!(hits[0].text, "gamma");
+    }
+}
+
+#[test]
+fn import_rejects_inconsistent_bundles() {
+    let dir = TestDir::new("import_invalid");
+    let test_db = dir.path("docs.db");
+    let bundle = dir.path("bundle");
+
+    // IVF clusters that leave one vector unassigned
+    write_bundle(
+        &bundle,
+        r#"{"format_version": 1, "index_type": "ivf", "dimensions": 3, "count": 3}"#,
+        ("clusters.json", r#"{"centroids": [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]], "lists": [["a"], ["b"]]}"#),
+    );
+    let mut storage = Collection::open(&test_db).unwrap();
+    assert!(import_prebuilt(&mut storage, &bundle).is_err());
+    assert_eq!(storage.count(), 0);
+
+    // HNSW graph pointing at an id that is not in vectors.jsonl
+    write_bundle(
+        &bundle,
+        r#"{"format_version": 1, "index_type": "hnsw", "dimensions": 3, "count": 3}"#,
+        ("graph.json", r#"{"entry_point": "a", "nodes": {"a": [["zzz"]], "b": [["a"]], "c": [["a"]]}}"#),
+    );
+    assert!(import_prebuilt(&mut storage, &bundle).is_err());
+    assert_eq!(storage.count(), 0);
+
+    // A valid IVF bundle goes through
+    write_bundle(
+        &bundle,
+        r#"{"format_version": 1, "index_type": "ivf", "dimensions": 3, "count": 3, "ivf": {"num_probes": 2}}"#,
+        ("clusters.json", r#"{"centroids": [[1.0, 0.0, 0.0], [0.0, 1.0, 0.5]], "lists": [["a"], ["b", "c"]]}"#),
+    );
+    let report = import_prebuilt(&mut storage, &bundle).unwrap();
+    assert_eq!(report.index_type, IndexType::Ivf);
+    assert_eq!(storage.count(), 3);
+    drop(storage);
+}
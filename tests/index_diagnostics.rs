@@ -0,0 +1,109 @@
+mod common;
+
+use piramid::config::{AppConfig, CollectionConfig, ExecutionMode, SearchConfig};
+use piramid::index::{HnswConfig, HnswConnectivity, HnswIndex, IndexConfig, IndexDetails};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document, Metric};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use common::vector;
+
+fn hnsw() -> IndexConfig {
+    IndexConfig::Hnsw {
+        m: 16,
+        m_max: 32,
+        ef_construction: 200,
+        ef_search: 200,
+        ml: 1.0 / 16f32.ln(),
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+        shards: 1,
+    }
+}
+
+#[test]
+fn graph_stats_report_degree_depth_and_connectivity() {
+    // Two pieces: a <-> b and c <-> d, entered at a; x points at a but nothing points at x
+    let [a, b, c, d, x] = [(); 5].map(|_| Uuid::new_v4());
+    let graph = HashMap::from([
+        (a, vec![vec![b], vec![]]),
+        (b, vec![vec![a]]),
+        (c, vec![vec![d]]),
+        (d, vec![vec![c]]),
+        (x, vec![vec![a]]),
+    ]);
+    let split = HnswIndex::from_graph(HnswConfig::default(), graph, Some(a));
+    let stats = split.stats();
+    assert_eq!((stats.avg_out_degree, stats.entry_point_depth), (vec![1.0, 0.0], 1));
+    assert_eq!(split.connectivity(), HnswConnectivity { components: 2, unreachable: 3 });
+
+    let mut index = HnswIndex::new(HnswConfig::default());
+    let mut vectors = HashMap::new();
+    let ids: Vec<Uuid> = (0..300).map(|_| Uuid::new_v4()).collect();
+    for (i, id) in ids.iter().enumerate() {
+        vectors.insert(*id, vector(i));
+        index.insert(*id, &vector(i), &vectors);
+    }
+    let stats = index.stats();
+    assert_eq!(stats.avg_out_degree.len(), stats.layer_sizes.len());
+    assert!(stats.avg_out_degree[0] > 1.0 && stats.avg_out_degree[0] <= 32.0, "{stats:?}");
+    assert_eq!(stats.entry_point_depth, stats.max_layer);
+    // Pruning can drop every edge into a node, which leaves it unreachable but not cut off
+    let connectivity = index.connectivity();
+    assert_eq!(connectivity.components, 1);
+    assert!(connectivity.unreachable < 150, "{connectivity:?}");
+
+    // Deleted nodes keep their edges, so nothing new becomes unreachable through them
+    for id in &ids[..150] {
+        index.remove(id);
+    }
+    assert!(index.connectivity().unreachable <= connectivity.unreachable);
+    assert!(index.stats().entry_point_depth >= 0);
+}
+
+#[tokio::test]
+async fn stats_endpoint_adds_diagnostics_on_request() {
+    let dir = TestDir::new("index_diagnostics_http");
+    let data_dir = dir.data_dir();
+    let mut storage = Collection::open_with_options(&format!("{data_dir}/docs.db"), CollectionConfig::with_index(hnsw()).into()).unwrap();
+    let docs = (0..400).map(|i| Document::new(vector(i), format!("doc {i}"))).collect();
+    storage.insert_batch(docs).unwrap();
+
+    // Library: the sampled recall of the collection's own search path
+    let stats = storage.index_diagnostics(30, 5).unwrap();
+    let IndexDetails::Hnsw { diagnostics: Some(diagnostics), .. } = stats.details else { panic!("{stats:?}") };
+    assert_eq!(diagnostics.components, 1);
+    assert!(diagnostics.unreachable < 40, "{diagnostics:?}");
+    assert_eq!((diagnostics.recall_k, diagnostics.recall_queries), (5, 30));
+    assert!(diagnostics.recall_estimate.unwrap() >= 0.8, "{diagnostics:?}");
+    storage.checkpoint().unwrap();
+    drop(storage);
+
+    let config = AppConfig { index: hnsw(), ..Default::default() };
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    state.discover_collections().unwrap();
+    let base = format!("{}/collections/docs/index/stats", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let res: Value = client.get(&base).send().await.unwrap().json().await.unwrap();
+    let details = &res["details"];
+    assert_eq!(details["type"], "Hnsw");
+    assert_eq!(details["avg_out_degree"].as_array().unwrap().len(), details["layer_sizes"].as_array().unwrap().len());
+    assert_eq!(details["entry_point_depth"], details["max_layer"]);
+    assert!(details.get("diagnostics").is_none());
+
+    let res: Value = client.get(format!("{base}?diagnostics=true&sample_size=20&k=3"))
+        .send().await.unwrap().json().await.unwrap();
+    let diagnostics = &res["details"]["diagnostics"];
+    assert_eq!(diagnostics["components"], 1);
+    assert!(diagnostics["unreachable"].as_u64().unwrap() < 40);
+    assert_eq!((diagnostics["recall_k"].as_u64(), diagnostics["recall_queries"].as_u64()), (Some(3), Some(20)));
+    assert!(diagnostics["recall_estimate"].as_f64().unwrap() >= 0.8, "{res}");
+
+    let res = client.get(format!("{base}?diagnostics=true&k=0")).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use piramid::config::{AppConfig, ExecutionMode, SearchConfig};
+use piramid::index::{IndexConfig, IndexType};
+use piramid::server::state::AppState;
+use piramid::storage::collection::{get_index_config_path, migrate_index};
+use piramid::testing::TestDir;
+use piramid::{Collection, CollectionConfig, Document, Metric, SearchParams};
+use parking_lot::RwLock;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Pseudo-random, so no two documents point the same way
+fn vector(i: usize) -> Vec<f32> {
+    common::random_vector(i, 16)
+}
+
+fn ivf() -> IndexConfig {
+    IndexConfig::Ivf {
+        num_clusters: 8,
+        num_probes: 8,
+        max_iterations: 10,
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    }
+}
+
+#[tokio::test]
+async fn migrate_endpoint_switches_the_index_and_keeps_it_across_restarts() {
+    let dir = TestDir::new("index_migration_http");
+    let data_dir = dir.data_dir();
+    let path = format!("{data_dir}/docs.db");
+    let ids = {
+        let mut storage = Collection::open(&path).unwrap();
+        let ids = storage.insert_batch((0..300).map(|i| Document::new(vector(i), format!("doc {i}"))).collect()).unwrap();
+        storage.checkpoint().unwrap();
+        ids
+    };
+
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    state.discover_collections().unwrap();
+    let base = common::serve(state).await;
+    let client = reqwest::Client::new();
+
+    let stats: Value = client.get(format!("{base}/collections/docs/index/stats")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(stats["index_type"], "Flat");
+    let res = client.post(format!("{base}/collections/docs/index/migrate"))
+        .json(&json!({"index": serde_json::to_value(ivf()).unwrap()}))
+        .send().await.unwrap();
+    assert!(res.status().is_success());
+    let queued: Value = res.json().await.unwrap();
+    let id = queued["job_id"].as_str().unwrap().to_string();
+
+    let mut job = Value::Null;
+    for _ in 0..200 {
+        job = client.get(format!("{base}/jobs/{id}")).send().await.unwrap().json().await.unwrap();
+        if job["state"] == "completed" || job["state"] == "failed" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert_eq!(job["state"], "completed", "{job}");
+    assert_eq!((job["kind"].as_str(), job["result"]["from"].as_str(), job["result"]["to"].as_str()), (Some("migrate_index"), Some("Flat"), Some("Ivf")));
+    assert_eq!(job["result"]["vectors"], 300);
+    let stats: Value = client.get(format!("{base}/collections/docs/index/stats")).send().await.unwrap().json().await.unwrap();
+    assert_eq!((stats["index_type"].as_str(), stats["total_vectors"].as_u64()), (Some("IVF"), Some(300)));
+    let hits: Value = client.post(format!("{base}/collections/docs/search"))
+        .json(&json!({"vector": vector(42), "k": 1}))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(hits["results"][0]["id"], ids[42].to_string(), "{hits}");
+
+    // Opened again with the configured (auto) index, the collection keeps the one it switched to, rebuilds included
+    assert!(fs::metadata(get_index_config_path(&path)).is_ok());
+    for suffix in ["", ".index.db", ".vecindex.db", ".metadata.db", ".indexcfg.json"] {
+        fs::copy(format!("{path}{suffix}"), format!("{data_dir}/docs_copy.db{suffix}")).unwrap();
+    }
+    let mut storage = Collection::open(&format!("{data_dir}/docs_copy.db")).unwrap();
+    assert_eq!(storage.vector_index().index_type(), IndexType::Ivf);
+    storage.rebuild_index().unwrap();
+    assert_eq!(storage.vector_index().index_type(), IndexType::Ivf);
+    assert_eq!(storage.config.index, ivf());
+    drop(storage);
+}
+
+#[test]
+fn writes_made_during_the_build_reach_the_new_index() {
+    let dir = TestDir::new("index_migration_writes");
+    let hnsw = IndexConfig::Hnsw {
+        m: 8,
+        m_max: 16,
+        ef_construction: 100,
+        ef_search: 64,
+        ml: 1.0 / (8f32).ln(),
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+        shards: 1,
+    };
+    let mut storage = Collection::open_with_options(&dir.path("docs.db"), CollectionConfig::with_index(hnsw).into()).unwrap();
+    let ids = storage.insert_batch((0..1000).map(|i| Document::new(vector(i), format!("doc {i}"))).collect()).unwrap();
+    let handle = Arc::new(RwLock::new(storage));
+
+    // Inserts, updates and deletes race the build; whichever side of it they land on, the index ends up right
+    let writer = {
+        let handle = handle.clone();
+        let ids = ids.clone();
+        std::thread::spawn(move || {
+            let mut added = Vec::new();
+            for i in 0..100 {
+                let mut storage = handle.write();
+                added.push(storage.insert(Document::new(vector(5000 + i), format!("new {i}"))).unwrap());
+                storage.delete(&ids[i]).unwrap();
+                storage.update_vector(&ids[500 + i], vector(9000 + i)).unwrap();
+            }
+            added
+        })
+    };
+    let report = migrate_index(&handle, ivf()).unwrap();
+    let added = writer.join().unwrap();
+    assert_eq!((report.from, report.to), (IndexType::Hnsw, IndexType::Ivf));
+
+    let mut storage = handle.write();
+    let last = storage.insert(Document::new(vector(7777), "last".into())).unwrap();
+    let indexed: HashSet<_> = storage.vector_index().ids().into_iter().collect();
+    let stored: HashSet<_> = storage.ids().into_iter().collect();
+    assert_eq!(indexed, stored);
+    assert_eq!(stored.len(), 1000 + 1);
+    assert!(indexed.contains(&added[99]) && indexed.contains(&last) && !indexed.contains(&ids[0]));
+    // Probing every cluster, the IVF search is exact: each document is found at its current vector
+    for i in [0, 50, 99] {
+        let hits = storage.search(&vector(9000 + i), 1, Metric::Cosine, SearchParams::default());
+        assert_eq!(hits[0].id, ids[500 + i]);
+    }
+    drop(storage);
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use parking_lot::RwLock;
+use piramid::config::{AppConfig, CollectionConfig, ExecutionMode, SearchConfig};
+use piramid::index::{IndexConfig, IndexType};
+use piramid::server::state::AppState;
+use piramid::storage::collection::recover_index;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document, Metric, SearchParams};
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::Arc;
+use common::vector;
+
+fn hnsw() -> IndexConfig {
+    IndexConfig::Hnsw {
+        m: 16,
+        m_max: 32,
+        ef_construction: 200,
+        ef_search: 200,
+        ml: 1.0 / 16f32.ln(),
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+        shards: 1,
+    }
+}
+
+fn quarantined(dir: &str, prefix: &str) -> Vec<String> {
+    fs::read_dir(dir).unwrap()
+        .filter_map(|e| e.ok()?.file_name().into_string().ok())
+        .filter(|name| name.starts_with(prefix) && name.contains(".vecindex.db.corrupt-"))
+        .collect()
+}
+
+#[test]
+fn corrupt_index_is_quarantined_and_rebuilt() {
+    let dir = TestDir::new("index_recovery");
+    let path = dir.path("docs.db");
+    let config = CollectionConfig { index: hnsw(), ..Default::default() };
+
+    let mut storage = Collection::open_with_options(&path, config.clone().into()).unwrap();
+    for i in 0..50 {
+        storage.insert(Document::new(vector(i), format!("doc {i}"))).unwrap();
+    }
+    storage.checkpoint().unwrap();
+    drop(storage);
+    fs::write(format!("{path}.vecindex.db"), b"definitely not an index").unwrap();
+
+    // Opens on an exact stand-in: every search still finds the right document
+    let storage = Collection::open_with_options(&path, config.clone().into()).unwrap();
+    let recovery = storage.index_recovery().expect("corruption detected").clone();
+    assert!(recovery.quarantined_to.contains(".vecindex.db.corrupt-"), "{recovery:?}");
+    assert_eq!(fs::read(&recovery.quarantined_to).unwrap(), b"definitely not an index");
+    assert!(!std::path::Path::new(&format!("{path}.vecindex.db")).exists());
+    assert_eq!(storage.vector_index().index_type(), IndexType::Flat);
+    let hits = storage.search(&vector(7), 1, Metric::Cosine, SearchParams::default());
+    assert_eq!(hits[0].text, "doc 7");
+
+    // The stand-in is not checkpointed over the missing file
+    storage.checkpoint().unwrap();
+    assert!(!std::path::Path::new(&format!("{path}.vecindex.db")).exists());
+
+    let handle = RwLock::new(storage);
+    assert!(recover_index(&handle).unwrap());
+    assert!(!recover_index(&handle).unwrap());
+    let storage = handle.into_inner();
+    assert!(storage.index_recovery().is_none());
+    assert_eq!(storage.vector_index().index_type(), IndexType::Hnsw);
+    assert_eq!(storage.vector_index().ids().len(), 50);
+    drop(storage);
+
+    // The rebuilt index was saved and loads cleanly
+    let storage = Collection::open_with_options(&path, config.into()).unwrap();
+    assert!(storage.index_recovery().is_none());
+    assert_eq!(storage.vector_index().index_type(), IndexType::Hnsw);
+    assert_eq!(storage.search(&vector(3), 1, Metric::Cosine, SearchParams::default())[0].text, "doc 3");
+    assert_eq!(quarantined(dir.data_dir(), "docs").len(), 1);
+    drop(storage);
+}
+
+#[tokio::test]
+async fn server_rebuilds_a_corrupt_index_in_the_background() {
+    let dir = TestDir::new("index_recovery_http");
+    let data_dir = dir.data_dir();
+    let config = AppConfig { index: hnsw(), ..Default::default() };
+
+    let state = Arc::new(AppState::new(data_dir, config.clone(), 500, None, false, None));
+    let vectors: Vec<Vec<f32>> = (0..40).map(vector).collect();
+    let texts: Vec<String> = (0..40).map(|i| format!("doc {i}")).collect();
+    state.get_or_create_collection("docs").unwrap();
+    {
+        let handle = state.collections.get("docs").unwrap().clone();
+        let mut storage = handle.write();
+        for (v, t) in vectors.iter().zip(&texts) {
+            storage.insert(Document::new(v.clone(), t.clone())).unwrap();
+        }
+    }
+    state.checkpoint_all().unwrap();
+    state.collections.clear();
+    fs::write(format!("{data_dir}/docs.db.vecindex.db"), vec![0xff; 64]).unwrap();
+
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    let base = common::serve(state).await;
+    let client = reqwest::Client::new();
+
+    // Searches are served throughout; the warning is gone once the rebuild is done
+    let mut rebuilt = false;
+    for _ in 0..100 {
+        let res: Value = client.post(format!("{base}/collections/docs/search"))
+            .json(&json!({"vector": vector(11), "k": 1})).send().await.unwrap().json().await.unwrap();
+        assert_eq!(res["results"][0]["text"], "doc 11", "{res}");
+        let info: Value = client.get(format!("{base}/collections/docs")).send().await.unwrap().json().await.unwrap();
+        match info.get("warning") {
+            Some(warning) => assert!(warning.as_str().unwrap().contains("corrupt"), "{info}"),
+            None => {
+                rebuilt = true;
+                break;
+            }
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(rebuilt);
+    let ready: Value = client.get(format!("{base}/readyz")).send().await.unwrap().json().await.unwrap();
+    assert!(ready["collections"][0].get("warning").is_none(), "{ready}");
+    assert_eq!(ready["collections"][0]["index_type"], "HNSW");
+    assert_eq!(quarantined(data_dir, "docs").len(), 1);
+}
@@ -0,0 +1,218 @@
+use async_trait::async_trait;
+use piramid::config::{AppConfig, IngestSourceConfig, IngestSourceKind};
+use piramid::ingest::{IngestSource, IngestWorker, SourceMessage};
+#[cfg(feature = "nats")]
+use piramid::ingest::{connect_source, IngestCheckpoint};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+#[cfg(feature = "nats")]
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+#[cfg(feature = "nats")]
+use tokio::net::TcpListener;
+
+fn source_config(name: &str, source: IngestSourceKind) -> IngestSourceConfig {
+    IngestSourceConfig { name: name.into(), collection: "events".into(), source, batch_size: 10, batch_timeout_ms: 200 }
+}
+
+fn message(id: &str, x: f32) -> Vec<u8> {
+    format!(r#"{{"id":"{id}","text":"doc {id}","vector":[{x},1.0,0.0],"metadata":{{"src":"test"}}}}"#).into_bytes()
+}
+
+// JetStream stand-in: describes the `events`/`ingest` consumer, answers the first pull request with
+// the given messages and later ones with a 404 status, and records every ack it receives
+#[cfg(feature = "nats")]
+async fn fake_jetstream(payloads: Vec<Vec<u8>>, acks: Arc<Mutex<Vec<String>>>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (read, mut write) = socket.into_split();
+        let mut reader = BufReader::new(read);
+        write.write_all(b"INFO {\"server_id\":\"test\",\"headers\":true,\"max_payload\":1048576,\"proto\":1}\r\n").await.unwrap();
+        let mut subs: Vec<(String, String)> = Vec::new(); // (subject, sid)
+        let mut pulls = 0;
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap_or(0) > 0 {
+            let parts: Vec<String> = line.split_whitespace().map(String::from).collect();
+            line.clear();
+            let Some(op) = parts.first() else { continue };
+            match op.as_str() {
+                "PING" => write.write_all(b"PONG\r\n").await.unwrap(),
+                "SUB" => subs.push((parts[1].clone(), parts.last().unwrap().clone())),
+                "PUB" | "HPUB" => {
+                    let len: usize = parts.last().unwrap().parse().unwrap();
+                    let mut payload = vec![0u8; len + 2];
+                    reader.read_exact(&mut payload).await.unwrap();
+                    let subject = parts[1].as_str();
+                    if subject.starts_with("$JS.ACK.") {
+                        // An empty ack body means +ACK
+                        let kind = if len == 0 { "+ACK".into() } else { String::from_utf8_lossy(&payload[..len]) };
+                        acks.lock().unwrap().push(format!("{subject} {kind}"));
+                        continue;
+                    }
+                    let reply = parts[2].clone();
+                    let sid = subs
+                        .iter()
+                        .find(|(s, _)| *s == reply || s.strip_suffix('*').is_some_and(|prefix| reply.starts_with(prefix)))
+                        .map(|(_, sid)| sid.clone())
+                        .unwrap();
+                    let mut out = Vec::new();
+                    let status = |code: &str| {
+                        let header = format!("NATS/1.0 {code}\r\n\r\n");
+                        format!("HMSG {reply} {sid} {} {}\r\n{header}\r\n", header.len(), header.len()).into_bytes()
+                    };
+                    match subject {
+                        "$JS.API.CONSUMER.INFO.events.ingest" => {
+                            let info = serde_json::json!({
+                                "type": "io.nats.jetstream.api.v1.consumer_info_response",
+                                "stream_name": "events", "name": "ingest", "created": "2024-01-01T00:00:00Z",
+                                "config": {"durable_name": "ingest", "deliver_policy": "all", "ack_policy": "explicit", "replay_policy": "instant"},
+                                "delivered": {"consumer_seq": 0, "stream_seq": 0}, "ack_floor": {"consumer_seq": 0, "stream_seq": 0},
+                                "num_ack_pending": 0, "num_redelivered": 0, "num_waiting": 0, "num_pending": payloads.len(),
+                            })
+                            .to_string();
+                            out.extend(format!("MSG {reply} {sid} {}\r\n{info}\r\n", info.len()).into_bytes());
+                        }
+                        "$JS.API.CONSUMER.MSG.NEXT.events.ingest" => {
+                            pulls += 1;
+                            if pulls == 1 {
+                                for (i, body) in payloads.iter().enumerate() {
+                                    let seq = i + 1;
+                                    out.extend(format!("MSG {reply} {sid} $JS.ACK.events.ingest.1.{seq}.{seq}.0.0 {}\r\n", body.len()).into_bytes());
+                                    out.extend(body);
+                                    out.extend(b"\r\n");
+                                }
+                                out.extend(status("408 Request Timeout"));
+                            } else {
+                                out.extend(status("404 No Messages"));
+                            }
+                        }
+                        other => panic!("unexpected request to {other}"),
+                    }
+                    write.write_all(&out).await.unwrap();
+                }
+                _ => {}
+            }
+        }
+    });
+    addr
+}
+
+#[cfg(feature = "nats")]
+#[tokio::test]
+async fn nats_messages_are_upserted_acked_and_checkpointed() {
+    let dir = TestDir::new("ingest_nats");
+    let data_dir = dir.data_dir();
+    let acks = Arc::new(Mutex::new(Vec::new()));
+    let payloads = vec![message("a", 1.0), b"not json".to_vec(), message("b", 2.0)];
+    let addr = fake_jetstream(payloads, acks.clone()).await;
+
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let config = source_config("nats-events", IngestSourceKind::Nats {
+        url: format!("nats://{addr}"),
+        stream: "events".into(),
+        consumer: "ingest".into(),
+    });
+    let source = connect_source(&config).await.unwrap();
+    let mut worker = IngestWorker::start(state.clone(), config, source).await.unwrap();
+
+    assert_eq!(worker.poll().await.unwrap(), 2);
+    assert_eq!(worker.poll().await.unwrap(), 0); // 404: nothing left
+
+    {
+        let storage = state.collections.get("events").unwrap();
+        let storage = storage.read();
+        assert_eq!(storage.count(), 2);
+        assert!(storage.external_ids_view().contains_key("a"));
+        assert!(storage.external_ids_view().contains_key("b"));
+    }
+    let status = state.ingest.get("nats-events").unwrap().clone();
+    assert_eq!((status.ingested, status.skipped, status.position), (2, 1, Some(3)));
+
+    // Every delivered message is acked once the batch is checkpointed, the malformed one included
+    let mut acked = acks.lock().unwrap().clone();
+    acked.sort();
+    assert_eq!(acked, vec![
+        "$JS.ACK.events.ingest.1.1.1.0.0 +ACK",
+        "$JS.ACK.events.ingest.1.2.2.0.0 +ACK",
+        "$JS.ACK.events.ingest.1.3.3.0.0 +ACK",
+    ]);
+    let checkpoint = IngestCheckpoint::load(&IngestCheckpoint::path(data_dir, "nats-events"), "nats-events", "events").unwrap();
+    assert_eq!(checkpoint.last().unwrap().position, 3);
+    assert_eq!(checkpoint.last().unwrap().wal_seq, status.wal_seq.unwrap());
+
+    drop(worker);
+    drop(state);
+}
+
+// Source over an in-memory log; positions are indexes into it
+struct MemorySource {
+    log: Vec<Vec<u8>>,
+    next: usize,
+    seeks: Arc<Mutex<Vec<Option<u64>>>>,
+}
+
+#[async_trait]
+impl IngestSource for MemorySource {
+    async fn fetch(&mut self, max: usize, _wait: Duration) -> piramid::Result<Vec<SourceMessage>> {
+        let end = (self.next + max).min(self.log.len());
+        let batch = (self.next..end).map(|i| SourceMessage { position: i as u64, payload: self.log[i].clone() }).collect();
+        self.next = end;
+        Ok(batch)
+    }
+
+    async fn commit(&mut self, _position: u64) -> piramid::Result<()> {
+        Ok(())
+    }
+
+    async fn seek(&mut self, position: Option<u64>) -> piramid::Result<()> {
+        self.seeks.lock().unwrap().push(position);
+        self.next = position.map_or(0, |p| p as usize + 1);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn checkpoint_resumes_and_rewinds_with_the_collection_wal() {
+    let dir = TestDir::new("ingest_resume");
+    let data_dir = dir.data_dir();
+    let log: Vec<Vec<u8>> = ["a", "b", "c", "d", "e"].iter().enumerate().map(|(i, id)| message(id, i as f32)).collect();
+    let seeks = Arc::new(Mutex::new(Vec::new()));
+    let memory = |len: usize| Box::new(MemorySource { log: log[..len].to_vec(), next: 0, seeks: seeks.clone() });
+    let config = source_config("memory", IngestSourceKind::Nats { url: String::new(), stream: "s".into(), consumer: "c".into() });
+    let count = |state: &AppState| state.collections.get("events").unwrap().read().count();
+
+    // First run: three messages from the start
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let mut worker = IngestWorker::start(state.clone(), config.clone(), memory(3)).await.unwrap();
+    assert_eq!(worker.poll().await.unwrap(), 3);
+    drop(worker);
+
+    // Restart against the same collection: continues after the checkpointed position
+    let mut worker = IngestWorker::start(state.clone(), config.clone(), memory(5)).await.unwrap();
+    assert_eq!(worker.poll().await.unwrap(), 2);
+    assert_eq!(count(&state), 5);
+    drop(worker);
+    drop(state);
+    assert_eq!(*seeks.lock().unwrap(), vec![None, Some(2)]);
+
+    // The collection is gone (its WAL head is back at 0): the checkpoint is ahead of it, so the
+    // source is rewound and everything is consumed again
+    for entry in fs::read_dir(data_dir).unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_name().to_string_lossy().starts_with("events.db") {
+            fs::remove_file(entry.path()).unwrap();
+        }
+    }
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let mut worker = IngestWorker::start(state.clone(), config, memory(5)).await.unwrap();
+    assert_eq!(worker.poll().await.unwrap(), 5);
+    assert_eq!(count(&state), 5);
+    assert_eq!(seeks.lock().unwrap().last(), Some(&None));
+
+    drop(worker);
+    drop(state);
+}
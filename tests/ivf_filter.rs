@@ -0,0 +1,86 @@
+mod common;
+
+use piramid::config::{ExecutionMode, SearchConfig};
+use piramid::index::{IndexConfig, IndexDetails, IvfConfig, IvfFilterStats, IvfIndex};
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, CollectionConfig, Document, Filter, Metadata, Metric, SearchParams, VectorIndex};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// Two groups far apart: "a" around the first axis, "b" around the second
+fn vector(i: usize, group: &str) -> Vec<f32> {
+    let mut v: Vec<f32> = common::wave(i, 8, 0.73).iter().map(|x| x * 0.3).collect();
+    v[if group == "a" { 0 } else { 1 }] += 3.0;
+    v
+}
+
+fn filter_stats(index: &dyn VectorIndex) -> IvfFilterStats {
+    match index.stats().details {
+        IndexDetails::Ivf { filter, .. } => filter,
+        _ => IvfFilterStats::default(),
+    }
+}
+
+#[test]
+fn filtered_probes_skip_clusters_without_matches() {
+    let config = IvfConfig { num_clusters: 8, num_probes: 1, max_iterations: 10, metric: Metric::Cosine, mode: ExecutionMode::default() };
+    let mut vectors: HashMap<Uuid, Vec<f32>> = HashMap::new();
+    let mut metadatas: HashMap<Uuid, Metadata> = HashMap::new();
+    for i in 0..400 {
+        let group = if i % 20 == 0 { "b" } else { "a" };
+        let id = Uuid::new_v4();
+        vectors.insert(id, vector(i, group));
+        metadatas.insert(id, metadata([("group", group.into())]));
+    }
+    let mut index = IvfIndex::new(config);
+    index.build_clusters(&vectors);
+
+    // The nearest cluster to a query on "a" holds no "b": it is skipped and a cluster of "b" probed instead
+    let only_b = Filter::new().eq("group", "b");
+    let hits = index.search(&vector(1, "a"), 5, &vectors, SearchConfig::default(), Some(&only_b), &metadatas);
+    assert_eq!(hits.len(), 5);
+    assert!(hits.iter().all(|id| metadatas[id]["group"] == "b".into()));
+    let stats = filter_stats(&index);
+    assert_eq!(stats.searches, 1);
+    assert!(stats.clusters_skipped > 0, "{stats:?}");
+
+    // Unfiltered searches are left alone
+    let hits = index.search(&vector(1, "a"), 5, &vectors, SearchConfig::default(), None, &metadatas);
+    assert!(hits.iter().all(|id| metadatas[id]["group"] == "a".into()));
+    assert_eq!(filter_stats(&index).searches, 1);
+
+    // Nothing matches: every cluster is skipped and nothing is scored
+    let none = Filter::new().eq("group", "c");
+    assert!(index.search(&vector(1, "a"), 5, &vectors, SearchConfig::default(), Some(&none), &metadatas).is_empty());
+}
+
+#[test]
+fn collection_search_finds_rare_matches_with_few_probes() {
+    let dir = TestDir::new("ivf_filter_collection");
+    let config = CollectionConfig::with_index(IndexConfig::Ivf {
+        num_clusters: 16,
+        num_probes: 2,
+        max_iterations: 10,
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    });
+    let mut storage = Collection::open_with_options(&dir.path("docs.db"), config.into()).unwrap();
+    let docs = (0..1200)
+        .map(|i| {
+            let group = if i % 100 == 0 { "b" } else { "a" };
+            Document::with_metadata(vector(i, group), format!("doc {i}"), metadata([("group", group.into())]))
+        })
+        .collect();
+    storage.insert_batch(docs).unwrap();
+    storage.rebuild_index().unwrap();
+
+    // All twelve "b" documents sit in clusters far from the query; a post-filter over 2 probes would find none
+    let only_b = Filter::new().eq("group", "b");
+    let params = SearchParams { filter: Some(&only_b), ..SearchParams::default() };
+    let hits = storage.search(&vector(7, "a"), 10, Metric::Cosine, params);
+    assert_eq!(hits.len(), 10);
+    assert!(hits.iter().all(|h| h.metadata["group"] == "b".into()));
+    assert!(filter_stats(storage.vector_index()).clusters_skipped > 0);
+    drop(storage);
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use piramid::config::{ExecutionMode, SearchConfig};
+use piramid::index::{spill_path, IndexConfig, IndexDetails, IvfConfig, IvfIndex, IvfSpillStats};
+use piramid::testing::TestDir;
+use piramid::{Collection, CollectionConfig, Document, Metric, SearchParams, VectorIndex};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+fn vector(i: usize) -> Vec<f32> {
+    common::wave(i, 8, 0.73)
+}
+
+fn spill_stats(index: &dyn VectorIndex) -> Option<IvfSpillStats> {
+    match index.stats().details {
+        IndexDetails::Ivf { spill, .. } => spill,
+        _ => None,
+    }
+}
+
+fn per_cluster(index: &dyn VectorIndex) -> Vec<usize> {
+    match index.stats().details {
+        IndexDetails::Ivf { vectors_per_cluster, .. } => vectors_per_cluster,
+        _ => Vec::new(),
+    }
+}
+
+#[test]
+fn spilled_lists_search_like_resident_ones() {
+    let dir = TestDir::new("ivf_spill_index");
+    let path = dir.path("docs.db");
+
+    let config = IvfConfig { num_clusters: 16, num_probes: 4, max_iterations: 10, metric: Metric::Cosine, mode: ExecutionMode::default() };
+    let mut vectors: HashMap<Uuid, Vec<f32>> = (0..2000).map(|i| (Uuid::new_v4(), vector(i))).collect();
+    let mut resident = IvfIndex::new(config);
+    resident.build_clusters(&vectors);
+    let mut spilled = resident.clone();
+    // Room for the centroids and a few lists only
+    spilled.set_memory_budget(Some(8 * 1024), &path);
+    let stats = spill_stats(&spilled).unwrap();
+    assert!(stats.spilled_clusters > 0 && stats.resident_clusters + stats.spilled_clusters == 16, "{stats:?}");
+    assert!(spill_path(&path).exists());
+    assert!(spilled.stats().memory_usage_bytes < resident.stats().memory_usage_bytes);
+
+    let empty = HashMap::new();
+    let search = |index: &IvfIndex, vectors: &HashMap<Uuid, Vec<f32>>, q: usize| {
+        index.search(&vector(q), 10, vectors, SearchConfig::default(), None, &empty)
+    };
+    for q in (0..2000).step_by(97) {
+        assert_eq!(search(&spilled, &vectors, q), search(&resident, &vectors, q));
+    }
+    let stats = spill_stats(&spilled).unwrap();
+    assert!(stats.misses > 0 && stats.hits + stats.misses == 21 * 4, "{stats:?}");
+
+    // Writes to spilled lists are seen by the next search
+    let ids: Vec<Uuid> = vectors.keys().copied().take(300).collect();
+    for id in &ids[..150] {
+        spilled.remove(id);
+        resident.remove(id);
+        vectors.remove(id);
+    }
+    for i in 2000..2300 {
+        let id = Uuid::new_v4();
+        vectors.insert(id, vector(i));
+        spilled.insert(id, &vector(i), &vectors);
+        resident.insert(id, &vector(i), &vectors);
+    }
+    for q in (0..2300).step_by(89) {
+        assert_eq!(search(&spilled, &vectors, q), search(&resident, &vectors, q));
+    }
+    assert_eq!(spilled.ids().len(), 2150);
+
+    // A clone (what gets saved) holds every list in memory
+    let copy = spilled.clone();
+    assert!(spill_stats(&copy).is_none());
+    assert_eq!(per_cluster(&copy), per_cluster(&resident));
+
+    spilled.set_memory_budget(None, &path);
+    assert!(spill_stats(&spilled).is_none());
+    assert!(!spill_path(&path).exists());
+    assert_eq!(search(&spilled, &vectors, 5), search(&resident, &vectors, 5));
+}
+
+#[test]
+fn collection_budget_spills_and_survives_reopen() {
+    let dir = TestDir::new("ivf_spill_collection");
+    let path = dir.path("docs.db");
+    let mut config = CollectionConfig::with_index(IndexConfig::Ivf {
+        num_clusters: 16,
+        num_probes: 16,
+        max_iterations: 10,
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    });
+    config.memory.index_memory_budget = Some(8 * 1024);
+
+    let mut storage = Collection::open_with_options(&path, config.clone().into()).unwrap();
+    let docs = (0..1500).map(|i| Document::new(vector(i), format!("doc {i}"))).collect();
+    let ids = storage.insert_batch(docs).unwrap();
+    storage.rebuild_index().unwrap();
+    storage.checkpoint().unwrap();
+    assert!(spill_stats(storage.vector_index()).unwrap().spilled_clusters > 0);
+    assert!(spill_path(&path).exists());
+    // Every cluster is probed, so the nearest document is the query itself
+    let hits = storage.search(&vector(42), 3, Metric::Cosine, SearchParams::default());
+    assert_eq!(hits[0].id, ids[42]);
+    drop(storage);
+    assert!(!spill_path(&path).exists());
+
+    let storage = Collection::open_with_options(&path, config.into()).unwrap();
+    let stats = spill_stats(storage.vector_index()).unwrap();
+    assert!(stats.spilled_clusters > 0 && stats.spilled_vectors > 0, "{stats:?}");
+    let again = storage.search(&vector(42), 3, Metric::Cosine, SearchParams::default());
+    assert_eq!(again.iter().map(|h| h.id).collect::<Vec<_>>(), hits.iter().map(|h| h.id).collect::<Vec<_>>());
+    assert!(spill_stats(storage.vector_index()).unwrap().misses > 0);
+    drop(storage);
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::wave;
+use piramid::config::ExecutionMode;
+use piramid::metrics::jit::{cached_kernels, cpu_features, kernel, select, CpuPath, KernelKey, KernelOp, SPECIALIZED_DIMS};
+use piramid::metrics::{cosine_similarity, dot_product, euclidean_distance_squared};
+
+#[test]
+fn every_path_matches_scalar() {
+    let features = cpu_features();
+    let paths = [CpuPath::Portable, CpuPath::Neon, CpuPath::Avx2, CpuPath::Avx512];
+    let dims: Vec<usize> = [1, 7, 15, 16, 17, 100].into_iter().chain(SPECIALIZED_DIMS).collect();
+    for dim in dims {
+        let (a, b) = (wave(1, dim, 0.37), wave(2, dim, 0.37));
+        let expected = [
+            (KernelOp::Cosine, cosine_similarity(&a, &b, ExecutionMode::Scalar)),
+            (KernelOp::Dot, dot_product(&a, &b, ExecutionMode::Scalar)),
+            (KernelOp::EuclideanSquared, euclidean_distance_squared(&a, &b, ExecutionMode::Scalar)),
+        ];
+        for (op, want) in expected {
+            // Paths the CPU lacks step down to one it has, so every request gets a working kernel
+            for path in paths {
+                let got = select(op, dim, path)(&a, &b);
+                assert!((got - want).abs() <= 1e-4 * want.abs().max(1.0), "{op:?} dim {dim} {path:?}: {got} vs {want}");
+            }
+        }
+    }
+    assert!(features.supports(features.best_path()));
+    assert!(features.supports(CpuPath::Portable));
+}
+
+#[test]
+fn kernels_are_cached_per_dimension() {
+    let (a, b) = (wave(3, 768, 0.37), wave(4, 768, 0.37));
+    let first = kernel(KernelOp::Dot, 768);
+    assert!(std::ptr::fn_addr_eq(first, kernel(KernelOp::Dot, 768)));
+    assert!(!std::ptr::fn_addr_eq(first, kernel(KernelOp::Dot, 769)));
+    let key = KernelKey { op: KernelOp::Dot, dim: 768, path: cpu_features().best_path() };
+    assert!(cached_kernels().contains(&key));
+
+    // The Jit execution mode goes through the cache
+    let jit = dot_product(&a, &b, ExecutionMode::Jit);
+    assert!((jit - dot_product(&a, &b, ExecutionMode::Scalar)).abs() < 1e-3);
+    let _ = cosine_similarity(&a, &b, ExecutionMode::Jit);
+    assert!(cached_kernels().iter().any(|k| k.op == KernelOp::Cosine && k.dim == 768));
+}
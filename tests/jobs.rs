@@ -0,0 +1,178 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::embeddings::{Embedder, EmbeddingResponse, EmbeddingResult};
+use piramid::jobs::{JobQueue, JobState};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+// Embeds every text as [2, 0, 0], counting texts; with a gate, each text waits for a permit
+struct MockEmbedder {
+    embedded: Arc<AtomicUsize>,
+    gate: Option<Arc<Semaphore>>,
+}
+
+#[async_trait::async_trait]
+impl Embedder for MockEmbedder {
+    async fn embed(&self, _text: &str) -> EmbeddingResult<EmbeddingResponse> {
+        if let Some(gate) = &self.gate {
+            gate.acquire().await.unwrap().forget();
+        }
+        self.embedded.fetch_add(1, Ordering::SeqCst);
+        Ok(EmbeddingResponse { embedding: vec![2.0, 0.0, 0.0], tokens: Some(1), model: "mock".to_string() })
+    }
+
+    fn provider_name(&self) -> &str {
+        "mock"
+    }
+
+    fn model_name(&self) -> &str {
+        "mock-v2"
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        Some(3)
+    }
+}
+
+fn seed(data_dir: &str, docs: usize) -> Vec<uuid::Uuid> {
+    fs::create_dir_all(data_dir).unwrap();
+    let mut storage = Collection::open(&format!("{data_dir}/docs.db")).unwrap();
+    for i in 0..docs {
+        storage.insert(Document::new(vec![1.0, 0.0, 0.0], format!("doc {i}"))).unwrap();
+    }
+    storage.checkpoint().unwrap();
+    storage.ids()
+}
+
+async fn wait_for(check: impl Fn() -> bool) {
+    for _ in 0..200 {
+        if check() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    panic!("timed out waiting for job");
+}
+
+#[tokio::test]
+async fn reembed_left_running_resumes_after_its_cursor() {
+    let dir = TestDir::new("jobs_resume");
+    let data_dir = dir.data_dir();
+    let ids = seed(data_dir, 10);
+
+    // The previous process finished the batch ending at the fourth document, then went down
+    fs::create_dir_all(format!("{data_dir}/jobs")).unwrap();
+    let job = json!({
+        "id": "job-1", "seq": 1, "collection": "docs", "kind": "reembed", "batch_size": 4,
+        "state": "running", "created_at": 0, "attempts": 1, "cursor": ids[3].to_string(),
+    });
+    fs::write(format!("{data_dir}/jobs/job-1.json"), job.to_string()).unwrap();
+
+    let embedded = Arc::new(AtomicUsize::new(0));
+    let embedder = Arc::new(MockEmbedder { embedded: embedded.clone(), gate: None });
+    let state = Arc::new(AppState::with_embedder(data_dir, AppConfig::default(), 500, embedder, None, false, None));
+    state.discover_collections().unwrap();
+    assert_eq!(state.jobs.get("job-1").unwrap().state, JobState::Queued);
+    assert_eq!(piramid::jobs::spawn_jobs(state.clone()), 1);
+    wait_for(|| state.jobs.get("job-1").unwrap().state.is_finished()).await;
+
+    let job = state.jobs.get("job-1").unwrap();
+    assert_eq!(job.state, JobState::Completed, "{:?}", job.error);
+    assert_eq!(job.attempts, 2);
+    assert_eq!(job.progress.done, 10);
+    assert_eq!(embedded.load(Ordering::SeqCst), 6);
+
+    let handle = state.collections.get("docs").unwrap().clone();
+    let storage = handle.read();
+    for (i, id) in ids.iter().enumerate() {
+        let x = storage.get(id).unwrap().get_vector()[0];
+        let expected = if i < 4 { 1.0 } else { 2.0 };
+        assert!((x - expected).abs() < 0.05, "doc {i}: {x}");
+    }
+    let model = storage.metadata().embedding_model.clone().unwrap();
+    assert_eq!((model.model.as_str(), model.dimensions), ("mock-v2", 3));
+    drop(storage);
+
+    // An import a restart interrupted is failed rather than run again
+    let import = json!({
+        "id": "job-2", "seq": 2, "collection": "docs", "kind": "import", "path": "/nowhere",
+        "state": "running", "created_at": 0,
+    });
+    fs::write(format!("{data_dir}/jobs/job-2.json"), import.to_string()).unwrap();
+    let reopened = JobQueue::open(data_dir);
+    assert_eq!(reopened.get("job-2").unwrap().state, JobState::Failed);
+    assert_eq!(reopened.get("job-1").unwrap().state, JobState::Completed);
+    assert_eq!(reopened.pending(), 0);
+}
+
+#[tokio::test]
+async fn jobs_can_be_cancelled_and_are_kept_on_disk() {
+    let dir = TestDir::new("jobs_cancel");
+    let data_dir = dir.data_dir();
+    seed(data_dir, 6);
+
+    let gate = Arc::new(Semaphore::new(0));
+    let embedder = Arc::new(MockEmbedder { embedded: Arc::new(AtomicUsize::new(0)), gate: Some(gate.clone()) });
+    let state = Arc::new(AppState::with_embedder(data_dir, AppConfig::default(), 500, embedder, None, false, None));
+    state.discover_collections().unwrap();
+    let base = common::serve(state).await;
+    let client = reqwest::Client::new();
+    let get = |path: String| {
+        let client = client.clone();
+        async move { client.get(path).send().await.unwrap().json::<Value>().await.unwrap() }
+    };
+
+    // The re-embed blocks inside its first batch, so the rebuild behind it stays queued
+    let res = client.post(format!("{base}/collections/docs/reembed")).json(&json!({"batch_size": 2})).send().await.unwrap();
+    assert!(res.status().is_success());
+    let reembed = res.json::<Value>().await.unwrap()["id"].as_str().unwrap().to_string();
+    let res: Value = client.post(format!("{base}/collections/docs/index/rebuild")).send().await.unwrap().json().await.unwrap();
+    let rebuild = res["job_id"].as_str().unwrap().to_string();
+    for _ in 0..200 {
+        if get(format!("{base}/jobs/{reembed}")).await["state"] == "running" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+
+    let res = client.post(format!("{base}/jobs/{rebuild}/cancel")).send().await.unwrap();
+    assert_eq!(res.json::<Value>().await.unwrap()["state"], "cancelled");
+    let status = get(format!("{base}/collections/docs/index/rebuild/status")).await;
+    assert_eq!((status["status"].as_str(), status["job_id"].as_str()), (Some("cancelled"), Some(rebuild.as_str())));
+    let res = client.post(format!("{base}/jobs/{rebuild}/cancel")).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+    let res = client.post(format!("{base}/jobs/missing/cancel")).send().await.unwrap();
+    assert_eq!(res.status(), 404);
+
+    // A running re-embed stops at its next batch
+    let res: Value = client.post(format!("{base}/jobs/{reembed}/cancel")).send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["state"].as_str(), res["cancel_requested"].as_bool()), (Some("running"), Some(true)));
+    gate.add_permits(100);
+    for _ in 0..200 {
+        if get(format!("{base}/jobs/{reembed}")).await["state"] == "cancelled" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    let job = get(format!("{base}/jobs/{reembed}")).await;
+    assert_eq!(job["state"], "cancelled");
+    assert_eq!(job["progress"], json!({"done": 2, "total": 6}));
+
+    let listed = get(format!("{base}/jobs?collection=docs")).await;
+    let ids: Vec<&str> = listed["jobs"].as_array().unwrap().iter().map(|j| j["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, vec![rebuild.as_str(), reembed.as_str()]);
+    assert!(get(format!("{base}/jobs?collection=other")).await["jobs"].as_array().unwrap().is_empty());
+
+    // Both jobs are on disk and load back as they were
+    let reopened = JobQueue::open(data_dir);
+    assert_eq!(reopened.list(None).len(), 2);
+    assert!(reopened.list(None).iter().all(|j| j.state == JobState::Cancelled));
+}
@@ -1,4 +1,11 @@
-use piramid::metrics::latency::{LatencyTracker, time_operation, time_operation_sync};
+mod common;
+
+use piramid::metrics::latency::{LatencyOp, LatencyTracker, time_operation, time_operation_sync};
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::sync::Arc;
 use std::time::Duration;
 
 #[test]
@@ -36,3 +43,93 @@ fn time_operation_sync_measures_duration() {
     assert_eq!(result, "ok");
     assert!(duration.as_millis() >= 5);
 }
+
+#[test]
+fn histogram_exposes_tail_latency() {
+    let tracker = LatencyTracker::new();
+    for _ in 0..990 {
+        tracker.record_search(Duration::from_micros(500));
+    }
+    for _ in 0..10 {
+        tracker.record_search(Duration::from_millis(200));
+    }
+
+    let p50 = tracker.quantile_ms(LatencyOp::Search, 0.5).unwrap();
+    let p99 = tracker.quantile_ms(LatencyOp::Search, 0.99).unwrap();
+    let p999 = tracker.quantile_ms(LatencyOp::Search, 0.999).unwrap();
+    assert!((p50 - 0.5).abs() < 0.02, "p50 {p50}");
+    assert!(p99 < 1.0, "p99 {p99}");
+    // The slow tail is visible instead of being averaged away (within the bucket precision)
+    assert!((p999 - 200.0).abs() < 200.0 * 0.04, "p99.9 {p999}");
+
+    let summary = tracker.summary(LatencyOp::Search).unwrap();
+    assert_eq!(summary.count, 1000);
+    assert_eq!(summary.max_ms, 200.0);
+    assert!(tracker.summary(LatencyOp::Delete).is_none());
+}
+
+#[test]
+fn histograms_survive_save_and_load() {
+    let dir = TestDir::new("latency_histograms");
+    let path = dir.root().join("docs.latency.json");
+
+    // A missing file gives an empty tracker
+    assert!(LatencyTracker::load(&path).unwrap().summary(LatencyOp::Insert).is_none());
+
+    let tracker = LatencyTracker::new();
+    for ms in 1..=100 {
+        tracker.record_insert(Duration::from_millis(ms));
+    }
+    tracker.record_lock_write(Duration::from_micros(3));
+    tracker.save(&path).unwrap();
+
+    let loaded = LatencyTracker::load(&path).unwrap();
+    let (before, after) = (tracker.summary(LatencyOp::Insert).unwrap(), loaded.summary(LatencyOp::Insert).unwrap());
+    assert_eq!(after.count, 100);
+    assert_eq!(after.p99_ms, before.p99_ms);
+    assert_eq!(after.mean_ms, before.mean_ms);
+    assert_eq!(loaded.quantile_ms(LatencyOp::LockWrite, 0.5), Some(0.003));
+}
+
+#[test]
+fn reset_empties_every_histogram() {
+    let tracker = LatencyTracker::new();
+    let shared = tracker.clone();
+    tracker.record_search(Duration::from_millis(4));
+    tracker.record_lock_read(Duration::from_micros(10));
+    shared.reset();
+    for op in LatencyOp::ALL {
+        assert!(tracker.summary(op).is_none(), "{op:?}");
+        assert_eq!(tracker.histogram(op).max_us(), 0);
+    }
+    tracker.record_search(Duration::from_millis(2));
+    assert_eq!(tracker.summary(LatencyOp::Search).unwrap().max_ms, 2.0);
+}
+
+#[tokio::test]
+async fn persisted_histograms_reload_until_reset() {
+    let dir = TestDir::new("latency_persist");
+    let data_dir = dir.data_dir();
+    let config = AppConfig { persist_latency_histograms: true, ..Default::default() };
+
+    let state = Arc::new(AppState::new(data_dir, config.clone(), 1000, None, false, None));
+    state.get_or_create_collection("docs").unwrap();
+    state.latency_tracker.get("docs").unwrap().record_search(Duration::from_millis(7));
+    state.save_latency_histograms().unwrap();
+    drop(state);
+
+    // A restart picks the saved histograms up with the collection
+    let state = Arc::new(AppState::new(data_dir, config, 1000, None, false, None));
+    state.get_or_create_collection("docs").unwrap();
+    assert_eq!(state.latency_tracker.get("docs").unwrap().summary(LatencyOp::Search).unwrap().count, 1);
+
+    let base = common::serve(state.clone()).await;
+    let client = reqwest::Client::new();
+
+    let res = client.delete(format!("{base}/metrics/latency?collection=missing")).send().await.unwrap();
+    assert_eq!(res.status(), 404);
+    let reset: Value = client.delete(format!("{base}/metrics/latency?collection=docs")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(reset, json!({"reset": ["docs"]}));
+    assert!(state.latency_tracker.get("docs").unwrap().summary(LatencyOp::Search).is_none());
+    assert!(!state.latency_path("docs").exists());
+}
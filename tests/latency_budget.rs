@@ -0,0 +1,115 @@
+mod common;
+
+use piramid::config::{AppConfig, ExecutionMode, SearchConfig};
+use piramid::index::{IndexConfig, IndexType};
+use piramid::search::LatencyBudget;
+use piramid::server::state::AppState;
+use piramid::Metric;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn effort_follows_the_observed_latency() {
+    let budget = LatencyBudget::new();
+    // Nothing observed yet: the configured value, kept within bounds
+    assert_eq!(budget.choose(IndexType::Hnsw, 5.0, 100, 10, 1024), (100, None));
+    assert_eq!(budget.choose(IndexType::Hnsw, 5.0, 4, 10, 1024), (10, None));
+
+    // 10ms at ef=100 is 0.1ms per unit: a 5ms budget fits ef=50
+    budget.record(IndexType::Hnsw, 100, Duration::from_millis(10));
+    let (ef, predicted) = budget.choose(IndexType::Hnsw, 5.0, 100, 10, 1024);
+    assert_eq!(ef, 50);
+    assert!((predicted.unwrap() - 5.0).abs() < 1e-3);
+    // A much larger budget only doubles it per search, and never past the largest useful value
+    assert_eq!(budget.choose(IndexType::Hnsw, 1000.0, 100, 10, 1024).0, 200);
+    assert_eq!(budget.choose(IndexType::Hnsw, 1000.0, 100, 10, 150).0, 150);
+    assert_eq!(budget.choose(IndexType::Hnsw, 0.001, 100, 10, 1024).0, 50);
+
+    // Observations are smoothed, and another index type starts over
+    budget.record(IndexType::Hnsw, 50, Duration::from_millis(10));
+    let (ef, _) = budget.choose(IndexType::Hnsw, 5.0, 100, 10, 1024);
+    assert!((25..50).contains(&ef), "{ef}");
+    assert_eq!(budget.choose(IndexType::Ivf, 5.0, 3, 1, 16), (3, None));
+}
+
+fn vector(i: usize) -> Vec<f32> {
+    common::wave(i, 16, 0.43)
+}
+
+async fn serve(data_dir: &str) -> String {
+    let config = AppConfig {
+        index: IndexConfig::Hnsw {
+            m: 16,
+            m_max: 32,
+            ef_construction: 100,
+            ef_search: 40,
+            ml: 1.0 / 16f32.ln(),
+            metric: Metric::Cosine,
+            mode: ExecutionMode::default(),
+            search: SearchConfig::default(),
+            shards: 1,
+        },
+        ..Default::default()
+    };
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    format!("{}/collections/docs", common::serve(state).await)
+}
+
+#[tokio::test]
+async fn searches_report_the_parameters_they_ran_with() {
+    let dir = TestDir::new("latency_budget_http");
+    let data_dir = dir.data_dir();
+    let base = serve(data_dir).await;
+    let client = reqwest::Client::new();
+    let vectors: Vec<Vec<f32>> = (0..300).map(vector).collect();
+    let texts: Vec<String> = (0..300).map(|i| format!("doc {i}")).collect();
+    client.post(format!("{base}/vectors"))
+        .json(&json!({"vectors": vectors, "texts": texts}))
+        .send().await.unwrap();
+
+    // Without a target nothing is reported
+    let res: Value = client.post(format!("{base}/search"))
+        .json(&json!({"vector": vector(7), "k": 5}))
+        .send().await.unwrap().json().await.unwrap();
+    assert!(res.get("effective").is_none());
+
+    // The first budgeted search starts from the configured ef; a generous budget then raises it
+    let search = |target_ms: f32| {
+        let (client, base) = (client.clone(), base.clone());
+        async move {
+            client.post(format!("{base}/search"))
+                .json(&json!({"vector": vector(7), "k": 5, "target_ms": target_ms}))
+                .send().await.unwrap().json::<Value>().await.unwrap()
+        }
+    };
+    let first = search(10_000.0).await;
+    assert_eq!(first["effective"], json!({"ef": 40, "target_ms": 10_000.0}));
+    assert_eq!(first["results"].as_array().unwrap().len(), 5);
+    let second = search(10_000.0).await;
+    assert_eq!(second["effective"]["ef"], 80);
+    assert!(second["effective"]["predicted_ms"].as_f64().unwrap() <= 10_000.0);
+    let mut ef = 80;
+    for _ in 0..6 {
+        ef = search(10_000.0).await["effective"]["ef"].as_u64().unwrap();
+    }
+    assert_eq!(ef, 1024);
+    // A budget nothing fits falls back step by step, never below k
+    let mut ef = 1024;
+    for _ in 0..12 {
+        let res = search(0.000_1).await;
+        assert_eq!(res["results"].as_array().unwrap().len(), 5);
+        ef = res["effective"]["ef"].as_u64().unwrap();
+    }
+    assert_eq!(ef, 5);
+
+    for body in [
+        json!({"vector": vector(1), "target_ms": 0}),
+        json!({"vector": vector(1), "target_ms": -3.0}),
+        json!({"vectors": [vector(1), vector(2)], "target_ms": 5.0}),
+    ] {
+        let res = client.post(format!("{base}/search")).json(&body).send().await.unwrap();
+        assert_eq!(res.status(), 400, "{body}");
+    }
+}
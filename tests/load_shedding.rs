@@ -0,0 +1,78 @@
+use axum::http::Request;
+use piramid::config::{ClassLimits, LoadSheddingConfig};
+use piramid::server::shedding::{LoadShedder, Priority};
+use std::time::Duration;
+
+fn config(interactive: usize, batch: usize, queue: usize) -> LoadSheddingConfig {
+    LoadSheddingConfig {
+        enabled: true,
+        interactive: ClassLimits { max_concurrent: interactive, max_queue: queue },
+        batch: ClassLimits { max_concurrent: batch, max_queue: queue },
+        queue_timeout_ms: 50,
+        batch_api_keys: vec!["etl-key".into()],
+    }
+}
+
+fn request(path: &str, headers: &[(&str, &str)]) -> Request<()> {
+    let mut builder = Request::builder().uri(path);
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    builder.body(()).unwrap()
+}
+
+#[test]
+fn requests_are_classified_by_key_header_and_route() {
+    let shedder = LoadShedder::new(&config(4, 1, 0));
+    let classify = |path, headers| shedder.classify(&request(path, headers));
+
+    assert_eq!(classify("/api/collections/docs/search", &[]), Some(Priority::Interactive));
+    assert_eq!(classify("/api/v1/collections/docs/index/import", &[]), Some(Priority::Batch));
+    assert_eq!(classify("/api/collections/docs/compact", &[]), Some(Priority::Batch));
+    assert_eq!(classify("/api/collections/docs/vectors", &[("x-priority", "Batch")]), Some(Priority::Batch));
+    assert_eq!(classify("/api/collections/docs/compact", &[("x-priority", "interactive")]), Some(Priority::Interactive));
+    // A batch API key cannot be upgraded by the header
+    assert_eq!(
+        classify("/api/collections/docs/search", &[("x-api-key", "etl-key"), ("x-priority", "interactive")]),
+        Some(Priority::Batch)
+    );
+
+    // Health, metrics and the dashboard are never shed
+    for path in ["/api/health", "/api/v1/readyz", "/api/metrics", "/index.html"] {
+        assert_eq!(classify(path, &[]), None, "{path}");
+    }
+}
+
+#[tokio::test]
+async fn batch_work_is_shed_first() {
+    let shedder = LoadShedder::new(&config(2, 2, 1));
+
+    let batch = shedder.admit(Priority::Batch).await.unwrap();
+    let first = shedder.admit(Priority::Interactive).await.unwrap();
+    let _second = shedder.admit(Priority::Interactive).await.unwrap();
+
+    // Interactive is saturated: new batch work is rejected even though its own class has room
+    assert!(shedder.admit(Priority::Batch).await.is_err());
+
+    // One interactive request may queue and gets the slot once it frees up
+    let waiter = {
+        let release = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(first);
+        };
+        let (admitted, _) = tokio::join!(shedder.admit(Priority::Interactive), release);
+        admitted
+    };
+    assert!(waiter.is_ok());
+
+    // Queue timeout: nothing frees up within queue_timeout_ms
+    assert!(shedder.admit(Priority::Interactive).await.is_err());
+
+    let stats = shedder.stats();
+    assert_eq!(stats.interactive.in_flight, 2);
+    assert_eq!(stats.interactive.shed_total, 1);
+    assert_eq!(stats.batch.in_flight, 1);
+    assert_eq!(stats.batch.shed_total, 1);
+    drop(batch);
+    assert_eq!(shedder.stats().batch.in_flight, 0);
+}
@@ -0,0 +1,81 @@
+use parking_lot::RwLock;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document, Filter, Metric, SearchParams};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn metadata_updates_and_checkpoints_run_beside_searches() {
+    let dir = TestDir::new("locking_shared");
+    let path = dir.path("docs.db");
+
+    let mut storage = Collection::open(&path).unwrap();
+    let ids: Vec<_> = (0..20)
+        .map(|i| {
+            let angle = i as f32 * 0.3;
+            storage.insert(Document::new(vec![angle.cos(), angle.sin(), 0.5], format!("doc {i}"))).unwrap()
+        })
+        .collect();
+    let collection = Arc::new(RwLock::new(storage));
+
+    // A search holds the collection lock shared for as long as it runs
+    let search_guard = collection.read();
+
+    // Another reader updates metadata and checkpoints without waiting for that search to finish
+    let (done_tx, done_rx) = mpsc::channel();
+    let writer = {
+        let collection = collection.clone();
+        let target = ids[7];
+        thread::spawn(move || {
+            let storage = collection.read();
+            assert!(storage.update_metadata(&target, metadata([("tier", "gold".into())])).unwrap());
+            storage.checkpoint().unwrap();
+            storage.flush().unwrap();
+            done_tx.send(()).unwrap();
+        })
+    };
+    done_rx.recv_timeout(Duration::from_secs(10)).expect("metadata update blocked behind a search");
+    writer.join().unwrap();
+
+    // The search still in progress sees the new version
+    let filter = Filter::new().eq("tier", "gold");
+    let params = SearchParams { filter: Some(&filter), ..SearchParams::default() };
+    let hits = search_guard.search(&[1.0, 0.0, 0.5], 5, Metric::Cosine, params);
+    assert_eq!(hits.iter().map(|h| h.id).collect::<Vec<_>>(), vec![ids[7]]);
+    assert_eq!(search_guard.count(), 20);
+    drop(search_guard);
+
+    drop(collection);
+}
+
+#[test]
+fn metadata_update_keeps_vector_and_client_id_across_reopen() {
+    let dir = TestDir::new("locking_reopen");
+    let path = dir.path("docs.db");
+
+    let id;
+    {
+        let mut storage = Collection::open(&path).unwrap();
+        id = storage.insert(Document::new(vec![0.0, 1.0, 0.0], "kept".into()).with_external_id("ext-1")).unwrap();
+        storage.insert(Document::new(vec![1.0, 0.0, 0.0], "other".into())).unwrap();
+
+        let storage = &storage;
+        assert!(storage.update_metadata(&id, metadata([("tag", "v2".into())])).unwrap());
+        assert!(!storage.update_metadata(&uuid::Uuid::new_v4(), metadata([("tag", "x".into())])).unwrap());
+        assert_eq!(storage.resolve_id("ext-1"), Some(id));
+    }
+
+    // Reopen replays the WAL: the new metadata is there and the document is otherwise unchanged
+    let storage = Collection::open(&path).unwrap();
+    assert_eq!(storage.count(), 2);
+    let doc = storage.get(&id).unwrap();
+    assert_eq!(doc.text, "kept");
+    assert_eq!(doc.external_id(), Some("ext-1"));
+    assert_eq!(doc.metadata.get("tag").and_then(|v| v.as_string()), Some("v2"));
+    let hits = storage.search(&[0.0, 1.0, 0.0], 1, Metric::Cosine, SearchParams::default());
+    assert_eq!(hits[0].id, id);
+
+    drop(storage);
+}
@@ -5,13 +5,13 @@ fn metadata_helper_builds_map() {
     let meta = metadata([
         ("name", "test".into()),
         ("count", 42i64.into()),
-        ("score", 3.14f64.into()),
+        ("score", 2.5f64.into()),
         ("active", true.into()),
     ]);
 
     assert_eq!(meta.get("name").unwrap().as_string(), Some("test"));
     assert_eq!(meta.get("count").unwrap().as_integer(), Some(42));
-    assert!((meta.get("score").unwrap().as_float().unwrap() - 3.14).abs() < 1e-6);
+    assert!((meta.get("score").unwrap().as_float().unwrap() - 2.5).abs() < 1e-6);
     assert_eq!(meta.get("active").unwrap().as_boolean(), Some(true));
 }
 
@@ -20,6 +20,6 @@ fn metadata_value_from_impls() {
     let _: MetadataValue = "hello".into();
     let _: MetadataValue = String::from("world").into();
     let _: MetadataValue = 42i64.into();
-    let _: MetadataValue = 3.14f64.into();
+    let _: MetadataValue = 2.5f64.into();
     let _: MetadataValue = true.into();
 }
@@ -0,0 +1,198 @@
+mod common;
+
+use piramid::config::MetadataIndexConfig;
+use piramid::search::query::{MetadataPostings, RoaringBitmap};
+use piramid::testing::TestDir;
+use piramid::{metadata, Metadata, MetadataValue};
+use piramid::{Collection, CollectionConfig, Document, Filter, Metric, SearchParams};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+#[test]
+fn bitmaps_agree_with_sets_across_container_kinds() {
+    let mut rng = StdRng::seed_from_u64(7);
+    // Dense chunks (bitsets), sparse ones (arrays) and ids spread over several chunks
+    let draw = |rng: &mut StdRng, n: usize, spread: u32| (0..n).map(|_| rng.gen_range(0..spread)).collect::<BTreeSet<u32>>();
+    let (a, b) = (draw(&mut rng, 30_000, 100_000), draw(&mut rng, 3_000, 300_000));
+    let (ra, rb): (RoaringBitmap, RoaringBitmap) = (a.iter().copied().collect(), b.iter().copied().collect());
+    assert_eq!(ra.len(), a.len() as u64);
+    let ids = |bitmap: &RoaringBitmap| bitmap.iter().collect::<Vec<_>>();
+    assert_eq!(ids(&ra), a.iter().copied().collect::<Vec<_>>());
+    assert_eq!(ids(&(&ra & &rb)), a.intersection(&b).copied().collect::<Vec<_>>());
+    assert_eq!(ids(&(&ra | &rb)), a.union(&b).copied().collect::<Vec<_>>());
+    assert_eq!(ids(&(&ra - &rb)), a.difference(&b).copied().collect::<Vec<_>>());
+    assert_eq!(ids(&(&rb - &ra)), b.difference(&a).copied().collect::<Vec<_>>());
+
+    // Removing most of a dense chunk keeps the ids that are left
+    let mut shrinking = ra.clone();
+    for id in a.iter().copied().filter(|id| id % 10 != 0) {
+        assert!(shrinking.remove(id));
+    }
+    assert!(!shrinking.remove(1));
+    assert_eq!(ids(&shrinking), a.iter().copied().filter(|id| id % 10 == 0).collect::<Vec<_>>());
+    assert!(shrinking.contains(*a.iter().find(|id| *id % 10 == 0).unwrap()));
+    assert!((&ra & &RoaringBitmap::new()).is_empty());
+}
+
+fn random_metadata(rng: &mut StdRng) -> Metadata {
+    let mut meta = metadata([
+        ("color", ["red", "green", "blue"][rng.gen_range(0..3)].into()),
+        ("size", MetadataValue::Integer(rng.gen_range(0..20))),
+        ("price", MetadataValue::Float(rng.gen_range(0..100) as f64 / 4.0)),
+    ]);
+    // Some documents lack a field, some hold an array, some a field that is not indexed
+    if rng.gen_bool(0.2) {
+        meta.remove("color");
+    }
+    if rng.gen_bool(0.1) {
+        meta.insert("size".into(), MetadataValue::Array(vec![MetadataValue::Integer(3)]));
+    }
+    meta.insert("note".into(), MetadataValue::Boolean(rng.gen_bool(0.5)));
+    // Tags: a few of four, now and then a single one as a plain string
+    let tags: Vec<MetadataValue> = ["a", "b", "c", "d"].into_iter().filter(|_| rng.gen_bool(0.4)).map(Into::into).collect();
+    match rng.gen_range(0..10) {
+        0 => {}
+        1 => {
+            meta.insert("tags".into(), "b".into());
+        }
+        _ => {
+            meta.insert("tags".into(), MetadataValue::Array(tags));
+        }
+    }
+    meta
+}
+
+fn filters() -> Vec<Filter> {
+    vec![
+        Filter::new().eq("color", "red"),
+        Filter::new().ne("color", "red").gte("size", 10i64),
+        Filter::new().is_in("color", vec!["green".into(), "blue".into()]).lt("price", 7.5),
+        Filter::new().or(vec![Filter::new().eq("color", "blue").gt("size", 15i64), Filter::new().lte("price", 1.0)]),
+        Filter::new().not(Filter::new().or(vec![Filter::new().eq("color", "green"), Filter::new().gt("size", 2.5)])),
+        Filter::new().gt("size", 100i64),
+        Filter::new().any_in("tags", vec!["a".into(), "c".into()]),
+        Filter::new().all_in("tags", vec!["b".into(), "a".into()]).eq("color", "red"),
+        Filter::new().any_in("size", vec![MetadataValue::Integer(3), MetadataValue::Integer(4)]),
+        Filter::new().not(Filter::new().any_in("tags", vec!["d".into()])),
+        Filter::new().all_in("tags", vec![]),
+        Filter::new().any_in("tags", vec![]),
+        Filter::new(),
+    ]
+}
+
+#[test]
+fn postings_match_the_filter_through_updates_and_removals() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let mut postings = MetadataPostings::new(&["color".into(), "size".into(), "price".into(), "tags".into()]);
+    let mut docs: Vec<(Uuid, Metadata)> = (0..2_000).map(|_| (Uuid::new_v4(), random_metadata(&mut rng))).collect();
+    for (id, meta) in &docs {
+        postings.insert(*id, None, meta);
+    }
+    for round in 0..3 {
+        for filter in filters() {
+            let expected: BTreeSet<Uuid> = docs.iter().filter(|(_, m)| filter.matches(m)).map(|(id, _)| *id).collect();
+            let matches = postings.matches(&filter, usize::MAX).unwrap();
+            assert_eq!(matches.count, expected.len(), "round {round}: {filter:?}");
+            assert_eq!(matches.ids.unwrap().into_iter().collect::<BTreeSet<_>>(), expected);
+            assert!(postings.matches(&filter, matches.count.saturating_sub(1)).unwrap().ids.is_none() || matches.count == 0);
+        }
+        // Rewrite a third of the documents and drop a tenth
+        for (id, meta) in docs.iter_mut().step_by(3) {
+            let new = random_metadata(&mut rng);
+            postings.insert(*id, Some(meta), &new);
+            *meta = new;
+        }
+        for (id, meta) in docs.iter().step_by(10) {
+            postings.remove(id, meta);
+        }
+        docs = docs.into_iter().enumerate().filter(|(i, _)| i % 10 != 0).map(|(_, doc)| doc).collect();
+    }
+    assert_eq!(postings.len(), docs.len());
+
+    // Unindexed fields and array operands fall back to per-document matching
+    assert!(postings.matches(&Filter::new().eq("note", true), 10).is_none());
+    assert!(postings.matches(&Filter::new().or(vec![Filter::new().eq("color", "red"), Filter::new().eq("note", true)]), 10).is_none());
+    assert!(postings.matches(&Filter::new().eq("size", MetadataValue::Array(vec![])), 10).is_none());
+    assert!(postings.matches(&Filter::new().all_in("tags", vec!["a".into(), MetadataValue::Array(vec![])]), 10).is_none());
+}
+
+fn open(dir: &str, indexed: bool) -> Collection {
+    let mut config = CollectionConfig::default();
+    if indexed {
+        config = config.with_metadata_index(MetadataIndexConfig::with_fields(["color", "size", "price", "tags"]));
+    }
+    Collection::open_with_options(&format!("{dir}/docs.db"), config.into()).unwrap()
+}
+
+#[test]
+fn indexed_collections_return_the_same_hits() {
+    let (plain_dir, indexed_dir) = (TestDir::new("metadata_index_plain"), TestDir::new("metadata_index_indexed"));
+    let (plain_dir, indexed_dir) = (plain_dir.data_dir(), indexed_dir.data_dir());
+    let mut plain = open(plain_dir, false);
+    let mut indexed = open(indexed_dir, true);
+    let mut rng = StdRng::seed_from_u64(5);
+    let docs: Vec<Document> = (0..400)
+        .map(|i| {
+            let vector = common::wave(i, 16, 0.37);
+            Document::with_metadata(vector, format!("doc {i}"), random_metadata(&mut rng))
+        })
+        .collect();
+    let ids = plain.insert_batch(docs.clone()).unwrap();
+    indexed.insert_batch(docs.clone()).unwrap();
+    // Same ids in both, so later updates and deletes can address them alike
+    let indexed_ids: Vec<Uuid> = ids.iter().map(|id| indexed.get_all().iter().find(|d| d.text == plain.get(id).unwrap().text).unwrap().id).collect();
+
+    // With the filter evaluated up front the indexed collection scores every match exactly; the plain
+    // one post-filters index candidates, so it can only fall short of that
+    let cosine = |a: &[f32], b: &[f32]| {
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        dot / (a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt())
+    };
+    let check = |plain: &Collection, indexed: &Collection| {
+        let all = indexed.get_all();
+        for filter in filters() {
+            for query in docs.iter().step_by(50).map(|doc| doc.get_vector()) {
+                let mut exact: Vec<f32> = all.iter()
+                    .filter(|doc| filter.matches(&doc.metadata))
+                    .map(|doc| cosine(&query, &doc.get_vector()))
+                    .collect();
+                exact.sort_by(|a, b| b.total_cmp(a));
+                exact.truncate(5);
+                // Scores rather than ids: this data has exact ties
+                let params = SearchParams { filter: Some(&filter), ..Default::default() };
+                let hits = indexed.search(&query, 5, Metric::Cosine, params);
+                assert!(hits.iter().all(|hit| filter.matches(&hit.metadata)));
+                assert_eq!(hits.len(), exact.len(), "{filter:?}");
+                for (hit, score) in hits.iter().zip(&exact) {
+                    assert!((hit.score - score).abs() < 1e-4, "{filter:?}: {} vs {score}", hit.score);
+                }
+                assert!(plain.search(&query, 5, Metric::Cosine, params).len() <= exact.len());
+            }
+        }
+    };
+    check(&plain, &indexed);
+
+    for i in (0..400).step_by(7) {
+        let meta = metadata([("color", "red".into()), ("size", MetadataValue::Integer(19))]);
+        plain.update_metadata(&ids[i], meta.clone()).unwrap();
+        indexed.update_metadata(&indexed_ids[i], meta).unwrap();
+    }
+    for i in (0..400).step_by(9) {
+        plain.delete(&ids[i]).unwrap();
+        indexed.delete(&indexed_ids[i]).unwrap();
+    }
+    check(&plain, &indexed);
+
+    // The posting lists are rebuilt on reopen
+    indexed.checkpoint().unwrap();
+    drop(indexed);
+    let config = CollectionConfig::default().with_metadata_index(MetadataIndexConfig::with_fields(["color", "size", "price", "tags"]));
+    let indexed = Collection::open_with_options(&format!("{indexed_dir}/docs.db"), config.into()).unwrap();
+    check(&plain, &indexed);
+    let filter = Filter::new().eq("color", "red").eq("size", 19i64);
+    let matches = indexed.filter_matches(&filter, usize::MAX).unwrap();
+    assert_eq!(matches.count, indexed.get_all().iter().filter(|doc| filter.matches(&doc.metadata)).count());
+    assert!(matches.count >= (0..400).step_by(7).filter(|i| i % 9 != 0).count());
+}
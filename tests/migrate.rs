@@ -0,0 +1,199 @@
+mod common;
+
+use arrow_array::builder::{Float64Builder, LargeListBuilder};
+use arrow_array::types::UInt32Type;
+use arrow_array::{DictionaryArray, LargeStringArray, RecordBatch, StringArray, TimestampMicrosecondArray};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use piramid::config::AppConfig;
+use piramid::jobs::JobState;
+use piramid::metadata::MetadataValue;
+use piramid::server::state::AppState;
+use piramid::storage::collection::{import_documents, ExportFormat, FieldMapping, SourceFormat};
+use piramid::CollectionConfig;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document};
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn vector(i: usize) -> Vec<f32> {
+    common::counting_vector(i, 4)
+}
+
+fn seed(path: &str, n: usize) -> Vec<uuid::Uuid> {
+    let mut storage = Collection::open(path).unwrap();
+    let docs = (0..n)
+        .map(|i| Document::with_metadata(vector(i), format!("doc {i}"), metadata([("n", (i as i64).into())])))
+        .collect();
+    let ids = storage.insert_batch(docs).unwrap();
+    storage.checkpoint().unwrap();
+    ids
+}
+
+fn close(a: &[f32], b: &[f32]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < 0.05)
+}
+
+#[test]
+fn exports_and_vector_database_dumps_import_as_documents() {
+    let dir = TestDir::new("migrate_files");
+    let ids = seed(&dir.path("source.db"), 25);
+    let source = Collection::open(&dir.path("source.db")).unwrap();
+
+    // Our own exports come back with the same ids, vectors, text and metadata
+    for format in [ExportFormat::Parquet, ExportFormat::Arrow] {
+        let file = dir.path(&format!("docs.{}", format.extension()));
+        source.export(fs::File::create(&file).unwrap(), format, None).unwrap();
+        let mut target = Collection::open(&dir.path(&format!("{}.db", format.extension()))).unwrap();
+        let mut calls = Vec::new();
+        let from = SourceFormat::from_path(&file).unwrap();
+        let report = import_documents(&mut target, &file, from, &FieldMapping::default(), 10, |done, total| {
+            calls.push((done, total))
+        })
+        .unwrap();
+        assert_eq!((report.rows, report.inserted, report.updated), (25, 25, 0));
+        assert_eq!(calls, vec![(10, Some(25)), (20, Some(25)), (25, Some(25))]);
+        for (i, id) in ids.iter().enumerate() {
+            let doc = target.get(id).unwrap();
+            assert!(close(&doc.get_vector(), &vector(i)), "{format:?} doc {i}");
+            assert_eq!(doc.text, format!("doc {i}"));
+            assert_eq!(doc.metadata.get("n"), Some(&MetadataValue::Integer(i as i64)));
+        }
+    }
+
+    // Qdrant points: string ids become external ids, so importing twice updates in place
+    let qdrant = dir.path("points.jsonl");
+    let points: Vec<String> = (0..3)
+        .map(|i| json!({"id": format!("p{i}"), "vector": vector(i), "payload": {"body": format!("point {i}"), "tag": "q"}}).to_string())
+        .collect();
+    fs::write(&qdrant, points.join("\n")).unwrap();
+    let mapping = FieldMapping { text: Some("body".into()), ..Default::default() };
+    let mut target = Collection::open(&dir.path("qdrant.db")).unwrap();
+    let report = import_documents(&mut target, &qdrant, SourceFormat::Qdrant, &mapping, 2, |_, _| {}).unwrap();
+    assert_eq!((report.rows, report.inserted), (3, 3));
+    let report = import_documents(&mut target, &qdrant, SourceFormat::Qdrant, &mapping, 2, |_, _| {}).unwrap();
+    assert_eq!((report.inserted, report.updated), (0, 3));
+    assert_eq!(target.count(), 3);
+    let doc = target.get(&target.resolve_id("p2").unwrap()).unwrap();
+    assert_eq!(doc.text, "point 2");
+    assert_eq!(doc.metadata.get("tag"), Some(&MetadataValue::String("q".into())));
+    assert!(!doc.metadata.contains_key("body"));
+
+    // A Chroma get() page, keeping only the listed metadata fields
+    let chroma = dir.path("chroma.json");
+    let page = json!({
+        "ids": ["a", "b"],
+        "embeddings": [vector(0), vector(1)],
+        "documents": ["first", "second"],
+        "metadatas": [{"lang": "en", "drop": 1}, null],
+    });
+    fs::write(&chroma, page.to_string()).unwrap();
+    let mapping = FieldMapping { fields: Some(vec!["lang".into()]), ..Default::default() };
+    let mut target = Collection::open(&dir.path("chroma.db")).unwrap();
+    let report = import_documents(&mut target, &chroma, SourceFormat::Chroma, &mapping, 10, |_, _| {}).unwrap();
+    assert_eq!(report.inserted, 2);
+    let a = target.get(&target.resolve_id("a").unwrap()).unwrap();
+    assert_eq!((a.text.as_str(), a.metadata.get("lang")), ("first", Some(&MetadataValue::String("en".into()))));
+    assert!(!a.metadata.contains_key("drop"));
+    assert!(close(&target.get(&target.resolve_id("b").unwrap()).unwrap().get_vector(), &vector(1)));
+
+    // A mapping naming a column the file does not have is refused up front
+    let mapping = FieldMapping { text: Some("missing".into()), ..Default::default() };
+    assert!(import_documents(&mut target, &dir.path("docs.parquet"), SourceFormat::Parquet, &mapping, 10, |_, _| {}).is_err());
+}
+
+// What Polars and pyarrow write by default: ZSTD (or Snappy, GZIP) pages, dictionary encoded
+// strings, large lists of doubles, categoricals and timestamps
+#[test]
+fn compressed_parquet_from_other_writers_imports() {
+    let dir = TestDir::new("migrate_compressed");
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("id", DataType::LargeUtf8, true),
+        Field::new("vector", DataType::LargeList(Arc::new(Field::new("item", DataType::Float64, true))), true),
+        Field::new("text", DataType::Utf8, true),
+        Field::new("tag", DataType::Dictionary(Box::new(DataType::UInt32), Box::new(DataType::Utf8)), true),
+        Field::new("at", DataType::Timestamp(TimeUnit::Microsecond, None), true),
+    ]));
+    let mut vectors = LargeListBuilder::new(Float64Builder::new());
+    for i in 0..5 {
+        vectors.values().append_slice(&vector(i).iter().map(|&v| v as f64).collect::<Vec<_>>());
+        vectors.append(true);
+    }
+    let batch = RecordBatch::try_new(schema.clone(), vec![
+        Arc::new(LargeStringArray::from_iter_values((0..5).map(|i| format!("row-{i}")))),
+        Arc::new(vectors.finish()),
+        Arc::new(StringArray::from_iter_values((0..5).map(|i| format!("text {i}")))),
+        Arc::new((0..5).map(|i| if i % 2 == 0 { "even" } else { "odd" }).collect::<DictionaryArray<UInt32Type>>()),
+        Arc::new(TimestampMicrosecondArray::from_iter_values((0..5).map(|i| 1_700_000_000_000_000 + i))),
+    ])
+    .unwrap();
+
+    for (name, compression) in [
+        ("zstd", Compression::ZSTD(ZstdLevel::default())),
+        ("snappy", Compression::SNAPPY),
+        ("gzip", Compression::GZIP(GzipLevel::default())),
+    ] {
+        let file = dir.path(&format!("{name}.parquet"));
+        let properties = WriterProperties::builder().set_compression(compression).build();
+        let mut writer = ArrowWriter::try_new(fs::File::create(&file).unwrap(), schema.clone(), Some(properties)).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let mut target = dir.open(name, CollectionConfig::default()).unwrap();
+        let report = import_documents(&mut target, &file, SourceFormat::Parquet, &FieldMapping::default(), 10, |_, _| {}).unwrap();
+        assert_eq!((report.rows, report.inserted, report.skipped_columns.len()), (5, 5, 0), "{name}");
+        let doc = target.get(&target.resolve_id("row-3").unwrap()).unwrap();
+        assert!(close(&doc.get_vector(), &vector(3)), "{name}");
+        assert_eq!(doc.text, "text 3");
+        assert_eq!(doc.metadata.get("tag"), Some(&MetadataValue::String("odd".into())));
+        assert_eq!(doc.metadata.get("at"), Some(&MetadataValue::Integer(1_700_000_000_000_003)));
+    }
+}
+
+#[tokio::test]
+async fn import_endpoint_runs_a_job() {
+    let dir = TestDir::new("migrate_http");
+    let data_dir = dir.data_dir();
+    let ids = seed(&format!("{data_dir}/source.db"), 30);
+    let file = format!("{data_dir}/docs.parquet");
+    Collection::open(&format!("{data_dir}/source.db")).unwrap()
+        .export(fs::File::create(&file).unwrap(), ExportFormat::Parquet, None).unwrap();
+
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    state.discover_collections().unwrap();
+    let base = common::serve(state).await;
+    let client = reqwest::Client::new();
+
+    let res = client.post(format!("{base}/collections/copy/import"))
+        .json(&json!({"path": file, "batch_size": 8}))
+        .send().await.unwrap();
+    assert!(res.status().is_success());
+    let job: Value = res.json().await.unwrap();
+    assert_eq!(job["kind"], "import_documents");
+    let id = job["id"].as_str().unwrap().to_string();
+
+    let mut job = Value::Null;
+    for _ in 0..200 {
+        job = client.get(format!("{base}/jobs/{id}")).send().await.unwrap().json().await.unwrap();
+        if job["state"] == "completed" || job["state"] == "failed" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert_eq!(job["state"], serde_json::to_value(JobState::Completed).unwrap(), "{job}");
+    assert_eq!((job["progress"]["done"].as_u64(), job["progress"]["total"].as_u64()), (Some(30), Some(30)));
+    assert_eq!((job["result"]["rows"].as_u64(), job["result"]["inserted"].as_u64()), (Some(30), Some(30)));
+
+    let res: Value = client.get(format!("{base}/collections/copy/vectors/{}", ids[7])).send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["text"], "doc 7");
+
+    // No file, and no format the extension could give
+    let res = client.post(format!("{base}/collections/copy/import")).json(&json!({"path": "/nowhere.parquet"})).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+    let res = client.post(format!("{base}/collections/copy/import")).json(&json!({"path": format!("{data_dir}/source.db")})).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+}
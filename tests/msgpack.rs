@@ -0,0 +1,145 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::msgpack;
+use piramid::server::state::AppState;
+use piramid::server::types::body::Format;
+use piramid::testing::TestDir;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Kind {
+    Plain,
+    Scaled(f64),
+    Tagged { tag: String },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Sample {
+    vector: Vec<f32>,
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped: Option<u8>,
+    counts: Vec<i64>,
+    kinds: Vec<Kind>,
+    metadata: HashMap<String, Value>,
+}
+
+#[test]
+fn codec_round_trips_and_packs_floats_tightly() {
+    let sample = Sample {
+        vector: vec![0.25, -1.5, f32::MAX, f32::MIN_POSITIVE],
+        text: Some("x".repeat(300)),
+        skipped: None,
+        counts: vec![0, 127, 128, 255, 65_536, -1, -32, -33, -129, -40_000, i64::MIN, i64::MAX],
+        kinds: vec![Kind::Plain, Kind::Scaled(2.5), Kind::Tagged { tag: "t".into() }],
+        metadata: HashMap::from([
+            ("nested".to_string(), json!({"a": [1, -2, 3.5, null, true], "b": "c"})),
+            ("big".to_string(), json!(u64::MAX)),
+        ]),
+    };
+    let bytes = msgpack::to_vec(&sample).unwrap();
+    assert_eq!(msgpack::from_slice::<Sample>(&bytes).unwrap(), sample);
+    let value: Value = msgpack::from_slice(&bytes).unwrap();
+    assert!(value.get("skipped").is_none());
+    assert_eq!(value["metadata"]["nested"], json!({"a": [1, -2, 3.5, null, true], "b": "c"}));
+
+    // array16 header, then a float 32 per dimension
+    let vector: Vec<f32> = (0..1536).map(|i| (i as f32 * 0.01).sin()).collect();
+    let packed = msgpack::to_vec(&vector).unwrap();
+    assert_eq!(packed.len(), 3 + 1536 * 5);
+    assert_eq!(&packed[..4], &[0xdc, 0x06, 0x00, 0xca]);
+    assert!(packed.len() * 2 < serde_json::to_vec(&vector).unwrap().len());
+    assert_eq!(msgpack::from_slice::<Vec<f32>>(&packed).unwrap(), vector);
+
+    assert!(msgpack::from_slice::<Vec<f32>>(&packed[..packed.len() - 1]).is_err());
+    assert!(msgpack::from_slice::<Vec<f32>>(&[&packed[..], &[0xc0]].concat()).is_err());
+    assert!(msgpack::from_slice::<Value>(&[0xdd, 0xff, 0xff, 0xff, 0xff]).is_err());
+    assert!(msgpack::from_slice::<Value>(&[0x91; 1000]).is_err());
+
+    assert_eq!(Format::from_accept("application/msgpack"), Format::MsgPack);
+    assert_eq!(Format::from_accept("application/json, application/x-msgpack;q=0.5"), Format::Json);
+    assert_eq!(Format::from_accept("application/vnd.msgpack, */*;q=0.1"), Format::MsgPack);
+    assert_eq!(Format::from_accept("*/*"), Format::Json);
+}
+
+#[derive(Serialize)]
+struct BatchInsert {
+    vectors: Vec<Vec<f32>>,
+    texts: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Search {
+    vector: Vec<f32>,
+    k: usize,
+}
+
+#[tokio::test]
+async fn vector_endpoints_speak_msgpack() {
+    let dir = TestDir::new("msgpack_server");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let vectors: Vec<Vec<f32>> =
+        (0..40).map(|i| common::wave(i, 1536, 0.013)).collect();
+    let insert = BatchInsert { vectors: vectors.clone(), texts: (0..40).map(|i| format!("doc {i}")).collect() };
+    let body = msgpack::to_vec(&insert).unwrap();
+    assert!(body.len() * 2 < serde_json::to_vec(&json!({"vectors": vectors})).unwrap().len());
+    let res = client
+        .post(format!("{base}/vectors"))
+        .header("content-type", msgpack::CONTENT_TYPE)
+        .header("accept", msgpack::CONTENT_TYPE)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.headers()["content-type"], msgpack::CONTENT_TYPE);
+    let inserted: Value = msgpack::from_slice(&res.bytes().await.unwrap()).unwrap();
+    assert_eq!(inserted["count"], 40);
+
+    // Same search as MessagePack and as JSON gives the same hits
+    let query = Search { vector: vectors[7].clone(), k: 3 };
+    let res = client
+        .post(format!("{base}/search"))
+        .header("content-type", "application/x-msgpack")
+        .header("accept", "application/msgpack")
+        .body(msgpack::to_vec(&query).unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.headers()["content-type"], msgpack::CONTENT_TYPE);
+    let packed: Value = msgpack::from_slice(&res.bytes().await.unwrap()).unwrap();
+    let res = client.post(format!("{base}/search")).json(&json!({"vector": vectors[7], "k": 3})).send().await.unwrap();
+    assert!(res.headers()["content-type"].to_str().unwrap().starts_with("application/json"));
+    let plain: Value = res.json().await.unwrap();
+    let ids = |v: &Value| v["results"].as_array().unwrap().iter().map(|h| h["id"].clone()).collect::<Vec<_>>();
+    assert_eq!(ids(&packed), ids(&plain));
+    assert_eq!(packed["results"][0]["text"], "doc 7");
+
+    // Stored vectors come back as float 32s (within the default quantization's error)
+    let id = packed["results"][0]["id"].as_str().unwrap();
+    let res = client.get(format!("{base}/vectors/{id}")).header("accept", "application/msgpack").send().await.unwrap();
+    #[derive(Deserialize)]
+    struct Stored {
+        vector: Vec<f32>,
+    }
+    let stored: Stored = msgpack::from_slice(&res.bytes().await.unwrap()).unwrap();
+    assert_eq!(stored.vector.len(), 1536);
+    assert!(stored.vector.iter().zip(&vectors[7]).all(|(a, b)| (a - b).abs() < 0.02));
+
+    let res = client
+        .post(format!("{base}/search"))
+        .header("content-type", msgpack::CONTENT_TYPE)
+        .body(vec![0x81, 0xa6])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+}
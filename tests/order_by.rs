@@ -0,0 +1,90 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::search::OrderBy;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document, Metric, SearchParams};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+// Four posts; "tie-a" and "tie-b" share a vector, "undated" has no date
+fn seed(path: &str) {
+    let mut storage = Collection::open(path).unwrap();
+    let post = |vector: Vec<f32>, text: &str, date: Option<&str>| match date {
+        Some(date) => Document::with_metadata(vector, text.into(), metadata([("published", date.into())])),
+        None => Document::new(vector, text.into()),
+    };
+    storage.insert(post(vec![1.0, 0.0, 0.0], "newest", Some("2026-03-01T00:00:00Z"))).unwrap();
+    storage.insert(post(vec![0.9, 0.3, 0.0], "tie-b", Some("2025-06-01T00:00:00Z"))).unwrap();
+    storage.insert(post(vec![0.9, 0.3, 0.0], "tie-a", Some("2024-01-15T00:00:00Z"))).unwrap();
+    storage.insert(post(vec![0.95, 0.1, 0.0], "undated", None)).unwrap();
+    storage.insert(post(vec![0.0, 0.0, 1.0], "unrelated", Some("2027-01-01T00:00:00Z"))).unwrap();
+    storage.checkpoint().unwrap();
+}
+
+fn texts(hits: &[piramid::Hit]) -> Vec<&str> {
+    hits.iter().map(|h| h.text.as_str()).collect()
+}
+
+#[test]
+fn hits_sort_by_field_or_break_ties_with_it() {
+    let dir = TestDir::new("order_by_files");
+    seed(&dir.path("posts.db"));
+    let storage = Collection::open(&dir.path("posts.db")).unwrap();
+    let query = [1.0, 0.0, 0.0];
+    let search = |order: &OrderBy, k| storage.search(&query, k, Metric::Cosine, SearchParams { order_by: Some(order), ..SearchParams::default() });
+
+    // The k best by similarity, in date order; the undated one last whichever way
+    let order = OrderBy { field: "published".into(), descending: false, tie_break: false, min_score: None };
+    assert_eq!(texts(&search(&order, 4)), ["tie-a", "tie-b", "newest", "undated"]);
+    let order = OrderBy { descending: true, ..order };
+    assert_eq!(texts(&search(&order, 4)), ["newest", "tie-b", "tie-a", "undated"]);
+    let order = OrderBy { min_score: Some(0.97), ..order };
+    assert_eq!(texts(&search(&order, 4)), ["newest", "undated"]);
+
+    // As a tie-breaker the score order stands and the field settles equal scores, also at the cut
+    let order = OrderBy { field: "published".into(), descending: false, tie_break: true, min_score: None };
+    assert_eq!(texts(&search(&order, 4)), ["newest", "undated", "tie-a", "tie-b"]);
+    assert_eq!(texts(&search(&order, 3)), ["newest", "undated", "tie-a"]);
+    let order = OrderBy { descending: true, ..order };
+    assert_eq!(texts(&search(&order, 3)), ["newest", "undated", "tie-b"]);
+
+    // Timestamps kept by the collection are fields like any other
+    let order = OrderBy { field: "_created_at".into(), descending: false, tie_break: false, min_score: None };
+    assert_eq!(search(&order, 5).len(), 5);
+}
+
+#[tokio::test]
+async fn search_endpoints_take_order_by() {
+    let dir = TestDir::new("order_by_http");
+    let data_dir = dir.data_dir();
+    seed(&format!("{data_dir}/posts.db"));
+
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    state.discover_collections().unwrap();
+    let base = format!("{}/collections/posts", common::serve(state).await);
+    let client = reqwest::Client::new();
+    let texts = |res: &Value| res["results"].as_array().unwrap().iter().map(|h| h["text"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+
+    let body = json!({"vector": [1.0, 0.0, 0.0], "k": 4, "order_by": {"field": "published", "descending": true}});
+    let res: Value = client.post(format!("{base}/search")).json(&body).send().await.unwrap().json().await.unwrap();
+    assert_eq!(texts(&res), ["newest", "tie-b", "tie-a", "undated"]);
+    // Cached results keep the order they were computed with, and a different order is a different entry
+    let res: Value = client.post(format!("{base}/search")).json(&body).send().await.unwrap().json().await.unwrap();
+    assert_eq!(texts(&res), ["newest", "tie-b", "tie-a", "undated"]);
+    let body = json!({"vector": [1.0, 0.0, 0.0], "k": 4, "order_by": {"field": "published"}});
+    let res: Value = client.post(format!("{base}/search")).json(&body).send().await.unwrap().json().await.unwrap();
+    assert_eq!(texts(&res), ["tie-a", "tie-b", "newest", "undated"]);
+
+    // Range search: the threshold first, then the order
+    let body = json!({"vector": [1.0, 0.0, 0.0], "min_score": 0.9, "k": 10, "order_by": {"field": "published"}});
+    let res: Value = client.post(format!("{base}/search/range")).json(&body).send().await.unwrap().json().await.unwrap();
+    assert_eq!(texts(&res), ["tie-a", "tie-b", "newest", "undated"]);
+
+    for order_by in [json!({"field": ""}), json!({"field": "published", "tie_break": true, "min_score": 0.5})] {
+        let body = json!({"vector": [1.0, 0.0, 0.0], "order_by": order_by});
+        let res = client.post(format!("{base}/search")).json(&body).send().await.unwrap();
+        assert_eq!(res.status(), 400);
+    }
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::storage::collection::{OutlierMethod, OutlierOptions, OutlierQuery};
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+// A tight cluster around the first axis plus one stored document pointing elsewhere ("odd")
+fn seed(path: &str) -> uuid::Uuid {
+    let mut storage = Collection::open(path).unwrap();
+    let mut state = 11u64;
+    let mut noise = move || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 40) as f32 / (1u64 << 24) as f32 * 0.2 - 0.1
+    };
+    let docs = (0..300)
+        .map(|i| Document::new(vec![1.0, noise(), noise(), noise()], format!("doc {i}")))
+        .collect();
+    storage.insert_batch(docs).unwrap();
+    let odd = storage.insert(Document::new(vec![0.0, 0.1, 1.0, -0.2], "odd".into()).with_external_id("odd")).unwrap();
+    storage.checkpoint().unwrap();
+    odd
+}
+
+#[test]
+fn isolated_vectors_rank_high() {
+    let dir = TestDir::new("outliers_files");
+    let path = dir.path("docs.db");
+    let odd = seed(&path);
+    let storage = Collection::open(&path).unwrap();
+
+    for method in [OutlierMethod::Knn, OutlierMethod::Centroid] {
+        let queries = [
+            OutlierQuery::Vector(vec![1.0, 0.0, 0.0, 0.0]),
+            OutlierQuery::Vector(vec![-0.3, 1.0, 0.0, 0.5]),
+            OutlierQuery::Document(odd),
+        ];
+        let opts = OutlierOptions { method, k: 5, ..Default::default() };
+        let report = storage.score_outliers(&queries, &opts).unwrap();
+        assert_eq!(report.method, method);
+        assert_eq!(report.metric, "cosine");
+        assert_eq!(report.reference_size, 200);
+        let [typical, far, stored] = &report.scores[..] else { panic!("{report:?}") };
+        assert!(typical.percentile < 50.0, "{method:?} {typical:?}");
+        assert!(far.percentile >= 99.0 && far.score < typical.score, "{method:?} {far:?}");
+        assert!(stored.percentile >= 99.0, "{method:?} {stored:?}");
+        assert_eq!((typical.id, stored.id), (None, Some(odd)));
+    }
+
+    // Too few documents for the neighbour asked for, and a document that is not there
+    let opts = OutlierOptions { k: 301, ..Default::default() };
+    assert!(storage.score_outliers(&[OutlierQuery::Vector(vec![1.0, 0.0, 0.0, 0.0])], &opts).is_err());
+    assert!(storage.score_outliers(&[OutlierQuery::Document(uuid::Uuid::new_v4())], &OutlierOptions::default()).is_err());
+}
+
+#[tokio::test]
+async fn outlier_endpoint_scores_vectors_and_documents() {
+    let dir = TestDir::new("outliers_http");
+    let data_dir = dir.data_dir();
+    let odd = seed(&format!("{data_dir}/docs.db"));
+
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    state.discover_collections().unwrap();
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let res: Value = client.post(format!("{base}/outliers"))
+        .json(&json!({"vectors": [[1.0, 0.0, 0.0, 0.0]], "ids": ["odd"], "sample_size": 50}))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["method"].as_str(), res["k"].as_u64(), res["reference_size"].as_u64()), (Some("knn"), Some(10), Some(50)));
+    let scores = res["scores"].as_array().unwrap();
+    assert!(scores[0]["id"].is_null());
+    assert_eq!(scores[1]["id"], odd.to_string());
+    assert!(scores[1]["percentile"].as_f64().unwrap() >= 99.0);
+
+    let res: Value = client.post(format!("{base}/outliers"))
+        .json(&json!({"ids": [odd.to_string()], "method": "centroid"}))
+        .send().await.unwrap().json().await.unwrap();
+    assert!(res["k"].is_null());
+    assert!(res["scores"][0]["percentile"].as_f64().unwrap() >= 99.0);
+
+    for (body, status) in [
+        (json!({}), 400),
+        (json!({"ids": ["missing"]}), 404),
+        (json!({"vectors": [[1.0, 0.0]]}), 400),
+        (json!({"vectors": [[1.0, 0.0, 0.0, 0.0]], "sample_size": 0}), 400),
+    ] {
+        let res = client.post(format!("{base}/outliers")).json(&body).send().await.unwrap();
+        assert_eq!(res.status(), status, "{body}");
+    }
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+#[test]
+fn partial_batches_write_the_valid_entries() {
+    let dir = TestDir::new("partial_batch_files");
+    let mut storage = Collection::open(&dir.path("docs.db")).unwrap();
+    storage.insert(Document::new(vec![1.0, 0.0, 0.0], "taken".into()).with_external_id("taken")).unwrap();
+
+    let outcomes = storage.insert_batch_partial(vec![
+        Document::new(vec![0.0, 1.0, 0.0], "ok".into()).with_external_id("a"),
+        Document::new(vec![0.0, 1.0], "short".into()),
+        Document::new(vec![0.0, 0.0, 1.0], "collides".into()).with_external_id("taken"),
+        Document::new(vec![0.5, 0.5, 0.0], "repeat".into()).with_external_id("a"),
+        Document::new(vec![0.0, 0.5, 0.5], "ok too".into()),
+    ]).unwrap();
+    let ok: Vec<bool> = outcomes.iter().map(Result::is_ok).collect();
+    assert_eq!(ok, [true, false, false, false, true]);
+    assert!(outcomes[1].as_ref().unwrap_err().to_string().contains("dimension mismatch"));
+    assert_eq!(storage.count(), 3);
+    assert_eq!(storage.get(outcomes[4].as_ref().unwrap()).unwrap().text, "ok too");
+
+    // Upserts replace by client id; a wrong-sized vector fails only its own entry
+    let outcomes = storage.upsert_batch_partial(vec![
+        Document::new(vec![1.0, 1.0, 0.0], "replaced".into()).with_external_id("taken"),
+        Document::new(vec![1.0], "bad".into()),
+        Document::new(vec![0.0, 1.0, 1.0], "new".into()),
+    ]).unwrap();
+    assert_eq!(outcomes.iter().map(Result::is_ok).collect::<Vec<_>>(), [true, false, true]);
+    assert_eq!(storage.count(), 4);
+    let replaced = storage.resolve_id("taken").unwrap();
+    assert_eq!((outcomes[0].as_ref().unwrap(), storage.get(&replaced).unwrap().text.as_str()), (&replaced, "replaced"));
+}
+
+#[tokio::test]
+async fn batch_endpoints_report_per_item_status() {
+    let dir = TestDir::new("partial_batch_http");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+    let count = || async {
+        let res: Value = client.get(format!("{base}/count")).send().await.unwrap().json().await.unwrap();
+        res["count"].as_u64().unwrap()
+    };
+
+    // Without allow_partial one bad vector fails the whole batch
+    let batch = json!({"vectors": [[1.0, 0.0], [0.0, 1.0], [1.0, "x"]], "texts": ["a", "b", "c"]});
+    assert_eq!(client.post(format!("{base}/vectors")).json(&batch).send().await.unwrap().status(), 422);
+    let batch = json!({"vectors": [[1.0, 0.0], [], [0.0, 1.0], [1.0, 2.0, 3.0]], "texts": ["a", "b", "c", "d"], "external_ids": ["a", "b", "c", "d"]});
+    assert_eq!(client.post(format!("{base}/vectors")).json(&batch).send().await.unwrap().status(), 400);
+
+    let mut partial = batch.clone();
+    partial["allow_partial"] = json!(true);
+    let res: Value = client.post(format!("{base}/vectors")).json(&partial).send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["succeeded"].as_u64(), res["failed"].as_u64()), (Some(2), Some(2)));
+    let results = res["results"].as_array().unwrap();
+    assert_eq!(results.iter().map(|r| r["status"].as_str().unwrap()).collect::<Vec<_>>(), ["ok", "error", "ok", "error"]);
+    assert_eq!((results[1]["index"].as_u64(), results[1]["code"].as_u64()), (Some(1), Some(400)));
+    assert!(results[3]["error"].as_str().unwrap().contains("dimension mismatch"), "{res}");
+    assert!(results[0]["id"].is_string() && results[1]["id"].is_null());
+    assert_eq!(count().await, 2);
+
+    // Upsert batches: all or nothing up to the failing item, or per item with allow_partial
+    let items = json!([{"id": "a", "vector": [0.5, 0.5], "text": "a2"}, {"id": "e", "vector": [1.0], "text": "e"}, {"id": "f", "vector": [0.0, 1.0], "text": "f"}]);
+    let res = client.post(format!("{base}/upsert")).json(&json!({"items": items})).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+    assert!(res.text().await.unwrap().contains("item 1"));
+    let res: Value = client.post(format!("{base}/upsert")).json(&json!({"items": items, "allow_partial": true})).send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["succeeded"].as_u64(), res["results"][1]["status"].as_str()), (Some(2), Some("error")));
+    assert_eq!(count().await, 3);
+    let res: Value = client.post(format!("{base}/upsert"))
+        .json(&json!({"items": [{"id": "g", "vector": [1.0, 1.0], "text": "g"}, {"id": "a", "vector": [1.0, 0.0], "text": "a3"}]}))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["ids"].as_array().unwrap().len(), res["created"].as_u64()), (2, Some(1)));
+    let res: Value = client.post(format!("{base}/upsert")).json(&json!({"id": "a", "vector": [0.0, 1.0], "text": "a4"})).send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["created"], false);
+
+    let res: Value = client.delete(format!("{base}/vectors"))
+        .json(&json!({"ids": ["a", "missing", "c", "a"], "allow_partial": true}))
+        .send().await.unwrap().json().await.unwrap();
+    let status: Vec<_> = res["results"].as_array().unwrap().iter().map(|r| (r["status"].as_str().unwrap(), r["code"].as_u64())).collect();
+    assert_eq!(status, [("ok", None), ("error", Some(404)), ("ok", None), ("error", Some(400))]);
+    assert_eq!(count().await, 2);
+}
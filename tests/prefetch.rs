@@ -0,0 +1,63 @@
+mod common;
+
+use piramid::config::{CollectionConfig, ExecutionMode};
+use piramid::storage::{prefetch_ranges, PREFETCH_GAP};
+use piramid::testing::TestDir;
+use piramid::{Document, Metric, SearchParams};
+
+fn vector(i: usize) -> Vec<f32> {
+    common::wave(i, 16, 0.29)
+}
+
+fn params() -> SearchParams<'static> {
+    SearchParams {
+        mode: ExecutionMode::default(),
+        filter: None,
+        filter_overfetch_override: None,
+        search_config_override: None,
+        dedup_by: None,
+        score_expr: None,
+        order_by: None,
+        exclude_ids: None,
+    }
+}
+
+#[test]
+fn candidate_entries_are_merged_into_ranges_in_file_order() {
+    // Out of order; the entry at 300 is within a page of the one before it
+    let entries = [(20_000, 100), (0, 200), (300, 50), (1_000_000, 10), (5_000, 100)];
+    assert_eq!(prefetch_ranges(&entries, PREFETCH_GAP), vec![(0, 350), (5_000, 100), (20_000, 100), (1_000_000, 10)]);
+    assert_eq!(prefetch_ranges(&entries, 0), vec![(0, 200), (300, 50), (5_000, 100), (20_000, 100), (1_000_000, 10)]);
+    // Overlapping or nested entries keep the furthest end
+    assert_eq!(prefetch_ranges(&[(0, 500), (100, 50)], 0), vec![(0, 500)]);
+    assert!(prefetch_ranges(&[], PREFETCH_GAP).is_empty());
+}
+
+#[test]
+fn searches_hint_their_candidates_without_changing_results() {
+    let dir = TestDir::new("prefetch_search");
+    let docs = || (0..300).map(|i| Document::new(vector(i), format!("doc {i}"))).collect::<Vec<_>>();
+    let config = |prefetch| {
+        let mut config = CollectionConfig::default();
+        config.memory.prefetch = prefetch;
+        config
+    };
+    let mut hinted = dir.open("hinted", config(true)).unwrap();
+    hinted.insert_batch(docs()).unwrap();
+    let mut plain = dir.open("plain", config(false)).unwrap();
+    plain.insert_batch(docs()).unwrap();
+
+    let query = vector(1234);
+    let texts = |hits: Vec<piramid::search::Hit>| hits.into_iter().map(|h| h.text).collect::<Vec<_>>();
+    let results = texts(hinted.search(&query, 10, Metric::Cosine, params()));
+    assert_eq!(results.len(), 10);
+    assert_eq!(results, texts(plain.search(&query, 10, Metric::Cosine, params())));
+    assert!(hinted.prefetched_ranges() > 0);
+    assert_eq!(plain.prefetched_ranges(), 0);
+
+    // Ephemeral collections have no file behind their map
+    let mut memory = piramid::testing::ephemeral_collection("prefetch_memory", config(true)).unwrap();
+    memory.insert_batch(docs()).unwrap();
+    assert_eq!(texts(memory.search(&query, 10, Metric::Cosine, params())), results);
+    assert_eq!(memory.prefetched_ranges(), 0);
+}
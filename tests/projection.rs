@@ -0,0 +1,102 @@
+use piramid::config::TwoStageConfig;
+use piramid::storage::collection::ProjectionKind;
+use piramid::testing::TestDir;
+use piramid::{Collection, CollectionConfig, Document, Metric, SearchParams};
+
+// 32-dim vectors that live (up to a little noise) in an 8-dim subspace
+fn vectors(count: usize) -> Vec<Vec<f32>> {
+    let mut state = 42u64;
+    let mut next = move || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+    };
+    let basis: Vec<Vec<f32>> = (0..8).map(|_| (0..32).map(|_| next()).collect()).collect();
+    (0..count)
+        .map(|_| {
+            let weights: Vec<f32> = (0..8).map(|_| next() * 4.0).collect();
+            (0..32)
+                .map(|d| basis.iter().zip(&weights).map(|(b, w)| b[d] * w).sum::<f32>() + next() * 0.01)
+                .collect()
+        })
+        .collect()
+}
+
+fn top_ids(storage: &Collection, query: &[f32]) -> Vec<uuid::Uuid> {
+    storage
+        .search(query, 5, Metric::Cosine, SearchParams::default())
+        .into_iter()
+        .map(|h| h.id)
+        .collect()
+}
+
+#[test]
+fn pca_projection_keeps_results_and_survives_reopen() {
+    let dir = TestDir::new("projection_pca");
+    let path = dir.path("docs.db");
+    let data = vectors(300);
+    let mut storage = Collection::open(&path).unwrap();
+    for (i, v) in data.iter().enumerate() {
+        storage.insert(Document::new(v.clone(), format!("doc {i}"))).unwrap();
+    }
+    let queries = &data[..10];
+    let before: Vec<_> = queries.iter().map(|q| top_ids(&storage, q)).collect();
+
+    let projection = storage.train_projection(ProjectionKind::Pca, 8, 200).unwrap();
+    assert_eq!((projection.input_dims, projection.output_dims, projection.sample_size), (32, 8, 200));
+    assert!(projection.explained_variance > 0.99, "{}", projection.explained_variance);
+    assert!(storage.get_vectors().values().all(|v| v.len() == 8));
+
+    // Candidates come from the projected index and are re-ranked on the full vectors
+    let after: Vec<_> = queries.iter().map(|q| top_ids(&storage, q)).collect();
+    assert_eq!(after, before);
+
+    // New writes go through the projection too
+    let id = storage.insert(Document::new(data[0].clone(), "copy".into())).unwrap();
+    assert_eq!(storage.get_vectors()[&id].len(), 8);
+    storage.checkpoint().unwrap();
+    drop(storage);
+
+    let storage = Collection::open(&path).unwrap();
+    assert_eq!(storage.projection().unwrap().output_dims, 8);
+    assert!(storage.get_vectors().values().all(|v| v.len() == 8));
+    let hits = storage.search(&data[7], 1, Metric::Cosine, SearchParams::default());
+    assert_eq!(hits[0].text, "doc 7");
+
+    drop(storage);
+}
+
+#[test]
+fn opq_training_rules_and_clearing() {
+    let dir = TestDir::new("projection_opq");
+    let path = dir.path("docs.db");
+    let data = vectors(100);
+    let mut storage = Collection::open(&path).unwrap();
+    for (i, v) in data.iter().enumerate() {
+        storage.insert(Document::new(v.clone(), format!("doc {i}"))).unwrap();
+    }
+
+    // The output has to be smaller than the input and the sample larger than the output
+    assert!(storage.train_projection(ProjectionKind::Pca, 32, 100).is_err());
+    assert!(storage.train_projection(ProjectionKind::Pca, 16, 10).is_err());
+    assert!(storage.projection().is_none());
+
+    let projection = storage.train_projection(ProjectionKind::Opq, 8, 100).unwrap();
+    assert_eq!(projection.kind, ProjectionKind::Opq);
+    let hits = storage.search(&data[3], 1, Metric::Cosine, SearchParams::default());
+    assert_eq!(hits[0].text, "doc 3");
+
+    assert!(storage.clear_projection().unwrap());
+    assert!(!storage.clear_projection().unwrap());
+    assert!(storage.get_vectors().values().all(|v| v.len() == 32));
+    assert!(!std::path::Path::new(&format!("{path}.proj.db")).exists());
+    drop(storage);
+
+    // Two-stage collections scan their own codes and cannot take a projection
+    let config = CollectionConfig::default().with_two_stage(TwoStageConfig { enabled: true, ..Default::default() });
+    let mut storage = Collection::open_with_options(&dir.path("two_stage.db"), config.into()).unwrap();
+    for (i, v) in data.iter().enumerate() {
+        storage.insert(Document::new(v.clone(), format!("doc {i}"))).unwrap();
+    }
+    assert!(storage.train_projection(ProjectionKind::Pca, 8, 100).is_err());
+    drop(storage);
+}
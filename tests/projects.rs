@@ -0,0 +1,136 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use common::vector;
+
+async fn serve(data_dir: &str) -> String {
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    common::serve(state).await
+}
+
+#[tokio::test]
+async fn projects_share_keys_and_search_defaults() {
+    let dir = TestDir::new("projects_settings");
+    let data_dir = dir.data_dir();
+    let base = serve(data_dir).await;
+    let client = reqwest::Client::new();
+
+    let project = json!({
+        "name": "team",
+        "api_keys": ["k1"],
+        "search": {"ef": 77},
+        "embedding": {"provider": "openai", "model": "text-embedding-3-small", "api_key": "sk-secret"},
+    });
+    let res = client.post(format!("{base}/projects")).json(&project).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let text = res.text().await.unwrap();
+    assert!(!text.contains("sk-secret") && !text.contains("k1"), "{text}");
+    let info: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(info["api_keys"], 1);
+    assert_eq!(info["embedding"]["model"], "text-embedding-3-small");
+    let res = client.post(format!("{base}/projects")).json(&project).send().await.unwrap();
+    assert_eq!(res.status(), 409);
+
+    // Joining the project and using its collections takes one of its keys
+    let create = json!({"name": "docs", "project": "team"});
+    let res = client.post(format!("{base}/collections")).json(&create).send().await.unwrap();
+    assert_eq!(res.status(), 401);
+    let res = client.post(format!("{base}/collections")).header("x-api-key", "nope").json(&create).send().await.unwrap();
+    assert_eq!(res.status(), 403);
+    let res = client.post(format!("{base}/collections")).header("x-api-key", "k1").json(&create).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(client.get(format!("{base}/collections/docs/tuning")).send().await.unwrap().status(), 401);
+    assert_eq!(client.get(format!("{base}/v1/projects/team")).send().await.unwrap().status(), 401);
+    assert_eq!(client.get(format!("{base}/collections/loose/tuning")).send().await.unwrap().status(), 200);
+
+    let ef = |base: String| {
+        let client = client.clone();
+        async move {
+            let res: Value = client.get(format!("{base}/collections/docs/tuning")).header("x-api-key", "k1")
+                .send().await.unwrap().json().await.unwrap();
+            res["search"]["ef"].clone()
+        }
+    };
+    assert_eq!(ef(base.clone()).await, 77);
+
+    // New settings reach the open collection, and survive a restart
+    let res = client.put(format!("{base}/projects/team")).header("x-api-key", "k1")
+        .json(&json!({"collections": ["docs"], "api_keys": ["k1"], "search": {"ef": 33}}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(ef(base.clone()).await, 33);
+
+    let base = serve(data_dir).await;
+    let info: Value = client.get(format!("{base}/projects/team")).header("x-api-key", "k1")
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(info["collections"], json!(["docs"]));
+    assert!(info["embedding"].is_null());
+    assert_eq!(ef(base.clone()).await, 33);
+
+    // Without the project the collection is open again and back on the server's settings
+    let res: Value = client.delete(format!("{base}/projects/team")).header("x-api-key", "k1")
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["deleted"], true);
+    let res = client.get(format!("{base}/collections/docs/tuning")).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert!(res.json::<Value>().await.unwrap()["search"]["ef"].is_null());
+}
+
+#[tokio::test]
+async fn project_quotas_cap_collections_and_vectors() {
+    let dir = TestDir::new("projects_quotas");
+    let data_dir = dir.data_dir();
+    let base = serve(data_dir).await;
+    let client = reqwest::Client::new();
+
+    let res = client.post(format!("{base}/projects"))
+        .json(&json!({"name": "small", "quotas": {"max_collections": 1, "max_vectors": 3}}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res = client.post(format!("{base}/collections")).json(&json!({"name": "a", "project": "small"})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res = client.post(format!("{base}/collections")).json(&json!({"name": "b", "project": "small"})).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+    let res = client.post(format!("{base}/collections")).json(&json!({"name": "b", "project": "missing"})).send().await.unwrap();
+    assert_eq!(res.status(), 404);
+
+    // A collection belongs to one project at most
+    let res = client.post(format!("{base}/projects")).json(&json!({"name": "other", "collections": ["a"]})).send().await.unwrap();
+    assert_eq!(res.status(), 409);
+
+    let res = client.post(format!("{base}/collections/a/vectors"))
+        .json(&json!({"vectors": [vector(0), vector(1)], "texts": ["a", "b"]}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res = client.post(format!("{base}/collections/a/vectors"))
+        .json(&json!({"vectors": [vector(2), vector(3)], "texts": ["c", "d"]}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 400);
+    let res = client.post(format!("{base}/collections/a/upsert"))
+        .json(&json!({"id": "last", "vector": vector(2), "text": "e"}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res = client.post(format!("{base}/collections/a/upsert"))
+        .json(&json!({"id": "one-more", "vector": vector(3), "text": "e"}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 400);
+    // Replacing a stored vector adds nothing
+    let res = client.post(format!("{base}/collections/a/upsert"))
+        .json(&json!({"id": "last", "vector": vector(4), "text": "e"}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let info: Value = client.get(format!("{base}/projects/small")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(info["vectors"], 3);
+
+    // Deleting the collection frees its slot
+    client.delete(format!("{base}/collections/a")).send().await.unwrap();
+    let info: Value = client.get(format!("{base}/projects/small")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(info["collections"], json!([]));
+    assert_eq!(info["vectors"], 0);
+    let res = client.post(format!("{base}/collections")).json(&json!({"name": "b", "project": "small"})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use piramid::config::{AppConfig, QueryCacheConfig};
+use piramid::server::query_cache::{QueryCache, Revalidation};
+use piramid::server::state::AppState;
+use piramid::server::types::HitResponse;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+fn hit(text: &str) -> HitResponse {
+    HitResponse { id: text.into(), external_id: None, score: 1.0, text: text.into(), metadata: BTreeMap::new(), band: None, rerank_score: None }
+}
+
+#[test]
+fn entries_are_keyed_on_quantized_queries_and_dropped_when_stale() {
+    let cache = QueryCache::new(&QueryCacheConfig { enabled: true, max_entries: 2, ..Default::default() });
+    let key = cache.key("docs", &[0.5, 0.25], 10, "cosine");
+    assert!(cache.get(&key, 1).is_none());
+    cache.insert(key.clone(), 1, &[hit("a")]);
+
+    // Float noise below the grid step shares the entry; a different k or option does not
+    let near = cache.key("docs", &[0.500001, 0.25], 10, "cosine");
+    assert_eq!(cache.get(&near, 1).unwrap()[0].text, "a");
+    assert!(cache.get(&cache.key("docs", &[0.5, 0.25], 5, "cosine"), 1).is_none());
+    assert!(cache.get(&cache.key("docs", &[0.5, 0.25], 10, "dot"), 1).is_none());
+
+    // A write moves the sequence on: the entry is stale and dropped
+    assert!(cache.get(&key, 2).is_none());
+    assert!(cache.get(&key, 1).is_none());
+
+    cache.insert(key.clone(), 2, &[hit("b")]);
+    cache.insert(cache.key("other", &[0.5, 0.25], 10, "cosine"), 2, &[hit("c")]);
+    cache.invalidate("docs");
+    let stats = cache.stats();
+    assert_eq!((stats.entries, stats.capacity, stats.hits, stats.misses, stats.invalidated), (1, 2, 1, 5, 1));
+    assert_eq!(stats.hit_rate, Some(1.0 / 6.0));
+
+    // Disabled, nothing is stored or counted
+    let off = QueryCache::new(&QueryCacheConfig::default());
+    let key = off.key("docs", &[0.5], 1, ());
+    off.insert(key.clone(), 1, &[hit("a")]);
+    assert!(off.get(&key, 1).is_none());
+    assert_eq!((off.stats().entries, off.stats().misses, off.stats().hit_rate), (0, 0, None));
+}
+
+#[tokio::test]
+async fn repeated_searches_hit_until_the_collection_is_written() {
+    let dir = TestDir::new("query_cache_http");
+    let data_dir = dir.data_dir();
+    {
+        let mut storage = Collection::open(&format!("{data_dir}/docs.db")).unwrap();
+        storage.insert(Document::new(vec![1.0, 0.0, 0.0], "first".into())).unwrap();
+        storage.insert(Document::new(vec![0.0, 1.0, 0.0], "second".into())).unwrap();
+        storage.checkpoint().unwrap();
+    }
+
+    let config = AppConfig { query_cache: QueryCacheConfig { enabled: true, ..Default::default() }, ..Default::default() };
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    state.discover_collections().unwrap();
+    let api = common::serve(state).await;
+    let client = reqwest::Client::new();
+    let search = || async {
+        let body = json!({"vector": [0.9, 0.1, 0.0], "k": 1});
+        let res: Value = client.post(format!("{api}/collections/docs/search")).json(&body).send().await.unwrap().json().await.unwrap();
+        res["results"][0]["text"].as_str().unwrap().to_string()
+    };
+    let stats = || async {
+        let res: Value = client.get(format!("{api}/metrics")).send().await.unwrap().json().await.unwrap();
+        res["query_cache"].clone()
+    };
+
+    assert_eq!(search().await, "first");
+    assert_eq!(search().await, "first");
+    let cache = stats().await;
+    assert_eq!((cache["hits"].as_u64(), cache["misses"].as_u64(), cache["entries"].as_u64()), (Some(1), Some(1), Some(1)));
+
+    // A closer document is inserted: the cached result is stale
+    client.post(format!("{api}/collections/docs/vectors"))
+        .json(&json!({"vector": [0.9, 0.1, 0.0], "text": "closest"}))
+        .send().await.unwrap();
+    assert_eq!(search().await, "closest");
+    assert_eq!(search().await, "closest");
+    let cache = stats().await;
+    assert_eq!((cache["hits"].as_u64(), cache["misses"].as_u64(), cache["invalidated"].as_u64()), (Some(2), Some(2), Some(1)));
+    assert_eq!(cache["hit_rate"].as_f64(), Some(0.5));
+
+    let prometheus = client.get(format!("{api}/metrics/prometheus")).send().await.unwrap().text().await.unwrap();
+    assert!(prometheus.contains("piramid_query_cache_hits_total 2"), "{prometheus}");
+}
+
+#[test]
+fn entries_within_the_seq_lag_are_served_stale_and_revalidated_once() {
+    let cache = QueryCache::new(&QueryCacheConfig { enabled: true, max_seq_lag: 2, ..Default::default() });
+    let key = cache.key("docs", &[0.5, 0.25], 10, "cosine");
+    cache.insert(key.clone(), 5, &[hit("a")]);
+
+    let current = cache.lookup(&key, 5).unwrap();
+    assert_eq!((current.results[0].text.as_str(), current.lag, current.revalidate), ("a", 0, false));
+
+    // Two writes behind: still served, and only the first lookup is asked to recompute
+    let behind = cache.lookup(&key, 7).unwrap();
+    assert_eq!((behind.lag, behind.revalidate), (2, true));
+    assert!(!cache.lookup(&key, 7).unwrap().revalidate);
+    assert_eq!(cache.get(&key, 7).unwrap()[0].text, "a");
+
+    // A revalidation finishing after a newer search does not replace it
+    cache.insert(key.clone(), 8, &[hit("b")]);
+    cache.insert(key.clone(), 7, &[hit("stale")]);
+    assert_eq!(cache.get(&key, 8).unwrap()[0].text, "b");
+
+    // Past the bound the entry is dropped
+    assert!(cache.lookup(&key, 11).is_none());
+    assert!(cache.get(&key, 8).is_none());
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.stale_hits, stats.misses, stats.invalidated), (5, 3, 2, 1));
+}
+
+#[test]
+fn failed_revalidations_hand_the_entry_back() {
+    let cache = Arc::new(QueryCache::new(&QueryCacheConfig { enabled: true, max_seq_lag: 2, ..Default::default() }));
+    let key = cache.key("docs", &[0.5, 0.25], 10, "cosine");
+    cache.insert(key.clone(), 5, &[hit("a")]);
+    assert!(cache.lookup(&key, 6).unwrap().revalidate);
+    assert!(!cache.lookup(&key, 6).unwrap().revalidate);
+
+    // The recomputing search panics before it has results
+    let claim = Revalidation::new(cache.clone(), key.clone());
+    let failed = std::thread::spawn(move || {
+        let _claim = claim;
+        panic!("search failed");
+    });
+    assert!(failed.join().is_err());
+
+    // The next lookup behind is asked again, and its results replace the old ones
+    let retry = cache.lookup(&key, 6).unwrap();
+    assert_eq!((retry.results[0].text.as_str(), retry.revalidate), ("a", true));
+    Revalidation::new(cache.clone(), key.clone()).finish(6, &[hit("b")]);
+    let fresh = cache.lookup(&key, 6).unwrap();
+    assert_eq!((fresh.results[0].text.as_str(), fresh.lag, fresh.revalidate), ("b", 0, false));
+}
+
+#[tokio::test]
+async fn searches_behind_a_write_get_stale_results_then_revalidated_ones() {
+    let dir = TestDir::new("query_cache_revalidate");
+    let data_dir = dir.data_dir();
+    {
+        let mut storage = Collection::open(&format!("{data_dir}/docs.db")).unwrap();
+        storage.insert(Document::new(vec![1.0, 0.0, 0.0], "first".into())).unwrap();
+        storage.insert(Document::new(vec![0.0, 1.0, 0.0], "second".into())).unwrap();
+        storage.checkpoint().unwrap();
+    }
+
+    let query_cache = QueryCacheConfig { enabled: true, max_seq_lag: 1, ..Default::default() };
+    let state = Arc::new(AppState::new(data_dir, AppConfig { query_cache, ..Default::default() }, 500, None, false, None));
+    state.discover_collections().unwrap();
+    let api = common::serve(state).await;
+    let client = reqwest::Client::new();
+    let search = || async {
+        let body = json!({"vector": [0.9, 0.1, 0.0], "k": 1});
+        let res: Value = client.post(format!("{api}/collections/docs/search")).json(&body).send().await.unwrap().json().await.unwrap();
+        (res["results"][0]["text"].as_str().unwrap().to_string(), res["stale"].as_bool().unwrap_or(false))
+    };
+    let insert = |vector: [f32; 3], text: &'static str| {
+        let request = client.post(format!("{api}/collections/docs/vectors")).json(&json!({"vector": vector, "text": text}));
+        async move { assert!(request.send().await.unwrap().status().is_success()) }
+    };
+
+    assert_eq!(search().await, ("first".to_string(), false));
+    insert([0.85, 0.15, 0.0], "closer").await;
+    // One write behind: the old results come back flagged, while they are recomputed
+    assert_eq!(search().await, ("first".to_string(), true));
+    let mut revalidated = search().await;
+    for _ in 0..100 {
+        if !revalidated.1 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        revalidated = search().await;
+    }
+    assert_eq!(revalidated, ("closer".to_string(), false));
+
+    // Two writes behind is past the bound: the search runs again
+    insert([0.9, 0.1, 0.0], "closest").await;
+    insert([0.0, 0.0, 1.0], "far").await;
+    assert_eq!(search().await, ("closest".to_string(), false));
+    let metrics: Value = client.get(format!("{api}/metrics")).send().await.unwrap().json().await.unwrap();
+    assert!(metrics["query_cache"]["stale_hits"].as_u64().unwrap() >= 1);
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use piramid::config::{AppConfig, QueryLogConfig};
+use piramid::server::query_log::{LoggedHit, LoggedQuery, QueryKind, QueryLog};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document, Metric};
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::Arc;
+
+fn query(vector: Vec<f32>, hit: &str) -> LoggedQuery {
+    LoggedQuery {
+        kind: QueryKind::Search,
+        vector: Some(vector),
+        k: 5,
+        metric: Metric::Cosine,
+        results: vec![LoggedHit { id: hit.into(), external_id: None, score: 0.9 }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn feedback_is_joined_to_queries_across_rotations() {
+    let dir = TestDir::new("query_log_rotation");
+    let data_dir = dir.data_dir();
+    let config = QueryLogConfig { enabled: true, store_queries: false, max_file_bytes: 4096 };
+    let log = QueryLog::open(data_dir, &config);
+
+    let first = log.record("docs", query(vec![1.0, 0.0], "a")).unwrap();
+    log.feedback("docs", &first, vec!["a".into()]).unwrap();
+    // Enough queries to rotate the file at least once; the first one's feedback comes last
+    let ids: Vec<String> = (0..40).map(|i| log.record("docs", query(vec![i as f32; 16], "b")).unwrap()).collect();
+    assert!(fs::metadata(format!("{data_dir}/query_log/docs.1.jsonl")).is_ok());
+    log.feedback("docs", &ids[39], vec!["b".into(), "c".into(), "b".into()]).unwrap();
+
+    let exported = log.export("docs", 0).unwrap();
+    let last = exported.last().unwrap();
+    assert_eq!((last.query.query_id.as_str(), last.clicked.clone()), (ids[39].as_str(), vec!["b".to_string(), "c".to_string()]));
+    // Without store_queries only the hash of the vector is kept
+    assert!(last.query.vector.is_none() && !last.query.query_hash.is_empty());
+    assert!(exported.len() < 41, "the oldest lines are rotated out");
+    assert!(exported.iter().all(|q| q.query.query_id == ids[39] || q.clicked.is_empty()));
+
+    // Feedback needs a query id, and clicks; a later `since` leaves older queries out
+    assert!(log.feedback("docs", "not-an-id", vec!["a".into()]).is_err());
+    assert!(log.feedback("docs", &first, vec![]).is_err());
+    assert!(log.export("docs", last.query.at + 1).unwrap().is_empty());
+
+    log.rename("docs", "articles");
+    assert!(log.export("docs", 0).unwrap().is_empty());
+    assert!(log.clear("articles") && !log.clear("articles"));
+
+    let disabled = QueryLog::open(data_dir, &QueryLogConfig::default());
+    assert!(disabled.record("docs", query(vec![1.0], "a")).is_none());
+    assert!(disabled.feedback("docs", &first, vec!["a".into()]).is_err());
+}
+
+#[tokio::test]
+async fn searches_are_logged_and_exported_with_clicks() {
+    let dir = TestDir::new("query_log_api");
+    let data_dir = dir.data_dir();
+    {
+        let mut storage = Collection::open(&format!("{data_dir}/docs.db")).unwrap();
+        let doc = |vector: Vec<f32>, lang: &str| Document::with_metadata(vector, lang.into(), metadata([("lang", lang.into())]));
+        storage.insert(doc(vec![1.0, 0.0, 0.0], "en")).unwrap();
+        storage.insert(doc(vec![0.9, 0.1, 0.0], "de")).unwrap();
+        storage.insert(doc(vec![0.0, 1.0, 0.0], "en")).unwrap();
+        storage.checkpoint().unwrap();
+    }
+    let mut config = AppConfig::default();
+    config.query_log.enabled = true;
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+    let post = |path: &str, body: Value| client.post(format!("{base}/{path}")).json(&body).send();
+
+    let res: Value = post("search", json!({"vector": [1.0, 0.0, 0.0], "k": 2, "exclude_filter": {"lang": "de"}})).await.unwrap().json().await.unwrap();
+    let query_id = res["query_id"].as_str().unwrap().to_string();
+    let clicked = res["results"][0]["id"].as_str().unwrap().to_string();
+    let res = post(&format!("queries/{query_id}/feedback"), json!({"clicked": [clicked]})).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res: Value = post("search", json!({"vectors": [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]], "k": 1})).await.unwrap().json().await.unwrap();
+    assert_eq!(res["query_ids"].as_array().map(Vec::len), Some(2), "{res}");
+    let res: Value = post("search/range", json!({"vector": [0.0, 1.0, 0.0], "min_score": 0.5})).await.unwrap().json().await.unwrap();
+    assert!(res["query_id"].is_string());
+
+    let res = client.get(format!("{base}/queries/export")).send().await.unwrap();
+    assert_eq!(res.headers()["content-type"], "application/x-ndjson");
+    let lines: Vec<Value> = res.text().await.unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    let kinds: Vec<&str> = lines.iter().map(|l| l["kind"].as_str().unwrap()).collect();
+    assert_eq!(kinds, ["search", "batch_search", "batch_search", "range_search"]);
+    let first = &lines[0];
+    assert_eq!((first["query_id"].as_str(), first["k"].as_u64()), (Some(query_id.as_str()), Some(2)));
+    assert_eq!((first["vector"].clone(), first["options"]["exclude_filter"].clone()), (json!([1.0, 0.0, 0.0]), json!({"lang": "de"})));
+    assert_eq!(first["results"].as_array().unwrap().len(), 2);
+    assert_eq!(first["clicked"], json!([clicked]));
+    assert_eq!(lines[3]["options"]["min_score"], 0.5);
+
+    let res: Value = client.delete(format!("{base}/queries")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["cleared"], true);
+    assert!(client.get(format!("{base}/queries/export")).send().await.unwrap().text().await.unwrap().is_empty());
+}
@@ -0,0 +1,86 @@
+use piramid::index::{HnswConfig, HnswIndex, IndexType};
+use piramid::testing::{run_recall_check, RecallCheck, RecallGrid, SyntheticDataset};
+use piramid::Metric;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+fn check(metric: Metric) -> RecallCheck {
+    let dataset = SyntheticDataset { count: 800, dimensions: 16, clusters: 10, spread: 0.15, queries: 20 };
+    let mut grid = RecallGrid::standard(dataset.count, metric);
+    grid.ef = vec![32, 64];
+    grid.nprobe = vec![8, 16];
+    RecallCheck { dataset, grid, seeds: vec![1, 2], k: 10, floor: 0.9, metric }
+}
+
+#[test]
+fn every_index_clears_the_floor_on_clustered_data() {
+    for metric in [Metric::Cosine, Metric::Euclidean] {
+        let report = run_recall_check(&check(metric)).unwrap();
+        report.assert_passed();
+        assert!(report.passed);
+        let cases: Vec<&str> = report.points.iter().map(|p| p.case.as_str()).collect();
+        assert_eq!(cases.len(), 5, "{cases:?}");
+        assert!(cases[0].starts_with("hnsw") && cases[0].ends_with("ef=32"), "{cases:?}");
+        // Clusters sit apart, so a graph that only links within them would miss whole ones
+        let hnsw = report.points.iter().filter(|p| p.index_type == IndexType::Hnsw);
+        assert!(hnsw.clone().count() == 2 && hnsw.clone().all(|p| p.recall >= 0.95), "{metric:?}: {:?}", report.points);
+        let flat = report.points.last().unwrap();
+        assert_eq!((flat.case.as_str(), flat.recall), ("flat", 1.0));
+    }
+
+    // The same seed generates the same data
+    let dataset = check(Metric::Cosine).dataset;
+    assert_eq!(dataset.generate(7), dataset.generate(7));
+    assert_ne!(dataset.generate(7).1, dataset.generate(8).1);
+}
+
+#[test]
+fn points_under_the_floor_fail_the_check() {
+    let mut check = check(Metric::Cosine);
+    check.grid = check.grid.only(&[IndexType::Ivf]);
+    check.grid.nprobe = vec![1, 16];
+    check.floor = 0.99;
+    let report = run_recall_check(&check).unwrap();
+    assert!(!report.passed);
+    let failed: Vec<&str> = report.failures().map(|p| p.case.as_str()).collect();
+    assert_eq!(failed.len(), 1);
+    assert!(failed[0].ends_with("nprobe=1"), "{failed:?}");
+    assert!(report.points[0].recall <= report.points[0].mean_recall);
+    assert!(std::panic::catch_unwind(|| report.assert_passed()).is_err());
+
+    check.k = 0;
+    assert!(run_recall_check(&check).is_err());
+    check.k = 10;
+    check.grid.indexes.clear();
+    assert!(run_recall_check(&check).is_err());
+}
+
+#[test]
+fn hnsw_euclidean_search_returns_the_closest_point() {
+    // A 20 x 20 grid: every query point has one unambiguous nearest neighbour
+    let mut index = HnswIndex::new(HnswConfig { metric: Metric::Euclidean, ..Default::default() });
+    let mut vectors = HashMap::new();
+    for x in 0..20 {
+        for y in 0..20 {
+            let id = Uuid::new_v4();
+            let vector = vec![x as f32, y as f32];
+            vectors.insert(id, vector.clone());
+            index.insert(id, &vector, &vectors);
+        }
+    }
+
+    let metadata = HashMap::new();
+    for (x, y) in [(0.2, 0.1), (7.3, 12.4), (19.1, 18.8), (10.4, 3.3)] {
+        let query = [x, y];
+        let exact = vectors
+            .iter()
+            .min_by(|a, b| distance(&query, a.1).total_cmp(&distance(&query, b.1)))
+            .map(|(id, _)| *id);
+        let found = index.search(&query, 1, 32, &vectors, None, &metadata);
+        assert_eq!(found.first().copied(), exact, "query {query:?}");
+    }
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
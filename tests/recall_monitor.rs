@@ -0,0 +1,100 @@
+mod common;
+
+use piramid::config::{AppConfig, ExecutionMode, RecallMonitorConfig, SearchConfig};
+use piramid::index::IndexConfig;
+use piramid::server::recall_monitor::{check_all, RecallMonitor};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{Collection, CollectionConfig, Document, Metric};
+use serde_json::Value;
+use std::sync::Arc;
+
+// Pseudo-random, so nearest neighbours are spread over the clusters
+fn vector(i: usize) -> Vec<f32> {
+    common::random_vector(i, 16)
+}
+
+fn ivf(num_probes: usize) -> IndexConfig {
+    IndexConfig::Ivf {
+        num_clusters: 16,
+        num_probes,
+        max_iterations: 10,
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    }
+}
+
+fn monitor_config() -> RecallMonitorConfig {
+    RecallMonitorConfig { enabled: true, queries: 20, k: 10, interval_secs: 60, alert_below: 0.99 }
+}
+
+fn seeded(path: &str, index: IndexConfig) -> Collection {
+    let mut storage = Collection::open_with_options(path, CollectionConfig::with_index(index).into()).unwrap();
+    storage.insert_batch((0..500).map(|i| Document::new(vector(i), format!("doc {i}"))).collect()).unwrap();
+    storage
+}
+
+#[test]
+fn recall_is_measured_against_exact_results_kept_current_with_writes() {
+    let dir = TestDir::new("recall_monitor_measure");
+    let monitor = RecallMonitor::new(&monitor_config());
+
+    // Probing every cluster the search is exact
+    let mut exact = seeded(&dir.path("exact.db"), ivf(16));
+    let first = monitor.measure("exact", &exact).unwrap();
+    assert_eq!((first.recall, first.degraded, first.queries), (1.0, false, 20));
+    assert_eq!(first.truth_seq, exact.head_seq());
+
+    // Writes move the exact results, so they are recomputed before the next measurement
+    exact.insert_batch((500..600).map(|i| Document::new(vector(i), format!("doc {i}"))).collect()).unwrap();
+    let second = monitor.measure("exact", &exact).unwrap();
+    assert!(second.truth_seq > first.truth_seq);
+    assert_eq!(second.truth_seq, exact.head_seq());
+    assert_eq!(second.recall, 1.0);
+
+    // One probe of sixteen misses most of the neighbours
+    let narrow = seeded(&dir.path("narrow.db"), ivf(1));
+    let status = monitor.measure("narrow", &narrow).unwrap();
+    assert!(status.recall < 0.99 && status.degraded, "{status:?}");
+    assert!(monitor.status("narrow").unwrap().degraded);
+    monitor.forget("narrow");
+    assert!(monitor.status("narrow").is_none());
+
+    // A flat index is exact and is not measured
+    let flat = seeded(&dir.path("flat.db"), IndexConfig::Flat {
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    });
+    assert!(monitor.measure("flat", &flat).is_none());
+    drop((exact, narrow, flat));
+}
+
+#[tokio::test]
+async fn metrics_report_recall_and_flag_degraded_indexes() {
+    let dir = TestDir::new("recall_monitor_http");
+    let data_dir = dir.data_dir();
+    let config = AppConfig { index: ivf(1), recall_monitor: monitor_config(), ..AppConfig::default() };
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    state.get_or_create_collection("docs").unwrap();
+    {
+        let handle = state.collections.get("docs").unwrap().clone();
+        let mut storage = handle.write();
+        storage.insert_batch((0..500).map(|i| Document::new(vector(i), format!("doc {i}"))).collect()).unwrap();
+    }
+    assert!(state.recall_monitor.status("docs").is_none());
+    check_all(&state);
+
+    let base = common::serve(state).await;
+    let client = reqwest::Client::new();
+
+    let metrics: Value = client.get(format!("{base}/metrics")).send().await.unwrap().json().await.unwrap();
+    let recall = &metrics["collections"][0]["recall"];
+    assert_eq!((recall["k"].as_u64(), recall["queries"].as_u64(), recall["degraded"].as_bool()), (Some(10), Some(20), Some(true)), "{metrics}");
+    assert!(recall["recall"].as_f64().unwrap() < 0.99);
+
+    let text = client.get(format!("{base}/metrics/prometheus")).send().await.unwrap().text().await.unwrap();
+    assert!(text.contains("piramid_index_recall{collection=\"docs\",k=\"10\"}"), "{text}");
+    assert!(text.contains("piramid_index_recall_degraded{collection=\"docs\"} 1"), "{text}");
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::storage::collection::rename_files;
+use piramid::storage::load_metadata;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use common::vector;
+
+#[test]
+fn rename_moves_every_file_and_the_recorded_name() {
+    let dir = TestDir::new("rename_files");
+    let (from, to) = (dir.path("old.db"), dir.path("new.db"));
+    let mut storage = Collection::open(&from).unwrap();
+    let id = storage.insert(Document::new(vector(0), "a".into())).unwrap();
+    storage.checkpoint().unwrap();
+    storage.insert(Document::new(vector(1), "b".into())).unwrap(); // only in the WAL
+    storage.flush().unwrap();
+    drop(storage);
+    // The lock file does not move with the collection; the one at the new path comes with reopening it
+    let before = fs::read_dir(dir.root()).unwrap().count() - 1;
+
+    // A name that is taken is refused and nothing moves
+    fs::write(dir.path("taken.db.index.db"), b"x").unwrap();
+    assert_eq!(rename_files(&from, &dir.path("taken.db")).unwrap_err().status_code(), 409);
+    assert!(Path::new(&from).exists());
+
+    rename_files(&from, &to).unwrap();
+    let left: Vec<_> = fs::read_dir(dir.root()).unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("old."))
+        .collect();
+    assert!(left.is_empty(), "{left:?}");
+    assert_eq!(fs::read_dir(dir.root()).unwrap().count(), before + 1);
+    assert_eq!(load_metadata(&to).unwrap().unwrap().name, "new");
+    let storage = Collection::open(&to).unwrap();
+    assert_eq!(storage.count(), 2);
+    assert_eq!(storage.get(&id).unwrap().text, "a");
+    drop(storage);
+}
+
+#[tokio::test]
+async fn rename_endpoint_moves_the_collection() {
+    let dir = TestDir::new("rename_http");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = common::serve(state).await;
+    let client = reqwest::Client::new();
+
+    for name in ["docs", "other"] {
+        client.post(format!("{base}/collections")).json(&json!({"name": name})).send().await.unwrap();
+    }
+    client.post(format!("{base}/projects")).json(&json!({"name": "team", "collections": ["docs"]})).send().await.unwrap();
+    let res = client.post(format!("{base}/collections/docs/upsert"))
+        .json(&json!({"id": "a", "vector": vector(0), "text": "a"}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    client.post(format!("{base}/collections/docs/replicas")).json(&json!({"replicas": 1})).send().await.unwrap();
+
+    let rename = |from: &str, to: &str| client.post(format!("{base}/collections/{from}/rename")).json(&json!({"name": to})).send();
+    assert_eq!(rename("docs", "other").await.unwrap().status(), 409);
+    assert_eq!(rename("missing", "fresh").await.unwrap().status(), 404);
+    assert_eq!(rename("docs", "bad name").await.unwrap().status(), 400);
+    let res = rename("docs", "papers").await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.json::<Value>().await.unwrap()["count"], 1);
+
+    let list: Value = client.get(format!("{base}/collections")).send().await.unwrap().json().await.unwrap();
+    let names: Vec<_> = list["collections"].as_array().unwrap().iter().map(|c| c["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["other", "papers"]);
+    let project: Value = client.get(format!("{base}/projects/team")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(project["collections"], json!(["papers"]));
+
+    // Reads and writes carry on under the new name, replicas included, and survive a restart
+    let res: Value = client.post(format!("{base}/collections/papers/upsert"))
+        .json(&json!({"id": "b", "vector": vector(1), "text": "b"}))
+        .send().await.unwrap().json().await.unwrap();
+    let res: Value = client.post(format!("{base}/collections/papers/search"))
+        .json(&json!({"id": "a", "k": 5, "min_seq": res["seq"]}))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["results"][0]["external_id"], "b");
+    assert!(!Path::new(&format!("{data_dir}/docs.db")).exists());
+
+    let state = AppState::new(data_dir, AppConfig::default(), 500, None, false, None);
+    state.discover_collections().unwrap();
+    assert!(state.discovered.contains_key("papers") && !state.discovered.contains_key("docs"));
+    state.get_or_create_collection("papers").unwrap();
+    assert_eq!(state.collections.get("papers").unwrap().read().count(), 2);
+}
@@ -0,0 +1,113 @@
+use piramid::testing::TestDir;
+use piramid::{Collection, CollectionConfig, Document, Metric, SearchParams, TwoStageConfig};
+use std::sync::Arc;
+
+fn seed(storage: &mut Collection, n: usize) {
+    for i in 0..n {
+        let angle = i as f32 * 0.3;
+        storage
+            .insert(Document::new(vec![angle.cos(), angle.sin(), 0.1 * i as f32], format!("doc {i}")))
+            .unwrap();
+    }
+}
+
+#[test]
+fn replicas_return_the_primary_results() {
+    let dir = TestDir::new("replicas");
+    let path = dir.path("docs.db");
+
+    let mut storage = Collection::open(&path).unwrap();
+    seed(&mut storage, 30);
+    let replicas = storage.create_replicas(3).unwrap();
+    assert_eq!(replicas.len(), 3);
+
+    let query = [0.8, 0.2, 0.5];
+    let expected = storage.search(&query, 5, Metric::Cosine, SearchParams::default());
+    // Round-robin: every replica answers one of these
+    for _ in 0..replicas.len() {
+        let hits = replicas.search(&query, 5, Metric::Cosine, SearchParams::default());
+        let ids: Vec<_> = hits.iter().map(|h| h.id).collect();
+        assert_eq!(ids, expected.iter().map(|h| h.id).collect::<Vec<_>>());
+        assert_eq!(hits[0].text, expected[0].text);
+        assert!((hits[0].score - expected[0].score).abs() < 1e-3);
+    }
+
+    let batch = replicas.search_batch(&[query.to_vec(), vec![1.0, 0.0, 0.0]], 2, Metric::Cosine);
+    assert_eq!(batch.len(), 2);
+    assert_eq!(batch[0][0].id, expected[0].id);
+
+    drop(storage);
+}
+
+#[test]
+fn writes_reach_every_replica() {
+    let dir = TestDir::new("replicas_writes");
+    let path = dir.path("docs.db");
+
+    let mut storage = Collection::open(&path).unwrap();
+    seed(&mut storage, 10);
+    let replicas = storage.create_replicas(2).unwrap();
+
+    let target = vec![-1.0, -1.0, -1.0];
+    let id = storage.insert(Document::new(target.clone(), "new".into())).unwrap();
+    assert_eq!(replicas.pending(), 1);
+    for _ in 0..replicas.len() {
+        let hits = replicas.search(&target, 1, Metric::Cosine, SearchParams::default());
+        assert_eq!(hits[0].id, id);
+    }
+    // Applied everywhere, so the feed is trimmed
+    assert_eq!(replicas.pending(), 0);
+
+    storage.update_metadata(&id, piramid::metadata([("tag", "x".into())])).unwrap();
+    storage.delete(&id).unwrap();
+    for _ in 0..replicas.len() {
+        let hits = replicas.search(&target, 3, Metric::Cosine, SearchParams::default());
+        assert!(hits.iter().all(|h| h.id != id));
+    }
+
+    // A rejected write is never published
+    assert!(storage.insert(Document::new(vec![1.0, 2.0], "wrong dims".into())).is_err());
+    assert_eq!(replicas.pending(), 0);
+
+    // Writes after drop_replicas no longer reach the old set
+    storage.drop_replicas();
+    storage.insert(Document::new(target.clone(), "unseen".into())).unwrap();
+    let hits = replicas.search(&target, 1, Metric::Cosine, SearchParams::default());
+    assert_ne!(hits[0].text, "unseen");
+
+    drop(storage);
+}
+
+#[test]
+fn replicas_serve_concurrent_searches() {
+    let dir = TestDir::new("replicas_threads");
+    let path = dir.path("docs.db");
+
+    let mut storage = Collection::open(&path).unwrap();
+    seed(&mut storage, 20);
+    let replicas = storage.create_replicas(4).unwrap();
+    let expected = storage.search(&[1.0, 0.0, 0.0], 3, Metric::Cosine, SearchParams::default())[0].id;
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let replicas = Arc::clone(&replicas);
+            std::thread::spawn(move || {
+                (0..20)
+                    .map(|_| replicas.search(&[1.0, 0.0, 0.0], 3, Metric::Cosine, SearchParams::default())[0].id)
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert!(handle.join().unwrap().into_iter().all(|id| id == expected));
+    }
+
+    let two_stage = CollectionConfig::default().with_two_stage(TwoStageConfig::with_candidates(10));
+    let other_dir = TestDir::new("replicas_two_stage");
+    let other = other_dir.path("docs.db");
+    let mut two_stage_storage = Collection::open_with_options(&other, two_stage.into()).unwrap();
+    assert!(two_stage_storage.create_replicas(2).is_err());
+
+    drop(storage);
+    drop(two_stage_storage);
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use piramid::config::{AppConfig, QuantizationLevel, TwoStageConfig};
+use piramid::server::state::AppState;
+use piramid::storage::collection::get_quantization_config_path;
+use piramid::quantization::QuantizedVector;
+use piramid::testing::TestDir;
+use piramid::{Collection, CollectionConfig, Document, MetadataValue};
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Pseudo-random, so no two documents point the same way
+fn vector(i: usize) -> Vec<f32> {
+    common::random_vector(i, 16)
+}
+
+#[tokio::test]
+async fn quantization_endpoint_reencodes_stored_documents_and_keeps_the_level() {
+    let dir = TestDir::new("requantize_http");
+    let data_dir = dir.data_dir();
+    let path = format!("{data_dir}/docs.db");
+    let ids = {
+        let mut storage = Collection::open(&path).unwrap();
+        let docs = (0..300).map(|i| {
+            let mut doc = Document::new(vector(i), format!("doc {i}"));
+            doc.metadata.insert("n".into(), MetadataValue::Integer(i as i64));
+            doc
+        });
+        let ids = storage.insert_batch(docs.collect()).unwrap();
+        storage.checkpoint().unwrap();
+        ids
+    };
+
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    state.discover_collections().unwrap();
+    let base = common::serve(state).await;
+    let client = reqwest::Client::new();
+
+    let before: Value = client.get(format!("{base}/collections/docs/quantization")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(before["encodings"], json!({"int8": 300}), "{before}");
+    assert_eq!((before["pending"].as_u64(), before["mixed"].as_bool()), (Some(0), Some(false)));
+    let search = json!({"vector": vector(42), "k": 1});
+    let hits: Value = client.post(format!("{base}/collections/docs/search")).json(&search).send().await.unwrap().json().await.unwrap();
+
+    let res = client.post(format!("{base}/collections/docs/quantization"))
+        .json(&json!({"level": {"Pq": {"subquantizers": 4}}, "batch_size": 64}))
+        .send().await.unwrap();
+    assert!(res.status().is_success());
+    let queued: Value = res.json().await.unwrap();
+    let id = queued["job_id"].as_str().unwrap().to_string();
+    let mut job = Value::Null;
+    for _ in 0..200 {
+        job = client.get(format!("{base}/jobs/{id}")).send().await.unwrap().json().await.unwrap();
+        if job["state"] == "completed" || job["state"] == "failed" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert_eq!(job["state"], "completed", "{job}");
+    assert_eq!((job["kind"].as_str(), job["result"]["documents"].as_u64(), job["result"]["reencoded"].as_u64()), (Some("requantize"), Some(300), Some(300)));
+
+    let after: Value = client.get(format!("{base}/collections/docs/quantization")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(after["encodings"], json!({"pq4": 300}), "{after}");
+    assert_eq!(after["level"], json!({"Pq": {"subquantizers": 4}}));
+    assert_eq!((after["pending"].as_u64(), after["mixed"].as_bool()), (Some(0), Some(false)));
+    // The index was left alone, so searches answer as before
+    let again: Value = client.post(format!("{base}/collections/docs/search")).json(&search).send().await.unwrap().json().await.unwrap();
+    assert_eq!(again["results"][0]["id"], hits["results"][0]["id"]);
+    assert_eq!(again["results"][0]["id"], ids[42].to_string());
+
+    // Opened again with the configured level, the collection keeps the one it switched to
+    assert!(fs::metadata(get_quantization_config_path(&path)).is_ok());
+    for suffix in ["", ".index.db", ".vecindex.db", ".metadata.db", ".quantcfg.json"] {
+        fs::copy(format!("{path}{suffix}"), format!("{data_dir}/docs_copy.db{suffix}")).unwrap();
+    }
+    let mut storage = Collection::open(&format!("{data_dir}/docs_copy.db")).unwrap();
+    assert_eq!(storage.config().quantization.level, QuantizationLevel::Pq { subquantizers: 4 });
+    let doc = storage.get(&ids[7]).unwrap();
+    assert_eq!(doc.vector.encoding(), "pq4");
+    assert_eq!(doc.metadata.get("n"), Some(&MetadataValue::Integer(7)));
+    let added = storage.insert(Document::new(vector(1000), "new".into())).unwrap();
+    assert_eq!(storage.get(&added).unwrap().vector.encoding(), "pq4");
+    assert_eq!(storage.quantization_composition().encodings.get("pq4"), Some(&301));
+    drop(storage);
+}
+
+#[test]
+fn partial_requantize_reports_a_mixed_collection() {
+    let dir = TestDir::new("requantize_mixed");
+    let config = CollectionConfig::default().with_two_stage(TwoStageConfig { enabled: true, ..Default::default() });
+    let mut storage = dir.open("docs", config).unwrap();
+    let ids = storage.insert_batch((0..100).map(|i| Document::new(vector(i), format!("doc {i}"))).collect()).unwrap();
+
+    // New writes take the new level at once; older documents wait for a requantize
+    assert_eq!(storage.set_quantization(QuantizationLevel::Pq { subquantizers: 2 }).unwrap(), QuantizationLevel::None);
+    let added = storage.insert(Document::new(vector(500), "new".into())).unwrap();
+    let mixed = storage.quantization_composition();
+    assert!(mixed.mixed);
+    assert_eq!((mixed.pending, mixed.encodings.get("int8"), mixed.encodings.get("pq2")), (100, Some(&100), Some(&1)));
+
+    let mut sorted = storage.ids();
+    sorted.retain(|id| *id != added);
+    assert_eq!(storage.requantize(&sorted[..40]).unwrap(), 40);
+    assert_eq!(storage.requantize(&sorted[..40]).unwrap(), 0);
+    assert_eq!(storage.quantization_composition().pending, 60);
+    assert_eq!(storage.requantize(&storage.ids()).unwrap(), 60);
+    let done = storage.quantization_composition();
+    assert!(!done.mixed);
+    assert_eq!((done.pending, done.encodings.get("pq2")), (0, Some(&101)));
+
+    // Re-encoded from the full-precision sidecar, not from the int8 codes
+    for (i, id) in ids.iter().enumerate() {
+        let stored = storage.get(id).unwrap().get_vector();
+        let expected = QuantizedVector::from_f32_with_config(&vector(i), &storage.config().quantization).to_f32();
+        assert_eq!(stored, expected);
+    }
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::server::types::schema::field_errors;
+use piramid::server::types::InsertRequest;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+#[test]
+fn batch_errors_are_reported_by_pointer() {
+    let body = json!({"vectors": [[1.0, 0.0], [1.0, "x"], [true, 2.0], [0.5, 0.5]], "texts": [1, "b", "c", "d"]});
+    let errors = field_errors::<InsertRequest>(body.to_string().as_bytes());
+    let pointers: Vec<&str> = errors.iter().map(|e| e.pointer.as_str()).collect();
+    assert_eq!(pointers, ["/texts/0", "/vectors/1/1", "/vectors/2/0"], "{errors:?}");
+    assert_eq!(errors[1].expected.as_deref(), Some("f32"));
+    assert_eq!(errors[0].expected.as_deref(), Some("a string"));
+
+    // Every item of an array bad: each is still reported once
+    let body = json!({"external_ids": [1, 2, 3]});
+    assert_eq!(field_errors::<InsertRequest>(body.to_string().as_bytes()).len(), 3);
+    assert!(field_errors::<InsertRequest>(br#"{"vector": [1.0, 2.0]}"#).is_empty());
+    assert!(field_errors::<InsertRequest>(b"{not json").is_empty());
+}
+
+#[tokio::test]
+async fn invalid_bodies_get_field_errors() {
+    let dir = TestDir::new("request_schema");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 1000, None, false, None));
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let res = client.post(format!("{base}/search")).json(&json!({"vector": [1.0, "a"], "k": "ten"})).send().await.unwrap();
+    assert_eq!(res.status(), 422);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["error_code"], "VALIDATION_FAILED");
+    let pointers: Vec<&str> = body["errors"].as_array().unwrap().iter().map(|e| e["pointer"].as_str().unwrap()).collect();
+    assert_eq!(pointers, ["/k", "/vector/1"], "{body}");
+    assert_eq!(body["errors"][0]["expected"], "usize");
+    assert!(body["error"].as_str().unwrap().contains("(and 1 more)"), "{body}");
+
+    // Syntax errors and missing content types are rejected as before
+    let res = client.post(format!("{base}/vectors")).header("content-type", "application/json").body("{").send().await.unwrap();
+    assert_eq!(res.status(), 400);
+    let res = client.post(format!("{base}/vectors")).body(r#"{"vector": [1.0]}"#).send().await.unwrap();
+    assert_eq!(res.status(), 415);
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::storage::collection::DEFAULT_STATS_SAMPLE;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const LANGS: [&str; 5] = ["en", "de", "fr", "es", "it"];
+
+// Norm 1 for even documents, 3 for odd ones
+fn document(i: usize) -> Document {
+    let scale = if i % 2 == 0 { 1.0 } else { 3.0 };
+    let vector = vec![scale * 0.6, scale * 0.8, 0.0, 0.0];
+    Document::with_metadata(vector, format!("doc {i}"), metadata([("lang", LANGS[i % 5].into()), ("n", (i as i64).into())]))
+}
+
+#[test]
+fn sample_follows_writes_and_reopens() {
+    let dir = TestDir::new("sampled_stats_files");
+    let path = dir.path("docs.db");
+    let mut storage = Collection::open(&path).unwrap();
+    let ids = storage.insert_batch((0..3000).map(document).collect()).unwrap();
+
+    let stats = storage.sampled_stats();
+    assert_eq!((stats.count, stats.sample, stats.exact), (3000, DEFAULT_STATS_SAMPLE, false));
+    assert!((stats.norms.mean - 2.0).abs() < 0.2, "{:?}", stats.norms);
+    assert!((stats.norms.min - 1.0).abs() < 0.01 && (stats.norms.max - 3.0).abs() < 0.01, "{:?}", stats.norms);
+    let lang = stats.fields.iter().find(|f| f.field == "lang").unwrap();
+    assert_eq!((lang.coverage, lang.distinct_in_sample, lang.estimated_distinct), (1.0, 5, 5));
+    assert!((lang.top_values.iter().map(|v| v.share).sum::<f32>() - 1.0).abs() < 1e-4);
+    // Every value of `n` is unique: the estimate lands well above what the sample saw, never above the count
+    let n = stats.fields.iter().find(|f| f.field == "n").unwrap();
+    assert_eq!(n.distinct_in_sample, DEFAULT_STATS_SAMPLE);
+    assert!(n.estimated_distinct > 1500 && n.estimated_distinct <= 3000, "{}", n.estimated_distinct);
+
+    // Deletes are dropped from the sample, which is drawn again before it runs thin
+    storage.delete_batch(&ids[..2000]).unwrap();
+    let stats = storage.sampled_stats();
+    assert_eq!(stats.count, 1000);
+    assert!(stats.sample * 2 >= 1000 && stats.sample <= 1000, "{}", stats.sample);
+    drop(storage);
+
+    // Reopening draws the sample from the stored documents; a small collection is held whole
+    let storage = Collection::open(&path).unwrap();
+    let stats = storage.sampled_stats();
+    assert_eq!((stats.sample, stats.exact), (1000, true));
+    let remaining = ids[2000];
+    storage.update_metadata(&remaining, metadata([("lang", "pt".into())])).unwrap();
+    let stats = storage.sampled_stats();
+    let lang = stats.fields.iter().find(|f| f.field == "lang").unwrap();
+    assert_eq!(lang.estimated_distinct, 6);
+    assert!(stats.fields.iter().find(|f| f.field == "n").unwrap().coverage < 1.0);
+    drop(storage);
+}
+
+#[tokio::test]
+async fn statistics_get_returns_the_sample() {
+    let dir = TestDir::new("sampled_stats_http");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let vectors: Vec<Vec<f32>> = (0..50).map(|i| vec![1.0, i as f32, 0.0]).collect();
+    let texts: Vec<String> = (0..50).map(|i| format!("doc {i}")).collect();
+    let metadata: Vec<Value> = (0..50).map(|i| json!({"lang": LANGS[i % 5]})).collect();
+    let res = client.post(format!("{base}/vectors")).json(&json!({"vectors": vectors, "texts": texts, "metadata_list": metadata})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    let body: Value = client.get(format!("{base}/statistics")).send().await.unwrap().json().await.unwrap();
+    assert_eq!((body["count"].as_u64(), body["sample"].as_u64(), body["exact"].as_bool()), (Some(50), Some(50), Some(true)), "{body}");
+    assert_eq!(body["dimensions"], 3);
+    let lang = body["fields"].as_array().unwrap().iter().find(|f| f["field"] == "lang").unwrap();
+    assert_eq!((lang["estimated_distinct"].as_u64(), lang["top_values"].as_array().unwrap().len()), (Some(5), 5));
+    assert!(body["norms"]["max"].as_f64().unwrap() > 48.0);
+}
@@ -0,0 +1,110 @@
+use piramid::config::{AppConfig, OffPeakWindow, SchedulerConfig};
+use piramid::scheduler::{self, Priority};
+use piramid::server::routes::create_router;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+use serde_json::Value;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+
+// The scheduler is one per process; its tests take turns
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn window(from_now: u64, hours: u64) -> OffPeakWindow {
+    let hour = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() / 3600 % 24;
+    OffPeakWindow { start_hour: ((hour + from_now) % 24) as u8, end_hour: ((hour + from_now + hours) % 24) as u8 }
+}
+
+fn wait_until(check: impl Fn() -> bool) {
+    for _ in 0..400 {
+        if check() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("timed out waiting for the scheduler");
+}
+
+#[test]
+fn tasks_start_by_priority_within_the_limits() {
+    let _serial = SERIAL.blocking_lock();
+    scheduler::configure(&SchedulerConfig { max_concurrent: 1, max_low_priority: 1, off_peak: None });
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let record = |name: &'static str| {
+        let order = order.clone();
+        move || order.lock().unwrap().push(name)
+    };
+
+    let (release, gate) = mpsc::channel::<()>();
+    let blocker = scheduler::spawn(Priority::Normal, "blocker", move || gate.recv().unwrap());
+    wait_until(|| scheduler::stats().running.normal == 1);
+    let low = scheduler::spawn(Priority::Low, "low", record("low"));
+    let normal = scheduler::spawn(Priority::Normal, "normal", record("normal"));
+    let stats = scheduler::stats();
+    assert_eq!((stats.queued.normal, stats.queued.low), (1, 1), "{stats:?}");
+
+    // A checkpoint is not held behind the limit
+    assert_eq!(scheduler::spawn(Priority::High, "checkpoint", || 7).join().unwrap(), 7);
+    assert!(!blocker.is_finished());
+
+    release.send(()).unwrap();
+    blocker.join().unwrap();
+    low.join().unwrap();
+    normal.join().unwrap();
+    assert_eq!(*order.lock().unwrap(), ["normal", "low"]);
+
+    // A panicking task reports it and frees its place
+    assert!(scheduler::spawn(Priority::Normal, "panics", || panic!("boom")).join().is_err());
+    assert_eq!(scheduler::spawn(Priority::Normal, "after", || 1).join().unwrap(), 1);
+    scheduler::configure(&SchedulerConfig::default());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn low_priority_jobs_wait_for_the_off_peak_window() {
+    let _serial = SERIAL.lock().await;
+    let dir = TestDir::new("scheduler_off_peak");
+    let data_dir = dir.data_dir();
+    let mut storage = Collection::open(&format!("{data_dir}/docs.db")).unwrap();
+    for i in 0..20 {
+        storage.insert(Document::new(vec![1.0, i as f32, 0.0], format!("doc {i}"))).unwrap();
+    }
+    storage.checkpoint().unwrap();
+    drop(storage);
+
+    let mut config = AppConfig::default();
+    config.scheduler.off_peak = Some(window(3, 2));
+    let state = Arc::new(AppState::new(data_dir, config.clone(), 500, None, false, None));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}/api", listener.local_addr().unwrap());
+    let server = state.clone();
+    tokio::spawn(async move { axum::serve(listener, create_router(server)).await.unwrap() });
+    let client = reqwest::Client::new();
+
+    // A queued rebuild is low priority and waits; a compaction someone waits on goes ahead of it
+    let rebuild: Value = client.post(format!("{base}/collections/docs/index/rebuild")).send().await.unwrap().json().await.unwrap();
+    let job_url = format!("{base}/jobs/{}", rebuild["job_id"].as_str().unwrap());
+    let res = client.post(format!("{base}/collections/docs/compact")).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let job: Value = client.get(&job_url).send().await.unwrap().json().await.unwrap();
+    assert_eq!((job["state"].as_str(), job["priority"].as_str()), (Some("queued"), Some("low")), "{job}");
+    let low = scheduler::spawn(Priority::Low, "low", || "ran");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!low.is_finished());
+    assert_eq!(scheduler::stats().queued.low, 1);
+
+    // Moving the window over the current hour lets both start
+    config.scheduler.off_peak = Some(window(0, 2));
+    state.apply_config(config);
+    assert_eq!(low.wait().await.unwrap(), "ran");
+    for _ in 0..200 {
+        let job: Value = client.get(&job_url).send().await.unwrap().json().await.unwrap();
+        if job["state"] == "completed" {
+            scheduler::configure(&SchedulerConfig::default());
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    panic!("the rebuild did not run inside the window");
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::search::{ScoreBand, ScoreBands};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+fn band(name: &str, min_score: Option<f32>) -> ScoreBand {
+    ScoreBand { name: name.into(), min_score }
+}
+
+#[test]
+fn bands_bucket_hits_and_count_them() {
+    let bands = ScoreBands { bands: vec![band("high", Some(0.8)), band("medium", Some(0.5))], only: vec![] };
+    bands.validate().unwrap();
+    // In the order given within a band (here a field order), bands best first; below all is left out
+    let hits = vec![("a", 0.6), ("b", 0.9), ("c", 0.2), ("d", 0.5), ("e", 0.8)];
+    let (banded, counts) = bands.apply(hits.clone(), |h| h.1);
+    let names: Vec<(&str, usize)> = banded.iter().map(|((name, _), band)| (*name, *band)).collect();
+    assert_eq!(names, [("b", 0), ("e", 0), ("a", 1), ("d", 1)]);
+    assert_eq!(counts.iter().map(|c| (c.name.as_str(), c.count)).collect::<Vec<_>>(), [("high", 2), ("medium", 2)]);
+
+    // A last band without min_score takes the rest; `only` keeps some bands but counts them all
+    let bands = ScoreBands { bands: vec![band("high", Some(0.8)), band("rest", None)], only: vec!["rest".into()] };
+    bands.validate().unwrap();
+    let (banded, counts) = bands.apply(hits, |h| h.1);
+    assert_eq!(banded.iter().map(|((name, _), _)| *name).collect::<Vec<_>>(), ["a", "c", "d"]);
+    assert_eq!((counts[0].count, counts[1].count), (2, 3));
+
+    for invalid in [
+        ScoreBands { bands: vec![], only: vec![] },
+        ScoreBands { bands: vec![band("low", Some(0.2)), band("high", Some(0.8))], only: vec![] },
+        ScoreBands { bands: vec![band("any", None), band("high", Some(0.8))], only: vec![] },
+        ScoreBands { bands: vec![band("high", Some(0.8)), band("high", Some(0.5))], only: vec![] },
+        ScoreBands { bands: vec![band("high", Some(f32::NAN))], only: vec![] },
+        ScoreBands { bands: vec![band("high", Some(0.8))], only: vec!["low".into()] },
+    ] {
+        assert!(invalid.validate().is_err(), "{invalid:?}");
+    }
+}
+
+#[tokio::test]
+async fn search_responses_carry_bands_and_counts() {
+    let dir = TestDir::new("score_bands_api");
+    let data_dir = dir.data_dir();
+    {
+        let mut storage = Collection::open(&format!("{data_dir}/posts.db")).unwrap();
+        let post = |vector: Vec<f32>, text: &str, date: &str| Document::with_metadata(vector, text.into(), metadata([("published", date.into())]));
+        storage.insert(post(vec![1.0, 0.0, 0.0], "exact", "2024-01-01")).unwrap();
+        storage.insert(post(vec![0.95, 0.1, 0.0], "close", "2026-01-01")).unwrap();
+        storage.insert(post(vec![0.9, 0.3, 0.0], "near-old", "2023-01-01")).unwrap();
+        storage.insert(post(vec![0.9, 0.35, 0.0], "near-new", "2025-01-01")).unwrap();
+        storage.insert(post(vec![0.0, 0.0, 1.0], "unrelated", "2027-01-01")).unwrap();
+        storage.checkpoint().unwrap();
+    }
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections/posts", common::serve(state).await);
+    let client = reqwest::Client::new();
+    let search = |path: &'static str, body: Value| {
+        let client = client.clone();
+        let url = format!("{base}/{path}");
+        async move { client.post(url).json(&body).send().await.unwrap() }
+    };
+    let bands = json!({"bands": [{"name": "high", "min_score": 0.99}, {"name": "medium", "min_score": 0.9}]});
+    let tiers = |res: &Value| res["results"].as_array().unwrap().iter()
+        .map(|h| format!("{}:{}", h["band"].as_str().unwrap(), h["text"].as_str().unwrap()))
+        .collect::<Vec<_>>();
+
+    // Newest first within each band
+    let order = json!({"field": "published", "descending": true});
+    let res: Value = search("search", json!({"vector": [1.0, 0.0, 0.0], "k": 5, "order_by": order, "score_bands": bands})).await.json().await.unwrap();
+    assert_eq!(tiers(&res), ["high:close", "high:exact", "medium:near-new", "medium:near-old"], "{res}");
+    assert_eq!(res["bands"], json!([{"name": "high", "count": 2}, {"name": "medium", "count": 2}]));
+
+    let only = json!({"bands": bands["bands"], "only": ["high"]});
+    let res: Value = search("search", json!({"vector": [1.0, 0.0, 0.0], "k": 5, "score_bands": only})).await.json().await.unwrap();
+    assert_eq!(tiers(&res), ["high:exact", "high:close"], "{res}");
+    assert_eq!(res["bands"][1]["count"], 2);
+
+    // Per query in a batch, and on range searches
+    let res: Value = search("search", json!({"vectors": [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]], "k": 1, "score_bands": bands})).await.json().await.unwrap();
+    assert_eq!((res["bands"][0][0]["count"].as_u64(), res["results"][1].as_array().map(Vec::len)), (Some(1), Some(0)), "{res}");
+    let res: Value = search("search/range", json!({"vector": [1.0, 0.0, 0.0], "min_score": 0.9, "k": 5, "score_bands": bands})).await.json().await.unwrap();
+    assert_eq!(res["bands"][1]["count"], 2, "{res}");
+
+    let bad = json!({"bands": [{"name": "low", "min_score": 0.2}, {"name": "high", "min_score": 0.8}]});
+    assert_eq!(search("search", json!({"vector": [1.0, 0.0, 0.0], "score_bands": bad})).await.status(), 400);
+}
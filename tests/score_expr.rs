@@ -0,0 +1,81 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::search::ScoreExpr;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document, Metric, SearchParams};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+// Three near-identical documents; the least similar one is by far the most popular, one has no popularity
+fn seed(path: &str) {
+    let mut storage = Collection::open(path).unwrap();
+    storage.insert(Document::with_metadata(vec![1.0, 0.0, 0.0], "best match".into(), metadata([("popularity", 1i64.into())]))).unwrap();
+    storage.insert(Document::with_metadata(vec![0.9, 0.3, 0.0], "popular".into(), metadata([("popularity", 1000i64.into()), ("promoted", true.into())]))).unwrap();
+    storage.insert(Document::new(vec![0.95, 0.1, 0.0], "no popularity".into())).unwrap();
+    storage.checkpoint().unwrap();
+}
+
+#[test]
+fn expressions_parse_evaluate_and_rerank_search() {
+    let meta = metadata([("popularity", 99i64.into()), ("rating", 4.5.into()), ("promoted", true.into()), ("tag", "x".into())]);
+    let eval = |src: &str| ScoreExpr::parse(src).unwrap().eval(0.5, &meta);
+    assert_eq!(eval("0.8 * similarity + 0.2 * log10(1 + metadata.popularity)"), Some(0.8 * 0.5 + 0.2 * 2.0));
+    assert_eq!(eval("-2 ^ 2 + 2 ^ 3 ^ 2"), Some(-4.0 + 512.0));
+    assert_eq!(eval("score * (metadata.rating - 0.5) / 2"), Some(1.0));
+    assert_eq!(eval("max(metadata.promoted, 0.25, similarity) + min(3, 1e1) + clamp(7, 0, 5)"), Some(1.0 + 3.0 + 5.0));
+    assert_eq!(eval("pow(2, 10) + sqrt(16) + abs(-1) + exp(0) + ln(1)"), Some(1024.0 + 4.0 + 1.0 + 1.0));
+    // A missing or non-numeric field makes the value missing, unless coalesce supplies a default
+    assert_eq!(eval("similarity + metadata.missing"), None);
+    assert_eq!(eval("metadata.tag"), None);
+    assert_eq!(eval("coalesce(metadata.missing, log(0), 7)"), Some(7.0));
+    assert_eq!(eval("log(0)"), None);
+
+    for bad in ["", "similarity +", "foo(1)", "unknown", "log(1, 2)", "metadata", "(1", "1 2", "sqrt(", &"(".repeat(40)] {
+        assert!(ScoreExpr::parse(bad).is_err(), "{bad:?} parsed");
+    }
+    assert!(ScoreExpr::parse(&"1+".repeat(600)).is_err());
+
+    let dir = TestDir::new("score_expr_files");
+    seed(&dir.path("docs.db"));
+    let storage = Collection::open(&dir.path("docs.db")).unwrap();
+    let query = [1.0, 0.0, 0.0];
+    let plain = storage.search(&query, 3, Metric::Cosine, SearchParams::default());
+    assert_eq!(plain.iter().map(|h| h.text.as_str()).collect::<Vec<_>>(), ["best match", "no popularity", "popular"]);
+
+    let expr = ScoreExpr::parse("0.5 * similarity + 0.5 * log10(metadata.popularity) / 3").unwrap();
+    let params = SearchParams { score_expr: Some(&expr), ..SearchParams::default() };
+    let ranked = storage.search(&query, 3, Metric::Cosine, params);
+    assert_eq!(ranked.iter().map(|h| h.text.as_str()).collect::<Vec<_>>(), ["popular", "best match"]);
+    assert!((ranked[1].score - 0.5).abs() < 1e-3, "{}", ranked[1].score);
+}
+
+#[tokio::test]
+async fn search_endpoint_ranks_by_score_expr() {
+    let dir = TestDir::new("score_expr_http");
+    let data_dir = dir.data_dir();
+    seed(&format!("{data_dir}/docs.db"));
+
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    state.discover_collections().unwrap();
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let body = json!({"vector": [1.0, 0.0, 0.0], "k": 2, "score_expr": "similarity + coalesce(metadata.promoted, 0)"});
+    let res: Value = client.post(format!("{base}/search")).json(&body).send().await.unwrap().json().await.unwrap();
+    let results = res["results"].as_array().unwrap();
+    assert_eq!((results[0]["text"].as_str(), results[1]["text"].as_str()), (Some("popular"), Some("best match")));
+    assert!(results[0]["score"].as_f64().unwrap() > 1.0);
+
+    // Batch queries are re-scored the same way
+    let body = json!({"vectors": [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]], "k": 1, "score_expr": "coalesce(metadata.popularity, 0)"});
+    let res: Value = client.post(format!("{base}/search")).json(&body).send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["results"][0][0]["text"], "popular");
+    assert_eq!(res["results"][1][0]["text"], "popular");
+
+    let body = json!({"vector": [1.0, 0.0, 0.0], "score_expr": "similarity * system(1)"});
+    let res = client.post(format!("{base}/search")).json(&body).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+    assert!(res.text().await.unwrap().contains("unknown name 'system'"));
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use piramid::config::{AppConfig, CollectionConfig, ExecutionMode, SearchConfig};
+use piramid::error::{PiramidError, ServerError};
+use piramid::index::{IndexConfig, SerializableIndex};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document, Metric, SearchParams};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use common::vector;
+
+fn sealed_error<T: std::fmt::Debug>(result: piramid::error::Result<T>) -> bool {
+    matches!(result, Err(PiramidError::Server(ServerError::CollectionSealed(_))))
+}
+
+#[test]
+fn sealed_collections_reject_writes_and_reopen_without_a_wal() {
+    let dir = TestDir::new("sealed");
+    let path = dir.path("docs.db");
+    let mut config = CollectionConfig {
+        index: IndexConfig::Hnsw {
+            m: 16,
+            m_max: 32,
+            ef_construction: 200,
+            ef_search: 200,
+            ml: 1.0 / 16f32.ln(),
+            metric: Metric::Cosine,
+            mode: ExecutionMode::default(),
+            search: SearchConfig::default(),
+            shards: 1,
+        },
+        ..Default::default()
+    };
+    config.wal.history_retention_secs = Some(3600);
+
+    let mut storage = Collection::open_with_options(&path, config.clone().into()).unwrap();
+    let ids: Vec<_> = (0..60).map(|i| storage.insert(Document::new(vector(i), format!("doc {i}"))).unwrap()).collect();
+    storage.delete_batch(&ids[50..]).unwrap();
+    storage.checkpoint().unwrap();
+    let head = storage.head_seq();
+
+    let report = storage.seal(None).unwrap();
+    assert_eq!((report.documents, report.index_type.as_str(), report.already_sealed), (50, "HNSW", false), "{report:?}");
+    assert!(report.data_bytes < report.data_bytes_before && report.wal_bytes_dropped > 0, "{report:?}");
+    assert_eq!(fs::metadata(&path).unwrap().len(), report.data_bytes);
+    assert!(!Path::new(&format!("{path}.wal.db")).exists() && !Path::new(&format!("{path}.wal.hist")).exists());
+    let packed: SerializableIndex = bincode::deserialize(&fs::read(format!("{path}.vecindex.db")).unwrap()).unwrap();
+    assert!(matches!(packed, SerializableIndex::PackedHnsw(_)));
+
+    assert!(sealed_error(storage.insert(Document::new(vector(99), "late".into()))));
+    assert!(sealed_error(storage.delete(&ids[0])));
+    assert!(sealed_error(storage.update_metadata(&ids[1], metadata([("tag", "x".into())]))));
+    assert!(sealed_error(storage.upsert_batch_partial(vec![Document::new(vector(98), "late".into())])));
+    assert!(sealed_error(piramid::storage::collection::compact(&mut storage)));
+    let hits: Vec<_> = storage.search(&vector(7), 3, Metric::Cosine, SearchParams::default()).into_iter().map(|h| h.id).collect();
+    assert_eq!(hits[0], ids[7]);
+    drop(storage);
+
+    // The configured WAL stays off, and the file is not grown back
+    let mut storage = Collection::open_with_options(&path, config.into()).unwrap();
+    assert!(storage.is_sealed());
+    assert_eq!((storage.count(), storage.head_seq()), (50, head + 1));
+    let reopened: Vec<_> = storage.search(&vector(7), 3, Metric::Cosine, SearchParams::default()).into_iter().map(|h| h.id).collect();
+    assert_eq!(reopened, hits);
+    assert!(sealed_error(storage.insert(Document::new(vector(99), "late".into()))));
+    assert!(storage.seal(None).unwrap().already_sealed);
+    storage.checkpoint().unwrap();
+    drop(storage);
+    assert!(!Path::new(&format!("{path}.wal.db")).exists());
+    assert_eq!(fs::metadata(&path).unwrap().len(), report.data_bytes);
+}
+
+#[tokio::test]
+async fn seal_endpoint_turns_writes_away_with_a_stable_code() {
+    let dir = TestDir::new("sealed_api");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let vectors: Vec<Vec<f32>> = (0..5).map(vector).collect();
+    let texts: Vec<String> = (0..5).map(|i| format!("doc {i}")).collect();
+    let res = client.post(format!("{base}/docs/vectors")).json(&json!({"vectors": vectors, "texts": texts})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res = client.post(format!("{base}/docs/snapshots")).json(&json!({"name": "v1"})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    let res = client.post(format!("{base}/docs/seal")).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let report: Value = res.json().await.unwrap();
+    assert_eq!((report["documents"].as_u64(), report["already_sealed"].as_bool()), (Some(5), Some(false)), "{report}");
+    let info: Value = client.get(format!("{base}/docs")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(info["sealed_at"], report["sealed_at"], "{info}");
+
+    for res in [
+        client.post(format!("{base}/docs/vectors")).json(&json!({"vector": vector(9), "text": "late"})).send().await.unwrap(),
+        client.post(format!("{base}/docs/compact")).send().await.unwrap(),
+        client.post(format!("{base}/docs/snapshots/v1/restore")).send().await.unwrap(),
+    ] {
+        assert_eq!(res.status(), 409);
+        let body: Value = res.json().await.unwrap();
+        assert_eq!((body["error_code"].as_str(), body["retryable"].as_bool()), (Some("COLLECTION_SEALED"), Some(false)), "{body}");
+    }
+
+    // Reads go on as before; sealing again only reports
+    let res: Value = client.post(format!("{base}/docs/search")).json(&json!({"vector": vector(2), "k": 1})).send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["results"][0]["text"], "doc 2", "{res}");
+    let again: Value = client.post(format!("{base}/docs/seal")).json(&json!({})).send().await.unwrap().json().await.unwrap();
+    assert_eq!((again["already_sealed"].as_bool(), &again["sealed_at"]), (Some(true), &report["sealed_at"]), "{again}");
+
+    // Into a new name, the snapshot is an ordinary collection
+    let res = client.post(format!("{base}/docs/snapshots/v1/restore")).json(&json!({"target": "draft"})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res = client.post(format!("{base}/draft/vectors")).json(&json!({"vector": vector(9), "text": "late"})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+async fn serve(data_dir: &str) -> String {
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+    for (id, vector) in [("a", [1.0, 0.0, 0.0]), ("b", [0.9, 0.3, 0.0]), ("c", [0.5, 0.5, 0.5]), ("d", [0.0, 0.0, 1.0])] {
+        let res = client.post(format!("{base}/upsert")).json(&json!({"id": id, "vector": vector, "text": id})).send().await.unwrap();
+        assert_eq!(res.status(), 200);
+    }
+    base
+}
+
+fn ids(res: &Value) -> Vec<String> {
+    res["results"].as_array().unwrap().iter().map(|h| h["external_id"].as_str().unwrap().to_string()).collect()
+}
+
+#[tokio::test]
+async fn search_by_id_finds_neighbours_without_itself() {
+    let dir = TestDir::new("search_by_id");
+    let data_dir = dir.data_dir();
+    let base = serve(data_dir).await;
+    let client = reqwest::Client::new();
+
+    let res: Value = client.post(format!("{base}/search")).json(&json!({"id": "a", "k": 2})).send().await.unwrap().json().await.unwrap();
+    assert_eq!(ids(&res), ["b", "c"]);
+    // Asked for more than the others, every other document comes back
+    let res: Value = client.post(format!("{base}/search")).json(&json!({"id": "a", "k": 10})).send().await.unwrap().json().await.unwrap();
+    assert_eq!(ids(&res), ["b", "c", "d"]);
+    // The document UUID works too, and matches searching with the vector itself
+    let doc: Value = client.get(format!("{base}/vectors/d")).send().await.unwrap().json().await.unwrap();
+    let by_uuid: Value = client.post(format!("{base}/search")).json(&json!({"id": doc["id"], "k": 1})).send().await.unwrap().json().await.unwrap();
+    let by_vector: Value = client.post(format!("{base}/search")).json(&json!({"vector": doc["vector"], "k": 2})).send().await.unwrap().json().await.unwrap();
+    assert_eq!(ids(&by_uuid), ["c"]);
+    assert_eq!(ids(&by_vector), ["d", "c"]);
+
+    let res = client.post(format!("{base}/search")).json(&json!({"id": "missing"})).send().await.unwrap();
+    assert_eq!(res.status(), 404);
+    let res = client.post(format!("{base}/search")).json(&json!({"id": "a", "vector": [1.0, 0.0, 0.0]})).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn search_by_id_reads_replicas() {
+    let dir = TestDir::new("search_by_id_replicas");
+    let data_dir = dir.data_dir();
+    let base = serve(data_dir).await;
+    let client = reqwest::Client::new();
+    let res = client.post(format!("{base}/replicas")).json(&json!({"replicas": 2})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    // A write the replicas have applied is visible to the next query by id
+    let res: Value = client.post(format!("{base}/upsert")).json(&json!({"id": "e", "vector": [0.1, 0.0, 1.0], "text": "e"}))
+        .send().await.unwrap().json().await.unwrap();
+    let body = json!({"id": "e", "k": 1, "min_seq": res["seq"]});
+    for _ in 0..2 {
+        let res: Value = client.post(format!("{base}/search")).json(&body).send().await.unwrap().json().await.unwrap();
+        assert_eq!(ids(&res), ["d"]);
+    }
+}
@@ -1,4 +1,5 @@
-use piramid::{Collection, Document, Metric, Filter, SearchParams, metadata};
+use piramid::{Collection, CollectionConfig, Document, Metric, Filter, SearchParams, TransformConfig, metadata};
+use piramid::testing::TestDir;
 use std::fs;
 
 fn cleanup(path: &str) {
@@ -8,7 +9,7 @@ fn cleanup(path: &str) {
         format!("{}.vecindex.db", path),
         format!("{}.metadata.db", path),
     ];
-    for p in std::iter::once(path.to_string()).chain(sidecars.into_iter()) {
+    for p in std::iter::once(path.to_string()).chain(sidecars) {
         let _ = fs::remove_file(p);
     }
 }
@@ -41,6 +42,10 @@ fn search_respects_filter() {
             filter: Some(&filter),
             filter_overfetch_override: None,
             search_config_override: None,
+            dedup_by: None,
+            score_expr: None,
+            order_by: None,
+            exclude_ids: None,
         };
 
         let results =
@@ -51,3 +56,145 @@ fn search_respects_filter() {
 
     cleanup(test_db);
 }
+
+#[test]
+fn selective_filter_falls_back_to_exact_scan() {
+    let dir = TestDir::new("search_selectivity");
+    let test_db = dir.path("docs.db");
+
+    {
+        let mut storage = Collection::open(&test_db).unwrap();
+
+        // Most documents sit right next to the query; the few that match the filter are far away,
+        // so a static overfetch never reaches them through the index.
+        for i in 0..100 {
+            let doc = Document::with_metadata(
+                vec![1.0, i as f32 * 0.001, 0.0],
+                format!("common {i}"),
+                metadata([("tier", "common".into())]),
+            );
+            storage.insert(doc).unwrap();
+        }
+        for i in 0..3 {
+            let doc = Document::with_metadata(
+                vec![0.0, 1.0, i as f32 * 0.1],
+                format!("rare {i}"),
+                metadata([("tier", "rare".into())]),
+            );
+            storage.insert(doc).unwrap();
+        }
+
+        let filter = Filter::new().eq("tier", "rare");
+        let params = SearchParams {
+            mode: storage.config().execution,
+            filter: Some(&filter),
+            filter_overfetch_override: Some(1),
+            search_config_override: None,
+            dedup_by: None,
+            score_expr: None,
+            order_by: None,
+            exclude_ids: None,
+        };
+
+        // First query has no selectivity estimate yet and comes back short
+        let first = storage.search(&[1.0, 0.0, 0.0], 3, Metric::Cosine, params);
+        assert!(first.len() < 3);
+        assert!(storage.selectivity().estimate(&filter.shape()).is_some());
+
+        // Second query knows the filter is selective and scans the matching documents exactly
+        let second = storage.search(&[1.0, 0.0, 0.0], 3, Metric::Cosine, params);
+        assert_eq!(second.len(), 3);
+        assert!(second.iter().all(|hit| hit.text.starts_with("rare")));
+    }
+}
+
+#[test]
+fn truncated_search_reranks_on_full_vectors() {
+    let dir = TestDir::new("search_truncate");
+    let test_db = dir.path("docs.db");
+
+    let config = CollectionConfig::default().with_transform(TransformConfig::truncate(2));
+    let mut storage = Collection::open_with_options(&test_db, config.into()).unwrap();
+
+    // Identical in the first two dims, different in the rest
+    let a = storage.insert(Document::new(vec![1.0, 0.0, 1.0, 0.0], "a".into())).unwrap();
+    let b = storage.insert(Document::new(vec![1.0, 0.0, 0.0, 1.0], "b".into())).unwrap();
+
+    // The index only holds the truncated prefix
+    assert_eq!(storage.vectors_view()[&a].len(), 2);
+
+    let results = storage.search(&[1.0, 0.0, 0.0, 1.0], 2, Metric::Cosine, SearchParams::default());
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].id, b);
+    assert!((results[0].score - 1.0).abs() < 1e-4);
+    assert!(results[1].score < 0.9);
+    assert_eq!(results[0].vector.len(), 4);
+
+    drop(storage);
+
+    // Without re-rank the truncated scores are final: both documents look identical
+    let config = CollectionConfig::default().with_transform(TransformConfig::truncate(2).without_rerank());
+    let mut storage = Collection::open_with_options(&dir.path("plain.db"), config.into()).unwrap();
+    storage.insert(Document::new(vec![1.0, 0.0, 1.0, 0.0], "a".into())).unwrap();
+    storage.insert(Document::new(vec![1.0, 0.0, 0.0, 1.0], "b".into())).unwrap();
+    let results = storage.search(&[1.0, 0.0, 0.0, 1.0], 2, Metric::Cosine, SearchParams::default());
+    assert!(results.iter().all(|hit| (hit.score - 1.0).abs() < 1e-4));
+}
+
+#[test]
+fn dedup_by_collapses_hits_from_the_same_page() {
+    let dir = TestDir::new("search_dedup");
+    let test_db = dir.path("docs.db");
+
+    let mut storage = Collection::open(&test_db).unwrap();
+    // Many chunks of one page sit closer to the query than anything else
+    for i in 0..20 {
+        let chunk = Document::with_metadata(
+            vec![1.0, 0.01 * i as f32, 0.0],
+            format!("page a chunk {i}"),
+            metadata([("url", "a".into())]),
+        );
+        storage.insert(chunk).unwrap();
+    }
+    for (url, y) in [("b", 0.6), ("c", 0.9)] {
+        let doc = Document::with_metadata(vec![1.0, y, 0.3], format!("page {url}"), metadata([("url", url.into())]));
+        storage.insert(doc).unwrap();
+    }
+
+    let query = [1.0, 0.0, 0.0];
+    let plain = storage.search(&query, 3, Metric::Cosine, SearchParams::default());
+    assert!(plain.iter().all(|hit| hit.text.starts_with("page a")));
+
+    let params = SearchParams { dedup_by: Some("url"), ..SearchParams::default() };
+    let results = storage.search(&query, 3, Metric::Cosine, params);
+    let texts: Vec<_> = results.iter().map(|hit| hit.text.as_str()).collect();
+    assert_eq!(texts, ["page a chunk 0", "page b", "page c"]);
+
+    // Hits without the key are never collapsed
+    storage.insert(Document::new(vec![1.0, 0.0, 0.01], "no url".into())).unwrap();
+    let results = storage.search(&query, 5, Metric::Cosine, params);
+    assert_eq!(results.len(), 4);
+    assert!(results.iter().any(|hit| hit.text == "no url"));
+}
+
+#[test]
+fn mismatched_metric_is_reranked() {
+    let dir = TestDir::new("search_metric_mismatch");
+    let test_db = dir.path("docs.db");
+
+    let config = CollectionConfig::with_index(piramid::index::IndexConfig::Flat {
+        metric: Metric::Cosine,
+        mode: piramid::ExecutionMode::Auto,
+        search: piramid::config::SearchConfig::default(),
+    });
+    let mut storage = Collection::open_with_options(&test_db, config.into()).unwrap();
+    let far = storage.insert(Document::new(vec![5.0, 0.0], "same direction, far away".into())).unwrap();
+    let near = storage.insert(Document::new(vec![1.0, 0.5], "other direction, close by".into())).unwrap();
+    assert_eq!(storage.vector_index().stats().metric, Metric::Cosine);
+
+    // The cosine index ranks `far` first; a Euclidean query must not inherit that order
+    let cosine = storage.search(&[1.0, 0.0], 1, Metric::Cosine, SearchParams::default());
+    assert_eq!(cosine[0].id, far);
+    let euclidean = storage.search(&[1.0, 0.0], 1, Metric::Euclidean, SearchParams::default());
+    assert_eq!(euclidean[0].id, near);
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::search::Filter;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{metadata, Document, Metric, SearchParams};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+fn vector(i: usize) -> Vec<f32> {
+    let angle = i as f32 * 0.05;
+    vec![angle.cos(), angle.sin(), 0.2]
+}
+
+#[test]
+fn excluded_documents_leave_k_others_in_order() {
+    let dir = TestDir::new("search_exclusion");
+    let mut storage = dir.open("docs", Default::default()).unwrap();
+    let ids: Vec<_> = (0..40)
+        .map(|i| {
+            let group = format!("g{}", i / 2);
+            storage.insert(Document::with_metadata(vector(i), format!("doc {i}"), metadata([("group", group.into())]))).unwrap()
+        })
+        .collect();
+    let search = |params: SearchParams| storage.search(&vector(0), 5, Metric::Cosine, params).into_iter().map(|h| h.id).collect::<Vec<_>>();
+
+    // The next page is the rest of a longer search
+    let first = search(SearchParams::default());
+    assert_eq!(first, ids[..5]);
+    let seen: HashSet<_> = first.iter().copied().collect();
+    let next = search(SearchParams { exclude_ids: Some(&seen), ..SearchParams::default() });
+    assert_eq!(next, ids[5..10]);
+
+    // Left out before deduplication: an excluded document does not stand in for its group
+    let seen: HashSet<_> = [ids[0], ids[2]].into_iter().collect();
+    let deduped = search(SearchParams { exclude_ids: Some(&seen), dedup_by: Some("group"), ..SearchParams::default() });
+    assert_eq!(deduped, [ids[1], ids[3], ids[4], ids[6], ids[8]]);
+
+    // Both kinds together
+    let filter = Filter::new().not(Filter::new().eq("group", "g3"));
+    let seen: HashSet<_> = [ids[1]].into_iter().collect();
+    let both = search(SearchParams { exclude_ids: Some(&seen), filter: Some(&filter), ..SearchParams::default() });
+    assert_eq!(both, [ids[0], ids[2], ids[3], ids[4], ids[5]]);
+}
+
+#[tokio::test]
+async fn search_requests_leave_out_ids_and_filter_matches() {
+    let dir = TestDir::new("search_exclusion_api");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let vectors: Vec<Vec<f32>> = (0..20).map(vector).collect();
+    let texts: Vec<String> = (0..20).map(|i| format!("doc {i}")).collect();
+    let metadata: Vec<Value> = (0..20).map(|i| json!({"kind": if i % 4 == 3 { "ad" } else { "post" }})).collect();
+    let ids: Vec<String> = (0..20).map(|i| format!("item-{i}")).collect();
+    let res = client.post(format!("{base}/feed/vectors"))
+        .json(&json!({"vectors": vectors, "texts": texts, "metadata_list": metadata, "external_ids": ids}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let search = |body: Value| {
+        let client = client.clone();
+        let url = format!("{base}/feed/search");
+        async move { client.post(url).json(&body).send().await.unwrap() }
+    };
+    let texts_of = |res: Value| res["results"].as_array().unwrap().iter().map(|h| h["text"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+
+    // "Load more": the first page's ids (UUIDs or client ids) are left out of the second
+    let page: Value = search(json!({"vector": vector(0), "k": 3})).await.json().await.unwrap();
+    let seen: Vec<Value> = page["results"].as_array().unwrap().iter().map(|h| h["id"].clone()).collect();
+    assert_eq!(texts_of(page), ["doc 0", "doc 1", "doc 2"]);
+    let next: Value = search(json!({"vector": vector(0), "k": 3, "exclude_ids": seen})).await.json().await.unwrap();
+    assert_eq!(texts_of(next), ["doc 3", "doc 4", "doc 5"]);
+    let next: Value = search(json!({"vector": vector(0), "k": 3, "exclude_ids": ["item-0", "item-1", "item-2", "gone"]})).await.json().await.unwrap();
+    assert_eq!(texts_of(next), ["doc 3", "doc 4", "doc 5"]);
+
+    // By filter, with ids, and with a query by id (which leaves the document itself out too)
+    let res: Value = search(json!({"vector": vector(0), "k": 4, "exclude_filter": {"kind": "ad"}, "exclude_ids": ["item-1"]})).await.json().await.unwrap();
+    assert_eq!(texts_of(res), ["doc 0", "doc 2", "doc 4", "doc 5"]);
+    let res: Value = search(json!({"id": "item-0", "k": 2, "exclude_ids": ["item-1"]})).await.json().await.unwrap();
+    assert_eq!(texts_of(res), ["doc 2", "doc 3"]);
+    let res: Value = search(json!({"vectors": [vector(0), vector(19)], "k": 1, "exclude_ids": ["item-0", "item-19"]})).await.json().await.unwrap();
+    assert_eq!((res["results"][0][0]["text"].as_str(), res["results"][1][0]["text"].as_str()), (Some("doc 1"), Some("doc 18")), "{res}");
+
+    let too_many: Vec<String> = (0..10_001).map(|i| format!("x{i}")).collect();
+    assert_eq!(search(json!({"vector": vector(0), "exclude_ids": too_many})).await.status(), 400);
+}
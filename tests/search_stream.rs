@@ -0,0 +1,80 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::server::types::ndjson;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+fn vector(i: usize) -> Vec<f32> {
+    common::wave(i, 32, 0.61)
+}
+
+async fn serve(data_dir: &str) -> (reqwest::Client, String) {
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+    for batch in 0..3 {
+        let vectors: Vec<Vec<f32>> = (batch * 1000..(batch + 1) * 1000).map(vector).collect();
+        let texts: Vec<String> = (batch * 1000..(batch + 1) * 1000).map(|i| format!("doc {i}")).collect();
+        let res = client.post(format!("{base}/vectors")).json(&json!({"vectors": vectors, "texts": texts})).send().await.unwrap();
+        assert_eq!(res.status(), 200);
+    }
+    (client, base)
+}
+
+fn lines(body: &str) -> Vec<Value> {
+    body.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+}
+
+#[tokio::test]
+async fn large_k_streams_one_line_per_hit() {
+    let dir = TestDir::new("search_stream");
+    let data_dir = dir.data_dir();
+    let (client, base) = serve(data_dir).await;
+    let search = json!({"vector": vector(7), "k": 2000});
+
+    let buffered: Value = client.post(format!("{base}/search")).json(&search).send().await.unwrap().json().await.unwrap();
+    let res = client.post(format!("{base}/search")).header("accept", "application/x-ndjson").json(&search).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], ndjson::CONTENT_TYPE);
+    // Sent in chunks as it is encoded, with no length known up front
+    assert!(res.content_length().is_none());
+    let streamed = lines(&res.text().await.unwrap());
+
+    let (summary, hits) = streamed.split_last().unwrap();
+    assert_eq!((summary["done"].as_bool(), summary["hits"].as_u64()), (Some(true), Some(2000)));
+    assert!(summary["latency_ms"].is_number());
+    let expected = buffered["results"].as_array().unwrap();
+    assert_eq!(hits.len(), expected.len());
+    for (hit, want) in hits.iter().zip(expected) {
+        assert_eq!((&hit["id"], &hit["text"]), (&want["id"], &want["text"]));
+        assert!(hit.get("query").is_none());
+    }
+    assert_eq!(hits[0]["text"], "doc 7");
+}
+
+#[tokio::test]
+async fn batch_hits_carry_their_query() {
+    let dir = TestDir::new("search_stream_batch");
+    let data_dir = dir.data_dir();
+    let (client, base) = serve(data_dir).await;
+    let search = json!({"vectors": [vector(1), vector(2), vector(3)], "k": 50});
+    let res = client.post(format!("{base}/search")).header("accept", "application/x-ndjson, application/json;q=0.5").json(&search).send().await.unwrap();
+    let streamed = lines(&res.text().await.unwrap());
+    let (summary, hits) = streamed.split_last().unwrap();
+    assert_eq!(summary["hits"].as_u64(), Some(150));
+    let queries: Vec<u64> = hits.iter().map(|h| h["query"].as_u64().unwrap()).collect();
+    assert!(queries.windows(2).all(|w| w[0] <= w[1]) && queries[0] == 0 && queries[149] == 2);
+    for (query, doc) in [(0, "doc 1"), (1, "doc 2"), (2, "doc 3")] {
+        assert_eq!(hits[query * 50]["text"], doc);
+    }
+
+    // JSON stays the default, and wins when the client prefers it
+    assert!(ndjson::accepts("application/x-ndjson"));
+    assert!(!ndjson::accepts("application/json, application/x-ndjson;q=0.5"));
+    assert!(!ndjson::accepts("*/*"));
+    let res = client.post(format!("{base}/search")).header("accept", "*/*").json(&search).send().await.unwrap();
+    assert!(res.headers()["content-type"].to_str().unwrap().starts_with("application/json"));
+}
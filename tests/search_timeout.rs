@@ -0,0 +1,111 @@
+mod common;
+
+use common::random_vector;
+use piramid::config::{AppConfig, ExecutionMode, QueryCacheConfig, SearchConfig};
+use piramid::index::IndexConfig;
+use piramid::search::search_target_in_rounds;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{Collection, CollectionConfig, Document, Metric, SearchParams};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn params(search: Option<SearchConfig>) -> SearchParams<'static> {
+    SearchParams {
+        mode: ExecutionMode::default(),
+        filter: None,
+        filter_overfetch_override: None,
+        search_config_override: search,
+        dedup_by: None,
+        score_expr: None,
+        order_by: None,
+        exclude_ids: None,
+    }
+}
+
+#[test]
+fn deadline_searches_run_a_quick_round_before_the_configured_one() {
+    let dir = TestDir::new("search_timeout_rounds");
+    let ivf = CollectionConfig::with_index(IndexConfig::Ivf {
+        num_clusters: 16,
+        num_probes: 8,
+        max_iterations: 10,
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    });
+    let mut storage = dir.open("ivf", ivf).unwrap();
+    storage.insert_batch((0..1000).map(|i| Document::new(random_vector(i, 16), format!("doc {i}"))).collect()).unwrap();
+    let query = random_vector(5000, 16);
+
+    let mut rounds = Vec::new();
+    search_target_in_rounds(&storage, &query, 10, Metric::Cosine, params(None), |hits, last| rounds.push((hits, last)));
+    assert_eq!(rounds.iter().map(|(_, last)| *last).collect::<Vec<_>>(), vec![false, true]);
+    // A quarter of the probes first, then the configured search
+    let quick = SearchConfig { nprobe: Some(2), ..storage.config().search };
+    let ids = |hits: &[piramid::search::Hit]| hits.iter().map(|h| h.id).collect::<Vec<_>>();
+    assert_eq!(ids(&rounds[0].0), ids(&storage.search(&query, 10, Metric::Cosine, params(Some(quick)))));
+    assert_eq!(ids(&rounds[1].0), ids(&storage.search(&query, 10, Metric::Cosine, params(None))));
+
+    // Exhaustive indexes have nothing cheaper to offer: one round
+    let flat = CollectionConfig::with_index(IndexConfig::Flat { metric: Metric::Cosine, mode: ExecutionMode::default(), search: SearchConfig::default() });
+    let mut storage = dir.open("flat", flat).unwrap();
+    storage.insert_batch((0..100).map(|i| Document::new(random_vector(i, 16), format!("doc {i}"))).collect()).unwrap();
+    let mut rounds = 0;
+    search_target_in_rounds(&storage, &query, 10, Metric::Cosine, params(None), |hits, last| {
+        assert!(last && hits.len() == 10);
+        rounds += 1;
+    });
+    assert_eq!(rounds, 1);
+}
+
+#[tokio::test]
+async fn searches_past_their_timeout_return_cached_results_or_time_out() {
+    let dir = TestDir::new("search_timeout_http");
+    let data_dir = dir.data_dir();
+    {
+        let flat = CollectionConfig::with_index(IndexConfig::Flat { metric: Metric::Cosine, mode: ExecutionMode::default(), search: SearchConfig::default() });
+        let mut storage = Collection::open_with_options(&format!("{data_dir}/docs.db"), flat.into()).unwrap();
+        storage.insert_batch((0..20_000).map(|i| Document::new(random_vector(i, 128), format!("doc {i}"))).collect()).unwrap();
+        storage.checkpoint().unwrap();
+    }
+    let config = AppConfig { query_cache: QueryCacheConfig { enabled: true, ..Default::default() }, ..Default::default() };
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    state.discover_collections().unwrap();
+    let api = common::serve(state).await;
+    let client = reqwest::Client::new();
+    let search = |body: Value| client.post(format!("{api}/collections/docs/search")).json(&body).send();
+
+    // A generous deadline: the full results, flagged as neither
+    let full: Value = search(json!({"vector": random_vector(7, 128), "k": 3, "timeout_ms": 60_000})).await.unwrap().json().await.unwrap();
+    assert_eq!(full["results"][0]["text"], "doc 7");
+    assert!(full.get("partial").is_none() && full.get("stale").is_none(), "{full}");
+
+    // After a write the cached results are stale, and a search out of time falls back on them
+    client.post(format!("{api}/collections/docs/vectors")).json(&json!({"vector": random_vector(99_999, 128), "text": "new"})).send().await.unwrap();
+    let stale: Value = search(json!({"vector": random_vector(7, 128), "k": 3, "timeout_ms": 1, "on_timeout": "cached"})).await.unwrap().json().await.unwrap();
+    assert_eq!(stale["stale"], true, "{stale}");
+    assert_eq!(stale["results"], full["results"]);
+
+    // Nothing to fall back on: a timeout error, while the search finishes and fills the cache
+    let query = json!({"vector": random_vector(8, 128), "k": 3, "timeout_ms": 1, "on_timeout": "partial"});
+    let res = search(query.clone()).await.unwrap();
+    assert_eq!(res.status(), 408);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!(body["error_code"], "TIMEOUT");
+    let mut late = Value::Null;
+    for _ in 0..100 {
+        late = search(query.clone()).await.unwrap().json().await.unwrap();
+        if late["results"].is_array() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(late["results"][0]["text"], "doc 8", "{late}");
+    assert!(late.get("stale").is_none());
+
+    for bad in [json!({"vector": random_vector(1, 128), "timeout_ms": 0}), json!({"vectors": [random_vector(1, 128)], "timeout_ms": 10})] {
+        assert_eq!(search(bad).await.unwrap().status(), 400);
+    }
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use piramid::config::{CollectionConfig, ExecutionMode, SearchConfig};
+use piramid::index::{IndexConfig, IndexDetails, IndexType, VectorIndex};
+use piramid::testing::TestDir;
+use piramid::{Collection, Document, Metric, SearchParams};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+// Pseudo-random, so no two documents point the same way
+fn vector(i: usize) -> Vec<f32> {
+    common::random_vector(i, 16)
+}
+
+fn hnsw(shards: usize) -> IndexConfig {
+    IndexConfig::Hnsw {
+        m: 8,
+        m_max: 16,
+        ef_construction: 100,
+        ef_search: 64,
+        ml: 1.0 / 8f32.ln(),
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+        shards,
+    }
+}
+
+fn recall(storage: &Collection, exact: &HashMap<usize, HashSet<Uuid>>) -> f32 {
+    let found: usize = exact
+        .iter()
+        .map(|(q, truth)| {
+            let hits = storage.search(&vector(*q), 10, Metric::Cosine, SearchParams::default());
+            hits.iter().filter(|hit| truth.contains(&hit.id)).count()
+        })
+        .sum();
+    found as f32 / (exact.len() * 10) as f32
+}
+
+#[test]
+fn sharded_graphs_are_searched_together_and_reported_per_shard() {
+    let dir = TestDir::new("sharded_hnsw");
+    let docs = |storage: &mut Collection| {
+        storage.insert_batch((0..1200).map(|i| Document::new(vector(i), format!("doc {i}"))).collect()).unwrap()
+    };
+    let mut flat = dir.open("flat", CollectionConfig::with_index(IndexConfig::Flat {
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    })).unwrap();
+    let flat_ids = docs(&mut flat);
+    let mut sharded = dir.open("sharded", CollectionConfig::with_index(hnsw(4))).unwrap();
+    let ids = docs(&mut sharded);
+    let position: HashMap<Uuid, usize> = flat_ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    // Exact top 10 of each query, as ids of the sharded collection
+    let exact: HashMap<usize, HashSet<Uuid>> = (5000..5040)
+        .map(|q| {
+            let hits = flat.search(&vector(q), 10, Metric::Cosine, SearchParams::default());
+            (q, hits.iter().map(|hit| ids[position[&hit.id]]).collect())
+        })
+        .collect();
+    assert!(recall(&sharded, &exact) >= 0.9);
+
+    let stats = sharded.vector_index().stats();
+    assert_eq!((stats.index_type, stats.total_vectors), (IndexType::Hnsw, 1200));
+    let IndexDetails::Hnsw { shards, .. } = &stats.details else { panic!("{stats:?}") };
+    assert_eq!(shards.len(), 4);
+    assert_eq!(shards.iter().map(|s| s.vectors).sum::<usize>(), 1200);
+    assert!(shards.iter().all(|s| s.vectors > 200), "{shards:?}");
+
+    // Deletes reach the shard holding the document, and the split survives a restart
+    sharded.delete(&ids[3]).unwrap();
+    let hits = sharded.search(&vector(3), 1, Metric::Cosine, SearchParams::default());
+    assert_ne!(hits[0].id, ids[3]);
+    sharded.checkpoint().unwrap();
+    drop(sharded);
+    let sharded = dir.open("sharded", CollectionConfig::with_index(hnsw(4))).unwrap();
+    assert_eq!(sharded.vector_index().shards(), 4);
+    assert_eq!(sharded.vector_index().stats().total_vectors, 1199);
+    let hits = sharded.search(&vector(7), 1, Metric::Cosine, SearchParams::default());
+    assert_eq!(hits[0].id, ids[7]);
+}
+
+#[test]
+fn shards_default_to_one_graph() {
+    // Configs written before sharding have no `shards`
+    let config: IndexConfig = serde_json::from_value(serde_json::json!({
+        "type": "Hnsw", "m": 8, "m_max": 16, "ef_construction": 100, "ef_search": 64, "ml": 0.48, "metric": "Cosine"
+    })).unwrap();
+    assert!(matches!(config, IndexConfig::Hnsw { shards: 1, .. }));
+    let single = config.create_index(0);
+    assert_eq!(single.shards(), 1);
+    let IndexDetails::Hnsw { shards, .. } = single.stats().details else { panic!() };
+    assert!(shards.is_empty());
+
+    // Every vector is found at itself, whichever shard it went to
+    let mut index: Box<dyn VectorIndex> = hnsw(3).create_index(0);
+    let vectors: HashMap<Uuid, Vec<f32>> = (0..300).map(|i| (Uuid::new_v4(), vector(i))).collect();
+    for (id, v) in &vectors {
+        index.insert(*id, v, &vectors);
+    }
+    assert_eq!(index.shards(), 3);
+    assert_eq!(index.connectivity().unwrap().components, 3);
+    for (id, v) in vectors.iter().take(50) {
+        let hits = index.search(v, 1, &vectors, SearchConfig::default(), None, &HashMap::new());
+        assert_eq!(hits, vec![*id]);
+    }
+    let removed = *vectors.keys().next().unwrap();
+    index.remove(&removed);
+    assert_eq!(index.ids().len(), 299);
+    assert!(!index.ids().contains(&removed));
+}
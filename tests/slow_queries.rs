@@ -0,0 +1,82 @@
+mod common;
+
+use piramid::config::{AppConfig, SlowQueryConfig};
+use piramid::server::slow_queries::{SlowQuery, SlowQueryLog};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+fn vector(i: usize) -> Vec<f32> {
+    common::wave(i, 32, 0.61)
+}
+
+#[test]
+fn ring_buffer_keeps_the_newest_queries() {
+    let log = SlowQueryLog::new(&SlowQueryConfig { enabled: true, capacity: 3, store_queries: true });
+    for i in 0..5 {
+        let collection = if i % 2 == 0 { "even" } else { "odd" };
+        log.record(SlowQuery { collection: collection.into(), vectors: vec![vector(i)], k: i, ..Default::default() });
+    }
+    let held: Vec<usize> = log.list(None, 10).iter().map(|q| q.k).collect();
+    assert_eq!(held, [4, 3, 2]);
+    assert_eq!(log.list(Some("even"), 10).iter().map(|q| q.k).collect::<Vec<_>>(), [4, 2]);
+    assert_eq!(log.list(None, 1)[0].vectors, [vector(4)]);
+    assert!(log.list(None, 10).windows(2).all(|w| w[0].id > w[1].id));
+
+    // Without store_queries only the hash is kept, and it matches the stored-query hash
+    let hashed = SlowQueryLog::new(&SlowQueryConfig { enabled: true, capacity: 3, store_queries: false });
+    hashed.record(SlowQuery { vectors: vec![vector(4)], text: Some("secret".into()), ..Default::default() });
+    let entry = &hashed.list(None, 1)[0];
+    assert!(entry.vectors.is_empty() && entry.text.is_none());
+    assert_eq!(entry.query_hash, log.list(None, 1)[0].query_hash);
+
+    let disabled = SlowQueryLog::new(&SlowQueryConfig::default());
+    disabled.record(SlowQuery::default());
+    assert!(disabled.is_empty());
+    assert_eq!(log.clear(), 3);
+}
+
+#[tokio::test]
+async fn slow_searches_are_captured_and_can_be_replayed() {
+    let dir = TestDir::new("slow_queries");
+    let data_dir = dir.data_dir();
+    let mut config = AppConfig::default();
+    config.slow_queries.enabled = true;
+    // Every search of a millisecond or more counts as slow
+    let state = Arc::new(AppState::new(data_dir, config, 0, None, false, None));
+    let base = common::serve(state).await;
+    let client = reqwest::Client::new();
+
+    let vectors: Vec<Vec<f32>> = (0..1000).map(vector).collect();
+    let texts: Vec<String> = (0..1000).map(|i| format!("doc {i}")).collect();
+    let res = client.post(format!("{base}/collections/docs/vectors")).json(&json!({"vectors": vectors, "texts": texts})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    let queries: Vec<Vec<f32>> = (0..200).map(|i| vector(i * 5)).collect();
+    let search = json!({"vectors": queries, "k": 5, "ef": 64, "dedup_by": "group"});
+    let res = client.post(format!("{base}/collections/docs/search")).json(&search).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    let log: Value = client.get(format!("{base}/slow_queries?collection=docs")).send().await.unwrap().json().await.unwrap();
+    assert_eq!((log["enabled"].as_bool(), log["threshold_ms"].as_u64()), (Some(true), Some(0)));
+    let captured = &log["queries"][0];
+    assert_eq!(captured["kind"], "batch_search", "{log}");
+    assert_eq!((captured["k"].as_u64(), captured["search"]["ef"].as_u64(), captured["dedup_by"].as_str()), (Some(5), Some(64), Some("group")));
+    assert!(captured["latency"]["search_ms"].as_f64().unwrap() >= 1.0, "{captured}");
+    assert!(captured["latency"]["lock_wait_ms"].is_number());
+    assert_eq!(captured["vectors"].as_array().unwrap().len(), 200);
+
+    // The captured parameters are enough to send the same query again
+    let replay = json!({"vectors": captured["vectors"], "k": captured["k"], "ef": captured["search"]["ef"], "dedup_by": captured["dedup_by"]});
+    client.post(format!("{base}/collections/docs/search")).json(&replay).send().await.unwrap();
+    let log: Value = client.get(format!("{base}/slow_queries?limit=1")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(log["queries"].as_array().unwrap().len(), 1);
+    assert_eq!(log["queries"][0]["query_hash"], captured["query_hash"]);
+    assert!(log["queries"][0]["id"].as_u64() > captured["id"].as_u64());
+
+    let other: Value = client.get(format!("{base}/slow_queries?collection=other")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(other["queries"].as_array().unwrap().len(), 0);
+    let cleared: Value = client.delete(format!("{base}/slow_queries")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(cleared["cleared"].as_u64(), log["held"].as_u64());
+}
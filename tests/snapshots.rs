@@ -0,0 +1,85 @@
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::storage::collection::{delete_snapshot, list_snapshots};
+use piramid::testing::TestDir;
+use piramid::{Document, Metric, SearchParams};
+
+fn state(data_dir: &str) -> AppState {
+    AppState::new(data_dir, AppConfig::default(), 500, None, false, None)
+}
+
+fn insert(state: &AppState, collection: &str, vectors: &[[f32; 3]]) {
+    state.get_or_create_collection(collection).unwrap();
+    let handle = state.collections.get(collection).unwrap();
+    let mut storage = handle.write();
+    for (i, v) in vectors.iter().enumerate() {
+        storage.insert(Document::new(v.to_vec(), format!("doc {i}"))).unwrap();
+    }
+}
+
+fn count(state: &AppState, collection: &str) -> usize {
+    state.collections.get(collection).unwrap().read().count()
+}
+
+#[test]
+fn restore_replaces_collection_in_place() {
+    let dir = TestDir::new("snapshots_in_place");
+    let data_dir = dir.data_dir();
+    let state = state(data_dir);
+    insert(&state, "docs", &[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+
+    let manifest = state.create_snapshot("docs", "before").unwrap();
+    assert_eq!(manifest.vector_count, 2);
+    assert!(state.create_snapshot("docs", "before").is_err());
+
+    insert(&state, "docs", &[[0.0, 0.0, 1.0], [1.0, 1.0, 0.0], [0.0, 1.0, 1.0]]);
+    assert_eq!(count(&state, "docs"), 5);
+
+    let (target, replaced) = state.restore_snapshot("docs", "before", None).unwrap();
+    assert_eq!((target.as_str(), replaced), ("docs", true));
+    assert_eq!(count(&state, "docs"), 2);
+
+    // The restored collection is fully usable: searchable and writable
+    let handle = state.collections.get("docs").unwrap();
+    let hits = handle.read().search(&[0.0, 1.0, 0.0], 1, Metric::Cosine, SearchParams::default());
+    assert_eq!(hits[0].text, "doc 1");
+    handle.write().insert(Document::new(vec![0.5, 0.5, 0.0], "after".into())).unwrap();
+    assert_eq!(handle.read().count(), 3);
+    drop(handle);
+
+    drop(state);
+}
+
+#[test]
+fn restore_into_new_collection_and_manage_snapshots() {
+    let dir = TestDir::new("snapshots_new_target");
+    let data_dir = dir.data_dir();
+    let state = state(data_dir);
+    insert(&state, "docs", &[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+    insert(&state, "other", &[[1.0, 0.0, 0.0]]);
+    state.create_snapshot("docs", "v1").unwrap();
+    insert(&state, "docs", &[[0.0, 0.0, 1.0]]);
+    state.create_snapshot("docs", "v2").unwrap();
+
+    let root = state.snapshot_root("docs");
+    let names: Vec<_> = list_snapshots(&root).unwrap().into_iter().map(|m| m.name).collect();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"v1".to_string()) && names.contains(&"v2".to_string()));
+
+    // Existing collections other than the source are never overwritten
+    assert!(state.restore_snapshot("docs", "v1", Some("other")).is_err());
+    assert_eq!(count(&state, "other"), 1);
+
+    let (target, replaced) = state.restore_snapshot("docs", "v1", Some("docs_v1")).unwrap();
+    assert_eq!((target.as_str(), replaced), ("docs_v1", false));
+    assert_eq!(count(&state, "docs_v1"), 2);
+    assert_eq!(state.collections.get("docs_v1").unwrap().read().metadata().name, "docs_v1");
+    assert_eq!(count(&state, "docs"), 3);
+
+    assert!(delete_snapshot(&root.join("v1")).unwrap());
+    assert!(!delete_snapshot(&root.join("v1")).unwrap());
+    assert_eq!(list_snapshots(&root).unwrap().len(), 1);
+    assert!(state.restore_snapshot("docs", "v1", Some("docs_again")).is_err());
+
+    drop(state);
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::storage::collection::StatsOptions;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document, Filter};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+// Pseudo-random points of a plane laid into 8 dimensions; "new" documents are shifted along the last axis
+fn seed(path: &str) {
+    let mut storage = Collection::open(path).unwrap();
+    let mut docs = Vec::new();
+    let mut state = 7u64;
+    let mut next = move || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 40) as f32 / (1u64 << 24) as f32 * 2.0
+    };
+    for (model, shift) in [("old", 0.0), ("new", 3.0)] {
+        for i in 0..400 {
+            let (a, b) = (next(), next());
+            let vector = vec![1.0 + a, b, a - b, 0.5 * a, 0.5 * b, a + b, 0.0, 1.0 + shift];
+            docs.push(Document::with_metadata(vector, format!("{model} {i}"), metadata([("model", model.into())])));
+        }
+    }
+    storage.insert_batch(docs).unwrap();
+    storage.checkpoint().unwrap();
+}
+
+#[test]
+fn statistics_describe_the_vectors_and_their_drift() {
+    let dir = TestDir::new("statistics_files");
+    let path = dir.path("docs.db");
+    seed(&path);
+    let storage = Collection::open(&path).unwrap();
+
+    let all = storage.vector_stats(&StatsOptions::default()).unwrap();
+    assert_eq!((all.count, all.dimensions), (800, 8));
+    assert!((all.mean[7] - 2.5).abs() < 0.05, "{:?}", all.mean);
+    assert!((all.variance[7] - 2.25).abs() < 0.1, "{:?}", all.variance);
+    assert!(all.variance[6] < 1e-4);
+    assert!((all.total_variance - all.variance.iter().sum::<f32>()).abs() < 1e-3);
+    assert_eq!(all.norms.histogram.len(), 20);
+    assert_eq!(all.norms.histogram.iter().map(|b| b.count).sum::<usize>(), 800);
+    assert_eq!((all.norms.histogram[0].lower, all.norms.histogram[19].upper), (all.norms.min, all.norms.max));
+    let estimate = all.intrinsic_dimension.as_ref().unwrap().estimate;
+    assert!((1.5..4.0).contains(&estimate), "intrinsic dimension {estimate}");
+
+    // Each model's vectors on their own: the centroids point different ways
+    let of = |model: &str| StatsOptions { filter: Some(Filter::new().eq("model", model)), ..Default::default() };
+    let old = storage.vector_stats(&of("old")).unwrap();
+    let new = storage.vector_stats(&of("new")).unwrap();
+    assert_eq!((old.count, new.count), (400, 400));
+    assert!((old.mean[7] - 1.0).abs() < 0.05 && (new.mean[7] - 4.0).abs() < 0.05);
+    let cosine = old.centroid.iter().zip(&new.centroid).map(|(a, b)| a * b).sum::<f32>() / (old.centroid_norm * new.centroid_norm);
+    assert!(cosine < 0.95, "{cosine}");
+    assert!(old.centroid_norm > 0.0 && old.centroid_norm <= 1.0 + 1e-5);
+
+    let skipped = storage.vector_stats(&StatsOptions { intrinsic_sample: 0, histogram_bins: 4, ..Default::default() }).unwrap();
+    assert!(skipped.intrinsic_dimension.is_none());
+    assert_eq!(skipped.norms.histogram.len(), 4);
+}
+
+#[tokio::test]
+async fn statistics_endpoint_reports_and_validates() {
+    let dir = TestDir::new("statistics_http");
+    let data_dir = dir.data_dir();
+    seed(&format!("{data_dir}/docs.db"));
+
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    state.discover_collections().unwrap();
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let res: Value = client.post(format!("{base}/statistics")).send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["count"].as_u64(), res["dimensions"].as_u64()), (Some(800), Some(8)));
+    assert_eq!(res["centroid"].as_array().unwrap().len(), 8);
+    assert_eq!(res["norms"]["histogram"].as_array().unwrap().len(), 20);
+    assert_eq!(res["intrinsic_dimension"]["method"], "two_nn");
+
+    let res: Value = client.post(format!("{base}/statistics"))
+        .json(&json!({"filter": {"model": "new"}, "histogram_bins": 5, "intrinsic_sample": 0}))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["count"], 400);
+    assert_eq!(res["norms"]["histogram"].as_array().unwrap().len(), 5);
+    assert!(res["intrinsic_dimension"].is_null());
+
+    for body in [json!({"histogram_bins": 0}), json!({"intrinsic_sample": 1_000_000})] {
+        let res = client.post(format!("{base}/statistics")).json(&body).send().await.unwrap();
+        assert_eq!(res.status(), 400);
+    }
+}
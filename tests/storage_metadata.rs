@@ -1,4 +1,5 @@
 use piramid::storage::CollectionMetadata;
+use piramid::testing::TestDir;
 use std::thread;
 use std::time::Duration;
 
@@ -28,3 +29,47 @@ fn metadata_touch_and_counts() {
     assert_eq!(meta.vector_count, 100);
     assert!(meta.updated_at >= meta.created_at);
 }
+
+#[test]
+fn embedding_model_guard() {
+    let mut meta = CollectionMetadata::new("docs".into());
+    assert!(meta.check_embedding_model("any", Some(8)).is_ok());
+
+    assert!(meta.set_embedding_model("model-a", 384));
+    assert!(!meta.set_embedding_model("model-b", 768));
+    assert!(meta.check_embedding_model("model-a", Some(384)).is_ok());
+    assert!(meta.check_embedding_model("model-a", None).is_ok());
+    assert!(meta.check_embedding_model("model-b", None).is_err());
+    assert!(meta.check_embedding_model("model-a", Some(768)).is_err());
+}
+
+#[test]
+fn v1_metadata_files_are_upgraded() {
+    #[derive(serde::Serialize)]
+    struct V1 {
+        schema_version: u32,
+        name: String,
+        created_at: u64,
+        updated_at: u64,
+        dimensions: Option<usize>,
+        vector_count: usize,
+    }
+
+    let dir = TestDir::new("metadata_v1");
+    let path = dir.path("old.db");
+    let v1 = V1 {
+        schema_version: 1,
+        name: "old".into(),
+        created_at: 1,
+        updated_at: 2,
+        dimensions: Some(4),
+        vector_count: 7,
+    };
+    std::fs::write(format!("{path}.metadata.db"), bincode::serialize(&v1).unwrap()).unwrap();
+
+    let storage = piramid::Collection::open(&path).unwrap();
+    let meta = storage.metadata();
+    assert_eq!(meta.schema_version, 5);
+    assert_eq!((meta.name.as_str(), meta.dimensions), ("old", Some(4)));
+    assert!(meta.embedding_model.is_none());
+}
@@ -0,0 +1,90 @@
+use piramid::config::{CollectionConfig, WalConfig};
+use piramid::storage::collection::create_snapshot;
+use piramid::testing::{ephemeral_collection, fail_wal_after, freeze_clock, seed_ids, TestDir};
+use piramid::storage::CREATED_AT_KEY;
+use piramid::Document;
+use std::fs;
+use uuid::Uuid;
+
+fn doc(i: usize) -> Document {
+    let angle = i as f32 * 0.3;
+    Document::new(vec![angle.cos(), angle.sin(), 1.0], format!("doc {i}"))
+}
+
+// Five acknowledged inserts, then one torn 20 bytes in
+fn crash_mid_append(dir: &TestDir) -> Vec<Uuid> {
+    let _ids = seed_ids(7);
+    let _clock = freeze_clock(1_700_000_000);
+    let mut storage = dir.open("docs", CollectionConfig::default()).unwrap();
+    let ids: Vec<Uuid> = (0..5).map(|i| storage.insert(doc(i)).unwrap()).collect();
+
+    let fault = fail_wal_after(&dir.path("docs.db"), 20);
+    let err = storage.insert(doc(5)).unwrap_err();
+    assert!(err.to_string().contains("injected WAL fault"), "{err}");
+    assert_eq!(fault.remaining(), 0);
+    assert!(storage.insert(doc(6)).is_err());
+    drop(storage);
+    ids
+}
+
+#[test]
+fn torn_wal_append_replays_the_acknowledged_writes() {
+    let first = TestDir::new("testing_harness_crash_a");
+    let second = TestDir::new("testing_harness_crash_b");
+    let ids = crash_mid_append(&first);
+    // Same seed and clock: the same ids, and a log of the same entries torn at the same byte
+    assert_eq!(crash_mid_append(&second), ids);
+    let wal = |dir: &TestDir| fs::read(dir.path("docs.db.wal.db")).unwrap();
+    assert_eq!(wal(&first).len(), wal(&second).len());
+    assert!(!wal(&first).ends_with(b"\n"));
+
+    let mut storage = first.open("docs", CollectionConfig::default()).unwrap();
+    assert_eq!(storage.count(), 5);
+    for id in &ids {
+        let created = storage.get(id).unwrap().metadata.get(CREATED_AT_KEY).and_then(|v| v.as_integer());
+        assert_eq!(created, Some(1_700_000_000));
+    }
+    // The torn entry was cut off, so later appends start on a line of their own
+    assert!(wal(&first).ends_with(b"\n"));
+    storage.insert(doc(7)).unwrap();
+    drop(storage);
+    assert_eq!(first.open("docs", CollectionConfig::default()).unwrap().count(), 6);
+}
+
+#[test]
+fn frozen_clock_drives_interval_checkpoints() {
+    let dir = TestDir::new("testing_harness_clock");
+    let clock = freeze_clock(1_000);
+    let wal = WalConfig { checkpoint_frequency: 1_000_000, checkpoint_interval_secs: Some(60), ..Default::default() };
+    let config = CollectionConfig { wal, ..Default::default() };
+    let mut storage = dir.open("docs", config).unwrap();
+    assert_eq!(storage.metadata().created_at, 1_000);
+    storage.insert(doc(0)).unwrap();
+    storage.checkpoint().unwrap();
+    assert_eq!(storage.last_checkpoint(), Some(1_000));
+
+    clock.advance(59);
+    storage.insert(doc(1)).unwrap();
+    storage.wait_for_checkpoint();
+    assert_eq!(storage.last_checkpoint(), Some(1_000));
+    clock.advance(1);
+    storage.insert(doc(2)).unwrap();
+    storage.wait_for_checkpoint();
+    assert_eq!(storage.last_checkpoint(), Some(1_060));
+    // Snapshots and the other storage timestamps read the same clock
+    let manifest = create_snapshot(&mut storage, &dir.root().join("snapshots"), "s1").unwrap();
+    assert_eq!(manifest.created_at, 1_060);
+    drop(storage);
+    drop(clock);
+
+    // Ephemeral collections write nothing, and seeded ids repeat across them
+    let ids = |seed| {
+        let _ids = seed_ids(seed);
+        let mut storage = ephemeral_collection("scratch", CollectionConfig::default()).unwrap();
+        (0..3).map(|i| storage.insert(doc(i)).unwrap()).collect::<Vec<_>>()
+    };
+    assert_eq!(ids(1), ids(1));
+    assert_ne!(ids(1), ids(2));
+    assert!(!std::path::Path::new("scratch.db").exists());
+    assert!(dir.root().exists());
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use piramid::config::{AppConfig, QueryLogConfig};
+use piramid::embeddings::{Embedder, EmbeddingResponse, EmbeddingResult};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Embeds "doc N" near the Nth axis; counts single and batch calls
+#[derive(Default)]
+struct AxisEmbedder {
+    single: AtomicUsize,
+    batches: AtomicUsize,
+}
+
+fn axis(text: &str) -> Vec<f32> {
+    let n: usize = text.trim_start_matches("doc ").parse().unwrap_or(0);
+    (0..8).map(|d| if d == n % 8 { 1.0 } else { 0.1 * (d + n / 8) as f32 }).collect()
+}
+
+#[async_trait::async_trait]
+impl Embedder for AxisEmbedder {
+    async fn embed(&self, text: &str) -> EmbeddingResult<EmbeddingResponse> {
+        self.single.fetch_add(1, Ordering::Relaxed);
+        Ok(EmbeddingResponse { embedding: axis(text), tokens: Some(1), model: "axis".to_string() })
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> EmbeddingResult<Vec<EmbeddingResponse>> {
+        self.batches.fetch_add(1, Ordering::Relaxed);
+        Ok(texts.iter().map(|text| EmbeddingResponse { embedding: axis(text), tokens: Some(1), model: "axis".to_string() }).collect())
+    }
+
+    fn max_batch_size(&self) -> usize {
+        1000
+    }
+
+    fn provider_name(&self) -> &str {
+        "axis"
+    }
+
+    fn model_name(&self) -> &str {
+        "axis-v1"
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        Some(8)
+    }
+}
+
+async fn serve(data_dir: &str, config: AppConfig) -> (Arc<AxisEmbedder>, String) {
+    let embedder = Arc::new(AxisEmbedder::default());
+    let state = Arc::new(AppState::with_embedder(data_dir, config, 500, embedder.clone(), None, false, None));
+    let base = common::serve(state).await;
+    (embedder, base)
+}
+
+#[tokio::test]
+async fn batch_text_search_embeds_once_and_aligns_results() {
+    let dir = TestDir::new("text_search_batch");
+    let data_dir = dir.data_dir();
+    let config = AppConfig { query_log: QueryLogConfig { enabled: true, ..Default::default() }, ..Default::default() };
+    let (embedder, base) = serve(data_dir, config).await;
+    let client = reqwest::Client::new();
+    let texts: Vec<String> = (0..40).map(|i| format!("doc {i}")).collect();
+    let res = client.post(format!("{base}/collections/docs/vectors")).json(&json!({"texts": texts})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let before = embedder.batches.load(Ordering::Relaxed);
+
+    let queries: Vec<String> = [3, 17, 30, 5, 22].iter().map(|i| format!("doc {i}")).collect();
+    let batch: Value = client.post(format!("{base}/collections/docs/search/text"))
+        .json(&json!({"queries": queries, "k": 3})).send().await.unwrap().json().await.unwrap();
+    assert_eq!(embedder.batches.load(Ordering::Relaxed), before + 1);
+    let results = batch["results"].as_array().unwrap();
+    assert_eq!(results.len(), queries.len());
+    assert_eq!(batch["query_ids"].as_array().unwrap().len(), queries.len());
+
+    // Each entry is what the query would get on its own, in request order
+    let singles = embedder.single.load(Ordering::Relaxed);
+    for (query, hits) in queries.iter().zip(results) {
+        let single: Value = client.post(format!("{base}/collections/docs/search/text"))
+            .json(&json!({"query": query, "k": 3})).send().await.unwrap().json().await.unwrap();
+        assert_eq!(hits.as_array().unwrap().len(), 3);
+        assert_eq!(hits[0]["text"], *query);
+        let ids = |hits: &Value| hits.as_array().unwrap().iter().map(|h| h["id"].clone()).collect::<Vec<_>>();
+        assert_eq!(ids(hits), ids(&single["results"]));
+    }
+    assert_eq!(embedder.single.load(Ordering::Relaxed), singles + queries.len());
+}
+
+#[tokio::test]
+async fn batch_text_search_rejects_bad_batches_before_embedding() {
+    let dir = TestDir::new("text_search_batch_invalid");
+    let data_dir = dir.data_dir();
+    let (embedder, base) = serve(data_dir, AppConfig::default()).await;
+    let client = reqwest::Client::new();
+    let search = |body: Value| client.post(format!("{base}/collections/docs/search/text")).json(&body).send();
+    let bodies = [
+        json!({"queries": []}),
+        json!({"query": "doc 1", "queries": ["doc 2"]}),
+        json!({"k": 3}),
+        json!({"queries": ["doc 1"], "score_expr": "similarity +"}),
+    ];
+    for body in bodies {
+        assert_eq!(search(body.clone()).await.unwrap().status(), 400, "{body}");
+    }
+    assert_eq!(embedder.batches.load(Ordering::Relaxed) + embedder.single.load(Ordering::Relaxed), 0);
+}
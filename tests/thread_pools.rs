@@ -0,0 +1,67 @@
+use piramid::config::{AppConfig, CoreRange, ParallelismConfig};
+use piramid::metrics::cosine::cosine_similarity_parallel;
+use piramid::metrics::dot::dot_product_parallel;
+use piramid::metrics::euclidean::euclidean_distance_parallel;
+use piramid::parallel;
+
+fn thread_name() -> String {
+    std::thread::current().name().unwrap_or_default().to_string()
+}
+
+#[test]
+fn work_runs_on_sized_and_pinned_pools() {
+    // The pools are built once per process, so this is the only test that builds them
+    parallel::init(&ParallelismConfig {
+        query_threads: Some(2),
+        maintenance_threads: Some(1),
+        query_cores: CoreRange::parse("0"),
+        ..Default::default()
+    });
+    // A later config does not rebuild them
+    parallel::init(&ParallelismConfig::fixed(7));
+    assert_eq!((parallel::query_threads(), parallel::maintenance_threads()), (2, 1));
+
+    assert!(parallel::query(thread_name).starts_with("piramid-query-"));
+    assert!(parallel::maintenance(thread_name).starts_with("piramid-maintenance-"));
+    // Nested work stays on the pool it started on
+    assert!(parallel::maintenance(|| parallel::current(thread_name)).starts_with("piramid-maintenance-"));
+    assert!(parallel::current(thread_name).starts_with("piramid-query-"));
+    #[cfg(target_os = "linux")]
+    assert_eq!(parallel::query(|| unsafe { libc::sched_getcpu() }), 0);
+
+    let a: Vec<f32> = (0..10_000).map(|i| (i as f32 * 0.01).sin()).collect();
+    let b: Vec<f32> = (0..10_000).map(|i| (i as f32 * 0.02).cos()).collect();
+    let dot: f32 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+    let dist = a.iter().zip(&b).map(|(x, y)| (x - y) * (x - y)).sum::<f32>().sqrt();
+    let norms = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    assert!((dot_product_parallel(&a, &b) - dot).abs() < 1e-2);
+    assert!((euclidean_distance_parallel(&a, &b) - dist).abs() < 1e-2);
+    let cosine = parallel::maintenance(|| cosine_similarity_parallel(&a, &b));
+    assert!((cosine - dot / norms).abs() < 1e-4);
+}
+
+#[test]
+fn core_ranges_parse_and_validate() {
+    assert_eq!(CoreRange::parse("2-5"), Some(CoreRange { first: 2, last: 5 }));
+    assert_eq!(CoreRange::parse("3"), Some(CoreRange { first: 3, last: 3 }));
+    assert_eq!(CoreRange::parse("5-2"), None);
+    assert_eq!(CoreRange::parse("a-b"), None);
+    let config: ParallelismConfig = serde_json::from_str(
+        r#"{"mode": "Auto", "parallel_search": true, "query_threads": 3, "maintenance_cores": "0-0"}"#,
+    ).unwrap();
+    assert_eq!((config.query_threads(), config.maintenance_cores), (3, CoreRange::parse("0")));
+    assert_eq!(serde_json::to_value(config.maintenance_cores).unwrap(), "0-0");
+    assert!(serde_json::from_str::<ParallelismConfig>(r#"{"mode": "Auto", "parallel_search": true, "query_cores": "4-1"}"#).is_err());
+
+    // The maintenance pool defaults to a quarter of the threads
+    assert_eq!(ParallelismConfig::fixed(8).maintenance_threads(), 2);
+    assert_eq!(ParallelismConfig::single_threaded().maintenance_threads(), 1);
+
+    let mut config = AppConfig::default();
+    assert!(config.validate().is_ok());
+    config.parallelism.maintenance_threads = Some(0);
+    assert!(config.validate().unwrap_err().contains("maintenance_threads"));
+    config.parallelism.maintenance_threads = None;
+    config.parallelism.query_cores = Some(CoreRange { first: 0, last: num_cpus::get() });
+    assert!(config.validate().unwrap_err().contains("query_cores"));
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document, Filter, Metric, SearchParams};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn writes_keep_created_at_and_move_updated_at() {
+    let dir = TestDir::new("timestamps");
+    let path = dir.path("docs.db");
+    let mut storage = Collection::open(&path).unwrap();
+    // Hand-written values under the reserved keys are replaced
+    let doc = Document::with_metadata(vec![1.0, 0.0], "a".into(), metadata([("_created_at", 5i64.into())]));
+    let id = storage.insert(doc).unwrap();
+    let inserted = storage.get(&id).unwrap();
+    let created = inserted.created_at().unwrap();
+    assert!(created > 1_600_000_000);
+    assert_eq!(inserted.updated_at(), Some(created));
+
+    std::thread::sleep(Duration::from_millis(1100));
+    storage.update_metadata(&id, metadata([("tag", "x".into())])).unwrap();
+    let old = storage.insert(Document::new(vec![0.0, 1.0], "b".into())).unwrap();
+    let updated = storage.get(&id).unwrap();
+    assert_eq!(updated.created_at(), Some(created));
+    assert!(updated.updated_at().unwrap() > created);
+
+    // The timestamps are metadata, so filters read them
+    let filter = Filter::new().gt("_updated_at", created as i64);
+    let hits = storage.search(&[1.0, 0.0], 10, Metric::Cosine, SearchParams { filter: Some(&filter), ..Default::default() });
+    let mut ids: Vec<_> = hits.iter().map(|h| h.id).collect();
+    ids.sort();
+    let mut expected = vec![id, old];
+    expected.sort();
+    assert_eq!(ids, expected);
+    let filter = Filter::new().lte("_created_at", created as i64);
+    let hits = storage.search(&[1.0, 0.0], 10, Metric::Cosine, SearchParams { filter: Some(&filter), ..Default::default() });
+    assert_eq!(hits.iter().map(|h| h.id).collect::<Vec<_>>(), vec![id]);
+
+    drop(storage);
+    let storage = Collection::open(&path).unwrap();
+    assert_eq!(storage.get(&id).unwrap().created_at(), Some(created));
+    drop(storage);
+}
+
+#[tokio::test]
+async fn listings_sort_by_timestamps() {
+    let dir = TestDir::new("timestamps_http");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    for (id, vector) in [("first", [1.0, 0.0]), ("second", [0.0, 1.0])] {
+        client.post(format!("{base}/upsert"))
+            .json(&json!({"id": id, "vector": vector, "text": id}))
+            .send().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+    }
+    // Touching the first document makes it the most recently updated
+    client.patch(format!("{base}/vectors/first/metadata")).json(&json!({"metadata": {"tag": "x"}})).send().await.unwrap();
+
+    let list = |sort: &'static str| {
+        let (client, base) = (client.clone(), base.clone());
+        async move {
+            let docs: Vec<Value> = client.get(format!("{base}/vectors?sort={sort}")).send().await.unwrap().json().await.unwrap();
+            docs.iter().map(|d| d["external_id"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(list("created_at").await, ["first", "second"]);
+    assert_eq!(list("-created_at").await, ["second", "first"]);
+    assert_eq!(list("-updated_at").await, ["first", "second"]);
+
+    let doc: Value = client.get(format!("{base}/vectors/first")).send().await.unwrap().json().await.unwrap();
+    assert!(doc["updated_at"].as_u64().unwrap() > doc["created_at"].as_u64().unwrap());
+    assert_eq!(doc["metadata"]["_created_at"], doc["created_at"]);
+    let res = client.get(format!("{base}/vectors?sort=size")).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use piramid::config::{AppConfig, CollectionConfig, ExecutionMode, SearchConfig};
+use piramid::index::IndexConfig;
+use piramid::server::state::AppState;
+use piramid::storage::collection::{get_tuning_path, TuneOptions, TunedParam};
+use piramid::testing::TestDir;
+use piramid::{Collection, Document, Metric};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+fn ivf() -> IndexConfig {
+    IndexConfig::Ivf {
+        num_clusters: 16,
+        num_probes: 1,
+        max_iterations: 10,
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    }
+}
+
+fn vector(i: usize) -> Vec<f32> {
+    common::wave(i, 8, 0.73)
+}
+
+fn seed(path: &str, config: CollectionConfig) -> Vec<uuid::Uuid> {
+    let mut storage = Collection::open_with_options(path, config.into()).unwrap();
+    let docs = (0..600).map(|i| Document::new(vector(i), format!("doc {i}"))).collect();
+    let ids = storage.insert_batch(docs).unwrap();
+    storage.rebuild_index().unwrap();
+    storage.checkpoint().unwrap();
+    ids
+}
+
+#[test]
+fn sweep_recommends_nprobe_and_keeps_it_across_reopen() {
+    let dir = TestDir::new("tuning_sweep");
+    let path = dir.path("docs.db");
+    let ids = seed(&path, CollectionConfig::with_index(ivf()));
+
+    let mut storage = Collection::open_with_options(&path, CollectionConfig::with_index(ivf()).into()).unwrap();
+    let report = storage.tune(TuneOptions { sample_size: 40, ..Default::default() }).unwrap();
+    assert_eq!(report.param, TunedParam::Nprobe);
+    let values: Vec<usize> = report.curve.iter().map(|p| p.value).collect();
+    assert_eq!(values, vec![1, 2, 4, 8, 16]);
+    // Probing every cluster is exhaustive
+    assert!(report.curve[4].recall >= 0.99, "{:?}", report.curve);
+    assert!(report.met_target);
+    let first = report.curve.iter().find(|p| p.recall >= 0.95).unwrap();
+    assert_eq!((report.recommended.value, report.recommended.queries), (first.value, 40));
+
+    // Ground truth the index can never find: nothing reaches the target, the best (cheapest on ties) is recommended
+    let far = TuneOptions {
+        queries: vec![vector(0)],
+        ground_truth: Some(vec![vec![ids[599]]]),
+        k: 1,
+        values: Some(vec![8, 2, 2]),
+        ..Default::default()
+    };
+    let missed = storage.tune(far).unwrap();
+    assert!(!missed.met_target);
+    assert_eq!(missed.curve.iter().map(|p| p.value).collect::<Vec<_>>(), vec![2, 8]);
+    assert_eq!(missed.recommended.value, 2);
+
+    assert!(!storage.set_tuning(Some(report.recommended.clone())).unwrap());
+    assert_eq!(storage.config().search.nprobe, Some(first.value));
+    drop(storage);
+
+    let mut storage = Collection::open_with_options(&path, CollectionConfig::with_index(ivf()).into()).unwrap();
+    assert_eq!(storage.tuning().unwrap().value, first.value);
+    assert_eq!(storage.config().search.nprobe, Some(first.value));
+    assert!(storage.set_tuning(None).unwrap());
+    assert_eq!(storage.config().search.nprobe, None);
+    assert!(!std::path::Path::new(&get_tuning_path(&path)).exists());
+    drop(storage);
+
+    // Flat indexes have nothing to tune
+    let flat = CollectionConfig::with_index(IndexConfig::Flat {
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    });
+    let mut storage = Collection::open_with_options("flat.db", flat.ephemeral().into()).unwrap();
+    storage.insert(Document::new(vector(1), "a".into())).unwrap();
+    assert!(storage.tune(TuneOptions::default()).is_err());
+}
+
+#[tokio::test]
+async fn tuning_endpoints_set_and_clear_the_search_default() {
+    let dir = TestDir::new("tuning_http");
+    let data_dir = dir.data_dir();
+    let config = AppConfig { index: ivf(), ..Default::default() };
+    let ids = seed(&format!("{data_dir}/docs.db"), config.to_collection_config());
+
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    state.discover_collections().unwrap();
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    // A dry run reports without changing the defaults
+    let res: Value = client.post(format!("{base}/tuning/sweep")).json(&json!({"persist": false, "sample_size": 20})).send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["param"].as_str(), res["persisted"].as_bool()), (Some("nprobe"), Some(false)));
+    assert_eq!(res["curve"].as_array().unwrap().len(), 5);
+    let tuning: Value = client.get(format!("{base}/tuning")).send().await.unwrap().json().await.unwrap();
+    assert!(tuning["tuning"].is_null());
+
+    let body = json!({
+        "queries": [vector(3)], "ground_truth": [[ids[3].to_string()]], "k": 1,
+        "values": [4, 16], "target_recall": 1.0,
+    });
+    let res: Value = client.post(format!("{base}/tuning/sweep")).json(&body).send().await.unwrap().json().await.unwrap();
+    let value = res["recommended"]["value"].as_u64().unwrap();
+    assert!(res["met_target"].as_bool().unwrap());
+    let tuning: Value = client.get(format!("{base}/tuning")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(tuning["tuning"]["value"].as_u64(), Some(value));
+    assert_eq!(tuning["search"]["nprobe"].as_u64(), Some(value));
+
+    let res = client.post(format!("{base}/tuning/sweep")).json(&json!({"queries": [vector(3)], "ground_truth": [["missing"]]})).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+
+    let res: Value = client.delete(format!("{base}/tuning")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["deleted"], true);
+    let tuning: Value = client.get(format!("{base}/tuning")).send().await.unwrap().json().await.unwrap();
+    assert!(tuning["tuning"].is_null() && tuning["search"]["nprobe"].is_null());
+}
@@ -0,0 +1,90 @@
+use piramid::storage::collection::compact;
+use piramid::testing::TestDir;
+use piramid::{
+    metadata, Collection, CollectionConfig, Document, Filter, Metric, QuantizationConfig, QuantizedVector,
+    SearchParams, TwoStageConfig,
+};
+
+fn two_stage_config(candidates: usize) -> CollectionConfig {
+    CollectionConfig::default().with_two_stage(TwoStageConfig::with_candidates(candidates))
+}
+
+#[test]
+fn approx_score_matches_dequantized_vectors() {
+    let vector = [0.12, -0.7, 0.33, 0.9, -0.05, 0.41];
+    let query = [0.3, -0.2, 0.8, 0.1, 0.0, -0.6];
+
+    for code in [
+        QuantizedVector::from_f32(&vector),
+        QuantizedVector::from_f32_with_config(&vector, &QuantizationConfig::pq(2)),
+    ] {
+        let decoded = code.to_f32();
+        for metric in [Metric::Cosine, Metric::Euclidean, Metric::DotProduct] {
+            let expected = metric.calculate(&query, &decoded, piramid::ExecutionMode::Scalar);
+            let approx = code.approx_score(&query, metric);
+            assert!((expected - approx).abs() < 1e-4, "{metric:?}: {expected} vs {approx}");
+        }
+    }
+}
+
+#[test]
+fn reranks_with_full_precision_vectors() {
+    let dir = TestDir::new("two_stage");
+    let path = dir.path("docs.db");
+
+    let exact = vec![0.123_456, 0.5, -0.987_654, 0.031_25];
+    let id;
+    {
+        let mut storage = Collection::open_with_options(&path, two_stage_config(2).into()).unwrap();
+        id = storage.insert(Document::new(exact.clone(), "target".into())).unwrap();
+        for i in 0..10 {
+            let v = vec![i as f32 * 0.1, 1.0, 0.2, -0.3];
+            storage.insert(Document::new(v, format!("other {i}"))).unwrap();
+        }
+
+        let results = storage.search(&exact, 1, Metric::Cosine, SearchParams::default());
+        assert_eq!(results[0].id, id);
+        assert_eq!(results[0].vector, exact);
+        assert!((results[0].score - 1.0).abs() < 1e-6);
+    }
+
+    // The sidecar survives a reopen
+    let storage = Collection::open_with_options(&path, two_stage_config(2).into()).unwrap();
+    let results = storage.search(&exact, 3, Metric::Cosine, SearchParams::default());
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].vector, exact);
+
+    drop(storage);
+}
+
+#[test]
+fn filters_and_compaction_keep_exact_vectors() {
+    let dir = TestDir::new("two_stage_compact");
+    let path = dir.path("docs.db");
+
+    let mut storage = Collection::open_with_options(&path, two_stage_config(10).into()).unwrap();
+    let keep = vec![0.111_111, 0.222_222, 0.333_333];
+    let kept = storage
+        .insert(Document::with_metadata(keep.clone(), "keep".into(), metadata([("tag", "a".into())])))
+        .unwrap();
+    let dropped = storage
+        .insert(Document::with_metadata(vec![0.1, 0.2, 0.3], "drop".into(), metadata([("tag", "b".into())])))
+        .unwrap();
+
+    let filter = Filter::new().eq("tag", "b");
+    let params = SearchParams { filter: Some(&filter), ..SearchParams::default() };
+    let results = storage.search(&keep, 5, Metric::Cosine, params);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].id, dropped);
+
+    storage.delete(&dropped).unwrap();
+    compact(&mut storage).unwrap();
+
+    let results = storage.search(&keep, 5, Metric::Cosine, SearchParams::default());
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].id, kept);
+    assert_eq!(results[0].vector, keep);
+    assert_eq!(storage.two_stage().unwrap().codes().len(), 1);
+
+    drop(storage);
+}
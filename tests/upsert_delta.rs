@@ -0,0 +1,74 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+fn document(vector: Vec<f32>, text: &str, tag: &str) -> Document {
+    Document::with_metadata(vector, text.into(), metadata([("tag", tag.into())])).with_external_id("doc-1")
+}
+
+#[test]
+fn unchanged_documents_are_not_rewritten() {
+    let dir = TestDir::new("upsert_delta");
+    let path = dir.path("docs.db");
+    let mut storage = Collection::open(&path).unwrap();
+    let (id, changed) = storage.upsert_if_changed(document(vec![0.3, 0.5, 0.1], "a", "x")).unwrap();
+    assert!(changed);
+    let seq = storage.head_seq();
+
+    // Same content: no WAL entry, no new version
+    assert_eq!(storage.upsert_if_changed(document(vec![0.3, 0.5, 0.1], "a", "x")).unwrap(), (id, false));
+    assert_eq!((storage.head_seq(), storage.get(&id).unwrap().version()), (seq, 1));
+
+    // Any of vector, text or metadata differing writes a new version
+    for (n, next) in [
+        document(vec![0.3, 0.5, 0.2], "a", "x"),
+        document(vec![0.3, 0.5, 0.2], "b", "x"),
+        document(vec![0.3, 0.5, 0.2], "b", "y"),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        assert_eq!(storage.upsert_if_changed(next).unwrap(), (id, true));
+        assert_eq!(storage.get(&id).unwrap().version(), n as u64 + 2);
+    }
+    assert!(storage.head_seq() > seq);
+    // A plain upsert still writes
+    storage.upsert(document(vec![0.3, 0.5, 0.2], "b", "y")).unwrap();
+    assert_eq!(storage.get(&id).unwrap().version(), 5);
+    assert_eq!(storage.count(), 1);
+    drop(storage);
+}
+
+#[tokio::test]
+async fn upsert_reports_skipped_documents() {
+    let dir = TestDir::new("upsert_delta_http");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let item = |i: usize, text: &str| json!({"id": format!("doc-{i}"), "vector": [1.0, i as f32, 0.5], "text": text, "metadata": {"n": i}});
+    let body = json!({"items": [item(0, "a"), item(1, "b"), item(2, "c")], "skip_unchanged": true});
+    let res: Value = client.post(format!("{base}/upsert")).json(&body).send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["created"].as_u64(), res["unchanged"].as_u64()), (Some(3), Some(0)), "{res}");
+
+    // A nightly re-sync where only one document moved
+    let body = json!({"items": [item(0, "a"), item(1, "b2"), item(2, "c")], "skip_unchanged": true});
+    let res: Value = client.post(format!("{base}/upsert")).json(&body).send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["created"].as_u64(), res["unchanged"].as_u64()), (Some(0), Some(2)), "{res}");
+    let seq = res["seq"].as_u64();
+
+    let single = json!({"id": "doc-2", "vector": [1.0, 2.0, 0.5], "text": "c", "metadata": {"n": 2}, "skip_unchanged": true});
+    let res: Value = client.post(format!("{base}/upsert")).json(&single).send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["changed"].as_bool(), res["version"].as_u64(), res["seq"].as_u64()), (Some(false), Some(1), seq), "{res}");
+
+    let body = json!({"items": [item(0, "a"), item(1, "b3"), {"id": "bad", "vector": [1.0], "text": "x"}], "skip_unchanged": true, "allow_partial": true});
+    let res: Value = client.post(format!("{base}/upsert")).json(&body).send().await.unwrap().json().await.unwrap();
+    let changed: Vec<Option<bool>> = res["results"].as_array().unwrap().iter().map(|r| r["changed"].as_bool()).collect();
+    assert_eq!(changed, [Some(false), Some(true), None], "{res}");
+}
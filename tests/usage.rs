@@ -0,0 +1,117 @@
+mod common;
+
+use piramid::config::{AppConfig, UsageBudget};
+use piramid::embeddings::{Embedder, EmbeddingResponse, EmbeddingResult};
+use piramid::server::state::AppState;
+use piramid::server::usage::month_start;
+use piramid::testing::TestDir;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+// Embeds every text as [1, 0, 0], charging one token per character
+struct MockEmbedder;
+
+#[async_trait::async_trait]
+impl Embedder for MockEmbedder {
+    async fn embed(&self, text: &str) -> EmbeddingResult<EmbeddingResponse> {
+        Ok(EmbeddingResponse { embedding: vec![1.0, 0.0, 0.0], tokens: Some(text.len() as u32), model: "mock".to_string() })
+    }
+
+    fn provider_name(&self) -> &str {
+        "mock"
+    }
+
+    fn model_name(&self) -> &str {
+        "mock-v1"
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        Some(3)
+    }
+}
+
+async fn serve(data_dir: &str, config: AppConfig) -> (Arc<AppState>, String) {
+    let state = Arc::new(AppState::with_embedder(data_dir, config, 500, Arc::new(MockEmbedder), None, false, None));
+    let base = common::serve(state.clone()).await;
+    (state, base)
+}
+
+#[tokio::test]
+async fn usage_is_counted_per_collection_and_key() {
+    let dir = TestDir::new("usage_counts");
+    let data_dir = dir.data_dir();
+    let (state, base) = serve(data_dir, AppConfig::default()).await;
+    let client = reqwest::Client::new();
+
+    let res = client.post(format!("{base}/collections/docs/vectors")).header("x-api-key", "team-a-secret")
+        .json(&json!({"texts": ["abcd", "ef"]})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res = client.post(format!("{base}/collections/docs/search/text")).header("x-api-key", "team-b-secret")
+        .json(&json!({"query": "abc", "k": 1})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res = client.post(format!("{base}/collections/notes/embed")).json(&json!({"text": "x"})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    let text = client.get(format!("{base}/usage?granularity=month")).send().await.unwrap().text().await.unwrap();
+    assert!(!text.contains("team-a-secret"), "{text}");
+    let usage: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(usage["totals"], json!({"requests": 3, "texts": 4, "tokens": 10}));
+    assert_eq!(usage["buckets"].as_array().unwrap().len(), 3);
+    assert!(usage["buckets"].as_array().unwrap().iter().all(|b| b["provider"] == "mock" && b["model"] == "mock-v1"));
+
+    // Filters take the key itself
+    let usage: Value = client.get(format!("{base}/usage?api_key=team-a-secret")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(usage["totals"], json!({"requests": 1, "texts": 2, "tokens": 6}));
+    assert!(usage["buckets"][0]["api_key"].as_str().unwrap().starts_with("key-"));
+    let usage: Value = client.get(format!("{base}/usage?collection=notes")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(usage["totals"]["tokens"], 1);
+    assert!(usage["buckets"][0].get("api_key").is_none());
+    let res = client.get(format!("{base}/usage?from=10&to=5")).send().await.unwrap();
+    assert_eq!(res.status(), 400);
+
+    // Month buckets follow the calendar
+    assert_eq!(month_start(1_760_000_000), 1_759_276_800);
+    assert_eq!(month_start(1_709_208_000), 1_706_745_600);
+
+    // The ledger survives a restart
+    state.checkpoint_all().unwrap();
+    let (_, base) = serve(data_dir, AppConfig::default()).await;
+    let usage: Value = client.get(format!("{base}/usage")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(usage["totals"]["requests"], 3);
+}
+
+#[tokio::test]
+async fn spent_budget_blocks_embed_calls() {
+    let dir = TestDir::new("usage_budget");
+    let data_dir = dir.data_dir();
+    let mut config = AppConfig::default();
+    config.usage.budgets.push(UsageBudget {
+        name: Some("docs-tokens".into()),
+        collection: Some("docs".into()),
+        max_tokens: Some(5),
+        ..Default::default()
+    });
+    let (_, base) = serve(data_dir, config).await;
+    let client = reqwest::Client::new();
+
+    // The call that crosses the limit completes; the next one is refused
+    for text in ["abc", "defg"] {
+        let res = client.post(format!("{base}/collections/docs/embed")).json(&json!({"text": text})).send().await.unwrap();
+        assert_eq!(res.status(), 200);
+    }
+    let res = client.post(format!("{base}/collections/docs/search/text")).json(&json!({"query": "a", "k": 1})).send().await.unwrap();
+    assert_eq!(res.status(), 429);
+    let error = res.json::<Value>().await.unwrap()["error"].as_str().unwrap().to_string();
+    assert!(error.contains("docs-tokens") && error.contains("7/5 tokens"), "{error}");
+
+    // Other collections and requests without embedding are not affected
+    let res = client.post(format!("{base}/collections/notes/embed")).json(&json!({"text": "abc"})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res = client.post(format!("{base}/collections/docs/vectors")).json(&json!({"vector": [0.0, 1.0, 0.0], "text": "v"})).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    let usage: Value = client.get(format!("{base}/usage")).send().await.unwrap().json().await.unwrap();
+    let budget = &usage["budgets"][0];
+    assert_eq!((budget["exceeded"].clone(), budget["used"]["tokens"].clone()), (json!(true), json!(7)));
+    assert_eq!(budget["scope"], "collection 'docs'");
+}
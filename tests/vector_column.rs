@@ -0,0 +1,125 @@
+use piramid::config::{ExecutionMode, SearchConfig};
+use piramid::index::IndexConfig;
+use piramid::testing::TestDir;
+use piramid::{Collection, CollectionConfig, Document, Metric, SearchParams};
+
+fn config(index: IndexConfig, column: bool) -> CollectionConfig {
+    let mut config = CollectionConfig::with_index(index);
+    config.memory.vector_column = column;
+    config
+}
+
+fn vector(i: usize) -> Vec<f32> {
+    vec![
+        (i % 7) as f32 + 1.0,
+        (i % 5) as f32,
+        (i / 3) as f32 * 0.5,
+        1.0,
+    ]
+}
+
+#[test]
+fn column_follows_writes_and_reopen() {
+    let dir = TestDir::new("vector_column_writes");
+    let path = dir.path("docs.db");
+    let flat = IndexConfig::Flat {
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    };
+
+    let mut storage =
+        Collection::open_with_options(&path, config(flat.clone(), true).into()).unwrap();
+    let ids: Vec<_> = (0..20)
+        .map(|i| {
+            storage
+                .insert(Document::new(vector(i), format!("doc {i}")))
+                .unwrap()
+        })
+        .collect();
+    storage
+        .update_vector(&ids[3], vec![0.0, 0.0, 9.0, 0.0])
+        .unwrap();
+    storage.delete(&ids[5]).unwrap();
+    // The freed slot is reused instead of growing the column
+    let reused = storage
+        .insert(Document::new(vector(99), "reused".into()))
+        .unwrap();
+
+    let column = storage.vector_column().unwrap();
+    assert_eq!(column.len(), 20);
+    assert_eq!(column.get(&ids[3]).unwrap(), &[0.0, 0.0, 9.0, 0.0]);
+    assert!(column.get(&ids[5]).is_none());
+    // Rows hold exactly what the index sees, i.e. the cached vector after quantization round-trip
+    assert_eq!(column.get(&reused).unwrap(), storage.get_vectors()[&reused].as_slice());
+
+    let hits = storage.search(
+        &[0.0, 0.0, 1.0, 0.0],
+        1,
+        Metric::Cosine,
+        SearchParams::default(),
+    );
+    assert_eq!(hits[0].id, ids[3]);
+    storage.checkpoint().unwrap();
+    drop(storage);
+
+    let storage = Collection::open_with_options(&path, config(flat, true).into()).unwrap();
+    let column = storage.vector_column().unwrap();
+    assert_eq!(column.len(), 20);
+    assert_eq!(column.get(&ids[3]).unwrap(), &[0.0, 0.0, 9.0, 0.0]);
+    assert_eq!(column.view().rows().count(), 20);
+
+    drop(storage);
+}
+
+#[test]
+fn enabling_column_matches_cache_results() {
+    let dir = TestDir::new("vector_column_ivf");
+    let path = dir.path("docs.db");
+    let ivf = IndexConfig::Ivf {
+        num_clusters: 4,
+        num_probes: 4,
+        max_iterations: 10,
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    };
+
+    // Written without the column; it is built from the cache when first enabled
+    let mut storage =
+        Collection::open_with_options(&path, config(ivf.clone(), false).into()).unwrap();
+    for i in 0..60 {
+        storage
+            .insert(Document::new(vector(i), format!("doc {i}")))
+            .unwrap();
+    }
+    assert!(storage.vector_column().is_none());
+    let query = [2.0, 1.0, 3.0, 1.0];
+    let expected: Vec<_> = storage
+        .search(&query, 5, Metric::Cosine, SearchParams::default())
+        .into_iter()
+        .map(|h| h.id)
+        .collect();
+    storage.checkpoint().unwrap();
+    drop(storage);
+
+    let mut storage = Collection::open_with_options(&path, config(ivf, true).into()).unwrap();
+    assert_eq!(storage.vector_column().unwrap().len(), 60);
+    let hits: Vec<_> = storage
+        .search(&query, 5, Metric::Cosine, SearchParams::default())
+        .into_iter()
+        .map(|h| h.id)
+        .collect();
+    assert_eq!(hits, expected);
+
+    // IVF retrains from the column; probing every cluster keeps the search exhaustive
+    storage.rebuild_index().unwrap();
+    let rebuilt: Vec<_> = storage
+        .search(&query, 5, Metric::Cosine, SearchParams::default())
+        .into_iter()
+        .map(|h| h.id)
+        .collect();
+    assert_eq!(rebuilt, expected);
+
+    drop(storage);
+}
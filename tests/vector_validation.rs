@@ -0,0 +1,60 @@
+use piramid::config::{NonFinitePolicy, VectorValidationConfig};
+use piramid::error::{PiramidError, StorageError};
+use piramid::testing::TestDir;
+use piramid::{Collection, CollectionConfig, Document, Metric, SearchParams};
+
+fn open(path: &str, validation: VectorValidationConfig) -> Collection {
+    Collection::open_with_options(path, CollectionConfig::default().with_validation(validation).into()).unwrap()
+}
+
+#[test]
+fn strict_collections_reject_bad_vectors() {
+    let dir = TestDir::new("vector_validation_strict");
+    let path = dir.path("docs.db");
+    let mut storage = open(&path, VectorValidationConfig::strict());
+    let good = storage.insert(Document::new(vec![1.0, 0.0], "good".into())).unwrap();
+
+    let err = storage.insert(Document::new(vec![1.0, f32::NAN], "nan".into())).unwrap_err();
+    assert!(matches!(err, PiramidError::Storage(StorageError::NonFiniteValue { index: 1, .. })));
+    assert_eq!(err.status_code(), 400);
+    assert!(matches!(
+        storage.insert(Document::new(vec![0.0, 0.0], "zero".into())),
+        Err(PiramidError::Storage(StorageError::ZeroVector))
+    ));
+    // A single bad vector fails the whole batch
+    let batch = vec![
+        Document::new(vec![0.0, 1.0], "ok".into()),
+        Document::new(vec![f32::NEG_INFINITY, 1.0], "inf".into()),
+    ];
+    assert!(storage.insert_batch(batch).is_err());
+    assert!(storage.update_vector(&good, vec![f32::INFINITY, 0.0]).is_err());
+    assert_eq!(storage.count(), 1);
+
+    // Queries: the plain search matches nothing, try_search reports why
+    assert!(storage.search(&[f32::NAN, 1.0], 1, Metric::Cosine, SearchParams::default()).is_empty());
+    assert!(storage.try_search(&[0.0, 0.0], 1, Metric::Cosine, SearchParams::default()).is_err());
+    // Zero vectors are only a problem for cosine
+    assert_eq!(storage.try_search(&[0.0, 0.0], 1, Metric::Euclidean, SearchParams::default()).unwrap().len(), 1);
+
+    drop(storage);
+}
+
+#[test]
+fn sanitize_replaces_non_finite_components() {
+    let dir = TestDir::new("vector_validation_sanitize");
+    let path = dir.path("docs.db");
+    let validation = VectorValidationConfig { non_finite: NonFinitePolicy::Sanitize, reject_zero_cosine: false };
+    let mut storage = open(&path, validation);
+
+    let id = storage.insert(Document::new(vec![1.0, f32::NAN, f32::INFINITY], "a".into())).unwrap();
+    storage.insert(Document::new(vec![0.0, 1.0, 0.0], "b".into())).unwrap();
+    let stored = storage.get(&id).unwrap().get_vector();
+    assert!(stored.iter().all(|v| v.is_finite()));
+    assert_eq!(stored[1], 0.0);
+
+    let hits = storage.try_search(&[1.0, f32::NAN, 0.0], 2, Metric::Cosine, SearchParams::default()).unwrap();
+    assert_eq!(hits[0].id, id);
+    assert!(hits.iter().all(|h| h.score.is_finite()));
+
+    drop(storage);
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use piramid::config::{AppConfig, CollectionConfig, ExecutionMode, SearchConfig};
+use piramid::index::{IndexConfig, SerializableIndex};
+use piramid::server::state::AppState;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document, Metric, VectorIndex};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use uuid::Uuid;
+
+fn flat() -> CollectionConfig {
+    CollectionConfig::with_index(IndexConfig::Flat {
+        metric: Metric::Cosine,
+        mode: ExecutionMode::default(),
+        search: SearchConfig::default(),
+    })
+}
+
+fn vector(i: usize) -> Vec<f32> {
+    common::wave(i, 8, 0.41)
+}
+
+fn seed(path: &str, config: CollectionConfig) -> Vec<Uuid> {
+    let mut storage = Collection::open_with_options(path, config.into()).unwrap();
+    let docs = (0..20).map(|i| Document::new(vector(i), format!("doc {i}"))).collect();
+    let ids = storage.insert_batch(docs).unwrap();
+    storage.checkpoint().unwrap();
+    ids
+}
+
+// Damage the files the way a torn write would: a pointer past the end of the data file, one pointing
+// at another document, one lost (its index node stays), and an index node lost (its document stays)
+fn corrupt(path: &str, ids: &[Uuid]) {
+    let pointers_path = format!("{path}.index.db");
+    let mut pointers: HashMap<Uuid, (u64, u32, Option<u32>)> = bincode::deserialize(&fs::read(&pointers_path).unwrap()).unwrap();
+    pointers.insert(ids[0], (u64::MAX / 2, 64, None));
+    let other = pointers[&ids[2]];
+    pointers.insert(ids[1], other);
+    pointers.remove(&ids[3]);
+    fs::write(&pointers_path, bincode::serialize(&pointers).unwrap()).unwrap();
+
+    let index_path = format!("{path}.vecindex.db");
+    let SerializableIndex::Flat(mut index) = bincode::deserialize(&fs::read(&index_path).unwrap()).unwrap() else {
+        panic!("expected a flat index");
+    };
+    index.remove(&ids[4]);
+    fs::write(&index_path, bincode::serialize(&SerializableIndex::Flat(index)).unwrap()).unwrap();
+}
+
+#[test]
+fn verify_reports_damage_and_repair_fixes_it_for_good() {
+    let dir = TestDir::new("verify_repair");
+    let path = dir.path("docs.db");
+    let ids = seed(&path, flat());
+
+    let storage = Collection::open_with_options(&path, flat().into()).unwrap();
+    let report = storage.verify().unwrap();
+    assert!(report.is_consistent(), "{report:?}");
+    assert_eq!(report.documents, 20);
+    drop(storage);
+
+    corrupt(&path, &ids);
+    let mut storage = Collection::open_with_options(&path, flat().into()).unwrap();
+    let report = storage.verify().unwrap();
+    assert!(!report.is_consistent());
+    assert_eq!(report.documents, 19);
+    assert_eq!(report.out_of_bounds.ids, vec![ids[0]]);
+    assert_eq!(report.unreadable.ids, vec![ids[1]]);
+    assert_eq!(report.orphan_index_nodes.ids, vec![ids[3]]);
+    assert_eq!(report.missing_from_index.ids, vec![ids[4]]);
+    assert!(!report.repaired);
+
+    let repaired = storage.repair().unwrap();
+    assert!(repaired.repaired);
+    assert_eq!(repaired.out_of_bounds.count, 1);
+    assert_eq!(storage.count(), 17);
+    let hits = storage.search(&vector(4), 1, Metric::Cosine, Default::default());
+    assert_eq!(hits[0].id, ids[4]);
+    assert!(storage.verify().unwrap().is_consistent());
+    drop(storage);
+
+    // The repaired pointer and index files are what the next open loads
+    let storage = Collection::open_with_options(&path, flat().into()).unwrap();
+    let report = storage.verify().unwrap();
+    assert!(report.is_consistent(), "{report:?}");
+    assert_eq!((report.documents, report.recorded_count), (17, 17));
+    assert_eq!(storage.vector_index().ids().len(), 17);
+}
+
+#[tokio::test]
+async fn verify_endpoint_checks_and_repairs() {
+    let dir = TestDir::new("verify_http");
+    let data_dir = dir.data_dir();
+    let config = AppConfig { index: flat().index, ..Default::default() };
+    let path = format!("{data_dir}/docs.db");
+    let ids = seed(&path, config.to_collection_config());
+    corrupt(&path, &ids);
+
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    state.discover_collections().unwrap();
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    let res: Value = client.post(format!("{base}/verify")).send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["consistent"].as_bool(), res["repaired"].as_bool()), (Some(false), Some(false)));
+    assert_eq!(res["orphan_index_nodes"]["ids"], json!([ids[3].to_string()]));
+    assert_eq!(res["missing_from_index"]["count"], 1);
+
+    let res: Value = client.post(format!("{base}/verify")).json(&json!({"repair": true})).send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["repaired"], true);
+    let res: Value = client.post(format!("{base}/verify")).send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["consistent"].as_bool(), res["documents"].as_u64()), (Some(true), Some(17)));
+}
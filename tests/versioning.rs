@@ -0,0 +1,87 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::server::state::AppState;
+use piramid::storage::collection::compact;
+use piramid::testing::TestDir;
+use piramid::{metadata, Collection, Document};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+#[test]
+fn every_rewrite_bumps_the_version() {
+    let dir = TestDir::new("versioning");
+    let path = dir.path("docs.db");
+    let mut storage = Collection::open(&path).unwrap();
+    let id = storage.insert(Document::new(vec![1.0, 0.0, 0.0], "a".into())).unwrap();
+    let other = storage.insert(Document::new(vec![0.0, 1.0, 0.0], "b".into())).unwrap();
+    assert_eq!(storage.get(&id).unwrap().version(), 1);
+
+    let mut replacement = Document::with_metadata(vec![1.0, 0.1, 0.0], "a2".into(), metadata([("_version", 40i64.into())]));
+    replacement.id = id;
+    storage.upsert(replacement).unwrap();
+    assert_eq!(storage.get(&id).unwrap().version(), 2, "clients cannot set the version");
+    assert!(storage.update_metadata(&id, metadata([("tag", "x".into())])).unwrap());
+    assert!(storage.update_vector(&id, vec![1.0, 0.2, 0.0]).unwrap());
+    assert_eq!(storage.get(&id).unwrap().version(), 4);
+    assert_eq!(storage.get(&other).unwrap().version(), 1);
+
+    // Stale preconditions are refused; a missing document is at version 0
+    let err = storage.update_metadata_if(&id, metadata([("tag", "y".into())]), Some(3)).unwrap_err();
+    assert_eq!(err.status_code(), 409);
+    assert_eq!(storage.update_metadata_if(&id, metadata([("tag", "y".into())]), Some(4)).unwrap(), Some(5));
+    assert_eq!(storage.update_metadata_if(&uuid::Uuid::new_v4(), metadata([]), Some(0)).unwrap(), None);
+    assert!(storage.ensure_version(&uuid::Uuid::new_v4(), Some(0)).is_ok());
+    assert!(storage.ensure_version(&id, Some(0)).is_err());
+
+    // Versions live in the stored documents: they survive compaction and WAL replay
+    compact(&mut storage).unwrap();
+    assert_eq!(storage.get(&id).unwrap().version(), 5);
+    storage.update_metadata(&other, metadata([("tag", "z".into())])).unwrap();
+    drop(storage);
+    let storage = Collection::open(&path).unwrap();
+    assert_eq!(storage.get(&id).unwrap().version(), 5);
+    assert_eq!(storage.get(&other).unwrap().version(), 2);
+    drop(storage);
+}
+
+#[tokio::test]
+async fn if_version_makes_writes_conditional() {
+    let dir = TestDir::new("versioning_http");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = format!("{}/collections/docs", common::serve(state).await);
+    let client = reqwest::Client::new();
+
+    // if_version 0 only creates
+    let upsert = json!({"id": "doc-1", "vector": [1.0, 0.0, 0.0], "text": "a", "if_version": 0});
+    let res: Value = client.post(format!("{base}/upsert")).json(&upsert).send().await.unwrap().json().await.unwrap();
+    assert_eq!((res["created"].clone(), res["version"].clone()), (json!(true), json!(1)));
+    let res = client.post(format!("{base}/upsert")).json(&upsert).send().await.unwrap();
+    assert_eq!(res.status(), 409);
+
+    // Two writers read version 1; the second one's write is refused instead of clobbering the first
+    let patch = |tag: &str, if_version: u64| json!({"metadata": {"tag": tag}, "if_version": if_version});
+    let res: Value = client.patch(format!("{base}/vectors/doc-1/metadata")).json(&patch("first", 1))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["version"], 2);
+    let res = client.patch(format!("{base}/vectors/doc-1/metadata")).json(&patch("second", 1)).send().await.unwrap();
+    assert_eq!(res.status(), 409);
+    let doc: Value = client.get(format!("{base}/vectors/doc-1")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(doc["version"], 2);
+    assert_eq!(doc["metadata"]["tag"], "first");
+
+    let res = client.post(format!("{base}/upsert"))
+        .json(&json!({"id": "doc-1", "vector": [0.0, 1.0, 0.0], "text": "b", "if_version": 2}))
+        .send().await.unwrap();
+    assert_eq!(res.json::<Value>().await.unwrap()["version"], 3);
+    let res = client.post(format!("{base}/upsert"))
+        .json(&json!({"items": [{"id": "doc-2", "vector": [0.0, 0.0, 1.0], "text": "c"}], "if_version": 1}))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 400);
+
+    assert_eq!(client.delete(format!("{base}/vectors/doc-1?if_version=2")).send().await.unwrap().status(), 409);
+    assert_eq!(client.delete(format!("{base}/vectors/missing?if_version=1")).send().await.unwrap().status(), 409);
+    let res: Value = client.delete(format!("{base}/vectors/doc-1?if_version=3")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(res["deleted"], true);
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use piramid::config::{AppConfig, CollectionConfig, WalBackpressure, WalConfig};
+use piramid::server::state::AppState;
+use piramid::storage::collection::WritePressure;
+use piramid::testing::TestDir;
+use piramid::{Collection, Document};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn doc(i: usize) -> Document {
+    let angle = i as f32 * 0.2;
+    Document::new(vec![angle.cos(), angle.sin(), 0.5], format!("doc {i}"))
+}
+
+#[test]
+fn backlog_throttles_then_rejects_until_a_checkpoint_lands() {
+    let dir = TestDir::new("wal_backpressure");
+    let path = dir.path("docs.db");
+    let backpressure = WalBackpressure { throttle_ops: Some(50), reject_ops: Some(100), max_delay_ms: 1000, ..Default::default() };
+    // Never checkpointed by the write count, so only the backpressure brings the backlog down
+    let wal = WalConfig { checkpoint_frequency: 1_000_000, backpressure, ..Default::default() };
+    let config = CollectionConfig { wal, ..Default::default() };
+    let mut storage = Collection::open_with_options(&path, config.clone().into()).unwrap();
+
+    for i in 0..30 {
+        storage.insert(doc(i)).unwrap();
+    }
+    assert_eq!(storage.wal_backlog().ops, 30);
+    assert_eq!(storage.write_pressure().unwrap(), WritePressure::None);
+
+    // Halfway from the throttle to the reject threshold: half the longest delay, and a checkpoint starts
+    for i in 30..75 {
+        storage.insert(doc(i)).unwrap();
+    }
+    assert_eq!(storage.write_pressure().unwrap(), WritePressure::Throttle(Duration::from_millis(500)));
+    storage.wait_for_checkpoint();
+    let backlog = storage.wal_backlog();
+    assert_eq!(backlog.ops, 0);
+    assert!(backlog.last_checkpoint_ms.is_some() && backlog.checkpoint_running_ms.is_none());
+    assert_eq!(storage.write_pressure().unwrap(), WritePressure::None);
+
+    for i in 75..185 {
+        storage.insert(doc(i)).unwrap();
+    }
+    match storage.write_pressure().unwrap() {
+        WritePressure::Reject { retry_after } => assert!(retry_after >= Duration::from_secs(1)),
+        other => panic!("expected a rejection, got {other:?}"),
+    }
+    storage.wait_for_checkpoint();
+    assert_eq!(storage.write_pressure().unwrap(), WritePressure::None);
+    drop(storage);
+
+    // Reopening counts the backlog from the last checkpoint on disk
+    let storage = Collection::open_with_options(&path, config.into()).unwrap();
+    assert_eq!(storage.count(), 185);
+    assert_eq!(storage.wal_backlog().ops, 0);
+}
+
+#[tokio::test]
+async fn rejected_writes_get_429_with_retry_after() {
+    let dir = TestDir::new("wal_backpressure_http");
+    let data_dir = dir.data_dir();
+    let mut config = AppConfig::default();
+    config.wal.checkpoint_frequency = 1_000_000;
+    config.wal.backpressure.reject_ops = Some(20);
+    let state = Arc::new(AppState::new(data_dir, config, 500, None, false, None));
+    let base = common::serve(state).await;
+    let client = reqwest::Client::new();
+    let insert = |i: usize| {
+        let body = json!({"vector": [i as f32, 1.0, 0.5], "text": format!("doc {i}")});
+        client.post(format!("{base}/collections/docs/vectors")).json(&body).send()
+    };
+
+    let mut rejected = None;
+    for i in 0..40 {
+        let res = insert(i).await.unwrap();
+        if res.status() == 429 {
+            rejected = Some((i, res));
+            break;
+        }
+        assert_eq!(res.status(), 200);
+    }
+    let (at, res) = rejected.expect("a write over the backlog limit is rejected");
+    assert!(at >= 20, "rejected after {at} writes");
+    let retry_after: u64 = res.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+    assert!(retry_after >= 1);
+    let body: Value = res.json().await.unwrap();
+    assert_eq!((body["error_code"].as_str(), body["retryable"].as_bool()), (Some("WRITE_THROTTLED"), Some(true)));
+
+    // The rejection started a checkpoint; writes go through again once it is on disk
+    let mut accepted = false;
+    for _ in 0..50 {
+        if insert(100).await.unwrap().status() == 200 {
+            accepted = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(accepted);
+    let metrics: Value = client.get(format!("{base}/metrics")).send().await.unwrap().json().await.unwrap();
+    assert!(metrics["wal_stats"][0]["backlog"]["ops"].as_u64().unwrap() < 20, "{}", metrics["wal_stats"]);
+}
@@ -0,0 +1,80 @@
+use piramid::config::{WalCompression, WalKey};
+use piramid::testing::TestDir;
+use piramid::{Collection, CollectionConfig, Document};
+use std::fs;
+
+fn config(compression: WalCompression, key: Option<[u8; 32]>) -> CollectionConfig {
+    let mut config = CollectionConfig::default();
+    config.wal.compression = compression;
+    config.wal.encryption_key = key.map(WalKey);
+    config
+}
+
+// Documents with long, repetitive texts, left in the WAL (no checkpoint)
+fn write(path: &str, config: &CollectionConfig, count: usize) {
+    let mut storage = Collection::open_with_options(path, config.clone().into()).unwrap();
+    for i in 0..count {
+        let text = format!("secret payload {i} ").repeat(40);
+        storage.insert(Document::new(vec![i as f32, 1.0, 0.5, 0.25], text)).unwrap();
+    }
+}
+
+#[test]
+fn entries_are_compressed_and_encrypted_and_replay() {
+    let key = [7u8; 32];
+    let plain_dir = TestDir::new("wal_codec_plain");
+    let plain_path = plain_dir.path("docs.db");
+    write(&plain_path, &CollectionConfig::default(), 20);
+    let plain_size = fs::metadata(format!("{plain_path}.wal.db")).unwrap().len();
+
+    for (name, compression, key) in [
+        ("lz4", WalCompression::Lz4, None),
+        ("zstd", WalCompression::Zstd, None),
+        ("encrypted", WalCompression::None, Some(key)),
+        ("zstd_encrypted", WalCompression::Zstd, Some(key)),
+    ] {
+        let dir = TestDir::new(&format!("wal_codec_{name}"));
+        let path = dir.path("docs.db");
+        let config = config(compression, key);
+        write(&path, &config, 20);
+        let wal = fs::read_to_string(format!("{path}.wal.db")).unwrap();
+        if compression != WalCompression::None {
+            assert!((wal.len() as u64) * 2 < plain_size, "{name}: {} vs {plain_size}", wal.len());
+        }
+        if key.is_some() {
+            assert!(!wal.contains("secret"), "{name}");
+            // Without the key, or with another one, the log cannot be replayed
+            assert!(Collection::open_with_options(&path, self::config(compression, None).into()).is_err());
+            let err = Collection::open_with_options(&path, self::config(compression, Some([8; 32])).into()).err().unwrap();
+            assert!(err.to_string().contains("authentication"), "{err}");
+        }
+
+        let storage = Collection::open_with_options(&path, config.into()).unwrap();
+        assert_eq!(storage.count(), 20, "{name}");
+        assert!(storage.get_all().iter().any(|doc| doc.text.starts_with("secret payload 19 ")));
+    }
+}
+
+#[test]
+fn plain_logs_replay_after_encoding_is_switched_on() {
+    let dir = TestDir::new("wal_codec_upgrade");
+    let path = dir.path("docs.db");
+
+    // Without compression or a key the log is the original format
+    write(&path, &CollectionConfig::default(), 3);
+    let wal = fs::read_to_string(format!("{path}.wal.db")).unwrap();
+    assert_eq!(wal.lines().next(), Some(r#"{"version":1}"#));
+    assert!(wal.lines().skip(1).all(|line| line.starts_with('{')));
+
+    let encoded = config(WalCompression::Zstd, Some([1; 32]));
+    write(&path, &encoded, 2);
+    let wal = fs::read_to_string(format!("{path}.wal.db")).unwrap();
+    assert!(wal.lines().any(|line| line.starts_with('~')), "{wal}");
+    assert_eq!(Collection::open_with_options(&path, encoded.clone().into()).unwrap().count(), 5);
+
+    // Compression switched off again: entries of all three kinds replay
+    write(&path, &config(WalCompression::None, Some([1; 32])), 1);
+    assert_eq!(Collection::open_with_options(&path, encoded.into()).unwrap().count(), 6);
+    assert!(WalKey::from_hex("not hex").is_err());
+    assert_eq!(WalKey::from_hex(&"01".repeat(32)).unwrap(), WalKey([1; 32]));
+}
@@ -0,0 +1,105 @@
+use piramid::config::{CollectionConfig, WalConfig};
+use piramid::storage::wal::SegmentManifest;
+use piramid::Document;
+use piramid::testing::TestDir;
+use std::fs;
+use std::path::Path;
+
+fn doc(i: usize) -> Document {
+    let angle = i as f32 * 0.2;
+    Document::new(vec![angle.cos(), angle.sin(), 0.5], format!("doc {i}"))
+}
+
+fn config(archive_segments: bool) -> CollectionConfig {
+    let wal = WalConfig { checkpoint_frequency: 1_000_000, max_log_size: 4096, archive_segments, ..Default::default() };
+    CollectionConfig { wal, ..Default::default() }
+}
+
+fn manifest(dir: &str) -> SegmentManifest {
+    serde_json::from_slice(&fs::read(format!("{dir}/manifest.json")).unwrap()).unwrap()
+}
+
+// Each segment starts right after the previous one ends
+fn assert_contiguous(manifest: &SegmentManifest, first: u64, last: u64) {
+    let segments = &manifest.segments;
+    assert_eq!(segments.first().unwrap().first_seq, first, "{segments:?}");
+    assert_eq!(segments.last().unwrap().last_seq, last, "{segments:?}");
+    for pair in segments.windows(2) {
+        assert_eq!(pair[1].first_seq, pair[0].last_seq + 1, "{segments:?}");
+    }
+}
+
+#[test]
+fn full_wal_files_close_into_segments_replayed_on_open() {
+    let dir = TestDir::new("wal_segments");
+    let path = dir.path("docs.db");
+    let segments = format!("{path}.wal.segments");
+
+    let mut storage = dir.open("docs", config(false)).unwrap();
+    let ids: Vec<_> = (0..120).map(|i| storage.insert(doc(i)).unwrap()).collect();
+    let closed = manifest(&segments);
+    assert!(closed.segments.len() >= 3, "{closed:?}");
+    assert_contiguous(&closed, 1, closed.segments.last().unwrap().last_seq);
+    assert!(closed.segments.iter().all(|s| s.bytes >= 4096 && s.closed_at > 0));
+    assert!(fs::metadata(format!("{path}.wal.db")).unwrap().len() < 4096 + 1024);
+    let backlog = storage.wal_backlog();
+    assert_eq!(backlog.segments, closed.segments.len());
+    assert!(backlog.bytes > closed.segments.iter().map(|s| s.bytes).sum::<u64>());
+
+    // Never checkpointed: every document comes back from the segments and the live file
+    drop(storage);
+    let mut storage = dir.open("docs", config(false)).unwrap();
+    assert_eq!(storage.count(), 120);
+    assert!(ids.iter().all(|id| storage.get(id).is_some()));
+
+    // A checkpoint releases the segments it covers
+    storage.checkpoint().unwrap();
+    assert!(manifest(&segments).segments.is_empty());
+    assert_eq!(storage.wal_backlog().segments, 0);
+    assert!(!Path::new(&format!("{path}.wal.archive")).exists());
+    storage.insert(doc(200)).unwrap();
+    drop(storage);
+    assert_eq!(dir.open("docs", config(false)).unwrap().count(), 121);
+
+    // A segment the manifest never heard of (a crash right after the rename) is still replayed
+    let mut storage = dir.open("docs", config(false)).unwrap();
+    for i in 300..400 {
+        storage.insert(doc(i)).unwrap();
+    }
+    drop(storage);
+    fs::remove_file(format!("{segments}/manifest.json")).unwrap();
+    let storage = dir.open("docs", config(false)).unwrap();
+    assert_eq!(storage.count(), 221);
+    // Opening replayed and checkpointed them
+    assert_eq!(storage.wal_backlog().segments, 0);
+}
+
+#[test]
+fn released_segments_are_archived_in_order() {
+    let dir = TestDir::new("wal_segments_archive");
+    let path = dir.path("docs.db");
+    let archive = format!("{path}.wal.archive");
+
+    let mut storage = dir.open("docs", config(true)).unwrap();
+    for i in 0..80 {
+        storage.insert(doc(i)).unwrap();
+    }
+    assert!(!Path::new(&archive).exists());
+    storage.checkpoint().unwrap();
+    let first = manifest(&archive);
+    let through = storage.head_seq();
+    assert_contiguous(&first, 1, through);
+
+    // The next checkpoint's files follow on, and nothing is dropped from the archive
+    for i in 80..130 {
+        storage.insert(doc(i)).unwrap();
+    }
+    storage.checkpoint().unwrap();
+    let second = manifest(&archive);
+    assert_contiguous(&second, 1, storage.head_seq());
+    assert!(second.segments.len() > first.segments.len());
+    for segment in &second.segments {
+        let file = format!("{archive}/{:020}-{:020}.wal", segment.first_seq, segment.last_seq);
+        assert_eq!(fs::metadata(file).unwrap().len(), segment.bytes);
+    }
+}
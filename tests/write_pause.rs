@@ -0,0 +1,101 @@
+mod common;
+
+use piramid::config::AppConfig;
+use piramid::error::{PiramidError, ServerError};
+use piramid::server::state::AppState;
+use piramid::testing::{TestDir, freeze_clock};
+use piramid::{metadata, CollectionConfig, Document};
+use serde_json::{json, Value};
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use common::vector;
+
+fn paused_error<T: std::fmt::Debug>(result: piramid::error::Result<T>) -> bool {
+    matches!(result, Err(PiramidError::Server(ServerError::WritesPaused { .. })))
+}
+
+#[test]
+fn paused_collections_hold_their_files_until_resumed_or_the_deadline() {
+    let clock = freeze_clock(1_000_000);
+    let dir = TestDir::new("write_pause");
+    let path = dir.path("docs.db");
+    let mut storage = dir.open("docs", CollectionConfig::default()).unwrap();
+    let ids: Vec<_> = (0..40).map(|i| storage.insert(Document::new(vector(i), format!("doc {i}"))).unwrap()).collect();
+
+    // Pausing checkpoints what the WAL held, so the files on disk are complete
+    let pause = storage.pause_writes(60, Some("snapshot".into())).unwrap();
+    assert_eq!((pause.paused_at, pause.resume_at), (1_000_000, 1_000_060));
+    assert_eq!(storage.wal_backlog().ops, 0);
+    let files: Vec<_> = ["", ".index.db", ".vecindex.db", ".metadata.db"].iter().map(|s| fs::read(format!("{path}{s}")).unwrap()).collect();
+
+    assert!(paused_error(storage.insert(Document::new(vector(99), "late".into()))));
+    assert!(paused_error(storage.delete(&ids[0])));
+    assert!(paused_error(storage.update_metadata(&ids[1], metadata([("tag", "x".into())]))));
+    assert!(paused_error(storage.rebuild_index()));
+    // Reads carry on, and a checkpoint leaves the files alone
+    assert_eq!(storage.count(), 40);
+    assert!(storage.get(&ids[0]).is_some());
+    storage.checkpoint().unwrap();
+    let after: Vec<_> = ["", ".index.db", ".vecindex.db", ".metadata.db"].iter().map(|s| fs::read(format!("{path}{s}")).unwrap()).collect();
+    assert!(files == after);
+
+    // Pausing again moves the deadline, not the start
+    clock.advance(30);
+    let extended = storage.pause_writes(60, None).unwrap();
+    assert_eq!((extended.paused_at, extended.resume_at), (1_000_000, 1_000_090));
+    assert_eq!(storage.resume_writes(), Some(extended));
+    storage.insert(Document::new(vector(100), "resumed".into())).unwrap();
+    assert_eq!(storage.resume_writes(), None);
+
+    // Nobody resumes: past the deadline writes are accepted on their own
+    storage.pause_writes(10, None).unwrap();
+    assert!(paused_error(storage.delete(&ids[2])));
+    assert!(storage.expire_write_pause().is_none());
+    clock.advance(10);
+    assert!(storage.write_pause().is_none());
+    storage.delete(&ids[2]).unwrap();
+    assert!(storage.expire_write_pause().is_some());
+    assert!(storage.pause_writes(0, None).is_err());
+}
+
+#[tokio::test]
+async fn pause_endpoints_reject_writes_and_resume_on_timeout() {
+    let dir = TestDir::new("write_pause_http");
+    let data_dir = dir.data_dir();
+    let state = Arc::new(AppState::new(data_dir, AppConfig::default(), 500, None, false, None));
+    let base = common::serve(state).await;
+    let client = reqwest::Client::new();
+    let insert = |i: usize| client.post(format!("{base}/collections/docs/vectors")).json(&json!({"vector": vector(i), "text": format!("doc {i}")})).send();
+    assert!(insert(0).await.unwrap().status().is_success());
+
+    let paused: Value = client.post(format!("{base}/collections/docs/writes/pause"))
+        .json(&json!({"timeout_secs": 600, "reason": "lvm snapshot"}))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!((paused["paused"].as_bool(), paused["pause"]["reason"].as_str()), (Some(true), Some("lvm snapshot")), "{paused}");
+    let rejected = insert(1).await.unwrap();
+    assert_eq!(rejected.status(), 503);
+    assert!(rejected.headers()["retry-after"].to_str().unwrap().parse::<u64>().unwrap() > 500);
+    let body: Value = rejected.json().await.unwrap();
+    assert_eq!((body["error_code"].as_str(), body["retryable"].as_bool()), (Some("WRITES_PAUSED"), Some(true)), "{body}");
+    assert!(body["error"].as_str().unwrap().contains("lvm snapshot"));
+    let deleted = client.delete(format!("{base}/collections/docs")).send().await.unwrap();
+    assert_eq!(deleted.status(), 503);
+    let hits: Value = client.post(format!("{base}/collections/docs/search")).json(&json!({"vector": vector(0), "k": 1})).send().await.unwrap().json().await.unwrap();
+    assert_eq!(hits["results"].as_array().unwrap().len(), 1);
+
+    let resumed: Value = client.post(format!("{base}/collections/docs/writes/resume")).send().await.unwrap().json().await.unwrap();
+    assert_eq!((resumed["paused"].as_bool(), resumed["pause"]["reason"].as_str()), (Some(false), Some("lvm snapshot")));
+    assert!(insert(2).await.unwrap().status().is_success());
+
+    // A pause nobody lifts runs out
+    client.post(format!("{base}/collections/docs/writes/pause")).json(&json!({"timeout_secs": 1})).send().await.unwrap();
+    let status: Value = client.get(format!("{base}/collections/docs/writes")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(status["paused"], true);
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    let status: Value = client.get(format!("{base}/collections/docs/writes")).send().await.unwrap().json().await.unwrap();
+    assert_eq!(status, json!({"collection": "docs", "paused": false}));
+    assert!(insert(3).await.unwrap().status().is_success());
+    let too_long = client.post(format!("{base}/collections/docs/writes/pause")).json(&json!({"timeout_secs": 100_000})).send().await.unwrap();
+    assert_eq!(too_long.status(), 400);
+}